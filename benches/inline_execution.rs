@@ -0,0 +1,102 @@
+//! 示例函数（hello/echo/add）在进程内快速路径和外部运行时上的调用延迟对比
+//!
+//! 运行：`cargo bench --bench inline_execution`，迭代次数由 `FLUX_BENCH_ITERATIONS` 设置（默认 200）。
+//! 没有安装 node 时只测量快速路径。
+
+use flux::functions::{FunctionMetadata, InvokeRequest};
+use flux::runtime::SimpleRuntime;
+use flux::runtime::inline::{ExecutionHint, ExecutionPath};
+use flux::runtime::sandbox::{SandboxConfig, SandboxExecutor};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 每个组合正式测量前的预热调用次数
+const WARMUP_ITERATIONS: usize = 5;
+
+#[tokio::main]
+async fn main() {
+    let iterations = std::env::var("FLUX_BENCH_ITERATIONS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(200usize);
+    let runtime = SimpleRuntime::new();
+    let external = which::which("node").is_ok();
+    if external {
+        runtime.attach_sandbox(Arc::new(
+            SandboxExecutor::new(SandboxConfig::default()).expect("sandbox starts"),
+        ));
+    } else {
+        eprintln!("node is not installed, measuring the inline path only");
+    }
+
+    let samples = [
+        ("hello", "return \"Hello, World!\"", json!({})),
+        ("echo", "return input", json!({"message": "ping"})),
+        ("add", "return a + b", json!({"a": 1, "b": 2})),
+    ];
+    println!(
+        "{:<8}{:<10}{:>12}{:>12}{:>12}",
+        "sample", "path", "mean ms", "p50 ms", "p95 ms"
+    );
+    for (name, code, input) in samples {
+        for hint in [ExecutionHint::Auto, ExecutionHint::External] {
+            if hint == ExecutionHint::External && !external {
+                continue;
+            }
+            let mut function = FunctionMetadata::new(format!("bench-{name}"), code.to_string());
+            function.execution_hint = hint;
+            let (path, latencies) = measure(&runtime, &function, &input, iterations).await;
+            let mean = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+            println!(
+                "{name:<8}{:<10}{:>12.3}{:>12.3}{:>12.3}",
+                format!("{path:?}").to_lowercase(),
+                millis(mean),
+                millis(percentile(&latencies, 0.50)),
+                millis(percentile(&latencies, 0.95)),
+            );
+        }
+    }
+}
+
+/// 预热后调用 `iterations` 次，返回执行路径和按升序排列的延迟
+async fn measure(
+    runtime: &SimpleRuntime,
+    function: &FunctionMetadata,
+    input: &Value,
+    iterations: usize,
+) -> (ExecutionPath, Vec<Duration>) {
+    let request = InvokeRequest {
+        input: input.clone(),
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
+    };
+    let mut path = ExecutionPath::Inline;
+    let mut latencies = Vec::with_capacity(iterations);
+    for i in 0..WARMUP_ITERATIONS + iterations {
+        let started = Instant::now();
+        let response = runtime
+            .execute(function, &request)
+            .await
+            .expect("sample function executes");
+        assert!(response.status.is_success(), "{:?}", response.status);
+        if i >= WARMUP_ITERATIONS {
+            latencies.push(started.elapsed());
+        }
+        path = response.execution_path.unwrap_or(path);
+    }
+    latencies.sort();
+    (path, latencies)
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[index]
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
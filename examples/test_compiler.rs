@@ -1,3 +1,4 @@
+use flux::functions::context::InvocationContext;
 use flux::functions::{FunctionMetadata, InvokeRequest};
 use flux::runtime::compiler::{CompilerConfig, RustCompiler, check_compilation_support};
 
@@ -62,10 +63,16 @@ fn add_numbers(a: f64, b: f64) -> f64 {
                     "a": 10.5,
                     "b": 20.3
                 }),
+                retry_policy: None,
+                priority: None,
+                idempotency_key: None,
+                session_id: None,
+                affinity_key: None,
             };
+            let context = InvocationContext::new(&compiled.metadata, "test-compiler");
 
             match compiler
-                .execute_compiled_function(&compiled, &request)
+                .execute_compiled_function(&compiled, &request, &context)
                 .await
             {
                 Ok(response) => {
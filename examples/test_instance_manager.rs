@@ -3,6 +3,7 @@ use std::sync::Arc;
 use tempfile::TempDir;
 use tokio::time::{Duration, sleep};
 
+use flux::functions::priority::Priority;
 use flux::functions::{FunctionMetadata, InvokeRequest};
 use flux::runtime::compiler::{CompilerConfig, RustCompiler};
 use flux::runtime::instance::{InstanceConfig, InstanceManager};
@@ -72,6 +73,42 @@ fn hello_world(input: serde_json::Value) -> serde_json::Value {
         dependencies: vec![],
         parameters: vec![],
         return_type: "serde_json::Value".to_string(),
+        retry_policy: None,
+        idempotent: true,
+        labels: std::collections::HashMap::new(),
+        webhooks: None,
+        priority: Priority::Normal,
+        package: None,
+        http_response: false,
+        input_template: None,
+        revision: 0,
+        input_schema: None,
+        output_schema: None,
+        strict_output_schema: false,
+        coerce_output: false,
+        strict_return_type: false,
+        log_redaction: Vec::new(),
+        circuit_breaker: true,
+        runtime: None,
+        resource_quota: None,
+        requires_isolation: false,
+        scratch_limit_mb: None,
+        persist_scratch: false,
+        max_disk_write_mb: None,
+        max_cpu_time_ms: None,
+        tests: Vec::new(),
+        execution_hint: Default::default(),
+        mirror: None,
+        canary: None,
+        slo: None,
+        documentation: None,
+        header_fields: Default::default(),
+        http_routes: Vec::new(),
+        deprecation: None,
+        parameters_inferred: false,
+        return_type_inferred: false,
+        egress: None,
+        debug_capture: Default::default(),
     };
 
     let instance_id = manager
@@ -99,6 +136,11 @@ fn hello_world(input: serde_json::Value) -> serde_json::Value {
     println!("\n⚡ 测试3: 执行函数实例");
     let request = InvokeRequest {
         input: serde_json::json!({"name": "FluxFaaS"}),
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
     };
 
     match manager.execute_instance(&instance_id, &request).await {
@@ -137,6 +179,42 @@ fn add_numbers(input: serde_json::Value) -> serde_json::Value {
         dependencies: vec![],
         parameters: vec![],
         return_type: "serde_json::Value".to_string(),
+        retry_policy: None,
+        idempotent: true,
+        labels: std::collections::HashMap::new(),
+        webhooks: None,
+        priority: Priority::Normal,
+        package: None,
+        http_response: false,
+        input_template: None,
+        revision: 0,
+        input_schema: None,
+        output_schema: None,
+        strict_output_schema: false,
+        coerce_output: false,
+        strict_return_type: false,
+        log_redaction: Vec::new(),
+        circuit_breaker: true,
+        runtime: None,
+        resource_quota: None,
+        requires_isolation: false,
+        scratch_limit_mb: None,
+        persist_scratch: false,
+        max_disk_write_mb: None,
+        max_cpu_time_ms: None,
+        tests: Vec::new(),
+        execution_hint: Default::default(),
+        mirror: None,
+        canary: None,
+        slo: None,
+        documentation: None,
+        header_fields: Default::default(),
+        http_routes: Vec::new(),
+        deprecation: None,
+        parameters_inferred: false,
+        return_type_inferred: false,
+        egress: None,
+        debug_capture: Default::default(),
     };
 
     let add_instance_id = manager.create_instance(add_function, None).await?;
@@ -149,6 +227,11 @@ fn add_numbers(input: serde_json::Value) -> serde_json::Value {
     println!("\n➕ 测试5: 执行加法函数");
     let add_request = InvokeRequest {
         input: serde_json::json!({"a": 15, "b": 27}),
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
     };
 
     match manager
@@ -213,6 +296,11 @@ fn add_numbers(input: serde_json::Value) -> serde_json::Value {
     for i in 1..=iterations {
         let test_request = InvokeRequest {
             input: serde_json::json!({"iteration": i}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
         };
 
         let start = std::time::Instant::now();
@@ -3,7 +3,7 @@ use flux::functions::{FunctionMetadata, InvokeRequest};
 use flux::runtime::compiler::CompilerConfig;
 use flux::runtime::executor::{IsolatedExecutorConfig, IsolatedProcessExecutor};
 use flux::runtime::resource::ResourceQuota;
-use flux::runtime::sandbox::SandboxConfig;
+use flux::runtime::sandbox::{DEFAULT_MAX_CODE_BYTES, SandboxConfig};
 use serde_json::json;
 use std::path::PathBuf;
 use std::time::Instant;
@@ -78,6 +78,13 @@ pub fn main() {
             allowed_env_vars: vec!["PATH".to_string()],
             temp_root: PathBuf::from("/tmp/flux_isolated_test"),
             rust_target_dir: Some(PathBuf::from("~/rust_target")),
+            max_concurrent_executions: 16,
+            max_queued_executions: 64,
+            queue_timeout_ms: None,
+            max_code_bytes: DEFAULT_MAX_CODE_BYTES,
+            scratch_limit_mb: 64,
+            scratch_ttl_secs: 3600,
+            max_artifact_download_bytes: 16 * 1024 * 1024,
         },
         default_quota_name: Some("test_quota".to_string()),
         max_concurrent_executions: 50,
@@ -165,6 +172,11 @@ pub fn main() {
             "test_name": "basic_execution",
             "data": [1, 2, 3, 4, 5]
         }),
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
     };
 
     let start_time = Instant::now();
@@ -194,6 +206,11 @@ pub fn main() {
                 "test_name": format!("sequential_execution_{}", i),
                 "instance_id": i
             }),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
         };
 
         let start = Instant::now();
@@ -301,6 +318,11 @@ pub fn main() {
         input: json!({
             "test_name": "resource_limit_test"
         }),
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
     };
 
     let start_time = Instant::now();
@@ -361,7 +383,14 @@ pub fn main() {
         .to_string(),
     );
 
-    let request = InvokeRequest { input: json!({}) };
+    let request = InvokeRequest {
+        input: json!({}),
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
+    };
 
     let iterations = 10;
     let mut total_time = 0u64;
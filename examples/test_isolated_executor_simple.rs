@@ -2,7 +2,7 @@ use anyhow::Result;
 use flux::functions::{FunctionMetadata, InvokeRequest};
 use flux::runtime::compiler::CompilerConfig;
 use flux::runtime::executor::{IsolatedExecutorConfig, IsolatedProcessExecutor};
-use flux::runtime::sandbox::SandboxConfig;
+use flux::runtime::sandbox::{DEFAULT_MAX_CODE_BYTES, SandboxConfig};
 use serde_json::json;
 use std::path::PathBuf;
 use std::time::Instant;
@@ -52,6 +52,13 @@ pub fn main() {
             allowed_env_vars: vec!["PATH".to_string()],
             temp_root: PathBuf::from("/tmp/flux_isolated_simple_test"),
             rust_target_dir: Some(PathBuf::from("~/rust_target")),
+            max_concurrent_executions: 16,
+            max_queued_executions: 64,
+            queue_timeout_ms: None,
+            max_code_bytes: DEFAULT_MAX_CODE_BYTES,
+            scratch_limit_mb: 64,
+            scratch_ttl_secs: 3600,
+            max_artifact_download_bytes: 16 * 1024 * 1024,
         },
         default_quota_name: None, // 不使用配额，简化测试
         max_concurrent_executions: 10,
@@ -78,6 +85,11 @@ pub fn main() {
             "test_name": "basic_execution",
             "data": [1, 2, 3, 4, 5]
         }),
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
     };
 
     let start_time = Instant::now();
@@ -105,6 +117,11 @@ pub fn main() {
                 "test_name": format!("execution_{}", i),
                 "iteration": i
             }),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
         };
 
         let start = Instant::now();
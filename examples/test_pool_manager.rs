@@ -3,6 +3,7 @@ use std::sync::Arc;
 use tempfile::TempDir;
 use tokio::time::{Duration, sleep};
 
+use flux::functions::priority::Priority;
 use flux::functions::{FunctionMetadata, InvokeRequest};
 use flux::runtime::compiler::{CompilerConfig, RustCompiler};
 use flux::runtime::instance::{InstanceConfig, InstanceManager};
@@ -69,6 +70,7 @@ async fn main() -> Result<()> {
         health_check_interval_secs: 15,
         load_balance_strategy: LoadBalanceStrategy::RoundRobin,
         instance_config,
+        ..Default::default()
     };
 
     let pool_manager = PoolManager::new(instance_manager, Some(pool_config.clone()));
@@ -96,6 +98,42 @@ fn hello_pool(input: serde_json::Value) -> serde_json::Value {
         dependencies: vec![],
         parameters: vec![],
         return_type: "serde_json::Value".to_string(),
+        retry_policy: None,
+        idempotent: true,
+        labels: std::collections::HashMap::new(),
+        webhooks: None,
+        priority: Priority::Normal,
+        package: None,
+        http_response: false,
+        input_template: None,
+        revision: 0,
+        input_schema: None,
+        output_schema: None,
+        strict_output_schema: false,
+        coerce_output: false,
+        strict_return_type: false,
+        log_redaction: Vec::new(),
+        circuit_breaker: true,
+        runtime: None,
+        resource_quota: None,
+        requires_isolation: false,
+        scratch_limit_mb: None,
+        persist_scratch: false,
+        max_disk_write_mb: None,
+        max_cpu_time_ms: None,
+        tests: Vec::new(),
+        execution_hint: Default::default(),
+        mirror: None,
+        canary: None,
+        slo: None,
+        documentation: None,
+        header_fields: Default::default(),
+        http_routes: Vec::new(),
+        deprecation: None,
+        parameters_inferred: false,
+        return_type_inferred: false,
+        egress: None,
+        debug_capture: Default::default(),
     };
 
     let pool = pool_manager
@@ -125,6 +163,11 @@ fn hello_pool(input: serde_json::Value) -> serde_json::Value {
     println!("\n⚡ 测试4: 执行函数请求");
     let request = InvokeRequest {
         input: serde_json::json!({"name": "Pool Test", "iteration": 1}),
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
     };
 
     match pool.execute(&request).await {
@@ -152,6 +195,11 @@ fn hello_pool(input: serde_json::Value) -> serde_json::Value {
         let pool_clone = pool.clone();
         let request = InvokeRequest {
             input: serde_json::json!({"name": "Concurrent Test", "iteration": i + 1}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
         };
 
         let handle = tokio::spawn(async move {
@@ -234,6 +282,42 @@ fn calculator_pool(input: serde_json::Value) -> serde_json::Value {
         dependencies: vec![],
         parameters: vec![],
         return_type: "serde_json::Value".to_string(),
+        retry_policy: None,
+        idempotent: true,
+        labels: std::collections::HashMap::new(),
+        webhooks: None,
+        priority: Priority::Normal,
+        package: None,
+        http_response: false,
+        input_template: None,
+        revision: 0,
+        input_schema: None,
+        output_schema: None,
+        strict_output_schema: false,
+        coerce_output: false,
+        strict_return_type: false,
+        log_redaction: Vec::new(),
+        circuit_breaker: true,
+        runtime: None,
+        resource_quota: None,
+        requires_isolation: false,
+        scratch_limit_mb: None,
+        persist_scratch: false,
+        max_disk_write_mb: None,
+        max_cpu_time_ms: None,
+        tests: Vec::new(),
+        execution_hint: Default::default(),
+        mirror: None,
+        canary: None,
+        slo: None,
+        documentation: None,
+        header_fields: Default::default(),
+        http_routes: Vec::new(),
+        deprecation: None,
+        parameters_inferred: false,
+        return_type_inferred: false,
+        egress: None,
+        debug_capture: Default::default(),
     };
 
     let calculator_pool_config = PoolConfig {
@@ -268,6 +352,11 @@ fn calculator_pool(input: serde_json::Value) -> serde_json::Value {
                 "a": a,
                 "b": b
             }),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
         };
 
         match calculator_pool.execute(&calc_request).await {
@@ -353,6 +442,11 @@ fn calculator_pool(input: serde_json::Value) -> serde_json::Value {
     for i in 1..=performance_requests {
         let perf_request = InvokeRequest {
             input: serde_json::json!({"name": "Performance Test", "iteration": i}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
         };
 
         let start = std::time::Instant::now();
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use flux::functions::{FunctionMetadata, InvokeRequest};
 use flux::runtime::compiler::{CompilerConfig, RustCompiler};
-use flux::runtime::sandbox::{SandboxConfig, SandboxExecutor};
+use flux::runtime::sandbox::{DEFAULT_MAX_CODE_BYTES, SandboxConfig, SandboxExecutor};
 use serde_json::json;
 use std::time::Instant;
 
@@ -43,6 +43,13 @@ pub fn main() {
         allowed_env_vars: vec!["PATH".to_string()],
         temp_root: std::path::PathBuf::from("/tmp/flux_sandbox_test"),
         rust_target_dir: Some(std::path::PathBuf::from("~/rust_target")),
+        max_concurrent_executions: 16,
+        max_queued_executions: 64,
+        queue_timeout_ms: None,
+        max_code_bytes: DEFAULT_MAX_CODE_BYTES,
+        scratch_limit_mb: 64,
+        scratch_ttl_secs: 3600,
+        max_artifact_download_bytes: 16 * 1024 * 1024,
     };
 
     println!("📋 沙箱配置:");
@@ -85,6 +92,11 @@ pub fn main() {
             "a": 15.5,
             "b": 4.2
         }),
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
     };
 
     println!("🚀 在沙箱中执行函数...");
@@ -184,6 +196,11 @@ fn fibonacci(n: u32) -> u64 {
     for i in 0..iterations {
         let request = InvokeRequest {
             input: json!({"n": 20}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
         };
 
         let start = Instant::now();
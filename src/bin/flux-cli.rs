@@ -1,538 +1,542 @@
-use serde_json::{Value, Value::Null, json};
-use std::env;
-use std::io::{self, Write};
-
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // 获取服务器配置
-    let base_url =
-        env::var("FLUX_SERVER_URL").unwrap_or_else(|_| "http://127.0.0.1:3000".to_string());
-
-    let client = reqwest::Client::new();
+//! FluxFaaS 命令行客户端：通过 `/v1` HTTP API 管理和调用运行中服务上的函数
+//!
+//! 请求和响应使用服务端的类型（经 [`FluxClient`]），默认以表格输出，`--json` 输出原始 JSON。
+//!
+//! 退出码：0 成功；`invoke` 执行错误 1、超时 3、取消 4、超出资源限制 5（见
+//! [`ExecutionStatus::exit_code`]）；参数或输入文件错误 2；服务端拒绝请求 6；无法连接服务 7。
+
+use clap::{Parser, Subcommand};
+use flux::client::{
+    ClientError, FluxClient, InvokeRequest, InvokeResponse, RegisterFunctionRequest,
+};
+use flux::functions::ExecutionStatus;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// 命令行参数、输入 JSON 或本地文件有误（与 clap 的用法错误一致）
+const EXIT_USAGE: u8 = 2;
+/// 服务端拒绝请求（4xx/5xx 或响应格式不符）
+const EXIT_SERVER_ERROR: u8 = 6;
+/// 无法连接服务或读取响应
+const EXIT_UNREACHABLE: u8 = 7;
+
+#[derive(Debug, Parser)]
+#[command(name = "flux-cli", version, about = "FluxFaaS command line client")]
+struct Cli {
+    /// 服务地址
+    #[arg(
+        long,
+        global = true,
+        env = "FLUX_SERVER",
+        default_value = "http://127.0.0.1:3000"
+    )]
+    server: String,
+    /// 以 `Authorization: Bearer` 发送的 API 密钥
+    #[arg(long, global = true, env = "FLUX_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+    /// 输出 JSON 而不是表格
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
 
-    // 检查服务器连接
-    if (check_server_health(&client, &base_url).await).is_err() {
-        eprintln!("❌ 无法连接到FluxFaaS服务器: {base_url}");
-        eprintln!("💡 请确保服务器正在运行: cargo run --bin flux");
-        return Ok(());
-    }
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 管理函数
+    #[command(subcommand)]
+    Fn(FnCommand),
+    /// 调用函数，退出码反映执行状态
+    Invoke {
+        name: String,
+        /// 输入 JSON（缺省为 null）
+        #[arg(short, long, conflicts_with = "watch")]
+        data: Option<String>,
+        /// 从 JSON 文件读取输入，文件每次变化时重新调用（Ctrl-C 退出）
+        #[arg(long, value_name = "FILE")]
+        watch: Option<PathBuf>,
+    },
+    /// 函数最近一小时的调用统计
+    Stats { name: String },
+    /// 批量加载函数
+    #[command(subcommand)]
+    Load(LoadCommand),
+    /// 查看失败调用的调试包
+    #[command(subcommand)]
+    Debug(DebugCommand),
+}
 
-    println!("✅ 已连接到FluxFaaS服务器: {base_url}");
+#[derive(Debug, Subcommand)]
+enum FnCommand {
+    /// 列出函数
+    List,
+    /// 查看函数详情
+    Get { name: String },
+    /// 从源文件注册函数
+    Register {
+        /// 函数源文件
+        #[arg(short, long)]
+        file: PathBuf,
+        /// 函数名（缺省为文件名去掉扩展名）
+        #[arg(long)]
+        name: Option<String>,
+        /// 执行超时（毫秒）
+        #[arg(long)]
+        timeout: Option<u64>,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// 删除函数
+    Delete { name: String },
+}
 
-    loop {
-        println!("\n🌟 FluxFaaS CLI客户端 (HTTP模式)");
-        println!("====================================");
-        println!("1. 📋 查看所有函数");
-        println!("2. 🚀 调用函数");
-        println!("3. ➕ 注册新函数");
-        println!("4. 📁 从文件加载函数");
-        println!("5. 📂 从目录批量加载函数");
-        println!("6. 📊 查看系统状态");
-        println!("7. 🎯 查看缓存统计");
-        println!("8. 📊 查看性能监控");
-        println!("9. 🔄 重置监控数据");
-        println!("10. 🚪 退出");
+#[derive(Debug, Subcommand)]
+enum LoadCommand {
+    /// 加载服务端所在机器上目录中的函数（同名函数跳过）
+    Dir { path: PathBuf },
+}
 
-        print!("\nflux-cli> ");
-        io::stdout().flush()?;
+#[derive(Debug, Subcommand)]
+enum DebugCommand {
+    /// 下载调用的调试包（tar.gz），函数需启用 `debug_capture`
+    Fetch {
+        /// 调用 ID（即调用的请求 ID）
+        id: String,
+        /// 保存路径（缺省为当前目录下的 `<调用 ID>.tar.gz`）
+        #[arg(short, long, value_name = "FILE", conflicts_with = "manifest")]
+        output: Option<PathBuf>,
+        /// 只查看清单，不下载调试包
+        #[arg(long)]
+        manifest: bool,
+    },
+}
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let choice = input.trim();
+/// 命令失败，错误已输出到标准错误并映射为退出码
+struct Failure(u8);
 
-        match choice {
-            "1" => list_functions(&client, &base_url).await?,
-            "2" => invoke_function(&client, &base_url).await?,
-            "3" => register_new_function(&client, &base_url).await?,
-            "4" => load_function_from_file(&client, &base_url).await?,
-            "5" => load_functions_from_directory(&client, &base_url).await?,
-            "6" => show_system_status(&client, &base_url).await?,
-            "7" => show_cache_statistics(&client, &base_url).await?,
-            "8" => show_performance_monitor(&client, &base_url).await?,
-            "9" => reset_performance_data(&client, &base_url).await?,
-            "10" | "q" | "quit" | "exit" => {
-                println!("👋 再见！");
-                break;
-            }
-            _ => println!("❌ 无效选项，请重新选择"),
+impl From<ClientError> for Failure {
+    fn from(e: ClientError) -> Self {
+        eprintln!("error: {e}");
+        if let ClientError::Server { error, .. } = &e
+            && let Some(details) = &error.details
+        {
+            eprintln!("{}", pretty(details));
         }
+        Self(match e {
+            ClientError::Transport(_) => EXIT_UNREACHABLE,
+            _ => EXIT_SERVER_ERROR,
+        })
     }
-
-    Ok(())
 }
 
-/// 检查服务器健康状态
-async fn check_server_health(client: &reqwest::Client, base_url: &str) -> anyhow::Result<()> {
-    let response = client.get(format!("{base_url}/health")).send().await?;
-
-    if response.status().is_success() {
-        Ok(())
-    } else {
-        Err(anyhow::anyhow!("Server not healthy"))
+impl From<anyhow::Error> for Failure {
+    fn from(e: anyhow::Error) -> Self {
+        eprintln!("error: {e:#}");
+        Self(EXIT_USAGE)
     }
 }
 
-/// 列出所有函数
-async fn list_functions(client: &reqwest::Client, base_url: &str) -> anyhow::Result<()> {
-    let response = client.get(format!("{base_url}/functions")).send().await?;
-
-    if !response.status().is_success() {
-        println!("❌ 获取函数列表失败: {}", response.status());
-        return Ok(());
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut client = FluxClient::new(&cli.server);
+    if let Some(api_key) = &cli.api_key {
+        client = client.with_api_key(api_key);
     }
+    let output = Output { json: cli.json };
+    match run(&client, &output, cli.command).await {
+        Ok(code) | Err(Failure(code)) => ExitCode::from(code),
+    }
+}
 
-    let data: Value = response.json().await?;
-
-    if let Some(functions) = data["data"].as_array() {
-        if functions.is_empty() {
-            println!("📭 没有注册的函数");
-            return Ok(());
+async fn run(client: &FluxClient, output: &Output, command: Command) -> Result<u8, Failure> {
+    match command {
+        Command::Fn(FnCommand::List) => {
+            let functions = client.list_functions().await?;
+            output.table(
+                &functions,
+                &["NAME", "NAMESPACE", "PROFILE", "TIMEOUT", "DESCRIPTION"],
+                functions.iter().map(|function| {
+                    vec![
+                        function.name.clone(),
+                        function.namespace.clone(),
+                        function.profile.clone(),
+                        format!("{}ms", function.timeout_ms),
+                        function.description.clone(),
+                    ]
+                }),
+            );
         }
-
-        println!("📋 已注册的函数:");
-        println!("------------------------");
-        for (i, func) in functions.iter().enumerate() {
-            let name = func["name"].as_str().unwrap_or("Unknown");
-            let description = func["description"].as_str().unwrap_or("No description");
-            let id = func["id"].as_str().unwrap_or("Unknown");
-            let timeout = func["timeout_ms"].as_u64().unwrap_or(0);
-
-            println!("{}. {} - {}", i + 1, name, description);
-            println!("   🆔 SCRU128 ID: {id}");
-            println!("   ⏱️  Timeout: {timeout}ms");
-            println!();
+        Command::Fn(FnCommand::Get { name }) => {
+            let details = client.get_function(&name).await?;
+            if output.json {
+                output.print_json(&details);
+            } else {
+                let function = &details.function;
+                let mut fields = vec![
+                    ("name", function.name.clone()),
+                    ("id", function.id.to_string()),
+                    ("description", function.description.clone()),
+                    ("version", function.version.clone()),
+                    ("timeout", format!("{}ms", function.timeout_ms)),
+                    ("created", function.created_at.to_rfc3339()),
+                ];
+                if let Some(compilation) = &details.compilation {
+                    fields.push(("compilation", format!("{:?}", compilation.status)));
+                }
+                print_fields(&fields);
+                println!("\n{}", function.code);
+            }
+        }
+        Command::Fn(FnCommand::Register {
+            file,
+            name,
+            timeout,
+            description,
+        }) => {
+            let request = register_request(&file, name, timeout, description)?;
+            client.register(&request).await?;
+            output.message(
+                &json!({"registered": request.name}),
+                &format!("Registered function '{}'", request.name),
+            );
+        }
+        Command::Fn(FnCommand::Delete { name }) => {
+            client.delete(&name).await?;
+            output.message(
+                &json!({"deleted": name}),
+                &format!("Deleted function '{name}'"),
+            );
+        }
+        Command::Invoke {
+            name,
+            data,
+            watch: None,
+        } => {
+            let input = match data {
+                Some(data) => parse_input(&data)?,
+                None => Value::Null,
+            };
+            return invoke(client, output, &name, input).await;
+        }
+        Command::Invoke {
+            name,
+            watch: Some(file),
+            ..
+        } => watch(client, output, &name, &file).await?,
+        Command::Stats { name } => {
+            let report = client.function_report(&name).await?;
+            let summary = &report.summary;
+            let ms = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{v:.1}ms"));
+            output.table(
+                &report,
+                &[
+                    "INVOCATIONS",
+                    "ERRORS",
+                    "ERROR RATE",
+                    "P50",
+                    "P95",
+                    "P99",
+                    "MAX MEMORY",
+                    "CPU",
+                ],
+                [vec![
+                    summary.invocations.to_string(),
+                    summary.errors.to_string(),
+                    format!("{:.1}%", summary.error_rate * 100.0),
+                    ms(summary.p50_ms),
+                    ms(summary.p95_ms),
+                    ms(summary.p99_ms),
+                    format!("{}KiB", summary.max_memory_bytes / 1024),
+                    format!("{}ms", summary.cpu_ms),
+                ]],
+            );
+        }
+        Command::Load(LoadCommand::Dir { path }) => {
+            // 路径由服务端解析，本地存在时先转为绝对路径
+            let path = path.canonicalize().unwrap_or(path);
+            let result = client.load_directory(&path.to_string_lossy()).await?;
+            output.table(
+                &result,
+                &["FILE", "NAME", "ACTION", "REASON"],
+                result.files.iter().map(|file| {
+                    vec![
+                        file.path.clone(),
+                        file.name.clone().unwrap_or_default(),
+                        serde_json::to_value(file.action)
+                            .ok()
+                            .and_then(|action| action.as_str().map(str::to_string))
+                            .unwrap_or_default(),
+                        file.reason.clone().unwrap_or_default(),
+                    ]
+                }),
+            );
+            if !output.json {
+                let summary = &result.summary;
+                println!(
+                    "\n{} registered, {} updated, {} duplicates skipped, {} skipped, {} failed, {} rejected",
+                    summary.registered,
+                    summary.updated,
+                    summary.skipped_duplicate,
+                    summary.skipped,
+                    summary.failed,
+                    summary.rejected
+                );
+            }
+            if result.summary.failed > 0 || result.summary.rejected > 0 {
+                return Ok(EXIT_SERVER_ERROR);
+            }
+        }
+        Command::Debug(DebugCommand::Fetch {
+            id, manifest: true, ..
+        }) => {
+            let manifest = client.debug_manifest(&id).await?;
+            if output.json {
+                output.print_json(&manifest);
+            } else {
+                let mut fields = vec![
+                    ("invocation", manifest.invocation_id.clone()),
+                    ("function", manifest.function.clone()),
+                    ("version", manifest.version.clone()),
+                    ("type", manifest.script_type.clone()),
+                    ("success", manifest.success.to_string()),
+                    ("attempts", manifest.attempts.to_string()),
+                    ("duration", format!("{}ms", manifest.duration_ms)),
+                    ("captured", manifest.captured_at.to_rfc3339()),
+                ];
+                if let Some(error) = &manifest.error {
+                    fields.push(("error", error.clone()));
+                }
+                let toolchain: Vec<String> = manifest
+                    .toolchain
+                    .iter()
+                    .map(|(name, version)| format!("{name}={version}"))
+                    .collect();
+                fields.push(("toolchain", toolchain.join(", ")));
+                let env: Vec<&str> = manifest.env.keys().map(String::as_str).collect();
+                fields.push(("env", env.join(", ")));
+                print_fields(&fields);
+                println!();
+                output.table(
+                    &manifest.files,
+                    &["FILE", "BYTES"],
+                    manifest.files.iter().map(|file| {
+                        let truncated = if file.truncated { " (truncated)" } else { "" };
+                        vec![file.path.clone(), format!("{}{truncated}", file.bytes)]
+                    }),
+                );
+            }
+        }
+        Command::Debug(DebugCommand::Fetch {
+            id, output: path, ..
+        }) => {
+            let bundle = client.debug_bundle(&id).await?;
+            let path =
+                path.unwrap_or_else(|| PathBuf::from(format!("{}.tar.gz", id.replace(':', "_"))));
+            std::fs::write(&path, &bundle)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {e}", path.display()))?;
+            output.message(
+                &json!({"invocation_id": id, "path": path, "bytes": bundle.len()}),
+                &format!(
+                    "Saved debug bundle of '{id}' to {} ({} bytes)",
+                    path.display(),
+                    bundle.len()
+                ),
+            );
         }
-        println!("总计: {} 个函数", functions.len());
     }
-
-    Ok(())
+    Ok(0)
 }
 
-/// 调用函数
-async fn invoke_function(client: &reqwest::Client, base_url: &str) -> anyhow::Result<()> {
-    // 先获取函数列表
-    let response = client.get(format!("{base_url}/functions")).send().await?;
-
-    if !response.status().is_success() {
-        println!("❌ 获取函数列表失败");
-        return Ok(());
-    }
-
-    let data: Value = response.json().await?;
-    let empty_vec = vec![];
-    let functions = data["data"].as_array().unwrap_or(&empty_vec);
-
-    if functions.is_empty() {
-        println!("❌ 没有可用的函数");
-        return Ok(());
-    }
-
-    println!("📋 可用函数:");
-    for (i, func) in functions.iter().enumerate() {
-        let name = func["name"].as_str().unwrap_or("Unknown");
-        let id = func["id"].as_str().unwrap_or("Unknown");
-        println!("{}. {} (ID: {})", i + 1, name, id);
-    }
-
-    print!("请选择要调用的函数 (输入编号): ");
-    io::stdout().flush()?;
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-
-    let choice: usize = input.trim().parse().unwrap_or(0);
-    if choice == 0 || choice > functions.len() {
-        println!("❌ 无效选择");
-        return Ok(());
-    }
-
-    let selected_func = &functions[choice - 1];
-    let function_name = selected_func["name"].as_str().unwrap_or("unknown");
-
-    print!("请输入函数参数 (JSON 格式，例如: {{\"a\": 1, \"b\": 2}}): ");
-    io::stdout().flush()?;
-
-    let mut input_data = String::new();
-    io::stdin().read_line(&mut input_data)?;
-
-    let input_json: Value = match serde_json::from_str(input_data.trim()) {
-        Ok(json) => json,
-        Err(_) => json!(input_data.trim()),
+/// 从源文件构建注册请求，其余字段使用服务端默认值
+fn register_request(
+    file: &Path,
+    name: Option<String>,
+    timeout: Option<u64>,
+    description: Option<String>,
+) -> anyhow::Result<RegisterFunctionRequest> {
+    let code = std::fs::read_to_string(file)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", file.display()))?;
+    let name = match name {
+        Some(name) => name,
+        None => file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow::anyhow!("Cannot derive a function name from {file:?}"))?,
     };
-
-    println!("🚀 正在调用函数 '{function_name}'...");
-
-    let invoke_request = json!({
-        "input": input_json
-    });
-
-    let response = client
-        .post(format!("{base_url}/invoke/{function_name}"))
-        .json(&invoke_request)
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        let result: Value = response.json().await?;
-        println!("✅ 函数执行成功!");
-        println!("📤 输出: {}", serde_json::to_string_pretty(&result)?);
-    } else {
-        let error_text = response.text().await?;
-        println!("❌ 函数执行失败: {error_text}");
-    }
-
-    Ok(())
-}
-
-/// 注册新函数
-async fn register_new_function(client: &reqwest::Client, base_url: &str) -> anyhow::Result<()> {
-    println!("➕ 注册新函数（将生成 SCRU128 ID）");
-
-    print!("函数名称: ");
-    io::stdout().flush()?;
-    let mut name = String::new();
-    io::stdin().read_line(&mut name)?;
-    let name = name.trim().to_string();
-
-    if name.is_empty() {
-        println!("❌ 函数名称不能为空");
-        return Ok(());
-    }
-
-    print!("函数描述 (可选): ");
-    io::stdout().flush()?;
-    let mut description = String::new();
-    io::stdin().read_line(&mut description)?;
-    let description = description.trim();
-
-    print!("函数代码: ");
-    io::stdout().flush()?;
-    let mut code = String::new();
-    io::stdin().read_line(&mut code)?;
-    let code = code.trim().to_string();
-
-    if code.is_empty() {
-        println!("❌ 函数代码不能为空");
-        return Ok(());
-    }
-
-    print!("超时时间 (毫秒，默认 5000): ");
-    io::stdout().flush()?;
-    let mut timeout_input = String::new();
-    io::stdin().read_line(&mut timeout_input)?;
-    let timeout_ms = timeout_input.trim().parse().unwrap_or(5000);
-
-    let register_request = json!({
+    Ok(serde_json::from_value(json!({
         "name": name,
-        "description": if description.is_empty() { Null } else { json!(description) },
         "code": code,
-        "timeout_ms": timeout_ms
-    });
-
-    let response = client
-        .post(format!("{base_url}/functions"))
-        .json(&register_request)
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        let result: Value = response.json().await?;
-        println!("✅ 函数 '{name}' 注册成功!");
-        if let Some(function_data) = result.get("function") {
-            if let Some(id) = function_data["id"].as_str() {
-                println!("   🆔 分配的 SCRU128 ID: {id}");
-                println!("   💡 此 ID 包含时间信息，支持自然排序");
-            }
-        }
-    } else {
-        let error_text = response.text().await?;
-        println!("❌ 函数注册失败: {error_text}");
-    }
-
-    Ok(())
+        "timeout_ms": timeout,
+        "description": description,
+    }))?)
 }
 
-/// 从文件加载函数
-async fn load_function_from_file(client: &reqwest::Client, base_url: &str) -> anyhow::Result<()> {
-    println!("📁 从文件加载函数");
-
-    print!("请输入函数文件路径 (例如: examples/functions/greet.rs): ");
-    io::stdout().flush()?;
-    let mut file_path = String::new();
-    io::stdin().read_line(&mut file_path)?;
-    let file_path = file_path.trim();
-
-    if file_path.is_empty() {
-        println!("❌ 文件路径不能为空");
-        return Ok(());
-    }
-
-    print!("自定义函数名称 (可选，回车使用文件名): ");
-    io::stdout().flush()?;
-    let mut custom_name = String::new();
-    io::stdin().read_line(&mut custom_name)?;
-    let custom_name = custom_name.trim();
-
-    print!("函数描述 (可选): ");
-    io::stdout().flush()?;
-    let mut description = String::new();
-    io::stdin().read_line(&mut description)?;
-    let description = description.trim();
-
-    print!("超时时间 (毫秒，默认 5000): ");
-    io::stdout().flush()?;
-    let mut timeout_input = String::new();
-    io::stdin().read_line(&mut timeout_input)?;
-    let timeout_ms: Option<u64> = timeout_input.trim().parse().ok();
-
-    let load_request = json!({
-        "file_path": file_path,
-        "function_name": if custom_name.is_empty() { Null } else { json!(custom_name) },
-        "description": if description.is_empty() { Null } else { json!(description) },
-        "timeout_ms": timeout_ms
-    });
-
-    println!("📤 正在从文件加载函数...");
-
-    let response = client
-        .post(format!("{base_url}/load/file"))
-        .json(&load_request)
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        println!("✅ 函数从文件 '{file_path}' 加载成功!");
-        println!("💡 函数已通过安全验证并注册到系统中");
-    } else {
-        let error_text = response.text().await?;
-        println!("❌ 函数加载失败: {error_text}");
-        println!("💡 请检查文件路径和文件内容是否正确");
-    }
+fn parse_input(data: &str) -> anyhow::Result<Value> {
+    serde_json::from_str(data).map_err(|e| anyhow::anyhow!("Input is not valid JSON: {e}"))
+}
 
-    Ok(())
+/// 调用一次并输出结果，返回执行状态对应的退出码
+async fn invoke(
+    client: &FluxClient,
+    output: &Output,
+    name: &str,
+    input: Value,
+) -> Result<u8, Failure> {
+    let request = InvokeRequest {
+        input,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
+    };
+    let response = client.invoke(name, &request).await?;
+    output.invocation(&response);
+    Ok(response.status.exit_code())
 }
 
-/// 从目录批量加载函数
-async fn load_functions_from_directory(
-    client: &reqwest::Client,
-    base_url: &str,
+/// 调用一次，之后输入文件每次变化时重新调用；失败只输出错误，继续监控
+async fn watch(
+    client: &FluxClient,
+    output: &Output,
+    name: &str,
+    file: &Path,
 ) -> anyhow::Result<()> {
-    println!("📂 从目录批量加载函数");
-
-    print!("请输入函数目录路径 (例如: examples/functions): ");
-    io::stdout().flush()?;
-    let mut dir_path = String::new();
-    io::stdin().read_line(&mut dir_path)?;
-    let dir_path = dir_path.trim();
-
-    if dir_path.is_empty() {
-        println!("❌ 目录路径不能为空");
-        return Ok(());
-    }
-
-    println!("📤 正在从目录批量加载函数...");
-
-    let load_request = json!({
-        "directory_path": dir_path
-    });
-
-    let response = client
-        .post(format!("{base_url}/load/directory"))
-        .json(&load_request)
-        .send()
-        .await?;
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = RecommendedWatcher::new(
+        move |result: notify::Result<Event>| {
+            if let Ok(event) = result {
+                let _ = sender.send(event);
+            }
+        },
+        notify::Config::default(),
+    )?;
+    // 编辑器保存时常以新文件替换原文件，监控所在目录并按文件名过滤
+    let file = std::path::absolute(file)?;
+    let file_name = file.file_name().map(|name| name.to_os_string());
+    let dir = file.parent().unwrap_or(Path::new("."));
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    eprintln!("Watching {} (Ctrl-C to stop)", file.display());
 
-    if response.status().is_success() {
-        let result: Value = response.json().await?;
-        if let Some(data) = result.get("data") {
-            if let Some(data_str) = data.as_str() {
-                println!("✅ {data_str}");
-                println!("💡 所有函数都已通过安全验证并注册到系统中");
+    loop {
+        let input = tokio::fs::read_to_string(&file)
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|data| parse_input(&data));
+        match input {
+            Ok(input) => {
+                let _ = invoke(client, output, name, input).await;
             }
+            Err(e) => eprintln!("error: {e:#}"),
         }
-        if let Some(message) = result.get("message") {
-            if let Some(msg) = message.as_str() {
-                println!("📝 {msg}");
+
+        loop {
+            let event = receiver
+                .recv()
+                .await
+                .ok_or_else(|| anyhow::anyhow!("File watcher stopped"))?;
+            let relevant = (event.kind.is_create() || event.kind.is_modify())
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name() == file_name.as_deref());
+            if relevant {
+                break;
             }
         }
-    } else {
-        let error_text = response.text().await?;
-        println!("❌ 批量加载失败: {error_text}");
-        println!("💡 请检查目录路径是否正确");
+        // 一次保存通常产生多个事件，稍等片刻后合并
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        while receiver.try_recv().is_ok() {}
     }
-
-    Ok(())
 }
 
-/// 显示系统状态
-async fn show_system_status(client: &reqwest::Client, base_url: &str) -> anyhow::Result<()> {
-    let response = client.get(format!("{base_url}/status")).send().await?;
-
-    if !response.status().is_success() {
-        println!("❌ 获取系统状态失败");
-        return Ok(());
-    }
-
-    let data: Value = response.json().await?;
-
-    println!("📊 FluxFaaS 系统状态");
-    println!("==================");
-
-    if let Some(functions) = data["functions"]["total"].as_u64() {
-        println!("🔧 已注册函数数量: {functions}");
-    }
-
-    println!("🆔 ID 系统: SCRU128 (时间有序)");
-    println!(
-        "🚀 运行状态: {}",
-        data["status"].as_str().unwrap_or("unknown")
-    );
-
-    if let Some(runtime) = data["runtime"].as_object() {
-        println!(
-            "⚡ 执行引擎: {}",
-            runtime["type"].as_str().unwrap_or("unknown")
-        );
-    }
-
-    if let Some(timestamp) = data["timestamp"].as_str() {
-        println!("📅 状态时间: {timestamp}");
-    }
-
-    Ok(())
+/// 输出格式
+struct Output {
+    json: bool,
 }
 
-/// 显示缓存统计信息
-async fn show_cache_statistics(client: &reqwest::Client, base_url: &str) -> anyhow::Result<()> {
-    let response = client.get(format!("{base_url}/cache/stats")).send().await?;
-
-    if !response.status().is_success() {
-        println!("❌ 获取缓存统计失败");
-        return Ok(());
+impl Output {
+    fn print_json(&self, value: &impl Serialize) {
+        match serde_json::to_value(value) {
+            Ok(value) => println!("{}", pretty(&value)),
+            Err(e) => eprintln!("error: failed to serialize output: {e}"),
+        }
     }
 
-    let data: Value = response.json().await?;
-
-    println!("🎯 FluxFaaS 缓存统计");
-    println!("===================");
-
-    if let Some(stats) = data["data"]["basic_stats"].as_object() {
-        println!("📊 基本统计:");
-        if let Some(hit_rate) = stats["hit_rate"].as_f64() {
-            println!("  🎯 缓存命中率: {:.2}%", hit_rate * 100.0);
-        }
-        if let Some(hits) = stats["hits"].as_u64() {
-            println!("  ✅ 命中次数: {hits}");
-        }
-        if let Some(misses) = stats["misses"].as_u64() {
-            println!("  ❌ 未命中次数: {misses}");
-        }
-        if let Some(size) = stats["size"].as_u64() {
-            println!("  📏 当前缓存大小: {size} 个函数");
+    fn message(&self, value: &Value, text: &str) {
+        if self.json {
+            self.print_json(value);
+        } else {
+            println!("{text}");
         }
     }
 
-    if let Some(memory) = data["data"]["memory_usage"].as_object() {
-        println!("\n💾 内存使用:");
-        if let Some(current_kb) = memory["current_kb"].as_f64() {
-            println!("  🔍 当前使用: {current_kb:.2} KB");
+    /// `--json` 时输出 `value`，否则输出表格
+    fn table(
+        &self,
+        value: &impl Serialize,
+        headers: &[&str],
+        rows: impl IntoIterator<Item = Vec<String>>,
+    ) {
+        if self.json {
+            self.print_json(value);
+            return;
         }
-        if let Some(max_mb) = memory["max_mb"].as_f64() {
-            println!("  📏 最大限制: {max_mb:.2} MB");
+        let rows: Vec<Vec<String>> = rows.into_iter().collect();
+        let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
         }
-        if let Some(usage_percent) = memory["usage_percent"].as_f64() {
-            println!("  📊 使用率: {usage_percent:.2}%");
+        let line = |cells: Vec<&str>| {
+            let padded: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect();
+            println!("{}", padded.join("  ").trim_end());
+        };
+        line(headers.to_vec());
+        for row in &rows {
+            line(row.iter().map(String::as_str).collect());
         }
     }
 
-    Ok(())
-}
-
-/// 显示性能监控信息
-async fn show_performance_monitor(client: &reqwest::Client, base_url: &str) -> anyhow::Result<()> {
-    let response = client
-        .get(format!("{base_url}/performance/stats"))
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        println!("❌ 获取性能监控失败");
-        return Ok(());
-    }
-
-    let data: Value = response.json().await?;
-
-    println!("📊 FluxFaaS 性能监控");
-    println!("===================");
-
-    if let Some(monitor_data) = data["data"].as_object() {
-        if let Some(health) = monitor_data["health_status"].as_str() {
-            let health_icon = match health {
-                "healthy" => "💚",
-                "warning" => "💛",
-                "critical" => "❤️",
-                _ => "❓",
-            };
-            println!("🏥 系统健康状态: {health_icon} {health}");
+    fn invocation(&self, response: &InvokeResponse) {
+        if self.json {
+            self.print_json(response);
+            return;
         }
-
-        if let Some(global) = monitor_data["global_stats"].as_object() {
-            println!("\n📈 全局统计:");
-            if let Some(total) = global["total_requests"].as_u64() {
-                println!("  📝 总请求数: {total}");
-            }
-            if let Some(success) = global["total_success"].as_u64() {
-                println!("  ✅ 成功次数: {success}");
-            }
-            if let Some(failures) = global["total_failures"].as_u64() {
-                println!("  ❌ 失败次数: {failures}");
-            }
-            if let Some(rate) = global["success_rate"].as_f64() {
-                println!("  🎯 成功率: {rate:.2}%");
-            }
+        let state = match &response.status {
+            ExecutionStatus::Success => "success".to_string(),
+            ExecutionStatus::Timeout => "timeout".to_string(),
+            other => format!("failed: {}", other.failure_message().unwrap_or_default()),
+        };
+        let mut line = format!("{state} ({}ms", response.execution_time_ms);
+        if response.attempts_made > 1 {
+            line.push_str(&format!(", {} attempts", response.attempts_made));
         }
-
-        if let Some(hottest) = monitor_data["hottest_functions"].as_array() {
-            if !hottest.is_empty() {
-                println!("\n🔥 热点函数 (调用最频繁):");
-                for (i, func) in hottest.iter().enumerate() {
-                    if let (Some(name), Some(calls)) =
-                        (func["name"].as_str(), func["calls"].as_u64())
-                    {
-                        println!("  {}. {} ({} 次调用)", i + 1, name, calls);
-                    }
-                }
-            }
+        if let Some(request_id) = &response.request_id {
+            line.push_str(&format!(", request {request_id}"));
         }
+        println!("{line})");
+        println!("{}", pretty(&response.output));
     }
-
-    Ok(())
 }
 
-/// 重置性能监控数据
-async fn reset_performance_data(client: &reqwest::Client, base_url: &str) -> anyhow::Result<()> {
-    println!("🔄 重置性能监控数据");
-
-    print!("确认要重置所有性能监控数据吗？(y/N): ");
-    io::stdout().flush()?;
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    let confirmation = input.trim().to_lowercase();
-
-    if confirmation == "y" || confirmation == "yes" {
-        let response = client.post(format!("{base_url}/reset")).send().await?;
-
-        if response.status().is_success() {
-            println!("✅ 性能监控数据已重置");
-            println!("💡 新的统计将从现在开始重新计算");
-        } else {
-            let error_text = response.text().await?;
-            println!("❌ 重置失败: {error_text}");
-        }
-    } else {
-        println!("❌ 重置操作已取消");
+fn print_fields(fields: &[(&str, String)]) {
+    let width = fields.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    for (key, value) in fields {
+        println!("{key:<width$}  {value}");
     }
+}
 
-    Ok(())
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
 }
@@ -0,0 +1,50 @@
+use crate::gateway::envelope::ApiError;
+
+/// 客户端错误：区分网络/传输失败和服务端返回的错误
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    /// 请求未能送达或响应未能读取（连接失败、超时等）
+    #[error("Transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+
+    /// 服务端返回的错误（`code` 与 `/v1` 响应体中的 `error.code` 一致）
+    #[error("Server error {status} ({}): {}", .error.code, .error.message)]
+    Server { status: u16, error: ApiError },
+
+    /// 响应体不是预期的格式（例如函数返回了 `$raw`/`$http` 响应）
+    #[error("Unexpected response ({status}): {reason}")]
+    Decode { status: u16, reason: String },
+}
+
+impl ClientError {
+    /// HTTP 状态码（传输失败时为 `None`）
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Transport(e) => e.status().map(|status| status.as_u16()),
+            Self::Server { status, .. } | Self::Decode { status, .. } => Some(*status),
+        }
+    }
+
+    /// 服务端错误码，例如 `not_found`、`conflict`、`too_many_requests`
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Self::Server { error, .. } => Some(&error.code),
+            _ => None,
+        }
+    }
+
+    /// 函数不存在（`FluxError::FunctionNotFound`）
+    pub fn is_not_found(&self) -> bool {
+        self.code() == Some("not_found")
+    }
+
+    /// 函数已存在（`FluxError::FunctionAlreadyExists`）或仍在编译（`FluxError::StillCompiling`）
+    pub fn is_conflict(&self) -> bool {
+        self.code() == Some("conflict")
+    }
+
+    /// 调用队列已满（`FluxError::QueueFull`），可稍后重试
+    pub fn is_queue_full(&self) -> bool {
+        self.code() == Some("too_many_requests")
+    }
+}
@@ -0,0 +1,412 @@
+//! FluxFaaS HTTP API 的 Rust 客户端
+//!
+//! 请求和响应直接复用服务端类型（[`RegisterFunctionRequest`]、[`InvokeRequest`]、
+//! [`InvokeResponse`]、[`FunctionMetadata`] 等），通过 `/v1` 路由通信。
+
+use crate::gateway::envelope::{API_VERSION, Envelope};
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::time::Duration;
+
+pub mod error;
+
+pub use crate::functions::bundle::{ConflictStrategy, ImportPayload, ImportResult};
+pub use crate::functions::{
+    FunctionMetadata, InvokeRequest, InvokeResponse, RegisterFunctionRequest,
+};
+pub use crate::gateway::envelope::ApiError;
+pub use crate::gateway::handlers::{FunctionDetails, FunctionSummary};
+pub use crate::runtime::debug_capture::BundleManifest;
+pub use crate::runtime::loader::DirectoryLoadResult;
+pub use crate::runtime::series::FunctionReport;
+pub use error::ClientError;
+
+/// 客户端操作结果
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// FluxFaaS 异步客户端
+///
+/// 可廉价克隆，克隆后共享连接池。服务端返回 5xx 或连接失败时按配置重试；
+/// 调用函数时 500 表示函数自身执行失败，不会重试。
+#[derive(Debug, Clone)]
+pub struct FluxClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+impl FluxClient {
+    /// 创建客户端，`base_url` 例如 `http://127.0.0.1:3000`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key: None,
+            headers: Vec::new(),
+            timeout: Duration::from_secs(30),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(100),
+        }
+    }
+
+    /// 以 `Authorization: Bearer <key>` 发送 API 密钥
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// 每个请求都带上的请求头
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// 单次请求的超时时间（默认 30 秒）
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 5xx 或连接失败时的最大重试次数（默认 2），退避时间每次翻倍
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// 健康检查（`GET /v1/health`）
+    pub async fn health(&self) -> Result<String> {
+        self.send(Method::GET, "health", None::<&()>, true).await
+    }
+
+    /// 注册函数
+    pub async fn register(&self, request: &RegisterFunctionRequest) -> Result<()> {
+        self.send::<String>(Method::POST, "functions", Some(request), true)
+            .await
+            .map(drop)
+    }
+
+    /// 同步调用函数并等待结果
+    pub async fn invoke(&self, name: &str, request: &InvokeRequest) -> Result<InvokeResponse> {
+        self.send(
+            Method::POST,
+            &format!("invoke/{name}"),
+            Some(request),
+            false,
+        )
+        .await
+    }
+
+    /// 在后台调用函数，立即返回任务句柄
+    ///
+    /// 服务端没有异步调用接口，调用在客户端的后台任务中进行，
+    /// 丢弃句柄不会取消已发出的请求。
+    pub fn invoke_async(
+        &self,
+        name: &str,
+        request: InvokeRequest,
+    ) -> tokio::task::JoinHandle<Result<InvokeResponse>> {
+        let client = self.clone();
+        let name = name.to_string();
+        tokio::spawn(async move { client.invoke(&name, &request).await })
+    }
+
+    /// 列出函数
+    pub async fn list_functions(&self) -> Result<Vec<FunctionSummary>> {
+        self.send(Method::GET, "functions", None::<&()>, true).await
+    }
+
+    /// 获取函数详情（含编译状态）
+    pub async fn get_function(&self, name: &str) -> Result<FunctionDetails> {
+        self.send(Method::GET, &format!("functions/{name}"), None::<&()>, true)
+            .await
+    }
+
+    /// 删除函数
+    pub async fn delete(&self, name: &str) -> Result<()> {
+        self.send::<String>(
+            Method::DELETE,
+            &format!("functions/{name}"),
+            None::<&()>,
+            true,
+        )
+        .await
+        .map(drop)
+    }
+
+    /// 函数最近一小时的调用统计（延迟分位数、错误率和内存峰值）
+    pub async fn function_report(&self, name: &str) -> Result<FunctionReport> {
+        self.send(
+            Method::GET,
+            &format!("functions/{name}/report"),
+            None::<&()>,
+            true,
+        )
+        .await
+    }
+
+    /// 从服务端所在机器上的目录批量加载函数（同名函数跳过）
+    pub async fn load_directory(&self, directory_path: &str) -> Result<DirectoryLoadResult> {
+        let body = serde_json::json!({ "directory_path": directory_path });
+        self.send(Method::POST, "load/directory", Some(&body), true)
+            .await
+    }
+
+    /// 导入导出包或归档，`conflict` 为同名函数的处理方式
+    pub async fn import(
+        &self,
+        payload: &ImportPayload,
+        conflict: ConflictStrategy,
+    ) -> Result<Vec<ImportResult>> {
+        let conflict = match conflict {
+            ConflictStrategy::Skip => "skip",
+            ConflictStrategy::Overwrite => "overwrite",
+            ConflictStrategy::Rename => "rename",
+            ConflictStrategy::Fail => "fail",
+        };
+        self.send(
+            Method::POST,
+            &format!("functions/import?conflict={conflict}"),
+            Some(payload),
+            true,
+        )
+        .await
+    }
+
+    /// 系统状态（`GET /v1/status`：调度器、缓存、实例和沙箱统计）
+    pub async fn stats(&self) -> Result<Value> {
+        self.send(Method::GET, "status", None::<&()>, true).await
+    }
+
+    /// 调用的调试包清单（函数的 `debug_capture` 未启用或调试包已被淘汰时返回 404）
+    pub async fn debug_manifest(&self, id: &str) -> Result<BundleManifest> {
+        self.send(
+            Method::GET,
+            &format!("executions/{id}/bundle/manifest"),
+            None::<&()>,
+            true,
+        )
+        .await
+    }
+
+    /// 下载调用的调试包（tar.gz）
+    pub async fn debug_bundle(&self, id: &str) -> Result<Vec<u8>> {
+        let response = self
+            .request(
+                Method::GET,
+                &format!("executions/{id}/bundle"),
+                None::<&()>,
+                true,
+            )
+            .await?;
+        if response.status().is_success() {
+            return Ok(response.bytes().await?.to_vec());
+        }
+        // 失败时响应体为 `/v1` 错误
+        let status = response.status().as_u16();
+        Self::decode::<Value>(response).await?;
+        Err(ClientError::Decode {
+            status,
+            reason: "unexpected success envelope for a failed download".to_string(),
+        })
+    }
+
+    /// 发送请求并解析 `/v1` 响应体，必要时重试
+    async fn send<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&impl Serialize>,
+        retry_on_500: bool,
+    ) -> Result<T> {
+        Self::decode(self.request(method, path, body, retry_on_500).await?).await
+    }
+
+    /// 发送请求，服务端返回 5xx 或连接失败时按配置重试
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&impl Serialize>,
+        retry_on_500: bool,
+    ) -> Result<reqwest::Response> {
+        let url = format!("{}/{API_VERSION}/{path}", self.base_url);
+        let mut attempt = 0;
+        loop {
+            let mut request = self
+                .http
+                .request(method.clone(), &url)
+                .timeout(self.timeout);
+            if let Some(api_key) = &self.api_key {
+                request = request.bearer_auth(api_key);
+            }
+            for (name, value) in &self.headers {
+                request = request.header(name, value);
+            }
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+
+            let result = request.send().await;
+            let retryable = match &result {
+                Ok(response) => {
+                    let status = response.status();
+                    status.is_server_error()
+                        && (retry_on_500 || status != StatusCode::INTERNAL_SERVER_ERROR)
+                }
+                // 只重试未送达的请求，超时的调用可能已在服务端执行
+                Err(e) => e.is_connect(),
+            };
+            if retryable && attempt < self.max_retries {
+                tokio::time::sleep(self.retry_backoff * 2u32.saturating_pow(attempt)).await;
+                attempt += 1;
+                continue;
+            }
+            return Ok(result?);
+        }
+    }
+
+    async fn decode<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+        let status = response.status().as_u16();
+        let body = response.bytes().await?;
+        let envelope: Envelope =
+            serde_json::from_slice(&body).map_err(|e| ClientError::Decode {
+                status,
+                reason: format!("not a /{API_VERSION} response body: {e}"),
+            })?;
+        match envelope {
+            Envelope::Data { data, .. } => {
+                serde_json::from_value(data).map_err(|e| ClientError::Decode {
+                    status,
+                    reason: e.to_string(),
+                })
+            }
+            Envelope::Error { error, .. } => Err(ClientError::Server { status, error }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::FluxError;
+    use crate::gateway::routes::build_routes;
+    use crate::scheduler::SimpleScheduler;
+    use crate::scheduler::profiles::SchedulerRegistry;
+    use serde_json::json;
+    use silent::prelude::{Configs, Listener, Server};
+    use std::sync::Arc;
+
+    /// 在随机端口启动完整路由，返回基础地址
+    async fn spawn_server() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut configs = Configs::default();
+        configs.insert(Arc::new(SchedulerRegistry::with_default(Arc::new(
+            SimpleScheduler::new(),
+        ))));
+        tokio::spawn(
+            Server::new()
+                .with_configs(configs)
+                .listen(Listener::from(listener))
+                .serve(build_routes()),
+        );
+        format!("http://{addr}")
+    }
+
+    fn register_request(name: &str) -> RegisterFunctionRequest {
+        serde_json::from_value(json!({"name": name, "code": "return input"})).unwrap()
+    }
+
+    fn invoke_request(input: Value) -> InvokeRequest {
+        InvokeRequest {
+            input,
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_round_trip_against_real_server() {
+        let client = FluxClient::new(spawn_server().await).with_api_key("secret");
+
+        client.register(&register_request("greet")).await.unwrap();
+        let err = client
+            .register(&register_request("greet"))
+            .await
+            .unwrap_err();
+        assert!(err.is_conflict(), "{err}");
+        assert_eq!(err.status(), Some(409));
+
+        let functions = client.list_functions().await.unwrap();
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].name, "greet");
+        let details = client.get_function("greet").await.unwrap();
+        assert_eq!(details.function.name, "greet");
+        assert_eq!(details.function.code, "return input");
+
+        let response = client
+            .invoke("greet", &invoke_request(json!({"a": 1})))
+            .await
+            .unwrap();
+        assert!(response.status.is_success());
+        assert_eq!(response.output["input"], json!({"a": 1}));
+        assert!(response.request_id.is_some());
+        let response = client
+            .invoke_async("greet", invoke_request(json!(2)))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.output["input"], json!(2));
+
+        let report = client.function_report("greet").await.unwrap();
+        assert_eq!(report.summary.invocations, 2);
+
+        let stats = client.stats().await.unwrap();
+        assert!(stats.get("profiles").is_some(), "{stats}");
+
+        client.delete("greet").await.unwrap();
+        let err = client.get_function("greet").await.unwrap_err();
+        assert!(err.is_not_found(), "{err}");
+        // 错误信息来自服务端的 FluxError
+        let expected = FluxError::FunctionNotFound {
+            name: "greet".to_string(),
+        };
+        assert!(matches!(
+            &err,
+            ClientError::Server { error, .. } if error.message.contains(&expected.to_string())
+        ));
+        assert!(client.delete("greet").await.unwrap_err().is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_transport_failures_are_distinguished_from_server_errors() {
+        // 绑定后立即释放端口，连接会被拒绝
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let client = FluxClient::new(format!("http://{addr}/"))
+            .with_retries(1, Duration::from_millis(1))
+            .with_timeout(Duration::from_secs(2));
+        assert_eq!(client.base_url(), format!("http://{addr}"));
+
+        let err = client.list_functions().await.unwrap_err();
+        assert!(matches!(err, ClientError::Transport(_)), "{err}");
+        assert_eq!(err.status(), None);
+        assert_eq!(err.code(), None);
+    }
+}
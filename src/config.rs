@@ -0,0 +1,235 @@
+use crate::functions::redaction::LoggingConfig;
+use crate::functions::status::StatusWireFormat;
+use crate::functions::storage::StorageConfig;
+use crate::gateway::dashboard::DashboardConfig;
+use crate::gateway::payload::{CompressionConfig, InvokeBodyConfig};
+use crate::gateway::websocket::WsInvokeConfig;
+use crate::runtime::debug_capture::DebugCaptureConfig;
+use crate::runtime::environment::RuntimeProbeConfig;
+use crate::runtime::events::EventRetentionConfig;
+use crate::runtime::invocation_log::LogCaptureConfig;
+use crate::runtime::janitor::JanitorConfig;
+use crate::runtime::oci::OciSourceConfig;
+use crate::runtime::sandbox::SandboxConfig;
+use crate::runtime::series::SeriesConfig;
+use crate::runtime::slo::SloAlertConfig;
+use crate::runtime::state::StateConfig;
+use crate::scheduler::canary::CanaryProbeConfig;
+use crate::scheduler::chaos::ChaosConfig;
+use crate::scheduler::circuit::CircuitBreakerConfig;
+use crate::scheduler::console::ConsoleConfig;
+use crate::scheduler::history::HistoryConfig;
+use crate::scheduler::jobs::JobsConfig;
+use crate::scheduler::limiter::InvocationLimitConfig;
+use crate::scheduler::namespaces::NamespaceConfig;
+use crate::scheduler::peers::PeersConfig;
+use crate::scheduler::profiles::SchedulerProfileConfig;
+use crate::scheduler::sessions::SessionsConfig;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// 配置文件路径环境变量
+pub const CONFIG_PATH_ENV: &str = "FLUX_CONFIG";
+/// 默认配置文件路径
+pub const DEFAULT_CONFIG_PATH: &str = "flux-server.toml";
+
+/// 服务端配置（TOML 格式）
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FluxConfig {
+    /// 链路追踪配置
+    pub tracing: TracingConfig,
+    /// 实例生命周期事件保留配置
+    pub events: EventRetentionConfig,
+    /// WebSocket 调用通道配置
+    pub websocket: WsInvokeConfig,
+    /// HTTP 调用请求体配置
+    pub invoke: InvokeBodyConfig,
+    /// 请求体解压上限和响应压缩配置
+    pub compression: CompressionConfig,
+    /// 命名调度器配置（`[profiles.<name>]`），未配置 `default` 时自动补齐
+    pub profiles: BTreeMap<String, SchedulerProfileConfig>,
+    /// 执行状态的序列化格式（`legacy` 或 `typed`），默认 `legacy`
+    pub status_format: StatusWireFormat,
+    /// 磁盘清理配置（孤立目录 TTL、磁盘预算等）
+    pub janitor: JanitorConfig,
+    /// 函数执行时间序列配置（分桶时长、保留时长、跟踪函数数上限）
+    pub metrics: SeriesConfig,
+    /// 运行时探测配置（node、python、tsc/esbuild、rustc 的重新探测间隔）和命名执行环境
+    /// （`[[runtimes.definitions]]`）
+    pub runtimes: RuntimeProbeConfig,
+    /// 启动时创建的命名空间及其默认限制（`[namespaces.<name>]`）
+    pub namespaces: BTreeMap<String, NamespaceConfig>,
+    /// 日志中调用载荷的字节上限
+    pub logging: LoggingConfig,
+    /// 按调用捕获的结构化日志（`?capture_log=`）的条目数、字节上限、全局内存预算和保留数
+    pub log_capture: LogCaptureConfig,
+    /// 故障注入（`[chaos] enabled = true` 时开放 `/admin/chaos/rules`）
+    pub chaos: ChaosConfig,
+    /// 执行历史（`POST /executions/:id/replay` 重放的调用范围）
+    pub history: HistoryConfig,
+    /// 调用熔断（按函数统计失败率，`circuit_breaker = false` 的函数不参与）
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// 运维仪表盘（`/dashboard/stream` 的心跳间隔）
+    pub dashboard: DashboardConfig,
+    /// 函数键值状态（值大小、键数和总字节上限，可选的持久化文件）
+    pub state: StateConfig,
+    /// 函数目录的持久化后端（对象存储或本地目录），多个副本可共享同一对象存储前缀
+    pub storage: StorageConfig,
+    /// 全进程的调用并发上限、等待队列长度和排队超时（超出时返回 503）
+    pub limits: InvocationLimitConfig,
+    /// 调试包目录、总大小上限和保留时长（函数的 `debug_capture` 启用时写入）
+    pub debug_capture: DebugCaptureConfig,
+    /// 金丝雀探测（`[canaries] enabled = false` 时全局停止探测）
+    pub canaries: CanaryProbeConfig,
+    /// 函数 SLO 的燃烧率告警（快速和慢速窗口、阈值、解除比例和检查间隔）
+    pub slo: SloAlertConfig,
+    /// OCI 制品加载（`/load/oci`）的缓存目录、超时、大小上限和使用 HTTP 的仓库
+    pub oci: OciSourceConfig,
+    /// 异步调用任务的保留数、长轮询等待上限和并发数、完成回调的重试
+    pub jobs: JobsConfig,
+    /// 按客户端会话分组的调用记录的会话数上限、每个会话保留的调用数和保留的输入输出大小
+    pub sessions: SessionsConfig,
+    /// 脚本函数沙箱（执行超时、内存和 CPU 上限可通过 `POST /admin/config/reload` 重新加载）
+    pub sandbox: SandboxConfig,
+    /// 转发调用的 FluxFaaS 节点、健康检查和熔断，以及是否把函数同步给节点
+    pub peers: PeersConfig,
+    /// 交互式控制台（`/console/execute`）的代码和输入上限、按调用方的限速和代码保留时长
+    pub console: ConsoleConfig,
+}
+
+/// 链路追踪配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TracingConfig {
+    /// OTLP HTTP 导出地址，例如 `http://localhost:4318/v1/traces`（需启用 `otlp` 特性）
+    pub otlp_endpoint: Option<String>,
+    /// 上报的服务名
+    pub service_name: String,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: "flux".to_string(),
+        }
+    }
+}
+
+impl FluxConfig {
+    /// 从指定文件加载配置
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))
+    }
+
+    /// 配置文件路径：优先使用 `FLUX_CONFIG` 指定的文件，其次是存在的默认路径
+    pub fn locate() -> Option<PathBuf> {
+        match std::env::var(CONFIG_PATH_ENV) {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Some(PathBuf::from(DEFAULT_CONFIG_PATH))
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// 加载配置：从 [`Self::locate`] 找到的文件读取，没有配置文件时使用默认配置
+    pub fn load() -> Result<Self> {
+        match Self::locate() {
+            Some(path) => Self::from_file(path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// 校验各部分配置（启动和重新加载前调用，重新加载时校验失败则不应用任何改动）
+    pub fn validate(&self) -> Result<()> {
+        self.runtimes.validate()?;
+        self.circuit_breaker.validate()?;
+        self.slo.validate()?;
+        self.sandbox.validate()?;
+        self.peers.validate()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_partial_config() {
+        let config: FluxConfig =
+            toml::from_str("[tracing]\notlp_endpoint = \"http://localhost:4318/v1/traces\"\n")
+                .unwrap();
+        assert_eq!(
+            config.tracing.otlp_endpoint.as_deref(),
+            Some("http://localhost:4318/v1/traces")
+        );
+        assert_eq!(config.tracing.service_name, "flux");
+
+        let config: FluxConfig = toml::from_str("").unwrap();
+        assert!(config.tracing.otlp_endpoint.is_none());
+        assert_eq!(config.status_format, StatusWireFormat::Legacy);
+
+        let config: FluxConfig = toml::from_str("status_format = \"typed\"\n").unwrap();
+        assert_eq!(config.status_format, StatusWireFormat::Typed);
+    }
+
+    #[test]
+    fn test_parse_storage_config() {
+        let config: FluxConfig = toml::from_str(
+            r#"
+[storage]
+url = "memory:///"
+load_concurrency = 4
+options = { aws_region = "eu-west-1" }
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.storage.url.as_deref(), Some("memory:///"));
+        assert_eq!(config.storage.load_concurrency, 4);
+        assert_eq!(config.storage.max_retries, 3);
+        assert_eq!(config.storage.options["aws_region"], "eu-west-1");
+        assert!(config.storage.open("default").unwrap().is_some());
+
+        let config: FluxConfig = toml::from_str("").unwrap();
+        assert!(config.storage.open("default").unwrap().is_none());
+
+        let config: FluxConfig =
+            toml::from_str("[storage]\nurl = \"memory:///\"\npath = \"/tmp/flux\"\n").unwrap();
+        assert!(config.storage.open("default").is_err());
+    }
+
+    #[test]
+    fn test_parse_runtime_definitions() {
+        let config: FluxConfig = toml::from_str(
+            r#"
+[runtimes]
+refresh_interval_secs = 0
+
+[[runtimes.definitions]]
+name = "py39"
+kind = "python"
+binary = "/opt/py39/bin/python"
+env = { VIRTUAL_ENV = "/opt/py39" }
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.runtimes.refresh_interval_secs, 0);
+        let definition = &config.runtimes.definitions[0];
+        assert_eq!(definition.name, "py39");
+        assert_eq!(
+            definition.kind,
+            crate::runtime::environment::RuntimeKind::Python
+        );
+        assert_eq!(definition.env["VIRTUAL_ENV"], "/opt/py39");
+        assert!(config.runtimes.validate().is_ok());
+    }
+}
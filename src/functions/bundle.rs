@@ -0,0 +1,392 @@
+use super::deprecation::Deprecation;
+use super::package::FunctionPackage;
+use super::priority::Priority;
+use super::testing::FunctionTestCase;
+use super::{FluxError, FunctionMetadata, FunctionParameter, Result, RetryPolicy};
+use crate::runtime::inline::ExecutionHint;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// 导出格式版本
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// 当前支持的脚本类型
+pub const BUNDLE_SCRIPT_TYPE: &str = "rust";
+
+/// 单个函数的导出包（自包含，可在其他实例导入）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FunctionBundle {
+    /// 导出格式版本
+    pub format_version: u32,
+    pub name: String,
+    pub description: String,
+    pub code: String,
+    pub script_type: String,
+    pub timeout_ms: u64,
+    pub version: String,
+    pub dependencies: Vec<String>,
+    pub parameters: Vec<FunctionParameter>,
+    pub return_type: String,
+    pub retry_policy: Option<RetryPolicy>,
+    pub idempotent: bool,
+    /// 标签（有序，保证内容哈希稳定）
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// 调用优先级（默认 normal 时省略，保证旧包的内容哈希不变）
+    #[serde(default, skip_serializing_if = "Priority::is_normal")]
+    pub priority: Priority,
+    /// 多文件函数包（单文件函数省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<FunctionPackage>,
+    /// 输出按 HTTP 响应描述解释（默认 false 时省略）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub http_response: bool,
+    /// 调用输入模板（未设置时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_template: Option<String>,
+    /// 输入/输出 JSON Schema（未设置时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub input_schema: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub output_schema: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict_output_schema: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub coerce_output: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict_return_type: bool,
+    /// 日志脱敏字段（未设置时省略）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_redaction: Vec<String>,
+    /// 执行环境名（使用默认解释器时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<String>,
+    /// 资源配额名（未设置时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_quota: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub requires_isolation: bool,
+    /// 暂存目录上限（使用沙箱配置时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scratch_limit_mb: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub persist_scratch: bool,
+    /// 磁盘写入上限（未设置时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_disk_write_mb: Option<u64>,
+    /// CPU 时间上限（未设置时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cpu_time_ms: Option<u64>,
+    /// 测试用例（没有用例时省略）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<FunctionTestCase>,
+    /// 执行路径提示（`auto` 时省略）
+    #[serde(default, skip_serializing_if = "ExecutionHint::is_auto")]
+    pub execution_hint: ExecutionHint,
+    /// 是否参与调用熔断（默认 true 时省略）
+    #[serde(
+        default = "default_circuit_breaker",
+        skip_serializing_if = "is_default_circuit_breaker"
+    )]
+    pub circuit_breaker: bool,
+    /// 函数文档（未设置时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub documentation: Option<String>,
+    /// 弃用状态（未弃用时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<Deprecation>,
+    /// 除本字段外所有内容的 MD5
+    #[serde(default)]
+    pub content_hash: String,
+}
+
+/// 多个函数的导出归档
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FunctionArchive {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub functions: Vec<FunctionBundle>,
+}
+
+/// 导入请求体：单个函数包或归档
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ImportPayload {
+    Archive(FunctionArchive),
+    Bundle(Box<FunctionBundle>),
+}
+
+/// 导入时的同名冲突处理策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    /// 跳过已存在的函数
+    Skip,
+    /// 覆盖已存在的函数
+    Overwrite,
+    /// 以新名称导入（`name-2`、`name-3`...）
+    Rename,
+    /// 存在任何冲突时整体拒绝导入
+    #[default]
+    Fail,
+}
+
+/// 单个函数的导入状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Imported,
+    Skipped,
+    Failed,
+}
+
+/// 单个函数的导入结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportResult {
+    pub name: String,
+    pub status: ImportStatus,
+    /// 实际导入使用的名称（重命名时与 `name` 不同）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imported_as: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ImportResult {
+    pub fn imported(name: &str, imported_as: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: ImportStatus::Imported,
+            imported_as: Some(imported_as.to_string()),
+            reason: None,
+        }
+    }
+
+    pub fn skipped(name: &str, reason: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: ImportStatus::Skipped,
+            imported_as: None,
+            reason: Some(reason.into()),
+        }
+    }
+
+    pub fn failed(name: &str, reason: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: ImportStatus::Failed,
+            imported_as: None,
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+fn default_circuit_breaker() -> bool {
+    true
+}
+
+fn is_default_circuit_breaker(enabled: &bool) -> bool {
+    *enabled
+}
+
+impl FunctionBundle {
+    /// 从函数元数据生成导出包
+    pub fn from_metadata(function: &FunctionMetadata) -> Self {
+        let mut bundle = Self {
+            format_version: BUNDLE_FORMAT_VERSION,
+            name: function.name.clone(),
+            description: function.description.clone(),
+            code: function.code.clone(),
+            script_type: BUNDLE_SCRIPT_TYPE.to_string(),
+            timeout_ms: function.timeout_ms,
+            version: function.version.clone(),
+            dependencies: function.dependencies.clone(),
+            parameters: function.parameters.clone(),
+            return_type: function.return_type.clone(),
+            retry_policy: function.retry_policy.clone(),
+            idempotent: function.idempotent,
+            labels: function.labels.clone().into_iter().collect(),
+            priority: function.priority,
+            package: function.package.clone(),
+            http_response: function.http_response,
+            input_template: function.input_template.clone(),
+            input_schema: function.input_schema.clone(),
+            output_schema: function.output_schema.clone(),
+            strict_output_schema: function.strict_output_schema,
+            coerce_output: function.coerce_output,
+            strict_return_type: function.strict_return_type,
+            log_redaction: function.log_redaction.clone(),
+            runtime: function.runtime.clone(),
+            resource_quota: function.resource_quota.clone(),
+            requires_isolation: function.requires_isolation,
+            scratch_limit_mb: function.scratch_limit_mb,
+            persist_scratch: function.persist_scratch,
+            max_disk_write_mb: function.max_disk_write_mb,
+            max_cpu_time_ms: function.max_cpu_time_ms,
+            tests: function.tests.clone(),
+            execution_hint: function.execution_hint,
+            circuit_breaker: function.circuit_breaker,
+            documentation: function.documentation.clone(),
+            deprecation: function.deprecation.clone(),
+            content_hash: String::new(),
+        };
+        bundle.content_hash = bundle.compute_hash();
+        bundle
+    }
+
+    /// 计算内容哈希（忽略 `content_hash` 字段本身）
+    pub fn compute_hash(&self) -> String {
+        let unhashed = Self {
+            content_hash: String::new(),
+            ..self.clone()
+        };
+        let bytes = serde_json::to_vec(&unhashed).unwrap_or_default();
+        format!("{:x}", md5::compute(bytes))
+    }
+
+    /// 校验格式版本、脚本类型和内容哈希
+    pub fn verify(&self) -> Result<()> {
+        if self.format_version != BUNDLE_FORMAT_VERSION {
+            return Err(FluxError::ValidationError {
+                reason: format!(
+                    "Unsupported bundle format version {} (expected {BUNDLE_FORMAT_VERSION})",
+                    self.format_version
+                ),
+            });
+        }
+        if self.script_type != BUNDLE_SCRIPT_TYPE {
+            return Err(FluxError::ValidationError {
+                reason: format!("Unsupported script type: {}", self.script_type),
+            });
+        }
+        let expected = self.compute_hash();
+        if self.content_hash != expected {
+            return Err(FluxError::ValidationError {
+                reason: format!(
+                    "Content hash mismatch (bundle {}, computed {expected})",
+                    self.content_hash
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// 转换为新的函数元数据（生成新的 ID 和时间戳）
+    pub fn into_metadata(self, name: String) -> FunctionMetadata {
+        let mut function = FunctionMetadata::new(name, self.code);
+        function.description = self.description;
+        function.timeout_ms = self.timeout_ms;
+        function.version = self.version;
+        function.dependencies = self.dependencies;
+        function.parameters = self.parameters;
+        function.return_type = self.return_type;
+        function.retry_policy = self.retry_policy;
+        function.idempotent = self.idempotent;
+        function.labels = self.labels.into_iter().collect();
+        function.priority = self.priority;
+        function.package = self.package;
+        function.http_response = self.http_response;
+        function.input_template = self.input_template;
+        function.input_schema = self.input_schema;
+        function.output_schema = self.output_schema;
+        function.strict_output_schema = self.strict_output_schema;
+        function.coerce_output = self.coerce_output;
+        function.strict_return_type = self.strict_return_type;
+        function.log_redaction = self.log_redaction;
+        function.runtime = self.runtime;
+        function.resource_quota = self.resource_quota;
+        function.requires_isolation = self.requires_isolation;
+        function.scratch_limit_mb = self.scratch_limit_mb;
+        function.persist_scratch = self.persist_scratch;
+        function.max_disk_write_mb = self.max_disk_write_mb;
+        function.max_cpu_time_ms = self.max_cpu_time_ms;
+        function.tests = self.tests;
+        function.execution_hint = self.execution_hint;
+        function.circuit_breaker = self.circuit_breaker;
+        function.documentation = self.documentation;
+        function.deprecation = self.deprecation;
+        function
+    }
+}
+
+impl FunctionArchive {
+    /// 生成包含多个函数的归档
+    pub fn from_functions(functions: &[FunctionMetadata]) -> Self {
+        Self {
+            format_version: BUNDLE_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            functions: functions
+                .iter()
+                .map(FunctionBundle::from_metadata)
+                .collect(),
+        }
+    }
+}
+
+impl ImportPayload {
+    /// 展开为待导入的函数包列表，校验归档格式版本
+    pub fn into_bundles(self) -> Result<Vec<FunctionBundle>> {
+        match self {
+            Self::Archive(archive) => {
+                if archive.format_version != BUNDLE_FORMAT_VERSION {
+                    return Err(FluxError::ValidationError {
+                        reason: format!(
+                            "Unsupported archive format version {} (expected {BUNDLE_FORMAT_VERSION})",
+                            archive.format_version
+                        ),
+                    });
+                }
+                Ok(archive.functions)
+            }
+            Self::Bundle(bundle) => Ok(vec![*bundle]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::deprecation::SunsetBehavior;
+
+    #[test]
+    fn test_bundle_hash_detects_tampering() {
+        let function = FunctionMetadata::new("hello".to_string(), "fn main() {}".to_string());
+        let mut bundle = FunctionBundle::from_metadata(&function);
+        assert!(bundle.verify().is_ok());
+
+        bundle.code.push_str("\n// changed");
+        assert!(bundle.verify().unwrap_err().to_string().contains("hash"));
+
+        let mut bundle = FunctionBundle::from_metadata(&function);
+        bundle.format_version = 99;
+        assert!(bundle.verify().unwrap_err().to_string().contains("version"));
+    }
+
+    #[test]
+    fn test_bundle_carries_deprecation() {
+        let mut function = FunctionMetadata::new("old".to_string(), "fn main() {}".to_string());
+        let bundle = FunctionBundle::from_metadata(&function);
+        assert!(
+            !serde_json::to_string(&bundle)
+                .unwrap()
+                .contains("deprecation")
+        );
+
+        let now = Utc::now();
+        function.deprecation = Some(Deprecation {
+            reason: "replaced by new".to_string(),
+            sunset_at: now,
+            replacement: Some("new".to_string()),
+            on_sunset: SunsetBehavior::Redirect,
+            deprecated_at: now,
+        });
+        let bundle = FunctionBundle::from_metadata(&function);
+        assert!(bundle.verify().is_ok());
+        let imported = bundle.into_metadata("old".to_string());
+        assert_eq!(imported.deprecation, function.deprecation);
+    }
+}
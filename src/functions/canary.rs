@@ -0,0 +1,157 @@
+use super::{FluxError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// 标记关键函数的标签：值为 `true` 的函数金丝雀失败时就绪检查失败
+pub const CRITICAL_LABEL: &str = "critical";
+
+/// 金丝雀探测的最小间隔（秒）
+pub const MIN_INTERVAL_SECS: u64 = 1;
+
+/// 函数级金丝雀探测配置
+///
+/// 调度器按间隔以 `input` 调用函数，检查调用成功且（配置时）输出包含 `expected_output`，
+/// 结果维护为函数的健康状态。探测调用以最低优先级执行，不计入调用统计。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CanaryConfig {
+    /// 是否启用（默认 true）
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 探测间隔（秒，默认 60）
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// 探测调用的输入（默认 null）
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub input: Value,
+    /// 期望的输出：对象只比较其中出现的字段（递归），数字按数值比较，其他值要求相等
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub expected_output: Option<Value>,
+    /// 探测调用的超时（毫秒），缺省使用函数超时
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_interval_secs() -> u64 {
+    60
+}
+
+impl CanaryConfig {
+    /// 校验间隔和超时
+    pub fn validate(&self) -> Result<()> {
+        if self.interval_secs < MIN_INTERVAL_SECS {
+            return Err(FluxError::ValidationError {
+                reason: format!("Canary interval_secs must be at least {MIN_INTERVAL_SECS}"),
+            });
+        }
+        if self.timeout_ms == Some(0) {
+            return Err(FluxError::ValidationError {
+                reason: "Canary timeout_ms must be positive".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// 检查一次成功调用的输出，不符合期望时返回原因
+    pub fn check_output(&self, output: &Value) -> std::result::Result<(), String> {
+        match &self.expected_output {
+            Some(expected) if !matches_expected(expected, output) => Err(format!(
+                "Output {} does not match the expected output {}",
+                truncate(output),
+                truncate(expected)
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn matches_expected(expected: &Value, output: &Value) -> bool {
+    match (expected, output) {
+        (Value::Object(expected), Value::Object(output)) => expected.iter().all(|(key, value)| {
+            output
+                .get(key)
+                .is_some_and(|output| matches_expected(value, output))
+        }),
+        // 不同运行时对整数和浮点数的输出不一致（`3` 与 `3.0`），数字按数值比较
+        (Value::Number(expected), Value::Number(output)) => expected.as_f64() == output.as_f64(),
+        _ => expected == output,
+    }
+}
+
+/// 错误信息中的 JSON 最多保留的字符数
+const MAX_VALUE_CHARS: usize = 200;
+
+fn truncate(value: &Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(MAX_VALUE_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_defaults_validation_and_output_check() {
+        let config: CanaryConfig = serde_json::from_str(r#"{"input": {"a": 1}}"#).unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.interval_secs, 60);
+        assert!(config.validate().is_ok());
+        // 未配置期望输出时任何输出都通过
+        assert!(config.check_output(&json!("anything")).is_ok());
+
+        let config = CanaryConfig {
+            expected_output: Some(json!({"result": 2, "tags": ["a"]})),
+            ..config
+        };
+        assert!(
+            config
+                .check_output(&json!({"tags": ["a"], "result": 2}))
+                .is_ok()
+        );
+        // 输出中多出的字段不影响比较，数组要求完全相等
+        assert!(
+            config
+                .check_output(&json!({"input": {}, "result": 2, "tags": ["a"]}))
+                .is_ok()
+        );
+        assert!(
+            config
+                .check_output(&json!({"result": 2, "tags": ["a", "b"]}))
+                .is_err()
+        );
+        assert!(
+            config
+                .check_output(&json!({"result": 2.0, "tags": ["a"]}))
+                .is_ok()
+        );
+        let error = config.check_output(&json!({"result": 3})).unwrap_err();
+        assert!(error.contains(r#"{"result":3}"#), "{error}");
+
+        assert!(
+            CanaryConfig {
+                interval_secs: 0,
+                ..config.clone()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            CanaryConfig {
+                timeout_ms: Some(0),
+                ..config
+            }
+            .validate()
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 函数的后台编译状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum CompilationStatus {
+    /// 已登记，等待编译任务启动
+    Pending,
+    /// 正在编译
+    Compiling,
+    /// 编译完成，调用直接使用编译产物
+    Ready,
+    /// 编译失败（附错误摘要）
+    Failed(String),
+}
+
+impl CompilationStatus {
+    /// 编译是否仍在进行（Pending 或 Compiling）
+    pub fn is_in_progress(&self) -> bool {
+        matches!(self, Self::Pending | Self::Compiling)
+    }
+}
+
+/// 函数的编译记录，只对应当前注册代码的源代码哈希
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CompilationRecord {
+    pub status: CompilationStatus,
+    /// 被编译代码的 MD5
+    pub source_hash: String,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// 生成的入口绑定方式
+    #[serde(default)]
+    pub handler_mode: HandlerMode,
+    /// 编译失败时 cargo 的 stderr
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
+}
+
+impl CompilationRecord {
+    pub fn pending(source_hash: &str, handler_mode: HandlerMode) -> Self {
+        let now = Utc::now();
+        Self {
+            status: CompilationStatus::Pending,
+            source_hash: source_hash.to_string(),
+            started_at: now,
+            updated_at: now,
+            handler_mode,
+            stderr: None,
+        }
+    }
+
+    /// 由编译错误生成失败记录：错误信息中的 cargo stderr 单独保存
+    pub fn failure(error: &str) -> (CompilationStatus, Option<String>) {
+        match error.split_once("STDERR:\n") {
+            Some((_, stderr)) => (
+                CompilationStatus::Failed(
+                    error
+                        .lines()
+                        .next()
+                        .unwrap_or(error)
+                        .trim_end_matches(':')
+                        .to_string(),
+                ),
+                Some(stderr.trim_end().to_string()),
+            ),
+            None => (CompilationStatus::Failed(error.to_string()), None),
+        }
+    }
+
+    /// stderr 中 rustc 报告的错误及其位置（不含 cargo 的汇总行），用于随调用错误返回
+    pub fn rustc_errors(&self) -> Option<String> {
+        let stderr = self.stderr.as_deref()?;
+        let mut errors = Vec::new();
+        let mut lines = stderr.lines().peekable();
+        while let Some(line) = lines.next() {
+            if !line.starts_with("error") || line.starts_with("error: could not compile") {
+                continue;
+            }
+            errors.push(line.to_string());
+            if let Some(location) = lines.next_if(|next| next.trim_start().starts_with("-->")) {
+                errors.push(location.to_string());
+            }
+        }
+        (!errors.is_empty()).then(|| errors.join("\n"))
+    }
+}
+
+/// 函数仍在编译时调用的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OnCompiling {
+    /// 等待编译完成（最长等待函数超时时间）
+    #[default]
+    Wait,
+    /// 立即返回“仍在编译”错误
+    Reject,
+}
+
+/// 编译函数的入口绑定方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandlerMode {
+    /// 未声明参数：生成的入口把原始 JSON 输入交给用户代码
+    #[default]
+    RawJson,
+    /// 按声明的参数和返回类型生成类型化绑定，调用用户的 `handler` 函数
+    Typed,
+}
+
+impl HandlerMode {
+    /// 函数声明了参数时使用类型化绑定
+    pub fn for_function(function: &super::FunctionMetadata) -> Self {
+        if function.parameters.is_empty() {
+            Self::RawJson
+        } else {
+            Self::Typed
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Write};
+
+/// 支持的压缩编码（HTTP `Content-Encoding` / `Accept-Encoding` 中的名称）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Gzip,
+    Zstd,
+}
+
+impl Encoding {
+    /// 按响应压缩的优先顺序排列
+    pub const ALL: [Encoding; 2] = [Encoding::Zstd, Encoding::Gzip];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// 解析编码名（不区分大小写，`x-gzip` 视为 `gzip`）
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// 压缩数据
+    pub fn compress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Self::Zstd => zstd::encode_all(data, 0),
+        }
+    }
+
+    /// 解压数据，解压后超过 `limits` 时立即停止并返回错误，不会把超出部分读入内存
+    pub fn decompress(
+        self,
+        data: &[u8],
+        limits: DecompressionLimits,
+    ) -> Result<Vec<u8>, DecompressError> {
+        let by_ratio = data.len().saturating_mul(limits.max_ratio.max(1));
+        let cap = limits.max_bytes.min(by_ratio);
+
+        let mut output = Vec::new();
+        let read = match self {
+            Self::Gzip => flate2::read::MultiGzDecoder::new(data)
+                .take((cap as u64).saturating_add(1))
+                .read_to_end(&mut output),
+            Self::Zstd => zstd::stream::read::Decoder::new(data).and_then(|decoder| {
+                decoder
+                    .take((cap as u64).saturating_add(1))
+                    .read_to_end(&mut output)
+            }),
+        };
+        read.map_err(|e| DecompressError::Invalid {
+            encoding: self,
+            reason: e.to_string(),
+        })?;
+
+        if output.len() > cap {
+            return Err(if cap == limits.max_bytes {
+                DecompressError::TooLarge {
+                    limit: limits.max_bytes,
+                }
+            } else {
+                DecompressError::RatioExceeded {
+                    max_ratio: limits.max_ratio,
+                }
+            });
+        }
+        Ok(output)
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 解压上限，防止压缩炸弹
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecompressionLimits {
+    /// 解压后的大小上限（字节）
+    pub max_bytes: usize,
+    /// 解压后与压缩前的大小之比上限
+    pub max_ratio: usize,
+}
+
+impl DecompressionLimits {
+    /// 不限制压缩比，只限制解压后的大小
+    pub fn max_bytes(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            max_ratio: usize::MAX,
+        }
+    }
+}
+
+/// 解压失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecompressError {
+    /// 解压后超过大小上限
+    TooLarge { limit: usize },
+    /// 压缩比超过上限
+    RatioExceeded { max_ratio: usize },
+    /// 数据不是有效的压缩流
+    Invalid { encoding: Encoding, reason: String },
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge { limit } => {
+                write!(f, "Decompressed body exceeds limit of {limit} bytes")
+            }
+            Self::RatioExceeded { max_ratio } => {
+                write!(
+                    f,
+                    "Decompressed body exceeds {max_ratio}x the compressed size"
+                )
+            }
+            Self::Invalid { encoding, reason } => {
+                write!(f, "Invalid {encoding} data: {reason}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_bomb_limits() {
+        let data = b"return input.a + input.b;\n".repeat(100);
+        for encoding in Encoding::ALL {
+            let compressed = encoding.compress(&data).unwrap();
+            assert!(compressed.len() < data.len());
+            let limits = DecompressionLimits::max_bytes(data.len());
+            assert_eq!(encoding.decompress(&compressed, limits).unwrap(), data);
+
+            assert_eq!(
+                encoding.decompress(&compressed, DecompressionLimits::max_bytes(100)),
+                Err(DecompressError::TooLarge { limit: 100 })
+            );
+            let limits = DecompressionLimits {
+                max_bytes: usize::MAX,
+                max_ratio: 2,
+            };
+            assert_eq!(
+                encoding.decompress(&compressed, limits),
+                Err(DecompressError::RatioExceeded { max_ratio: 2 })
+            );
+            assert!(matches!(
+                encoding.decompress(b"not compressed", DecompressionLimits::max_bytes(1024)),
+                Err(DecompressError::Invalid { .. })
+            ));
+        }
+        assert_eq!(Encoding::parse(" X-GZIP"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::parse("br"), None);
+    }
+}
@@ -0,0 +1,337 @@
+use super::FunctionMetadata;
+use crate::runtime::affinity::AffinityRoute;
+use crate::runtime::cpu::CpuReport;
+use crate::runtime::debug_capture::DebugTrace;
+use crate::runtime::disk::DiskReport;
+use crate::runtime::egress::EgressHandle;
+use crate::runtime::state::StateHandle;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// 调用方信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CallerInfo {
+    /// 调用方使用的 API 密钥 ID（网关启用认证时提供）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_id: Option<String>,
+    /// 调用方 IP
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+}
+
+/// 随输入一起传给函数的调用上下文
+///
+/// `deadline_ms_remaining` 在函数即将开始执行时按截止时间计算（见 [`Self::snapshot`]），
+/// 排队和冷启动编译消耗的时间都已扣除。
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct InvocationContext {
+    pub invocation_id: String,
+    pub function_name: String,
+    pub version: String,
+    /// 函数开始执行时剩余的时间（毫秒）
+    pub deadline_ms_remaining: u64,
+    /// 第几次尝试（从 1 开始）
+    pub attempt: u32,
+    pub caller: CallerInfo,
+    /// 访问函数状态的地址和本次调用的令牌（未启用状态存储时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub state: Option<StateHandle>,
+    /// 出站 HTTP 代理的地址和本次调用的令牌（`http_fetch` 经它发送请求）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub egress: Option<EgressHandle>,
+    /// 本次调用可写的暂存目录（也通过环境变量 `FLUX_SCRATCH_DIR` 提供），调用结束后删除
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scratch_dir: Option<String>,
+    #[serde(skip)]
+    #[schema(ignore)]
+    deadline: Instant,
+    /// 本次尝试是否注入了故障（不传给函数，用于标记监控记录）
+    #[serde(skip)]
+    #[schema(ignore)]
+    chaos_injected: bool,
+    /// 是否为影子重放（不传给函数，监控只计入重放次数）
+    #[serde(skip)]
+    #[schema(ignore)]
+    shadow: bool,
+    /// 是否为请求镜像的调用（不传给函数，监控只计入镜像次数）
+    #[serde(skip)]
+    #[schema(ignore)]
+    mirror: bool,
+    /// 是否为金丝雀探测的调用（不传给函数，监控只计入探测次数）
+    #[serde(skip)]
+    #[schema(ignore)]
+    canary: bool,
+    /// 是否为函数测试用例的调用（不传给函数，监控只计入测试次数）
+    #[serde(skip)]
+    #[schema(ignore)]
+    test: bool,
+    /// 是否为控制台执行（不传给函数，使用最严格的沙箱配置，不计入任何函数的统计）
+    #[serde(skip)]
+    #[schema(ignore)]
+    console: bool,
+    /// 调试包的收集句柄（不传给函数，函数未启用调试包时为空）
+    #[serde(skip)]
+    #[schema(ignore)]
+    debug: Option<DebugTrace>,
+    /// 亲和键和执行实例的记录句柄（不传给函数，请求没有亲和键时为空）
+    #[serde(skip)]
+    #[schema(ignore)]
+    affinity: Option<AffinityRoute>,
+    /// 沙箱进程磁盘用量的记录句柄（不传给函数，调用方不需要时为空）
+    #[serde(skip)]
+    #[schema(ignore)]
+    disk: Option<DiskReport>,
+    /// 沙箱进程 CPU 时间的记录句柄（不传给函数，调用方不需要时为空）
+    #[serde(skip)]
+    #[schema(ignore)]
+    cpu: Option<CpuReport>,
+}
+
+impl InvocationContext {
+    /// 新调用的上下文，截止时间为现在加上函数超时
+    pub fn new(function: &FunctionMetadata, invocation_id: impl Into<String>) -> Self {
+        Self {
+            invocation_id: invocation_id.into(),
+            function_name: function.name.clone(),
+            version: function.version.clone(),
+            deadline_ms_remaining: function.timeout_ms,
+            attempt: 1,
+            caller: CallerInfo::default(),
+            state: None,
+            egress: None,
+            scratch_dir: None,
+            deadline: Instant::now() + Duration::from_millis(function.timeout_ms),
+            chaos_injected: false,
+            shadow: false,
+            mirror: false,
+            canary: false,
+            test: false,
+            console: false,
+            debug: None,
+            affinity: None,
+            disk: None,
+            cpu: None,
+        }
+    }
+
+    pub fn with_caller(mut self, caller: CallerInfo) -> Self {
+        self.caller = caller;
+        self
+    }
+
+    pub fn with_state(mut self, state: StateHandle) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn with_egress(mut self, egress: EgressHandle) -> Self {
+        self.egress = Some(egress);
+        self
+    }
+
+    pub fn with_scratch_dir(mut self, scratch_dir: &Path) -> Self {
+        self.scratch_dir = Some(scratch_dir.to_string_lossy().to_string());
+        self
+    }
+
+    pub fn with_attempt(mut self, attempt: u32) -> Self {
+        self.attempt = attempt;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn with_chaos_injected(mut self, chaos_injected: bool) -> Self {
+        self.chaos_injected = chaos_injected;
+        self
+    }
+
+    pub fn with_shadow(mut self, shadow: bool) -> Self {
+        self.shadow = shadow;
+        self
+    }
+
+    pub fn with_mirror(mut self, mirror: bool) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    pub fn with_canary(mut self, canary: bool) -> Self {
+        self.canary = canary;
+        self
+    }
+
+    pub fn with_test(mut self, test: bool) -> Self {
+        self.test = test;
+        self
+    }
+
+    pub fn with_console(mut self, console: bool) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn with_debug_trace(mut self, debug: Option<DebugTrace>) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    pub fn with_affinity(mut self, affinity: Option<AffinityRoute>) -> Self {
+        self.affinity = affinity;
+        self
+    }
+
+    pub fn with_disk_report(mut self, disk: Option<DiskReport>) -> Self {
+        self.disk = disk;
+        self
+    }
+
+    pub fn with_cpu_report(mut self, cpu: Option<CpuReport>) -> Self {
+        self.cpu = cpu;
+        self
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn chaos_injected(&self) -> bool {
+        self.chaos_injected
+    }
+
+    pub fn shadow(&self) -> bool {
+        self.shadow
+    }
+
+    pub fn mirror(&self) -> bool {
+        self.mirror
+    }
+
+    pub fn canary(&self) -> bool {
+        self.canary
+    }
+
+    pub fn test(&self) -> bool {
+        self.test
+    }
+
+    pub fn console(&self) -> bool {
+        self.console
+    }
+
+    pub fn debug_trace(&self) -> Option<&DebugTrace> {
+        self.debug.as_ref()
+    }
+
+    pub fn affinity(&self) -> Option<&AffinityRoute> {
+        self.affinity.as_ref()
+    }
+
+    pub fn disk_report(&self) -> Option<&DiskReport> {
+        self.disk.as_ref()
+    }
+
+    pub fn cpu_report(&self) -> Option<&CpuReport> {
+        self.cpu.as_ref()
+    }
+
+    /// 当前时刻的上下文：按截止时间重新计算剩余时间
+    pub fn snapshot(&self) -> Self {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        Self {
+            deadline_ms_remaining: remaining.as_millis().min(u64::MAX as u128) as u64,
+            ..self.clone()
+        }
+    }
+
+    /// 传给函数的 JSON（剩余时间为当前时刻的值）
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self.snapshot()).unwrap_or_default()
+    }
+}
+
+/// 函数详情中说明调用上下文如何传入函数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ContextContract {
+    /// 本函数获取上下文的方式
+    pub delivery: String,
+    /// 上下文中的字段
+    pub fields: Vec<String>,
+}
+
+impl ContextContract {
+    /// 按脚本类型（`javascript`、`typescript`、`python`、`rust`、`expression`）说明传递方式
+    pub fn for_script_type(script_type: &str) -> Self {
+        let delivery = match script_type {
+            "javascript" | "typescript" => {
+                "Second argument of handler(input, context); also available as the global `context`"
+            }
+            "python" => {
+                "Passed as handler(input, context) when the handler accepts two arguments; also available as the module-level `context`"
+            }
+            "rust" => {
+                "JSON string passed as the second argument of flux_execute_v3(input, context, host) or flux_execute_v2(input, context); artifacts exporting only flux_execute receive no context"
+            }
+            _ => "Not available to expression functions",
+        };
+        Self {
+            delivery: delivery.to_string(),
+            fields: [
+                "invocation_id",
+                "function_name",
+                "version",
+                "deadline_ms_remaining",
+                "attempt",
+                "caller.api_key_id",
+                "caller.ip",
+                "state.url",
+                "state.token",
+                "egress.url",
+                "egress.token",
+                "scratch_dir",
+            ]
+            .iter()
+            .map(|field| field.to_string())
+            .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_counts_down_to_deadline() {
+        let mut function = FunctionMetadata::new("ctx".to_string(), "return input".to_string());
+        function.timeout_ms = 1000;
+        let context = InvocationContext::new(&function, "inv-1")
+            .with_attempt(2)
+            .with_caller(CallerInfo {
+                api_key_id: None,
+                ip: Some("10.0.0.1".to_string()),
+            });
+
+        // 已消耗 400ms（例如排队和编译）时剩余不超过 600ms
+        let context = context.with_deadline(Instant::now() + Duration::from_millis(600));
+        let json = context.to_json();
+        assert_eq!(json["invocation_id"], "inv-1");
+        assert_eq!(json["function_name"], "ctx");
+        assert_eq!(json["version"], function.version);
+        assert_eq!(json["attempt"], 2);
+        assert_eq!(json["caller"], serde_json::json!({"ip": "10.0.0.1"}));
+        let remaining = json["deadline_ms_remaining"].as_u64().unwrap();
+        assert!(remaining <= 600 && remaining > 0, "{remaining}");
+
+        // 截止时间已过时为 0
+        let expired = context.with_deadline(Instant::now() - Duration::from_millis(1));
+        assert_eq!(expired.snapshot().deadline_ms_remaining, 0);
+    }
+}
@@ -0,0 +1,172 @@
+use super::{FluxError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 过了停用时间后调用的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SunsetBehavior {
+    /// 照常执行，只在响应中提示
+    #[default]
+    Warn,
+    /// 拒绝调用（410），错误中带替代函数
+    Reject,
+    /// 改为调用替代函数，响应中标记 `redirected_from`
+    Redirect,
+}
+
+/// 函数的弃用状态（通过 `POST /functions/:name/deprecate` 设置，重新注册时保留）
+///
+/// 弃用的函数仍可调用，响应带 `Deprecation`、`Sunset` 头和 `meta.deprecated`；
+/// 过了 `sunset_at` 后按 `on_sunset` 处理。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Deprecation {
+    pub reason: String,
+    /// 停用时间
+    pub sunset_at: DateTime<Utc>,
+    /// 替代函数名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    #[serde(default)]
+    pub on_sunset: SunsetBehavior,
+    /// 标记弃用的时间
+    pub deprecated_at: DateTime<Utc>,
+}
+
+/// 弃用函数的请求
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeprecateRequest {
+    /// 弃用原因，随响应返回给调用方
+    pub reason: String,
+    /// 停用时间（RFC 3339）
+    pub sunset_at: DateTime<Utc>,
+    /// 替代函数名，`on_sunset` 为 `redirect` 时必填
+    #[serde(default)]
+    pub replacement: Option<String>,
+    /// 过了停用时间后的处理方式，默认 `warn`
+    #[serde(default)]
+    pub on_sunset: SunsetBehavior,
+}
+
+/// 响应 `meta.deprecated` 中的弃用说明
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeprecationNotice {
+    pub reason: String,
+    pub sunset_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+impl DeprecateRequest {
+    /// 校验后生成弃用状态，`name` 为被弃用的函数
+    pub fn into_deprecation(self, name: &str, now: DateTime<Utc>) -> Result<Deprecation> {
+        let invalid = |reason: &str| {
+            Err(FluxError::ValidationError {
+                reason: format!("deprecation: {reason}"),
+            })
+        };
+        if self.reason.trim().is_empty() {
+            return invalid("reason must not be empty");
+        }
+        let replacement = self.replacement.filter(|name| !name.is_empty());
+        if replacement.as_deref() == Some(name) {
+            return invalid("a function cannot replace itself");
+        }
+        if self.on_sunset == SunsetBehavior::Redirect && replacement.is_none() {
+            return invalid("on_sunset = redirect requires a replacement");
+        }
+        Ok(Deprecation {
+            reason: self.reason,
+            sunset_at: self.sunset_at,
+            replacement,
+            on_sunset: self.on_sunset,
+            deprecated_at: now,
+        })
+    }
+}
+
+impl Deprecation {
+    pub fn is_sunset(&self, now: DateTime<Utc>) -> bool {
+        now >= self.sunset_at
+    }
+
+    /// 当前时间下需要改为调用的替代函数（已停用且 `on_sunset` 为 `redirect`）
+    pub fn redirect_target(&self, now: DateTime<Utc>) -> Option<&str> {
+        (self.on_sunset == SunsetBehavior::Redirect && self.is_sunset(now))
+            .then_some(self.replacement.as_deref())
+            .flatten()
+    }
+
+    /// 当前时间下是否拒绝调用（已停用且 `on_sunset` 为 `reject`）
+    pub fn rejects(&self, now: DateTime<Utc>) -> bool {
+        self.on_sunset == SunsetBehavior::Reject && self.is_sunset(now)
+    }
+
+    pub fn notice(&self) -> DeprecationNotice {
+        DeprecationNotice {
+            reason: self.reason.clone(),
+            sunset_at: self.sunset_at,
+            replacement: self.replacement.clone(),
+        }
+    }
+}
+
+/// 错误信息中的替代函数提示
+pub fn replacement_hint(replacement: &Option<String>) -> String {
+    replacement
+        .as_ref()
+        .map(|name| format!("; use {name} instead"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_request_validation_and_sunset_behavior() {
+        let now = Utc::now();
+        let request = |on_sunset, replacement: Option<&str>| DeprecateRequest {
+            reason: "use v2".to_string(),
+            sunset_at: now + Duration::days(1),
+            replacement: replacement.map(str::to_string),
+            on_sunset,
+        };
+        assert!(
+            request(SunsetBehavior::Redirect, None)
+                .into_deprecation("old", now)
+                .is_err()
+        );
+        assert!(
+            request(SunsetBehavior::Warn, Some("old"))
+                .into_deprecation("old", now)
+                .is_err()
+        );
+        let blank = DeprecateRequest {
+            reason: " ".to_string(),
+            ..request(SunsetBehavior::Warn, None)
+        };
+        assert!(blank.into_deprecation("old", now).is_err());
+
+        let deprecation = request(SunsetBehavior::Redirect, Some("new"))
+            .into_deprecation("old", now)
+            .unwrap();
+        assert_eq!(deprecation.deprecated_at, now);
+        assert!(!deprecation.is_sunset(now));
+        assert_eq!(deprecation.redirect_target(now), None);
+        let later = now + Duration::days(2);
+        assert_eq!(deprecation.redirect_target(later), Some("new"));
+        assert!(!deprecation.rejects(later));
+
+        let reject = Deprecation {
+            on_sunset: SunsetBehavior::Reject,
+            ..deprecation.clone()
+        };
+        assert!(!reject.rejects(now));
+        assert!(reject.rejects(later));
+        assert_eq!(reject.redirect_target(later), None);
+        assert_eq!(replacement_hint(&reject.replacement), "; use new instead");
+    }
+}
@@ -0,0 +1,509 @@
+//! 两个函数（或同一函数的两个版本）的结构化比较
+//!
+//! 元数据按字段比较，参数、依赖和标签按条目列出增删改，代码用 [`similar`] 生成统一格式的
+//! 文本差异。超过 [`DiffOptions::max_bytes`] 的代码只比较开头的完整行，并标记为已截断。
+
+use super::versions::UNTRACKED_FIELDS;
+use super::{FunctionMetadata, FunctionParameter};
+use crate::scheduler::namespaces::script_type;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use similar::{ChangeTag, TextDiff};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// 统一差异默认的上下文行数
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// 每个文件每一侧参与比较的字节上限
+pub const MAX_DIFF_BYTES: usize = 256 * 1024;
+
+/// 单个文件的行差异计算时长上限，超时后退化为较粗的差异
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 单文件函数的代码在差异中的路径
+pub const CODE_PATH: &str = "code";
+
+/// 单独比较、不出现在 `fields` 中的字段
+const STRUCTURED_FIELDS: &[&str] = &[
+    "name",
+    "code",
+    "package",
+    "parameters",
+    "dependencies",
+    "labels",
+];
+
+/// 比较选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// 统一差异中每处变化前后保留的上下文行数
+    pub context_lines: usize,
+    /// 每个文件每一侧参与比较的字节上限
+    pub max_bytes: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            context_lines: DEFAULT_CONTEXT_LINES,
+            max_bytes: MAX_DIFF_BYTES,
+        }
+    }
+}
+
+/// 比较的一侧
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DiffTarget {
+    pub name: String,
+    pub version: String,
+    pub revision: u64,
+    pub script_type: String,
+}
+
+impl DiffTarget {
+    pub fn of(function: &FunctionMetadata) -> Self {
+        Self {
+            name: function.name.clone(),
+            version: function.version.clone(),
+            revision: function.revision,
+            script_type: script_type(function).to_string(),
+        }
+    }
+
+    /// 差异标题中的标识，如 `report:1.4.0`
+    fn label(&self) -> String {
+        format!("{}:{}", self.name, self.version)
+    }
+}
+
+/// 变化的元数据字段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FieldChange {
+    pub field: String,
+    /// 旧值（字段不存在时为 null）
+    #[schema(value_type = Object)]
+    pub from: Value,
+    /// 新值（字段不存在时为 null）
+    #[schema(value_type = Object)]
+    pub to: Value,
+}
+
+/// 同名参数的定义变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ParameterChange {
+    pub name: String,
+    pub from: FunctionParameter,
+    pub to: FunctionParameter,
+}
+
+/// 按参数名比较的参数变化
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ParameterChanges {
+    pub added: Vec<FunctionParameter>,
+    pub removed: Vec<FunctionParameter>,
+    pub changed: Vec<ParameterChange>,
+}
+
+impl ParameterChanges {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// 依赖的增删
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DependencyChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// 标签的变化：`from` 为空表示新增，`to` 为空表示删除
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LabelChange {
+    pub key: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// 一个文件的代码差异
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CodeDiff {
+    /// 文件路径（单文件函数为 `code`）
+    pub path: String,
+    /// 统一格式的文本差异
+    pub unified: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// 至少一侧超出字节上限，只比较了开头部分
+    pub truncated: bool,
+}
+
+/// 差异统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DiffSummary {
+    /// 两侧定义完全相同（不计修订号、时间戳和版本号）
+    pub identical: bool,
+    pub fields_changed: usize,
+    pub files_changed: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// 有文件因超出字节上限而只比较了开头部分
+    pub truncated: bool,
+}
+
+/// 两个函数定义的结构化比较
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FunctionDiff {
+    pub from: DiffTarget,
+    pub to: DiffTarget,
+    /// 变化的元数据字段（按字段名排序，参数、依赖、标签和代码单独列出）
+    pub fields: Vec<FieldChange>,
+    pub parameters: ParameterChanges,
+    pub dependencies: DependencyChanges,
+    pub labels: Vec<LabelChange>,
+    /// 变化的文件（按路径排序）
+    pub code: Vec<CodeDiff>,
+    pub summary: DiffSummary,
+}
+
+impl FunctionDiff {
+    /// 比较 `from` 到 `to` 的变化
+    pub fn between(from: &FunctionMetadata, to: &FunctionMetadata, options: DiffOptions) -> Self {
+        let (from_target, to_target) = (DiffTarget::of(from), DiffTarget::of(to));
+        let fields = field_changes(from, to);
+        let parameters = parameter_changes(&from.parameters, &to.parameters);
+        let dependencies = DependencyChanges {
+            added: difference(&to.dependencies, &from.dependencies),
+            removed: difference(&from.dependencies, &to.dependencies),
+        };
+        let labels = label_changes(from, to);
+
+        let (old_files, new_files) = (source_files(from), source_files(to));
+        let paths: BTreeSet<&str> = old_files.keys().chain(new_files.keys()).copied().collect();
+        let code: Vec<CodeDiff> = paths
+            .into_iter()
+            .filter_map(|path| {
+                let old = old_files.get(path).copied().unwrap_or_default();
+                let new = new_files.get(path).copied().unwrap_or_default();
+                (old != new).then(|| {
+                    let headers = [&from_target, &to_target].map(|target| {
+                        if path == CODE_PATH {
+                            target.label()
+                        } else {
+                            format!("{}/{path}", target.label())
+                        }
+                    });
+                    code_diff(path, old, new, &headers, options)
+                })
+            })
+            .collect();
+
+        let summary = DiffSummary {
+            identical: fields.is_empty()
+                && parameters.is_empty()
+                && dependencies.added.is_empty()
+                && dependencies.removed.is_empty()
+                && labels.is_empty()
+                && code.is_empty(),
+            fields_changed: fields.len(),
+            files_changed: code.len(),
+            lines_added: code.iter().map(|file| file.lines_added).sum(),
+            lines_removed: code.iter().map(|file| file.lines_removed).sum(),
+            truncated: code.iter().any(|file| file.truncated),
+        };
+        Self {
+            from: from_target,
+            to: to_target,
+            fields,
+            parameters,
+            dependencies,
+            labels,
+            code,
+            summary,
+        }
+    }
+}
+
+/// 两个版本间所有文件新增和删除的行数
+pub fn line_stats(from: &FunctionMetadata, to: &FunctionMetadata) -> (usize, usize) {
+    let (old_files, new_files) = (source_files(from), source_files(to));
+    let paths: BTreeSet<&str> = old_files.keys().chain(new_files.keys()).copied().collect();
+    paths
+        .into_iter()
+        .map(|path| {
+            let old = old_files.get(path).copied().unwrap_or_default();
+            let new = new_files.get(path).copied().unwrap_or_default();
+            if old == new {
+                return (0, 0);
+            }
+            let (old, _) = capped(old, MAX_DIFF_BYTES);
+            let (new, _) = capped(new, MAX_DIFF_BYTES);
+            count_lines(&line_diff(old, new))
+        })
+        .fold((0, 0), |(added, removed), (a, r)| (added + a, removed + r))
+}
+
+/// 函数的源文件：函数包为包内各文件，否则为 `code`
+fn source_files(function: &FunctionMetadata) -> BTreeMap<&str, &str> {
+    match &function.package {
+        Some(package) => package
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.content.as_str()))
+            .collect(),
+        None => BTreeMap::from([(CODE_PATH, function.code.as_str())]),
+    }
+}
+
+fn field_changes(from: &FunctionMetadata, to: &FunctionMetadata) -> Vec<FieldChange> {
+    let fields = |function: &FunctionMetadata| match serde_json::to_value(function) {
+        Ok(Value::Object(map)) => map,
+        _ => Default::default(),
+    };
+    let (old, new) = (fields(from), fields(to));
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|key| {
+            !UNTRACKED_FIELDS.contains(&key.as_str()) && !STRUCTURED_FIELDS.contains(&key.as_str())
+        })
+        .filter(|key| old.get(*key) != new.get(*key))
+        .map(|key| FieldChange {
+            field: key.clone(),
+            from: old.get(key).cloned().unwrap_or(Value::Null),
+            to: new.get(key).cloned().unwrap_or(Value::Null),
+        })
+        .collect()
+}
+
+fn parameter_changes(from: &[FunctionParameter], to: &[FunctionParameter]) -> ParameterChanges {
+    let find = |params: &[FunctionParameter], name: &str| {
+        params.iter().find(|param| param.name == name).cloned()
+    };
+    ParameterChanges {
+        added: to
+            .iter()
+            .filter(|param| find(from, &param.name).is_none())
+            .cloned()
+            .collect(),
+        removed: from
+            .iter()
+            .filter(|param| find(to, &param.name).is_none())
+            .cloned()
+            .collect(),
+        changed: from
+            .iter()
+            .filter_map(|old| {
+                let new = find(to, &old.name)?;
+                (new != *old).then(|| ParameterChange {
+                    name: old.name.clone(),
+                    from: old.clone(),
+                    to: new,
+                })
+            })
+            .collect(),
+    }
+}
+
+/// `a` 中有而 `b` 中没有的条目（保持 `a` 的顺序）
+fn difference(a: &[String], b: &[String]) -> Vec<String> {
+    a.iter().filter(|item| !b.contains(item)).cloned().collect()
+}
+
+fn label_changes(from: &FunctionMetadata, to: &FunctionMetadata) -> Vec<LabelChange> {
+    let keys: BTreeSet<&String> = from.labels.keys().chain(to.labels.keys()).collect();
+    keys.into_iter()
+        .filter_map(|key| {
+            let (old, new) = (from.labels.get(key), to.labels.get(key));
+            (old != new).then(|| LabelChange {
+                key: key.clone(),
+                from: old.cloned(),
+                to: new.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// 超出字节上限时截取开头的完整行
+fn capped(text: &str, max_bytes: usize) -> (&str, bool) {
+    if text.len() <= max_bytes {
+        return (text, false);
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = text[..end].rfind('\n').map_or(end, |newline| newline + 1);
+    (&text[..end], true)
+}
+
+fn line_diff<'a>(old: &'a str, new: &'a str) -> TextDiff<'a, 'a, 'a, str> {
+    TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(old, new)
+}
+
+fn count_lines<'a>(diff: &TextDiff<'a, 'a, 'a, str>) -> (usize, usize) {
+    diff.iter_all_changes()
+        .fold((0, 0), |(added, removed), change| match change.tag() {
+            ChangeTag::Insert => (added + 1, removed),
+            ChangeTag::Delete => (added, removed + 1),
+            ChangeTag::Equal => (added, removed),
+        })
+}
+
+fn code_diff(
+    path: &str,
+    old: &str,
+    new: &str,
+    headers: &[String; 2],
+    options: DiffOptions,
+) -> CodeDiff {
+    let (old, old_truncated) = capped(old, options.max_bytes);
+    let (new, new_truncated) = capped(new, options.max_bytes);
+    let diff = line_diff(old, new);
+    let (lines_added, lines_removed) = count_lines(&diff);
+    let unified = diff
+        .unified_diff()
+        .context_radius(options.context_lines)
+        .header(&headers[0], &headers[1])
+        .to_string();
+    CodeDiff {
+        path: path.to_string(),
+        unified,
+        lines_added,
+        lines_removed,
+        truncated: old_truncated || new_truncated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::package::{FunctionPackage, PackageFile};
+
+    fn param(name: &str, param_type: &str) -> FunctionParameter {
+        FunctionParameter {
+            name: name.to_string(),
+            param_type: param_type.to_string(),
+            description: None,
+            required: true,
+            default_value: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_metadata_and_code() {
+        let mut old = FunctionMetadata::new(
+            "report".to_string(),
+            "let a = 1;\nlet b = 2;\nlet c = 3;\nreturn a + b + c;\n".to_string(),
+        );
+        old.version = "1.4.0".to_string();
+        old.parameters = vec![param("from", "string"), param("limit", "integer")];
+        old.dependencies = vec!["serde".to_string()];
+        old.labels.insert("team".to_string(), "billing".to_string());
+        old.labels.insert("tier".to_string(), "gold".to_string());
+
+        let mut new = old.clone();
+        new.version = "1.5.0".to_string();
+        new.revision = 7;
+        new.timeout_ms = 10_000;
+        new.code = "let a = 1;\nlet b = 20;\nlet c = 3;\nreturn a + b + c;\n".to_string();
+        new.parameters = vec![param("from", "number"), param("format", "string")];
+        new.dependencies = vec!["serde".to_string(), "chrono".to_string()];
+        new.labels.remove("tier");
+        new.labels.insert("team".to_string(), "finance".to_string());
+
+        let diff = FunctionDiff::between(&old, &new, DiffOptions::default());
+        assert_eq!(diff.from.version, "1.4.0");
+        assert_eq!(diff.to.revision, 7);
+        let fields: Vec<&str> = diff.fields.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(fields, vec!["timeout_ms"]);
+        assert_eq!(diff.fields[0].to, serde_json::json!(10_000));
+
+        assert_eq!(diff.parameters.added, vec![param("format", "string")]);
+        assert_eq!(diff.parameters.removed, vec![param("limit", "integer")]);
+        assert_eq!(diff.parameters.changed[0].to.param_type, "number");
+        assert_eq!(diff.dependencies.added, vec!["chrono"]);
+        assert!(diff.dependencies.removed.is_empty());
+        assert_eq!(
+            diff.labels,
+            vec![
+                LabelChange {
+                    key: "team".to_string(),
+                    from: Some("billing".to_string()),
+                    to: Some("finance".to_string()),
+                },
+                LabelChange {
+                    key: "tier".to_string(),
+                    from: Some("gold".to_string()),
+                    to: None,
+                },
+            ]
+        );
+
+        let code = &diff.code[0];
+        assert_eq!(code.path, CODE_PATH);
+        assert_eq!((code.lines_added, code.lines_removed), (1, 1));
+        assert!(
+            code.unified
+                .starts_with("--- report:1.4.0\n+++ report:1.5.0\n")
+        );
+        assert!(code.unified.contains("-let b = 2;\n+let b = 20;\n"));
+        assert!(!diff.summary.identical);
+        assert_eq!(diff.summary.files_changed, 1);
+
+        // 上下文行数可配置
+        let narrow = FunctionDiff::between(
+            &old,
+            &new,
+            DiffOptions {
+                context_lines: 0,
+                ..Default::default()
+            },
+        );
+        assert!(!narrow.code[0].unified.contains(" let a = 1;"));
+
+        let same = FunctionDiff::between(&old, &old, DiffOptions::default());
+        assert!(same.summary.identical && same.code.is_empty());
+    }
+
+    #[test]
+    fn test_diff_truncates_large_bodies_and_compares_package_files() {
+        let line = "x".repeat(99) + "\n";
+        let old = FunctionMetadata::new("big".to_string(), line.repeat(100));
+        let new = FunctionMetadata::new("big".to_string(), line.repeat(100) + "tail\n");
+        let diff = FunctionDiff::between(
+            &old,
+            &new,
+            DiffOptions {
+                max_bytes: 1000,
+                ..Default::default()
+            },
+        );
+        // 两侧都只比较前 1000 字节，截断后相同
+        assert!(diff.code[0].truncated && diff.summary.truncated);
+        assert_eq!(diff.code[0].lines_added, 0);
+
+        let package = |files: &[(&str, &str)]| FunctionPackage {
+            entrypoint: "main.py".to_string(),
+            files: files
+                .iter()
+                .map(|(path, content)| PackageFile {
+                    path: path.to_string(),
+                    content: content.to_string(),
+                })
+                .collect(),
+        };
+        let mut old = FunctionMetadata::new("pkg".to_string(), String::new());
+        old.package = Some(package(&[("main.py", "a\n"), ("util.py", "u\n")]));
+        let mut new = old.clone();
+        new.package = Some(package(&[("main.py", "a\n"), ("lib.py", "l\n")]));
+        let diff = FunctionDiff::between(&old, &new, DiffOptions::default());
+        let paths: Vec<&str> = diff.code.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["lib.py", "util.py"]);
+        assert!(diff.code[0].unified.starts_with("--- pkg:1.0.0/lib.py\n"));
+        assert_eq!(line_stats(&old, &new), (1, 1));
+    }
+}
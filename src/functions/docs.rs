@@ -0,0 +1,86 @@
+use super::{FluxError, Result};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd, html};
+
+/// 函数文档（Markdown）的字节上限
+pub const MAX_DOCUMENTATION_BYTES: usize = 64 * 1024;
+
+/// 函数列表中文档摘要的字符上限
+pub const EXCERPT_CHARS: usize = 200;
+
+/// 校验文档大小
+pub fn validate(documentation: &str) -> Result<()> {
+    if documentation.len() > MAX_DOCUMENTATION_BYTES {
+        return Err(FluxError::ValidationError {
+            reason: format!(
+                "Documentation is {} bytes, exceeding the limit of {MAX_DOCUMENTATION_BYTES} bytes",
+                documentation.len()
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// 渲染为 HTML（CommonMark），并清理脚本、事件属性等不安全内容
+pub fn render_html(documentation: &str) -> String {
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, Parser::new(documentation));
+    ammonia::clean(&rendered)
+}
+
+/// 文档摘要：第一个段落的纯文本，超出 [`EXCERPT_CHARS`] 时截断并加省略号
+///
+/// 标题不计为段落；没有段落时为 `None`。
+pub fn excerpt(documentation: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut in_paragraph = false;
+    for event in Parser::new(documentation) {
+        match event {
+            Event::Start(Tag::Paragraph) => in_paragraph = true,
+            Event::End(TagEnd::Paragraph) if !text.trim().is_empty() => break,
+            Event::End(TagEnd::Paragraph) => in_paragraph = false,
+            Event::Text(fragment) | Event::Code(fragment) if in_paragraph => {
+                text.push_str(&fragment)
+            }
+            Event::SoftBreak | Event::HardBreak if in_paragraph => text.push(' '),
+            _ => {}
+        }
+    }
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if text.chars().count() <= EXCERPT_CHARS {
+        return Some(text.to_string());
+    }
+    let truncated: String = text.chars().take(EXCERPT_CHARS).collect();
+    Some(format!("{}…", truncated.trim_end()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excerpt_is_first_paragraph() {
+        let doc = "# greet\n\nReturns a `greeting`\nfor the *given* name.\n\nSecond paragraph.";
+        assert_eq!(
+            excerpt(doc).as_deref(),
+            Some("Returns a greeting for the given name.")
+        );
+        assert_eq!(excerpt("# Only a title"), None);
+
+        let long = "word ".repeat(100);
+        let excerpt = excerpt(&long).unwrap();
+        assert!(excerpt.ends_with('…'));
+        assert!(excerpt.chars().count() <= EXCERPT_CHARS + 1);
+    }
+
+    #[test]
+    fn test_render_html_is_sanitized() {
+        let html = render_html("# Title\n\n<script>alert(1)</script>\n\n[x](javascript:alert(1))");
+        assert!(html.contains("<h1>Title</h1>"), "{html}");
+        assert!(!html.contains("<script"), "{html}");
+        assert!(!html.contains("javascript:"), "{html}");
+        assert!(validate(&"x".repeat(MAX_DOCUMENTATION_BYTES + 1)).is_err());
+    }
+}
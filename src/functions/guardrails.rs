@@ -0,0 +1,213 @@
+//! 注册表变更护栏：函数总数上限和变更速率限制
+//!
+//! 护栏在 [`FunctionRegistry`](super::registry::FunctionRegistry) 内执行，HTTP 注册、导入、
+//! 目录加载和 Git 同步都受同样的限制。变更速率按令牌桶计算，与调用的准入控制相互独立：
+//! 每次注册、更新、删除或一次批量提交（导入、目录加载）消耗一个令牌。
+
+use super::{FluxError, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex as StdMutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// 函数数达到上限的该比例时记录一次警告
+pub const WARNING_RATIO: f64 = 0.9;
+
+/// 注册表护栏配置（`[profiles.<name>.guardrails]`），缺省不做限制
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct RegistryGuardrailsConfig {
+    /// 注册函数的总数上限
+    pub max_functions: Option<usize>,
+    /// 每分钟允许的变更数（注册、更新、删除、导入和加载）
+    pub mutations_per_minute: Option<u32>,
+    /// 允许的突发变更数（令牌桶容量，缺省等于 `mutations_per_minute`）
+    pub mutation_burst: Option<u32>,
+}
+
+/// 护栏的当前计数和限制，出现在 `GET /status` 中
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RegistryGuardrailsStatus {
+    pub functions: usize,
+    pub max_functions: Option<usize>,
+    pub mutations_per_minute: Option<u32>,
+    /// 当前可用的变更令牌数（未限制速率时为空）
+    pub mutation_tokens: Option<u32>,
+    /// 因函数数达到上限被拒绝的注册数
+    pub rejected_full: u64,
+    /// 因变更速率超限被拒绝的变更数
+    pub rejected_rate_limited: u64,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// 注册表护栏的运行时状态
+#[derive(Debug)]
+pub struct RegistryGuardrails {
+    config: RegistryGuardrailsConfig,
+    bucket: StdMutex<TokenBucket>,
+    /// 已记录过接近上限的警告（函数数回落到警告线以下后重置）
+    warned: AtomicBool,
+    rejected_full: AtomicU64,
+    rejected_rate_limited: AtomicU64,
+}
+
+impl RegistryGuardrails {
+    pub fn new(config: RegistryGuardrailsConfig) -> Self {
+        let capacity = Self::capacity(&config);
+        Self {
+            config,
+            bucket: StdMutex::new(TokenBucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+            }),
+            warned: AtomicBool::new(false),
+            rejected_full: AtomicU64::new(0),
+            rejected_rate_limited: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &RegistryGuardrailsConfig {
+        &self.config
+    }
+
+    fn capacity(config: &RegistryGuardrailsConfig) -> f64 {
+        match config.mutations_per_minute {
+            Some(rate) => f64::from(config.mutation_burst.unwrap_or(rate).max(1)),
+            None => 0.0,
+        }
+    }
+
+    /// 按经过的时间补充令牌，返回当前令牌数
+    fn refill(&self, bucket: &mut TokenBucket, rate: u32) -> f64 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens =
+            (bucket.tokens + elapsed * f64::from(rate) / 60.0).min(Self::capacity(&self.config));
+        bucket.refilled_at = now;
+        bucket.tokens
+    }
+
+    /// 为一次变更消耗一个令牌，令牌不足时返回 `MutationRateLimited`
+    pub fn acquire_mutation(&self) -> Result<()> {
+        let Some(rate) = self.config.mutations_per_minute else {
+            return Ok(());
+        };
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        let tokens = self.refill(&mut bucket, rate);
+        if tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        self.rejected_rate_limited.fetch_add(1, Ordering::Relaxed);
+        let wait = (1.0 - tokens) * 60.0 / f64::from(rate.max(1));
+        Err(FluxError::MutationRateLimited {
+            per_minute: rate,
+            retry_after: Duration::from_secs_f64(wait),
+        })
+    }
+
+    /// 已有 `current` 个函数时能否再注册一个新函数
+    pub fn check_capacity(&self, current: usize) -> Result<()> {
+        match self.config.max_functions {
+            Some(limit) if current >= limit => {
+                self.rejected_full.fetch_add(1, Ordering::Relaxed);
+                Err(FluxError::RegistryFull { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 函数数变化后调用：首次达到上限的 90% 时记录一次警告
+    pub fn observe_count(&self, count: usize) {
+        let Some(limit) = self.config.max_functions else {
+            return;
+        };
+        let threshold = (limit as f64 * WARNING_RATIO).ceil() as usize;
+        if count < threshold {
+            self.warned.store(false, Ordering::Relaxed);
+        } else if !self.warned.swap(true, Ordering::Relaxed) {
+            tracing::warn!(
+                "Function registry is at {count} of {limit} functions; new registrations are rejected once the limit is reached"
+            );
+        }
+    }
+
+    pub fn status(&self, functions: usize) -> RegistryGuardrailsStatus {
+        let mutation_tokens = self.config.mutations_per_minute.map(|rate| {
+            let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+            self.refill(&mut bucket, rate).floor() as u32
+        });
+        RegistryGuardrailsStatus {
+            functions,
+            max_functions: self.config.max_functions,
+            mutations_per_minute: self.config.mutations_per_minute,
+            mutation_tokens,
+            rejected_full: self.rejected_full.load(Ordering::Relaxed),
+            rejected_rate_limited: self.rejected_rate_limited.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for RegistryGuardrails {
+    fn default() -> Self {
+        Self::new(RegistryGuardrailsConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_allows_burst_then_rejects() {
+        let guardrails = RegistryGuardrails::new(RegistryGuardrailsConfig {
+            mutations_per_minute: Some(60),
+            mutation_burst: Some(2),
+            ..Default::default()
+        });
+        guardrails.acquire_mutation().unwrap();
+        guardrails.acquire_mutation().unwrap();
+        match guardrails.acquire_mutation() {
+            Err(FluxError::MutationRateLimited { retry_after, .. }) => {
+                assert!(retry_after <= Duration::from_secs(1), "{retry_after:?}");
+            }
+            other => panic!("expected rate limit, got {other:?}"),
+        }
+        assert_eq!(guardrails.status(0).rejected_rate_limited, 1);
+
+        // 每分钟 60 次即每秒补充一个令牌
+        std::thread::sleep(Duration::from_millis(1100));
+        guardrails.acquire_mutation().unwrap();
+    }
+
+    #[test]
+    fn test_capacity_and_unlimited_defaults() {
+        let unlimited = RegistryGuardrails::default();
+        unlimited.check_capacity(usize::MAX - 1).unwrap();
+        unlimited.acquire_mutation().unwrap();
+        assert_eq!(unlimited.status(3).mutation_tokens, None);
+
+        let capped = RegistryGuardrails::new(RegistryGuardrailsConfig {
+            max_functions: Some(10),
+            ..Default::default()
+        });
+        capped.check_capacity(9).unwrap();
+        assert!(matches!(
+            capped.check_capacity(10),
+            Err(FluxError::RegistryFull { limit: 10 })
+        ));
+        assert_eq!(capped.status(10).rejected_full, 1);
+
+        // 警告只在越过 90% 时记录一次，回落后重新计算
+        capped.observe_count(9);
+        assert!(capped.warned.load(Ordering::Relaxed));
+        capped.observe_count(8);
+        assert!(!capped.warned.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,311 @@
+//! 源文件的元数据头
+//!
+//! 从文件或目录加载函数时，源文件开头注释块中的 `flux:` 行以 JSON 给出函数元数据：
+//! JavaScript、TypeScript 和 Rust 为 `// flux: {...}`，Python 为 `# flux: {...}`，JSON 可以
+//! 在后续的注释行中继续。同目录的 `<name>.flux.json` 附属文件中的字段优先于文件头。
+//!
+//! 文件头从函数代码中移除，只修改文件头时代码哈希不变（不会重新编译）。
+
+use super::{FluxError, FunctionParameter, RegisterFunctionRequest, Result, RetryPolicy};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use utoipa::ToSchema;
+
+/// 附属元数据文件的后缀（`hello.js` 对应 `hello.flux.json`）
+pub const SIDECAR_SUFFIX: &str = ".flux.json";
+
+/// 文件头中的指令前缀
+const DIRECTIVE: &str = "flux:";
+
+/// 源文件头或附属文件中的函数元数据，未知字段视为错误
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileHeader {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub timeout_ms: Option<u64>,
+    pub version: Option<String>,
+    pub dependencies: Option<Vec<String>>,
+    pub parameters: Option<Vec<FunctionParameter>>,
+    pub return_type: Option<String>,
+    pub retry_policy: Option<RetryPolicy>,
+    pub idempotent: Option<bool>,
+    pub labels: Option<HashMap<String, String>>,
+}
+
+/// 元数据字段的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderSource {
+    /// 源文件开头的 `flux:` 注释
+    Header,
+    /// `<name>.flux.json` 附属文件
+    Sidecar,
+}
+
+/// 按字段名记录取自文件头或附属文件的元数据
+pub type HeaderFields = BTreeMap<String, HeaderSource>;
+
+/// 脚本类型的行注释前缀
+pub fn comment_prefix(script_type: &str) -> &'static str {
+    match script_type {
+        "python" => "#",
+        _ => "//",
+    }
+}
+
+/// 源文件对应的附属元数据文件名
+pub fn sidecar_name(file_stem: &str) -> String {
+    format!("{file_stem}{SIDECAR_SUFFIX}")
+}
+
+/// 去掉位置后缀的 serde_json 错误信息，位置按源文件另行报告
+fn message(error: &serde_json::Error) -> String {
+    let error = error.to_string();
+    match error.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_string(),
+        None => error,
+    }
+}
+
+fn invalid(path: &Path, line: usize, reason: impl std::fmt::Display) -> FluxError {
+    FluxError::ValidationError {
+        reason: format!("Invalid flux header at {}:{line}: {reason}", path.display()),
+    }
+}
+
+/// 注释行去掉前缀后的内容，不是注释行时返回 None
+fn comment_text<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    line.trim_start().strip_prefix(prefix).map(str::trim)
+}
+
+/// 解析源文件开头注释块中的文件头，返回文件头和移除文件头后的代码
+///
+/// 注释块之前只允许空行（第一行可以是 `#!`），遇到第一行代码即停止查找。
+pub fn parse_source(
+    content: &str,
+    prefix: &str,
+    path: &Path,
+) -> Result<(Option<FileHeader>, String)> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let comment = |line| comment_text(line, prefix);
+    let mut start = None;
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() || (index == 0 && line.starts_with("#!")) {
+            continue;
+        }
+        match comment(line) {
+            Some(text) if text.starts_with(DIRECTIVE) => {
+                start = Some(index);
+                break;
+            }
+            Some(_) => continue,
+            None => break,
+        }
+    }
+    let Some(start) = start else {
+        return Ok((None, content.to_string()));
+    };
+
+    // JSON 从指令之后开始，可以在紧接着的注释行中继续
+    let mut json = comment(lines[start]).unwrap_or_default()[DIRECTIVE.len()..].to_string();
+    for line in &lines[start + 1..] {
+        let Some(text) = comment(line) else { break };
+        json.push('\n');
+        json.push_str(text);
+    }
+    let mut stream = serde_json::Deserializer::from_str(&json).into_iter::<FileHeader>();
+    let header = match stream.next() {
+        Some(Ok(header)) => header,
+        Some(Err(e)) => return Err(invalid(path, start + e.line(), message(&e))),
+        None => {
+            return Err(invalid(
+                path,
+                start + 1,
+                "expected a JSON object after `flux:`",
+            ));
+        }
+    };
+    let end = stream.byte_offset();
+    let used = json[..end].matches('\n').count() + 1;
+    let rest = json[end..].split('\n').next().unwrap_or_default();
+    if !rest.trim().is_empty() {
+        return Err(invalid(
+            path,
+            start + used,
+            format!(
+                "unexpected content after the header object: '{}'",
+                rest.trim()
+            ),
+        ));
+    }
+    header
+        .validate()
+        .map_err(|reason| invalid(path, start + 1, reason))?;
+
+    let code = lines[..start]
+        .iter()
+        .chain(&lines[start + used..])
+        .copied()
+        .collect();
+    Ok((Some(header), code))
+}
+
+/// 解析附属元数据文件
+pub fn parse_sidecar(content: &str, path: &Path) -> Result<FileHeader> {
+    let header: FileHeader =
+        serde_json::from_str(content).map_err(|e| FluxError::ValidationError {
+            reason: format!(
+                "Invalid metadata file {}:{}: {}",
+                path.display(),
+                e.line(),
+                message(&e)
+            ),
+        })?;
+    header
+        .validate()
+        .map_err(|reason| FluxError::ValidationError {
+            reason: format!("Invalid metadata file {}: {reason}", path.display()),
+        })?;
+    Ok(header)
+}
+
+impl FileHeader {
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.timeout_ms == Some(0) {
+            return Err("timeout_ms must be positive".to_string());
+        }
+        if self.name.as_deref().is_some_and(str::is_empty) {
+            return Err("name must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// 给出的字段名
+    fn fields(&self) -> Vec<&'static str> {
+        [
+            ("name", self.name.is_some()),
+            ("description", self.description.is_some()),
+            ("timeout_ms", self.timeout_ms.is_some()),
+            ("version", self.version.is_some()),
+            ("dependencies", self.dependencies.is_some()),
+            ("parameters", self.parameters.is_some()),
+            ("return_type", self.return_type.is_some()),
+            ("retry_policy", self.retry_policy.is_some()),
+            ("idempotent", self.idempotent.is_some()),
+            ("labels", self.labels.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(field, present)| present.then_some(field))
+        .collect()
+    }
+
+    /// 逐字段合并文件头和附属文件（附属文件优先），返回合并结果和各字段的来源
+    pub fn merge(header: Option<Self>, sidecar: Option<Self>) -> (Self, HeaderFields) {
+        let mut fields = HeaderFields::new();
+        for (source, origin) in [
+            (&header, HeaderSource::Header),
+            (&sidecar, HeaderSource::Sidecar),
+        ] {
+            for field in source.iter().flat_map(Self::fields) {
+                fields.insert(field.to_string(), origin);
+            }
+        }
+        let (header, sidecar) = (header.unwrap_or_default(), sidecar.unwrap_or_default());
+        let merged = Self {
+            name: sidecar.name.or(header.name),
+            description: sidecar.description.or(header.description),
+            timeout_ms: sidecar.timeout_ms.or(header.timeout_ms),
+            version: sidecar.version.or(header.version),
+            dependencies: sidecar.dependencies.or(header.dependencies),
+            parameters: sidecar.parameters.or(header.parameters),
+            return_type: sidecar.return_type.or(header.return_type),
+            retry_policy: sidecar.retry_policy.or(header.retry_policy),
+            idempotent: sidecar.idempotent.or(header.idempotent),
+            labels: sidecar.labels.or(header.labels),
+        };
+        (merged, fields)
+    }
+
+    /// 填入注册请求中尚未给出的字段（名称由调用方处理）
+    pub fn apply(self, request: &mut RegisterFunctionRequest) {
+        request.description = request.description.take().or(self.description);
+        request.timeout_ms = request.timeout_ms.or(self.timeout_ms);
+        request.version = request.version.take().or(self.version);
+        request.dependencies = request.dependencies.take().or(self.dependencies);
+        request.parameters = request.parameters.take().or(self.parameters);
+        request.return_type = request.return_type.take().or(self.return_type);
+        request.retry_policy = request.retry_policy.take().or(self.retry_policy);
+        request.idempotent = request.idempotent.or(self.idempotent);
+        request.labels = request.labels.take().or(self.labels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str, prefix: &str) -> Result<(Option<FileHeader>, String)> {
+        parse_source(content, prefix, Path::new("fn.js"))
+    }
+
+    #[test]
+    fn test_header_is_parsed_and_stripped() {
+        let content = "#!/usr/bin/env node\n// Adds numbers\n// flux: {\"timeout_ms\": 100,\n//   \"description\": \"adds\"}\n// more notes\nreturn input.a + input.b;\n";
+        let (header, code) = parse(content, "//").unwrap();
+        let header = header.unwrap();
+        assert_eq!(header.timeout_ms, Some(100));
+        assert_eq!(header.description.as_deref(), Some("adds"));
+        assert_eq!(
+            code,
+            "#!/usr/bin/env node\n// Adds numbers\n// more notes\nreturn input.a + input.b;\n"
+        );
+
+        // 代码之后的 flux: 注释不是文件头
+        let content = "return 1;\n// flux: {\"timeout_ms\": 1}\n";
+        assert_eq!(parse(content, "//").unwrap(), (None, content.to_string()));
+        // Python 的注释前缀
+        let (header, code) = parse("# flux: {\"name\": \"py\"}\nreturn 1\n", "#").unwrap();
+        assert_eq!(header.unwrap().name.as_deref(), Some("py"));
+        assert_eq!(code, "return 1\n");
+    }
+
+    #[test]
+    fn test_malformed_headers_report_the_file_line() {
+        let error = |content: &str| parse(content, "//").unwrap_err().to_string();
+        let e = error("\n// flux: {\"timeout_ms\": 10,\n//  \"description\": }\nreturn 1;");
+        assert!(e.contains("fn.js:3:"), "{e}");
+        assert!(e.contains("expected value"), "{e}");
+        let e = error("// flux: {\"timout_ms\": 10}\n");
+        assert!(
+            e.contains("fn.js:1:") && e.contains("unknown field `timout_ms`"),
+            "{e}"
+        );
+        let e = error("// flux: {\"timeout_ms\": 0}\n");
+        assert!(e.contains("timeout_ms must be positive"), "{e}");
+        let e = error("// flux: {} trailing\n");
+        assert!(e.contains("unexpected content"), "{e}");
+        let e = error("// flux:\n");
+        assert!(e.contains("fn.js:1:"), "{e}");
+    }
+
+    #[test]
+    fn test_sidecar_fields_take_precedence() {
+        let header = FileHeader {
+            description: Some("from header".to_string()),
+            timeout_ms: Some(100),
+            ..Default::default()
+        };
+        let sidecar = parse_sidecar(r#"{"timeout_ms": 200}"#, Path::new("f.flux.json")).unwrap();
+        let (merged, fields) = FileHeader::merge(Some(header), Some(sidecar));
+        assert_eq!(merged.timeout_ms, Some(200));
+        assert_eq!(merged.description.as_deref(), Some("from header"));
+        assert_eq!(fields["timeout_ms"], HeaderSource::Sidecar);
+        assert_eq!(fields["description"], HeaderSource::Header);
+        assert_eq!(fields.len(), 2);
+
+        let e = parse_sidecar("{\n  \"labels\": 1\n}", Path::new("f.flux.json")).unwrap_err();
+        assert!(e.to_string().contains("f.flux.json:2:"), "{e}");
+    }
+}
@@ -0,0 +1,475 @@
+//! 自定义 HTTP 路由绑定：把 `GET /api/users/:id` 之类的请求映射为函数调用
+//!
+//! 绑定保存在目标函数的元数据中，随注册表持久化，函数删除时一并移除。
+
+use super::{FluxError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// 可以绑定的 HTTP 方法
+pub const ROUTE_METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE"];
+
+/// 输入映射中表示合并到输入顶层的目标
+pub const MERGE_INTO_INPUT: &str = "$";
+
+/// 一条路由绑定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct HttpRoute {
+    /// 绑定ID（注册时分配）
+    #[serde(default)]
+    pub id: String,
+    /// HTTP 方法（不区分大小写，保存为大写）
+    pub method: String,
+    /// 路径模式，例如 `/api/users/:id`；`:name` 段匹配任意一段并作为路径参数
+    pub path_pattern: String,
+    /// 目标函数
+    pub function: String,
+    /// 请求各部分如何组成函数输入
+    #[serde(default)]
+    pub input_mapping: InputMapping,
+}
+
+/// 请求各部分在函数输入对象中的位置
+///
+/// 每项为输入中的字段名，`"$"` 表示合并到输入顶层，`null` 表示不传入。
+/// 按请求体、查询参数、请求头、路径参数的顺序写入，同名字段以后写入的为准。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct InputMapping {
+    /// 路径参数（默认 `"$"`）
+    #[serde(default = "default_path")]
+    pub path: Option<String>,
+    /// 查询参数（默认 `"query"`）
+    #[serde(default = "default_query")]
+    pub query: Option<String>,
+    /// 请求体（默认 `"body"`；合并到顶层时请求体必须是 JSON 对象）
+    #[serde(default = "default_body")]
+    pub body: Option<String>,
+    /// 传入的请求头名称白名单（默认不传入任何请求头）
+    #[serde(default)]
+    pub headers: Vec<String>,
+    /// 白名单中的请求头（默认 `"headers"`）
+    #[serde(default = "default_headers_field")]
+    pub headers_field: Option<String>,
+}
+
+fn default_path() -> Option<String> {
+    Some(MERGE_INTO_INPUT.to_string())
+}
+
+fn default_query() -> Option<String> {
+    Some("query".to_string())
+}
+
+fn default_body() -> Option<String> {
+    Some("body".to_string())
+}
+
+fn default_headers_field() -> Option<String> {
+    Some("headers".to_string())
+}
+
+impl Default for InputMapping {
+    fn default() -> Self {
+        Self {
+            path: default_path(),
+            query: default_query(),
+            body: default_body(),
+            headers: Vec::new(),
+            headers_field: default_headers_field(),
+        }
+    }
+}
+
+/// 一次请求中传入函数的各部分
+#[derive(Debug, Clone, Default)]
+pub struct RouteRequest {
+    pub path_params: BTreeMap<String, String>,
+    pub query: BTreeMap<String, String>,
+    /// 请求头（名称为小写）
+    pub headers: BTreeMap<String, String>,
+    /// 请求体（没有请求体时为 `None`）
+    pub body: Option<Value>,
+}
+
+impl InputMapping {
+    /// 按映射组成函数输入；请求体要合并到顶层但不是对象时返回错误
+    pub fn compose(&self, request: RouteRequest) -> Result<Value> {
+        let mut input = Map::new();
+        if let (Some(target), Some(body)) = (&self.body, request.body) {
+            place(&mut input, target, body).map_err(|_| FluxError::ValidationError {
+                reason: "Request body must be a JSON object to be merged into the input"
+                    .to_string(),
+            })?;
+        }
+        if let Some(target) = &self.query {
+            place(&mut input, target, string_object(request.query))
+                .expect("query parameters are an object");
+        }
+        if let Some(target) = &self.headers_field {
+            let headers = request
+                .headers
+                .into_iter()
+                .filter(|(name, _)| {
+                    self.headers
+                        .iter()
+                        .any(|allowed| allowed.eq_ignore_ascii_case(name))
+                })
+                .collect();
+            if !self.headers.is_empty() {
+                place(&mut input, target, string_object(headers)).expect("headers are an object");
+            }
+        }
+        if let Some(target) = &self.path {
+            place(&mut input, target, string_object(request.path_params))
+                .expect("path parameters are an object");
+        }
+        Ok(Value::Object(input))
+    }
+
+    fn validate(&self) -> Result<()> {
+        for target in [&self.path, &self.query, &self.body, &self.headers_field]
+            .into_iter()
+            .flatten()
+        {
+            if target.trim().is_empty() {
+                return Err(FluxError::ValidationError {
+                    reason: "Input mapping targets must not be empty (use null to omit a part)"
+                        .to_string(),
+                });
+            }
+        }
+        if let Some(header) = self.headers.iter().find(|header| {
+            header.is_empty()
+                || !header
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        }) {
+            return Err(FluxError::ValidationError {
+                reason: format!("Invalid header name in input mapping: '{header}'"),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// 写入输入对象的一个字段，目标为 `"$"` 时合并对象（值不是对象时返回错误）
+fn place(
+    input: &mut Map<String, Value>,
+    target: &str,
+    value: Value,
+) -> std::result::Result<(), ()> {
+    if target != MERGE_INTO_INPUT {
+        input.insert(target.to_string(), value);
+        return Ok(());
+    }
+    match value {
+        Value::Object(fields) => {
+            input.extend(fields);
+            Ok(())
+        }
+        _ => Err(()),
+    }
+}
+
+fn string_object(values: BTreeMap<String, String>) -> Value {
+    Value::Object(
+        values
+            .into_iter()
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect(),
+    )
+}
+
+/// 路径模式中的一段
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+impl HttpRoute {
+    /// 校验并规范化绑定：方法转为大写，路径模式去掉末尾的 `/`
+    ///
+    /// 第一段必须是字面量，且不能是 `reserved` 中的内置路由前缀（内置路由不会被绑定遮蔽）。
+    pub fn normalize(&mut self, reserved: &[String]) -> Result<()> {
+        let invalid = |reason: String| FluxError::ValidationError { reason };
+        self.method = self.method.trim().to_ascii_uppercase();
+        if !ROUTE_METHODS.contains(&self.method.as_str()) {
+            return Err(invalid(format!(
+                "Unsupported route method '{}' (expected one of {})",
+                self.method,
+                ROUTE_METHODS.join(", ")
+            )));
+        }
+        if self.function.trim().is_empty() {
+            return Err(invalid(
+                "Route target function must not be empty".to_string(),
+            ));
+        }
+        let pattern = self.path_pattern.trim();
+        if !pattern.starts_with('/') {
+            return Err(invalid(format!(
+                "Route pattern must start with '/': {pattern}"
+            )));
+        }
+        self.path_pattern = format!("/{}", pattern.trim_matches('/'));
+        let segments = self.segments();
+        match segments.first() {
+            Some(Segment::Literal(first)) if reserved.iter().any(|prefix| prefix == first) => {
+                return Err(invalid(format!(
+                    "Route pattern {} would shadow the built-in '/{first}' routes",
+                    self.path_pattern
+                )));
+            }
+            Some(Segment::Literal(first)) if !first.is_empty() => {}
+            _ => {
+                return Err(invalid(format!(
+                    "Route pattern {} must start with a literal segment",
+                    self.path_pattern
+                )));
+            }
+        }
+        let mut names = Vec::new();
+        for segment in &segments {
+            match segment {
+                Segment::Literal(literal) if literal.is_empty() => {
+                    return Err(invalid(format!(
+                        "Route pattern {} contains an empty segment",
+                        self.path_pattern
+                    )));
+                }
+                Segment::Param(name)
+                    if name.is_empty()
+                        || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') =>
+                {
+                    return Err(invalid(format!(
+                        "Invalid path parameter ':{name}' in route pattern {}",
+                        self.path_pattern
+                    )));
+                }
+                Segment::Param(name) if names.contains(&name) => {
+                    return Err(invalid(format!(
+                        "Duplicate path parameter ':{name}' in route pattern {}",
+                        self.path_pattern
+                    )));
+                }
+                Segment::Param(name) => names.push(name),
+                Segment::Literal(_) => {}
+            }
+        }
+        self.input_mapping.validate()
+    }
+
+    fn segments(&self) -> Vec<Segment> {
+        self.path_pattern
+            .trim_start_matches('/')
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(name) => Segment::Param(name.to_string()),
+                None => Segment::Literal(segment.to_string()),
+            })
+            .collect()
+    }
+
+    /// 两条绑定的方法相同且存在同时匹配两者的路径
+    pub fn conflicts_with(&self, other: &HttpRoute) -> bool {
+        let (ours, theirs) = (self.segments(), other.segments());
+        self.method == other.method
+            && ours.len() == theirs.len()
+            && ours.iter().zip(&theirs).all(|pair| match pair {
+                (Segment::Literal(a), Segment::Literal(b)) => a == b,
+                _ => true,
+            })
+    }
+
+    /// 路径与模式匹配时返回路径参数（已按百分号编码解码）
+    pub fn match_path(&self, path: &str) -> Option<BTreeMap<String, String>> {
+        let path = path.trim_matches('/');
+        let parts: Vec<&str> = path.split('/').collect();
+        let segments = self.segments();
+        if parts.len() != segments.len() {
+            return None;
+        }
+        let mut params = BTreeMap::new();
+        for (segment, part) in segments.into_iter().zip(parts) {
+            match segment {
+                Segment::Literal(literal) if literal == part => {}
+                Segment::Param(name) if !part.is_empty() => {
+                    params.insert(name, percent_decode(part)?);
+                }
+                _ => return None,
+            }
+        }
+        Some(params)
+    }
+}
+
+/// 解码路径段中的百分号编码（不是合法 UTF-8 时返回 `None`）
+fn percent_decode(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = segment
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// 路由表中按方法和路径查找的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteLookup {
+    /// 匹配到绑定
+    Matched {
+        route: Box<HttpRoute>,
+        params: BTreeMap<String, String>,
+    },
+    /// 路径匹配但方法不匹配，附带允许的方法
+    MethodNotAllowed {
+        allowed: Vec<String>,
+    },
+    NotFound,
+}
+
+/// 在绑定中查找请求对应的路由
+pub fn lookup(routes: &[HttpRoute], method: &str, path: &str) -> RouteLookup {
+    let mut allowed = Vec::new();
+    for route in routes {
+        if let Some(params) = route.match_path(path) {
+            if route.method.eq_ignore_ascii_case(method) {
+                return RouteLookup::Matched {
+                    route: Box::new(route.clone()),
+                    params,
+                };
+            }
+            if !allowed.contains(&route.method) {
+                allowed.push(route.method.clone());
+            }
+        }
+    }
+    if allowed.is_empty() {
+        RouteLookup::NotFound
+    } else {
+        allowed.sort();
+        RouteLookup::MethodNotAllowed { allowed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn route(method: &str, pattern: &str) -> HttpRoute {
+        let mut route = HttpRoute {
+            id: String::new(),
+            method: method.to_string(),
+            path_pattern: pattern.to_string(),
+            function: "get-user".to_string(),
+            input_mapping: InputMapping::default(),
+        };
+        route.normalize(&["functions".to_string()]).unwrap();
+        route
+    }
+
+    #[test]
+    fn test_patterns_extract_params_and_detect_conflicts() {
+        let user = route("get", "/api/users/:id/");
+        assert_eq!(user.method, "GET");
+        assert_eq!(user.path_pattern, "/api/users/:id");
+        assert_eq!(
+            user.match_path("/api/users/a%20b"),
+            Some(BTreeMap::from([("id".to_string(), "a b".to_string())]))
+        );
+        assert_eq!(user.match_path("/api/users"), None);
+        assert_eq!(user.match_path("/api/users/1/posts"), None);
+
+        assert!(user.conflicts_with(&route("GET", "/api/users/me")));
+        assert!(user.conflicts_with(&route("GET", "/api/:kind/:name")));
+        assert!(!user.conflicts_with(&route("POST", "/api/users/:id")));
+        assert!(!user.conflicts_with(&route("GET", "/api/groups/:id")));
+
+        let post = route("POST", "/api/users/:id");
+        assert_eq!(
+            lookup(&[user.clone(), post], "DELETE", "/api/users/1"),
+            RouteLookup::MethodNotAllowed {
+                allowed: vec!["GET".to_string(), "POST".to_string()]
+            }
+        );
+        assert_eq!(lookup(&[user], "GET", "/other"), RouteLookup::NotFound);
+    }
+
+    #[test]
+    fn test_invalid_patterns_are_rejected() {
+        for (method, pattern) in [
+            ("TRACE", "/api"),
+            ("GET", "api/users"),
+            ("GET", "/functions/:name"),
+            ("GET", "/:anything"),
+            ("GET", "/"),
+            ("GET", "/api//users"),
+            ("GET", "/api/:id/:id"),
+            ("GET", "/api/:user-id"),
+        ] {
+            let mut route = HttpRoute {
+                id: String::new(),
+                method: method.to_string(),
+                path_pattern: pattern.to_string(),
+                function: "f".to_string(),
+                input_mapping: InputMapping::default(),
+            };
+            assert!(
+                route.normalize(&["functions".to_string()]).is_err(),
+                "{method} {pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_mapping_composes_input() {
+        let request = RouteRequest {
+            path_params: BTreeMap::from([("id".to_string(), "42".to_string())]),
+            query: BTreeMap::from([("verbose".to_string(), "1".to_string())]),
+            headers: BTreeMap::from([
+                ("x-tenant".to_string(), "acme".to_string()),
+                ("authorization".to_string(), "secret".to_string()),
+            ]),
+            body: Some(json!({"id": "from-body", "name": "n"})),
+        };
+
+        let default = InputMapping::default();
+        assert_eq!(
+            default.compose(request.clone()).unwrap(),
+            json!({"id": "42", "query": {"verbose": "1"}, "body": {"id": "from-body", "name": "n"}})
+        );
+
+        let merged = InputMapping {
+            body: Some(MERGE_INTO_INPUT.to_string()),
+            query: None,
+            headers: vec!["X-Tenant".to_string()],
+            ..Default::default()
+        };
+        // 路径参数最后写入，覆盖请求体中的同名字段；白名单外的请求头不传入
+        assert_eq!(
+            merged.compose(request.clone()).unwrap(),
+            json!({"id": "42", "name": "n", "headers": {"x-tenant": "acme"}})
+        );
+
+        let scalar = RouteRequest {
+            body: Some(json!([1, 2])),
+            ..request
+        };
+        assert!(merged.compose(scalar).is_err());
+    }
+}
@@ -0,0 +1,833 @@
+//! 注册时从源代码推断函数签名
+//!
+//! 参数和返回类型只在注册时未声明的情况下填充，推断尽力而为：无法解析的代码不推断，
+//! 也从不阻止注册。
+//!
+//! - Python：解析 `def handler(a, b: int = 3) -> str:` 的参数名、类型注解和默认值；
+//! - JavaScript：识别 `function handler(a, b)`、箭头函数和解构参数 `({ a, b = 1 })`，
+//!   只能得到参数名和默认值；
+//! - Rust：用 syn 解析 `fn handler(a: i64) -> anyhow::Result<T>`，得到精确的参数类型和返回类型
+//!   （只推断类型化绑定可以调用的签名：非泛型、返回 `Result<T>`）。
+//!
+//! 脚本函数按约定以 `handler(input, context)` 调用：末尾名为 `context`/`ctx` 的参数是调用上下文，
+//! 只剩一个名为 `input`/`event` 的参数时表示原始输入，两者都不推断为参数。
+//! `*args`、`**kwargs` 和 `...rest` 这类可变参数同样跳过。
+//!
+//! 识别和推断的开销与代码大小无关：Python/JavaScript 的 `handler` 定义只在代码的前
+//! [`DETECTION_WINDOW`] 字节中查找，Rust 语法解析只用于不超过 [`MAX_PARSE_BYTES`] 的代码。
+
+use super::{FunctionMetadata, FunctionParameter};
+use crate::runtime::binding::HANDLER_NAME;
+use crate::runtime::script_cache::ScriptLanguage;
+use aho_corasick::AhoCorasick;
+use quote::ToTokens;
+use std::sync::LazyLock;
+
+/// 未声明返回类型时的默认值
+pub const DEFAULT_RETURN_TYPE: &str = "serde_json::Value";
+
+/// 识别脚本语言时检查的代码前缀字节数
+pub const DETECTION_WINDOW: usize = 64 * 1024;
+
+/// 推断 Rust 签名时语法解析的代码字节数上限，更大的代码不推断
+pub const MAX_PARSE_BYTES: usize = 1024 * 1024;
+
+/// Rust 源文件常见的关键字，只用于决定是否需要语法解析
+const RUST_MARKERS: &[&str] = &[
+    "fn ", "use ", "impl ", "struct ", "enum ", "mod ", "static ", "extern ",
+];
+
+/// 预编译的关键字自动机：第 0 个模式为 `handler`，其余为 [`RUST_MARKERS`]
+static MARKERS: LazyLock<AhoCorasick> = LazyLock::new(|| {
+    AhoCorasick::new(std::iter::once(HANDLER_NAME).chain(RUST_MARKERS.iter().copied()))
+        .expect("detection markers are valid patterns")
+});
+
+/// 代码前缀中出现的关键字
+#[derive(Debug, Clone, Copy, Default)]
+struct Markers {
+    handler: bool,
+    rust: bool,
+}
+
+impl Markers {
+    /// 一次遍历 `prefix`，两类关键字都出现后提前结束
+    fn scan(prefix: &str) -> Self {
+        let mut markers = Self::default();
+        for found in MARKERS.find_iter(prefix) {
+            if found.pattern().as_usize() == 0 {
+                markers.handler = true;
+            } else {
+                markers.rust = true;
+            }
+            if markers.handler && markers.rust {
+                break;
+            }
+        }
+        markers
+    }
+}
+
+/// 代码的前 [`DETECTION_WINDOW`] 字节（在字符边界处截断）
+fn detection_prefix(source: &str) -> &str {
+    if source.len() <= DETECTION_WINDOW {
+        return source;
+    }
+    let mut end = DETECTION_WINDOW;
+    while !source.is_char_boundary(end) {
+        end -= 1;
+    }
+    &source[..end]
+}
+
+/// 从源代码推断出的签名
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InferredSignature {
+    pub parameters: Vec<FunctionParameter>,
+    pub return_type: Option<String>,
+}
+
+/// 源代码的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Rust,
+    Python,
+    JavaScript,
+}
+
+impl Language {
+    /// 函数包按入口文件扩展名判断，单文件函数依次尝试各语言
+    fn candidates(function: &FunctionMetadata) -> &'static [Language] {
+        let extension = function
+            .package
+            .as_ref()
+            .and_then(|package| package.entrypoint.rsplit_once('.'))
+            .map(|(_, extension)| extension);
+        match extension {
+            Some("rs") => &[Self::Rust],
+            Some("py") => &[Self::Python],
+            Some("js" | "mjs" | "cjs" | "ts") => &[Self::JavaScript],
+            _ => &[Self::Rust, Self::Python, Self::JavaScript],
+        }
+    }
+}
+
+/// 推断函数的签名，无法推断时为 `None`
+pub fn infer(function: &FunctionMetadata) -> Option<InferredSignature> {
+    let source = match &function.package {
+        Some(package) => package.entry_source().unwrap_or(&function.code),
+        None => &function.code,
+    };
+    let prefix = detection_prefix(source);
+    Language::candidates(function)
+        .iter()
+        .find_map(|language| match language {
+            Language::Rust if source.len() <= MAX_PARSE_BYTES => infer_rust(source),
+            Language::Rust => None,
+            Language::Python => infer_python(prefix),
+            Language::JavaScript => infer_javascript(prefix),
+        })
+}
+
+/// 单文件函数源代码的脚本语言：按 Python 的 `def handler(...)` 或 JavaScript 的 `handler` 定义识别，
+/// 可以解析为 Rust 源文件或两者都不是时为 `None`
+///
+/// 只检查代码的前 [`DETECTION_WINDOW`] 字节：前缀中没有 `handler` 时直接视为 Rust；
+/// 同时出现 Rust 关键字且代码不超过该长度时才用 syn 解析整个文件区分两者。
+pub fn script_language(source: &str) -> Option<ScriptLanguage> {
+    let prefix = detection_prefix(source);
+    let markers = Markers::scan(prefix);
+    if !markers.handler {
+        return None;
+    }
+    if markers.rust && source.len() <= DETECTION_WINDOW && syn::parse_file(source).is_ok() {
+        return None;
+    }
+    if infer_python(prefix).is_some() {
+        Some(ScriptLanguage::Python)
+    } else if javascript_params(prefix).is_some() {
+        Some(ScriptLanguage::JavaScript)
+    } else {
+        None
+    }
+}
+
+/// 未声明的参数和返回类型用推断结果填充，返回是否填充了任何字段
+pub fn fill_missing(function: &mut FunctionMetadata) -> bool {
+    let missing_parameters = function.parameters.is_empty();
+    let missing_return_type = is_default_return_type(&function.return_type);
+    if !missing_parameters && !missing_return_type {
+        return false;
+    }
+    let Some(inferred) = infer(function) else {
+        return false;
+    };
+    let mut filled = false;
+    if missing_parameters && !inferred.parameters.is_empty() {
+        function.parameters = inferred.parameters;
+        function.parameters_inferred = true;
+        filled = true;
+    }
+    if missing_return_type && let Some(return_type) = inferred.return_type {
+        function.return_type = return_type;
+        function.return_type_inferred = true;
+        filled = true;
+    }
+    filled
+}
+
+/// 按当前代码重新推断：丢弃此前推断的字段（声明的字段保持不变）后重新填充
+pub fn refresh(function: &mut FunctionMetadata) -> bool {
+    if function.parameters_inferred {
+        function.parameters.clear();
+        function.parameters_inferred = false;
+    }
+    if function.return_type_inferred {
+        function.return_type = DEFAULT_RETURN_TYPE.to_string();
+        function.return_type_inferred = false;
+    }
+    fill_missing(function)
+}
+
+fn is_default_return_type(return_type: &str) -> bool {
+    matches!(return_type.trim(), "" | DEFAULT_RETURN_TYPE | "any")
+}
+
+fn parameter(name: &str, param_type: String, default_value: Option<String>) -> FunctionParameter {
+    FunctionParameter {
+        name: name.to_string(),
+        param_type,
+        description: None,
+        required: default_value.is_none(),
+        default_value,
+    }
+}
+
+// ---- Rust ----
+
+fn infer_rust(source: &str) -> Option<InferredSignature> {
+    let file = syn::parse_file(source).ok()?;
+    let handler = file.items.iter().find_map(|item| match item {
+        syn::Item::Fn(item) if item.sig.ident == HANDLER_NAME => Some(&item.sig),
+        _ => None,
+    })?;
+    // 类型化绑定无法为泛型参数选择具体类型
+    if !handler.generics.params.is_empty() || handler.variadic.is_some() {
+        return None;
+    }
+    let syn::ReturnType::Type(_, output) = &handler.output else {
+        return None;
+    };
+    let return_type = result_ok_type(output)?;
+
+    let mut parameters = Vec::new();
+    for input in &handler.inputs {
+        let syn::FnArg::Typed(typed) = input else {
+            return None;
+        };
+        let syn::Pat::Ident(pat) = typed.pat.as_ref() else {
+            return None;
+        };
+        if matches!(typed.ty.as_ref(), syn::Type::ImplTrait(_)) {
+            return None;
+        }
+        let param_type = type_string(&typed.ty);
+        let mut param = parameter(&pat.ident.to_string(), param_type.clone(), None);
+        param.required = !param_type.starts_with("Option<");
+        parameters.push(param);
+    }
+    // 只接收原始输入的 handler 不适合类型化绑定
+    if without_conventional(parameters.clone()).len() != parameters.len() {
+        return None;
+    }
+    Some(InferredSignature {
+        parameters,
+        return_type: Some(return_type),
+    })
+}
+
+/// `Result<T>` / `anyhow::Result<T>` / `Result<T, E>` 中的 `T`
+fn result_ok_type(ty: &syn::Type) -> Option<String> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        syn::GenericArgument::Type(ok) => Some(type_string(ok)),
+        _ => None,
+    }
+}
+
+/// 类型的源代码形式，去掉记号之间多余的空格（`Vec < String >` → `Vec<String>`）
+fn type_string(ty: &syn::Type) -> String {
+    let tokens = ty.to_token_stream().to_string();
+    let chars: Vec<char> = tokens.chars().collect();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+    let mut out = String::with_capacity(tokens.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if c == ' ' {
+            let keep =
+                i > 0 && i + 1 < chars.len() && is_word(chars[i - 1]) && is_word(chars[i + 1]);
+            if keep {
+                out.push(c);
+            }
+        } else {
+            out.push(c);
+            if c == ',' {
+                out.push(' ');
+            }
+        }
+    }
+    out
+}
+
+// ---- Python ----
+
+fn infer_python(source: &str) -> Option<InferredSignature> {
+    let mut lines = source.split('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len() + 1;
+        Some((start, line))
+    });
+    let (offset, line) = lines.find(|(_, line)| {
+        let line = line.strip_prefix("async ").unwrap_or(line);
+        line.strip_prefix("def ")
+            .map(str::trim_start)
+            .and_then(|rest| rest.strip_prefix(HANDLER_NAME))
+            .is_some_and(|rest| rest.trim_start().starts_with('('))
+    })?;
+    let open = offset + line.find('(')?;
+    let (params, rest) = split_enclosed(&source[open..])?;
+    let return_type = rest
+        .trim_start()
+        .strip_prefix("->")
+        .and_then(|annotation| annotation.split_once(':'))
+        .map(|(annotation, _)| python_type(annotation.trim()));
+
+    let mut parameters = Vec::new();
+    for param in split_top_level(params, ',') {
+        let param = param.trim();
+        if param.is_empty() || param == "/" || param == "*" || param.starts_with('*') {
+            continue;
+        }
+        let (declaration, default) = match split_once_top_level(param, '=') {
+            Some((declaration, default)) => (declaration, Some(default.trim())),
+            None => (param, None),
+        };
+        let (name, annotation) = match split_once_top_level(declaration, ':') {
+            Some((name, annotation)) => (name.trim(), Some(annotation.trim())),
+            None => (declaration.trim(), None),
+        };
+        if !is_identifier(name) {
+            return None;
+        }
+        let param_type = annotation.map_or_else(|| "any".to_string(), python_type);
+        parameters.push(parameter(name, param_type, default.map(literal_default)));
+    }
+    Some(InferredSignature {
+        parameters: without_conventional(parameters),
+        return_type: return_type.filter(|ty| ty != "any"),
+    })
+}
+
+/// Python 类型注解映射为声明的类型名
+fn python_type(annotation: &str) -> String {
+    let annotation = annotation.trim().trim_matches(|c| c == '"' || c == '\'');
+    let union: Vec<&str> = split_top_level(annotation, '|')
+        .into_iter()
+        .map(str::trim)
+        .collect();
+    if union.len() == 2 && union.contains(&"None") {
+        let inner = union.iter().find(|ty| **ty != "None").unwrap_or(&"Any");
+        return format!("Option<{}>", python_type(inner));
+    }
+    let (base, argument) = match annotation.split_once('[') {
+        Some((base, rest)) => (base.trim(), rest.strip_suffix(']').map(str::trim)),
+        None => (annotation, None),
+    };
+    let base = base.rsplit('.').next().unwrap_or(base);
+    match (base, argument) {
+        ("Optional", Some(inner)) => format!("Option<{}>", python_type(inner)),
+        ("list" | "List" | "Sequence", Some(inner)) => format!("Vec<{}>", python_type(inner)),
+        ("list" | "List" | "Sequence" | "tuple" | "Tuple", _) => "array".to_string(),
+        ("dict" | "Dict" | "Mapping", _) => "object".to_string(),
+        ("int", None) => "integer".to_string(),
+        ("float", None) => "number".to_string(),
+        ("str", None) => "string".to_string(),
+        ("bool", None) => "boolean".to_string(),
+        ("None", None) => "void".to_string(),
+        _ => "any".to_string(),
+    }
+}
+
+// ---- JavaScript ----
+
+fn infer_javascript(source: &str) -> Option<InferredSignature> {
+    let params = javascript_params(source)?;
+    let mut parameters = Vec::new();
+    let mut params = split_top_level(params, ',');
+    // 解构的第一个参数：其中的字段就是输入的参数
+    if let Some(first) = params.first().map(|param| param.trim())
+        && let Some(fields) = first
+            .strip_prefix('{')
+            .and_then(|rest| split_once_top_level(rest, '}'))
+            .map(|(fields, _)| fields)
+    {
+        for field in split_top_level(fields, ',') {
+            let field = field.trim();
+            if field.is_empty() || field.starts_with("...") {
+                continue;
+            }
+            let (declaration, default) = match split_once_top_level(field, '=') {
+                Some((declaration, default)) => (declaration, Some(default.trim())),
+                None => (field, None),
+            };
+            // `{ key: alias }` 的输入字段名是 `key`
+            let name = declaration.split(':').next().unwrap_or(declaration).trim();
+            if !is_identifier(name) {
+                return None;
+            }
+            parameters.push(parameter(
+                name,
+                "any".to_string(),
+                default.map(literal_default),
+            ));
+        }
+        return Some(InferredSignature {
+            parameters,
+            return_type: None,
+        });
+    }
+    params.retain(|param| !param.trim().is_empty());
+    for param in params {
+        let param = param.trim();
+        if param.starts_with("...") {
+            continue;
+        }
+        let (name, default) = match split_once_top_level(param, '=') {
+            Some((name, default)) => (name.trim(), Some(default.trim())),
+            None => (param, None),
+        };
+        if !is_identifier(name) {
+            return None;
+        }
+        parameters.push(parameter(
+            name,
+            "any".to_string(),
+            default.map(literal_default),
+        ));
+    }
+    Some(InferredSignature {
+        parameters: without_conventional(parameters),
+        return_type: None,
+    })
+}
+
+/// `handler` 的参数列表源代码：函数声明、函数表达式或箭头函数
+fn javascript_params(source: &str) -> Option<&str> {
+    let mut search = 0;
+    while let Some(found) = source[search..].find(HANDLER_NAME) {
+        let start = search + found;
+        let end = start + HANDLER_NAME.len();
+        search = end;
+        let before = &source[..start];
+        let after = &source[end..];
+        let bounded = !before.ends_with(|c: char| is_identifier_char(c))
+            && !after.starts_with(|c: char| is_identifier_char(c));
+        if !bounded {
+            continue;
+        }
+        // function handler(...) / async function* handler(...)
+        let declared = before
+            .trim_end()
+            .trim_end_matches('*')
+            .trim_end()
+            .ends_with("function");
+        if declared {
+            if let Some(rest) = after.trim_start().strip_prefix('(') {
+                return split_enclosed(&after[after.len() - rest.len() - 1..]).map(|(p, _)| p);
+            }
+            continue;
+        }
+        // handler = ... / handler: ...（对象属性）
+        let rest = after.trim_start();
+        let rhs = match rest.strip_prefix('=') {
+            Some(rhs) if !rhs.starts_with('=') => rhs,
+            _ => match rest.strip_prefix(':') {
+                Some(rhs) => rhs,
+                None => continue,
+            },
+        };
+        if let Some(params) = function_expression_params(rhs) {
+            return Some(params);
+        }
+    }
+    None
+}
+
+/// `function (...) {}`、`(...) => ...` 或 `x => ...` 的参数列表
+fn function_expression_params(rhs: &str) -> Option<&str> {
+    let mut rhs = rhs.trim_start();
+    if let Some(rest) = rhs.strip_prefix("async")
+        && rest.starts_with(|c: char| c.is_whitespace() || c == '(')
+    {
+        rhs = rest.trim_start();
+    }
+    if let Some(rest) = rhs.strip_prefix("function") {
+        let open = rest.find('(')?;
+        return split_enclosed(&rest[open..]).map(|(params, _)| params);
+    }
+    if rhs.starts_with('(') {
+        let (params, rest) = split_enclosed(rhs)?;
+        return rest.trim_start().starts_with("=>").then_some(params);
+    }
+    let end = rhs
+        .find(|c: char| !is_identifier_char(c))
+        .unwrap_or(rhs.len());
+    let (name, rest) = rhs.split_at(end);
+    (!name.is_empty() && rest.trim_start().starts_with("=>")).then_some(name)
+}
+
+// ---- 共用 ----
+
+/// 去掉调用上下文和表示原始输入的参数（见模块说明）
+fn without_conventional(mut parameters: Vec<FunctionParameter>) -> Vec<FunctionParameter> {
+    if parameters
+        .last()
+        .is_some_and(|param| matches!(param.name.as_str(), "context" | "ctx"))
+    {
+        parameters.pop();
+    }
+    if let [only] = parameters.as_slice()
+        && matches!(only.name.as_str(), "input" | "event")
+    {
+        parameters.clear();
+    }
+    parameters
+}
+
+/// 默认值的 JSON 形式：字符串字面量转为 JSON 字符串，`None`/`True`/`False` 转为 JSON 字面量，
+/// 其他表达式原样保留（绑定时按字符串处理）
+fn literal_default(text: &str) -> String {
+    let text = text.trim();
+    match text {
+        "None" | "null" | "undefined" => return "null".to_string(),
+        "True" => return "true".to_string(),
+        "False" => return "false".to_string(),
+        _ => {}
+    }
+    for quote in ['"', '\'', '`'] {
+        if let Some(inner) = text
+            .strip_prefix(quote)
+            .and_then(|rest| rest.strip_suffix(quote))
+        {
+            return serde_json::Value::String(inner.to_string()).to_string();
+        }
+    }
+    text.to_string()
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(is_identifier_char)
+}
+
+/// 从开括号开始取出匹配的括号内的内容和之后的源代码（跳过字符串中的括号）
+fn split_enclosed(source: &str) -> Option<(&str, &str)> {
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in source.char_indices() {
+        if let Some(q) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == q => quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' | '`' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some((&source[1..i], &source[i + 1..]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 按不在括号或字符串中的分隔符切分
+fn split_top_level(source: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = source;
+    while let Some((part, tail)) = split_once_top_level(rest, separator) {
+        parts.push(part);
+        rest = tail;
+    }
+    parts.push(rest);
+    parts
+}
+
+/// 在第一个不在括号或字符串中的分隔符处切分（`=` 不匹配 `==`、`=>` 等运算符）
+fn split_once_top_level(source: &str, separator: char) -> Option<(&str, &str)> {
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut previous = None;
+    for (i, c) in source.char_indices() {
+        if let Some(q) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == q => quote = None,
+                _ => {}
+            }
+            previous = Some(c);
+            continue;
+        }
+        match c {
+            '"' | '\'' | '`' => quote = Some(c),
+            '(' | '[' | '{' | '<' if separator != c => depth += 1,
+            ')' | ']' | '}' | '>' if separator != c => depth = depth.saturating_sub(1),
+            _ if c == separator && depth == 0 => {
+                let next = source[i + c.len_utf8()..].chars().next();
+                let operator = separator == '='
+                    && (matches!(next, Some('=' | '>'))
+                        || matches!(previous, Some('=' | '!' | '<' | '>')));
+                if !operator {
+                    return Some((&source[..i], &source[i + c.len_utf8()..]));
+                }
+            }
+            _ => {}
+        }
+        previous = Some(c);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(signature: &InferredSignature) -> Vec<(&str, bool, Option<&str>)> {
+        signature
+            .parameters
+            .iter()
+            .map(|param| {
+                (
+                    param.name.as_str(),
+                    param.required,
+                    param.default_value.as_deref(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_python_signatures() {
+        let source = r#"
+import json
+
+def helper(x):
+    return x
+
+async def handler(name: str, count: int = 3, *args, tags: list[str] | None = None,
+                  mode='fast', **kwargs) -> dict[str, int]:
+    return {}
+"#;
+        let signature = infer_python(source).unwrap();
+        assert_eq!(
+            names(&signature),
+            vec![
+                ("name", true, None),
+                ("count", false, Some("3")),
+                ("tags", false, Some("null")),
+                ("mode", false, Some("\"fast\"")),
+            ]
+        );
+        let types: Vec<&str> = signature
+            .parameters
+            .iter()
+            .map(|param| param.param_type.as_str())
+            .collect();
+        assert_eq!(
+            types,
+            vec!["string", "integer", "Option<Vec<string>>", "any"]
+        );
+        assert_eq!(signature.return_type.as_deref(), Some("object"));
+
+        // 约定的 handler(input, context) 表示原始输入，不推断参数
+        let signature = infer_python("def handler(input, context):\n    return input\n").unwrap();
+        assert!(signature.parameters.is_empty());
+        assert!(infer_python("def handle(a):\n    pass\n").is_none());
+    }
+
+    #[test]
+    fn test_javascript_signatures() {
+        let declared = "async function handler(a, b = 'x', ...rest) { return a; }";
+        assert_eq!(
+            names(&infer_javascript(declared).unwrap()),
+            vec![("a", true, None), ("b", false, Some("\"x\""))]
+        );
+
+        let arrow = "const handler = async ({ user, limit = 10, sort: order }, context) => {\n  return [];\n};";
+        assert_eq!(
+            names(&infer_javascript(arrow).unwrap()),
+            vec![
+                ("user", true, None),
+                ("limit", false, Some("10")),
+                ("sort", true, None)
+            ]
+        );
+
+        let single = "module.exports.handler = value => value * 2;";
+        assert_eq!(
+            names(&infer_javascript(single).unwrap()),
+            vec![("value", true, None)]
+        );
+
+        // 名称中包含 handler 的其他标识符不匹配
+        let other = "function handlerHelper(a) {}\nconst handler = function (input, context) { return input; };";
+        assert!(infer_javascript(other).unwrap().parameters.is_empty());
+        assert!(infer_javascript("if (handler == null) {}").is_none());
+    }
+
+    #[test]
+    fn test_rust_signatures() {
+        let source = r#"
+use std::collections::HashMap;
+
+fn helper() {}
+
+fn handler(a: i64, tags: Vec<String>, limits: Option<HashMap<String, u32>>) -> anyhow::Result<f64> {
+    Ok(a as f64)
+}
+"#;
+        let signature = infer_rust(source).unwrap();
+        let params: Vec<(&str, &str, bool)> = signature
+            .parameters
+            .iter()
+            .map(|param| {
+                (
+                    param.name.as_str(),
+                    param.param_type.as_str(),
+                    param.required,
+                )
+            })
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                ("a", "i64", true),
+                ("tags", "Vec<String>", true),
+                ("limits", "Option<HashMap<String, u32>>", false),
+            ]
+        );
+        assert_eq!(signature.return_type.as_deref(), Some("f64"));
+
+        // 泛型、非 Result 返回值和无法解析的代码都不推断
+        assert!(
+            infer_rust("fn handler<T: Into<i64>>(x: T) -> anyhow::Result<i64> { Ok(x.into()) }")
+                .is_none()
+        );
+        assert!(
+            infer_rust("pub fn handler(input: serde_json::Value) -> serde_json::Value { input }")
+                .is_none()
+        );
+        assert!(infer_rust("fn handler(a: i64 -> {").is_none());
+        // 只接收原始输入时保持原始 JSON 调用方式
+        assert!(
+            infer_rust("fn handler(input: serde_json::Value) -> anyhow::Result<serde_json::Value> { Ok(input) }")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_fill_missing_keeps_declared_fields() {
+        let mut function = FunctionMetadata::new(
+            "typed".to_string(),
+            "fn handler(a: i64, b: i64) -> anyhow::Result<i64> { Ok(a + b) }".to_string(),
+        );
+        function.return_type = DEFAULT_RETURN_TYPE.to_string();
+        assert!(fill_missing(&mut function));
+        assert!(function.parameters_inferred && function.return_type_inferred);
+        assert_eq!(function.parameters.len(), 2);
+        assert_eq!(function.return_type, "i64");
+
+        // 代码更新后重新推断只替换推断的字段
+        function.code = "fn handler(x: String) -> anyhow::Result<bool> { Ok(true) }".to_string();
+        function.return_type = "boolean".to_string();
+        function.return_type_inferred = false;
+        assert!(refresh(&mut function));
+        assert_eq!(function.parameters[0].name, "x");
+        assert_eq!(function.return_type, "boolean");
+
+        // 解析失败时不推断，也不报错
+        let mut broken = FunctionMetadata::new("broken".to_string(), "def handler(".to_string());
+        assert!(!fill_missing(&mut broken));
+        assert!(broken.parameters.is_empty() && !broken.parameters_inferred);
+    }
+
+    #[test]
+    fn test_script_language_is_bounded_for_large_code() {
+        let filler = "// padding line for a very large function body\n".repeat(120_000);
+        let cases = [
+            (
+                format!(
+                    "def handler(a, b):\n    return a + b\n{}",
+                    filler.replace("//", "#")
+                ),
+                Some(ScriptLanguage::Python),
+            ),
+            (
+                format!("const handler = async (input) => input;\n{filler}"),
+                Some(ScriptLanguage::JavaScript),
+            ),
+            (
+                format!("pub fn handler(input: i64) -> i64 {{ input }}\n{filler}"),
+                None,
+            ),
+            // `handler` 定义只在前缀中识别
+            (format!("{filler}function handler(input) {{}}\n"), None),
+        ];
+        for (source, expected) in &cases {
+            assert!(source.len() > 5 * 1024 * 1024);
+            let started = std::time::Instant::now();
+            assert_eq!(script_language(source), *expected);
+            let elapsed = started.elapsed();
+            // 未优化的测试构建也远低于该上限，解析整个代码需要数百毫秒
+            assert!(elapsed.as_millis() < 50, "detection took {elapsed:?}");
+        }
+
+        // 前缀在多字节字符中间截断时退回到字符边界
+        let source = format!(
+            "def handler(x):\n    return x\n{}",
+            "é".repeat(DETECTION_WINDOW)
+        );
+        assert_eq!(script_language(&source), Some(ScriptLanguage::Python));
+
+        // 小代码仍按完整语法区分 Rust 和脚本
+        assert_eq!(
+            script_language("fn helper() {}\nfunction handler(input) { return input; }"),
+            Some(ScriptLanguage::JavaScript)
+        );
+        assert_eq!(script_language("use std::fmt;\nfn handler() {}"), None);
+    }
+}
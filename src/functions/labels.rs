@@ -0,0 +1,293 @@
+use super::{FluxError, Result};
+use std::collections::{BTreeSet, HashMap};
+
+/// 标签键最大长度
+pub const MAX_LABEL_KEY_LEN: usize = 63;
+/// 标签值最大长度
+pub const MAX_LABEL_VALUE_LEN: usize = 63;
+
+/// 选择器语法说明，用于错误提示
+pub const SELECTOR_SYNTAX: &str = "comma-separated requirements: `key=value`, `key!=value`, \
+     `key in (v1,v2)`, `key notin (v1,v2)`, `key` (exists) or `!key` (not exists)";
+
+/// 校验标签键：1-63 个字符，只能包含字母、数字、`-`、`_`、`.`、`/`，且以字母或数字开头和结尾
+pub fn validate_label_key(key: &str) -> Result<()> {
+    validate_label_token(key, "key", MAX_LABEL_KEY_LEN, false, &['-', '_', '.', '/'])
+}
+
+/// 校验标签值：最多 63 个字符（可以为空），只能包含字母、数字、`-`、`_`、`.`，且以字母或数字开头和结尾
+pub fn validate_label_value(value: &str) -> Result<()> {
+    validate_label_token(value, "value", MAX_LABEL_VALUE_LEN, true, &['-', '_', '.'])
+}
+
+/// 校验一组标签
+pub fn validate_labels(labels: &HashMap<String, String>) -> Result<()> {
+    for (key, value) in labels {
+        validate_label_key(key)?;
+        validate_label_value(value)?;
+    }
+    Ok(())
+}
+
+fn validate_label_token(
+    token: &str,
+    kind: &str,
+    max_len: usize,
+    allow_empty: bool,
+    extra: &[char],
+) -> Result<()> {
+    let invalid = |rule: String| FluxError::ValidationError {
+        reason: format!("Invalid label {kind} '{token}': {rule}"),
+    };
+
+    if token.is_empty() {
+        return if allow_empty {
+            Ok(())
+        } else {
+            Err(invalid("must not be empty".to_string()))
+        };
+    }
+    if token.len() > max_len {
+        return Err(invalid(format!("must be at most {max_len} characters")));
+    }
+    if let Some(c) = token
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || extra.contains(c)))
+    {
+        return Err(invalid(format!("character {c:?} is not allowed")));
+    }
+    let alnum_edge = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric());
+    if !alnum_edge(token.chars().next()) || !alnum_edge(token.chars().last()) {
+        return Err(invalid(
+            "must start and end with an ASCII letter or digit".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 单个选择条件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelRequirement {
+    /// `key=value` / `key==value`
+    Equals(String, String),
+    /// `key!=value`（不存在该键也视为匹配）
+    NotEquals(String, String),
+    /// `key in (v1,v2)`
+    In(String, BTreeSet<String>),
+    /// `key notin (v1,v2)`（不存在该键也视为匹配）
+    NotIn(String, BTreeSet<String>),
+    /// `key`
+    Exists(String),
+    /// `!key`
+    NotExists(String),
+}
+
+impl LabelRequirement {
+    /// 条件涉及的标签键
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Equals(key, _)
+            | Self::NotEquals(key, _)
+            | Self::In(key, _)
+            | Self::NotIn(key, _)
+            | Self::Exists(key)
+            | Self::NotExists(key) => key,
+        }
+    }
+
+    /// 标签是否满足条件
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        let value = labels.get(self.key());
+        match self {
+            Self::Equals(_, expected) => value == Some(expected),
+            Self::NotEquals(_, expected) => value != Some(expected),
+            Self::In(_, values) => value.is_some_and(|v| values.contains(v)),
+            Self::NotIn(_, values) => !value.is_some_and(|v| values.contains(v)),
+            Self::Exists(_) => value.is_some(),
+            Self::NotExists(_) => value.is_none(),
+        }
+    }
+}
+
+/// 标签选择器，所有条件同时满足才匹配
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelSelector {
+    pub requirements: Vec<LabelRequirement>,
+}
+
+impl LabelSelector {
+    /// 解析选择器表达式，例如 `team=payments,env in (staging,prod)`
+    pub fn parse(expr: &str) -> Result<Self> {
+        let requirements = split_requirements(expr)
+            .map_err(|reason| selector_error(expr, reason))?
+            .into_iter()
+            .map(|part| parse_requirement(part).map_err(|reason| selector_error(expr, reason)))
+            .collect::<Result<Vec<_>>>()?;
+
+        if requirements.is_empty() {
+            return Err(selector_error(
+                expr,
+                "selector must not be empty".to_string(),
+            ));
+        }
+        Ok(Self { requirements })
+    }
+
+    /// 标签是否满足选择器
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.requirements.iter().all(|req| req.matches(labels))
+    }
+}
+
+fn selector_error(expr: &str, reason: String) -> FluxError {
+    FluxError::ValidationError {
+        reason: format!("Invalid label selector '{expr}': {reason}; expected {SELECTOR_SYNTAX}"),
+    }
+}
+
+/// 按括号外的逗号拆分条件
+fn split_requirements(expr: &str) -> std::result::Result<Vec<&str>, String> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in expr.char_indices() {
+        match c {
+            '(' if depth > 0 => return Err("nested parentheses are not allowed".to_string()),
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err("unbalanced ')'".to_string()),
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(expr[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err("unbalanced '('".to_string());
+    }
+    parts.push(expr[start..].trim());
+
+    if parts.len() > 1 && parts.iter().any(|part| part.is_empty()) {
+        return Err("empty requirement".to_string());
+    }
+    Ok(parts.into_iter().filter(|part| !part.is_empty()).collect())
+}
+
+fn parse_requirement(part: &str) -> std::result::Result<LabelRequirement, String> {
+    let check_key = |key: &str| {
+        validate_label_key(key)
+            .map(|_| key.to_string())
+            .map_err(|e| e.to_string())
+    };
+    let check_value = |value: &str| {
+        validate_label_value(value)
+            .map(|_| value.to_string())
+            .map_err(|e| e.to_string())
+    };
+
+    if let Some((key, value)) = part.split_once("!=") {
+        return Ok(LabelRequirement::NotEquals(
+            check_key(key.trim())?,
+            check_value(value.trim())?,
+        ));
+    }
+    if let Some((key, value)) = part.split_once("==").or_else(|| part.split_once('=')) {
+        return Ok(LabelRequirement::Equals(
+            check_key(key.trim())?,
+            check_value(value.trim())?,
+        ));
+    }
+
+    let mut words = part.splitn(2, char::is_whitespace);
+    let key = words.next().unwrap_or_default();
+    let rest = words.next().map(str::trim_start).unwrap_or_default();
+
+    if rest.is_empty() {
+        return match key.strip_prefix('!') {
+            Some(key) => Ok(LabelRequirement::NotExists(check_key(key)?)),
+            None => Ok(LabelRequirement::Exists(check_key(key)?)),
+        };
+    }
+
+    let (negated, list) = if let Some(list) = rest.strip_prefix("notin") {
+        (true, list)
+    } else if let Some(list) = rest.strip_prefix("in") {
+        (false, list)
+    } else {
+        return Err(format!("unrecognized requirement '{part}'"));
+    };
+
+    let list = list.trim();
+    let values = list
+        .strip_prefix('(')
+        .and_then(|list| list.strip_suffix(')'))
+        .ok_or_else(|| format!("expected a parenthesized value list in '{part}'"))?
+        .split(',')
+        .map(|value| check_value(value.trim()))
+        .collect::<std::result::Result<BTreeSet<_>, _>>()?;
+
+    let key = check_key(key)?;
+    Ok(if negated {
+        LabelRequirement::NotIn(key, values)
+    } else {
+        LabelRequirement::In(key, values)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_and_match_selectors() {
+        let selector =
+            LabelSelector::parse("team=payments, env in (staging,prod),!legacy").unwrap();
+        assert_eq!(selector.requirements.len(), 3);
+        assert!(selector.matches(&labels(&[("team", "payments"), ("env", "prod")])));
+        assert!(!selector.matches(&labels(&[("team", "payments"), ("env", "dev")])));
+        assert!(!selector.matches(&labels(&[
+            ("team", "payments"),
+            ("env", "prod"),
+            ("legacy", "")
+        ])));
+
+        let selector = LabelSelector::parse("env notin (prod),team!=search").unwrap();
+        assert!(selector.matches(&labels(&[])));
+        assert!(!selector.matches(&labels(&[("env", "prod")])));
+
+        // 值可以为空
+        assert!(LabelSelector::parse("team=").is_ok());
+
+        for bad in [
+            "",
+            "team=pay ments",
+            "env in staging",
+            "env in (a,(b))",
+            "env in (a",
+            "team=payments,,env=prod",
+            "-team=payments",
+        ] {
+            assert!(
+                LabelSelector::parse(bad).is_err(),
+                "{bad:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_label_validation() {
+        assert!(validate_labels(&labels(&[("team", "payments"), ("app.io/tier", "")])).is_ok());
+        assert!(validate_labels(&labels(&[("", "x")])).is_err());
+        assert!(validate_labels(&labels(&[("team", "a b")])).is_err());
+        assert!(validate_labels(&labels(&[(&"k".repeat(64), "x")])).is_err());
+        assert!(validate_labels(&labels(&[("team", "x-")])).is_err());
+    }
+}
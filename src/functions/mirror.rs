@@ -0,0 +1,95 @@
+use super::{FluxError, Result};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 函数级请求镜像配置
+///
+/// 按采样率把函数的调用在后台以相同输入复制到 `target`，用于上线前验证新实现；
+/// 镜像调用不影响调用方的延迟和结果。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MirrorConfig {
+    /// 镜像目标函数
+    pub target: String,
+    /// 采样率（0 到 1，默认 1 即镜像所有调用）
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f64,
+    /// 比较两次调用的输出并记录不一致（默认 true）
+    #[serde(default = "default_compare")]
+    pub compare: bool,
+}
+
+fn default_sample_rate() -> f64 {
+    1.0
+}
+
+fn default_compare() -> bool {
+    true
+}
+
+impl MirrorConfig {
+    /// 校验采样率范围，且目标不能是函数自身
+    pub fn validate(&self, function: &str) -> Result<()> {
+        if self.target.trim().is_empty() {
+            return Err(FluxError::ValidationError {
+                reason: "Mirror target must not be empty".to_string(),
+            });
+        }
+        if self.target == function {
+            return Err(FluxError::ValidationError {
+                reason: format!("Function '{function}' cannot mirror to itself"),
+            });
+        }
+        if !(0.0..=1.0).contains(&self.sample_rate) {
+            return Err(FluxError::ValidationError {
+                reason: "sample_rate must be between 0 and 1".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// 调用是否被采样：按调用 ID 的哈希判断，同一调用 ID 的结果稳定
+    pub fn samples(&self, invocation_id: &str) -> bool {
+        if self.sample_rate >= 1.0 {
+            return true;
+        }
+        let digest = md5::compute(invocation_id.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest.0[..8]);
+        (u64::from_be_bytes(bytes) as f64 / u64::MAX as f64) < self.sample_rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(sample_rate: f64) -> MirrorConfig {
+        MirrorConfig {
+            target: "parse-invoice-v2".to_string(),
+            sample_rate,
+            compare: true,
+        }
+    }
+
+    #[test]
+    fn test_sampling_follows_rate() {
+        let ids: Vec<String> = (0..2000).map(|i| format!("inv-{i}")).collect();
+        assert!(ids.iter().all(|id| config(1.0).samples(id)));
+        assert!(!ids.iter().any(|id| config(0.0).samples(id)));
+
+        let sampled = ids.iter().filter(|id| config(0.25).samples(id)).count();
+        assert!((400..600).contains(&sampled), "sampled {sampled} of 2000");
+        // 同一调用 ID 的采样结果稳定
+        assert_eq!(config(0.25).samples("inv-7"), config(0.25).samples("inv-7"));
+    }
+
+    #[test]
+    fn test_validate_rejects_self_and_bad_rate() {
+        assert!(config(0.5).validate("parse-invoice").is_ok());
+        assert!(config(0.5).validate("parse-invoice-v2").is_err());
+        assert!(config(1.5).validate("parse-invoice").is_err());
+        let config: MirrorConfig = serde_json::from_str(r#"{"target": "v2"}"#).unwrap();
+        assert_eq!(config.sample_rate, 1.0);
+        assert!(config.compare);
+    }
+}
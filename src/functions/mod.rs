@@ -1,41 +1,196 @@
 #![allow(dead_code)]
+use crate::runtime::debug_capture::DebugCapture;
+use crate::runtime::egress::EgressPolicy;
+use crate::runtime::inline::{ExecutionHint, ExecutionPath};
+use canary::CanaryConfig;
 use chrono::{DateTime, Utc};
+use deprecation::Deprecation;
+use header::HeaderSource;
+use http_route::HttpRoute;
+use mirror::MirrorConfig;
+use package::{FunctionPackage, PackageFile};
+use priority::Priority;
+use schema::SchemaViolation;
 use scru128::Scru128Id;
 use serde::{Deserialize, Serialize};
+use slo::SloConfig;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use testing::FunctionTestCase;
+use utoipa::ToSchema;
+use webhook::WebhookConfig;
 
+pub use status::{ErrorKind, ErrorOrigin, ExecutionStatus, ResourceKind};
+pub use timing::{InstanceSelection, InvocationTiming, ProcessTermination};
+
+pub mod bundle;
+pub mod canary;
+pub mod compilation;
+pub mod compression;
+pub mod context;
+pub mod deprecation;
+pub mod diff;
+pub mod docs;
+pub mod guardrails;
+pub mod header;
+pub mod http_route;
+pub mod inference;
+pub mod labels;
+pub mod mirror;
+pub mod name;
+pub mod object_storage;
+pub mod package;
+pub mod priority;
+pub mod redaction;
 pub mod registry;
+pub mod return_type;
+pub mod schema;
+pub mod slo;
+pub mod status;
 pub mod storage;
+pub mod template;
+pub mod testing;
+pub mod timing;
+pub mod versions;
 pub mod watcher;
+pub mod webhook;
 
 /// 函数调用请求
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct InvokeRequest {
     pub input: serde_json::Value,
+    /// 本次调用覆盖函数默认的重试策略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_policy: Option<RetryPolicy>,
+    /// 本次调用覆盖函数默认的优先级
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// 幂等键：同一函数下重复的键不会再次执行，返回首次执行的结果
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// 客户端会话 ID：调用记录在该会话的调用列表中（见 `GET /sessions/{id}/executions`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// 亲和键：带相同键的调用尽量由同一个实例执行（见 [`crate::runtime::affinity`]）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affinity_key: Option<String>,
 }
 
 /// 函数调用响应
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct InvokeResponse {
     pub output: serde_json::Value,
     pub execution_time_ms: u64,
     pub status: ExecutionStatus,
+    /// 调用请求 ID（由网关分配或取自 X-Request-Id）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// 实际尝试次数（包含首次执行）
+    #[serde(default)]
+    pub attempts_made: u32,
+    /// 成功结果是否来自重试
+    #[serde(default)]
+    pub succeeded_on_retry: bool,
+    /// 是否冷启动（函数首次加载或本次调用触发了编译）
+    #[serde(default)]
+    pub cold_start: bool,
+    /// 输出不符合函数 `output_schema` 的条目（非严格模式下只报告、不使调用失败）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema_violations: Option<Vec<SchemaViolation>>,
+    /// 执行本次调用的运行时后端（只在配置了多运行时路由时设置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    /// 是否为幂等键重复请求返回的已保存结果（只在为 true 时出现）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub replayed: bool,
+    /// 结果是否受故障注入影响（只在为 true 时出现）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub chaos_injected: bool,
+    /// 输出不符合函数声明的 `return_type`（非严格模式下只标记、不使调用失败；只在为 true 时出现）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub return_type_mismatch: bool,
+    /// 排队、编译、启动和执行各阶段的耗时
+    #[serde(default)]
+    pub timing: InvocationTiming,
+    /// 函数进程被终止（超时或超出资源限制）时出现：是否强制终止以及已运行的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub termination: Option<ProcessTermination>,
+    /// 执行路径：`inline` 为进程内的表达式引擎，`external` 为完整运行时（由运行时执行时设置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_path: Option<ExecutionPath>,
+    /// 转发给其他节点执行时为该节点的地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<String>,
+    /// 弃用函数停用后改为调用替代函数时为原函数名（见 [`deprecation::SunsetBehavior::Redirect`]）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirected_from: Option<String>,
 }
 
-/// 函数执行状态
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ExecutionStatus {
-    Success,
-    /// 执行完成（第三阶段新增）
-    Completed,
-    Error(String),
-    /// 执行失败（第三阶段新增）
-    Failed,
+/// 可触发重试的执行结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum RetryOn {
+    Error,
     Timeout,
 }
 
+/// 重试策略
+///
+/// 只对执行阶段的失败生效：校验错误、函数不存在等调度错误以及非幂等函数都不会重试。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// 最大尝试次数（包含首次执行）
+    pub max_attempts: u32,
+    /// 首次重试前的等待时间（毫秒）
+    pub backoff_ms: u64,
+    /// 每次重试后等待时间的放大倍数
+    pub backoff_multiplier: f64,
+    /// 触发重试的执行结果
+    pub retry_on: Vec<RetryOn>,
+    /// 所有尝试（含等待）的总时间预算（毫秒），缺省为 `timeout_ms * max_attempts`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline_ms: Option<u64>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_ms: 100,
+            backoff_multiplier: 2.0,
+            retry_on: vec![RetryOn::Error, RetryOn::Timeout],
+            deadline_ms: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 执行结果是否应当重试
+    pub fn should_retry(&self, status: &ExecutionStatus) -> bool {
+        let kind = match status {
+            ExecutionStatus::Error { .. } | ExecutionStatus::ResourceLimitExceeded { .. } => {
+                RetryOn::Error
+            }
+            ExecutionStatus::Timeout => RetryOn::Timeout,
+            ExecutionStatus::Success | ExecutionStatus::Cancelled => return false,
+        };
+        self.retry_on.contains(&kind)
+    }
+
+    /// 第 `attempt` 次尝试（从 1 开始）失败后的等待时间
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let factor = self
+            .backoff_multiplier
+            .max(1.0)
+            .powi(attempt.saturating_sub(1).min(i32::MAX as u32) as i32);
+        Duration::from_millis((self.backoff_ms as f64 * factor).min(u64::MAX as f64) as u64)
+    }
+}
+
 /// 函数元数据
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FunctionMetadata {
+    #[schema(value_type = String)]
     pub id: Scru128Id,
     pub name: String,
     pub description: String,
@@ -51,10 +206,129 @@ pub struct FunctionMetadata {
     pub parameters: Vec<FunctionParameter>,
     /// 第二阶段新增：返回类型
     pub return_type: String,
+    /// `parameters` 是从源代码推断的（见 [`inference`]；只在为 true 时出现）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub parameters_inferred: bool,
+    /// `return_type` 是从源代码推断的（只在为 true 时出现）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub return_type_inferred: bool,
+    /// 默认重试策略
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    /// 是否幂等，非幂等函数从不重试
+    #[serde(default = "default_idempotent")]
+    pub idempotent: bool,
+    /// 标签（如 `team=payments`、`env=staging`）
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// 调用完成后的 webhook 通知
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhooks: Option<WebhookConfig>,
+    /// 调用优先级（默认 normal）
+    #[serde(default, skip_serializing_if = "Priority::is_normal")]
+    pub priority: Priority,
+    /// 多文件函数包（`code` 为入口文件内容）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<FunctionPackage>,
+    /// 输出本身即 HTTP 响应描述（`status`/`headers`/`body`），无需 `$http` 包装
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub http_response: bool,
+    /// 调用输入模板（见 [`template::InputTemplate`]），执行前用于转换调用输入
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_template: Option<String>,
+    /// 修订号：每次变更由注册表分配（递增），用于 `If-Match` 乐观并发控制
+    #[serde(default)]
+    pub revision: u64,
+    /// 调用输入的 JSON Schema，不符合时拒绝调用（422）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub input_schema: Option<serde_json::Value>,
+    /// 函数输出的 JSON Schema，不符合时在响应中报告（见 `strict_output_schema`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub output_schema: Option<serde_json::Value>,
+    /// 输出不符合 schema 时调用失败，而不只是在响应中报告
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict_output_schema: bool,
+    /// 检查 `return_type` 前把数字字符串转换为数字
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub coerce_output: bool,
+    /// 输出不符合 `return_type` 时调用失败，而不只是在响应中标记
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict_return_type: bool,
+    /// 记录日志或存储调用载荷前脱敏的字段（字段名或 `$.a.b`、`$.a.*` 路径）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_redaction: Vec<String>,
+    /// 执行环境名（`[[runtimes.definitions]]` 中的 `name`），缺省时使用脚本类型的默认解释器
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<String>,
+    /// 资源配额名（`/quotas` 中定义），执行时解析为内存和 CPU 限制，缺省使用实例配置的配额
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_quota: Option<String>,
+    /// 只在命名空间隔离可用时执行（仅进程隔离时拒绝执行）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub requires_isolation: bool,
+    /// 每次调用的暂存目录（`context.scratch_dir`）的大小上限（MB），缺省使用沙箱配置的上限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scratch_limit_mb: Option<u64>,
+    /// 调用结束后保留暂存目录中的文件，通过 `GET /executions/:id/artifacts` 下载
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub persist_scratch: bool,
+    /// 每次调用写入磁盘的字节上限（MB，见 [`crate::runtime::disk`]），超出时终止进程；缺省不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_disk_write_mb: Option<u64>,
+    /// 每次调用累计 CPU 时间的上限（毫秒，见 [`crate::runtime::cpu`]），超出时终止进程，
+    /// 与 `timeout_ms`（墙钟时间）相互独立；缺省不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cpu_time_ms: Option<u64>,
+    /// 测试用例，通过 `POST /functions/:name/test` 执行
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<FunctionTestCase>,
+    /// 执行路径提示：简单表达式默认在进程内求值（见 [`crate::runtime::inline`]）
+    #[serde(default, skip_serializing_if = "ExecutionHint::is_auto")]
+    pub execution_hint: ExecutionHint,
+    /// 请求镜像：调用在后台复制到影子函数并比较输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror: Option<MirrorConfig>,
+    /// 金丝雀探测：按间隔以固定输入调用函数并维护健康状态
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canary: Option<CanaryConfig>,
+    /// 服务目标：监控维护窗口内的合规数字和错误预算，燃烧率过高时发出告警
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slo: Option<SloConfig>,
+    /// 是否参与调用熔断（默认 true；经常合理失败的函数可以关闭）
+    #[serde(default = "default_circuit_breaker")]
+    pub circuit_breaker: bool,
+    /// 出站 HTTP 策略：允许经宿主代理访问的地址和每次调用的上限，缺省时 `http_fetch` 不可用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub egress: Option<EgressPolicy>,
+    /// 调试包模式：失败（或每次）调用后保存源代码、输入和进程输出，通过 `GET /executions/:id/bundle` 下载
+    #[serde(default, skip_serializing_if = "DebugCapture::is_off")]
+    pub debug_capture: DebugCapture,
+    /// 函数文档（Markdown），通过 `GET /functions/:name/docs` 提供
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub documentation: Option<String>,
+    /// 从文件加载时取自源文件 `flux:` 头或 `<name>.flux.json` 的元数据字段及其来源
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub header_fields: BTreeMap<String, HeaderSource>,
+    /// 调用本函数的自定义 HTTP 路由（通过 `/routes` 管理，重新注册时保留）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub http_routes: Vec<HttpRoute>,
+    /// 弃用状态（通过 `/functions/:name/deprecate` 管理，重新注册时保留）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<Deprecation>,
+}
+
+fn default_idempotent() -> bool {
+    true
+}
+
+fn default_circuit_breaker() -> bool {
+    true
 }
 
 /// 函数参数信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct FunctionParameter {
     pub name: String,
     pub param_type: String,
@@ -64,10 +338,12 @@ pub struct FunctionParameter {
 }
 
 /// 函数注册请求
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RegisterFunctionRequest {
     pub name: String,
     pub description: Option<String>,
+    /// 函数代码（提供 `files` 时可省略）
+    #[serde(default)]
     pub code: String,
     pub timeout_ms: Option<u64>,
     /// 第二阶段新增：版本信息
@@ -78,6 +354,162 @@ pub struct RegisterFunctionRequest {
     pub parameters: Option<Vec<FunctionParameter>>,
     /// 第二阶段新增：返回类型
     pub return_type: Option<String>,
+    /// 默认重试策略
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    /// 是否幂等（默认 true）
+    #[serde(default)]
+    pub idempotent: Option<bool>,
+    /// 标签
+    #[serde(default)]
+    pub labels: Option<HashMap<String, String>>,
+    /// 调度器配置名（默认 `default`）
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// 调用完成后的 webhook 通知
+    #[serde(default)]
+    pub webhooks: Option<WebhookConfig>,
+    /// 调用优先级（默认 normal）
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    /// 多文件函数包的文件，提供时 `code` 取自入口文件
+    #[serde(default)]
+    pub files: Option<Vec<PackageFile>>,
+    /// 函数包入口文件路径（提供 `files` 时必填）
+    #[serde(default)]
+    pub entrypoint: Option<String>,
+    /// 输出是否按 HTTP 响应描述解释（默认 false）
+    #[serde(default)]
+    pub http_response: Option<bool>,
+    /// 调用输入模板（JSON，字符串中可使用 `{{path}}` 占位符）
+    #[serde(default)]
+    pub input_template: Option<String>,
+    /// 调用输入的 JSON Schema
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub input_schema: Option<serde_json::Value>,
+    /// 函数输出的 JSON Schema
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub output_schema: Option<serde_json::Value>,
+    /// 输出不符合 schema 时调用失败（默认 false，只在响应中报告）
+    #[serde(default)]
+    pub strict_output_schema: Option<bool>,
+    /// 检查 `return_type` 前把数字字符串转换为数字（默认 false）
+    #[serde(default)]
+    pub coerce_output: Option<bool>,
+    /// 输出不符合 `return_type` 时调用失败（默认 false，只在响应中标记）
+    #[serde(default)]
+    pub strict_return_type: Option<bool>,
+    /// 记录日志或存储调用载荷前脱敏的字段，例如 `["password", "$.card.*"]`
+    #[serde(default)]
+    pub log_redaction: Option<Vec<String>>,
+    /// 是否参与调用熔断（默认 true）
+    pub circuit_breaker: Option<bool>,
+    /// 所属命名空间（默认 `default`，也可以在 `name` 中使用 `<命名空间>/<函数名>`）
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// 执行环境名，必须是已配置且与脚本类型匹配的运行时定义
+    #[serde(default)]
+    pub runtime: Option<String>,
+    /// 资源配额名，必须是已定义的配额
+    #[serde(default)]
+    pub resource_quota: Option<String>,
+    /// 是否要求命名空间隔离（默认 false）
+    #[serde(default)]
+    pub requires_isolation: Option<bool>,
+    /// 暂存目录的大小上限（MB，缺省使用沙箱配置的上限）
+    #[serde(default)]
+    pub scratch_limit_mb: Option<u64>,
+    /// 是否保留暂存目录中的文件（默认 false）
+    #[serde(default)]
+    pub persist_scratch: Option<bool>,
+    /// 每次调用写入磁盘的字节上限（MB，缺省不限制）
+    #[serde(default)]
+    pub max_disk_write_mb: Option<u64>,
+    /// 每次调用累计 CPU 时间的上限（毫秒，缺省不限制）
+    #[serde(default)]
+    pub max_cpu_time_ms: Option<u64>,
+    /// 测试用例（每个函数最多 50 个）
+    #[serde(default)]
+    pub tests: Option<Vec<FunctionTestCase>>,
+    /// 执行路径提示（`auto`、`inline` 或 `external`）
+    #[serde(default)]
+    pub execution_hint: Option<ExecutionHint>,
+    /// 函数文档（Markdown，不超过 64 KiB）
+    #[serde(default)]
+    pub documentation: Option<String>,
+    /// 出站 HTTP 策略（缺省时函数不能使用 `http_fetch`）
+    #[serde(default)]
+    pub egress: Option<EgressPolicy>,
+    /// 调试包模式（默认 off）
+    #[serde(default)]
+    pub debug_capture: Option<DebugCapture>,
+    /// 金丝雀探测配置（缺省时不探测）
+    #[serde(default)]
+    pub canary: Option<CanaryConfig>,
+    /// 服务目标（缺省时不跟踪）
+    #[serde(default)]
+    pub slo: Option<SloConfig>,
+}
+
+impl RegisterFunctionRequest {
+    /// 注册后的限定名（见 [`name::qualify`]）
+    pub fn qualified_name(&self) -> String {
+        match &self.namespace {
+            Some(namespace) => name::qualify(namespace, &self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// 函数更新请求（只更新元数据，不修改代码）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct UpdateFunctionRequest {
+    pub description: Option<String>,
+    pub timeout_ms: Option<u64>,
+    pub version: Option<String>,
+    /// 替换全部标签
+    pub labels: Option<HashMap<String, String>>,
+    pub retry_policy: Option<RetryPolicy>,
+    pub idempotent: Option<bool>,
+    pub priority: Option<Priority>,
+    pub http_response: Option<bool>,
+    /// 替换调用输入模板，空字符串表示移除
+    pub input_template: Option<String>,
+    /// 替换输入 JSON Schema，空对象 `{}` 表示移除
+    #[schema(value_type = Option<Object>)]
+    pub input_schema: Option<serde_json::Value>,
+    /// 替换输出 JSON Schema，空对象 `{}` 表示移除
+    #[schema(value_type = Option<Object>)]
+    pub output_schema: Option<serde_json::Value>,
+    pub strict_output_schema: Option<bool>,
+    pub coerce_output: Option<bool>,
+    pub strict_return_type: Option<bool>,
+    /// 替换日志脱敏字段，空数组表示移除
+    pub log_redaction: Option<Vec<String>>,
+    /// 是否参与调用熔断（默认 true）
+    pub circuit_breaker: Option<bool>,
+    /// 替换资源配额，空字符串表示移除
+    pub resource_quota: Option<String>,
+    /// 是否要求命名空间隔离
+    pub requires_isolation: Option<bool>,
+    /// 替换暂存目录的大小上限，0 表示恢复沙箱配置的上限
+    pub scratch_limit_mb: Option<u64>,
+    /// 是否保留暂存目录中的文件
+    pub persist_scratch: Option<bool>,
+    /// 替换磁盘写入上限，0 表示移除
+    pub max_disk_write_mb: Option<u64>,
+    /// 替换 CPU 时间上限，0 表示移除
+    pub max_cpu_time_ms: Option<u64>,
+    /// 替换全部测试用例，空数组表示移除
+    pub tests: Option<Vec<FunctionTestCase>>,
+    /// 执行路径提示
+    pub execution_hint: Option<ExecutionHint>,
+    /// 替换出站 HTTP 策略，`allow` 为空表示移除
+    pub egress: Option<EgressPolicy>,
+    /// 调试包模式
+    pub debug_capture: Option<DebugCapture>,
 }
 
 /// 系统错误类型
@@ -95,6 +527,18 @@ pub enum FluxError {
     #[error("Execution timeout")]
     Timeout,
 
+    #[error("Platform error: {0}")]
+    Platform(String),
+
+    #[error("{}", resource.message())]
+    ResourceLimitExceeded { resource: ResourceKind },
+
+    #[error("Disk write limit exceeded: {written_bytes} bytes written, limit is {limit_mb}MB")]
+    DiskWriteLimitExceeded { written_bytes: u64, limit_mb: u64 },
+
+    #[error("CPU time limit exceeded: {cpu_time_ms}ms of CPU time used, limit is {limit_ms}ms")]
+    CpuTimeLimitExceeded { cpu_time_ms: u64, limit_ms: u64 },
+
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
@@ -108,14 +552,292 @@ pub enum FluxError {
     #[error("Function compilation failed: {0}")]
     CompilationError(String),
 
+    #[error("Function is still compiling: {name}")]
+    StillCompiling { name: String },
+
     #[error("Function validation failed: {reason}")]
     ValidationError { reason: String },
 
     #[error("Cache error: {0}")]
     CacheError(String),
 
+    #[error("Queue for {priority} priority invocations is full (depth {depth})")]
+    QueueFull { priority: Priority, depth: usize },
+
     #[error("Storage error: {0}")]
     StorageError(String),
+
+    #[error("Revision mismatch for function {name}: expected {expected}, current {current}")]
+    RevisionMismatch {
+        name: String,
+        expected: u64,
+        current: u64,
+    },
+
+    #[error("Not applied: {failed} other entries in the same transaction failed")]
+    TransactionAborted { failed: usize },
+
+    #[error("Version {version} of function {name} is not retained (available: {})", available.join(", "))]
+    VersionNotFound {
+        name: String,
+        version: String,
+        available: Vec<String>,
+    },
+
+    #[error(
+        "Version {version} is the latest version of function {name}; delete the function instead"
+    )]
+    LatestVersion { name: String, version: String },
+
+    #[error(
+        "Input of function {name} violates its input_schema: {}",
+        schema::describe(violations)
+    )]
+    InputSchemaViolation {
+        name: String,
+        violations: Vec<SchemaViolation>,
+    },
+
+    #[error(
+        "Output of function {name} violates its output_schema: {}",
+        schema::describe(violations)
+    )]
+    OutputSchemaViolation {
+        name: String,
+        violations: Vec<SchemaViolation>,
+    },
+
+    #[error("Output of function {name} does not match its return type: {mismatch}")]
+    ReturnTypeMismatch {
+        name: String,
+        mismatch: return_type::ReturnTypeMismatch,
+    },
+
+    #[error("Namespace not found: {name}")]
+    NamespaceNotFound { name: String },
+
+    #[error("Namespace already exists: {name}")]
+    NamespaceAlreadyExists { name: String },
+
+    #[error(
+        "Namespace {name} still contains {functions} functions; delete them first or pass force=true"
+    )]
+    NamespaceNotEmpty { name: String, functions: usize },
+
+    #[error("Resource quota not found: {name}")]
+    QuotaNotFound { name: String },
+
+    #[error("Resource quota already exists: {name}")]
+    QuotaAlreadyExists { name: String },
+
+    #[error("Resource quota {name} is still used by {functions} functions")]
+    QuotaInUse { name: String, functions: usize },
+
+    #[error("State limit of function {function} exceeded: {reason}")]
+    StateLimitExceeded { function: String, reason: String },
+
+    #[error("State of function {function} is read-only during shadow and mirrored invocations")]
+    StateReadOnly { function: String },
+
+    #[error("State key {key} of function {function} is at version {current}, expected {expected}")]
+    StateVersionConflict {
+        function: String,
+        key: String,
+        expected: u64,
+        current: u64,
+    },
+
+    #[error("Outbound HTTP request of function {function} denied: {reason}")]
+    EgressDenied { function: String, reason: String },
+
+    #[error("Outbound HTTP limit of function {function} exceeded: {reason}")]
+    EgressLimitExceeded { function: String, reason: String },
+
+    #[error("Outbound HTTP request of function {function} failed: {reason}")]
+    EgressFailed { function: String, reason: String },
+
+    #[error("Quota of namespace {namespace} exceeded: {reason}")]
+    NamespaceQuotaExceeded { namespace: String, reason: String },
+
+    #[error("Cannot pin more than {limit} functions in the cache")]
+    PinLimitExceeded { limit: usize },
+
+    #[error("Chaos fault injection is disabled; set chaos.enabled = true to enable it")]
+    ChaosDisabled,
+
+    #[error("Execution not found in history: {id}")]
+    ExecutionNotFound { id: String },
+
+    #[error("Debug bundle not found: {id}")]
+    DebugBundleNotFound { id: String },
+
+    #[error("No scratch artifacts for execution {id}")]
+    ArtifactsNotFound { id: String },
+
+    #[error("No captured log for execution {id}")]
+    InvocationLogNotFound { id: String },
+
+    #[error("Execution {id} already has a captured log; use a different request id")]
+    InvocationLogConflict { id: String },
+
+    #[error("Scratch artifact not found: {path} (execution {id})")]
+    ArtifactNotFound { id: String, path: String },
+
+    #[error(
+        "Scratch artifact {path} is {size} bytes, exceeding the download limit of {limit} bytes"
+    )]
+    ArtifactTooLarge { path: String, size: u64, limit: u64 },
+
+    #[error("Function {name} has no test case named '{case}'")]
+    TestCaseNotFound { name: String, case: String },
+
+    #[error("Tests failed for function {name}: {reason}")]
+    TestsFailed { name: String, reason: String },
+
+    #[error("Execution {id} cannot be replayed: {reason}")]
+    ReplayUnavailable { id: String, reason: String },
+
+    #[error(
+        "Circuit breaker for function {name} is open; retry after {}ms",
+        retry_after.as_millis()
+    )]
+    CircuitOpen { name: String, retry_after: Duration },
+
+    #[error("Deployment not found: {id}")]
+    DeploymentNotFound { id: String },
+
+    #[error("Deployment conflict: {reason}")]
+    DeploymentConflict { reason: String },
+
+    #[error("HTTP route not found: {id}")]
+    RouteNotFound { id: String },
+
+    #[error("HTTP route conflict: {reason}")]
+    RouteConflict { reason: String },
+
+    #[error("Function code is {size} bytes, exceeding the limit of {limit} bytes")]
+    CodeTooLarge { size: usize, limit: usize },
+
+    #[error("Function {name} is draining and no longer accepts invocations")]
+    FunctionDraining { name: String },
+
+    #[error(
+        "Function {name} was sunset at {sunset_at}{}",
+        deprecation::replacement_hint(replacement)
+    )]
+    FunctionSunset {
+        name: String,
+        sunset_at: DateTime<Utc>,
+        replacement: Option<String>,
+    },
+
+    #[error("Function storage is unavailable, the catalog is read-only: {reason}")]
+    StorageUnavailable { reason: String },
+
+    #[error("The function registry is full: at most {limit} functions can be registered")]
+    RegistryFull { limit: usize },
+
+    #[error(
+        "Too many registry changes (at most {per_minute} per minute); retry after {}ms",
+        retry_after.as_millis()
+    )]
+    MutationRateLimited {
+        per_minute: u32,
+        retry_after: Duration,
+    },
+
+    #[error(
+        "Function {name} cannot be executed: {script_type} functions are not supported: {reason}"
+    )]
+    UnsupportedScriptType {
+        name: String,
+        script_type: String,
+        reason: String,
+    },
+
+    #[error("OCI artifact not found: {reference}")]
+    OciArtifactNotFound { reference: String },
+
+    #[error("Digest mismatch for OCI artifact {reference}: expected {expected}, got {actual}")]
+    OciDigestMismatch {
+        reference: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Failed to pull OCI artifact {reference}: {reason}")]
+    OciPullFailed { reference: String, reason: String },
+
+    #[error("Server is overloaded: {reason}; retry after {}ms", retry_after.as_millis())]
+    Overloaded {
+        reason: String,
+        retry_after: Duration,
+    },
+
+    #[error("Input is {size} bytes, exceeding the limit of {limit} bytes")]
+    InputTooLarge { size: usize, limit: usize },
+
+    #[error("The console is disabled; set console.enabled = true to enable it")]
+    ConsoleDisabled,
+
+    #[error(
+        "Too many console executions (at most {per_minute} per minute); retry after {}ms",
+        retry_after.as_millis()
+    )]
+    ConsoleRateLimited {
+        per_minute: u32,
+        retry_after: Duration,
+    },
+
+    #[error("Console execution not found or no longer retained: {id}")]
+    ConsoleExecutionNotFound { id: String },
+}
+
+impl FluxError {
+    /// 失败的归属：用户代码、平台、限制还是输入
+    pub fn origin(&self) -> ErrorOrigin {
+        match self {
+            Self::Runtime(_)
+            | Self::OutputSchemaViolation { .. }
+            | Self::ReturnTypeMismatch { .. }
+            | Self::EgressDenied { .. }
+            | Self::StateVersionConflict { .. }
+            | Self::StateReadOnly { .. } => ErrorOrigin::UserCode,
+            Self::Timeout
+            | Self::ResourceLimitExceeded { .. }
+            | Self::DiskWriteLimitExceeded { .. }
+            | Self::CpuTimeLimitExceeded { .. }
+            | Self::QueueFull { .. }
+            | Self::NamespaceQuotaExceeded { .. }
+            | Self::StateLimitExceeded { .. }
+            | Self::EgressLimitExceeded { .. }
+            | Self::CircuitOpen { .. }
+            | Self::Overloaded { .. }
+            | Self::CodeTooLarge { .. }
+            | Self::PinLimitExceeded { .. }
+            | Self::RegistryFull { .. }
+            | Self::MutationRateLimited { .. }
+            | Self::InputTooLarge { .. }
+            | Self::ConsoleRateLimited { .. } => ErrorOrigin::Limit,
+            Self::ValidationError { .. }
+            | Self::InputSchemaViolation { .. }
+            | Self::Serialization(_)
+            | Self::FunctionNotFound { .. }
+            | Self::FunctionSunset { .. }
+            | Self::VersionNotFound { .. }
+            | Self::NamespaceNotFound { .. }
+            | Self::InvocationLogConflict { .. } => ErrorOrigin::Input,
+            _ => ErrorOrigin::Platform,
+        }
+    }
+
+    /// 被注册表护栏拒绝的变更（函数数达到上限或变更速率超限）
+    pub fn is_registry_limit(&self) -> bool {
+        matches!(
+            self,
+            Self::RegistryFull { .. } | Self::MutationRateLimited { .. }
+        )
+    }
 }
 
 pub type Result<T> = std::result::Result<T, FluxError>;
@@ -135,6 +857,42 @@ impl FunctionMetadata {
             dependencies: Vec::new(),
             parameters: Vec::new(),
             return_type: "serde_json::Value".to_string(),
+            parameters_inferred: false,
+            return_type_inferred: false,
+            retry_policy: None,
+            idempotent: true,
+            labels: HashMap::new(),
+            webhooks: None,
+            priority: Priority::Normal,
+            package: None,
+            http_response: false,
+            input_template: None,
+            revision: 0,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: false,
+            coerce_output: false,
+            strict_return_type: false,
+            log_redaction: Vec::new(),
+            circuit_breaker: true,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: false,
+            scratch_limit_mb: None,
+            persist_scratch: false,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: Vec::new(),
+            execution_hint: ExecutionHint::Auto,
+            mirror: None,
+            canary: None,
+            slo: None,
+            documentation: None,
+            header_fields: BTreeMap::new(),
+            http_routes: Vec::new(),
+            deprecation: None,
+            egress: None,
+            debug_capture: DebugCapture::Off,
         }
     }
 
@@ -186,13 +944,125 @@ impl FunctionMetadata {
         self.updated_at = Utc::now();
     }
 
+    /// 用于 API 响应的副本，webhook 密钥替换为占位符；函数包文件内容不随元数据返回
+    /// （详情接口单独列出文件树）
+    pub fn redacted(mut self) -> Self {
+        self.webhooks = self.webhooks.map(|webhooks| webhooks.redacted());
+        self.package = None;
+        self
+    }
+
+    /// 应用元数据更新
+    pub fn apply_update(&mut self, req: UpdateFunctionRequest) {
+        if let Some(description) = req.description {
+            self.description = description;
+        }
+        if let Some(timeout_ms) = req.timeout_ms {
+            self.timeout_ms = timeout_ms;
+        }
+        if let Some(version) = req.version {
+            self.version = version;
+        }
+        if let Some(labels) = req.labels {
+            self.labels = labels;
+        }
+        if let Some(retry_policy) = req.retry_policy {
+            self.retry_policy = Some(retry_policy);
+        }
+        if let Some(idempotent) = req.idempotent {
+            self.idempotent = idempotent;
+        }
+        if let Some(priority) = req.priority {
+            self.priority = priority;
+        }
+        if let Some(http_response) = req.http_response {
+            self.http_response = http_response;
+        }
+        if let Some(template) = req.input_template {
+            self.input_template = (!template.is_empty()).then_some(template);
+        }
+        let is_empty =
+            |schema: &serde_json::Value| schema.as_object().is_some_and(|o| o.is_empty());
+        if let Some(schema) = req.input_schema {
+            self.input_schema = (!is_empty(&schema)).then_some(schema);
+        }
+        if let Some(schema) = req.output_schema {
+            self.output_schema = (!is_empty(&schema)).then_some(schema);
+        }
+        if let Some(strict) = req.strict_output_schema {
+            self.strict_output_schema = strict;
+        }
+        if let Some(coerce) = req.coerce_output {
+            self.coerce_output = coerce;
+        }
+        if let Some(strict) = req.strict_return_type {
+            self.strict_return_type = strict;
+        }
+        if let Some(log_redaction) = req.log_redaction {
+            self.log_redaction = log_redaction;
+        }
+        if let Some(circuit_breaker) = req.circuit_breaker {
+            self.circuit_breaker = circuit_breaker;
+        }
+        if let Some(quota) = req.resource_quota {
+            self.resource_quota = (!quota.is_empty()).then_some(quota);
+        }
+        if let Some(requires_isolation) = req.requires_isolation {
+            self.requires_isolation = requires_isolation;
+        }
+        if let Some(limit) = req.scratch_limit_mb {
+            self.scratch_limit_mb = (limit > 0).then_some(limit);
+        }
+        if let Some(limit) = req.max_disk_write_mb {
+            self.max_disk_write_mb = (limit > 0).then_some(limit);
+        }
+        if let Some(limit) = req.max_cpu_time_ms {
+            self.max_cpu_time_ms = (limit > 0).then_some(limit);
+        }
+        if let Some(persist) = req.persist_scratch {
+            self.persist_scratch = persist;
+        }
+        if let Some(tests) = req.tests {
+            self.tests = tests;
+        }
+        if let Some(hint) = req.execution_hint {
+            self.execution_hint = hint;
+        }
+        if let Some(egress) = req.egress {
+            self.egress = (!egress.allow.is_empty()).then_some(egress);
+        }
+        if let Some(debug_capture) = req.debug_capture {
+            self.debug_capture = debug_capture;
+        }
+        self.updated_at = Utc::now();
+    }
+
+    /// 函数所属的命名空间
+    pub fn namespace(&self) -> &str {
+        name::split_qualified(&self.name).0
+    }
+
+    /// 不带命名空间前缀的函数名
+    pub fn short_name(&self) -> &str {
+        name::split_qualified(&self.name).1
+    }
+
     pub fn from_request(req: RegisterFunctionRequest) -> Self {
         let now = Utc::now();
-        Self {
+        let name = req.qualified_name();
+        let package = req.files.map(|files| FunctionPackage {
+            entrypoint: req.entrypoint.unwrap_or_default(),
+            files,
+        });
+        let code = match package.as_ref().and_then(FunctionPackage::entry_source) {
+            Some(entry) => entry.to_string(),
+            None => req.code,
+        };
+        let mut function = Self {
             id: scru128::new(),
-            name: req.name,
+            name,
             description: req.description.unwrap_or_default(),
-            code: req.code,
+            code,
             created_at: now,
             updated_at: now,
             timeout_ms: req.timeout_ms.unwrap_or(5000),
@@ -202,6 +1072,44 @@ impl FunctionMetadata {
             return_type: req
                 .return_type
                 .unwrap_or_else(|| "serde_json::Value".to_string()),
-        }
+            retry_policy: req.retry_policy,
+            idempotent: req.idempotent.unwrap_or(true),
+            labels: req.labels.unwrap_or_default(),
+            webhooks: req.webhooks,
+            priority: req.priority.unwrap_or_default(),
+            package,
+            http_response: req.http_response.unwrap_or(false),
+            input_template: req.input_template,
+            revision: 0,
+            input_schema: req.input_schema,
+            output_schema: req.output_schema,
+            strict_output_schema: req.strict_output_schema.unwrap_or(false),
+            coerce_output: req.coerce_output.unwrap_or(false),
+            strict_return_type: req.strict_return_type.unwrap_or(false),
+            log_redaction: req.log_redaction.unwrap_or_default(),
+            circuit_breaker: req.circuit_breaker.unwrap_or(true),
+            runtime: req.runtime,
+            resource_quota: req.resource_quota,
+            requires_isolation: req.requires_isolation.unwrap_or(false),
+            scratch_limit_mb: req.scratch_limit_mb.filter(|limit| *limit > 0),
+            persist_scratch: req.persist_scratch.unwrap_or(false),
+            max_disk_write_mb: req.max_disk_write_mb.filter(|limit| *limit > 0),
+            max_cpu_time_ms: req.max_cpu_time_ms.filter(|limit| *limit > 0),
+            tests: req.tests.unwrap_or_default(),
+            execution_hint: req.execution_hint.unwrap_or_default(),
+            mirror: None,
+            canary: req.canary,
+            slo: req.slo,
+            documentation: req.documentation,
+            header_fields: BTreeMap::new(),
+            http_routes: Vec::new(),
+            deprecation: None,
+            egress: req.egress,
+            debug_capture: req.debug_capture.unwrap_or_default(),
+            parameters_inferred: false,
+            return_type_inferred: false,
+        };
+        inference::fill_missing(&mut function);
+        function
     }
 }
@@ -0,0 +1,185 @@
+use super::{FluxError, Result};
+
+/// 函数名最大长度
+pub const MAX_FUNCTION_NAME_LEN: usize = 64;
+
+/// 保留名称 - 与网关路由段或系统功能冲突，不允许作为函数名
+pub const RESERVED_FUNCTION_NAMES: &[&str] = &[
+    "health",
+    "status",
+    "functions",
+    "invoke",
+    "load",
+    "cache",
+    "performance",
+    "reset",
+    "admin",
+    "system",
+    "flux",
+    "export",
+    "import",
+    "namespaces",
+];
+
+/// 默认命名空间：不带前缀的函数名属于该命名空间
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// 限定名中命名空间与函数名的分隔符（`team-a/hello`）
+pub const NAMESPACE_SEPARATOR: char = '/';
+
+/// 命名空间内的函数的限定名；默认命名空间的函数不带前缀
+pub fn qualify(namespace: &str, name: &str) -> String {
+    if namespace == DEFAULT_NAMESPACE {
+        name.to_string()
+    } else {
+        format!("{namespace}{NAMESPACE_SEPARATOR}{name}")
+    }
+}
+
+/// 把限定名拆分为（命名空间，函数名），不带前缀的名称属于默认命名空间
+pub fn split_qualified(name: &str) -> (&str, &str) {
+    name.split_once(NAMESPACE_SEPARATOR)
+        .unwrap_or((DEFAULT_NAMESPACE, name))
+}
+
+/// 标识符规则：ASCII 字母或 `_` 开头，只含 ASCII 字母、数字、`_` 和 `-`，最长 64 个字符
+fn check_identifier(value: &str) -> std::result::Result<(), String> {
+    let mut chars = value.chars();
+    let Some(first) = chars.next() else {
+        return Err("name must not be empty".to_string());
+    };
+
+    if value.len() > MAX_FUNCTION_NAME_LEN {
+        return Err(format!(
+            "name must be at most {MAX_FUNCTION_NAME_LEN} characters"
+        ));
+    }
+
+    if !(first.is_ascii_alphabetic() || first == '_') {
+        return Err("name must start with an ASCII letter or '_'".to_string());
+    }
+
+    if let Some(c) = chars.find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))) {
+        return Err(format!(
+            "character {c:?} is not allowed (only ASCII letters, digits, '_' and '-')"
+        ));
+    }
+    Ok(())
+}
+
+/// 校验命名空间名（规则与函数名相同，但没有保留名称）
+pub fn parse_namespace(namespace: &str) -> Result<&str> {
+    check_identifier(namespace).map_err(|rule| FluxError::ValidationError {
+        reason: format!("Invalid namespace '{namespace}': {rule}"),
+    })?;
+    Ok(namespace)
+}
+
+/// 经过校验的函数名
+///
+/// 规则：`^[a-zA-Z_][a-zA-Z0-9_\-]{0,63}$`，且不能是保留名称。
+/// 其他命名空间的函数使用 `<命名空间>/<函数名>` 形式的限定名，两部分分别按上述规则校验；
+/// 默认命名空间的函数不带前缀。
+/// 函数名保留原始大小写，但唯一性按小写比较：`Foo` 与 `foo` 视为同一个函数名，
+/// 不能同时注册；调用和查询仍需使用注册时的原始名称。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionName(String);
+
+impl FunctionName {
+    /// 校验并创建函数名（可以是限定名）
+    pub fn parse(name: &str) -> Result<Self> {
+        let invalid = |rule: String| FluxError::ValidationError {
+            reason: format!("Invalid function name '{name}': {rule}"),
+        };
+
+        let short = match name.split_once(NAMESPACE_SEPARATOR) {
+            Some((namespace, short)) => {
+                check_identifier(namespace).map_err(|rule| invalid(format!("namespace {rule}")))?;
+                if namespace == DEFAULT_NAMESPACE {
+                    return Err(invalid(format!(
+                        "functions in the '{DEFAULT_NAMESPACE}' namespace are named without a prefix"
+                    )));
+                }
+                short
+            }
+            None => name,
+        };
+        check_identifier(short).map_err(invalid)?;
+
+        if let Some(reserved) = RESERVED_FUNCTION_NAMES
+            .iter()
+            .find(|reserved| reserved.eq_ignore_ascii_case(short))
+        {
+            return Err(invalid(format!("'{reserved}' is a reserved name")));
+        }
+
+        Ok(Self(name.to_string()))
+    }
+
+    /// 原始函数名
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// 用于唯一性比较的规范化形式（小写）
+    pub fn normalized(&self) -> String {
+        self.0.to_ascii_lowercase()
+    }
+}
+
+impl std::fmt::Display for FunctionName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_function_name_rules() {
+        assert!(FunctionName::parse("hello").is_ok());
+        assert!(FunctionName::parse("_private-fn_2").is_ok());
+        assert!(FunctionName::parse(&"a".repeat(64)).is_ok());
+
+        for bad in [
+            "",
+            "../../etc/passwd",
+            "has space",
+            "1starts_with_digit",
+            "-dash",
+            "Health",
+            "invoke",
+        ] {
+            assert!(
+                FunctionName::parse(bad).is_err(),
+                "{bad:?} should be rejected"
+            );
+        }
+
+        let err = FunctionName::parse(&"a".repeat(65)).unwrap_err();
+        assert!(err.to_string().contains("at most 64"));
+
+        // 限定名：命名空间和函数名分别校验
+        assert!(FunctionName::parse("team-a/hello").is_ok());
+        for bad in [
+            "team-a/",
+            "/hello",
+            "a/b/c",
+            "default/hello",
+            "team-a/invoke",
+        ] {
+            assert!(
+                FunctionName::parse(bad).is_err(),
+                "{bad:?} should be rejected"
+            );
+        }
+        assert_eq!(qualify(DEFAULT_NAMESPACE, "hello"), "hello");
+        assert_eq!(
+            split_qualified(&qualify("team-a", "hello")),
+            ("team-a", "hello")
+        );
+        assert_eq!(split_qualified("hello"), (DEFAULT_NAMESPACE, "hello"));
+    }
+}
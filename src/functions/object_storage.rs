@@ -0,0 +1,463 @@
+//! 基于 `object_store` 的函数目录后端（S3、GCS、Azure 和本地目录）
+//!
+//! 每个函数一个 JSON 对象（`<前缀>/<函数名>.json`，内容为 [`FunctionRecord`]）。写入使用
+//! 对象存储的条件写入：新函数以“对象不存在”为条件，更新以最后读到的 ETag/版本为条件，
+//! 多个副本共享同一前缀时不会互相覆盖。不支持条件更新的后端（本地目录）退化为先比较修订号再覆盖。
+
+use super::storage::{FunctionRecord, RegistryStorage, check_stored_revision};
+use super::{FluxError, Result};
+use futures_util::{StreamExt, TryStreamExt};
+use object_store::path::Path;
+use object_store::{ObjectStore, PutMode, PutOptions, PutPayload, UpdateVersion};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+/// 启动时并发读取的对象数
+pub const DEFAULT_LOAD_CONCURRENCY: usize = 16;
+
+/// 暂时性错误的重试次数和首次退避时间（之后每次翻倍）
+#[derive(Debug, Clone, Copy)]
+pub struct RetrySettings {
+    pub max_retries: u32,
+    pub backoff: Duration,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+/// 对象存储上的函数目录
+#[derive(Debug)]
+pub struct ObjectStoreStorage {
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+    /// 函数名 -> 最近一次读写的修订号和对象版本，作为条件更新的前提
+    versions: StdMutex<HashMap<String, (u64, UpdateVersion)>>,
+    load_concurrency: usize,
+    retry: RetrySettings,
+}
+
+impl ObjectStoreStorage {
+    pub fn new(store: Arc<dyn ObjectStore>, prefix: Path) -> Self {
+        Self {
+            store,
+            prefix,
+            versions: StdMutex::default(),
+            load_concurrency: DEFAULT_LOAD_CONCURRENCY,
+            retry: RetrySettings::default(),
+        }
+    }
+
+    /// 按 URL 创建（`s3://bucket/prefix`、`gs://…`、`az://…`、`file:///path`、`memory:///`），
+    /// `options` 为后端的配置项（区域、凭据、端点等），缺省项从环境变量读取
+    pub fn from_url(url: &str, options: &BTreeMap<String, String>) -> anyhow::Result<Self> {
+        let parsed =
+            url::Url::parse(url).map_err(|e| anyhow::anyhow!("Invalid storage url {url}: {e}"))?;
+        let (store, prefix) = object_store::parse_url_opts(&parsed, options)?;
+        Ok(Self::new(Arc::from(store), prefix))
+    }
+
+    /// 同一后端下的子前缀（各调度器配置的函数目录互不重叠）
+    pub fn scoped(&self, segment: &str) -> Self {
+        Self {
+            prefix: self.prefix.child(segment),
+            ..Self::new(self.store.clone(), self.prefix.clone())
+        }
+        .with_load_concurrency(self.load_concurrency)
+        .with_retry(self.retry)
+    }
+
+    /// 启动时并发读取的对象数（默认 16）
+    pub fn with_load_concurrency(mut self, load_concurrency: usize) -> Self {
+        self.load_concurrency = load_concurrency.max(1);
+        self
+    }
+
+    /// 暂时性错误的重试次数和退避时间
+    pub fn with_retry(mut self, retry: RetrySettings) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn location(&self, name: &str) -> Path {
+        self.prefix.child(format!("{name}.json"))
+    }
+
+    fn remember(&self, name: &str, revision: u64, version: UpdateVersion) {
+        self.versions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.to_string(), (revision, version));
+    }
+
+    fn forget(&self, name: &str) {
+        self.versions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(name);
+    }
+
+    /// 最近一次读写时记下的对象版本（修订号一致时才可用）
+    fn remembered(&self, name: &str, revision: u64) -> Option<UpdateVersion> {
+        match self
+            .versions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+        {
+            Some((remembered, version)) if *remembered == revision => Some(version.clone()),
+            _ => None,
+        }
+    }
+
+    /// 执行操作，暂时性错误按退避重试，返回最后一次的结果
+    async fn with_retries<T, F, Fut>(
+        &self,
+        operation: &str,
+        mut attempt: F,
+    ) -> object_store::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = object_store::Result<T>>,
+    {
+        let mut retries = 0;
+        loop {
+            match attempt().await {
+                Err(e) if is_transient(&e) && retries < self.retry.max_retries => {
+                    let backoff = self.retry.backoff * 2u32.saturating_pow(retries);
+                    tracing::warn!(
+                        "Function storage {} failed, retrying in {:?}: {}",
+                        operation,
+                        backoff,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// 读取一个对象并记下其版本；对象不存在时为 `None`
+    async fn read(&self, location: &Path) -> Result<Option<FunctionRecord>> {
+        let fetched = self
+            .with_retries("read", || async {
+                let result = self.store.get(location).await?;
+                let meta = result.meta.clone();
+                Ok((meta, result.bytes().await?))
+            })
+            .await;
+        let (meta, bytes) = match fetched {
+            Ok(fetched) => fetched,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(storage_error("read", e)),
+        };
+        let record: FunctionRecord = serde_json::from_slice(&bytes).map_err(|e| {
+            FluxError::StorageError(format!("Failed to parse function record {location}: {e}"))
+        })?;
+        let record = record
+            .decompressed()
+            .map_err(|e| FluxError::StorageError(format!("{e:#}")))?;
+        self.remember(
+            &record.metadata.name,
+            record.metadata.revision,
+            UpdateVersion {
+                e_tag: meta.e_tag,
+                version: meta.version,
+            },
+        );
+        Ok(Some(record))
+    }
+
+    async fn current_revision(&self, name: &str) -> Result<Option<u64>> {
+        Ok(self
+            .read(&self.location(name))
+            .await?
+            .map(|record| record.metadata.revision))
+    }
+
+    async fn write(
+        &self,
+        location: &Path,
+        payload: &PutPayload,
+        mode: PutMode,
+    ) -> object_store::Result<object_store::PutResult> {
+        let options = PutOptions::from(mode);
+        self.with_retries("write", || {
+            self.store
+                .put_opts(location, payload.clone(), options.clone())
+        })
+        .await
+    }
+
+    /// 条件写入失败后后端中的修订号（不存在时为 0）；已是本次写入的内容时为 `None`
+    /// （重试前的请求可能已经成功）
+    async fn conflicting_revision(&self, record: &FunctionRecord) -> Result<Option<u64>> {
+        let stored = self.read(&self.location(&record.metadata.name)).await?;
+        Ok(match stored {
+            Some(stored)
+                if stored.metadata.revision == record.metadata.revision
+                    && stored.checksum == record.checksum =>
+            {
+                None
+            }
+            Some(stored) => Some(stored.metadata.revision),
+            None => Some(0),
+        })
+    }
+}
+
+/// 网络、服务端等可重试的错误
+fn is_transient(e: &object_store::Error) -> bool {
+    matches!(
+        e,
+        object_store::Error::Generic { .. } | object_store::Error::JoinError { .. }
+    )
+}
+
+fn storage_error(operation: &str, e: object_store::Error) -> FluxError {
+    if is_transient(&e) {
+        FluxError::StorageUnavailable {
+            reason: format!("{operation}: {e}"),
+        }
+    } else {
+        FluxError::StorageError(format!("Function storage {operation} failed: {e}"))
+    }
+}
+
+#[async_trait::async_trait]
+impl RegistryStorage for ObjectStoreStorage {
+    async fn load_all(&self) -> Result<Vec<FunctionRecord>> {
+        let objects: Vec<Path> = self
+            .with_retries("list", || {
+                self.store
+                    .list(Some(&self.prefix))
+                    .map_ok(|meta| meta.location)
+                    .try_collect()
+            })
+            .await
+            .map_err(|e| storage_error("list", e))?;
+        // 子前缀（其他调度器配置的目录）中的对象不属于本目录
+        let depth = self.prefix.parts().count() + 1;
+        let objects = objects.into_iter().filter(|location| {
+            location.parts().count() == depth && location.extension() == Some("json")
+        });
+
+        let loaded: Vec<Result<Option<FunctionRecord>>> = futures_util::stream::iter(objects)
+            .map(|location| async move {
+                self.read(&location).await.or_else(|e| match e {
+                    // 损坏的记录跳过，其他记录照常加载
+                    FluxError::StorageError(reason) => {
+                        tracing::warn!("Skipping function record {}: {}", location, reason);
+                        Ok(None)
+                    }
+                    e => Err(e),
+                })
+            })
+            .buffer_unordered(self.load_concurrency)
+            .collect()
+            .await;
+        let mut records = Vec::new();
+        for record in loaded {
+            records.extend(record?);
+        }
+        records.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+        Ok(records)
+    }
+
+    async fn put(&self, record: &FunctionRecord, expected_revision: Option<u64>) -> Result<()> {
+        let name = &record.metadata.name;
+        let location = self.location(name);
+        let mode = match expected_revision {
+            None => PutMode::Create,
+            Some(expected) => {
+                if self.remembered(name, expected).is_none() {
+                    // 没有读过或已被其他副本更新：读取当前对象作为条件
+                    let current = self.current_revision(name).await?;
+                    check_stored_revision(name, current, Some(expected))?;
+                }
+                match self.remembered(name, expected) {
+                    Some(version) => PutMode::Update(version),
+                    None => PutMode::Overwrite,
+                }
+            }
+        };
+        let payload = PutPayload::from(serde_json::to_vec(record)?);
+
+        let result = match self.write(&location, &payload, mode).await {
+            // 后端不支持条件更新（本地目录）：比较修订号后直接覆盖
+            Err(object_store::Error::NotImplemented) => {
+                let current = self.current_revision(name).await?;
+                check_stored_revision(name, current, expected_revision)?;
+                self.write(&location, &payload, PutMode::Overwrite).await
+            }
+            result => result,
+        };
+        match result {
+            Ok(written) => {
+                self.remember(
+                    name,
+                    record.metadata.revision,
+                    UpdateVersion {
+                        e_tag: written.e_tag,
+                        version: written.version,
+                    },
+                );
+                Ok(())
+            }
+            Err(
+                e @ (object_store::Error::AlreadyExists { .. }
+                | object_store::Error::Precondition { .. }),
+            ) => {
+                // 条件不满足：其他副本先写入了该函数
+                let Some(current) = self.conflicting_revision(record).await? else {
+                    return Ok(());
+                };
+                match expected_revision {
+                    None if current != 0 => {
+                        Err(FluxError::FunctionAlreadyExists { name: name.clone() })
+                    }
+                    Some(expected) => Err(FluxError::RevisionMismatch {
+                        name: name.clone(),
+                        expected,
+                        current,
+                    }),
+                    None => Err(storage_error("write", e)),
+                }
+            }
+            Err(e) => Err(storage_error("write", e)),
+        }
+    }
+
+    async fn delete(&self, name: &str, expected_revision: Option<u64>) -> Result<bool> {
+        let Some(current) = self.current_revision(name).await? else {
+            return Ok(false);
+        };
+        if expected_revision.is_some() {
+            check_stored_revision(name, Some(current), expected_revision)?;
+        }
+        let location = self.location(name);
+        match self
+            .with_retries("delete", || self.store.delete(&location))
+            .await
+        {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+            Err(e) => return Err(storage_error("delete", e)),
+        }
+        self.forget(name);
+        Ok(true)
+    }
+
+    async fn get_revision(&self, name: &str) -> Result<Option<u64>> {
+        self.current_revision(name).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::FunctionMetadata;
+    use object_store::memory::InMemory;
+
+    fn record(name: &str, code: &str, revision: u64) -> FunctionRecord {
+        let mut function = FunctionMetadata::new(name.to_string(), code.to_string());
+        function.revision = revision;
+        FunctionRecord::from_metadata(&function)
+    }
+
+    #[tokio::test]
+    async fn test_conditional_writes_between_replicas() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let a = ObjectStoreStorage::new(store.clone(), Path::from("catalog"));
+        let b = ObjectStoreStorage::new(store.clone(), Path::from("catalog"));
+
+        a.put(&record("sum", "return a + b", 1), None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            b.put(&record("sum", "return a", 1), None).await,
+            Err(FluxError::FunctionAlreadyExists { .. })
+        ));
+
+        // 两个副本都基于修订号 1 更新，后写入的一方被拒绝
+        assert_eq!(b.get_revision("sum").await.unwrap(), Some(1));
+        a.put(&record("sum", "return a * b", 2), Some(1))
+            .await
+            .unwrap();
+        assert!(matches!(
+            b.put(&record("sum", "return a - b", 3), Some(1)).await,
+            Err(FluxError::RevisionMismatch {
+                expected: 1,
+                current: 2,
+                ..
+            })
+        ));
+
+        let loaded = b.load_all().await.unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].clone().into_metadata().code, "return a * b");
+
+        assert!(matches!(
+            b.delete("sum", Some(1)).await,
+            Err(FluxError::RevisionMismatch { .. })
+        ));
+        assert!(b.delete("sum", Some(2)).await.unwrap());
+        assert!(!a.delete("sum", None).await.unwrap());
+        assert_eq!(a.get_revision("sum").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_load_all_ignores_other_profiles_and_corrupt_records() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let root = ObjectStoreStorage::new(store.clone(), Path::from("catalog"));
+        let default = root.scoped("default").with_load_concurrency(2);
+        let other = root.scoped("untrusted");
+        for i in 0..10 {
+            default
+                .put(&record(&format!("f{i}"), "return input", 1), None)
+                .await
+                .unwrap();
+        }
+        other
+            .put(&record("g", "return input", 1), None)
+            .await
+            .unwrap();
+        store
+            .put(&Path::from("catalog/default/broken.json"), "{".into())
+            .await
+            .unwrap();
+
+        let loaded = default.load_all().await.unwrap();
+        assert_eq!(loaded.len(), 10);
+        assert_eq!(loaded[0].metadata.name, "f0");
+        assert_eq!(other.load_all().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_local_directory_falls_back_to_revision_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("file://{}", dir.path().display());
+        let storage = ObjectStoreStorage::from_url(&url, &BTreeMap::new()).unwrap();
+        storage
+            .put(&record("sum", "return a + b", 1), None)
+            .await
+            .unwrap();
+        storage
+            .put(&record("sum", "return a * b", 2), Some(1))
+            .await
+            .unwrap();
+        assert!(matches!(
+            storage.put(&record("sum", "return a", 3), Some(1)).await,
+            Err(FluxError::RevisionMismatch { .. })
+        ));
+        assert_eq!(storage.get_revision("sum").await.unwrap(), Some(2));
+    }
+}
@@ -0,0 +1,225 @@
+use super::{FluxError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Component, Path};
+use utoipa::ToSchema;
+
+/// 函数包的总大小上限（所有文件内容之和，4 MiB）
+pub const MAX_PACKAGE_BYTES: usize = 4 * 1024 * 1024;
+
+/// 函数包的文件数上限
+pub const MAX_PACKAGE_FILES: usize = 256;
+
+/// 函数包中的单个文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PackageFile {
+    /// 相对于包根目录的路径，例如 `lib/util.py`
+    pub path: String,
+    pub content: String,
+}
+
+/// 文件树中的条目（不含文件内容）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PackageEntry {
+    pub path: String,
+    pub size: usize,
+}
+
+/// 函数包的文件树
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PackageTree {
+    pub entrypoint: String,
+    pub total_size: usize,
+    pub files: Vec<PackageEntry>,
+}
+
+/// 多文件函数包：入口文件及其辅助模块，与元数据一起保存
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FunctionPackage {
+    /// 入口文件路径，必须是 `files` 中的一个
+    pub entrypoint: String,
+    pub files: Vec<PackageFile>,
+}
+
+/// 校验包内文件路径：必须是相对路径，且不能包含 `..` 等跳出包目录的部分
+pub fn validate_package_path(path: &str) -> Result<()> {
+    let invalid = |reason: &str| {
+        Err(FluxError::ValidationError {
+            reason: format!("Invalid package file path '{path}': {reason}"),
+        })
+    };
+    if path.is_empty() {
+        return invalid("path is empty");
+    }
+    if path.contains('\\') || path.contains('\0') {
+        return invalid("path contains a backslash or NUL");
+    }
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(_) => {}
+            Component::CurDir => return invalid("path contains '.'"),
+            Component::ParentDir => return invalid("path escapes the package directory"),
+            Component::RootDir | Component::Prefix(_) => return invalid("path must be relative"),
+        }
+    }
+    Ok(())
+}
+
+impl FunctionPackage {
+    /// 校验路径、重复文件、入口文件和总大小
+    pub fn validate(&self) -> Result<()> {
+        if self.files.is_empty() {
+            return Err(FluxError::ValidationError {
+                reason: "Package has no files".to_string(),
+            });
+        }
+        if self.files.len() > MAX_PACKAGE_FILES {
+            return Err(FluxError::ValidationError {
+                reason: format!(
+                    "Package has {} files, more than the limit of {MAX_PACKAGE_FILES}",
+                    self.files.len()
+                ),
+            });
+        }
+
+        let mut seen = HashSet::new();
+        for file in &self.files {
+            validate_package_path(&file.path)?;
+            if !seen.insert(file.path.as_str()) {
+                return Err(FluxError::ValidationError {
+                    reason: format!("Duplicate package file path '{}'", file.path),
+                });
+            }
+        }
+        if !seen.contains(self.entrypoint.as_str()) {
+            return Err(FluxError::ValidationError {
+                reason: format!(
+                    "Package entrypoint '{}' is not one of the package files",
+                    self.entrypoint
+                ),
+            });
+        }
+
+        let total = self.total_size();
+        if total > MAX_PACKAGE_BYTES {
+            return Err(FluxError::ValidationError {
+                reason: format!(
+                    "Package size {total} bytes exceeds the limit of {MAX_PACKAGE_BYTES} bytes"
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// 所有文件内容的总字节数
+    pub fn total_size(&self) -> usize {
+        self.files.iter().map(|file| file.content.len()).sum()
+    }
+
+    /// 入口文件内容
+    pub fn entry_source(&self) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|file| file.path == self.entrypoint)
+            .map(|file| file.content.as_str())
+    }
+
+    /// 所有文件的内容哈希（按路径排序，与声明顺序无关）
+    pub fn content_hash(&self) -> String {
+        let mut files: Vec<_> = self.files.iter().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut context = md5::Context::new();
+        context.consume(self.entrypoint.as_bytes());
+        for file in files {
+            context.consume([0]);
+            context.consume(file.path.as_bytes());
+            context.consume([0]);
+            context.consume(file.content.as_bytes());
+        }
+        format!("{:x}", context.compute())
+    }
+
+    /// 文件树（按路径排序，不含内容）
+    pub fn tree(&self) -> PackageTree {
+        let mut files: Vec<_> = self
+            .files
+            .iter()
+            .map(|file| PackageEntry {
+                path: file.path.clone(),
+                size: file.content.len(),
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        PackageTree {
+            entrypoint: self.entrypoint.clone(),
+            total_size: self.total_size(),
+            files,
+        }
+    }
+
+    /// 把所有文件写到 `dir` 下（保留目录结构），调用方需先通过 `validate`
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<()> {
+        for file in &self.files {
+            let path = dir.join(&file.path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, &file.content)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(paths: &[&str]) -> FunctionPackage {
+        FunctionPackage {
+            entrypoint: paths[0].to_string(),
+            files: paths
+                .iter()
+                .map(|path| PackageFile {
+                    path: path.to_string(),
+                    content: format!("// {path}"),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_package_validation() {
+        let valid = package(&["main.py", "lib/util.py"]);
+        assert!(valid.validate().is_ok());
+        let tree = valid.tree();
+        assert_eq!(tree.files[0].path, "lib/util.py");
+        assert_eq!(tree.total_size, valid.total_size());
+
+        for bad in [
+            "../escape.py",
+            "/etc/passwd",
+            "lib/../../x.py",
+            "./main.py",
+            "",
+        ] {
+            assert!(
+                package(&["main.py", bad]).validate().is_err(),
+                "accepted {bad:?}"
+            );
+        }
+        assert!(package(&["main.py", "main.py"]).validate().is_err());
+
+        let mut missing_entry = valid.clone();
+        missing_entry.entrypoint = "other.py".to_string();
+        assert!(missing_entry.validate().is_err());
+
+        let mut oversized = valid.clone();
+        oversized.files[1].content = "x".repeat(MAX_PACKAGE_BYTES);
+        assert!(oversized.validate().is_err());
+
+        // 哈希与文件声明顺序无关
+        let mut reordered = valid.clone();
+        reordered.files.reverse();
+        assert_eq!(valid.content_hash(), reordered.content_hash());
+    }
+}
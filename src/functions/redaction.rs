@@ -0,0 +1,365 @@
+use super::{FluxError, FunctionMetadata, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 被脱敏的值替换成的占位符
+pub const REDACTED: &str = "[REDACTED]";
+
+/// 从自由文本（错误信息、标准错误输出）中抹去的敏感值的最小长度，
+/// 更短的值只在结构化数据中脱敏，避免误伤普通文本
+pub const MIN_SCRUB_LEN: usize = 4;
+
+/// 日志配置（`[logging]`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// 日志中输入、输出和进程输出的字节上限，超出部分截断并以省略标记结尾
+    pub max_payload_bytes: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            max_payload_bytes: DEFAULT_MAX_PAYLOAD_BYTES,
+        }
+    }
+}
+
+const DEFAULT_MAX_PAYLOAD_BYTES: usize = 512;
+
+static MAX_PAYLOAD_BYTES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_PAYLOAD_BYTES);
+
+/// 设置日志配置（进程级开关，启动时根据配置设置）
+pub fn set_logging_config(config: &LoggingConfig) {
+    MAX_PAYLOAD_BYTES.store(config.max_payload_bytes, Ordering::Relaxed);
+}
+
+/// 当前日志中载荷的字节上限
+pub fn max_payload_bytes() -> usize {
+    MAX_PAYLOAD_BYTES.load(Ordering::Relaxed)
+}
+
+/// 按日志字节上限截断文本，截断时以 `…(+N bytes)` 结尾
+pub fn truncate_for_log(text: &str) -> String {
+    let budget = max_payload_bytes();
+    if text.len() <= budget {
+        return text.to_string();
+    }
+    let mut end = budget;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…(+{} bytes)", &text[..end], text.len() - end)
+}
+
+/// 日志中代替函数代码的摘要（长度和 MD5），完整代码只在 TRACE 级别记录
+pub fn code_for_log(code: &str) -> String {
+    format!("<{} bytes, md5 {:x}>", code.len(), md5::compute(code))
+}
+
+/// 脱敏路径的一段
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    /// `*`：对象的任意字段或数组的任意元素
+    Any,
+}
+
+/// 按函数的 `log_redaction` 模式脱敏载荷
+///
+/// 模式为字段名（如 `password`，匹配任意层级的同名字段）或以 `$` 开头的路径
+/// （如 `$.card.number`、`$.card.*`），路径中的字段名遇到数组时作用于每个元素。
+/// 通过 [`Redactor::with_secrets_from`] 记下调用输入中被脱敏的值后，
+/// 这些值也会从错误信息、标准错误输出等自由文本中抹去。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Redactor {
+    keys: Vec<String>,
+    paths: Vec<Vec<Segment>>,
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    /// 解析脱敏模式，模式非法时返回校验错误
+    pub fn parse(patterns: &[String]) -> Result<Self> {
+        let mut redactor = Self::default();
+        for pattern in patterns {
+            let pattern = pattern.trim();
+            let invalid = |reason: &str| FluxError::ValidationError {
+                reason: format!("Invalid log_redaction pattern '{pattern}': {reason}"),
+            };
+            if pattern.is_empty() {
+                return Err(invalid("pattern is empty"));
+            }
+            let Some(path) = pattern.strip_prefix('$') else {
+                redactor.keys.push(pattern.to_string());
+                continue;
+            };
+            let Some(path) = path.strip_prefix('.') else {
+                return Err(invalid("paths must start with '$.'"));
+            };
+            let segments = path
+                .split('.')
+                .map(|segment| match segment {
+                    "" => Err(invalid("path contains an empty segment")),
+                    "*" => Ok(Segment::Any),
+                    key => Ok(Segment::Key(key.to_string())),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            redactor.paths.push(segments);
+        }
+        Ok(redactor)
+    }
+
+    /// 函数的脱敏规则（注册时已校验，这里忽略非法模式）
+    pub fn for_function(function: &FunctionMetadata) -> Self {
+        Self::parse(&function.log_redaction).unwrap_or_else(|e| {
+            tracing::warn!(
+                "Ignoring log_redaction of function {}: {}",
+                function.name,
+                e
+            );
+            Self::default()
+        })
+    }
+
+    /// 记下 `input` 中被脱敏的值，之后从自由文本中抹去
+    pub fn with_secrets_from(mut self, input: &Value) -> Self {
+        let mut input = input.clone();
+        let mut secrets = Vec::new();
+        self.visit(&mut input, &mut |value| collect_leaves(value, &mut secrets));
+        secrets.retain(|secret| secret.len() >= MIN_SCRUB_LEN);
+        // 先替换较长的值，避免其中包含的较短值先被替换
+        secrets.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        secrets.dedup();
+        self.secrets = secrets;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.paths.is_empty()
+    }
+
+    /// 把匹配的字段替换为 [`REDACTED`]
+    pub fn redact(&self, value: &Value) -> Value {
+        let mut value = value.clone();
+        if !self.is_empty() {
+            self.visit(&mut value, &mut |value| {
+                *value = Value::String(REDACTED.to_string());
+            });
+        }
+        value
+    }
+
+    /// 从自由文本中抹去记下的敏感值
+    pub fn scrub(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret, REDACTED)
+        })
+    }
+
+    /// 用于日志的载荷：脱敏、抹去敏感值后按字节上限截断
+    pub fn log_value(&self, value: &Value) -> String {
+        truncate_for_log(&self.scrub(&self.redact(value).to_string()))
+    }
+
+    fn visit(&self, value: &mut Value, f: &mut dyn FnMut(&mut Value)) {
+        for path in &self.paths {
+            visit_path(value, path, f);
+        }
+        if !self.keys.is_empty() {
+            visit_keys(value, &self.keys, f);
+        }
+    }
+}
+
+fn visit_path(value: &mut Value, path: &[Segment], f: &mut dyn FnMut(&mut Value)) {
+    let Some((segment, rest)) = path.split_first() else {
+        f(value);
+        return;
+    };
+    match (segment, value) {
+        (Segment::Key(key), Value::Object(map)) => {
+            if let Some(value) = map.get_mut(key) {
+                visit_path(value, rest, f);
+            }
+        }
+        (Segment::Key(_), Value::Array(items)) => {
+            for item in items {
+                visit_path(item, path, f);
+            }
+        }
+        (Segment::Any, Value::Object(map)) => {
+            for value in map.values_mut() {
+                visit_path(value, rest, f);
+            }
+        }
+        (Segment::Any, Value::Array(items)) => {
+            for item in items {
+                visit_path(item, rest, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn visit_keys(value: &mut Value, keys: &[String], f: &mut dyn FnMut(&mut Value)) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if keys.contains(key) {
+                    f(value);
+                } else {
+                    visit_keys(value, keys, f);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                visit_keys(item, keys, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_leaves(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) => out.push(s.clone()),
+        Value::Number(n) => out.push(n.to_string()),
+        Value::Object(map) => map.values().for_each(|value| collect_leaves(value, out)),
+        Value::Array(items) => items.iter().for_each(|value| collect_leaves(value, out)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn redactor(patterns: &[&str]) -> Redactor {
+        Redactor::parse(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn test_redacts_keys_and_paths() {
+        let input = json!({
+            "user": "alice",
+            "password": "hunter22",
+            "nested": {"token": "tok-123", "keep": 1},
+            "card": {"number": "4111111111111111", "cvc": 123},
+            "items": [{"sku": "a", "price": 10}, {"sku": "b", "price": 20}],
+        });
+        let redactor = redactor(&["password", "token", "$.card.*", "$.items.price"]);
+        assert_eq!(
+            redactor.redact(&input),
+            json!({
+                "user": "alice",
+                "password": REDACTED,
+                "nested": {"token": REDACTED, "keep": 1},
+                "card": {"number": REDACTED, "cvc": REDACTED},
+                "items": [{"sku": "a", "price": REDACTED}, {"sku": "b", "price": REDACTED}],
+            })
+        );
+
+        // 记下的敏感值从自由文本中抹去，过短的值保留
+        let redactor = redactor.with_secrets_from(&input);
+        assert_eq!(
+            redactor.scrub("bad password hunter22 for card 4111111111111111 (cvc 123)"),
+            format!("bad password {REDACTED} for card {REDACTED} (cvc 123)")
+        );
+    }
+
+    /// 收集格式化后日志输出的写入器
+    #[derive(Clone, Default)]
+    struct Capture(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_marked_fields_never_appear_in_log_output() {
+        use crate::functions::InvokeRequest;
+        use crate::functions::webhook::WebhookEvent;
+        use crate::runtime::SimpleRuntime;
+        use crate::scheduler::webhooks::WebhookPayload;
+
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer({
+                let capture = capture.clone();
+                move || capture.clone()
+            })
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        // echo 函数原样返回输入，输入和输出都会被记录
+        let mut function = FunctionMetadata::new("echo".to_string(), String::new());
+        function.log_redaction = vec!["password".to_string(), "$.card.*".to_string()];
+        let request = InvokeRequest {
+            input: json!({
+                "user": "alice",
+                "password": "s3cret-pass",
+                "card": {"number": "4111111111111111"},
+            }),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+        let response = SimpleRuntime::new()
+            .execute(&function, &request)
+            .await
+            .unwrap();
+        assert_eq!(response.output, request.input);
+
+        let redactor = Redactor::for_function(&function).with_secrets_from(&request.input);
+        let payload = WebhookPayload::from_result(
+            "echo",
+            "inv-1",
+            Ok(&response),
+            &redactor,
+            std::time::Duration::ZERO,
+            4096,
+        );
+        assert_eq!(payload.status, WebhookEvent::Success);
+        tracing::info!(
+            "Webhook payload: {}",
+            serde_json::to_string(&payload).unwrap()
+        );
+
+        let logs = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("alice"), "{logs}");
+        assert!(logs.contains(REDACTED), "{logs}");
+        assert!(!logs.contains("s3cret-pass"), "{logs}");
+        assert!(!logs.contains("4111111111111111"), "{logs}");
+    }
+
+    #[test]
+    fn test_invalid_patterns_and_truncation() {
+        for pattern in ["", "$", "$card", "$.card..number"] {
+            assert!(
+                Redactor::parse(&[pattern.to_string()]).is_err(),
+                "{pattern}"
+            );
+        }
+
+        let long = "é".repeat(600);
+        let truncated = truncate_for_log(&long);
+        assert!(truncated.len() < long.len());
+        assert!(truncated.ends_with("…(+688 bytes)"), "{truncated}");
+        assert!(code_for_log("fn main() {}").starts_with("<12 bytes, md5 "));
+    }
+}
@@ -1,39 +1,634 @@
+use super::compilation::{CompilationRecord, CompilationStatus, HandlerMode};
+use super::deprecation::Deprecation;
+use super::docs;
+use super::guardrails::{RegistryGuardrails, RegistryGuardrailsConfig, RegistryGuardrailsStatus};
+use super::http_route::HttpRoute;
+use super::labels::{LabelRequirement, LabelSelector, validate_labels};
+use super::name::FunctionName;
+use super::redaction::Redactor;
+use super::schema::FunctionSchemas;
+use super::storage::{FunctionRecord, RegistryStorage};
+use super::template::InputTemplate;
+use super::testing;
+use super::versions::DEFAULT_MAX_VERSIONS;
 use super::{FluxError, FunctionMetadata, Result};
+use crate::runtime::compiler::RustCompiler;
+use crate::runtime::inline;
 use crate::runtime::loader::FunctionLoader;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::Path;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify, OwnedMutexGuard, RwLock, broadcast};
+
+/// 变更通知的缓冲区大小，订阅者落后更多时收到 `Lagged`
+const CHANGE_CHANNEL_CAPACITY: usize = 256;
+
+/// 注册表变更事件，订阅者据此只使受影响的缓存条目失效
+///
+/// 哈希为 [`RustCompiler::compile_key`] 计算的代码哈希。订阅者落后而收到 `Lagged` 时
+/// 无法得知丢失了哪些事件，应退回到全部失效。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryChange {
+    /// 注册了新函数
+    Registered(String),
+    /// 替换了已有函数，`version` 为写入的版本
+    Updated {
+        name: String,
+        version: String,
+        old_hash: String,
+        new_hash: String,
+    },
+    /// 删除了函数及其所有版本
+    Deleted(String),
+    /// 丢弃了函数的一个历史版本（超出保留数或单独删除）
+    VersionPruned {
+        name: String,
+        version: String,
+        hash: String,
+    },
+}
+
+impl RegistryChange {
+    /// 变更涉及的函数名
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Registered(name) | Self::Deleted(name) => name,
+            Self::Updated { name, .. } | Self::VersionPruned { name, .. } => name,
+        }
+    }
+
+    fn pruned(function: &FunctionMetadata) -> Self {
+        Self::VersionPruned {
+            name: function.name.clone(),
+            version: function.version.clone(),
+            hash: RustCompiler::compile_key(function).1,
+        }
+    }
+}
 
 /// 函数注册表 - 内存中存储函数元数据
 #[derive(Debug, Clone)]
 pub struct FunctionRegistry {
     functions: Arc<RwLock<HashMap<String, FunctionMetadata>>>,
+    /// 标签索引：键 -> 值 -> 函数名（总是在持有 `functions` 写锁时更新）
+    label_index: Arc<RwLock<LabelIndex>>,
+    /// 后台编译记录：函数名 -> 当前代码的编译状态
+    compilations: Arc<RwLock<HashMap<String, CompilationRecord>>>,
+    /// 任一编译状态变化时通知等待者
+    compilation_changed: Arc<Notify>,
+    /// 函数名（忽略大小写）-> 变更锁，串行化同名函数的多步变更
+    name_locks: Arc<StdMutex<HashMap<String, Arc<Mutex<()>>>>>,
+    /// 最近分配的修订号（注册表内全局递增，删除后重建的函数不会复用旧修订号）
+    last_revision: Arc<AtomicU64>,
+    /// 函数名 -> 仍可按版本调用的历史版本（从旧到新，不含最新版本；总是在持有 `functions` 写锁时更新）
+    history: Arc<StdMutex<HashMap<String, Vec<FunctionMetadata>>>>,
+    /// 每个函数保留的版本数（包含最新版本）
+    max_versions: usize,
+    /// 注册表变更事件
+    changes: broadcast::Sender<RegistryChange>,
+    /// 持久化后端（未设置时函数只保存在内存中），变更先写入后端再生效
+    storage: Arc<OnceLock<Arc<dyn RegistryStorage>>>,
+    /// 后端不可达时只读：继续提供已加载的函数，拒绝变更
+    read_only: Arc<AtomicBool>,
+    /// 已从后端加载过一次函数目录（之前一直只读）
+    loaded: Arc<AtomicBool>,
+    /// 函数总数上限和变更速率限制
+    guardrails: Arc<RegistryGuardrails>,
 }
 
+type LabelIndex = HashMap<String, HashMap<String, HashSet<String>>>;
+
 impl FunctionRegistry {
     /// 创建新的函数注册表
     pub fn new() -> Self {
         Self {
             functions: Arc::new(RwLock::new(HashMap::new())),
+            label_index: Arc::new(RwLock::new(HashMap::new())),
+            compilations: Arc::new(RwLock::new(HashMap::new())),
+            compilation_changed: Arc::new(Notify::new()),
+            name_locks: Arc::default(),
+            last_revision: Arc::default(),
+            history: Arc::default(),
+            max_versions: DEFAULT_MAX_VERSIONS,
+            changes: broadcast::channel(CHANGE_CHANNEL_CAPACITY).0,
+            storage: Arc::default(),
+            read_only: Arc::default(),
+            loaded: Arc::default(),
+            guardrails: Arc::default(),
         }
     }
 
-    /// 注册函数
-    pub async fn register(&self, function: FunctionMetadata) -> Result<()> {
+    /// 订阅注册表变更事件
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<RegistryChange> {
+        self.changes.subscribe()
+    }
+
+    fn publish(&self, change: RegistryChange) {
+        // 没有订阅者时发送失败是正常情况
+        let _ = self.changes.send(change);
+    }
+
+    /// 设置每个函数保留的版本数（包含最新版本，至少为 1）
+    pub fn with_max_versions(mut self, max_versions: usize) -> Self {
+        self.max_versions = max_versions.max(1);
+        self
+    }
+
+    /// 设置函数总数上限和变更速率限制
+    pub fn with_guardrails(mut self, config: RegistryGuardrailsConfig) -> Self {
+        self.guardrails = Arc::new(RegistryGuardrails::new(config));
+        self
+    }
+
+    /// 函数数、护栏限制和被拒绝的变更数
+    pub async fn guardrails_status(&self) -> RegistryGuardrailsStatus {
+        self.guardrails.status(self.count().await)
+    }
+
+    /// 获取函数名（忽略大小写）的变更锁
+    ///
+    /// 调度器在一次变更的全部步骤（注册表写入、缓存失效、启动编译）期间持有该锁，
+    /// 同名函数的并发变更因此不会交错，其他请求也不会看到只完成一半的变更。
+    pub async fn lock_name(&self, name: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.name_locks.lock().unwrap_or_else(|e| e.into_inner());
+            let key = name.to_ascii_lowercase();
+            if !locks.contains_key(&key) {
+                // 只有映射表持有引用的锁无人持有或等待，可以清理
+                locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            }
+            locks.entry(key).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+
+    /// 按名称顺序获取多个变更锁，避免多个批量操作之间死锁
+    pub async fn lock_names<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<OwnedMutexGuard<()>> {
+        let names: BTreeSet<String> = names
+            .into_iter()
+            .map(|name| name.to_ascii_lowercase())
+            .collect();
+        let mut guards = Vec::with_capacity(names.len());
+        for name in names {
+            guards.push(self.lock_name(&name).await);
+        }
+        guards
+    }
+
+    /// 分配下一个修订号（调用方持有 `functions` 写锁）
+    fn next_revision(&self) -> u64 {
+        self.last_revision.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// 最近分配的修订号，每次成功变更加一
+    pub fn last_revision(&self) -> u64 {
+        self.last_revision.load(Ordering::SeqCst)
+    }
+
+    /// 设置持久化后端（只能设置一次，已设置时返回 false），成功加载函数目录前注册表只读
+    pub fn set_storage(&self, storage: Arc<dyn RegistryStorage>) -> bool {
+        if self.storage.set(storage).is_err() {
+            return false;
+        }
+        self.read_only.store(true, Ordering::SeqCst);
+        true
+    }
+
+    /// 持久化后端不可达，注册表处于只读状态
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
+
+    /// 从持久化后端加载全部函数（保留后端中的修订号），返回加载的函数
+    ///
+    /// 后端不可达时进入只读状态并返回 `StorageUnavailable`，已加载的函数照常提供。
+    pub async fn load_from_storage(&self) -> Result<Vec<FunctionMetadata>> {
+        let Some(storage) = self.storage.get() else {
+            return Ok(Vec::new());
+        };
+        let records = match storage.load_all().await {
+            Ok(records) => records,
+            Err(e) => {
+                self.observe_storage(&e);
+                return Err(e);
+            }
+        };
         let mut functions = self.functions.write().await;
+        let mut index = self.label_index.write().await;
+        let mut loaded = Vec::with_capacity(records.len());
+        for record in records {
+            let function = record.into_metadata();
+            let name = match Self::validate(&function) {
+                Ok(name) => name,
+                Err(e) => {
+                    tracing::warn!("Skipping stored function {}: {}", function.name, e);
+                    continue;
+                }
+            };
+            if let Err(e) = Self::check_collision(&functions, &name, true) {
+                tracing::warn!("Skipping stored function {}: {}", function.name, e);
+                continue;
+            }
+            // 之后分配的修订号大于后端中已有的修订号
+            self.last_revision
+                .fetch_max(function.revision, Ordering::SeqCst);
+            loaded.push(self.write_locked(&mut functions, &mut index, function).0);
+        }
+        self.loaded.store(true, Ordering::SeqCst);
+        self.read_only.store(false, Ordering::SeqCst);
+        tracing::info!("Loaded {} functions from storage", loaded.len());
+        Ok(loaded)
+    }
 
-        if functions.contains_key(&function.name) {
-            return Err(FluxError::FunctionAlreadyExists {
-                name: function.name.clone(),
+    /// 后端暂时不可达时进入只读状态
+    fn observe_storage(&self, error: &FluxError) {
+        if let FluxError::StorageUnavailable { reason } = error
+            && !self.read_only.swap(true, Ordering::SeqCst)
+        {
+            tracing::warn!(
+                "Function storage unavailable, catalog is read-only: {}",
+                reason
+            );
+        }
+    }
+
+    /// 只读状态下先探测后端，恢复后退出只读状态
+    async fn ensure_writable(&self, storage: &dyn RegistryStorage, name: &str) -> Result<()> {
+        if !self.is_read_only() {
+            return Ok(());
+        }
+        if !self.loaded.load(Ordering::SeqCst) {
+            return Err(FluxError::StorageUnavailable {
+                reason: "the catalog has not been loaded from storage yet".to_string(),
             });
         }
+        match storage.get_revision(name).await {
+            Ok(_) => {
+                self.read_only.store(false, Ordering::SeqCst);
+                tracing::info!("Function storage is reachable again");
+                Ok(())
+            }
+            Err(e) => {
+                self.observe_storage(&e);
+                Err(FluxError::StorageUnavailable {
+                    reason: e.to_string(),
+                })
+            }
+        }
+    }
+
+    /// 把函数写入持久化后端（调用方持有 `functions` 写锁），`previous` 为后端中应有的修订号
+    async fn persist(&self, function: &FunctionMetadata, previous: Option<u64>) -> Result<()> {
+        let Some(storage) = self.storage.get() else {
+            return Ok(());
+        };
+        self.ensure_writable(storage.as_ref(), &function.name)
+            .await?;
+        let result = storage
+            .put(&FunctionRecord::from_metadata(function), previous)
+            .await;
+        if let Err(e) = &result {
+            self.observe_storage(e);
+        }
+        result
+    }
+
+    /// 从持久化后端删除函数（调用方持有 `functions` 写锁）
+    async fn unpersist(&self, name: &str, revision: u64) -> Result<()> {
+        let Some(storage) = self.storage.get() else {
+            return Ok(());
+        };
+        self.ensure_writable(storage.as_ref(), name).await?;
+        let result = storage.delete(name, Some(revision)).await.map(drop);
+        if let Err(e) = &result {
+            self.observe_storage(e);
+        }
+        result
+    }
+
+    /// 撤销已写入后端、但未写入内存的函数（整体放弃的事务），恢复为 `previous`
+    async fn revert_persisted(
+        &self,
+        written: &FunctionMetadata,
+        previous: Option<&FunctionMetadata>,
+    ) {
+        let Some(storage) = self.storage.get() else {
+            return;
+        };
+        let result = match previous {
+            Some(previous) => {
+                storage
+                    .put(
+                        &FunctionRecord::from_metadata(previous),
+                        Some(written.revision),
+                    )
+                    .await
+            }
+            None => storage
+                .delete(&written.name, Some(written.revision))
+                .await
+                .map(drop),
+        };
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to revert stored function {} after aborted transaction: {}",
+                written.name,
+                e
+            );
+        }
+    }
+
+    /// 校验函数定义（名称、标签、函数包、输入模板、JSON Schema、日志脱敏模式、文档大小、测试用例、
+    /// 执行路径提示）
+    pub(crate) fn validate(function: &FunctionMetadata) -> Result<FunctionName> {
+        let name = FunctionName::parse(&function.name)?;
+        validate_labels(&function.labels)?;
+        if let Some(package) = &function.package {
+            package.validate()?;
+        }
+        if let Some(template) = &function.input_template {
+            InputTemplate::parse(template)?;
+        }
+        FunctionSchemas::compile(function)?;
+        Redactor::parse(&function.log_redaction)?;
+        if let Some(documentation) = &function.documentation {
+            docs::validate(documentation)?;
+        }
+        if let Some(egress) = &function.egress {
+            egress.validate()?;
+        }
+        testing::validate(&function.tests)?;
+        inline::validate(function)?;
+        Ok(name)
+    }
+
+    /// 自定义路由通过 `/routes`、弃用状态通过 `/functions/:name/deprecate` 单独管理：
+    /// 替换的定义没有设置时沿用已有函数的路由和弃用状态
+    fn keep_managed_fields(
+        functions: &HashMap<String, FunctionMetadata>,
+        function: &mut FunctionMetadata,
+    ) {
+        let Some(existing) = functions.get(&function.name) else {
+            return;
+        };
+        if function.http_routes.is_empty() {
+            function.http_routes = existing.http_routes.clone();
+        }
+        if function.deprecation.is_none() {
+            function.deprecation = existing.deprecation.clone();
+        }
+    }
+
+    /// 期望修订号与当前修订号不一致时返回 `RevisionMismatch`
+    pub(crate) fn check_revision(name: &str, current: u64, expected: Option<u64>) -> Result<()> {
+        match expected {
+            Some(expected) if expected != current => Err(FluxError::RevisionMismatch {
+                name: name.to_string(),
+                expected,
+                current,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// 注册函数
+    ///
+    /// 函数名必须通过 [`FunctionName`] 校验，且与已有函数名忽略大小写后不能重复。
+    pub async fn register(&self, mut function: FunctionMetadata) -> Result<()> {
+        let name = Self::validate(&function)?;
+        let mut functions = self.functions.write().await;
+        Self::check_collision(&functions, &name, false)?;
+        self.guardrails.check_capacity(functions.len())?;
+        self.guardrails.acquire_mutation()?;
+        function.revision = self.next_revision();
+        self.persist(&function, None).await?;
 
         tracing::info!("Registering function: {}", function.name);
-        functions.insert(function.name.clone(), function);
+        self.write_locked(
+            &mut functions,
+            &mut *self.label_index.write().await,
+            function,
+        );
+        self.guardrails.observe_count(functions.len());
         Ok(())
     }
 
+    /// 注册或替换函数，返回被替换的旧版本
+    pub async fn upsert(&self, function: FunctionMetadata) -> Result<Option<FunctionMetadata>> {
+        self.upsert_if(function, None)
+            .await
+            .map(|(_, previous)| previous)
+    }
+
+    /// 注册或替换函数，`expected_revision` 不为空时要求函数存在且修订号一致
+    ///
+    /// 返回写入后的函数（含新修订号）和被替换的旧版本。
+    pub async fn upsert_if(
+        &self,
+        mut function: FunctionMetadata,
+        expected_revision: Option<u64>,
+    ) -> Result<(FunctionMetadata, Option<FunctionMetadata>)> {
+        let name = Self::validate(&function)?;
+        let mut functions = self.functions.write().await;
+        let current = functions
+            .get(&function.name)
+            .map(|current| current.revision);
+        if let Some(expected) = expected_revision {
+            let current = current.ok_or_else(|| FluxError::FunctionNotFound {
+                name: function.name.clone(),
+            })?;
+            Self::check_revision(&function.name, current, Some(expected))?;
+        }
+        Self::check_collision(&functions, &name, true)?;
+        if current.is_none() {
+            self.guardrails.check_capacity(functions.len())?;
+        }
+        self.guardrails.acquire_mutation()?;
+        Self::keep_managed_fields(&functions, &mut function);
+        function.revision = self.next_revision();
+        self.persist(&function, current).await?;
+
+        tracing::info!("Upserting function: {}", function.name);
+        let written = self.write_locked(
+            &mut functions,
+            &mut *self.label_index.write().await,
+            function,
+        );
+        self.guardrails.observe_count(functions.len());
+        Ok(written)
+    }
+
+    /// 开始一个多函数事务
+    pub fn transaction(&self) -> RegistryTransaction<'_> {
+        RegistryTransaction {
+            registry: self,
+            entries: Vec::new(),
+            all_or_nothing: false,
+        }
+    }
+
+    /// 检查名称冲突：注册时忽略大小写后不能重名；替换时只允许与自身同名
+    fn check_collision(
+        functions: &HashMap<String, FunctionMetadata>,
+        name: &FunctionName,
+        replace: bool,
+    ) -> Result<()> {
+        match Self::find_colliding(functions, name) {
+            Some(existing) if !replace || existing != name.as_str() => {
+                Err(FluxError::FunctionAlreadyExists {
+                    name: existing.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 写入已分配修订号的函数和标签索引（调用方持有两把写锁），返回写入的函数和旧版本
+    fn write_locked(
+        &self,
+        functions: &mut HashMap<String, FunctionMetadata>,
+        index: &mut LabelIndex,
+        function: FunctionMetadata,
+    ) -> (FunctionMetadata, Option<FunctionMetadata>) {
+        Self::index_labels(index, &function);
+        let previous = functions.insert(function.name.clone(), function.clone());
+        if let Some(previous) = &previous {
+            Self::unindex_labels(index, previous, &function.labels);
+        }
+        self.retain_version(&function, previous.as_ref());
+        self.publish(match &previous {
+            None => RegistryChange::Registered(function.name.clone()),
+            Some(previous) => RegistryChange::Updated {
+                name: function.name.clone(),
+                version: function.version.clone(),
+                old_hash: RustCompiler::compile_key(previous).1,
+                new_hash: RustCompiler::compile_key(&function).1,
+            },
+        });
+        (function, previous)
+    }
+
+    /// 版本号变化时把被替换的版本移入历史，并丢弃超出保留数的最早版本
+    fn retain_version(&self, function: &FunctionMetadata, previous: Option<&FunctionMetadata>) {
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let versions = history.entry(function.name.clone()).or_default();
+        versions.retain(|retained| retained.version != function.version);
+        if let Some(previous) = previous.filter(|previous| previous.version != function.version) {
+            versions.push(previous.clone());
+        }
+        let excess = versions.len().saturating_sub(self.max_versions - 1);
+        for pruned in versions.drain(..excess) {
+            tracing::info!(
+                "Pruned version {} of function {}",
+                pruned.version,
+                pruned.name
+            );
+            self.publish(RegistryChange::pruned(&pruned));
+        }
+        if versions.is_empty() {
+            history.remove(&function.name);
+        }
+    }
+
+    /// 将函数加入标签索引
+    fn index_labels(index: &mut LabelIndex, function: &FunctionMetadata) {
+        for (key, value) in &function.labels {
+            index
+                .entry(key.clone())
+                .or_default()
+                .entry(value.clone())
+                .or_default()
+                .insert(function.name.clone());
+        }
+    }
+
+    /// 从标签索引移除函数的旧标签（`keep` 中仍然存在的标签保留）
+    fn unindex_labels(
+        index: &mut LabelIndex,
+        function: &FunctionMetadata,
+        keep: &HashMap<String, String>,
+    ) {
+        for (key, value) in &function.labels {
+            if keep.get(key) == Some(value) {
+                continue;
+            }
+            if let Some(values) = index.get_mut(key) {
+                if let Some(names) = values.get_mut(value) {
+                    names.remove(&function.name);
+                    if names.is_empty() {
+                        values.remove(value);
+                    }
+                }
+                if values.is_empty() {
+                    index.remove(key);
+                }
+            }
+        }
+    }
+
+    /// 按标签选择器列出函数
+    ///
+    /// 先用索引按第一个正向条件（`=`、`in`、存在）缩小候选集，再逐一匹配全部条件。
+    pub async fn list_by_selector(&self, selector: &LabelSelector) -> Vec<FunctionMetadata> {
+        let functions = self.functions.read().await;
+        let candidates: Option<HashSet<String>> = {
+            let index = self.label_index.read().await;
+            selector.requirements.iter().find_map(|req| {
+                let values = index.get(req.key());
+                match req {
+                    LabelRequirement::Equals(_, value) => Some(
+                        values
+                            .and_then(|values| values.get(value))
+                            .cloned()
+                            .unwrap_or_default(),
+                    ),
+                    LabelRequirement::In(_, wanted) => Some(
+                        wanted
+                            .iter()
+                            .filter_map(|value| values.and_then(|values| values.get(value)))
+                            .flatten()
+                            .cloned()
+                            .collect(),
+                    ),
+                    LabelRequirement::Exists(_) => Some(
+                        values
+                            .map(|values| values.values().flatten().cloned().collect())
+                            .unwrap_or_default(),
+                    ),
+                    _ => None,
+                }
+            })
+        };
+
+        let matches = |function: &&FunctionMetadata| selector.matches(&function.labels);
+        match candidates {
+            Some(names) => names
+                .iter()
+                .filter_map(|name| functions.get(name))
+                .filter(matches)
+                .cloned()
+                .collect(),
+            None => functions.values().filter(matches).cloned().collect(),
+        }
+    }
+
+    /// 查找与给定名称忽略大小写后相同的已注册函数名
+    fn find_colliding<'a>(
+        functions: &'a HashMap<String, FunctionMetadata>,
+        name: &FunctionName,
+    ) -> Option<&'a str> {
+        let normalized = name.normalized();
+        functions
+            .keys()
+            .find(|existing| existing.to_ascii_lowercase() == normalized)
+            .map(String::as_str)
+    }
+
     /// 获取函数
     pub async fn get(&self, name: &str) -> Result<FunctionMetadata> {
         let functions = self.functions.read().await;
@@ -45,6 +640,128 @@ impl FunctionRegistry {
             })
     }
 
+    /// 获取函数的指定版本，版本未保留时返回列出可用版本的 `VersionNotFound`
+    pub async fn get_version(&self, name: &str, version: &str) -> Result<FunctionMetadata> {
+        let versions = self.versions(name).await?;
+        match versions.iter().find(|function| function.version == version) {
+            Some(function) => Ok(function.clone()),
+            None => Err(FluxError::VersionNotFound {
+                name: name.to_string(),
+                version: version.to_string(),
+                available: versions
+                    .into_iter()
+                    .map(|function| function.version)
+                    .collect(),
+            }),
+        }
+    }
+
+    /// 函数保留的所有版本，从旧到新，最后一个为最新版本
+    pub async fn versions(&self, name: &str) -> Result<Vec<FunctionMetadata>> {
+        let functions = self.functions.read().await;
+        let latest = functions
+            .get(name)
+            .ok_or_else(|| FluxError::FunctionNotFound {
+                name: name.to_string(),
+            })?;
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let mut versions = history.get(name).cloned().unwrap_or_default();
+        versions.push(latest.clone());
+        Ok(versions)
+    }
+
+    /// 删除函数的一个历史版本（最新版本不能单独删除）；返回被删除的版本
+    pub async fn remove_version(&self, name: &str, version: &str) -> Result<FunctionMetadata> {
+        let functions = self.functions.write().await;
+        let latest = functions
+            .get(name)
+            .ok_or_else(|| FluxError::FunctionNotFound {
+                name: name.to_string(),
+            })?;
+        if latest.version == version {
+            return Err(FluxError::LatestVersion {
+                name: name.to_string(),
+                version: version.to_string(),
+            });
+        }
+        self.guardrails.acquire_mutation()?;
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let versions = history.entry(name.to_string()).or_default();
+        let Some(position) = versions
+            .iter()
+            .position(|function| function.version == version)
+        else {
+            let mut available: Vec<String> = versions
+                .iter()
+                .map(|function| function.version.clone())
+                .collect();
+            available.push(latest.version.clone());
+            if versions.is_empty() {
+                history.remove(name);
+            }
+            return Err(FluxError::VersionNotFound {
+                name: name.to_string(),
+                version: version.to_string(),
+                available,
+            });
+        };
+        let removed = versions.remove(position);
+        if versions.is_empty() {
+            history.remove(name);
+        }
+        tracing::info!("Removed version {} of function {}", version, name);
+        self.publish(RegistryChange::pruned(&removed));
+        Ok(removed)
+    }
+
+    /// 替换函数的自定义路由，不改变函数定义；返回更新后的函数
+    pub async fn set_http_routes(
+        &self,
+        name: &str,
+        routes: Vec<HttpRoute>,
+    ) -> Result<FunctionMetadata> {
+        self.update_managed(name, |function| function.http_routes = routes)
+            .await
+    }
+
+    /// 替换函数的弃用状态（`None` 表示取消弃用），不改变函数定义；返回更新后的函数
+    pub async fn set_deprecation(
+        &self,
+        name: &str,
+        deprecation: Option<Deprecation>,
+    ) -> Result<FunctionMetadata> {
+        self.update_managed(name, |function| function.deprecation = deprecation)
+            .await
+    }
+
+    /// 只修改单独管理的字段并写入新修订号
+    async fn update_managed(
+        &self,
+        name: &str,
+        update: impl FnOnce(&mut FunctionMetadata),
+    ) -> Result<FunctionMetadata> {
+        let mut functions = self.functions.write().await;
+        let mut function =
+            functions
+                .get(name)
+                .cloned()
+                .ok_or_else(|| FluxError::FunctionNotFound {
+                    name: name.to_string(),
+                })?;
+        self.guardrails.acquire_mutation()?;
+        let current = function.revision;
+        update(&mut function);
+        function.revision = self.next_revision();
+        self.persist(&function, Some(current)).await?;
+        Ok(self
+            .write_locked(
+                &mut functions,
+                &mut *self.label_index.write().await,
+                function,
+            )
+            .0)
+    }
+
     /// 列出所有函数
     pub async fn list(&self) -> Vec<FunctionMetadata> {
         let functions = self.functions.read().await;
@@ -53,15 +770,123 @@ impl FunctionRegistry {
 
     /// 删除函数
     pub async fn remove(&self, name: &str) -> Result<()> {
+        self.remove_if(name, None).await.map(drop)
+    }
+
+    /// 删除函数，`expected_revision` 不为空时要求修订号一致；返回被删除的函数
+    pub async fn remove_if(
+        &self,
+        name: &str,
+        expected_revision: Option<u64>,
+    ) -> Result<FunctionMetadata> {
         let mut functions = self.functions.write().await;
-        functions
+        let current = functions
+            .get(name)
+            .ok_or_else(|| FluxError::FunctionNotFound {
+                name: name.to_string(),
+            })?
+            .revision;
+        Self::check_revision(name, current, expected_revision)?;
+        self.guardrails.acquire_mutation()?;
+        self.unpersist(name, current).await?;
+        let removed = functions
             .remove(name)
             .ok_or_else(|| FluxError::FunctionNotFound {
                 name: name.to_string(),
             })?;
+        // 删除也是一次变更
+        self.next_revision();
+        self.history
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(name);
+        Self::unindex_labels(
+            &mut *self.label_index.write().await,
+            &removed,
+            &HashMap::new(),
+        );
+        if self.compilations.write().await.remove(name).is_some() {
+            self.compilation_changed.notify_waiters();
+        }
+        self.publish(RegistryChange::Deleted(name.to_string()));
+        self.guardrails.observe_count(functions.len());
 
         tracing::info!("Removed function: {}", name);
-        Ok(())
+        Ok(removed)
+    }
+
+    /// 为函数当前代码登记一次编译，返回是否需要启动编译
+    ///
+    /// 同一源代码哈希已在编译或已就绪时不重复编译；代码变化时旧记录被直接替换，
+    /// 之后旧编译任务的状态更新都会因哈希不匹配而被忽略。
+    pub async fn start_compilation(
+        &self,
+        name: &str,
+        source_hash: &str,
+        handler_mode: HandlerMode,
+    ) -> bool {
+        let mut compilations = self.compilations.write().await;
+        if let Some(record) = compilations.get(name)
+            && record.source_hash == source_hash
+            && !matches!(record.status, CompilationStatus::Failed(_))
+        {
+            return false;
+        }
+        compilations.insert(
+            name.to_string(),
+            CompilationRecord::pending(source_hash, handler_mode),
+        );
+        drop(compilations);
+        self.compilation_changed.notify_waiters();
+        true
+    }
+
+    /// 更新编译状态，只作用于同一源代码哈希的记录
+    pub async fn update_compilation(
+        &self,
+        name: &str,
+        source_hash: &str,
+        status: CompilationStatus,
+        stderr: Option<String>,
+    ) -> bool {
+        let mut compilations = self.compilations.write().await;
+        let Some(record) = compilations
+            .get_mut(name)
+            .filter(|record| record.source_hash == source_hash)
+        else {
+            return false;
+        };
+        record.status = status;
+        record.stderr = stderr;
+        record.updated_at = chrono::Utc::now();
+        drop(compilations);
+        self.compilation_changed.notify_waiters();
+        true
+    }
+
+    /// 获取函数的编译记录（未启用编译时为 `None`）
+    pub async fn compilation(&self, name: &str) -> Option<CompilationRecord> {
+        self.compilations.read().await.get(name).cloned()
+    }
+
+    /// 等待编译结束（就绪、失败或记录被移除），最多等待 `timeout`，返回最新记录
+    pub async fn wait_for_compilation(
+        &self,
+        name: &str,
+        timeout: Duration,
+    ) -> Option<CompilationRecord> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            // 先注册通知再检查状态，避免错过两者之间的更新
+            let changed = self.compilation_changed.notified();
+            let record = self.compilation(name).await;
+            if !record.as_ref().is_some_and(|r| r.status.is_in_progress()) {
+                return record;
+            }
+            if tokio::time::timeout_at(deadline, changed).await.is_err() {
+                return record;
+            }
+        }
     }
 
     /// 函数是否存在
@@ -89,8 +914,10 @@ impl FunctionRegistry {
             .load_function_from_file(path, name, description, timeout_ms)
             .await?;
 
-        // 验证函数代码
-        loader.validate_function_code(&function.code).await?;
+        // 验证函数代码（脚本文件以函数包注册，不经过 Rust 校验器）
+        if function.package.is_none() {
+            loader.validate_function_code(&function.code).await?;
+        }
 
         self.register(function).await
     }
@@ -102,8 +929,10 @@ impl FunctionRegistry {
 
         let mut registered_count = 0;
         for function in functions {
-            // 验证函数代码
-            if let Err(e) = loader.validate_function_code(&function.code).await {
+            // 验证函数代码（函数包在加载时已校验）
+            if function.package.is_none()
+                && let Err(e) = loader.validate_function_code(&function.code).await
+            {
                 tracing::warn!(
                     "Skipping function {} due to validation error: {}",
                     function.name,
@@ -131,8 +960,525 @@ impl FunctionRegistry {
     }
 }
 
+/// 注册表事务：一次提交多个函数的注册或替换
+///
+/// 提交时先逐条校验（包括与已有函数及同一事务内其他条目的名称冲突），再在同一把写锁内
+/// 写入全部通过校验的条目，其他请求不会看到只写入了一部分的状态。每个条目单独报告结果，
+/// 失败的条目不影响其他条目；[`all_or_nothing`](Self::all_or_nothing) 时任一条目失败则全部不写入。
+#[derive(Debug)]
+pub struct RegistryTransaction<'a> {
+    registry: &'a FunctionRegistry,
+    entries: Vec<(FunctionMetadata, bool)>,
+    all_or_nothing: bool,
+}
+
+impl RegistryTransaction<'_> {
+    /// 注册新函数（已存在时该条目失败）
+    pub fn register(&mut self, function: FunctionMetadata) -> &mut Self {
+        self.entries.push((function, false));
+        self
+    }
+
+    /// 注册或替换函数
+    pub fn upsert(&mut self, function: FunctionMetadata) -> &mut Self {
+        self.entries.push((function, true));
+        self
+    }
+
+    /// 任一条目失败时不写入任何条目
+    pub fn all_or_nothing(mut self) -> Self {
+        self.all_or_nothing = true;
+        self
+    }
+
+    /// 事务涉及的函数名（用于提交前获取变更锁）
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .map(|(function, _)| function.name.as_str())
+    }
+
+    /// 提交事务，按条目顺序返回写入后的函数或失败原因
+    ///
+    /// 整体放弃时，本身通过校验的条目返回 [`FluxError::TransactionAborted`]。
+    /// 整个事务消耗一次变更配额；超出函数总数上限的新函数条目返回 [`FluxError::RegistryFull`]。
+    pub async fn commit(self) -> Vec<Result<FunctionMetadata>> {
+        let registry = self.registry;
+        if let Err(FluxError::MutationRateLimited {
+            per_minute,
+            retry_after,
+        }) = registry.guardrails.acquire_mutation()
+        {
+            return self
+                .entries
+                .iter()
+                .map(|_| {
+                    Err(FluxError::MutationRateLimited {
+                        per_minute,
+                        retry_after,
+                    })
+                })
+                .collect();
+        }
+        let mut functions = registry.functions.write().await;
+        let mut claimed = HashMap::new();
+        let mut count = functions.len();
+        let checks: Vec<Result<()>> = self
+            .entries
+            .iter()
+            .map(|(function, replace)| {
+                let name = FunctionRegistry::validate(function)?;
+                FunctionRegistry::check_collision(&functions, &name, *replace)?;
+                if let Some(other) = claimed.insert(name.normalized(), function.name.clone()) {
+                    return Err(FluxError::FunctionAlreadyExists { name: other });
+                }
+                if !functions.contains_key(&function.name) {
+                    registry.guardrails.check_capacity(count)?;
+                    count += 1;
+                }
+                Ok(())
+            })
+            .collect();
+
+        let failed = checks.iter().filter(|check| check.is_err()).count();
+        if self.all_or_nothing && failed > 0 {
+            return checks
+                .into_iter()
+                .map(|check| check.and(Err(FluxError::TransactionAborted { failed })))
+                .collect();
+        }
+
+        // 先写入持久化后端，写入成功的条目才写入内存
+        let mut checks = checks;
+        let mut entries = Vec::with_capacity(self.entries.len());
+        let mut persisted = Vec::new();
+        for (position, ((mut function, _), check)) in
+            self.entries.into_iter().zip(&mut checks).enumerate()
+        {
+            if check.is_ok() {
+                FunctionRegistry::keep_managed_fields(&functions, &mut function);
+                function.revision = registry.next_revision();
+                let previous = functions
+                    .get(&function.name)
+                    .map(|previous| previous.revision);
+                match registry.persist(&function, previous).await {
+                    Ok(()) => persisted.push(position),
+                    Err(e) => *check = Err(e),
+                }
+            }
+            entries.push(function);
+        }
+        let failed = checks.iter().filter(|check| check.is_err()).count();
+        if self.all_or_nothing && failed > 0 {
+            for position in persisted {
+                let written = &entries[position];
+                registry
+                    .revert_persisted(written, functions.get(&written.name))
+                    .await;
+            }
+            return checks
+                .into_iter()
+                .map(|check| check.and(Err(FluxError::TransactionAborted { failed })))
+                .collect();
+        }
+
+        let mut index = registry.label_index.write().await;
+        let results: Vec<_> = entries
+            .into_iter()
+            .zip(checks)
+            .map(|(function, check)| {
+                check.map(|()| {
+                    registry
+                        .write_locked(&mut functions, &mut index, function)
+                        .0
+                })
+            })
+            .collect();
+        registry.guardrails.observe_count(functions.len());
+        tracing::info!(
+            "Committed registry transaction: {} written, {failed} failed",
+            results.len() - failed
+        );
+        results
+    }
+}
+
 impl Default for FunctionRegistry {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::versions::VersionSummary;
+
+    #[tokio::test]
+    async fn test_register_validates_name_and_case_collisions() {
+        let registry = FunctionRegistry::new();
+
+        let invalid = FunctionMetadata::new("../etc/passwd".to_string(), "code".to_string());
+        assert!(matches!(
+            registry.register(invalid).await,
+            Err(FluxError::ValidationError { .. })
+        ));
+
+        let foo = FunctionMetadata::new("Foo".to_string(), "code".to_string());
+        registry.register(foo).await.unwrap();
+
+        let lower = FunctionMetadata::new("foo".to_string(), "code".to_string());
+        assert!(matches!(
+            registry.register(lower).await,
+            Err(FluxError::FunctionAlreadyExists { name }) if name == "Foo"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_version_history_is_bounded_and_diffed() {
+        let registry = FunctionRegistry::new().with_max_versions(3);
+        let version = |version: &str, code: &str| {
+            FunctionMetadata::new_with_version(
+                "f".to_string(),
+                code.to_string(),
+                version.to_string(),
+            )
+        };
+        registry.register(version("1", "a")).await.unwrap();
+        registry.upsert(version("2", "bb")).await.unwrap();
+        // 同一版本号重新写入只替换该版本
+        let mut relabelled = version("2", "bbb");
+        relabelled
+            .labels
+            .insert("env".to_string(), "prod".to_string());
+        registry.upsert(relabelled).await.unwrap();
+        registry.upsert(version("3", "c")).await.unwrap();
+        registry.upsert(version("4", "dddd")).await.unwrap();
+
+        let versions = registry.versions("f").await.unwrap();
+        let names: Vec<_> = versions.iter().map(|f| f.version.as_str()).collect();
+        assert_eq!(names, ["2", "3", "4"]);
+        assert_eq!(registry.get_version("f", "2").await.unwrap().code, "bbb");
+
+        let summaries = VersionSummary::list(&versions);
+        assert!(summaries[0].diff.is_none());
+        assert!(summaries[2].latest);
+        let diff = summaries[1].diff.as_ref().unwrap();
+        assert_eq!((diff.from.as_str(), diff.code_size_delta), ("2", -2));
+        assert_eq!(diff.changed_fields, ["code", "labels"]);
+
+        registry.remove("f").await.unwrap();
+        registry.register(version("5", "e")).await.unwrap();
+        assert!(matches!(
+            registry.get_version("f", "4").await,
+            Err(FluxError::VersionNotFound { available, .. }) if available == ["5"]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_changes_carry_versions_and_code_hashes() {
+        let registry = FunctionRegistry::new().with_max_versions(2);
+        let mut changes = registry.subscribe_changes();
+        let version = |version: &str, code: &str| {
+            FunctionMetadata::new_with_version(
+                "f".to_string(),
+                code.to_string(),
+                version.to_string(),
+            )
+        };
+        let hash = |code: &str| RustCompiler::compile_key(&version("1", code)).1;
+
+        registry.register(version("1", "a")).await.unwrap();
+        registry.upsert(version("1", "b")).await.unwrap();
+        // 写入版本 3 时版本 1 超出保留数被丢弃
+        registry.upsert(version("2", "b")).await.unwrap();
+        registry.upsert(version("3", "c")).await.unwrap();
+        registry.remove_version("f", "2").await.unwrap();
+        registry.remove("f").await.unwrap();
+
+        let updated = |version: &str, old: &str, new: &str| RegistryChange::Updated {
+            name: "f".to_string(),
+            version: version.to_string(),
+            old_hash: hash(old),
+            new_hash: hash(new),
+        };
+        let pruned = |version: &str, code: &str| RegistryChange::VersionPruned {
+            name: "f".to_string(),
+            version: version.to_string(),
+            hash: hash(code),
+        };
+        let expected = [
+            RegistryChange::Registered("f".to_string()),
+            updated("1", "a", "b"),
+            updated("2", "b", "b"),
+            pruned("1", "b"),
+            updated("3", "b", "c"),
+            pruned("2", "b"),
+            RegistryChange::Deleted("f".to_string()),
+        ];
+        for change in expected {
+            assert_eq!(changes.try_recv().unwrap(), change);
+        }
+        assert!(changes.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_reports_failed_entries_and_aborts_atomically() {
+        let registry = FunctionRegistry::new();
+        let function = |name: &str| FunctionMetadata::new(name.to_string(), "code".to_string());
+        registry.register(function("existing")).await.unwrap();
+
+        let mut tx = registry.transaction();
+        tx.register(function("alpha"))
+            .register(function("Existing"))
+            .upsert(function("existing"))
+            .register(function("ALPHA"))
+            .register(function("../bad"));
+        let results = tx.commit().await;
+        assert!(results[0].is_ok());
+        assert!(
+            matches!(&results[1], Err(FluxError::FunctionAlreadyExists { name }) if name == "existing")
+        );
+        assert!(results[2].as_ref().is_ok_and(|f| f.revision > 1));
+        assert!(
+            matches!(&results[3], Err(FluxError::FunctionAlreadyExists { name }) if name == "alpha")
+        );
+        assert!(matches!(results[4], Err(FluxError::ValidationError { .. })));
+        assert_eq!(registry.count().await, 2);
+
+        // 整体放弃时不写入任何条目
+        let revision = registry.last_revision();
+        let mut tx = registry.transaction();
+        tx.register(function("beta")).register(function("alpha"));
+        let results = tx.all_or_nothing().commit().await;
+        assert!(matches!(
+            results[0],
+            Err(FluxError::TransactionAborted { failed: 1 })
+        ));
+        assert!(matches!(
+            results[1],
+            Err(FluxError::FunctionAlreadyExists { .. })
+        ));
+        assert!(!registry.exists("beta").await);
+        assert_eq!(registry.last_revision(), revision);
+
+        // 修订号不一致时拒绝替换和删除
+        let stale = registry.get("alpha").await.unwrap();
+        let (updated, _) = registry
+            .upsert_if(stale.clone(), Some(stale.revision))
+            .await
+            .unwrap();
+        assert!(matches!(
+            registry.remove_if("alpha", Some(stale.revision)).await,
+            Err(FluxError::RevisionMismatch { expected, current, .. })
+                if expected == stale.revision && current == updated.revision
+        ));
+        registry
+            .remove_if("alpha", Some(updated.revision))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_by_selector_tracks_label_updates() {
+        let registry = FunctionRegistry::new();
+        for (name, team, env) in [
+            ("pay_api", "payments", "staging"),
+            ("pay_worker", "payments", "prod"),
+            ("search", "search", "staging"),
+        ] {
+            let mut function = FunctionMetadata::new(name.to_string(), "code".to_string());
+            function.labels = HashMap::from([
+                ("team".to_string(), team.to_string()),
+                ("env".to_string(), env.to_string()),
+            ]);
+            registry.register(function).await.unwrap();
+        }
+
+        let names = |mut functions: Vec<FunctionMetadata>| {
+            functions.sort_by(|a, b| a.name.cmp(&b.name));
+            functions.into_iter().map(|f| f.name).collect::<Vec<_>>()
+        };
+        let select = |expr: &str| LabelSelector::parse(expr).unwrap();
+
+        assert_eq!(
+            names(registry.list_by_selector(&select("team=payments")).await),
+            vec!["pay_api", "pay_worker"]
+        );
+        assert_eq!(
+            names(
+                registry
+                    .list_by_selector(&select("env in (staging),team!=payments"))
+                    .await
+            ),
+            vec!["search"]
+        );
+
+        // 更新标签后索引同步变化
+        let mut function = registry.get("pay_api").await.unwrap();
+        function
+            .labels
+            .insert("team".to_string(), "billing".to_string());
+        registry.upsert(function).await.unwrap();
+        registry.remove("pay_worker").await.unwrap();
+        assert!(
+            registry
+                .list_by_selector(&select("team=payments"))
+                .await
+                .is_empty()
+        );
+        assert_eq!(
+            names(registry.list_by_selector(&select("team")).await),
+            vec!["pay_api", "search"]
+        );
+
+        let mut invalid = FunctionMetadata::new("bad_labels".to_string(), "code".to_string());
+        invalid.labels = HashMap::from([("team name".to_string(), "x".to_string())]);
+        assert!(registry.register(invalid).await.is_err());
+    }
+
+    /// 可以切换为不可达的持久化后端
+    #[derive(Debug)]
+    struct FlakyStorage {
+        inner: crate::functions::object_storage::ObjectStoreStorage,
+        down: AtomicBool,
+    }
+
+    impl FlakyStorage {
+        fn check(&self) -> Result<()> {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(FluxError::StorageUnavailable {
+                    reason: "connection refused".to_string(),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl RegistryStorage for FlakyStorage {
+        async fn load_all(&self) -> Result<Vec<FunctionRecord>> {
+            self.check()?;
+            self.inner.load_all().await
+        }
+
+        async fn put(&self, record: &FunctionRecord, expected: Option<u64>) -> Result<()> {
+            self.check()?;
+            self.inner.put(record, expected).await
+        }
+
+        async fn delete(&self, name: &str, expected: Option<u64>) -> Result<bool> {
+            self.check()?;
+            self.inner.delete(name, expected).await
+        }
+
+        async fn get_revision(&self, name: &str) -> Result<Option<u64>> {
+            self.check()?;
+            self.inner.get_revision(name).await
+        }
+    }
+
+    fn shared_store() -> crate::functions::object_storage::ObjectStoreStorage {
+        crate::functions::object_storage::ObjectStoreStorage::new(
+            Arc::new(object_store::memory::InMemory::new()),
+            object_store::path::Path::from("catalog"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_replicas_share_storage_without_overwriting() {
+        let store = shared_store();
+        let a = FunctionRegistry::new();
+        a.set_storage(Arc::new(store.scoped("default")));
+        a.load_from_storage().await.unwrap();
+        a.register(FunctionMetadata::new("f".to_string(), "v1".to_string()))
+            .await
+            .unwrap();
+
+        let b = FunctionRegistry::new();
+        b.set_storage(Arc::new(store.scoped("default")));
+        let loaded = b.load_from_storage().await.unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(b.get("f").await.unwrap().code, "v1");
+        // 新副本分配的修订号大于后端中已有的修订号
+        assert!(b.last_revision() >= loaded[0].revision);
+
+        // a 更新后，b 基于旧修订号的更新被拒绝，不会覆盖 a 的写入
+        a.upsert(FunctionMetadata::new("f".to_string(), "v2".to_string()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            b.upsert(FunctionMetadata::new("f".to_string(), "v3".to_string()))
+                .await,
+            Err(FluxError::RevisionMismatch { .. })
+        ));
+        assert_eq!(b.get("f").await.unwrap().code, "v1");
+
+        // 重新加载后 b 看到 a 的写入，可以继续修改
+        b.load_from_storage().await.unwrap();
+        assert_eq!(b.get("f").await.unwrap().code, "v2");
+        b.remove("f").await.unwrap();
+        // 其他副本已删除的函数在本地仍可删除
+        a.remove("f").await.unwrap();
+        let c = FunctionRegistry::new();
+        c.set_storage(Arc::new(store.scoped("default")));
+        assert!(c.load_from_storage().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_storage_serves_read_only_catalog() {
+        let storage = Arc::new(FlakyStorage {
+            inner: shared_store(),
+            down: AtomicBool::new(true),
+        });
+        let registry = FunctionRegistry::new();
+        registry.set_storage(storage.clone());
+
+        // 从未加载成功时拒绝变更，即使后端恢复也要先加载
+        assert!(matches!(
+            registry.load_from_storage().await,
+            Err(FluxError::StorageUnavailable { .. })
+        ));
+        assert!(registry.is_read_only());
+        storage.down.store(false, Ordering::SeqCst);
+        assert!(matches!(
+            registry
+                .register(FunctionMetadata::new("f".to_string(), "v1".to_string()))
+                .await,
+            Err(FluxError::StorageUnavailable { .. })
+        ));
+        registry.load_from_storage().await.unwrap();
+        assert!(!registry.is_read_only());
+        registry
+            .register(FunctionMetadata::new("f".to_string(), "v1".to_string()))
+            .await
+            .unwrap();
+
+        // 加载后后端不可达：继续提供已加载的函数，拒绝变更
+        storage.down.store(true, Ordering::SeqCst);
+        assert!(matches!(
+            registry
+                .upsert(FunctionMetadata::new("f".to_string(), "v2".to_string()))
+                .await,
+            Err(FluxError::StorageUnavailable { .. })
+        ));
+        assert!(registry.is_read_only());
+        assert!(matches!(
+            registry.remove("f").await,
+            Err(FluxError::StorageUnavailable { .. })
+        ));
+        assert_eq!(registry.get("f").await.unwrap().code, "v1");
+
+        // 后端恢复后下一次变更退出只读状态
+        storage.down.store(false, Ordering::SeqCst);
+        registry
+            .upsert(FunctionMetadata::new("f".to_string(), "v2".to_string()))
+            .await
+            .unwrap();
+        assert!(!registry.is_read_only());
+        assert_eq!(
+            storage.inner.get_revision("f").await.unwrap(),
+            Some(registry.get("f").await.unwrap().revision)
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use serde_json::{Number, Value};
+use std::fmt;
+
+/// 由 `return_type` 类型名解析出的可检查类型
+///
+/// 类型名的写法与 OpenAPI 和类型化绑定一致（`i64`、`integer`、`Vec<String>`、`Option<f64>` 等）；
+/// 无法识别的类型名（如自定义结构体）视为 `Any`，不做检查。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReturnType {
+    Any,
+    /// 没有返回值，输出必须为 `null`
+    Void,
+    Integer,
+    Number,
+    Boolean,
+    String,
+    Object,
+    Array(Box<ReturnType>),
+    /// `null` 或内部类型
+    Optional(Box<ReturnType>),
+}
+
+/// 输出与声明的返回类型不符
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReturnTypeMismatch {
+    /// 不符合处期望的类型
+    pub expected: String,
+    /// 实际的 JSON 类型
+    pub actual: &'static str,
+    /// 不符合的位置（`$` 为整个输出，数组元素为 `$[i]`）
+    pub path: String,
+}
+
+impl fmt::Display for ReturnTypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected {} at {}, got {}",
+            self.expected, self.path, self.actual
+        )
+    }
+}
+
+impl fmt::Display for ReturnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("any"),
+            Self::Void => f.write_str("void"),
+            Self::Integer => f.write_str("integer"),
+            Self::Number => f.write_str("number"),
+            Self::Boolean => f.write_str("boolean"),
+            Self::String => f.write_str("string"),
+            Self::Object => f.write_str("object"),
+            Self::Array(inner) => write!(f, "array<{inner}>"),
+            Self::Optional(inner) => write!(f, "{inner} or null"),
+        }
+    }
+}
+
+impl ReturnType {
+    /// 解析类型名
+    pub fn parse(type_name: &str) -> Self {
+        let type_name: String = type_name.chars().filter(|c| !c.is_whitespace()).collect();
+        if let Some(inner) = generic_argument(&type_name, "Vec").or_else(|| {
+            type_name
+                .strip_prefix('[')
+                .and_then(|t| t.strip_suffix(']'))
+        }) {
+            return Self::Array(Box::new(Self::parse(inner)));
+        }
+        if let Some(inner) = generic_argument(&type_name, "Option") {
+            return Self::Optional(Box::new(Self::parse(inner)));
+        }
+        if generic_argument(&type_name, "HashMap").is_some()
+            || generic_argument(&type_name, "BTreeMap").is_some()
+        {
+            return Self::Object;
+        }
+        match type_name.to_ascii_lowercase().as_str() {
+            "()" | "void" | "unit" => Self::Void,
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+            | "u128" | "usize" | "int" | "integer" => Self::Integer,
+            "f32" | "f64" | "float" | "double" | "number" => Self::Number,
+            "bool" | "boolean" => Self::Boolean,
+            "string" | "&str" | "str" | "char" => Self::String,
+            "array" => Self::Array(Box::new(Self::Any)),
+            "object" | "map" => Self::Object,
+            _ => Self::Any,
+        }
+    }
+
+    /// 检查输出是否符合类型
+    ///
+    /// 小数部分为零的数字满足 `Integer`；`coerce` 为 true 时数字字符串转换为数字。
+    /// 返回 `Ok(Some(转换后的输出))`、`Ok(None)`（无需转换）或不符合的位置。
+    pub fn check(&self, value: &Value, coerce: bool) -> Result<Option<Value>, ReturnTypeMismatch> {
+        self.check_at(value, coerce, "$")
+    }
+
+    fn check_at(
+        &self,
+        value: &Value,
+        coerce: bool,
+        path: &str,
+    ) -> Result<Option<Value>, ReturnTypeMismatch> {
+        let mismatch = || ReturnTypeMismatch {
+            expected: self.to_string(),
+            actual: json_kind(value),
+            path: path.to_string(),
+        };
+        match (self, value) {
+            (Self::Any, _)
+            | (Self::Void | Self::Optional(_), Value::Null)
+            | (Self::Number, Value::Number(_))
+            | (Self::Boolean, Value::Bool(_))
+            | (Self::String, Value::String(_))
+            | (Self::Object, Value::Object(_)) => Ok(None),
+            (Self::Integer, Value::Number(number)) if is_integral(number) => Ok(None),
+            (Self::Integer, Value::String(text)) if coerce => {
+                parse_integer(text).map(Some).ok_or_else(mismatch)
+            }
+            (Self::Number, Value::String(text)) if coerce => {
+                parse_number(text).map(Some).ok_or_else(mismatch)
+            }
+            (Self::Optional(inner), _) => inner.check_at(value, coerce, path),
+            (Self::Array(inner), Value::Array(items)) => {
+                let mut coerced: Option<Vec<Value>> = None;
+                for (i, item) in items.iter().enumerate() {
+                    if let Some(item) = inner.check_at(item, coerce, &format!("{path}[{i}]"))? {
+                        coerced.get_or_insert_with(|| items.clone())[i] = item;
+                    }
+                }
+                Ok(coerced.map(Value::Array))
+            }
+            _ => Err(mismatch()),
+        }
+    }
+}
+
+/// 取出 `Name<T>` 中的 `T`
+fn generic_argument<'a>(type_name: &'a str, name: &str) -> Option<&'a str> {
+    type_name
+        .strip_prefix(name)?
+        .strip_prefix('<')?
+        .strip_suffix('>')
+}
+
+fn json_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn is_integral(number: &Number) -> bool {
+    number.is_i64()
+        || number.is_u64()
+        || number
+            .as_f64()
+            .is_some_and(|f| f.is_finite() && f.fract() == 0.0)
+}
+
+fn parse_integer(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Ok(integer) = text.parse::<i64>() {
+        return Some(integer.into());
+    }
+    if let Ok(integer) = text.parse::<u64>() {
+        return Some(integer.into());
+    }
+    // `"3.0"` 这类小数部分为零的字符串同样转换为整数
+    let float = text.parse::<f64>().ok()?;
+    (float.is_finite() && float.fract() == 0.0 && float.abs() < i64::MAX as f64)
+        .then(|| (float as i64).into())
+}
+
+fn parse_number(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Some(integer) = parse_integer(text).filter(|_| !text.contains(['.', 'e', 'E'])) {
+        return Some(integer);
+    }
+    Number::from_f64(text.parse::<f64>().ok()?).map(Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_type_names() {
+        for (name, expected) in [
+            ("i64", ReturnType::Integer),
+            ("Integer", ReturnType::Integer),
+            ("f64", ReturnType::Number),
+            ("bool", ReturnType::Boolean),
+            ("String", ReturnType::String),
+            ("()", ReturnType::Void),
+            ("HashMap<String, i64>", ReturnType::Object),
+            ("serde_json::Value", ReturnType::Any),
+            ("MyStruct", ReturnType::Any),
+            (
+                "Vec<Option<i32>>",
+                ReturnType::Array(Box::new(ReturnType::Optional(Box::new(
+                    ReturnType::Integer,
+                )))),
+            ),
+        ] {
+            assert_eq!(ReturnType::parse(name), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_check_reports_path_and_coerces_nested_values() {
+        let mismatch = ReturnType::parse("Vec<i64>")
+            .check(&json!([1, 2.0, "x"]), true)
+            .unwrap_err();
+        assert_eq!(mismatch.to_string(), "expected integer at $[2], got string");
+
+        // 只有需要转换时才返回新的输出
+        assert_eq!(
+            ReturnType::parse("Vec<i64>").check(&json!([1, "2"]), true),
+            Ok(Some(json!([1, 2])))
+        );
+        assert_eq!(
+            ReturnType::parse("Vec<i64>").check(&json!([1, 2]), true),
+            Ok(None)
+        );
+        assert_eq!(
+            ReturnType::parse("f64").check(&json!(" 2.5 "), true),
+            Ok(Some(json!(2.5)))
+        );
+        assert_eq!(
+            ReturnType::parse("i64").check(&json!("3.0"), true),
+            Ok(Some(json!(3)))
+        );
+    }
+}
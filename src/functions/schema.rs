@@ -0,0 +1,169 @@
+use super::{FluxError, FunctionMetadata, Result};
+use jsonschema::Validator;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use utoipa::ToSchema;
+
+/// 单次校验最多报告的失败条目数
+const MAX_VIOLATIONS: usize = 50;
+
+/// 一条 JSON Schema 校验失败
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SchemaViolation {
+    /// 失败位置（JSON Pointer，根为 `/`）
+    pub path: String,
+    /// 未通过的 schema 关键字，例如 `required`、`minimum`
+    pub keyword: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.path, self.keyword, self.message)
+    }
+}
+
+/// 以 `; ` 连接多条校验失败（用于错误信息）
+pub fn describe(violations: &[SchemaViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// 编译后的函数输入/输出 JSON Schema
+#[derive(Debug)]
+pub struct FunctionSchemas {
+    input: Option<Validator>,
+    output: Option<Validator>,
+}
+
+impl FunctionSchemas {
+    /// 编译函数声明的 schema，不合法的 schema 返回带解析错误的 `ValidationError`
+    pub fn compile(function: &FunctionMetadata) -> Result<Self> {
+        Ok(Self {
+            input: function
+                .input_schema
+                .as_ref()
+                .map(|schema| compile(schema, "input_schema"))
+                .transpose()?,
+            output: function
+                .output_schema
+                .as_ref()
+                .map(|schema| compile(schema, "output_schema"))
+                .transpose()?,
+        })
+    }
+
+    /// 校验调用输入，返回所有失败条目（没有输入 schema 时为空）
+    pub fn check_input(&self, input: &Value) -> Vec<SchemaViolation> {
+        self.input
+            .as_ref()
+            .map(|validator| violations(validator, input))
+            .unwrap_or_default()
+    }
+
+    /// 校验函数输出，返回所有失败条目（没有输出 schema 时为空）
+    pub fn check_output(&self, output: &Value) -> Vec<SchemaViolation> {
+        self.output
+            .as_ref()
+            .map(|validator| violations(validator, output))
+            .unwrap_or_default()
+    }
+}
+
+fn compile(schema: &Value, field: &str) -> Result<Validator> {
+    jsonschema::validator_for(schema).map_err(|e| FluxError::ValidationError {
+        reason: format!("Invalid {field}: {e}"),
+    })
+}
+
+fn violations(validator: &Validator, instance: &Value) -> Vec<SchemaViolation> {
+    validator
+        .iter_errors(instance)
+        .take(MAX_VIOLATIONS)
+        .map(|error| {
+            let path = error.instance_path.to_string();
+            let schema_path = error.schema_path.to_string();
+            SchemaViolation {
+                path: if path.is_empty() {
+                    "/".to_string()
+                } else {
+                    path
+                },
+                keyword: schema_path
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                message: error.to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reports_each_failed_path_and_keyword() {
+        let mut function = FunctionMetadata::new("orders".to_string(), String::new());
+        function.input_schema = Some(json!({
+            "type": "object",
+            "required": ["order"],
+            "properties": {
+                "order": {
+                    "type": "object",
+                    "required": ["id"],
+                    "properties": {
+                        "id": {"type": "integer"},
+                        "items": {"type": "array", "items": {"type": "string"}, "minItems": 1}
+                    }
+                }
+            }
+        }));
+        function.output_schema = Some(json!({"type": "object", "required": ["total"]}));
+        let schemas = FunctionSchemas::compile(&function).unwrap();
+
+        assert!(
+            schemas
+                .check_input(&json!({"order": {"id": 7, "items": ["a"]}}))
+                .is_empty()
+        );
+        let mut found: Vec<_> = schemas
+            .check_input(&json!({"order": {"id": "7", "items": [1]}}))
+            .into_iter()
+            .map(|v| (v.path, v.keyword))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                ("/order/id".to_string(), "type".to_string()),
+                ("/order/items/0".to_string(), "type".to_string())
+            ]
+        );
+        let missing = schemas.check_input(&json!({}));
+        assert_eq!(
+            (missing[0].path.as_str(), missing[0].keyword.as_str()),
+            ("/", "required")
+        );
+
+        assert_eq!(
+            schemas.check_output(&json!({"sum": 1}))[0].keyword,
+            "required"
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_schema() {
+        let mut function = FunctionMetadata::new("bad".to_string(), String::new());
+        function.output_schema = Some(json!({"type": "no-such-type"}));
+        let err = FunctionSchemas::compile(&function).unwrap_err();
+        assert!(err.to_string().contains("Invalid output_schema"), "{err}");
+    }
+}
@@ -0,0 +1,103 @@
+use super::{FluxError, Result};
+use crate::runtime::series::parse_span;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// 函数级服务目标（SLO）
+///
+/// 窗口内成功调用的比例不低于 `availability`，配置 `latency_p95_ms` 时 p95 延迟不高于该值。
+/// 错误预算为窗口内允许失败的调用数，燃烧率为失败率与允许失败率之比。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SloConfig {
+    /// 可用性目标（成功调用比例，例如 0.995）
+    pub availability: f64,
+    /// p95 延迟目标（毫秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_p95_ms: Option<u64>,
+    /// 统计窗口（`30m`、`1h`、`7d` 形式，默认 `30d`）
+    #[serde(default = "default_window")]
+    pub window: String,
+}
+
+fn default_window() -> String {
+    "30d".to_string()
+}
+
+impl SloConfig {
+    /// 校验目标在 (0, 1) 内、窗口可解析且不为零
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| {
+            Err(FluxError::ValidationError {
+                reason: format!("slo: {reason}"),
+            })
+        };
+        if !(self.availability > 0.0 && self.availability < 1.0) {
+            return invalid(format!(
+                "availability must be in (0, 1), got {}",
+                self.availability
+            ));
+        }
+        if self.latency_p95_ms == Some(0) {
+            return invalid("latency_p95_ms must be positive".to_string());
+        }
+        match parse_span(&self.window) {
+            Some(window) if !window.is_zero() => Ok(()),
+            _ => invalid(format!(
+                "invalid window '{}', expected e.g. 30m, 1h or 7d",
+                self.window
+            )),
+        }
+    }
+
+    /// 统计窗口（无效时为零，注册时已校验）
+    pub fn window(&self) -> Duration {
+        parse_span(&self.window).unwrap_or_default()
+    }
+
+    /// 允许的失败率
+    pub fn error_budget_ratio(&self) -> f64 {
+        1.0 - self.availability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_and_validation() {
+        let slo: SloConfig = serde_json::from_str(r#"{"availability": 0.995}"#).unwrap();
+        assert_eq!(slo.window(), Duration::from_secs(30 * 86400));
+        assert!((slo.error_budget_ratio() - 0.005).abs() < 1e-12);
+        assert!(slo.validate().is_ok());
+
+        for invalid in [
+            SloConfig {
+                availability: 1.0,
+                ..slo.clone()
+            },
+            SloConfig {
+                availability: 0.0,
+                ..slo.clone()
+            },
+            SloConfig {
+                latency_p95_ms: Some(0),
+                ..slo.clone()
+            },
+            SloConfig {
+                window: "1w".to_string(),
+                ..slo.clone()
+            },
+            SloConfig {
+                window: "0h".to_string(),
+                ..slo.clone()
+            },
+        ] {
+            assert!(
+                matches!(invalid.validate(), Err(FluxError::ValidationError { .. })),
+                "{invalid:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,458 @@
+use super::FluxError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use utoipa::ToSchema;
+use utoipa::openapi::{RefOr, Schema};
+
+/// 执行失败的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// 函数自身返回错误或异常退出
+    Runtime,
+    /// 函数编译失败
+    Compilation,
+    /// 沙箱/进程层面的失败（无法启动、被信号终止等）
+    Sandbox,
+    /// 平台内部错误
+    Internal,
+    /// 旧格式 `Failed` 未携带失败原因
+    Unknown,
+}
+
+impl ErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Runtime => "runtime",
+            Self::Compilation => "compilation",
+            Self::Sandbox => "sandbox",
+            Self::Internal => "internal",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// 未单独指定归属时该类别失败的归属
+    pub fn origin(self) -> ErrorOrigin {
+        match self {
+            Self::Runtime => ErrorOrigin::UserCode,
+            Self::Compilation | Self::Sandbox | Self::Internal | Self::Unknown => {
+                ErrorOrigin::Platform
+            }
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 失败的归属，用于区分函数作者的问题和平台的问题（错误率 SLO 可排除用户代码和输入导致的失败）
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorOrigin {
+    /// 用户代码抛出异常、返回错误或非零退出
+    UserCode,
+    /// 编译失败、运行时缺失、临时目录 IO、动态库加载等平台侧失败
+    Platform,
+    /// 超时、内存、配额或并发等限制
+    Limit,
+    /// 输入校验或输入模板失败
+    Input,
+}
+
+impl ErrorOrigin {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::UserCode => "user_code",
+            Self::Platform => "platform",
+            Self::Limit => "limit",
+            Self::Input => "input",
+        }
+    }
+}
+
+impl fmt::Display for ErrorOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 超出限制的资源（超时单独由 `ExecutionStatus::Timeout` 表示）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceKind {
+    Memory,
+    Cpu,
+    /// 暂存目录超出大小上限
+    Disk,
+}
+
+impl ResourceKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Memory => "memory",
+            Self::Cpu => "cpu",
+            Self::Disk => "disk",
+        }
+    }
+
+    /// 旧格式下使用的错误信息
+    pub(crate) fn message(self) -> &'static str {
+        match self {
+            Self::Memory => "Memory limit exceeded",
+            Self::Cpu => "CPU limit exceeded",
+            Self::Disk => "Scratch space limit exceeded",
+        }
+    }
+}
+
+impl fmt::Display for ResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 函数执行状态
+///
+/// 序列化格式由 [`set_wire_format`] 控制：兼容模式（当前默认）输出旧版
+/// 的 `"Success"` / `{"Error": "..."}` / `"Timeout"`，关闭后输出带 `state` 标签的
+/// 新格式。反序列化始终同时接受两种格式（包括旧的 `Completed` 和 `Failed`）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionStatus {
+    Success,
+    Error {
+        kind: ErrorKind,
+        origin: ErrorOrigin,
+        message: String,
+    },
+    Timeout,
+    Cancelled,
+    ResourceLimitExceeded {
+        resource: ResourceKind,
+    },
+}
+
+impl ExecutionStatus {
+    /// 归属取类别的默认归属
+    pub fn error(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self::error_with_origin(kind, kind.origin(), message)
+    }
+
+    /// 执行失败的错误：编译失败归为编译类别，其余平台侧失败归为内部错误，归属取错误本身的归属
+    pub fn from_error(e: &FluxError, message: impl Into<String>) -> Self {
+        let origin = e.origin();
+        let kind = match e {
+            FluxError::CompilationError(_) => ErrorKind::Compilation,
+            _ if origin == ErrorOrigin::Platform => ErrorKind::Internal,
+            _ => ErrorKind::Runtime,
+        };
+        Self::error_with_origin(kind, origin, message)
+    }
+
+    pub fn error_with_origin(
+        kind: ErrorKind,
+        origin: ErrorOrigin,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::Error {
+            kind,
+            origin,
+            message: message.into(),
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success)
+    }
+
+    /// `flux-cli invoke` 的退出码：成功 0、执行错误 1、超时 3、取消 4、超出资源限制 5
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Success => 0,
+            Self::Error { .. } => 1,
+            Self::Timeout => 3,
+            Self::Cancelled => 4,
+            Self::ResourceLimitExceeded { .. } => 5,
+        }
+    }
+
+    /// 失败的归属（成功时为 `None`）；超时和超出资源限制属于限制，取消由平台发起
+    pub fn origin(&self) -> Option<ErrorOrigin> {
+        match self {
+            Self::Success => None,
+            Self::Error { origin, .. } => Some(*origin),
+            Self::Timeout | Self::ResourceLimitExceeded { .. } => Some(ErrorOrigin::Limit),
+            Self::Cancelled => Some(ErrorOrigin::Platform),
+        }
+    }
+
+    /// 失败原因（成功时为 `None`）
+    pub fn failure_message(&self) -> Option<String> {
+        match self {
+            Self::Success => None,
+            Self::Error { message, .. } => Some(message.clone()),
+            Self::Timeout => Some("Execution timeout".to_string()),
+            Self::Cancelled => Some("Execution cancelled".to_string()),
+            Self::ResourceLimitExceeded { resource } => Some(resource.message().to_string()),
+        }
+    }
+}
+
+/// `ExecutionStatus` 的序列化格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusWireFormat {
+    /// 旧格式，保留一个版本周期供客户端迁移
+    #[default]
+    Legacy,
+    /// 带 `state` 标签和失败原因的新格式
+    Typed,
+}
+
+static LEGACY_WIRE_FORMAT: AtomicBool = AtomicBool::new(true);
+
+/// 设置 `ExecutionStatus` 的序列化格式（进程级开关，启动时根据配置设置）
+pub fn set_wire_format(format: StatusWireFormat) {
+    LEGACY_WIRE_FORMAT.store(format == StatusWireFormat::Legacy, Ordering::Relaxed);
+}
+
+/// 当前的序列化格式
+pub fn wire_format() -> StatusWireFormat {
+    if LEGACY_WIRE_FORMAT.load(Ordering::Relaxed) {
+        StatusWireFormat::Legacy
+    } else {
+        StatusWireFormat::Typed
+    }
+}
+
+/// 新格式：`{"state": "error", "kind": "runtime", "origin": "user_code", "message": "..."}`
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(tag = "state", rename_all = "snake_case")]
+#[schema(as = ExecutionStatus)]
+enum TypedStatus {
+    Success,
+    Error {
+        kind: ErrorKind,
+        /// 缺省时取类别的默认归属
+        #[serde(default)]
+        origin: Option<ErrorOrigin>,
+        message: String,
+    },
+    Timeout,
+    Cancelled,
+    ResourceLimitExceeded {
+        resource: ResourceKind,
+    },
+}
+
+/// 旧格式（serde 默认的外部标签表示）
+#[derive(Serialize, Deserialize)]
+enum LegacyStatus {
+    Success,
+    Completed,
+    Error(String),
+    Failed,
+    Timeout,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WireStatus {
+    Typed(TypedStatus),
+    Legacy(LegacyStatus),
+}
+
+impl From<&ExecutionStatus> for TypedStatus {
+    fn from(status: &ExecutionStatus) -> Self {
+        match status.clone() {
+            ExecutionStatus::Success => Self::Success,
+            ExecutionStatus::Error {
+                kind,
+                origin,
+                message,
+            } => Self::Error {
+                kind,
+                origin: Some(origin),
+                message,
+            },
+            ExecutionStatus::Timeout => Self::Timeout,
+            ExecutionStatus::Cancelled => Self::Cancelled,
+            ExecutionStatus::ResourceLimitExceeded { resource } => {
+                Self::ResourceLimitExceeded { resource }
+            }
+        }
+    }
+}
+
+impl From<TypedStatus> for ExecutionStatus {
+    fn from(status: TypedStatus) -> Self {
+        match status {
+            TypedStatus::Success => Self::Success,
+            TypedStatus::Error {
+                kind,
+                origin,
+                message,
+            } => Self::error_with_origin(kind, origin.unwrap_or(kind.origin()), message),
+            TypedStatus::Timeout => Self::Timeout,
+            TypedStatus::Cancelled => Self::Cancelled,
+            TypedStatus::ResourceLimitExceeded { resource } => {
+                Self::ResourceLimitExceeded { resource }
+            }
+        }
+    }
+}
+
+impl From<&ExecutionStatus> for LegacyStatus {
+    /// 旧格式没有的变体降级为 `Error`，保留原因
+    fn from(status: &ExecutionStatus) -> Self {
+        match status {
+            ExecutionStatus::Success => Self::Success,
+            ExecutionStatus::Timeout => Self::Timeout,
+            other => Self::Error(other.failure_message().unwrap_or_default()),
+        }
+    }
+}
+
+impl From<LegacyStatus> for ExecutionStatus {
+    fn from(status: LegacyStatus) -> Self {
+        match status {
+            LegacyStatus::Success | LegacyStatus::Completed => Self::Success,
+            LegacyStatus::Error(message) => Self::error(ErrorKind::Runtime, message),
+            LegacyStatus::Failed => Self::error(ErrorKind::Unknown, String::new()),
+            LegacyStatus::Timeout => Self::Timeout,
+        }
+    }
+}
+
+impl Serialize for ExecutionStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if wire_format() == StatusWireFormat::Legacy {
+            LegacyStatus::from(self).serialize(serializer)
+        } else {
+            TypedStatus::from(self).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ExecutionStatus {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match WireStatus::deserialize(deserializer)? {
+            WireStatus::Typed(status) => Ok(status.into()),
+            WireStatus::Legacy(status) => Ok(status.into()),
+        }
+    }
+}
+
+impl<'s> ToSchema<'s> for ExecutionStatus {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        TypedStatus::schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn all_variants() -> Vec<ExecutionStatus> {
+        vec![
+            ExecutionStatus::Success,
+            ExecutionStatus::error(ErrorKind::Runtime, "boom"),
+            ExecutionStatus::Timeout,
+            ExecutionStatus::Cancelled,
+            ExecutionStatus::ResourceLimitExceeded {
+                resource: ResourceKind::Memory,
+            },
+        ]
+    }
+
+    fn encode(status: &ExecutionStatus, legacy: bool) -> Value {
+        // 绕过全局开关，避免并行测试互相干扰
+        if legacy {
+            serde_json::to_value(LegacyStatus::from(status)).unwrap()
+        } else {
+            serde_json::to_value(TypedStatus::from(status)).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_wire_format_for_every_variant() {
+        let typed = [
+            json!({"state": "success"}),
+            json!({"state": "error", "kind": "runtime", "origin": "user_code", "message": "boom"}),
+            json!({"state": "timeout"}),
+            json!({"state": "cancelled"}),
+            json!({"state": "resource_limit_exceeded", "resource": "memory"}),
+        ];
+        let legacy = [
+            json!("Success"),
+            json!({"Error": "boom"}),
+            json!("Timeout"),
+            json!({"Error": "Execution cancelled"}),
+            json!({"Error": "Memory limit exceeded"}),
+        ];
+
+        for ((status, typed), legacy) in all_variants().iter().zip(typed).zip(legacy) {
+            assert_eq!(encode(status, false), typed, "{status:?}");
+            assert_eq!(encode(status, true), legacy, "{status:?}");
+            // 新格式可无损往返
+            let decoded: ExecutionStatus = serde_json::from_value(typed).unwrap();
+            assert_eq!(&decoded, status);
+        }
+        for kind in [
+            ErrorKind::Compilation,
+            ErrorKind::Sandbox,
+            ErrorKind::Internal,
+            ErrorKind::Unknown,
+        ] {
+            let status = ExecutionStatus::error(kind, "x");
+            assert_eq!(encode(&status, false)["kind"], kind.as_str());
+            assert_eq!(encode(&status, false)["origin"], "platform");
+        }
+        // 指定的归属覆盖类别的默认归属，缺省归属的新格式按类别补齐
+        let status =
+            ExecutionStatus::error_with_origin(ErrorKind::Runtime, ErrorOrigin::Input, "x");
+        let typed = encode(&status, false);
+        assert_eq!(typed["origin"], "input");
+        assert_eq!(
+            serde_json::from_value::<ExecutionStatus>(typed).unwrap(),
+            status
+        );
+        let decoded: ExecutionStatus = serde_json::from_value(
+            json!({"state": "error", "kind": "compilation", "message": "x"}),
+        )
+        .unwrap();
+        assert_eq!(decoded.origin(), Some(ErrorOrigin::Platform));
+        assert_eq!(
+            encode(
+                &ExecutionStatus::ResourceLimitExceeded {
+                    resource: ResourceKind::Cpu
+                },
+                false
+            )["resource"],
+            "cpu"
+        );
+    }
+
+    #[test]
+    fn test_legacy_strings_are_accepted() {
+        let decode = |value: Value| serde_json::from_value::<ExecutionStatus>(value).unwrap();
+        assert_eq!(decode(json!("Success")), ExecutionStatus::Success);
+        assert_eq!(decode(json!("Completed")), ExecutionStatus::Success);
+        assert_eq!(decode(json!("Timeout")), ExecutionStatus::Timeout);
+        assert_eq!(
+            decode(json!({"Error": "boom"})),
+            ExecutionStatus::error(ErrorKind::Runtime, "boom")
+        );
+        assert_eq!(
+            decode(json!("Failed")),
+            ExecutionStatus::error(ErrorKind::Unknown, "")
+        );
+        assert!(serde_json::from_value::<ExecutionStatus>(json!("Exploded")).is_err());
+    }
+}
@@ -1,13 +1,81 @@
 use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+use crate::functions::compression::{DecompressionLimits, Encoding};
+use crate::functions::object_storage::{self, ObjectStoreStorage, RetrySettings};
 use crate::functions::{FluxError, FunctionMetadata};
 
+/// 函数目录的持久化配置（`[storage]`），`url` 和 `path` 都未设置时函数只保存在内存中
+///
+/// 每个调度器配置使用后端下以配置名命名的子前缀（子目录）。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// 对象存储地址：`s3://bucket/prefix`、`gs://bucket/prefix`、`az://container/prefix`、
+    /// `file:///path`
+    pub url: Option<String>,
+    /// 本地目录（文件后端），与 `url` 二选一
+    pub path: Option<PathBuf>,
+    /// 对象存储的配置项（例如 `aws_region`、`aws_endpoint`），缺省项从环境变量读取
+    pub options: BTreeMap<String, String>,
+    /// 启动时并发读取的记录数
+    pub load_concurrency: usize,
+    /// 暂时性错误的重试次数
+    pub max_retries: u32,
+    /// 首次重试的退避时间（毫秒），之后每次翻倍
+    pub retry_backoff_ms: u64,
+    /// 启动时后端不可达的情况下重新加载的间隔（秒）
+    pub reload_interval_secs: u64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            path: None,
+            options: BTreeMap::new(),
+            load_concurrency: object_storage::DEFAULT_LOAD_CONCURRENCY,
+            max_retries: 3,
+            retry_backoff_ms: 200,
+            reload_interval_secs: 10,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// 调度器配置 `profile` 的持久化后端，未配置时为 `None`
+    pub fn open(&self, profile: &str) -> Result<Option<Arc<dyn RegistryStorage>>> {
+        match (&self.url, &self.path) {
+            (Some(_), Some(_)) => {
+                anyhow::bail!("storage.url and storage.path cannot both be set")
+            }
+            (Some(url), None) => {
+                let storage = ObjectStoreStorage::from_url(url, &self.options)?
+                    .with_load_concurrency(self.load_concurrency)
+                    .with_retry(RetrySettings {
+                        max_retries: self.max_retries,
+                        backoff: Duration::from_millis(self.retry_backoff_ms),
+                    })
+                    .scoped(profile);
+                Ok(Some(Arc::new(storage)))
+            }
+            (None, Some(path)) => Ok(Some(Arc::new(FileSystemStorage::new(path.join(profile))?))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// 解压后的源代码大小上限（字节）
+const MAX_SOURCE_BYTES: usize = 64 * 1024 * 1024;
+
 /// 函数存储记录
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionRecord {
@@ -19,6 +87,10 @@ pub struct FunctionRecord {
     pub version: String,
     pub dependencies: Vec<String>,
     pub checksum: String,
+    /// 源代码的压缩方式：设置时 `source_code` 为压缩数据的 base64，
+    /// 与源代码相同的 `metadata.code` 被省略，读取时解压并按 `checksum` 校验
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Encoding>,
 }
 
 impl FunctionRecord {
@@ -41,9 +113,84 @@ impl FunctionRecord {
             version,
             dependencies,
             checksum,
+            compression: None,
         }
     }
 
+    /// 压缩源代码（已压缩时不变）
+    pub fn compressed(mut self, encoding: Encoding) -> Result<Self> {
+        if self.compression.is_some() {
+            return Ok(self);
+        }
+        let compressed = encoding
+            .compress(self.source_code.as_bytes())
+            .with_context(|| format!("Failed to compress function: {}", self.metadata.name))?;
+        if self.metadata.code == self.source_code {
+            self.metadata.code.clear();
+        }
+        self.source_code = STANDARD.encode(compressed);
+        self.compression = Some(encoding);
+        Ok(self)
+    }
+
+    /// 解压源代码并校验内容哈希（未压缩时不变）
+    pub fn decompressed(mut self) -> Result<Self> {
+        let Some(encoding) = self.compression else {
+            return Ok(self);
+        };
+        let name = &self.metadata.name;
+        let compressed = STANDARD
+            .decode(&self.source_code)
+            .with_context(|| format!("Invalid compressed source of function: {name}"))?;
+        let source = encoding
+            .decompress(
+                &compressed,
+                DecompressionLimits::max_bytes(MAX_SOURCE_BYTES),
+            )
+            .map_err(|e| anyhow::anyhow!("Failed to decompress function {name}: {e}"))?;
+        let source = String::from_utf8(source)
+            .with_context(|| format!("Decompressed source of function {name} is not UTF-8"))?;
+
+        let checksum = format!("{:x}", md5::compute(&source));
+        if checksum != self.checksum {
+            return Err(FluxError::StorageError(format!(
+                "Checksum mismatch for function {name}: expected {}, got {checksum}",
+                self.checksum
+            ))
+            .into());
+        }
+        if self.metadata.code.is_empty() {
+            self.metadata.code = source.clone();
+        }
+        self.source_code = source;
+        self.compression = None;
+        Ok(self)
+    }
+
+    /// 注册表中函数的存储记录（源代码取自 `metadata.code`）
+    pub fn from_metadata(metadata: &FunctionMetadata) -> Self {
+        Self {
+            created_at: metadata.created_at,
+            updated_at: metadata.updated_at,
+            ..Self::new(
+                metadata.clone(),
+                metadata.code.clone(),
+                None,
+                metadata.version.clone(),
+                metadata.dependencies.clone(),
+            )
+        }
+    }
+
+    /// 记录中的函数元数据（元数据省略代码时以源代码补齐）
+    pub fn into_metadata(self) -> FunctionMetadata {
+        let mut metadata = self.metadata;
+        if metadata.code.is_empty() {
+            metadata.code = self.source_code;
+        }
+        metadata
+    }
+
     pub fn update_code(&mut self, new_code: String) {
         self.source_code = new_code;
         self.updated_at = chrono::Utc::now();
@@ -70,10 +217,43 @@ pub trait FunctionStorage: Send + Sync {
     async fn restore(&self, backup_path: &Path) -> Result<()>;
 }
 
+/// 函数注册表的持久化后端
+///
+/// 记录以函数名为键，按记录中的修订号（`metadata.revision`）做乐观并发控制：写入和删除
+/// 时给出调用方最后看到的修订号，与后端当前的修订号不一致时返回 `RevisionMismatch`，
+/// 多个副本共享同一后端时不会互相覆盖。后端暂时不可达时返回 `StorageUnavailable`。
+#[async_trait::async_trait]
+pub trait RegistryStorage: Send + Sync + std::fmt::Debug {
+    /// 加载全部函数记录（无法解析的记录跳过并记录警告）
+    async fn load_all(&self) -> crate::functions::Result<Vec<FunctionRecord>>;
+
+    /// 写入函数记录；`expected_revision` 为 `None` 时要求后端中没有该函数
+    /// （否则返回 `FunctionAlreadyExists`），否则要求后端中的修订号与之一致
+    async fn put(
+        &self,
+        record: &FunctionRecord,
+        expected_revision: Option<u64>,
+    ) -> crate::functions::Result<()>;
+
+    /// 删除函数记录，`expected_revision` 不为空时要求修订号一致；返回记录是否存在
+    async fn delete(
+        &self,
+        name: &str,
+        expected_revision: Option<u64>,
+    ) -> crate::functions::Result<bool>;
+
+    /// 后端中函数当前的修订号（不存在时为 `None`）
+    async fn get_revision(&self, name: &str) -> crate::functions::Result<Option<u64>>;
+}
+
 /// 文件系统存储实现
+///
+/// 启用压缩时源代码压缩后写入磁盘；从磁盘加载的记录保持压缩状态，读取单个函数时才解压。
+#[derive(Debug)]
 pub struct FileSystemStorage {
     storage_dir: PathBuf,
     functions: Arc<RwLock<HashMap<String, FunctionRecord>>>,
+    compression: Option<Encoding>,
 }
 
 impl FileSystemStorage {
@@ -85,11 +265,18 @@ impl FileSystemStorage {
         let storage = Self {
             storage_dir,
             functions: Arc::new(RwLock::new(HashMap::new())),
+            compression: None,
         };
 
         Ok(storage)
     }
 
+    /// 以指定编码压缩写入的源代码（已有的未压缩文件仍可读取）
+    pub fn with_compression(mut self, encoding: Encoding) -> Self {
+        self.compression = Some(encoding);
+        self
+    }
+
     fn get_function_file_path(&self, name: &str) -> PathBuf {
         self.storage_dir.join(format!("{name}.json"))
     }
@@ -113,19 +300,19 @@ impl FileSystemStorage {
             let entry = entry?;
             let path = entry.path();
 
-            if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
-                    if name.starts_with("backup_") {
-                        continue; // 跳过备份文件
-                    }
+            if path.extension().and_then(|s| s.to_str()) == Some("json")
+                && let Some(name) = path.file_stem().and_then(|s| s.to_str())
+            {
+                if name.starts_with("backup_") {
+                    continue; // 跳过备份文件
+                }
 
-                    match self.load_function_from_file(&path).await {
-                        Ok(record) => {
-                            functions.insert(name.to_string(), record);
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to load function {} from disk: {}", name, e);
-                        }
+                match self.load_function_from_file(&path).await {
+                    Ok(record) => {
+                        functions.insert(name.to_string(), record);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to load function {} from disk: {}", name, e);
                     }
                 }
             }
@@ -147,6 +334,14 @@ impl FileSystemStorage {
 
     async fn save_function_to_file(&self, name: &str, record: &FunctionRecord) -> Result<()> {
         let path = self.get_function_file_path(name);
+        let compressed;
+        let record = match self.compression {
+            Some(encoding) if record.compression.is_none() => {
+                compressed = record.clone().compressed(encoding)?;
+                &compressed
+            }
+            _ => record,
+        };
         let content = serde_json::to_string_pretty(record)
             .with_context(|| format!("Failed to serialize function: {name}"))?;
 
@@ -174,11 +369,11 @@ impl FunctionStorage for FileSystemStorage {
     }
 
     async fn load(&self, name: &str) -> Result<Option<FunctionRecord>> {
-        // 先从内存加载
+        // 先从内存加载（压缩的记录在此时解压）
         {
             let functions = self.functions.read().await;
             if let Some(record) = functions.get(name) {
-                return Ok(Some(record.clone()));
+                return record.clone().decompressed().map(Some);
             }
         }
 
@@ -187,6 +382,7 @@ impl FunctionStorage for FileSystemStorage {
         if path.exists() {
             match self.load_function_from_file(&path).await {
                 Ok(record) => {
+                    let record = record.decompressed()?;
                     // 加载到内存
                     {
                         let mut functions = self.functions.write().await;
@@ -302,6 +498,76 @@ impl FunctionStorage for FileSystemStorage {
     }
 }
 
+/// 单个进程独占存储目录，修订号比较和写入之间不需要跨进程的原子性
+#[async_trait::async_trait]
+impl RegistryStorage for FileSystemStorage {
+    async fn load_all(&self) -> crate::functions::Result<Vec<FunctionRecord>> {
+        let mut records = Vec::new();
+        for name in FunctionStorage::list(self).await? {
+            match FunctionStorage::load(self, &name).await {
+                Ok(Some(record)) => records.push(record),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to load function {} from disk: {:#}", name, e),
+            }
+        }
+        Ok(records)
+    }
+
+    async fn put(
+        &self,
+        record: &FunctionRecord,
+        expected_revision: Option<u64>,
+    ) -> crate::functions::Result<()> {
+        let name = &record.metadata.name;
+        let current = RegistryStorage::get_revision(self, name).await?;
+        check_stored_revision(name, current, expected_revision)?;
+        self.store(name, record.clone()).await?;
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        name: &str,
+        expected_revision: Option<u64>,
+    ) -> crate::functions::Result<bool> {
+        let Some(current) = RegistryStorage::get_revision(self, name).await? else {
+            return Ok(false);
+        };
+        if expected_revision.is_some() {
+            check_stored_revision(name, Some(current), expected_revision)?;
+        }
+        Ok(FunctionStorage::delete(self, name).await?)
+    }
+
+    async fn get_revision(&self, name: &str) -> crate::functions::Result<Option<u64>> {
+        Ok(FunctionStorage::load(self, name)
+            .await?
+            .map(|record| record.metadata.revision))
+    }
+}
+
+/// 比较后端中的修订号：期望为 `None` 时要求记录不存在
+pub(crate) fn check_stored_revision(
+    name: &str,
+    current: Option<u64>,
+    expected: Option<u64>,
+) -> crate::functions::Result<()> {
+    match (current, expected) {
+        (None, None) => Ok(()),
+        (Some(_), None) => Err(FluxError::FunctionAlreadyExists {
+            name: name.to_string(),
+        }),
+        (current, Some(expected)) if current != Some(expected) => {
+            Err(FluxError::RevisionMismatch {
+                name: name.to_string(),
+                expected,
+                current: current.unwrap_or(0),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
 /// 内存存储实现（用于测试和临时存储）
 pub struct MemoryStorage {
     functions: Arc<RwLock<HashMap<String, FunctionRecord>>>,
@@ -442,4 +708,40 @@ mod tests {
         let loaded = storage2.load("test").await.unwrap().unwrap();
         assert_eq!(loaded.metadata.name, "test");
     }
+
+    #[tokio::test]
+    async fn test_compressed_filesystem_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileSystemStorage::new(temp_dir.path().to_path_buf())
+            .unwrap()
+            .with_compression(Encoding::Zstd);
+
+        let code = "return input.a + input.b;\n".repeat(200);
+        let metadata = FunctionMetadata::new("sum".to_string(), code.clone());
+        let record = FunctionRecord::new(metadata, code.clone(), None, "1.0.0".to_string(), vec![]);
+        storage.store("sum", record).await.unwrap();
+
+        // 磁盘上的记录被压缩并标记
+        let path = temp_dir.path().join("sum.json");
+        let on_disk: FunctionRecord =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(on_disk.compression, Some(Encoding::Zstd));
+        assert!(on_disk.source_code.len() < code.len() / 4);
+        assert!(on_disk.metadata.code.is_empty());
+
+        // 重新加载时解压并校验哈希
+        let storage2 = FileSystemStorage::new(temp_dir.path().to_path_buf()).unwrap();
+        assert_eq!(storage2.list().await.unwrap(), vec!["sum"]);
+        let loaded = storage2.load("sum").await.unwrap().unwrap();
+        assert_eq!(loaded.source_code, code);
+        assert_eq!(loaded.metadata.code, code);
+        assert_eq!(loaded.compression, None);
+
+        // 内容被篡改时拒绝读取
+        let mut tampered = on_disk;
+        tampered.checksum = "0".repeat(32);
+        fs::write(&path, serde_json::to_string(&tampered).unwrap()).unwrap();
+        let storage3 = FileSystemStorage::new(temp_dir.path().to_path_buf()).unwrap();
+        assert!(storage3.load("sum").await.is_err());
+    }
 }
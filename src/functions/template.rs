@@ -0,0 +1,386 @@
+use super::{FluxError, Result};
+use serde_json::{Map, Number, Value};
+
+/// 调用输入模板
+///
+/// 模板本身是 JSON，字符串中的 `{{path}}` 占位符在调用时替换为输入中对应的值：
+///
+/// ```text
+/// {"user_id": "{{body.user.id | number}}", "tier": "{{body.tier | default(\"free\")}}"}
+/// ```
+///
+/// - 路径用 `.` 分隔，数组下标写作 `items.0` 或 `items[0]`，`$` 表示整个输入
+/// - 整个字符串只有一个占位符时保留原值的类型，否则按文本拼接
+/// - 过滤器：`default(<JSON>)` 路径不存在时使用的值，`number`/`string`/`bool` 类型转换
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputTemplate {
+    root: Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Literal(Value),
+    /// 整个字符串就是一个占位符
+    Placeholder(Placeholder),
+    /// 文本与占位符拼接
+    Interpolated(Vec<Segment>),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Placeholder {
+    /// 原始路径（用于错误信息）
+    path: String,
+    segments: Vec<PathSegment>,
+    default: Option<Value>,
+    coercion: Option<Coercion>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Coercion {
+    Number,
+    String,
+    Bool,
+}
+
+fn invalid(reason: impl Into<String>) -> FluxError {
+    FluxError::ValidationError {
+        reason: format!("Invalid input template: {}", reason.into()),
+    }
+}
+
+impl InputTemplate {
+    /// 解析模板（语法错误返回 `ValidationError`）
+    pub fn parse(template: &str) -> Result<Self> {
+        let value: Value =
+            serde_json::from_str(template).map_err(|e| invalid(format!("not valid JSON: {e}")))?;
+        Ok(Self {
+            root: Node::parse(value)?,
+        })
+    }
+
+    /// 将模板应用到调用输入，路径无法解析时返回指明该路径的 `ValidationError`
+    pub fn apply(&self, input: &Value) -> Result<Value> {
+        self.root.render(input)
+    }
+}
+
+impl Node {
+    fn parse(value: Value) -> Result<Self> {
+        Ok(match value {
+            Value::String(text) => Self::parse_string(&text)?,
+            Value::Array(items) => {
+                Self::Array(items.into_iter().map(Self::parse).collect::<Result<_>>()?)
+            }
+            Value::Object(map) => Self::Object(
+                map.into_iter()
+                    .map(|(key, value)| Ok((key, Self::parse(value)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            other => Self::Literal(other),
+        })
+    }
+
+    fn parse_string(text: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let end = rest[start..]
+                .find("}}")
+                .map(|end| start + end)
+                .ok_or_else(|| invalid(format!("unterminated placeholder in \"{text}\"")))?;
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            segments.push(Segment::Placeholder(Placeholder::parse(
+                &rest[start + 2..end],
+            )?));
+            rest = &rest[end + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+
+        Ok(match segments.as_slice() {
+            [] => Self::Literal(Value::String(String::new())),
+            [Segment::Text(text)] => Self::Literal(Value::String(text.clone())),
+            [Segment::Placeholder(placeholder)] => Self::Placeholder(placeholder.clone()),
+            _ => Self::Interpolated(segments),
+        })
+    }
+
+    fn render(&self, input: &Value) -> Result<Value> {
+        Ok(match self {
+            Self::Literal(value) => value.clone(),
+            Self::Placeholder(placeholder) => placeholder.resolve(input)?,
+            Self::Interpolated(segments) => {
+                let mut text = String::new();
+                for segment in segments {
+                    match segment {
+                        Segment::Text(part) => text.push_str(part),
+                        Segment::Placeholder(placeholder) => {
+                            text.push_str(&to_text(&placeholder.resolve(input)?))
+                        }
+                    }
+                }
+                Value::String(text)
+            }
+            Self::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| item.render(input))
+                    .collect::<Result<_>>()?,
+            ),
+            Self::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, node)| Ok((key.clone(), node.render(input)?)))
+                    .collect::<Result<Map<_, _>>>()?,
+            ),
+        })
+    }
+}
+
+impl Placeholder {
+    fn parse(expression: &str) -> Result<Self> {
+        let mut parts = split_filters(expression).into_iter();
+        let path = parts.next().unwrap_or_default().trim().to_string();
+        let mut placeholder = Self {
+            segments: parse_path(&path)?,
+            path,
+            default: None,
+            coercion: None,
+        };
+
+        for filter in parts {
+            let filter = filter.trim();
+            match filter {
+                "number" => placeholder.coercion = Some(Coercion::Number),
+                "string" => placeholder.coercion = Some(Coercion::String),
+                "bool" => placeholder.coercion = Some(Coercion::Bool),
+                _ => {
+                    let literal = filter
+                        .strip_prefix("default(")
+                        .and_then(|rest| rest.strip_suffix(')'))
+                        .ok_or_else(|| invalid(format!("unknown filter '{filter}'")))?;
+                    let value = serde_json::from_str(literal.trim()).map_err(|e| {
+                        invalid(format!(
+                            "default for '{}' is not JSON: {e}",
+                            placeholder.path
+                        ))
+                    })?;
+                    placeholder.default = Some(value);
+                }
+            }
+        }
+        Ok(placeholder)
+    }
+
+    fn resolve(&self, input: &Value) -> Result<Value> {
+        let value = lookup(input, &self.segments)
+            .cloned()
+            .or_else(|| self.default.clone())
+            .ok_or_else(|| FluxError::ValidationError {
+                reason: format!("Input template path '{}' could not be resolved", self.path),
+            })?;
+        match self.coercion {
+            Some(coercion) => coerce(value, coercion).ok_or_else(|| FluxError::ValidationError {
+                reason: format!(
+                    "Input template path '{}' cannot be converted to {}",
+                    self.path,
+                    coercion.as_str()
+                ),
+            }),
+            None => Ok(value),
+        }
+    }
+}
+
+impl Coercion {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Bool => "bool",
+        }
+    }
+}
+
+/// 按 `|` 拆分过滤器（忽略 JSON 字符串字面量中的 `|`）
+fn split_filters(expression: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut in_string, mut escaped) = (0, false, false);
+    for (i, c) in expression.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '|' if !in_string => {
+                parts.push(&expression[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&expression[start..]);
+    parts
+}
+
+fn parse_path(path: &str) -> Result<Vec<PathSegment>> {
+    if path == "$" {
+        return Ok(Vec::new());
+    }
+    let path = path.strip_prefix("$.").unwrap_or(path);
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, mut indexes) = match part.find('[') {
+            Some(bracket) => (&part[..bracket], &part[bracket..]),
+            None => (part, ""),
+        };
+        if key.is_empty() && indexes.is_empty() {
+            return Err(invalid(format!("empty segment in path '{path}'")));
+        }
+        if !key.is_empty() {
+            segments.push(match key.parse() {
+                Ok(index) => PathSegment::Index(index),
+                Err(_) => PathSegment::Key(key.to_string()),
+            });
+        }
+        while !indexes.is_empty() {
+            let index = indexes
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once(']'))
+                .and_then(|(index, rest)| Some((index.parse().ok()?, rest)));
+            let Some((index, rest)) = index else {
+                return Err(invalid(format!("malformed index in path '{path}'")));
+            };
+            segments.push(PathSegment::Index(index));
+            indexes = rest;
+        }
+    }
+    Ok(segments)
+}
+
+fn lookup<'a>(input: &'a Value, segments: &[PathSegment]) -> Option<&'a Value> {
+    segments
+        .iter()
+        .try_fold(input, |value, segment| match (segment, value) {
+            (PathSegment::Key(key), Value::Object(map)) => map.get(key),
+            (PathSegment::Index(index), Value::Array(items)) => items.get(*index),
+            // 数字键也可以用于对象
+            (PathSegment::Index(index), Value::Object(map)) => map.get(&index.to_string()),
+            _ => None,
+        })
+}
+
+fn coerce(value: Value, coercion: Coercion) -> Option<Value> {
+    match (coercion, value) {
+        (Coercion::Number, Value::Number(number)) => Some(Value::Number(number)),
+        (Coercion::Number, Value::String(text)) => {
+            let text = text.trim();
+            text.parse::<i64>()
+                .ok()
+                .map(Number::from)
+                .or_else(|| text.parse::<f64>().ok().and_then(Number::from_f64))
+                .map(Value::Number)
+        }
+        (Coercion::String, value) => Some(Value::String(to_text(&value))),
+        (Coercion::Bool, Value::Bool(flag)) => Some(Value::Bool(flag)),
+        (Coercion::Bool, Value::String(text)) => match text.trim() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// 拼接到字符串中的文本：字符串取原文，其他值取 JSON 表示
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn apply(template: Value, input: Value) -> Result<Value> {
+        InputTemplate::parse(&template.to_string())?.apply(&input)
+    }
+
+    #[test]
+    fn test_picks_fields_with_defaults_and_coercions() {
+        let webhook = json!({
+            "event": "order.created",
+            "body": {"order": {"id": "1042", "items": [{"sku": "A-1"}], "paid": "true"}}
+        });
+        let output = apply(
+            json!({
+                "order_id": "{{body.order.id | number}}",
+                "first_sku": "{{body.order.items[0].sku}}",
+                "paid": "{{ body.order.paid | bool }}",
+                "currency": "{{body.order.currency | default(\"EUR\")}}",
+                "summary": "{{event}}: {{body.order.items.0.sku}}",
+                "raw": "{{$}}",
+                "fixed": [1, true]
+            }),
+            webhook.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            output,
+            json!({
+                "order_id": 1042,
+                "first_sku": "A-1",
+                "paid": true,
+                "currency": "EUR",
+                "summary": "order.created: A-1",
+                "raw": webhook,
+                "fixed": [1, true]
+            })
+        );
+        // 默认值中的 `|` 不会被当作过滤器分隔符
+        assert_eq!(
+            apply(json!("{{missing | default(\"a|b\")}}"), json!({})).unwrap(),
+            json!("a|b")
+        );
+    }
+
+    #[test]
+    fn test_unresolved_paths_and_bad_templates_are_rejected() {
+        let err = apply(json!({"id": "{{body.user.id}}"}), json!({"body": {}})).unwrap_err();
+        assert!(
+            matches!(&err, FluxError::ValidationError { reason } if reason.contains("'body.user.id'"))
+        );
+
+        let err = apply(json!("{{name | number}}"), json!({"name": "abc"})).unwrap_err();
+        assert!(err.to_string().contains("cannot be converted to number"));
+
+        for bad in [
+            "not json",
+            r#""{{unterminated""#,
+            r#""{{a | upper}}""#,
+            r#""{{a..b}}""#,
+        ] {
+            assert!(InputTemplate::parse(bad).is_err(), "{bad}");
+        }
+    }
+}
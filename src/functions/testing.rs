@@ -0,0 +1,431 @@
+//! 函数自带的测试用例
+//!
+//! 注册时可以附带 `tests`，`POST /functions/:name/test` 以测试模式执行全部或指定的用例。
+//! 测试调用经过正常的执行路径，但与金丝雀探测一样没有副作用：不计入调用统计、不写入函数缓存，
+//! 在命名空间中与正常调用分开计数。部署和导入设置 `activate_if_tests_pass` 时，用例全部通过的
+//! 函数才会激活。
+
+use super::{ExecutionStatus, FluxError, FunctionMetadata, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// 每个函数的用例数上限
+pub const MAX_TEST_CASES: usize = 50;
+
+/// 一次测试运行的总时长上限（毫秒），超出后剩余的用例记为失败
+pub const MAX_TEST_RUN_MS: u64 = 120_000;
+
+/// 一次测试运行中同时执行的用例数
+pub const TEST_CONCURRENCY: usize = 4;
+
+/// 每个用例报告的输出差异数上限
+const MAX_DIFFS: usize = 20;
+
+/// 期望输出的比较方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMatch {
+    /// 完全相等（数字按数值比较）
+    #[default]
+    Exact,
+    /// 对象只比较期望中出现的字段（递归），其他值要求相等
+    Subset,
+}
+
+impl OutputMatch {
+    fn is_exact(&self) -> bool {
+        *self == Self::Exact
+    }
+}
+
+/// 用例期望的执行状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TestStatus {
+    #[default]
+    Success,
+    /// 执行失败，或调用在执行前被拒绝（如输入不符合 schema）
+    Error,
+    Timeout,
+    Cancelled,
+    ResourceLimitExceeded,
+}
+
+impl TestStatus {
+    pub fn of(status: &ExecutionStatus) -> Self {
+        match status {
+            ExecutionStatus::Success => Self::Success,
+            ExecutionStatus::Error { .. } => Self::Error,
+            ExecutionStatus::Timeout => Self::Timeout,
+            ExecutionStatus::Cancelled => Self::Cancelled,
+            ExecutionStatus::ResourceLimitExceeded { .. } => Self::ResourceLimitExceeded,
+        }
+    }
+}
+
+/// 函数的一个测试用例
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FunctionTestCase {
+    /// 用例名（同一函数内唯一）
+    pub name: String,
+    /// 调用输入（默认 null）
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub input: Value,
+    /// 期望的输出，缺省时只检查执行状态；失败的调用输出为 `{"error": "..."}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub expected_output: Option<Value>,
+    /// 期望的执行状态（默认 success）
+    #[serde(default)]
+    pub expected_status: TestStatus,
+    /// 期望输出的比较方式（默认 exact）
+    #[serde(default, skip_serializing_if = "OutputMatch::is_exact")]
+    pub output_match: OutputMatch,
+    /// 用例的超时（毫秒），缺省使用函数超时
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// 实际输出与期望不一致的位置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct OutputDiff {
+    /// JSON Pointer（整个输出为空字符串）
+    pub path: String,
+    /// 期望的值（实际输出多出的字段缺省）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub expected: Option<Value>,
+    /// 实际的值（实际输出缺少的字段缺省）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub actual: Option<Value>,
+}
+
+/// 单个用例的结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TestCaseResult {
+    pub name: String,
+    pub passed: bool,
+    pub expected_status: TestStatus,
+    pub actual_status: TestStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub expected_output: Option<Value>,
+    #[schema(value_type = Object)]
+    pub actual_output: Value,
+    /// 输出差异（最多 20 处，未配置期望输出时为空）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diffs: Vec<OutputDiff>,
+    pub duration_ms: u64,
+    /// 调用失败的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 一次测试运行的报告
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FunctionTestReport {
+    pub function: String,
+    pub version: String,
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub duration_ms: u64,
+    pub results: Vec<TestCaseResult>,
+}
+
+impl FunctionTestReport {
+    pub fn new(
+        function: &FunctionMetadata,
+        results: Vec<TestCaseResult>,
+        elapsed: Duration,
+    ) -> Self {
+        let passed = results.iter().filter(|result| result.passed).count();
+        Self {
+            function: function.name.clone(),
+            version: function.version.clone(),
+            total: results.len(),
+            passed,
+            failed: results.len() - passed,
+            duration_ms: elapsed.as_millis() as u64,
+            results,
+        }
+    }
+
+    pub fn all_passed(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// 失败用例的摘要，例如 `1 of 2 test cases failed: negative`
+    pub fn summary(&self) -> String {
+        let failed: Vec<&str> = self
+            .results
+            .iter()
+            .filter(|result| !result.passed)
+            .map(|result| result.name.as_str())
+            .collect();
+        match failed.is_empty() {
+            true => format!("All {} test cases passed", self.total),
+            false => format!(
+                "{} of {} test cases failed: {}",
+                failed.len(),
+                self.total,
+                failed.join(", ")
+            ),
+        }
+    }
+}
+
+/// 校验用例：数量上限、用例名非空且不重复、超时为正
+pub fn validate(cases: &[FunctionTestCase]) -> Result<()> {
+    if cases.len() > MAX_TEST_CASES {
+        return Err(FluxError::ValidationError {
+            reason: format!(
+                "A function can have at most {MAX_TEST_CASES} test cases (got {})",
+                cases.len()
+            ),
+        });
+    }
+    let mut names = HashSet::new();
+    for case in cases {
+        if case.name.trim().is_empty() {
+            return Err(FluxError::ValidationError {
+                reason: "Test case name must not be empty".to_string(),
+            });
+        }
+        if !names.insert(case.name.as_str()) {
+            return Err(FluxError::ValidationError {
+                reason: format!("Test case '{}' appears more than once", case.name),
+            });
+        }
+        if case.timeout_ms == Some(0) {
+            return Err(FluxError::ValidationError {
+                reason: format!("Test case '{}' timeout_ms must be positive", case.name),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 按名称选出要执行的用例（`names` 缺省时为全部用例，保持定义顺序）
+pub fn select(
+    function: &FunctionMetadata,
+    names: Option<&[String]>,
+) -> Result<Vec<FunctionTestCase>> {
+    let Some(names) = names else {
+        return Ok(function.tests.clone());
+    };
+    if let Some(missing) = names
+        .iter()
+        .find(|name| !function.tests.iter().any(|case| &case.name == *name))
+    {
+        return Err(FluxError::TestCaseNotFound {
+            name: function.name.clone(),
+            case: missing.clone(),
+        });
+    }
+    Ok(function
+        .tests
+        .iter()
+        .filter(|case| names.contains(&case.name))
+        .cloned()
+        .collect())
+}
+
+impl FunctionTestCase {
+    /// 按实际结果判定用例是否通过
+    pub fn evaluate(
+        &self,
+        status: TestStatus,
+        output: Value,
+        error: Option<String>,
+        elapsed: Duration,
+    ) -> TestCaseResult {
+        let mut diffs = Vec::new();
+        if let Some(expected) = &self.expected_output {
+            diff(
+                expected,
+                &output,
+                self.output_match,
+                String::new(),
+                &mut diffs,
+            );
+        }
+        TestCaseResult {
+            name: self.name.clone(),
+            passed: status == self.expected_status && diffs.is_empty(),
+            expected_status: self.expected_status,
+            actual_status: status,
+            expected_output: self.expected_output.clone(),
+            actual_output: output,
+            diffs,
+            duration_ms: elapsed.as_millis() as u64,
+            error,
+        }
+    }
+}
+
+/// 比较期望值和实际值，把不一致的位置追加到 `diffs`
+fn diff(
+    expected: &Value,
+    actual: &Value,
+    mode: OutputMatch,
+    path: String,
+    diffs: &mut Vec<OutputDiff>,
+) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, value) in expected {
+                let child = format!("{path}/{}", escape(key));
+                match actual.get(key) {
+                    Some(actual) => diff(value, actual, mode, child, diffs),
+                    None => push(diffs, child, Some(value), None),
+                }
+            }
+            if mode == OutputMatch::Exact {
+                for (key, value) in actual
+                    .iter()
+                    .filter(|(key, _)| !expected.contains_key(*key))
+                {
+                    push(diffs, format!("{path}/{}", escape(key)), None, Some(value));
+                }
+            }
+        }
+        (Value::Array(expected_items), Value::Array(actual_items))
+            if expected_items.len() == actual_items.len() =>
+        {
+            for (index, (expected, actual)) in expected_items.iter().zip(actual_items).enumerate() {
+                diff(expected, actual, mode, format!("{path}/{index}"), diffs);
+            }
+        }
+        // 不同运行时对整数和浮点数的输出不一致（`3` 与 `3.0`），数字按数值比较
+        (Value::Number(a), Value::Number(b)) if a.as_f64() == b.as_f64() => {}
+        _ if expected == actual => {}
+        _ => push(diffs, path, Some(expected), Some(actual)),
+    }
+}
+
+fn push(
+    diffs: &mut Vec<OutputDiff>,
+    path: String,
+    expected: Option<&Value>,
+    actual: Option<&Value>,
+) {
+    if diffs.len() < MAX_DIFFS {
+        diffs.push(OutputDiff {
+            path,
+            expected: expected.cloned(),
+            actual: actual.cloned(),
+        });
+    }
+}
+
+/// JSON Pointer 的转义（RFC 6901）
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn case(expected: Value, output_match: OutputMatch) -> FunctionTestCase {
+        FunctionTestCase {
+            name: "case".to_string(),
+            input: Value::Null,
+            expected_output: Some(expected),
+            expected_status: TestStatus::Success,
+            output_match,
+            timeout_ms: None,
+        }
+    }
+
+    fn run(case: &FunctionTestCase, output: Value) -> TestCaseResult {
+        case.evaluate(TestStatus::Success, output, None, Duration::ZERO)
+    }
+
+    #[test]
+    fn test_exact_and_subset_matching() {
+        let expected = json!({"sum": 3, "items": [{"id": 1}], "a/b": true});
+        let exact = case(expected.clone(), OutputMatch::Exact);
+        let subset = case(expected, OutputMatch::Subset);
+
+        let output = json!({"sum": 3.0, "items": [{"id": 1}], "a/b": true});
+        assert!(run(&exact, output.clone()).passed);
+        assert!(run(&subset, output).passed);
+
+        // 多出的字段只在完全匹配时报告
+        let output = json!({"sum": 3, "items": [{"id": 1, "extra": 0}], "a/b": true, "x": 1});
+        let result = run(&exact, output.clone());
+        assert!(!result.passed);
+        let mut paths: Vec<&str> = result.diffs.iter().map(|d| d.path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(paths, ["/items/0/extra", "/x"]);
+        assert!(result.diffs.iter().all(|d| d.expected.is_none()));
+        assert!(run(&subset, output).passed);
+
+        // 缺少的字段和不相等的值在两种模式下都报告，键按 JSON Pointer 转义
+        let result = run(&subset, json!({"sum": 4, "items": [{"id": 1}, {"id": 2}]}));
+        let diff = |path: &str| result.diffs.iter().find(|d| d.path == path).unwrap();
+        assert_eq!(result.diffs.len(), 3);
+        assert_eq!(diff("/sum").actual, Some(json!(4)));
+        assert_eq!(diff("/items").expected, Some(json!([{"id": 1}])));
+        assert_eq!(diff("/a~1b").actual, None);
+    }
+
+    #[test]
+    fn test_status_must_match() {
+        let mut expect_error = case(json!({"error": "boom"}), OutputMatch::Exact);
+        expect_error.expected_status = TestStatus::Error;
+        let result = expect_error.evaluate(
+            TestStatus::Error,
+            json!({"error": "boom"}),
+            Some("boom".to_string()),
+            Duration::ZERO,
+        );
+        assert!(result.passed);
+        let result = expect_error.evaluate(
+            TestStatus::Success,
+            json!({"error": "boom"}),
+            None,
+            Duration::ZERO,
+        );
+        assert!(!result.passed);
+        assert!(result.diffs.is_empty());
+    }
+
+    #[test]
+    fn test_validation_and_selection() {
+        let mut function = FunctionMetadata::new("f".to_string(), "return 1".to_string());
+        function.tests = vec![
+            case(json!(1), OutputMatch::Exact),
+            FunctionTestCase {
+                name: "other".to_string(),
+                ..case(json!(2), OutputMatch::Exact)
+            },
+        ];
+        assert!(validate(&function.tests).is_ok());
+        let selected = select(&function, Some(&["other".to_string()])).unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(select(&function, None).unwrap().len(), 2);
+        assert!(matches!(
+            select(&function, Some(&["missing".to_string()])),
+            Err(FluxError::TestCaseNotFound { .. })
+        ));
+
+        let duplicate = vec![function.tests[0].clone(), function.tests[0].clone()];
+        assert!(validate(&duplicate).is_err());
+        let too_many = vec![function.tests[0].clone(); MAX_TEST_CASES + 1];
+        assert!(validate(&too_many).is_err());
+        let zero_timeout = vec![FunctionTestCase {
+            timeout_ms: Some(0),
+            ..function.tests[0].clone()
+        }];
+        assert!(validate(&zero_timeout).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// 一次调用各阶段的耗时（毫秒），本次调用没有发生的阶段为 `null`
+///
+/// 各阶段之和等于 `total_ms`：`queue_ms` 是收到调用后不属于其他阶段的全部时间
+/// （准入排队、等待后台编译、沙箱执行名额、重试退避等）。重试时编译、启动和执行
+/// 按各次尝试累加。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct InvocationTiming {
+    /// 从收到调用到运行时开始执行
+    pub queue_ms: Option<u64>,
+    /// 本次调用触发的编译（含等待同源编译）
+    pub compile_ms: Option<u64>,
+    /// 进程或解释器启动到函数体开始执行
+    pub spawn_ms: Option<u64>,
+    /// 函数体执行
+    pub execute_ms: Option<u64>,
+    /// 转发给其他节点时往返中不属于节点上调用的时间（网络传输和序列化）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_ms: Option<u64>,
+    pub total_ms: u64,
+    /// 函数进程及其子进程累计使用的 CPU 时间（不属于上面的阶段，可能超过墙钟时间），
+    /// 没有启动沙箱进程的调用为 `null`
+    #[serde(default)]
+    pub cpu_ms: Option<u64>,
+    /// 执行本次调用的实例（请求带有亲和键时设置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<InstanceSelection>,
+}
+
+/// 执行调用的实例或常驻工作进程，以及是否由亲和键对应的实例执行
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct InstanceSelection {
+    pub instance_id: String,
+    /// 对应的实例不健康、负载过高或忙碌时按常规策略选择，为 false
+    pub affinity_honored: bool,
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().min(u64::MAX as u128) as u64
+}
+
+fn add(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.saturating_add(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+impl InvocationTiming {
+    /// 在进程内执行的一次尝试：没有启动阶段，编译之外的时间都计为函数体执行
+    pub fn in_process(total: Duration, compile: Option<Duration>) -> Self {
+        let total_ms = millis(total);
+        let compile_ms = compile.map(|compile| millis(compile).min(total_ms));
+        Self {
+            queue_ms: None,
+            compile_ms,
+            spawn_ms: None,
+            execute_ms: Some(total_ms - compile_ms.unwrap_or(0)),
+            network_ms: None,
+            total_ms,
+            cpu_ms: None,
+            instance: None,
+        }
+    }
+
+    /// 各阶段（不含总耗时）之和
+    pub fn phases_ms(&self) -> u64 {
+        [
+            self.queue_ms,
+            self.compile_ms,
+            self.spawn_ms,
+            self.execute_ms,
+            self.network_ms,
+        ]
+        .into_iter()
+        .flatten()
+        .fold(0, u64::saturating_add)
+    }
+
+    /// 累加另一次尝试的各阶段耗时（执行实例取最后一次尝试的）
+    pub fn absorb(&mut self, other: &Self) {
+        if other.instance.is_some() {
+            self.instance = other.instance.clone();
+        }
+        self.queue_ms = add(self.queue_ms, other.queue_ms);
+        self.compile_ms = add(self.compile_ms, other.compile_ms);
+        self.spawn_ms = add(self.spawn_ms, other.spawn_ms);
+        self.execute_ms = add(self.execute_ms, other.execute_ms);
+        self.network_ms = add(self.network_ms, other.network_ms);
+        self.cpu_ms = add(self.cpu_ms, other.cpu_ms);
+        self.total_ms = self.total_ms.saturating_add(other.total_ms);
+    }
+
+    /// 以收到调用起的总耗时收尾：编译、启动和执行之外的时间都计为排队
+    pub fn finish(mut self, total: Duration) -> Self {
+        self.total_ms = millis(total);
+        self.queue_ms = None;
+        let busy = self.phases_ms();
+        if busy > self.total_ms {
+            // 各阶段分别取整，总和可能略超总耗时
+            self.total_ms = busy;
+        }
+        self.queue_ms = Some(self.total_ms - busy);
+        self
+    }
+}
+
+/// 超时或超出资源限制时终止函数进程的情况
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ProcessTermination {
+    /// 请求退出（SIGTERM）后宽限期内仍未退出，被强制终止（SIGKILL）
+    pub forced: bool,
+    /// 进程从启动到被终止运行的时间（毫秒）
+    pub ran_for_ms: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finish_attributes_the_remainder_to_queue() {
+        let mut timing = InvocationTiming::in_process(
+            Duration::from_millis(30),
+            Some(Duration::from_millis(20)),
+        );
+        assert_eq!(timing.compile_ms, Some(20));
+        assert_eq!(timing.execute_ms, Some(10));
+        assert_eq!(timing.spawn_ms, None);
+
+        timing.absorb(&InvocationTiming::in_process(
+            Duration::from_millis(5),
+            None,
+        ));
+        let timing = timing.finish(Duration::from_millis(50));
+        assert_eq!(timing.compile_ms, Some(20));
+        assert_eq!(timing.execute_ms, Some(15));
+        assert_eq!(timing.queue_ms, Some(15));
+        assert_eq!(timing.phases_ms(), timing.total_ms);
+
+        let json = serde_json::to_value(&timing).unwrap();
+        assert!(json["spawn_ms"].is_null());
+    }
+}
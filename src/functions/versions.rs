@@ -0,0 +1,102 @@
+use super::FunctionMetadata;
+use super::diff;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 每个函数默认保留的版本数（包含最新版本）
+pub const DEFAULT_MAX_VERSIONS: usize = 5;
+
+/// 不参与版本差异比较的字段（每次写入都会变化或本身就是版本标识）
+pub(crate) const UNTRACKED_FIELDS: &[&str] =
+    &["id", "created_at", "updated_at", "revision", "version"];
+
+/// 保留的函数版本
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VersionSummary {
+    pub version: String,
+    /// 该版本最后一次写入时的修订号
+    pub revision: u64,
+    /// 该版本最后一次写入的时间
+    pub registered_at: DateTime<Utc>,
+    /// 是否为最新版本（未指定版本的调用执行该版本）
+    pub latest: bool,
+    /// 代码大小（字节，函数包为所有文件之和）
+    pub code_size: usize,
+    /// 与上一个保留版本的差异（最早的保留版本没有）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<VersionDiff>,
+}
+
+/// 两个版本之间的差异摘要
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VersionDiff {
+    /// 上一个保留版本
+    pub from: String,
+    /// 代码大小变化（字节）
+    pub code_size_delta: i64,
+    /// 代码新增的行数
+    pub lines_added: usize,
+    /// 代码删除的行数
+    pub lines_removed: usize,
+    /// 发生变化的字段名（按字母序）
+    pub changed_fields: Vec<String>,
+}
+
+/// 函数代码大小（字节）
+fn code_size(function: &FunctionMetadata) -> usize {
+    function.code.len()
+        + function
+            .package
+            .as_ref()
+            .map(|package| package.files.iter().map(|file| file.content.len()).sum())
+            .unwrap_or(0)
+}
+
+impl VersionDiff {
+    /// 比较两个版本的定义
+    pub fn between(from: &FunctionMetadata, to: &FunctionMetadata) -> Self {
+        let fields = |function: &FunctionMetadata| match serde_json::to_value(function) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => Default::default(),
+        };
+        let (old, new) = (fields(from), fields(to));
+        let mut changed_fields: Vec<String> = old
+            .keys()
+            .chain(new.keys())
+            .filter(|key| !UNTRACKED_FIELDS.contains(&key.as_str()))
+            .filter(|key| old.get(*key) != new.get(*key))
+            .cloned()
+            .collect();
+        changed_fields.sort();
+        changed_fields.dedup();
+        let (lines_added, lines_removed) = diff::line_stats(from, to);
+        Self {
+            from: from.version.clone(),
+            code_size_delta: code_size(to) as i64 - code_size(from) as i64,
+            lines_added,
+            lines_removed,
+            changed_fields,
+        }
+    }
+}
+
+impl VersionSummary {
+    /// 按从旧到新的顺序汇总保留的版本（最后一个为最新版本）
+    pub fn list(versions: &[FunctionMetadata]) -> Vec<Self> {
+        versions
+            .iter()
+            .enumerate()
+            .map(|(i, function)| Self {
+                version: function.version.clone(),
+                revision: function.revision,
+                registered_at: function.updated_at,
+                latest: i + 1 == versions.len(),
+                code_size: code_size(function),
+                diff: i
+                    .checked_sub(1)
+                    .map(|prev| VersionDiff::between(&versions[prev], function)),
+            })
+            .collect()
+    }
+}
@@ -110,10 +110,10 @@ impl FunctionWatcher {
         let mut watcher = RecommendedWatcher::new(
             move |result: notify::Result<Event>| match result {
                 Ok(event) => {
-                    if let Some(change_event) = Self::convert_notify_event(event) {
-                        if let Err(e) = event_sender.send(change_event) {
-                            tracing::error!("Failed to send file change event: {}", e);
-                        }
+                    if let Some(change_event) = Self::convert_notify_event(event)
+                        && let Err(e) = event_sender.send(change_event)
+                    {
+                        tracing::error!("Failed to send file change event: {}", e);
                     }
                 }
                 Err(e) => {
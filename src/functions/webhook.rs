@@ -0,0 +1,87 @@
+use super::{ExecutionStatus, FluxError, Result};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 响应中代替 webhook 密钥返回的占位符
+pub const REDACTED_SECRET: &str = "********";
+
+/// 触发 webhook 的执行结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEvent {
+    Success,
+    Error,
+    Timeout,
+    /// SLO 燃烧率告警的开始或解除（不对应某次调用）
+    #[serde(rename = "slo_alert")]
+    SloAlert,
+}
+
+impl WebhookEvent {
+    /// 执行状态对应的事件
+    pub fn from_status(status: &ExecutionStatus) -> Self {
+        match status {
+            ExecutionStatus::Success => Self::Success,
+            ExecutionStatus::Timeout => Self::Timeout,
+            ExecutionStatus::Error { .. }
+            | ExecutionStatus::Cancelled
+            | ExecutionStatus::ResourceLimitExceeded { .. } => Self::Error,
+        }
+    }
+}
+
+/// 函数级 webhook 配置
+///
+/// 成功的调用通知 `on_success_url`，失败或超时的调用以及 SLO 告警通知 `on_failure_url`。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub on_success_url: Option<String>,
+    pub on_failure_url: Option<String>,
+    /// 配置后以 HMAC-SHA256 签名请求体（`X-Flux-Signature` 头）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// 需要通知的执行结果，为空时通知所有结果
+    pub statuses: Vec<WebhookEvent>,
+}
+
+impl WebhookConfig {
+    /// 校验 URL 必须是 http(s) 地址
+    pub fn validate(&self) -> Result<()> {
+        for url in [&self.on_success_url, &self.on_failure_url]
+            .into_iter()
+            .flatten()
+        {
+            let valid = reqwest::Url::parse(url)
+                .map(|parsed| matches!(parsed.scheme(), "http" | "https"))
+                .unwrap_or(false);
+            if !valid {
+                return Err(FluxError::ValidationError {
+                    reason: format!("Invalid webhook URL '{url}': must be an http(s) URL"),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 该执行结果需要通知的 URL
+    pub fn target(&self, event: WebhookEvent) -> Option<&str> {
+        if !self.statuses.is_empty() && !self.statuses.contains(&event) {
+            return None;
+        }
+        match event {
+            WebhookEvent::Success => self.on_success_url.as_deref(),
+            WebhookEvent::Error | WebhookEvent::Timeout | WebhookEvent::SloAlert => {
+                self.on_failure_url.as_deref()
+            }
+        }
+    }
+
+    /// 用于 API 响应的副本，密钥替换为占位符
+    pub fn redacted(&self) -> Self {
+        Self {
+            secret: self.secret.as_ref().map(|_| REDACTED_SECRET.to_string()),
+            ..self.clone()
+        }
+    }
+}
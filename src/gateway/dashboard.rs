@@ -0,0 +1,422 @@
+//! 运维仪表盘：一次请求返回所有函数的汇总行，并通过 SSE 推送行的增量更新
+//!
+//! 快照先从注册表、性能监控器、缓存和实例管理器复制出所需数据（每次只短暂持有各自的锁），
+//! 再在锁外计算调用速率和时间戳，所有行共用同一个生成时间。
+
+use super::handlers::{ApiResponse, api_json};
+use crate::functions::registry::RegistryChange;
+use crate::runtime::instance::{InstanceManager, InstanceState};
+use crate::scheduler::profiles::{SchedulerProfile, SchedulerRegistry};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, BoxStream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use silent::prelude::{SSEEvent, sse_reply};
+use silent::{Request, Response, Result as SilentResult, StatusCode};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// 短窗口调用速率的时间窗口
+pub const SHORT_WINDOW: Duration = Duration::from_secs(60);
+
+/// 长窗口调用速率以及错误率、p95 延迟的时间窗口
+pub const LONG_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// 仪表盘配置（`[dashboard]`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DashboardConfig {
+    /// SSE 心跳注释的间隔（秒），防止代理关闭空闲连接
+    pub heartbeat_secs: u64,
+}
+
+impl Default for DashboardConfig {
+    fn default() -> Self {
+        Self { heartbeat_secs: 15 }
+    }
+}
+
+/// 函数在缓存中的状态
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RowCacheStatus {
+    /// 缓存中的版本数
+    pub cached_versions: usize,
+    pub memory_usage_bytes: usize,
+    pub pinned: bool,
+}
+
+/// 函数的实例数
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RowInstances {
+    pub total: usize,
+    /// 正在执行的实例
+    pub running: usize,
+    /// 池中就绪或空闲、可直接复用的实例
+    pub idle: usize,
+}
+
+/// 仪表盘中一个函数的汇总行
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DashboardRow {
+    pub function: String,
+    pub profile: String,
+    pub namespace: String,
+    pub version: String,
+    /// 最近 1 分钟的调用速率（次/秒）
+    pub rate_1m: f64,
+    /// 最近 5 分钟的调用速率（次/秒）
+    pub rate_5m: f64,
+    /// 最近 5 分钟的调用数
+    pub invocations_5m: u64,
+    /// 最近 5 分钟的错误率
+    pub error_rate: f64,
+    /// 最近 5 分钟的 p95 延迟（毫秒）
+    pub p95_ms: Option<f64>,
+    pub last_invocation_at: Option<DateTime<Utc>>,
+    pub cache: RowCacheStatus,
+    pub instances: RowInstances,
+}
+
+/// `GET /dashboard/summary` 的快照
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardSummary {
+    pub generated_at: DateTime<Utc>,
+    /// 按函数名排序
+    pub functions: Vec<DashboardRow>,
+}
+
+impl DashboardSummary {
+    /// 汇总所有调度器中的函数
+    pub async fn collect(
+        schedulers: &SchedulerRegistry,
+        instances: Option<&InstanceManager>,
+    ) -> Self {
+        let generated_at = Utc::now();
+        let now = Instant::now();
+        let instance_counts = count_instances(instances).await;
+        let mut functions = Vec::new();
+        for profile in schedulers.profiles() {
+            functions
+                .extend(profile_rows(profile, &instance_counts, None, generated_at, now).await);
+        }
+        functions.sort_by(|a, b| a.function.cmp(&b.function));
+        Self {
+            generated_at,
+            functions,
+        }
+    }
+}
+
+/// 按函数统计实例数
+async fn count_instances(instances: Option<&InstanceManager>) -> HashMap<String, RowInstances> {
+    let mut counts: HashMap<String, RowInstances> = HashMap::new();
+    let Some(manager) = instances else {
+        return counts;
+    };
+    for instance in manager.list_instance_summaries().await {
+        let count = counts.entry(instance.function_name).or_default();
+        count.total += 1;
+        match instance.state {
+            InstanceState::Running => count.running += 1,
+            InstanceState::Ready | InstanceState::Idle => count.idle += 1,
+            _ => {}
+        }
+    }
+    counts
+}
+
+/// 计算调度器中函数的汇总行，`only` 不为空时只计算该函数（函数不存在时为空）
+async fn profile_rows(
+    profile: &SchedulerProfile,
+    instance_counts: &HashMap<String, RowInstances>,
+    only: Option<&str>,
+    generated_at: DateTime<Utc>,
+    now: Instant,
+) -> Vec<DashboardRow> {
+    let scheduler = &profile.scheduler;
+    let functions = match only {
+        Some(name) => scheduler.registry().get(name).await.into_iter().collect(),
+        None => scheduler.registry().list().await,
+    };
+    if functions.is_empty() {
+        return Vec::new();
+    }
+
+    // 先复制出各部分数据，计算在锁外进行
+    let monitor = scheduler.runtime().monitor();
+    let activity = monitor.activity(LONG_WINDOW).await;
+    let summaries = monitor.window_summaries(LONG_WINDOW).await;
+    let cache = scheduler.runtime().cache();
+    let mut cached: HashMap<String, RowCacheStatus> = HashMap::new();
+    for entry in cache.entries().await {
+        let status = cached.entry(entry.function).or_default();
+        status.cached_versions += 1;
+        status.memory_usage_bytes += entry.memory_usage;
+        status.pinned |= entry.pinned;
+    }
+
+    functions
+        .into_iter()
+        .map(|function| {
+            let activity = activity.get(&function.name).cloned().unwrap_or_default();
+            let summary = summaries.get(&function.name).cloned().unwrap_or_default();
+            let last_invocation_at = activity.last_execution.map(|at| {
+                generated_at
+                    - chrono::Duration::from_std(now.saturating_duration_since(at))
+                        .unwrap_or_default()
+            });
+            DashboardRow {
+                profile: profile.name.clone(),
+                namespace: function.namespace().to_string(),
+                version: function.version.clone(),
+                rate_1m: activity.invocation_rate(SHORT_WINDOW, now),
+                rate_5m: activity.invocation_rate(LONG_WINDOW, now),
+                invocations_5m: summary.invocations,
+                error_rate: summary.error_rate,
+                p95_ms: summary.p95_ms,
+                last_invocation_at,
+                cache: RowCacheStatus {
+                    pinned: cache.is_pinned(&function.name),
+                    ..cached.remove(&function.name).unwrap_or_default()
+                },
+                instances: instance_counts
+                    .get(&function.name)
+                    .cloned()
+                    .unwrap_or_default(),
+                function: function.name,
+            }
+        })
+        .collect()
+}
+
+/// 需要推送的变化
+enum Signal {
+    /// 函数新增或时间桶滚动，推送调度器中该函数的最新行
+    Updated {
+        profile: usize,
+        function: String,
+    },
+    Removed {
+        profile: usize,
+        function: String,
+    },
+    /// 订阅者落后，丢失了若干通知
+    Lagged(u64),
+    Heartbeat,
+}
+
+/// 把广播接收端转换为流，落后时产生 `Err(丢失数)`
+fn broadcast_stream<T: Clone + Send + 'static>(
+    receiver: broadcast::Receiver<T>,
+) -> impl Stream<Item = Result<T, u64>> + Send + 'static {
+    stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            Ok(value) => Some((Ok(value), receiver)),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => Some((Err(skipped), receiver)),
+            Err(broadcast::error::RecvError::Closed) => None,
+        }
+    })
+}
+
+/// 仪表盘事件流：首先是完整快照（`snapshot`），之后是行更新（`row`）、删除（`removed`）、
+/// 丢失通知（`lagged`，客户端应重新获取快照）和心跳注释
+pub(crate) async fn dashboard_events(
+    schedulers: Arc<SchedulerRegistry>,
+    instances: Option<Arc<InstanceManager>>,
+    heartbeat: Duration,
+) -> impl Stream<Item = SSEEvent> + Send + 'static {
+    // 先订阅再生成快照，快照之后的变化不会丢失
+    let mut sources: Vec<BoxStream<'static, Signal>> = Vec::new();
+    for (index, profile) in schedulers.profiles().enumerate() {
+        let rollovers = broadcast_stream(
+            profile.scheduler.runtime().monitor().subscribe_rollovers(),
+        )
+        .map(move |rollover| match rollover {
+            Ok(function) => Signal::Updated {
+                profile: index,
+                function,
+            },
+            Err(skipped) => Signal::Lagged(skipped),
+        });
+        let changes =
+            broadcast_stream(profile.scheduler.registry().subscribe_changes()).map(move |change| {
+                match change {
+                    Ok(RegistryChange::Deleted(function)) => Signal::Removed {
+                        profile: index,
+                        function,
+                    },
+                    Ok(change) => Signal::Updated {
+                        profile: index,
+                        function: change.name().to_string(),
+                    },
+                    Err(skipped) => Signal::Lagged(skipped),
+                }
+            });
+        sources.push(rollovers.boxed());
+        sources.push(changes.boxed());
+    }
+    let heartbeat = heartbeat.max(Duration::from_millis(10));
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + heartbeat, heartbeat);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    sources.push(
+        stream::unfold(ticker, |mut ticker| async move {
+            ticker.tick().await;
+            Some((Signal::Heartbeat, ticker))
+        })
+        .boxed(),
+    );
+
+    let snapshot = DashboardSummary::collect(&schedulers, instances.as_deref()).await;
+    let snapshot = SSEEvent::default()
+        .event("snapshot")
+        .data(serde_json::to_string(&snapshot).unwrap_or_default());
+
+    let updates = stream::select_all(sources).filter_map(move |signal| {
+        let schedulers = schedulers.clone();
+        let instances = instances.clone();
+        async move {
+            match signal {
+                Signal::Updated { profile, function } => {
+                    let profile = schedulers.profiles().nth(profile)?;
+                    let instance_counts = count_instances(instances.as_deref()).await;
+                    // 推送前函数可能已被删除
+                    let row = profile_rows(
+                        profile,
+                        &instance_counts,
+                        Some(&function),
+                        Utc::now(),
+                        Instant::now(),
+                    )
+                    .await
+                    .pop()?;
+                    Some(
+                        SSEEvent::default()
+                            .event("row")
+                            .data(serde_json::to_string(&row).unwrap_or_default()),
+                    )
+                }
+                Signal::Removed { profile, function } => {
+                    let profile = schedulers.profiles().nth(profile)?;
+                    let removed = serde_json::json!({
+                        "function": function,
+                        "profile": profile.name,
+                    });
+                    Some(
+                        SSEEvent::default()
+                            .event("removed")
+                            .data(removed.to_string()),
+                    )
+                }
+                Signal::Lagged(skipped) => Some(
+                    SSEEvent::default()
+                        .event("lagged")
+                        .data(skipped.to_string()),
+                ),
+                Signal::Heartbeat => Some(SSEEvent::default().comment("heartbeat")),
+            }
+        }
+    });
+    stream::once(async move { snapshot }).chain(updates)
+}
+
+/// 获取仪表盘快照：每个函数的调用速率（1m/5m）、错误率、p95 延迟、最后调用时间、
+/// 缓存状态和实例数
+pub async fn get_dashboard_summary(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let instances = req.get_config::<Arc<InstanceManager>>().ok();
+
+    let summary = DashboardSummary::collect(schedulers, instances.map(Arc::as_ref)).await;
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!(
+            "Dashboard summary of {} functions",
+            summary.functions.len()
+        )),
+        data: Some(summary),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 以 SSE 推送仪表盘快照和行的增量更新
+pub async fn stream_dashboard(req: Request) -> SilentResult<Response> {
+    let schedulers: Arc<SchedulerRegistry> = req.get_config::<Arc<SchedulerRegistry>>()?.clone();
+    let instances = req.get_config::<Arc<InstanceManager>>().ok().cloned();
+    let heartbeat_secs = req
+        .get_config::<Arc<DashboardConfig>>()
+        .map(|config| config.heartbeat_secs)
+        .unwrap_or_else(|_| DashboardConfig::default().heartbeat_secs);
+
+    let events = dashboard_events(schedulers, instances, Duration::from_secs(heartbeat_secs)).await;
+    sse_reply(events.map(Ok))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::{FunctionMetadata, InvokeRequest};
+    use crate::scheduler::{Scheduler, SimpleScheduler};
+
+    async fn next_event(events: &mut (impl Stream<Item = SSEEvent> + Unpin)) -> String {
+        tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await
+            .expect("event arrives")
+            .expect("stream continues")
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_summary_and_stream_updates() {
+        let scheduler = Arc::new(SimpleScheduler::new());
+        let schedulers = Arc::new(SchedulerRegistry::with_default(scheduler.clone()));
+        let add = FunctionMetadata::new("add".to_string(), String::new());
+        scheduler.registry().register(add).await.unwrap();
+
+        let summary = DashboardSummary::collect(&schedulers, None).await;
+        let row = &summary.functions[0];
+        assert_eq!(row.function, "add");
+        assert_eq!(row.profile, "default");
+        assert_eq!((row.rate_1m, row.invocations_5m), (0.0, 0));
+        assert_eq!(row.last_invocation_at, None);
+
+        let events = dashboard_events(schedulers.clone(), None, Duration::from_millis(50)).await;
+        let mut events = Box::pin(events);
+        let snapshot = next_event(&mut events).await;
+        assert!(snapshot.starts_with("event:snapshot\n"), "{snapshot}");
+
+        // 首次调用开启新时间桶，推送该函数的行
+        let request = InvokeRequest {
+            input: serde_json::json!({"a": 1, "b": 2}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+        scheduler.schedule("add", request).await.unwrap();
+        let row = loop {
+            let event = next_event(&mut events).await;
+            if event.starts_with("event:row\n") {
+                break event;
+            }
+            assert_eq!(event, ":heartbeat\n\n");
+        };
+        let data: serde_json::Value =
+            serde_json::from_str(row.lines().nth(1).unwrap().trim_start_matches("data:")).unwrap();
+        assert_eq!(data["function"], "add");
+        assert_eq!(data["invocations_5m"], 1);
+        assert!(data["p95_ms"].is_number(), "{data}");
+        assert!(data["last_invocation_at"].is_string(), "{data}");
+
+        scheduler.registry().remove("add").await.unwrap();
+        let removed = loop {
+            let event = next_event(&mut events).await;
+            if !event.starts_with(':') {
+                break event;
+            }
+        };
+        assert!(removed.starts_with("event:removed\n"), "{removed}");
+        assert!(removed.contains(r#""function":"add""#), "{removed}");
+    }
+}
@@ -0,0 +1,399 @@
+use super::handlers::{REQUEST_ID_HEADER, request_id_from_headers};
+use super::payload::{self, CompressionConfig};
+use crate::functions::deprecation::DeprecationNotice;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use silent::header::{HeaderName, HeaderValue};
+use silent::prelude::ResBody;
+use silent::{
+    Handler, MiddleWareHandler, Next, Request, Response, Result as SilentResult, StatusCode,
+};
+use std::time::Instant;
+use utoipa::ToSchema;
+
+/// 当前 API 版本前缀
+pub const API_VERSION: &str = "v1";
+
+/// 处理器通过 [`super::handlers::api_json`] 生成响应时记录的状态码，供版本化中间件改写响应体
+#[derive(Debug, Clone, Copy)]
+pub struct ApiStatus(pub StatusCode);
+
+/// `/v1` 响应的元数据
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ResponseMeta {
+    pub request_id: String,
+    pub duration_ms: u64,
+    /// 调用的函数已弃用时的弃用说明（处理器通过响应扩展提供）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<DeprecationNotice>,
+}
+
+/// `/v1` 结构化错误
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiError {
+    /// 由 HTTP 状态码派生的错误码，例如 `not_found`、`payload_too_large`
+    pub code: String,
+    pub message: String,
+    /// 失败时仍返回的数据（例如目录加载中每个文件的结果）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: String, details: Option<Value>) -> Self {
+        Self {
+            code: status
+                .canonical_reason()
+                .unwrap_or("error")
+                .to_ascii_lowercase()
+                .replace([' ', '-'], "_"),
+            message,
+            details,
+        }
+    }
+}
+
+/// `/v1` 响应体：成功时为 `{"data", "meta"}`，失败时为 `{"error", "meta"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Envelope {
+    Data { data: Value, meta: ResponseMeta },
+    Error { error: ApiError, meta: ResponseMeta },
+}
+
+impl Envelope {
+    /// 将处理器生成的 `ApiResponse` 改写为版本化响应体（状态码 >= 400 视为失败）
+    pub fn from_api_response(status: StatusCode, body: &[u8], meta: ResponseMeta) -> Option<Self> {
+        let mut response: serde_json::Map<String, Value> = serde_json::from_slice(body).ok()?;
+        response.get("success")?.as_bool()?;
+        let data = response.remove("data").unwrap_or(Value::Null);
+
+        if status.as_u16() < 400 {
+            return Some(Self::Data { data, meta });
+        }
+        let message = ["error", "message"]
+            .iter()
+            .find_map(|key| response.get(*key)?.as_str().map(str::to_string))
+            .unwrap_or_else(|| status.to_string());
+        let details = (!data.is_null()).then_some(data);
+        Some(Self::Error {
+            error: ApiError::new(status, message, details),
+            meta,
+        })
+    }
+
+    /// 框架层错误（例如缺少注入的配置）的版本化响应体
+    pub fn from_error(status: StatusCode, message: String, meta: ResponseMeta) -> Self {
+        Self::Error {
+            error: ApiError::new(status, message, None),
+            meta,
+        }
+    }
+}
+
+/// `/v1` 路由中间件：统一请求 ID，并将响应改写为版本化响应体
+///
+/// 只改写通过 `api_json` 生成的 JSON 响应；原始响应体、SSE 和 WebSocket 升级原样返回。
+#[derive(Debug, Default, Clone)]
+pub struct VersionedEnvelope;
+
+#[async_trait::async_trait]
+impl MiddleWareHandler for VersionedEnvelope {
+    async fn handle(&self, mut req: Request, next: &Next) -> SilentResult<Response> {
+        let start = Instant::now();
+        let accept_encoding = payload::accept_encoding(&req);
+        let compression = CompressionConfig::from_request(&req);
+        // 先确定请求 ID 并写回请求头，处理器读取到的是同一个 ID
+        let request_id = request_id_from_headers(&req);
+        let header_value = HeaderValue::from_str(&request_id).ok();
+        if let Some(value) = header_value.clone() {
+            req.headers_mut()
+                .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+        }
+
+        let result = next.call(req).await;
+        let meta = ResponseMeta {
+            request_id,
+            duration_ms: start.elapsed().as_millis() as u64,
+            deprecated: result
+                .as_ref()
+                .ok()
+                .and_then(|response| response.extensions().get::<DeprecationNotice>().cloned()),
+        };
+
+        let mut response = match result {
+            Ok(mut response) => {
+                let status = response.extensions().get::<ApiStatus>().map(|s| s.0);
+                let envelope = match (status, response.body()) {
+                    (Some(status), ResBody::Once(body)) => {
+                        Envelope::from_api_response(status, body.as_ref(), meta)
+                    }
+                    _ => None,
+                };
+                if let Some(envelope) = envelope {
+                    let body = serde_json::to_vec(&envelope).unwrap_or_default();
+                    response.set_body(body.into());
+                }
+                response
+            }
+            Err(e) => {
+                let status = e.status();
+                Response::json(&Envelope::from_error(status, e.message(), meta)).with_status(status)
+            }
+        };
+        if let Some(value) = header_value {
+            response.set_header(HeaderName::from_static(REQUEST_ID_HEADER), value);
+        }
+        // 改写响应体之后再压缩
+        payload::compress_response(&mut response, accept_encoding.as_deref(), &compression);
+        Ok(response)
+    }
+}
+
+/// 未加版本前缀的旧路由中间件：响应中标记弃用并指向 `/v1` 路由
+///
+/// 处理器已设置弃用头（调用的函数已弃用）时保留处理器的值。
+#[derive(Debug, Default, Clone)]
+pub struct DeprecatedAlias;
+
+#[async_trait::async_trait]
+impl MiddleWareHandler for DeprecatedAlias {
+    async fn handle(&self, req: Request, next: &Next) -> SilentResult<Response> {
+        let successor = format!(
+            "</{API_VERSION}{}>; rel=\"successor-version\"",
+            req.uri().path()
+        );
+        let accept_encoding = payload::accept_encoding(&req);
+        let compression = CompressionConfig::from_request(&req);
+        let mut response = next.call(req).await?;
+        payload::compress_response(&mut response, accept_encoding.as_deref(), &compression);
+        if response.headers().contains_key("deprecation") {
+            return Ok(response);
+        }
+        response.set_header(
+            HeaderName::from_static("deprecation"),
+            HeaderValue::from_static("true"),
+        );
+        if let Ok(link) = HeaderValue::from_str(&successor) {
+            response.set_header(HeaderName::from_static("link"), link);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::routes::build_routes;
+    use crate::scheduler::SimpleScheduler;
+    use crate::scheduler::profiles::SchedulerRegistry;
+    use serde_json::json;
+    use silent::prelude::{Configs, Listener, Server};
+    use std::sync::Arc;
+
+    /// 在随机端口启动完整路由，返回基础地址
+    async fn spawn_server() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut configs = Configs::default();
+        configs.insert(Arc::new(SchedulerRegistry::with_default(Arc::new(
+            SimpleScheduler::new(),
+        ))));
+        tokio::spawn(
+            Server::new()
+                .with_configs(configs)
+                .listen(Listener::from(listener))
+                .serve(build_routes()),
+        );
+        format!("http://{addr}")
+    }
+
+    /// 去掉不确定的字段后比较完整响应体
+    fn without_timing(mut body: Value) -> Value {
+        assert!(body["meta"]["duration_ms"].is_u64());
+        body["meta"]["duration_ms"] = json!(0);
+        if let Some(data) = body.get_mut("data").and_then(Value::as_object_mut) {
+            data.remove("execution_time_ms");
+            data.remove("timing");
+        }
+        if let Some(functions) = body.get_mut("data").and_then(Value::as_array_mut) {
+            for function in functions {
+                let function = function.as_object_mut().unwrap();
+                assert!(function.remove("id").is_some());
+                assert!(function.remove("created_at").is_some());
+            }
+        }
+        body
+    }
+
+    #[tokio::test]
+    async fn test_v1_envelope_contract() {
+        let base = spawn_server().await;
+        let client = reqwest::Client::new();
+
+        // 注册
+        let res = client
+            .post(format!("{base}/v1/functions"))
+            .header(REQUEST_ID_HEADER, "req-register")
+            .json(&json!({"name": "greet", "code": "return input"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()[REQUEST_ID_HEADER], "req-register");
+        assert_eq!(
+            without_timing(res.json().await.unwrap()),
+            json!({
+                "data": "Function registration received",
+                "meta": {"request_id": "req-register", "duration_ms": 0}
+            })
+        );
+
+        // 列表
+        let res = client
+            .get(format!("{base}/v1/functions"))
+            .header(REQUEST_ID_HEADER, "req-list")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            without_timing(res.json().await.unwrap()),
+            json!({
+                "data": [{
+                    "name": "greet",
+                    "description": "",
+                    "timeout_ms": 5000,
+                    "labels": {},
+                    "profile": "default",
+                    "namespace": "default"
+                }],
+                "meta": {"request_id": "req-list", "duration_ms": 0}
+            })
+        );
+
+        // 调用：函数输出原样放在 data.output 中，不混入运行时元数据
+        let res = client
+            .post(format!("{base}/v1/invoke/greet"))
+            .header(REQUEST_ID_HEADER, "req-invoke")
+            .json(&json!({"input": {"a": 1}}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            without_timing(res.json().await.unwrap()),
+            json!({
+                "data": {
+                    "output": {"result": {"a": 1}, "input": {"a": 1}},
+                    "status": "Success",
+                    "request_id": "req-invoke",
+                    "attempts_made": 1,
+                    "succeeded_on_retry": false,
+                    "cold_start": true,
+                    "execution_path": "inline"
+                },
+                "meta": {"request_id": "req-invoke", "duration_ms": 0}
+            })
+        );
+
+        // 错误：结构化错误对象
+        let res = client
+            .get(format!("{base}/v1/functions/missing"))
+            .header(REQUEST_ID_HEADER, "req-missing")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 404);
+        assert_eq!(
+            without_timing(res.json().await.unwrap()),
+            json!({
+                "error": {
+                    "code": "not_found",
+                    "message": "Function not found: Function not found: missing"
+                },
+                "meta": {"request_id": "req-missing", "duration_ms": 0}
+            })
+        );
+
+        let res = client
+            .post(format!("{base}/v1/functions"))
+            .header(REQUEST_ID_HEADER, "req-invalid")
+            .json(&json!({"name": "x", "code": "return input", "profile": "nope"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 400);
+        let body: Value = res.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "bad_request");
+        assert_eq!(body["meta"]["request_id"], "req-invalid");
+
+        // 旧路由保持原有响应格式，并标记弃用
+        let res = client
+            .get(format!("{base}/functions/missing"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 404);
+        assert_eq!(res.headers()["deprecation"], "true");
+        assert_eq!(
+            res.headers()["link"],
+            "</v1/functions/missing>; rel=\"successor-version\""
+        );
+        let body: Value = res.json().await.unwrap();
+        assert_eq!(body["success"], false);
+    }
+
+    #[tokio::test]
+    async fn test_namespaced_routes() {
+        let base = spawn_server().await;
+        let client = reqwest::Client::new();
+
+        let res = client
+            .post(format!("{base}/v1/namespaces"))
+            .json(&json!({"name": "team-a", "max_timeout_ms": 1000}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        let res = client
+            .post(format!("{base}/v1/namespaces/team-a/functions"))
+            .json(&json!({"name": "greet", "code": "return 'team-a'"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+
+        // 命名空间路由与限定名的旧路由指向同一个函数，不带前缀的名称不会落到其他命名空间
+        let body: Value = client
+            .post(format!("{base}/v1/namespaces/team-a/invoke/greet"))
+            .json(&json!({"input": {}}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["data"]["output"]["result"], "team-a");
+        let res = client
+            .get(format!("{base}/v1/functions/greet"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 404);
+
+        let res = client
+            .delete(format!("{base}/v1/namespaces/team-a"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 409);
+        let body: Value = client
+            .delete(format!("{base}/v1/namespaces/team-a?force=true"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["data"], json!(["team-a/greet"]));
+    }
+}
@@ -1,26 +1,442 @@
-use crate::functions::{FunctionMetadata, InvokeRequest, RegisterFunctionRequest};
-use crate::scheduler::{Scheduler, SimpleScheduler};
+use crate::functions::bundle::{
+    ConflictStrategy, FunctionArchive, FunctionBundle, ImportPayload, ImportResult, ImportStatus,
+};
+use crate::functions::canary::CanaryConfig;
+use crate::functions::compilation::{CompilationRecord, OnCompiling};
+use crate::functions::context::{CallerInfo, ContextContract};
+use crate::functions::deprecation::{DeprecateRequest, Deprecation};
+use crate::functions::diff::{DEFAULT_CONTEXT_LINES, DiffOptions, FunctionDiff};
+use crate::functions::docs;
+use crate::functions::http_route::{HttpRoute, RouteRequest};
+use crate::functions::labels::LabelSelector;
+use crate::functions::mirror::MirrorConfig;
+use crate::functions::name;
+use crate::functions::package::{FunctionPackage, PackageTree};
+use crate::functions::priority::Priority;
+use crate::functions::slo::SloConfig;
+use crate::functions::template::InputTemplate;
+use crate::functions::testing::FunctionTestReport;
+use crate::functions::versions::VersionSummary;
+use crate::functions::webhook::WebhookConfig;
+use crate::functions::{
+    ErrorOrigin, FluxError, FunctionMetadata, InvokeRequest, InvokeResponse,
+    RegisterFunctionRequest, UpdateFunctionRequest,
+};
+use crate::gateway::envelope::{API_VERSION, ApiStatus};
+use crate::gateway::payload::{self, BodyKind, CompressionConfig, InvokeBodyConfig, RawOutput};
+use crate::gateway::routes;
+use crate::gateway::shaping::HttpOutput;
+use crate::runtime::SimpleRuntime;
+use crate::runtime::cache::CachePolicyUpdate;
+use crate::runtime::debug_capture::BundleManifest;
+use crate::runtime::egress::{EGRESS_TOKEN_HEADER, FetchRequest};
+use crate::runtime::environment::RuntimeEnvironment;
+use crate::runtime::git::GitLoadRequest;
+use crate::runtime::instance::InstanceManager;
+use crate::runtime::invocation_log::{CaptureLevel, LogCaptureGuard};
+use crate::runtime::janitor::DiskJanitor;
+use crate::runtime::loader::DirectoryLoadResult;
+use crate::runtime::oci::{OciLoadRequest, OciLoadResult};
+use crate::runtime::resource::ResourceQuota;
+use crate::runtime::scratch::{ScratchArtifacts, ScratchStore};
+use crate::runtime::series::{FunctionReport, RankMetric, parse_span, unix_now};
+use crate::runtime::state::{STATE_TOKEN_HEADER, StateOperation};
+use crate::runtime::windows::StatsWindow;
+use crate::scheduler::ScheduleOptions;
+use crate::scheduler::canary::{CanaryReport, ReadinessReport};
+use crate::scheduler::chaos::ChaosRuleRequest;
+use crate::scheduler::circuit::CircuitStatus;
+use crate::scheduler::console::{
+    ConsoleExecuteRequest, ConsoleExecution, ConsolePromoteRequest, ConsolePromotion, ConsoleStats,
+};
+use crate::scheduler::deployments::{Deployment, DeploymentState};
+use crate::scheduler::deprecation::DeprecationReport;
+use crate::scheduler::drain::DrainStatus;
+use crate::scheduler::fanout::{FanoutRequest, FanoutResponse};
+use crate::scheduler::history::ReplayResponse;
+use crate::scheduler::jobs::{AsyncInvokeRequest, Job};
+use crate::scheduler::mirror::MirrorReport;
+use crate::scheduler::namespaces::{CreateNamespaceRequest, Namespace, script_type};
+use crate::scheduler::peers::{FORWARDED_BY_HEADER, PeerStatus};
+use crate::scheduler::profiles::{DEFAULT_PROFILE, SchedulerProfile, SchedulerRegistry};
+use crate::scheduler::sessions::{SessionExecutionsPage, SessionSummary};
+use crate::scheduler::slo::SloReport;
+use crate::scheduler::warmup::{WarmupReport, WarmupRequest};
+use crate::scheduler::webhooks::WebhookDeliveryLog;
+use crate::tunables::{ConfigReloader, ReloadError};
 use serde::{Deserialize, Serialize};
+use silent::header::{CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, HeaderName, HeaderValue, IF_MATCH};
+use silent::prelude::{SSEEvent, sse_reply};
 use silent::{Request, Response, Result as SilentResult, StatusCode};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::Duration;
+use tracing::Instrument;
+use utoipa::{IntoParams, ToSchema};
+
+/// 请求 ID 头
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// 调用幂等键请求头，优先于请求体中的 `idempotency_key`
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// 客户端会话 ID 请求头，优先于请求体中的 `session_id`
+pub const SESSION_ID_HEADER: &str = "x-flux-session-id";
+/// 执行亲和键请求头，优先于请求体中的 `affinity_key`
+pub const AFFINITY_KEY_HEADER: &str = "x-flux-affinity-key";
+/// 响应为幂等键重放的已保存结果时设置的响应头
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+/// 调用失败时设置的响应头：失败的归属（`user_code`、`platform`、`limit`、`input`）
+pub const ERROR_ORIGIN_HEADER: &str = "x-flux-error-origin";
 
 /// 从文件加载函数的请求
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct LoadFileRequest {
     pub file_path: String,
     pub name: Option<String>,
     pub description: Option<String>,
     pub timeout_ms: Option<u64>,
+    /// 目标调度器配置名（默认 `default`）
+    #[serde(default)]
+    pub profile: Option<String>,
 }
 
 /// 从目录加载函数的请求
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct LoadDirectoryRequest {
     pub directory_path: String,
 }
 
+/// 目录加载查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct LoadDirectoryQuery {
+    /// 只解析、验证并检测冲突，不注册任何函数
+    pub dry_run: Option<bool>,
+    /// 同名冲突处理策略：skip（默认）、overwrite 或 fail
+    pub on_conflict: Option<ConflictStrategy>,
+    /// 目标调度器配置名（默认 `default`）
+    pub profile: Option<String>,
+}
+
+/// 调度器查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct ProfileQuery {
+    /// 调度器配置名（默认 `default`）
+    pub profile: Option<String>,
+    /// 只统计该命名空间的函数
+    pub namespace: Option<String>,
+}
+
+/// 性能统计的查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct PerformanceStatsQuery {
+    /// 调度器配置名（默认 `default`）
+    pub profile: Option<String>,
+    /// 只统计该命名空间的函数
+    pub namespace: Option<String>,
+    /// 滑动窗口：`1m`、`5m`（默认）或 `1h`
+    pub window: Option<String>,
+}
+
+/// 删除命名空间的查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct DeleteNamespaceQuery {
+    /// 命名空间非空时一并删除其中的所有函数
+    pub force: Option<bool>,
+}
+
+/// 删除函数的查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct DeleteFunctionQuery {
+    /// 不等待在途调用结束，直接取消（被取消的调用返回 Cancelled）
+    pub force: Option<bool>,
+}
+
+/// 实例及事件查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct NamespaceQuery {
+    /// 只返回该命名空间中函数的实例或事件
+    pub namespace: Option<String>,
+}
+
+/// 命名空间详情：默认限制及当前用量
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NamespaceDetails {
+    #[serde(flatten)]
+    pub namespace: Namespace,
+    /// 命名空间中的函数（限定名）
+    pub functions: Vec<String>,
+}
+
+/// 资源配额详情：定义及选择该配额的函数
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaDetails {
+    #[serde(flatten)]
+    pub quota: ResourceQuota,
+    /// 选择该配额的函数
+    pub functions: Vec<String>,
+}
+
+/// 函数比较查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct DiffQuery {
+    /// 比较的另一个函数（缺省为同一函数的另一个版本）
+    pub against: Option<String>,
+    /// 本函数的版本（缺省为最新版本）
+    pub version: Option<String>,
+    /// 另一侧的版本（缺省为最新版本）
+    pub other_version: Option<String>,
+    /// 统一差异的上下文行数（默认 3）
+    pub context: Option<usize>,
+    /// 允许比较脚本类型不同的函数
+    pub force: Option<bool>,
+}
+
+/// 函数执行报告查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct ReportQuery {
+    /// 统计窗口，如 `30m`、`1h`、`1d`（默认 `1h`）
+    pub window: Option<String>,
+    /// 序列分辨率，向上取整为分桶时长的整数倍（默认 `5m`）
+    pub resolution: Option<String>,
+}
+
+/// 函数排行查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct TopFunctionsQuery {
+    /// 排行指标：`p50`、`p90`、`p99`、`error_rate`、`invocations`、`max_memory`、`cpu_time`（默认 `p99`）
+    pub metric: Option<RankMetric>,
+    /// 返回的函数数（默认 10）
+    pub limit: Option<usize>,
+    /// 统计窗口（默认 `1h`）
+    pub window: Option<String>,
+    /// 调度器配置名（默认 `default`）
+    pub profile: Option<String>,
+}
+
+/// 函数调用查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct InvokeQuery {
+    /// 函数仍在编译时的处理方式：wait（默认，最长等待函数超时时间）或 reject（立即返回 409）
+    pub on_compiling: Option<OnCompiling>,
+    /// 调用优先级，覆盖请求体和函数的默认值（原始请求体无法携带时使用）
+    pub priority: Option<Priority>,
+    /// 调用指定的保留版本（缺省调用最新版本）
+    pub version: Option<String>,
+    /// 为 true 时执行失败按失败归属返回状态码（输入 400、用户代码 422、限制 429、平台 500），
+    /// 缺省时执行失败仍返回 200，失败信息在结果的 `status` 中
+    pub strict: Option<bool>,
+    /// 捕获本次调用范围内该级别及以上的日志（`GET /executions/:id/log`），缺省不捕获；
+    /// 请求 ID 已有捕获的日志时返回 409
+    pub capture_log: Option<CaptureLevel>,
+}
+
+/// 异步调用任务查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct JobQuery {
+    /// 任务未完成时最长等待的毫秒数（长轮询），缺省或为 0 时立即返回
+    pub wait_ms: Option<u64>,
+}
+
+/// 会话调用列表查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct SessionExecutionsQuery {
+    /// 跳过的调用数（最新的在前）
+    pub offset: Option<usize>,
+    /// 最多返回的调用数，缺省 50
+    pub limit: Option<usize>,
+}
+
+/// 暂存文件查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct ArtifactQuery {
+    /// 要下载的文件（相对暂存目录的路径，如 `out/report.png`），缺省时列出全部文件
+    pub path: Option<String>,
+}
+
+/// 执行重放查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct ReplayQuery {
+    /// 为 true 时固定到原执行的函数版本（该版本需仍被保留），缺省调用最新版本
+    pub original_version: Option<bool>,
+    /// 为 true 时为影子重放：照常执行，但监控只计入重放次数，也不发送 webhook
+    pub shadow: Option<bool>,
+}
+
+/// 实例事件查询参数
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InstanceEventsQuery {
+    pub limit: Option<usize>,
+}
+
+/// 函数列表查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct ListFunctionsQuery {
+    /// 标签选择器，例如 `team=payments,env in (staging,prod)`
+    pub label: Option<String>,
+    /// 只列出该命名空间的函数
+    pub namespace: Option<String>,
+    /// `true` 只列出已弃用的函数，`false` 只列出未弃用的函数
+    pub deprecated: Option<bool>,
+}
+
+/// 按标签批量删除请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkDeleteRequest {
+    pub selector: String,
+}
+
+/// 按标签批量调用请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkInvokeRequest {
+    pub selector: String,
+    #[serde(default)]
+    pub input: serde_json::Value,
+}
+
+/// 输入模板预览请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TemplatePreviewRequest {
+    /// 示例调用输入
+    #[serde(default)]
+    pub input: serde_json::Value,
+    /// 待试用的模板（省略时使用函数当前的模板）
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+/// 批量调用中单个函数的结果
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkInvokeResult {
+    pub name: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<InvokeResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 函数导入查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct ImportQuery {
+    pub conflict: Option<ConflictStrategy>,
+    /// 先执行每个函数的测试用例，有用例失败的函数不导入
+    pub activate_if_tests_pass: Option<bool>,
+    /// 目标调度器配置名（默认 `default`）
+    pub profile: Option<String>,
+}
+
+/// 函数列表项
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FunctionSummary {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub timeout_ms: u64,
+    pub labels: HashMap<String, String>,
+    /// 所属调度器配置名
+    pub profile: String,
+    /// 所属命名空间
+    pub namespace: String,
+    /// 文档第一段的纯文本摘要（没有文档时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    /// 弃用状态（未弃用时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<Deprecation>,
+}
+
+/// 函数文档查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct DocsQuery {
+    /// `markdown`（默认，原始文档）或 `html`（渲染并清理后的 HTML）
+    pub format: Option<String>,
+}
+
+/// 函数测试查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct FunctionTestQuery {
+    /// 只执行这些用例（逗号分隔的用例名），缺省执行全部用例
+    pub cases: Option<String>,
+}
+
+/// 更新后的函数文档概要
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FunctionDocsSummary {
+    pub name: String,
+    /// 文档字节数，0 表示已移除
+    pub bytes: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+}
+
+/// 函数详情及其后台编译状态
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FunctionDetails {
+    #[serde(flatten)]
+    pub function: FunctionMetadata,
+    /// 最近一次编译记录（未启用编译时省略）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compilation: Option<CompilationRecord>,
+    /// 多文件函数包的文件树（不含文件内容）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<PackageTree>,
+    /// 调用上下文如何传入本函数
+    pub context: ContextContract,
+    /// 生命周期状态（删除排空中为 `draining`）和在途调用数
+    #[serde(default)]
+    pub lifecycle: DrainStatus,
+}
+
 /// 通用 API 响应格式
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
+#[aliases(
+    MessageResponse = ApiResponse<String>,
+    ErrorResponse = ApiResponse<String>,
+    JsonResponse = ApiResponse<serde_json::Value>,
+    FunctionResponse = ApiResponse<FunctionMetadata>,
+    FunctionDetailsResponse = ApiResponse<FunctionDetails>,
+    CompilationResponse = ApiResponse<CompilationRecord>,
+    FunctionReportResponse = ApiResponse<FunctionReport>,
+    VersionListResponse = ApiResponse<Vec<VersionSummary>>,
+    FunctionDiffResponse = ApiResponse<FunctionDiff>,
+    WebhookConfigResponse = ApiResponse<WebhookConfig>,
+    WebhookDeliveryLogResponse = ApiResponse<WebhookDeliveryLog>,
+    MirrorConfigResponse = ApiResponse<MirrorConfig>,
+    MirrorReportResponse = ApiResponse<MirrorReport>,
+    CircuitStatusResponse = ApiResponse<CircuitStatus>,
+    FunctionDocsResponse = ApiResponse<FunctionDocsSummary>,
+    FunctionListResponse = ApiResponse<Vec<FunctionSummary>>,
+    InvokeApiResponse = ApiResponse<InvokeResponse>,
+    BundleResponse = ApiResponse<FunctionBundle>,
+    ArchiveResponse = ApiResponse<FunctionArchive>,
+    ImportResponse = ApiResponse<Vec<ImportResult>>,
+    NameListResponse = ApiResponse<Vec<String>>,
+    BulkInvokeResponse = ApiResponse<Vec<BulkInvokeResult>>,
+    FanoutApiResponse = ApiResponse<FanoutResponse>,
+    JobResponse = ApiResponse<Job>,
+    ConsoleExecutionResponse = ApiResponse<ConsoleExecution>,
+    ConsolePromotionResponse = ApiResponse<ConsolePromotion>,
+    ConsoleStatsResponse = ApiResponse<ConsoleStats>,
+    SessionResponse = ApiResponse<SessionSummary>,
+    SessionExecutionsResponse = ApiResponse<SessionExecutionsPage>,
+    ReplayApiResponse = ApiResponse<ReplayResponse>,
+    DebugBundleManifestResponse = ApiResponse<BundleManifest>,
+    ScratchArtifactsResponse = ApiResponse<ScratchArtifacts>,
+    DirectoryLoadResponse = ApiResponse<DirectoryLoadResult>,
+    OciLoadResponse = ApiResponse<OciLoadResult>,
+    NamespaceResponse = ApiResponse<Namespace>,
+    NamespaceListResponse = ApiResponse<Vec<Namespace>>,
+    NamespaceDetailsResponse = ApiResponse<NamespaceDetails>,
+    DeploymentResponse = ApiResponse<Deployment>,
+    HttpRouteResponse = ApiResponse<HttpRoute>,
+    HttpRouteListResponse = ApiResponse<Vec<HttpRoute>>,
+    CanaryReportResponse = ApiResponse<CanaryReport>,
+    SloReportResponse = ApiResponse<SloReport>,
+    DeprecationReportResponse = ApiResponse<DeprecationReport>,
+    FunctionTestReportResponse = ApiResponse<FunctionTestReport>,
+    ReadinessResponse = ApiResponse<ReadinessReport>,
+    PeersResponse = ApiResponse<Vec<PeerStatus>>
+)]
 pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
@@ -28,7 +444,16 @@ pub struct ApiResponse<T> {
     pub message: Option<String>,
 }
 
+/// 生成 `ApiResponse` JSON 响应，并记录状态码供 `/v1` 中间件改写为版本化响应体
+pub fn api_json<T: Serialize>(response: &ApiResponse<T>, status: StatusCode) -> Response {
+    let mut res = Response::json(response).with_status(status);
+    res.extensions_mut().insert(ApiStatus(status));
+    res
+}
+
 /// 健康检查
+#[utoipa::path(get, path = "/health", tag = "system",
+    responses((status = 200, description = "服务正常", body = MessageResponse)))]
 pub async fn health_check(_req: Request) -> SilentResult<Response> {
     let response = ApiResponse {
         success: true,
@@ -36,73 +461,210 @@ pub async fn health_check(_req: Request) -> SilentResult<Response> {
         error: None,
         message: Some("Health check passed".to_string()),
     };
-    Ok(Response::json(&response))
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 就绪检查：汇总各函数的金丝雀探测
+///
+/// 非关键函数的探测失败只作为警告列出；带有 `critical=true` 标签的函数探测失败时返回 503。
+#[utoipa::path(get, path = "/health/ready", tag = "system",
+    responses(
+        (status = 200, description = "服务就绪（可能带有非关键函数的探测失败）", body = ReadinessResponse),
+        (status = 503, description = "关键函数的金丝雀探测失败", body = ReadinessResponse)
+    ))]
+pub async fn readiness_check(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let mut reports = Vec::new();
+    let mut enabled = false;
+    for profile in schedulers.profiles() {
+        enabled |= profile.scheduler.canaries().is_enabled();
+        for report in profile.scheduler.canary_reports().await {
+            reports.push((profile.name.clone(), report));
+        }
+    }
+    let report = ReadinessReport::from_reports(enabled, reports);
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let response = ApiResponse {
+        success: report.ready,
+        message: Some(match (report.ready, report.failing.len()) {
+            (false, _) => "Canaries of critical functions are failing".to_string(),
+            (true, 0) => "Ready".to_string(),
+            (true, failing) => format!("Ready with {failing} failing canaries"),
+        }),
+        data: Some(report),
+        error: None,
+    };
+    Ok(api_json(&response, status))
+}
+
+/// 转发节点的健康、熔断、转发统计和函数同步状态
+#[utoipa::path(get, path = "/peers", tag = "system",
+    responses((status = 200, description = "各节点的状态（未配置节点时为空）", body = PeersResponse)))]
+pub async fn list_peers(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let peers = schedulers.peers().status();
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!(
+            "{} of {} peers healthy",
+            peers
+                .iter()
+                .filter(|peer| peer.healthy && !peer.circuit_open)
+                .count(),
+            peers.len()
+        )),
+        data: Some(peers),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
 }
 
 /// 注册函数
+#[utoipa::path(post, path = "/functions", tag = "functions",
+    request_body = RegisterFunctionRequest,
+    responses(
+        (status = 200, description = "注册成功", body = MessageResponse),
+        (status = 400, description = "请求体或函数定义无效", body = ErrorResponse),
+        (status = 409, description = "函数已存在", body = ErrorResponse),
+        (status = 429, description = "注册表变更速率超限，响应带 Retry-After", body = ErrorResponse),
+        (status = 503, description = "持久化后端不可达，函数目录只读", body = ErrorResponse),
+        (status = 507, description = "函数数已达到注册表上限", body = ErrorResponse)
+    ))]
 pub async fn register_function(mut req: Request) -> SilentResult<Response> {
-    // 解析请求体 - 使用 json_parse() 方法
-    let register_req: RegisterFunctionRequest = match req.json_parse().await {
+    // 解析请求体（支持 gzip/zstd 压缩的请求体）
+    let mut register_req: RegisterFunctionRequest = match payload::read_json(&mut req).await {
         Ok(req) => req,
         Err(e) => {
             let response = ApiResponse::<()> {
                 success: false,
                 data: None,
-                error: Some(format!("Invalid request body: {e}")),
+                error: Some(e.to_string()),
                 message: Some("Failed to parse request body".to_string()),
             };
-            return Ok(Response::json(&response).with_status(StatusCode::BAD_REQUEST));
+            return Ok(api_json(&response, e.status()));
         }
     };
-    // 从配置中获取 scheduler
-    let scheduler = req.get_config_uncheck::<Arc<SimpleScheduler>>();
+    // `/namespaces/<ns>/functions` 注册到路径中的命名空间
+    if let Ok(namespace) = req.get_path_params::<String>("ns") {
+        if register_req
+            .namespace
+            .as_ref()
+            .is_some_and(|requested| *requested != namespace)
+        {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!(
+                    "Request body namespace does not match path namespace '{namespace}'"
+                )),
+                message: Some("Failed to register function".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+        register_req.namespace = Some(namespace);
+    }
+    // 从配置中获取调度器注册表，按请求中的 profile 注册
+    let schedulers = req.get_config_uncheck::<Arc<SchedulerRegistry>>();
+    let name = register_req.qualified_name();
 
-    let response = match scheduler
-        .registry()
-        .register(FunctionMetadata::from_request(register_req.clone()))
-        .await
-    {
-        Ok(_) => ApiResponse {
+    let response = match schedulers.register(register_req).await {
+        Ok(profile) => ApiResponse {
             success: true,
             data: Some("Function registration received".to_string()),
             error: None,
             message: Some(format!(
-                "Function '{}' registration request received",
-                register_req.name
+                "Function '{name}' registration request received (profile '{}')",
+                profile.name
             )),
         },
         Err(e) => {
+            let status = match e {
+                FluxError::FunctionAlreadyExists { .. } => StatusCode::CONFLICT,
+                FluxError::NamespaceNotFound { .. } => StatusCode::NOT_FOUND,
+                FluxError::NamespaceQuotaExceeded { .. } => StatusCode::FORBIDDEN,
+                // 当前构建和运行环境无法执行该脚本类型
+                FluxError::UnsupportedScriptType { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+                FluxError::CodeTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+                FluxError::StorageUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+                FluxError::RegistryFull { .. } => StatusCode::INSUFFICIENT_STORAGE,
+                FluxError::MutationRateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::BAD_REQUEST,
+            };
             let response = ApiResponse::<()> {
                 success: false,
                 data: None,
                 error: Some(format!("Register function failed: {e}")),
                 message: Some("Failed to register function".to_string()),
             };
-            return Ok(Response::json(&response).with_status(StatusCode::BAD_REQUEST));
+            return Ok(with_retry_after(api_json(&response, status), &e));
         }
     };
-    Ok(Response::json(&response))
+    Ok(api_json(&response, StatusCode::OK))
 }
 
-/// 列出所有函数
-pub async fn list_functions(req: Request) -> SilentResult<Response> {
-    // 从配置中获取 scheduler
-    let scheduler: &Arc<SimpleScheduler> = req.get_config()?;
+/// 标签选择器解析失败时的 400 响应（错误信息中包含语法说明）
+fn selector_error_response(e: FluxError) -> Response {
+    let response = ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(e.to_string()),
+        message: Some("Malformed label selector".to_string()),
+    };
+    api_json(&response, StatusCode::BAD_REQUEST)
+}
+
+/// 列出所有函数，支持 `?label=` 标签选择器和 `?deprecated=` 按弃用状态筛选
+#[utoipa::path(get, path = "/functions", tag = "functions",
+    params(ListFunctionsQuery),
+    responses(
+        (status = 200, description = "函数列表", body = FunctionListResponse),
+        (status = 400, description = "标签选择器格式错误", body = ErrorResponse)
+    ))]
+pub async fn list_functions(mut req: Request) -> SilentResult<Response> {
+    let query: ListFunctionsQuery = req.params_parse().unwrap_or_default();
+
+    // 从配置中获取调度器注册表
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
 
-    // 获取所有函数列表
-    let functions = scheduler.registry().list().await;
+    // 汇总所有调度器的函数列表
+    let functions = match query.label.as_deref() {
+        Some(expr) => match LabelSelector::parse(expr) {
+            Ok(selector) => schedulers.list_by_selector(&selector).await,
+            Err(e) => return Ok(selector_error_response(e)),
+        },
+        None => schedulers.list().await,
+    };
+
+    // `/namespaces/<ns>/functions` 或 `?namespace=` 只列出该命名空间的函数
+    let namespace = req.get_path_params::<String>("ns").ok().or(query.namespace);
+    let functions: Vec<_> = functions
+        .into_iter()
+        .filter(|(_, f)| namespace.as_deref().is_none_or(|ns| f.namespace() == ns))
+        .filter(|(_, f)| {
+            query
+                .deprecated
+                .is_none_or(|deprecated| f.deprecation.is_some() == deprecated)
+        })
+        .collect();
 
     // 构建函数列表数据
     let function_list: Vec<_> = functions
         .iter()
-        .map(|f| {
-            serde_json::json!({
-                "id": f.id.to_string(),
-                "name": f.name,
-                "description": f.description,
-                "created_at": f.created_at,
-                "timeout_ms": f.timeout_ms
-            })
+        .map(|(profile, f)| FunctionSummary {
+            id: f.id.to_string(),
+            name: f.name.clone(),
+            description: f.description.clone(),
+            created_at: f.created_at,
+            timeout_ms: f.timeout_ms,
+            labels: f.labels.clone(),
+            profile: profile.clone(),
+            namespace: f.namespace().to_string(),
+            excerpt: f.documentation.as_deref().and_then(docs::excerpt),
+            deprecation: f.deprecation.clone(),
         })
         .collect();
 
@@ -115,16 +677,22 @@ pub async fn list_functions(req: Request) -> SilentResult<Response> {
             functions.len()
         )),
     };
-    Ok(Response::json(&response))
+    Ok(payload::compressible(api_json(&response, StatusCode::OK)))
 }
 
 /// 获取单个函数信息
+#[utoipa::path(get, path = "/functions/{name}", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "函数详情（`ETag` 响应头为函数修订号）", body = FunctionDetailsResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
 pub async fn get_function(req: Request) -> SilentResult<Response> {
-    // 从配置中获取 scheduler
-    let scheduler: &Arc<SimpleScheduler> = req.get_config()?;
+    // 从配置中获取调度器注册表
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
 
     // 获取路径参数
-    let name: String = match req.get_path_params("name") {
+    let name: String = match path_function_name(&req) {
         Ok(name) => name,
         Err(_) => {
             let response = ApiResponse::<()> {
@@ -133,20 +701,29 @@ pub async fn get_function(req: Request) -> SilentResult<Response> {
                 error: Some("Missing function name parameter".to_string()),
                 message: Some("Function name is required".to_string()),
             };
-            return Ok(Response::json(&response).with_status(StatusCode::BAD_REQUEST));
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
         }
     };
 
-    // 从注册表获取函数详情
+    // 从函数所在调度器的注册表获取函数详情
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
     match scheduler.registry().get(&name).await {
         Ok(function) => {
+            let compilation = scheduler.registry().compilation(&name).await;
+            let revision = function.revision;
             let response = ApiResponse {
                 success: true,
-                data: Some(function),
+                data: Some(FunctionDetails {
+                    package: function.package.as_ref().map(FunctionPackage::tree),
+                    context: ContextContract::for_script_type(script_type(&function)),
+                    function: function.redacted(),
+                    compilation,
+                    lifecycle: scheduler.drain_status(&name),
+                }),
                 error: None,
                 message: Some(format!("Function '{name}' details retrieved")),
             };
-            Ok(Response::json(&response))
+            Ok(with_etag(api_json(&response, StatusCode::OK), revision))
         }
         Err(e) => {
             let response = ApiResponse::<()> {
@@ -155,17 +732,24 @@ pub async fn get_function(req: Request) -> SilentResult<Response> {
                 error: Some(format!("Function not found: {e}")),
                 message: Some(format!("Function '{name}' not found")),
             };
-            Ok(Response::json(&response).with_status(StatusCode::NOT_FOUND))
+            Ok(api_json(&response, StatusCode::NOT_FOUND))
         }
     }
 }
 
-/// 删除函数
-pub async fn delete_function(req: Request) -> SilentResult<Response> {
-    // 从配置中获取 scheduler
-    let scheduler: &Arc<SimpleScheduler> = req.get_config()?;
+/// 获取函数的后台编译状态，编译失败时包含 cargo 的 stderr
+#[utoipa::path(get, path = "/functions/{name}/compilation", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "编译记录", body = CompilationResponse),
+        (status = 404, description = "函数不存在或没有编译记录", body = ErrorResponse)
+    ))]
+pub async fn get_function_compilation(req: Request) -> SilentResult<Response> {
+    // 从配置中获取调度器注册表
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
 
-    let name: String = match req.get_path_params("name") {
+    // 获取路径参数
+    let name: String = match path_function_name(&req) {
         Ok(name) => name,
         Err(_) => {
             let response = ApiResponse::<()> {
@@ -174,56 +758,118 @@ pub async fn delete_function(req: Request) -> SilentResult<Response> {
                 error: Some("Missing function name parameter".to_string()),
                 message: Some("Function name is required".to_string()),
             };
-            return Ok(Response::json(&response).with_status(StatusCode::BAD_REQUEST));
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
         }
     };
 
-    // 从注册表删除函数
-    match scheduler.registry().remove(&name).await {
-        Ok(_) => {
-            // 同时从缓存中移除函数
-            scheduler.runtime().cache().remove(&name).await;
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    let record = match scheduler.registry().get(&name).await {
+        Ok(_) => scheduler
+            .registry()
+            .compilation(&name)
+            .await
+            .ok_or_else(|| {
+                format!("No compilation record for function '{name}' (compilation disabled)")
+            }),
+        Err(e) => Err(format!("Function not found: {e}")),
+    };
+    match record {
+        Ok(record) => {
+            let response = ApiResponse {
+                success: true,
+                data: Some(record),
+                error: None,
+                message: Some(format!("Compilation status of function '{name}' retrieved")),
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e),
+                message: Some(format!("No compilation status for function '{name}'")),
+            };
+            Ok(api_json(&response, StatusCode::NOT_FOUND))
+        }
+    }
+}
+
+/// 列出函数保留的版本（从旧到新），包含时间戳和与上一个版本的差异摘要
+#[utoipa::path(get, path = "/functions/{name}/versions", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "保留的版本", body = VersionListResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn list_function_versions(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
 
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler.registry().versions(&name).await {
+        Ok(versions) => {
+            let versions = VersionSummary::list(&versions);
             let response = ApiResponse {
                 success: true,
-                data: Some(format!("Function '{name}' deleted successfully")),
+                message: Some(format!(
+                    "Function '{name}' has {} retained versions",
+                    versions.len()
+                )),
+                data: Some(versions),
                 error: None,
-                message: Some(format!("Function '{name}' deleted successfully")),
             };
-            Ok(Response::json(&response))
+            Ok(api_json(&response, StatusCode::OK))
         }
         Err(e) => {
             let response = ApiResponse::<()> {
                 success: false,
                 data: None,
-                error: Some(format!("Failed to delete function: {e}")),
+                error: Some(e.to_string()),
                 message: Some(format!("Function '{name}' not found")),
             };
-            Ok(Response::json(&response).with_status(StatusCode::NOT_FOUND))
+            Ok(api_json(&response, StatusCode::NOT_FOUND))
         }
     }
 }
 
-/// 调用函数
-pub async fn invoke_function(mut req: Request) -> SilentResult<Response> {
-    // 先解析请求体
-    let invoke_req: InvokeRequest = match req.json_parse().await {
-        Ok(req) => req,
+/// 比较两个函数或同一函数的两个版本：元数据字段、参数、依赖、标签的变化和代码的统一差异
+#[utoipa::path(get, path = "/functions/{name}/diff", tag = "functions",
+    params(("name" = String, Path, description = "函数名"), DiffQuery),
+    responses(
+        (status = 200, description = "结构化比较结果", body = FunctionDiffResponse),
+        (status = 400, description = "查询参数无效，或两侧脚本类型不同且未指定 force", body = ErrorResponse),
+        (status = 404, description = "任一侧的函数或版本不存在", body = ErrorResponse)
+    ))]
+pub async fn diff_functions(mut req: Request) -> SilentResult<Response> {
+    let query: DiffQuery = match req.params_parse() {
+        Ok(query) => query,
         Err(e) => {
             let response = ApiResponse::<()> {
                 success: false,
                 data: None,
-                error: Some(format!("Invalid request body: {e}")),
-                message: Some("Failed to parse request body".to_string()),
+                error: Some(format!("Invalid query: {e}")),
+                message: Some("Invalid diff query".to_string()),
             };
-            return Ok(Response::json(&response).with_status(StatusCode::BAD_REQUEST));
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
         }
     };
 
-    // 从配置中获取 scheduler
-    let scheduler: &Arc<SimpleScheduler> = req.get_config()?;
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
 
-    let name: String = match req.get_path_params("name") {
+    let name: String = match path_function_name(&req) {
         Ok(name) => name,
         Err(_) => {
             let response = ApiResponse::<()> {
@@ -232,284 +878,4975 @@ pub async fn invoke_function(mut req: Request) -> SilentResult<Response> {
                 error: Some("Missing function name parameter".to_string()),
                 message: Some("Function name is required".to_string()),
             };
-            return Ok(Response::json(&response).with_status(StatusCode::BAD_REQUEST));
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
         }
     };
 
-    // 使用调度器执行函数
-    match scheduler.schedule(&name, invoke_req).await {
-        Ok(invoke_response) => {
+    let other = query.against.clone().unwrap_or_else(|| name.clone());
+    let mut sides = Vec::with_capacity(2);
+    for (side, version) in [(&name, &query.version), (&other, &query.other_version)] {
+        let registry = schedulers.resolve(side).await.scheduler.registry();
+        let function = match version {
+            Some(version) => registry.get_version(side, version).await,
+            None => registry.get(side).await,
+        };
+        match function {
+            Ok(function) => sides.push(function),
+            Err(e) => {
+                let response = ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    error: Some(e.to_string()),
+                    message: Some(format!("Function '{side}' not found")),
+                };
+                return Ok(api_json(&response, StatusCode::NOT_FOUND));
+            }
+        }
+    }
+
+    let (from, to) = (&sides[0], &sides[1]);
+    let (from_type, to_type) = (script_type(from), script_type(to));
+    if from_type != to_type && !query.force.unwrap_or(false) {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(format!(
+                "Cannot compare a {from_type} function with a {to_type} function; pass force=true to compare anyway"
+            )),
+            message: Some("Script types differ".to_string()),
+        };
+        return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+    }
+
+    let options = DiffOptions {
+        context_lines: query.context.unwrap_or(DEFAULT_CONTEXT_LINES),
+        ..Default::default()
+    };
+    let diff = FunctionDiff::between(from, to, options);
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!(
+            "{}:{} -> {}:{}: {} fields changed, +{} -{} lines",
+            diff.from.name,
+            diff.from.version,
+            diff.to.name,
+            diff.to.version,
+            diff.summary.fields_changed,
+            diff.summary.lines_added,
+            diff.summary.lines_removed
+        )),
+        data: Some(diff),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 删除函数的一个历史版本（最新版本不能单独删除）
+#[utoipa::path(delete, path = "/functions/{name}/versions/{version}", tag = "functions",
+    params(
+        ("name" = String, Path, description = "函数名"),
+        ("version" = String, Path, description = "版本号")
+    ),
+    responses(
+        (status = 200, description = "被删除的版本", body = FunctionResponse),
+        (status = 404, description = "函数或版本不存在", body = ErrorResponse),
+        (status = 409, description = "不能删除最新版本", body = ErrorResponse)
+    ))]
+pub async fn delete_function_version(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let (name, version): (String, String) =
+        match (path_function_name(&req), req.get_path_params("version")) {
+            (Ok(name), Ok(version)) => (name, version),
+            _ => {
+                let response = ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    error: Some("Missing function name or version parameter".to_string()),
+                    message: Some("Function name and version are required".to_string()),
+                };
+                return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+            }
+        };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler.delete_version(&name, &version).await {
+        Ok(removed) => {
             let response = ApiResponse {
                 success: true,
-                data: Some(invoke_response),
+                message: Some(format!("Version {version} of function '{name}' deleted")),
+                data: Some(removed),
                 error: None,
-                message: Some(format!("Function '{name}' executed successfully")),
             };
-            Ok(Response::json(&response))
+            Ok(api_json(&response, StatusCode::OK))
         }
         Err(e) => {
+            let status = match e {
+                FluxError::LatestVersion { .. } => StatusCode::CONFLICT,
+                _ => StatusCode::NOT_FOUND,
+            };
             let response = ApiResponse::<()> {
                 success: false,
                 data: None,
-                error: Some(format!("Function execution failed: {e}")),
-                message: Some(format!("Failed to execute function '{name}'")),
+                error: Some(e.to_string()),
+                message: Some(format!(
+                    "Failed to delete version {version} of function '{name}'"
+                )),
             };
-            Ok(Response::json(&response).with_status(StatusCode::INTERNAL_SERVER_ERROR))
+            Ok(api_json(&response, status))
         }
     }
 }
 
-/// 获取调度器状态
-pub async fn get_scheduler_status(req: Request) -> SilentResult<Response> {
-    // 从配置中获取 scheduler
-    let _scheduler: &Arc<SimpleScheduler> = req.get_config()?;
-
-    // 暂时返回模拟状态，因为 SimpleScheduler 还没有这些方法
-    let response = ApiResponse {
-        success: true,
-        data: Some("Scheduler is running".to_string()),
-        error: None,
-        message: Some("Scheduler status retrieved successfully".to_string()),
-    };
-    Ok(Response::json(&response))
-}
+/// 导出单个函数
+#[utoipa::path(get, path = "/functions/{name}/export", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "函数导出包", body = BundleResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn export_function(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
 
-/// 从文件加载函数
-pub async fn load_function_from_file(mut req: Request) -> SilentResult<Response> {
-    // 先解析请求体
-    let load_req: LoadFileRequest = match req.json_parse().await {
-        Ok(req) => req,
-        Err(e) => {
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
             let response = ApiResponse::<()> {
                 success: false,
                 data: None,
-                error: Some(format!("Invalid request body: {e}")),
-                message: Some("Failed to parse request body".to_string()),
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
             };
-            return Ok(Response::json(&response).with_status(StatusCode::BAD_REQUEST));
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
         }
     };
 
-    // 从配置中获取 scheduler
-    let scheduler: &Arc<SimpleScheduler> = req.get_config()?;
-
-    // 使用 FunctionLoader 从文件加载函数
-    match scheduler
-        .loader()
-        .load_function_from_file(
-            &load_req.file_path,
-            load_req.name,
-            load_req.description,
-            load_req.timeout_ms,
-        )
-        .await
-    {
-        Ok(function_metadata) => {
-            let function_name = function_metadata.name.clone();
-            // 将函数注册到注册表
-            match scheduler
-                .registry()
-                .register(function_metadata.clone())
-                .await
-            {
-                Ok(_) => {
-                    let response = ApiResponse {
-                        success: true,
-                        data: Some(function_metadata),
-                        error: None,
-                        message: Some(format!(
-                            "Function '{function_name}' loaded successfully from file"
-                        )),
-                    };
-                    Ok(Response::json(&response))
-                }
-                Err(e) => {
-                    let response = ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some(format!("Failed to register function: {e}")),
-                        message: Some("Function loading failed during registration".to_string()),
-                    };
-                    Ok(Response::json(&response).with_status(StatusCode::INTERNAL_SERVER_ERROR))
-                }
-            }
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler.export_function(&name).await {
+        Ok(bundle) => {
+            let response = ApiResponse {
+                success: true,
+                data: Some(bundle),
+                error: None,
+                message: Some(format!("Function '{name}' exported")),
+            };
+            Ok(payload::compressible(api_json(&response, StatusCode::OK)))
         }
         Err(e) => {
             let response = ApiResponse::<()> {
                 success: false,
                 data: None,
-                error: Some(format!("Failed to load function from file: {e}")),
-                message: Some("Function loading failed".to_string()),
+                error: Some(format!("Function not found: {e}")),
+                message: Some(format!("Function '{name}' not found")),
             };
-            Ok(Response::json(&response).with_status(StatusCode::INTERNAL_SERVER_ERROR))
+            Ok(api_json(&response, StatusCode::NOT_FOUND))
         }
     }
 }
 
-/// 从目录加载函数
-pub async fn load_functions_from_directory(mut req: Request) -> SilentResult<Response> {
-    // 先解析请求体
-    let load_req: LoadDirectoryRequest = match req.json_parse().await {
-        Ok(req) => req,
+/// 导出所有函数
+#[utoipa::path(get, path = "/functions/export", tag = "functions",
+    responses((status = 200, description = "所有函数的导出归档", body = ArchiveResponse)))]
+pub async fn export_functions(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let mut functions: Vec<_> = schedulers
+        .list()
+        .await
+        .into_iter()
+        .map(|(_, function)| function)
+        .collect();
+    functions.sort_by(|a, b| a.name.cmp(&b.name));
+    let archive = FunctionArchive::from_functions(&functions);
+
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!("Exported {} functions", archive.functions.len())),
+        data: Some(archive),
+        error: None,
+    };
+    Ok(payload::compressible(api_json(&response, StatusCode::OK)))
+}
+
+/// 导入函数包或归档
+#[utoipa::path(post, path = "/functions/import", tag = "functions",
+    params(ImportQuery),
+    request_body(content = serde_json::Value, description = "FunctionBundle 或 FunctionArchive"),
+    responses(
+        (status = 200, description = "每个函数的导入结果", body = ImportResponse),
+        (status = 400, description = "格式版本、内容哈希或请求体无效", body = ErrorResponse),
+        (status = 409, description = "conflict=fail 时存在同名函数", body = ErrorResponse),
+        (status = 503, description = "持久化后端不可达，函数目录只读", body = ErrorResponse)
+    ))]
+pub async fn import_functions(mut req: Request) -> SilentResult<Response> {
+    let query: ImportQuery = match req.params_parse() {
+        Ok(query) => query,
         Err(e) => {
             let response = ApiResponse::<()> {
                 success: false,
                 data: None,
-                error: Some(format!("Invalid request body: {e}")),
-                message: Some("Failed to parse request body".to_string()),
+                error: Some(format!("Invalid query: {e}")),
+                message: Some("conflict must be one of skip, overwrite, rename, fail".to_string()),
             };
-            return Ok(Response::json(&response).with_status(StatusCode::BAD_REQUEST));
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
         }
     };
 
-    // 从配置中获取 scheduler
-    let scheduler: &Arc<SimpleScheduler> = req.get_config()?;
+    let payload: ImportPayload = match payload::read_json(&mut req).await {
+        Ok(payload) => payload,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse function bundle".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
 
-    // 使用 FunctionLoader 从目录加载函数
-    match scheduler
-        .loader()
-        .load_functions_from_directory(&load_req.directory_path)
-        .await
-    {
-        Ok(functions) => {
-            let mut loaded_functions = Vec::new();
-            let mut failed_functions = Vec::new();
-
-            // 批量注册函数
-            for function_metadata in functions {
-                let function_name = function_metadata.name.clone();
-                match scheduler
-                    .registry()
-                    .register(function_metadata.clone())
-                    .await
-                {
-                    Ok(_) => {
-                        loaded_functions.push(function_name);
-                    }
-                    Err(e) => {
-                        failed_functions.push(format!("{function_name}: {e}"));
-                    }
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let result = match (
+        payload.into_bundles(),
+        schedulers.get(query.profile.as_deref().unwrap_or(DEFAULT_PROFILE)),
+    ) {
+        (Ok(bundles), Ok(target)) => {
+            // 已属于其他调度器的函数不能导入到目标调度器
+            let mut owned = Vec::new();
+            let mut importable = Vec::new();
+            for bundle in bundles {
+                match schedulers.find(&bundle.name).await {
+                    Some(owner) if owner.name != target.name => owned.push(ImportResult::failed(
+                        &bundle.name,
+                        format!("Function already exists in profile '{}'", owner.name),
+                    )),
+                    _ => importable.push(bundle),
                 }
             }
+            target
+                .scheduler
+                .import_bundles(
+                    importable,
+                    query.conflict.unwrap_or_default(),
+                    query.activate_if_tests_pass.unwrap_or(false),
+                )
+                .await
+                .map(|mut results| {
+                    results.extend(owned);
+                    results
+                })
+        }
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    };
 
-            if failed_functions.is_empty() {
+    match result {
+        Ok(results) => {
+            let imported = results
+                .iter()
+                .filter(|r| r.status == ImportStatus::Imported)
+                .count();
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Imported {imported} of {} functions",
+                    results.len()
+                )),
+                data: Some(results),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::FunctionAlreadyExists { .. } => StatusCode::CONFLICT,
+                FluxError::UnsupportedScriptType { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+                FluxError::CodeTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+                FluxError::StorageUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+                FluxError::RegistryFull { .. } => StatusCode::INSUFFICIENT_STORAGE,
+                FluxError::MutationRateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Import failed: {e}")),
+                message: Some("Failed to import functions".to_string()),
+            };
+            Ok(with_retry_after(api_json(&response, status), &e))
+        }
+    }
+}
+
+/// 部署查询参数
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct DeploymentQuery {
+    /// 目标调度器配置名（默认 `default`）
+    pub profile: Option<String>,
+    /// 准备时执行各函数的测试用例，全部通过后自动激活
+    pub activate_if_tests_pass: Option<bool>,
+}
+
+/// 部署操作失败时的状态码
+fn deployment_error_status(e: &FluxError) -> StatusCode {
+    match e {
+        FluxError::DeploymentNotFound { .. } => StatusCode::NOT_FOUND,
+        FluxError::DeploymentConflict { .. } => StatusCode::CONFLICT,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+/// 持有部署的调度器配置
+fn deployment_profile<'a>(
+    schedulers: &'a SchedulerRegistry,
+    id: &str,
+) -> Result<&'a SchedulerProfile, FluxError> {
+    schedulers
+        .profiles()
+        .find(|profile| profile.scheduler.deployment(id).is_ok())
+        .ok_or_else(|| FluxError::DeploymentNotFound { id: id.to_string() })
+}
+
+/// 部署操作失败的响应
+fn deployment_error(e: &FluxError, message: String) -> Response {
+    let response = ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(e.to_string()),
+        message: Some(message),
+    };
+    api_json(&response, deployment_error_status(e))
+}
+
+/// 创建部署：一组函数定义（与导入包格式相同）在后台校验和预编译，不修改线上函数
+///
+/// 返回 `preparing` 状态的部署，通过 `GET /deployments/{id}` 查看准备结果。
+/// `activate_if_tests_pass=true` 时准备阶段还执行各函数的测试用例，全部通过后自动激活。
+#[utoipa::path(post, path = "/deployments", tag = "deployments",
+    params(DeploymentQuery),
+    request_body(content = serde_json::Value, description = "FunctionBundle 或 FunctionArchive"),
+    responses(
+        (status = 202, description = "已创建的部署（准备中）", body = DeploymentResponse),
+        (status = 400, description = "格式版本、函数名重复或请求体无效", body = ErrorResponse),
+        (status = 409, description = "与未结束的部署有重叠的函数，或函数属于其他调度器配置", body = ErrorResponse)
+    ))]
+pub async fn create_deployment(mut req: Request) -> SilentResult<Response> {
+    let query = req.params_parse::<DeploymentQuery>().unwrap_or_default();
+    let payload: ImportPayload = match payload::read_json(&mut req).await {
+        Ok(payload) => payload,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse function bundle".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let target = match schedulers.get(query.profile.as_deref().unwrap_or(DEFAULT_PROFILE)) {
+        Ok(target) => target,
+        Err(e) => {
+            return Ok(deployment_error(
+                &e,
+                "Failed to create deployment".to_string(),
+            ));
+        }
+    };
+    let bundles = match payload.into_bundles() {
+        Ok(bundles) => bundles,
+        Err(e) => {
+            return Ok(deployment_error(
+                &e,
+                "Failed to create deployment".to_string(),
+            ));
+        }
+    };
+    // 已属于其他调度器的函数不能部署到目标调度器
+    for bundle in &bundles {
+        if let Some(owner) = schedulers.find(&bundle.name).await
+            && owner.name != target.name
+        {
+            let e = FluxError::DeploymentConflict {
+                reason: format!(
+                    "Function {} already exists in profile '{}'",
+                    bundle.name, owner.name
+                ),
+            };
+            return Ok(deployment_error(
+                &e,
+                "Failed to create deployment".to_string(),
+            ));
+        }
+    }
+
+    match target
+        .scheduler
+        .create_deployment(bundles, query.activate_if_tests_pass.unwrap_or(false))
+    {
+        Ok(deployment) => {
+            let scheduler = target.scheduler.clone();
+            let id = deployment.id.clone();
+            tokio::spawn(async move {
+                if let Err(e) = scheduler.prepare_deployment(&id).await {
+                    tracing::warn!("Failed to prepare deployment {}: {}", id, e);
+                }
+            });
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Deployment '{}' of {} functions is being prepared",
+                    deployment.id,
+                    deployment.items.len()
+                )),
+                data: Some(deployment),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::ACCEPTED))
+        }
+        Err(e) => Ok(deployment_error(
+            &e,
+            "Failed to create deployment".to_string(),
+        )),
+    }
+}
+
+/// 获取部署及各函数的准备、激活结果
+#[utoipa::path(get, path = "/deployments/{id}", tag = "deployments",
+    params(("id" = String, Path, description = "部署 ID")),
+    responses(
+        (status = 200, description = "部署", body = DeploymentResponse),
+        (status = 404, description = "部署不存在", body = ErrorResponse)
+    ))]
+pub async fn get_deployment(req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    match deployment_profile(schedulers, &id).and_then(|profile| profile.scheduler.deployment(&id))
+    {
+        Ok(deployment) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!("Deployment '{id}' is {}", deployment.state)),
+                data: Some(deployment),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(deployment_error(
+            &e,
+            format!("Failed to get deployment '{id}'"),
+        )),
+    }
+}
+
+/// 激活就绪的部署：原子地写入全部函数
+///
+/// 任一函数写入失败时线上函数保持不变，部署进入 `failed`，响应中带有各函数的失败原因。
+#[utoipa::path(post, path = "/deployments/{id}/activate", tag = "deployments",
+    params(("id" = String, Path, description = "部署 ID")),
+    responses(
+        (status = 200, description = "已激活的部署", body = DeploymentResponse),
+        (status = 404, description = "部署不存在", body = ErrorResponse),
+        (status = 409, description = "部署未就绪，或激活失败（响应中带有失败的部署）", body = DeploymentResponse)
+    ))]
+pub async fn activate_deployment(req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let profile = match deployment_profile(schedulers, &id) {
+        Ok(profile) => profile,
+        Err(e) => {
+            return Ok(deployment_error(
+                &e,
+                format!("Failed to activate deployment '{id}'"),
+            ));
+        }
+    };
+    match profile.scheduler.activate_deployment(&id).await {
+        Ok(deployment) if deployment.state == DeploymentState::Active => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Activated deployment '{id}' ({} functions)",
+                    deployment.items.len()
+                )),
+                data: Some(deployment),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Ok(deployment) => {
+            let response = ApiResponse {
+                success: false,
+                error: Some(format!(
+                    "Deployment '{id}' failed to activate; live functions were not changed"
+                )),
+                message: Some(format!("Failed to activate deployment '{id}'")),
+                data: Some(deployment),
+            };
+            Ok(api_json(&response, StatusCode::CONFLICT))
+        }
+        Err(e) => Ok(deployment_error(
+            &e,
+            format!("Failed to activate deployment '{id}'"),
+        )),
+    }
+}
+
+/// 回滚已激活的部署：恢复激活前的函数版本，删除部署新增的函数
+#[utoipa::path(post, path = "/deployments/{id}/rollback", tag = "deployments",
+    params(("id" = String, Path, description = "部署 ID")),
+    responses(
+        (status = 200, description = "已回滚的部署", body = DeploymentResponse),
+        (status = 404, description = "部署不存在", body = ErrorResponse),
+        (status = 409, description = "部署未激活，或函数在激活后又被修改", body = ErrorResponse)
+    ))]
+pub async fn rollback_deployment(req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let result = match deployment_profile(schedulers, &id) {
+        Ok(profile) => profile.scheduler.rollback_deployment(&id).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(deployment) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!("Rolled back deployment '{id}'")),
+                data: Some(deployment),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(deployment_error(
+            &e,
+            format!("Failed to roll back deployment '{id}'"),
+        )),
+    }
+}
+
+/// 更新函数元数据（描述、超时、标签等，不修改代码）
+#[utoipa::path(patch, path = "/functions/{name}", tag = "functions",
+    params(
+        ("name" = String, Path, description = "函数名"),
+        ("If-Match" = Option<String>, Header, description = "期望的函数修订号")
+    ),
+    request_body = UpdateFunctionRequest,
+    responses(
+        (status = 200, description = "更新后的函数", body = FunctionResponse),
+        (status = 400, description = "更新内容无效", body = ErrorResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse),
+        (status = 412, description = "函数修订号与 If-Match 不一致", body = ErrorResponse),
+        (status = 429, description = "注册表变更速率超限，响应带 Retry-After", body = ErrorResponse),
+        (status = 503, description = "持久化后端不可达，函数目录只读", body = ErrorResponse)
+    ))]
+pub async fn update_function(mut req: Request) -> SilentResult<Response> {
+    let update_req: UpdateFunctionRequest = match req.json_parse().await {
+        Ok(update_req) => update_req,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let expected_revision = match expected_revision(&req) {
+        Ok(revision) => revision,
+        Err(response) => return Ok(*response),
+    };
+
+    // 新的超时同样受函数所在调度器的上限约束
+    let profile = schedulers.resolve(&name).await;
+    let result = match update_req.timeout_ms.map(|t| profile.check_timeout(t)) {
+        Some(Err(e)) => Err(e),
+        _ => {
+            profile
+                .scheduler
+                .update_function(&name, update_req, expected_revision)
+                .await
+        }
+    };
+    match result {
+        Ok(function) => {
+            let revision = function.revision;
+            let response = ApiResponse {
+                success: true,
+                data: Some(function.redacted()),
+                error: None,
+                message: Some(format!("Function '{name}' updated")),
+            };
+            Ok(with_etag(api_json(&response, StatusCode::OK), revision))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::FunctionNotFound { .. } => StatusCode::NOT_FOUND,
+                FluxError::RevisionMismatch { .. } => StatusCode::PRECONDITION_FAILED,
+                FluxError::UnsupportedScriptType { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+                FluxError::CodeTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+                FluxError::StorageUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+                FluxError::RegistryFull { .. } => StatusCode::INSUFFICIENT_STORAGE,
+                FluxError::MutationRateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Update function failed: {e}")),
+                message: Some(format!("Failed to update function '{name}'")),
+            };
+            Ok(with_retry_after(api_json(&response, status), &e))
+        }
+    }
+}
+
+/// 按当前代码重新推断函数的参数和返回类型（只替换此前推断的字段，声明的字段保持不变）
+#[utoipa::path(post, path = "/functions/{name}/infer", tag = "functions",
+    params(
+        ("name" = String, Path, description = "函数名"),
+        ("If-Match" = Option<String>, Header, description = "期望的函数修订号")
+    ),
+    responses(
+        (status = 200, description = "重新推断后的函数", body = FunctionResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse),
+        (status = 412, description = "函数修订号与 If-Match 不一致", body = ErrorResponse),
+        (status = 429, description = "注册表变更速率超限，响应带 Retry-After", body = ErrorResponse),
+        (status = 503, description = "持久化后端不可达，函数目录只读", body = ErrorResponse)
+    ))]
+pub async fn infer_function_signature(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let expected_revision = match expected_revision(&req) {
+        Ok(revision) => revision,
+        Err(response) => return Ok(*response),
+    };
+
+    let result = schedulers
+        .resolve(&name)
+        .await
+        .scheduler
+        .infer_signature(&name, expected_revision)
+        .await;
+    match result {
+        Ok(function) => {
+            let revision = function.revision;
+            let response = ApiResponse {
+                success: true,
+                data: Some(function.redacted()),
+                error: None,
+                message: Some(format!("Signature of function '{name}' inferred")),
+            };
+            Ok(with_etag(api_json(&response, StatusCode::OK), revision))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::FunctionNotFound { .. } => StatusCode::NOT_FOUND,
+                FluxError::RevisionMismatch { .. } => StatusCode::PRECONDITION_FAILED,
+                FluxError::StorageUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+                FluxError::RegistryFull { .. } => StatusCode::INSUFFICIENT_STORAGE,
+                FluxError::MutationRateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Infer signature failed: {e}")),
+                message: Some(format!("Failed to infer signature of function '{name}'")),
+            };
+            Ok(with_retry_after(api_json(&response, status), &e))
+        }
+    }
+}
+
+/// 设置函数的 webhook 配置（两个 URL 都为空时清除）
+#[utoipa::path(put, path = "/functions/{name}/webhooks", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    request_body = WebhookConfig,
+    responses(
+        (status = 200, description = "生效的 webhook 配置（密钥已隐藏）", body = WebhookConfigResponse),
+        (status = 400, description = "配置无效", body = ErrorResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn set_function_webhooks(mut req: Request) -> SilentResult<Response> {
+    let webhooks: WebhookConfig = match req.json_parse().await {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let webhooks = (webhooks.on_success_url.is_some() || webhooks.on_failure_url.is_some())
+        .then_some(webhooks);
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler.set_webhooks(&name, webhooks).await {
+        Ok(function) => {
+            let response = ApiResponse {
+                success: true,
+                data: Some(function.webhooks.unwrap_or_default().redacted()),
+                error: None,
+                message: Some(format!("Webhooks of function '{name}' updated")),
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::FunctionNotFound { .. } => StatusCode::NOT_FOUND,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Update webhooks failed: {e}")),
+                message: Some(format!("Failed to update webhooks of function '{name}'")),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 设置函数的请求镜像：调用按采样率在后台复制到目标函数并比较输出
+#[utoipa::path(put, path = "/functions/{name}/mirror", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    request_body = MirrorConfig,
+    responses(
+        (status = 200, description = "生效的镜像配置", body = MirrorConfigResponse),
+        (status = 400, description = "配置无效或镜像目标不存在", body = ErrorResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn set_function_mirror(mut req: Request) -> SilentResult<Response> {
+    let mirror: MirrorConfig = match req.json_parse().await {
+        Ok(mirror) => mirror,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    update_function_mirror(&req, Some(mirror)).await
+}
+
+/// 停止函数的请求镜像（已记录的不一致保留）
+#[utoipa::path(delete, path = "/functions/{name}/mirror", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "镜像已停止", body = MessageResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn delete_function_mirror(req: Request) -> SilentResult<Response> {
+    update_function_mirror(&req, None).await
+}
+
+async fn update_function_mirror(
+    req: &Request,
+    mirror: Option<MirrorConfig>,
+) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler.set_mirror(&name, mirror).await {
+        Ok(function) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(match &function.mirror {
+                    Some(mirror) => format!("Function '{name}' is mirrored to '{}'", mirror.target),
+                    None => format!("Mirroring of function '{name}' stopped"),
+                }),
+                data: function.mirror,
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::FunctionNotFound { .. } => StatusCode::NOT_FOUND,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Update mirror failed: {e}")),
+                message: Some(format!("Failed to update mirror of function '{name}'")),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 获取函数的金丝雀探测配置和健康状态
+#[utoipa::path(get, path = "/functions/{name}/canary", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "探测配置和健康状态", body = CanaryReportResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn get_function_canary(req: Request) -> SilentResult<Response> {
+    function_canary(&req, None).await
+}
+
+/// 设置函数的金丝雀探测：按间隔以固定输入调用函数并检查状态和输出
+#[utoipa::path(put, path = "/functions/{name}/canary", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    request_body = CanaryConfig,
+    responses(
+        (status = 200, description = "生效的配置和（重置后的）健康状态", body = CanaryReportResponse),
+        (status = 400, description = "配置无效", body = ErrorResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn set_function_canary(mut req: Request) -> SilentResult<Response> {
+    let canary: CanaryConfig = match req.json_parse().await {
+        Ok(canary) => canary,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    function_canary(&req, Some(Some(canary))).await
+}
+
+/// 移除函数的金丝雀探测
+#[utoipa::path(delete, path = "/functions/{name}/canary", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "探测已移除", body = CanaryReportResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn delete_function_canary(req: Request) -> SilentResult<Response> {
+    function_canary(&req, Some(None)).await
+}
+
+/// `update` 为 `Some` 时先替换探测配置，再返回配置和健康状态
+async fn function_canary(
+    req: &Request,
+    update: Option<Option<CanaryConfig>>,
+) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    let updated = match update {
+        Some(canary) => scheduler.set_canary(&name, canary).await.map(|_| ()),
+        None => Ok(()),
+    };
+    match updated.and(scheduler.canary_report(&name).await) {
+        Ok(report) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(match &report.config {
+                    Some(_) => format!(
+                        "Canary of function '{name}' is {}",
+                        report.health.status.as_str()
+                    ),
+                    None => format!("Function '{name}' has no canary"),
+                }),
+                data: Some(report),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::FunctionNotFound { .. } => StatusCode::NOT_FOUND,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Canary request failed: {e}")),
+                message: Some(format!("Failed to access canary of function '{name}'")),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 获取函数的 SLO 合规数字：窗口内的成功率、p95 延迟、剩余错误预算和燃烧率
+///
+/// SLO 窗口超出时间序列的保留时长时按保留时长统计，`window_degraded` 为 true。
+#[utoipa::path(get, path = "/functions/{name}/slo", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "SLO 配置、合规数字和燃烧率", body = SloReportResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn get_function_slo(req: Request) -> SilentResult<Response> {
+    function_slo(&req, None).await
+}
+
+/// 设置函数的 SLO（可用性目标、可选的 p95 延迟目标和统计窗口）
+#[utoipa::path(put, path = "/functions/{name}/slo", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    request_body = SloConfig,
+    responses(
+        (status = 200, description = "生效的配置和合规数字", body = SloReportResponse),
+        (status = 400, description = "配置无效", body = ErrorResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn set_function_slo(mut req: Request) -> SilentResult<Response> {
+    let slo: SloConfig = match req.json_parse().await {
+        Ok(slo) => slo,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    function_slo(&req, Some(Some(slo))).await
+}
+
+/// 移除函数的 SLO
+#[utoipa::path(delete, path = "/functions/{name}/slo", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "SLO 已移除", body = SloReportResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn delete_function_slo(req: Request) -> SilentResult<Response> {
+    function_slo(&req, Some(None)).await
+}
+
+/// `update` 为 `Some` 时先替换 SLO，再返回配置和合规数字
+async fn function_slo(req: &Request, update: Option<Option<SloConfig>>) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    let updated = match update {
+        Some(slo) => scheduler.set_slo(&name, slo).await.map(|_| ()),
+        None => Ok(()),
+    };
+    match updated.and(scheduler.slo_report(&name).await) {
+        Ok(report) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(match &report.compliance {
+                    Some(compliance) if compliance.availability_met => {
+                        format!("Function '{name}' meets its availability target")
+                    }
+                    Some(_) => format!("Function '{name}' is violating its availability target"),
+                    None => format!("Function '{name}' has no SLO"),
+                }),
+                data: Some(report),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::FunctionNotFound { .. } => StatusCode::NOT_FOUND,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("SLO request failed: {e}")),
+                message: Some(format!("Failed to access SLO of function '{name}'")),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 获取函数的弃用状态和弃用后各调用方的调用次数
+#[utoipa::path(get, path = "/functions/{name}/deprecate", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "弃用状态和各调用方的调用次数", body = DeprecationReportResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn get_function_deprecation(req: Request) -> SilentResult<Response> {
+    function_deprecation(&req, None).await
+}
+
+/// 弃用函数：调用照常执行，响应带 `Deprecation`、`Sunset` 头和 `meta.deprecated`
+///
+/// 过了 `sunset_at` 后按 `on_sunset` 处理：`warn` 照常执行，`reject` 返回 410，
+/// `redirect` 改为调用 `replacement` 并在响应中标记 `redirected_from`。
+#[utoipa::path(post, path = "/functions/{name}/deprecate", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    request_body = DeprecateRequest,
+    responses(
+        (status = 200, description = "生效的弃用状态", body = DeprecationReportResponse),
+        (status = 400, description = "请求无效或替代函数不存在", body = ErrorResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn deprecate_function(mut req: Request) -> SilentResult<Response> {
+    let request: DeprecateRequest = match req.json_parse().await {
+        Ok(request) => request,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    function_deprecation(&req, Some(Some(request))).await
+}
+
+/// 取消函数的弃用
+#[utoipa::path(delete, path = "/functions/{name}/deprecate", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "已取消弃用", body = DeprecationReportResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn undeprecate_function(req: Request) -> SilentResult<Response> {
+    function_deprecation(&req, Some(None)).await
+}
+
+/// `update` 为 `Some` 时先弃用（`Some(None)` 时取消弃用），再返回弃用状态和调用方计数
+async fn function_deprecation(
+    req: &Request,
+    update: Option<Option<DeprecateRequest>>,
+) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    let updated = match update {
+        Some(Some(request)) => scheduler.deprecate(&name, request).await.map(|_| ()),
+        Some(None) => scheduler.undeprecate(&name).await.map(|_| ()),
+        None => Ok(()),
+    };
+    match updated.and(scheduler.deprecation_report(&name).await) {
+        Ok(report) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(match &report.deprecation {
+                    Some(deprecation) if report.sunset => {
+                        format!("Function '{name}' was sunset at {}", deprecation.sunset_at)
+                    }
+                    Some(deprecation) => format!(
+                        "Function '{name}' is deprecated until {}",
+                        deprecation.sunset_at
+                    ),
+                    None => format!("Function '{name}' is not deprecated"),
+                }),
+                data: Some(report),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let status = match &e {
+                FluxError::FunctionNotFound { .. } => StatusCode::NOT_FOUND,
+                e if e.is_registry_limit() => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Deprecation request failed: {e}")),
+                message: Some(format!("Failed to access deprecation of function '{name}'")),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 以测试模式执行函数自带的测试用例
+///
+/// 测试调用经过正常的执行路径，但不计入调用统计、不写入缓存，在命名空间中单独计数。
+/// 有用例失败时仍返回 200，报告中带有每个用例的实际结果、与期望的差异和耗时。
+#[utoipa::path(post, path = "/functions/{name}/test", tag = "functions",
+    params(("name" = String, Path, description = "函数名"), FunctionTestQuery),
+    responses(
+        (status = 200, description = "各用例的结果", body = FunctionTestReportResponse),
+        (status = 404, description = "函数或用例不存在", body = ErrorResponse)
+    ))]
+pub async fn test_function(mut req: Request) -> SilentResult<Response> {
+    let name = path_function_name(&req)?;
+    let query = req.params_parse::<FunctionTestQuery>().unwrap_or_default();
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let cases: Option<Vec<String>> = query.cases.map(|cases| {
+        cases
+            .split(',')
+            .map(str::trim)
+            .filter(|case| !case.is_empty())
+            .map(str::to_string)
+            .collect()
+    });
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler.run_function_tests(&name, cases.as_deref()).await {
+        Ok(report) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(report.summary()),
+                data: Some(report),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::FunctionNotFound { .. } | FluxError::TestCaseNotFound { .. } => {
+                    StatusCode::NOT_FOUND
+                }
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Test run failed: {e}")),
+                message: Some(format!("Failed to test function '{name}'")),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 获取函数文档：默认返回原始 Markdown，`?format=html` 返回渲染并清理后的 HTML
+#[utoipa::path(get, path = "/functions/{name}/docs", tag = "functions",
+    params(("name" = String, Path, description = "函数名"), DocsQuery),
+    responses(
+        (status = 200, description = "函数文档（text/markdown 或 text/html）", body = String),
+        (status = 400, description = "不支持的 format", body = ErrorResponse),
+        (status = 404, description = "函数不存在或没有文档", body = ErrorResponse)
+    ))]
+pub async fn get_function_docs(mut req: Request) -> SilentResult<Response> {
+    let query: DocsQuery = req.params_parse().unwrap_or_default();
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let not_found = |error: String, message: String| {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(error),
+            message: Some(message),
+        };
+        api_json(&response, StatusCode::NOT_FOUND)
+    };
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    let function = match scheduler.registry().get(&name).await {
+        Ok(function) => function,
+        Err(e) => {
+            return Ok(not_found(
+                format!("Function not found: {e}"),
+                format!("Function '{name}' not found"),
+            ));
+        }
+    };
+    let Some(documentation) = function.documentation else {
+        return Ok(not_found(
+            format!("Function '{name}' has no documentation"),
+            format!("No documentation for function '{name}'"),
+        ));
+    };
+
+    let (content_type, body) = match query.format.as_deref().unwrap_or("markdown") {
+        "markdown" | "md" => ("text/markdown; charset=utf-8", documentation),
+        "html" => (
+            "text/html; charset=utf-8",
+            docs::render_html(&documentation),
+        ),
+        other => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Unsupported documentation format: {other}")),
+                message: Some("format must be markdown or html".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    let mut response = Response::empty();
+    response.set_header(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    Ok(response.with_body(body.into()))
+}
+
+/// 替换函数文档（请求体为原始 Markdown，空请求体表示移除）
+///
+/// 只更新文档，不改变函数版本，也不触发重新编译。
+#[utoipa::path(put, path = "/functions/{name}/docs", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    request_body(content = String, content_type = "text/markdown", description = "Markdown 文档"),
+    responses(
+        (status = 200, description = "更新后的文档概要", body = FunctionDocsResponse),
+        (status = 400, description = "文档不是有效的 UTF-8", body = ErrorResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse),
+        (status = 413, description = "文档超过 64 KiB", body = ErrorResponse)
+    ))]
+pub async fn set_function_docs(mut req: Request) -> SilentResult<Response> {
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let config = payload::CompressionConfig::from_request(&req);
+    let body = match payload::read_decoded_body(&mut req, docs::MAX_DOCUMENTATION_BYTES, &config)
+        .await
+        .map_err(|e| (e.status(), e.to_string()))
+        .and_then(|body| {
+            String::from_utf8(body).map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "Documentation must be UTF-8 text".to_string(),
+                )
+            })
+        }) {
+        Ok(body) => body,
+        Err((status, error)) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(error),
+                message: Some("Failed to read documentation".to_string()),
+            };
+            return Ok(api_json(&response, status));
+        }
+    };
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let documentation = (!body.trim().is_empty()).then_some(body);
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler.set_documentation(&name, documentation).await {
+        Ok(function) => {
+            let documentation = function.documentation.unwrap_or_default();
+            let summary = FunctionDocsSummary {
+                name: function.name,
+                bytes: documentation.len(),
+                excerpt: docs::excerpt(&documentation),
+            };
+            let response = ApiResponse {
+                success: true,
+                message: Some(if summary.bytes == 0 {
+                    format!("Documentation of function '{name}' removed")
+                } else {
+                    format!("Documentation of function '{name}' updated")
+                }),
+                data: Some(summary),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let status = match &e {
+                FluxError::FunctionNotFound { .. } => StatusCode::NOT_FOUND,
+                FluxError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Update documentation failed: {e}")),
+                message: Some(format!(
+                    "Failed to update documentation of function '{name}'"
+                )),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 获取函数的熔断状态和最近的状态转换（最近的在前）
+#[utoipa::path(get, path = "/functions/{name}/circuit", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "熔断状态", body = CircuitStatusResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn get_function_circuit(req: Request) -> SilentResult<Response> {
+    function_circuit(req, false).await
+}
+
+/// 手动关闭函数的熔断并清空统计窗口
+#[utoipa::path(post, path = "/functions/{name}/circuit/reset", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "重置后的熔断状态", body = CircuitStatusResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn reset_function_circuit(req: Request) -> SilentResult<Response> {
+    function_circuit(req, true).await
+}
+
+async fn function_circuit(req: Request, reset: bool) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    let function = match scheduler.registry().get(&name).await {
+        Ok(function) => function,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Function not found: {e}")),
+                message: Some(format!("Function '{name}' not found")),
+            };
+            return Ok(api_json(&response, StatusCode::NOT_FOUND));
+        }
+    };
+
+    if reset {
+        scheduler.circuits().reset(&name);
+    }
+    let status = scheduler.circuits().status(&function);
+    let response = ApiResponse {
+        success: true,
+        message: Some(if reset {
+            format!("Circuit breaker of function '{name}' reset")
+        } else {
+            format!("Circuit breaker of function '{name}' is {:?}", status.state)
+        }),
+        data: Some(status),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 获取函数的镜像计数和最近的输出不一致记录（最近的在前）
+#[utoipa::path(get, path = "/functions/{name}/mirror/mismatches", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "镜像配置、计数和不一致记录", body = MirrorReportResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn get_mirror_mismatches(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    let function = match scheduler.registry().get(&name).await {
+        Ok(function) => function,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Function not found: {e}")),
+                message: Some(format!("Function '{name}' not found")),
+            };
+            return Ok(api_json(&response, StatusCode::NOT_FOUND));
+        }
+    };
+
+    let report = scheduler.mirrors().report(&name, function.mirror);
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!(
+            "Retrieved {} mirror mismatches of function '{name}'",
+            report.mismatches.len()
+        )),
+        data: Some(report),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 预览输入模板：返回示例输入经模板转换后的结果，不执行函数
+#[utoipa::path(post, path = "/functions/{name}/template/preview", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    request_body = TemplatePreviewRequest,
+    responses(
+        (status = 200, description = "转换后的输入", body = JsonResponse),
+        (status = 400, description = "模板无效或路径无法解析", body = ErrorResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn preview_input_template(mut req: Request) -> SilentResult<Response> {
+    let preview_req: TemplatePreviewRequest = match req.json_parse().await {
+        Ok(preview_req) => preview_req,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    let function = match scheduler.registry().get(&name).await {
+        Ok(function) => function,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Function not found: {e}")),
+                message: Some(format!("Function '{name}' not found")),
+            };
+            return Ok(api_json(&response, StatusCode::NOT_FOUND));
+        }
+    };
+
+    // 没有模板时输入原样传给函数
+    let result = match preview_req.template.or(function.input_template) {
+        Some(template) => {
+            InputTemplate::parse(&template).and_then(|template| template.apply(&preview_req.input))
+        }
+        None => Ok(preview_req.input),
+    };
+    match result {
+        Ok(output) => {
+            let response = ApiResponse {
+                success: true,
+                data: Some(output),
+                error: None,
+                message: Some(format!("Input template of function '{name}' applied")),
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Template preview failed: {e}")),
+                message: Some(format!(
+                    "Failed to apply input template of function '{name}'"
+                )),
+            };
+            Ok(api_json(&response, StatusCode::BAD_REQUEST))
+        }
+    }
+}
+
+/// 获取函数最近的 webhook 投递记录（最近的在前）
+#[utoipa::path(get, path = "/functions/{name}/webhooks/deliveries", tag = "functions",
+    params(("name" = String, Path, description = "函数名")),
+    responses(
+        (status = 200, description = "投递记录", body = WebhookDeliveryLogResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn get_webhook_deliveries(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    if let Err(e) = scheduler.registry().get(&name).await {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(format!("Function not found: {e}")),
+            message: Some(format!("Function '{name}' not found")),
+        };
+        return Ok(api_json(&response, StatusCode::NOT_FOUND));
+    }
+
+    let log = scheduler.webhooks().deliveries(&name);
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!(
+            "Retrieved {} webhook deliveries of function '{name}'",
+            log.deliveries.len()
+        )),
+        data: Some(log),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 按标签选择器批量删除函数
+#[utoipa::path(post, path = "/functions/bulk/delete", tag = "functions",
+    request_body = BulkDeleteRequest,
+    responses(
+        (status = 200, description = "被删除的函数名", body = NameListResponse),
+        (status = 400, description = "标签选择器格式错误", body = ErrorResponse)
+    ))]
+pub async fn bulk_delete_functions(mut req: Request) -> SilentResult<Response> {
+    let bulk_req: BulkDeleteRequest = match req.json_parse().await {
+        Ok(bulk_req) => bulk_req,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    let selector = match LabelSelector::parse(&bulk_req.selector) {
+        Ok(selector) => selector,
+        Err(e) => return Ok(selector_error_response(e)),
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let mut deleted = Vec::new();
+    for profile in schedulers.profiles() {
+        deleted.extend(profile.scheduler.delete_by_selector(&selector).await);
+    }
+
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!("Deleted {} functions", deleted.len())),
+        data: Some(deleted),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 按标签选择器批量调用函数
+#[utoipa::path(post, path = "/functions/bulk/invoke", tag = "functions",
+    request_body = BulkInvokeRequest,
+    responses(
+        (status = 200, description = "每个函数的调用结果", body = BulkInvokeResponse),
+        (status = 400, description = "标签选择器格式错误", body = ErrorResponse)
+    ))]
+pub async fn bulk_invoke_functions(mut req: Request) -> SilentResult<Response> {
+    let bulk_req: BulkInvokeRequest = match req.json_parse().await {
+        Ok(bulk_req) => bulk_req,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    let selector = match LabelSelector::parse(&bulk_req.selector) {
+        Ok(selector) => selector,
+        Err(e) => return Ok(selector_error_response(e)),
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let request = InvokeRequest {
+        input: bulk_req.input,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        session_id: None,
+        affinity_key: None,
+    };
+    let mut invoked = Vec::new();
+    for profile in schedulers.profiles() {
+        invoked.extend(
+            profile
+                .scheduler
+                .invoke_by_selector(&selector, request.clone())
+                .await,
+        );
+    }
+    let results: Vec<BulkInvokeResult> = invoked
+        .into_iter()
+        .map(|(name, result)| match result {
+            Ok(response) => BulkInvokeResult {
+                name,
+                success: true,
+                response: Some(response),
+                error: None,
+            },
+            Err(e) => BulkInvokeResult {
+                name,
+                success: false,
+                response: None,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect();
+
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!("Invoked {} functions", results.len())),
+        data: Some(results),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 删除函数
+///
+/// 删除期间函数处于排空状态，新调用返回 410；默认等待在途调用结束（最长为排空超时）后再删除，
+/// `?force=true` 时直接取消在途调用。
+#[utoipa::path(delete, path = "/functions/{name}", tag = "functions",
+    params(
+        ("name" = String, Path, description = "函数名"),
+        ("If-Match" = Option<String>, Header, description = "期望的函数修订号"),
+        DeleteFunctionQuery
+    ),
+    responses(
+        (status = 200, description = "删除成功", body = MessageResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse),
+        (status = 412, description = "函数修订号与 If-Match 不一致", body = ErrorResponse),
+        (status = 429, description = "注册表变更速率超限，响应带 Retry-After", body = ErrorResponse),
+        (status = 503, description = "持久化后端不可达，函数目录只读", body = ErrorResponse)
+    ))]
+pub async fn delete_function(mut req: Request) -> SilentResult<Response> {
+    let query = req
+        .params_parse::<DeleteFunctionQuery>()
+        .unwrap_or_default();
+    // 从配置中获取调度器注册表
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let expected_revision = match expected_revision(&req) {
+        Ok(revision) => revision,
+        Err(response) => return Ok(*response),
+    };
+
+    // 从函数所在调度器删除函数（同时从缓存中移除）
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler
+        .drain_and_delete(&name, expected_revision, query.force.unwrap_or(false))
+        .await
+    {
+        Ok(_) => {
+            let response = ApiResponse {
+                success: true,
+                data: Some(format!("Function '{name}' deleted successfully")),
+                error: None,
+                message: Some(format!("Function '{name}' deleted successfully")),
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let (status, message) = match e {
+                FluxError::RevisionMismatch { .. } => (
+                    StatusCode::PRECONDITION_FAILED,
+                    format!("Function '{name}' was modified concurrently"),
+                ),
+                FluxError::StorageUnavailable { .. } => (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Function storage is unavailable, the catalog is read-only".to_string(),
+                ),
+                FluxError::MutationRateLimited { .. } => (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "Too many registry changes, retry later".to_string(),
+                ),
+                _ => (
+                    StatusCode::NOT_FOUND,
+                    format!("Function '{name}' not found"),
+                ),
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to delete function: {e}")),
+                message: Some(message),
+            };
+            Ok(with_retry_after(api_json(&response, status), &e))
+        }
+    }
+}
+
+/// 调用函数
+///
+/// `application/json` 请求体为完整的 `InvokeRequest`；`text/*` 请求体作为字符串输入，
+/// 其他内容类型以 `{"body_base64", "content_type"}` 输入。函数输出声明了 `content_type`
+/// 时按原始响应返回；输出为 `{"$http": {"status", "headers", "body"}}`（或函数设置了
+/// `http_response`）时按声明的状态码、响应头和内容类型返回。
+/// 函数仍在后台编译时按 `on_compiling` 等待或返回 409。
+#[utoipa::path(post, path = "/invoke/{name}", tag = "invoke",
+    params(("name" = String, Path, description = "函数名"), InvokeQuery),
+    request_body(content = InvokeRequest, description = "JSON 调用请求；也接受 text/* 和任意二进制请求体"),
+    responses(
+        (status = 200, description = "调用结果（`$http` 响应使用函数声明的状态码）", body = InvokeApiResponse),
+        (status = 400, description = "请求体无效，或输入模板无法应用于本次输入", body = ErrorResponse),
+        (status = 409, description = "函数仍在编译（on_compiling=reject），或请求 ID 已有捕获的日志（capture_log）", body = ErrorResponse),
+        (status = 410, description = "函数正在删除，不再接受调用", body = ErrorResponse),
+        (status = 413, description = "请求体超过大小上限", body = ErrorResponse),
+        (status = 422, description = "严格模式（strict=true）下用户代码执行失败", body = InvokeApiResponse),
+        (status = 429, description = "该优先级的等待队列已满（错误信息包含当前队列深度）", body = ErrorResponse),
+        (status = 500, description = "调度或执行失败，或函数声明的 HTTP 响应无效", body = ErrorResponse),
+        (status = 503, description = "函数的熔断已打开，或全局调用名额和等待队列已满（Retry-After 为建议的重试间隔秒数）", body = ErrorResponse)
+    ))]
+pub async fn invoke_function(mut req: Request) -> SilentResult<Response> {
+    let request_id = request_id_from_headers(&req);
+    let bad_request = |status: StatusCode, error: String, message: &str| {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(error),
+            message: Some(message.to_string()),
+        };
+        with_request_id(api_json(&response, status), &request_id)
+    };
+
+    // 先读取请求体（所有内容类型都受大小上限约束）
+    let (kind, content_type, body) = match read_invoke_body(&mut req).await {
+        Ok(body) => body,
+        Err((status, error, message)) => return Ok(bad_request(status, error, message)),
+    };
+    let body_len = body.len();
+
+    let invoke_req = match kind {
+        BodyKind::Json => serde_json::from_slice::<InvokeRequest>(&body).map_err(|e| e.to_string()),
+        _ => payload::raw_input(kind, content_type.as_deref().unwrap_or_default(), body).map(
+            |input| InvokeRequest {
+                input,
+                retry_policy: None,
+                priority: None,
+                idempotency_key: None,
+                session_id: None,
+                affinity_key: None,
+            },
+        ),
+    };
+    let mut invoke_req = match invoke_req {
+        Ok(invoke_req) => invoke_req,
+        Err(e) => {
+            return Ok(bad_request(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid request body: {e}"),
+                "Failed to parse request body",
+            ));
+        }
+    };
+
+    let query = req.params_parse::<InvokeQuery>().unwrap_or_default();
+    let on_compiling = query.on_compiling.unwrap_or_default();
+    let strict = query.strict.unwrap_or(false);
+    let version = query.version.filter(|version| !version.is_empty());
+    if query.priority.is_some() {
+        invoke_req.priority = query.priority;
+    }
+    if let Some(key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        // 非法的值交给调度器按幂等键规则拒绝
+        invoke_req.idempotency_key = Some(key.to_str().unwrap_or_default().to_string());
+    }
+    if let Some(session_id) = req.headers().get(SESSION_ID_HEADER) {
+        invoke_req.session_id = Some(session_id.to_str().unwrap_or_default().to_string());
+    }
+    if let Some(key) = req.headers().get(AFFINITY_KEY_HEADER) {
+        invoke_req.affinity_key = Some(key.to_str().unwrap_or_default().to_string());
+    }
+
+    // 从配置中获取调度器注册表
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            return Ok(bad_request(
+                StatusCode::BAD_REQUEST,
+                "Missing function name parameter".to_string(),
+                "Function name is required",
+            ));
+        }
+    };
+
+    // 捕获在 span 创建前开始，span 内的事件都记入本次调用的日志；调用结束时停止
+    let log_capture = match query.capture_log.map(|level| {
+        schedulers
+            .invocation_logs()
+            .begin(&request_id, &name, level)
+    }) {
+        Some(Err(e)) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to capture the invocation log".to_string()),
+            };
+            return Ok(api_json(&response, invoke_error_status(&e)));
+        }
+        Some(Ok(capture)) => capture,
+        None => None,
+    };
+    // 使用调度器执行函数，整个调用链路挂在同一个 span 下
+    let span = tracing::info_span!(
+        "invoke",
+        request_id = %request_id,
+        function = %name,
+        log_capture = log_capture.as_ref().map(LogCaptureGuard::id)
+    );
+    // 只记录请求体的类型和大小，不记录内容
+    tracing::debug!(parent: &span, "Invoke body: {:?} ({} bytes)", kind, body_len);
+    let profile = schedulers.resolve(&name).await;
+    tracing::debug!(parent: &span, "Resolved scheduler profile '{}'", profile.name);
+    let result = profile
+        .scheduler
+        .schedule_with(
+            &name,
+            invoke_req,
+            ScheduleOptions {
+                on_compiling,
+                invocation_id: Some(request_id.clone()),
+                version: version.clone(),
+                caller: caller_info(&req),
+                // 其他节点转发来的调用不再转发
+                forwarded: req.headers().contains_key(FORWARDED_BY_HEADER),
+                ..Default::default()
+            },
+        )
+        .instrument(span)
+        .await;
+
+    // 声明了 HTTP 响应（`$http` 或 `http_response` 函数）时按声明的状态码、响应头和内容类型返回
+    let latest = profile.scheduler.registry().get(&name).await.ok();
+    let http_response = match (&result, &version) {
+        (Ok(_), Some(version)) => profile
+            .scheduler
+            .registry()
+            .get_version(&name, version)
+            .await
+            .is_ok_and(|function| function.http_response),
+        (Ok(_), None) => latest
+            .as_ref()
+            .is_some_and(|function| function.http_response),
+        (Err(_), _) => false,
+    };
+    let deprecation = latest.and_then(|function| function.deprecation);
+    let replayed = result.as_ref().is_ok_and(|response| response.replayed);
+    let error_origin = match &result {
+        Ok(response) => response.status.origin(),
+        Err(e) => Some(e.origin()),
+    };
+    let shaped = result
+        .as_ref()
+        .ok()
+        .and_then(|response| declared_response(&response.output, http_response, &name));
+    let mut response = match (result, shaped) {
+        (Ok(_), Some(response)) => response,
+        (Ok(mut invoke_response), None) => {
+            invoke_response.request_id = Some(request_id.clone());
+            match error_origin.filter(|_| strict) {
+                Some(origin) => {
+                    let response = ApiResponse {
+                        success: false,
+                        error: invoke_response.status.failure_message(),
+                        data: Some(invoke_response),
+                        message: Some(format!("Function '{name}' failed ({origin} error)")),
+                    };
+                    api_json(&response, origin_status(origin))
+                }
+                None => {
+                    let response = ApiResponse {
+                        success: true,
+                        data: Some(invoke_response),
+                        error: None,
+                        message: Some(format!("Function '{name}' executed successfully")),
+                    };
+                    api_json(&response, StatusCode::OK)
+                }
+            }
+        }
+        (Err(e), _) => {
+            let status = invoke_error_status(&e);
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Function execution failed: {e}")),
+                message: Some(format!("Failed to execute function '{name}'")),
+            };
+            with_retry_after(api_json(&response, status), &e)
+        }
+    };
+    if replayed {
+        response.set_header(
+            HeaderName::from_static(IDEMPOTENT_REPLAYED_HEADER),
+            HeaderValue::from_static("true"),
+        );
+    }
+    if let Some(origin) = error_origin {
+        response.set_header(
+            HeaderName::from_static(ERROR_ORIGIN_HEADER),
+            HeaderValue::from_static(origin.as_str()),
+        );
+    }
+    if let Some(deprecation) = &deprecation {
+        with_deprecation(&mut response, deprecation);
+    }
+    Ok(with_request_id(response, &request_id))
+}
+
+/// 弃用函数的调用响应：`Deprecation`（弃用时间）、`Sunset`（停用时间）和指向替代函数的 `Link` 头，
+/// 以及供 `/v1` 响应写入 `meta.deprecated` 的弃用说明
+fn with_deprecation(response: &mut Response, deprecation: &Deprecation) {
+    let successor = deprecation
+        .replacement
+        .as_ref()
+        .map(|name| format!("</{API_VERSION}/invoke/{name}>; rel=\"successor-version\""));
+    let headers = [
+        (
+            "deprecation",
+            Some(format!("@{}", deprecation.deprecated_at.timestamp())),
+        ),
+        (
+            "sunset",
+            Some(
+                deprecation
+                    .sunset_at
+                    .format("%a, %d %b %Y %H:%M:%S GMT")
+                    .to_string(),
+            ),
+        ),
+        ("link", successor),
+    ];
+    for (name, value) in headers {
+        if let Some(value) = value
+            && let Ok(value) = HeaderValue::from_str(&value)
+        {
+            response.set_header(HeaderName::from_static(name), value);
+        }
+    }
+    response.extensions_mut().insert(deprecation.notice());
+}
+
+/// 严格模式下执行失败的状态码：用户代码和输入的问题为 4xx，平台侧失败为 5xx
+fn origin_status(origin: ErrorOrigin) -> StatusCode {
+    match origin {
+        ErrorOrigin::Input => StatusCode::BAD_REQUEST,
+        ErrorOrigin::UserCode => StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorOrigin::Limit => StatusCode::TOO_MANY_REQUESTS,
+        ErrorOrigin::Platform => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// 函数输出声明了 HTTP 响应或原始响应时按声明构造响应（声明无效时为 500），否则返回 `None`
+fn declared_response(
+    output: &serde_json::Value,
+    http_response: bool,
+    name: &str,
+) -> Option<Response> {
+    let (shaped, kind) = match HttpOutput::from_output(output, http_response) {
+        Some(shaped) => (shaped.map(HttpOutput::into_response), "HTTP"),
+        None => (RawOutput::from_output(output)?.map(raw_response), "raw"),
+    };
+    Some(shaped.unwrap_or_else(|e| {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(e),
+            message: Some(format!(
+                "Function '{name}' returned an invalid {kind} response"
+            )),
+        };
+        api_json(&response, StatusCode::INTERNAL_SERVER_ERROR)
+    }))
+}
+
+/// 读取调用请求体及其类型，失败时返回错误响应的状态码、错误和说明
+async fn read_invoke_body(
+    req: &mut Request,
+) -> Result<(BodyKind, Option<String>, Vec<u8>), (StatusCode, String, &'static str)> {
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let kind = BodyKind::from_content_type(content_type.as_deref());
+    let max_body_bytes = InvokeBodyConfig::from_request(req).max_body_bytes;
+    // 压缩的请求体在解压后同样受大小上限约束
+    let compression = CompressionConfig::from_request(req);
+    match payload::read_decoded_body(req, max_body_bytes, &compression).await {
+        Ok(body) => Ok((kind, content_type, body)),
+        Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            e.to_string(),
+            "Request body too large",
+        )),
+        Err(e) => Err((e.status(), e.to_string(), "Failed to read request body")),
+    }
+}
+
+/// 调度失败时的状态码
+fn invoke_error_status(e: &FluxError) -> StatusCode {
+    match e {
+        FluxError::FunctionNotFound { .. } => StatusCode::NOT_FOUND,
+        FluxError::StillCompiling { .. } => StatusCode::CONFLICT,
+        FluxError::QueueFull { .. } => StatusCode::TOO_MANY_REQUESTS,
+        // 命名空间的并发调用数已满
+        FluxError::NamespaceQuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+        FluxError::NamespaceNotFound { .. } => StatusCode::NOT_FOUND,
+        // 指定的版本已被裁剪或删除
+        FluxError::VersionNotFound { .. } => StatusCode::NOT_FOUND,
+        // 输入不符合函数的 input_schema
+        FluxError::InputSchemaViolation { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        // 输入模板无法应用于本次输入，或扇出请求无效
+        FluxError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        // 执行历史中没有该调用，或其输入未完整保留
+        FluxError::ExecutionNotFound { .. } => StatusCode::NOT_FOUND,
+        FluxError::ReplayUnavailable { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        // 没有该调用的调试包，或已被淘汰
+        FluxError::DebugBundleNotFound { .. } => StatusCode::NOT_FOUND,
+        // 函数未设置 persist_scratch、暂存文件已过期，或没有该文件
+        FluxError::ArtifactsNotFound { .. } | FluxError::ArtifactNotFound { .. } => {
+            StatusCode::NOT_FOUND
+        }
+        FluxError::ArtifactTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        // 调用没有请求 capture_log，或捕获的日志已被淘汰
+        FluxError::InvocationLogNotFound { .. } => StatusCode::NOT_FOUND,
+        // 请求 ID 已有捕获的日志（进行中或尚未淘汰），不替换其他调用的记录
+        FluxError::InvocationLogConflict { .. } => StatusCode::CONFLICT,
+        // 函数的熔断已打开，响应带 Retry-After
+        FluxError::CircuitOpen { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        // 函数正在删除，不再接受调用
+        FluxError::FunctionDraining { .. } => StatusCode::GONE,
+        // 弃用的函数已停用且 `on_sunset` 为 `reject`
+        FluxError::FunctionSunset { .. } => StatusCode::GONE,
+        // 全局调用名额已满且队列已满或排队超时，响应带 Retry-After
+        FluxError::Overloaded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// 熔断打开、服务过载、注册表变更或控制台执行速率超限时带上 Retry-After（秒，向上取整）
+fn with_retry_after(mut response: Response, e: &FluxError) -> Response {
+    if let FluxError::CircuitOpen { retry_after, .. }
+    | FluxError::Overloaded { retry_after, .. }
+    | FluxError::MutationRateLimited { retry_after, .. }
+    | FluxError::ConsoleRateLimited { retry_after, .. } = e
+    {
+        let secs = retry_after.as_millis().div_ceil(1000).max(1);
+        if let Ok(value) = HeaderValue::from_str(&secs.to_string()) {
+            response.set_header(HeaderName::from_static("retry-after"), value);
+        }
+    }
+    response
+}
+
+/// 按自定义 HTTP 路由调用函数：请求各部分按绑定的输入映射组成函数输入
+///
+/// 函数输出声明了 HTTP 响应（`$http` 或 `http_response` 函数）或原始响应时按声明返回，
+/// 否则输出本身作为 JSON 返回。
+pub async fn invoke_http_route(
+    mut req: Request,
+    route: HttpRoute,
+    path_params: BTreeMap<String, String>,
+) -> SilentResult<Response> {
+    let request_id = request_id_from_headers(&req);
+    let name = route.function.clone();
+    let failure = |status: StatusCode, error: String, message: String| {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(error),
+            message: Some(message),
+        };
+        with_request_id(api_json(&response, status), &request_id)
+    };
+
+    let (kind, content_type, body) = match read_invoke_body(&mut req).await {
+        Ok(body) => body,
+        Err((status, error, message)) => {
+            return Ok(failure(status, error, message.to_string()));
+        }
+    };
+    let body = match kind {
+        _ if body.is_empty() => Ok(None),
+        BodyKind::Json => serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(|e| e.to_string()),
+        _ => payload::raw_input(kind, content_type.as_deref().unwrap_or_default(), body).map(Some),
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => {
+            return Ok(failure(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid request body: {e}"),
+                "Failed to parse request body".to_string(),
+            ));
+        }
+    };
+    let request = RouteRequest {
+        path_params,
+        query: req.params().clone().into_iter().collect(),
+        headers: req
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect(),
+        body,
+    };
+    let input = match route.input_mapping.compose(request) {
+        Ok(input) => input,
+        Err(e) => {
+            return Ok(failure(
+                StatusCode::BAD_REQUEST,
+                e.to_string(),
+                format!(
+                    "Request does not match the input mapping of route {}",
+                    route.id
+                ),
+            ));
+        }
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let span = tracing::info_span!("invoke", request_id = %request_id, function = %name, route = %route.id);
+    let profile = schedulers.resolve(&name).await;
+    let result = profile
+        .scheduler
+        .schedule_with(
+            &name,
+            InvokeRequest {
+                input,
+                retry_policy: None,
+                priority: None,
+                idempotency_key: None,
+                session_id: None,
+                affinity_key: None,
+            },
+            ScheduleOptions {
+                invocation_id: Some(request_id.clone()),
+                caller: caller_info(&req),
+                ..Default::default()
+            },
+        )
+        .instrument(span)
+        .await;
+
+    let response = match result {
+        Ok(response) => {
+            let http_response = profile
+                .scheduler
+                .registry()
+                .get(&name)
+                .await
+                .is_ok_and(|function| function.http_response);
+            declared_response(&response.output, http_response, &name)
+                .unwrap_or_else(|| Response::json(&response.output))
+        }
+        Err(e) => {
+            let status = invoke_error_status(&e);
+            let response = failure(
+                status,
+                format!("Function execution failed: {e}"),
+                format!("Failed to execute function '{name}'"),
+            );
+            with_retry_after(response, &e)
+        }
+    };
+    Ok(with_request_id(response, &request_id))
+}
+
+/// 自定义 HTTP 路由操作失败时的状态码
+fn http_route_error_status(e: &FluxError) -> StatusCode {
+    match e {
+        FluxError::FunctionNotFound { .. } | FluxError::RouteNotFound { .. } => {
+            StatusCode::NOT_FOUND
+        }
+        FluxError::RouteConflict { .. } => StatusCode::CONFLICT,
+        FluxError::StorageUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        FluxError::MutationRateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+/// 注册自定义 HTTP 路由：按方法和路径模式调用目标函数
+///
+/// 路径模式的第一段不能是内置路由前缀，与已有绑定重叠（同一方法下存在同时匹配两者的路径）时返回 409。
+#[utoipa::path(post, path = "/routes", tag = "routes",
+    request_body = HttpRoute,
+    responses(
+        (status = 201, description = "已注册的绑定", body = HttpRouteResponse),
+        (status = 400, description = "方法、路径模式或输入映射无效，或会遮蔽内置路由", body = ErrorResponse),
+        (status = 404, description = "目标函数不存在", body = ErrorResponse),
+        (status = 409, description = "与已有绑定重叠", body = ErrorResponse)
+    ))]
+pub async fn create_http_route(mut req: Request) -> SilentResult<Response> {
+    let route: HttpRoute = match payload::read_json(&mut req).await {
+        Ok(route) => route,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse route binding".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    match schedulers
+        .add_http_route(route, &routes::reserved_prefixes())
+        .await
+    {
+        Ok(route) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Route {} {} bound to function '{}'",
+                    route.method, route.path_pattern, route.function
+                )),
+                data: Some(route),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::CREATED))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to register route binding".to_string()),
+            };
+            Ok(with_retry_after(
+                api_json(&response, http_route_error_status(&e)),
+                &e,
+            ))
+        }
+    }
+}
+
+/// 列出所有自定义 HTTP 路由（按创建顺序）
+#[utoipa::path(get, path = "/routes", tag = "routes",
+    responses(
+        (status = 200, description = "所有绑定", body = HttpRouteListResponse)
+    ))]
+pub async fn list_http_routes(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let routes = schedulers.http_routes().await.to_vec();
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!("{} route bindings", routes.len())),
+        data: Some(routes),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 删除自定义 HTTP 路由，立即生效
+#[utoipa::path(delete, path = "/routes/{id}", tag = "routes",
+    params(("id" = String, Path, description = "绑定ID")),
+    responses(
+        (status = 200, description = "已删除的绑定", body = HttpRouteResponse),
+        (status = 404, description = "绑定不存在", body = ErrorResponse)
+    ))]
+pub async fn delete_http_route(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let id: String = req.get_path_params("id")?;
+    match schedulers.remove_http_route(&id).await {
+        Ok(route) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!("Route {id} deleted")),
+                data: Some(route),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to delete route binding".to_string()),
+            };
+            Ok(with_retry_after(
+                api_json(&response, http_route_error_status(&e)),
+                &e,
+            ))
+        }
+    }
+}
+
+/// 扇出调用：执行函数后把输出并发传给请求中列出的下游函数
+///
+/// 命名空间路由下，不带命名空间前缀的下游函数名属于该命名空间。
+/// 调用结果中 `success` 为 false 表示主函数或某个 `required` 下游函数失败，HTTP 状态仍为 200。
+#[utoipa::path(post, path = "/invoke/{name}/fanout", tag = "invoke",
+    params(("name" = String, Path, description = "主函数名"), InvokeQuery),
+    request_body = FanoutRequest,
+    responses(
+        (status = 200, description = "主函数和各下游函数的结果", body = FanoutApiResponse),
+        (status = 400, description = "请求体无效、下游列表为空或直接引用主函数", body = ErrorResponse),
+        (status = 404, description = "主函数不存在", body = ErrorResponse),
+        (status = 429, description = "该优先级的等待队列已满", body = ErrorResponse),
+        (status = 503, description = "主函数的熔断已打开，或全局调用名额和等待队列已满", body = ErrorResponse)
+    ))]
+pub async fn invoke_fanout(mut req: Request) -> SilentResult<Response> {
+    let request_id = request_id_from_headers(&req);
+    let mut fanout: FanoutRequest = match payload::read_json(&mut req).await {
+        Ok(fanout) => fanout,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse fan-out request".to_string()),
+            };
+            return Ok(with_request_id(
+                api_json(&response, e.status()),
+                &request_id,
+            ));
+        }
+    };
+    let name = path_function_name(&req)?;
+    if let Ok(namespace) = req.get_path_params::<String>("ns") {
+        for target in &mut fanout.targets {
+            if !target.function.contains(name::NAMESPACE_SEPARATOR) {
+                target.function = name::qualify(&namespace, &target.function);
+            }
+        }
+    }
+    let query = req.params_parse::<InvokeQuery>().unwrap_or_default();
+    let options = ScheduleOptions {
+        on_compiling: query.on_compiling.unwrap_or_default(),
+        invocation_id: Some(request_id.clone()),
+        version: query.version.filter(|version| !version.is_empty()),
+        caller: caller_info(&req),
+        ..Default::default()
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let span = tracing::info_span!("fanout", request_id = %request_id, function = %name);
+    let response = match schedulers
+        .fanout(&name, fanout, options)
+        .instrument(span)
+        .await
+    {
+        Ok(mut result) => {
+            result.primary.request_id = Some(request_id.clone());
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Fan-out of '{name}' to {} targets {}",
+                    result.targets.len(),
+                    if result.success {
+                        "succeeded"
+                    } else {
+                        "failed"
+                    }
+                )),
+                data: Some(result),
+                error: None,
+            };
+            api_json(&response, StatusCode::OK)
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Fan-out failed: {e}")),
+                message: Some(format!("Failed to execute function '{name}'")),
+            };
+            with_retry_after(api_json(&response, invoke_error_status(&e)), &e)
+        }
+    };
+    Ok(with_request_id(response, &request_id))
+}
+
+/// 异步调用函数：立即返回任务，调用在后台执行
+///
+/// 任务 ID 即调用 ID。可通过 `GET /jobs/{id}?wait_ms=` 长轮询结果，或在请求中给出 `callback_url`，
+/// 任务完成后把任务结果 POST 到该地址（函数配置了 webhook 密钥时带签名）。
+#[utoipa::path(post, path = "/invoke/{name}/async", tag = "invoke",
+    params(("name" = String, Path, description = "函数名"), InvokeQuery),
+    request_body = AsyncInvokeRequest,
+    responses(
+        (status = 202, description = "任务已提交", body = JobResponse),
+        (status = 400, description = "请求体无效，或回调地址不是 http/https 地址", body = ErrorResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn invoke_function_async(mut req: Request) -> SilentResult<Response> {
+    let request_id = request_id_from_headers(&req);
+    let error = |status: StatusCode, error: String, message: String| {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(error),
+            message: Some(message),
+        };
+        with_request_id(api_json(&response, status), &request_id)
+    };
+    let mut async_req: AsyncInvokeRequest = match payload::read_json(&mut req).await {
+        Ok(async_req) => async_req,
+        Err(e) => {
+            return Ok(error(
+                e.status(),
+                e.to_string(),
+                "Failed to parse request body".to_string(),
+            ));
+        }
+    };
+    let name = path_function_name(&req)?;
+    let query = req.params_parse::<InvokeQuery>().unwrap_or_default();
+    if query.priority.is_some() {
+        async_req.request.priority = query.priority;
+    }
+    if let Some(key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        async_req.request.idempotency_key = Some(key.to_str().unwrap_or_default().to_string());
+    }
+    if let Some(session_id) = req.headers().get(SESSION_ID_HEADER) {
+        async_req.request.session_id = Some(session_id.to_str().unwrap_or_default().to_string());
+    }
+    if let Some(key) = req.headers().get(AFFINITY_KEY_HEADER) {
+        async_req.request.affinity_key = Some(key.to_str().unwrap_or_default().to_string());
+    }
+    let options = ScheduleOptions {
+        on_compiling: query.on_compiling.unwrap_or_default(),
+        version: query.version.filter(|version| !version.is_empty()),
+        caller: caller_info(&req),
+        ..Default::default()
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let profile = schedulers.resolve(&name).await;
+    if let Err(e) = profile.scheduler.registry().get(&name).await {
+        return Ok(error(
+            StatusCode::NOT_FOUND,
+            e.to_string(),
+            format!("Function '{name}' not found"),
+        ));
+    }
+    let response =
+        match schedulers
+            .jobs()
+            .submit(profile.scheduler.clone(), &name, async_req, options)
+        {
+            Ok(job) => {
                 let response = ApiResponse {
                     success: true,
-                    data: Some(format!(
-                        "Loaded {} functions: {:?}",
-                        loaded_functions.len(),
-                        loaded_functions
-                    )),
+                    message: Some(format!("Job {} submitted for function '{name}'", job.id)),
+                    data: Some(job),
                     error: None,
-                    message: Some(format!(
-                        "Successfully loaded {} functions from directory",
-                        loaded_functions.len()
-                    )),
                 };
-                Ok(Response::json(&response))
+                with_request_id(api_json(&response, StatusCode::ACCEPTED), &request_id)
+            }
+            Err(e) => error(
+                invoke_error_status(&e),
+                e.to_string(),
+                "Invalid async invoke request".to_string(),
+            ),
+        };
+    Ok(response)
+}
+
+/// 查询异步调用任务
+///
+/// `wait_ms` 大于 0 且任务未完成时长轮询，直到任务完成或等待超时（按 `[jobs] max_wait_ms` 截断）；
+/// 同时进行的长轮询已达上限时立即返回当前状态。
+#[utoipa::path(get, path = "/jobs/{id}", tag = "invoke",
+    params(("id" = String, Path, description = "任务 ID"), JobQuery),
+    responses(
+        (status = 200, description = "任务当前状态（`status` 为 running 时尚未完成）", body = JobResponse),
+        (status = 404, description = "任务不存在或已被淘汰", body = ErrorResponse)
+    ))]
+pub async fn get_job(mut req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let query = req.params_parse::<JobQuery>().unwrap_or_default();
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let jobs = schedulers.jobs();
+
+    let mut job = jobs.get(&id);
+    let wait_ms = query.wait_ms.unwrap_or(0).min(jobs.config().max_wait_ms);
+    if job.as_ref().is_some_and(|job| !job.is_finished()) && wait_ms > 0 {
+        if let Some(_permit) = jobs.try_long_poll() {
+            job = jobs.wait(&id, Duration::from_millis(wait_ms)).await;
+        } else {
+            tracing::debug!("Long-poll limit reached, returning job {} immediately", id);
+        }
+    }
+    let Some(job) = job else {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(format!("Job not found: {id}")),
+            message: Some("Job not found".to_string()),
+        };
+        return Ok(api_json(&response, StatusCode::NOT_FOUND));
+    };
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!("Job {} is {:?}", job.id, job.status).to_lowercase()),
+        data: Some(job),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 控制台请求失败的状态码
+fn console_error_status(e: &FluxError) -> StatusCode {
+    match e {
+        FluxError::ConsoleDisabled => StatusCode::FORBIDDEN,
+        FluxError::ConsoleRateLimited { .. } | FluxError::MutationRateLimited { .. } => {
+            StatusCode::TOO_MANY_REQUESTS
+        }
+        FluxError::CodeTooLarge { .. } | FluxError::InputTooLarge { .. } => {
+            StatusCode::PAYLOAD_TOO_LARGE
+        }
+        // 当前构建和运行环境无法执行该脚本类型
+        FluxError::UnsupportedScriptType { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        // 控制台执行 ID 不存在、已过期或属于其他调用方
+        FluxError::ConsoleExecutionNotFound { .. } => StatusCode::NOT_FOUND,
+        FluxError::FunctionAlreadyExists { .. } => StatusCode::CONFLICT,
+        FluxError::StorageUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        FluxError::RegistryFull { .. } => StatusCode::INSUFFICIENT_STORAGE,
+        e => invoke_error_status(e),
+    }
+}
+
+/// 在控制台中执行代码
+///
+/// 代码不经过注册表，按与注册相同的规则识别脚本类型后以最严格的沙箱配置执行一次，返回调用结果、
+/// 进程输出和捕获的日志。执行按调用方限速，不计入任何函数的统计；代码保留一段时间，
+/// 可通过 `POST /console/promote` 注册为函数。
+#[utoipa::path(post, path = "/console/execute", tag = "invoke",
+    request_body = ConsoleExecuteRequest,
+    responses(
+        (status = 200, description = "执行结果（用户代码失败时 `response.status` 为错误）", body = ConsoleExecutionResponse),
+        (status = 400, description = "请求体无效、代码为空、超时超出上限，或脚本类型不是 JavaScript、Python 或表达式", body = ErrorResponse),
+        (status = 403, description = "控制台未启用", body = ErrorResponse),
+        (status = 413, description = "代码或输入超过大小上限", body = ErrorResponse),
+        (status = 422, description = "当前运行环境无法执行该脚本类型", body = ErrorResponse),
+        (status = 429, description = "调用方的执行速率超限（Retry-After 为建议的重试间隔秒数）", body = ErrorResponse),
+        (status = 503, description = "全局调用名额和等待队列已满", body = ErrorResponse)
+    ))]
+pub async fn console_execute(mut req: Request) -> SilentResult<Response> {
+    let request: ConsoleExecuteRequest = match payload::read_json(&mut req).await {
+        Ok(request) => request,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    match schedulers.execute_console(request, caller_info(&req)).await {
+        Ok(execution) => {
+            let origin = execution.response.status.origin();
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Console execution {} finished ({})",
+                    execution.console_id, execution.script_type
+                )),
+                data: Some(execution),
+                error: None,
+            };
+            let mut response = api_json(&response, StatusCode::OK);
+            if let Some(origin) = origin {
+                response.set_header(
+                    HeaderName::from_static(ERROR_ORIGIN_HEADER),
+                    HeaderValue::from_static(origin.as_str()),
+                );
+            }
+            Ok(response)
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Console execution failed: {e}")),
+                message: Some("Failed to execute console code".to_string()),
+            };
+            Ok(with_retry_after(
+                api_json(&response, console_error_status(&e)),
+                &e,
+            ))
+        }
+    }
+}
+
+/// 把控制台执行过的代码注册为函数
+///
+/// 只能提升同一调用方在保留期内执行过的代码；请求体中除 `console_id` 外的字段与注册请求相同。
+#[utoipa::path(post, path = "/console/promote", tag = "invoke",
+    request_body = ConsolePromoteRequest,
+    responses(
+        (status = 200, description = "函数已注册", body = ConsolePromotionResponse),
+        (status = 400, description = "请求体无效，或另外给出了代码", body = ErrorResponse),
+        (status = 404, description = "控制台执行不存在、已过期或属于其他调用方", body = ErrorResponse),
+        (status = 409, description = "同名函数已存在", body = ErrorResponse)
+    ))]
+pub async fn console_promote(mut req: Request) -> SilentResult<Response> {
+    let request: ConsolePromoteRequest = match payload::read_json(&mut req).await {
+        Ok(request) => request,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let console_id = request.console_id.clone();
+    let name = request.function.qualified_name();
+    match schedulers
+        .promote_console(request, &caller_info(&req))
+        .await
+    {
+        Ok(profile) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Console execution {console_id} promoted to function '{name}' (profile '{}')",
+                    profile.name
+                )),
+                data: Some(ConsolePromotion {
+                    console_id,
+                    name,
+                    profile: profile.name.clone(),
+                }),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Promote console execution failed: {e}")),
+                message: Some("Failed to promote console execution".to_string()),
+            };
+            Ok(with_retry_after(
+                api_json(&response, console_error_status(&e)),
+                &e,
+            ))
+        }
+    }
+}
+
+/// 控制台的用量计数
+#[utoipa::path(get, path = "/console/stats", tag = "invoke",
+    responses((status = 200, description = "执行、拒绝和提升次数", body = ConsoleStatsResponse)))]
+pub async fn console_stats(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let response = ApiResponse {
+        success: true,
+        data: Some(schedulers.console().stats()),
+        error: None,
+        message: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 查询会话的累计统计
+#[utoipa::path(get, path = "/sessions/{id}", tag = "invoke",
+    params(("id" = String, Path, description = "会话 ID")),
+    responses(
+        (status = 200, description = "会话的调用数、错误数和总执行时间", body = SessionResponse),
+        (status = 404, description = "会话不存在或已被淘汰", body = ErrorResponse)
+    ))]
+pub async fn get_session(req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let Some(summary) = schedulers.sessions().get(&id) else {
+        return Ok(session_not_found(&id));
+    };
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!(
+            "Session {id} has {} invocations",
+            summary.invocations
+        )),
+        data: Some(summary),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 查询会话的调用列表（最新的在前）
+///
+/// 输入按函数的脱敏规则处理；输入或输出超过 `[sessions] max_payload_bytes` 时只保留大小。
+#[utoipa::path(get, path = "/sessions/{id}/executions", tag = "invoke",
+    params(("id" = String, Path, description = "会话 ID"), SessionExecutionsQuery),
+    responses(
+        (status = 200, description = "会话的调用列表", body = SessionExecutionsResponse),
+        (status = 404, description = "会话不存在或已被淘汰", body = ErrorResponse)
+    ))]
+pub async fn list_session_executions(mut req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let query = req
+        .params_parse::<SessionExecutionsQuery>()
+        .unwrap_or_default();
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let sessions = schedulers.sessions();
+    let limit = query
+        .limit
+        .unwrap_or(50)
+        .min(sessions.config().max_executions_per_session);
+    let Some(page) = sessions.executions(&id, query.offset.unwrap_or(0), limit) else {
+        return Ok(session_not_found(&id));
+    };
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!(
+            "Returned {} executions of session {id}",
+            page.executions.len()
+        )),
+        data: Some(page),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 清除会话及其调用记录
+#[utoipa::path(delete, path = "/sessions/{id}", tag = "invoke",
+    params(("id" = String, Path, description = "会话 ID")),
+    responses(
+        (status = 200, description = "会话已清除", body = MessageResponse),
+        (status = 404, description = "会话不存在或已被淘汰", body = ErrorResponse)
+    ))]
+pub async fn delete_session(req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    if !schedulers.sessions().remove(&id) {
+        return Ok(session_not_found(&id));
+    }
+    let response = ApiResponse::<()> {
+        success: true,
+        data: None,
+        error: None,
+        message: Some(format!("Session {id} cleared")),
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+fn session_not_found(id: &str) -> Response {
+    let response = ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(format!("Session not found: {id}")),
+        message: Some("Session not found".to_string()),
+    };
+    api_json(&response, StatusCode::NOT_FOUND)
+}
+
+/// 用执行历史中记录的输入重新调用函数，返回原执行和重放执行的结果及输出差异
+///
+/// 重放与普通调用一样受命名空间限制和准入队列约束；调用 ID 即原调用的请求 ID。
+#[utoipa::path(post, path = "/executions/{id}/replay", tag = "invoke",
+    params(("id" = String, Path, description = "原调用 ID（即其请求 ID）"), ReplayQuery),
+    responses(
+        (status = 200, description = "原执行、重放执行和输出差异", body = ReplayApiResponse),
+        (status = 404, description = "执行历史中没有该调用，或原版本已不再保留", body = ErrorResponse),
+        (status = 422, description = "原调用的输入被截断或包含脱敏字段，无法重放", body = ErrorResponse),
+        (status = 429, description = "该优先级的等待队列或命名空间并发已满", body = ErrorResponse)
+    ))]
+pub async fn replay_execution(mut req: Request) -> SilentResult<Response> {
+    let request_id = request_id_from_headers(&req);
+    let id: String = req.get_path_params("id")?;
+    let query = req.params_parse::<ReplayQuery>().unwrap_or_default();
+    let options = ScheduleOptions {
+        invocation_id: Some(request_id.clone()),
+        caller: caller_info(&req),
+        shadow: query.shadow.unwrap_or(false),
+        ..Default::default()
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let span = tracing::info_span!("replay", request_id = %request_id, execution = %id);
+    let response = match schedulers
+        .replay(&id, query.original_version.unwrap_or(false), options)
+        .instrument(span)
+        .await
+    {
+        Ok(result) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Replayed execution '{id}' of '{}' as '{}'",
+                    result.original.function, result.replay.invocation_id
+                )),
+                data: Some(result),
+                error: None,
+            };
+            api_json(&response, StatusCode::OK)
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Replay failed: {e}")),
+                message: Some(format!("Failed to replay execution '{id}'")),
+            };
+            with_retry_after(api_json(&response, invoke_error_status(&e)), &e)
+        }
+    };
+    Ok(with_request_id(response, &request_id))
+}
+
+/// 下载调用的调试包（tar.gz）：生成的源代码树、脱敏后的输入、环境变量名、工具链版本和进程输出
+///
+/// 只有 `debug_capture` 为 `on_failure`（调用失败时）或 `always` 的函数保存调试包。
+#[utoipa::path(get, path = "/executions/{id}/bundle", tag = "invoke",
+    params(("id" = String, Path, description = "调用 ID（即其请求 ID）")),
+    responses(
+        (status = 200, description = "调试包", content_type = "application/gzip", body = Vec<u8>),
+        (status = 404, description = "没有该调用的调试包，或已被淘汰", body = ErrorResponse)
+    ))]
+pub async fn get_execution_bundle(req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    match schedulers.debug_artifacts().bundle(&id) {
+        Ok(bundle) => {
+            let mut response = Response::empty();
+            response.set_header(CONTENT_TYPE, HeaderValue::from_static("application/gzip"));
+            if let Ok(disposition) = HeaderValue::from_str(&format!(
+                "attachment; filename=\"{}.tar.gz\"",
+                id.replace(':', "_")
+            )) {
+                response.set_header(CONTENT_DISPOSITION, disposition);
+            }
+            Ok(response.with_body(bundle.into()))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some(format!("No debug bundle for execution '{id}'")),
+            };
+            Ok(api_json(&response, invoke_error_status(&e)))
+        }
+    }
+}
+
+/// 列出或下载调用保留的暂存文件
+///
+/// 只有设置了 `persist_scratch` 的函数在调用结束后保留暂存目录（`context.scratch_dir`），
+/// 保留时长为 `[sandbox] scratch_ttl_secs`。指定 `path` 时下载该文件，内容类型按文件开头的字节嗅探。
+#[utoipa::path(get, path = "/executions/{id}/artifacts", tag = "invoke",
+    params(("id" = String, Path, description = "调用 ID（即其请求 ID）"), ArtifactQuery),
+    responses(
+        (status = 200, description = "暂存文件列表；指定 path 时为文件内容", body = ScratchArtifactsResponse),
+        (status = 404, description = "没有该调用保留的暂存文件或指定的文件，或已过期", body = ErrorResponse),
+        (status = 413, description = "文件超出下载上限", body = ErrorResponse)
+    ))]
+pub async fn get_execution_artifacts(mut req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let query = req.params_parse::<ArtifactQuery>().unwrap_or_default();
+    let store: &Arc<ScratchStore> = req.get_config()?;
+    let result = match &query.path {
+        Some(path) => store.read(&id, path).map(|(content, content_type)| {
+            let mut response = Response::empty();
+            if let Ok(content_type) = HeaderValue::from_str(&content_type) {
+                response.set_header(CONTENT_TYPE, content_type);
+            }
+            // 函数写出的内容按附件下载，浏览器不另行猜测类型
+            response.set_header(
+                HeaderName::from_static("x-content-type-options"),
+                HeaderValue::from_static("nosniff"),
+            );
+            let name = path.rsplit('/').next().unwrap_or(path).replace('"', "_");
+            if let Ok(disposition) =
+                HeaderValue::from_str(&format!("attachment; filename=\"{name}\""))
+            {
+                response.set_header(CONTENT_DISPOSITION, disposition);
+            }
+            response.with_body(content.into())
+        }),
+        None => store.list(&id).map(|artifacts| {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Execution '{id}' left {} scratch files",
+                    artifacts.files.len()
+                )),
+                data: Some(artifacts),
+                error: None,
+            };
+            api_json(&response, StatusCode::OK)
+        }),
+    };
+    Ok(result.unwrap_or_else(|e| {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(e.to_string()),
+            message: Some(format!("No scratch artifacts for execution '{id}'")),
+        };
+        api_json(&response, invoke_error_status(&e))
+    }))
+}
+
+/// 以 JSON 行返回调用捕获的日志
+///
+/// 只有带 `capture_log` 的调用捕获日志。每行为一个条目（级别、target、消息、字段、距开始捕获的
+/// 微秒数），按记录顺序排列，消息和字段按函数的 `log_redaction` 脱敏；因上限或内存预算停止捕获时
+/// 最后一行为截断标记（target 为 `flux::invocation_log`）。
+#[utoipa::path(get, path = "/executions/{id}/log", tag = "invoke",
+    params(("id" = String, Path, description = "调用 ID（即其请求 ID）")),
+    responses(
+        (status = 200, description = "日志条目（每行一个 JSON 对象）", content_type = "application/x-ndjson", body = Vec<LogEntry>),
+        (status = 404, description = "调用没有捕获日志，或已被淘汰", body = ErrorResponse)
+    ))]
+pub async fn get_execution_log(req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    match schedulers.invocation_logs().get(&id) {
+        Ok(log) => {
+            let mut response = Response::empty();
+            response.set_header(
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/x-ndjson"),
+            );
+            Ok(response.with_body(log.to_json_lines().into()))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some(format!("No captured log for execution '{id}'")),
+            };
+            Ok(api_json(&response, invoke_error_status(&e)))
+        }
+    }
+}
+
+/// 查看调用的调试包清单（不下载调试包）
+#[utoipa::path(get, path = "/executions/{id}/bundle/manifest", tag = "invoke",
+    params(("id" = String, Path, description = "调用 ID（即其请求 ID）")),
+    responses(
+        (status = 200, description = "调试包清单", body = DebugBundleManifestResponse),
+        (status = 404, description = "没有该调用的调试包，或已被淘汰", body = ErrorResponse)
+    ))]
+pub async fn get_execution_bundle_manifest(req: Request) -> SilentResult<Response> {
+    let id: String = req.get_path_params("id")?;
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    match schedulers.debug_artifacts().manifest(&id) {
+        Ok(manifest) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Debug bundle of execution '{id}' ({} files)",
+                    manifest.files.len()
+                )),
+                data: Some(manifest),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some(format!("No debug bundle for execution '{id}'")),
+            };
+            Ok(api_json(&response, invoke_error_status(&e)))
+        }
+    }
+}
+
+/// 按函数声明的内容类型返回原始响应体
+fn raw_response(raw: RawOutput) -> Response {
+    let mut response = Response::empty();
+    if let Ok(content_type) = HeaderValue::from_str(&raw.content_type) {
+        response.set_header(CONTENT_TYPE, content_type);
+    }
+    response.with_body(raw.body.into())
+}
+
+/// 读取请求中的 X-Request-Id，缺失或非法时生成新的 ID
+pub(crate) fn request_id_from_headers(req: &Request) -> String {
+    req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 128
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+        })
+        .map(str::to_string)
+        .unwrap_or_else(scru128::new_string)
+}
+
+/// 调用方信息：IP 取自 `x-real-ip`（由服务器按连接地址填入，反向代理可以覆盖）
+fn caller_info(req: &Request) -> CallerInfo {
+    let ip = req
+        .headers()
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| match value.parse::<std::net::SocketAddr>() {
+            Ok(addr) => addr.ip().to_string(),
+            Err(_) => value.trim().to_string(),
+        })
+        .filter(|ip| !ip.is_empty());
+    CallerInfo {
+        api_key_id: None,
+        ip,
+    }
+}
+
+/// 解析 `If-Match` 请求头中的期望修订号（`"3"`、`W/"3"` 或 `3`），缺省或 `*` 时不检查
+fn expected_revision(req: &Request) -> Result<Option<u64>, Box<Response>> {
+    let Some(value) = req.headers().get(IF_MATCH) else {
+        return Ok(None);
+    };
+    let value = value.to_str().unwrap_or_default().trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    match value.trim_start_matches("W/").trim_matches('"').parse() {
+        Ok(revision) => Ok(Some(revision)),
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!(
+                    "Invalid If-Match header '{value}': expected a function revision"
+                )),
+                message: Some("Invalid If-Match header".to_string()),
+            };
+            Err(Box::new(api_json(&response, StatusCode::BAD_REQUEST)))
+        }
+    }
+}
+
+/// 路径中的函数名；`/namespaces/<ns>/...` 路由下返回该命名空间中的限定名
+fn path_function_name(req: &Request) -> SilentResult<String> {
+    let name: String = req.get_path_params("name")?;
+    Ok(match req.get_path_params::<String>("ns") {
+        Ok(namespace) => name::qualify(&namespace, &name),
+        Err(_) => name,
+    })
+}
+
+/// 以 `ETag` 响应头返回函数的修订号
+fn with_etag(response: Response, revision: u64) -> Response {
+    match HeaderValue::from_str(&format!("\"{revision}\"")) {
+        Ok(value) => response.with_header(ETAG, value),
+        Err(_) => response,
+    }
+}
+
+/// 在响应头中附加请求 ID
+fn with_request_id(response: Response, request_id: &str) -> Response {
+    match HeaderValue::from_str(request_id) {
+        Ok(value) => response.with_header(HeaderName::from_static(REQUEST_ID_HEADER), value),
+        Err(_) => response,
+    }
+}
+
+/// 从文件加载函数
+#[utoipa::path(post, path = "/load/file", tag = "load",
+    request_body = LoadFileRequest,
+    responses(
+        (status = 200, description = "加载结果", body = JsonResponse),
+        (status = 400, description = "加载失败", body = ErrorResponse)
+    ))]
+pub async fn load_function_from_file(mut req: Request) -> SilentResult<Response> {
+    // 先解析请求体
+    let load_req: LoadFileRequest = match req.json_parse().await {
+        Ok(req) => req,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    // 从配置中获取调度器注册表
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let scheduler = match schedulers.get(load_req.profile.as_deref().unwrap_or(DEFAULT_PROFILE)) {
+        Ok(profile) => &profile.scheduler,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Function loading failed".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    // 使用 FunctionLoader 从文件加载函数
+    match scheduler
+        .loader()
+        .load_function_from_file(
+            &load_req.file_path,
+            load_req.name,
+            load_req.description,
+            load_req.timeout_ms,
+        )
+        .await
+    {
+        Ok(function_metadata) => {
+            let function_name = function_metadata.name.clone();
+            // 将函数注册到目标调度器（函数名不能已属于其他调度器）
+            let registered = match schedulers
+                .target_for(&function_name, load_req.profile.as_deref())
+                .await
+            {
+                Ok(target) => {
+                    target
+                        .scheduler
+                        .register_function(function_metadata.clone())
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match registered {
+                Ok(_) => {
+                    let response = ApiResponse {
+                        success: true,
+                        data: Some(function_metadata),
+                        error: None,
+                        message: Some(format!(
+                            "Function '{function_name}' loaded successfully from file"
+                        )),
+                    };
+                    Ok(api_json(&response, StatusCode::OK))
+                }
+                Err(e) => {
+                    let response = ApiResponse::<()> {
+                        success: false,
+                        data: None,
+                        error: Some(format!("Failed to register function: {e}")),
+                        message: Some("Function loading failed during registration".to_string()),
+                    };
+                    Ok(api_json(&response, StatusCode::INTERNAL_SERVER_ERROR))
+                }
+            }
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to load function from file: {e}")),
+                message: Some("Function loading failed".to_string()),
+            };
+            Ok(api_json(&response, StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+/// 从目录加载函数，返回每个文件的处理结果
+#[utoipa::path(post, path = "/load/directory", tag = "load",
+    params(LoadDirectoryQuery),
+    request_body = LoadDirectoryRequest,
+    responses(
+        (status = 200, description = "所有文件均已处理（或预览）", body = DirectoryLoadResponse),
+        (status = 206, description = "部分文件加载失败", body = DirectoryLoadResponse),
+        (status = 400, description = "请求无效", body = ErrorResponse),
+        (status = 409, description = "on_conflict=fail 时存在同名函数，未注册任何函数", body = DirectoryLoadResponse)
+    ))]
+pub async fn load_functions_from_directory(mut req: Request) -> SilentResult<Response> {
+    let query: LoadDirectoryQuery = match req.params_parse() {
+        Ok(query) => query,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid query: {e}")),
+                message: Some(
+                    "dry_run must be a boolean and on_conflict one of skip, overwrite, fail"
+                        .to_string(),
+                ),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    // 先解析请求体
+    let load_req: LoadDirectoryRequest = match req.json_parse().await {
+        Ok(req) => req,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    // 从配置中获取目标调度器
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let scheduler = match schedulers.get(query.profile.as_deref().unwrap_or(DEFAULT_PROFILE)) {
+        Ok(profile) => &profile.scheduler,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Directory loading failed".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    match scheduler
+        .load_directory(
+            &load_req.directory_path,
+            query.on_conflict.unwrap_or(ConflictStrategy::Skip),
+            query.dry_run.unwrap_or(false),
+        )
+        .await
+    {
+        Ok(result) => {
+            let (status, message, error) = directory_load_outcome(&result);
+            let response = ApiResponse {
+                success: status == StatusCode::OK,
+                error,
+                message: Some(message),
+                data: Some(result),
+            };
+            Ok(api_json(&response, status))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to load functions from directory: {e}")),
+                message: Some("Directory loading failed".to_string()),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 目录加载结果对应的状态码、提示和错误信息
+fn directory_load_outcome(result: &DirectoryLoadResult) -> (StatusCode, String, Option<String>) {
+    let summary = &result.summary;
+    let status = if result.aborted {
+        StatusCode::CONFLICT
+    } else if summary.failed > 0 || summary.rejected > 0 {
+        StatusCode::PARTIAL_CONTENT
+    } else {
+        StatusCode::OK
+    };
+    let message = if result.aborted {
+        "Name conflicts with on_conflict=fail - no functions were registered".to_string()
+    } else {
+        format!(
+            "{} {} registered, {} updated, {} duplicates skipped, {} skipped, {} failed, {} rejected",
+            if result.dry_run {
+                "Dry run:"
+            } else {
+                "Loaded:"
+            },
+            summary.registered,
+            summary.updated,
+            summary.skipped_duplicate,
+            summary.skipped,
+            summary.failed,
+            summary.rejected
+        )
+    };
+    let error = (status != StatusCode::OK).then(|| {
+        format!(
+            "{} files failed to load, {} rejected by registry limits",
+            summary.failed, summary.rejected
+        )
+    });
+    (status, message, error)
+}
+
+/// 从 OCI 制品加载函数：拉取并校验函数层后按目录加载的规则注册，返回每个函数的处理结果
+#[utoipa::path(post, path = "/load/oci", tag = "load",
+    params(LoadDirectoryQuery),
+    request_body = OciLoadRequest,
+    responses(
+        (status = 200, description = "所有函数均已处理（或预览）", body = OciLoadResponse),
+        (status = 206, description = "部分函数加载失败", body = OciLoadResponse),
+        (status = 400, description = "请求或制品无效", body = ErrorResponse),
+        (status = 404, description = "制品不存在", body = ErrorResponse),
+        (status = 409, description = "on_conflict=fail 时存在同名函数，未注册任何函数", body = OciLoadResponse),
+        (status = 502, description = "拉取失败或摘要不符", body = ErrorResponse)
+    ))]
+pub async fn load_functions_from_oci(mut req: Request) -> SilentResult<Response> {
+    let query: LoadDirectoryQuery = match req.params_parse() {
+        Ok(query) => query,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid query: {e}")),
+                message: Some(
+                    "dry_run must be a boolean and on_conflict one of skip, overwrite, fail"
+                        .to_string(),
+                ),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    // 直接解析请求体，避免凭据经过序列化
+    let load_req: OciLoadRequest = match payload::read_json(&mut req).await {
+        Ok(req) => req,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let scheduler = match schedulers.get(query.profile.as_deref().unwrap_or(DEFAULT_PROFILE)) {
+        Ok(profile) => &profile.scheduler,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("OCI loading failed".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    match scheduler
+        .load_from_oci(
+            schedulers.oci_source(),
+            &load_req,
+            query.on_conflict.unwrap_or(ConflictStrategy::Skip),
+            query.dry_run.unwrap_or(false),
+        )
+        .await
+    {
+        Ok(result) => {
+            let (status, message, error) = directory_load_outcome(&result.load);
+            let response = ApiResponse {
+                success: status == StatusCode::OK,
+                error,
+                message: Some(format!("{message} ({})", result.digest)),
+                data: Some(result),
+            };
+            Ok(api_json(&response, status))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+                FluxError::OciArtifactNotFound { .. } => StatusCode::NOT_FOUND,
+                FluxError::OciDigestMismatch { .. } | FluxError::OciPullFailed { .. } => {
+                    StatusCode::BAD_GATEWAY
+                }
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to load functions from OCI artifact: {e}")),
+                message: Some("OCI loading failed".to_string()),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 从 Git 仓库加载函数
+#[utoipa::path(post, path = "/load/git", tag = "load",
+    request_body = GitLoadRequest,
+    responses(
+        (status = 200, description = "同步报告", body = JsonResponse),
+        (status = 400, description = "同步失败", body = ErrorResponse)
+    ))]
+pub async fn load_functions_from_git(mut req: Request) -> SilentResult<Response> {
+    // 先解析请求体
+    let load_req: GitLoadRequest = match req.json_parse().await {
+        Ok(req) => req,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    // Git 同步的函数注册到默认调度器
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let scheduler = &schedulers.default_profile().scheduler;
+
+    match scheduler.load_from_git(&load_req).await {
+        Ok(report) => {
+            let complete = report.failed.is_empty() && report.rejected.is_empty();
+            let status = if complete {
+                StatusCode::OK
+            } else {
+                StatusCode::PARTIAL_CONTENT
+            };
+            let message = format!(
+                "Synced repository at {}: {} added, {} updated, {} removed",
+                report.commit,
+                report.added.len(),
+                report.updated.len(),
+                report.removed.len()
+            );
+            let response = ApiResponse {
+                success: complete,
+                error: (!complete).then(|| {
+                    format!(
+                        "Failed to load {} functions, {} rejected by registry limits",
+                        report.failed.len(),
+                        report.rejected.len()
+                    )
+                }),
+                data: Some(report),
+                message: Some(message),
+            };
+            Ok(api_json(&response, status))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to load functions from git: {e}")),
+                message: Some("Git loading failed".to_string()),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 函数是否属于查询的命名空间（未指定命名空间时总是成立）
+fn in_namespace(function_name: &str, namespace: Option<&str>) -> bool {
+    namespace.is_none_or(|namespace| name::split_qualified(function_name).0 == namespace)
+}
+
+/// 命名空间请求失败时的响应
+fn namespace_error_response(e: FluxError, message: &str) -> Response {
+    let status = match e {
+        FluxError::NamespaceNotFound { .. } => StatusCode::NOT_FOUND,
+        FluxError::NamespaceAlreadyExists { .. } | FluxError::NamespaceNotEmpty { .. } => {
+            StatusCode::CONFLICT
+        }
+        _ => StatusCode::BAD_REQUEST,
+    };
+    let response = ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(e.to_string()),
+        message: Some(message.to_string()),
+    };
+    api_json(&response, status)
+}
+
+/// 列出所有命名空间
+#[utoipa::path(get, path = "/namespaces", tag = "namespaces",
+    responses((status = 200, description = "命名空间列表", body = NamespaceListResponse)))]
+pub async fn list_namespaces(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let namespaces = schedulers.namespaces().list();
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!("Retrieved {} namespaces", namespaces.len())),
+        data: Some(namespaces),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 创建命名空间及其默认限制
+#[utoipa::path(post, path = "/namespaces", tag = "namespaces",
+    request_body = CreateNamespaceRequest,
+    responses(
+        (status = 200, description = "创建的命名空间", body = NamespaceResponse),
+        (status = 400, description = "命名空间名无效", body = ErrorResponse),
+        (status = 409, description = "命名空间已存在", body = ErrorResponse)
+    ))]
+pub async fn create_namespace(mut req: Request) -> SilentResult<Response> {
+    let create_req: CreateNamespaceRequest = match req.json_parse().await {
+        Ok(create_req) => create_req,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Invalid request body: {e}")),
+                message: Some("Failed to parse request body".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    match schedulers
+        .namespaces()
+        .create(&create_req.name, create_req.config)
+    {
+        Ok(namespace) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!("Namespace '{}' created", namespace.name)),
+                data: Some(namespace),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(namespace_error_response(e, "Failed to create namespace")),
+    }
+}
+
+/// 获取命名空间的默认限制及当前用量
+#[utoipa::path(get, path = "/namespaces/{ns}", tag = "namespaces",
+    params(("ns" = String, Path, description = "命名空间")),
+    responses(
+        (status = 200, description = "命名空间详情", body = NamespaceDetailsResponse),
+        (status = 404, description = "命名空间不存在", body = ErrorResponse)
+    ))]
+pub async fn get_namespace(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let namespace: String = req.get_path_params("ns")?;
+
+    match schedulers.namespaces().get(&namespace) {
+        Ok(details) => {
+            let response = ApiResponse {
+                success: true,
+                data: Some(NamespaceDetails {
+                    namespace: details,
+                    functions: schedulers.namespace_functions(&namespace).await,
+                }),
+                error: None,
+                message: Some(format!("Namespace '{namespace}' details retrieved")),
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(namespace_error_response(e, "Namespace not found")),
+    }
+}
+
+/// 删除命名空间；命名空间非空时需要 `?force=true`，此时一并删除其中的所有函数
+#[utoipa::path(delete, path = "/namespaces/{ns}", tag = "namespaces",
+    params(("ns" = String, Path, description = "命名空间"), DeleteNamespaceQuery),
+    responses(
+        (status = 200, description = "被删除的函数名", body = NameListResponse),
+        (status = 400, description = "默认命名空间不能删除", body = ErrorResponse),
+        (status = 404, description = "命名空间不存在", body = ErrorResponse),
+        (status = 409, description = "命名空间非空且未指定 force", body = ErrorResponse)
+    ))]
+pub async fn delete_namespace(mut req: Request) -> SilentResult<Response> {
+    let query: DeleteNamespaceQuery = req.params_parse().unwrap_or_default();
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let namespace: String = req.get_path_params("ns")?;
+
+    match schedulers
+        .delete_namespace(&namespace, query.force.unwrap_or(false))
+        .await
+    {
+        Ok(deleted) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Namespace '{namespace}' deleted with {} functions",
+                    deleted.len()
+                )),
+                data: Some(deleted),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(namespace_error_response(
+            e,
+            &format!("Failed to delete namespace '{namespace}'"),
+        )),
+    }
+}
+
+/// 资源配额请求失败时的响应
+fn quota_error_response(e: FluxError, message: &str) -> Response {
+    let status = match e {
+        FluxError::QuotaNotFound { .. } => StatusCode::NOT_FOUND,
+        FluxError::QuotaAlreadyExists { .. } | FluxError::QuotaInUse { .. } => StatusCode::CONFLICT,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    let response = ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(e.to_string()),
+        message: Some(message.to_string()),
+    };
+    api_json(&response, status)
+}
+
+/// 读取配额定义请求体，解析失败时返回错误响应
+async fn read_quota(req: &mut Request) -> Result<ResourceQuota, Response> {
+    payload::read_json(req).await.map_err(|e| {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(e.to_string()),
+            message: Some("Failed to parse quota definition".to_string()),
+        };
+        api_json(&response, e.status())
+    })
+}
+
+/// 列出所有资源配额
+pub async fn list_quotas(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let quotas = schedulers.resources().list_quotas().await;
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!("Retrieved {} quotas", quotas.len())),
+        data: Some(quotas),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 创建资源配额（软限制须小于硬限制，缺省字段取默认配额的值）
+pub async fn create_quota(mut req: Request) -> SilentResult<Response> {
+    let quota = match read_quota(&mut req).await {
+        Ok(quota) => quota,
+        Err(response) => return Ok(response),
+    };
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    match schedulers.resources().create_quota(quota).await {
+        Ok(quota) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!("Quota '{}' created", quota.name)),
+                data: Some(quota),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(quota_error_response(e, "Failed to create quota")),
+    }
+}
+
+/// 获取资源配额的定义及选择它的函数
+pub async fn get_quota(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let name: String = req.get_path_params("name")?;
+
+    match schedulers.resources().get_quota(&name).await {
+        Some(quota) => {
+            let response = ApiResponse {
+                success: true,
+                data: Some(QuotaDetails {
+                    quota,
+                    functions: schedulers.quota_functions(&name).await,
+                }),
+                error: None,
+                message: Some(format!("Quota '{name}' retrieved")),
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        None => Ok(quota_error_response(
+            FluxError::QuotaNotFound { name },
+            "Quota not found",
+        )),
+    }
+}
+
+/// 替换资源配额的定义，选择它的函数之后的执行立即按新定义检查
+pub async fn update_quota(mut req: Request) -> SilentResult<Response> {
+    let name: String = req.get_path_params("name")?;
+    let mut quota = match read_quota(&mut req).await {
+        Ok(quota) => quota,
+        Err(response) => return Ok(response),
+    };
+    quota.name = name.clone();
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    match schedulers.resources().update_quota(quota).await {
+        Ok(quota) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!("Quota '{name}' updated")),
+                data: Some(quota),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(quota_error_response(
+            e,
+            &format!("Failed to update quota '{name}'"),
+        )),
+    }
+}
+
+/// 删除资源配额；仍有函数选择它时返回 409，`default` 配额不能删除
+pub async fn delete_quota(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let name: String = req.get_path_params("name")?;
+
+    match schedulers.delete_quota(&name).await {
+        Ok(quota) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!("Quota '{name}' deleted")),
+                data: Some(quota),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(quota_error_response(
+            e,
+            &format!("Failed to delete quota '{name}'"),
+        )),
+    }
+}
+
+/// 最近在该配额下运行的执行的资源用量汇总，用于判断限制是否合适
+pub async fn get_quota_usage(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let name: String = req.get_path_params("name")?;
+
+    match schedulers.resources().quota_usage(&name).await {
+        Ok(usage) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Usage of quota '{name}' over {} executions",
+                    usage.executions
+                )),
+                data: Some(usage),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(quota_error_response(e, "Quota not found")),
+    }
+}
+
+/// 状态操作失败时的响应
+fn state_error_response(e: FluxError, message: &str) -> Response {
+    let status = match e {
+        FluxError::StateVersionConflict { .. } => StatusCode::CONFLICT,
+        FluxError::StateLimitExceeded { .. } | FluxError::StateReadOnly { .. } => {
+            StatusCode::FORBIDDEN
+        }
+        _ => StatusCode::BAD_REQUEST,
+    };
+    let response = ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(e.to_string()),
+        message: Some(message.to_string()),
+    };
+    api_json(&response, status)
+}
+
+/// 获取函数的全部键值状态
+pub async fn get_function_state(req: Request) -> SilentResult<Response> {
+    function_state(req, false).await
+}
+
+/// 清空函数的键值状态
+pub async fn clear_function_state(req: Request) -> SilentResult<Response> {
+    function_state(req, true).await
+}
+
+async fn function_state(req: Request, clear: bool) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    if let Err(e) = scheduler.registry().get(&name).await {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(format!("Function not found: {e}")),
+            message: Some(format!("Function '{name}' not found")),
+        };
+        return Ok(api_json(&response, StatusCode::NOT_FOUND));
+    }
+
+    let state = scheduler.state().snapshot(&name);
+    let message = if clear {
+        let removed = scheduler.state().clear(&name);
+        format!("Cleared {removed} state keys of function '{name}'")
+    } else {
+        format!("Function '{name}' has {} state keys", state.keys)
+    };
+    let response = ApiResponse {
+        success: true,
+        data: Some(state),
+        error: None,
+        message: Some(message),
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 执行中的函数读写自己的状态（`x-flux-state-token` 为调用上下文中的 `state.token`）
+pub async fn access_state(mut req: Request) -> SilentResult<Response> {
+    let token = req
+        .headers()
+        .get(STATE_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let Some(handle) = token.and_then(|token| schedulers.state().resolve(&token)) else {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some("Missing or expired state token".to_string()),
+            message: Some("State is only available to executing functions".to_string()),
+        };
+        return Ok(api_json(&response, StatusCode::UNAUTHORIZED));
+    };
+
+    let operation: StateOperation = match payload::read_json(&mut req).await {
+        Ok(operation) => operation,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse state operation".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
+    match handle.apply(operation) {
+        Ok(entry) => {
+            let response = ApiResponse {
+                success: true,
+                data: entry,
+                error: None,
+                message: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(state_error_response(e, "State operation failed")),
+    }
+}
+
+/// 执行中的函数经宿主发送出站 HTTP 请求（`x-flux-egress-token` 为调用上下文中的 `egress.token`）
+pub async fn broker_http_fetch(mut req: Request) -> SilentResult<Response> {
+    let token = req
+        .headers()
+        .get(EGRESS_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let Some(handle) = token.and_then(|token| schedulers.egress().resolve(&token)) else {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some("Missing or expired egress token".to_string()),
+            message: Some("HTTP fetch is only available to executing functions".to_string()),
+        };
+        return Ok(api_json(&response, StatusCode::UNAUTHORIZED));
+    };
+
+    let request: FetchRequest = match payload::read_json(&mut req).await {
+        Ok(request) => request,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse fetch request".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
+    match handle.fetch(request).await {
+        Ok(fetched) => {
+            let response = ApiResponse {
+                success: true,
+                data: Some(fetched),
+                error: None,
+                message: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let status = match e {
+                FluxError::EgressDenied { .. } => StatusCode::FORBIDDEN,
+                FluxError::EgressLimitExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+                FluxError::EgressFailed { .. } => StatusCode::BAD_GATEWAY,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("HTTP fetch failed".to_string()),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 按 `?profile=` 选择调度器（默认 `default`），未知配置名返回 400 响应
+fn profile_runtime(req: &mut Request) -> SilentResult<Result<Arc<SimpleRuntime>, Response>> {
+    let query: ProfileQuery = req.params_parse().unwrap_or_default();
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    Ok(
+        match schedulers.get(query.profile.as_deref().unwrap_or(DEFAULT_PROFILE)) {
+            Ok(profile) => Ok(profile.scheduler.runtime().clone()),
+            Err(e) => {
+                let response = ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    error: Some(e.to_string()),
+                    message: Some("Unknown scheduler profile".to_string()),
+                };
+                Err(api_json(&response, StatusCode::BAD_REQUEST))
+            }
+        },
+    )
+}
+
+/// 运行时的缓存统计，指定命名空间时附带该命名空间的缓存用量
+async fn cache_stats_json(runtime: &SimpleRuntime, namespace: Option<&str>) -> serde_json::Value {
+    let cache_stats = runtime.cache().stats().await;
+    let hit_rate = runtime.cache().hit_rate().await;
+    let policy = runtime.cache().policy().await;
+
+    let mut stats = serde_json::json!({
+        "hits": cache_stats.hits,
+        "misses": cache_stats.misses,
+        "hit_rate": format!("{:.2}%", hit_rate * 100.0),
+        "size": cache_stats.size,
+        "memory_usage_bytes": cache_stats.memory_usage,
+        "memory_usage_mb": cache_stats.memory_usage as f64 / (1024.0 * 1024.0),
+        "max_memory_bytes": cache_stats.max_memory,
+        "max_memory_mb": cache_stats.max_memory as f64 / (1024.0 * 1024.0),
+        "evictions": cache_stats.evictions,
+        "expirations": cache_stats.expirations,
+        "strategy": policy.strategy,
+        "ttl_secs": policy.ttl_secs,
+        "pinned_functions": cache_stats.pinned_functions,
+        "pinned_entries": cache_stats.pinned_entries,
+        "evictable_entries": cache_stats.evictable_entries,
+        "last_warmup_ms": cache_stats.last_warmup_ms,
+        "spillover": {
+            "enabled": cache_stats.spillover_enabled,
+            "entries": cache_stats.spilled_entries,
+            "disk_usage_bytes": cache_stats.disk_usage,
+            "hits": cache_stats.spillover_hits,
+            "writes": cache_stats.spillover_writes,
+            "corrupted": cache_stats.spillover_corrupted,
+        },
+    });
+    let largest: Vec<_> = cache_stats
+        .largest_entries
+        .into_iter()
+        .filter(|entry| {
+            let function = entry
+                .key
+                .rsplit_once('@')
+                .map_or(entry.key.as_str(), |(name, _)| name);
+            in_namespace(function, namespace)
+        })
+        .collect();
+    let pinned: Vec<_> = runtime
+        .cache()
+        .pinned_functions()
+        .into_iter()
+        .filter(|function| in_namespace(function, namespace))
+        .collect();
+    let entries: Vec<_> = runtime
+        .cache()
+        .entries()
+        .await
+        .into_iter()
+        .filter(|entry| in_namespace(&entry.function, namespace))
+        .collect();
+    stats["pinned"] = serde_json::json!(pinned);
+    stats["largest_entries"] = serde_json::to_value(largest).unwrap_or_default();
+    stats["entries"] = serde_json::to_value(entries).unwrap_or_default();
+    if let Some(namespace) = namespace {
+        let (entries, memory_usage) = runtime.cache().namespace_usage(namespace).await;
+        stats["namespace"] = serde_json::json!({
+            "name": namespace,
+            "size": entries,
+            "memory_usage_bytes": memory_usage,
+        });
+    }
+    stats
+}
+
+/// 运行时的性能统计，指定命名空间时函数维度的统计只包含该命名空间的函数
+async fn performance_stats_json(
+    runtime: &SimpleRuntime,
+    namespace: Option<&str>,
+    window: StatsWindow,
+) -> serde_json::Value {
+    let performance_report = runtime.monitor().generate_report().await;
+    let global_stats = performance_report.global_stats;
+    let now = unix_now();
+    let window_functions: BTreeMap<_, _> = runtime
+        .monitor()
+        .window_stats(window)
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let lifetime_functions: BTreeMap<_, _> = runtime
+        .monitor()
+        .lifetime_stats()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let hottest_functions: Vec<_> = runtime
+        .monitor()
+        .get_hottest_functions(usize::MAX)
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .take(5)
+        .collect();
+    let slowest_functions: Vec<_> = runtime
+        .monitor()
+        .get_slowest_functions(usize::MAX)
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .take(5)
+        .collect();
+    let cold_start_stats: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .cold_start_stats()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+
+    let output_schema_violations: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .output_schema_violations()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let return_type_mismatches: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .return_type_mismatches()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let deprecated_calls: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .all_deprecated_calls()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let chaos_injected: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .chaos_injections()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let shadow_replays: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .shadow_replays()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let mirror_executions: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .mirror_executions()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let canary_executions: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .canary_executions()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let test_executions: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .test_executions()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let inline_executions: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .inline_executions()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    let failures_by_origin: std::collections::BTreeMap<_, _> = runtime
+        .monitor()
+        .failures_by_origin()
+        .await
+        .into_iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .collect();
+    // 后端统计不区分命名空间
+    let backends = runtime.monitor().backend_stats().await;
+
+    let function_stats: Vec<_> = performance_report
+        .function_stats
+        .iter()
+        .filter(|(function, _)| in_namespace(function, namespace))
+        .map(|(_, stats)| stats)
+        .collect();
+    let namespace_stats = namespace.map(|namespace| {
+        serde_json::json!({
+            "name": namespace,
+            "total_requests": function_stats.iter().map(|s| s.total_calls).sum::<u64>(),
+            "total_success": function_stats.iter().map(|s| s.successful_calls).sum::<u64>(),
+            "total_failures": function_stats.iter().map(|s| s.failed_calls).sum::<u64>(),
+        })
+    });
+
+    serde_json::json!({
+        "global_stats": {
+            "total_requests": global_stats.total_requests,
+            "total_success": global_stats.total_success,
+            "total_failures": global_stats.total_failures,
+            "success_rate": if global_stats.total_requests > 0 {
+                global_stats.total_success as f64 / global_stats.total_requests as f64 * 100.0
+            } else { 0.0 },
+            "active_functions": global_stats.active_functions,
+            "current_system_memory_bytes": global_stats.current_system_memory,
+            "peak_system_memory_bytes": global_stats.peak_system_memory,
+            "chaos_injected": global_stats.chaos_injected,
+            "shadow_replays": global_stats.shadow_replays,
+            "mirror_executions": global_stats.mirror_executions,
+            "canary_executions": global_stats.canary_executions,
+            "test_executions": global_stats.test_executions,
+            "inline_executions": global_stats.inline_executions,
+            "failures_by_origin": global_stats.failures_by_origin,
+            "uptime_seconds": global_stats.start_time.map(|start| start.elapsed().as_secs()).unwrap_or(0)
+        },
+        "window": {
+            "global": global_stats.windows.window(window, now),
+            "functions": window_functions,
+        },
+        "lifetime": {
+            "global": global_stats.windows.lifetime(now),
+            "functions": lifetime_functions,
+        },
+        "hottest_functions": hottest_functions,
+        "slowest_functions": slowest_functions,
+        "cold_start_stats": cold_start_stats,
+        "output_schema_violations": output_schema_violations,
+        "return_type_mismatches": return_type_mismatches,
+        "deprecated_calls": deprecated_calls,
+        "chaos_injected": chaos_injected,
+        "shadow_replays": shadow_replays,
+        "mirror_executions": mirror_executions,
+        "canary_executions": canary_executions,
+        "test_executions": test_executions,
+        "inline_executions": inline_executions,
+        "failures_by_origin": failures_by_origin,
+        "backends": backends,
+        "namespace": namespace_stats,
+        "function_count": function_stats.len(),
+        "health_status": format!("{:?}", performance_report.health_status),
+        "recommendations": performance_report.recommendations
+    })
+}
+
+/// 获取缓存统计，支持 `?profile=` 选择调度器、`?namespace=` 附带命名空间的缓存用量
+pub async fn get_cache_stats(mut req: Request) -> SilentResult<Response> {
+    let runtime = match profile_runtime(&mut req)? {
+        Ok(runtime) => runtime,
+        Err(response) => return Ok(response),
+    };
+    let query: ProfileQuery = req.params_parse().unwrap_or_default();
+
+    let mut stats = cache_stats_json(&runtime, query.namespace.as_deref()).await;
+    if let Ok(janitor) = req.get_config::<Arc<DiskJanitor>>() {
+        stats["disk"] = serde_json::to_value(janitor.stats()).unwrap_or_default();
+    }
+
+    let response = ApiResponse {
+        success: true,
+        data: Some(stats),
+        error: None,
+        message: Some("Cache statistics retrieved successfully".to_string()),
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 查看函数缓存的驱逐策略和存活时间
+pub async fn get_cache_config(mut req: Request) -> SilentResult<Response> {
+    let runtime = match profile_runtime(&mut req)? {
+        Ok(runtime) => runtime,
+        Err(response) => return Ok(response),
+    };
+    let response = ApiResponse {
+        success: true,
+        data: Some(runtime.cache().policy().await),
+        error: None,
+        message: Some("Cache configuration retrieved successfully".to_string()),
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 调整函数缓存的驱逐策略和存活时间，无需重启即作用于之后的缓存操作
+pub async fn update_cache_config(mut req: Request) -> SilentResult<Response> {
+    let runtime = match profile_runtime(&mut req)? {
+        Ok(runtime) => runtime,
+        Err(response) => return Ok(response),
+    };
+    let update: CachePolicyUpdate = match payload::read_json(&mut req).await {
+        Ok(update) => update,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse cache configuration".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
+    let response = ApiResponse {
+        success: true,
+        data: Some(runtime.cache().configure(update).await),
+        error: None,
+        message: Some("Cache configuration updated successfully".to_string()),
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 预热缓存：`{"functions": [...]}` 或 `{"all": true}`，按函数所在调度器分别预热
+pub async fn warm_cache(mut req: Request) -> SilentResult<Response> {
+    let warmup: WarmupRequest = match payload::read_json(&mut req).await {
+        Ok(warmup) => warmup,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse warm-up request".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
+    if !warmup.all && warmup.functions.is_empty() {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some("Either 'functions' or 'all' is required".to_string()),
+            message: Some("Nothing to warm".to_string()),
+        };
+        return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+    }
+
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let concurrency = warmup.concurrency();
+    let mut report = WarmupReport::default();
+    if warmup.all {
+        for profile in schedulers.profiles() {
+            report.merge(profile.scheduler.warm_cache(None, concurrency).await);
+        }
+    } else {
+        // 按持有函数的调度器分组，未知函数交给默认调度器报告失败
+        let mut groups: BTreeMap<&str, (&SchedulerProfile, Vec<String>)> = BTreeMap::new();
+        for name in &warmup.functions {
+            let profile = schedulers.resolve(name).await;
+            groups
+                .entry(profile.name.as_str())
+                .or_insert_with(|| (profile, Vec::new()))
+                .1
+                .push(name.clone());
+        }
+        for (profile, names) in groups.into_values() {
+            report.merge(
+                profile
+                    .scheduler
+                    .warm_cache(Some(&names), concurrency)
+                    .await,
+            );
+        }
+    }
+
+    let response = ApiResponse {
+        success: report.failed.is_empty(),
+        message: Some(format!(
+            "Warmed {} functions ({} failed) in {}ms",
+            report.warmed.len(),
+            report.failed.len(),
+            report.duration_ms
+        )),
+        data: Some(report),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 缓存操作失败时的响应：函数不存在返回 404，固定数量超出上限返回 409
+fn cache_error_response(name: &str, e: FluxError) -> Response {
+    let status = match e {
+        FluxError::FunctionNotFound { .. } => StatusCode::NOT_FOUND,
+        FluxError::PinLimitExceeded { .. } => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let response = ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(e.to_string()),
+        message: Some(format!("Cache operation on function '{name}' failed")),
+    };
+    api_json(&response, status)
+}
+
+/// 固定函数的缓存条目，固定的条目不会因容量或过期被驱逐
+pub async fn pin_cache_entry(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let name = path_function_name(&req)?;
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler.pin_cached(&name).await {
+        Ok(newly_pinned) => {
+            let message = if newly_pinned {
+                format!("Function '{name}' pinned in the cache")
             } else {
-                let response = ApiResponse {
-                    success: false,
-                    data: Some(format!(
-                        "Loaded: {loaded_functions:?}, Failed: {failed_functions:?}"
-                    )),
-                    error: Some(format!(
-                        "Failed to register {} functions",
-                        failed_functions.len()
-                    )),
-                    message: Some("Partial success - some functions failed to load".to_string()),
-                };
-                Ok(Response::json(&response).with_status(StatusCode::PARTIAL_CONTENT))
-            }
+                format!("Function '{name}' is already pinned")
+            };
+            let response = ApiResponse {
+                success: true,
+                data: Some(message.clone()),
+                error: None,
+                message: Some(message),
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(cache_error_response(&name, e)),
+    }
+}
+
+/// 取消固定函数的缓存条目
+pub async fn unpin_cache_entry(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let name = path_function_name(&req)?;
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler.unpin_cached(&name).await {
+        Ok(was_pinned) => {
+            let message = if was_pinned {
+                format!("Function '{name}' unpinned")
+            } else {
+                format!("Function '{name}' was not pinned")
+            };
+            let response = ApiResponse {
+                success: true,
+                data: Some(message.clone()),
+                error: None,
+                message: Some(message),
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(cache_error_response(&name, e)),
+    }
+}
+
+/// 移除函数所有版本的缓存条目（不影响固定状态）
+pub async fn invalidate_cache_entry(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let name = path_function_name(&req)?;
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    match scheduler.invalidate_cached(&name).await {
+        Ok(removed) => {
+            let response = ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({ "function": name, "removed": removed })),
+                error: None,
+                message: Some(format!("Removed {removed} cache entries of '{name}'")),
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => Ok(cache_error_response(&name, e)),
+    }
+}
+
+/// 立即执行一次磁盘清理并返回释放的空间
+pub async fn run_gc(req: Request) -> SilentResult<Response> {
+    let janitor: Arc<DiskJanitor> = req.get_config::<Arc<DiskJanitor>>()?.clone();
+
+    match janitor.run_once().await {
+        Ok(report) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!("Freed {} bytes", report.freed_bytes)),
+                data: Some(report),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
         }
         Err(e) => {
             let response = ApiResponse::<()> {
                 success: false,
                 data: None,
-                error: Some(format!("Failed to load functions from directory: {e}")),
-                message: Some("Directory loading failed".to_string()),
+                error: Some(format!("Disk cleanup failed: {}", e)),
+                message: None,
             };
-            Ok(Response::json(&response).with_status(StatusCode::INTERNAL_SERVER_ERROR))
+            Ok(api_json(&response, StatusCode::INTERNAL_SERVER_ERROR))
         }
     }
 }
 
-/// 获取缓存统计
-pub async fn get_cache_stats(req: Request) -> SilentResult<Response> {
-    // 从配置中获取 scheduler
-    let scheduler: &Arc<SimpleScheduler> = req.get_config()?;
+/// 故障注入未启用时的响应
+fn chaos_disabled_response() -> Response {
+    let response = ApiResponse::<()> {
+        success: false,
+        data: None,
+        error: Some(FluxError::ChaosDisabled.to_string()),
+        message: Some("Chaos fault injection is disabled".to_string()),
+    };
+    api_json(&response, StatusCode::FORBIDDEN)
+}
 
-    // 获取缓存统计信息
-    let cache_stats = scheduler.runtime().cache().stats().await;
-    let hit_rate = scheduler.runtime().cache().hit_rate().await;
+/// 列出生效中的故障注入规则
+pub async fn list_chaos_rules(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let chaos = schedulers.chaos();
+    let rules = chaos.rules();
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!("{} chaos rules", rules.len())),
+        data: Some(serde_json::json!({
+            "enabled": chaos.is_enabled(),
+            "rules": rules,
+        })),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
 
-    // 构建响应数据
-    let stats_data = serde_json::json!({
-        "hits": cache_stats.hits,
-        "misses": cache_stats.misses,
-        "hit_rate": format!("{:.2}%", hit_rate * 100.0),
-        "size": cache_stats.size,
-        "memory_usage_bytes": cache_stats.memory_usage,
-        "memory_usage_mb": cache_stats.memory_usage as f64 / (1024.0 * 1024.0),
-        "max_memory_bytes": cache_stats.max_memory,
-        "max_memory_mb": cache_stats.max_memory as f64 / (1024.0 * 1024.0),
-        "evictions": cache_stats.evictions
-    });
+/// 创建故障注入规则（需要 `chaos.enabled = true`）
+pub async fn create_chaos_rule(mut req: Request) -> SilentResult<Response> {
+    let rule: ChaosRuleRequest = match payload::read_json(&mut req).await {
+        Ok(rule) => rule,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Failed to parse chaos rule".to_string()),
+            };
+            return Ok(api_json(&response, e.status()));
+        }
+    };
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    match schedulers.chaos().add_rule(rule) {
+        Ok(rule) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!("Chaos rule {} created", rule.id)),
+                data: Some(rule),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(FluxError::ChaosDisabled) => Ok(chaos_disabled_response()),
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Invalid chaos rule".to_string()),
+            };
+            Ok(api_json(&response, StatusCode::BAD_REQUEST))
+        }
+    }
+}
 
-    let response = ApiResponse {
+/// 删除故障注入规则
+pub async fn delete_chaos_rule(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let id: String = req.get_path_params("id")?;
+    if !schedulers.chaos().remove_rule(&id) {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(format!("Chaos rule not found: {id}")),
+            message: None,
+        };
+        return Ok(api_json(&response, StatusCode::NOT_FOUND));
+    }
+    let response = ApiResponse::<()> {
         success: true,
-        data: Some(stats_data),
+        data: None,
         error: None,
-        message: Some("Cache statistics retrieved successfully".to_string()),
+        message: Some(format!("Chaos rule {id} deleted")),
     };
-    Ok(Response::json(&response))
+    Ok(api_json(&response, StatusCode::OK))
 }
 
-/// 获取性能统计
-pub async fn get_performance_stats(req: Request) -> SilentResult<Response> {
-    // 从配置中获取 scheduler
-    let scheduler: &Arc<SimpleScheduler> = req.get_config()?;
+/// 列出默认运行时和配置的执行环境及其探测到的版本（超过刷新间隔时先重新探测）
+pub async fn list_runtimes(req: Request) -> SilentResult<Response> {
+    let environment: Arc<RuntimeEnvironment> = req.get_config::<Arc<RuntimeEnvironment>>()?.clone();
 
-    // 获取性能统计信息
-    let performance_report = scheduler.runtime().monitor().generate_report().await;
-    let global_stats = performance_report.global_stats;
-    let hottest_functions = scheduler.runtime().monitor().get_hottest_functions(5).await;
-    let slowest_functions = scheduler.runtime().monitor().get_slowest_functions(5).await;
+    match tokio::task::spawn_blocking(move || {
+        environment.refresh_if_stale();
+        environment.snapshot()
+    })
+    .await
+    {
+        Ok(snapshot) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "{} runtime definitions",
+                    snapshot.definitions.len()
+                )),
+                data: Some(snapshot),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Runtime probe failed: {}", e)),
+                message: None,
+            };
+            Ok(api_json(&response, StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
 
-    // 构建响应数据
-    let stats_data = serde_json::json!({
-        "global_stats": {
-            "total_requests": global_stats.total_requests,
-            "total_success": global_stats.total_success,
-            "total_failures": global_stats.total_failures,
-            "success_rate": if global_stats.total_requests > 0 {
-                global_stats.total_success as f64 / global_stats.total_requests as f64 * 100.0
-            } else { 0.0 },
-            "active_functions": global_stats.active_functions,
-            "current_system_memory_bytes": global_stats.current_system_memory,
-            "peak_system_memory_bytes": global_stats.peak_system_memory,
-            "uptime_seconds": global_stats.start_time.map(|start| start.elapsed().as_secs()).unwrap_or(0)
+/// 重新探测已安装的解释器和编译器（安装新的运行时后无需重启服务）
+pub async fn refresh_runtimes(req: Request) -> SilentResult<Response> {
+    let environment: Arc<RuntimeEnvironment> = req.get_config::<Arc<RuntimeEnvironment>>()?.clone();
+
+    match tokio::task::spawn_blocking(move || environment.refresh()).await {
+        Ok(snapshot) => {
+            let available = snapshot.runtimes.iter().filter(|d| d.available).count();
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "{} of {} runtimes available",
+                    available,
+                    snapshot.runtimes.len()
+                )),
+                data: Some(snapshot),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(format!("Runtime probe failed: {}", e)),
+                message: None,
+            };
+            Ok(api_json(&response, StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+/// 重新读取配置文件并应用可热加载的配置，需要重启才能生效的改动在结果中列出
+pub async fn reload_config(req: Request) -> SilentResult<Response> {
+    let reloader: Arc<ConfigReloader> = req.get_config::<Arc<ConfigReloader>>()?.clone();
+
+    match reloader.reload().await {
+        Ok(report) => {
+            let response = ApiResponse {
+                success: true,
+                message: Some(format!(
+                    "Applied {} changes, {} require a restart",
+                    report.applied.len(),
+                    report.requires_restart.len()
+                )),
+                data: Some(report),
+                error: None,
+            };
+            Ok(api_json(&response, StatusCode::OK))
+        }
+        Err(e) => {
+            let status = match e {
+                ReloadError::NoConfigFile => StatusCode::CONFLICT,
+                ReloadError::Invalid(_) => StatusCode::BAD_REQUEST,
+            };
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Configuration was not reloaded".to_string()),
+            };
+            Ok(api_json(&response, status))
+        }
+    }
+}
+
+/// 获取性能统计，支持 `?profile=` 选择调度器、`?namespace=` 按命名空间过滤函数统计、
+/// `?window=1m|5m|1h` 选择滑动窗口（默认 5m）；`lifetime` 为按半衰期衰减的生命周期汇总，
+/// `invocation_limit` 为全局调用名额和排队统计
+pub async fn get_performance_stats(mut req: Request) -> SilentResult<Response> {
+    let runtime = match profile_runtime(&mut req)? {
+        Ok(runtime) => runtime,
+        Err(response) => return Ok(response),
+    };
+    let query: PerformanceStatsQuery = req.params_parse().unwrap_or_default();
+    let window = match query.window.as_deref() {
+        None => StatsWindow::default(),
+        Some(text) => match StatsWindow::parse(text) {
+            Some(window) => window,
+            None => {
+                let response = ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    error: Some(format!("Invalid window '{text}', expected 1m, 5m or 1h")),
+                    message: Some("Invalid performance statistics parameters".to_string()),
+                };
+                return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+            }
         },
-        "hottest_functions": hottest_functions,
-        "slowest_functions": slowest_functions,
-        "function_count": performance_report.function_stats.len(),
-        "health_status": format!("{:?}", performance_report.health_status),
-        "recommendations": performance_report.recommendations
-    });
+    };
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let mut stats = performance_stats_json(&runtime, query.namespace.as_deref(), window).await;
+    // 全局调用名额不区分调度器和命名空间
+    stats["invocation_limit"] = serde_json::json!(schedulers.invocation_limiter().stats());
 
     let response = ApiResponse {
         success: true,
-        data: Some(stats_data),
+        data: Some(stats),
         error: None,
         message: Some("Performance statistics retrieved successfully".to_string()),
     };
-    Ok(Response::json(&response))
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 解析时长查询参数，缺省时使用 `default`
+fn span_param(value: Option<&str>, default: Duration) -> Result<Duration, String> {
+    match value {
+        None => Ok(default),
+        Some(text) => parse_span(text)
+            .filter(|span| !span.is_zero())
+            .ok_or_else(|| format!("Invalid duration '{text}', expected e.g. 30s, 5m, 1h or 1d")),
+    }
+}
+
+/// 获取函数的执行报告：按分辨率合并的时间序列及窗口内的分位数汇总
+#[utoipa::path(get, path = "/functions/{name}/report", tag = "functions",
+    params(("name" = String, Path, description = "函数名"), ReportQuery),
+    responses(
+        (status = 200, description = "执行报告", body = FunctionReportResponse),
+        (status = 400, description = "时长参数无效", body = ErrorResponse),
+        (status = 404, description = "函数不存在", body = ErrorResponse)
+    ))]
+pub async fn get_function_report(mut req: Request) -> SilentResult<Response> {
+    let schedulers: Arc<SchedulerRegistry> = req.get_config::<Arc<SchedulerRegistry>>()?.clone();
+
+    let name: String = match path_function_name(&req) {
+        Ok(name) => name,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing function name parameter".to_string()),
+                message: Some("Function name is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let query: ReportQuery = req.params_parse().unwrap_or_default();
+    let spans = span_param(query.window.as_deref(), Duration::from_secs(3600)).and_then(|window| {
+        span_param(query.resolution.as_deref(), Duration::from_secs(300))
+            .map(|resolution| (window, resolution))
+    });
+    let (window, resolution) = match spans {
+        Ok(spans) => spans,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e),
+                message: Some("Invalid report parameters".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let scheduler = &schedulers.resolve(&name).await.scheduler;
+    if let Err(e) = scheduler.registry().get(&name).await {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(e.to_string()),
+            message: Some(format!("Function '{name}' not found")),
+        };
+        return Ok(api_json(&response, StatusCode::NOT_FOUND));
+    }
+
+    let report = scheduler
+        .runtime()
+        .monitor()
+        .function_report(&name, window, resolution)
+        .await;
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!(
+            "Report of function '{name}' over {}s retrieved",
+            report.window_secs
+        )),
+        data: Some(report),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 按延迟分位数、错误率、调用数或内存峰值对函数排行，支持 `?profile=` 选择调度器
+pub async fn get_top_functions(mut req: Request) -> SilentResult<Response> {
+    let query: TopFunctionsQuery = match req.params_parse() {
+        Ok(query) => query,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+                message: Some("Invalid ranking parameters".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    let window = match span_param(query.window.as_deref(), Duration::from_secs(3600)) {
+        Ok(window) => window,
+        Err(e) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(e),
+                message: Some("Invalid ranking parameters".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+    let runtime = match profile_runtime(&mut req)? {
+        Ok(runtime) => runtime,
+        Err(response) => return Ok(response),
+    };
+
+    let metric = query.metric.unwrap_or_default();
+    let ranked = runtime
+        .monitor()
+        .top_functions(metric, window, query.limit.unwrap_or(10))
+        .await;
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!("Ranked {} functions by {:?}", ranked.len(), metric)),
+        data: Some(ranked),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
 }
 
 /// 重置调度器
 pub async fn reset_scheduler(req: Request) -> SilentResult<Response> {
-    // 从配置中获取 scheduler
-    let _scheduler: &Arc<SimpleScheduler> = req.get_config()?;
+    // 从配置中获取调度器注册表
+    let _schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
 
     // 暂时返回成功响应，因为 SimpleScheduler 还没有这些方法
     let response = ApiResponse {
@@ -518,5 +5855,119 @@ pub async fn reset_scheduler(req: Request) -> SilentResult<Response> {
         error: None,
         message: Some("Scheduler reset request received".to_string()),
     };
-    Ok(Response::json(&response))
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 列出所有实例，包括执行定义了 `init`/`teardown` 钩子的脚本函数的常驻工作进程
+pub async fn list_instances(mut req: Request) -> SilentResult<Response> {
+    let query: NamespaceQuery = req.params_parse().unwrap_or_default();
+    let manager: &Arc<InstanceManager> = req.get_config()?;
+
+    let instances: Vec<_> = manager
+        .list_instance_summaries()
+        .await
+        .into_iter()
+        .filter(|instance| in_namespace(&instance.function_name, query.namespace.as_deref()))
+        .collect();
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!("Retrieved {} instances", instances.len())),
+        data: Some(instances),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 获取实例的生命周期事件
+pub async fn get_instance_events(mut req: Request) -> SilentResult<Response> {
+    let query: InstanceEventsQuery = req.params_parse().unwrap_or_default();
+    let manager: &Arc<InstanceManager> = req.get_config()?;
+
+    let instance_id: String = match req.get_path_params("id") {
+        Ok(id) => id,
+        Err(_) => {
+            let response = ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some("Missing instance id parameter".to_string()),
+                message: Some("Instance id is required".to_string()),
+            };
+            return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let limit = query
+        .limit
+        .unwrap_or(100)
+        .min(manager.events().retention().max_events);
+    let events = manager.get_instance_events(&instance_id, Some(limit)).await;
+
+    // 已停止的实例仍可查询保留期内的事件
+    if events.is_empty() && manager.get_instance(&instance_id).await.is_none() {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(format!("Instance not found: {instance_id}")),
+            message: Some(format!("Instance '{instance_id}' not found")),
+        };
+        return Ok(api_json(&response, StatusCode::NOT_FOUND));
+    }
+
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!(
+            "Retrieved {} events for instance '{instance_id}'",
+            events.len()
+        )),
+        data: Some(events),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 获取实例管理器统计（同样计入脚本函数的常驻工作进程）
+pub async fn get_instance_stats(req: Request) -> SilentResult<Response> {
+    let manager: &Arc<InstanceManager> = req.get_config()?;
+
+    let response = ApiResponse {
+        success: true,
+        data: Some(manager.get_instance_stats().await),
+        error: None,
+        message: Some("Instance statistics retrieved successfully".to_string()),
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+/// 以 SSE 推送实时生命周期事件，支持 `?namespace=` 只推送该命名空间中函数的事件
+pub async fn stream_instance_events(mut req: Request) -> SilentResult<Response> {
+    let query: NamespaceQuery = req.params_parse().unwrap_or_default();
+    let manager: &Arc<InstanceManager> = req.get_config()?;
+    let receiver = manager.events().subscribe();
+
+    let stream = futures_util::stream::unfold(
+        (receiver, query.namespace),
+        |(mut receiver, namespace)| async move {
+            let sse_event = loop {
+                match receiver.recv().await {
+                    Ok(event) if !in_namespace(&event.function_name, namespace.as_deref()) => {}
+                    Ok(event) => {
+                        break SSEEvent::default()
+                            .id(event.event_id.clone())
+                            .event("lifecycle")
+                            .data(serde_json::to_string(&event).unwrap_or_default());
+                    }
+                    // 客户端消费过慢时告知丢失的事件数，继续推送后续事件
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        break SSEEvent::default()
+                            .event("lagged")
+                            .data(skipped.to_string());
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                }
+            };
+            Some((Ok(sse_event), (receiver, namespace)))
+        },
+    );
+
+    sse_reply(stream)
 }
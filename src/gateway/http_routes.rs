@@ -0,0 +1,70 @@
+//! 自定义 HTTP 路由的分发中间件
+//!
+//! 挂在根路由上，只处理第一段不是内置路由前缀的请求：按绑定调用函数，
+//! 方法不匹配时返回 405，没有匹配的绑定时交给内置路由（返回 404）。
+
+use super::handlers::{self, ApiResponse, api_json};
+use crate::functions::http_route::RouteLookup;
+use crate::scheduler::profiles::SchedulerRegistry;
+use silent::header::{ALLOW, HeaderValue};
+use silent::{
+    Handler, MiddleWareHandler, Next, Request, Response, Result as SilentResult, StatusCode,
+};
+use std::sync::Arc;
+
+/// 自定义 HTTP 路由分发
+pub struct HttpRouteBindings {
+    /// 内置路由的第一段路径
+    reserved: Vec<String>,
+}
+
+impl HttpRouteBindings {
+    pub fn new(reserved: Vec<String>) -> Self {
+        Self { reserved }
+    }
+}
+
+#[async_trait::async_trait]
+impl MiddleWareHandler for HttpRouteBindings {
+    async fn match_req(&self, req: &Request) -> bool {
+        let first = req
+            .uri()
+            .path()
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        !first.is_empty() && !self.reserved.iter().any(|prefix| prefix == first)
+    }
+
+    async fn handle(&self, req: Request, next: &Next) -> SilentResult<Response> {
+        let schedulers: Arc<SchedulerRegistry> =
+            req.get_config::<Arc<SchedulerRegistry>>()?.clone();
+        let lookup = schedulers
+            .lookup_http_route(req.method().as_str(), req.uri().path())
+            .await;
+        match lookup {
+            RouteLookup::Matched { route, params } => {
+                handlers::invoke_http_route(req, *route, params).await
+            }
+            RouteLookup::MethodNotAllowed { allowed } => {
+                let response = ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    error: Some(format!(
+                        "Method {} is not allowed for {}",
+                        req.method(),
+                        req.uri().path()
+                    )),
+                    message: Some(format!("Allowed methods: {}", allowed.join(", "))),
+                };
+                let mut response = api_json(&response, StatusCode::METHOD_NOT_ALLOWED);
+                if let Ok(value) = HeaderValue::from_str(&allowed.join(", ")) {
+                    response.set_header(ALLOW, value);
+                }
+                Ok(response)
+            }
+            RouteLookup::NotFound => next.call(req).await,
+        }
+    }
+}
@@ -4,8 +4,16 @@ use crate::scheduler::SimpleScheduler;
 use silent::prelude::*;
 use std::sync::Arc;
 
+pub mod dashboard;
+pub mod envelope;
 pub mod handlers;
+pub mod http_routes;
+pub mod openapi;
+pub mod payload;
 pub mod routes;
+pub mod shaping;
+pub mod status;
+pub mod websocket;
 
 /// FluxFaaS 网关，负责处理 HTTP 请求
 #[derive(Debug, Clone)]
@@ -51,6 +59,38 @@ impl FluxGateway {
             dependencies: None,
             parameters: None,
             return_type: None,
+            retry_policy: None,
+            idempotent: None,
+            labels: None,
+            profile: None,
+            webhooks: None,
+            priority: None,
+            files: None,
+            entrypoint: None,
+            http_response: None,
+            input_template: None,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: None,
+            coerce_output: None,
+            strict_return_type: None,
+            log_redaction: None,
+            circuit_breaker: None,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: None,
+            scratch_limit_mb: None,
+            persist_scratch: None,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: None,
+            execution_hint: None,
+            namespace: None,
+            documentation: None,
+            egress: None,
+            debug_capture: None,
+            canary: None,
+            slo: None,
         });
         registry
             .register(hello_fn)
@@ -67,6 +107,38 @@ impl FluxGateway {
             dependencies: None,
             parameters: None,
             return_type: None,
+            retry_policy: None,
+            idempotent: None,
+            labels: None,
+            profile: None,
+            webhooks: None,
+            priority: None,
+            files: None,
+            entrypoint: None,
+            http_response: None,
+            input_template: None,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: None,
+            coerce_output: None,
+            strict_return_type: None,
+            log_redaction: None,
+            circuit_breaker: None,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: None,
+            scratch_limit_mb: None,
+            persist_scratch: None,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: None,
+            execution_hint: None,
+            namespace: None,
+            documentation: None,
+            egress: None,
+            debug_capture: None,
+            canary: None,
+            slo: None,
         });
         registry
             .register(echo_fn)
@@ -83,6 +155,38 @@ impl FluxGateway {
             dependencies: None,
             parameters: None,
             return_type: None,
+            retry_policy: None,
+            idempotent: None,
+            labels: None,
+            profile: None,
+            webhooks: None,
+            priority: None,
+            files: None,
+            entrypoint: None,
+            http_response: None,
+            input_template: None,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: None,
+            coerce_output: None,
+            strict_return_type: None,
+            log_redaction: None,
+            circuit_breaker: None,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: None,
+            scratch_limit_mb: None,
+            persist_scratch: None,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: None,
+            execution_hint: None,
+            namespace: None,
+            documentation: None,
+            egress: None,
+            debug_capture: None,
+            canary: None,
+            slo: None,
         });
         registry
             .register(add_fn)
@@ -0,0 +1,724 @@
+use super::handlers::{self, *};
+use crate::functions::bundle::{
+    ConflictStrategy, FunctionArchive, FunctionBundle, ImportResult, ImportStatus,
+};
+use crate::functions::canary::CanaryConfig;
+use crate::functions::compilation::{
+    CompilationRecord, CompilationStatus, HandlerMode, OnCompiling,
+};
+use crate::functions::context::ContextContract;
+use crate::functions::deprecation::{
+    DeprecateRequest, Deprecation, DeprecationNotice, SunsetBehavior,
+};
+use crate::functions::diff::{
+    CodeDiff, DependencyChanges, DiffSummary, DiffTarget, FieldChange, FunctionDiff, LabelChange,
+    ParameterChange, ParameterChanges,
+};
+use crate::functions::header::HeaderSource;
+use crate::functions::http_route::{HttpRoute, InputMapping};
+use crate::functions::mirror::MirrorConfig;
+use crate::functions::package::{FunctionPackage, PackageEntry, PackageFile, PackageTree};
+use crate::functions::priority::Priority;
+use crate::functions::schema::SchemaViolation;
+use crate::functions::slo::SloConfig;
+use crate::functions::testing::{
+    FunctionTestCase, FunctionTestReport, OutputDiff, OutputMatch, TestCaseResult, TestStatus,
+};
+use crate::functions::versions::{VersionDiff, VersionSummary};
+use crate::functions::webhook::{WebhookConfig, WebhookEvent};
+use crate::functions::{
+    ErrorKind, ErrorOrigin, ExecutionStatus, FunctionMetadata, FunctionParameter,
+    InstanceSelection, InvocationTiming, InvokeRequest, InvokeResponse, ProcessTermination,
+    RegisterFunctionRequest, ResourceKind, RetryOn, RetryPolicy, UpdateFunctionRequest,
+};
+use crate::runtime::debug_capture::{BundleFile, BundleManifest, DebugCapture};
+use crate::runtime::disk::{DiskAccounting, DiskUsage};
+use crate::runtime::egress::{EgressCall, EgressPolicy, EgressRule};
+use crate::runtime::git::GitLoadRequest;
+use crate::runtime::inline::{ExecutionHint, ExecutionPath};
+use crate::runtime::invocation_log::{CaptureLevel, LogEntry};
+use crate::runtime::loader::{
+    DirectoryLoadResult, DirectoryLoadSummary, FileLoadResult, LoadAction,
+};
+use crate::runtime::oci::{OciAuth, OciLoadRequest, OciLoadResult};
+use crate::runtime::scratch::{ScratchArtifact, ScratchArtifacts};
+use crate::runtime::series::{FunctionReport, PhaseSummary, SeriesPoint, SeriesSummary};
+use crate::runtime::slo::{BurnRateStatus, BurnWindow, SloAlertTransition, SloCompliance};
+use crate::scheduler::canary::{
+    CanaryHealth, CanaryReport, CanaryStatus, FailingCanary, ReadinessReport,
+};
+use crate::scheduler::circuit::{
+    CircuitBreakerConfig, CircuitState, CircuitStatus, CircuitTransition,
+};
+use crate::scheduler::console::{
+    ConsoleExecuteRequest, ConsoleExecution, ConsolePromoteRequest, ConsolePromotion, ConsoleStats,
+};
+use crate::scheduler::deployments::{
+    Deployment, DeploymentItem, DeploymentItemStatus, DeploymentState,
+};
+use crate::scheduler::deprecation::DeprecationReport;
+use crate::scheduler::drain::{DrainStatus, FunctionState};
+use crate::scheduler::fanout::{
+    FanoutRequest, FanoutResponse, FanoutTarget, FanoutTargetResult, InputMap,
+};
+use crate::scheduler::history::{
+    ChangeKind, ExecutionRecord, InputCapture, JsonChange, ReplayResponse,
+};
+use crate::scheduler::jobs::{AsyncInvokeRequest, Job, JobCallback, JobStatus};
+use crate::scheduler::mirror::{MirrorMismatch, MirrorReport, MirrorStats};
+use crate::scheduler::namespaces::{CreateNamespaceRequest, Namespace, NamespaceConfig};
+use crate::scheduler::peers::{FunctionSyncStatus, PeerStatus};
+use crate::scheduler::profiles::SchedulerRegistry;
+use crate::scheduler::sessions::{SessionExecution, SessionExecutionsPage, SessionSummary};
+use crate::scheduler::slo::SloReport;
+use crate::scheduler::webhooks::{
+    DeliveryStatus, WebhookDelivery, WebhookDeliveryLog, WebhookPayload,
+};
+use silent::{Request, Response, Result as SilentResult};
+use std::sync::Arc;
+use utoipa::OpenApi;
+use utoipa::openapi::path::{OperationBuilder, PathItem, PathItemType};
+use utoipa::openapi::request_body::RequestBodyBuilder;
+use utoipa::openapi::schema::{
+    AllOfBuilder, ArrayBuilder, KnownFormat, ObjectBuilder, Schema, SchemaFormat, SchemaType,
+};
+use utoipa::openapi::{Content, Ref, RefOr, Required, ResponseBuilder};
+
+/// 网关的静态 API 描述（由请求/响应类型推导）
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "FluxFaaS", description = "FluxFaaS 网关 HTTP API"),
+    paths(
+        handlers::health_check,
+        handlers::readiness_check,
+        handlers::list_peers,
+        handlers::register_function,
+        handlers::list_functions,
+        handlers::get_function,
+        handlers::get_function_compilation,
+        handlers::get_function_report,
+        handlers::list_function_versions,
+        handlers::diff_functions,
+        handlers::delete_function_version,
+        handlers::set_function_webhooks,
+        handlers::get_webhook_deliveries,
+        handlers::set_function_mirror,
+        handlers::delete_function_mirror,
+        handlers::get_mirror_mismatches,
+        handlers::get_function_canary,
+        handlers::set_function_canary,
+        handlers::delete_function_canary,
+        handlers::get_function_slo,
+        handlers::set_function_slo,
+        handlers::delete_function_slo,
+        handlers::get_function_deprecation,
+        handlers::deprecate_function,
+        handlers::undeprecate_function,
+        handlers::test_function,
+        handlers::get_function_docs,
+        handlers::set_function_docs,
+        handlers::get_function_circuit,
+        handlers::reset_function_circuit,
+        handlers::preview_input_template,
+        handlers::update_function,
+        handlers::infer_function_signature,
+        handlers::delete_function,
+        handlers::bulk_delete_functions,
+        handlers::bulk_invoke_functions,
+        handlers::export_function,
+        handlers::export_functions,
+        handlers::import_functions,
+        handlers::create_deployment,
+        handlers::get_deployment,
+        handlers::activate_deployment,
+        handlers::rollback_deployment,
+        handlers::create_http_route,
+        handlers::list_http_routes,
+        handlers::delete_http_route,
+        handlers::invoke_function,
+        handlers::invoke_fanout,
+        handlers::invoke_function_async,
+        handlers::get_job,
+        handlers::console_execute,
+        handlers::console_promote,
+        handlers::console_stats,
+        handlers::get_session,
+        handlers::list_session_executions,
+        handlers::delete_session,
+        handlers::replay_execution,
+        handlers::get_execution_bundle,
+        handlers::get_execution_bundle_manifest,
+        handlers::get_execution_artifacts,
+        handlers::get_execution_log,
+        handlers::load_function_from_file,
+        handlers::load_functions_from_directory,
+        handlers::load_functions_from_git,
+        handlers::load_functions_from_oci,
+        handlers::list_namespaces,
+        handlers::create_namespace,
+        handlers::get_namespace,
+        handlers::delete_namespace,
+    ),
+    components(schemas(
+        FunctionMetadata,
+        FunctionParameter,
+        RegisterFunctionRequest,
+        UpdateFunctionRequest,
+        InvokeRequest,
+        InvokeResponse,
+        InvocationTiming,
+        InstanceSelection,
+        ProcessTermination,
+        SchemaViolation,
+        ExecutionStatus,
+        ErrorKind,
+        ErrorOrigin,
+        ResourceKind,
+        RetryPolicy,
+        RetryOn,
+        Priority,
+        PackageFile,
+        FunctionPackage,
+        HeaderSource,
+        PackageEntry,
+        PackageTree,
+        FunctionBundle,
+        FunctionArchive,
+        ConflictStrategy,
+        ImportStatus,
+        ImportResult,
+        FunctionSummary,
+        LoadFileRequest,
+        LoadDirectoryRequest,
+        GitLoadRequest,
+        OciAuth,
+        OciLoadRequest,
+        OciLoadResult,
+        BulkDeleteRequest,
+        BulkInvokeRequest,
+        TemplatePreviewRequest,
+        BulkInvokeResult,
+        MessageResponse,
+        ErrorResponse,
+        JsonResponse,
+        FunctionResponse,
+        FunctionDetails,
+        ContextContract,
+        DrainStatus,
+        FunctionState,
+        FunctionDetailsResponse,
+        CompilationStatus,
+        HandlerMode,
+        CompilationRecord,
+        OnCompiling,
+        CompilationResponse,
+        SeriesSummary,
+        PhaseSummary,
+        SeriesPoint,
+        FunctionReport,
+        FunctionReportResponse,
+        VersionSummary,
+        VersionDiff,
+        VersionListResponse,
+        DiffTarget,
+        FieldChange,
+        ParameterChange,
+        ParameterChanges,
+        DependencyChanges,
+        LabelChange,
+        CodeDiff,
+        DiffSummary,
+        FunctionDiff,
+        FunctionDiffResponse,
+        WebhookEvent,
+        WebhookConfig,
+        WebhookConfigResponse,
+        WebhookPayload,
+        DeliveryStatus,
+        WebhookDelivery,
+        WebhookDeliveryLog,
+        WebhookDeliveryLogResponse,
+        MirrorConfig,
+        MirrorConfigResponse,
+        MirrorMismatch,
+        MirrorStats,
+        MirrorReport,
+        MirrorReportResponse,
+        CanaryConfig,
+        CanaryStatus,
+        CanaryHealth,
+        CanaryReport,
+        CanaryReportResponse,
+        SloConfig,
+        SloCompliance,
+        BurnWindow,
+        BurnRateStatus,
+        SloAlertTransition,
+        SloReport,
+        SloReportResponse,
+        SunsetBehavior,
+        Deprecation,
+        DeprecateRequest,
+        DeprecationNotice,
+        DeprecationReport,
+        DeprecationReportResponse,
+        FunctionTestCase,
+        OutputMatch,
+        TestStatus,
+        OutputDiff,
+        TestCaseResult,
+        FunctionTestReport,
+        FunctionTestReportResponse,
+        FailingCanary,
+        ReadinessReport,
+        ReadinessResponse,
+        FunctionSyncStatus,
+        PeerStatus,
+        PeersResponse,
+        CircuitBreakerConfig,
+        CircuitState,
+        CircuitTransition,
+        CircuitStatus,
+        CircuitStatusResponse,
+        EgressRule,
+        EgressPolicy,
+        EgressCall,
+        FunctionDocsSummary,
+        FunctionDocsResponse,
+        FunctionListResponse,
+        InvokeApiResponse,
+        BundleResponse,
+        ArchiveResponse,
+        ImportResponse,
+        DeploymentState,
+        DeploymentItemStatus,
+        DeploymentItem,
+        Deployment,
+        DeploymentResponse,
+        InputMapping,
+        HttpRoute,
+        HttpRouteResponse,
+        HttpRouteListResponse,
+        NameListResponse,
+        BulkInvokeResponse,
+        FanoutRequest,
+        FanoutTarget,
+        InputMap,
+        FanoutTargetResult,
+        FanoutResponse,
+        FanoutApiResponse,
+        AsyncInvokeRequest,
+        JobStatus,
+        JobCallback,
+        Job,
+        JobResponse,
+        ConsoleExecuteRequest,
+        ConsoleExecution,
+        ConsoleExecutionResponse,
+        ConsolePromoteRequest,
+        ConsolePromotion,
+        ConsolePromotionResponse,
+        ConsoleStats,
+        ConsoleStatsResponse,
+        SessionExecution,
+        SessionSummary,
+        SessionExecutionsPage,
+        SessionResponse,
+        SessionExecutionsResponse,
+        InputCapture,
+        DiskAccounting,
+        DiskUsage,
+        ExecutionRecord,
+        ChangeKind,
+        JsonChange,
+        ReplayResponse,
+        ReplayApiResponse,
+        DebugCapture,
+        ExecutionHint,
+        ExecutionPath,
+        BundleFile,
+        BundleManifest,
+        DebugBundleManifestResponse,
+        ScratchArtifact,
+        ScratchArtifacts,
+        ScratchArtifactsResponse,
+        CaptureLevel,
+        LogEntry,
+        LoadAction,
+        FileLoadResult,
+        DirectoryLoadSummary,
+        DirectoryLoadResult,
+        DirectoryLoadResponse,
+        OciLoadResponse,
+        NamespaceConfig,
+        Namespace,
+        CreateNamespaceRequest,
+        NamespaceDetails,
+        NamespaceResponse,
+        NamespaceListResponse,
+        NamespaceDetailsResponse,
+    )),
+    tags(
+        (name = "system", description = "健康检查"),
+        (name = "functions", description = "函数管理"),
+        (name = "invoke", description = "函数调用"),
+        (name = "load", description = "从文件、目录或 Git 仓库加载函数"),
+        (name = "namespaces", description = "命名空间及其默认限制"),
+        (name = "deployments", description = "多函数原子部署与回滚"),
+        (name = "routes", description = "把自定义 HTTP 路由绑定到函数调用"),
+    )
+)]
+pub struct ApiDoc;
+
+/// 生成完整的 OpenAPI 文档：静态接口 + 每个已注册函数的调用接口
+pub fn build_openapi(functions: &[FunctionMetadata]) -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+
+    for function in functions {
+        doc.paths.paths.insert(
+            format!("/invoke/{}", function.name),
+            PathItem::new(PathItemType::Post, function_operation(function)),
+        );
+    }
+
+    doc
+}
+
+/// 单个函数的调用操作，请求体的 `input` 取自 `input_schema`，未声明时由参数定义生成
+fn function_operation(function: &FunctionMetadata) -> utoipa::openapi::path::Operation {
+    let mut input = ObjectBuilder::new().schema_type(SchemaType::Object);
+    for param in &function.parameters {
+        input = input.property(&param.name, parameter_schema(param));
+        if param.required {
+            input = input.required(&param.name);
+        }
+    }
+    let input: RefOr<Schema> = function
+        .input_schema
+        .as_ref()
+        .and_then(raw_schema)
+        .unwrap_or_else(|| input.into());
+    let output = function
+        .output_schema
+        .as_ref()
+        .and_then(raw_schema)
+        .or_else(|| (!function.return_type.is_empty()).then(|| type_schema(&function.return_type)));
+
+    let request_schema = ObjectBuilder::new()
+        .schema_type(SchemaType::Object)
+        .property("input", input)
+        .required("input")
+        .property(
+            "timeout_ms",
+            ObjectBuilder::new().schema_type(SchemaType::Integer),
+        )
+        .property("retry_policy", Ref::from_schema_name("RetryPolicy"))
+        .property("priority", Ref::from_schema_name("Priority"));
+
+    let mut response_schema = AllOfBuilder::new().item(Ref::from_schema_name("InvokeApiResponse"));
+    if let Some(output) = output {
+        response_schema = response_schema.item(
+            ObjectBuilder::new().property("data", ObjectBuilder::new().property("output", output)),
+        );
+    }
+
+    let description = (!function.description.is_empty()).then(|| function.description.clone());
+
+    OperationBuilder::new()
+        .tag("invoke")
+        .operation_id(Some(format!("invoke_{}", function.name)))
+        .summary(Some(format!("Invoke function '{}'", function.name)))
+        .description(description)
+        .request_body(Some(
+            RequestBodyBuilder::new()
+                .content("application/json", Content::new(request_schema))
+                .content(
+                    "text/plain",
+                    Content::new(ObjectBuilder::new().schema_type(SchemaType::String)),
+                )
+                .content("application/octet-stream", Content::new(binary_schema()))
+                .required(Some(Required::True))
+                .build(),
+        ))
+        .response(
+            "200",
+            ResponseBuilder::new()
+                .description("调用结果；函数输出声明 content_type 时为原始响应体")
+                .content("application/json", Content::new(response_schema))
+                .content("application/octet-stream", Content::new(binary_schema())),
+        )
+        .response(
+            "413",
+            ResponseBuilder::new()
+                .description("请求体超过大小上限")
+                .content(
+                    "application/json",
+                    Content::new(Ref::from_schema_name("ErrorResponse")),
+                ),
+        )
+        .response(
+            "500",
+            ResponseBuilder::new()
+                .description("调度或执行失败")
+                .content(
+                    "application/json",
+                    Content::new(Ref::from_schema_name("ErrorResponse")),
+                ),
+        )
+        .build()
+}
+
+/// 函数声明的 JSON Schema 转换为 OpenAPI schema，包含 OpenAPI 3.0 不支持的写法时返回 `None`
+fn raw_schema(schema: &serde_json::Value) -> Option<RefOr<Schema>> {
+    serde_json::from_value(schema.clone()).ok()
+}
+
+/// 任意二进制内容
+fn binary_schema() -> ObjectBuilder {
+    ObjectBuilder::new()
+        .schema_type(SchemaType::String)
+        .format(Some(SchemaFormat::KnownFormat(KnownFormat::Binary)))
+}
+
+/// 参数的 schema（类型、描述、默认值）
+fn parameter_schema(param: &FunctionParameter) -> RefOr<Schema> {
+    let schema = type_schema(&param.param_type);
+    let RefOr::T(Schema::Object(mut object)) = schema else {
+        return schema;
+    };
+    object.description = param.description.clone();
+    object.default = param.default_value.as_deref().map(|value| {
+        serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()))
+    });
+    RefOr::T(Schema::Object(object))
+}
+
+/// 将参数类型名映射为 JSON Schema，无法识别的类型视为任意 JSON 值
+fn type_schema(type_name: &str) -> RefOr<Schema> {
+    let type_name: String = type_name.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if let Some(inner) = generic_argument(&type_name, "Vec").or_else(|| {
+        type_name
+            .strip_prefix('[')
+            .and_then(|t| t.strip_suffix(']'))
+    }) {
+        return ArrayBuilder::new().items(type_schema(inner)).into();
+    }
+    if let Some(inner) = generic_argument(&type_name, "Option") {
+        return match type_schema(inner) {
+            RefOr::T(Schema::Object(mut object)) => {
+                object.nullable = true;
+                object.into()
+            }
+            other => other,
+        };
+    }
+
+    let schema_type = match type_name.to_ascii_lowercase().as_str() {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+        | "usize" | "int" | "integer" => SchemaType::Integer,
+        "f32" | "f64" | "float" | "double" | "number" => SchemaType::Number,
+        "bool" | "boolean" => SchemaType::Boolean,
+        "string" | "&str" | "str" | "char" => SchemaType::String,
+        "array" => return ArrayBuilder::new().items(ObjectBuilder::new()).into(),
+        "object" => SchemaType::Object,
+        _ => SchemaType::Value,
+    };
+    ObjectBuilder::new().schema_type(schema_type).into()
+}
+
+/// 取出 `Name<T>` 中的 `T`
+fn generic_argument<'a>(type_name: &'a str, name: &str) -> Option<&'a str> {
+    type_name
+        .strip_prefix(name)?
+        .strip_prefix('<')?
+        .strip_suffix('>')
+}
+
+/// 获取 OpenAPI 文档
+pub async fn get_openapi_spec(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let functions: Vec<_> = schedulers
+        .list()
+        .await
+        .into_iter()
+        .map(|(_, function)| function)
+        .collect();
+    Ok(Response::json(&build_openapi(&functions)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    const SCHEMA_TYPES: [&str; 7] = [
+        "object", "array", "string", "integer", "number", "boolean", "null",
+    ];
+
+    /// 校验 schema：类型合法、引用可解析，并递归检查子 schema
+    fn check_schema(spec: &Value, schema: &Value, at: &str) {
+        if let Some(target) = schema.get("$ref") {
+            let name = target
+                .as_str()
+                .and_then(|t| t.strip_prefix("#/components/schemas/"))
+                .unwrap_or_else(|| panic!("{at}: unsupported reference {target}"));
+            assert!(
+                spec["components"]["schemas"].get(name).is_some(),
+                "{at}: dangling reference {target}"
+            );
+            return;
+        }
+        if let Some(schema_type) = schema.get("type") {
+            assert!(
+                SCHEMA_TYPES.contains(&schema_type.as_str().unwrap_or_default()),
+                "{at}: invalid type {schema_type}"
+            );
+        }
+        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+            for (name, property) in properties {
+                check_schema(spec, property, &format!("{at}.{name}"));
+            }
+            for required in schema["required"].as_array().into_iter().flatten() {
+                let required = required.as_str().unwrap();
+                assert!(
+                    properties.contains_key(required),
+                    "{at}: required property {required} is not defined"
+                );
+            }
+        }
+        for key in ["allOf", "oneOf", "anyOf"] {
+            for item in schema
+                .get(key)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                check_schema(spec, item, &format!("{at}.{key}"));
+            }
+        }
+        for key in ["items", "additionalProperties"] {
+            if let Some(child) = schema.get(key).filter(|child| child.is_object()) {
+                check_schema(spec, child, &format!("{at}.{key}"));
+            }
+        }
+    }
+
+    /// OpenAPI 3.0 结构校验：必填字段、操作响应、路径参数和所有 schema
+    fn validate_spec(spec: &Value) {
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3.0"));
+        assert!(spec["info"]["title"].is_string());
+        assert!(spec["info"]["version"].is_string());
+
+        for (name, schema) in spec["components"]["schemas"].as_object().unwrap() {
+            check_schema(spec, schema, name);
+        }
+
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            assert!(path.starts_with('/'), "{path}: path must start with '/'");
+            for (method, operation) in item.as_object().unwrap() {
+                let at = format!("{method} {path}");
+                let responses = operation["responses"].as_object().unwrap();
+                assert!(!responses.is_empty(), "{at}: no responses");
+                for (status, response) in responses {
+                    assert!(response["description"].is_string(), "{at} {status}");
+                    for media in response["content"].as_object().into_iter().flatten() {
+                        check_schema(spec, &media.1["schema"], &format!("{at} {status}"));
+                    }
+                }
+                for media in operation["requestBody"]["content"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                {
+                    check_schema(spec, &media.1["schema"], &format!("{at} body"));
+                }
+
+                // 路径模板中的参数必须声明为必填的 path 参数
+                let parameters = operation["parameters"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default();
+                for segment in path.split('/').filter(|s| s.starts_with('{')) {
+                    let name = segment.trim_matches(|c| c == '{' || c == '}');
+                    assert!(
+                        parameters.iter().any(|p| p["name"] == name
+                            && p["in"] == "path"
+                            && p["required"] == true),
+                        "{at}: path parameter {name} is not declared"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_spec_is_valid_and_includes_function_schema() {
+        let mut function = FunctionMetadata::new("add_two".to_string(), "a + b".to_string());
+        function.return_type = "i64".to_string();
+        function.parameters = ["a", "b"]
+            .into_iter()
+            .map(|name| FunctionParameter {
+                name: name.to_string(),
+                param_type: "i64".to_string(),
+                description: None,
+                required: true,
+                default_value: None,
+            })
+            .collect();
+        let untyped = FunctionMetadata::new("plain".to_string(), "input".to_string());
+        let mut schema_typed =
+            FunctionMetadata::new("schema_typed".to_string(), "input".to_string());
+        schema_typed.input_schema = Some(serde_json::json!({
+            "type": "object",
+            "required": ["order"],
+            "properties": {"order": {"type": "object", "properties": {"id": {"type": "integer"}}}}
+        }));
+        schema_typed.output_schema =
+            Some(serde_json::json!({"type": "array", "items": {"type": "string"}}));
+
+        let spec = serde_json::to_value(build_openapi(&[function, untyped, schema_typed])).unwrap();
+        validate_spec(&spec);
+
+        // 声明了 JSON Schema 时请求体和输出直接使用该 schema
+        let typed = &spec["paths"]["/invoke/schema_typed"]["post"];
+        let input =
+            &typed["requestBody"]["content"]["application/json"]["schema"]["properties"]["input"];
+        assert_eq!(
+            input["properties"]["order"]["properties"]["id"]["type"],
+            "integer"
+        );
+        assert_eq!(input["required"], serde_json::json!(["order"]));
+        assert_eq!(
+            typed["responses"]["200"]["content"]["application/json"]["schema"]["allOf"][1]["properties"]
+                ["data"]["properties"]["output"]["items"]["type"],
+            "string"
+        );
+
+        assert!(spec["paths"]["/invoke/{name}"]["post"].is_object());
+        let plain_input = &spec["paths"]["/invoke/plain"]["post"]["requestBody"]["content"]["application/json"]
+            ["schema"]["properties"]["input"];
+        assert!(plain_input.get("required").is_none());
+
+        let operation = &spec["paths"]["/invoke/add_two"]["post"];
+        let input = &operation["requestBody"]["content"]["application/json"]["schema"]["properties"]
+            ["input"];
+        assert_eq!(input["properties"]["a"]["type"], "integer");
+        assert_eq!(input["properties"]["b"]["type"], "integer");
+        assert_eq!(input["required"], serde_json::json!(["a", "b"]));
+        assert_eq!(
+            operation["responses"]["200"]["content"]["application/json"]["schema"]["allOf"][1]["properties"]
+                ["data"]["properties"]["output"]["type"],
+            "integer"
+        );
+    }
+
+    #[test]
+    fn test_type_schema_mapping() {
+        let to_json = |name| serde_json::to_value(type_schema(name)).unwrap();
+        assert_eq!(to_json("f64")["type"], "number");
+        assert_eq!(to_json("Vec<String>")["items"]["type"], "string");
+        assert_eq!(to_json("Option<bool>")["nullable"], true);
+        assert!(to_json("serde_json::Value").get("type").is_none());
+    }
+}
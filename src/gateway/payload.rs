@@ -0,0 +1,516 @@
+use crate::functions::compression::{DecompressError, DecompressionLimits, Encoding};
+use crate::tunables::Tunables;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use http_body_util::{BodyExt, Limited};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use silent::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, HeaderValue, VARY};
+use silent::prelude::ResBody;
+use silent::{Request, Response, StatusCode};
+use std::sync::Arc;
+
+/// 非 JSON 请求体/响应体中 base64 编码正文的字段名
+pub const BODY_BASE64_FIELD: &str = "body_base64";
+/// 文本响应体正文的字段名
+pub const BODY_FIELD: &str = "body";
+/// 正文内容类型的字段名
+pub const CONTENT_TYPE_FIELD: &str = "content_type";
+
+/// HTTP 调用请求体配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InvokeBodyConfig {
+    /// 调用请求体大小上限（字节），对所有内容类型生效
+    pub max_body_bytes: usize,
+}
+
+impl Default for InvokeBodyConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 6 * 1024 * 1024,
+        }
+    }
+}
+
+/// 请求体解压和响应压缩配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    /// 解压后的请求体大小上限（字节），也是注册和导入请求体的大小上限
+    pub max_decompressed_bytes: usize,
+    /// 解压后与压缩前的大小之比上限，超过时视为压缩炸弹拒绝
+    pub max_ratio: usize,
+    /// 响应体达到该大小（字节）时才按 `Accept-Encoding` 压缩
+    pub min_response_bytes: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            max_decompressed_bytes: 16 * 1024 * 1024,
+            max_ratio: 100,
+            min_response_bytes: 1024,
+        }
+    }
+}
+
+impl InvokeBodyConfig {
+    /// 从请求中获取当前的可重新加载配置，未注入时使用默认值
+    pub fn from_request(req: &Request) -> Self {
+        req.get_config::<Arc<Tunables>>()
+            .map(|tunables| tunables.current().invoke.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl CompressionConfig {
+    /// 从请求中获取当前的可重新加载配置，未注入时使用默认值
+    pub fn from_request(req: &Request) -> Self {
+        req.get_config::<Arc<Tunables>>()
+            .map(|tunables| tunables.current().compression.clone())
+            .unwrap_or_default()
+    }
+
+    /// 解压后不超过 `max_bytes` 字节（同时受 `max_decompressed_bytes` 约束）
+    pub fn limits(&self, max_bytes: usize) -> DecompressionLimits {
+        DecompressionLimits {
+            max_bytes: max_bytes.min(self.max_decompressed_bytes),
+            max_ratio: self.max_ratio,
+        }
+    }
+}
+
+/// 处理器标记可以按 `Accept-Encoding` 压缩的响应（列表、导出等大响应）
+#[derive(Debug, Clone, Copy)]
+pub struct Compressible;
+
+/// 标记响应可压缩，由外层中间件在改写响应体之后压缩
+pub fn compressible(mut response: Response) -> Response {
+    response.extensions_mut().insert(Compressible);
+    response
+}
+
+/// 按 `Accept-Encoding` 选择响应编码：取 q 值最高的受支持编码，q 值相同时 zstd 优先
+pub fn negotiate_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let mut best: Option<(Encoding, f32)> = None;
+    let mut wildcard = None;
+    let mut listed = Vec::new();
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim();
+        let q = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if name == "*" {
+            wildcard = Some(q);
+            continue;
+        }
+        let Some(encoding) = Encoding::parse(name) else {
+            continue;
+        };
+        listed.push(encoding);
+        if q > 0.0 && best.is_none_or(|(current, best_q)| better(encoding, q, current, best_q)) {
+            best = Some((encoding, q));
+        }
+    }
+    if let Some(q) = wildcard.filter(|q| *q > 0.0)
+        && let Some(encoding) = Encoding::ALL.into_iter().find(|e| !listed.contains(e))
+        && best.is_none_or(|(current, best_q)| better(encoding, q, current, best_q))
+    {
+        best = Some((encoding, q));
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+fn better(encoding: Encoding, q: f32, current: Encoding, current_q: f32) -> bool {
+    let rank = |e: Encoding| Encoding::ALL.iter().position(|a| *a == e);
+    q > current_q || (q == current_q && rank(encoding) < rank(current))
+}
+
+/// 压缩标记为 [`Compressible`] 的响应：响应体达到大小下限且客户端接受受支持的编码时才压缩
+pub fn compress_response(
+    response: &mut Response,
+    accept_encoding: Option<&str>,
+    config: &CompressionConfig,
+) {
+    if response.extensions().get::<Compressible>().is_none()
+        || response.headers().contains_key(CONTENT_ENCODING)
+    {
+        return;
+    }
+    response.set_header(VARY, HeaderValue::from_static("accept-encoding"));
+    let Some(encoding) = accept_encoding.and_then(negotiate_encoding) else {
+        return;
+    };
+    let ResBody::Once(body) = response.body() else {
+        return;
+    };
+    if body.len() < config.min_response_bytes {
+        return;
+    }
+    match encoding.compress(body) {
+        Ok(compressed) => {
+            response.headers_mut().remove(CONTENT_LENGTH);
+            response.set_body(compressed.into());
+            response.set_header(
+                CONTENT_ENCODING,
+                HeaderValue::from_static(encoding.as_str()),
+            );
+        }
+        Err(e) => tracing::warn!("Failed to compress response with {}: {}", encoding, e),
+    }
+}
+
+/// 请求的 `Accept-Encoding`
+pub fn accept_encoding(req: &Request) -> Option<String> {
+    req.headers()
+        .get(ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// 请求体的类别，决定如何转换为函数输入
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyKind {
+    /// `application/json`（或未声明内容类型）：请求体是完整的 `InvokeRequest`
+    Json,
+    /// `text/*`：请求体作为 JSON 字符串传入
+    Text,
+    /// 其他类型：请求体以 `{"body_base64", "content_type"}` 传入
+    Binary,
+}
+
+impl BodyKind {
+    /// 根据 Content-Type 判断请求体类别
+    pub fn from_content_type(content_type: Option<&str>) -> Self {
+        let Some(content_type) = content_type else {
+            return Self::Json;
+        };
+        let essence = essence(content_type);
+        if essence == "application/json" || essence.ends_with("+json") {
+            Self::Json
+        } else if essence.starts_with("text/") {
+            Self::Text
+        } else {
+            Self::Binary
+        }
+    }
+}
+
+/// 请求体读取失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BodyError {
+    /// 超过大小上限
+    TooLarge { limit: usize },
+    /// 读取请求体失败
+    Read(String),
+    /// 不支持的 `Content-Encoding`
+    UnsupportedEncoding(String),
+    /// 解压失败或超过解压上限
+    Decompress(DecompressError),
+    /// 请求体不是有效的 JSON
+    Json(String),
+}
+
+impl BodyError {
+    /// 对应的 HTTP 状态码
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::TooLarge { .. }
+            | Self::Decompress(
+                DecompressError::TooLarge { .. } | DecompressError::RatioExceeded { .. },
+            ) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::UnsupportedEncoding(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Read(_) | Self::Decompress(DecompressError::Invalid { .. }) | Self::Json(_) => {
+                StatusCode::BAD_REQUEST
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for BodyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge { limit } => write!(f, "Request body exceeds limit of {limit} bytes"),
+            Self::Read(e) => write!(f, "Failed to read request body: {e}"),
+            Self::UnsupportedEncoding(encoding) => {
+                write!(
+                    f,
+                    "Unsupported Content-Encoding: {encoding} (use gzip or zstd)"
+                )
+            }
+            Self::Decompress(e) => e.fmt(f),
+            Self::Json(e) => write!(f, "Invalid request body: {e}"),
+        }
+    }
+}
+
+/// 请求的 `Content-Encoding`，`identity` 或未声明时返回 `None`
+pub fn content_encoding(req: &Request) -> Result<Option<Encoding>, BodyError> {
+    let Some(value) = req.headers().get(CONTENT_ENCODING) else {
+        return Ok(None);
+    };
+    let value = value.to_str().unwrap_or_default().trim();
+    if value.is_empty() || value.eq_ignore_ascii_case("identity") {
+        return Ok(None);
+    }
+    Encoding::parse(value)
+        .map(Some)
+        .ok_or_else(|| BodyError::UnsupportedEncoding(value.to_string()))
+}
+
+/// 读取并按 `Content-Encoding` 解压请求体
+///
+/// 压缩前不超过 `limit` 字节，解压后不超过 `limit` 和 `max_decompressed_bytes`，
+/// 且压缩比不超过 `max_ratio`。
+pub async fn read_decoded_body(
+    req: &mut Request,
+    limit: usize,
+    config: &CompressionConfig,
+) -> Result<Vec<u8>, BodyError> {
+    let encoding = content_encoding(req)?;
+    let body = read_body(req, limit).await?;
+    match encoding {
+        Some(encoding) => encoding
+            .decompress(&body, config.limits(limit))
+            .map_err(BodyError::Decompress),
+        None => Ok(body),
+    }
+}
+
+/// 读取（必要时解压）并解析 JSON 请求体，大小上限为 `max_decompressed_bytes`
+pub async fn read_json<T: DeserializeOwned>(req: &mut Request) -> Result<T, BodyError> {
+    let config = CompressionConfig::from_request(req);
+    let body = read_decoded_body(req, config.max_decompressed_bytes, &config).await?;
+    serde_json::from_slice(&body).map_err(|e| BodyError::Json(e.to_string()))
+}
+
+/// 读取请求体，超过 `limit` 字节时返回 [`BodyError::TooLarge`]
+pub async fn read_body(req: &mut Request, limit: usize) -> Result<Vec<u8>, BodyError> {
+    let body = Limited::new(req.take_body(), limit);
+    match body.collect().await {
+        Ok(collected) => Ok(collected.to_bytes().to_vec()),
+        Err(e) if e.is::<http_body_util::LengthLimitError>() => Err(BodyError::TooLarge { limit }),
+        Err(e) => Err(BodyError::Read(e.to_string())),
+    }
+}
+
+/// 将非 JSON 请求体转换为函数输入
+pub fn raw_input(kind: BodyKind, content_type: &str, body: Vec<u8>) -> Result<Value, String> {
+    match kind {
+        BodyKind::Text => String::from_utf8(body)
+            .map(Value::String)
+            .map_err(|_| "Text body is not valid UTF-8".to_string()),
+        BodyKind::Binary => Ok(json!({
+            BODY_BASE64_FIELD: STANDARD.encode(body),
+            CONTENT_TYPE_FIELD: content_type,
+        })),
+        BodyKind::Json => serde_json::from_slice(&body).map_err(|e| e.to_string()),
+    }
+}
+
+/// 函数返回的非 JSON 响应
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawOutput {
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl RawOutput {
+    /// 识别声明了响应内容类型的函数输出
+    ///
+    /// 输出恰好为 `{"content_type", "body_base64"}`（二进制）或 `{"content_type", "body"}`（文本）
+    /// 时按原始响应返回；其他输出返回 `None`，照常作为 JSON 返回。
+    pub fn from_output(output: &Value) -> Option<Result<Self, String>> {
+        let object = output.as_object().filter(|object| object.len() == 2)?;
+        let content_type = object.get(CONTENT_TYPE_FIELD)?.as_str()?.to_string();
+
+        let body = if let Some(encoded) = object.get(BODY_BASE64_FIELD) {
+            let encoded = encoded.as_str()?;
+            match STANDARD.decode(encoded) {
+                Ok(body) => body,
+                Err(e) => return Some(Err(format!("Invalid {BODY_BASE64_FIELD} in output: {e}"))),
+            }
+        } else {
+            object.get(BODY_FIELD)?.as_str()?.as_bytes().to_vec()
+        };
+
+        if !content_type.contains('/') || HeaderValue::from_str(&content_type).is_err() {
+            return Some(Err(format!(
+                "Invalid {CONTENT_TYPE_FIELD} in output: {content_type}"
+            )));
+        }
+        Some(Ok(Self { content_type, body }))
+    }
+}
+
+/// 去掉参数后的小写 MIME 类型，例如 `text/csv; charset=utf-8` -> `text/csv`
+pub(crate) fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::routes::build_routes;
+    use crate::scheduler::SimpleScheduler;
+    use crate::scheduler::profiles::SchedulerRegistry;
+    use silent::prelude::{Configs, Listener, Server};
+
+    async fn spawn_server() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut configs = Configs::default();
+        configs.insert(Arc::new(SchedulerRegistry::with_default(Arc::new(
+            SimpleScheduler::new(),
+        ))));
+        tokio::spawn(
+            Server::new()
+                .with_configs(configs)
+                .listen(Listener::from(listener))
+                .serve(build_routes()),
+        );
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn test_negotiate_encoding() {
+        assert_eq!(negotiate_encoding("gzip, deflate"), Some(Encoding::Gzip));
+        assert_eq!(negotiate_encoding("gzip, zstd"), Some(Encoding::Zstd));
+        assert_eq!(negotiate_encoding("zstd;q=0.5, gzip"), Some(Encoding::Gzip));
+        assert_eq!(negotiate_encoding("gzip;q=0, br"), None);
+        assert_eq!(negotiate_encoding("*"), Some(Encoding::Zstd));
+        assert_eq!(negotiate_encoding("zstd;q=0, *"), Some(Encoding::Gzip));
+        assert_eq!(negotiate_encoding("identity"), None);
+    }
+
+    #[tokio::test]
+    async fn test_compressed_register_invoke_and_bomb_rejection() {
+        let base = spawn_server().await;
+        let client = reqwest::Client::new();
+
+        // gzip 压缩的注册请求体，随后以 zstd 压缩的调用请求体调用
+        let body = serde_json::to_vec(&json!({
+            "name": "sum",
+            "description": "x".repeat(4096),
+            "code": "return a + b",
+        }))
+        .unwrap();
+        let res = client
+            .post(format!("{base}/v1/functions"))
+            .header("content-encoding", "gzip")
+            .body(Encoding::Gzip.compress(&body).unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+
+        let body = serde_json::to_vec(&json!({"input": {"a": 2, "b": 3}})).unwrap();
+        let res = client
+            .post(format!("{base}/v1/invoke/sum"))
+            .header("content-encoding", "zstd")
+            .body(Encoding::Zstd.compress(&body).unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        let value: Value = res.json().await.unwrap();
+        assert_eq!(value["data"]["output"]["result"], 5);
+
+        // 列表响应按 Accept-Encoding 压缩
+        let res = client
+            .get(format!("{base}/v1/functions"))
+            .header("accept-encoding", "gzip")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.headers()["content-encoding"], "gzip");
+        let listed = Encoding::Gzip
+            .decompress(
+                &res.bytes().await.unwrap(),
+                DecompressionLimits::max_bytes(1 << 20),
+            )
+            .unwrap();
+        let listed: Value = serde_json::from_slice(&listed).unwrap();
+        assert_eq!(listed["data"][0]["name"], "sum");
+
+        // 压缩炸弹：1 MiB 的零压缩到约 1 KiB，超过默认压缩比上限
+        let bomb = Encoding::Gzip.compress(&vec![0; 1 << 20]).unwrap();
+        let res = client
+            .post(format!("{base}/v1/functions"))
+            .header("content-encoding", "gzip")
+            .body(bomb)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 413);
+        let value: Value = res.json().await.unwrap();
+        assert!(
+            value["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("compressed size")
+        );
+
+        let res = client
+            .post(format!("{base}/v1/invoke/sum"))
+            .header("content-encoding", "br")
+            .body("{}")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 415);
+    }
+
+    #[test]
+    fn test_body_conversions() {
+        assert_eq!(BodyKind::from_content_type(None), BodyKind::Json);
+        assert_eq!(
+            BodyKind::from_content_type(Some("application/vnd.api+json")),
+            BodyKind::Json
+        );
+        assert_eq!(
+            BodyKind::from_content_type(Some("Text/CSV; charset=utf-8")),
+            BodyKind::Text
+        );
+        assert_eq!(
+            BodyKind::from_content_type(Some("application/x-protobuf")),
+            BodyKind::Binary
+        );
+
+        assert_eq!(
+            raw_input(BodyKind::Text, "text/csv", b"a,b\n1,2".to_vec()).unwrap(),
+            json!("a,b\n1,2")
+        );
+        assert!(raw_input(BodyKind::Text, "text/plain", vec![0xff, 0xfe]).is_err());
+
+        let binary =
+            raw_input(BodyKind::Binary, "application/x-protobuf", vec![0, 1, 255]).unwrap();
+        assert_eq!(binary[CONTENT_TYPE_FIELD], "application/x-protobuf");
+
+        // 二进制输入原样返回即得到相同的二进制响应
+        let raw = RawOutput::from_output(&binary).unwrap().unwrap();
+        assert_eq!(raw.body, vec![0, 1, 255]);
+        assert_eq!(raw.content_type, "application/x-protobuf");
+
+        let text = RawOutput::from_output(&json!({"content_type": "text/csv", "body": "x"}));
+        assert_eq!(text.unwrap().unwrap().body, b"x");
+
+        // 普通 JSON 输出不受影响
+        assert!(RawOutput::from_output(&json!({"body": "x", "status": 1})).is_none());
+        assert!(RawOutput::from_output(&json!({"content_type": "text/csv"})).is_none());
+        assert!(
+            RawOutput::from_output(&json!({"content_type": "a/b", "body_base64": "!!"}))
+                .unwrap()
+                .is_err()
+        );
+    }
+}
@@ -1,33 +1,164 @@
-use super::handlers;
+use super::envelope::{API_VERSION, DeprecatedAlias, VersionedEnvelope};
+use super::http_routes::HttpRouteBindings;
+use super::{dashboard, handlers, openapi, status, websocket};
 use silent::prelude::*;
 
+/// 构建路由：`/v1/...` 返回版本化响应体，未加前缀的旧路由作为弃用别名保留原有格式
 pub fn build_routes() -> RootRoute {
     let mut root = RootRoute::new();
 
+    let mut v1 = Route::new(API_VERSION);
+    for route in api_routes() {
+        v1 = v1.append(route);
+    }
+    root.push(v1.hook(VersionedEnvelope));
+
+    for route in api_routes() {
+        root.push(route.hook(DeprecatedAlias));
+    }
+
+    // 自定义 HTTP 路由只处理内置路由之外的路径
+    root.hook(HttpRouteBindings::new(reserved_prefixes()));
+
+    root
+}
+
+/// 内置路由的第一段路径（自定义 HTTP 路由不能以这些前缀开头）
+pub fn reserved_prefixes() -> Vec<String> {
+    let mut prefixes = vec![API_VERSION.to_string()];
+    for route in api_routes() {
+        if !prefixes.contains(&route.path) {
+            prefixes.push(route.path);
+        }
+    }
+    prefixes
+}
+
+/// 所有 API 路由（不含版本前缀）
+fn api_routes() -> Vec<Route> {
+    let mut root = Vec::new();
+
     // 健康检查路由
     let health_route = Route::new("health").get(handlers::health_check);
     root.push(health_route);
 
+    // 就绪检查路由（汇总金丝雀探测）
+    let ready_route = Route::new("health/ready").get(handlers::readiness_check);
+    root.push(ready_route);
+
+    // OpenAPI 文档路由
+    let openapi_route = Route::new("openapi.json").get(openapi::get_openapi_spec);
+    root.push(openapi_route);
+
     // 函数管理路由
     let functions_route = Route::new("functions")
         .post(handlers::register_function)
         .get(handlers::list_functions);
     root.push(functions_route);
 
-    // 单个函数操作路由
-    let function_route = Route::new("functions/<name>")
-        .get(handlers::get_function)
-        .delete(handlers::delete_function);
-    root.push(function_route);
+    // 按标签批量操作路由
+    let bulk_delete_route =
+        Route::new("functions/bulk/delete").post(handlers::bulk_delete_functions);
+    root.push(bulk_delete_route);
 
-    // 函数调用路由
-    let invoke_route = Route::new("invoke/<name>").post(handlers::invoke_function);
-    root.push(invoke_route);
+    let bulk_invoke_route =
+        Route::new("functions/bulk/invoke").post(handlers::bulk_invoke_functions);
+    root.push(bulk_invoke_route);
+
+    // 函数导出/导入路由
+    let export_all_route = Route::new("functions/export").get(handlers::export_functions);
+    root.push(export_all_route);
+
+    let import_route = Route::new("functions/import").post(handlers::import_functions);
+    root.push(import_route);
+
+    // 多函数部署
+    let deployments_route = Route::new("deployments").post(handlers::create_deployment);
+    root.push(deployments_route);
+
+    let deployment_route = Route::new("deployments/<id>").get(handlers::get_deployment);
+    root.push(deployment_route);
+
+    let activate_route =
+        Route::new("deployments/<id>/activate").post(handlers::activate_deployment);
+    root.push(activate_route);
+
+    let rollback_route =
+        Route::new("deployments/<id>/rollback").post(handlers::rollback_deployment);
+    root.push(rollback_route);
+
+    // 缓存统计和预热路由（需在 `cache/<name>` 之前注册）
+    let cache_route = Route::new("cache/stats").get(handlers::get_cache_stats);
+    root.push(cache_route);
+
+    let cache_warm_route = Route::new("cache/warm").post(handlers::warm_cache);
+    root.push(cache_warm_route);
+
+    let cache_config_route = Route::new("cache/config")
+        .get(handlers::get_cache_config)
+        .put(handlers::update_cache_config);
+    root.push(cache_config_route);
+
+    // 单个函数的路由，`/namespaces/<ns>/...` 下同样可用（作用于该命名空间）
+    root.extend(function_routes());
+
+    // 命名空间路由
+    let namespaces_route = Route::new("namespaces")
+        .post(handlers::create_namespace)
+        .get(handlers::list_namespaces);
+    root.push(namespaces_route);
+
+    let mut namespace_route = Route::new("namespaces/<ns>")
+        .get(handlers::get_namespace)
+        .delete(handlers::delete_namespace);
+    let namespace_functions_route = Route::new("functions")
+        .post(handlers::register_function)
+        .get(handlers::list_functions);
+    namespace_route = namespace_route.append(namespace_functions_route);
+    for route in function_routes() {
+        namespace_route = namespace_route.append(route);
+    }
+    root.push(namespace_route);
+
+    // 资源配额路由
+    let quotas_route = Route::new("quotas")
+        .post(handlers::create_quota)
+        .get(handlers::list_quotas);
+    root.push(quotas_route);
+
+    let quota_route = Route::new("quotas/<name>")
+        .get(handlers::get_quota)
+        .put(handlers::update_quota)
+        .delete(handlers::delete_quota);
+    root.push(quota_route);
+
+    let quota_usage_route = Route::new("quotas/<name>/usage").get(handlers::get_quota_usage);
+    root.push(quota_usage_route);
+
+    // 执行中的函数读写状态的回环接口
+    let state_route = Route::new("state").post(handlers::access_state);
+    root.push(state_route);
 
-    // 调度器状态路由
-    let status_route = Route::new("status").get(handlers::get_scheduler_status);
+    // 执行中的函数经宿主发送出站 HTTP 请求的回环接口
+    let egress_route = Route::new("egress").post(handlers::broker_http_fetch);
+    root.push(egress_route);
+
+    // WebSocket 调用路由
+    let ws_invoke_route = Route::new("ws/invoke").get(websocket::ws_invoke);
+    root.push(ws_invoke_route);
+
+    // 系统状态路由
+    let status_route = Route::new("status").get(status::get_system_status);
     root.push(status_route);
 
+    // 运维仪表盘路由
+    let dashboard_summary_route =
+        Route::new("dashboard/summary").get(dashboard::get_dashboard_summary);
+    root.push(dashboard_summary_route);
+
+    let dashboard_stream_route = Route::new("dashboard/stream").get(dashboard::stream_dashboard);
+    root.push(dashboard_stream_route);
+
     // 文件加载路由
     let load_file_route = Route::new("load/file").post(handlers::load_function_from_file);
     root.push(load_file_route);
@@ -36,17 +167,231 @@ pub fn build_routes() -> RootRoute {
     let load_dir_route = Route::new("load/directory").post(handlers::load_functions_from_directory);
     root.push(load_dir_route);
 
-    // 缓存统计路由
-    let cache_route = Route::new("cache/stats").get(handlers::get_cache_stats);
-    root.push(cache_route);
+    // Git 仓库加载路由
+    let load_git_route = Route::new("load/git").post(handlers::load_functions_from_git);
+    root.push(load_git_route);
+
+    // 从 OCI 制品加载函数路由
+    let load_oci_route = Route::new("load/oci").post(handlers::load_functions_from_oci);
+    root.push(load_oci_route);
+
+    // 磁盘清理路由
+    let gc_route = Route::new("admin/gc").post(handlers::run_gc);
+    root.push(gc_route);
+
+    // 运行时重新探测路由
+    let runtimes_route = Route::new("admin/runtimes/refresh").post(handlers::refresh_runtimes);
+    root.push(runtimes_route);
+    let runtime_list_route = Route::new("runtimes").get(handlers::list_runtimes);
+    root.push(runtime_list_route);
+
+    // 转发节点状态路由
+    let peers_route = Route::new("peers").get(handlers::list_peers);
+    root.push(peers_route);
+
+    // 配置重新加载路由
+    let reload_route = Route::new("admin/config/reload").post(handlers::reload_config);
+    root.push(reload_route);
+
+    // 自定义 HTTP 路由绑定
+    let http_routes_route = Route::new("routes")
+        .post(handlers::create_http_route)
+        .get(handlers::list_http_routes);
+    root.push(http_routes_route);
+
+    let http_route_route = Route::new("routes/<id>").delete(handlers::delete_http_route);
+    root.push(http_route_route);
+
+    // 故障注入规则路由
+    let chaos_rules_route = Route::new("admin/chaos/rules")
+        .post(handlers::create_chaos_rule)
+        .get(handlers::list_chaos_rules);
+    root.push(chaos_rules_route);
+
+    let chaos_rule_route = Route::new("admin/chaos/rules/<id>").delete(handlers::delete_chaos_rule);
+    root.push(chaos_rule_route);
+
+    // 异步调用任务路由
+    let job_route = Route::new("jobs/<id>").get(handlers::get_job);
+    root.push(job_route);
+
+    // 交互式控制台路由
+    let console_execute_route = Route::new("console/execute").post(handlers::console_execute);
+    root.push(console_execute_route);
+    let console_promote_route = Route::new("console/promote").post(handlers::console_promote);
+    root.push(console_promote_route);
+    let console_stats_route = Route::new("console/stats").get(handlers::console_stats);
+    root.push(console_stats_route);
+
+    // 会话调用记录路由
+    let session_route = Route::new("sessions/<id>")
+        .get(handlers::get_session)
+        .delete(handlers::delete_session);
+    root.push(session_route);
+
+    let session_executions_route =
+        Route::new("sessions/<id>/executions").get(handlers::list_session_executions);
+    root.push(session_executions_route);
+
+    // 执行重放路由
+    let replay_route = Route::new("executions/<id>/replay").post(handlers::replay_execution);
+    root.push(replay_route);
+
+    // 调试包路由
+    let bundle_route = Route::new("executions/<id>/bundle").get(handlers::get_execution_bundle);
+    root.push(bundle_route);
+    let bundle_manifest_route =
+        Route::new("executions/<id>/bundle/manifest").get(handlers::get_execution_bundle_manifest);
+    root.push(bundle_manifest_route);
+
+    // 保留的暂存文件
+    let artifacts_route =
+        Route::new("executions/<id>/artifacts").get(handlers::get_execution_artifacts);
+    root.push(artifacts_route);
+
+    // 按调用捕获的日志
+    let log_route = Route::new("executions/<id>/log").get(handlers::get_execution_log);
+    root.push(log_route);
 
     // 性能统计路由
     let perf_route = Route::new("performance/stats").get(handlers::get_performance_stats);
     root.push(perf_route);
 
+    let top_route = Route::new("performance/top").get(handlers::get_top_functions);
+    root.push(top_route);
+
+    // 实例路由
+    let instances_route = Route::new("instances").get(handlers::list_instances);
+    root.push(instances_route);
+
+    let instance_stats_route = Route::new("instances/stats").get(handlers::get_instance_stats);
+    root.push(instance_stats_route);
+
+    let instance_stream_route =
+        Route::new("instances/events/stream").get(handlers::stream_instance_events);
+    root.push(instance_stream_route);
+
+    let instance_events_route =
+        Route::new("instances/<id>/events").get(handlers::get_instance_events);
+    root.push(instance_events_route);
+
     // 重置路由
     let reset_route = Route::new("reset").post(handlers::reset_scheduler);
     root.push(reset_route);
 
     root
 }
+
+/// 单个函数的路由（函数名取自 `<name>`，命名空间路由下还有 `<ns>`）
+fn function_routes() -> Vec<Route> {
+    let mut routes = Vec::new();
+
+    let export_route = Route::new("functions/<name>/export").get(handlers::export_function);
+    routes.push(export_route);
+
+    let compilation_route =
+        Route::new("functions/<name>/compilation").get(handlers::get_function_compilation);
+    routes.push(compilation_route);
+
+    let report_route = Route::new("functions/<name>/report").get(handlers::get_function_report);
+    routes.push(report_route);
+
+    let versions_route =
+        Route::new("functions/<name>/versions").get(handlers::list_function_versions);
+    routes.push(versions_route);
+
+    let diff_route = Route::new("functions/<name>/diff").get(handlers::diff_functions);
+    routes.push(diff_route);
+
+    let version_route =
+        Route::new("functions/<name>/versions/<version>").delete(handlers::delete_function_version);
+    routes.push(version_route);
+
+    let webhooks_route =
+        Route::new("functions/<name>/webhooks").put(handlers::set_function_webhooks);
+    routes.push(webhooks_route);
+
+    let deliveries_route =
+        Route::new("functions/<name>/webhooks/deliveries").get(handlers::get_webhook_deliveries);
+    routes.push(deliveries_route);
+
+    let mirror_route = Route::new("functions/<name>/mirror")
+        .put(handlers::set_function_mirror)
+        .delete(handlers::delete_function_mirror);
+    routes.push(mirror_route);
+
+    let mismatches_route =
+        Route::new("functions/<name>/mirror/mismatches").get(handlers::get_mirror_mismatches);
+    routes.push(mismatches_route);
+
+    let canary_route = Route::new("functions/<name>/canary")
+        .get(handlers::get_function_canary)
+        .put(handlers::set_function_canary)
+        .delete(handlers::delete_function_canary);
+    routes.push(canary_route);
+    let slo_route = Route::new("functions/<name>/slo")
+        .get(handlers::get_function_slo)
+        .put(handlers::set_function_slo)
+        .delete(handlers::delete_function_slo);
+    routes.push(slo_route);
+    let deprecate_route = Route::new("functions/<name>/deprecate")
+        .get(handlers::get_function_deprecation)
+        .post(handlers::deprecate_function)
+        .delete(handlers::undeprecate_function);
+    routes.push(deprecate_route);
+
+    let test_route = Route::new("functions/<name>/test").post(handlers::test_function);
+    routes.push(test_route);
+
+    let docs_route = Route::new("functions/<name>/docs")
+        .get(handlers::get_function_docs)
+        .put(handlers::set_function_docs);
+    routes.push(docs_route);
+
+    let circuit_route = Route::new("functions/<name>/circuit").get(handlers::get_function_circuit);
+    routes.push(circuit_route);
+
+    let circuit_reset_route =
+        Route::new("functions/<name>/circuit/reset").post(handlers::reset_function_circuit);
+    routes.push(circuit_reset_route);
+
+    let state_route = Route::new("functions/<name>/state")
+        .get(handlers::get_function_state)
+        .delete(handlers::clear_function_state);
+    routes.push(state_route);
+
+    let template_preview_route =
+        Route::new("functions/<name>/template/preview").post(handlers::preview_input_template);
+    routes.push(template_preview_route);
+
+    let infer_route = Route::new("functions/<name>/infer").post(handlers::infer_function_signature);
+    routes.push(infer_route);
+
+    // 单个函数操作路由
+    let function_route = Route::new("functions/<name>")
+        .get(handlers::get_function)
+        .patch(handlers::update_function)
+        .delete(handlers::delete_function);
+    routes.push(function_route);
+
+    // 缓存固定和失效路由
+    let cache_pin_route = Route::new("cache/pin/<name>")
+        .post(handlers::pin_cache_entry)
+        .delete(handlers::unpin_cache_entry);
+    routes.push(cache_pin_route);
+
+    let cache_entry_route = Route::new("cache/<name>").delete(handlers::invalidate_cache_entry);
+    routes.push(cache_entry_route);
+
+    // 函数调用路由（扇出和异步调用路由需在 `invoke/<name>` 之前注册）
+    let fanout_route = Route::new("invoke/<name>/fanout").post(handlers::invoke_fanout);
+    routes.push(fanout_route);
+
+    let async_route = Route::new("invoke/<name>/async").post(handlers::invoke_function_async);
+    routes.push(async_route);
+
+    let invoke_route = Route::new("invoke/<name>").post(handlers::invoke_function);
+    routes.push(invoke_route);
+
+    routes
+}
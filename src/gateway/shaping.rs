@@ -0,0 +1,262 @@
+use crate::gateway::handlers::REQUEST_ID_HEADER;
+use crate::gateway::payload::essence;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde_json::{Map, Value};
+use silent::header::{CONTENT_TYPE, HeaderName, HeaderValue};
+use silent::{Response, StatusCode};
+
+/// 函数输出中声明 HTTP 响应的字段名：`{"$http": {"status", "headers", "body"}}`
+pub const HTTP_OUTPUT_KEY: &str = "$http";
+
+/// 函数可以设置的响应头（另外允许除 `x-request-id` 外的 `x-*` 自定义头）
+///
+/// `transfer-encoding`、`content-length`、`connection` 等由网关控制的头不在其中。
+pub const ALLOWED_RESPONSE_HEADERS: &[&str] = &[
+    "cache-control",
+    "content-disposition",
+    "content-language",
+    "content-type",
+    "etag",
+    "expires",
+    "last-modified",
+    "link",
+    "location",
+    "retry-after",
+    "set-cookie",
+    "vary",
+    "www-authenticate",
+];
+
+/// 响应描述中允许的字段
+const SPEC_FIELDS: &[&str] = &["status", "headers", "body", "body_base64", "content_type"];
+
+/// 函数声明的 HTTP 响应
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpOutput {
+    pub status: StatusCode,
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// 响应头是否允许由函数设置
+pub fn is_allowed_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ALLOWED_RESPONSE_HEADERS.contains(&name.as_str())
+        || (name.starts_with("x-") && name != REQUEST_ID_HEADER)
+}
+
+impl HttpOutput {
+    /// 识别声明了 HTTP 响应的函数输出
+    ///
+    /// 输出为 `{"$http": {...}}`，或函数设置了 `http_response` 时整个输出即响应描述；
+    /// 其他输出返回 `None`，照常作为 JSON 返回。
+    pub fn from_output(output: &Value, http_response: bool) -> Option<Result<Self, String>> {
+        if http_response {
+            return Some(Self::parse(output));
+        }
+        let object = output.as_object().filter(|object| object.len() == 1)?;
+        object.get(HTTP_OUTPUT_KEY).map(Self::parse)
+    }
+
+    fn parse(spec: &Value) -> Result<Self, String> {
+        let spec = spec
+            .as_object()
+            .ok_or_else(|| "HTTP response must be an object".to_string())?;
+        if let Some(field) = spec.keys().find(|key| !SPEC_FIELDS.contains(&key.as_str())) {
+            return Err(format!("Unknown HTTP response field '{field}'"));
+        }
+
+        let status = match spec.get("status") {
+            None | Some(Value::Null) => StatusCode::OK,
+            Some(status) => status
+                .as_u64()
+                .filter(|code| (100..=599).contains(code))
+                .and_then(|code| StatusCode::from_u16(code as u16).ok())
+                .ok_or_else(|| format!("Invalid HTTP status {status}, expected 100-599"))?,
+        };
+
+        let mut headers = Vec::new();
+        let mut content_type = None;
+        if let Some(map) = spec.get("headers").filter(|value| !value.is_null()) {
+            let map = map
+                .as_object()
+                .ok_or_else(|| "HTTP headers must be an object".to_string())?;
+            for (name, value) in map {
+                if !is_allowed_header(name) {
+                    return Err(format!("Header '{name}' cannot be set by functions"));
+                }
+                let header = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| format!("Invalid header name '{name}'"))?;
+                for value in header_values(name, value)? {
+                    if header == CONTENT_TYPE {
+                        content_type = Some(value);
+                        continue;
+                    }
+                    let value = HeaderValue::from_str(&value)
+                        .map_err(|_| format!("Invalid value for header '{name}'"))?;
+                    headers.push((header.clone(), value));
+                }
+            }
+        }
+        if let Some(declared) = spec.get("content_type").filter(|value| !value.is_null()) {
+            content_type = Some(
+                declared
+                    .as_str()
+                    .ok_or_else(|| "content_type must be a string".to_string())?
+                    .to_string(),
+            );
+        }
+        if let Some(content_type) = &content_type
+            && (!content_type.contains('/') || HeaderValue::from_str(content_type).is_err())
+        {
+            return Err(format!("Invalid content type: {content_type}"));
+        }
+
+        let (content_type, body) = encode_body(spec, content_type)?;
+        Ok(Self {
+            status,
+            headers,
+            content_type,
+            body,
+        })
+    }
+
+    /// 转换为实际的 HTTP 响应
+    pub fn into_response(self) -> Response {
+        let mut response = Response::empty();
+        response.set_status(self.status);
+        if let Some(value) = self
+            .content_type
+            .and_then(|content_type| HeaderValue::from_str(&content_type).ok())
+        {
+            response.set_header(CONTENT_TYPE, value);
+        }
+        for (name, value) in self.headers {
+            response.headers_mut().append(name, value);
+        }
+        response.with_body(self.body.into())
+    }
+}
+
+/// 响应头的值：字符串、数字或布尔值，多个值用数组
+fn header_values(name: &str, value: &Value) -> Result<Vec<String>, String> {
+    let scalar = |value: &Value| match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(_) | Value::Bool(_) => Ok(value.to_string()),
+        _ => Err(format!("Invalid value for header '{name}'")),
+    };
+    match value {
+        Value::Array(values) => values.iter().map(scalar).collect(),
+        value => scalar(value).map(|value| vec![value]),
+    }
+}
+
+/// 按内容类型序列化响应体：JSON 类型序列化 `body`，其他类型要求 `body` 为字符串，
+/// 二进制内容使用 `body_base64`；未声明类型时字符串按纯文本、其他值按 JSON 返回
+fn encode_body(
+    spec: &Map<String, Value>,
+    content_type: Option<String>,
+) -> Result<(Option<String>, Vec<u8>), String> {
+    let body = spec.get("body").filter(|value| !value.is_null());
+    if let Some(encoded) = spec.get("body_base64").filter(|value| !value.is_null()) {
+        if body.is_some() {
+            return Err("Only one of body and body_base64 may be set".to_string());
+        }
+        let encoded = encoded
+            .as_str()
+            .ok_or_else(|| "body_base64 must be a string".to_string())?;
+        let body = STANDARD
+            .decode(encoded)
+            .map_err(|e| format!("Invalid body_base64: {e}"))?;
+        return Ok((content_type, body));
+    }
+
+    let Some(body) = body else {
+        return Ok((content_type, Vec::new()));
+    };
+    let content_type = content_type.unwrap_or_else(|| match body {
+        Value::String(_) => "text/plain; charset=utf-8".to_string(),
+        _ => "application/json".to_string(),
+    });
+    let essence = essence(&content_type);
+    let bytes = if essence == "application/json" || essence.ends_with("+json") {
+        serde_json::to_vec(body).map_err(|e| e.to_string())?
+    } else {
+        body.as_str()
+            .ok_or_else(|| format!("body must be a string for content type {content_type}"))?
+            .as_bytes()
+            .to_vec()
+    };
+    Ok((Some(content_type), bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(output: Value) -> Result<HttpOutput, String> {
+        HttpOutput::from_output(&output, false).unwrap()
+    }
+
+    #[test]
+    fn test_redirect_and_custom_content_type() {
+        let redirect = parse(json!({"$http": {
+            "status": 302,
+            "headers": {"Location": "https://example.com/next", "Cache-Control": "no-store"}
+        }}))
+        .unwrap();
+        assert_eq!(redirect.status, StatusCode::FOUND);
+        assert!(redirect.body.is_empty());
+        assert!(redirect.headers.contains(&(
+            HeaderName::from_static("location"),
+            HeaderValue::from_static("https://example.com/next")
+        )));
+
+        let csv = parse(json!({"$http": {
+            "headers": {"content-type": "text/csv", "x-total": 2},
+            "body": "a,b\n1,2\n"
+        }}))
+        .unwrap();
+        assert_eq!(csv.status, StatusCode::OK);
+        assert_eq!(csv.content_type.as_deref(), Some("text/csv"));
+        assert_eq!(csv.body, b"a,b\n1,2\n");
+        assert_eq!(csv.headers[0].1, "2");
+
+        // 非字符串响应体不能按 text/csv 返回，JSON 响应体按声明类型序列化
+        assert!(parse(json!({"$http": {"content_type": "text/csv", "body": [1]}})).is_err());
+        let problem = parse(json!({"$http": {
+            "status": 404,
+            "content_type": "application/problem+json",
+            "body": {"title": "Not Found"}
+        }}))
+        .unwrap();
+        assert_eq!(problem.body, br#"{"title":"Not Found"}"#);
+
+        // `http_response` 函数的整个输出即响应描述；普通输出保持原样
+        let flagged = HttpOutput::from_output(&json!({"status": 204}), true).unwrap();
+        assert_eq!(flagged.unwrap().status, StatusCode::NO_CONTENT);
+        assert!(HttpOutput::from_output(&json!({"status": 204}), false).is_none());
+        assert!(HttpOutput::from_output(&json!({"$http": {}, "other": 1}), false).is_none());
+    }
+
+    #[test]
+    fn test_forbidden_headers_and_invalid_status_are_rejected() {
+        for header in [
+            "Transfer-Encoding",
+            "content-length",
+            "Connection",
+            "X-Request-Id",
+        ] {
+            let err =
+                parse(json!({"$http": {"headers": {header: "x"}, "body": "hi"}})).unwrap_err();
+            assert!(err.contains("cannot be set"), "{header}: {err}");
+        }
+        for status in [json!(99), json!(600), json!("200"), json!(-1)] {
+            assert!(parse(json!({"$http": {"status": status}})).is_err());
+        }
+        assert!(parse(json!({"$http": {"stauts": 200}})).is_err());
+    }
+}
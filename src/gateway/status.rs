@@ -0,0 +1,346 @@
+use super::handlers::{ApiResponse, api_json};
+use crate::functions::guardrails::RegistryGuardrailsStatus;
+use crate::runtime::SimpleRuntime;
+use crate::runtime::capabilities::CapabilityMatrix;
+use crate::runtime::environment::RuntimeSnapshot;
+use crate::runtime::execution_gate::ExecutorStats;
+use crate::runtime::instance::{InstanceManager, InstanceManagerStats};
+use crate::runtime::monitor::RecentSummary;
+use crate::runtime::sandbox::SystemUsage;
+use crate::runtime::script_cache::ScriptCacheStats;
+use crate::runtime::windows::StatsWindow;
+use crate::scheduler::admission::AdmissionStats;
+use crate::scheduler::limiter::InvocationLimitStats;
+use crate::scheduler::profiles::{SchedulerProfile, SchedulerRegistry};
+use futures_util::future::join_all;
+use serde::{Serialize, Serializer};
+use silent::{Request, Response, Result as SilentResult, StatusCode};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+/// 单个子系统统计的采集超时，超时的部分标记为 `"unavailable"`
+pub const SECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 性能统计的时间窗口
+pub const RECENT_WINDOW: StatsWindow = StatsWindow::FiveMinutes;
+
+/// 服务启动时间
+static STARTED_AT: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// 记录服务启动时间（启动时调用一次，之后的调用不改变记录）
+pub fn mark_started() {
+    LazyLock::force(&STARTED_AT);
+}
+
+/// 状态中的一个子系统：采集成功时为统计数据，超时或失败时序列化为 `"unavailable"`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Section<T> {
+    Available(T),
+    Unavailable,
+}
+
+impl<T: Serialize> Serialize for Section<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Available(value) => value.serialize(serializer),
+            Self::Unavailable => serializer.serialize_str("unavailable"),
+        }
+    }
+}
+
+impl<T> Section<T> {
+    /// 在超时内采集一个子系统的统计
+    async fn gather(timeout: Duration, future: impl Future<Output = T>) -> Self {
+        match tokio::time::timeout(timeout, future).await {
+            Ok(value) => Self::Available(value),
+            Err(_) => Self::Unavailable,
+        }
+    }
+
+    /// 在超时内采集可能失败的统计
+    async fn try_gather<E: std::fmt::Display>(
+        timeout: Duration,
+        future: impl Future<Output = Result<T, E>>,
+    ) -> Self {
+        match Section::gather(timeout, future).await {
+            Section::Available(Ok(value)) => Self::Available(value),
+            Section::Available(Err(e)) => {
+                tracing::warn!("Failed to collect status section: {}", e);
+                Self::Unavailable
+            }
+            Section::Unavailable => Self::Unavailable,
+        }
+    }
+}
+
+/// 已注册函数数量
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FunctionCounts {
+    pub total: usize,
+    /// 按脚本类型统计（目前所有函数均为 Rust）
+    pub by_script_type: BTreeMap<String, usize>,
+}
+
+/// 函数缓存统计
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CacheStatus {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub hit_rate: f64,
+    pub memory_usage_bytes: usize,
+    pub evictions: u64,
+}
+
+/// 性能监控汇总
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PerformanceStatus {
+    pub total_invocations: u64,
+    pub total_failures: u64,
+    pub error_rate: f64,
+    /// 最近 5 分钟的调用统计
+    pub recent: RecentSummary,
+}
+
+/// 编译器状态
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompilerStatus {
+    pub enabled: bool,
+    /// 缓存中的编译产物数
+    pub cached_functions: usize,
+}
+
+/// 沙箱状态
+#[derive(Debug, Clone, Serialize)]
+pub struct SandboxStatus {
+    pub active_processes: usize,
+    pub system: SystemUsage,
+    /// 脚本缓存命中统计
+    pub script_cache: ScriptCacheStats,
+    /// 并发执行名额和等待队列
+    pub executions: ExecutorStats,
+}
+
+/// 单个调度器配置的状态
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileStatus {
+    pub compilation_enabled: bool,
+    pub max_timeout_ms: Option<u64>,
+    pub functions: Section<FunctionCounts>,
+    pub cache: Section<CacheStatus>,
+    pub performance: Section<PerformanceStatus>,
+    pub compiler: Section<CompilerStatus>,
+    /// 执行许可和各优先级队列的深度与等待时间
+    pub queues: AdmissionStats,
+    /// 函数目录的持久化后端不可达，拒绝变更
+    pub catalog_read_only: bool,
+    /// 函数数和变更速率相对护栏限制的当前值
+    pub registry_limits: RegistryGuardrailsStatus,
+    /// 各脚本类型的执行方式（`native`/`external`/`embedded`/`unsupported`），
+    /// `unsupported` 的脚本类型注册时被拒绝
+    pub capabilities: CapabilityMatrix,
+}
+
+/// `GET /status` 的系统状态汇总，字段名保持稳定
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemStatus {
+    pub status: String,
+    pub version: String,
+    pub uptime_seconds: u64,
+    /// 各调度器配置的状态（按配置名排序）
+    pub profiles: BTreeMap<String, ProfileStatus>,
+    /// 全局调用名额、等待队列深度、排队时间分位数和被拒绝的调用数
+    pub invocations: InvocationLimitStats,
+    pub sandbox: Section<SandboxStatus>,
+    pub instances: Section<InstanceManagerStats>,
+    /// 缓存的解释器和编译器探测结果（路径、版本和探测时间）
+    pub runtimes: Section<RuntimeSnapshot>,
+}
+
+impl SystemStatus {
+    /// 并发采集所有子系统的状态，每个子系统最多等待 `timeout`
+    pub async fn collect(
+        schedulers: &SchedulerRegistry,
+        instances: Option<&InstanceManager>,
+        timeout: Duration,
+    ) -> Self {
+        let profiles = join_all(
+            schedulers
+                .profiles()
+                .map(|profile| ProfileStatus::collect(profile, timeout)),
+        );
+        let sandbox = async {
+            match instances {
+                Some(manager) => {
+                    let sandbox = manager.sandbox();
+                    Section::try_gather(timeout, async {
+                        Ok::<_, anyhow::Error>(SandboxStatus {
+                            active_processes: sandbox.get_active_process_count().await,
+                            system: sandbox.get_system_usage().await?,
+                            script_cache: sandbox.script_cache().stats().await,
+                            executions: sandbox.get_executor_stats(),
+                        })
+                    })
+                    .await
+                }
+                None => Section::Unavailable,
+            }
+        };
+        let instance_stats = async {
+            match instances {
+                Some(manager) => Section::gather(timeout, manager.get_instance_stats()).await,
+                None => Section::Unavailable,
+            }
+        };
+        let runtimes = match instances {
+            Some(manager) => Section::Available(manager.sandbox().environment().snapshot()),
+            None => Section::Unavailable,
+        };
+        let (profiles, sandbox, instances) = tokio::join!(profiles, sandbox, instance_stats);
+
+        Self {
+            status: "running".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_seconds: STARTED_AT.elapsed().as_secs(),
+            profiles: schedulers.names().into_iter().zip(profiles).collect(),
+            invocations: schedulers.invocation_limiter().stats(),
+            sandbox,
+            instances,
+            runtimes,
+        }
+    }
+}
+
+impl ProfileStatus {
+    async fn collect(profile: &SchedulerProfile, timeout: Duration) -> Self {
+        let runtime: &SimpleRuntime = profile.scheduler.runtime();
+        let functions = Section::gather(timeout, async {
+            let total = profile.scheduler.registry().count().await;
+            FunctionCounts {
+                total,
+                by_script_type: BTreeMap::from([("rust".to_string(), total)]),
+            }
+        });
+        let cache = Section::gather(timeout, async {
+            let stats = runtime.cache().stats().await;
+            CacheStatus {
+                hits: stats.hits,
+                misses: stats.misses,
+                entries: stats.size,
+                hit_rate: runtime.cache().hit_rate().await,
+                memory_usage_bytes: stats.memory_usage,
+                evictions: stats.evictions,
+            }
+        });
+        let performance = Section::gather(timeout, async {
+            let stats = runtime.monitor().get_global_stats().await;
+            PerformanceStatus {
+                total_invocations: stats.total_requests,
+                total_failures: stats.total_failures,
+                error_rate: if stats.total_requests > 0 {
+                    stats.total_failures as f64 / stats.total_requests as f64
+                } else {
+                    0.0
+                },
+                recent: stats.recent_summary(RECENT_WINDOW),
+            }
+        });
+        let compiler = Section::gather(timeout, async {
+            CompilerStatus {
+                enabled: runtime.supports_compilation(),
+                cached_functions: match runtime.compiler() {
+                    Some(compiler) => compiler.compiled_count().await,
+                    None => 0,
+                },
+            }
+        });
+        let (functions, cache, performance, compiler) =
+            tokio::join!(functions, cache, performance, compiler);
+
+        Self {
+            compilation_enabled: runtime.supports_compilation(),
+            max_timeout_ms: profile.config.max_timeout_ms,
+            functions,
+            cache,
+            performance,
+            compiler,
+            queues: profile.scheduler.admission().stats(),
+            catalog_read_only: profile.scheduler.registry().is_read_only(),
+            registry_limits: profile.scheduler.registry().guardrails_status().await,
+            capabilities: profile.scheduler.capabilities(),
+        }
+    }
+}
+
+/// 获取系统状态：各调度器的函数、缓存、性能和编译器统计，以及沙箱和实例统计
+pub async fn get_system_status(req: Request) -> SilentResult<Response> {
+    let schedulers: &Arc<SchedulerRegistry> = req.get_config()?;
+    let instances = req.get_config::<Arc<InstanceManager>>().ok();
+
+    let status =
+        SystemStatus::collect(schedulers, instances.map(Arc::as_ref), SECTION_TIMEOUT).await;
+    let response = ApiResponse {
+        success: true,
+        message: Some(format!(
+            "System status retrieved for {} profiles",
+            status.profiles.len()
+        )),
+        data: Some(status),
+        error: None,
+    };
+    Ok(api_json(&response, StatusCode::OK))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::SimpleScheduler;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_system_status_sections() {
+        let scheduler = Arc::new(SimpleScheduler::new());
+        let schedulers = SchedulerRegistry::with_default(scheduler.clone());
+        let function =
+            crate::functions::FunctionMetadata::new("echo".to_string(), "return input".to_string());
+        scheduler.registry().register(function).await.unwrap();
+
+        let status = SystemStatus::collect(&schedulers, None, SECTION_TIMEOUT).await;
+        let value = serde_json::to_value(&status).unwrap();
+        assert_eq!(value["status"], "running");
+        assert_eq!(value["version"], env!("CARGO_PKG_VERSION"));
+        let profile = &value["profiles"]["default"];
+        assert_eq!(
+            profile["functions"],
+            json!({"total": 1, "by_script_type": {"rust": 1}})
+        );
+        assert_eq!(profile["compiler"]["enabled"], false);
+        // 没有沙箱时脚本只能由内嵌引擎执行，不编译的调度器不能执行 Rust 函数
+        assert_eq!(profile["capabilities"]["expression"], "native");
+        assert_eq!(profile["capabilities"]["rust"], "unsupported");
+        assert_eq!(profile["capabilities"]["python"], "unsupported");
+        assert_eq!(
+            profile["capabilities"]["javascript"],
+            if cfg!(feature = "pure") {
+                "embedded"
+            } else {
+                "unsupported"
+            }
+        );
+        assert_eq!(profile["performance"]["recent"]["window_secs"], 300);
+        assert_eq!(profile["queues"]["queues"]["high"]["depth"], 0);
+        assert_eq!(value["invocations"]["queued"], 0);
+        assert_eq!(value["invocations"]["max_concurrent"], 256);
+        // 未注入实例管理器时相关部分不可用
+        assert_eq!(value["sandbox"], "unavailable");
+        assert_eq!(value["instances"], "unavailable");
+        assert_eq!(value["runtimes"], "unavailable");
+
+        // 卡住的子系统在超时后标记为不可用
+        let stuck: Section<u64> =
+            Section::gather(Duration::from_millis(20), std::future::pending()).await;
+        assert_eq!(serde_json::to_value(&stuck).unwrap(), json!("unavailable"));
+    }
+}
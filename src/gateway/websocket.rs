@@ -0,0 +1,363 @@
+use super::handlers::{ApiResponse, api_json};
+use crate::functions::{ExecutionStatus, InvokeRequest};
+use crate::scheduler::Scheduler;
+use crate::scheduler::profiles::SchedulerRegistry;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use silent::header::{CONNECTION, HeaderValue, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE};
+use silent::{Request, Response, Result as SilentResult, StatusCode};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Semaphore, mpsc};
+use tokio::time::{Instant, interval};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::{Bytes, Error as WsError, Message};
+use tracing::Instrument;
+
+/// WebSocket 调用通道配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WsInvokeConfig {
+    /// 单个连接上同时执行的调用数上限
+    pub max_in_flight: usize,
+    /// 发送 ping 的间隔（秒）
+    pub ping_interval_secs: u64,
+    /// 超过该时间未收到任何帧（包括 pong）则关闭连接（秒）
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for WsInvokeConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 64,
+            ping_interval_secs: 15,
+            idle_timeout_secs: 45,
+        }
+    }
+}
+
+/// 客户端发送的调用帧
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsInvokeFrame {
+    /// 客户端生成的请求 ID，响应帧原样返回
+    pub id: String,
+    pub function: String,
+    #[serde(default)]
+    pub input: serde_json::Value,
+}
+
+/// 服务端返回的响应帧（调用结果或错误）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WsResponseFrame {
+    /// 对应请求的 ID（无法解析出 ID 的错误帧为空）
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_time_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ExecutionStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl WsResponseFrame {
+    fn error(id: Option<String>, error: impl Into<String>) -> Self {
+        Self {
+            id,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+
+    fn to_message(&self) -> Message {
+        Message::text(serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+/// WebSocket 调用入口：校验握手后升级连接，调用在同一连接上多路复用
+pub async fn ws_invoke(mut req: Request) -> SilentResult<Response> {
+    let schedulers: Arc<SchedulerRegistry> = req.get_config::<Arc<SchedulerRegistry>>()?.clone();
+    let config = req
+        .get_config::<Arc<WsInvokeConfig>>()
+        .map(|config| config.as_ref().clone())
+        .unwrap_or_default();
+
+    let is_upgrade = req
+        .headers()
+        .get(UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    let key = req.headers().get(SEC_WEBSOCKET_KEY).cloned();
+    let on_upgrade = req.extensions_mut().remove::<hyper::upgrade::OnUpgrade>();
+
+    let (Some(key), Some(on_upgrade), true) = (key, on_upgrade, is_upgrade) else {
+        let response = ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some("Expected a WebSocket upgrade request".to_string()),
+            message: Some("Connect with a WebSocket client".to_string()),
+        };
+        return Ok(api_json(&response, StatusCode::BAD_REQUEST));
+    };
+
+    tokio::spawn(async move {
+        match on_upgrade.await {
+            Ok(upgraded) => {
+                let ws =
+                    WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None)
+                        .await;
+                serve_connection(ws, schedulers, config).await;
+            }
+            Err(e) => tracing::warn!("WebSocket upgrade failed: {}", e),
+        }
+    });
+
+    let mut response = Response::empty();
+    response.set_status(StatusCode::SWITCHING_PROTOCOLS);
+    response
+        .headers_mut()
+        .insert(CONNECTION, HeaderValue::from_static("Upgrade"));
+    response
+        .headers_mut()
+        .insert(UPGRADE, HeaderValue::from_static("websocket"));
+    if let Ok(accept) = HeaderValue::from_str(&derive_accept_key(key.as_bytes())) {
+        response.headers_mut().insert(SEC_WEBSOCKET_ACCEPT, accept);
+    }
+    Ok(response)
+}
+
+/// 处理一个已升级的连接，直到客户端关闭或超时未响应
+pub async fn serve_connection<S>(ws: S, schedulers: Arc<SchedulerRegistry>, config: WsInvokeConfig)
+where
+    S: Stream<Item = Result<Message, WsError>> + Sink<Message, Error = WsError> + Send + 'static,
+{
+    let (mut sink, mut stream) = ws.split();
+
+    // 所有响应经由通道写出，调用可以乱序完成
+    let (sender, mut outgoing) = mpsc::unbounded_channel::<Message>();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = outgoing.recv().await {
+            if sink.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    let permits = Arc::new(Semaphore::new(config.max_in_flight));
+    let idle_timeout = Duration::from_secs(config.idle_timeout_secs);
+    let mut ping = interval(Duration::from_secs(config.ping_interval_secs.max(1)));
+    ping.tick().await;
+    let mut last_seen = Instant::now();
+
+    loop {
+        tokio::select! {
+            message = stream.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        tracing::debug!("WebSocket read error: {}", e);
+                        break;
+                    }
+                    None => break,
+                };
+                last_seen = Instant::now();
+
+                let payload = match message {
+                    Message::Text(text) => text.as_bytes().to_vec(),
+                    Message::Binary(bytes) => bytes.to_vec(),
+                    Message::Close(_) => break,
+                    // ping 由协议层自动回复 pong；pong 只用于刷新活跃时间
+                    _ => continue,
+                };
+                handle_frame(&payload, &schedulers, &permits, config.max_in_flight, &sender);
+            }
+            _ = ping.tick() => {
+                if last_seen.elapsed() > idle_timeout {
+                    tracing::info!(
+                        "Closing WebSocket connection idle for {}s",
+                        last_seen.elapsed().as_secs()
+                    );
+                    break;
+                }
+                let _ = sender.send(Message::Ping(Bytes::new()));
+            }
+        }
+    }
+
+    // 丢弃写端，已在执行的调用完成后发送失败即被忽略
+    drop(sender);
+    writer.abort();
+}
+
+/// 解析一帧调用请求并在独立任务中执行
+fn handle_frame(
+    payload: &[u8],
+    schedulers: &Arc<SchedulerRegistry>,
+    permits: &Arc<Semaphore>,
+    max_in_flight: usize,
+    sender: &mpsc::UnboundedSender<Message>,
+) {
+    let frame: WsInvokeFrame = match serde_json::from_slice(payload) {
+        Ok(frame) => frame,
+        Err(e) => {
+            // 尽量取出 ID，方便客户端对应到请求
+            let id = serde_json::from_slice::<serde_json::Value>(payload)
+                .ok()
+                .and_then(|value| value.get("id")?.as_str().map(str::to_string));
+            let _ = sender
+                .send(WsResponseFrame::error(id, format!("Malformed frame: {e}")).to_message());
+            return;
+        }
+    };
+
+    let Ok(permit) = permits.clone().try_acquire_owned() else {
+        let _ = sender.send(
+            WsResponseFrame::error(
+                Some(frame.id),
+                format!("Too many in-flight requests on this connection (limit {max_in_flight})"),
+            )
+            .to_message(),
+        );
+        return;
+    };
+
+    let schedulers = schedulers.clone();
+    let sender = sender.clone();
+    let span = tracing::info_span!("ws_invoke", request_id = %frame.id, function = %frame.function);
+    tokio::spawn(
+        async move {
+            let request = InvokeRequest {
+                input: frame.input,
+                retry_policy: None,
+                priority: None,
+                idempotency_key: None,
+                session_id: None,
+                affinity_key: None,
+            };
+            // 每帧按目标函数解析所属调度器
+            let scheduler = schedulers.resolve(&frame.function).await.scheduler.clone();
+            let response = match scheduler.schedule(&frame.function, request).await {
+                Ok(response) => WsResponseFrame {
+                    id: Some(frame.id),
+                    output: Some(response.output),
+                    execution_time_ms: Some(response.execution_time_ms),
+                    status: Some(response.status),
+                    error: None,
+                },
+                Err(e) => WsResponseFrame::error(
+                    Some(frame.id),
+                    format!("Function execution failed: {e}"),
+                ),
+            };
+            drop(permit);
+            let _ = sender.send(response.to_message());
+        }
+        .instrument(span),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::FunctionMetadata;
+    use crate::scheduler::SimpleScheduler;
+    use std::collections::HashMap;
+
+    async fn connect(
+        config: WsInvokeConfig,
+    ) -> (
+        WebSocketStream<tokio::io::DuplexStream>,
+        tokio::task::JoinHandle<()>,
+    ) {
+        let scheduler = Arc::new(SimpleScheduler::new());
+        scheduler
+            .registry()
+            .register(FunctionMetadata::new(
+                "echo".to_string(),
+                "return input".to_string(),
+            ))
+            .await
+            .unwrap();
+        let schedulers = Arc::new(SchedulerRegistry::with_default(scheduler));
+
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            let ws = WebSocketStream::from_raw_socket(server_io, Role::Server, None).await;
+            serve_connection(ws, schedulers, config).await;
+        });
+        let client = WebSocketStream::from_raw_socket(client_io, Role::Client, None).await;
+        (client, server)
+    }
+
+    async fn next_frame(client: &mut WebSocketStream<tokio::io::DuplexStream>) -> WsResponseFrame {
+        loop {
+            match client.next().await.unwrap().unwrap() {
+                Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+                _ => continue,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multiplexed_invocations_and_error_frames() {
+        let (mut client, _server) = connect(WsInvokeConfig {
+            max_in_flight: 1,
+            ..Default::default()
+        })
+        .await;
+
+        // 两帧一次性写入：第二帧到达时第一帧仍在执行，超出上限
+        let frame = |id: &str| {
+            Message::text(
+                serde_json::json!({"id": id, "function": "echo", "input": {"v": id}}).to_string(),
+            )
+        };
+        client.feed(frame("a")).await.unwrap();
+        client.feed(frame("b")).await.unwrap();
+        client
+            .feed(Message::text(r#"{"id": "c", "function": 1}"#))
+            .await
+            .unwrap();
+        client.feed(Message::text("not json")).await.unwrap();
+        client.flush().await.unwrap();
+
+        let mut frames = HashMap::new();
+        for _ in 0..4 {
+            let frame = next_frame(&mut client).await;
+            frames.insert(frame.id.clone().unwrap_or_default(), frame);
+        }
+
+        assert!(matches!(frames["a"].status, Some(ExecutionStatus::Success)));
+        assert_eq!(frames["a"].output.as_ref().unwrap()["v"], "a");
+        assert!(frames["b"].error.as_ref().unwrap().contains("in-flight"));
+        assert!(frames["c"].error.as_ref().unwrap().contains("Malformed"));
+        assert!(frames[""].error.as_ref().unwrap().contains("Malformed"));
+
+        // 出错后连接仍然可用
+        client.send(frame("d")).await.unwrap();
+        let frame = next_frame(&mut client).await;
+        assert_eq!(frame.id.as_deref(), Some("d"));
+        assert!(matches!(frame.status, Some(ExecutionStatus::Success)));
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_connection_is_closed() {
+        let (_client, server) = connect(WsInvokeConfig {
+            ping_interval_secs: 1,
+            idle_timeout_secs: 1,
+            ..Default::default()
+        })
+        .await;
+
+        // 客户端从不读取，因此不会回复 pong
+        tokio::time::timeout(Duration::from_secs(10), server)
+            .await
+            .expect("idle connection should be closed")
+            .unwrap();
+    }
+}
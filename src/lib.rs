@@ -1,4 +1,9 @@
+pub mod client;
+pub mod config;
 pub mod functions;
 pub mod gateway;
 pub mod runtime;
 pub mod scheduler;
+pub mod server;
+pub mod telemetry;
+pub mod tunables;
@@ -0,0 +1,136 @@
+//! 执行亲和
+//!
+//! 调用请求带有亲和键（请求体的 `affinity_key` 或 `X-Flux-Affinity-Key` 请求头）时，实例池和
+//! 脚本函数的常驻工作进程按一致性哈希环（[`HashRing`]）把同一个键映射到同一个实例，函数在
+//! 实例内按键构建的数据可以复用。对应的实例不健康、负载过高或忙碌时本次调用按常规策略选择，
+//! 键的归属不变；实例增减时只有映射到该实例的键改变归属。
+//!
+//! [`HashRing`]: crate::scheduler::balancer::HashRing
+
+use crate::functions::{FluxError, InstanceSelection, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+
+/// 亲和键的最大长度
+pub const MAX_AFFINITY_KEY_LEN: usize = 255;
+
+/// 每个实例分别计数的亲和键上限，超过后新的键只计入请求总数
+const MAX_TRACKED_KEYS: usize = 1024;
+
+/// 统计中列出的请求最多的键数
+const HOT_KEYS: usize = 5;
+
+/// 校验亲和键：非空、不超过 255 个字符且只含可见 ASCII 字符
+pub fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty()
+        || key.len() > MAX_AFFINITY_KEY_LEN
+        || !key.bytes().all(|b| b.is_ascii_graphic())
+    {
+        return Err(FluxError::ValidationError {
+            reason: format!(
+                "Affinity key must be 1-{MAX_AFFINITY_KEY_LEN} visible ASCII characters"
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// 一次调用的亲和键和执行实例的记录句柄，随调用上下文传给运行时（克隆后共享同一份数据，重试时后一次尝试覆盖前一次）
+#[derive(Debug, Clone)]
+pub struct AffinityRoute {
+    key: Arc<str>,
+    selection: Arc<StdMutex<Option<InstanceSelection>>>,
+}
+
+impl PartialEq for AffinityRoute {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.selection, &other.selection)
+    }
+}
+
+impl AffinityRoute {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.into(),
+            selection: Arc::default(),
+        }
+    }
+
+    /// 亲和键
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// 记录执行本次调用的实例
+    pub fn record(&self, instance_id: &str, affinity_honored: bool) {
+        *self.selection.lock().unwrap_or_else(|e| e.into_inner()) = Some(InstanceSelection {
+            instance_id: instance_id.to_string(),
+            affinity_honored,
+        });
+    }
+
+    /// 执行本次调用的实例（运行时没有按亲和键选择实例时为 `None`）
+    pub fn selection(&self) -> Option<InstanceSelection> {
+        self.selection
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// 一个实例上各亲和键的请求数
+#[derive(Debug, Clone, Default)]
+pub struct KeyCounts {
+    keys: HashMap<String, u64>,
+    requests: u64,
+}
+
+impl KeyCounts {
+    /// 记录一次带亲和键的请求
+    pub fn record(&mut self, key: &str) {
+        self.requests = self.requests.saturating_add(1);
+        if let Some(count) = self.keys.get_mut(key) {
+            *count = count.saturating_add(1);
+        } else if self.keys.len() < MAX_TRACKED_KEYS {
+            self.keys.insert(key.to_string(), 1);
+        }
+    }
+
+    /// 实例的亲和统计
+    pub fn stats(&self, instance_id: &str) -> InstanceKeyStats {
+        let mut hot_keys: Vec<HotKey> = self
+            .keys
+            .iter()
+            .map(|(key, requests)| HotKey {
+                key: key.clone(),
+                requests: *requests,
+            })
+            .collect();
+        hot_keys.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.key.cmp(&b.key)));
+        hot_keys.truncate(HOT_KEYS);
+        InstanceKeyStats {
+            instance_id: instance_id.to_string(),
+            keys: self.keys.len(),
+            requests: self.requests,
+            hot_keys,
+        }
+    }
+}
+
+/// 实例的亲和统计：映射到该实例的键数、带亲和键的请求数和请求最多的键
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceKeyStats {
+    pub instance_id: String,
+    /// 不同的键数（最多分别计数 1024 个）
+    pub keys: usize,
+    pub requests: u64,
+    pub hot_keys: Vec<HotKey>,
+}
+
+/// 亲和键及其请求数
+#[derive(Debug, Clone, Serialize)]
+pub struct HotKey {
+    pub key: String,
+    pub requests: u64,
+}
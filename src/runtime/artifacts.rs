@@ -0,0 +1,259 @@
+//! 编译产物的动态库句柄与延迟删除
+//!
+//! 库文件按函数名和源代码哈希寻址，写入后不再覆盖。调用开始时解析出共享的库句柄
+//! （[`LibraryLease`]）并在执行期间持有，租约计数即执行中的调用数；编译完成的产物先试加载，成功后才把函数的
+//! “当前产物”切换过去。被淘汰或被新代码替换的产物只是退役：所有句柄释放、
+//! 且距最近一次使用超过宽限期后才卸载并删除库文件，因此更新函数不会影响正在执行的调用。
+
+use super::compiler::CompileKey;
+use anyhow::{Context, Result};
+use libloading::Library;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+
+/// 已加载的动态库，执行期间由调用方持有
+#[derive(Debug)]
+pub struct LoadedLibrary {
+    library: Library,
+    path: PathBuf,
+    /// 持有租约（执行中）的调用数
+    leases: AtomicUsize,
+}
+
+impl LoadedLibrary {
+    /// 加载库文件并检查导出的符号（试加载失败的产物不会成为当前产物）
+    pub fn load(path: &Path) -> Result<Arc<Self>> {
+        let library = unsafe {
+            Library::new(path).with_context(|| format!("Failed to load library: {path:?}"))?
+        };
+        unsafe {
+            library
+                .get::<unsafe extern "C" fn(*mut std::os::raw::c_char)>(b"flux_free_string")
+                .context("Function 'flux_free_string' not found in library")?;
+        }
+        Ok(Arc::new(Self {
+            library,
+            path: path.to_path_buf(),
+            leases: AtomicUsize::new(0),
+        }))
+    }
+
+    pub fn library(&self) -> &Library {
+        &self.library
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn leases(&self) -> usize {
+        self.leases.load(Ordering::Acquire)
+    }
+}
+
+/// 调用期间持有的库句柄，释放时归还租约
+#[derive(Debug)]
+pub struct LibraryLease(Arc<LoadedLibrary>);
+
+impl LibraryLease {
+    fn new(handle: Arc<LoadedLibrary>) -> Self {
+        handle.leases.fetch_add(1, Ordering::AcqRel);
+        Self(handle)
+    }
+}
+
+impl Deref for LibraryLease {
+    type Target = LoadedLibrary;
+
+    fn deref(&self) -> &LoadedLibrary {
+        &self.0
+    }
+}
+
+impl Drop for LibraryLease {
+    fn drop(&mut self) {
+        self.0.leases.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// 退役等待删除的产物
+#[derive(Debug)]
+struct Retired {
+    key: CompileKey,
+    path: PathBuf,
+    handle: Option<Arc<LoadedLibrary>>,
+    /// 宽限期的起点：退役前最近一次被使用的时间
+    last_used: Instant,
+}
+
+impl Retired {
+    fn in_use(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_some_and(|handle| handle.leases() > 0)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Table {
+    loaded: HashMap<CompileKey, Arc<LoadedLibrary>>,
+    current: HashMap<String, CompileKey>,
+    retired: Vec<Retired>,
+}
+
+/// 编译器持有的库句柄表：已加载的句柄、每个函数的当前产物和退役中的产物
+#[derive(Debug, Default)]
+pub struct ArtifactTable {
+    table: StdMutex<Table>,
+}
+
+impl ArtifactTable {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Table> {
+        self.table.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 试加载成功后把函数的当前产物切换到 `key`（同一产物若正在退役则恢复使用）
+    pub fn activate(&self, key: &CompileKey, handle: Arc<LoadedLibrary>) {
+        let mut table = self.lock();
+        table.retired.retain(|retired| &retired.key != key);
+        table.loaded.insert(key.clone(), handle);
+        table.current.insert(key.0.clone(), key.clone());
+    }
+
+    /// 函数的当前产物
+    pub fn current(&self, name: &str) -> Option<CompileKey> {
+        self.lock().current.get(name).cloned()
+    }
+
+    /// 解析编译键对应的库句柄并取得租约，未加载时加载（已退役但尚未删除的产物仍可解析）
+    ///
+    /// 租约在表锁内取得，[`Self::sweep`] 不会卸载刚解析出的句柄。
+    pub fn resolve(&self, key: &CompileKey, path: &Path) -> Result<LibraryLease> {
+        if let Some(lease) = Self::cached(&self.lock(), key).map(LibraryLease::new) {
+            return Ok(lease);
+        }
+        let loaded = LoadedLibrary::load(path)?;
+        let mut table = self.lock();
+        if let Some(retired) = table.retired.iter_mut().find(|r| &r.key == key) {
+            return Ok(LibraryLease::new(
+                retired.handle.get_or_insert(loaded).clone(),
+            ));
+        }
+        Ok(LibraryLease::new(
+            table.loaded.entry(key.clone()).or_insert(loaded).clone(),
+        ))
+    }
+
+    fn cached(table: &Table, key: &CompileKey) -> Option<Arc<LoadedLibrary>> {
+        table.loaded.get(key).cloned().or_else(|| {
+            table
+                .retired
+                .iter()
+                .find(|retired| &retired.key == key)
+                .and_then(|retired| retired.handle.clone())
+        })
+    }
+
+    /// 退役一个产物：不再作为当前产物，等句柄全部释放且过了宽限期后由 [`Self::sweep`] 删除
+    pub fn retire(&self, key: &CompileKey, path: PathBuf, last_used: Option<Instant>) {
+        let mut table = self.lock();
+        let handle = table.loaded.remove(key);
+        if table.current.get(&key.0) == Some(key) {
+            table.current.remove(&key.0);
+        }
+        if table.retired.iter().any(|retired| &retired.key == key) {
+            return;
+        }
+        table.retired.push(Retired {
+            key: key.clone(),
+            path,
+            handle,
+            last_used: last_used.unwrap_or_else(Instant::now),
+        });
+    }
+
+    /// 卸载并删除无人持有且超过宽限期的退役产物，返回 (删除的文件数, 释放字节数)
+    ///
+    /// 删除时持有表锁，同一产物不会在删除过程中被重新激活。
+    pub fn sweep(&self, grace: Duration) -> (usize, u64) {
+        let mut table = self.lock();
+        let (expired, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut table.retired)
+            .into_iter()
+            .partition(|retired| !retired.in_use() && retired.last_used.elapsed() >= grace);
+        table.retired = pending;
+
+        let (mut removed, mut freed) = (0, 0);
+        for retired in expired {
+            // 先释放句柄（卸载动态库）再删除文件
+            drop(retired.handle);
+            let size = fs::metadata(&retired.path).map(|m| m.len()).unwrap_or(0);
+            match fs::remove_file(&retired.path) {
+                Ok(()) => {
+                    removed += 1;
+                    freed += size;
+                }
+                Err(e) => tracing::debug!("Failed to remove library {:?}: {}", retired.path, e),
+            }
+        }
+        (removed, freed)
+    }
+
+    /// 执行中持有该产物库句柄的调用数
+    pub fn holders(&self, key: &CompileKey) -> usize {
+        Self::cached(&self.lock(), key).map_or(0, |handle| handle.leases())
+    }
+
+    /// 退役中尚未删除的库文件
+    pub fn retired_paths(&self) -> Vec<PathBuf> {
+        self.lock()
+            .retired
+            .iter()
+            .map(|retired| retired.path.clone())
+            .collect()
+    }
+
+    /// 退役中的产物数
+    pub fn retired_count(&self) -> usize {
+        self.lock().retired.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> CompileKey {
+        (name.to_string(), "abc".to_string())
+    }
+
+    #[test]
+    fn test_retired_artifacts_wait_for_grace_period() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("old_abc.so");
+        fs::write(&path, b"lib").unwrap();
+        let table = ArtifactTable::default();
+
+        table.retire(&key("old"), path.clone(), None);
+        assert_eq!(table.retired_paths(), vec![path.clone()]);
+        // 刚被使用过：宽限期内不删除
+        assert_eq!(table.sweep(Duration::from_secs(60)), (0, 0));
+        assert!(path.exists());
+
+        assert_eq!(table.sweep(Duration::ZERO), (1, 3));
+        assert!(!path.exists());
+        assert_eq!(table.retired_count(), 0);
+    }
+
+    #[test]
+    fn test_missing_library_fails_to_resolve() {
+        let table = ArtifactTable::default();
+        let path = Path::new("/tmp/flux_missing_artifact.so");
+        assert!(table.resolve(&key("missing"), path).is_err());
+        assert!(table.current("missing").is_none());
+    }
+}
@@ -0,0 +1,205 @@
+//! 编译函数的类型化入口绑定
+//!
+//! 函数声明了参数时，生成的动态库不再把原始 JSON 交给用户代码，而是把输入反序列化为
+//! 由参数列表生成的结构体，以类型化参数调用用户的 `handler`，再把声明的返回类型序列化回 JSON。
+//! 声明的签名与用户代码不一致时由 rustc 在编译期报错。
+
+use crate::functions::{FunctionMetadata, FunctionParameter};
+use anyhow::{Result, bail};
+use std::fmt::Write;
+
+/// 用户代码中被调用的函数名
+pub const HANDLER_NAME: &str = "handler";
+
+/// 将声明的类型名映射为 Rust 类型
+///
+/// 支持 JSON Schema 风格的别名（`integer`、`number`、`string`、`boolean`、`object`、`array`），
+/// `Vec<T>` / `Option<T>` / `[T]` 递归映射，其他类型名按 Rust 类型原样使用。
+pub fn rust_type(type_name: &str) -> String {
+    let type_name: String = type_name.chars().filter(|c| !c.is_whitespace()).collect();
+    if let Some(inner) = generic_argument(&type_name, "Vec").or_else(|| {
+        type_name
+            .strip_prefix('[')
+            .and_then(|t| t.strip_suffix(']'))
+    }) {
+        return format!("Vec<{}>", rust_type(inner));
+    }
+    if let Some(inner) = generic_argument(&type_name, "Option") {
+        return format!("Option<{}>", rust_type(inner));
+    }
+    match type_name.to_ascii_lowercase().as_str() {
+        "" | "any" | "json" | "value" | "object" => "serde_json::Value".to_string(),
+        "int" | "integer" => "i64".to_string(),
+        "float" | "double" | "number" => "f64".to_string(),
+        "boolean" => "bool".to_string(),
+        "string" | "&str" | "str" => "String".to_string(),
+        "array" => "Vec<serde_json::Value>".to_string(),
+        _ => type_name,
+    }
+}
+
+/// 取出 `Name<T>` 中的 `T`
+fn generic_argument<'a>(type_name: &'a str, name: &str) -> Option<&'a str> {
+    type_name
+        .strip_prefix(name)?
+        .strip_prefix('<')?
+        .strip_suffix('>')
+}
+
+/// 类型化绑定的签名，参与编译键的计算（签名变化时重新编译）
+pub fn signature(function: &FunctionMetadata) -> String {
+    let params: Vec<String> = function
+        .parameters
+        .iter()
+        .map(|param| {
+            format!(
+                "{}: {}{}{}",
+                param.name,
+                rust_type(&param.param_type),
+                if param.required { "" } else { "?" },
+                param
+                    .default_value
+                    .as_deref()
+                    .map(|value| format!(" = {value}"))
+                    .unwrap_or_default()
+            )
+        })
+        .collect();
+    format!(
+        "fn {HANDLER_NAME}({}) -> anyhow::Result<{}>",
+        params.join(", "),
+        rust_type(&function.return_type)
+    )
+}
+
+/// 生成类型化的 `execute_user_function`：反序列化参数结构体、调用 `handler`、序列化返回值
+///
+/// 结构体字段按位置命名并通过 `serde(rename)` 对应参数名，参数名不必是合法的 Rust 标识符；
+/// 非必填参数缺省时使用 `default_value`（非 JSON 的默认值视为字符串），没有默认值时使用类型的 `Default`。
+pub fn typed_shim(function: &FunctionMetadata) -> Result<String> {
+    let mut names = std::collections::HashSet::new();
+    for param in &function.parameters {
+        if param.name.is_empty() {
+            bail!("Parameter names must not be empty");
+        }
+        if !names.insert(param.name.as_str()) {
+            bail!("Duplicate parameter '{}'", param.name);
+        }
+    }
+
+    let mut shim = String::new();
+    shim.push_str("#[derive(serde::Deserialize)]\nstruct FluxArgs {\n");
+    for (index, param) in function.parameters.iter().enumerate() {
+        writeln!(shim, "    #[serde(rename = {:?})]", param.name)?;
+        if !param.required {
+            match &param.default_value {
+                Some(_) => writeln!(shim, "    #[serde(default = \"flux_default_{index}\")]")?,
+                None => shim.push_str("    #[serde(default)]\n"),
+            }
+        }
+        writeln!(shim, "    p{index}: {},", rust_type(&param.param_type))?;
+    }
+    shim.push_str("}\n");
+
+    for (index, param) in function.parameters.iter().enumerate() {
+        if let Some(value) = default_json(param).filter(|_| !param.required) {
+            writeln!(
+                shim,
+                "\nfn flux_default_{index}() -> {ty} {{\n    serde_json::from_str({value:?}).expect(\"invalid default for parameter {name}\")\n}}",
+                ty = rust_type(&param.param_type),
+                name = param.name.escape_default(),
+            )?;
+        }
+    }
+
+    let arguments: Vec<String> = (0..function.parameters.len())
+        .map(|index| format!("args.p{index}"))
+        .collect();
+    write!(
+        shim,
+        r#"
+// 用户函数执行入口（类型化绑定）
+fn execute_user_function(input: serde_json::Value, _context: serde_json::Value) -> serde_json::Value {{
+    let args: FluxArgs = match serde_json::from_value(input) {{
+        Ok(args) => args,
+        Err(e) => return serde_json::json!({{ "error": format!("Invalid input: {{e}}") }}),
+    }};
+    let result: anyhow::Result<{return_type}> = {HANDLER_NAME}({arguments});
+    match result {{
+        Ok(value) => serde_json::to_value(&value)
+            .unwrap_or_else(|e| serde_json::json!({{ "error": format!("Failed to serialize result: {{e}}") }})),
+        Err(e) => serde_json::json!({{ "error": format!("{{e:#}}") }}),
+    }}
+}}
+"#,
+        return_type = rust_type(&function.return_type),
+        arguments = arguments.join(", "),
+    )?;
+    Ok(shim)
+}
+
+/// 参数默认值的 JSON 文本（非 JSON 的默认值视为字符串）
+fn default_json(param: &FunctionParameter) -> Option<String> {
+    let value = param.default_value.as_deref()?;
+    let json = serde_json::from_str::<serde_json::Value>(value)
+        .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+    Some(json.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(
+        name: &str,
+        param_type: &str,
+        required: bool,
+        default: Option<&str>,
+    ) -> FunctionParameter {
+        FunctionParameter {
+            name: name.to_string(),
+            param_type: param_type.to_string(),
+            description: None,
+            required,
+            default_value: default.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_rust_type_mapping() {
+        assert_eq!(rust_type("integer"), "i64");
+        assert_eq!(rust_type("i32"), "i32");
+        assert_eq!(rust_type("Vec<string>"), "Vec<String>");
+        assert_eq!(rust_type("Option< number >"), "Option<f64>");
+        assert_eq!(rust_type("[bool]"), "Vec<bool>");
+        assert_eq!(rust_type("serde_json::Value"), "serde_json::Value");
+        assert_eq!(rust_type("object"), "serde_json::Value");
+    }
+
+    #[test]
+    fn test_typed_shim_binds_declared_parameters() {
+        let mut function = FunctionMetadata::new("greet".to_string(), String::new());
+        function.parameters = vec![
+            param("user-id", "integer", true, None),
+            param("greeting", "string", false, Some("hello")),
+            param("tags", "Vec<String>", false, None),
+        ];
+        function.return_type = "f64".to_string();
+
+        let shim = typed_shim(&function).unwrap();
+        assert!(shim.contains("#[serde(rename = \"user-id\")]\n    p0: i64,"));
+        assert!(shim.contains("#[serde(default = \"flux_default_1\")]\n    p1: String,"));
+        assert!(shim.contains("#[serde(default)]\n    p2: Vec<String>,"));
+        assert!(shim.contains(r#"serde_json::from_str("\"hello\"")"#));
+        assert!(shim.contains("anyhow::Result<f64> = handler(args.p0, args.p1, args.p2)"));
+        assert_eq!(
+            signature(&function),
+            "fn handler(user-id: i64, greeting: String? = hello, tags: Vec<String>?) -> anyhow::Result<f64>"
+        );
+
+        function
+            .parameters
+            .push(param("tags", "string", true, None));
+        assert!(typed_shim(&function).is_err());
+    }
+}
@@ -1,10 +1,81 @@
-use crate::functions::{FunctionMetadata, Result};
-use lru::LruCache;
-use std::num::NonZeroUsize;
-use std::sync::Arc;
+use crate::functions::{FluxError, FunctionMetadata, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
+/// 按代价挑选驱逐对象时比较的最先驱逐条目数
+const EVICTION_WINDOW: usize = 8;
+/// 统计中列出的最大条目数
+const LARGEST_ENTRIES: usize = 10;
+
+/// 缓存驱逐策略：条目数达到容量上限时按策略选出被驱逐的条目
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheStrategy {
+    /// 最近最少使用
+    #[default]
+    Lru,
+    /// 先进先出
+    Fifo,
+    /// 最不常用（访问次数相同时驱逐最久未用的）
+    Lfu,
+    /// 最先过期的优先驱逐
+    Ttl,
+}
+
+/// 函数缓存配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FunctionCacheConfig {
+    /// 内存中的最大条目数
+    pub capacity: usize,
+    /// 内存中条目的总字节数上限
+    pub max_bytes: usize,
+    /// 驱逐策略
+    pub strategy: CacheStrategy,
+    /// 条目最大存活时间（秒），0 表示不过期
+    pub max_age_secs: u64,
+    /// 后台清理过期条目的间隔（秒）
+    pub sweep_interval_secs: u64,
+    /// 从内存驱逐的条目写入 `<cache_dir>/registry/`，未命中时先从磁盘重新加载
+    pub spillover: bool,
+    pub cache_dir: PathBuf,
+}
+
+impl Default for FunctionCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 100,
+            max_bytes: 50 * 1024 * 1024,
+            strategy: CacheStrategy::Lru,
+            max_age_secs: 3600,
+            sweep_interval_secs: 60,
+            spillover: false,
+            cache_dir: PathBuf::from("./flux_cache"),
+        }
+    }
+}
+
+/// 运行时可调整的驱逐策略和存活时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachePolicy {
+    pub strategy: CacheStrategy,
+    /// 新条目的存活时间（秒），0 表示不过期
+    pub ttl_secs: u64,
+}
+
+/// `PUT /cache/config` 请求体，未指定的字段保持不变
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CachePolicyUpdate {
+    pub strategy: Option<CacheStrategy>,
+    pub ttl_secs: Option<u64>,
+}
+
 /// 缓存的函数执行结果
 #[derive(Debug, Clone)]
 pub struct CachedFunction {
@@ -20,6 +91,11 @@ pub struct CachedFunction {
     pub access_count: u64,
     /// 内存使用估算（字节）
     pub memory_usage: usize,
+    /// 过期时间（`None` 表示不过期）
+    pub expires_at: Option<Instant>,
+    /// 放入和最近访问时的操作序号，用于按策略排序
+    inserted_seq: u64,
+    accessed_seq: u64,
 }
 
 /// 编译后的代码（简化版本）
@@ -48,136 +124,868 @@ pub struct CacheStats {
     pub max_memory: usize,
     /// 缓存驱逐次数
     pub evictions: u64,
+    /// 因过期被移除的条目数
+    pub expirations: u64,
+    /// 已固定的函数数
+    pub pinned_functions: usize,
+    /// 属于已固定函数、不会被驱逐的条目数
+    pub pinned_entries: usize,
+    /// 可被驱逐的条目数
+    pub evictable_entries: usize,
+    /// 最近一次预热的耗时（毫秒）
+    pub last_warmup_ms: Option<u64>,
+    /// 是否启用磁盘溢出
+    pub spillover_enabled: bool,
+    /// 溢出到磁盘的条目数
+    pub spilled_entries: usize,
+    /// 溢出到磁盘的条目占用（字节）
+    pub disk_usage: usize,
+    /// 从磁盘重新加载的命中次数
+    pub spillover_hits: u64,
+    /// 写入磁盘的条目数
+    pub spillover_writes: u64,
+    /// 因内容哈希不符而丢弃的磁盘条目数
+    pub spillover_corrupted: u64,
+    /// 内存和磁盘中最大的条目（最多 10 个）
+    pub largest_entries: Vec<CacheEntrySize>,
+}
+
+/// 缓存条目的大小
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CacheEntrySize {
+    pub key: String,
+    pub bytes: usize,
+    /// 条目是否在磁盘上
+    pub on_disk: bool,
+}
+
+/// 单个缓存条目的信息
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CacheEntryInfo {
+    /// 缓存键（`<函数名>@<版本>`）
+    pub key: String,
+    pub function: String,
+    pub version: String,
+    pub pinned: bool,
+    pub access_count: u64,
+    pub memory_usage: usize,
+    pub created_at: DateTime<Utc>,
+    pub last_accessed_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// 驱逐顺序的排序值，越小越先被驱逐
+type EvictionRank = (u64, u64);
+
+/// 内存中的缓存条目，按驱逐策略和过期时间分别建立有序索引，放入、访问和移除均为 O(log n)
+#[derive(Debug)]
+struct EntryStore {
+    entries: HashMap<String, CachedFunction>,
+    /// 驱逐顺序索引
+    order: BTreeSet<(EvictionRank, String)>,
+    /// 过期时间索引，清理时按时间顺序取出过期条目
+    expiry: BTreeSet<(Instant, String)>,
+    capacity: usize,
+    strategy: CacheStrategy,
+    /// 新条目的存活时间，零表示不过期
+    ttl: Duration,
+    /// 单调递增的操作序号
+    clock: u64,
+    /// TTL 策略排序值的时间基准
+    epoch: Instant,
+}
+
+impl EntryStore {
+    fn new(capacity: usize, strategy: CacheStrategy, ttl: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeSet::new(),
+            expiry: BTreeSet::new(),
+            capacity: capacity.max(1),
+            strategy,
+            ttl,
+            clock: 0,
+            epoch: Instant::now(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    fn get(&self, key: &str) -> Option<&CachedFunction> {
+        self.entries.get(key)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&String, &CachedFunction)> {
+        self.entries.iter()
+    }
+
+    fn policy(&self) -> CachePolicy {
+        CachePolicy {
+            strategy: self.strategy,
+            ttl_secs: self.ttl.as_secs(),
+        }
+    }
+
+    /// 从 `from` 开始按当前存活时间计算的过期时间
+    fn deadline(&self, from: Instant) -> Option<Instant> {
+        (!self.ttl.is_zero()).then(|| from + self.ttl)
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// 放入条目（过期时间由调用方设置），返回被替换的旧条目
+    fn insert(&mut self, key: String, mut cached: CachedFunction) -> Option<CachedFunction> {
+        let old = self.remove(&key);
+        let seq = self.tick();
+        cached.inserted_seq = seq;
+        cached.accessed_seq = seq;
+        self.order.insert((
+            eviction_rank(self.strategy, self.epoch, &cached),
+            key.clone(),
+        ));
+        if let Some(expires_at) = cached.expires_at {
+            self.expiry.insert((expires_at, key.clone()));
+        }
+        self.entries.insert(key, cached);
+        old
+    }
+
+    /// 记录一次访问并更新驱逐顺序
+    fn touch(&mut self, key: &str) -> Option<&CachedFunction> {
+        let seq = self.tick();
+        let cached = self.entries.get_mut(key)?;
+        self.order.remove(&(
+            eviction_rank(self.strategy, self.epoch, cached),
+            key.to_string(),
+        ));
+        cached.last_accessed = Instant::now();
+        cached.access_count += 1;
+        cached.accessed_seq = seq;
+        self.order.insert((
+            eviction_rank(self.strategy, self.epoch, cached),
+            key.to_string(),
+        ));
+        Some(cached)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<CachedFunction> {
+        let cached = self.entries.remove(key)?;
+        self.order.remove(&(
+            eviction_rank(self.strategy, self.epoch, &cached),
+            key.to_string(),
+        ));
+        if let Some(expires_at) = cached.expires_at {
+            self.expiry.remove(&(expires_at, key.to_string()));
+        }
+        Some(cached)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.expiry.clear();
+    }
+
+    /// 切换驱逐策略，按已记录的访问信息重建驱逐顺序
+    fn set_strategy(&mut self, strategy: CacheStrategy) {
+        self.strategy = strategy;
+        self.order = self
+            .entries
+            .iter()
+            .map(|(key, cached)| (eviction_rank(strategy, self.epoch, cached), key.clone()))
+            .collect();
+    }
+
+    /// 按驱逐顺序排列的未固定条目
+    fn eviction_candidates<'a>(
+        &'a self,
+        pinned: &'a BTreeSet<String>,
+    ) -> impl Iterator<Item = (&'a String, &'a CachedFunction)> + 'a {
+        self.order
+            .iter()
+            .filter_map(|(_, key)| self.entries.get_key_value(key))
+            .filter(|(_, cached)| !pinned.contains(&cached.metadata.name))
+    }
+
+    /// 已过期且未固定的条目键
+    fn expired_keys(&self, now: Instant, pinned: &BTreeSet<String>) -> Vec<String> {
+        self.expiry
+            .iter()
+            .take_while(|(expires_at, _)| *expires_at <= now)
+            .filter(|(_, key)| {
+                self.entries
+                    .get(key)
+                    .is_some_and(|cached| !pinned.contains(&cached.metadata.name))
+            })
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+}
+
+/// 条目在某个策略下的驱逐排序值
+fn eviction_rank(strategy: CacheStrategy, epoch: Instant, cached: &CachedFunction) -> EvictionRank {
+    match strategy {
+        CacheStrategy::Lru => (cached.accessed_seq, 0),
+        CacheStrategy::Fifo => (cached.inserted_seq, 0),
+        CacheStrategy::Lfu => (cached.access_count, cached.accessed_seq),
+        CacheStrategy::Ttl => {
+            let expires = cached.expires_at.map_or(u64::MAX, |expires_at| {
+                expires_at.saturating_duration_since(epoch).as_millis() as u64
+            });
+            (expires, cached.inserted_seq)
+        }
+    }
+}
+
+fn is_expired(cached: &CachedFunction, now: Instant) -> bool {
+    cached
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
 }
 
 /// 函数缓存管理器
 #[derive(Debug)]
 pub struct FunctionCache {
-    /// LRU 缓存
-    cache: Arc<RwLock<LruCache<String, CachedFunction>>>,
+    /// 内存中的条目
+    cache: RwLock<EntryStore>,
     /// 缓存统计
     stats: Arc<RwLock<CacheStats>>,
     /// 最大内存使用限制（字节）
     max_memory: usize,
-    /// 缓存条目最大存活时间
-    max_age: Duration,
+    /// 已固定的函数名，其所有版本的条目不会被驱逐或过期
+    pinned: StdMutex<BTreeSet<String>>,
+    /// 可固定的函数数上限（小于缓存容量，保证总有可驱逐的条目）
+    max_pinned: usize,
+    /// 磁盘溢出（可选）
+    spillover: Option<Spillover>,
+    /// 后台清理过期条目的间隔
+    sweep_interval: Duration,
+}
+
+/// 从内存驱逐的条目在磁盘上的副本
+#[derive(Debug)]
+struct Spillover {
+    dir: PathBuf,
+    /// 缓存键 → 磁盘上的文件大小
+    entries: StdMutex<BTreeMap<String, usize>>,
+}
+
+/// 溢出文件内容：元数据 JSON 及其哈希，用于发现损坏的文件
+#[derive(Serialize, Deserialize)]
+struct SpillFile {
+    key: String,
+    hash: String,
+    metadata: String,
+    /// 过期时间（Unix 毫秒），重新加载时沿用
+    #[serde(default)]
+    expires_at: Option<u64>,
+}
+
+/// 从磁盘读出的条目
+struct SpilledEntry {
+    metadata: FunctionMetadata,
+    expires_at: Option<u64>,
+}
+
+impl Spillover {
+    /// 打开溢出目录，按已有文件重建索引（无法解析的文件直接删除）
+    fn open(dir: PathBuf) -> Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let mut entries = BTreeMap::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            match std::fs::read(&path).ok().and_then(|content| {
+                Some((
+                    serde_json::from_slice::<SpillFile>(&content).ok()?,
+                    content.len(),
+                ))
+            }) {
+                Some((file, bytes)) if path == spill_path(&dir, &file.key) => {
+                    entries.insert(file.key, bytes);
+                }
+                _ => {
+                    tracing::warn!("Removing unreadable cache spillover file: {:?}", path);
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+        }
+        if !entries.is_empty() {
+            tracing::info!("Found {} spilled cache entries in {:?}", entries.len(), dir);
+        }
+        Ok(Self {
+            dir,
+            entries: StdMutex::new(entries),
+        })
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, usize>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        spill_path(&self.dir, key)
+    }
+
+    /// 写入驱逐的条目
+    async fn write(&self, key: &str, cached: &CachedFunction) -> Result<()> {
+        let metadata = serde_json::to_string(&cached.metadata)?;
+        let file = SpillFile {
+            key: key.to_string(),
+            hash: format!("{:x}", md5::compute(&metadata)),
+            metadata,
+            expires_at: cached.expires_at.map(unix_millis),
+        };
+        let content = serde_json::to_vec(&file)?;
+        tokio::fs::write(self.path(key), &content).await?;
+        self.entries().insert(key.to_string(), content.len());
+        Ok(())
+    }
+
+    /// 读出并删除条目；文件损坏时返回错误（文件同样删除）
+    async fn take(&self, key: &str) -> Option<Result<SpilledEntry>> {
+        self.entries().remove(key)?;
+        let path = self.path(key);
+        let content = tokio::fs::read(&path).await;
+        let _ = tokio::fs::remove_file(&path).await;
+        let read = || -> Result<SpilledEntry> {
+            let file: SpillFile = serde_json::from_slice(&content?)?;
+            if file.key != key || file.hash != format!("{:x}", md5::compute(&file.metadata)) {
+                return Err(FluxError::CacheError(format!(
+                    "Content hash mismatch for spilled cache entry {key}"
+                )));
+            }
+            Ok(SpilledEntry {
+                metadata: serde_json::from_str(&file.metadata)?,
+                expires_at: file.expires_at,
+            })
+        };
+        Some(read())
+    }
+
+    /// 删除条目，返回是否存在
+    async fn remove(&self, key: &str) -> bool {
+        if self.entries().remove(key).is_none() {
+            return false;
+        }
+        let _ = tokio::fs::remove_file(self.path(key)).await;
+        true
+    }
+
+    /// 删除满足条件的条目，返回删除的条目数
+    async fn remove_matching(&self, matches: impl Fn(&str) -> bool) -> usize {
+        let keys: Vec<String> = self
+            .entries()
+            .keys()
+            .filter(|key| matches(key))
+            .cloned()
+            .collect();
+        for key in &keys {
+            self.remove(key).await;
+        }
+        keys.len()
+    }
+}
+
+/// 时刻对应的 Unix 毫秒数
+fn unix_millis(at: Instant) -> u64 {
+    let now = Instant::now();
+    let system = if at >= now {
+        SystemTime::now() + (at - now)
+    } else {
+        SystemTime::now() - (now - at)
+    };
+    system
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+/// 溢出文件路径：缓存键可能包含 `/` 等字符，文件名使用其哈希
+fn spill_path(dir: &Path, key: &str) -> PathBuf {
+    dir.join(format!("{:x}.json", md5::compute(key)))
 }
 
 impl FunctionCache {
-    /// 创建新的函数缓存
+    /// 创建新的函数缓存（LRU 策略）
     pub fn new(capacity: usize, max_memory_mb: usize, max_age_seconds: u64) -> Self {
         let max_memory = max_memory_mb * 1024 * 1024; // 转换为字节
         let max_age = Duration::from_secs(max_age_seconds);
 
         Self {
-            cache: Arc::new(RwLock::new(LruCache::new(
-                NonZeroUsize::new(capacity).unwrap(),
-            ))),
+            cache: RwLock::new(EntryStore::new(capacity, CacheStrategy::Lru, max_age)),
             stats: Arc::new(RwLock::new(CacheStats {
                 max_memory,
                 ..Default::default()
             })),
             max_memory,
-            max_age,
+            pinned: StdMutex::default(),
+            max_pinned: (capacity / 2).max(1),
+            spillover: None,
+            sweep_interval: Duration::from_secs(60),
         }
     }
 
-    /// 获取缓存的函数
-    pub async fn get(&self, function_name: &str) -> Option<CachedFunction> {
+    /// 按配置创建缓存，启用溢出时打开 `<cache_dir>/registry/`
+    pub fn from_config(config: &FunctionCacheConfig) -> Result<Self> {
+        let cache = Self::new(config.capacity, 0, config.max_age_secs)
+            .with_max_bytes(config.max_bytes)
+            .with_strategy(config.strategy)
+            .with_sweep_interval(Duration::from_secs(config.sweep_interval_secs.max(1)));
+        if config.spillover {
+            cache.with_spillover(config.cache_dir.join("registry"))
+        } else {
+            Ok(cache)
+        }
+    }
+
+    /// 设置内存中条目的总字节数上限
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_memory = max_bytes;
+        self.stats = Arc::new(RwLock::new(CacheStats {
+            max_memory: max_bytes,
+            ..Default::default()
+        }));
+        self
+    }
+
+    /// 设置驱逐策略
+    pub fn with_strategy(mut self, strategy: CacheStrategy) -> Self {
+        self.cache.get_mut().set_strategy(strategy);
+        self
+    }
+
+    /// 设置新条目的存活时间（零表示不过期）
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.cache.get_mut().ttl = ttl;
+        self
+    }
+
+    /// 设置后台清理过期条目的间隔
+    pub fn with_sweep_interval(mut self, interval: Duration) -> Self {
+        self.sweep_interval = interval;
+        self
+    }
+
+    /// 启用磁盘溢出：驱逐的条目写入 `dir`，未命中时先从磁盘重新加载
+    pub fn with_spillover(mut self, dir: impl Into<PathBuf>) -> Result<Self> {
+        self.spillover = Some(Spillover::open(dir.into())?);
+        Ok(self)
+    }
+
+    /// 设置可固定的函数数上限
+    pub fn with_max_pinned(mut self, max_pinned: usize) -> Self {
+        self.max_pinned = max_pinned;
+        self
+    }
+
+    /// 当前的驱逐策略和存活时间
+    pub async fn policy(&self) -> CachePolicy {
+        self.cache.read().await.policy()
+    }
+
+    /// 调整驱逐策略和存活时间，作用于之后的缓存操作（已有条目保留原来的过期时间）
+    pub async fn configure(&self, update: CachePolicyUpdate) -> CachePolicy {
         let mut cache = self.cache.write().await;
-        let mut stats = self.stats.write().await;
+        if let Some(strategy) = update.strategy
+            && strategy != cache.strategy
+        {
+            cache.set_strategy(strategy);
+        }
+        if let Some(ttl_secs) = update.ttl_secs {
+            cache.ttl = Duration::from_secs(ttl_secs);
+        }
+        let policy = cache.policy();
+        tracing::info!(
+            "Function cache policy set to {:?} (ttl: {}s)",
+            policy.strategy,
+            policy.ttl_secs
+        );
+        policy
+    }
 
-        if let Some(cached_function) = cache.get_mut(function_name) {
-            // 检查是否过期
-            if cached_function.created_at.elapsed() > self.max_age {
-                cache.pop(function_name);
-                stats.misses += 1;
-                stats.evictions += 1;
-                tracing::debug!("Cache entry expired for function: {}", function_name);
-                return None;
+    /// 启动后台任务，按间隔清理过期条目（缓存释放后任务自动退出）
+    pub fn spawn_sweeper(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let cache = Arc::downgrade(self);
+        let interval = self.sweep_interval.max(Duration::from_millis(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(cache) = cache.upgrade() else {
+                    break;
+                };
+                cache.cleanup_expired().await;
             }
+        })
+    }
 
-            // 更新访问信息
-            cached_function.last_accessed = Instant::now();
-            cached_function.access_count += 1;
-            stats.hits += 1;
-
-            tracing::debug!(
-                "Cache hit for function: {} (accessed {} times)",
-                function_name,
-                cached_function.access_count
-            );
+    /// 固定函数：其所有版本的缓存条目不会被驱逐或过期，返回之前是否未固定
+    pub fn pin(&self, function_name: &str) -> Result<bool> {
+        let mut pinned = self.pinned.lock().unwrap_or_else(|e| e.into_inner());
+        if pinned.contains(function_name) {
+            return Ok(false);
+        }
+        if pinned.len() >= self.max_pinned {
+            return Err(FluxError::PinLimitExceeded {
+                limit: self.max_pinned,
+            });
+        }
+        pinned.insert(function_name.to_string());
+        tracing::info!("Pinned function in cache: {}", function_name);
+        Ok(true)
+    }
 
-            Some(cached_function.clone())
-        } else {
-            stats.misses += 1;
-            tracing::debug!("Cache miss for function: {}", function_name);
-            None
+    /// 取消固定，返回之前是否已固定
+    pub fn unpin(&self, function_name: &str) -> bool {
+        let removed = self
+            .pinned
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(function_name);
+        if removed {
+            tracing::info!("Unpinned function in cache: {}", function_name);
         }
+        removed
     }
 
-    /// 缓存函数
-    pub async fn put(&self, function_name: String, function: FunctionMetadata) -> Result<()> {
-        // 编译函数代码（简化版本）
-        let compiled_code = self.compile_function(&function).await?;
-        let memory_usage = self.estimate_memory_usage(&function, &compiled_code);
+    /// 函数是否已固定
+    pub fn is_pinned(&self, function_name: &str) -> bool {
+        self.pinned_names().contains(function_name)
+    }
 
-        let cached_function = CachedFunction {
-            metadata: function,
-            compiled_code,
-            created_at: Instant::now(),
-            last_accessed: Instant::now(),
-            access_count: 0,
-            memory_usage,
+    /// 已固定的函数名
+    pub fn pinned_functions(&self) -> Vec<String> {
+        self.pinned_names().into_iter().collect()
+    }
+
+    fn pinned_names(&self) -> BTreeSet<String> {
+        self.pinned
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 记录一次预热的耗时
+    pub async fn record_warmup(&self, duration: Duration) {
+        self.stats.write().await.last_warmup_ms = Some(duration.as_millis() as u64);
+    }
+
+    /// 所有缓存条目的信息（按最近访问排序）
+    pub async fn entries(&self) -> Vec<CacheEntryInfo> {
+        let pinned = self.pinned_names();
+        let cache = self.cache.read().await;
+        let now = Utc::now();
+        let at = |instant: Instant| {
+            now - chrono::Duration::from_std(instant.elapsed()).unwrap_or_default()
         };
+        let mut entries: Vec<_> = cache.iter().collect();
+        entries.sort_by_key(|(_, cached)| std::cmp::Reverse(cached.accessed_seq));
+        entries
+            .into_iter()
+            .map(|(key, cached)| CacheEntryInfo {
+                key: key.clone(),
+                function: cached.metadata.name.clone(),
+                version: cached.metadata.version.clone(),
+                pinned: pinned.contains(&cached.metadata.name),
+                access_count: cached.access_count,
+                memory_usage: cached.memory_usage,
+                created_at: at(cached.created_at),
+                last_accessed_at: at(cached.last_accessed),
+                expires_at: cached.expires_at.map(|expires_at| {
+                    now + chrono::Duration::from_std(
+                        expires_at.saturating_duration_since(Instant::now()),
+                    )
+                    .unwrap_or_default()
+                }),
+            })
+            .collect()
+    }
 
-        let mut cache = self.cache.write().await;
-        let mut stats = self.stats.write().await;
+    /// 获取缓存的函数，内存未命中时从磁盘溢出中重新加载
+    pub async fn get(&self, function_name: &str) -> Option<CachedFunction> {
+        {
+            let mut cache = self.cache.write().await;
+            let mut stats = self.stats.write().await;
 
-        // 检查内存限制
-        if stats.memory_usage + memory_usage > self.max_memory {
-            // 需要清理缓存
-            self.evict_to_fit(memory_usage, &mut cache, &mut stats)
-                .await;
+            if let Some(cached_function) = cache.get(function_name) {
+                // 检查是否过期（已固定的条目不过期）
+                if is_expired(cached_function, Instant::now())
+                    && !self.is_pinned(&cached_function.metadata.name)
+                {
+                    if let Some(expired) = cache.remove(function_name) {
+                        stats.memory_usage -= expired.memory_usage;
+                    }
+                    stats.size = cache.len();
+                    stats.misses += 1;
+                    stats.expirations += 1;
+                    tracing::debug!("Cache entry expired for function: {}", function_name);
+                    return None;
+                }
+
+                // 更新访问信息和驱逐顺序
+                let cached_function = cache.touch(function_name)?;
+                stats.hits += 1;
+
+                tracing::debug!(
+                    "Cache hit for function: {} (accessed {} times)",
+                    function_name,
+                    cached_function.access_count
+                );
+
+                return Some(cached_function.clone());
+            }
         }
 
-        // 添加到缓存
-        if let Some(old_function) = cache.put(function_name.clone(), cached_function) {
-            // 更新内存使用统计
-            stats.memory_usage = stats.memory_usage - old_function.memory_usage + memory_usage;
-        } else {
-            stats.memory_usage += memory_usage;
+        if let Some(cached_function) = self.reload_spilled(function_name).await {
+            return Some(cached_function);
         }
+        self.stats.write().await.misses += 1;
+        tracing::debug!("Cache miss for function: {}", function_name);
+        None
+    }
 
-        stats.size = cache.len();
+    /// 从磁盘重新加载溢出的条目并放回内存（已过期的条目直接丢弃）
+    async fn reload_spilled(&self, key: &str) -> Option<CachedFunction> {
+        let spilled = match self.spillover.as_ref()?.take(key).await? {
+            Ok(spilled) => spilled,
+            Err(e) => {
+                self.stats.write().await.spillover_corrupted += 1;
+                tracing::warn!("Discarded spilled cache entry {}: {}", key, e);
+                return None;
+            }
+        };
+        let now = Instant::now();
+        let expires_at = match spilled.expires_at {
+            Some(expires_at) => {
+                let remaining = expires_at.saturating_sub(unix_millis(now));
+                if remaining == 0 && !self.is_pinned(&spilled.metadata.name) {
+                    self.stats.write().await.expirations += 1;
+                    tracing::debug!("Discarded expired spilled cache entry: {}", key);
+                    return None;
+                }
+                Some(now + Duration::from_millis(remaining))
+            }
+            None => self.cache.read().await.deadline(now),
+        };
+        let mut cached_function = match self.build_entry(spilled.metadata).await {
+            Ok(cached_function) => cached_function,
+            Err(e) => {
+                tracing::warn!("Failed to reload spilled cache entry {}: {}", key, e);
+                return None;
+            }
+        };
+        cached_function.access_count = 1;
+        cached_function.expires_at = expires_at;
+        self.insert(key.to_string(), cached_function.clone()).await;
+
+        let mut stats = self.stats.write().await;
+        stats.hits += 1;
+        stats.spillover_hits += 1;
+        tracing::debug!("Reloaded spilled cache entry: {}", key);
+        Some(cached_function)
+    }
+
+    /// 查看缓存条目，不更新访问信息和命中统计（不返回已过期的条目）
+    pub async fn peek(&self, key: &str) -> Option<CachedFunction> {
+        self.cache
+            .read()
+            .await
+            .get(key)
+            .filter(|cached| {
+                !is_expired(cached, Instant::now()) || self.is_pinned(&cached.metadata.name)
+            })
+            .cloned()
+    }
+
+    /// 缓存函数，存活时间按当前配置计算
+    pub async fn put(&self, function_name: String, function: FunctionMetadata) -> Result<()> {
+        let mut cached_function = self.build_entry(function).await?;
+        cached_function.expires_at = self.cache.read().await.deadline(cached_function.created_at);
+        let memory_usage = cached_function.memory_usage;
+        let total = self.insert(function_name.clone(), cached_function).await;
 
         tracing::info!(
             "Cached function: {} (memory usage: {} bytes, total: {} bytes)",
             function_name,
             memory_usage,
-            stats.memory_usage
+            total
         );
 
         Ok(())
     }
 
+    /// 编译函数并计算条目大小（执行用不到的函数文档不放入缓存）
+    async fn build_entry(&self, mut function: FunctionMetadata) -> Result<CachedFunction> {
+        function.documentation = None;
+        // 编译函数代码（简化版本）
+        let compiled_code = self.compile_function(&function).await?;
+        let memory_usage = entry_size(&function, &compiled_code);
+
+        Ok(CachedFunction {
+            metadata: function,
+            compiled_code,
+            created_at: Instant::now(),
+            last_accessed: Instant::now(),
+            access_count: 0,
+            memory_usage,
+            expires_at: None,
+            inserted_seq: 0,
+            accessed_seq: 0,
+        })
+    }
+
+    /// 放入内存（先移除过期条目，再按需驱逐其他条目，启用溢出时驱逐的条目写入磁盘），
+    /// 返回内存使用总量
+    async fn insert(&self, key: String, cached_function: CachedFunction) -> usize {
+        let memory_usage = cached_function.memory_usage;
+        let pinned = self.pinned_names();
+        let (evicted, total) = {
+            let mut cache = self.cache.write().await;
+            let mut stats = self.stats.write().await;
+            purge_expired(&mut cache, &mut stats, &pinned);
+
+            // 检查内存限制
+            let mut evicted = Vec::new();
+            if stats.memory_usage + memory_usage > self.max_memory {
+                // 需要清理缓存
+                evicted = self.evict_to_fit(memory_usage, &mut cache, &mut stats, &pinned);
+            }
+            // 条目数已满时按策略驱逐一个未固定的条目
+            if !cache.contains(&key)
+                && cache.len() >= cache.capacity
+                && let Some((evicted_key, evicted_function)) = pop_victim(&mut cache, &pinned, 1)
+            {
+                stats.memory_usage -= evicted_function.memory_usage;
+                stats.evictions += 1;
+                tracing::debug!("Evicted function from cache: {}", evicted_key);
+                evicted.push((evicted_key, evicted_function));
+            }
+
+            // 添加到缓存
+            if let Some(old_function) = cache.insert(key.clone(), cached_function) {
+                // 更新内存使用统计
+                stats.memory_usage = stats.memory_usage - old_function.memory_usage + memory_usage;
+            } else {
+                stats.memory_usage += memory_usage;
+            }
+
+            stats.size = cache.len();
+            (evicted, stats.memory_usage)
+        };
+
+        if let Some(spillover) = &self.spillover {
+            // 内存中的新条目取代磁盘上的旧副本
+            spillover.remove(&key).await;
+            for (evicted_key, evicted_function) in evicted {
+                match spillover.write(&evicted_key, &evicted_function).await {
+                    Ok(()) => self.stats.write().await.spillover_writes += 1,
+                    Err(e) => {
+                        tracing::warn!("Failed to spill cache entry {} to disk: {}", evicted_key, e)
+                    }
+                }
+            }
+        }
+        total
+    }
+
     /// 移除缓存的函数
     pub async fn remove(&self, function_name: &str) -> bool {
+        let spilled = match &self.spillover {
+            Some(spillover) => spillover.remove(function_name).await,
+            None => false,
+        };
         let mut cache = self.cache.write().await;
         let mut stats = self.stats.write().await;
 
-        if let Some(removed_function) = cache.pop(function_name) {
+        if let Some(removed_function) = cache.remove(function_name) {
             stats.memory_usage -= removed_function.memory_usage;
             stats.size = cache.len();
             tracing::info!("Removed function from cache: {}", function_name);
             true
         } else {
-            false
+            spilled
+        }
+    }
+
+    /// 函数某个版本的缓存键：`<函数名>@<版本>`，按版本调用时不会命中其他版本的条目
+    pub fn key(function: &FunctionMetadata) -> String {
+        format!("{}@{}", function.name, function.version)
+    }
+
+    /// 移除函数所有版本的缓存条目
+    pub async fn remove_function(&self, function_name: &str) -> usize {
+        self.retain_versions(function_name, &[]).await
+    }
+
+    /// 只保留函数指定版本的缓存条目（包括磁盘上的），返回移除的条目数
+    pub async fn retain_versions(&self, function_name: &str, versions: &[&str]) -> usize {
+        let prefix = format!("{function_name}@");
+        let is_stale = |key: &str| {
+            key.strip_prefix(&prefix)
+                .is_some_and(|version| !versions.contains(&version))
+        };
+        let spilled = match &self.spillover {
+            Some(spillover) => spillover.remove_matching(is_stale).await,
+            None => 0,
+        };
+        let mut cache = self.cache.write().await;
+        let mut stats = self.stats.write().await;
+
+        let stale: Vec<String> = cache
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| is_stale(key))
+            .cloned()
+            .collect();
+        for key in &stale {
+            if let Some(removed_function) = cache.remove(key) {
+                stats.memory_usage -= removed_function.memory_usage;
+            }
+        }
+        stats.size = cache.len();
+        let removed = stale.len() + spilled;
+        if removed > 0 {
+            tracing::info!(
+                "Removed {} cached versions of function {}",
+                removed,
+                function_name
+            );
         }
+        removed
     }
 
-    /// 清空缓存
+    /// 命名空间中函数的缓存条目数和内存使用（字节）
+    pub async fn namespace_usage(&self, namespace: &str) -> (usize, usize) {
+        let cache = self.cache.read().await;
+        cache
+            .iter()
+            .filter(|(_, cached)| cached.metadata.namespace() == namespace)
+            .fold((0, 0), |(entries, memory), (_, cached)| {
+                (entries + 1, memory + cached.memory_usage)
+            })
+    }
+
+    /// 清空缓存（包括磁盘上的条目）
     pub async fn clear(&self) {
+        if let Some(spillover) = &self.spillover {
+            spillover.remove_matching(|_| true).await;
+        }
         let mut cache = self.cache.write().await;
         let mut stats = self.stats.write().await;
 
@@ -191,9 +999,39 @@ impl FunctionCache {
 
     /// 获取缓存统计信息
     pub async fn stats(&self) -> CacheStats {
+        let pinned = self.pinned_names();
         let cache = self.cache.read().await;
         let mut stats = self.stats.read().await.clone();
         stats.size = cache.len();
+        stats.pinned_functions = pinned.len();
+        stats.pinned_entries = cache
+            .iter()
+            .filter(|(_, cached)| pinned.contains(&cached.metadata.name))
+            .count();
+        stats.evictable_entries = stats.size - stats.pinned_entries;
+
+        let mut largest: Vec<CacheEntrySize> = cache
+            .iter()
+            .map(|(key, cached)| CacheEntrySize {
+                key: key.clone(),
+                bytes: cached.memory_usage,
+                on_disk: false,
+            })
+            .collect();
+        if let Some(spillover) = &self.spillover {
+            let spilled = spillover.entries();
+            stats.spillover_enabled = true;
+            stats.spilled_entries = spilled.len();
+            stats.disk_usage = spilled.values().sum();
+            largest.extend(spilled.iter().map(|(key, bytes)| CacheEntrySize {
+                key: key.clone(),
+                bytes: *bytes,
+                on_disk: true,
+            }));
+        }
+        largest.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.key.cmp(&b.key)));
+        largest.truncate(LARGEST_ENTRIES);
+        stats.largest_entries = largest;
         stats
     }
 
@@ -208,36 +1046,12 @@ impl FunctionCache {
         }
     }
 
-    /// 清理过期的缓存条目
+    /// 清理过期的缓存条目（已固定的条目除外）
     pub async fn cleanup_expired(&self) -> usize {
+        let pinned = self.pinned_names();
         let mut cache = self.cache.write().await;
         let mut stats = self.stats.write().await;
-        let mut removed_count = 0;
-        let mut removed_memory = 0;
-
-        // 收集过期的键
-        let mut expired_keys = Vec::new();
-        let now = Instant::now();
-
-        // 注意：LRU 缓存不支持直接迭代，所以我们使用不同的策略
-        // 这里我们简化处理，只在 get 方法中检查过期
-        let cache_len = cache.len();
-        for _ in 0..cache_len {
-            if let Some((key, value)) = cache.pop_lru() {
-                if now.duration_since(value.created_at) > self.max_age {
-                    expired_keys.push(key);
-                    removed_memory += value.memory_usage;
-                    removed_count += 1;
-                } else {
-                    // 如果没过期，重新放回缓存
-                    cache.put(key, value);
-                }
-            }
-        }
-
-        stats.memory_usage -= removed_memory;
-        stats.size = cache.len();
-        stats.evictions += removed_count as u64;
+        let (removed_count, removed_memory) = purge_expired(&mut cache, &mut stats, &pinned);
 
         if removed_count > 0 {
             tracing::info!(
@@ -293,52 +1107,100 @@ impl FunctionCache {
         Ok(expressions)
     }
 
-    /// 估算内存使用量
-    fn estimate_memory_usage(&self, function: &FunctionMetadata, compiled: &CompiledCode) -> usize {
-        // 简化的内存使用估算
-        let base_size = std::mem::size_of::<CachedFunction>();
-        let metadata_size = function.code.len() + function.name.len() + function.description.len();
-        let compiled_size = compiled.source.len()
-            + compiled
-                .parsed_expressions
-                .iter()
-                .map(|s| s.len())
-                .sum::<usize>();
-
-        base_size + metadata_size + compiled_size + 100 // 额外开销
-    }
-
-    /// 清理缓存以腾出空间
-    async fn evict_to_fit(
+    /// 清理缓存以腾出空间，返回驱逐的条目
+    fn evict_to_fit(
         &self,
         needed_memory: usize,
-        cache: &mut LruCache<String, CachedFunction>,
+        cache: &mut EntryStore,
         stats: &mut CacheStats,
-    ) {
+        pinned: &BTreeSet<String>,
+    ) -> Vec<(String, CachedFunction)> {
         let mut freed_memory = 0;
-        let mut evicted_count = 0;
+        let mut evicted = Vec::new();
 
         while stats.memory_usage + needed_memory > self.max_memory && !cache.is_empty() {
-            if let Some((key, value)) = cache.pop_lru() {
+            if let Some((key, value)) = pop_victim(cache, pinned, EVICTION_WINDOW) {
+                stats.memory_usage -= value.memory_usage;
                 freed_memory += value.memory_usage;
-                evicted_count += 1;
                 tracing::debug!("Evicted function from cache: {}", key);
+                evicted.push((key, value));
             } else {
                 break;
             }
         }
 
-        stats.memory_usage -= freed_memory;
-        stats.evictions += evicted_count;
+        stats.evictions += evicted.len() as u64;
 
-        if evicted_count > 0 {
+        if !evicted.is_empty() {
             tracing::info!(
                 "Evicted {} functions from cache, freed {} bytes",
-                evicted_count,
+                evicted.len(),
                 freed_memory
             );
         }
+        evicted
+    }
+}
+
+/// 条目大小：编译结果加上序列化后的元数据
+fn entry_size(function: &FunctionMetadata, compiled: &CompiledCode) -> usize {
+    let metadata_size = serde_json::to_vec(function)
+        .map(|json| json.len())
+        .unwrap_or(function.code.len());
+    let compiled_size = compiled.source.len()
+        + compiled
+            .parsed_expressions
+            .iter()
+            .map(|s| s.len())
+            .sum::<usize>();
+    metadata_size + compiled_size
+}
+
+/// 驱逐代价：字节数除以近期命中率（每秒访问次数），大而冷的条目优先驱逐
+fn eviction_weight(cached: &CachedFunction) -> f64 {
+    let age_secs = cached.created_at.elapsed().as_secs_f64().max(1.0);
+    let hit_rate = (cached.access_count + 1) as f64 / age_secs;
+    cached.memory_usage as f64 / hit_rate
+}
+
+/// 在按策略最先驱逐的 `window` 个未固定条目中弹出驱逐代价最大的一个，代价相同时弹出排在前面的
+fn pop_victim(
+    cache: &mut EntryStore,
+    pinned: &BTreeSet<String>,
+    window: usize,
+) -> Option<(String, CachedFunction)> {
+    let key = cache
+        .eviction_candidates(pinned)
+        .take(window)
+        .fold(None::<(&String, f64)>, |victim, (key, cached)| {
+            let weight = eviction_weight(cached);
+            match victim {
+                Some((_, max)) if max >= weight => victim,
+                _ => Some((key, weight)),
+            }
+        })
+        .map(|(key, _)| key.clone())?;
+    let cached = cache.remove(&key)?;
+    Some((key, cached))
+}
+
+/// 移除过期且未固定的条目，返回移除的条目数和释放的字节数
+fn purge_expired(
+    cache: &mut EntryStore,
+    stats: &mut CacheStats,
+    pinned: &BTreeSet<String>,
+) -> (usize, usize) {
+    let expired = cache.expired_keys(Instant::now(), pinned);
+    let mut removed_memory = 0;
+    for key in &expired {
+        if let Some(removed_function) = cache.remove(key) {
+            removed_memory += removed_function.memory_usage;
+        }
     }
+    stats.memory_usage -= removed_memory;
+    stats.size = cache.len();
+    stats.expirations += expired.len() as u64;
+    (expired.len(), removed_memory)
 }
 
 impl Default for FunctionCache {
@@ -347,3 +1209,320 @@ impl Default for FunctionCache {
         Self::new(100, 50, 3600)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(name: &str, code_len: usize) -> FunctionMetadata {
+        FunctionMetadata::new(name.to_string(), format!("return {}", "a".repeat(code_len)))
+    }
+
+    #[tokio::test]
+    async fn test_byte_budget_evicts_large_cold_entries_first() {
+        let cache = FunctionCache::new(10, 0, 3600).with_max_bytes(16 * 1024);
+        let large = function("large", 4096);
+        let small = function("small", 16);
+        cache
+            .put(FunctionCache::key(&large), large.clone())
+            .await
+            .unwrap();
+        cache
+            .put(FunctionCache::key(&small), small.clone())
+            .await
+            .unwrap();
+        let stats = cache.stats().await;
+        assert_eq!(stats.largest_entries[0].key, FunctionCache::key(&large));
+        assert!(stats.memory_usage > 8192);
+
+        // 超出字节上限时驱逐大的条目，即使它比小条目更近被访问
+        cache.get(&FunctionCache::key(&large)).await.unwrap();
+        let other = function("other", 1024);
+        cache
+            .put(FunctionCache::key(&other), other.clone())
+            .await
+            .unwrap();
+        assert!(cache.peek(&FunctionCache::key(&large)).await.is_none());
+        assert!(cache.peek(&FunctionCache::key(&small)).await.is_some());
+        let stats = cache.stats().await;
+        assert!(stats.memory_usage <= stats.max_memory);
+        assert_eq!(stats.evictions, 1);
+        assert!(!stats.spillover_enabled);
+        assert_eq!(stats.spilled_entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_documentation_is_not_cached() {
+        let cache = FunctionCache::default();
+        let mut documented = function("documented", 16);
+        documented.documentation = Some("x".repeat(32 * 1024));
+        let key = FunctionCache::key(&documented);
+        cache.put(key.clone(), documented).await.unwrap();
+        let cached = cache.peek(&key).await.unwrap();
+        assert!(cached.metadata.documentation.is_none());
+        assert!(cached.memory_usage < 32 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_spillover_reloads_evicted_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = FunctionCacheConfig {
+            capacity: 1,
+            spillover: true,
+            cache_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let cache = FunctionCache::from_config(&config).unwrap();
+        let first = function("first", 8);
+        let second = function("second", 8);
+        let third = function("third", 8);
+        for function in [&first, &second, &third] {
+            cache
+                .put(FunctionCache::key(function), function.clone())
+                .await
+                .unwrap();
+        }
+        let stats = cache.stats().await;
+        assert_eq!(stats.size, 1);
+        assert_eq!(stats.spilled_entries, 2);
+        assert_eq!(stats.spillover_writes, 2);
+        assert!(stats.disk_usage > 0);
+        assert!(stats.largest_entries.iter().any(|entry| entry.on_disk));
+
+        // 未命中时从磁盘重新加载，当前内存中的条目溢出到磁盘
+        let reloaded = cache.get(&FunctionCache::key(&first)).await.unwrap();
+        assert_eq!(reloaded.metadata.code, first.code);
+        let stats = cache.stats().await;
+        assert_eq!(stats.spillover_hits, 1);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.spilled_entries, 2);
+
+        // 重启后按目录中的文件重建索引，损坏的文件在加载时被发现并丢弃
+        drop(cache);
+        let path = spill_path(&dir.path().join("registry"), &FunctionCache::key(&second));
+        let mut file: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        file["metadata"] = serde_json::json!(
+            file["metadata"]
+                .as_str()
+                .unwrap()
+                .replace("second", "tampered")
+        );
+        std::fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+        let cache = FunctionCache::from_config(&config).unwrap();
+        assert_eq!(cache.stats().await.spilled_entries, 2);
+        assert!(cache.get(&FunctionCache::key(&second)).await.is_none());
+        let stats = cache.stats().await;
+        assert_eq!(stats.spillover_corrupted, 1);
+        assert_eq!(stats.misses, 1);
+        assert!(!path.exists());
+
+        // 移除函数时同时删除磁盘上的条目
+        assert!(cache.remove(&FunctionCache::key(&third)).await);
+        assert_eq!(cache.stats().await.spilled_entries, 0);
+    }
+
+    /// 按策略独立计算的驱逐对象：返回应被驱逐的键
+    struct Model {
+        strategy: CacheStrategy,
+        capacity: usize,
+        clock: u64,
+        /// 键 → (放入序号, 最近访问序号, 访问次数)
+        entries: BTreeMap<String, (u64, u64, u64)>,
+    }
+
+    impl Model {
+        fn put(&mut self, key: &str) {
+            self.clock += 1;
+            if !self.entries.contains_key(key) && self.entries.len() >= self.capacity {
+                let victim = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (inserted, accessed, count))| match self.strategy {
+                        CacheStrategy::Lru => (*accessed, 0),
+                        // 所有条目的存活时间相同，最先过期的就是最早放入的
+                        CacheStrategy::Fifo | CacheStrategy::Ttl => (*inserted, 0),
+                        CacheStrategy::Lfu => (*count, *accessed),
+                    })
+                    .map(|(key, _)| key.clone())
+                    .unwrap();
+                self.entries.remove(&victim);
+            }
+            self.entries
+                .insert(key.to_string(), (self.clock, self.clock, 0));
+        }
+
+        fn get(&mut self, key: &str) -> bool {
+            self.clock += 1;
+            match self.entries.get_mut(key) {
+                Some((_, accessed, count)) => {
+                    *accessed = self.clock;
+                    *count += 1;
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_strategies_evict_expected_victims() {
+        let functions: Vec<_> = (0..6).map(|i| function(&format!("f{i}"), 8)).collect();
+        for strategy in [
+            CacheStrategy::Lru,
+            CacheStrategy::Fifo,
+            CacheStrategy::Lfu,
+            CacheStrategy::Ttl,
+        ] {
+            let cache = FunctionCache::new(3, 50, 3600).with_strategy(strategy);
+            let mut model = Model {
+                strategy,
+                capacity: 3,
+                clock: 0,
+                entries: BTreeMap::new(),
+            };
+            // 固定种子的伪随机访问序列
+            let mut seed = 0x2545_f491_u64;
+            for _ in 0..300 {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let function = &functions[(seed >> 33) as usize % functions.len()];
+                let key = FunctionCache::key(function);
+                if (seed >> 20).is_multiple_of(3) {
+                    cache.put(key.clone(), function.clone()).await.unwrap();
+                    model.put(&key);
+                } else {
+                    let hit = cache.get(&key).await.is_some();
+                    assert_eq!(hit, model.get(&key), "{strategy:?}: get {key}");
+                }
+                let mut keys: Vec<_> = cache.entries().await.into_iter().map(|e| e.key).collect();
+                keys.sort();
+                let expected: Vec<_> = model.entries.keys().cloned().collect();
+                assert_eq!(keys, expected, "{strategy:?}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scripted_victims_per_strategy() {
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|name| function(name, 8));
+        let victim = |strategy| {
+            let (a, b, c, d) = (a.clone(), b.clone(), c.clone(), d.clone());
+            async move {
+                let cache = FunctionCache::new(3, 50, 3600).with_strategy(strategy);
+                for function in [&a, &b, &c] {
+                    cache
+                        .put(FunctionCache::key(function), function.clone())
+                        .await
+                        .unwrap();
+                }
+                // a 访问两次，b 访问一次，c 未访问
+                for function in [&a, &a, &b] {
+                    cache.get(&FunctionCache::key(function)).await.unwrap();
+                }
+                cache.put(FunctionCache::key(&d), d).await.unwrap();
+                let remaining: BTreeSet<_> = cache
+                    .entries()
+                    .await
+                    .into_iter()
+                    .map(|e| e.function)
+                    .collect();
+                ["a", "b", "c"]
+                    .into_iter()
+                    .find(|name| !remaining.contains(*name))
+                    .unwrap()
+            }
+        };
+        assert_eq!(victim(CacheStrategy::Lru).await, "c");
+        assert_eq!(victim(CacheStrategy::Fifo).await, "a");
+        assert_eq!(victim(CacheStrategy::Lfu).await, "c");
+        assert_eq!(victim(CacheStrategy::Ttl).await, "a");
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_never_returned() {
+        let cache = Arc::new(
+            FunctionCache::new(10, 50, 0)
+                .with_ttl(Duration::from_millis(40))
+                .with_sweep_interval(Duration::from_millis(10)),
+        );
+        let [expiring, pinned, swept] =
+            ["expiring", "pinned", "swept"].map(|name| function(name, 8));
+        cache.pin("pinned").unwrap();
+        for function in [&expiring, &pinned, &swept] {
+            cache
+                .put(FunctionCache::key(function), function.clone())
+                .await
+                .unwrap();
+        }
+        assert!(cache.get(&FunctionCache::key(&expiring)).await.is_some());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        // 过期条目在读取时被移除，固定的条目不过期
+        assert!(cache.peek(&FunctionCache::key(&expiring)).await.is_none());
+        assert!(cache.get(&FunctionCache::key(&expiring)).await.is_none());
+        assert!(cache.get(&FunctionCache::key(&pinned)).await.is_some());
+        // 后台任务清理未被读取的过期条目
+        let sweeper = cache.spawn_sweeper();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        sweeper.abort();
+        let stats = cache.stats().await;
+        assert_eq!(stats.size, 1);
+        assert_eq!(stats.expirations, 2);
+        let pinned_entry = cache.peek(&FunctionCache::key(&pinned)).await.unwrap();
+        assert_eq!(stats.memory_usage, pinned_entry.memory_usage);
+
+        // 存活时间为 0 的条目不过期
+        cache
+            .configure(CachePolicyUpdate {
+                ttl_secs: Some(0),
+                ..Default::default()
+            })
+            .await;
+        cache
+            .put(FunctionCache::key(&expiring), expiring.clone())
+            .await
+            .unwrap();
+        assert!(
+            cache
+                .peek(&FunctionCache::key(&expiring))
+                .await
+                .unwrap()
+                .expires_at
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_configure_switches_strategy_for_existing_entries() {
+        let cache = FunctionCache::new(2, 50, 3600);
+        let [a, b, c] = ["a", "b", "c"].map(|name| function(name, 8));
+        for function in [&a, &b] {
+            cache
+                .put(FunctionCache::key(function), function.clone())
+                .await
+                .unwrap();
+        }
+        cache.get(&FunctionCache::key(&a)).await.unwrap();
+
+        // LRU 下会驱逐 b，切换到 FIFO 后驱逐最早放入的 a
+        let policy = cache
+            .configure(CachePolicyUpdate {
+                strategy: Some(CacheStrategy::Fifo),
+                ttl_secs: Some(60),
+            })
+            .await;
+        assert_eq!(
+            policy,
+            CachePolicy {
+                strategy: CacheStrategy::Fifo,
+                ttl_secs: 60
+            }
+        );
+        cache.put(FunctionCache::key(&c), c.clone()).await.unwrap();
+        assert!(cache.peek(&FunctionCache::key(&a)).await.is_none());
+        assert!(cache.peek(&FunctionCache::key(&b)).await.is_some());
+        assert_eq!(cache.stats().await.evictions, 1);
+    }
+}
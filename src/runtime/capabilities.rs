@@ -0,0 +1,118 @@
+//! 执行能力：当前构建启用的特性和运行环境能否执行各脚本类型的函数
+//!
+//! - `native`：进程内执行（表达式引擎，启用编译时的 Rust 函数）；
+//! - `external`：在沙箱中由外部解释器执行（node、python3 或函数选择的执行环境）；
+//! - `embedded`：由内嵌引擎执行（`pure` 特性下的 JavaScript，不需要 node）；
+//! - `unsupported`：无法执行，注册时以 `UnsupportedScriptType` 拒绝。
+//!
+//! 外部解释器优先于内嵌引擎：两者都可用时 JavaScript 仍交给 node，行为与未启用 `pure` 时一致。
+
+use crate::runtime::environment::RuntimeKind;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// 能力矩阵中列出的脚本类型（见 `scheduler::namespaces::script_type`）
+pub const SCRIPT_TYPES: [&str; 5] = ["expression", "rust", "javascript", "typescript", "python"];
+
+/// 是否编译了内嵌 JavaScript 引擎（`pure` 特性）
+pub const EMBEDDED_JAVASCRIPT: bool = cfg!(feature = "pure");
+
+/// 一种脚本类型的执行方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionCapability {
+    Native,
+    External,
+    Embedded,
+    Unsupported,
+}
+
+/// 各脚本类型的执行方式
+pub type CapabilityMatrix = BTreeMap<String, ExecutionCapability>;
+
+/// 判断脚本类型的执行方式，无法执行时返回原因
+///
+/// `compilation` 为运行时是否编译执行 Rust 函数，`external` 解析执行该语言的外部解释器
+/// （没有可用的沙箱或解释器时返回原因）。
+pub fn resolve(
+    script_type: &str,
+    compilation: bool,
+    external: impl FnOnce(RuntimeKind) -> Result<(), String>,
+) -> Result<ExecutionCapability, String> {
+    match script_type {
+        "expression" => Ok(ExecutionCapability::Native),
+        "rust" if compilation => Ok(ExecutionCapability::Native),
+        "rust" => Err(
+            "Rust functions need a scheduler profile with compilation enabled \
+             (profiles.<name>.compilation = true)"
+                .to_string(),
+        ),
+        "javascript" => match external(RuntimeKind::Node) {
+            Ok(()) => Ok(ExecutionCapability::External),
+            Err(_) if EMBEDDED_JAVASCRIPT => Ok(ExecutionCapability::Embedded),
+            Err(reason) => Err(format!(
+                "{reason}; build with the `pure` feature to run JavaScript on the embedded engine"
+            )),
+        },
+        "python" => match external(RuntimeKind::Python) {
+            Ok(()) => Ok(ExecutionCapability::External),
+            Err(reason) if EMBEDDED_JAVASCRIPT => Err(format!(
+                "{reason}; the embedded engine of the `pure` build only runs JavaScript"
+            )),
+            Err(reason) => Err(reason),
+        },
+        "typescript" => Err("TypeScript functions are not executed; \
+             compile them to JavaScript and register the output"
+            .to_string()),
+        other => Err(format!("Unknown script type {other}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_capabilities() {
+        let present = |_| Ok(());
+        let absent = |kind: RuntimeKind| Err(format!("Runtime {} is not available", kind.name()));
+
+        assert_eq!(
+            resolve("expression", false, absent),
+            Ok(ExecutionCapability::Native)
+        );
+        assert_eq!(
+            resolve("rust", true, absent),
+            Ok(ExecutionCapability::Native)
+        );
+        assert!(
+            resolve("rust", false, present)
+                .unwrap_err()
+                .contains("compilation")
+        );
+        assert_eq!(
+            resolve("javascript", false, present),
+            Ok(ExecutionCapability::External)
+        );
+        assert_eq!(
+            resolve("python", false, present),
+            Ok(ExecutionCapability::External)
+        );
+        assert!(resolve("typescript", true, present).is_err());
+
+        // 没有外部解释器时，JavaScript 只在 `pure` 构建中可以执行
+        let javascript = resolve("javascript", false, absent);
+        if EMBEDDED_JAVASCRIPT {
+            assert_eq!(javascript, Ok(ExecutionCapability::Embedded));
+        } else {
+            let reason = javascript.unwrap_err();
+            assert!(
+                reason.contains("node") && reason.contains("`pure`"),
+                "{reason}"
+            );
+        }
+        let reason = resolve("python", false, absent).unwrap_err();
+        assert!(reason.contains("python"), "{reason}");
+    }
+}
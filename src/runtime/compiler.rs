@@ -1,16 +1,26 @@
 use anyhow::{Context, Result};
-use libloading::{Library, Symbol};
+use libloading::Symbol;
 
-use std::collections::HashMap;
-use std::ffi::OsStr;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, CString, OsStr, c_void};
 use std::fs;
+use std::os::raw::c_char;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
+use std::time::{Duration, Instant};
 use tempfile::TempDir;
-use tokio::sync::RwLock;
-
-use crate::functions::{ExecutionStatus, FunctionMetadata, InvokeRequest, InvokeResponse};
+use tokio::sync::{Mutex, RwLock, Semaphore};
+
+use super::artifacts::{ArtifactTable, LoadedLibrary};
+use super::binding;
+use crate::functions::compilation::HandlerMode;
+use crate::functions::context::InvocationContext;
+use crate::functions::{
+    ErrorKind, ExecutionStatus, FunctionMetadata, InvocationTiming, InvokeRequest, InvokeResponse,
+};
+use crate::runtime::egress::{EgressHandle, FetchRequest};
+use crate::runtime::state::{StateEntry, StateHandle, StateWrite};
 
 /// 编译后的函数信息
 #[derive(Debug, Clone)]
@@ -25,7 +35,163 @@ pub struct CompiledFunction {
     pub source_hash: String,
     /// 编译所用时间（毫秒）
     pub compile_time_ms: u64,
+    /// 生成的入口绑定方式
+    pub handler_mode: HandlerMode,
+}
+
+/// 编译产物的键：函数名 + 源代码哈希
+pub type CompileKey = (String, String);
+
+/// 一次编译请求的结果，区分排队等待和实际编译耗时
+#[derive(Debug, Clone)]
+pub struct CompileOutcome {
+    pub compiled: CompiledFunction,
+    /// 等待同源编译或编译并发许可的时间
+    pub wait_time: Duration,
+    /// 本次实际编译的时间（命中缓存时为 0）
+    pub compile_time: Duration,
+    /// 是否未触发编译（命中缓存或复用了并发请求的编译结果）
+    pub cache_hit: bool,
+}
+
+impl CompileOutcome {
+    /// 本次调用花在编译上的时间（等待同源编译和实际编译），直接命中缓存时为 `None`
+    pub fn compile_duration(&self) -> Option<Duration> {
+        (!self.cache_hit || !self.wait_time.is_zero()).then(|| self.wait_time + self.compile_time)
+    }
+}
+
+/// 传给 `flux_execute_v3` 的宿主回调表，布局与编译模板中的 `FluxHost` 一致
+/// （新的回调只追加在末尾，旧的编译产物读取的前缀布局不变）
+#[repr(C)]
+struct FluxHost {
+    version: u32,
+    /// 指向本次调用的 [`HostContext`]，只在调用期间有效
+    ctx: *const c_void,
+    state_get: extern "C" fn(*const c_void, *const c_char) -> *mut c_char,
+    state_set: extern "C" fn(*const c_void, *const c_char, *const c_char) -> *mut c_char,
+    free_string: extern "C" fn(*mut c_char),
+    http_fetch: extern "C" fn(*const c_void, *const c_char) -> *mut c_char,
+}
+
+/// 宿主回调表的版本（2 起提供 `http_fetch`）
+const FLUX_HOST_VERSION: u32 = 2;
+
+/// 宿主回调访问的调用期数据
+struct HostContext<'a> {
+    state: Option<&'a StateHandle>,
+    egress: Option<&'a EgressHandle>,
+}
+
+impl FluxHost {
+    fn new(ctx: &HostContext<'_>) -> Self {
+        Self {
+            version: FLUX_HOST_VERSION,
+            ctx: ctx as *const HostContext<'_> as *const c_void,
+            state_get: host_state_get,
+            state_set: host_state_set,
+            free_string: host_free_string,
+            http_fetch: host_http_fetch,
+        }
+    }
+}
+
+/// 回调的返回值：`{"data": ...}` 或 `{"error": "..."}`，由 `host_free_string` 释放
+fn host_reply(result: std::result::Result<impl serde::Serialize, String>) -> *mut c_char {
+    let reply = match result {
+        Ok(data) => serde_json::json!({ "data": data }),
+        Err(error) => serde_json::json!({ "error": error }),
+    };
+    CString::new(reply.to_string()).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// 读取回调参数中的 C 字符串
+fn host_str<'a>(ptr: *const c_char) -> std::result::Result<&'a str, String> {
+    if ptr.is_null() {
+        return Err("Missing argument".to_string());
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|e| e.to_string())
+}
+
+fn host_state<'a>(ctx: *const c_void) -> std::result::Result<&'a StateHandle, String> {
+    let ctx = unsafe { &*(ctx as *const HostContext<'a>) };
+    ctx.state
+        .ok_or_else(|| "State is not available to this invocation".to_string())
+}
+
+extern "C" fn host_state_get(ctx: *const c_void, key: *const c_char) -> *mut c_char {
+    host_reply((|| -> std::result::Result<Option<StateEntry>, String> {
+        Ok(host_state(ctx)?.get(host_str(key)?))
+    })())
+}
+
+extern "C" fn host_state_set(
+    ctx: *const c_void,
+    key: *const c_char,
+    request: *const c_char,
+) -> *mut c_char {
+    host_reply((|| {
+        let state = host_state(ctx)?;
+        let key = host_str(key)?;
+        let write: StateWrite =
+            serde_json::from_str(host_str(request)?).map_err(|e| e.to_string())?;
+        state.set(key, write).map_err(|e| e.to_string())
+    })())
+}
+
+extern "C" fn host_http_fetch(ctx: *const c_void, request: *const c_char) -> *mut c_char {
+    let ctx = unsafe { &*(ctx as *const HostContext<'_>) };
+    host_reply((|| {
+        let egress = ctx
+            .egress
+            .ok_or_else(|| "HTTP fetch is not available to this invocation".to_string())?;
+        let request: FetchRequest =
+            serde_json::from_str(host_str(request)?).map_err(|e| e.to_string())?;
+        egress.fetch_blocking(request).map_err(|e| e.to_string())
+    })())
+}
+
+extern "C" fn host_free_string(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        drop(unsafe { CString::from_raw(ptr) });
+    }
+}
+
+/// 未声明参数时的入口：把原始 JSON 输入交给用户代码
+const RAW_JSON_ENTRY: &str = r#"// 用户函数执行入口
+fn execute_user_function(input: serde_json::Value, context: serde_json::Value) -> serde_json::Value {
+    // 简化版本：直接执行字符串形式的代码
+    // 实际实现中需要更复杂的代码解析和执行逻辑
+    match input {
+        serde_json::Value::Object(ref map) => {
+            // 示例：如果是加法操作
+            if let (Some(a), Some(b)) = (map.get("a"), map.get("b")) {
+                if let (Some(a_num), Some(b_num)) = (a.as_f64(), b.as_f64()) {
+                    return serde_json::json!({ "result": a_num + b_num });
+                }
+            }
+
+            // 默认行为：返回处理过的输入
+            serde_json::json!({
+                "message": "Function executed",
+                "input": input,
+                "context": context,
+                "timestamp": chrono::Utc::now().to_rfc3339()
+            })
+        },
+        _ => {
+            serde_json::json!({
+                "message": "Hello from compiled function",
+                "input": input,
+                "context": context,
+                "timestamp": chrono::Utc::now().to_rfc3339()
+            })
+        }
+    }
 }
+"#;
 
 /// 编译器配置
 #[derive(Debug, Clone)]
@@ -44,6 +210,18 @@ pub struct CompilerConfig {
     pub max_cache_entries: usize,
     /// 自定义Rust编译目标路径
     pub rust_target_dir: Option<PathBuf>,
+    /// 同时进行的 cargo 编译数上限
+    pub max_concurrent_builds: usize,
+    /// 退役的编译产物在最近一次使用后至少保留的时间（秒），之后无人持有时才删除
+    pub artifact_grace_secs: u64,
+}
+
+/// 默认的编译并发上限：CPU 核数的一半（至少为 1）
+pub fn default_max_concurrent_builds() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get() / 2)
+        .unwrap_or(1)
+        .max(1)
 }
 
 impl Default for CompilerConfig {
@@ -56,6 +234,8 @@ impl Default for CompilerConfig {
             cache_dir: PathBuf::from("./flux_cache"),
             max_cache_entries: 100,
             rust_target_dir: None,
+            max_concurrent_builds: default_max_concurrent_builds(),
+            artifact_grace_secs: 30,
         }
     }
 }
@@ -64,8 +244,16 @@ impl Default for CompilerConfig {
 #[derive(Debug)]
 pub struct RustCompiler {
     config: CompilerConfig,
-    compiled_functions: Arc<RwLock<HashMap<String, CompiledFunction>>>,
+    compiled_functions: Arc<RwLock<HashMap<CompileKey, CompiledFunction>>>,
     temp_dirs: Arc<RwLock<Vec<TempDir>>>, // 保持临时目录引用，防止被清理
+    /// 每个编译键一把锁，同源的并发请求只编译一次
+    compile_locks: StdMutex<HashMap<CompileKey, Arc<Mutex<()>>>>,
+    /// 限制同时运行的 cargo 编译数
+    build_permits: Arc<Semaphore>,
+    /// 编译产物最近一次被使用的时间（用于按 LRU 淘汰）
+    last_used: StdMutex<HashMap<CompileKey, Instant>>,
+    /// 已加载的库句柄、每个函数的当前产物和等待删除的退役产物
+    artifacts: ArtifactTable,
 }
 
 impl RustCompiler {
@@ -75,10 +263,16 @@ impl RustCompiler {
         fs::create_dir_all(&config.cache_dir)
             .with_context(|| format!("Failed to create cache directory: {:?}", config.cache_dir))?;
 
+        let build_permits = Arc::new(Semaphore::new(config.max_concurrent_builds.max(1)));
+
         Ok(Self {
             config,
             compiled_functions: Arc::new(RwLock::new(HashMap::new())),
             temp_dirs: Arc::new(RwLock::new(Vec::new())),
+            compile_locks: StdMutex::new(HashMap::new()),
+            build_permits,
+            last_used: StdMutex::new(HashMap::new()),
+            artifacts: ArtifactTable::default(),
         })
     }
 
@@ -112,18 +306,100 @@ impl RustCompiler {
 
     /// 编译函数代码
     pub async fn compile_function(&self, function: &FunctionMetadata) -> Result<CompiledFunction> {
-        let start_time = std::time::Instant::now();
+        self.compile_function_timed(function)
+            .await
+            .map(|outcome| outcome.compiled)
+    }
 
-        // 计算源代码哈希
-        let source_hash = format!("{:x}", md5::compute(&function.code));
+    /// 编译函数代码，并返回排队等待和实际编译的耗时
+    #[tracing::instrument(name = "compile", skip_all, fields(function = %function.name))]
+    pub async fn compile_function_timed(
+        &self,
+        function: &FunctionMetadata,
+    ) -> Result<CompileOutcome> {
+        let key = Self::compile_key(function);
 
         // 检查缓存
-        if let Some(cached) = self.get_cached_function(&function.name, &source_hash).await {
-            tracing::debug!("Using cached compilation for function: {}", function.name);
-            return Ok(cached);
+        if let Some(compiled) = self.get_cached_function(&key).await {
+            tracing::debug!(
+                phase = "compile",
+                "Using cached compilation for function: {}",
+                function.name
+            );
+            return Ok(CompileOutcome {
+                compiled,
+                wait_time: Duration::ZERO,
+                compile_time: Duration::ZERO,
+                cache_hit: true,
+            });
+        }
+
+        // 同源的并发请求在此排队，等待第一个请求完成编译
+        let wait_start = Instant::now();
+        let lock = self.compile_lock(&key);
+        let outcome = {
+            let _guard = lock.lock().await;
+            match self.get_cached_function(&key).await {
+                Some(compiled) => {
+                    tracing::debug!(
+                        "Reusing concurrent compilation for function: {}",
+                        function.name
+                    );
+                    Ok(CompileOutcome {
+                        compiled,
+                        wait_time: wait_start.elapsed(),
+                        compile_time: Duration::ZERO,
+                        cache_hit: true,
+                    })
+                }
+                None => self.compile_uncached(function, &key, wait_start).await,
+            }
+        };
+        self.release_compile_lock(&key, lock);
+
+        outcome
+    }
+
+    /// 获取编译键对应的锁
+    fn compile_lock(&self, key: &CompileKey) -> Arc<Mutex<()>> {
+        self.compile_locks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.clone())
+            .or_default()
+            .clone()
+    }
+
+    /// 没有其他请求持有时移除编译键的锁
+    fn release_compile_lock(&self, key: &CompileKey, lock: Arc<Mutex<()>>) {
+        let mut locks = self.compile_locks.lock().unwrap_or_else(|e| e.into_inner());
+        // 映射表和当前调用各持有一个引用
+        if Arc::strong_count(&lock) <= 2 {
+            locks.remove(key);
         }
+    }
 
-        tracing::info!("Compiling function: {}", function.name);
+    /// 实际调用 cargo 编译（调用方已持有编译键的锁）
+    async fn compile_uncached(
+        &self,
+        function: &FunctionMetadata,
+        key: &CompileKey,
+        wait_start: Instant,
+    ) -> Result<CompileOutcome> {
+        let _permit = self
+            .build_permits
+            .acquire()
+            .await
+            .context("Compiler is shutting down")?;
+        let wait_time = wait_start.elapsed();
+        let start_time = Instant::now();
+
+        tracing::info!(
+            phase = "compile",
+            "Compiling function: {} (waited {}ms)",
+            function.name,
+            wait_time.as_millis()
+        );
 
         // 验证rustc可用性
         let rustc_path = self.check_rustc()?;
@@ -136,30 +412,33 @@ impl RustCompiler {
         let source_file = self.generate_source_file(function, work_dir)?;
 
         // 生成Cargo.toml
-        let _cargo_toml = self.generate_cargo_toml(function, work_dir)?;
+        let crate_name = Self::crate_name(key);
+        let _cargo_toml = self.generate_cargo_toml(&crate_name, work_dir)?;
 
         // 编译为动态库
         let library_path = self
-            .compile_to_dylib(&rustc_path, &source_file, work_dir)
+            .compile_to_dylib(&rustc_path, &source_file, &crate_name, work_dir)
             .await?;
 
-        // 复制到缓存目录
-        let cached_library_path =
-            self.cache_library(&function.name, &source_hash, &library_path)?;
+        // 复制到缓存目录并试加载，失败时不切换函数的当前产物
+        let cached_library_path = self.cache_library(key, &library_path)?;
+        let handle = LoadedLibrary::load(&cached_library_path)?;
 
-        let compile_time_ms = start_time.elapsed().as_millis() as u64;
+        let compile_time = start_time.elapsed();
 
         let compiled_function = CompiledFunction {
             metadata: function.clone(),
             library_path: cached_library_path,
             compiled_at: chrono::Utc::now(),
-            source_hash,
-            compile_time_ms,
+            source_hash: key.1.clone(),
+            compile_time_ms: compile_time.as_millis() as u64,
+            handler_mode: HandlerMode::for_function(function),
         };
 
         // 缓存编译结果
-        self.cache_compiled_function(&function.name, compiled_function.clone())
+        self.cache_compiled_function(key, compiled_function.clone())
             .await;
+        self.artifacts.activate(key, handle);
 
         // 保持临时目录引用
         {
@@ -175,25 +454,81 @@ impl RustCompiler {
         tracing::info!(
             "Successfully compiled function '{}' in {}ms",
             function.name,
-            compile_time_ms
+            compiled_function.compile_time_ms
         );
 
-        Ok(compiled_function)
+        Ok(CompileOutcome {
+            compiled: compiled_function,
+            wait_time,
+            compile_time,
+            cache_hit: false,
+        })
+    }
+
+    /// 计算函数的编译键（多文件函数包按所有文件计算哈希，类型化绑定的签名也参与计算）
+    pub fn compile_key(function: &FunctionMetadata) -> CompileKey {
+        let mut source_hash = match &function.package {
+            Some(package) => package.content_hash(),
+            None => format!("{:x}", md5::compute(&function.code)),
+        };
+        if HandlerMode::for_function(function) == HandlerMode::Typed {
+            source_hash = format!(
+                "{:x}",
+                md5::compute(format!("{source_hash}\n{}", binding::signature(function)))
+            );
+        }
+        (function.name.clone(), source_hash)
+    }
+
+    /// 编译产物的 crate 名（包含源代码哈希，共享 target 目录时不会互相覆盖）
+    fn crate_name(key: &CompileKey) -> String {
+        format!("flux_function_{}", Self::artifact_stem(key))
+    }
+
+    /// 编译产物文件名主干：`<函数名>_<源代码哈希>`，函数名中的非字母数字字符替换为 `_`
+    fn artifact_stem((name, source_hash): &CompileKey) -> String {
+        let clean_name: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("{clean_name}_{source_hash}")
+    }
+
+    /// 当前平台的动态库文件名
+    fn library_filename(crate_name: &str) -> String {
+        if cfg!(target_os = "windows") {
+            format!("{crate_name}.dll")
+        } else if cfg!(target_os = "macos") {
+            format!("lib{crate_name}.dylib")
+        } else {
+            format!("lib{crate_name}.so")
+        }
     }
 
     /// 生成Rust源文件
+    ///
+    /// 多文件函数包的其余文件按原目录结构放在 `src/` 下，入口文件包装为 `src/lib.rs`，
+    /// 因此入口中的 `mod util;` 解析为 `src/util.rs`。
     fn generate_source_file(
         &self,
         function: &FunctionMetadata,
         work_dir: &Path,
     ) -> Result<PathBuf> {
-        let source_content = self.wrap_user_code(&function.code)?;
+        let source_content = self.wrap_user_code(function)?;
 
         // 创建src目录
         let src_dir = work_dir.join("src");
         fs::create_dir_all(&src_dir)
             .with_context(|| format!("Failed to create src directory: {src_dir:?}"))?;
 
+        if let Some(package) = &function.package {
+            let mut modules = package.clone();
+            modules.files.retain(|file| file.path != package.entrypoint);
+            modules
+                .write_to(&src_dir)
+                .with_context(|| format!("Failed to lay out package files in {src_dir:?}"))?;
+        }
+
         let source_file = src_dir.join("lib.rs");
 
         fs::write(&source_file, source_content)
@@ -202,8 +537,13 @@ impl RustCompiler {
         Ok(source_file)
     }
 
-    /// 包装用户代码为标准的动态库格式
-    fn wrap_user_code(&self, user_code: &str) -> Result<String> {
+    /// 包装用户代码为标准的动态库格式，按入口绑定方式生成 `execute_user_function`
+    fn wrap_user_code(&self, function: &FunctionMetadata) -> Result<String> {
+        let user_code = &function.code;
+        let entry = match HandlerMode::for_function(function) {
+            HandlerMode::RawJson => RAW_JSON_ENTRY.to_string(),
+            HandlerMode::Typed => binding::typed_shim(function)?,
+        };
         // 基础的函数包装模板
         let wrapped_code = format!(
             r#"
@@ -213,27 +553,31 @@ use std::os::raw::c_char;
 // 用户代码
 {user_code}
 
-// 导出函数接口
+// 导出函数接口（第一版 ABI，只有输入）
 #[no_mangle]
 pub extern "C" fn flux_execute(input_ptr: *const c_char) -> *mut c_char {{
+    flux_execute_v2(input_ptr, std::ptr::null())
+}}
+
+// 导出函数接口（第二版 ABI）：输入和调用上下文均为 JSON 字符串
+#[no_mangle]
+pub extern "C" fn flux_execute_v2(
+    input_ptr: *const c_char,
+    context_ptr: *const c_char,
+) -> *mut c_char {{
     if input_ptr.is_null() {{
         return std::ptr::null_mut();
     }}
 
-    let input_cstr = unsafe {{ CStr::from_ptr(input_ptr) }};
-    let input_str = match input_cstr.to_str() {{
-        Ok(s) => s,
-        Err(_) => return std::ptr::null_mut(),
-    }};
-
     // 解析JSON输入
-    let input_json: serde_json::Value = match serde_json::from_str(input_str) {{
-        Ok(json) => json,
-        Err(_) => serde_json::Value::Null,
+    let input_json = match read_json(input_ptr) {{
+        Some(json) => json,
+        None => return std::ptr::null_mut(),
     }};
+    let context_json = read_json(context_ptr).unwrap_or(serde_json::Value::Null);
 
     // 调用用户函数（这里需要根据具体的用户代码格式来适配）
-    let result = execute_user_function(input_json);
+    let result = execute_user_function(input_json, context_json);
 
     // 序列化结果
     let result_str = match serde_json::to_string(&result) {{
@@ -248,6 +592,139 @@ pub extern "C" fn flux_execute(input_ptr: *const c_char) -> *mut c_char {{
     }}
 }}
 
+// 宿主回调表（第三版 ABI）：回调返回的字符串由 `free_string` 释放，
+// 内容为 {{"data": ...}} 或 {{"error": "..."}}
+#[repr(C)]
+pub struct FluxHost {{
+    pub version: u32,
+    pub ctx: *const std::ffi::c_void,
+    pub state_get: extern "C" fn(*const std::ffi::c_void, *const c_char) -> *mut c_char,
+    pub state_set:
+        extern "C" fn(*const std::ffi::c_void, *const c_char, *const c_char) -> *mut c_char,
+    pub free_string: extern "C" fn(*mut c_char),
+    // 第 2 版起提供
+    pub http_fetch: extern "C" fn(*const std::ffi::c_void, *const c_char) -> *mut c_char,
+}}
+
+thread_local! {{
+    static FLUX_HOST: std::cell::Cell<*const FluxHost> = const {{ std::cell::Cell::new(std::ptr::null()) }};
+}}
+
+// 导出函数接口（第三版 ABI）：另外传入宿主回调，调用期间可通过 `flux_state` 读写状态、
+// 通过 `flux_http` 经宿主发送出站 HTTP 请求
+#[no_mangle]
+pub extern "C" fn flux_execute_v3(
+    input_ptr: *const c_char,
+    context_ptr: *const c_char,
+    host: *const FluxHost,
+) -> *mut c_char {{
+    FLUX_HOST.with(|cell| cell.set(host));
+    let result = flux_execute_v2(input_ptr, context_ptr);
+    FLUX_HOST.with(|cell| cell.set(std::ptr::null()));
+    result
+}}
+
+// 读写本函数的键值状态：写入为后写者胜，`compare_and_swap` 只在版本相符时写入
+// （版本 0 表示键不存在）；返回写入后的版本号
+#[allow(dead_code)]
+pub mod flux_state {{
+    use super::FLUX_HOST;
+    use std::ffi::{{CStr, CString}};
+    use std::os::raw::c_char;
+
+    pub(crate) fn call(
+        what: &str,
+        min_version: u32,
+        invoke: impl FnOnce(&super::FluxHost) -> *mut c_char,
+    ) -> Result<serde_json::Value, String> {{
+        let host = FLUX_HOST.with(|cell| cell.get());
+        if host.is_null() || unsafe {{ (*host).version }} < min_version {{
+            return Err(format!("{{what}} is not available to this invocation"));
+        }}
+        let host = unsafe {{ &*host }};
+        let ptr = invoke(host);
+        if ptr.is_null() {{
+            return Err(format!("{{what}} call failed"));
+        }}
+        let text = unsafe {{ CStr::from_ptr(ptr) }}.to_string_lossy().into_owned();
+        (host.free_string)(ptr);
+        let reply: serde_json::Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        match reply.get("error").and_then(|error| error.as_str()) {{
+            Some(error) => Err(error.to_string()),
+            None => Ok(reply.get("data").cloned().unwrap_or(serde_json::Value::Null)),
+        }}
+    }}
+
+    pub(crate) fn c_string(text: &str) -> Result<CString, String> {{
+        CString::new(text).map_err(|e| e.to_string())
+    }}
+
+    // 键的当前值和版本（`{{"value", "version", "updated_at", "expires_at"}}`）
+    pub fn entry(key: &str) -> Result<Option<serde_json::Value>, String> {{
+        let key = c_string(key)?;
+        let data = call("State", 1, |host| (host.state_get)(host.ctx, key.as_ptr()))?;
+        Ok((!data.is_null()).then_some(data))
+    }}
+
+    pub fn get(key: &str) -> Result<Option<serde_json::Value>, String> {{
+        Ok(entry(key)?.map(|entry| entry["value"].clone()))
+    }}
+
+    pub fn set(key: &str, value: serde_json::Value, ttl_secs: Option<u64>) -> Result<u64, String> {{
+        write(key, serde_json::json!({{"value": value, "ttl_secs": ttl_secs}}))
+    }}
+
+    pub fn compare_and_swap(
+        key: &str,
+        expected_version: u64,
+        value: serde_json::Value,
+        ttl_secs: Option<u64>,
+    ) -> Result<u64, String> {{
+        write(
+            key,
+            serde_json::json!({{
+                "value": value,
+                "ttl_secs": ttl_secs,
+                "expected_version": expected_version,
+            }}),
+        )
+    }}
+
+    fn write(key: &str, request: serde_json::Value) -> Result<u64, String> {{
+        let key = c_string(key)?;
+        let request = c_string(&request.to_string())?;
+        let data = call("State", 1, |host| {{
+            (host.state_set)(host.ctx, key.as_ptr(), request.as_ptr())
+        }})?;
+        Ok(data["version"].as_u64().unwrap_or(0))
+    }}
+}}
+
+// 经宿主发送出站 HTTP 请求（函数需配置 `egress` 允许列表）：请求为
+// {{"method", "url", "headers", "body", "base64"}}，返回 {{"status", "headers", "body", "base64"}}
+#[allow(dead_code)]
+pub mod flux_http {{
+    pub fn fetch(request: serde_json::Value) -> Result<serde_json::Value, String> {{
+        let request = super::flux_state::c_string(&request.to_string())?;
+        super::flux_state::call("HTTP fetch", 2, |host| {{
+            (host.http_fetch)(host.ctx, request.as_ptr())
+        }})
+    }}
+
+    pub fn get(url: &str) -> Result<serde_json::Value, String> {{
+        fetch(serde_json::json!({{"method": "GET", "url": url}}))
+    }}
+}}
+
+// 读取 C 字符串中的 JSON，空指针时为 None，非法 JSON 视为 null
+fn read_json(ptr: *const c_char) -> Option<serde_json::Value> {{
+    if ptr.is_null() {{
+        return None;
+    }}
+    let text = unsafe {{ CStr::from_ptr(ptr) }}.to_str().ok()?;
+    Some(serde_json::from_str(text).unwrap_or(serde_json::Value::Null))
+}}
+
 // 释放内存的函数
 #[no_mangle]
 pub extern "C" fn flux_free_string(ptr: *mut c_char) {{
@@ -258,66 +735,83 @@ pub extern "C" fn flux_free_string(ptr: *mut c_char) {{
     }}
 }}
 
-// 用户函数执行入口
-fn execute_user_function(input: serde_json::Value) -> serde_json::Value {{
-    // 简化版本：直接执行字符串形式的代码
-    // 实际实现中需要更复杂的代码解析和执行逻辑
-    match input {{
-        serde_json::Value::Object(ref map) => {{
-            // 示例：如果是加法操作
-            if let (Some(a), Some(b)) = (map.get("a"), map.get("b")) {{
-                if let (Some(a_num), Some(b_num)) = (a.as_f64(), b.as_f64()) {{
-                    return serde_json::json!({{ "result": a_num + b_num }});
-                }}
-            }}
-
-            // 默认行为：返回处理过的输入
-            serde_json::json!({{
-                "message": "Function executed",
-                "input": input,
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            }})
-        }},
-        _ => {{
-            serde_json::json!({{
-                "message": "Hello from compiled function",
-                "input": input,
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            }})
-        }}
-    }}
-}}
-"#
+{entry}"#
         );
 
         Ok(wrapped_code)
     }
 
+    /// 编译函数时生成的项目文件（相对路径和内容）：`Cargo.toml`、包装后的 `src/lib.rs` 和包内的其余模块
+    pub fn project_files(&self, function: &FunctionMetadata) -> Result<Vec<(String, String)>> {
+        let crate_name = Self::crate_name(&Self::compile_key(function));
+        let mut files = vec![
+            ("Cargo.toml".to_string(), Self::cargo_toml(&crate_name)),
+            ("src/lib.rs".to_string(), self.wrap_user_code(function)?),
+        ];
+        if let Some(package) = &function.package {
+            files.extend(
+                package
+                    .files
+                    .iter()
+                    .filter(|file| file.path != package.entrypoint)
+                    .map(|file| (format!("src/{}", file.path), file.content.clone())),
+            );
+        }
+        Ok(files)
+    }
+
+    /// `rustc --version` 和 `cargo --version` 的输出（进程内只查询一次，命令不可用时省略）
+    pub fn toolchain(&self) -> Vec<(String, String)> {
+        static TOOLCHAIN: OnceLock<Vec<(String, String)>> = OnceLock::new();
+        let rustc = self
+            .config
+            .rustc_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("rustc"));
+        TOOLCHAIN
+            .get_or_init(|| {
+                [("rustc", rustc), ("cargo", PathBuf::from("cargo"))]
+                    .into_iter()
+                    .filter_map(|(name, program)| {
+                        let output = Command::new(program).arg("--version").output().ok()?;
+                        output.status.success().then(|| {
+                            let version = String::from_utf8_lossy(&output.stdout);
+                            (name.to_string(), version.trim().to_string())
+                        })
+                    })
+                    .collect()
+            })
+            .clone()
+    }
+
     /// 生成Cargo.toml文件
-    fn generate_cargo_toml(&self, function: &FunctionMetadata, work_dir: &Path) -> Result<PathBuf> {
-        let clean_name = function.name.replace(['-', ' '], "_");
-        let cargo_content = format!(
+    fn generate_cargo_toml(&self, crate_name: &str, work_dir: &Path) -> Result<PathBuf> {
+        let cargo_file = work_dir.join("Cargo.toml");
+        fs::write(&cargo_file, Self::cargo_toml(crate_name))
+            .with_context(|| format!("Failed to write Cargo.toml: {cargo_file:?}"))?;
+
+        Ok(cargo_file)
+    }
+
+    /// 函数项目的 Cargo.toml 内容
+    fn cargo_toml(crate_name: &str) -> String {
+        format!(
             r#"[package]
-name = "flux_function_{clean_name}"
+name = "{crate_name}"
 version = "0.1.0"
 edition = "2021"
 
 [lib]
-name = "flux_function_{clean_name}"
+name = "{crate_name}"
 crate-type = ["cdylib"]
 
 [dependencies]
 serde = {{ version = "1.0", features = ["derive"] }}
 serde_json = "1.0"
+anyhow = "1.0"
 chrono = {{ version = "0.4", features = ["serde"] }}
 "#
-        );
-
-        let cargo_file = work_dir.join("Cargo.toml");
-        fs::write(&cargo_file, cargo_content)
-            .with_context(|| format!("Failed to write Cargo.toml: {cargo_file:?}"))?;
-
-        Ok(cargo_file)
+        )
     }
 
     /// 编译为动态库
@@ -325,18 +819,9 @@ chrono = {{ version = "0.4", features = ["serde"] }}
         &self,
         _rustc_path: &Path,
         _source_file: &Path,
+        crate_name: &str,
         work_dir: &Path,
     ) -> Result<PathBuf> {
-        let _output_name = if cfg!(target_os = "windows") {
-            "flux_function.dll"
-        } else if cfg!(target_os = "macos") {
-            "libflux_function.dylib"
-        } else {
-            "libflux_function.so"
-        };
-
-        let _output_path = work_dir.join(_output_name);
-
         // 使用cargo build而不是直接rustc，以便处理依赖
         let mut cmd = Command::new("cargo");
         cmd.arg("build")
@@ -382,109 +867,157 @@ chrono = {{ version = "0.4", features = ["serde"] }}
             work_dir.join("target").join("release")
         };
 
-        // 寻找实际生成的库文件
-        for entry in fs::read_dir(&target_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    if ext == "so" || ext == "dylib" || ext == "dll" {
-                        return Ok(path);
-                    }
-                }
-            }
+        // 只取本次编译的库文件，共享 target 目录中可能有其他函数的产物
+        let library_path = target_dir.join(Self::library_filename(crate_name));
+        if library_path.is_file() {
+            Ok(library_path)
+        } else {
+            Err(anyhow::anyhow!(
+                "Compiled library not found: {library_path:?}"
+            ))
         }
-
-        Err(anyhow::anyhow!(
-            "Compiled library not found in target directory"
-        ))
     }
 
     /// 将库文件复制到缓存目录
-    fn cache_library(
-        &self,
-        function_name: &str,
-        source_hash: &str,
-        library_path: &Path,
-    ) -> Result<PathBuf> {
-        let cache_filename = format!(
-            "{}_{}.{}",
-            function_name,
-            source_hash,
-            library_path
-                .extension()
-                .and_then(OsStr::to_str)
-                .unwrap_or("so")
-        );
-        let cached_path = self.config.cache_dir.join(cache_filename);
+    fn cache_library(&self, key: &CompileKey, library_path: &Path) -> Result<PathBuf> {
+        let extension = library_path
+            .extension()
+            .and_then(OsStr::to_str)
+            .unwrap_or("so");
+        let cached_path = self.cached_library_path(key, extension);
+        // 路径按内容寻址，已存在的库文件可能正被执行中的调用加载，不覆盖
+        if cached_path.exists() {
+            return Ok(cached_path);
+        }
 
-        fs::copy(library_path, &cached_path)
+        // 先写临时文件再重命名，避免其他进程加载到写了一半的库
+        let partial_path = cached_path.with_extension(format!("{extension}.partial"));
+        fs::copy(library_path, &partial_path)
+            .with_context(|| format!("Failed to cache library to: {partial_path:?}"))?;
+        fs::rename(&partial_path, &cached_path)
             .with_context(|| format!("Failed to cache library to: {cached_path:?}"))?;
 
         Ok(cached_path)
     }
 
+    /// 缓存目录中的库文件路径（按函数名和源代码哈希区分）
+    fn cached_library_path(&self, key: &CompileKey, extension: &str) -> PathBuf {
+        self.config
+            .cache_dir
+            .join(format!("{}.{extension}", Self::artifact_stem(key)))
+    }
+
     /// 获取缓存的编译结果
-    async fn get_cached_function(
-        &self,
-        function_name: &str,
-        source_hash: &str,
-    ) -> Option<CompiledFunction> {
-        let compiled_functions = self.compiled_functions.read().await;
-        if let Some(compiled) = compiled_functions.get(function_name) {
-            if compiled.source_hash == source_hash && compiled.library_path.exists() {
+    async fn get_cached_function(&self, key: &CompileKey) -> Option<CompiledFunction> {
+        {
+            let compiled_functions = self.compiled_functions.read().await;
+            if let Some(compiled) = compiled_functions.get(key)
+                && compiled.library_path.exists()
+            {
+                self.touch(key);
                 return Some(compiled.clone());
             }
         }
 
-        // 检查磁盘缓存
-        let cache_filename = format!("{function_name}_{source_hash}.so");
-        let cached_path = self.config.cache_dir.join(cache_filename);
+        None
+    }
 
-        if cached_path.exists() {
-            // TODO: 从缓存重建CompiledFunction
-            None
-        } else {
-            None
+    /// 移除函数不在 `keep_hashes` 中的源代码版本的编译产物（只保留仍可调用的版本）
+    pub async fn evict_stale(&self, name: &str, keep_hashes: &HashSet<String>) -> usize {
+        self.evict_matching(|(n, hash)| n == name && !keep_hashes.contains(hash))
+            .await
+    }
+
+    /// 移除一个编译键的编译产物，返回是否存在
+    pub async fn evict(&self, key: &CompileKey) -> bool {
+        self.evict_matching(|candidate| candidate == key).await > 0
+    }
+
+    /// 只保留 `keep` 中编译键的编译产物，返回移除的数量
+    pub async fn retain_keys(&self, keep: &HashSet<CompileKey>) -> usize {
+        self.evict_matching(|key| !keep.contains(key)).await
+    }
+
+    /// 移除编译键满足条件的编译产物，库文件退役后延迟删除
+    async fn evict_matching(&self, stale: impl Fn(&CompileKey) -> bool) -> usize {
+        let mut compiled_functions = self.compiled_functions.write().await;
+        let stale: Vec<CompileKey> = compiled_functions
+            .keys()
+            .filter(|key| stale(key))
+            .cloned()
+            .collect();
+        for key in &stale {
+            if let Some(compiled) = compiled_functions.remove(key) {
+                self.retire(key, compiled);
+            }
         }
+        drop(compiled_functions);
+        self.sweep_retired();
+        stale.len()
+    }
+
+    /// 退役一个编译产物：不再作为函数的当前产物，正在执行的调用仍持有其库句柄
+    fn retire(&self, key: &CompileKey, compiled: CompiledFunction) {
+        let last_used = self
+            .last_used
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key);
+        self.artifacts.retire(key, compiled.library_path, last_used);
+    }
+
+    /// 删除无人持有且超过宽限期的退役产物，返回 (删除的文件数, 释放字节数)
+    pub fn sweep_retired(&self) -> (usize, u64) {
+        self.artifacts
+            .sweep(Duration::from_secs(self.config.artifact_grace_secs))
+    }
+
+    /// 执行中持有编译产物库句柄的调用数
+    pub fn artifact_holders(&self, key: &CompileKey) -> usize {
+        self.artifacts.holders(key)
+    }
+
+    /// 函数当前使用的编译产物（最近一次试加载成功的编译键）
+    pub fn current_artifact(&self, name: &str) -> Option<CompileKey> {
+        self.artifacts.current(name)
     }
 
     /// 缓存编译结果
-    async fn cache_compiled_function(&self, function_name: &str, compiled: CompiledFunction) {
+    pub(crate) async fn cache_compiled_function(
+        &self,
+        key: &CompileKey,
+        compiled: CompiledFunction,
+    ) {
         let mut compiled_functions = self.compiled_functions.write().await;
-        compiled_functions.insert(function_name.to_string(), compiled);
-
-        // 限制缓存大小
-        if compiled_functions.len() > self.config.max_cache_entries {
-            // 移除最旧的条目（简化版本）
-            if let Some(oldest_key) = compiled_functions.keys().next().cloned() {
-                compiled_functions.remove(&oldest_key);
-            }
+        compiled_functions.insert(key.clone(), compiled);
+        self.touch(key);
+
+        // 限制缓存大小，移除最早编译的条目
+        if compiled_functions.len() > self.config.max_cache_entries
+            && let Some(oldest_key) = compiled_functions
+                .iter()
+                .min_by_key(|(_, compiled)| compiled.compiled_at)
+                .map(|(key, _)| key.clone())
+            && let Some(oldest) = compiled_functions.remove(&oldest_key)
+        {
+            self.retire(&oldest_key, oldest);
         }
     }
 
     /// 执行编译后的函数
+    #[tracing::instrument(name = "invoke_library", skip_all, fields(function = %compiled.metadata.name))]
     pub async fn execute_compiled_function(
         &self,
         compiled: &CompiledFunction,
         request: &InvokeRequest,
+        context: &InvocationContext,
     ) -> Result<InvokeResponse> {
         let start_time = std::time::Instant::now();
 
-        // 加载动态库
-        let library = unsafe {
-            Library::new(&compiled.library_path)
-                .with_context(|| format!("Failed to load library: {:?}", compiled.library_path))?
-        };
-
-        // 获取函数符号
-        let flux_execute: Symbol<
-            unsafe extern "C" fn(*const std::os::raw::c_char) -> *mut std::os::raw::c_char,
-        > = unsafe {
-            library
-                .get(b"flux_execute")
-                .context("Function 'flux_execute' not found in library")?
-        };
+        // 调用开始时解析共享的库句柄，执行期间持有，函数被更新时库文件也不会被删除
+        let key = (compiled.metadata.name.clone(), compiled.source_hash.clone());
+        let handle = self.artifacts.resolve(&key, &compiled.library_path)?;
+        let library = handle.library();
 
         let flux_free: Symbol<unsafe extern "C" fn(*mut std::os::raw::c_char)> = unsafe {
             library
@@ -492,20 +1025,71 @@ chrono = {{ version = "0.4", features = ["serde"] }}
                 .context("Function 'flux_free_string' not found in library")?
         };
 
-        // 准备输入
+        // 准备输入和调用上下文（剩余时间按当前时刻计算）
         let input_json =
             serde_json::to_string(&request.input).context("Failed to serialize input")?;
         let input_cstring =
             std::ffi::CString::new(input_json).context("Failed to create input C string")?;
-
-        // 调用函数
-        let result_ptr = unsafe { flux_execute(input_cstring.as_ptr()) };
+        let context_json =
+            serde_json::to_string(&context.snapshot()).context("Failed to serialize context")?;
+        let context_cstring =
+            std::ffi::CString::new(context_json).context("Failed to create context C string")?;
+
+        // 调用函数：有状态访问或出站 HTTP 授权时使用带宿主回调的第三版 ABI，其次是带调用上下文的第二版，
+        // 旧的编译产物只导出 `flux_execute`
+        type ExecuteV1 = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+        type ExecuteV2 = unsafe extern "C" fn(*const c_char, *const c_char) -> *mut c_char;
+        type ExecuteV3 =
+            unsafe extern "C" fn(*const c_char, *const c_char, *const FluxHost) -> *mut c_char;
+        let host_context = HostContext {
+            state: context.state.as_ref(),
+            egress: context.egress.as_ref(),
+        };
+        let execute_v3 = if host_context.state.is_some() || host_context.egress.is_some() {
+            unsafe { library.get::<ExecuteV3>(b"flux_execute_v3") }
+                .ok()
+                .map(|execute| (execute, FluxHost::new(&host_context)))
+        } else {
+            None
+        };
+        let result_ptr = match (execute_v3, unsafe {
+            library.get::<ExecuteV2>(b"flux_execute_v2")
+        }) {
+            (Some((flux_execute_v3, host)), _) => unsafe {
+                flux_execute_v3(input_cstring.as_ptr(), context_cstring.as_ptr(), &host)
+            },
+            (None, Ok(flux_execute_v2)) => unsafe {
+                flux_execute_v2(input_cstring.as_ptr(), context_cstring.as_ptr())
+            },
+            (None, Err(_)) => {
+                let flux_execute: Symbol<ExecuteV1> = unsafe {
+                    library
+                        .get(b"flux_execute")
+                        .context("Function 'flux_execute' not found in library")?
+                };
+                unsafe { flux_execute(input_cstring.as_ptr()) }
+            }
+        };
 
         if result_ptr.is_null() {
             return Ok(InvokeResponse {
                 output: serde_json::json!({"error": "Function returned null"}),
                 execution_time_ms: start_time.elapsed().as_millis() as u64,
-                status: ExecutionStatus::Error("Function execution failed".to_string()),
+                status: ExecutionStatus::error(ErrorKind::Runtime, "Function returned null"),
+                request_id: None,
+                attempts_made: 1,
+                succeeded_on_retry: false,
+                cold_start: false,
+                output_schema_violations: None,
+                backend: None,
+                replayed: false,
+                chaos_injected: false,
+                return_type_mismatch: false,
+                timing: InvocationTiming::in_process(start_time.elapsed(), None),
+                termination: None,
+                execution_path: None,
+                peer: None,
+                redirected_from: None,
             });
         }
 
@@ -527,9 +1111,118 @@ chrono = {{ version = "0.4", features = ["serde"] }}
             output,
             execution_time_ms,
             status: ExecutionStatus::Success,
+            request_id: None,
+            attempts_made: 1,
+            succeeded_on_retry: false,
+            cold_start: false,
+            output_schema_violations: None,
+            backend: None,
+            replayed: false,
+            chaos_injected: false,
+            return_type_mismatch: false,
+            timing: InvocationTiming::in_process(start_time.elapsed(), None),
+            termination: None,
+            execution_path: None,
+            peer: None,
+            redirected_from: None,
         })
     }
 
+    /// 记录编译产物被使用
+    fn touch(&self, key: &CompileKey) {
+        self.last_used
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.clone(), Instant::now());
+    }
+
+    /// 编译产物缓存目录
+    pub fn cache_dir(&self) -> &Path {
+        &self.config.cache_dir
+    }
+
+    /// 共享的 cargo target 目录（展开 `~`），未配置时每次编译使用独立的临时目录
+    pub fn target_dir(&self) -> Option<PathBuf> {
+        self.config
+            .rust_target_dir
+            .as_ref()
+            .map(|dir| PathBuf::from(shellexpand::tilde(&dir.to_string_lossy()).to_string()))
+    }
+
+    /// 编译索引和退役中的产物仍在引用的动态库路径
+    pub async fn referenced_artifacts(&self) -> HashSet<PathBuf> {
+        let mut referenced: HashSet<PathBuf> = self
+            .compiled_functions
+            .read()
+            .await
+            .values()
+            .map(|compiled| compiled.library_path.clone())
+            .collect();
+        referenced.extend(self.artifacts.retired_paths());
+        referenced
+    }
+
+    /// 编译索引中仍在引用的 crate 名（用于识别共享 target 目录中的产物）
+    pub async fn referenced_crate_names(&self) -> HashSet<String> {
+        self.compiled_functions
+            .read()
+            .await
+            .keys()
+            .map(Self::crate_name)
+            .collect()
+    }
+
+    /// 按最近最少使用的顺序淘汰编译产物，直到释放 `bytes_to_free` 字节；
+    /// 闲置不足 `min_idle` 的产物不淘汰。返回 (淘汰数, 释放字节数)
+    pub async fn evict_lru(&self, bytes_to_free: u64, min_idle: Duration) -> (usize, u64) {
+        let mut compiled_functions = self.compiled_functions.write().await;
+        let mut candidates: Vec<(CompileKey, Duration)> = {
+            let last_used = self.last_used.lock().unwrap_or_else(|e| e.into_inner());
+            compiled_functions
+                .keys()
+                .map(|key| {
+                    let idle = last_used
+                        .get(key)
+                        .map(Instant::elapsed)
+                        .unwrap_or(Duration::MAX);
+                    (key.clone(), idle)
+                })
+                .filter(|(_, idle)| *idle >= min_idle)
+                .collect()
+        };
+        candidates.sort_by_key(|(_, idle)| std::cmp::Reverse(*idle));
+
+        let (mut evicted, mut released) = (0, 0);
+        for (key, _) in candidates {
+            if released >= bytes_to_free {
+                break;
+            }
+            let Some(compiled) = compiled_functions.remove(&key) else {
+                continue;
+            };
+            let size = fs::metadata(&compiled.library_path)
+                .map(|m| m.len())
+                .unwrap_or(0);
+            self.retire(&key, compiled);
+            released += size;
+            evicted += 1;
+            tracing::info!(
+                "Evicted compiled artifact for function '{}' ({} bytes)",
+                key.0,
+                size
+            );
+        }
+        drop(compiled_functions);
+        // 仍被执行中的调用持有的产物要等句柄释放后才删除，只计入实际释放的字节
+        let (_, freed) = self.sweep_retired();
+        (evicted, freed)
+    }
+
+    /// 缓存中的编译产物数
+    pub async fn compiled_count(&self) -> usize {
+        self.compiled_functions.read().await.len()
+    }
+
     /// 获取编译统计信息
     pub async fn get_stats(&self) -> HashMap<String, serde_json::Value> {
         let compiled_functions = self.compiled_functions.read().await;
@@ -556,16 +1249,24 @@ chrono = {{ version = "0.4", features = ["serde"] }}
             serde_json::Value::Number(avg_compile_time.into()),
         );
 
+        stats.insert(
+            "retired_artifacts_count".to_string(),
+            serde_json::Value::Number(self.artifacts.retired_count().into()),
+        );
+
         stats
     }
 
     /// 清理缓存
     pub async fn clear_cache(&self) -> Result<()> {
-        // 清理内存缓存
+        // 清理内存缓存，产物退役后延迟删除
         {
             let mut compiled_functions = self.compiled_functions.write().await;
-            compiled_functions.clear();
+            for (key, compiled) in compiled_functions.drain() {
+                self.retire(&key, compiled);
+            }
         }
+        self.sweep_retired();
 
         // 清理临时目录
         {
@@ -573,17 +1274,18 @@ chrono = {{ version = "0.4", features = ["serde"] }}
             temp_dirs.clear();
         }
 
-        // 清理磁盘缓存（可选）
+        // 清理磁盘缓存（可选），跳过仍在宽限期内的退役产物
         if self.config.cache_dir.exists() {
+            let retired: HashSet<PathBuf> = self.artifacts.retired_paths().into_iter().collect();
             for entry in fs::read_dir(&self.config.cache_dir)? {
                 let entry = entry?;
                 let path = entry.path();
-                if path.is_file() {
-                    if let Some(ext) = path.extension() {
-                        if ext == "so" || ext == "dylib" || ext == "dll" {
-                            let _ = fs::remove_file(path);
-                        }
-                    }
+                if path.is_file()
+                    && !retired.contains(&path)
+                    && let Some(ext) = path.extension()
+                    && (ext == "so" || ext == "dylib" || ext == "dll")
+                {
+                    let _ = fs::remove_file(path);
                 }
             }
         }
@@ -614,6 +1316,7 @@ mod tests {
         assert_eq!(config.opt_level, 2);
         assert!(!config.debug);
         assert_eq!(config.compile_timeout_secs, 30);
+        assert!(config.max_concurrent_builds >= 1);
     }
 
     #[tokio::test]
@@ -622,11 +1325,157 @@ mod tests {
         let compiler = RustCompiler::new(config).unwrap();
 
         let user_code = "fn test() -> i32 { 42 }";
-        let wrapped = compiler.wrap_user_code(user_code).unwrap();
+        let function = FunctionMetadata::new("test".to_string(), user_code.to_string());
+        let wrapped = compiler.wrap_user_code(&function).unwrap();
 
         assert!(wrapped.contains(user_code));
-        assert!(wrapped.contains("flux_execute"));
         assert!(wrapped.contains("flux_free_string"));
+        // 第二版 ABI 接收调用上下文，第一版入口转发到第二版（上下文为 null）
+        assert!(wrapped.contains("pub extern \"C\" fn flux_execute_v2("));
+        assert!(wrapped.contains("flux_execute_v2(input_ptr, std::ptr::null())"));
+        assert!(wrapped.contains("execute_user_function(input_json, context_json)"));
+        // 第三版 ABI 另外传入宿主回调，用户代码通过 `flux_state` 读写状态、通过 `flux_http` 发送请求
+        assert!(wrapped.contains("pub extern \"C\" fn flux_execute_v3("));
+        assert!(wrapped.contains("pub mod flux_state"));
+        assert!(wrapped.contains("pub mod flux_http"));
+    }
+
+    #[tokio::test]
+    async fn test_typed_handler_binding() {
+        let compiler = RustCompiler::new(CompilerConfig::default()).unwrap();
+        let user_code = "fn handler(a: i64, b: String) -> anyhow::Result<f64> { Ok(a as f64) }";
+        let mut function = FunctionMetadata::new("typed".to_string(), user_code.to_string());
+        let raw_key = RustCompiler::compile_key(&function);
+        function.parameters = ["a", "b"]
+            .iter()
+            .zip(["integer", "string"])
+            .map(|(name, param_type)| crate::functions::FunctionParameter {
+                name: name.to_string(),
+                param_type: param_type.to_string(),
+                description: None,
+                required: true,
+                default_value: None,
+            })
+            .collect();
+        function.return_type = "f64".to_string();
+
+        let wrapped = compiler.wrap_user_code(&function).unwrap();
+        assert!(wrapped.contains(user_code));
+        assert!(wrapped.contains("struct FluxArgs"));
+        assert!(wrapped.contains("handler(args.p0, args.p1)"));
+        assert!(!wrapped.contains("Function executed"));
+        // 声明的签名参与编译键，修改参数或返回类型会重新编译
+        let typed_key = RustCompiler::compile_key(&function);
+        assert_ne!(typed_key, raw_key);
+        function.return_type = "i64".to_string();
+        assert_ne!(RustCompiler::compile_key(&function), typed_key);
+    }
+
+    #[test]
+    fn test_package_files_laid_out_under_src() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let compiler = RustCompiler::new(CompilerConfig {
+            cache_dir: temp_dir.path().join("cache"),
+            ..Default::default()
+        })
+        .unwrap();
+        let request: crate::functions::RegisterFunctionRequest =
+            serde_json::from_value(serde_json::json!({
+                "name": "pkg",
+                "entrypoint": "main.rs",
+                "files": [
+                    {"path": "main.rs", "content": "mod util;"},
+                    {"path": "util/mod.rs", "content": "pub fn one() -> i32 { 1 }"}
+                ]
+            }))
+            .unwrap();
+        let function = FunctionMetadata::from_request(request);
+
+        let work_dir = temp_dir.path().join("work");
+        let lib = compiler.generate_source_file(&function, &work_dir).unwrap();
+        assert!(fs::read_to_string(lib).unwrap().contains("mod util;"));
+        assert!(work_dir.join("src/util/mod.rs").is_file());
+        // 入口文件只以 lib.rs 的形式出现，避免被当作二进制目标
+        assert!(!work_dir.join("src/main.rs").exists());
+    }
+
+    #[tokio::test]
+    async fn test_artifacts_keyed_by_name_and_source() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let compiler = RustCompiler::new(CompilerConfig {
+            cache_dir: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let v1 = FunctionMetadata::new("my-fn".to_string(), "fn a() {}".to_string());
+        let v2 = FunctionMetadata::new("my-fn".to_string(), "fn b() {}".to_string());
+        let (key1, key2) = (
+            RustCompiler::compile_key(&v1),
+            RustCompiler::compile_key(&v2),
+        );
+
+        assert_ne!(key1, key2);
+        assert_ne!(
+            RustCompiler::crate_name(&key1),
+            RustCompiler::crate_name(&key2)
+        );
+        assert_ne!(
+            compiler.cached_library_path(&key1, "so"),
+            compiler.cached_library_path(&key2, "so")
+        );
+        assert!(RustCompiler::crate_name(&key1).starts_with("flux_function_my_fn_"));
+
+        // 同一编译键共享一把锁，释放后从映射表移除
+        let lock = compiler.compile_lock(&key1);
+        assert!(Arc::ptr_eq(&lock, &compiler.compile_lock(&key1)));
+        assert!(!Arc::ptr_eq(&lock, &compiler.compile_lock(&key2)));
+        compiler.release_compile_lock(&key1, lock);
+        assert!(!compiler.compile_locks.lock().unwrap().contains_key(&key1));
+    }
+
+    #[tokio::test]
+    async fn test_evict_lru_keeps_recently_used_artifacts() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let compiler = RustCompiler::new(CompilerConfig {
+            cache_dir: temp_dir.path().to_path_buf(),
+            artifact_grace_secs: 0,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut keys = Vec::new();
+        for name in ["old", "new"] {
+            let function = FunctionMetadata::new(name.to_string(), format!("fn {name}() {{}}"));
+            let key = RustCompiler::compile_key(&function);
+            let library_path = compiler.cached_library_path(&key, "so");
+            fs::write(&library_path, vec![0u8; 1024]).unwrap();
+            compiler
+                .cache_compiled_function(
+                    &key,
+                    CompiledFunction {
+                        metadata: function,
+                        library_path,
+                        compiled_at: chrono::Utc::now(),
+                        source_hash: key.1.clone(),
+                        compile_time_ms: 0,
+                        handler_mode: HandlerMode::RawJson,
+                    },
+                )
+                .await;
+            keys.push(key);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        // 再次使用 old，new 变为最久未使用
+        assert!(compiler.get_cached_function(&keys[0]).await.is_some());
+
+        assert_eq!(compiler.evict_lru(1, Duration::ZERO).await, (1, 1024));
+        assert!(compiler.get_cached_function(&keys[0]).await.is_some());
+        assert!(compiler.get_cached_function(&keys[1]).await.is_none());
+        assert_eq!(
+            compiler.evict_lru(u64::MAX, Duration::from_secs(60)).await,
+            (0, 0)
+        );
     }
 
     #[test]
@@ -0,0 +1,255 @@
+//! 调用的 CPU 时间
+//!
+//! 沙箱按累计 CPU 时间（用户态加内核态）统计每次调用：Linux 上读取 `/proc/<pid>/stat` 的
+//! `utime`、`stime` 和已回收子进程的 `cutime`、`cstime`，并累加仍在运行的子进程。进程退出后、
+//! 回收前再读取一次，因此短于采样间隔的调用也能得到准确的用时。没有 `/proc` 时按采样到的 CPU
+//! 使用率对时间积分估算，结果中的 [`CpuAccounting`] 注明采用的方式。
+//!
+//! 函数设置了 `max_cpu_time_ms` 时，累计 CPU 时间超出上限的进程在下一次采样时被终止，
+//! 与执行超时（墙钟时间）相互独立：空闲机器上空转的函数同样会被终止。常驻工作进程按调用前后
+//! 工作进程累计 CPU 时间的差值统计。
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// CPU 时间的统计方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CpuAccounting {
+    /// 进程及其子进程的累计 CPU 时间（`/proc/<pid>/stat`）
+    ProcStat,
+    /// 按采样的 CPU 使用率积分估算（没有 `/proc` 或无法读取进程的统计）
+    #[default]
+    Sampled,
+}
+
+/// 每秒的时钟滴答数（`/proc/<pid>/stat` 中 CPU 时间的单位）
+fn clock_ticks() -> u64 {
+    static TICKS: OnceLock<u64> = OnceLock::new();
+    *TICKS.get_or_init(|| {
+        #[cfg(unix)]
+        {
+            let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+            if ticks > 0 {
+                return ticks as u64;
+            }
+        }
+        100
+    })
+}
+
+/// `/proc/<pid>/stat` 中进程自身和已回收子进程的 CPU 时间（时钟滴答）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StatTicks {
+    own: u64,
+    children: u64,
+}
+
+fn parse_stat(text: &str) -> Option<StatTicks> {
+    // 进程名可能包含空格和括号，字段从最后一个 `)` 之后开始（第一个是进程状态）
+    let fields: Vec<&str> = text
+        .get(text.rfind(')')? + 1..)?
+        .split_whitespace()
+        .collect();
+    let field = |index: usize| fields.get(index)?.parse::<u64>().ok();
+    Some(StatTicks {
+        own: field(11)?.saturating_add(field(12)?),
+        children: field(13)?.saturating_add(field(14)?),
+    })
+}
+
+/// 仍在运行的直接子进程
+fn children(pid: u32) -> Vec<u32> {
+    let Ok(tasks) = std::fs::read_dir(format!("/proc/{pid}/task")) else {
+        return Vec::new();
+    };
+    tasks
+        .flatten()
+        .filter_map(|task| std::fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|text| {
+            text.split_whitespace()
+                .filter_map(|pid| pid.parse().ok())
+                .collect::<Vec<u32>>()
+        })
+        .collect()
+}
+
+/// 进程及其所有子进程（包括已回收的）的累计 CPU 时间（毫秒）；
+/// 非 Linux 平台、进程已被回收或没有权限时为 `None`
+pub fn proc_cpu_time_ms(pid: u32) -> Option<u64> {
+    if !cfg!(target_os = "linux") || pid == 0 {
+        return None;
+    }
+    let root = parse_stat(&std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?)?;
+    let mut ticks = root.own.saturating_add(root.children);
+    let mut pending = children(pid);
+    // 子进程在读取期间退出时跳过（其用时在被回收后计入父进程的 cutime）
+    while let Some(child) = pending.pop() {
+        if let Some(stat) = std::fs::read_to_string(format!("/proc/{child}/stat"))
+            .ok()
+            .as_deref()
+            .and_then(parse_stat)
+        {
+            ticks = ticks.saturating_add(stat.own).saturating_add(stat.children);
+            pending.extend(children(child));
+        }
+    }
+    Some(ticks.saturating_mul(1000) / clock_ticks())
+}
+
+/// 等待子进程退出但不回收它，之后仍可以读取它最终的 CPU 时间（非 Linux 平台立即返回）
+pub async fn wait_exited(pid: u32) {
+    #[cfg(target_os = "linux")]
+    if pid != 0 {
+        let _ = tokio::task::spawn_blocking(move || {
+            let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+            loop {
+                let waited = unsafe {
+                    libc::waitid(
+                        libc::P_PID,
+                        pid as libc::id_t,
+                        &mut info,
+                        libc::WEXITED | libc::WNOWAIT,
+                    )
+                };
+                if waited == 0
+                    || std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted
+                {
+                    break;
+                }
+            }
+        })
+        .await;
+    }
+}
+
+/// 一个沙箱进程的 CPU 时间采样器
+#[derive(Debug)]
+pub struct CpuSampler {
+    pid: u32,
+    /// 工作进程在本次调用开始前已使用的 CPU 时间（毫秒）
+    baseline_ms: u64,
+    proc_ms: u64,
+    sampled_ms: f64,
+    method: CpuAccounting,
+}
+
+impl CpuSampler {
+    pub fn new(pid: u32) -> Self {
+        Self {
+            pid,
+            baseline_ms: 0,
+            proc_ms: 0,
+            sampled_ms: 0.0,
+            method: CpuAccounting::Sampled,
+        }
+    }
+
+    /// 常驻工作进程：只统计从现在起新增的 CPU 时间
+    pub fn from_now(pid: u32) -> Self {
+        let mut sampler = Self::new(pid);
+        sampler.baseline_ms = proc_cpu_time_ms(pid).unwrap_or(0);
+        sampler
+    }
+
+    /// 读取一次进程的累计 CPU 时间，返回到目前为止的用时（毫秒）
+    pub fn sample(&mut self) -> u64 {
+        if let Some(ms) = proc_cpu_time_ms(self.pid) {
+            self.method = CpuAccounting::ProcStat;
+            self.proc_ms = self.proc_ms.max(ms.saturating_sub(self.baseline_ms));
+        }
+        self.cpu_time_ms()
+    }
+
+    /// 无法读取进程的累计 CPU 时间时，按 `elapsed` 内采样到的使用率（百分比）估算
+    pub fn add_sampled(&mut self, percent: f64, elapsed: Duration) {
+        if self.method == CpuAccounting::Sampled {
+            self.sampled_ms += percent.max(0.0) / 100.0 * elapsed.as_secs_f64() * 1000.0;
+        }
+    }
+
+    pub fn cpu_time_ms(&self) -> u64 {
+        match self.method {
+            CpuAccounting::ProcStat => self.proc_ms,
+            CpuAccounting::Sampled => self.sampled_ms as u64,
+        }
+    }
+
+    pub fn method(&self) -> CpuAccounting {
+        self.method
+    }
+}
+
+/// 一次调用的 CPU 时间记录句柄，随调用上下文传给沙箱（克隆后共享同一份数据，重试时各次尝试累加）
+#[derive(Debug, Clone, Default)]
+pub struct CpuReport(Arc<StdMutex<Option<u64>>>);
+
+impl PartialEq for CpuReport {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl CpuReport {
+    pub fn record(&self, cpu_time_ms: u64) {
+        let mut recorded = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        *recorded = Some(recorded.unwrap_or(0).saturating_add(cpu_time_ms));
+    }
+
+    /// 累计的 CPU 时间（毫秒），调用没有启动沙箱进程时为 `None`
+    pub fn cpu_time_ms(&self) -> Option<u64> {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stat_fields_are_parsed_after_the_command_name() {
+        let text = "4242 (my (odd) name) R 1 4242 4242 0 -1 4194560 120 0 0 0 \
+                    250 30 7 3 20 0 1 0 100 1000 200\n";
+        assert_eq!(
+            parse_stat(text),
+            Some(StatTicks {
+                own: 280,
+                children: 10
+            })
+        );
+        assert_eq!(parse_stat("4242 (short) R 1 2"), None);
+        assert_eq!(parse_stat("garbage"), None);
+    }
+
+    #[test]
+    fn test_sampler_measures_cpu_time_of_a_running_process() {
+        if !cfg!(target_os = "linux") || proc_cpu_time_ms(std::process::id()).is_none() {
+            return;
+        }
+        let mut sampler = CpuSampler::from_now(std::process::id());
+        assert!(sampler.sample() < 100);
+        // 空转直到累计 CPU 时间达到 100ms（墙钟时间取决于机器负载）
+        let start = std::time::Instant::now();
+        let mut spins = 0u64;
+        while sampler.sample() < 100 && start.elapsed() < Duration::from_secs(30) {
+            for _ in 0..100_000 {
+                spins = std::hint::black_box(spins + 1);
+            }
+        }
+        let used = sampler.cpu_time_ms();
+        assert_eq!(sampler.method(), CpuAccounting::ProcStat);
+        assert!(used >= 100, "{used}");
+
+        // 读取到累计值后不再按使用率估算
+        sampler.add_sampled(100.0, Duration::from_secs(10));
+        assert_eq!(sampler.cpu_time_ms(), used);
+
+        let report = CpuReport::default();
+        assert_eq!(report.cpu_time_ms(), None);
+        report.record(used);
+        report.clone().record(5);
+        assert_eq!(report.cpu_time_ms(), Some(used + 5));
+    }
+}
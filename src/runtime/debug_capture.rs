@@ -0,0 +1,755 @@
+//! 调试包：保存一次调用的完整执行现场，便于在本地复现失败
+//!
+//! 函数的 `debug_capture` 为 `on_failure` 或 `always` 时，运行时在执行过程中把生成的源代码树
+//! （Rust 函数的 `Cargo.toml` 和包装后的 `src/lib.rs`，或包装后的 JavaScript/Python 脚本）、
+//! 进程可见的环境变量名、工具链版本和标准输出/错误写入调用上下文中的 [`DebugTrace`]；
+//! 调用结束后由 [`DebugArtifactStore`] 连同输入打包为 `<调用 ID>.tar.gz`，清单另存为 `<调用 ID>.json`。
+//!
+//! 写入的所有内容都按函数的 `log_redaction` 脱敏：输入中匹配的字段被替换，这些字段的值也从
+//! 源代码、进程输出和错误信息中抹去；环境变量只记录变量名。调试包目录按总大小和保留时长淘汰，
+//! 未启用调试包的函数不收集任何信息。
+
+use crate::functions::redaction::{REDACTED, Redactor};
+use crate::functions::{ExecutionStatus, FluxError, FunctionMetadata, InvokeResponse, Result};
+use crate::scheduler::namespaces::script_type;
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant, SystemTime};
+use utoipa::ToSchema;
+
+/// 函数的调试包模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugCapture {
+    /// 不收集（默认）
+    #[default]
+    Off,
+    /// 只保存失败的调用
+    OnFailure,
+    /// 保存每次调用
+    Always,
+}
+
+impl DebugCapture {
+    pub fn is_off(&self) -> bool {
+        *self == Self::Off
+    }
+
+    /// 调用结果是否需要保存调试包
+    pub fn applies(self, success: bool) -> bool {
+        match self {
+            Self::Off => false,
+            Self::OnFailure => !success,
+            Self::Always => true,
+        }
+    }
+}
+
+/// 调试包配置（`[debug_capture]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugCaptureConfig {
+    /// 为 false 时忽略函数的 `debug_capture`，不收集也不写入调试包
+    pub enabled: bool,
+    /// 调试包目录
+    pub dir: PathBuf,
+    /// 所有调试包的总字节上限，超出时从最早的开始淘汰
+    pub max_total_bytes: u64,
+    /// 调试包的保留时长（秒）
+    pub max_age_secs: u64,
+    /// 标准输出和标准错误各自保留的字节上限，超出部分截断
+    pub max_output_bytes: usize,
+}
+
+impl Default for DebugCaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: default_dir(),
+            max_total_bytes: 256 * 1024 * 1024,
+            max_age_secs: 24 * 60 * 60,
+            max_output_bytes: 1024 * 1024,
+        }
+    }
+}
+
+fn default_dir() -> PathBuf {
+    if cfg!(unix) {
+        PathBuf::from("/tmp/flux_debug")
+    } else {
+        std::env::temp_dir().join("flux_debug")
+    }
+}
+
+/// 运行时在一次调用中收集的调试信息
+#[derive(Debug, Default)]
+struct TraceData {
+    /// 生成的源代码树（相对路径和内容）
+    sources: Vec<(String, String)>,
+    env: Vec<String>,
+    toolchain: BTreeMap<String, String>,
+    stdout: String,
+    stderr: String,
+}
+
+/// 一次调用的调试信息收集句柄，随调用上下文传给运行时（克隆后共享同一份数据，重试时后一次尝试覆盖前一次）
+#[derive(Debug, Clone, Default)]
+pub struct DebugTrace(Arc<StdMutex<TraceData>>);
+
+impl PartialEq for DebugTrace {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl DebugTrace {
+    fn data(&self) -> std::sync::MutexGuard<'_, TraceData> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 记录生成的源代码树
+    pub fn set_sources(&self, sources: Vec<(String, String)>) {
+        self.data().sources = sources;
+    }
+
+    /// 记录进程可见的环境变量名
+    pub fn set_env(&self, names: Vec<String>) {
+        self.data().env = names;
+    }
+
+    /// 记录一个工具链组件的版本（或路径）
+    pub fn set_toolchain(&self, name: impl Into<String>, version: impl Into<String>) {
+        self.data().toolchain.insert(name.into(), version.into());
+    }
+
+    /// 记录进程的标准输出和标准错误
+    pub fn set_output(&self, stdout: &str, stderr: &str) {
+        let mut data = self.data();
+        data.stdout = stdout.to_string();
+        data.stderr = stderr.to_string();
+    }
+
+    /// 记录的标准输出和标准错误（进程内执行的调用为空）
+    pub fn output(&self) -> (String, String) {
+        let data = self.data();
+        (data.stdout.clone(), data.stderr.clone())
+    }
+}
+
+/// 调试包中的一个文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BundleFile {
+    /// 包内相对路径（不含以调用 ID 命名的根目录）
+    pub path: String,
+    pub bytes: usize,
+    /// 是否因超出 `max_output_bytes` 被截断
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// 调试包清单：不下载调试包即可查看的执行概要
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BundleManifest {
+    pub invocation_id: String,
+    pub function: String,
+    pub version: String,
+    /// 脚本类型（见 `scheduler::namespaces::script_type`）
+    pub script_type: String,
+    /// 函数选择的执行环境名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<String>,
+    pub mode: DebugCapture,
+    pub success: bool,
+    /// 执行状态（调度错误时缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ExecutionStatus>,
+    /// 脱敏后的错误信息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub attempts: u32,
+    pub duration_ms: u64,
+    /// 编译器或解释器的版本
+    pub toolchain: BTreeMap<String, String>,
+    /// 进程可见的环境变量，值一律为 `[REDACTED]`
+    pub env: BTreeMap<String, String>,
+    /// 输入中是否有字段按 `log_redaction` 被替换
+    pub input_redacted: bool,
+    pub files: Vec<BundleFile>,
+    pub captured_at: DateTime<Utc>,
+    /// 打包和写入调试包的耗时（毫秒）
+    pub capture_ms: u64,
+    /// 调试包（tar.gz）的字节数（包内的清单中缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle_bytes: Option<u64>,
+}
+
+/// 一次已结束的调用及其收集到的调试信息
+#[derive(Debug, Clone)]
+pub struct Capture {
+    function: FunctionMetadata,
+    invocation_id: String,
+    /// 函数实际收到的输入（已应用输入模板）
+    input: serde_json::Value,
+    success: bool,
+    status: Option<ExecutionStatus>,
+    error: Option<String>,
+    attempts: u32,
+    duration: Duration,
+    trace: DebugTrace,
+}
+
+impl Capture {
+    pub fn new(
+        function: &FunctionMetadata,
+        invocation_id: &str,
+        input: &serde_json::Value,
+        result: std::result::Result<&InvokeResponse, &FluxError>,
+        duration: Duration,
+        trace: DebugTrace,
+    ) -> Self {
+        let (success, status, error, attempts) = match result {
+            Ok(response) => (
+                response.status.is_success(),
+                Some(response.status.clone()),
+                response.status.failure_message(),
+                response.attempts_made,
+            ),
+            Err(e) => (false, None, Some(e.to_string()), 1),
+        };
+        Self {
+            function: function.clone(),
+            invocation_id: invocation_id.to_string(),
+            input: input.clone(),
+            success,
+            status,
+            error,
+            attempts,
+            duration,
+            trace,
+        }
+    }
+}
+
+/// 调试包目录：按调用 ID 保存调试包和清单，按总大小和保留时长淘汰（同一进程的调度器配置共享）
+#[derive(Debug)]
+pub struct DebugArtifactStore {
+    config: StdMutex<DebugCaptureConfig>,
+    /// 写入和淘汰互斥，淘汰时不会删除正在写入的调试包
+    io: StdMutex<()>,
+}
+
+impl Default for DebugArtifactStore {
+    fn default() -> Self {
+        Self::new(DebugCaptureConfig::default())
+    }
+}
+
+impl DebugArtifactStore {
+    pub fn new(config: DebugCaptureConfig) -> Self {
+        Self {
+            config: StdMutex::new(config),
+            io: StdMutex::new(()),
+        }
+    }
+
+    /// 应用配置（启动时调用），并按新的上限淘汰已有的调试包
+    pub fn configure(&self, config: &DebugCaptureConfig) {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        self.evict();
+    }
+
+    pub fn config(&self) -> DebugCaptureConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 函数启用了调试包时返回本次调用的收集句柄；影子重放和镜像调用不收集
+    pub fn trace_for(&self, function: &FunctionMetadata, shadow: bool) -> Option<DebugTrace> {
+        (!function.debug_capture.is_off() && !shadow && self.config().enabled)
+            .then(DebugTrace::default)
+    }
+
+    /// 按函数的模式保存调试包（在阻塞线程池中写入），失败只记录日志，不影响调用结果
+    pub async fn capture(self: &Arc<Self>, capture: Capture) -> Option<BundleManifest> {
+        if !capture.function.debug_capture.applies(capture.success) {
+            return None;
+        }
+        let store = self.clone();
+        let invocation_id = capture.invocation_id.clone();
+        match tokio::task::spawn_blocking(move || store.write(&capture)).await {
+            Ok(Ok(manifest)) => Some(manifest),
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to capture debug bundle of {}: {}", invocation_id, e);
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Debug bundle task of {} failed: {}", invocation_id, e);
+                None
+            }
+        }
+    }
+
+    /// 打包并写入调试包和清单，已有同一调用的调试包时替换
+    pub fn write(&self, capture: &Capture) -> Result<BundleManifest> {
+        let started = Instant::now();
+        let config = self.config();
+        let stem = file_stem(&capture.invocation_id)?;
+        let function = &capture.function;
+        let redactor = Redactor::for_function(function).with_secrets_from(&capture.input);
+        let trace = std::mem::take(&mut *capture.trace.data());
+
+        let input = redactor.redact(&capture.input);
+        let mut files: Vec<(BundleFile, String)> = Vec::new();
+        let mut add = |path: String, content: String, truncated: bool| {
+            let file = BundleFile {
+                path,
+                bytes: content.len(),
+                truncated,
+            };
+            files.push((file, content));
+        };
+        add(
+            "input.json".to_string(),
+            serde_json::to_string_pretty(&input)?,
+            false,
+        );
+        for (path, content) in trace.sources {
+            add(format!("source/{path}"), redactor.scrub(&content), false);
+        }
+        for (path, output) in [("stdout.txt", trace.stdout), ("stderr.txt", trace.stderr)] {
+            if !output.is_empty() {
+                let (output, truncated) =
+                    truncate(redactor.scrub(&output), config.max_output_bytes);
+                add(path.to_string(), output, truncated);
+            }
+        }
+
+        let captured_at = Utc::now();
+        let mut manifest = BundleManifest {
+            invocation_id: capture.invocation_id.clone(),
+            function: function.name.clone(),
+            version: function.version.clone(),
+            script_type: script_type(function).to_string(),
+            runtime: function.runtime.clone(),
+            mode: function.debug_capture,
+            success: capture.success,
+            status: capture.status.clone().map(|status| match status {
+                ExecutionStatus::Error {
+                    kind,
+                    origin,
+                    message,
+                } => ExecutionStatus::error_with_origin(kind, origin, redactor.scrub(&message)),
+                status => status,
+            }),
+            error: capture.error.as_deref().map(|error| redactor.scrub(error)),
+            attempts: capture.attempts,
+            duration_ms: capture.duration.as_millis() as u64,
+            toolchain: trace.toolchain,
+            env: trace
+                .env
+                .into_iter()
+                .map(|name| (name, REDACTED.to_string()))
+                .collect(),
+            input_redacted: input != capture.input,
+            files: files.iter().map(|(file, _)| file.clone()).collect(),
+            captured_at,
+            capture_ms: 0,
+            bundle_bytes: None,
+        };
+
+        // 包内文件放在以调用 ID 命名的根目录下，清单在最前
+        let encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+        let entries = std::iter::once(("manifest.json", manifest_json.as_slice())).chain(
+            files
+                .iter()
+                .map(|(file, content)| (file.path.as_str(), content.as_bytes())),
+        );
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(captured_at.timestamp().max(0) as u64);
+            archive.append_data(&mut header, format!("{stem}/{path}"), content)?;
+        }
+        let bundle = archive.into_inner()?.finish()?;
+
+        manifest.bundle_bytes = Some(bundle.len() as u64);
+        manifest.capture_ms = started.elapsed().as_millis() as u64;
+        if bundle.len() as u64 > config.max_total_bytes {
+            return Err(FluxError::Runtime(format!(
+                "Debug bundle of {} bytes exceeds max_total_bytes ({})",
+                bundle.len(),
+                config.max_total_bytes
+            )));
+        }
+
+        {
+            let _io = self.io.lock().unwrap_or_else(|e| e.into_inner());
+            std::fs::create_dir_all(&config.dir)?;
+            write_atomic(&config.dir.join(format!("{stem}.tar.gz")), &bundle)?;
+            write_atomic(
+                &config.dir.join(format!("{stem}.json")),
+                &serde_json::to_vec_pretty(&manifest)?,
+            )?;
+        }
+        self.evict();
+        tracing::info!(
+            "Captured debug bundle of {} ({} bytes, {}ms)",
+            capture.invocation_id,
+            bundle.len(),
+            manifest.capture_ms
+        );
+        Ok(manifest)
+    }
+
+    /// 调用的调试包清单
+    pub fn manifest(&self, invocation_id: &str) -> Result<BundleManifest> {
+        let path = self.existing(invocation_id, "json")?;
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    /// 调用的调试包（tar.gz）
+    pub fn bundle(&self, invocation_id: &str) -> Result<Vec<u8>> {
+        let path = self.existing(invocation_id, "tar.gz")?;
+        Ok(std::fs::read(path)?)
+    }
+
+    /// 未过期的调试包文件，不存在或已过期时返回 `DebugBundleNotFound`
+    fn existing(&self, invocation_id: &str, extension: &str) -> Result<PathBuf> {
+        let not_found = || FluxError::DebugBundleNotFound {
+            id: invocation_id.to_string(),
+        };
+        let stem = file_stem(invocation_id).map_err(|_| not_found())?;
+        let config = self.config();
+        let path = config.dir.join(format!("{stem}.{extension}"));
+        let modified = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|_| not_found())?;
+        if age(modified) > Duration::from_secs(config.max_age_secs) {
+            return Err(not_found());
+        }
+        Ok(path)
+    }
+
+    /// 删除超过保留时长的调试包，总大小仍超出上限时从最早的开始删除，返回删除的调试包数
+    pub fn evict(&self) -> usize {
+        let config = self.config();
+        let _io = self.io.lock().unwrap_or_else(|e| e.into_inner());
+        let Ok(entries) = std::fs::read_dir(&config.dir) else {
+            return 0;
+        };
+
+        // 按调用 ID 汇总调试包和清单的大小，修改时间取较早的一个
+        let mut bundles: BTreeMap<String, (SystemTime, u64)> = BTreeMap::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(stem) = name
+                .strip_suffix(".tar.gz")
+                .or_else(|| name.strip_suffix(".json"))
+            else {
+                continue;
+            };
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            let bundle = bundles.entry(stem.to_string()).or_insert((modified, 0));
+            bundle.0 = bundle.0.min(modified);
+            bundle.1 += metadata.len();
+        }
+
+        let max_age = Duration::from_secs(config.max_age_secs);
+        let mut oldest_first: Vec<_> = bundles.into_iter().collect();
+        oldest_first.sort_by_key(|(_, (modified, _))| *modified);
+        let mut total: u64 = oldest_first.iter().map(|(_, (_, bytes))| bytes).sum();
+        let mut evicted = 0;
+        for (stem, (modified, bytes)) in oldest_first {
+            if age(modified) <= max_age && total <= config.max_total_bytes {
+                continue;
+            }
+            for extension in ["tar.gz", "json"] {
+                let _ = std::fs::remove_file(config.dir.join(format!("{stem}.{extension}")));
+            }
+            total = total.saturating_sub(bytes);
+            evicted += 1;
+        }
+        if evicted > 0 {
+            tracing::debug!("Evicted {} debug bundles", evicted);
+        }
+        evicted
+    }
+}
+
+/// 调用 ID 对应的文件名主干；ID 只能包含请求 ID 允许的字符，`:` 编码为 `%3A`
+pub(crate) fn file_stem(invocation_id: &str) -> Result<String> {
+    let valid = !invocation_id.is_empty()
+        && invocation_id.len() <= 128
+        && !invocation_id.starts_with('.')
+        && invocation_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    if !valid {
+        return Err(FluxError::ValidationError {
+            reason: format!("Invalid invocation ID: {invocation_id}"),
+        });
+    }
+    Ok(invocation_id.replace(':', "%3A"))
+}
+
+fn age(modified: SystemTime) -> Duration {
+    SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or(Duration::ZERO)
+}
+
+/// 先写临时文件再重命名，读取方不会看到写了一半的文件
+fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, content)?;
+    std::fs::rename(&partial, path)
+}
+
+/// 截断到 `max_bytes`（按字符边界），截断时以 `…(+N bytes)` 结尾
+fn truncate(mut text: String, max_bytes: usize) -> (String, bool) {
+    if text.len() <= max_bytes {
+        return (text, false);
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let dropped = text.len() - end;
+    text.truncate(end);
+    text.push_str(&format!("…(+{dropped} bytes)"));
+    (text, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::ErrorKind;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn store(dir: &Path) -> DebugArtifactStore {
+        DebugArtifactStore::new(DebugCaptureConfig {
+            dir: dir.to_path_buf(),
+            max_output_bytes: 64,
+            ..Default::default()
+        })
+    }
+
+    fn function(mode: DebugCapture) -> FunctionMetadata {
+        let mut function = FunctionMetadata::new("login".to_string(), "return input".to_string());
+        function.debug_capture = mode;
+        function.log_redaction = vec!["password".to_string()];
+        function
+    }
+
+    fn failed(message: &str) -> InvokeResponse {
+        InvokeResponse {
+            output: serde_json::json!({"error": message}),
+            execution_time_ms: 5,
+            status: ExecutionStatus::error(ErrorKind::Runtime, message),
+            request_id: None,
+            attempts_made: 2,
+            succeeded_on_retry: false,
+            cold_start: false,
+            output_schema_violations: None,
+            backend: None,
+            replayed: false,
+            chaos_injected: false,
+            return_type_mismatch: false,
+            timing: Default::default(),
+            termination: None,
+            execution_path: None,
+            peer: None,
+            redirected_from: None,
+        }
+    }
+
+    /// 解出调试包中的文件（路径去掉根目录）
+    fn unpack(bundle: &[u8]) -> BTreeMap<String, String> {
+        let mut archive = tar::Archive::new(GzDecoder::new(bundle));
+        archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let path = entry.path().unwrap().to_string_lossy().to_string();
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                (path, content)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_capture_writes_redacted_bundle_and_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(store(dir.path()));
+        let function = function(DebugCapture::OnFailure);
+        let input = serde_json::json!({"user": "ada", "password": "hunter2-secret"});
+
+        let trace = store.trace_for(&function, false).unwrap();
+        trace.set_sources(vec![(
+            "main.py".to_string(),
+            "def handler(input):\n    pass\n".to_string(),
+        )]);
+        trace.set_env(vec!["PATH".to_string(), "HOME".to_string()]);
+        trace.set_toolchain("python", "Python 3.12.1");
+        trace.set_output(
+            "",
+            &format!("login failed for hunter2-secret\n{}", "x".repeat(100)),
+        );
+
+        // 成功的调用在 on_failure 模式下不保存
+        let mut ok = failed("");
+        ok.status = ExecutionStatus::Success;
+        let capture = Capture::new(
+            &function,
+            "inv-ok",
+            &input,
+            Ok(&ok),
+            Duration::ZERO,
+            trace.clone(),
+        );
+        assert!(store.capture(capture).await.is_none());
+
+        let response = failed("bad password hunter2-secret");
+        let capture = Capture::new(
+            &function,
+            "inv:1",
+            &input,
+            Ok(&response),
+            Duration::from_millis(7),
+            trace,
+        );
+        let manifest = store.capture(capture).await.unwrap();
+        assert!(!manifest.success);
+        assert_eq!(manifest.attempts, 2);
+        assert_eq!(manifest.script_type, "expression");
+        assert!(manifest.input_redacted);
+        assert_eq!(manifest.env["PATH"], REDACTED);
+        assert_eq!(manifest.toolchain["python"], "Python 3.12.1");
+        assert!(!manifest.error.as_ref().unwrap().contains("hunter2"));
+        let stderr = manifest
+            .files
+            .iter()
+            .find(|file| file.path == "stderr.txt")
+            .unwrap();
+        assert!(stderr.truncated);
+        // `:` 编码后作为文件名
+        assert!(dir.path().join("inv%3A1.tar.gz").is_file());
+
+        assert_eq!(store.manifest("inv:1").unwrap(), manifest);
+        let files = unpack(&store.bundle("inv:1").unwrap());
+        assert_eq!(
+            files.keys().cloned().collect::<Vec<_>>(),
+            [
+                "inv%3A1/input.json",
+                "inv%3A1/manifest.json",
+                "inv%3A1/source/main.py",
+                "inv%3A1/stderr.txt"
+            ]
+        );
+        assert!(files.values().all(|content| !content.contains("hunter2")));
+        assert!(files["inv%3A1/input.json"].contains(REDACTED));
+        assert!(files["inv%3A1/stderr.txt"].contains("[REDACTED]"));
+
+        assert!(matches!(
+            store.manifest("missing"),
+            Err(FluxError::DebugBundleNotFound { .. })
+        ));
+        assert!(matches!(
+            store.bundle("../etc/passwd"),
+            Err(FluxError::DebugBundleNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_trace_only_for_enabled_functions() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path());
+        assert!(
+            store
+                .trace_for(&function(DebugCapture::Off), false)
+                .is_none()
+        );
+        assert!(
+            store
+                .trace_for(&function(DebugCapture::Always), true)
+                .is_none()
+        );
+        assert!(
+            store
+                .trace_for(&function(DebugCapture::Always), false)
+                .is_some()
+        );
+        store.configure(&DebugCaptureConfig {
+            enabled: false,
+            ..store.config()
+        });
+        assert!(
+            store
+                .trace_for(&function(DebugCapture::Always), false)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_eviction_by_size_and_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path());
+        let function = function(DebugCapture::Always);
+        let capture = |id: &str| {
+            let trace = DebugTrace::default();
+            trace.set_sources(vec![("big.txt".to_string(), id.repeat(2000))]);
+            let capture = Capture::new(
+                &function,
+                id,
+                &serde_json::json!(null),
+                Err(&FluxError::Timeout),
+                Duration::ZERO,
+                trace,
+            );
+            store.write(&capture).unwrap()
+        };
+
+        let first = capture("a");
+        std::thread::sleep(Duration::from_millis(20));
+        capture("b");
+        assert!(store.manifest("a").is_ok());
+
+        // 总大小（含清单）只容得下两个调试包时淘汰最早的
+        let total: u64 = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().metadata().unwrap().len())
+            .sum();
+        store.configure(&DebugCaptureConfig {
+            max_total_bytes: total + first.bundle_bytes.unwrap() / 2,
+            ..store.config()
+        });
+        capture("c");
+        assert!(store.manifest("a").is_err());
+        assert!(store.manifest("b").is_ok() && store.manifest("c").is_ok());
+
+        // 保留时长为 0 时全部过期
+        std::thread::sleep(Duration::from_millis(1100));
+        store.configure(&DebugCaptureConfig {
+            max_age_secs: 0,
+            ..store.config()
+        });
+        assert!(store.manifest("c").is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
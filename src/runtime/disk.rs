@@ -0,0 +1,178 @@
+//! 调用的磁盘用量
+//!
+//! 沙箱在执行期间定期采样进程的磁盘用量：Linux 上读取 `/proc/<pid>/io` 的 `read_bytes` 和
+//! `write_bytes`，同时采样隔离目录（包括暂存目录）相对启动前新增的大小；没有 `/proc` 或无法读取
+//! 进程的 IO 统计时只按目录大小统计，结果中的 [`DiskAccounting`] 注明采用的方式。函数设置了
+//! `max_disk_write_mb` 时，观测到的写入字节数超出上限的进程在下一次采样时被终止。
+//!
+//! 常驻工作进程在多次调用间复用，不按调用统计磁盘用量。
+
+use crate::runtime::scratch;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use utoipa::ToSchema;
+
+/// 磁盘用量的统计方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskAccounting {
+    /// 进程的 IO 统计（`/proc/<pid>/io`）加隔离目录大小
+    ProcIo,
+    /// 只采样隔离目录大小（没有 `/proc`、无法读取进程的 IO 统计或进程在首次采样前已结束）
+    #[default]
+    DirectorySize,
+}
+
+/// 一次调用的磁盘用量
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DiskUsage {
+    /// 进程从存储读取的字节数（只按目录大小统计时为 0）
+    pub read_bytes: u64,
+    /// 观测到的写入字节数：进程写入存储的字节数和隔离目录新增大小的峰值中较大的一个
+    pub write_bytes: u64,
+    /// 隔离目录（包括暂存目录）新增大小的峰值
+    pub peak_dir_bytes: u64,
+    /// 暂存目录大小的峰值
+    pub peak_scratch_bytes: u64,
+    pub method: DiskAccounting,
+}
+
+/// `/proc/<pid>/io` 中进程实际读写存储的字节数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcIo {
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+/// 读取进程的 IO 统计；非 Linux 平台、进程已退出或没有权限时为 `None`
+pub fn proc_io(pid: u32) -> Option<ProcIo> {
+    if !cfg!(target_os = "linux") || pid == 0 {
+        return None;
+    }
+    parse_proc_io(&std::fs::read_to_string(format!("/proc/{pid}/io")).ok()?)
+}
+
+fn parse_proc_io(text: &str) -> Option<ProcIo> {
+    let field = |name: &str| {
+        text.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.trim().parse().ok())
+    };
+    Some(ProcIo {
+        read_bytes: field("read_bytes")?,
+        write_bytes: field("write_bytes")?,
+    })
+}
+
+/// 一个沙箱进程的磁盘用量采样器（目录遍历是阻塞操作，在阻塞线程中调用 [`sample`](Self::sample)）
+#[derive(Debug)]
+pub struct DiskSampler {
+    pid: u32,
+    work_dir: PathBuf,
+    scratch: Option<PathBuf>,
+    /// 启动进程前隔离目录已有的大小（编译函数的执行器、包文件等）
+    baseline: u64,
+    proc_write_bytes: u64,
+    usage: DiskUsage,
+}
+
+impl DiskSampler {
+    pub fn new(pid: u32, work_dir: &Path, scratch: Option<&Path>, baseline: u64) -> Self {
+        Self {
+            pid,
+            work_dir: work_dir.to_path_buf(),
+            scratch: scratch.map(Path::to_path_buf),
+            baseline,
+            proc_write_bytes: 0,
+            usage: DiskUsage::default(),
+        }
+    }
+
+    /// 采样一次并返回到目前为止的用量（进程已退出时只更新目录大小）
+    pub fn sample(&mut self) -> &DiskUsage {
+        if let Some(io) = proc_io(self.pid) {
+            self.usage.method = DiskAccounting::ProcIo;
+            self.usage.read_bytes = self.usage.read_bytes.max(io.read_bytes);
+            self.proc_write_bytes = self.proc_write_bytes.max(io.write_bytes);
+        }
+        let grown = scratch::usage(&self.work_dir).saturating_sub(self.baseline);
+        self.usage.peak_dir_bytes = self.usage.peak_dir_bytes.max(grown);
+        if let Some(scratch) = &self.scratch {
+            self.usage.peak_scratch_bytes =
+                self.usage.peak_scratch_bytes.max(scratch::usage(scratch));
+        }
+        self.usage.write_bytes = self.proc_write_bytes.max(self.usage.peak_dir_bytes);
+        &self.usage
+    }
+
+    pub fn usage(&self) -> &DiskUsage {
+        &self.usage
+    }
+}
+
+/// 一次调用的磁盘用量记录句柄，随调用上下文传给沙箱（克隆后共享同一份数据，重试时后一次尝试覆盖前一次）
+#[derive(Debug, Clone, Default)]
+pub struct DiskReport(Arc<StdMutex<Option<DiskUsage>>>);
+
+impl PartialEq for DiskReport {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl DiskReport {
+    pub fn record(&self, usage: DiskUsage) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(usage);
+    }
+
+    /// 记录的用量（调用没有启动沙箱进程时为 `None`）
+    pub fn usage(&self) -> Option<DiskUsage> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proc_io_fields_are_parsed() {
+        let text = "rchar: 4096\nwchar: 8192\nsyscr: 3\nsyscw: 2\nread_bytes: 512\n\
+                    write_bytes: 1048576\ncancelled_write_bytes: 0\n";
+        assert_eq!(
+            parse_proc_io(text),
+            Some(ProcIo {
+                read_bytes: 512,
+                write_bytes: 1048576
+            })
+        );
+        assert_eq!(parse_proc_io("rchar: 1\n"), None);
+        if cfg!(target_os = "linux") && Path::new("/proc/self/io").exists() {
+            assert!(proc_io(std::process::id()).is_some());
+        }
+    }
+
+    #[test]
+    fn test_sampler_counts_growth_over_the_baseline() {
+        let jail = tempfile::tempdir().unwrap();
+        std::fs::write(jail.path().join("executor"), vec![0u8; 4096]).unwrap();
+        let scratch = scratch::create(jail.path()).unwrap();
+        let baseline = scratch::usage(jail.path());
+
+        // 进程不存在：只按目录大小统计
+        let mut sampler = DiskSampler::new(0, jail.path(), Some(&scratch), baseline);
+        assert_eq!(sampler.sample(), &DiskUsage::default());
+        std::fs::write(scratch.join("out.bin"), vec![0u8; 10_000]).unwrap();
+        std::fs::write(jail.path().join("tmp.bin"), vec![0u8; 5_000]).unwrap();
+        let usage = sampler.sample().clone();
+        assert_eq!(usage.peak_dir_bytes, 15_000);
+        assert_eq!(usage.peak_scratch_bytes, 10_000);
+        assert_eq!(usage.write_bytes, 15_000);
+        assert_eq!(usage.method, DiskAccounting::DirectorySize);
+
+        // 删除文件后峰值保持不变
+        std::fs::remove_file(scratch.join("out.bin")).unwrap();
+        assert_eq!(sampler.sample(), &usage);
+    }
+}
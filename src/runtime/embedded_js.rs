@@ -0,0 +1,198 @@
+//! 内嵌 JavaScript 引擎（`pure` 特性）：用 QuickJS 在进程内执行 JavaScript 函数，不依赖 node
+//!
+//! 调用约定与 node 包装脚本相同（见 `ScriptLanguage::wrap`）：代码定义 `handler(input, context)`，
+//! 返回值（可以是 Promise）按 JSON 输出，上下文同时是全局 `context`。
+//! 引擎只提供 ECMAScript 标准库：没有 `require`、`process`、定时器、状态访问和出站 HTTP，
+//! `console` 的输出被丢弃。每次调用使用独立的引擎实例，超过截止时间时中断执行。
+
+use anyhow::{Result, anyhow};
+use rquickjs::{CatchResultExt, CaughtError, Coerced, Context, Runtime};
+use std::time::Instant;
+
+/// 单次调用的引擎内存上限
+pub const MEMORY_LIMIT_BYTES: usize = 256 * 1024 * 1024;
+
+/// 单次调用的栈上限
+const STACK_LIMIT_BYTES: usize = 1024 * 1024;
+
+/// 用户代码执行前定义的全局对象
+const PRELUDE: &str = r#"globalThis.console = {
+  log() {}, info() {}, warn() {}, error() {}, debug() {}, trace() {},
+};
+"#;
+
+/// 用户代码执行后调用 `handler`，结果以 JSON 字符串写入全局变量
+const INVOKE: &str = r#"(() => {
+  const payload = JSON.parse(globalThis.__fluxPayload);
+  globalThis.context = payload.context === undefined ? null : payload.context;
+  const input = payload.input === undefined ? null : payload.input;
+  let output;
+  try {
+    output = handler(input, globalThis.context);
+  } catch (error) {
+    globalThis.__fluxError = String(error);
+    return;
+  }
+  Promise.resolve(output).then(
+      (output) => { globalThis.__fluxOutput = JSON.stringify(output === undefined ? null : output) ?? 'null'; },
+      (error) => { globalThis.__fluxError = String(error); },
+    );
+})();
+"#;
+
+/// 执行 JavaScript 函数并返回其输出；在当前线程上同步执行，异步调用方应放到阻塞线程池中
+pub fn execute(
+    source: &str,
+    input: &serde_json::Value,
+    context: &serde_json::Value,
+    deadline: Instant,
+) -> Result<serde_json::Value> {
+    let runtime = Runtime::new()?;
+    runtime.set_memory_limit(MEMORY_LIMIT_BYTES);
+    runtime.set_max_stack_size(STACK_LIMIT_BYTES);
+    runtime.set_interrupt_handler(Some(Box::new(move || Instant::now() >= deadline)));
+    let js = Context::full(&runtime)?;
+    let payload = serde_json::to_string(&serde_json::json!({
+        "input": input,
+        "context": context,
+    }))?;
+
+    let failure = |error: String| {
+        if Instant::now() >= deadline {
+            anyhow!("Execution timeout")
+        } else {
+            anyhow!(error)
+        }
+    };
+
+    js.with(|ctx| {
+        let run = || {
+            ctx.globals().set("__fluxPayload", payload).catch(&ctx)?;
+            ctx.eval::<(), _>(PRELUDE).catch(&ctx)?;
+            ctx.eval::<(), _>(source).catch(&ctx)?;
+            ctx.eval::<(), _>(INVOKE).catch(&ctx)
+        };
+        run().map_err(describe).map_err(failure)?;
+
+        // 执行 Promise 回调直到没有待处理的任务（中断时回调抛出无法捕获的异常，结果保持未设置）
+        while ctx.execute_pending_job() {}
+
+        let globals = ctx.globals();
+        if let Some(error) = globals.get::<_, Option<String>>("__fluxError")? {
+            return Err(failure(error));
+        }
+        match globals.get::<_, Option<String>>("__fluxOutput")? {
+            Some(output) => Ok(serde_json::from_str(&output)?),
+            None => Err(failure(
+                "handler did not settle: the embedded engine has no timers or I/O to resolve pending promises"
+                    .to_string(),
+            )),
+        }
+    })
+}
+
+/// 异常的类型和消息，例如 `TypeError: x is not a function`
+fn describe(error: CaughtError<'_>) -> String {
+    match error {
+        CaughtError::Exception(exception) => {
+            let name = exception
+                .get::<_, Option<Coerced<String>>>("name")
+                .ok()
+                .flatten()
+                .map(|name| name.0)
+                .unwrap_or_else(|| "Error".to_string());
+            match exception.message() {
+                Some(message) => format!("{name}: {message}"),
+                None => name,
+            }
+        }
+        CaughtError::Value(value) => value
+            .get::<Coerced<String>>()
+            .map(|value| value.0)
+            .unwrap_or_else(|_| format!("{value:?}")),
+        CaughtError::Error(error) => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn run(source: &str, input: serde_json::Value) -> Result<serde_json::Value> {
+        execute(
+            source,
+            &input,
+            &serde_json::json!({"function_name": "test"}),
+            Instant::now() + Duration::from_secs(5),
+        )
+    }
+
+    #[test]
+    fn test_handler_output_and_errors() {
+        let output = run(
+            "const handler = (input, ctx) => ({ name: ctx.function_name, global: context.function_name, doubled: input.map((x) => x * 2) });",
+            serde_json::json!([1, 2]),
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            serde_json::json!({"name": "test", "global": "test", "doubled": [2, 4]})
+        );
+
+        let output = run(
+            "async function handler(input) { console.log('ignored'); return await Promise.resolve(input.a + 1); }",
+            serde_json::json!({"a": 1}),
+        )
+        .unwrap();
+        assert_eq!(output, serde_json::json!(2));
+        assert_eq!(
+            run("function handler() {}", serde_json::json!(null)).unwrap(),
+            serde_json::json!(null)
+        );
+
+        let err = run(
+            "function handler() { throw new TypeError('bad input'); }",
+            serde_json::json!(null),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "TypeError: bad input");
+        let err = run("function handler( {", serde_json::json!(null)).unwrap_err();
+        assert!(err.to_string().starts_with("SyntaxError"), "{err}");
+        let err = run(
+            "function handler() { return new Promise(() => {}); }",
+            serde_json::json!(null),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("did not settle"), "{err}");
+        // 没有 node 的全局对象
+        let err = run(
+            "function handler() { return require('fs'); }",
+            serde_json::json!(null),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("require"), "{err}");
+    }
+
+    #[test]
+    fn test_deadline_interrupts_execution() {
+        let started = Instant::now();
+        let err = execute(
+            "function handler() { while (true) {} }",
+            &serde_json::json!(null),
+            &serde_json::json!(null),
+            Instant::now() + Duration::from_millis(100),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Execution timeout");
+        let err = execute(
+            "async function handler() { await null; while (true) {} }",
+            &serde_json::json!(null),
+            &serde_json::json!(null),
+            Instant::now() + Duration::from_millis(100),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Execution timeout");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,688 @@
+use crate::runtime::script_cache::ScriptLanguage;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+
+/// 运行时探测配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeProbeConfig {
+    /// 两次自动重新探测之间的间隔（秒），为 0 时只在启动和手动刷新时探测
+    pub refresh_interval_secs: u64,
+    /// 命名执行环境（`[[runtimes.definitions]]`），函数通过 `runtime` 字段选择
+    pub definitions: Vec<RuntimeDefinition>,
+}
+
+impl Default for RuntimeProbeConfig {
+    fn default() -> Self {
+        Self {
+            refresh_interval_secs: 300,
+            definitions: Vec::new(),
+        }
+    }
+}
+
+impl RuntimeProbeConfig {
+    /// 校验执行环境定义：名称非空且不重复，只能定义解释器（node、python）
+    pub fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for definition in &self.definitions {
+            if definition.name.trim().is_empty() {
+                return Err(anyhow::anyhow!("Runtime definition name must not be empty"));
+            }
+            if !names.insert(definition.name.as_str()) {
+                return Err(anyhow::anyhow!(
+                    "Duplicate runtime definition: {}",
+                    definition.name
+                ));
+            }
+            if !matches!(definition.kind, RuntimeKind::Node | RuntimeKind::Python) {
+                return Err(anyhow::anyhow!(
+                    "Runtime definition {}: only node and python runtimes can be defined, got {}",
+                    definition.name,
+                    definition.kind.name()
+                ));
+            }
+            if definition.binary.as_os_str().is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Runtime definition {}: binary must not be empty",
+                    definition.name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 命名执行环境：把函数固定到指定的解释器，例如某个 venv 中的 Python 3.9
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeDefinition {
+    pub name: String,
+    pub kind: RuntimeKind,
+    /// 解释器可执行文件（绝对路径或 `PATH` 中的命令）
+    pub binary: PathBuf,
+    /// 额外设置的环境变量，覆盖沙箱白名单中的同名变量
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// 可探测的运行时
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuntimeKind {
+    Node,
+    Python,
+    TypeScript,
+    Rust,
+}
+
+impl RuntimeKind {
+    pub const ALL: [RuntimeKind; 4] = [Self::Node, Self::Python, Self::TypeScript, Self::Rust];
+
+    /// 运行时名称（与序列化名称一致）
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Node => "node",
+            Self::Python => "python",
+            Self::TypeScript => "typescript",
+            Self::Rust => "rust",
+        }
+    }
+
+    /// 按优先级排列的候选命令，使用第一个可用的
+    pub fn candidates(self) -> &'static [&'static str] {
+        match self {
+            Self::Node => &["node"],
+            Self::Python => &["python3", "python"],
+            Self::TypeScript => &["tsc", "esbuild"],
+            Self::Rust => &["rustc"],
+        }
+    }
+
+    /// 执行该脚本类型（见 `scheduler::namespaces::script_type`）的解释器，
+    /// 表达式和 Rust 函数不经解释器执行，返回 `None`
+    pub fn for_script_type(script_type: &str) -> Option<Self> {
+        match script_type {
+            "javascript" => Some(Self::Node),
+            "python" => Some(Self::Python),
+            _ => None,
+        }
+    }
+}
+
+impl From<ScriptLanguage> for RuntimeKind {
+    fn from(language: ScriptLanguage) -> Self {
+        match language {
+            ScriptLanguage::JavaScript => Self::Node,
+            ScriptLanguage::Python => Self::Python,
+        }
+    }
+}
+
+/// 一个运行时的探测结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DetectedRuntime {
+    pub runtime: RuntimeKind,
+    pub available: bool,
+    /// 命中的候选命令，例如 `python`（`python3` 不存在时）
+    pub command: Option<String>,
+    /// 解析后的可执行文件路径
+    pub path: Option<PathBuf>,
+    /// `--version` 输出的第一行
+    pub version: Option<String>,
+    /// 所有候选命令都不可用时的原因
+    pub error: Option<String>,
+}
+
+/// 一个执行环境定义的探测结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DetectedDefinition {
+    pub name: String,
+    pub kind: RuntimeKind,
+    pub binary: PathBuf,
+    /// 额外设置的环境变量名（值可能包含凭据，不返回）
+    pub env: Vec<String>,
+    pub available: bool,
+    /// `--version` 输出的第一行
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 运行时探测结果快照
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuntimeSnapshot {
+    /// 最近一次探测的时间，从未探测时为空
+    pub probed_at: Option<DateTime<Utc>>,
+    pub refresh_interval_secs: u64,
+    pub runtimes: Vec<DetectedRuntime>,
+    /// 配置的执行环境
+    pub definitions: Vec<DetectedDefinition>,
+}
+
+/// 执行脚本使用的解释器
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedRuntime {
+    /// 执行环境名；未选择执行环境时为默认运行时的名称（如 `python`）
+    pub name: String,
+    pub binary: PathBuf,
+    /// 执行环境定义的额外环境变量
+    pub env: Vec<(String, String)>,
+}
+
+/// 探测单个命令：返回可执行文件路径和版本字符串
+pub type Prober = dyn Fn(&str) -> std::result::Result<(PathBuf, String), String> + Send + Sync;
+
+/// 最近一次探测的结果
+#[derive(Debug)]
+struct ProbeState {
+    at: DateTime<Utc>,
+    instant: Instant,
+    runtimes: Vec<DetectedRuntime>,
+    definitions: Vec<DetectedDefinition>,
+}
+
+/// 运行时环境：启动时和按间隔探测可用的解释器与编译器，缓存路径和版本，
+/// 执行脚本时直接使用缓存的路径，不再每次调用都运行 `--version`
+pub struct RuntimeEnvironment {
+    config: StdMutex<RuntimeProbeConfig>,
+    prober: Box<Prober>,
+    state: StdMutex<Option<ProbeState>>,
+}
+
+impl std::fmt::Debug for RuntimeEnvironment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RuntimeEnvironment")
+            .field("config", &self.config)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for RuntimeEnvironment {
+    fn default() -> Self {
+        Self::new(RuntimeProbeConfig::default())
+    }
+}
+
+impl RuntimeEnvironment {
+    /// 使用 `PATH` 查找命令并运行 `--version` 的探测器
+    pub fn new(config: RuntimeProbeConfig) -> Self {
+        Self::with_prober(config, probe_command)
+    }
+
+    /// 使用自定义探测器（测试中模拟已安装的命令）
+    pub fn with_prober(
+        config: RuntimeProbeConfig,
+        prober: impl Fn(&str) -> std::result::Result<(PathBuf, String), String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            config: StdMutex::new(config),
+            prober: Box::new(prober),
+            state: StdMutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> RuntimeProbeConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 替换探测配置和执行环境定义（启动时按配置文件设置），新定义在下次探测时生效
+    pub fn configure(&self, config: &RuntimeProbeConfig) -> Result<()> {
+        config.validate()?;
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        Ok(())
+    }
+
+    /// 按名称查找执行环境定义
+    pub fn definition(&self, name: &str) -> Option<RuntimeDefinition> {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .definitions
+            .iter()
+            .find(|definition| definition.name == name)
+            .cloned()
+    }
+
+    /// 校验函数选择的执行环境：必须已定义，且种类与函数的脚本类型一致
+    pub fn check_selection(
+        &self,
+        runtime: Option<&str>,
+        script_type: &str,
+    ) -> std::result::Result<(), String> {
+        let Some(name) = runtime else {
+            return Ok(());
+        };
+        let Some(definition) = self.definition(name) else {
+            let config = self.config();
+            let defined: Vec<&str> = config.definitions.iter().map(|d| d.name.as_str()).collect();
+            return Err(format!(
+                "Runtime {name} is not defined (defined: {})",
+                if defined.is_empty() {
+                    "none".to_string()
+                } else {
+                    defined.join(", ")
+                }
+            ));
+        };
+        if RuntimeKind::for_script_type(script_type) != Some(definition.kind) {
+            return Err(format!(
+                "Runtime {name} is a {} runtime and cannot run {script_type} functions",
+                definition.kind.name()
+            ));
+        }
+        Ok(())
+    }
+
+    /// 解析脚本的解释器：选择了执行环境时使用其定义，否则使用该种类的默认运行时
+    pub fn resolve_script(
+        &self,
+        kind: RuntimeKind,
+        runtime: Option<&str>,
+    ) -> Result<ResolvedRuntime> {
+        let Some(name) = runtime else {
+            return Ok(ResolvedRuntime {
+                name: kind.name().to_string(),
+                binary: self.resolve(kind)?,
+                env: Vec::new(),
+            });
+        };
+        let definition = self
+            .definition(name)
+            .ok_or_else(|| anyhow::anyhow!("Runtime {name} is not defined"))?;
+        if definition.kind != kind {
+            return Err(anyhow::anyhow!(
+                "Runtime {name} is a {} runtime, not {}",
+                definition.kind.name(),
+                kind.name()
+            ));
+        }
+        Ok(ResolvedRuntime {
+            name: definition.name,
+            binary: definition.binary,
+            env: definition.env.into_iter().collect(),
+        })
+    }
+
+    /// 重新探测所有运行时和执行环境定义
+    pub fn refresh(&self) -> RuntimeSnapshot {
+        let runtimes = RuntimeKind::ALL
+            .iter()
+            .map(|&runtime| self.detect(runtime))
+            .collect();
+        let definitions = self
+            .config()
+            .definitions
+            .iter()
+            .map(|definition| self.detect_definition(definition))
+            .collect();
+        *self.state.lock().unwrap_or_else(|e| e.into_inner()) = Some(ProbeState {
+            at: Utc::now(),
+            instant: Instant::now(),
+            runtimes,
+            definitions,
+        });
+        self.snapshot()
+    }
+
+    /// 距上次探测超过刷新间隔（或从未探测）时重新探测，返回是否探测过
+    pub fn refresh_if_stale(&self) -> bool {
+        self.refresh_if_stale_at(Instant::now())
+    }
+
+    fn refresh_if_stale_at(&self, now: Instant) -> bool {
+        let refresh_interval_secs = self.config().refresh_interval_secs;
+        let stale = match &*self.state.lock().unwrap_or_else(|e| e.into_inner()) {
+            None => true,
+            Some(_) if refresh_interval_secs == 0 => false,
+            Some(state) => {
+                now.saturating_duration_since(state.instant)
+                    >= Duration::from_secs(refresh_interval_secs)
+            }
+        };
+        if stale {
+            self.refresh();
+        }
+        stale
+    }
+
+    /// 当前缓存的探测结果
+    pub fn snapshot(&self) -> RuntimeSnapshot {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        RuntimeSnapshot {
+            probed_at: state.as_ref().map(|state| state.at),
+            refresh_interval_secs: self.config().refresh_interval_secs,
+            runtimes: state
+                .as_ref()
+                .map(|state| state.runtimes.clone())
+                .unwrap_or_default(),
+            definitions: state
+                .as_ref()
+                .map(|state| state.definitions.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// 最近一次探测到的版本：选择了执行环境时为该定义的版本，否则为该种类默认运行时的版本（不触发探测）
+    pub fn version(&self, kind: RuntimeKind, runtime: Option<&str>) -> Option<String> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let state = state.as_ref()?;
+        match runtime {
+            Some(name) => state
+                .definitions
+                .iter()
+                .find(|definition| definition.name == name)
+                .and_then(|definition| definition.version.clone()),
+            None => state
+                .runtimes
+                .iter()
+                .find(|detected| detected.runtime == kind)
+                .and_then(|detected| detected.version.clone()),
+        }
+    }
+
+    /// 返回运行时的可执行文件路径；从未探测时先探测一次，
+    /// 运行时不可用时立即失败，错误中包含候选命令和最近一次探测时间
+    pub fn resolve(&self, runtime: RuntimeKind) -> Result<PathBuf> {
+        if self
+            .state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_none()
+        {
+            self.refresh();
+        }
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(state) = state.as_ref() else {
+            return Err(anyhow::anyhow!(
+                "Runtime {} has not been probed",
+                runtime.name()
+            ));
+        };
+        let detected = state.runtimes.iter().find(|d| d.runtime == runtime);
+        match detected.and_then(|d| d.path.clone()) {
+            Some(path) => Ok(path),
+            None => Err(anyhow::anyhow!(
+                "Runtime {} is not available (tried {}; last probed at {}): {}. \
+                 Install it and POST /admin/runtimes/refresh",
+                runtime.name(),
+                runtime.candidates().join(", "),
+                state.at.to_rfc3339(),
+                detected
+                    .and_then(|d| d.error.clone())
+                    .unwrap_or_else(|| "not detected".to_string())
+            )),
+        }
+    }
+
+    /// 按后台间隔定期重新探测，间隔为 0 时不启动
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let refresh_interval_secs = self.config().refresh_interval_secs;
+        if refresh_interval_secs == 0 {
+            return None;
+        }
+        let environment = self.clone();
+        let interval = Duration::from_secs(refresh_interval_secs);
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let environment = environment.clone();
+                if let Err(e) = tokio::task::spawn_blocking(move || environment.refresh()).await {
+                    tracing::warn!("Runtime probe failed: {}", e);
+                }
+            }
+        }))
+    }
+
+    /// 探测执行环境定义的解释器版本
+    fn detect_definition(&self, definition: &RuntimeDefinition) -> DetectedDefinition {
+        let probed = (self.prober)(&definition.binary.to_string_lossy());
+        DetectedDefinition {
+            name: definition.name.clone(),
+            kind: definition.kind,
+            binary: definition.binary.clone(),
+            env: definition.env.keys().cloned().collect(),
+            available: probed.is_ok(),
+            version: probed.as_ref().ok().map(|(_, version)| version.clone()),
+            error: probed.err(),
+        }
+    }
+
+    /// 依次尝试候选命令，使用第一个可用的
+    fn detect(&self, runtime: RuntimeKind) -> DetectedRuntime {
+        let mut errors = Vec::new();
+        for &command in runtime.candidates() {
+            match (self.prober)(command) {
+                Ok((path, version)) => {
+                    return DetectedRuntime {
+                        runtime,
+                        available: true,
+                        command: Some(command.to_string()),
+                        path: Some(path),
+                        version: Some(version),
+                        error: None,
+                    };
+                }
+                Err(e) => errors.push(format!("{command}: {e}")),
+            }
+        }
+        DetectedRuntime {
+            runtime,
+            available: false,
+            command: None,
+            path: None,
+            version: None,
+            error: Some(errors.join("; ")),
+        }
+    }
+}
+
+/// 在 `PATH` 中查找命令并读取 `--version` 输出的第一行（部分解释器输出到标准错误）
+fn probe_command(command: &str) -> std::result::Result<(PathBuf, String), String> {
+    let path = which::which(command).map_err(|_| "not found in PATH".to_string())?;
+    let output = Command::new(&path)
+        .arg("--version")
+        .output()
+        .map_err(|e| format!("failed to run --version: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("--version exited with {}", output.status));
+    }
+    let text = if output.stdout.is_empty() {
+        output.stderr
+    } else {
+        output.stdout
+    };
+    let version = String::from_utf8_lossy(&text)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    Ok((path, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[test]
+    fn test_python_falls_back_to_python_binary() {
+        let environment =
+            RuntimeEnvironment::with_prober(Default::default(), |command| match command {
+                "python" => Ok((PathBuf::from("/usr/bin/python"), "Python 3.12.1".into())),
+                _ => Err("not found in PATH".into()),
+            });
+
+        let path = environment.resolve(RuntimeKind::Python).unwrap();
+        assert_eq!(path, PathBuf::from("/usr/bin/python"));
+        let snapshot = environment.snapshot();
+        let python = snapshot
+            .runtimes
+            .iter()
+            .find(|d| d.runtime == RuntimeKind::Python)
+            .unwrap();
+        assert_eq!(python.command.as_deref(), Some("python"));
+        assert_eq!(python.version.as_deref(), Some("Python 3.12.1"));
+
+        // 不可用的运行时立即失败，错误中带候选命令和探测时间
+        let err = environment
+            .resolve(RuntimeKind::Node)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("node is not available"), "{err}");
+        assert!(
+            err.contains(&snapshot.probed_at.unwrap().to_rfc3339()),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_stale_cache_is_refreshed_after_interval() {
+        let installed = Arc::new(AtomicBool::new(false));
+        let probes = Arc::new(AtomicUsize::new(0));
+        let environment = RuntimeEnvironment::with_prober(
+            RuntimeProbeConfig {
+                refresh_interval_secs: 60,
+                ..Default::default()
+            },
+            {
+                let (installed, probes) = (installed.clone(), probes.clone());
+                move |command| {
+                    probes.fetch_add(1, Ordering::SeqCst);
+                    if command == "node" && installed.load(Ordering::SeqCst) {
+                        Ok((PathBuf::from("/opt/node/bin/node"), "v20.11.0".into()))
+                    } else {
+                        Err("not found in PATH".into())
+                    }
+                }
+            },
+        );
+        assert!(environment.refresh_if_stale());
+        assert!(environment.resolve(RuntimeKind::Node).is_err());
+
+        // 安装后缓存仍是旧结果，直到过了刷新间隔
+        installed.store(true, Ordering::SeqCst);
+        let probed = probes.load(Ordering::SeqCst);
+        assert!(!environment.refresh_if_stale());
+        assert!(environment.resolve(RuntimeKind::Node).is_err());
+        assert_eq!(probes.load(Ordering::SeqCst), probed);
+
+        assert!(environment.refresh_if_stale_at(Instant::now() + Duration::from_secs(61)));
+        assert_eq!(
+            environment.resolve(RuntimeKind::Node).unwrap(),
+            PathBuf::from("/opt/node/bin/node")
+        );
+    }
+
+    fn py39() -> RuntimeDefinition {
+        RuntimeDefinition {
+            name: "py39".to_string(),
+            kind: RuntimeKind::Python,
+            binary: PathBuf::from("/opt/py39/bin/python"),
+            env: BTreeMap::from([("VIRTUAL_ENV".to_string(), "/opt/py39".to_string())]),
+        }
+    }
+
+    fn with_definitions(definitions: Vec<RuntimeDefinition>) -> RuntimeEnvironment {
+        let environment =
+            RuntimeEnvironment::with_prober(Default::default(), |command| match command {
+                "/opt/py39/bin/python" => Ok((PathBuf::from(command), "Python 3.9.18".into())),
+                "python3" => Ok((PathBuf::from("/usr/bin/python3"), "Python 3.12.1".into())),
+                _ => Err("not found in PATH".into()),
+            });
+        environment
+            .configure(&RuntimeProbeConfig {
+                definitions,
+                ..Default::default()
+            })
+            .unwrap();
+        environment
+    }
+
+    #[test]
+    fn test_named_runtime_selection_and_default_fallback() {
+        let environment = with_definitions(vec![py39()]);
+
+        let resolved = environment
+            .resolve_script(RuntimeKind::Python, Some("py39"))
+            .unwrap();
+        assert_eq!(resolved.name, "py39");
+        assert_eq!(resolved.binary, PathBuf::from("/opt/py39/bin/python"));
+        assert_eq!(
+            resolved.env,
+            vec![("VIRTUAL_ENV".to_string(), "/opt/py39".to_string())]
+        );
+
+        // 未选择执行环境时使用探测到的默认解释器
+        let resolved = environment
+            .resolve_script(RuntimeKind::Python, None)
+            .unwrap();
+        assert_eq!(resolved.name, "python");
+        assert_eq!(resolved.binary, PathBuf::from("/usr/bin/python3"));
+        assert!(resolved.env.is_empty());
+
+        let snapshot = environment.refresh();
+        assert_eq!(snapshot.definitions.len(), 1);
+        assert_eq!(
+            snapshot.definitions[0].version.as_deref(),
+            Some("Python 3.9.18")
+        );
+        assert_eq!(snapshot.definitions[0].env, vec!["VIRTUAL_ENV".to_string()]);
+    }
+
+    #[test]
+    fn test_runtime_selection_must_exist_and_match_script_type() {
+        let environment = with_definitions(vec![py39()]);
+
+        assert!(environment.check_selection(None, "javascript").is_ok());
+        assert!(environment.check_selection(Some("py39"), "python").is_ok());
+
+        let err = environment
+            .check_selection(Some("py39"), "javascript")
+            .unwrap_err();
+        assert!(err.contains("python runtime"), "{err}");
+        assert!(
+            environment
+                .check_selection(Some("py39"), "expression")
+                .is_err()
+        );
+        let err = environment
+            .check_selection(Some("node18"), "javascript")
+            .unwrap_err();
+        assert!(
+            err.contains("node18 is not defined (defined: py39)"),
+            "{err}"
+        );
+        assert!(
+            environment
+                .resolve_script(RuntimeKind::Node, Some("py39"))
+                .is_err()
+        );
+
+        // 重名或不可执行脚本的种类在配置时被拒绝
+        let config = RuntimeProbeConfig {
+            definitions: vec![py39(), py39()],
+            ..Default::default()
+        };
+        assert!(environment.configure(&config).is_err());
+        let config = RuntimeProbeConfig {
+            definitions: vec![RuntimeDefinition {
+                kind: RuntimeKind::Rust,
+                ..py39()
+            }],
+            ..Default::default()
+        };
+        assert!(environment.configure(&config).is_err());
+    }
+}
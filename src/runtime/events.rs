@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::Duration;
+use tokio::sync::{Notify, broadcast, mpsc};
+use tokio::time::interval;
+
+/// 生命周期事件类型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LifecycleEventType {
+    /// 实例创建
+    Created,
+    /// 开始预热
+    WarmingStarted,
+    /// 预热完成
+    WarmingCompleted,
+    /// 预热失败
+    WarmingFailed,
+    /// 实例就绪
+    Ready,
+    /// 开始执行
+    ExecutionStarted,
+    /// 执行完成
+    ExecutionCompleted,
+    /// 执行失败
+    ExecutionFailed,
+    /// 实例空闲
+    Idle,
+    /// 实例暂停
+    Paused,
+    /// 实例恢复
+    Resumed,
+    /// 清理开始
+    CleanupStarted,
+    /// 清理完成
+    CleanupCompleted,
+    /// 实例停止
+    Stopped,
+    /// 实例错误
+    Error,
+    /// 函数的 SLO 燃烧率达到告警阈值（不属于某个实例）
+    SloBurnRateAlert,
+    /// 函数的 SLO 燃烧率告警解除
+    SloBurnRateRecovered,
+}
+
+/// 实例生命周期事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceLifecycleEvent {
+    /// 事件ID
+    pub event_id: String,
+    /// 实例ID
+    pub instance_id: String,
+    /// 函数名称
+    pub function_name: String,
+    /// 事件类型
+    pub event_type: LifecycleEventType,
+    /// 事件时间
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// 事件描述
+    pub description: String,
+    /// 相关数据
+    pub metadata: HashMap<String, String>,
+    /// 耗时（毫秒）
+    pub duration_ms: Option<u64>,
+}
+
+impl InstanceLifecycleEvent {
+    /// 创建新事件
+    pub fn new(
+        instance_id: &str,
+        function_name: &str,
+        event_type: LifecycleEventType,
+        description: String,
+    ) -> Self {
+        Self {
+            event_id: scru128::new_string(),
+            instance_id: instance_id.to_string(),
+            function_name: function_name.to_string(),
+            event_type,
+            timestamp: chrono::Utc::now(),
+            description,
+            metadata: HashMap::new(),
+            duration_ms: None,
+        }
+    }
+}
+
+/// 事件保留配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventRetentionConfig {
+    /// 最多保留的事件数
+    pub max_events: usize,
+    /// 事件最长保留时间（秒）
+    pub max_age_secs: u64,
+    /// 保留策略执行间隔（秒）
+    pub sweep_interval_secs: u64,
+    /// 实时订阅通道容量
+    pub subscriber_capacity: usize,
+}
+
+impl Default for EventRetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_events: 10000,
+            max_age_secs: 3600,
+            sweep_interval_secs: 10,
+            subscriber_capacity: 1024,
+        }
+    }
+}
+
+/// 生命周期事件流 - 实例管理器和生命周期管理器共用的唯一事件来源
+///
+/// 发布方只向 mpsc 队列投递事件，不获取任何锁；事件历史只由持有历史锁的一方
+/// （保留任务或读取方）从队列中取出写入，因此任何事件路径都不需要同时持有两把锁。
+/// 保留任务同时是写入任务：每次发布后取出队列中的事件并按数量上限裁剪历史，
+/// 没有读取方时队列和历史也不会持续增长。未启动保留任务时事件在读取时写入。
+#[derive(Debug)]
+pub struct LifecycleEventStream {
+    /// 事件历史（按时间顺序）
+    history: Mutex<EventHistory>,
+    /// 待写入历史的事件队列
+    queue: mpsc::UnboundedSender<InstanceLifecycleEvent>,
+    /// 有新事件入队时唤醒保留任务
+    queued: Arc<Notify>,
+    /// 实时事件广播
+    sender: broadcast::Sender<InstanceLifecycleEvent>,
+    /// 保留配置
+    retention: EventRetentionConfig,
+    /// 保留任务句柄
+    retention_handle: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+/// 事件历史及其待写入队列
+#[derive(Debug)]
+struct EventHistory {
+    events: VecDeque<InstanceLifecycleEvent>,
+    pending: mpsc::UnboundedReceiver<InstanceLifecycleEvent>,
+}
+
+impl EventHistory {
+    /// 将队列中的事件按发布顺序写入历史
+    fn drain_pending(&mut self) {
+        while let Ok(event) = self.pending.try_recv() {
+            self.events.push_back(event);
+        }
+    }
+}
+
+impl LifecycleEventStream {
+    /// 创建新的事件流
+    pub fn new(retention: EventRetentionConfig) -> Self {
+        let (sender, _) = broadcast::channel(retention.subscriber_capacity.max(1));
+        let (queue, pending) = mpsc::unbounded_channel();
+        Self {
+            history: Mutex::new(EventHistory {
+                events: VecDeque::new(),
+                pending,
+            }),
+            queue,
+            queued: Arc::new(Notify::new()),
+            sender,
+            retention,
+            retention_handle: Mutex::new(None),
+        }
+    }
+
+    /// 获取保留配置
+    pub fn retention(&self) -> &EventRetentionConfig {
+        &self.retention
+    }
+
+    /// 发布事件
+    pub async fn publish(&self, event: InstanceLifecycleEvent) {
+        // 没有订阅者时发送失败是正常情况
+        let _ = self.sender.send(event.clone());
+        // 接收端由事件流自身持有，投递不会失败
+        let _ = self.queue.send(event);
+        self.queued.notify_one();
+    }
+
+    /// 订阅新事件
+    pub fn subscribe(&self) -> broadcast::Receiver<InstanceLifecycleEvent> {
+        self.sender.subscribe()
+    }
+
+    /// 获取历史锁并写入队列中的事件（守卫不得跨越 `.await`）
+    fn history(&self) -> MutexGuard<'_, EventHistory> {
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        history.drain_pending();
+        history
+    }
+
+    /// 获取最近的事件（按时间顺序）
+    pub async fn recent(&self, limit: usize) -> Vec<InstanceLifecycleEvent> {
+        let history = self.history();
+        let skip = history.events.len().saturating_sub(limit);
+        history.events.iter().skip(skip).cloned().collect()
+    }
+
+    /// 获取指定实例最近的事件（按时间顺序）
+    pub async fn for_instance(
+        &self,
+        instance_id: &str,
+        limit: usize,
+    ) -> Vec<InstanceLifecycleEvent> {
+        let history = self.history();
+        let mut matched: Vec<_> = history
+            .events
+            .iter()
+            .rev()
+            .filter(|event| event.instance_id == instance_id)
+            .take(limit)
+            .cloned()
+            .collect();
+        matched.reverse();
+        matched
+    }
+
+    /// 当前保留的事件数
+    pub async fn len(&self) -> usize {
+        self.history().events.len()
+    }
+
+    /// 事件流是否为空
+    pub async fn is_empty(&self) -> bool {
+        self.history().events.is_empty()
+    }
+
+    /// 按数量和时间执行保留策略，返回删除的事件数
+    pub async fn enforce_retention(&self) -> usize {
+        let cutoff = chrono::Utc::now()
+            - chrono::Duration::seconds(self.retention.max_age_secs.min(i64::MAX as u64) as i64);
+        let mut history = self.history();
+        let events = &mut history.events;
+        let before = events.len();
+
+        while events.front().is_some_and(|event| event.timestamp < cutoff) {
+            events.pop_front();
+        }
+        self.trim(events);
+
+        before - events.len()
+    }
+
+    /// 写入队列中的事件并按数量上限裁剪历史
+    fn write_pending(&self) {
+        self.trim(&mut self.history().events);
+    }
+
+    fn trim(&self, events: &mut VecDeque<InstanceLifecycleEvent>) {
+        let overflow = events.len().saturating_sub(self.retention.max_events);
+        events.drain(..overflow);
+    }
+
+    /// 启动保留任务（每个事件流只有一个，同时负责写入事件历史），事件流释放后任务自动退出
+    pub fn start_retention_task(self: &Arc<Self>) {
+        let mut handle = self
+            .retention_handle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if handle.is_some() {
+            return;
+        }
+
+        let stream: Weak<Self> = Arc::downgrade(self);
+        let queued = self.queued.clone();
+        let sweep_interval = Duration::from_secs(self.retention.sweep_interval_secs.max(1));
+
+        *handle = Some(tokio::spawn(async move {
+            let mut interval = interval(sweep_interval);
+
+            loop {
+                let sweep = tokio::select! {
+                    _ = interval.tick() => true,
+                    _ = queued.notified() => false,
+                };
+
+                let Some(stream) = stream.upgrade() else {
+                    break;
+                };
+                if !sweep {
+                    stream.write_pending();
+                    continue;
+                }
+                let removed = stream.enforce_retention().await;
+                if removed > 0 {
+                    tracing::debug!("Dropped {} expired lifecycle events", removed);
+                }
+            }
+        }));
+    }
+
+    /// 停止保留任务
+    pub fn stop_retention_task(&self) {
+        let handle = self
+            .retention_handle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(handle) = handle {
+            handle.abort();
+        }
+    }
+}
+
+impl Default for LifecycleEventStream {
+    fn default() -> Self {
+        Self::new(EventRetentionConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retention_by_count_and_age() {
+        let stream = LifecycleEventStream::new(EventRetentionConfig {
+            max_events: 3,
+            max_age_secs: 60,
+            ..Default::default()
+        });
+
+        let mut stale = InstanceLifecycleEvent::new(
+            "old",
+            "f",
+            LifecycleEventType::Created,
+            "stale".to_string(),
+        );
+        stale.timestamp = chrono::Utc::now() - chrono::Duration::seconds(120);
+        stream.publish(stale).await;
+
+        for i in 0..4 {
+            stream
+                .publish(InstanceLifecycleEvent::new(
+                    &format!("i{i}"),
+                    "f",
+                    LifecycleEventType::Created,
+                    String::new(),
+                ))
+                .await;
+        }
+
+        assert_eq!(stream.enforce_retention().await, 2);
+        let ids: Vec<_> = stream
+            .recent(10)
+            .await
+            .into_iter()
+            .map(|event| event.instance_id)
+            .collect();
+        assert_eq!(ids, vec!["i1", "i2", "i3"]);
+        assert_eq!(stream.for_instance("i2", 10).await.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_retention_task_writes_events_without_readers() {
+        let stream = Arc::new(LifecycleEventStream::new(EventRetentionConfig {
+            max_events: 100,
+            sweep_interval_secs: 3600,
+            ..Default::default()
+        }));
+        stream.start_retention_task();
+
+        // 发布期间历史锁被其他线程占用，发布之后不再有读取方或新事件
+        let (locked, wait_locked) = std::sync::mpsc::channel();
+        let holder = {
+            let stream = stream.clone();
+            std::thread::spawn(move || {
+                let _history = stream.history.lock().unwrap();
+                locked.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(200));
+            })
+        };
+        wait_locked.recv().unwrap();
+        for i in 0..1000 {
+            stream
+                .publish(InstanceLifecycleEvent::new(
+                    &format!("i{i}"),
+                    "f",
+                    LifecycleEventType::Created,
+                    String::new(),
+                ))
+                .await;
+        }
+
+        holder.join().unwrap();
+
+        // 不经读取方，队列由保留任务取空，历史不超过数量上限
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            {
+                let history = stream.history.lock().unwrap();
+                if history.pending.is_empty() {
+                    assert_eq!(history.events.len(), 100);
+                    assert_eq!(history.events.back().unwrap().instance_id, "i999");
+                    break;
+                }
+            }
+            assert!(tokio::time::Instant::now() < deadline, "queue not drained");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        stream.stop_retention_task();
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_new_events() {
+        let stream = LifecycleEventStream::default();
+        let mut receiver = stream.subscribe();
+
+        stream
+            .publish(InstanceLifecycleEvent::new(
+                "i1",
+                "f",
+                LifecycleEventType::Ready,
+                String::new(),
+            ))
+            .await;
+
+        let event = receiver.recv().await.unwrap();
+        assert_eq!(event.event_type, LifecycleEventType::Ready);
+    }
+}
@@ -0,0 +1,131 @@
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 沙箱准入失败（可通过 `anyhow::Error::downcast_ref` 识别）
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SandboxAdmissionError {
+    #[error("Sandbox execution queue is full ({queued} waiting, capacity {capacity})")]
+    QueueFull { queued: usize, capacity: usize },
+    #[error("Timed out after {waited_ms}ms waiting for a sandbox execution slot")]
+    QueueTimeout { waited_ms: u64 },
+}
+
+/// 沙箱并发执行统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ExecutorStats {
+    pub max_concurrent: usize,
+    pub running: usize,
+    pub queued: usize,
+    pub queue_capacity: usize,
+    /// 队列已满或排队超时被拒绝的执行数
+    pub rejected: u64,
+    /// 排队期间耗尽执行超时预算的执行数
+    pub expired_in_queue: u64,
+}
+
+/// 已获得的执行名额，释放时归还
+#[derive(Debug)]
+pub struct ExecutionSlot {
+    _permit: OwnedSemaphorePermit,
+    waited: Duration,
+}
+
+impl ExecutionSlot {
+    /// 在队列中等待的时间（计入函数的执行超时预算）
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+}
+
+/// 沙箱进程的全局并发上限：超出上限的执行进入有界队列等待，
+/// 队列已满或排队超时时以 [`SandboxAdmissionError`] 拒绝
+#[derive(Debug)]
+pub struct ExecutionGate {
+    semaphore: Arc<Semaphore>,
+    max_concurrent: usize,
+    queue_capacity: usize,
+    queue_timeout: Option<Duration>,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+    expired: AtomicU64,
+}
+
+impl ExecutionGate {
+    pub fn new(
+        max_concurrent: usize,
+        queue_capacity: usize,
+        queue_timeout: Option<Duration>,
+    ) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+            queue_capacity,
+            queue_timeout,
+            queued: AtomicUsize::new(0),
+            rejected: AtomicU64::new(0),
+            expired: AtomicU64::new(0),
+        }
+    }
+
+    /// 获取执行名额；排队时间不超过 `budget`（函数的执行超时），
+    /// 预算耗尽时返回 `Ok(None)`，由调用方按超时处理
+    pub async fn acquire(&self, budget: Duration) -> anyhow::Result<Option<ExecutionSlot>> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(Some(ExecutionSlot {
+                _permit: permit,
+                waited: Duration::ZERO,
+            }));
+        }
+
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst);
+        if queued >= self.queue_capacity {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(SandboxAdmissionError::QueueFull {
+                queued,
+                capacity: self.queue_capacity,
+            }
+            .into());
+        }
+
+        let start = Instant::now();
+        let queue_timeout = self.queue_timeout.unwrap_or(Duration::MAX);
+        let wait = queue_timeout.min(budget);
+        let result = tokio::time::timeout(wait, self.semaphore.clone().acquire_owned()).await;
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+
+        match result {
+            Ok(Ok(permit)) => Ok(Some(ExecutionSlot {
+                _permit: permit,
+                waited: start.elapsed(),
+            })),
+            Ok(Err(_)) => Err(anyhow::anyhow!("Sandbox execution gate is closed")),
+            Err(_) if queue_timeout < budget => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                Err(SandboxAdmissionError::QueueTimeout {
+                    waited_ms: start.elapsed().as_millis() as u64,
+                }
+                .into())
+            }
+            Err(_) => {
+                self.expired.fetch_add(1, Ordering::Relaxed);
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn stats(&self) -> ExecutorStats {
+        ExecutorStats {
+            max_concurrent: self.max_concurrent,
+            running: self.max_concurrent - self.semaphore.available_permits(),
+            queued: self.queued.load(Ordering::SeqCst),
+            queue_capacity: self.queue_capacity,
+            rejected: self.rejected.load(Ordering::Relaxed),
+            expired_in_queue: self.expired.load(Ordering::Relaxed),
+        }
+    }
+}
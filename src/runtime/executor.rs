@@ -4,9 +4,12 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{error, info, warn};
 
-use crate::functions::{ExecutionStatus, FunctionMetadata, InvokeRequest, InvokeResponse};
+use crate::functions::{
+    ErrorKind, ExecutionStatus, FunctionMetadata, InvocationTiming, InvokeRequest, InvokeResponse,
+};
 use crate::runtime::compiler::{CompilerConfig, RustCompiler};
 use crate::runtime::resource::{ResourceManager, ResourceQuota};
+use crate::runtime::rolling::{RollingStats, bump};
 use crate::runtime::sandbox::{SandboxConfig, SandboxExecutor, SandboxResult};
 
 /// 进程级隔离执行器
@@ -64,8 +67,20 @@ pub struct ExecutionStatistics {
     pub average_execution_time_ms: f64,
     /// 最长执行时间（毫秒）
     pub max_execution_time_ms: u64,
-    /// 最短执行时间（毫秒）
+    /// 最短执行时间（毫秒，没有执行时为 0）
     pub min_execution_time_ms: u64,
+    /// 执行时间的累计量，上面三个字段由它导出
+    execution_times: RollingStats,
+}
+
+impl ExecutionStatistics {
+    /// 记录一次执行时间并刷新导出的字段
+    fn record_execution_time(&mut self, execution_time_ms: u64) {
+        self.execution_times.record(execution_time_ms as f64);
+        self.min_execution_time_ms = self.execution_times.min_u64();
+        self.max_execution_time_ms = self.execution_times.max_u64();
+        self.average_execution_time_ms = self.execution_times.mean();
+    }
 }
 
 /// 进程级隔离配置
@@ -143,11 +158,15 @@ impl IsolatedProcessExecutor {
         self.check_concurrent_limit().await?;
 
         // 编译函数
-        let compiled = self
+        let outcome = self
             .compiler
-            .compile_function(function)
+            .compile_function_timed(function)
             .await
             .context("Failed to compile function for isolated execution")?;
+        let compile_ms = outcome
+            .compile_duration()
+            .map(|compile| compile.as_millis() as u64);
+        let compiled = outcome.compiled;
 
         // 创建执行实例记录
         let execution_instance = ExecutionInstance {
@@ -197,7 +216,7 @@ impl IsolatedProcessExecutor {
                 instance.ended_at = Some(chrono::Utc::now());
                 instance.status = match &execution_result {
                     Ok(result) => result.status.clone(),
-                    Err(_) => ExecutionStatus::Failed,
+                    Err(e) => ExecutionStatus::error(ErrorKind::Sandbox, e.to_string()),
                 };
                 if let Ok(result) = &execution_result {
                     // 更新进程ID如果有的话
@@ -231,6 +250,25 @@ impl IsolatedProcessExecutor {
                     output: sandbox_result.output,
                     status: sandbox_result.status,
                     execution_time_ms: sandbox_result.execution_time_ms,
+                    request_id: None,
+                    attempts_made: 1,
+                    succeeded_on_retry: false,
+                    cold_start: false,
+                    output_schema_violations: None,
+                    backend: None,
+                    replayed: false,
+                    chaos_injected: false,
+                    return_type_mismatch: false,
+                    // 编译和沙箱阶段之外的时间（如登记执行实例）计为排队
+                    timing: InvocationTiming {
+                        compile_ms,
+                        ..sandbox_result.timing
+                    }
+                    .finish(execution_time),
+                    termination: sandbox_result.termination,
+                    execution_path: None,
+                    peer: None,
+                    redirected_from: None,
                 })
             }
             Err(e) => {
@@ -241,8 +279,26 @@ impl IsolatedProcessExecutor {
 
                 Ok(InvokeResponse {
                     output: serde_json::json!(null),
-                    status: ExecutionStatus::Failed,
+                    status: ExecutionStatus::error(ErrorKind::Sandbox, e.to_string()),
                     execution_time_ms: execution_time.as_millis() as u64,
+                    request_id: None,
+                    attempts_made: 1,
+                    succeeded_on_retry: false,
+                    cold_start: false,
+                    output_schema_violations: None,
+                    backend: None,
+                    replayed: false,
+                    chaos_injected: false,
+                    return_type_mismatch: false,
+                    timing: InvocationTiming {
+                        compile_ms,
+                        ..Default::default()
+                    }
+                    .finish(execution_time),
+                    termination: None,
+                    execution_path: None,
+                    peer: None,
+                    redirected_from: None,
                 })
             }
         }
@@ -265,34 +321,22 @@ impl IsolatedProcessExecutor {
     async fn update_execution_stats(&self, result: &Result<SandboxResult>, execution_time_ms: u64) {
         let mut stats = self.execution_stats.write().await;
 
-        stats.total_executions += 1;
+        bump(&mut stats.total_executions);
 
-        match result {
+        let counter = match result {
             Ok(sandbox_result) => match sandbox_result.status {
-                ExecutionStatus::Completed | ExecutionStatus::Success => {
-                    stats.successful_executions += 1
-                }
-                ExecutionStatus::Failed | ExecutionStatus::Error(_) => stats.failed_executions += 1,
-                ExecutionStatus::Timeout => stats.timeout_executions += 1,
+                ExecutionStatus::Success => &mut stats.successful_executions,
+                ExecutionStatus::Timeout => &mut stats.timeout_executions,
+                ExecutionStatus::Error { .. }
+                | ExecutionStatus::Cancelled
+                | ExecutionStatus::ResourceLimitExceeded { .. } => &mut stats.failed_executions,
             },
-            Err(_) => stats.failed_executions += 1,
-        }
+            Err(_) => &mut stats.failed_executions,
+        };
+        bump(counter);
 
         // 更新执行时间统计
-        if stats.total_executions == 1 {
-            stats.min_execution_time_ms = execution_time_ms;
-            stats.max_execution_time_ms = execution_time_ms;
-            stats.average_execution_time_ms = execution_time_ms as f64;
-        } else {
-            stats.min_execution_time_ms = stats.min_execution_time_ms.min(execution_time_ms);
-            stats.max_execution_time_ms = stats.max_execution_time_ms.max(execution_time_ms);
-
-            // 计算移动平均值
-            let prev_avg = stats.average_execution_time_ms;
-            let count = stats.total_executions as f64;
-            stats.average_execution_time_ms =
-                (prev_avg * (count - 1.0) + execution_time_ms as f64) / count;
-        }
+        stats.record_execution_time(execution_time_ms);
     }
 
     /// 获取活跃执行实例列表
@@ -333,7 +377,7 @@ impl IsolatedProcessExecutor {
 
                     match signal::kill(Pid::from_raw(process_id as i32), Signal::SIGTERM) {
                         Ok(_) => {
-                            instance.status = ExecutionStatus::Failed;
+                            instance.status = ExecutionStatus::Cancelled;
                             instance.ended_at = Some(chrono::Utc::now());
                             info!(
                                 "Terminated execution {} (PID: {})",
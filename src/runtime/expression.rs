@@ -0,0 +1,608 @@
+use crate::functions::{FluxError, Result};
+use serde_json::{Number, Value};
+use std::fmt;
+
+/// 函数代码的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeType {
+    /// 单条 `return <表达式>`，由表达式引擎求值
+    SimpleExpression,
+    /// 其他代码（需要编译或外部解释器）
+    Program,
+}
+
+impl CodeType {
+    /// 识别代码类型：去掉首尾空白后以 `return` 开头且不含其他语句（花括号）的视为简单表达式
+    pub fn detect(code: &str) -> Self {
+        match return_expression(code) {
+            Some(expr) if !expr.contains(['{', '}']) => Self::SimpleExpression,
+            _ => Self::Program,
+        }
+    }
+}
+
+/// 取出 `return` 之后的表达式（去掉末尾分号）
+fn return_expression(code: &str) -> Option<&str> {
+    let rest = code.trim().strip_prefix("return")?;
+    if !rest.starts_with(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '(' | '!' | '-')) {
+        return None;
+    }
+    Some(rest.trim().trim_end_matches(';').trim_end())
+}
+
+/// 对简单表达式函数求值：`input` 为整个输入，其他变量从输入对象的同名字段读取
+///
+/// 支持算术（含优先级和括号）、比较、布尔逻辑、字符串拼接、三元表达式以及
+/// `user.address.city`、`items[0]` 形式的路径访问。无法解析或求值时返回带位置的错误。
+pub fn evaluate_function(code: &str, input: &Value) -> Result<Value> {
+    let expr = return_expression(code).ok_or_else(|| {
+        FluxError::Runtime("Simple expression functions must start with `return`".to_string())
+    })?;
+    evaluate(expr, input)
+}
+
+/// 对单个表达式求值
+pub fn evaluate(expr: &str, input: &Value) -> Result<Value> {
+    let ast = parse(expr).map_err(ExprError::into_flux)?;
+    eval(&ast, input).map_err(ExprError::into_flux)
+}
+
+/// 简单表达式函数能否由表达式引擎解析（不求值）
+pub fn is_supported(code: &str) -> bool {
+    return_expression(code).is_some_and(|expr| parse(expr).is_ok())
+}
+
+fn parse(expr: &str) -> ExprResult<Ast> {
+    let tokens = tokenize(expr)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: expr.chars().count(),
+    };
+    let ast = parser.parse(0)?;
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        return Err(ExprError::at(
+            token.pos,
+            format!("unexpected {}", token.kind),
+        ));
+    }
+    Ok(ast)
+}
+
+/// 带位置（从 0 开始的字符偏移）的表达式错误
+#[derive(Debug)]
+struct ExprError {
+    pos: usize,
+    message: String,
+}
+
+impl ExprError {
+    fn at(pos: usize, message: impl Into<String>) -> Self {
+        Self {
+            pos,
+            message: message.into(),
+        }
+    }
+
+    fn into_flux(self) -> FluxError {
+        FluxError::Runtime(format!(
+            "Expression error at position {}: {}",
+            self.pos, self.message
+        ))
+    }
+}
+
+type ExprResult<T> = std::result::Result<T, ExprError>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "number {n}"),
+            Self::Str(s) => write!(f, "string {s:?}"),
+            Self::Ident(name) => write!(f, "identifier `{name}`"),
+            Self::Op(op) => write!(f, "`{op}`"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    pos: usize,
+}
+
+/// 按最长匹配排列的运算符
+const OPERATORS: &[&str] = &[
+    "===", "!==", "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "?",
+    ":", "(", ")", "[", "]", ".",
+];
+
+fn tokenize(expr: &str) -> ExprResult<Vec<Token>> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text
+                .parse()
+                .map_err(|_| ExprError::at(start, format!("invalid number `{text}`")))?;
+            tokens.push(Token {
+                kind: TokenKind::Number(value),
+                pos: start,
+            });
+        } else if c == '"' || c == '\'' {
+            let start = i;
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(ExprError::at(start, "unterminated string literal")),
+                    Some(&q) if q == c => break,
+                    Some('\\') => {
+                        let escaped = chars
+                            .get(i + 1)
+                            .ok_or_else(|| ExprError::at(start, "unterminated string literal"))?;
+                        value.push(match escaped {
+                            'n' => '\n',
+                            't' => '\t',
+                            'r' => '\r',
+                            other => *other,
+                        });
+                        i += 2;
+                    }
+                    Some(&ch) => {
+                        value.push(ch);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push(Token {
+                kind: TokenKind::Str(value),
+                pos: start,
+            });
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '$')) {
+                i += 1;
+            }
+            tokens.push(Token {
+                kind: TokenKind::Ident(chars[start..i].iter().collect()),
+                pos: start,
+            });
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| {
+                    op.chars()
+                        .enumerate()
+                        .all(|(k, ch)| chars.get(i + k) == Some(&ch))
+                })
+                .ok_or_else(|| ExprError::at(i, format!("unexpected character `{c}`")))?;
+            tokens.push(Token {
+                kind: TokenKind::Op(op),
+                pos: i,
+            });
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Ast {
+    Literal(Value),
+    Variable(String, usize),
+    Member(Box<Ast>, Box<Ast>, usize),
+    Unary(&'static str, Box<Ast>, usize),
+    Binary(&'static str, Box<Ast>, Box<Ast>, usize),
+    Ternary(Box<Ast>, Box<Ast>, Box<Ast>),
+}
+
+/// 二元运算符的绑定强度（越大越先结合），三元表达式为 1
+fn binding_power(op: &str) -> Option<u8> {
+    Some(match op {
+        "?" => 1,
+        "||" => 2,
+        "&&" => 3,
+        "==" | "!=" | "===" | "!==" => 4,
+        "<" | "<=" | ">" | ">=" => 5,
+        "+" | "-" => 6,
+        "*" | "/" | "%" => 7,
+        _ => return None,
+    })
+}
+
+/// 前缀运算符的绑定强度
+const PREFIX_POWER: u8 = 8;
+
+/// Pratt 解析器
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// 表达式长度，用于报告“意外结束”的位置
+    end: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> ExprResult<Token> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| ExprError::at(self.end, "unexpected end of expression"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token {
+                kind: TokenKind::Op(op),
+                ..
+            }) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> ExprResult<()> {
+        let token = self.next()?;
+        match token.kind {
+            TokenKind::Op(op) if op == expected => Ok(()),
+            other => Err(ExprError::at(
+                token.pos,
+                format!("expected `{expected}`, found {other}"),
+            )),
+        }
+    }
+
+    fn parse(&mut self, min_power: u8) -> ExprResult<Ast> {
+        let mut lhs = self.parse_prefix()?;
+        while let Some(op) = self.peek_op() {
+            let Some(power) = binding_power(op) else {
+                break;
+            };
+            if power <= min_power {
+                break;
+            }
+            let pos = self.tokens[self.pos].pos;
+            self.pos += 1;
+            lhs = if op == "?" {
+                let then = self.parse(0)?;
+                self.expect(":")?;
+                // 三元表达式右结合
+                let otherwise = self.parse(power - 1)?;
+                Ast::Ternary(Box::new(lhs), Box::new(then), Box::new(otherwise))
+            } else {
+                let rhs = self.parse(power)?;
+                Ast::Binary(op, Box::new(lhs), Box::new(rhs), pos)
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn parse_prefix(&mut self) -> ExprResult<Ast> {
+        let token = self.next()?;
+        let mut node = match token.kind {
+            TokenKind::Number(n) => Ast::Literal(number(n)),
+            TokenKind::Str(s) => Ast::Literal(Value::String(s)),
+            TokenKind::Ident(name) => match name.as_str() {
+                "true" => Ast::Literal(Value::Bool(true)),
+                "false" => Ast::Literal(Value::Bool(false)),
+                "null" | "undefined" => Ast::Literal(Value::Null),
+                _ => Ast::Variable(name, token.pos),
+            },
+            TokenKind::Op("(") => {
+                let inner = self.parse(0)?;
+                self.expect(")")?;
+                inner
+            }
+            TokenKind::Op(op @ ("!" | "-" | "+")) => {
+                let operand = self.parse(PREFIX_POWER)?;
+                Ast::Unary(op, Box::new(operand), token.pos)
+            }
+            other => {
+                return Err(ExprError::at(token.pos, format!("unexpected {other}")));
+            }
+        };
+
+        // 成员访问和下标
+        loop {
+            match self.peek_op() {
+                Some(".") => {
+                    let pos = self.tokens[self.pos].pos;
+                    self.pos += 1;
+                    let field = self.next()?;
+                    let TokenKind::Ident(name) = field.kind else {
+                        return Err(ExprError::at(
+                            field.pos,
+                            format!("expected property name, found {}", field.kind),
+                        ));
+                    };
+                    node = Ast::Member(
+                        Box::new(node),
+                        Box::new(Ast::Literal(Value::String(name))),
+                        pos,
+                    );
+                }
+                Some("[") => {
+                    let pos = self.tokens[self.pos].pos;
+                    self.pos += 1;
+                    let index = self.parse(0)?;
+                    self.expect("]")?;
+                    node = Ast::Member(Box::new(node), Box::new(index), pos);
+                }
+                _ => return Ok(node),
+            }
+        }
+    }
+}
+
+/// 浮点结果为整数时以整数表示（`1 + 2` 得到 `3` 而不是 `3.0`）
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+        Value::Number(Number::from(n as i64))
+    } else {
+        Number::from_f64(n).map_or(Value::Null, Value::Number)
+    }
+}
+
+/// 按 JavaScript 规则判断真值
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0 && !n.is_nan()),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(_) | Value::Object(_) => true,
+    }
+}
+
+/// 字符串拼接时的文本形式
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn eval(ast: &Ast, input: &Value) -> ExprResult<Value> {
+    match ast {
+        Ast::Literal(value) => Ok(value.clone()),
+        Ast::Variable(name, pos) => {
+            if name == "input" {
+                return Ok(input.clone());
+            }
+            input
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ExprError::at(*pos, format!("unknown variable `{name}`")))
+        }
+        Ast::Member(object, key, pos) => {
+            let object = eval(object, input)?;
+            let key = eval(key, input)?;
+            let value = match (&object, &key) {
+                (Value::Object(map), Value::String(k)) => map.get(k),
+                (Value::Array(items), Value::Number(n)) => {
+                    n.as_u64().and_then(|i| items.get(i as usize))
+                }
+                (Value::Array(items), Value::String(k)) if k == "length" => {
+                    return Ok(Value::from(items.len()));
+                }
+                (Value::String(s), Value::String(k)) if k == "length" => {
+                    return Ok(Value::from(s.chars().count()));
+                }
+                (Value::Null, _) => {
+                    return Err(ExprError::at(
+                        *pos,
+                        format!("cannot read property {key} of null"),
+                    ));
+                }
+                _ => None,
+            };
+            Ok(value.cloned().unwrap_or(Value::Null))
+        }
+        Ast::Unary(op, operand, pos) => {
+            let value = eval(operand, input)?;
+            match *op {
+                "!" => Ok(Value::Bool(!truthy(&value))),
+                _ => {
+                    let n = value.as_f64().ok_or_else(|| {
+                        ExprError::at(
+                            *pos,
+                            format!("cannot apply `{op}` to {}", type_name(&value)),
+                        )
+                    })?;
+                    Ok(number(if *op == "-" { -n } else { n }))
+                }
+            }
+        }
+        Ast::Ternary(condition, then, otherwise) => {
+            if truthy(&eval(condition, input)?) {
+                eval(then, input)
+            } else {
+                eval(otherwise, input)
+            }
+        }
+        Ast::Binary("&&", lhs, rhs, _) => {
+            let left = eval(lhs, input)?;
+            if truthy(&left) {
+                eval(rhs, input)
+            } else {
+                Ok(left)
+            }
+        }
+        Ast::Binary("||", lhs, rhs, _) => {
+            let left = eval(lhs, input)?;
+            if truthy(&left) {
+                Ok(left)
+            } else {
+                eval(rhs, input)
+            }
+        }
+        Ast::Binary(op, lhs, rhs, pos) => binary(op, eval(lhs, input)?, eval(rhs, input)?, *pos),
+    }
+}
+
+fn binary(op: &str, left: Value, right: Value, pos: usize) -> ExprResult<Value> {
+    let mismatch = || {
+        ExprError::at(
+            pos,
+            format!(
+                "cannot apply `{op}` to {} and {}",
+                type_name(&left),
+                type_name(&right)
+            ),
+        )
+    };
+    match op {
+        "==" | "===" => return Ok(Value::Bool(equals(&left, &right))),
+        "!=" | "!==" => return Ok(Value::Bool(!equals(&left, &right))),
+        "+" if left.is_string() || right.is_string() => {
+            return Ok(Value::String(display(&left) + &display(&right)));
+        }
+        _ => {}
+    }
+    if let (Value::String(a), Value::String(b)) = (&left, &right) {
+        return match op {
+            "<" => Ok(Value::Bool(a < b)),
+            "<=" => Ok(Value::Bool(a <= b)),
+            ">" => Ok(Value::Bool(a > b)),
+            ">=" => Ok(Value::Bool(a >= b)),
+            _ => Err(mismatch()),
+        };
+    }
+    let (Some(a), Some(b)) = (left.as_f64(), right.as_f64()) else {
+        return Err(mismatch());
+    };
+    match op {
+        "+" => Ok(number(a + b)),
+        "-" => Ok(number(a - b)),
+        "*" => Ok(number(a * b)),
+        "/" | "%" if b == 0.0 => Err(ExprError::at(pos, "division by zero")),
+        "/" => Ok(number(a / b)),
+        "%" => Ok(number(a % b)),
+        "<" => Ok(Value::Bool(a < b)),
+        "<=" => Ok(Value::Bool(a <= b)),
+        ">" => Ok(Value::Bool(a > b)),
+        ">=" => Ok(Value::Bool(a >= b)),
+        _ => Err(mismatch()),
+    }
+}
+
+/// 数值按大小比较（`1 == 1.0`），其他类型按 JSON 值比较
+fn equals(left: &Value, right: &Value) -> bool {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => left == right,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_evaluates_with_precedence_paths_and_ternaries() {
+        let input = json!({
+            "a": 1, "b": 2, "name": "flux", "items": [10, 20],
+            "user": {"address": {"city": "Hangzhou"}, "age": 17}
+        });
+        for (code, expected) in [
+            // 原有行为：数值相加、字符串拼接、字面量和整个输入
+            ("return a + b", json!(3)),
+            ("return \"Hello, \" + name;", json!("Hello, flux")),
+            ("return \"Hello, World!\"", json!("Hello, World!")),
+            ("return input", input.clone()),
+            ("return a + b * 2", json!(5)),
+            ("return (a + b) * 2", json!(6)),
+            ("return 7 / 2", json!(3.5)),
+            ("return -a - -b", json!(1)),
+            ("return a < b && !(a == b) || false", json!(true)),
+            ("return name + a + b", json!("flux12")),
+            ("return user.address.city", json!("Hangzhou")),
+            ("return items[1] + items.length", json!(22)),
+            (
+                "return user.age >= 18 ? 'adult' : user.age > 12 ? 'teen' : 'child'",
+                json!("teen"),
+            ),
+            ("return user.missing", json!(null)),
+        ] {
+            assert_eq!(evaluate_function(code, &input).unwrap(), expected, "{code}");
+        }
+
+        let strings = json!({"a": "1", "b": "2"});
+        assert_eq!(
+            evaluate_function("return a + b", &strings).unwrap(),
+            json!("12")
+        );
+    }
+
+    #[test]
+    fn test_errors_report_position_instead_of_echoing() {
+        let input = json!({"a": 1});
+        for (expr, position) in [
+            ("a +", 3),
+            ("(a + 1", 6),
+            ("a + missing", 4),
+            ("a ? 1", 5),
+            ("a @ 2", 2),
+            ("'open", 0),
+            ("a / 0", 2),
+            ("a - 'x'", 2),
+            ("a b", 2),
+        ] {
+            let err = evaluate(expr, &input).unwrap_err();
+            assert!(matches!(err, FluxError::Runtime(_)), "{expr}");
+            assert!(
+                err.to_string()
+                    .contains(&format!("Expression error at position {position}:")),
+                "{expr}: {err}"
+            );
+        }
+
+        assert_eq!(
+            CodeType::detect("return a + b;"),
+            CodeType::SimpleExpression
+        );
+        assert_eq!(
+            CodeType::detect("  return\n input"),
+            CodeType::SimpleExpression
+        );
+        assert_eq!(
+            CodeType::detect("fn hello() -> String { return \"hi\".to_string(); }"),
+            CodeType::Program
+        );
+        assert_eq!(CodeType::detect("returns"), CodeType::Program);
+    }
+}
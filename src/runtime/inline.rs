@@ -0,0 +1,284 @@
+//! 简单表达式函数的进程内快速路径
+//!
+//! `return "Hello, World!"` 这样的函数由进程内的表达式引擎直接求值，不经过外部解释器或编译器
+//! （临时文件和进程启动占了这类调用的绝大部分延迟）。识别是保守的：含函数定义、循环、导入、
+//! 多条语句或代码超过 [`MAX_INLINE_CODE_BYTES`] 的表达式交给完整运行时执行；启发式误判时
+//! 函数的 `execution_hint` 可以强制任一路径。
+
+use super::expression::{self, CodeType};
+use crate::functions::{FluxError, FunctionMetadata, Result};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 自动走快速路径的表达式函数的最大代码长度（字节）
+pub const MAX_INLINE_CODE_BYTES: usize = 256;
+
+/// 出现在字符串字面量之外时说明代码不只是一个表达式的关键字
+const PROGRAM_KEYWORDS: [&str; 16] = [
+    "function", "def", "lambda", "class", "for", "while", "do", "loop", "import", "require",
+    "async", "await", "new", "eval", "yield", "with",
+];
+
+/// 执行路径提示
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionHint {
+    /// 按代码自动选择（默认）
+    #[default]
+    Auto,
+    /// 只要是 `return <表达式>` 形式就在进程内求值，不受长度和关键字限制
+    Inline,
+    /// 始终使用完整运行时
+    External,
+}
+
+impl ExecutionHint {
+    pub fn is_auto(&self) -> bool {
+        *self == Self::Auto
+    }
+}
+
+/// 一次调用实际的执行路径
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionPath {
+    /// 进程内的表达式引擎
+    Inline,
+    /// 外部解释器、内嵌脚本引擎或编译后的函数
+    External,
+}
+
+/// 按函数代码和 `execution_hint` 选择执行路径（不考虑运行时能否执行外部路径）
+pub fn classify(function: &FunctionMetadata) -> ExecutionPath {
+    let code = &function.code;
+    if function.package.is_some() || CodeType::detect(code) != CodeType::SimpleExpression {
+        return ExecutionPath::External;
+    }
+    let inline = match function.execution_hint {
+        ExecutionHint::External => false,
+        ExecutionHint::Inline => true,
+        ExecutionHint::Auto => {
+            code.len() <= MAX_INLINE_CODE_BYTES
+                && !has_program_constructs(code)
+                && expression::is_supported(code)
+        }
+    };
+    if inline {
+        ExecutionPath::Inline
+    } else {
+        ExecutionPath::External
+    }
+}
+
+/// `execution_hint: inline` 只适用于 `return <表达式>` 形式的函数
+pub fn validate(function: &FunctionMetadata) -> Result<()> {
+    if function.execution_hint == ExecutionHint::Inline
+        && classify(function) != ExecutionPath::Inline
+    {
+        return Err(FluxError::ValidationError {
+            reason: "execution_hint 'inline' requires a single `return <expression>` function"
+                .to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// 字符串字面量之外是否出现关键字、箭头函数、模板字符串、换行或中间的分号
+fn has_program_constructs(code: &str) -> bool {
+    let code = code.trim().trim_end_matches(';');
+    let mut quote = None;
+    let mut escaped = false;
+    let mut word = String::new();
+    let mut previous = '\0';
+    for c in code.chars() {
+        if let Some(q) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == q => quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' || c == '$' {
+            word.push(c);
+            continue;
+        }
+        if PROGRAM_KEYWORDS.contains(&word.as_str()) {
+            return true;
+        }
+        word.clear();
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '`' | ';' | '\n' | '\r' => return true,
+            '>' if previous == '=' => return true,
+            _ => {}
+        }
+        previous = c;
+    }
+    PROGRAM_KEYWORDS.contains(&word.as_str())
+}
+
+/// 以 JavaScript 执行表达式函数时的函数定义：代码改为与表达式引擎输出相同的 `handler`
+///
+/// 标识符按表达式引擎的规则解析：`input` 为整个输入，其他变量取输入对象的同名字段（缺少时为 `null`）。
+pub fn javascript_function(function: &FunctionMetadata) -> FunctionMetadata {
+    let expression = function
+        .code
+        .trim()
+        .trim_start_matches("return")
+        .trim()
+        .trim_end_matches(';');
+    let mut script = function.clone();
+    script.code = format!(
+        "function handler(input) {{\n\
+         \x20 const __fluxScope = new Proxy({{}}, {{\n\
+         \x20   has: (_, key) => typeof key === 'string',\n\
+         \x20   get: (_, key) => key === 'input' ? input\n\
+         \x20     : input !== null && typeof input === 'object' ? input[key] : undefined,\n\
+         \x20 }});\n\
+         \x20 with (__fluxScope) {{\n\
+         \x20   const result = ({expression});\n\
+         \x20   return {{ result: result === undefined ? null : result, input }};\n\
+         \x20 }}\n\
+         }}\n"
+    );
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(code: &str, hint: ExecutionHint) -> FunctionMetadata {
+        let mut function = FunctionMetadata::new("expr".to_string(), code.to_string());
+        function.execution_hint = hint;
+        function
+    }
+
+    #[test]
+    fn test_classification_is_conservative() {
+        for code in [
+            "return \"Hello, World!\"",
+            "return input",
+            "return a + b;",
+            "return user.age >= 18 ? 'adult' : 'minor'",
+            // 字符串中的关键字和符号不影响判断
+            "return 'for while => ; function' + name",
+        ] {
+            assert_eq!(
+                classify(&function(code, ExecutionHint::Auto)),
+                ExecutionPath::Inline,
+                "{code}"
+            );
+        }
+        let long = format!("return {}", vec!["a"; 200].join(" + "));
+        for code in [
+            "function handler(input) { return input; }",
+            "return items.map(x => x * 2)",
+            "return require('os').hostname()",
+            "return new Date()",
+            "return a; return b",
+            "return `hi ${name}`",
+            "return input.name.toUpperCase()",
+            long.as_str(),
+        ] {
+            assert_eq!(
+                classify(&function(code, ExecutionHint::Auto)),
+                ExecutionPath::External,
+                "{code}"
+            );
+        }
+
+        // 提示覆盖启发式；不是表达式的代码始终使用完整运行时
+        assert_eq!(
+            classify(&function("return a + b", ExecutionHint::External)),
+            ExecutionPath::External
+        );
+        assert_eq!(
+            classify(&function(&long, ExecutionHint::Inline)),
+            ExecutionPath::Inline
+        );
+        assert_eq!(
+            classify(&function(
+                "def handler(input):\n    return input",
+                ExecutionHint::Inline
+            )),
+            ExecutionPath::External
+        );
+        assert!(validate(&function("return input", ExecutionHint::Inline)).is_ok());
+        assert!(matches!(
+            validate(&function("fn handler() {}", ExecutionHint::Inline)),
+            Err(FluxError::ValidationError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_inline_and_external_outputs_match() {
+        use crate::functions::InvokeRequest;
+        use crate::runtime::SimpleRuntime;
+        use crate::runtime::sandbox::{SandboxConfig, SandboxExecutor};
+        use serde_json::json;
+        use std::sync::Arc;
+
+        if which::which("node").is_err() {
+            return;
+        }
+        let runtime = SimpleRuntime::new();
+        runtime.attach_sandbox(Arc::new(
+            SandboxExecutor::new(SandboxConfig::default()).unwrap(),
+        ));
+        let input = json!({"a": 1, "b": 2, "name": "flux", "items": [10, 20],
+            "user": {"address": {"city": "Hangzhou"}, "age": 17}});
+        for code in [
+            "return \"Hello, World!\"",
+            "return input",
+            "return a + b",
+            "return \"Hello, \" + name;",
+            "return (a + b) * 2 - 7 / 2",
+            "return a < b && !(a == b) || false",
+            "return name + a + b",
+            "return items[1] + items.length",
+            "return user.address.city",
+            "return user.age >= 18 ? 'adult' : user.age > 12 ? 'teen' : 'child'",
+        ] {
+            let request = InvokeRequest {
+                input: input.clone(),
+                retry_policy: None,
+                priority: None,
+                idempotency_key: None,
+                session_id: None,
+                affinity_key: None,
+            };
+            let inline = runtime
+                .execute(&function(code, ExecutionHint::Auto), &request)
+                .await
+                .unwrap();
+            let external = runtime
+                .execute(&function(code, ExecutionHint::External), &request)
+                .await
+                .unwrap();
+            assert_eq!(inline.execution_path, Some(ExecutionPath::Inline), "{code}");
+            assert_eq!(
+                external.execution_path,
+                Some(ExecutionPath::External),
+                "{code}"
+            );
+            assert_eq!(
+                (&inline.status, &inline.output),
+                (&external.status, &external.output),
+                "{code}"
+            );
+        }
+        let stats = runtime.monitor().get_function_stats("expr").await.unwrap();
+        assert_eq!((stats.total_calls, stats.inline_executions), (20, 10));
+    }
+
+    #[test]
+    fn test_javascript_function_defines_handler() {
+        let script = javascript_function(&function("return a + b;", ExecutionHint::External));
+        assert!(script.code.starts_with("function handler(input)"));
+        assert!(script.code.contains("const result = (a + b);"));
+        assert_eq!(CodeType::detect(&script.code), CodeType::Program);
+    }
+}
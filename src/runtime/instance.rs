@@ -1,15 +1,23 @@
 use anyhow::Result;
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::{Duration, Instant};
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::Mutex;
 use tokio::time::{interval, sleep};
 
-use crate::functions::{ExecutionStatus, FunctionMetadata, InvokeRequest, InvokeResponse};
+use crate::functions::{
+    ErrorKind, ExecutionStatus, FunctionMetadata, InvocationTiming, InvokeRequest, InvokeResponse,
+};
 use crate::runtime::compiler::{CompiledFunction, RustCompiler};
-use crate::runtime::resource::{ResourceManager, ResourceSummary};
+use crate::runtime::events::LifecycleEventStream;
+pub use crate::runtime::events::{InstanceLifecycleEvent, LifecycleEventType};
+use crate::runtime::lock_order;
+use crate::runtime::resource::{ResourceManager, ResourceQuota, ResourceSummary, ResourceType};
+use crate::runtime::rolling::{RollingSnapshot, RollingStats, bump};
 use crate::runtime::sandbox::{SandboxExecutor, SandboxResult};
+use crate::runtime::workers::WorkerSummary;
 
 /// 函数实例状态
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -92,10 +100,12 @@ pub struct FunctionInstance {
     pub process_id: Option<u32>,
     /// 版本号
     pub version: u64,
+    /// 在途执行数（同一实例可并发执行，归零时回到空闲状态）
+    pub in_flight: u32,
 }
 
 /// 实例执行统计
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct InstanceExecutionStats {
     /// 总执行次数
     pub total_executions: u64,
@@ -103,89 +113,73 @@ pub struct InstanceExecutionStats {
     pub successful_executions: u64,
     /// 失败执行次数
     pub failed_executions: u64,
-    /// 平均执行时间（毫秒）
+    /// 平均执行时间（毫秒，由 `execution_time` 导出）
     pub avg_execution_time_ms: f64,
-    /// 最大执行时间（毫秒）
+    /// 最大执行时间（毫秒，由 `execution_time` 导出）
     pub max_execution_time_ms: u64,
-    /// 最小执行时间（毫秒）
+    /// 最小执行时间（毫秒，没有执行时为 0）
     pub min_execution_time_ms: u64,
+    /// 执行时间的计数、均值、标准差和最值（毫秒）
+    pub execution_time: RollingSnapshot,
     /// 最后执行时间
     pub last_execution_time: Option<chrono::DateTime<chrono::Utc>>,
     /// 累计CPU使用时间（毫秒）
     pub total_cpu_time_ms: u64,
     /// 峰值内存使用（字节）
     pub peak_memory_bytes: u64,
+    #[serde(skip)]
+    execution_times: RollingStats,
 }
 
-/// 实例生命周期事件
-#[derive(Debug, Clone, Serialize)]
-pub struct InstanceLifecycleEvent {
-    /// 事件ID
-    pub event_id: String,
-    /// 实例ID
-    pub instance_id: String,
-    /// 函数名称
-    pub function_name: String,
-    /// 事件类型
-    pub event_type: LifecycleEventType,
-    /// 事件时间
-    pub timestamp: chrono::DateTime<chrono::Utc>,
-    /// 事件描述
-    pub description: String,
-    /// 相关数据
-    pub metadata: HashMap<String, String>,
-}
-
-/// 生命周期事件类型
-#[derive(Debug, Clone, Serialize)]
-pub enum LifecycleEventType {
-    /// 实例创建
-    Created,
-    /// 开始预热
-    WarmingStarted,
-    /// 预热完成
-    WarmingCompleted,
-    /// 预热失败
-    WarmingFailed,
-    /// 实例就绪
-    Ready,
-    /// 开始执行
-    ExecutionStarted,
-    /// 执行完成
-    ExecutionCompleted,
-    /// 执行失败
-    ExecutionFailed,
-    /// 实例空闲
-    Idle,
-    /// 实例暂停
-    Paused,
-    /// 实例恢复
-    Resumed,
-    /// 实例停止
-    Stopped,
-    /// 实例错误
-    Error,
+impl InstanceExecutionStats {
+    /// 记录一次执行时间并刷新导出的字段
+    pub fn record_execution_time(&mut self, execution_time: Duration) {
+        self.execution_times.record_duration(execution_time);
+        self.avg_execution_time_ms = self.execution_times.mean();
+        self.max_execution_time_ms = self.execution_times.max_u64();
+        self.min_execution_time_ms = self.execution_times.min_u64();
+        self.execution_time = self.execution_times.snapshot();
+    }
 }
 
 /// 函数实例管理器
+///
+/// 实例表均为 `DashMap`，分片锁只在同步代码中持有，不跨越 `.await`；
+/// 锁顺序见 [`lock_order`]。
 #[derive(Debug)]
 pub struct InstanceManager {
     /// 活跃实例
-    active_instances: Arc<RwLock<HashMap<String, FunctionInstance>>>,
+    active_instances: Arc<DashMap<String, FunctionInstance>>,
     /// 函数名到实例ID的映射
-    function_instances: Arc<RwLock<HashMap<String, Vec<String>>>>,
+    function_instances: Arc<DashMap<String, Vec<String>>>,
     /// 编译器
     compiler: Arc<RustCompiler>,
     /// 沙箱执行器
     sandbox: Arc<SandboxExecutor>,
     /// 资源管理器
     resource_manager: Arc<ResourceManager>,
-    /// 生命周期事件历史
-    lifecycle_events: Arc<RwLock<Vec<InstanceLifecycleEvent>>>,
+    /// 生命周期事件流
+    lifecycle_events: Arc<LifecycleEventStream>,
     /// 默认配置
     default_config: InstanceConfig,
     /// 清理任务句柄
     cleanup_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    /// 注入的执行延迟（用于测试和故障演练）
+    injected_latency: Arc<DashMap<String, Duration>>,
+    /// 每个实例的恢复锁，保证并发请求只恢复一次
+    resume_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+    /// 暂停/恢复累计统计
+    suspension_totals: Arc<StdMutex<SuspensionTotals>>,
+}
+
+/// 暂停/恢复累计统计
+#[derive(Debug, Clone, Copy, Default)]
+struct SuspensionTotals {
+    suspensions: u64,
+    resumes: u64,
+    /// 恢复时需要重新编译的次数（编译产物已被淘汰）
+    recompiled_resumes: u64,
+    total_resume_time_ms: u64,
 }
 
 impl InstanceManager {
@@ -196,15 +190,38 @@ impl InstanceManager {
         resource_manager: Arc<ResourceManager>,
         default_config: Option<InstanceConfig>,
     ) -> Self {
+        Self::with_event_stream(
+            compiler,
+            sandbox,
+            resource_manager,
+            default_config,
+            Arc::new(LifecycleEventStream::default()),
+        )
+    }
+
+    /// 使用指定事件流创建实例管理器
+    pub fn with_event_stream(
+        compiler: Arc<RustCompiler>,
+        sandbox: Arc<SandboxExecutor>,
+        resource_manager: Arc<ResourceManager>,
+        default_config: Option<InstanceConfig>,
+        lifecycle_events: Arc<LifecycleEventStream>,
+    ) -> Self {
+        // 事件保留策略由事件流的单一后台任务执行
+        lifecycle_events.start_retention_task();
+
         let manager = Self {
-            active_instances: Arc::new(RwLock::new(HashMap::new())),
-            function_instances: Arc::new(RwLock::new(HashMap::new())),
+            active_instances: Arc::new(DashMap::new()),
+            function_instances: Arc::new(DashMap::new()),
             compiler,
             sandbox,
             resource_manager,
-            lifecycle_events: Arc::new(RwLock::new(Vec::new())),
+            lifecycle_events,
             default_config: default_config.unwrap_or_default(),
             cleanup_handle: Arc::new(Mutex::new(None)),
+            injected_latency: Arc::new(DashMap::new()),
+            resume_locks: Arc::new(Mutex::new(HashMap::new())),
+            suspension_totals: Arc::new(StdMutex::new(SuspensionTotals::default())),
         };
 
         // 启动清理任务
@@ -250,6 +267,7 @@ impl InstanceManager {
             execution_stats: InstanceExecutionStats::default(),
             process_id: None,
             version: 1,
+            in_flight: 0,
         };
 
         // 编译函数
@@ -273,19 +291,13 @@ impl InstanceManager {
         let function_name = instance.function_name.clone();
 
         // 注册实例
-        {
-            let mut instances = self.active_instances.write().await;
-            instances.insert(instance_id.clone(), instance);
-        }
+        self.active_instances.insert(instance_id.clone(), instance);
 
         // 更新函数到实例的映射
-        {
-            let mut function_instances = self.function_instances.write().await;
-            function_instances
-                .entry(function_name)
-                .or_insert_with(Vec::new)
-                .push(instance_id.clone());
-        }
+        self.function_instances
+            .entry(function_name)
+            .or_default()
+            .push(instance_id.clone());
 
         // 如果启用自动预热，开始预热
         if self.default_config.enable_auto_warm {
@@ -297,21 +309,20 @@ impl InstanceManager {
 
     /// 预热实例
     pub async fn warm_instance(&self, instance_id: &str) -> Result<()> {
-        let mut instance = {
-            let mut instances = self.active_instances.write().await;
-            instances
-                .get_mut(instance_id)
-                .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))?
-                .clone()
-        };
-
-        if instance.state != InstanceState::Ready {
-            return Err(anyhow::anyhow!(
-                "Instance {} is not ready for warming (current state: {:?})",
-                instance_id,
-                instance.state
-            ));
-        }
+        // 状态检查和切换到预热中在同一分片锁内完成
+        let instance = self
+            .modify_instance(instance_id, |instance| {
+                if instance.state != InstanceState::Ready {
+                    return Err(anyhow::anyhow!(
+                        "Instance {} is not ready for warming (current state: {:?})",
+                        instance_id,
+                        instance.state
+                    ));
+                }
+                instance.state = InstanceState::Warming;
+                Ok(instance.clone())
+            })
+            .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))??;
 
         tracing::info!("Starting warm-up for instance: {}", instance_id);
 
@@ -325,18 +336,11 @@ impl InstanceManager {
         )
         .await;
 
-        // 更新状态为预热中
-        instance.state = InstanceState::Warming;
-        self.update_instance(instance.clone()).await?;
-
         // 执行预热（这里可以实现具体的预热逻辑）
         let warm_result = self.perform_warm_up(&instance).await;
 
-        match warm_result {
+        let state = match warm_result {
             Ok(_) => {
-                instance.state = InstanceState::Ready;
-                instance.last_activity = chrono::Utc::now();
-
                 self.emit_lifecycle_event(
                     instance_id,
                     &instance.function_name,
@@ -347,10 +351,9 @@ impl InstanceManager {
                 .await;
 
                 tracing::info!("Instance {} warmed up successfully", instance_id);
+                InstanceState::Ready
             }
             Err(e) => {
-                instance.state = InstanceState::Error(format!("Warm-up failed: {e}"));
-
                 self.emit_lifecycle_event(
                     instance_id,
                     &instance.function_name,
@@ -361,10 +364,15 @@ impl InstanceManager {
                 .await;
 
                 tracing::error!("Failed to warm up instance {instance_id}: {e}");
+                InstanceState::Error(format!("Warm-up failed: {e}"))
             }
-        }
+        };
 
-        self.update_instance(instance).await?;
+        self.modify_instance(instance_id, |instance| {
+            instance.state = state;
+            instance.last_activity = chrono::Utc::now();
+        })
+        .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))?;
         Ok(())
     }
 
@@ -376,24 +384,44 @@ impl InstanceManager {
     ) -> Result<InvokeResponse> {
         let start_time = Instant::now();
 
-        // 获取实例
-        let mut instance = {
-            let instances = self.active_instances.read().await;
-            instances
-                .get(instance_id)
-                .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))?
-                .clone()
-        };
+        let delay = self
+            .injected_latency
+            .get(instance_id)
+            .map(|delay| *delay.value());
+        if let Some(delay) = delay {
+            sleep(delay).await;
+        }
 
-        // 检查实例状态
-        if !matches!(instance.state, InstanceState::Ready | InstanceState::Idle) {
-            return Err(anyhow::anyhow!(
-                "Instance {} is not ready for execution (current state: {:?})",
-                instance_id,
-                instance.state
-            ));
+        // 已暂停的实例先恢复
+        let paused = self
+            .active_instances
+            .get(instance_id)
+            .map(|instance| instance.state == InstanceState::Paused)
+            .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))?;
+        if paused {
+            self.resume_instance(instance_id).await?;
         }
 
+        // 占用实例：状态检查、切换为运行中和在途计数在同一分片锁内完成
+        let instance = self
+            .modify_instance(instance_id, |instance| {
+                if !matches!(
+                    instance.state,
+                    InstanceState::Ready | InstanceState::Idle | InstanceState::Running
+                ) {
+                    return Err(anyhow::anyhow!(
+                        "Instance {} is not ready for execution (current state: {:?})",
+                        instance_id,
+                        instance.state
+                    ));
+                }
+                instance.state = InstanceState::Running;
+                instance.in_flight += 1;
+                instance.last_activity = chrono::Utc::now();
+                Ok(instance.clone())
+            })
+            .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))??;
+
         // 记录执行开始事件
         self.emit_lifecycle_event(
             instance_id,
@@ -404,39 +432,41 @@ impl InstanceManager {
         )
         .await;
 
-        // 更新状态为运行中
-        instance.state = InstanceState::Running;
-        instance.last_activity = chrono::Utc::now();
-        self.update_instance(instance.clone()).await?;
-
-        // 开始资源监控
-        let monitoring_started = if let Some(ref quota_name) = instance.config.resource_quota_name {
-            self.resource_manager
-                .start_monitoring(0, instance.function_name.clone(), Some(quota_name.clone()))
-                .await
-                .is_ok()
-        } else {
-            false
+        // 按执行时的配额定义确定资源限制（函数选择的配额优先于实例配置）
+        let quota = self.resolve_quota(&instance).await;
+        let limits = match &quota {
+            Some(quota) => self.sandbox.default_limits().with_quota(quota),
+            None => self.sandbox.default_limits(),
         };
 
         // 执行函数
         let execution_result = if let Some(ref compiled) = instance.compiled_function {
-            self.sandbox.execute_in_sandbox(compiled, request).await
+            self.sandbox
+                .execute_in_sandbox_with_limits(compiled, request, &limits)
+                .await
         } else {
             Err(anyhow::anyhow!("Function not compiled"))
         };
 
         let execution_time = start_time.elapsed();
 
-        // 停止资源监控
-        let resource_summary = if monitoring_started {
-            self.resource_manager
-                .stop_monitoring(0)
-                .await
-                .ok()
-                .flatten()
-        } else {
-            None
+        // 按配额检查本次执行的资源峰值并计入配额用量
+        let resource_summary = match (&quota, &execution_result) {
+            (Some(quota), Ok(sandbox_result)) => Some(
+                self.resource_manager
+                    .record_execution(
+                        0,
+                        &instance.function_name,
+                        quota,
+                        execution_time.as_millis() as u64,
+                        &HashMap::from([
+                            (ResourceType::Memory, sandbox_result.peak_memory_bytes),
+                            (ResourceType::Cpu, sandbox_result.cpu_usage_percent as u64),
+                        ]),
+                    )
+                    .await,
+            ),
+            _ => None,
         };
 
         // 处理执行结果
@@ -448,8 +478,7 @@ impl InstanceManager {
                     &sandbox_result,
                     execution_time,
                     resource_summary,
-                )
-                .await?;
+                );
 
                 // 记录执行完成事件
                 self.emit_lifecycle_event(
@@ -465,6 +494,20 @@ impl InstanceManager {
                     output: sandbox_result.output,
                     execution_time_ms: sandbox_result.execution_time_ms,
                     status: sandbox_result.status,
+                    request_id: None,
+                    attempts_made: 1,
+                    succeeded_on_retry: false,
+                    cold_start: false,
+                    output_schema_violations: None,
+                    backend: None,
+                    replayed: false,
+                    chaos_injected: false,
+                    return_type_mismatch: false,
+                    timing: sandbox_result.timing,
+                    termination: sandbox_result.termination,
+                    execution_path: None,
+                    peer: None,
+                    redirected_from: None,
                 }
             }
             Err(e) => {
@@ -481,28 +524,188 @@ impl InstanceManager {
                 InvokeResponse {
                     output: serde_json::json!({"error": e.to_string()}),
                     execution_time_ms: execution_time.as_millis() as u64,
-                    status: ExecutionStatus::Failed,
+                    status: ExecutionStatus::error(ErrorKind::Sandbox, e.to_string()),
+                    request_id: None,
+                    attempts_made: 1,
+                    succeeded_on_retry: false,
+                    cold_start: false,
+                    output_schema_violations: None,
+                    backend: None,
+                    replayed: false,
+                    chaos_injected: false,
+                    return_type_mismatch: false,
+                    timing: InvocationTiming::default().finish(execution_time),
+                    termination: None,
+                    execution_path: None,
+                    peer: None,
+                    redirected_from: None,
                 }
             }
         };
 
-        // 更新实例状态为空闲
-        instance.state = InstanceState::Idle;
-        instance.last_activity = chrono::Utc::now();
-        self.update_instance(instance).await?;
+        // 释放实例：最后一个在途执行结束后回到空闲状态（执行期间被停止的实例已移除）
+        self.modify_instance(instance_id, |instance| {
+            instance.in_flight = instance.in_flight.saturating_sub(1);
+            if instance.in_flight == 0 && instance.state == InstanceState::Running {
+                instance.state = InstanceState::Idle;
+            }
+            instance.last_activity = chrono::Utc::now();
+        });
 
         Ok(response)
     }
 
+    /// 为实例注入执行延迟（`None` 表示取消），用于测试和故障演练
+    pub async fn inject_latency(&self, instance_id: &str, delay: Option<Duration>) {
+        match delay {
+            Some(delay) => {
+                self.injected_latency.insert(instance_id.to_string(), delay);
+            }
+            None => {
+                self.injected_latency.remove(instance_id);
+            }
+        }
+    }
+
+    /// 暂停实例：释放编译产物句柄，保留元数据；编译产物仍保留在编译缓存中，
+    /// 恢复时无需重新编译
+    pub async fn suspend_instance(&self, instance_id: &str) -> Result<()> {
+        let function_name = self
+            .modify_instance(instance_id, |instance| {
+                if !matches!(instance.state, InstanceState::Ready | InstanceState::Idle) {
+                    return Err(anyhow::anyhow!(
+                        "Instance {} cannot be suspended (current state: {:?})",
+                        instance_id,
+                        instance.state
+                    ));
+                }
+                instance.state = InstanceState::Paused;
+                instance.compiled_function = None;
+                Ok(instance.function_name.clone())
+            })
+            .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))??;
+        self.suspension_totals
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .suspensions += 1;
+
+        self.emit_lifecycle_event(
+            instance_id,
+            &function_name,
+            LifecycleEventType::Paused,
+            "Instance suspended".to_string(),
+            HashMap::new(),
+        )
+        .await;
+
+        tracing::info!("Suspended instance: {}", instance_id);
+        Ok(())
+    }
+
+    /// 恢复已暂停的实例：重新加载编译产物并标记为就绪，返回恢复耗时
+    ///
+    /// 并发恢复同一实例时只有第一个请求执行加载，其余请求等待后直接返回。
+    pub async fn resume_instance(&self, instance_id: &str) -> Result<Duration> {
+        let lock = self
+            .resume_locks
+            .lock()
+            .await
+            .entry(instance_id.to_string())
+            .or_default()
+            .clone();
+        let _guard = lock.lock().await;
+
+        let result = self.resume_locked(instance_id).await;
+        {
+            let mut locks = self.resume_locks.lock().await;
+            // 映射表和当前调用各持有一个引用
+            if Arc::strong_count(&lock) <= 2 {
+                locks.remove(instance_id);
+            }
+        }
+        result
+    }
+
+    /// 恢复实例（调用方已持有该实例的恢复锁）
+    async fn resume_locked(&self, instance_id: &str) -> Result<Duration> {
+        let start_time = Instant::now();
+        let instance = self
+            .get_instance(instance_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))?;
+        if instance.state != InstanceState::Paused {
+            // 已被并发请求恢复
+            return Ok(Duration::ZERO);
+        }
+
+        let outcome = self
+            .compiler
+            .compile_function_timed(&instance.function_metadata)
+            .await;
+        let resume_time = start_time.elapsed();
+
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                self.modify_instance(instance_id, |instance| {
+                    instance.state = InstanceState::Error(format!("Resume failed: {e}"));
+                });
+                self.emit_lifecycle_event(
+                    instance_id,
+                    &instance.function_name,
+                    LifecycleEventType::Error,
+                    format!("Instance resume failed: {e}"),
+                    HashMap::new(),
+                )
+                .await;
+                return Err(e);
+            }
+        };
+
+        self.modify_instance(instance_id, |instance| {
+            instance.compiled_function = Some(outcome.compiled);
+            instance.state = InstanceState::Ready;
+            instance.last_activity = chrono::Utc::now();
+        })
+        .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))?;
+        {
+            let mut totals = self
+                .suspension_totals
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            totals.resumes += 1;
+            totals.total_resume_time_ms += resume_time.as_millis() as u64;
+            if !outcome.cache_hit {
+                totals.recompiled_resumes += 1;
+            }
+        }
+
+        let mut event = InstanceLifecycleEvent::new(
+            instance_id,
+            &instance.function_name,
+            LifecycleEventType::Resumed,
+            "Instance resumed".to_string(),
+        );
+        event.duration_ms = Some(resume_time.as_millis() as u64);
+        event
+            .metadata
+            .insert("recompiled".to_string(), (!outcome.cache_hit).to_string());
+        self.lifecycle_events.publish(event).await;
+
+        tracing::info!(
+            "Resumed instance {} in {}ms",
+            instance_id,
+            resume_time.as_millis()
+        );
+        Ok(resume_time)
+    }
+
     /// 停止实例
     pub async fn stop_instance(&self, instance_id: &str) -> Result<()> {
-        let mut instance = {
-            let instances = self.active_instances.read().await;
-            instances
-                .get(instance_id)
-                .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))?
-                .clone()
-        };
+        let instance = self
+            .get_instance(instance_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))?;
 
         tracing::info!("Stopping instance: {}", instance_id);
 
@@ -521,75 +724,102 @@ impl InstanceManager {
             let _ = self.resource_manager.stop_monitoring(process_id).await;
         }
 
-        // 更新状态
-        instance.state = InstanceState::Stopped;
-        self.update_instance(instance.clone()).await?;
-
-        // 从活跃实例中移除
-        {
-            let mut instances = self.active_instances.write().await;
-            instances.remove(instance_id);
-        }
-
-        // 从函数映射中移除
-        {
-            let mut function_instances = self.function_instances.write().await;
-            if let Some(instance_ids) = function_instances.get_mut(&instance.function_name) {
-                instance_ids.retain(|id| id != instance_id);
-                if instance_ids.is_empty() {
-                    function_instances.remove(&instance.function_name);
-                }
-            }
-        }
+        // 从活跃实例和函数映射中移除
+        self.active_instances.remove(instance_id);
+        self.injected_latency.remove(instance_id);
+        unindex_instance(
+            &self.function_instances,
+            &instance.function_name,
+            instance_id,
+        );
 
         Ok(())
     }
 
     /// 获取函数的所有实例
     pub async fn get_function_instances(&self, function_name: &str) -> Vec<String> {
-        let function_instances = self.function_instances.read().await;
-        function_instances
+        self.function_instances
             .get(function_name)
-            .cloned()
+            .map(|instance_ids| instance_ids.clone())
             .unwrap_or_default()
     }
 
     /// 获取实例信息
     pub async fn get_instance(&self, instance_id: &str) -> Option<FunctionInstance> {
-        let instances = self.active_instances.read().await;
-        instances.get(instance_id).cloned()
+        self.active_instances
+            .get(instance_id)
+            .map(|instance| instance.clone())
     }
 
     /// 获取所有活跃实例
     pub async fn get_all_instances(&self) -> Vec<FunctionInstance> {
-        let instances = self.active_instances.read().await;
-        instances.values().cloned().collect()
+        self.active_instances
+            .iter()
+            .map(|instance| instance.clone())
+            .collect()
     }
 
-    /// 获取实例统计信息
-    pub async fn get_instance_stats(&self) -> InstanceManagerStats {
-        let instances = self.active_instances.read().await;
-        let function_instances = self.function_instances.read().await;
+    /// 实例使用的沙箱执行器
+    pub fn sandbox(&self) -> &Arc<SandboxExecutor> {
+        &self.sandbox
+    }
 
-        let mut stats = InstanceManagerStats {
-            total_instances: instances.len() as u64,
-            active_functions: function_instances.len() as u64,
-            ..Default::default()
-        };
+    /// 获取实例统计信息（包括沙箱中脚本函数的常驻工作进程）
+    pub async fn get_instance_stats(&self) -> InstanceManagerStats {
+        let mut stats = InstanceManagerStats::default();
+        let mut functions: HashSet<String> = self
+            .function_instances
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect();
 
-        for instance in instances.values() {
+        let now = chrono::Utc::now();
+        let workers: Vec<_> = self
+            .sandbox
+            .workers()
+            .summaries()
+            .iter()
+            .map(|worker| worker_instance(worker, now))
+            .collect();
+        for instance in self
+            .active_instances
+            .iter()
+            .map(|instance| instance.value().summary(now))
+            .chain(workers)
+        {
+            functions.insert(instance.function_name.clone());
+            stats.total_instances += 1;
             match instance.state {
                 InstanceState::Ready => stats.ready_instances += 1,
                 InstanceState::Running => stats.running_instances += 1,
                 InstanceState::Idle => stats.idle_instances += 1,
                 InstanceState::Warming => stats.warming_instances += 1,
+                InstanceState::Paused => stats.suspended_instances += 1,
                 InstanceState::Error(_) => stats.error_instances += 1,
                 _ => {}
             }
 
-            stats.total_executions += instance.execution_stats.total_executions;
-            stats.successful_executions += instance.execution_stats.successful_executions;
-            stats.failed_executions += instance.execution_stats.failed_executions;
+            stats.total_executions = stats
+                .total_executions
+                .saturating_add(instance.execution_stats.total_executions);
+            stats.successful_executions = stats
+                .successful_executions
+                .saturating_add(instance.execution_stats.successful_executions);
+            stats.failed_executions = stats
+                .failed_executions
+                .saturating_add(instance.execution_stats.failed_executions);
+        }
+        stats.active_functions = functions.len() as u64;
+
+        let totals = *self
+            .suspension_totals
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        stats.total_suspensions = totals.suspensions;
+        stats.total_resumes = totals.resumes;
+        stats.recompiled_resumes = totals.recompiled_resumes;
+        if totals.resumes > 0 {
+            stats.avg_resume_time_ms = totals.total_resume_time_ms as f64 / totals.resumes as f64;
         }
 
         stats
@@ -600,18 +830,12 @@ impl InstanceManager {
         let now = chrono::Utc::now();
         let mut cleaned_count = 0u64;
 
-        let instances_to_cleanup: Vec<String> = {
-            let instances = self.active_instances.read().await;
-            instances
-                .values()
-                .filter(|instance| {
-                    matches!(instance.state, InstanceState::Idle)
-                        && (now - instance.last_activity).num_seconds() as u64
-                            > instance.config.max_idle_duration_secs
-                })
-                .map(|instance| instance.instance_id.clone())
-                .collect()
-        };
+        let instances_to_cleanup: Vec<String> = self
+            .active_instances
+            .iter()
+            .filter(|instance| is_idle_expired(instance, now))
+            .map(|instance| instance.instance_id.clone())
+            .collect();
 
         for instance_id in instances_to_cleanup {
             if let Err(e) = self.stop_instance(&instance_id).await {
@@ -625,11 +849,18 @@ impl InstanceManager {
         Ok(cleaned_count)
     }
 
-    /// 更新实例
-    async fn update_instance(&self, instance: FunctionInstance) -> Result<()> {
-        let mut instances = self.active_instances.write().await;
-        instances.insert(instance.instance_id.clone(), instance);
-        Ok(())
+    /// 原地修改实例，分片锁只在闭包执行期间持有；实例不存在时返回 `None`
+    fn modify_instance<R>(
+        &self,
+        instance_id: &str,
+        f: impl FnOnce(&mut FunctionInstance) -> R,
+    ) -> Option<R> {
+        lock_order::get_mut(
+            "instance_manager.active_instances",
+            &self.active_instances,
+            instance_id,
+        )
+        .map(|mut instance| f(&mut instance))
     }
 
     /// 执行预热操作
@@ -644,52 +875,53 @@ impl InstanceManager {
         Ok(())
     }
 
+    /// 实例执行所用的资源配额：函数元数据中的 `resource_quota` 优先于实例配置，
+    /// 配额在此时从资源管理器读取，修改定义后的执行立即生效
+    async fn resolve_quota(&self, instance: &FunctionInstance) -> Option<ResourceQuota> {
+        let name = instance
+            .compiled_function
+            .as_ref()
+            .and_then(|compiled| compiled.metadata.resource_quota.as_deref())
+            .or(instance.config.resource_quota_name.as_deref())?;
+        let quota = self.resource_manager.get_quota(name).await;
+        if quota.is_none() {
+            tracing::warn!(
+                "Resource quota '{}' of function {} no longer exists; using sandbox defaults",
+                name,
+                instance.function_name
+            );
+        }
+        quota
+    }
+
     /// 更新执行统计
-    async fn update_execution_stats(
+    fn update_execution_stats(
         &self,
         instance_id: &str,
         sandbox_result: &SandboxResult,
         execution_time: Duration,
         resource_summary: Option<ResourceSummary>,
-    ) -> Result<()> {
-        let mut instances = self.active_instances.write().await;
-
-        if let Some(instance) = instances.get_mut(instance_id) {
+    ) {
+        self.modify_instance(instance_id, |instance| {
             let stats = &mut instance.execution_stats;
 
-            stats.total_executions += 1;
+            bump(&mut stats.total_executions);
             stats.last_execution_time = Some(chrono::Utc::now());
 
-            let execution_time_ms = execution_time.as_millis() as u64;
-
-            if matches!(
-                sandbox_result.status,
-                ExecutionStatus::Success | ExecutionStatus::Completed
-            ) {
-                stats.successful_executions += 1;
+            if sandbox_result.status.is_success() {
+                bump(&mut stats.successful_executions);
             } else {
-                stats.failed_executions += 1;
+                bump(&mut stats.failed_executions);
             }
 
             // 更新执行时间统计
-            if stats.min_execution_time_ms == 0 || execution_time_ms < stats.min_execution_time_ms {
-                stats.min_execution_time_ms = execution_time_ms;
-            }
-
-            if execution_time_ms > stats.max_execution_time_ms {
-                stats.max_execution_time_ms = execution_time_ms;
-            }
-
-            // 更新平均执行时间
-            stats.avg_execution_time_ms = (stats.avg_execution_time_ms
-                * (stats.total_executions - 1) as f64
-                + execution_time_ms as f64)
-                / stats.total_executions as f64;
+            stats.record_execution_time(execution_time);
+            stats.total_cpu_time_ms = stats
+                .total_cpu_time_ms
+                .saturating_add(sandbox_result.cpu_time_ms.unwrap_or(0));
 
             // 更新资源使用统计
             if let Some(resource_summary) = resource_summary {
-                stats.total_cpu_time_ms += resource_summary.total_duration_ms;
-
                 // 更新峰值内存使用
                 for (_, usage) in resource_summary.resource_usage {
                     if usage.peak_usage > stats.peak_memory_bytes {
@@ -697,9 +929,7 @@ impl InstanceManager {
                     }
                 }
             }
-        }
-
-        Ok(())
+        });
     }
 
     /// 发出生命周期事件
@@ -711,23 +941,11 @@ impl InstanceManager {
         description: String,
         metadata: HashMap<String, String>,
     ) {
-        let event = InstanceLifecycleEvent {
-            event_id: scru128::new().to_string(),
-            instance_id: instance_id.to_string(),
-            function_name: function_name.to_string(),
-            event_type,
-            timestamp: chrono::Utc::now(),
-            description,
-            metadata,
-        };
-
-        let mut events = self.lifecycle_events.write().await;
-        events.push(event);
+        let mut event =
+            InstanceLifecycleEvent::new(instance_id, function_name, event_type, description);
+        event.metadata = metadata;
 
-        // 限制事件历史大小
-        if events.len() > 10000 {
-            events.remove(0);
-        }
+        self.lifecycle_events.publish(event).await;
     }
 
     /// 启动清理任务
@@ -743,50 +961,27 @@ impl InstanceManager {
                 interval.tick().await;
 
                 let now = chrono::Utc::now();
-                let instances_to_cleanup: Vec<String> = {
-                    let instances_guard = instances.read().await;
-                    instances_guard
-                        .values()
-                        .filter(|instance| {
-                            matches!(instance.state, InstanceState::Idle)
-                                && (now - instance.last_activity).num_seconds() as u64
-                                    > instance.config.max_idle_duration_secs
-                        })
-                        .map(|instance| instance.instance_id.clone())
-                        .collect()
-                };
+                let instances_to_cleanup: Vec<String> = instances
+                    .iter()
+                    .filter(|instance| is_idle_expired(instance, now))
+                    .map(|instance| instance.instance_id.clone())
+                    .collect();
 
                 for instance_id in instances_to_cleanup {
+                    // 收集后到移除前实例可能已重新开始执行，移除时再次检查
+                    let Some((_, instance)) = instances
+                        .remove_if(&instance_id, |_, instance| is_idle_expired(instance, now))
+                    else {
+                        continue;
+                    };
+                    unindex_instance(&function_instances, &instance.function_name, &instance_id);
+
                     // 停止资源监控
-                    if let Some(instance) = {
-                        let instances_guard = instances.read().await;
-                        instances_guard.get(&instance_id).cloned()
-                    } {
-                        if let Some(process_id) = instance.process_id {
-                            let _ = resource_manager.stop_monitoring(process_id).await;
-                        }
-
-                        // 从活跃实例中移除
-                        {
-                            let mut instances_guard = instances.write().await;
-                            instances_guard.remove(&instance_id);
-                        }
-
-                        // 从函数映射中移除
-                        {
-                            let mut function_instances_guard = function_instances.write().await;
-                            if let Some(instance_ids) =
-                                function_instances_guard.get_mut(&instance.function_name)
-                            {
-                                instance_ids.retain(|id| id != &instance_id);
-                                if instance_ids.is_empty() {
-                                    function_instances_guard.remove(&instance.function_name);
-                                }
-                            }
-                        }
-
-                        tracing::info!("Cleaned up idle instance: {}", instance_id);
+                    if let Some(process_id) = instance.process_id {
+                        let _ = resource_manager.stop_monitoring(process_id).await;
                     }
+
+                    tracing::info!("Cleaned up idle instance: {}", instance_id);
                 }
             }
         });
@@ -801,14 +996,42 @@ impl InstanceManager {
 
     /// 获取生命周期事件
     pub async fn get_lifecycle_events(&self, limit: Option<usize>) -> Vec<InstanceLifecycleEvent> {
-        let events = self.lifecycle_events.read().await;
-        let limit = limit.unwrap_or(100);
+        self.lifecycle_events.recent(limit.unwrap_or(100)).await
+    }
 
-        if events.len() <= limit {
-            events.clone()
-        } else {
-            events[events.len() - limit..].to_vec()
-        }
+    /// 获取指定实例的生命周期事件
+    pub async fn get_instance_events(
+        &self,
+        instance_id: &str,
+        limit: Option<usize>,
+    ) -> Vec<InstanceLifecycleEvent> {
+        self.lifecycle_events
+            .for_instance(instance_id, limit.unwrap_or(100))
+            .await
+    }
+
+    /// 获取生命周期事件流
+    pub fn events(&self) -> &Arc<LifecycleEventStream> {
+        &self.lifecycle_events
+    }
+
+    /// 获取所有实例的摘要信息，包括沙箱中脚本函数的常驻工作进程（定义了 `init`/`teardown` 钩子的函数）
+    pub async fn list_instance_summaries(&self) -> Vec<InstanceSummary> {
+        let now = chrono::Utc::now();
+        let mut summaries: Vec<_> = self
+            .active_instances
+            .iter()
+            .map(|instance| instance.value().summary(now))
+            .chain(
+                self.sandbox
+                    .workers()
+                    .summaries()
+                    .iter()
+                    .map(|worker| worker_instance(worker, now)),
+            )
+            .collect();
+        summaries.sort_by_key(|summary| summary.created_at);
+        summaries
     }
 
     /// 清理资源
@@ -822,10 +1045,11 @@ impl InstanceManager {
         }
 
         // 停止所有实例
-        let instance_ids: Vec<String> = {
-            let instances = self.active_instances.read().await;
-            instances.keys().cloned().collect()
-        };
+        let instance_ids: Vec<String> = self
+            .active_instances
+            .iter()
+            .map(|instance| instance.key().clone())
+            .collect();
 
         for instance_id in instance_ids {
             if let Err(e) = self.stop_instance(&instance_id).await {
@@ -842,6 +1066,82 @@ impl InstanceManager {
     }
 }
 
+/// 实例是否闲置超时（有在途执行的实例不会被回收）
+fn is_idle_expired(instance: &FunctionInstance, now: chrono::DateTime<chrono::Utc>) -> bool {
+    instance.state == InstanceState::Idle
+        && instance.in_flight == 0
+        && (now - instance.last_activity).num_seconds() as u64
+            > instance.config.max_idle_duration_secs
+}
+
+/// 从函数到实例的映射中移除实例，函数没有实例时移除整项
+fn unindex_instance(
+    function_instances: &DashMap<String, Vec<String>>,
+    function_name: &str,
+    instance_id: &str,
+) {
+    function_instances.remove_if_mut(function_name, |_, instance_ids| {
+        instance_ids.retain(|id| id != instance_id);
+        instance_ids.is_empty()
+    });
+}
+
+/// 实例摘要信息
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceSummary {
+    /// 实例ID
+    pub instance_id: String,
+    /// 函数名称
+    pub function_name: String,
+    /// 当前状态
+    pub state: InstanceState,
+    /// 创建时间
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// 最后活动时间
+    pub last_activity: chrono::DateTime<chrono::Utc>,
+    /// 运行时长（秒）
+    pub uptime_secs: u64,
+    /// 执行统计
+    pub execution_stats: InstanceExecutionStats,
+}
+
+impl FunctionInstance {
+    fn summary(&self, now: chrono::DateTime<chrono::Utc>) -> InstanceSummary {
+        InstanceSummary {
+            instance_id: self.instance_id.clone(),
+            function_name: self.function_name.clone(),
+            state: self.state.clone(),
+            created_at: self.created_at,
+            last_activity: self.last_activity,
+            uptime_secs: (now - self.created_at).num_seconds().max(0) as u64,
+            execution_stats: self.execution_stats.clone(),
+        }
+    }
+}
+
+/// 脚本函数的常驻工作进程作为实例：执行调用时为运行中，否则为空闲
+fn worker_instance(worker: &WorkerSummary, now: chrono::DateTime<chrono::Utc>) -> InstanceSummary {
+    InstanceSummary {
+        instance_id: worker.id.clone(),
+        function_name: worker.function.clone(),
+        state: if worker.busy {
+            InstanceState::Running
+        } else {
+            InstanceState::Idle
+        },
+        created_at: worker.started_at,
+        last_activity: worker.last_used,
+        uptime_secs: (now - worker.started_at).num_seconds().max(0) as u64,
+        execution_stats: InstanceExecutionStats {
+            total_executions: worker.executions,
+            successful_executions: worker.executions - worker.failures,
+            failed_executions: worker.failures,
+            last_execution_time: (worker.executions > 0).then_some(worker.last_used),
+            ..Default::default()
+        },
+    }
+}
+
 /// 实例管理器统计信息
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct InstanceManagerStats {
@@ -859,6 +1159,16 @@ pub struct InstanceManagerStats {
     pub warming_instances: u64,
     /// 错误实例数
     pub error_instances: u64,
+    /// 已暂停实例数
+    pub suspended_instances: u64,
+    /// 累计暂停次数
+    pub total_suspensions: u64,
+    /// 累计恢复次数
+    pub total_resumes: u64,
+    /// 恢复时需要重新编译的次数
+    pub recompiled_resumes: u64,
+    /// 平均恢复耗时（毫秒）
+    pub avg_resume_time_ms: f64,
     /// 总执行次数
     pub total_executions: u64,
     /// 成功执行次数
@@ -870,6 +1180,7 @@ pub struct InstanceManagerStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::functions::priority::Priority;
     use crate::runtime::compiler::CompilerConfig;
     use crate::runtime::sandbox::SandboxConfig;
     use tempfile::TempDir;
@@ -904,6 +1215,42 @@ mod tests {
             dependencies: vec![],
             parameters: vec![],
             return_type: "i32".to_string(),
+            retry_policy: None,
+            idempotent: true,
+            labels: std::collections::HashMap::new(),
+            webhooks: None,
+            priority: Priority::Normal,
+            package: None,
+            http_response: false,
+            input_template: None,
+            revision: 0,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: false,
+            coerce_output: false,
+            strict_return_type: false,
+            log_redaction: Vec::new(),
+            circuit_breaker: true,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: false,
+            scratch_limit_mb: None,
+            persist_scratch: false,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: Vec::new(),
+            execution_hint: Default::default(),
+            mirror: None,
+            canary: None,
+            slo: None,
+            documentation: None,
+            header_fields: Default::default(),
+            http_routes: Vec::new(),
+            deprecation: None,
+            parameters_inferred: false,
+            return_type_inferred: false,
+            egress: None,
+            debug_capture: Default::default(),
         };
 
         let instance_id = manager
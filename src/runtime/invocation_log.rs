@@ -0,0 +1,835 @@
+//! 调用范围的结构化日志
+//!
+//! 调用方以 `?capture_log=<级别>` 请求捕获时，网关、调度器、运行时和沙箱在该调用的 span
+//! （带 `log_capture` 字段的 `invoke` span 及其子 span）内发出的 tracing 事件由
+//! [`InvocationLogLayer`] 复制到服务的 [`InvocationLogStore`] 中按调用 ID 保存的有界缓冲区，
+//! 通过 `GET /executions/:id/log` 以 JSON 行返回，读取时按函数的 `log_redaction` 脱敏。
+//!
+//! 每次调用的条目数和字节数有上限，所有调用的缓冲区共享存储的内存预算。超出任一上限时本次调用
+//! 停止捕获并追加一个截断标记，不淘汰其他调用已记录的内容；结束的调用按数量从最早的开始淘汰。
+//!
+//! tracing 层是全局安装的，同一进程中可以有多个服务：存储开始捕获时在进程级的登记表中分配捕获 ID，
+//! 写在 `invoke` span 的 `log_capture` 字段上，捕获层据此找到事件所属的存储和调用。
+
+use crate::functions::redaction::Redactor;
+use crate::functions::{FluxError, FunctionMetadata, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex as StdMutex};
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Filter, Layer};
+use tracing_subscriber::registry::LookupSpan;
+use utoipa::ToSchema;
+
+/// 截断标记的 target
+pub const MARKER_TARGET: &str = "flux::invocation_log";
+
+/// 每个条目在消息和字段之外的估算开销（字节）
+const ENTRY_OVERHEAD_BYTES: usize = 64;
+
+static ACTIVE: LazyLock<ActiveCaptures> = LazyLock::new(ActiveCaptures::default);
+
+/// 全局安装的捕获层（带过滤器）
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    InvocationLogLayer.filtered()
+}
+
+/// 调用日志捕获配置（`[log_capture]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogCaptureConfig {
+    /// 为 false 时忽略调用的 `capture_log`
+    pub enabled: bool,
+    /// 每次调用最多记录的条目数
+    pub max_entries: usize,
+    /// 每次调用最多记录的字节数（消息、target 和字段的估算大小）
+    pub max_bytes: usize,
+    /// 所有调用共享的内存预算（字节），用尽时新的条目不再记录
+    pub memory_budget_bytes: usize,
+    /// 保留的已结束调用数，超出时从最早结束的开始淘汰
+    pub retained: usize,
+}
+
+impl Default for LogCaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 1000,
+            max_bytes: 256 * 1024,
+            memory_budget_bytes: 64 * 1024 * 1024,
+            retained: 1000,
+        }
+    }
+}
+
+/// 捕获级别：记录该级别及更严重的事件
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureLevel {
+    /// 不捕获（默认）
+    #[default]
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl CaptureLevel {
+    fn of(level: &Level) -> Self {
+        match *level {
+            Level::ERROR => Self::Error,
+            Level::WARN => Self::Warn,
+            Level::INFO => Self::Info,
+            Level::DEBUG => Self::Debug,
+            Level::TRACE => Self::Trace,
+        }
+    }
+}
+
+/// 捕获的一条日志
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LogEntry {
+    /// 距开始捕获的微秒数
+    pub elapsed_us: u64,
+    /// 级别（`error`、`warn`、`info`、`debug`、`trace`）
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// 事件的结构化字段
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, Value>,
+    /// 发出事件的最内层 span
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<String>,
+}
+
+impl LogEntry {
+    fn size(&self) -> usize {
+        ENTRY_OVERHEAD_BYTES
+            + self.target.len()
+            + self.message.len()
+            + self
+                .fields
+                .iter()
+                .map(|(name, value)| name.len() + value.to_string().len())
+                .sum::<usize>()
+    }
+
+    fn marker(reason: &str, message: String) -> Self {
+        Self {
+            elapsed_us: 0,
+            level: "warn".to_string(),
+            target: MARKER_TARGET.to_string(),
+            message,
+            fields: BTreeMap::from([("reason".to_string(), Value::from(reason))]),
+            span: None,
+        }
+    }
+}
+
+/// 一次调用捕获的日志
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct InvocationLog {
+    pub invocation_id: String,
+    pub function: String,
+    pub level: CaptureLevel,
+    pub started_at: DateTime<Utc>,
+    /// 调用是否仍在执行
+    pub active: bool,
+    /// 是否因上限或内存预算停止捕获（最后一条为截断标记）
+    pub truncated: bool,
+    pub entries: Vec<LogEntry>,
+}
+
+impl InvocationLog {
+    /// 按 JSON 行输出条目
+    pub fn to_json_lines(&self) -> String {
+        self.entries
+            .iter()
+            .filter_map(|entry| serde_json::to_string(entry).ok())
+            .map(|line| line + "\n")
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct Capture {
+    function: String,
+    level: CaptureLevel,
+    /// 进程内唯一的捕获 ID
+    id: u64,
+    started: Instant,
+    started_at: DateTime<Utc>,
+    active: bool,
+    truncated: bool,
+    bytes: usize,
+    entries: Vec<LogEntry>,
+    redactor: Option<Redactor>,
+}
+
+impl Capture {
+    /// 停止捕获并追加截断标记
+    fn stop(&mut self, reason: &str, message: String) {
+        let mut marker = LogEntry::marker(reason, message);
+        marker.elapsed_us = self.started.elapsed().as_micros() as u64;
+        self.entries.push(marker);
+        self.truncated = true;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    captures: HashMap<String, Capture>,
+    /// 已结束的调用（按结束顺序）
+    finished: VecDeque<String>,
+    used_bytes: usize,
+}
+
+impl Inner {
+    fn remove(&mut self, invocation_id: &str) {
+        if let Some(capture) = self.captures.remove(invocation_id) {
+            self.used_bytes -= capture.bytes;
+        }
+    }
+}
+
+/// 进程内所有存储进行中的捕获，按捕获 ID 登记
+#[derive(Debug, Default)]
+struct ActiveCaptures {
+    captures: StdMutex<HashMap<u64, (CapturedBy, CaptureLevel)>>,
+    /// 进行中捕获的最详细级别，tracing 层据此跳过不需要的事件
+    max_level: AtomicU8,
+    next_id: AtomicU64,
+}
+
+impl ActiveCaptures {
+    fn captures(&self) -> std::sync::MutexGuard<'_, HashMap<u64, (CapturedBy, CaptureLevel)>> {
+        self.captures.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn insert(&self, captured: CapturedBy, level: CaptureLevel) {
+        let mut captures = self.captures();
+        captures.insert(captured.capture, (captured, level));
+        self.update_max_level(&captures);
+    }
+
+    fn remove(&self, capture: u64) {
+        let mut captures = self.captures();
+        captures.remove(&capture);
+        self.update_max_level(&captures);
+    }
+
+    fn get(&self, capture: u64) -> Option<CapturedBy> {
+        self.captures()
+            .get(&capture)
+            .map(|(captured, _)| captured.clone())
+    }
+
+    fn update_max_level(&self, captures: &HashMap<u64, (CapturedBy, CaptureLevel)>) {
+        let level = captures
+            .values()
+            .map(|(_, level)| *level)
+            .max()
+            .unwrap_or_default();
+        self.max_level.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// 是否有进行中的捕获需要该级别的事件
+    fn wants(&self, level: &Level) -> bool {
+        CaptureLevel::of(level) as u8 <= self.max_level.load(Ordering::Relaxed)
+    }
+
+    fn has_active(&self) -> bool {
+        self.max_level.load(Ordering::Relaxed) != CaptureLevel::Off as u8
+    }
+}
+
+/// 按调用 ID 保存捕获的日志（每个服务一个，所有调度器共享）
+#[derive(Debug, Default)]
+pub struct InvocationLogStore {
+    config: StdMutex<LogCaptureConfig>,
+    inner: StdMutex<Inner>,
+}
+
+impl InvocationLogStore {
+    pub fn new(config: LogCaptureConfig) -> Self {
+        Self {
+            config: StdMutex::new(config),
+            inner: StdMutex::default(),
+        }
+    }
+
+    pub fn configure(&self, config: &LogCaptureConfig) {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+    }
+
+    pub fn config(&self) -> LogCaptureConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn inner(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 开始捕获一次调用的日志，`level` 为 `off` 或关闭了捕获时返回 `None`
+    ///
+    /// 调用 ID 已有捕获的日志（进行中或尚未淘汰）时拒绝，不替换其他调用的记录；
+    /// 内存预算容不下一条日志时只记录截断标记。
+    pub fn begin(
+        self: &Arc<Self>,
+        invocation_id: &str,
+        function: &str,
+        level: CaptureLevel,
+    ) -> Result<Option<LogCaptureGuard>> {
+        let config = self.config();
+        if level == CaptureLevel::Off || !config.enabled {
+            return Ok(None);
+        }
+        let id = ACTIVE.next_id();
+        let mut capture = Capture {
+            function: function.to_string(),
+            level,
+            id,
+            started: Instant::now(),
+            started_at: Utc::now(),
+            active: true,
+            truncated: false,
+            bytes: 0,
+            entries: Vec::new(),
+            redactor: None,
+        };
+        let mut inner = self.inner();
+        if inner.captures.contains_key(invocation_id) {
+            return Err(FluxError::InvocationLogConflict {
+                id: invocation_id.to_string(),
+            });
+        }
+        if inner.used_bytes + ENTRY_OVERHEAD_BYTES > config.memory_budget_bytes {
+            capture.stop(
+                "memory_budget",
+                format!(
+                    "Log capture disabled: memory budget of {} bytes is exhausted",
+                    config.memory_budget_bytes
+                ),
+            );
+        }
+        inner.captures.insert(invocation_id.to_string(), capture);
+        drop(inner);
+        let invocation_id: Arc<str> = invocation_id.into();
+        ACTIVE.insert(
+            CapturedBy {
+                store: self.clone(),
+                invocation_id: invocation_id.clone(),
+                capture: id,
+            },
+            level,
+        );
+        Ok(Some(LogCaptureGuard {
+            store: self.clone(),
+            invocation_id,
+            id,
+        }))
+    }
+
+    /// 结束捕获，超出保留数时淘汰最早结束的调用
+    fn finish(&self, invocation_id: &str) {
+        let retained = self.config().retained;
+        let mut inner = self.inner();
+        let Some(capture) = inner.captures.get_mut(invocation_id) else {
+            return;
+        };
+        capture.active = false;
+        inner.finished.push_back(invocation_id.to_string());
+        while inner.finished.len() > retained {
+            let Some(oldest) = inner.finished.pop_front() else {
+                break;
+            };
+            inner.remove(&oldest);
+        }
+    }
+
+    /// 调用是否正在捕获日志
+    pub fn is_capturing(&self, invocation_id: &str) -> bool {
+        self.inner()
+            .captures
+            .get(invocation_id)
+            .is_some_and(|capture| capture.active)
+    }
+
+    /// 调用正在捕获日志时，记下读取时使用的脱敏规则（函数的 `log_redaction` 和输入中的敏感值）
+    pub fn attach_redactor(&self, invocation_id: &str, function: &FunctionMetadata, input: &Value) {
+        if self.is_capturing(invocation_id) {
+            self.set_redactor(
+                invocation_id,
+                Redactor::for_function(function).with_secrets_from(input),
+            );
+        }
+    }
+
+    fn set_redactor(&self, invocation_id: &str, redactor: Redactor) {
+        if let Some(capture) = self.inner().captures.get_mut(invocation_id) {
+            capture.redactor = Some(redactor);
+        }
+    }
+
+    /// 记录一条日志；超出本次调用的上限或存储的内存预算时停止捕获并追加截断标记
+    fn record(&self, invocation_id: &str, id: u64, level: &Level, mut entry: LogEntry) {
+        let config = self.config();
+        let mut inner = self.inner();
+        let used_bytes = inner.used_bytes;
+        let Some(capture) = inner.captures.get_mut(invocation_id) else {
+            return;
+        };
+        if capture.id != id
+            || !capture.active
+            || capture.truncated
+            || CaptureLevel::of(level) > capture.level
+        {
+            return;
+        }
+        let size = entry.size();
+        if capture.entries.len() >= config.max_entries {
+            capture.stop(
+                "max_entries",
+                format!(
+                    "Log capture stopped: {} entries recorded",
+                    config.max_entries
+                ),
+            );
+        } else if capture.bytes + size > config.max_bytes {
+            capture.stop(
+                "max_bytes",
+                format!("Log capture stopped: {} bytes recorded", capture.bytes),
+            );
+        } else if used_bytes + size > config.memory_budget_bytes {
+            capture.stop(
+                "memory_budget",
+                format!(
+                    "Log capture stopped: memory budget of {} bytes is exhausted",
+                    config.memory_budget_bytes
+                ),
+            );
+        } else {
+            // 在锁内取时间，条目的偏移按记录顺序单调递增
+            entry.elapsed_us = capture.started.elapsed().as_micros() as u64;
+            capture.entries.push(entry);
+            capture.bytes += size;
+            inner.used_bytes += size;
+        }
+    }
+
+    /// 调用捕获的日志，消息和字段按函数的脱敏规则处理
+    pub fn get(&self, invocation_id: &str) -> Result<InvocationLog> {
+        let inner = self.inner();
+        let capture =
+            inner
+                .captures
+                .get(invocation_id)
+                .ok_or_else(|| FluxError::InvocationLogNotFound {
+                    id: invocation_id.to_string(),
+                })?;
+        let mut entries = capture.entries.clone();
+        if let Some(redactor) = capture.redactor.as_ref().filter(|r| !r.is_empty()) {
+            for entry in &mut entries {
+                entry.message = redactor.scrub(&entry.message);
+                let fields = Value::Object(std::mem::take(&mut entry.fields).into_iter().collect());
+                if let Value::Object(fields) = redactor.redact(&fields) {
+                    entry.fields = fields
+                        .into_iter()
+                        .map(|(name, value)| match value {
+                            Value::String(text) => (name, Value::String(redactor.scrub(&text))),
+                            value => (name, value),
+                        })
+                        .collect();
+                }
+            }
+        }
+        Ok(InvocationLog {
+            invocation_id: invocation_id.to_string(),
+            function: capture.function.clone(),
+            level: capture.level,
+            started_at: capture.started_at,
+            active: capture.active,
+            truncated: capture.truncated,
+            entries,
+        })
+    }
+
+    /// 所有捕获占用的字节数
+    pub fn used_bytes(&self) -> usize {
+        self.inner().used_bytes
+    }
+}
+
+/// 进行中的捕获，drop 时结束
+#[derive(Debug)]
+pub struct LogCaptureGuard {
+    store: Arc<InvocationLogStore>,
+    invocation_id: Arc<str>,
+    id: u64,
+}
+
+impl LogCaptureGuard {
+    /// 捕获 ID，写在调用的 `invoke` span 的 `log_capture` 字段上
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for LogCaptureGuard {
+    fn drop(&mut self) {
+        ACTIVE.remove(self.id);
+        self.store.finish(&self.invocation_id);
+    }
+}
+
+/// span 所属的捕获（span 扩展，子 span 从父 span 继承）
+#[derive(Debug, Clone)]
+struct CapturedBy {
+    store: Arc<InvocationLogStore>,
+    invocation_id: Arc<str>,
+    capture: u64,
+}
+
+/// 把捕获中调用的 span 内的事件复制到所属 [`InvocationLogStore`] 的 tracing 层
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InvocationLogLayer;
+
+impl InvocationLogLayer {
+    /// 带上只放行进行中捕获所需事件的过滤器
+    pub fn filtered<S>(self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        Layer::with_filter(self, CaptureFilter)
+    }
+}
+
+impl<S> Layer<S> for InvocationLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = CaptureIdVisitor::default();
+        attrs.record(&mut visitor);
+        let captured = match visitor.0.and_then(|capture| ACTIVE.get(capture)) {
+            Some(captured) => Some(captured),
+            None => span
+                .parent()
+                .and_then(|parent| parent.extensions().get::<CapturedBy>().cloned()),
+        };
+        if let Some(captured) = captured {
+            span.extensions_mut().insert(captured);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(scope) = ctx.event_scope(event) else {
+            return;
+        };
+        let mut innermost = None;
+        for span in scope {
+            innermost.get_or_insert(span.name());
+            let Some(captured) = span.extensions().get::<CapturedBy>().cloned() else {
+                continue;
+            };
+            let metadata = event.metadata();
+            let mut visitor = EntryVisitor::default();
+            event.record(&mut visitor);
+            let entry = LogEntry {
+                elapsed_us: 0,
+                level: metadata.level().as_str().to_lowercase(),
+                target: metadata.target().to_string(),
+                message: visitor.message,
+                fields: visitor.fields,
+                span: innermost.map(str::to_string),
+            };
+            captured.store.record(
+                &captured.invocation_id,
+                captured.capture,
+                metadata.level(),
+                entry,
+            );
+            return;
+        }
+    }
+}
+
+/// 没有进行中的捕获时不启用任何 span 和事件，否则只放行最详细捕获级别以内的事件
+struct CaptureFilter;
+
+impl<S> Filter<S> for CaptureFilter {
+    fn enabled(&self, metadata: &Metadata<'_>, _: &Context<'_, S>) -> bool {
+        if metadata.is_span() {
+            ACTIVE.has_active()
+        } else {
+            ACTIVE.wants(metadata.level())
+        }
+    }
+
+    fn callsite_enabled(&self, _: &'static Metadata<'static>) -> tracing::subscriber::Interest {
+        // 捕获按请求开始和结束，不能缓存调用点的结果
+        tracing::subscriber::Interest::sometimes()
+    }
+}
+
+/// 取 span 的 `log_capture` 字段（捕获 ID）
+#[derive(Default)]
+struct CaptureIdVisitor(Option<u64>);
+
+impl Visit for CaptureIdVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "log_capture" {
+            self.0 = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, _: &Field, _: &dyn fmt::Debug) {}
+}
+
+/// 取事件的消息和其他字段
+#[derive(Default)]
+struct EntryVisitor {
+    message: String,
+    fields: BTreeMap<String, Value>,
+}
+
+impl EntryVisitor {
+    fn insert(&mut self, field: &Field, value: Value) {
+        // `log` 兼容层附带的元数据字段不记录
+        if !field.name().starts_with("log.") {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for EntryVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.insert(field, Value::from(value));
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            self.insert(field, Value::from(format!("{value:?}")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+
+    fn capture<F: FnOnce()>(f: F) {
+        let subscriber = tracing_subscriber::registry().with(InvocationLogLayer.filtered());
+        tracing::subscriber::with_default(subscriber, f);
+    }
+
+    fn begin(store: &Arc<InvocationLogStore>, id: &str, level: CaptureLevel) -> LogCaptureGuard {
+        store.begin(id, "f", level).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_events_in_captured_spans_are_recorded_in_order() {
+        let store = Arc::new(InvocationLogStore::new(LogCaptureConfig::default()));
+        capture(|| {
+            let guard = begin(&store, "inv-1", CaptureLevel::Debug);
+            let other = begin(&store, "inv-2", CaptureLevel::Error);
+            tracing::info!("outside any invocation");
+            let span =
+                tracing::info_span!("invoke", request_id = "inv-1", log_capture = guard.id());
+            let _entered = span.enter();
+            tracing::debug!(phase = "compile", "compiling");
+            {
+                let child = tracing::info_span!("spawn");
+                let _entered = child.enter();
+                tracing::debug!(phase = "spawn", pid = 42u64, "spawned");
+                tracing::trace!("too verbose");
+            }
+            tracing::error!("failed");
+            let span =
+                tracing::info_span!("invoke", request_id = "inv-2", log_capture = other.id());
+            span.in_scope(|| {
+                tracing::warn!("below the error level");
+                tracing::error!("other failure");
+            });
+            drop(guard);
+            tracing::error!("after the capture ended");
+        });
+
+        let log = store.get("inv-1").unwrap();
+        assert!(!log.active && !log.truncated);
+        let messages: Vec<_> = log.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["compiling", "spawned", "failed"]);
+        assert_eq!(log.entries[1].fields["pid"], 42);
+        assert_eq!(log.entries[1].span.as_deref(), Some("spawn"));
+        assert_eq!(log.entries[2].level, "error");
+        assert!(
+            log.entries
+                .windows(2)
+                .all(|pair| pair[0].elapsed_us <= pair[1].elapsed_us)
+        );
+        let other = store.get("inv-2").unwrap();
+        assert_eq!(other.entries.len(), 1);
+        assert_eq!(other.to_json_lines().lines().count(), 1);
+        assert!(matches!(
+            store.get("inv-3"),
+            Err(FluxError::InvocationLogNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_limits_stop_capture_with_a_marker_without_evicting_others() {
+        let store = Arc::new(InvocationLogStore::new(LogCaptureConfig {
+            max_entries: 4,
+            memory_budget_bytes: 1000,
+            retained: 2,
+            ..Default::default()
+        }));
+        capture(|| {
+            let first = begin(&store, "a", CaptureLevel::Info);
+            tracing::info_span!("invoke", log_capture = first.id()).in_scope(|| {
+                for i in 0..5 {
+                    tracing::info!("entry {i}");
+                }
+            });
+            drop(first);
+            let second = begin(&store, "b", CaptureLevel::Info);
+            tracing::info_span!("invoke", log_capture = second.id()).in_scope(|| {
+                for _ in 0..3 {
+                    tracing::info!("{}", "x".repeat(200));
+                }
+            });
+            drop(second);
+        });
+
+        let first = store.get("a").unwrap();
+        assert!(first.truncated);
+        assert_eq!(first.entries.len(), 5);
+        assert_eq!(first.entries[4].target, MARKER_TARGET);
+        assert_eq!(first.entries[4].fields["reason"], "max_entries");
+        // 预算用尽时停止的是新调用，已记录的调用保持不变
+        let second = store.get("b").unwrap();
+        assert!(second.truncated);
+        assert_eq!(second.entries.len(), 2);
+        assert_eq!(second.entries[1].fields["reason"], "memory_budget");
+        assert_eq!(store.get("a").unwrap().entries, first.entries);
+        assert!(store.used_bytes() <= 1000);
+
+        // 超出保留数时最早结束的调用被淘汰
+        drop(begin(&store, "c", CaptureLevel::Info));
+        assert!(store.get("a").is_err());
+        assert!(store.get("b").is_ok() && store.get("c").is_ok());
+        assert!(store.begin("d", "f", CaptureLevel::Off).unwrap().is_none());
+
+        // 预算容不下一条日志时新的捕获只有截断标记
+        let exhausted = Arc::new(InvocationLogStore::new(LogCaptureConfig {
+            memory_budget_bytes: 10,
+            ..Default::default()
+        }));
+        let _guard = begin(&exhausted, "e", CaptureLevel::Info);
+        let log = exhausted.get("e").unwrap();
+        assert!(log.truncated);
+        assert_eq!(log.entries.len(), 1);
+        assert_eq!(log.entries[0].fields["reason"], "memory_budget");
+    }
+
+    #[test]
+    fn test_stores_are_separate_and_captures_are_not_replaced() {
+        let first = Arc::new(InvocationLogStore::default());
+        let second = Arc::new(InvocationLogStore::default());
+        capture(|| {
+            // 两个服务的调用使用同一个请求 ID
+            let a = begin(&first, "same", CaptureLevel::Info);
+            let b = begin(&second, "same", CaptureLevel::Info);
+            tracing::info_span!("invoke", log_capture = a.id()).in_scope(|| tracing::info!("a"));
+            tracing::info_span!("invoke", log_capture = b.id()).in_scope(|| tracing::info!("b"));
+            // 同一存储中已有捕获的调用 ID 不能再次捕获
+            assert!(matches!(
+                first.begin("same", "g", CaptureLevel::Debug),
+                Err(FluxError::InvocationLogConflict { .. })
+            ));
+            drop(a);
+            assert!(first.begin("same", "g", CaptureLevel::Debug).is_err());
+        });
+        let messages = |store: &InvocationLogStore| {
+            let log = store.get("same").unwrap();
+            assert_eq!(log.function, "f");
+            log.entries
+                .into_iter()
+                .map(|entry| entry.message)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(messages(&first), ["a"]);
+        assert_eq!(messages(&second), ["b"]);
+    }
+
+    #[test]
+    fn test_reading_applies_redaction() {
+        let store = Arc::new(InvocationLogStore::new(LogCaptureConfig::default()));
+        let mut function = FunctionMetadata::new("f".to_string(), "return input".to_string());
+        function.log_redaction = vec!["password".to_string()];
+        capture(|| {
+            let guard = begin(&store, "r", CaptureLevel::Info);
+            store.attach_redactor("r", &function, &serde_json::json!({"password": "hunter2"}));
+            tracing::info_span!("invoke", log_capture = guard.id()).in_scope(|| {
+                tracing::info!(
+                    password = "hunter2",
+                    detail = "got hunter2",
+                    "login with hunter2"
+                );
+            });
+        });
+        let log = store.get("r").unwrap();
+        let entry = &log.entries[0];
+        assert!(!entry.message.contains("hunter2"));
+        assert!(!entry.fields["password"].to_string().contains("hunter2"));
+        assert!(!entry.fields["detail"].to_string().contains("hunter2"));
+    }
+}
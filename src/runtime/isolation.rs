@@ -0,0 +1,515 @@
+//! Linux 命名空间隔离：沙箱进程在新的 user、mount 命名空间中运行（禁止网络时还有 network
+//! 命名空间，只有回环接口），根目录切换为只包含只读系统目录、运行时安装目录、`allowed_dirs`
+//! 和可写隔离目录的最小根目录
+//!
+//! 命名空间不可用时（非 Linux 或权限不足）退化为仅进程隔离：执行结果记录为 `process-only`，
+//! 进程仍能访问网络和当前用户可读的宿主文件。
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::process::Command as TokioCommand;
+
+/// `temp_root` 下作为新根目录挂载点的目录名（磁盘清理时跳过）
+pub const ROOT_MOUNT_DIR: &str = ".root";
+
+/// 以相同路径只读挂载进新根目录的系统目录（存在时；符号链接按原样重建）
+const SYSTEM_DIRS: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/libx32", "/etc", "/opt", "/nix",
+];
+
+/// 挂载进新根目录的设备文件
+const DEVICES: &[&str] = &[
+    "/dev/null",
+    "/dev/zero",
+    "/dev/full",
+    "/dev/random",
+    "/dev/urandom",
+];
+
+/// 沙箱进程实际使用的隔离方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IsolationLevel {
+    /// 独立的命名空间：网络和文件系统按沙箱配置受限
+    Namespaces,
+    /// 只有隔离目录、环境变量过滤和资源限制
+    ProcessOnly,
+}
+
+impl IsolationLevel {
+    /// 当前进程可用的隔离方式（首次调用时在子进程中试建命名空间，结果缓存）
+    pub fn detect() -> Self {
+        static LEVEL: OnceLock<IsolationLevel> = OnceLock::new();
+        *LEVEL.get_or_init(|| {
+            #[cfg(target_os = "linux")]
+            return linux::probe();
+
+            #[cfg(not(target_os = "linux"))]
+            IsolationLevel::ProcessOnly
+        })
+    }
+
+    pub fn is_confined(self) -> bool {
+        self == Self::Namespaces
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Namespaces => "namespaces",
+            Self::ProcessOnly => "process-only",
+        }
+    }
+}
+
+/// 一次执行的命名空间配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct Confinement {
+    /// 新根目录的挂载点（宿主上的空目录，只在子进程的命名空间中挂载）
+    pub root: PathBuf,
+    /// 可写的隔离目录，子进程在其中启动
+    pub work_dir: PathBuf,
+    /// 以相同路径只读挂载的宿主目录
+    pub read_only: Vec<PathBuf>,
+    /// 为 false 时进入新的网络命名空间（只有回环接口）
+    pub allow_network: bool,
+}
+
+impl Confinement {
+    /// 只包含系统目录和隔离目录的最小根目录
+    pub fn new(
+        root: impl Into<PathBuf>,
+        work_dir: impl Into<PathBuf>,
+        allow_network: bool,
+    ) -> Self {
+        Self {
+            root: root.into(),
+            work_dir: work_dir.into(),
+            read_only: Vec::new(),
+            allow_network,
+        }
+    }
+
+    /// 另外以只读方式挂载宿主目录（不存在时忽略）
+    pub fn with_read_only(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        if !self.read_only.contains(&path) {
+            self.read_only.push(path);
+        }
+        self
+    }
+
+    /// 另外挂载程序所在的安装目录（例如 `~/.pyenv/shims/python3` 对应 `~/.pyenv`），
+    /// 程序位于系统目录或隔离目录中时不需要
+    pub fn with_program(mut self, program: &Path) -> Self {
+        let mut candidates = vec![program.to_path_buf()];
+        if let Ok(canonical) = program.canonicalize() {
+            candidates.push(canonical);
+        }
+        for path in candidates {
+            if !path.is_absolute()
+                || path.starts_with(&self.work_dir)
+                || SYSTEM_DIRS.iter().any(|dir| path.starts_with(dir))
+            {
+                continue;
+            }
+            let Some(bin_dir) = path.parent() else {
+                continue;
+            };
+            // 安装目录为 bin 目录的上一级，太靠近根目录时只挂载 bin 目录本身
+            let prefix = match bin_dir.parent() {
+                Some(prefix) if prefix.components().count() > 2 => prefix,
+                _ => bin_dir,
+            };
+            self = self.with_read_only(prefix);
+        }
+        self
+    }
+
+    /// 让子进程在 exec 前进入命名空间并切换到最小根目录（仅 Linux）
+    pub fn apply(&self, cmd: &mut TokioCommand) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            let plan = linux::Plan::build(self)?;
+            // SAFETY: 闭包在 fork 之后、exec 之前运行，只调用系统调用，所需内存已预先分配
+            unsafe {
+                cmd.pre_exec(move || plan.enter());
+            }
+            Ok(())
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = cmd;
+            Err(anyhow::anyhow!(
+                "Namespace isolation is not supported on this platform"
+            ))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{Confinement, DEVICES, IsolationLevel, SYSTEM_DIRS};
+    use anyhow::{Context, Result};
+    use std::collections::HashSet;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Component, Path, PathBuf};
+
+    /// 子进程中的一步挂载准备
+    enum Step {
+        Mkdir(CString),
+        Touch(CString),
+        Symlink {
+            target: CString,
+            link: CString,
+        },
+        Bind {
+            source: CString,
+            target: CString,
+            /// 只读重新挂载时保留的源挂载标志（用户命名空间中不能去掉 nosuid/nodev 等）
+            read_only: Option<libc::c_ulong>,
+        },
+    }
+
+    /// 预先准备好的命名空间配置，`enter` 在子进程中执行
+    pub struct Plan {
+        flags: libc::c_int,
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+        root: CString,
+        steps: Vec<Step>,
+        work_dir: CString,
+        loopback: bool,
+    }
+
+    fn c_path(path: &Path) -> Result<CString> {
+        CString::new(path.as_os_str().as_bytes())
+            .with_context(|| format!("Path contains a NUL byte: {path:?}"))
+    }
+
+    /// 新根目录中对应宿主路径 `path` 的位置
+    fn inside(root: &Path, path: &Path) -> PathBuf {
+        root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// 在新根目录中逐级创建 `path` 的各级目录（已创建或已重建为符号链接的跳过）
+    fn mkdir_all(
+        steps: &mut Vec<Step>,
+        created: &mut HashSet<PathBuf>,
+        root: &Path,
+        path: &Path,
+    ) -> Result<()> {
+        let mut current = PathBuf::from("/");
+        for component in path.components() {
+            if let Component::Normal(name) = component {
+                current.push(name);
+                if created.insert(current.clone()) {
+                    steps.push(Step::Mkdir(c_path(&inside(root, &current))?));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 源目录所在挂载的标志，只读重新挂载时需要保留
+    fn mount_flags(path: &Path) -> libc::c_ulong {
+        let Ok(path) = c_path(path) else {
+            return 0;
+        };
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return 0;
+        }
+        [
+            (libc::ST_NOSUID, libc::MS_NOSUID),
+            (libc::ST_NODEV, libc::MS_NODEV),
+            (libc::ST_NOEXEC, libc::MS_NOEXEC),
+            (libc::ST_NOATIME, libc::MS_NOATIME),
+            (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+            (libc::ST_RELATIME, libc::MS_RELATIME),
+        ]
+        .iter()
+        .filter(|(st, _)| stat.f_flag & st != 0)
+        .fold(0, |flags, (_, ms)| flags | ms)
+    }
+
+    impl Plan {
+        pub fn build(confinement: &Confinement) -> Result<Self> {
+            let root = &confinement.root;
+            let work_dir = confinement.work_dir.canonicalize().with_context(|| {
+                format!("Failed to resolve work dir {:?}", confinement.work_dir)
+            })?;
+            let mut steps = Vec::new();
+            let mut created = HashSet::new();
+            let mut binds: Vec<(PathBuf, bool)> = Vec::new();
+            for dir in SYSTEM_DIRS.iter().map(Path::new) {
+                match std::fs::symlink_metadata(dir) {
+                    Ok(meta) if meta.file_type().is_symlink() => {
+                        let target = std::fs::read_link(dir)?;
+                        steps.push(Step::Symlink {
+                            target: c_path(&target)?,
+                            link: c_path(&inside(root, dir))?,
+                        });
+                        created.insert(dir.to_path_buf());
+                    }
+                    Ok(meta) if meta.is_dir() => binds.push((dir.to_path_buf(), true)),
+                    _ => {}
+                }
+            }
+            for dir in &confinement.read_only {
+                match dir.canonicalize() {
+                    Ok(dir) if dir.is_dir() => binds.push((dir, true)),
+                    _ => tracing::debug!("Skipping missing read-only dir {:?}", dir),
+                }
+            }
+            binds.push((PathBuf::from("/proc"), false));
+            binds.push((work_dir.clone(), false));
+            // 父目录先挂载，子目录挂载在其上
+            binds.sort_by_key(|(path, _)| path.components().count());
+            binds.dedup_by(|a, b| a.0 == b.0);
+
+            for (path, read_only) in &binds {
+                mkdir_all(&mut steps, &mut created, root, path)?;
+                steps.push(Step::Bind {
+                    source: c_path(path)?,
+                    target: c_path(&inside(root, path))?,
+                    read_only: read_only.then(|| mount_flags(path)),
+                });
+            }
+            mkdir_all(&mut steps, &mut created, root, Path::new("/dev"))?;
+            for device in DEVICES.iter().map(Path::new).filter(|path| path.exists()) {
+                let target = c_path(&inside(root, device))?;
+                steps.push(Step::Touch(target.clone()));
+                steps.push(Step::Bind {
+                    source: c_path(device)?,
+                    target,
+                    read_only: None,
+                });
+            }
+
+            let mut flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS;
+            if !confinement.allow_network {
+                flags |= libc::CLONE_NEWNET;
+            }
+            let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+            Ok(Self {
+                flags,
+                uid_map: format!("{uid} {uid} 1").into_bytes(),
+                gid_map: format!("{gid} {gid} 1").into_bytes(),
+                root: c_path(root)?,
+                steps,
+                work_dir: c_path(&work_dir)?,
+                loopback: !confinement.allow_network,
+            })
+        }
+
+        /// 在子进程中执行：进入命名空间、搭建最小根目录并切换过去
+        pub fn enter(&self) -> io::Result<()> {
+            check(unsafe { libc::unshare(self.flags) })?;
+            enter_user_namespace(&self.uid_map, &self.gid_map)?;
+            check(unsafe {
+                libc::mount(
+                    std::ptr::null(),
+                    c"/".as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_REC | libc::MS_PRIVATE,
+                    std::ptr::null(),
+                )
+            })?;
+            check(unsafe {
+                libc::mount(
+                    c"tmpfs".as_ptr(),
+                    self.root.as_ptr(),
+                    c"tmpfs".as_ptr(),
+                    libc::MS_NOSUID | libc::MS_NODEV,
+                    c"mode=0755".as_ptr().cast(),
+                )
+            })?;
+            for step in &self.steps {
+                run_step(step)?;
+            }
+            if self.loopback {
+                loopback_up()?;
+            }
+
+            check(unsafe { libc::chdir(self.root.as_ptr()) })?;
+            // 新旧根目录叠放在同一位置，卸载旧根目录后只剩新根目录
+            check(unsafe {
+                libc::syscall(libc::SYS_pivot_root, c".".as_ptr(), c".".as_ptr()) as libc::c_int
+            })?;
+            check(unsafe { libc::umount2(c".".as_ptr(), libc::MNT_DETACH) })?;
+            check(unsafe { libc::chdir(self.work_dir.as_ptr()) })
+        }
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_file(path: &std::ffi::CStr, data: &[u8]) -> io::Result<()> {
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+        check(fd)?;
+        let written = unsafe { libc::write(fd, data.as_ptr().cast(), data.len()) };
+        unsafe { libc::close(fd) };
+        if written < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// 在新的用户命名空间中把当前用户和组映射为自身
+    fn enter_user_namespace(uid_map: &[u8], gid_map: &[u8]) -> io::Result<()> {
+        write_file(c"/proc/self/setgroups", b"deny")?;
+        write_file(c"/proc/self/uid_map", uid_map)?;
+        write_file(c"/proc/self/gid_map", gid_map)
+    }
+
+    fn run_step(step: &Step) -> io::Result<()> {
+        match step {
+            Step::Mkdir(path) => {
+                if unsafe { libc::mkdir(path.as_ptr(), 0o755) } == -1 {
+                    let error = io::Error::last_os_error();
+                    if error.raw_os_error() != Some(libc::EEXIST) {
+                        return Err(error);
+                    }
+                }
+                Ok(())
+            }
+            Step::Touch(path) => {
+                let fd = unsafe {
+                    libc::open(
+                        path.as_ptr(),
+                        libc::O_WRONLY | libc::O_CREAT | libc::O_CLOEXEC,
+                        0o644,
+                    )
+                };
+                check(fd)?;
+                unsafe { libc::close(fd) };
+                Ok(())
+            }
+            Step::Symlink { target, link } => {
+                check(unsafe { libc::symlink(target.as_ptr(), link.as_ptr()) })
+            }
+            Step::Bind {
+                source,
+                target,
+                read_only,
+            } => {
+                check(unsafe {
+                    libc::mount(
+                        source.as_ptr(),
+                        target.as_ptr(),
+                        std::ptr::null(),
+                        libc::MS_BIND | libc::MS_REC,
+                        std::ptr::null(),
+                    )
+                })?;
+                if let Some(flags) = read_only {
+                    check(unsafe {
+                        libc::mount(
+                            std::ptr::null(),
+                            target.as_ptr(),
+                            std::ptr::null(),
+                            libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY | flags,
+                            std::ptr::null(),
+                        )
+                    })?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// 启用新网络命名空间中的回环接口
+    fn loopback_up() -> io::Result<()> {
+        let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+        check(fd)?;
+        let mut request: libc::ifreq = unsafe { std::mem::zeroed() };
+        for (dst, src) in request.ifr_name.iter_mut().zip(b"lo\0") {
+            *dst = *src as libc::c_char;
+        }
+        let result = (|| {
+            check(unsafe { libc::ioctl(fd, libc::SIOCGIFFLAGS, &mut request) })?;
+            unsafe { request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short };
+            check(unsafe { libc::ioctl(fd, libc::SIOCSIFFLAGS, &request) })
+        })();
+        unsafe { libc::close(fd) };
+        result
+    }
+
+    /// 在子进程中试建命名空间，成功时可用命名空间隔离
+    pub fn probe() -> IsolationLevel {
+        let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+        let uid_map = format!("{uid} {uid} 1").into_bytes();
+        let gid_map = format!("{gid} {gid} 1").into_bytes();
+        let flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET;
+
+        // SAFETY: 子进程只调用系统调用后退出
+        let pid = unsafe { libc::fork() };
+        if pid == 0 {
+            let entered = check(unsafe { libc::unshare(flags) })
+                .and_then(|_| enter_user_namespace(&uid_map, &gid_map))
+                .and_then(|_| {
+                    check(unsafe {
+                        libc::mount(
+                            std::ptr::null(),
+                            c"/".as_ptr(),
+                            std::ptr::null(),
+                            libc::MS_REC | libc::MS_PRIVATE,
+                            std::ptr::null(),
+                        )
+                    })
+                });
+            unsafe { libc::_exit(if entered.is_ok() { 0 } else { 1 }) };
+        }
+        if pid < 0 {
+            return IsolationLevel::ProcessOnly;
+        }
+        let mut status = 0;
+        let waited = loop {
+            let result = unsafe { libc::waitpid(pid, &mut status, 0) };
+            if result != -1 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                break result;
+            }
+        };
+        if waited == pid && libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0 {
+            IsolationLevel::Namespaces
+        } else {
+            IsolationLevel::ProcessOnly
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isolation_level_serializes_as_marker() {
+        assert_eq!(
+            serde_json::to_value(IsolationLevel::ProcessOnly).unwrap(),
+            "process-only"
+        );
+        assert_eq!(IsolationLevel::Namespaces.name(), "namespaces");
+    }
+
+    #[test]
+    fn test_program_install_prefix_is_mounted() {
+        let confinement = Confinement::new("/tmp/root", "/tmp/jail", false)
+            .with_program(Path::new("/opt-tools/.pyenv/shims/python3"))
+            .with_program(Path::new("/usr/bin/node"))
+            .with_program(Path::new("/tmp/jail/executor"));
+        assert_eq!(
+            confinement.read_only,
+            vec![PathBuf::from("/opt-tools/.pyenv")]
+        );
+    }
+}
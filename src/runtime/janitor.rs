@@ -0,0 +1,402 @@
+use crate::runtime::compiler::RustCompiler;
+use crate::runtime::isolation::ROOT_MOUNT_DIR;
+use crate::runtime::sandbox::SandboxExecutor;
+use crate::runtime::scratch::ARTIFACTS_DIR;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+
+/// 脚本缓存所在的子目录（由脚本缓存自行淘汰，清理时跳过）
+const SCRIPT_CACHE_DIR: &str = "scripts";
+
+/// 最近使用过的编译产物不按磁盘预算淘汰，避免删除即将被加载的库
+const MIN_ARTIFACT_IDLE: Duration = Duration::from_secs(60);
+
+/// 磁盘清理配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JanitorConfig {
+    /// 是否启动后台清理任务
+    pub enabled: bool,
+    /// 两次清理之间的间隔（秒）
+    pub interval_secs: u64,
+    /// 无人引用且超过该时长未修改的目录和产物视为孤立（秒）
+    pub orphan_ttl_secs: u64,
+    /// 沙箱临时目录、编译缓存和共享 target 目录的总磁盘预算（字节），缺省不限制
+    pub disk_budget_bytes: Option<u64>,
+}
+
+impl Default for JanitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 300,
+            orphan_ttl_secs: 3600,
+            disk_budget_bytes: Some(5 * 1024 * 1024 * 1024),
+        }
+    }
+}
+
+/// 一次清理的结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GcReport {
+    /// 删除的孤立沙箱目录数
+    pub removed_dirs: usize,
+    /// 删除的孤立编译产物文件数
+    pub removed_files: usize,
+    /// 因超出磁盘预算淘汰的编译产物数
+    pub evicted_artifacts: usize,
+    /// 过了保留时长而删除的暂存文件目录数
+    pub expired_scratch: usize,
+    /// 因属于运行中的执行而跳过的目录数
+    pub skipped_active: usize,
+    pub freed_bytes: u64,
+    /// 清理后的磁盘占用
+    pub disk_usage_bytes: u64,
+    pub duration_ms: u64,
+}
+
+/// 磁盘清理累计统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct JanitorStats {
+    pub runs: u64,
+    pub last_run_at: Option<DateTime<Utc>>,
+    /// 最近一次清理后的磁盘占用
+    pub disk_usage_bytes: u64,
+    pub disk_budget_bytes: Option<u64>,
+    pub removed_dirs: u64,
+    pub removed_files: u64,
+    pub evicted_artifacts: u64,
+    pub expired_scratch: u64,
+    pub freed_bytes: u64,
+}
+
+/// 磁盘清理器：定期删除孤立的沙箱目录和编译产物，并按磁盘预算淘汰最久未用的编译产物
+///
+/// 运行中执行的工作目录（沙箱活跃进程表）和编译索引引用的产物从不删除，
+/// 退役中的产物由编译器在无人持有且过了宽限期后删除。
+#[derive(Debug)]
+pub struct DiskJanitor {
+    config: JanitorConfig,
+    compiler: Arc<RustCompiler>,
+    sandbox: Arc<SandboxExecutor>,
+    stats: StdMutex<JanitorStats>,
+    /// 同一时间只运行一次清理
+    running: Mutex<()>,
+}
+
+/// 目录树的大小和最近修改时间
+struct TreeUsage {
+    bytes: u64,
+    newest: SystemTime,
+}
+
+impl DiskJanitor {
+    pub fn new(
+        config: JanitorConfig,
+        compiler: Arc<RustCompiler>,
+        sandbox: Arc<SandboxExecutor>,
+    ) -> Self {
+        let stats = JanitorStats {
+            disk_budget_bytes: config.disk_budget_bytes,
+            ..Default::default()
+        };
+        Self {
+            config,
+            compiler,
+            sandbox,
+            stats: StdMutex::new(stats),
+            running: Mutex::new(()),
+        }
+    }
+
+    pub fn config(&self) -> &JanitorConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> JanitorStats {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 启动后台清理任务（未启用时不启动）
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+        let janitor = self.clone();
+        let interval = Duration::from_secs(self.config.interval_secs.max(1));
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match janitor.run_once().await {
+                    Ok(report) if report.freed_bytes > 0 => tracing::info!(
+                        "Disk janitor freed {} bytes ({} dirs, {} files, {} artifacts evicted)",
+                        report.freed_bytes,
+                        report.removed_dirs,
+                        report.removed_files,
+                        report.evicted_artifacts
+                    ),
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Disk janitor pass failed: {}", e),
+                }
+            }
+        }))
+    }
+
+    /// 执行一次清理
+    pub async fn run_once(&self) -> Result<GcReport> {
+        let _running = self.running.lock().await;
+        let start = std::time::Instant::now();
+        let ttl = Duration::from_secs(self.config.orphan_ttl_secs);
+        let mut report = GcReport::default();
+
+        self.sweep_sandbox_dirs(ttl, &mut report).await;
+        let (expired, freed) = self.sandbox.scratch_store().sweep();
+        report.expired_scratch = expired;
+        report.freed_bytes += freed;
+        // 退役产物在句柄释放且过了宽限期后删除
+        let (removed, freed) = self.compiler.sweep_retired();
+        report.removed_files += removed;
+        report.freed_bytes += freed;
+        self.sweep_artifacts(ttl, &mut report).await;
+
+        let mut usage = self.disk_usage();
+        if let Some(budget) = self.config.disk_budget_bytes
+            && usage > budget
+        {
+            let (evicted, freed) = self
+                .compiler
+                .evict_lru(usage - budget, MIN_ARTIFACT_IDLE)
+                .await;
+            report.evicted_artifacts = evicted;
+            report.freed_bytes += freed;
+            usage = self.disk_usage();
+            if usage > budget {
+                tracing::warn!(
+                    "Disk usage {} bytes still exceeds budget {} bytes after eviction",
+                    usage,
+                    budget
+                );
+            }
+        }
+        report.disk_usage_bytes = usage;
+        report.duration_ms = start.elapsed().as_millis() as u64;
+
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        stats.runs += 1;
+        stats.last_run_at = Some(Utc::now());
+        stats.disk_usage_bytes = usage;
+        stats.removed_dirs += report.removed_dirs as u64;
+        stats.removed_files += report.removed_files as u64;
+        stats.evicted_artifacts += report.evicted_artifacts as u64;
+        stats.expired_scratch += report.expired_scratch as u64;
+        stats.freed_bytes += report.freed_bytes;
+        Ok(report)
+    }
+
+    /// 删除 `temp_root` 下孤立的执行目录（保留的暂存文件按其保留时长另行删除）
+    async fn sweep_sandbox_dirs(&self, ttl: Duration, report: &mut GcReport) {
+        let active = self.sandbox.active_work_dirs().await;
+        for path in list_dir(self.sandbox.temp_root()) {
+            if path.file_name().is_some_and(|name| {
+                name == SCRIPT_CACHE_DIR || name == ROOT_MOUNT_DIR || name == ARTIFACTS_DIR
+            }) || !path.is_dir()
+            {
+                continue;
+            }
+            if active.contains(&path) {
+                report.skipped_active += 1;
+                continue;
+            }
+            let usage = tree_usage(&path);
+            if !is_older_than(usage.newest, ttl) {
+                continue;
+            }
+            // 删除前再次确认：目录可能刚被新的执行使用
+            if self.sandbox.active_work_dirs().await.contains(&path) {
+                report.skipped_active += 1;
+                continue;
+            }
+            match fs::remove_dir_all(&path) {
+                Ok(()) => {
+                    report.removed_dirs += 1;
+                    report.freed_bytes += usage.bytes;
+                }
+                Err(e) => tracing::debug!("Failed to remove sandbox dir {:?}: {}", path, e),
+            }
+        }
+    }
+
+    /// 删除编译缓存和共享 target 目录中编译索引未引用的产物
+    async fn sweep_artifacts(&self, ttl: Duration, report: &mut GcReport) {
+        let referenced = self.compiler.referenced_artifacts().await;
+        let orphans = list_dir(self.compiler.cache_dir())
+            .into_iter()
+            .filter(|path| path.is_file() && !referenced.contains(path));
+        let mut candidates: Vec<PathBuf> = orphans.collect();
+
+        if let Some(target_dir) = self.compiler.target_dir() {
+            let crates = self.compiler.referenced_crate_names().await;
+            let release = target_dir.join("release");
+            for path in list_dir(&release)
+                .into_iter()
+                .chain(list_dir(&release.join("deps")))
+            {
+                let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                let Some(start) = name.find("flux_function_") else {
+                    continue;
+                };
+                let crate_name = name[start..].split(['.', '-']).next().unwrap_or_default();
+                if path.is_file() && !crates.contains(crate_name) {
+                    candidates.push(path);
+                }
+            }
+        }
+
+        for path in candidates {
+            let Ok(metadata) = fs::symlink_metadata(&path) else {
+                continue;
+            };
+            let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+            if !is_older_than(modified, ttl) {
+                continue;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    report.removed_files += 1;
+                    report.freed_bytes += metadata.len();
+                }
+                Err(e) => tracing::debug!("Failed to remove artifact {:?}: {}", path, e),
+            }
+        }
+    }
+
+    /// 沙箱临时目录、编译缓存和共享 target 目录的总占用
+    fn disk_usage(&self) -> u64 {
+        let mut roots = vec![
+            self.sandbox.temp_root().to_path_buf(),
+            self.compiler.cache_dir().to_path_buf(),
+        ];
+        roots.extend(self.compiler.target_dir());
+        let unique: HashSet<_> = roots.into_iter().collect();
+        unique.iter().map(|root| tree_usage(root).bytes).sum()
+    }
+}
+
+fn list_dir(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default()
+}
+
+fn is_older_than(time: SystemTime, ttl: Duration) -> bool {
+    SystemTime::now()
+        .duration_since(time)
+        .is_ok_and(|age| age >= ttl)
+}
+
+/// 统计目录树大小和最近修改时间（不跟随符号链接）
+fn tree_usage(path: &Path) -> TreeUsage {
+    let mut usage = TreeUsage {
+        bytes: 0,
+        newest: SystemTime::UNIX_EPOCH,
+    };
+    let mut stack = vec![path.to_path_buf()];
+    while let Some(path) = stack.pop() {
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if let Ok(modified) = metadata.modified() {
+            usage.newest = usage.newest.max(modified);
+        }
+        if metadata.is_dir() {
+            stack.extend(list_dir(&path));
+        } else if metadata.is_file() {
+            usage.bytes += metadata.len();
+        }
+    }
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::compiler::CompilerConfig;
+    use crate::runtime::sandbox::{SandboxConfig, SandboxLimits};
+
+    #[tokio::test]
+    async fn test_gc_removes_orphans_but_not_running_executions() {
+        let root = tempfile::TempDir::new().unwrap();
+        let compiler = Arc::new(
+            RustCompiler::new(CompilerConfig {
+                cache_dir: root.path().join("cache"),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let sandbox = Arc::new(
+            SandboxExecutor::new(SandboxConfig {
+                temp_root: root.path().join("sandbox"),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let janitor = DiskJanitor::new(
+            JanitorConfig {
+                orphan_ttl_secs: 0,
+                ..Default::default()
+            },
+            compiler.clone(),
+            sandbox.clone(),
+        );
+
+        // 被杀死的执行留下的目录和编译索引未引用的产物
+        let orphan_dir = sandbox.temp_root().join(".tmp-orphan");
+        fs::create_dir_all(orphan_dir.join("target")).unwrap();
+        fs::write(orphan_dir.join("target/big"), vec![0u8; 4096]).unwrap();
+        let orphan_lib = compiler.cache_dir().join("gone_abc.so");
+        fs::write(&orphan_lib, vec![0u8; 1024]).unwrap();
+        // 未过保留时长的暂存文件不是孤立目录
+        let persisted = sandbox.scratch_store().root().join("inv-1");
+        fs::create_dir_all(&persisted).unwrap();
+        fs::write(persisted.join("report.txt"), b"done").unwrap();
+
+        // 运行中的执行
+        let running = {
+            let sandbox = sandbox.clone();
+            tokio::spawn(async move {
+                let limits = SandboxLimits::from(&SandboxConfig::default());
+                sandbox
+                    .execute_command_in_sandbox("sleep", &["1".to_string()], None, &limits)
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let active = sandbox.active_work_dirs().await;
+        assert!(!active.is_empty());
+
+        let report = janitor.run_once().await.unwrap();
+        assert_eq!(report.removed_dirs, 1);
+        assert_eq!(report.removed_files, 1);
+        assert!(report.freed_bytes >= 4096 + 1024);
+        assert!(report.skipped_active >= 1);
+        assert!(!orphan_dir.exists() && !orphan_lib.exists());
+        assert_eq!(report.expired_scratch, 0);
+        assert!(persisted.join("report.txt").exists());
+        assert!(active.iter().all(|dir| dir.exists()));
+
+        let result = running.await.unwrap().unwrap();
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(janitor.stats().runs, 1);
+    }
+}
@@ -1,26 +1,215 @@
-use crate::functions::{FluxError, FunctionMetadata, RegisterFunctionRequest, Result};
+use crate::functions::docs;
+use crate::functions::header::{self, FileHeader};
+use crate::functions::name::FunctionName;
+use crate::functions::package::{
+    MAX_PACKAGE_BYTES, MAX_PACKAGE_FILES, PackageFile, validate_package_path,
+};
+use crate::functions::{
+    FluxError, FunctionMetadata, FunctionParameter, RegisterFunctionRequest, Result, RetryPolicy,
+};
 use crate::runtime::validator::FunctionValidator;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tokio::fs;
+use utoipa::ToSchema;
 
-/// 动态函数加载器
+/// 函数清单文件名（位于函数目录内）
+pub const FUNCTION_MANIFEST_FILE: &str = "flux.toml";
+
+/// 目录加载时单个源文件的默认大小上限（1 MiB）
+pub const DEFAULT_MAX_SOURCE_FILE_BYTES: u64 = 1024 * 1024;
+
+/// 支持的源文件扩展名及对应的脚本类型
+const SOURCE_EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("js", "javascript"),
+    ("ts", "typescript"),
+    ("py", "python"),
+];
+
+/// 源文件扩展名对应的脚本类型
+fn source_script_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?;
+    SOURCE_EXTENSIONS
+        .iter()
+        .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+        .map(|(_, script_type)| *script_type)
+}
+
+/// 函数清单 - 来自 `flux.toml` 的可选元数据
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FunctionManifest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub timeout_ms: Option<u64>,
+    /// 脚本类型，目前仅支持 `rust`
+    pub script_type: Option<String>,
+    /// 入口文件（相对于函数目录），默认依次尝试 `main.rs`、`lib.rs`
+    pub entry: Option<String>,
+    pub version: Option<String>,
+    pub dependencies: Option<Vec<String>>,
+    pub parameters: Option<Vec<FunctionParameter>>,
+    pub return_type: Option<String>,
+    pub retry_policy: Option<RetryPolicy>,
+    pub idempotent: Option<bool>,
+    pub labels: Option<HashMap<String, String>>,
+    /// Markdown 文档文件（相对于函数目录），默认使用与目录同名的相邻 `.md` 文件
+    pub docs: Option<String>,
+}
+
+/// 目录加载时被跳过的文件
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+/// 目录加载结果
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryLoadReport {
+    pub functions: Vec<FunctionMetadata>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+impl DirectoryLoadReport {
+    fn skip(&mut self, path: &Path, error: FluxError) {
+        tracing::warn!("Failed to load function from {}: {}", path.display(), error);
+        self.skipped.push(SkippedFile {
+            path: path.display().to_string(),
+            reason: error.to_string(),
+        });
+    }
+}
+
+/// 目录扫描中单个条目的结果
 #[derive(Debug, Clone)]
+pub enum ScannedEntry {
+    /// 解析并验证通过的函数
+    Function {
+        path: PathBuf,
+        script_type: String,
+        function: Box<FunctionMetadata>,
+    },
+    /// 未尝试加载的条目（隐藏文件、非源文件、超出大小限制等）
+    Skipped { path: PathBuf, reason: String },
+    /// 尝试加载但失败的条目
+    Failed {
+        path: PathBuf,
+        name: Option<String>,
+        script_type: Option<String>,
+        error: String,
+    },
+}
 
+/// 目录加载中对单个文件采取的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadAction {
+    /// 注册为新函数
+    Registered,
+    /// 覆盖已存在的同名函数
+    Updated,
+    /// 已存在同名函数，按策略跳过
+    SkippedDuplicate,
+    /// 未尝试加载（隐藏文件、非源文件、超出大小限制等）
+    Skipped,
+    /// 解析、验证或注册失败
+    Failed,
+    /// 函数数达到注册表上限或变更速率超限，未注册
+    Rejected,
+}
+
+/// 目录加载中单个文件的结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FileLoadResult {
+    pub path: String,
+    /// 推断出的函数名
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script_type: Option<String>,
+    pub action: LoadAction,
+    /// 跳过原因或失败时的错误信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// 目录加载的汇总计数
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DirectoryLoadSummary {
+    pub discovered: usize,
+    pub registered: usize,
+    pub updated: usize,
+    pub skipped_duplicate: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub rejected: usize,
+}
+
+impl DirectoryLoadSummary {
+    /// 根据各文件结果统计
+    pub fn from_files(files: &[FileLoadResult]) -> Self {
+        let mut summary = Self {
+            discovered: files.len(),
+            ..Default::default()
+        };
+        for file in files {
+            match file.action {
+                LoadAction::Registered => summary.registered += 1,
+                LoadAction::Updated => summary.updated += 1,
+                LoadAction::SkippedDuplicate => summary.skipped_duplicate += 1,
+                LoadAction::Skipped => summary.skipped += 1,
+                LoadAction::Failed => summary.failed += 1,
+                LoadAction::Rejected => summary.rejected += 1,
+            }
+        }
+        summary
+    }
+}
+
+/// 目录加载的详细结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DirectoryLoadResult {
+    pub directory: String,
+    /// 为 true 时只做预览，没有注册任何函数
+    pub dry_run: bool,
+    /// 为 true 时因 `on_conflict=fail` 下存在名称冲突而未注册任何函数
+    pub aborted: bool,
+    pub files: Vec<FileLoadResult>,
+    pub summary: DirectoryLoadSummary,
+}
+
+/// 动态函数加载器
+#[derive(Debug, Clone)]
 pub struct FunctionLoader {
     validator: FunctionValidator,
+    max_file_size: u64,
 }
 
 impl FunctionLoader {
     /// 创建新的函数加载器
     pub fn new() -> Self {
-        Self {
-            validator: FunctionValidator::new(),
-        }
+        Self::with_validator(FunctionValidator::new())
     }
 
     /// 创建带自定义验证器的函数加载器
     pub fn with_validator(validator: FunctionValidator) -> Self {
-        Self { validator }
+        Self {
+            validator,
+            max_file_size: DEFAULT_MAX_SOURCE_FILE_BYTES,
+        }
+    }
+
+    /// 设置目录扫描时单个源文件的大小上限
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// 目录扫描时单个源文件的大小上限
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size
     }
 
     /// 从文件路径加载函数代码
@@ -36,15 +225,12 @@ impl FunctionLoader {
         }
 
         // 检查文件扩展名
-        if let Some(ext) = path.extension() {
-            if ext != "rs" {
-                return Err(FluxError::ValidationError {
-                    reason: format!("Only .rs files are supported, got: {}", path.display()),
-                });
-            }
-        } else {
+        if source_script_type(path).is_none() {
             return Err(FluxError::ValidationError {
-                reason: format!("File must have .rs extension: {}", path.display()),
+                reason: format!(
+                    "Unsupported source file {}: expected a .rs, .js, .ts or .py file",
+                    path.display()
+                ),
             });
         }
 
@@ -56,6 +242,10 @@ impl FunctionLoader {
     }
 
     /// 从文件路径创建函数元数据
+    ///
+    /// 源文件开头的 `flux:` 头和 `<name>.flux.json` 附属文件中的元数据（附属文件优先）补充
+    /// 推断的值，显式给出的名称、描述和超时优先于两者。JavaScript、TypeScript 和 Python
+    /// 文件以单文件函数包注册，脚本类型由扩展名决定。
     pub async fn load_function_from_file<P: AsRef<Path>>(
         &self,
         path: P,
@@ -64,20 +254,60 @@ impl FunctionLoader {
         timeout_ms: Option<u64>,
     ) -> Result<FunctionMetadata> {
         let path = path.as_ref();
-        let code = self.load_from_file(path).await?;
+        let content = self.load_from_file(path).await?;
+        let script_type = source_script_type(path).unwrap_or("rust");
+        let (header, code) =
+            header::parse_source(&content, header::comment_prefix(script_type), path)?;
+        let file_stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("unknown");
+        let sidecar_path = path.with_file_name(header::sidecar_name(file_stem));
+        let sidecar = match sidecar_path.is_file() {
+            true => {
+                let content = fs::read_to_string(&sidecar_path).await?;
+                Some(header::parse_sidecar(&content, &sidecar_path)?)
+            }
+            false => None,
+        };
+        let (mut header, mut header_fields) = FileHeader::merge(header, sidecar);
 
-        // 验证函数代码
-        self.validate_function_code(&code).await?;
+        // 验证函数代码（校验器只理解 Rust，脚本由各自的运行时检查）
+        if script_type == "rust" {
+            self.validate_function_code(&code).await?;
+        }
 
-        // 如果没有提供名称，从文件名推断
-        let function_name = name.unwrap_or_else(|| {
-            path.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("unknown")
-                .to_string()
-        });
+        for (field, given) in [
+            ("name", name.is_some()),
+            ("description", description.is_some()),
+            ("timeout_ms", timeout_ms.is_some()),
+        ] {
+            if given {
+                header_fields.remove(field);
+            }
+        }
+        // 没有提供名称时依次使用文件头中的名称和文件名
+        let function_name = name
+            .or(header.name.take())
+            .unwrap_or_else(|| file_stem.to_string());
+        FunctionName::parse(&function_name)?;
+        let documentation = load_documentation(&path.with_extension("md")).await?;
+        let (files, entrypoint) = match script_type {
+            "rust" => (None, None),
+            _ => {
+                let file_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let file = PackageFile {
+                    path: file_name.clone(),
+                    content: code.clone(),
+                };
+                (Some(vec![file]), Some(file_name))
+            }
+        };
 
-        let req = RegisterFunctionRequest {
+        let mut req = RegisterFunctionRequest {
             name: function_name,
             description,
             code,
@@ -86,9 +316,44 @@ impl FunctionLoader {
             dependencies: None,
             parameters: None,
             return_type: None,
+            retry_policy: None,
+            idempotent: None,
+            labels: None,
+            profile: None,
+            webhooks: None,
+            priority: None,
+            files,
+            entrypoint,
+            http_response: None,
+            input_template: None,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: None,
+            coerce_output: None,
+            strict_return_type: None,
+            log_redaction: None,
+            circuit_breaker: None,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: None,
+            scratch_limit_mb: None,
+            persist_scratch: None,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: None,
+            execution_hint: None,
+            namespace: None,
+            documentation,
+            egress: None,
+            debug_capture: None,
+            canary: None,
+            slo: None,
         };
+        header.apply(&mut req);
 
-        Ok(FunctionMetadata::from_request(req))
+        let mut function = FunctionMetadata::from_request(req);
+        function.header_fields = header_fields;
+        Ok(function)
     }
 
     /// 从多个文件路径批量加载函数
@@ -96,6 +361,16 @@ impl FunctionLoader {
         &self,
         dir_path: P,
     ) -> Result<Vec<FunctionMetadata>> {
+        self.load_functions_from_directory_with_report(dir_path)
+            .await
+            .map(|report| report.functions)
+    }
+
+    /// 从目录批量加载函数，并返回被跳过的文件及原因
+    pub async fn load_functions_from_directory_with_report<P: AsRef<Path>>(
+        &self,
+        dir_path: P,
+    ) -> Result<DirectoryLoadReport> {
         let dir_path = dir_path.as_ref();
 
         if !dir_path.exists() {
@@ -111,7 +386,7 @@ impl FunctionLoader {
             });
         }
 
-        let mut functions = Vec::new();
+        let mut report = DirectoryLoadReport::default();
         let mut entries = fs::read_dir(dir_path).await?;
 
         while let Some(entry) = entries.next_entry().await? {
@@ -121,23 +396,337 @@ impl FunctionLoader {
             if path.is_file() && path.extension().is_some_and(|ext| ext == "rs") {
                 match self.load_function_from_file(&path, None, None, None).await {
                     Ok(function) => {
-                        functions.push(function);
+                        report.functions.push(function);
                         tracing::info!("Loaded function from: {}", path.display());
                     }
-                    Err(e) => {
-                        tracing::warn!("Failed to load function from {}: {}", path.display(), e);
-                        // 继续处理其他文件，不中断整个过程
-                    }
+                    // 继续处理其他文件，不中断整个过程
+                    Err(e) => report.skip(&path, e),
                 }
             }
         }
 
         tracing::info!(
-            "Loaded {} functions from directory: {}",
-            functions.len(),
-            dir_path.display()
+            "Loaded {} functions from directory: {} ({} skipped)",
+            report.functions.len(),
+            dir_path.display(),
+            report.skipped.len()
         );
-        Ok(functions)
+        Ok(report)
+    }
+
+    /// 读取函数目录中的 `flux.toml` 清单
+    pub async fn load_manifest<P: AsRef<Path>>(&self, path: P) -> Result<FunctionManifest> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).await?;
+        toml::from_str(&content).map_err(|e| FluxError::ValidationError {
+            reason: format!("Invalid function manifest {}: {}", path.display(), e),
+        })
+    }
+
+    /// 从带 `flux.toml` 清单的函数目录加载函数，目录中的所有文件作为一个函数包
+    pub async fn load_function_from_manifest_dir<P: AsRef<Path>>(
+        &self,
+        dir_path: P,
+    ) -> Result<FunctionMetadata> {
+        let dir_path = dir_path.as_ref();
+        let manifest = self
+            .load_manifest(dir_path.join(FUNCTION_MANIFEST_FILE))
+            .await?;
+
+        if let Some(script_type) = &manifest.script_type
+            && !script_type.eq_ignore_ascii_case("rust")
+        {
+            return Err(FluxError::ValidationError {
+                reason: format!(
+                    "Unsupported script_type '{}' in {}",
+                    script_type,
+                    dir_path.display()
+                ),
+            });
+        }
+
+        let entrypoint = match &manifest.entry {
+            Some(entry) => {
+                validate_package_path(entry)?;
+                entry.clone()
+            }
+            None => ["main.rs", "lib.rs"]
+                .iter()
+                .find(|candidate| dir_path.join(candidate).is_file())
+                .map(|candidate| candidate.to_string())
+                .ok_or_else(|| FluxError::ValidationError {
+                    reason: format!("No entry file found in {}", dir_path.display()),
+                })?,
+        };
+
+        let code = self.load_from_file(dir_path.join(&entrypoint)).await?;
+        self.validate_function_code(&code).await?;
+        let files = collect_package_files(dir_path).await?;
+
+        // 清单未指定名称时，使用目录名
+        let function_name = manifest.name.unwrap_or_else(|| {
+            dir_path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("unknown")
+                .to_string()
+        });
+        FunctionName::parse(&function_name)?;
+
+        let documentation = match &manifest.docs {
+            Some(docs_path) => {
+                validate_package_path(docs_path)?;
+                let documentation = load_documentation(&dir_path.join(docs_path)).await?;
+                if documentation.is_none() {
+                    return Err(FluxError::ValidationError {
+                        reason: format!(
+                            "Documentation file '{}' not found in {}",
+                            docs_path,
+                            dir_path.display()
+                        ),
+                    });
+                }
+                documentation
+            }
+            None => load_documentation(&dir_path.with_extension("md")).await?,
+        };
+
+        let req = RegisterFunctionRequest {
+            name: function_name,
+            description: manifest.description,
+            code,
+            timeout_ms: manifest.timeout_ms,
+            version: manifest.version,
+            dependencies: manifest.dependencies,
+            parameters: manifest.parameters,
+            return_type: manifest.return_type,
+            retry_policy: manifest.retry_policy,
+            idempotent: manifest.idempotent,
+            labels: manifest.labels,
+            profile: None,
+            webhooks: None,
+            priority: None,
+            files: Some(files),
+            entrypoint: Some(entrypoint),
+            http_response: None,
+            input_template: None,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: None,
+            coerce_output: None,
+            strict_return_type: None,
+            log_redaction: None,
+            circuit_breaker: None,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: None,
+            scratch_limit_mb: None,
+            persist_scratch: None,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: None,
+            execution_hint: None,
+            namespace: None,
+            documentation,
+            egress: None,
+            debug_capture: None,
+            canary: None,
+            slo: None,
+        };
+
+        let function = FunctionMetadata::from_request(req);
+        if let Some(package) = &function.package {
+            package.validate()?;
+        }
+        Ok(function)
+    }
+
+    /// 加载目录中的函数，同时识别带 `flux.toml` 的函数子目录
+    ///
+    /// 顶层 `.rs` 文件按文件名推断元数据，子目录中存在 `flux.toml` 时按清单加载。
+    /// 只有加载失败的条目计入 `skipped`，隐藏文件和非源文件直接忽略。
+    pub async fn load_functions_with_manifests<P: AsRef<Path>>(
+        &self,
+        dir_path: P,
+    ) -> Result<DirectoryLoadReport> {
+        let mut report = DirectoryLoadReport::default();
+        for entry in self.scan_directory(dir_path).await? {
+            match entry {
+                ScannedEntry::Function { function, .. } => report.functions.push(*function),
+                ScannedEntry::Failed { path, error, .. } => report.skipped.push(SkippedFile {
+                    path: path.display().to_string(),
+                    reason: error,
+                }),
+                ScannedEntry::Skipped { .. } => {}
+            }
+        }
+        Ok(report)
+    }
+
+    /// 扫描目录，返回每个条目的解析结果（按路径排序）
+    ///
+    /// 顶层源文件按文件名推断元数据，带 `flux.toml` 的子目录按清单加载；
+    /// 隐藏文件、非源文件和超出大小限制的文件只记录跳过原因，不尝试加载。
+    /// 不检查与已注册函数的名称冲突，但同一目录内的重名条目会被标记为失败。
+    pub async fn scan_directory<P: AsRef<Path>>(&self, dir_path: P) -> Result<Vec<ScannedEntry>> {
+        let dir_path = dir_path.as_ref();
+        if !dir_path.is_dir() {
+            return Err(FluxError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Directory not found: {}", dir_path.display()),
+            )));
+        }
+
+        let mut paths = Vec::new();
+        let mut entries = fs::read_dir(dir_path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            paths.push(entry.path());
+        }
+        paths.sort();
+
+        let mut scanned = Vec::with_capacity(paths.len());
+        let mut seen: HashMap<String, PathBuf> = HashMap::new();
+        for path in paths {
+            let entry = self.scan_entry(&path).await;
+            let entry = match entry {
+                ScannedEntry::Function {
+                    path,
+                    script_type,
+                    function,
+                } => match seen.get(&function.name) {
+                    Some(first) => ScannedEntry::Failed {
+                        error: format!(
+                            "Duplicate function name '{}' (already defined by {})",
+                            function.name,
+                            first.display()
+                        ),
+                        name: Some(function.name.clone()),
+                        script_type: Some(script_type),
+                        path,
+                    },
+                    None => {
+                        seen.insert(function.name.clone(), path.clone());
+                        ScannedEntry::Function {
+                            path,
+                            script_type,
+                            function,
+                        }
+                    }
+                },
+                other => other,
+            };
+            if let ScannedEntry::Failed { path, error, .. } = &entry {
+                tracing::warn!("Failed to load function from {}: {}", path.display(), error);
+            }
+            scanned.push(entry);
+        }
+        Ok(scanned)
+    }
+
+    /// 扫描单个目录条目
+    async fn scan_entry(&self, path: &Path) -> ScannedEntry {
+        let skipped = |reason: String| ScannedEntry::Skipped {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if file_name.starts_with('.') {
+            return skipped("Hidden file".to_string());
+        }
+
+        if path.is_dir() {
+            if !path.join(FUNCTION_MANIFEST_FILE).is_file() {
+                return skipped(format!("Directory without {FUNCTION_MANIFEST_FILE}"));
+            }
+            return match self.load_function_from_manifest_dir(path).await {
+                Ok(function) => ScannedEntry::Function {
+                    path: path.to_path_buf(),
+                    script_type: "rust".to_string(),
+                    function: Box::new(function),
+                },
+                Err(e) => ScannedEntry::Failed {
+                    path: path.to_path_buf(),
+                    // 清单可读时按清单中的函数名
+                    name: self
+                        .load_manifest(path.join(FUNCTION_MANIFEST_FILE))
+                        .await
+                        .ok()
+                        .and_then(|manifest| manifest.name)
+                        .or(Some(file_name)),
+                    script_type: None,
+                    error: e.to_string(),
+                },
+            };
+        }
+
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        let Some(script_type) = source_script_type(path) else {
+            // 源文件的附属元数据文件随源文件一起加载
+            if let Some(stem) = file_name.strip_suffix(header::SIDECAR_SUFFIX) {
+                let source = SOURCE_EXTENSIONS
+                    .iter()
+                    .map(|(ext, _)| path.with_file_name(format!("{stem}.{ext}")))
+                    .find(|candidate| candidate.is_file());
+                if let Some(source) = source {
+                    let source = source
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    return skipped(format!("Metadata for {source}"));
+                }
+            }
+            // 与源文件或函数目录同名的 .md 文件随函数一起加载
+            if extension.eq_ignore_ascii_case("md") {
+                let documented = SOURCE_EXTENSIONS
+                    .iter()
+                    .map(|(ext, _)| path.with_extension(ext))
+                    .chain([path.with_extension("")])
+                    .find(|candidate| candidate.exists());
+                if let Some(documented) = documented {
+                    let documented = documented
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    return skipped(format!("Documentation for {documented}"));
+                }
+            }
+            return skipped("Not a supported source file".to_string());
+        };
+
+        let size = match fs::metadata(path).await {
+            Ok(metadata) => metadata.len(),
+            Err(e) => return skipped(format!("Unreadable file: {e}")),
+        };
+        if size > self.max_file_size {
+            return skipped(format!(
+                "File size {size} bytes exceeds limit of {} bytes",
+                self.max_file_size
+            ));
+        }
+
+        let inferred_name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(str::to_string);
+        match self.load_function_from_file(path, None, None, None).await {
+            Ok(function) => ScannedEntry::Function {
+                path: path.to_path_buf(),
+                script_type: script_type.to_string(),
+                function: Box::new(function),
+            },
+            Err(e) => ScannedEntry::Failed {
+                path: path.to_path_buf(),
+                name: inferred_name,
+                script_type: Some(script_type.to_string()),
+                error: e.to_string(),
+            },
+        }
     }
 
     /// 验证函数代码
@@ -146,8 +735,322 @@ impl FunctionLoader {
     }
 }
 
+/// 读取函数目录中的所有文件作为函数包（跳过清单、隐藏文件、符号链接和 `target/`）
+async fn collect_package_files(dir_path: &Path) -> Result<Vec<PackageFile>> {
+    let mut files = Vec::new();
+    let mut total = 0;
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let mut entries = fs::read_dir(dir_path.join(&relative)).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = relative.join(&name);
+            let file_type = entry.file_type().await?;
+            if name.starts_with('.') || file_type.is_symlink() {
+                continue;
+            }
+            if file_type.is_dir() {
+                if relative.as_os_str().is_empty() && name == "target" {
+                    continue;
+                }
+                pending.push(path);
+                continue;
+            }
+            if relative.as_os_str().is_empty() && name == FUNCTION_MANIFEST_FILE {
+                continue;
+            }
+
+            let content =
+                fs::read_to_string(entry.path())
+                    .await
+                    .map_err(|e| FluxError::ValidationError {
+                        reason: format!("Unreadable package file {}: {e}", path.display()),
+                    })?;
+            total += content.len();
+            if total > MAX_PACKAGE_BYTES || files.len() >= MAX_PACKAGE_FILES {
+                return Err(FluxError::ValidationError {
+                    reason: format!(
+                        "Package {} exceeds the limit of {MAX_PACKAGE_FILES} files or {MAX_PACKAGE_BYTES} bytes",
+                        dir_path.display()
+                    ),
+                });
+            }
+            files.push(PackageFile {
+                path: path.to_string_lossy().into_owned(),
+                content,
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// 读取函数的 Markdown 文档，文件不存在时返回 `None`
+async fn load_documentation(path: &Path) -> Result<Option<String>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let documentation = fs::read_to_string(path).await?;
+    if let Err(FluxError::ValidationError { reason }) = docs::validate(&documentation) {
+        return Err(FluxError::ValidationError {
+            reason: format!("{}: {}", path.display(), reason),
+        });
+    }
+    tracing::debug!("Loaded function documentation from: {}", path.display());
+    Ok(Some(documentation))
+}
+
 impl Default for FunctionLoader {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::header::HeaderSource;
+
+    const VALID_CODE: &str = "fn hello() -> String { return \"hi\".to_string(); }";
+
+    fn fixture_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("hello.rs", VALID_CODE);
+        write("bad-name!.rs", VALID_CODE);
+        write(".hidden.rs", VALID_CODE);
+        write("README.md", "# functions");
+        write("huge.rs", &format!("{VALID_CODE}\n// {}", "x".repeat(2048)));
+        write("notes/readme.txt", "no manifest here");
+        write("greet/main.rs", VALID_CODE);
+        write("greet/lib/util.rs", "pub fn util() {}");
+        write("greet/flux.toml", "name = \"hello\"\n");
+        write("greet.md", "# greet\n\nSays hello.\n");
+        dir
+    }
+
+    fn by_path<'a>(entries: &'a [ScannedEntry], name: &str) -> &'a ScannedEntry {
+        entries
+            .iter()
+            .find(|entry| {
+                let path = match entry {
+                    ScannedEntry::Function { path, .. }
+                    | ScannedEntry::Skipped { path, .. }
+                    | ScannedEntry::Failed { path, .. } => path,
+                };
+                path.file_name().unwrap() == name
+            })
+            .unwrap_or_else(|| panic!("{name} was not scanned"))
+    }
+
+    #[tokio::test]
+    async fn test_scan_directory_reports_every_entry() {
+        let dir = fixture_dir();
+        let loader = FunctionLoader::new().with_max_file_size(1024);
+        let entries = loader.scan_directory(dir.path()).await.unwrap();
+        assert_eq!(entries.len(), 8);
+
+        // 按路径排序：greet/ 先于 hello.rs 被加载，hello.rs 因重名失败
+        // 带清单的目录整体作为一个函数包
+        match by_path(&entries, "greet") {
+            ScannedEntry::Function { function, .. } => {
+                assert_eq!(function.name, "hello");
+                let tree = function.package.as_ref().unwrap().tree();
+                assert_eq!(tree.entrypoint, "main.rs");
+                let paths: Vec<_> = tree.files.iter().map(|f| f.path.as_str()).collect();
+                assert_eq!(paths, ["lib/util.rs", "main.rs"]);
+                // 与目录同名的相邻 .md 文件作为函数文档
+                assert_eq!(
+                    function.documentation.as_deref(),
+                    Some("# greet\n\nSays hello.\n")
+                );
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        match by_path(&entries, "hello.rs") {
+            ScannedEntry::Failed { name, error, .. } => {
+                assert_eq!(name.as_deref(), Some("hello"));
+                assert!(error.contains("Duplicate function name"), "{error}");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(
+            by_path(&entries, "bad-name!.rs"),
+            ScannedEntry::Failed { script_type: Some(t), .. } if t == "rust"
+        ));
+
+        for (name, reason) in [
+            (".hidden.rs", "Hidden"),
+            ("README.md", "Not a supported source file"),
+            ("greet.md", "Documentation for greet"),
+            ("huge.rs", "exceeds limit"),
+            ("notes", "without flux.toml"),
+        ] {
+            match by_path(&entries, name) {
+                ScannedEntry::Skipped { reason: actual, .. } => {
+                    assert!(actual.contains(reason), "{name}: {actual}")
+                }
+                other => panic!("{name}: unexpected {other:?}"),
+            }
+        }
+
+        // 兼容接口只报告失败条目
+        let report = loader
+            .load_functions_with_manifests(dir.path())
+            .await
+            .unwrap();
+        assert_eq!(report.functions.len(), 1);
+        assert_eq!(report.skipped.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sibling_markdown_becomes_documentation() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("hello.rs"), VALID_CODE).unwrap();
+        let loader = FunctionLoader::new();
+
+        let function = loader
+            .load_function_from_file(dir.path().join("hello.rs"), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(function.documentation, None);
+
+        std::fs::write(dir.path().join("hello.md"), "Greets the caller.").unwrap();
+        let function = loader
+            .load_function_from_file(dir.path().join("hello.rs"), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            function.documentation.as_deref(),
+            Some("Greets the caller.")
+        );
+
+        // 超过大小上限的文档导致加载失败
+        std::fs::write(
+            dir.path().join("hello.md"),
+            "x".repeat(docs::MAX_DOCUMENTATION_BYTES + 1),
+        )
+        .unwrap();
+        let error = loader
+            .load_function_from_file(dir.path().join("hello.rs"), None, None, None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("hello.md"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn test_flux_headers_in_every_language() {
+        let dir = tempfile::tempdir().unwrap();
+        let write =
+            |path: &str, content: &str| std::fs::write(dir.path().join(path), content).unwrap();
+        write(
+            "add.rs",
+            &format!("// flux: {{\"timeout_ms\": 10000, \"description\": \"adds\"}}\n{VALID_CODE}"),
+        );
+        write(
+            "greet.js",
+            "// Greets the caller\n// flux: {\"description\": \"greets\",\n//   \"parameters\": [{\"name\": \"who\", \"param_type\": \"string\", \"required\": true}]}\nreturn `hi ${input.who}`;\n",
+        );
+        write(
+            "shout.ts",
+            "// flux: {\"name\": \"shout-ts\", \"timeout_ms\": 500}\nreturn String(input).toUpperCase();\n",
+        );
+        write(
+            "shout.flux.json",
+            r#"{"timeout_ms": 750, "labels": {"team": "web"}}"#,
+        );
+        write(
+            "square.py",
+            "#!/usr/bin/env python3\n# flux: {\"idempotent\": false}\nreturn input * input\n",
+        );
+        write(
+            "broken.js",
+            "\n// flux: {\"timeout_ms\": 10,\n//   \"description\": }\nreturn 1;\n",
+        );
+        write("orphan.flux.json", "{}");
+
+        let loader = FunctionLoader::new();
+        let entries = loader.scan_directory(dir.path()).await.unwrap();
+        let function = |name: &str| match by_path(&entries, name) {
+            ScannedEntry::Function {
+                script_type,
+                function,
+                ..
+            } => (script_type.clone(), function.clone()),
+            other => panic!("{name}: unexpected {other:?}"),
+        };
+
+        let (script_type, add) = function("add.rs");
+        assert_eq!(script_type, "rust");
+        assert_eq!((add.timeout_ms, add.description.as_str()), (10000, "adds"));
+        assert_eq!(add.code, VALID_CODE);
+        assert!(add.package.is_none());
+        assert_eq!(
+            add.header_fields.keys().collect::<Vec<_>>(),
+            ["description", "timeout_ms"]
+        );
+
+        // 脚本以单文件函数包注册，文件头从代码中移除
+        let (script_type, greet) = function("greet.js");
+        assert_eq!(script_type, "javascript");
+        assert_eq!(greet.name, "greet");
+        assert_eq!(greet.parameters[0].name, "who");
+        let package = greet.package.as_ref().unwrap();
+        assert_eq!(package.entrypoint, "greet.js");
+        assert_eq!(
+            package.files[0].content,
+            "// Greets the caller\nreturn `hi ${input.who}`;\n"
+        );
+
+        // 附属文件优先于文件头，文件头中的名称优先于文件名
+        let (script_type, shout) = function("shout.ts");
+        assert_eq!(script_type, "typescript");
+        assert_eq!(shout.name, "shout-ts");
+        assert_eq!(shout.timeout_ms, 750);
+        assert_eq!(shout.labels["team"], "web");
+        assert_eq!(shout.header_fields["name"], HeaderSource::Header);
+        assert_eq!(shout.header_fields["timeout_ms"], HeaderSource::Sidecar);
+
+        let (script_type, square) = function("square.py");
+        assert_eq!(script_type, "python");
+        assert!(!square.idempotent);
+        assert_eq!(
+            square.package.as_ref().unwrap().files[0].content,
+            "#!/usr/bin/env python3\nreturn input * input\n"
+        );
+
+        match by_path(&entries, "broken.js") {
+            ScannedEntry::Failed { name, error, .. } => {
+                assert_eq!(name.as_deref(), Some("broken"));
+                assert!(error.contains("broken.js:3:"), "{error}");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        for (name, reason) in [
+            ("shout.flux.json", "Metadata for shout.ts"),
+            ("orphan.flux.json", "Not a supported source file"),
+        ] {
+            match by_path(&entries, name) {
+                ScannedEntry::Skipped { reason: actual, .. } => {
+                    assert!(actual.contains(reason), "{name}: {actual}")
+                }
+                other => panic!("{name}: unexpected {other:?}"),
+            }
+        }
+
+        // 显式参数优先于文件头，不再记为取自文件头
+        let add = loader
+            .load_function_from_file(dir.path().join("add.rs"), None, None, Some(200))
+            .await
+            .unwrap();
+        assert_eq!(add.timeout_ms, 200);
+        assert_eq!(
+            add.header_fields.keys().collect::<Vec<_>>(),
+            ["description"]
+        );
+    }
+}
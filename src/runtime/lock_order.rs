@@ -0,0 +1,124 @@
+//! 锁顺序约定与调试期死锁检测
+//!
+//! 调度器和运行时组件只能按 [`LOCK_ORDER`] 的顺序嵌套获取锁，并遵守：
+//!
+//! - 热路径的 `HashMap` 使用 `DashMap`，分片锁只在同步代码中短暂持有，不跨越 `.await`，
+//!   也不在持有一个分片引用时访问另一张表；
+//! - `tokio` 锁的守卫在调用其他组件（实例管理器、生命周期管理器、事件流）前释放；
+//! - 事件发布只向 mpsc 队列投递，不获取任何锁。
+//!
+//! 启用 `deadlock-detection` 特性的调试构建中，通过 [`acquire`] 和 [`get_mut`]
+//! 获取锁时等待超过 [`DEADLOCK_TIMEOUT`] 会 panic，消息中给出锁名和约定的锁顺序。
+
+use dashmap::DashMap;
+use dashmap::mapref::one::RefMut;
+use std::borrow::Borrow;
+use std::future::Future;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// 约定的锁获取顺序（从外到内）
+pub const LOCK_ORDER: &[&str] = &[
+    "pool_manager.pools",
+    "pool.scaling",
+    "pool.state",
+    "pool.instances",
+    "pool.execution_totals",
+    "pool.scaling_history",
+    "lifecycle.lifecycles",
+    "lifecycle.statistics",
+    "instance_manager.active_instances",
+    "instance_manager.function_instances",
+    "instance_manager.injected_latency",
+    "events.history",
+];
+
+/// 死锁检测的等待上限
+pub const DEADLOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 获取异步锁；死锁检测开启时等待超时会 panic
+pub async fn acquire<F: Future>(name: &'static str, lock: F) -> F::Output {
+    #[cfg(all(feature = "deadlock-detection", debug_assertions))]
+    {
+        match tokio::time::timeout(DEADLOCK_TIMEOUT, lock).await {
+            Ok(guard) => guard,
+            Err(_) => deadlock(name),
+        }
+    }
+    #[cfg(not(all(feature = "deadlock-detection", debug_assertions)))]
+    {
+        let _ = name;
+        lock.await
+    }
+}
+
+/// 获取 `DashMap` 条目的可变引用；死锁检测开启时以 `try_get_mut` 重试，超时会 panic
+pub fn get_mut<'a, K, V, Q>(
+    name: &'static str,
+    map: &'a DashMap<K, V>,
+    key: &Q,
+) -> Option<RefMut<'a, K, V>>
+where
+    K: Eq + Hash + Borrow<Q>,
+    Q: Eq + Hash + ?Sized,
+{
+    #[cfg(all(feature = "deadlock-detection", debug_assertions))]
+    {
+        use dashmap::try_result::TryResult;
+
+        let deadline = std::time::Instant::now() + DEADLOCK_TIMEOUT;
+        loop {
+            match map.try_get_mut(key) {
+                TryResult::Present(entry) => return Some(entry),
+                TryResult::Absent => return None,
+                TryResult::Locked if std::time::Instant::now() >= deadline => deadlock(name),
+                TryResult::Locked => std::thread::yield_now(),
+            }
+        }
+    }
+    #[cfg(not(all(feature = "deadlock-detection", debug_assertions)))]
+    {
+        let _ = name;
+        map.get_mut(key)
+    }
+}
+
+#[cfg(all(feature = "deadlock-detection", debug_assertions))]
+fn deadlock(name: &str) -> ! {
+    panic!(
+        "possible deadlock: waited {:?} for lock `{}` (lock order: {})",
+        DEADLOCK_TIMEOUT,
+        name,
+        LOCK_ORDER.join(" -> ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::RwLock;
+
+    #[tokio::test]
+    async fn test_acquire_and_get_mut() {
+        let lock = RwLock::new(1);
+        *acquire("test.lock", lock.write()).await += 1;
+        assert_eq!(*acquire("test.lock", lock.read()).await, 2);
+
+        let map = DashMap::new();
+        map.insert("a".to_string(), 1);
+        if let Some(mut entry) = get_mut("test.map", &map, "a") {
+            *entry += 1;
+        }
+        assert_eq!(*map.get("a").unwrap(), 2);
+        assert!(get_mut("test.map", &map, "b").is_none());
+    }
+
+    #[cfg(all(feature = "deadlock-detection", debug_assertions))]
+    #[tokio::test]
+    #[should_panic(expected = "possible deadlock: waited 5s for lock `test.lock`")]
+    async fn test_detects_lock_held_too_long() {
+        let lock = RwLock::new(());
+        let _held = lock.write().await;
+        let _guard = acquire("test.lock", lock.read()).await;
+    }
+}
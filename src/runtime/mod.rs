@@ -1,23 +1,114 @@
 #![allow(dead_code)]
+use crate::functions::context::InvocationContext;
+use crate::functions::redaction::{Redactor, code_for_log, truncate_for_log};
+use crate::functions::return_type::ReturnType;
 use crate::functions::{
-    ExecutionStatus, FluxError, FunctionMetadata, InvokeRequest, InvokeResponse, Result,
+    ErrorOrigin, ExecutionStatus, FluxError, FunctionMetadata, InvocationTiming, InvokeRequest,
+    InvokeResponse, Result,
 };
 use crate::runtime::cache::FunctionCache;
+use crate::runtime::capabilities::{CapabilityMatrix, ExecutionCapability, SCRIPT_TYPES};
 use crate::runtime::compiler::{CompilerConfig, RustCompiler};
+use crate::runtime::debug_capture::DebugTrace;
+use crate::runtime::expression::CodeType;
+use crate::runtime::inline::ExecutionPath;
 use crate::runtime::monitor::{ExecutionResult, PerformanceMonitor};
-use std::sync::Arc;
+use crate::runtime::sandbox::{DEFAULT_MAX_CODE_BYTES, ResourceLimit, SandboxExecutor};
+use crate::runtime::script_cache::ScriptLanguage;
+use crate::scheduler::namespaces::script_type;
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
+use tracing::Instrument;
 
+pub mod affinity;
+pub mod artifacts;
+pub mod binding;
 pub mod cache;
+pub mod capabilities;
 pub mod compiler;
+pub mod cpu;
+pub mod debug_capture;
+pub mod disk;
+pub mod egress;
+#[cfg(feature = "pure")]
+pub mod embedded_js;
+pub mod environment;
+pub mod events;
+pub mod execution_gate;
 pub mod executor;
+pub mod expression;
+pub mod git;
+pub mod inline;
 pub mod instance;
+pub mod invocation_log;
+pub mod isolation;
+pub mod janitor;
 pub mod loader;
+pub mod lock_order;
 pub mod monitor;
+pub mod oci;
+pub mod platform;
 pub mod resource;
+pub mod rolling;
 pub mod sandbox;
+pub mod scratch;
+pub mod script_cache;
+pub mod series;
+pub mod slo;
+pub mod state;
 pub mod validator;
+pub mod windows;
+pub mod workers;
+
+/// 运行时后端：执行单次调用尝试
+///
+/// 调度器通过该特征执行函数，多运行时路由（见 `scheduler::routing`）按策略在多个后端间选择。
+#[async_trait::async_trait]
+pub trait RuntimeBackend: Send + Sync + std::fmt::Debug {
+    /// 执行函数的一次尝试，尝试次数（从 1 开始）和截止时间见 `context`
+    async fn execute_attempt(
+        &self,
+        function: &FunctionMetadata,
+        request: &InvokeRequest,
+        context: &InvocationContext,
+    ) -> Result<InvokeResponse>;
+
+    /// 是否编译执行 Rust 函数
+    fn supports_compilation(&self) -> bool;
+
+    /// 后端的编译器（编译产物随注册表变更失效），没有时为 `None`
+    fn compiler(&self) -> Option<&Arc<RustCompiler>> {
+        None
+    }
+}
+
+#[async_trait::async_trait]
+impl RuntimeBackend for SimpleRuntime {
+    async fn execute_attempt(
+        &self,
+        function: &FunctionMetadata,
+        request: &InvokeRequest,
+        context: &InvocationContext,
+    ) -> Result<InvokeResponse> {
+        SimpleRuntime::execute_attempt(self, function, request, context).await
+    }
+
+    fn supports_compilation(&self) -> bool {
+        SimpleRuntime::supports_compilation(self)
+    }
+
+    fn compiler(&self) -> Option<&Arc<RustCompiler>> {
+        SimpleRuntime::compiler(self)
+    }
+}
+
+/// 一次执行的输出，以及本次调用是否触发了编译、在编译上花费的时间
+struct Executed {
+    output: serde_json::Value,
+    compiled: bool,
+    compile_time: Option<Duration>,
+}
 
 /// 简单的函数执行器
 #[derive(Debug)]
@@ -30,6 +121,8 @@ pub struct SimpleRuntime {
     compiler: Option<Arc<RustCompiler>>,
     /// 是否启用真实编译
     enable_compilation: bool,
+    /// 执行 JavaScript/Python 函数的沙箱（与 `sharing` 创建的运行时共享），未设置时只能使用内嵌引擎
+    sandbox: Arc<OnceLock<Arc<SandboxExecutor>>>,
 }
 
 impl SimpleRuntime {
@@ -39,6 +132,7 @@ impl SimpleRuntime {
             monitor: Arc::new(PerformanceMonitor::new()),
             compiler: None,
             enable_compilation: false,
+            sandbox: Arc::default(),
         }
     }
 
@@ -48,6 +142,7 @@ impl SimpleRuntime {
             monitor: Arc::new(PerformanceMonitor::new()),
             compiler: None,
             enable_compilation: false,
+            sandbox: Arc::default(),
         }
     }
 
@@ -57,6 +152,7 @@ impl SimpleRuntime {
             monitor,
             compiler: None,
             enable_compilation: false,
+            sandbox: Arc::default(),
         }
     }
 
@@ -70,6 +166,7 @@ impl SimpleRuntime {
             monitor: Arc::new(PerformanceMonitor::new()),
             compiler: Some(Arc::new(compiler)),
             enable_compilation: true,
+            sandbox: Arc::default(),
         })
     }
 
@@ -82,6 +179,29 @@ impl SimpleRuntime {
             monitor: Arc::new(PerformanceMonitor::new()),
             compiler: Some(Arc::new(compiler)),
             enable_compilation: true,
+            sandbox: Arc::default(),
+        })
+    }
+
+    /// 替换函数缓存
+    pub fn with_function_cache(mut self, cache: Arc<FunctionCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// 与本运行时共享缓存和性能监控的新运行时，`compilation` 时带有独立的编译器
+    pub fn sharing(&self, compilation: bool) -> anyhow::Result<Self> {
+        let compiler = if compilation {
+            Some(Arc::new(RustCompiler::new(CompilerConfig::default())?))
+        } else {
+            None
+        };
+        Ok(Self {
+            cache: self.cache.clone(),
+            monitor: self.monitor.clone(),
+            compiler,
+            enable_compilation: compilation,
+            sandbox: self.sandbox.clone(),
         })
     }
 
@@ -95,30 +215,145 @@ impl SimpleRuntime {
         self.compiler.is_some() && self.enable_compilation
     }
 
+    /// 设置执行 JavaScript/Python 函数的沙箱（只生效一次，与共享的运行时一起生效）
+    pub fn attach_sandbox(&self, sandbox: Arc<SandboxExecutor>) {
+        let _ = self.sandbox.set(sandbox);
+    }
+
+    /// 执行 JavaScript/Python 函数的沙箱（未设置时为 `None`）
+    pub fn sandbox(&self) -> Option<&Arc<SandboxExecutor>> {
+        self.sandbox.get()
+    }
+
+    /// 本运行时执行该脚本类型的方式，无法执行时返回原因；`runtime` 为函数选择的执行环境
+    pub fn capability(
+        &self,
+        script_type: &str,
+        runtime: Option<&str>,
+    ) -> std::result::Result<ExecutionCapability, String> {
+        capabilities::resolve(script_type, self.supports_compilation(), |kind| {
+            let sandbox = self
+                .sandbox
+                .get()
+                .ok_or_else(|| "No script sandbox is attached to this runtime".to_string())?;
+            sandbox
+                .environment()
+                .resolve_script(kind, runtime)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    /// 各脚本类型的执行方式（使用默认解释器）
+    pub fn capabilities(&self) -> CapabilityMatrix {
+        SCRIPT_TYPES
+            .iter()
+            .map(|&script_type| {
+                let capability = self
+                    .capability(script_type, None)
+                    .unwrap_or(ExecutionCapability::Unsupported);
+                (script_type.to_string(), capability)
+            })
+            .collect()
+    }
+
+    /// 校验函数可以在本运行时执行，不能时返回 `UnsupportedScriptType`；
+    /// 代码超过沙箱的字节数上限时返回 `CodeTooLarge`
+    pub fn check_capability(&self, function: &FunctionMetadata) -> Result<ExecutionCapability> {
+        let size = function
+            .package
+            .as_ref()
+            .and_then(|package| package.entry_source())
+            .map_or(function.code.len(), str::len);
+        let limit = self
+            .sandbox
+            .get()
+            .map_or(DEFAULT_MAX_CODE_BYTES, |sandbox| sandbox.max_code_bytes());
+        if size > limit {
+            return Err(FluxError::CodeTooLarge { size, limit });
+        }
+        let script_type = script_type(function);
+        self.capability(script_type, function.runtime.as_deref())
+            .map_err(|reason| FluxError::UnsupportedScriptType {
+                name: function.name.clone(),
+                script_type: script_type.to_string(),
+                reason,
+            })
+    }
+
     /// 使用真实编译执行函数
     async fn execute_with_compilation(
         &self,
         function: &FunctionMetadata,
         request: &InvokeRequest,
-    ) -> Result<serde_json::Value> {
+        context: &InvocationContext,
+    ) -> Result<Executed> {
         let compiler = self
             .compiler
             .as_ref()
-            .ok_or_else(|| FluxError::Runtime("Compiler not available".to_string()))?;
+            .ok_or_else(|| FluxError::Platform("Compiler not available".to_string()))?;
+
+        if let Some(trace) = context.debug_trace() {
+            self.trace_compilation(function, trace);
+        }
 
         // 编译函数
-        let compiled = compiler
-            .compile_function(function)
+        let outcome = compiler
+            .compile_function_timed(function)
             .await
-            .map_err(|e| FluxError::Runtime(format!("Compilation failed: {e}")))?;
+            .map_err(|e| {
+                // 进程内执行没有单独的输出，编译失败时把编译器输出记为标准错误
+                if let Some(trace) = context.debug_trace() {
+                    trace.set_output("", &e.to_string());
+                }
+                FluxError::CompilationError(e.to_string())
+            })?;
+        if !outcome.cache_hit || !outcome.wait_time.is_zero() {
+            self.monitor
+                .record_compilation(
+                    &function.name,
+                    outcome.wait_time,
+                    outcome.compile_time,
+                    !outcome.cache_hit,
+                )
+                .await;
+        }
 
-        // 执行编译后的函数
+        // 执行编译后的函数，剩余时间已扣除编译耗时
         let response = compiler
-            .execute_compiled_function(&compiled, request)
+            .execute_compiled_function(&outcome.compiled, request, &context.snapshot())
             .await
-            .map_err(|e| FluxError::Runtime(format!("Execution failed: {e}")))?;
+            .map_err(|e| FluxError::Platform(format!("Execution failed: {e}")))?;
+
+        Ok(Executed {
+            output: response.output,
+            compiled: !outcome.cache_hit,
+            compile_time: outcome.compile_duration(),
+        })
+    }
+
+    /// 记录 Rust 函数的调试信息：生成的项目文件、工具链版本和（函数在进程内执行时可见的）环境变量名
+    pub fn trace_compilation(&self, function: &FunctionMetadata, trace: &DebugTrace) {
+        let Some(compiler) = self.compiler() else {
+            return;
+        };
+        match compiler.project_files(function) {
+            Ok(files) => trace.set_sources(files),
+            Err(e) => tracing::warn!("Failed to generate sources of {}: {}", function.name, e),
+        }
+        for (name, version) in compiler.toolchain() {
+            trace.set_toolchain(name, version);
+        }
+        let mut env: Vec<String> = std::env::vars_os()
+            .map(|(name, _)| name.to_string_lossy().to_string())
+            .collect();
+        env.sort();
+        trace.set_env(env);
+    }
 
-        Ok(response.output)
+    /// 获取编译器（未启用编译时为 `None`）
+    pub fn compiler(&self) -> Option<&Arc<RustCompiler>> {
+        self.compiler.as_ref().filter(|_| self.enable_compilation)
     }
 
     /// 获取性能监控器引用
@@ -136,32 +371,111 @@ impl SimpleRuntime {
         &self,
         function: &FunctionMetadata,
         request: &InvokeRequest,
+    ) -> Result<InvokeResponse> {
+        let context = InvocationContext::new(function, scru128::new_string());
+        self.execute_attempt(function, request, &context).await
+    }
+
+    /// 执行函数的一次尝试，尝试次数（从 1 开始）会记录到性能监控
+    #[tracing::instrument(name = "execute", skip_all, fields(function = %function.name, attempt = context.attempt))]
+    pub async fn execute_attempt(
+        &self,
+        function: &FunctionMetadata,
+        request: &InvokeRequest,
+        context: &InvocationContext,
     ) -> Result<InvokeResponse> {
         let start_time = Instant::now();
+        let attempt = context.attempt;
 
         tracing::info!("Executing function: {}", function.name);
-
-        // 尝试从缓存获取编译后的函数
-        if let Some(_cached_function) = self.cache.get(&function.name).await {
-            tracing::debug!("Using cached version of function: {}", function.name);
-            // 可以在这里使用预编译的结果来优化执行
-        } else {
-            // 缓存函数以备下次使用
-            if let Err(e) = self
-                .cache
-                .put(function.name.clone(), function.clone())
-                .await
+        // 代码只记录长度和哈希（完整代码仅在 TRACE 级别），输入按函数的脱敏规则处理
+        let redactor = Redactor::for_function(function).with_secrets_from(&request.input);
+        tracing::debug!("Function code: {}", code_for_log(&function.code));
+        tracing::trace!("Function code:\n{}", function.code);
+        tracing::debug!("Function input: {}", redactor.log_value(&request.input));
+
+        // 尝试从缓存获取编译后的函数，未命中视为冷启动；
+        // 修订号不同的条目来自函数的旧定义（例如与删除并发写入的条目），直接替换。
+        // 测试用例的调用不写入缓存（部署和导入时测试的是尚未激活的定义）
+        let cache_miss = async {
+            let cache_key = FunctionCache::key(function);
+            if let Some(cached) = self.cache.get(&cache_key).await
+                && cached.metadata.revision == function.revision
             {
-                tracing::warn!("Failed to cache function {}: {}", function.name, e);
+                tracing::debug!("Using cached version of function: {}", function.name);
+                // 可以在这里使用预编译的结果来优化执行
+                false
+            } else if context.test() {
+                true
+            } else {
+                // 缓存函数以备下次使用
+                if let Err(e) = self.cache.put(cache_key, function.clone()).await {
+                    tracing::warn!("Failed to cache function {}: {}", function.name, e);
+                }
+                true
             }
         }
+        .instrument(tracing::info_span!("cache_lookup"))
+        .await;
 
         // 设置执行超时
         let timeout_duration = Duration::from_millis(function.timeout_ms);
+        let execution_path = self.execution_path(function);
 
-        let result = timeout(timeout_duration, self.execute_function(function, request)).await;
+        let result = timeout(
+            timeout_duration,
+            self.execute_function(function, request, context, execution_path),
+        )
+        .await;
 
         let execution_time_ms = start_time.elapsed().as_millis() as u64;
+        let cold_start = cache_miss || matches!(&result, Ok(Ok(executed)) if executed.compiled);
+        let timing = InvocationTiming::in_process(
+            start_time.elapsed(),
+            match &result {
+                Ok(Ok(executed)) => executed.compile_time,
+                _ => None,
+            },
+        );
+
+        // 检查输出是否符合声明的返回类型：默认只在响应中标记，严格模式下按执行失败处理
+        let mut return_type_mismatch = false;
+        let result = match result {
+            Ok(Ok(executed)) => {
+                match ReturnType::parse(&function.return_type)
+                    .check(&executed.output, function.coerce_output)
+                {
+                    Ok(coerced) => Ok(Ok(coerced.unwrap_or(executed.output))),
+                    Err(mismatch) => {
+                        if !context.shadow()
+                            && !context.mirror()
+                            && !context.canary()
+                            && !context.test()
+                        {
+                            self.monitor
+                                .record_return_type_mismatch(&function.name)
+                                .await;
+                        }
+                        if function.strict_return_type {
+                            Ok(Err(FluxError::ReturnTypeMismatch {
+                                name: function.name.clone(),
+                                mismatch,
+                            }))
+                        } else {
+                            tracing::warn!(
+                                "Output of function {} does not match its return type: {}",
+                                function.name,
+                                mismatch
+                            );
+                            return_type_mismatch = true;
+                            Ok(Ok(executed.output))
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => Ok(Err(e)),
+            Err(elapsed) => Err(elapsed),
+        };
 
         let response = match result {
             Ok(Ok(output)) => {
@@ -170,6 +484,7 @@ impl SimpleRuntime {
                     function.name,
                     execution_time_ms
                 );
+                tracing::debug!("Function output: {}", redactor.log_value(&output));
 
                 // 记录成功执行的性能数据
                 let execution_result = ExecutionResult {
@@ -178,6 +493,16 @@ impl SimpleRuntime {
                     success: true,
                     memory_usage: 1024, // 估算值，实际项目中应该测量真实内存使用
                     error_message: None,
+                    error_origin: None,
+                    attempt,
+                    cold_start,
+                    chaos_injected: context.chaos_injected(),
+                    shadow: context.shadow(),
+                    mirror: context.mirror(),
+                    canary: context.canary(),
+                    test: context.test(),
+                    console: context.console(),
+                    execution_path: Some(execution_path),
                 };
 
                 if let Err(e) = self.monitor.record_execution(execution_result).await {
@@ -188,10 +513,30 @@ impl SimpleRuntime {
                     output,
                     execution_time_ms,
                     status: ExecutionStatus::Success,
+                    request_id: None,
+                    attempts_made: attempt,
+                    succeeded_on_retry: false,
+                    cold_start,
+                    output_schema_violations: None,
+                    backend: None,
+                    replayed: false,
+                    chaos_injected: context.chaos_injected(),
+                    return_type_mismatch,
+                    timing,
+                    termination: None,
+                    execution_path: Some(execution_path),
+                    peer: None,
+                    redirected_from: None,
                 }
             }
             Ok(Err(e)) => {
-                tracing::error!("Function {} execution failed: {}", function.name, e);
+                // 错误信息可能包含进程输出，抹去输入中的敏感值后再记录和返回
+                let message = redactor.scrub(&e.to_string());
+                tracing::error!(
+                    "Function {} execution failed: {}",
+                    function.name,
+                    truncate_for_log(&message)
+                );
 
                 // 记录失败执行的性能数据
                 let execution_result = ExecutionResult {
@@ -199,7 +544,17 @@ impl SimpleRuntime {
                     duration: start_time.elapsed(),
                     success: false,
                     memory_usage: 512, // 失败情况下的估算内存使用
-                    error_message: Some(e.to_string()),
+                    error_message: Some(message.clone()),
+                    error_origin: Some(e.origin()),
+                    attempt,
+                    cold_start,
+                    chaos_injected: context.chaos_injected(),
+                    shadow: context.shadow(),
+                    mirror: context.mirror(),
+                    canary: context.canary(),
+                    test: context.test(),
+                    console: context.console(),
+                    execution_path: Some(execution_path),
                 };
 
                 if let Err(monitor_err) = self.monitor.record_execution(execution_result).await {
@@ -207,9 +562,23 @@ impl SimpleRuntime {
                 }
 
                 InvokeResponse {
-                    output: serde_json::json!({"error": e.to_string()}),
+                    output: serde_json::json!({"error": message.clone()}),
                     execution_time_ms,
-                    status: ExecutionStatus::Error(e.to_string()),
+                    status: ExecutionStatus::from_error(&e, message),
+                    request_id: None,
+                    attempts_made: attempt,
+                    succeeded_on_retry: false,
+                    cold_start,
+                    output_schema_violations: None,
+                    backend: None,
+                    replayed: false,
+                    chaos_injected: context.chaos_injected(),
+                    return_type_mismatch: false,
+                    timing,
+                    termination: None,
+                    execution_path: Some(execution_path),
+                    peer: None,
+                    redirected_from: None,
                 }
             }
             Err(_) => {
@@ -222,6 +591,16 @@ impl SimpleRuntime {
                     success: false,
                     memory_usage: 256, // 超时情况下的估算内存使用
                     error_message: Some("Execution timeout".to_string()),
+                    error_origin: Some(ErrorOrigin::Limit),
+                    attempt,
+                    cold_start,
+                    chaos_injected: context.chaos_injected(),
+                    shadow: context.shadow(),
+                    mirror: context.mirror(),
+                    canary: context.canary(),
+                    test: context.test(),
+                    console: context.console(),
+                    execution_path: Some(execution_path),
                 };
 
                 if let Err(e) = self.monitor.record_execution(execution_result).await {
@@ -232,6 +611,20 @@ impl SimpleRuntime {
                     output: serde_json::json!({"error": "Execution timeout"}),
                     execution_time_ms,
                     status: ExecutionStatus::Timeout,
+                    request_id: None,
+                    attempts_made: attempt,
+                    succeeded_on_retry: false,
+                    cold_start,
+                    output_schema_violations: None,
+                    backend: None,
+                    replayed: false,
+                    chaos_injected: context.chaos_injected(),
+                    return_type_mismatch: false,
+                    timing,
+                    termination: None,
+                    execution_path: Some(execution_path),
+                    peer: None,
+                    redirected_from: None,
                 }
             }
         };
@@ -239,17 +632,201 @@ impl SimpleRuntime {
         Ok(response)
     }
 
+    /// 本次调用的执行路径（见 [`inline`]）：没有外部运行时可以执行的简单表达式也在进程内求值
+    pub fn execution_path(&self, function: &FunctionMetadata) -> ExecutionPath {
+        match inline::classify(function) {
+            ExecutionPath::External
+                if script_type(function) == "expression"
+                    && !self.runs_expressions_externally(function)
+                    && !self.supports_compilation() =>
+            {
+                ExecutionPath::Inline
+            }
+            path => path,
+        }
+    }
+
+    /// 不走快速路径的简单表达式有外部 JavaScript 解释器时由解释器执行
+    fn runs_expressions_externally(&self, function: &FunctionMetadata) -> bool {
+        matches!(
+            self.capability("javascript", function.runtime.as_deref()),
+            Ok(ExecutionCapability::External)
+        )
+    }
+
     /// 实际执行函数代码
     async fn execute_function(
         &self,
         function: &FunctionMetadata,
         request: &InvokeRequest,
+        context: &InvocationContext,
+        path: ExecutionPath,
+    ) -> Result<Executed> {
+        if path == ExecutionPath::Inline {
+            if let Some(trace) = context.debug_trace() {
+                trace.set_sources(vec![("expression.txt".to_string(), function.code.clone())]);
+            }
+            return Ok(Executed {
+                output: self.execute_builtin(function, request).await?,
+                compiled: false,
+                compile_time: None,
+            });
+        }
+        let output = match script_type(function) {
+            "javascript" => {
+                self.execute_script(ScriptLanguage::JavaScript, function, request, context)
+                    .await?
+            }
+            "python" => {
+                self.execute_script(ScriptLanguage::Python, function, request, context)
+                    .await?
+            }
+            "expression" if self.runs_expressions_externally(function) => {
+                let script = inline::javascript_function(function);
+                self.execute_script(ScriptLanguage::JavaScript, &script, request, context)
+                    .await?
+            }
+            // 第三阶段：支持真实Rust代码编译和执行
+            _ if self.supports_compilation() => {
+                return self
+                    .execute_with_compilation(function, request, context)
+                    .await;
+            }
+            _ => {
+                if let Some(trace) = context.debug_trace() {
+                    trace.set_sources(vec![("expression.txt".to_string(), function.code.clone())]);
+                }
+                self.execute_builtin(function, request).await?
+            }
+        };
+        Ok(Executed {
+            output,
+            compiled: false,
+            compile_time: None,
+        })
+    }
+
+    /// 执行 JavaScript/Python 函数：有外部解释器时在沙箱中执行，否则使用内嵌引擎
+    async fn execute_script(
+        &self,
+        language: ScriptLanguage,
+        function: &FunctionMetadata,
+        request: &InvokeRequest,
+        context: &InvocationContext,
     ) -> Result<serde_json::Value> {
-        // 第三阶段：支持真实Rust代码编译和执行
-        if self.supports_compilation() {
-            return self.execute_with_compilation(function, request).await;
+        match self.check_capability(function)? {
+            ExecutionCapability::External => {
+                let sandbox = self.sandbox.get().ok_or_else(|| {
+                    FluxError::Platform("No script sandbox is attached to this runtime".to_string())
+                })?;
+                let limits = sandbox
+                    .default_limits()
+                    .with_scratch(function.scratch_limit_mb, function.persist_scratch)
+                    .with_disk_write_limit(function.max_disk_write_mb)
+                    .with_cpu_time_limit(function.max_cpu_time_ms)
+                    .with_strict(context.console());
+                let runtime = function.runtime.as_deref();
+                let result = match &function.package {
+                    Some(package) => {
+                        sandbox
+                            .execute_package_script(
+                                language,
+                                runtime,
+                                package,
+                                &request.input,
+                                context,
+                                &limits,
+                            )
+                            .await
+                    }
+                    None => {
+                        sandbox
+                            .execute_function_script(
+                                language,
+                                runtime,
+                                &function.code,
+                                &request.input,
+                                context,
+                                &limits,
+                            )
+                            .await
+                    }
+                }
+                .map_err(|e| FluxError::Platform(format!("Script execution failed: {e}")))?;
+                // 超出磁盘写入或 CPU 时间上限时错误信息带上观测到的用量
+                if let Some(ResourceLimit::DiskWrite {
+                    written_bytes,
+                    limit_mb,
+                }) = result.killed_by
+                {
+                    return Err(FluxError::DiskWriteLimitExceeded {
+                        written_bytes,
+                        limit_mb,
+                    });
+                }
+                if let Some(ResourceLimit::CpuTime {
+                    cpu_time_ms,
+                    limit_ms,
+                }) = result.killed_by
+                {
+                    return Err(FluxError::CpuTimeLimitExceeded {
+                        cpu_time_ms,
+                        limit_ms,
+                    });
+                }
+                // 沙箱结果按失败归属转换：用户代码的异常和非零退出、平台侧失败、资源限制
+                match result.status {
+                    ExecutionStatus::Success => Ok(result.output),
+                    ExecutionStatus::Timeout => Err(FluxError::Timeout),
+                    ExecutionStatus::Error {
+                        origin: ErrorOrigin::UserCode,
+                        message,
+                        ..
+                    } => Err(FluxError::Runtime(message)),
+                    ExecutionStatus::Error { message, .. } => Err(FluxError::Platform(message)),
+                    ExecutionStatus::ResourceLimitExceeded { resource } => {
+                        Err(FluxError::ResourceLimitExceeded { resource })
+                    }
+                    ExecutionStatus::Cancelled => Err(FluxError::Platform(
+                        "Script execution cancelled".to_string(),
+                    )),
+                }
+            }
+            #[cfg(feature = "pure")]
+            ExecutionCapability::Embedded => {
+                let source = match &function.package {
+                    Some(package) => package.entry_source().unwrap_or(&function.code),
+                    None => &function.code,
+                }
+                .to_string();
+                if let Some(trace) = context.debug_trace() {
+                    trace.set_sources(vec![(language.script_name().to_string(), source.clone())]);
+                    trace.set_toolchain("engine", "quickjs (embedded)");
+                }
+                let input = request.input.clone();
+                let invocation = context.to_json();
+                let deadline = context.deadline();
+                tokio::task::spawn_blocking(move || {
+                    embedded_js::execute(&source, &input, &invocation, deadline)
+                })
+                .await
+                .map_err(|e| FluxError::Platform(format!("Embedded engine failed: {e}")))?
+                .map_err(|e| FluxError::Runtime(e.to_string()))
+            }
+            capability => Err(FluxError::UnsupportedScriptType {
+                name: function.name.clone(),
+                script_type: script_type(function).to_string(),
+                reason: format!("{capability:?} execution is not available for scripts"),
+            }),
         }
+    }
 
+    /// 不编译时的内置示例函数和表达式
+    async fn execute_builtin(
+        &self,
+        function: &FunctionMetadata,
+        request: &InvokeRequest,
+    ) -> Result<serde_json::Value> {
         // 保留向后兼容：简单的字符串处理示例
         match function.name.as_str() {
             "hello" => {
@@ -276,109 +853,31 @@ impl SimpleRuntime {
                     ))
                 }
             }
-            _ => {
-                // 对于其他函数，尝试简单的代码执行模拟
-                // 这是一个非常简化的实现，仅用于 MVP 演示
-                self.simulate_code_execution(&function.code, request).await
-            }
+            _ => self.evaluate_expression(function, request),
         }
     }
 
-    /// 模拟代码执行（MVP 阶段的简化实现）
-    async fn simulate_code_execution(
+    /// 简单表达式由表达式引擎求值；其他代码需要编译，不编译的运行时不执行（注册时已拒绝）
+    fn evaluate_expression(
         &self,
-        code: &str,
+        function: &FunctionMetadata,
         request: &InvokeRequest,
     ) -> Result<serde_json::Value> {
-        // 这是一个非常简化的代码执行模拟
-        // 真实的实现应该使用沙盒环境或容器
-
-        if code.contains("return") {
-            // 提取 return 语句后的内容
-            let return_part = code
-                .split("return")
-                .nth(1)
-                .unwrap_or("")
-                .trim()
-                .trim_end_matches(';')
-                .trim();
-
-            // 处理简单的字符串操作和变量替换
-            let result = self.process_expression(return_part, request)?;
-
-            Ok(serde_json::json!({
-                "result": result,
-                "input": request.input
-            }))
-        } else {
-            Ok(serde_json::json!({
-                "message": "Function executed",
-                "code": code,
-                "input": request.input
-            }))
-        }
-    }
-
-    /// 处理简单的表达式（字符串连接、变量替换等）
-    fn process_expression(&self, expr: &str, request: &InvokeRequest) -> Result<String> {
-        let mut result = expr.to_string();
-
-        // 移除多余的引号
-        if result.starts_with('"') && result.ends_with('"') {
-            result = result[1..result.len() - 1].to_string();
-        }
-
-        // 处理字符串连接（简单的 + 操作）
-        if result.contains(" + ") {
-            let parts: Vec<&str> = result.split(" + ").collect();
-            let mut concatenated = String::new();
-
-            for part in parts.iter() {
-                let processed_part = self.process_variable_or_literal(part.trim(), request)?;
-                concatenated.push_str(&processed_part);
-            }
-
-            return Ok(concatenated);
-        }
-
-        // 处理单个变量或字面量
-        self.process_variable_or_literal(&result, request)
-    }
-
-    /// 处理变量或字面量
-    fn process_variable_or_literal(&self, part: &str, request: &InvokeRequest) -> Result<String> {
-        let trimmed = part.trim();
-
-        // 如果是字符串字面量
-        if trimmed.starts_with('"') && trimmed.ends_with('"') {
-            return Ok(trimmed[1..trimmed.len() - 1].to_string());
-        }
-
-        // 如果是单引号字符串
-        if trimmed.starts_with('\'') && trimmed.ends_with('\'') {
-            return Ok(trimmed[1..trimmed.len() - 1].to_string());
-        }
-
-        // 如果是变量引用，尝试从输入中获取
-        if let Some(value) = request.input.get(trimmed) {
-            if let Some(str_val) = value.as_str() {
-                return Ok(str_val.to_string());
-            } else if let Some(num_val) = value.as_f64() {
-                return Ok(num_val.to_string());
-            } else if let Some(bool_val) = value.as_bool() {
-                return Ok(bool_val.to_string());
-            } else {
-                return Ok(value.to_string());
+        match CodeType::detect(&function.code) {
+            CodeType::SimpleExpression => {
+                let result = expression::evaluate_function(&function.code, &request.input)?;
+                Ok(serde_json::json!({
+                    "result": result,
+                    "input": request.input
+                }))
             }
+            CodeType::Program => Err(self.check_capability(function).err().unwrap_or_else(|| {
+                FluxError::Platform(format!(
+                    "Function {} can only be executed with compilation",
+                    function.name
+                ))
+            })),
         }
-
-        // 如果是数字字面量
-        if trimmed.parse::<f64>().is_ok() {
-            return Ok(trimmed.to_string());
-        }
-
-        // 默认作为字符串字面量处理
-        Ok(trimmed.to_string())
     }
 }
 
@@ -1,8 +1,26 @@
-use crate::functions::Result;
-use std::collections::HashMap;
+use crate::functions::priority::Priority;
+use crate::functions::{ErrorOrigin, InvocationTiming, Result};
+use crate::runtime::inline::ExecutionPath;
+use crate::runtime::rolling::{RollingStats, bump};
+use crate::runtime::series::{
+    FunctionReport, FunctionWindow, RankMetric, RankedFunction, SeriesConfig, SeriesStore,
+    SeriesSummary, unix_now,
+};
+use crate::runtime::windows::{LifetimeSummary, StatsWindow, WindowSummary, WindowedStats};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, broadcast};
+
+/// 每个函数保留的冷/热启动延迟样本数上限（用于计算分位数）
+pub const LATENCY_SAMPLE_LIMIT: usize = 1024;
+
+/// 每个函数保留的最近调用时间戳数上限（用于计算调用速率）
+pub const RECENT_CALL_LIMIT: usize = 4096;
+
+/// 时间桶滚动通知的缓冲区大小，订阅者落后更多时收到 `Lagged`
+const ROLLOVER_CHANNEL_CAPACITY: usize = 1024;
 
 /// 函数性能监控器
 #[derive(Debug, Clone)]
@@ -11,6 +29,25 @@ pub struct PerformanceMonitor {
     stats: Arc<RwLock<HashMap<String, FunctionStats>>>,
     /// 全局统计
     global_stats: Arc<RwLock<GlobalStats>>,
+    /// 按时间分桶的函数统计
+    series: Arc<RwLock<SeriesStore>>,
+    /// 多运行时路由时各后端的负载和调用统计
+    backends: Arc<RwLock<HashMap<String, BackendStats>>>,
+    /// 函数开启新时间桶（上一个桶的统计已定稿）时广播函数名
+    rollovers: broadcast::Sender<String>,
+}
+
+/// 运行时后端的负载和调用统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BackendStats {
+    /// 正在执行的调用数
+    pub in_flight: usize,
+    /// 已完成的调用数
+    pub invocations: u64,
+    /// 执行失败（含调度错误）的调用数
+    pub failures: u64,
+    /// 已完成调用的总执行时间（毫秒）
+    pub total_duration_ms: u64,
 }
 
 /// 单个函数的统计信息
@@ -22,6 +59,8 @@ pub struct FunctionStats {
     pub successful_calls: u64,
     /// 失败调用次数
     pub failed_calls: u64,
+    /// 重试执行次数（第 2 次及以后的尝试）
+    pub retry_attempts: u64,
     /// 总执行时间
     pub total_duration: Duration,
     /// 最快执行时间
@@ -30,12 +69,140 @@ pub struct FunctionStats {
     pub max_duration: Option<Duration>,
     /// 平均执行时间
     pub avg_duration: Duration,
+    /// 执行时间（毫秒）的累计量，最快、最慢和平均执行时间由它导出
+    pub durations: RollingStats,
     /// 最后执行时间
     pub last_execution: Option<Instant>,
     /// 内存使用峰值（字节）
     pub peak_memory: u64,
     /// 平均内存使用（字节）
     pub avg_memory: u64,
+    /// 内存使用（字节）的累计量
+    pub memory: RollingStats,
+    /// 实际触发的编译次数
+    pub compilations: u64,
+    /// 等待编译（同源编译或编译并发许可）的总时间
+    pub total_compile_wait: Duration,
+    /// 实际编译的总时间
+    pub total_compile_time: Duration,
+    /// 冷启动调用次数（首次加载或触发编译）
+    pub cold_starts: u64,
+    /// 最近冷启动调用的延迟样本
+    pub cold_latencies: VecDeque<Duration>,
+    /// 最近热调用的延迟样本
+    pub warm_latencies: VecDeque<Duration>,
+    /// 最近调用的时间戳（用于计算调用速率）
+    pub recent_calls: VecDeque<Instant>,
+    /// 各优先级调用在准入队列中的等待（调用数、总等待时间、最长等待时间）
+    pub queue_waits: HashMap<Priority, (u64, Duration, Duration)>,
+    /// 输出不符合 `output_schema` 的调用次数
+    pub output_schema_violations: u64,
+    /// 输出不符合声明的 `return_type` 的调用次数
+    pub return_type_mismatches: u64,
+    /// 受故障注入影响的执行次数
+    pub chaos_injected: u64,
+    /// 影子重放的执行次数
+    pub shadow_replays: u64,
+    /// 请求镜像的执行次数
+    pub mirror_executions: u64,
+    /// 金丝雀探测的执行次数
+    pub canary_executions: u64,
+    /// 函数测试用例的执行次数
+    pub test_executions: u64,
+    /// 由进程内表达式引擎执行的调用次数
+    pub inline_executions: u64,
+    /// 按归属统计的失败次数
+    pub failures_by_origin: HashMap<ErrorOrigin, u64>,
+    /// 函数弃用后按调用方（见 [`crate::scheduler::console::caller_key`]）统计的调用次数
+    pub deprecated_calls: HashMap<String, u64>,
+    /// 最近 1 分钟、5 分钟、1 小时的滑动窗口和衰减的生命周期汇总
+    pub windows: WindowedStats,
+}
+
+/// 单个函数的冷启动统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ColdStartStats {
+    pub cold_starts: u64,
+    pub warm_calls: u64,
+    pub cold_p50_ms: Option<f64>,
+    pub cold_p95_ms: Option<f64>,
+    pub warm_p50_ms: Option<f64>,
+    pub warm_p95_ms: Option<f64>,
+}
+
+impl FunctionStats {
+    /// 冷启动次数以及冷/热调用延迟的 p50、p95
+    pub fn cold_start_stats(&self) -> ColdStartStats {
+        let cold = sorted_millis(&self.cold_latencies);
+        let warm = sorted_millis(&self.warm_latencies);
+        ColdStartStats {
+            cold_starts: self.cold_starts,
+            warm_calls: self.total_calls - self.cold_starts,
+            cold_p50_ms: percentile(&cold, 0.50),
+            cold_p95_ms: percentile(&cold, 0.95),
+            warm_p50_ms: percentile(&warm, 0.50),
+            warm_p95_ms: percentile(&warm, 0.95),
+        }
+    }
+
+    /// 最近 `window` 时间内的调用速率（次/秒）
+    pub fn invocation_rate(&self, window: Duration) -> f64 {
+        invocation_rate(&self.recent_calls, window, Instant::now())
+    }
+}
+
+/// 截至 `now` 的最近 `window` 时间内的调用速率（次/秒），`calls` 需按时间升序
+fn invocation_rate<'a>(
+    calls: impl IntoIterator<Item = &'a Instant, IntoIter: DoubleEndedIterator>,
+    window: Duration,
+    now: Instant,
+) -> f64 {
+    if window.is_zero() {
+        return 0.0;
+    }
+    let calls = calls
+        .into_iter()
+        .rev()
+        .take_while(|at| now.saturating_duration_since(**at) <= window)
+        .count();
+    calls as f64 / window.as_secs_f64()
+}
+
+/// 从统计中复制出的函数最近活动，用于在不持有锁的情况下计算调用速率
+#[derive(Debug, Clone, Default)]
+pub struct FunctionActivity {
+    /// 复制时窗口内的调用时间戳（升序）
+    pub recent_calls: Vec<Instant>,
+    pub last_execution: Option<Instant>,
+}
+
+impl FunctionActivity {
+    /// 截至 `now` 的最近 `window` 时间内的调用速率（次/秒）
+    pub fn invocation_rate(&self, window: Duration, now: Instant) -> f64 {
+        invocation_rate(&self.recent_calls, window, now)
+    }
+}
+
+fn sorted_millis(samples: &VecDeque<Duration>) -> Vec<f64> {
+    let mut millis: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+    millis.sort_by(|a, b| a.total_cmp(b));
+    millis
+}
+
+/// 最近秩法计算分位数（`sorted` 需已升序）
+fn percentile(sorted: &[f64], quantile: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn push_bounded<T>(samples: &mut VecDeque<T>, value: T, limit: usize) {
+    if samples.len() == limit {
+        samples.pop_front();
+    }
+    samples.push_back(value);
 }
 
 /// 全局统计信息
@@ -58,6 +225,46 @@ pub struct GlobalStats {
     pub current_system_memory: u64,
     /// 最后重置时间
     pub last_reset: Option<Instant>,
+    /// 所有函数合计的滑动窗口和衰减汇总
+    pub windows: WindowedStats,
+    /// 受故障注入影响的执行次数
+    pub chaos_injected: u64,
+    /// 影子重放的执行次数
+    pub shadow_replays: u64,
+    /// 请求镜像的执行次数
+    pub mirror_executions: u64,
+    /// 金丝雀探测的执行次数
+    pub canary_executions: u64,
+    /// 函数测试用例的执行次数
+    pub test_executions: u64,
+    /// 由进程内表达式引擎执行的调用次数
+    pub inline_executions: u64,
+    /// 按归属统计的失败次数
+    pub failures_by_origin: HashMap<ErrorOrigin, u64>,
+}
+
+/// 时间窗口内的全局调用统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RecentSummary {
+    pub window_secs: u64,
+    pub invocations: u64,
+    pub failures: u64,
+    pub error_rate: f64,
+    pub avg_latency_ms: Option<f64>,
+}
+
+impl GlobalStats {
+    /// 最近 `window` 时间内的调用次数、错误率和平均延迟
+    pub fn recent_summary(&self, window: StatsWindow) -> RecentSummary {
+        let summary = self.windows.window(window, unix_now());
+        RecentSummary {
+            window_secs: summary.window_secs,
+            invocations: summary.invocations,
+            failures: summary.failures,
+            error_rate: summary.error_rate,
+            avg_latency_ms: summary.avg_latency_ms,
+        }
+    }
 }
 
 /// 执行结果统计
@@ -74,6 +281,26 @@ pub struct ExecutionResult {
     pub memory_usage: u64,
     /// 错误信息（如果有）
     pub error_message: Option<String>,
+    /// 失败的归属（成功时为 `None`）
+    pub error_origin: Option<ErrorOrigin>,
+    /// 第几次尝试（从 1 开始）
+    pub attempt: u32,
+    /// 是否冷启动
+    pub cold_start: bool,
+    /// 是否受故障注入影响
+    pub chaos_injected: bool,
+    /// 是否为影子重放（只计入重放次数，不影响其他统计）
+    pub shadow: bool,
+    /// 是否为请求镜像的执行（只计入镜像次数，不影响 SLO 统计）
+    pub mirror: bool,
+    /// 是否为金丝雀探测的执行（只计入探测次数，不影响调用统计）
+    pub canary: bool,
+    /// 是否为函数测试用例的执行（只计入测试次数，不影响调用统计）
+    pub test: bool,
+    /// 是否为控制台执行（不计入任何统计，由控制台单独计数）
+    pub console: bool,
+    /// 执行路径（故障注入直接返回的失败没有执行函数，为 `None`）
+    pub execution_path: Option<ExecutionPath>,
 }
 
 /// 性能报告
@@ -107,6 +334,11 @@ pub enum HealthStatus {
 impl PerformanceMonitor {
     /// 创建新的性能监控器
     pub fn new() -> Self {
+        Self::with_series_config(SeriesConfig::default())
+    }
+
+    /// 使用指定的时间序列配置创建性能监控器
+    pub fn with_series_config(config: SeriesConfig) -> Self {
         let global_stats = GlobalStats {
             start_time: Some(Instant::now()),
             ..Default::default()
@@ -115,11 +347,160 @@ impl PerformanceMonitor {
         Self {
             stats: Arc::new(RwLock::new(HashMap::new())),
             global_stats: Arc::new(RwLock::new(global_stats)),
+            series: Arc::new(RwLock::new(SeriesStore::new(config))),
+            backends: Arc::default(),
+            rollovers: broadcast::channel(ROLLOVER_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// 订阅时间桶滚动通知（函数名），用于推送增量更新
+    pub fn subscribe_rollovers(&self) -> broadcast::Receiver<String> {
+        self.rollovers.subscribe()
+    }
+
+    /// 复制各函数最近 `window` 内的调用时间戳和最后执行时间
+    pub async fn activity(&self, window: Duration) -> HashMap<String, FunctionActivity> {
+        let now = Instant::now();
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| stats.last_execution.is_some())
+            .map(|(name, stats)| {
+                let skip = stats
+                    .recent_calls
+                    .iter()
+                    .take_while(|at| now.saturating_duration_since(**at) > window)
+                    .count();
+                let activity = FunctionActivity {
+                    recent_calls: stats.recent_calls.iter().skip(skip).copied().collect(),
+                    last_execution: stats.last_execution,
+                };
+                (name.clone(), activity)
+            })
+            .collect()
+    }
+
+    /// 各函数最近 `window` 内的执行汇总（错误率、延迟分位数等）
+    pub async fn window_summaries(&self, window: Duration) -> HashMap<String, SeriesSummary> {
+        self.series
+            .read()
+            .await
+            .window_summaries(unix_now(), window)
+    }
+
+    /// 记录后端开始执行一次调用
+    pub async fn backend_started(&self, backend: &str) {
+        let mut backends = self.backends.write().await;
+        backends.entry(backend.to_string()).or_default().in_flight += 1;
+    }
+
+    /// 记录后端完成一次调用
+    pub async fn backend_finished(&self, backend: &str, success: bool, duration: Duration) {
+        let mut backends = self.backends.write().await;
+        let stats = backends.entry(backend.to_string()).or_default();
+        stats.in_flight = stats.in_flight.saturating_sub(1);
+        stats.invocations += 1;
+        if !success {
+            stats.failures += 1;
         }
+        stats.total_duration_ms += duration.as_millis() as u64;
+    }
+
+    /// 各后端的统计（按后端名排序）
+    pub async fn backend_stats(&self) -> std::collections::BTreeMap<String, BackendStats> {
+        let backends = self.backends.read().await;
+        backends
+            .iter()
+            .map(|(name, stats)| (name.clone(), stats.clone()))
+            .collect()
+    }
+
+    /// 替换时间序列配置（已有的分桶数据被丢弃）
+    pub async fn set_series_config(&self, config: SeriesConfig) {
+        *self.series.write().await = SeriesStore::new(config);
+    }
+
+    /// 函数在最近 `window` 内按 `resolution` 合并的执行报告
+    pub async fn function_report(
+        &self,
+        function_name: &str,
+        window: Duration,
+        resolution: Duration,
+    ) -> FunctionReport {
+        self.series
+            .read()
+            .await
+            .report(function_name, unix_now(), window, resolution)
+    }
+
+    /// 函数最近 `window` 内的汇总（窗口限制在保留时长内）
+    pub async fn function_window(&self, function_name: &str, window: Duration) -> FunctionWindow {
+        self.series
+            .read()
+            .await
+            .function_window(function_name, unix_now(), window)
+    }
+
+    /// 按指标对最近 `window` 内有调用的函数排行
+    pub async fn top_functions(
+        &self,
+        metric: RankMetric,
+        window: Duration,
+        limit: usize,
+    ) -> Vec<RankedFunction> {
+        self.series
+            .read()
+            .await
+            .top(unix_now(), window, metric, limit)
     }
 
     /// 记录函数执行结果
     pub async fn record_execution(&self, result: ExecutionResult) -> Result<()> {
+        // 影子重放只计数，不进入调用、延迟和时间序列统计
+        if result.shadow {
+            self.stats
+                .write()
+                .await
+                .entry(result.function_name)
+                .or_default()
+                .shadow_replays += 1;
+            self.global_stats.write().await.shadow_replays += 1;
+            return Ok(());
+        }
+        if result.mirror {
+            self.stats
+                .write()
+                .await
+                .entry(result.function_name)
+                .or_default()
+                .mirror_executions += 1;
+            self.global_stats.write().await.mirror_executions += 1;
+            return Ok(());
+        }
+        if result.canary {
+            self.stats
+                .write()
+                .await
+                .entry(result.function_name)
+                .or_default()
+                .canary_executions += 1;
+            self.global_stats.write().await.canary_executions += 1;
+            return Ok(());
+        }
+        if result.console {
+            return Ok(());
+        }
+        if result.test {
+            self.stats
+                .write()
+                .await
+                .entry(result.function_name)
+                .or_default()
+                .test_executions += 1;
+            self.global_stats.write().await.test_executions += 1;
+            return Ok(());
+        }
+
         // 更新函数统计
         self.update_function_stats(&result).await;
 
@@ -129,12 +510,238 @@ impl PerformanceMonitor {
         Ok(())
     }
 
+    /// 记录一次编译的排队等待和实际编译耗时（命中缓存且未等待时无需记录）
+    pub async fn record_compilation(
+        &self,
+        function_name: &str,
+        wait_time: Duration,
+        compile_time: Duration,
+        compiled: bool,
+    ) {
+        let mut stats = self.stats.write().await;
+        let function_stats = stats.entry(function_name.to_string()).or_default();
+        if compiled {
+            function_stats.compilations += 1;
+        }
+        function_stats.total_compile_wait += wait_time;
+        function_stats.total_compile_time += compile_time;
+    }
+
+    /// 记录一次调用在准入队列中的等待时间
+    pub async fn record_queue_wait(
+        &self,
+        function_name: &str,
+        priority: Priority,
+        waited: Duration,
+    ) {
+        let mut stats = self.stats.write().await;
+        let function_stats = stats.entry(function_name.to_string()).or_default();
+        let (count, total, max) = function_stats.queue_waits.entry(priority).or_default();
+        *count += 1;
+        *total += waited;
+        *max = (*max).max(waited);
+    }
+
+    /// 记录一次调用（含重试）各阶段的耗时，用于报告中各阶段的 p95
+    pub async fn record_timing(&self, function_name: &str, timing: &InvocationTiming) {
+        let rolled_over =
+            self.series
+                .write()
+                .await
+                .record_timing(function_name, unix_now(), timing);
+        if rolled_over {
+            let _ = self.rollovers.send(function_name.to_string());
+        }
+    }
+
+    /// 记录一次输出不符合 `output_schema` 的调用
+    pub async fn record_output_schema_violation(&self, function_name: &str) {
+        let mut stats = self.stats.write().await;
+        stats
+            .entry(function_name.to_string())
+            .or_default()
+            .output_schema_violations += 1;
+    }
+
+    /// 记录一次输出不符合 `return_type` 的调用
+    pub async fn record_return_type_mismatch(&self, function_name: &str) {
+        let mut stats = self.stats.write().await;
+        stats
+            .entry(function_name.to_string())
+            .or_default()
+            .return_type_mismatches += 1;
+    }
+
+    /// 记录一次对弃用函数的调用，`caller` 为调用方标识
+    pub async fn record_deprecated_call(&self, function_name: &str, caller: &str) {
+        let mut stats = self.stats.write().await;
+        *stats
+            .entry(function_name.to_string())
+            .or_default()
+            .deprecated_calls
+            .entry(caller.to_string())
+            .or_default() += 1;
+    }
+
+    /// 函数弃用后各调用方的调用次数
+    pub async fn deprecated_calls(&self, function_name: &str) -> BTreeMap<String, u64> {
+        let stats = self.stats.read().await;
+        stats
+            .get(function_name)
+            .map(|stats| stats.deprecated_calls.clone().into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// 各函数弃用后按调用方的调用次数，不包含没有弃用调用的函数
+    pub async fn all_deprecated_calls(&self) -> HashMap<String, BTreeMap<String, u64>> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| !stats.deprecated_calls.is_empty())
+            .map(|(name, stats)| {
+                let callers = stats.deprecated_calls.clone().into_iter().collect();
+                (name.clone(), callers)
+            })
+            .collect()
+    }
+
+    /// 各函数受故障注入影响的执行次数，不包含没有注入过故障的函数
+    pub async fn chaos_injections(&self) -> HashMap<String, u64> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| stats.chaos_injected > 0)
+            .map(|(name, stats)| (name.clone(), stats.chaos_injected))
+            .collect()
+    }
+
+    /// 各函数按归属统计的失败次数，不包含没有失败的函数
+    pub async fn failures_by_origin(&self) -> HashMap<String, HashMap<ErrorOrigin, u64>> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| !stats.failures_by_origin.is_empty())
+            .map(|(name, stats)| (name.clone(), stats.failures_by_origin.clone()))
+            .collect()
+    }
+
+    /// 各函数影子重放的执行次数，不包含没有重放过的函数
+    pub async fn shadow_replays(&self) -> HashMap<String, u64> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| stats.shadow_replays > 0)
+            .map(|(name, stats)| (name.clone(), stats.shadow_replays))
+            .collect()
+    }
+
+    /// 各函数作为镜像目标的执行次数，不包含没有镜像执行的函数
+    pub async fn mirror_executions(&self) -> HashMap<String, u64> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| stats.mirror_executions > 0)
+            .map(|(name, stats)| (name.clone(), stats.mirror_executions))
+            .collect()
+    }
+
+    /// 各函数金丝雀探测的执行次数，不包含没有探测的函数
+    pub async fn canary_executions(&self) -> HashMap<String, u64> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| stats.canary_executions > 0)
+            .map(|(name, stats)| (name.clone(), stats.canary_executions))
+            .collect()
+    }
+
+    /// 各函数测试用例的执行次数，不包含没有执行过测试的函数
+    pub async fn test_executions(&self) -> HashMap<String, u64> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| stats.test_executions > 0)
+            .map(|(name, stats)| (name.clone(), stats.test_executions))
+            .collect()
+    }
+
+    /// 各函数由进程内表达式引擎执行的调用次数，不包含没有这类调用的函数
+    pub async fn inline_executions(&self) -> HashMap<String, u64> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| stats.inline_executions > 0)
+            .map(|(name, stats)| (name.clone(), stats.inline_executions))
+            .collect()
+    }
+
+    /// 各函数输出不符合 `output_schema` 的调用次数，不包含没有违规的函数
+    pub async fn output_schema_violations(&self) -> HashMap<String, u64> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| stats.output_schema_violations > 0)
+            .map(|(name, stats)| (name.clone(), stats.output_schema_violations))
+            .collect()
+    }
+
+    /// 各函数输出不符合 `return_type` 的调用次数，不包含没有不符的函数
+    pub async fn return_type_mismatches(&self) -> HashMap<String, u64> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| stats.return_type_mismatches > 0)
+            .map(|(name, stats)| (name.clone(), stats.return_type_mismatches))
+            .collect()
+    }
+
+    /// 各函数在滑动窗口内的执行汇总，不包含窗口内没有调用的函数
+    pub async fn window_stats(&self, window: StatsWindow) -> HashMap<String, WindowSummary> {
+        let now = unix_now();
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .map(|(name, stats)| (name.clone(), stats.windows.window(window, now)))
+            .filter(|(_, summary)| summary.invocations > 0)
+            .collect()
+    }
+
+    /// 各函数衰减后的生命周期汇总，不包含没有执行记录的函数
+    pub async fn lifetime_stats(&self) -> HashMap<String, LifetimeSummary> {
+        let now = unix_now();
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .filter(|(_, stats)| stats.total_calls > 0)
+            .map(|(name, stats)| (name.clone(), stats.windows.lifetime(now)))
+            .collect()
+    }
+
     /// 获取函数统计信息
     pub async fn get_function_stats(&self, function_name: &str) -> Option<FunctionStats> {
         let stats = self.stats.read().await;
         stats.get(function_name).cloned()
     }
 
+    /// 各函数最近 `window` 时间内的调用速率（次/秒），不包含没有调用的函数
+    pub async fn recent_invocation_rates(&self, window: Duration) -> HashMap<String, f64> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .map(|(name, stats)| (name.clone(), stats.invocation_rate(window)))
+            .filter(|(_, rate)| *rate > 0.0)
+            .collect()
+    }
+
+    /// 各函数的冷启动统计
+    pub async fn cold_start_stats(&self) -> HashMap<String, ColdStartStats> {
+        let stats = self.stats.read().await;
+        stats
+            .iter()
+            .map(|(name, stats)| (name.clone(), stats.cold_start_stats()))
+            .collect()
+    }
+
     /// 获取全局统计信息
     pub async fn get_global_stats(&self) -> GlobalStats {
         self.global_stats.read().await.clone()
@@ -166,6 +773,7 @@ impl PerformanceMonitor {
             let mut stats = self.stats.write().await;
             stats.clear();
         }
+        self.series.write().await.clear();
 
         {
             let mut global_stats = self.global_stats.write().await;
@@ -188,7 +796,7 @@ impl PerformanceMonitor {
             .map(|(name, stats)| (name.clone(), stats.total_calls))
             .collect();
 
-        functions.sort_by(|a, b| b.1.cmp(&a.1));
+        functions.sort_by_key(|f| std::cmp::Reverse(f.1));
         functions.truncate(limit);
         functions
     }
@@ -201,7 +809,7 @@ impl PerformanceMonitor {
             .map(|(name, stats)| (name.clone(), stats.avg_duration))
             .collect();
 
-        functions.sort_by(|a, b| b.1.cmp(&a.1));
+        functions.sort_by_key(|f| std::cmp::Reverse(f.1));
         functions.truncate(limit);
         functions
     }
@@ -228,65 +836,123 @@ impl PerformanceMonitor {
 
     /// 更新函数统计
     async fn update_function_stats(&self, result: &ExecutionResult) {
+        let rolled_over = self.series.write().await.record(
+            &result.function_name,
+            unix_now(),
+            result.duration,
+            result.success,
+            result.memory_usage,
+        );
+        if rolled_over {
+            // 没有订阅者时发送失败是正常情况
+            let _ = self.rollovers.send(result.function_name.clone());
+        }
         let mut stats = self.stats.write().await;
         let function_stats = stats
             .entry(result.function_name.clone())
             .or_insert_with(FunctionStats::default);
 
-        function_stats.total_calls += 1;
+        bump(&mut function_stats.total_calls);
         if result.success {
-            function_stats.successful_calls += 1;
+            bump(&mut function_stats.successful_calls);
         } else {
-            function_stats.failed_calls += 1;
+            bump(&mut function_stats.failed_calls);
+            if let Some(origin) = result.error_origin {
+                bump(function_stats.failures_by_origin.entry(origin).or_default());
+            }
+        }
+        if result.attempt > 1 {
+            bump(&mut function_stats.retry_attempts);
+        }
+        if result.chaos_injected {
+            bump(&mut function_stats.chaos_injected);
+        }
+        if result.execution_path == Some(ExecutionPath::Inline) {
+            bump(&mut function_stats.inline_executions);
         }
 
-        function_stats.total_duration += result.duration;
+        function_stats.total_duration = function_stats
+            .total_duration
+            .saturating_add(result.duration);
+        function_stats.windows.record(
+            unix_now(),
+            result.duration,
+            result.success,
+            result.memory_usage,
+        );
         function_stats.last_execution = Some(Instant::now());
+        push_bounded(
+            &mut function_stats.recent_calls,
+            Instant::now(),
+            RECENT_CALL_LIMIT,
+        );
 
-        // 更新最小/最大执行时间
-        match function_stats.min_duration {
-            None => function_stats.min_duration = Some(result.duration),
-            Some(min) if result.duration < min => {
-                function_stats.min_duration = Some(result.duration)
-            }
-            _ => {}
-        }
-
-        match function_stats.max_duration {
-            None => function_stats.max_duration = Some(result.duration),
-            Some(max) if result.duration > max => {
-                function_stats.max_duration = Some(result.duration)
-            }
-            _ => {}
+        // 冷/热调用分别记录延迟样本
+        if result.cold_start {
+            bump(&mut function_stats.cold_starts);
+            push_bounded(
+                &mut function_stats.cold_latencies,
+                result.duration,
+                LATENCY_SAMPLE_LIMIT,
+            );
+        } else {
+            push_bounded(
+                &mut function_stats.warm_latencies,
+                result.duration,
+                LATENCY_SAMPLE_LIMIT,
+            );
         }
 
-        // 更新平均执行时间
-        function_stats.avg_duration =
-            function_stats.total_duration / function_stats.total_calls as u32;
+        // 更新最小/最大/平均执行时间
+        function_stats.durations.record_duration(result.duration);
+        function_stats.min_duration = Some(
+            function_stats
+                .min_duration
+                .map_or(result.duration, |min| min.min(result.duration)),
+        );
+        function_stats.max_duration = Some(
+            function_stats
+                .max_duration
+                .map_or(result.duration, |max| max.max(result.duration)),
+        );
+        function_stats.avg_duration = function_stats.durations.mean_duration();
 
         // 更新内存统计
-        if result.memory_usage > function_stats.peak_memory {
-            function_stats.peak_memory = result.memory_usage;
-        }
-
-        function_stats.avg_memory =
-            ((function_stats.avg_memory * (function_stats.total_calls - 1)) + result.memory_usage)
-                / function_stats.total_calls;
+        function_stats.memory.record(result.memory_usage as f64);
+        function_stats.peak_memory = function_stats.peak_memory.max(result.memory_usage);
+        function_stats.avg_memory = function_stats.memory.mean() as u64;
     }
 
     /// 更新全局统计
     async fn update_global_stats(&self, result: &ExecutionResult) {
         let mut global_stats = self.global_stats.write().await;
 
-        global_stats.total_requests += 1;
+        bump(&mut global_stats.total_requests);
         if result.success {
-            global_stats.total_success += 1;
+            bump(&mut global_stats.total_success);
         } else {
-            global_stats.total_failures += 1;
+            bump(&mut global_stats.total_failures);
+            if let Some(origin) = result.error_origin {
+                bump(global_stats.failures_by_origin.entry(origin).or_default());
+            }
+        }
+        if result.chaos_injected {
+            bump(&mut global_stats.chaos_injected);
+        }
+        if result.execution_path == Some(ExecutionPath::Inline) {
+            bump(&mut global_stats.inline_executions);
         }
+        global_stats.windows.record(
+            unix_now(),
+            result.duration,
+            result.success,
+            result.memory_usage,
+        );
 
         // 估算系统内存使用
-        global_stats.current_system_memory += result.memory_usage;
+        global_stats.current_system_memory = global_stats
+            .current_system_memory
+            .saturating_add(result.memory_usage);
         if global_stats.current_system_memory > global_stats.peak_system_memory {
             global_stats.peak_system_memory = global_stats.current_system_memory;
         }
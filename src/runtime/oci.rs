@@ -0,0 +1,835 @@
+//! OCI 制品函数源
+//!
+//! 从 OCI 仓库拉取函数制品：按引用获取清单，选择函数层并校验摘要后解包到按摘要命名的缓存目录。
+//! 函数层可以是导出格式的函数包（[`BUNDLE_MEDIA_TYPE`]），也可以是函数源文件和 `flux.toml`
+//! 清单组成的 tar（可 gzip 压缩）。仓库凭据只用于本次拉取，不写入缓存，也不出现在日志和错误信息中。
+
+use crate::functions::bundle::{FunctionBundle, ImportPayload};
+use crate::functions::{FluxError, Result};
+use crate::runtime::loader::{DirectoryLoadResult, FUNCTION_MANIFEST_FILE};
+use reqwest::StatusCode;
+use reqwest::header::{ACCEPT, WWW_AUTHENTICATE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// 导出格式函数包（单个函数包或归档 JSON）的层媒体类型
+pub const BUNDLE_MEDIA_TYPE: &str = "application/vnd.flux.bundle.v1+json";
+
+/// 请求清单时接受的媒体类型
+const MANIFEST_MEDIA_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, \
+     application/vnd.docker.distribution.manifest.v2+json";
+
+/// 缓存的函数包文件名
+const BUNDLE_FILE: &str = "bundle.json";
+
+/// 未指定仓库地址时的默认仓库
+const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// OCI 函数源配置（`[oci]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OciSourceConfig {
+    /// 按摘要缓存清单和解包后制品的目录
+    pub cache_dir: PathBuf,
+    /// 单次仓库请求的超时（秒）
+    pub timeout_secs: u64,
+    /// 函数层的字节上限
+    pub max_layer_bytes: u64,
+    /// 解包后所有文件的字节上限
+    pub max_unpacked_bytes: u64,
+    /// 使用 HTTP 访问的仓库（`host[:port]`），`localhost` 和回环地址总是使用 HTTP
+    pub insecure_registries: Vec<String>,
+}
+
+impl Default for OciSourceConfig {
+    fn default() -> Self {
+        Self {
+            cache_dir: PathBuf::from("./flux_data/oci"),
+            timeout_secs: 120,
+            max_layer_bytes: 64 * 1024 * 1024,
+            max_unpacked_bytes: 256 * 1024 * 1024,
+            insecure_registries: Vec::new(),
+        }
+    }
+}
+
+/// 仓库凭据：用户名和密码（换取令牌或 Basic 认证），或直接使用的 Bearer 令牌
+#[derive(Clone, Default, Deserialize, ToSchema)]
+pub struct OciAuth {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+}
+
+// 手动实现 Debug，避免凭据出现在日志中
+impl std::fmt::Debug for OciAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OciAuth")
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+/// 从 OCI 仓库加载函数的请求（只反序列化，凭据不会被序列化）
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct OciLoadRequest {
+    /// 制品引用，例如 `ghcr.io/org/fns:v3` 或 `ghcr.io/org/fns@sha256:...`
+    pub reference: String,
+    #[serde(default)]
+    pub auth: Option<OciAuth>,
+}
+
+/// 解析后的制品引用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OciReference {
+    pub registry: String,
+    pub repository: String,
+    pub tag: Option<String>,
+    pub digest: Option<String>,
+}
+
+impl OciReference {
+    /// 解析 `[registry/]repository[:tag][@digest]`，未指定标签和摘要时使用 `latest`
+    pub fn parse(reference: &str) -> Result<Self> {
+        let invalid = |reason: &str| FluxError::ValidationError {
+            reason: format!("Invalid OCI reference '{reference}': {reason}"),
+        };
+        let (name, digest) = match reference.split_once('@') {
+            Some((name, digest)) => {
+                parse_digest(digest).ok_or_else(|| invalid("digest must be sha256:<hex>"))?;
+                (name, Some(digest.to_string()))
+            }
+            None => (reference, None),
+        };
+        let (name, tag) = match name.rsplit_once(':') {
+            Some((repository, tag)) if !tag.contains('/') => (repository, Some(tag.to_string())),
+            _ => (name, None),
+        };
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, path))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host.to_string(), path.to_string())
+            }
+            Some(_) => (DEFAULT_REGISTRY.to_string(), name.to_string()),
+            None => (DEFAULT_REGISTRY.to_string(), format!("library/{name}")),
+        };
+        let valid_component = |component: &str| {
+            !component.is_empty()
+                && component.chars().all(|c| {
+                    c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | '-')
+                })
+        };
+        if !repository.split('/').all(valid_component) {
+            return Err(invalid("repository must be lowercase path components"));
+        }
+        if tag.as_ref().is_some_and(|tag| {
+            tag.is_empty()
+                || tag.len() > 128
+                || !tag
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        }) {
+            return Err(invalid("tag must be at most 128 of [A-Za-z0-9._-]"));
+        }
+        let tag = tag.or_else(|| digest.is_none().then(|| "latest".to_string()));
+        Ok(Self {
+            registry,
+            repository,
+            tag,
+            digest,
+        })
+    }
+
+    /// 请求清单时使用的引用：有摘要时按摘要，否则按标签
+    fn manifest_reference(&self) -> &str {
+        self.digest
+            .as_deref()
+            .or(self.tag.as_deref())
+            .unwrap_or("latest")
+    }
+}
+
+impl std::fmt::Display for OciReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.registry, self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{tag}")?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{digest}")?;
+        }
+        Ok(())
+    }
+}
+
+/// 解析 `sha256:<hex>` 摘要，返回十六进制部分
+fn parse_digest(digest: &str) -> Option<&str> {
+    digest
+        .strip_prefix("sha256:")
+        .filter(|hex| hex.len() == 64 && hex.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')))
+}
+
+fn sha256_digest(bytes: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(bytes))
+}
+
+/// 清单中的描述符
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    media_type: String,
+    digest: String,
+    size: u64,
+}
+
+/// 镜像清单（只读取需要的字段）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+    /// 镜像索引的子清单，存在时说明引用指向多平台索引
+    #[serde(default)]
+    manifests: Vec<serde_json::Value>,
+}
+
+/// 函数层的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayerFormat {
+    Bundle,
+    Tar,
+    TarGzip,
+}
+
+impl LayerFormat {
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            BUNDLE_MEDIA_TYPE => Some(Self::Bundle),
+            _ if media_type.ends_with("tar+gzip") || media_type.ends_with("tar.gzip") => {
+                Some(Self::TarGzip)
+            }
+            _ if media_type.ends_with("tar") || media_type == "application/x-tar" => {
+                Some(Self::Tar)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 拉取得到的函数制品
+#[derive(Debug, Clone)]
+pub enum OciArtifact {
+    /// 解包后的函数目录
+    Directory(PathBuf),
+    /// 导出格式的函数包
+    Bundles(Vec<FunctionBundle>),
+}
+
+/// 一次拉取的结果
+#[derive(Debug, Clone)]
+pub struct OciPull {
+    /// 规范化的制品引用
+    pub reference: String,
+    /// 函数层的摘要
+    pub digest: String,
+    /// 函数层来自本地缓存，没有下载
+    pub cached: bool,
+    pub artifact: OciArtifact,
+}
+
+/// `/load/oci` 的结果：制品信息和与目录加载相同的逐函数结果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OciLoadResult {
+    pub reference: String,
+    /// 函数层的摘要
+    pub digest: String,
+    /// 函数层来自本地缓存，没有下载
+    pub cached: bool,
+    #[serde(flatten)]
+    pub load: DirectoryLoadResult,
+}
+
+/// OCI 函数源 - 拉取制品并按摘要缓存
+#[derive(Debug, Default)]
+pub struct OciFunctionSource {
+    config: StdMutex<OciSourceConfig>,
+}
+
+impl OciFunctionSource {
+    pub fn new(config: OciSourceConfig) -> Self {
+        Self {
+            config: StdMutex::new(config),
+        }
+    }
+
+    /// 应用配置（启动时调用）
+    pub fn configure(&self, config: &OciSourceConfig) {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+    }
+
+    pub fn config(&self) -> OciSourceConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 拉取制品的函数层；按摘要引用且清单已缓存时不访问仓库，函数层已缓存时不下载
+    pub async fn pull(&self, req: &OciLoadRequest) -> Result<OciPull> {
+        let reference = OciReference::parse(&req.reference)?;
+        let config = self.config();
+        let pull_failed = |reason: String| FluxError::OciPullFailed {
+            reference: reference.to_string(),
+            reason,
+        };
+        let mut client = RegistryClient::new(&config, &reference, req.auth.as_ref())
+            .map_err(|e| pull_failed(e.to_string()))?;
+
+        let manifest = match self.cached_manifest(&config, &reference).await {
+            Some(manifest) => manifest,
+            None => {
+                let manifest = client.manifest().await?;
+                if let Some(expected) = &reference.digest {
+                    verify_digest(&reference, expected, &manifest)?;
+                    self.cache_manifest(&config, expected, &manifest).await;
+                }
+                manifest
+            }
+        };
+        let manifest: Manifest = serde_json::from_slice(&manifest)
+            .map_err(|e| pull_failed(format!("Invalid manifest: {e}")))?;
+        if !manifest.manifests.is_empty() {
+            return Err(FluxError::ValidationError {
+                reason: format!(
+                    "{reference} is an image index; reference a single manifest by digest instead"
+                ),
+            });
+        }
+        let (layer, format) = manifest
+            .layers
+            .iter()
+            .find_map(|layer| LayerFormat::from_media_type(&layer.media_type).map(|f| (layer, f)))
+            .ok_or_else(|| FluxError::ValidationError {
+                reason: format!(
+                    "{reference} has no function layer ({BUNDLE_MEDIA_TYPE} or a tar of function files)"
+                ),
+            })?;
+        let hex = parse_digest(&layer.digest)
+            .ok_or_else(|| pull_failed(format!("Unsupported layer digest {}", layer.digest)))?;
+        if layer.size > config.max_layer_bytes {
+            return Err(pull_failed(format!(
+                "Layer is {} bytes, exceeding the limit of {} bytes",
+                layer.size, config.max_layer_bytes
+            )));
+        }
+
+        let dir = config.cache_dir.join(format!("sha256-{hex}"));
+        let cached = tokio::fs::try_exists(&dir).await.unwrap_or(false);
+        if !cached {
+            let blob = client.blob(layer, config.max_layer_bytes).await?;
+            verify_digest(&reference, &layer.digest, &blob)?;
+            unpack(&config, format, blob, dir.clone()).await?;
+            tracing::info!("Pulled OCI artifact {} ({})", reference, layer.digest);
+        }
+
+        let artifact = match format {
+            LayerFormat::Bundle => {
+                let bundle = tokio::fs::read(dir.join(BUNDLE_FILE)).await?;
+                OciArtifact::Bundles(parse_bundles(&bundle)?)
+            }
+            LayerFormat::Tar | LayerFormat::TarGzip => OciArtifact::Directory(dir),
+        };
+        Ok(OciPull {
+            reference: reference.to_string(),
+            digest: layer.digest.clone(),
+            cached,
+            artifact,
+        })
+    }
+
+    fn manifest_path(config: &OciSourceConfig, digest: &str) -> Option<PathBuf> {
+        parse_digest(digest).map(|hex| {
+            config
+                .cache_dir
+                .join("manifests")
+                .join(format!("sha256-{hex}.json"))
+        })
+    }
+
+    /// 按摘要引用时读取缓存的清单（写入前已校验摘要）
+    async fn cached_manifest(
+        &self,
+        config: &OciSourceConfig,
+        reference: &OciReference,
+    ) -> Option<Vec<u8>> {
+        let path = Self::manifest_path(config, reference.digest.as_deref()?)?;
+        tokio::fs::read(path).await.ok()
+    }
+
+    /// 缓存按摘要引用的清单，失败只影响下次是否访问仓库
+    async fn cache_manifest(&self, config: &OciSourceConfig, digest: &str, manifest: &[u8]) {
+        let Some(path) = Self::manifest_path(config, digest) else {
+            return;
+        };
+        let written = async {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, manifest).await
+        };
+        if let Err(e) = written.await {
+            tracing::warn!("Failed to cache OCI manifest {}: {}", digest, e);
+        }
+    }
+}
+
+fn verify_digest(reference: &OciReference, expected: &str, content: &[u8]) -> Result<()> {
+    let actual = sha256_digest(content);
+    if actual != expected {
+        return Err(FluxError::OciDigestMismatch {
+            reference: reference.to_string(),
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+fn parse_bundles(content: &[u8]) -> Result<Vec<FunctionBundle>> {
+    let payload: ImportPayload =
+        serde_json::from_slice(content).map_err(|e| FluxError::ValidationError {
+            reason: format!("Invalid function bundle layer: {e}"),
+        })?;
+    Ok(match payload {
+        ImportPayload::Archive(archive) => archive.functions,
+        ImportPayload::Bundle(bundle) => vec![*bundle],
+    })
+}
+
+/// 在临时目录中解包函数层再整体改名为缓存目录，解包失败的制品不会进入缓存
+async fn unpack(
+    config: &OciSourceConfig,
+    format: LayerFormat,
+    blob: Vec<u8>,
+    dir: PathBuf,
+) -> Result<()> {
+    tokio::fs::create_dir_all(&config.cache_dir).await?;
+    let staging = config
+        .cache_dir
+        .join(format!(".staging-{}", scru128::new_string()));
+    let max_unpacked_bytes = config.max_unpacked_bytes;
+    let target = staging.clone();
+    let unpacked = tokio::task::spawn_blocking(move || -> Result<()> {
+        std::fs::create_dir_all(&target)?;
+        match format {
+            LayerFormat::Bundle => {
+                parse_bundles(&blob)?;
+                std::fs::write(target.join(BUNDLE_FILE), &blob)?;
+            }
+            LayerFormat::Tar => unpack_tar(&blob[..], &target, max_unpacked_bytes)?,
+            LayerFormat::TarGzip => unpack_tar(
+                flate2::read::GzDecoder::new(&blob[..]),
+                &target,
+                max_unpacked_bytes,
+            )?,
+        }
+        if format != LayerFormat::Bundle && !has_functions(&target)? {
+            return Err(FluxError::ValidationError {
+                reason: format!(
+                    "Artifact contains no functions: expected top-level source files or \
+                     directories with a {FUNCTION_MANIFEST_FILE} manifest"
+                ),
+            });
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| FluxError::Runtime(format!("Unpacking task failed: {e}")))?;
+
+    let renamed = match unpacked {
+        Ok(()) => tokio::fs::rename(&staging, &dir).await,
+        Err(e) => {
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+            return Err(e);
+        }
+    };
+    if let Err(e) = renamed {
+        let _ = tokio::fs::remove_dir_all(&staging).await;
+        // 并发拉取同一制品时另一个请求已经写入缓存
+        if !tokio::fs::try_exists(&dir).await.unwrap_or(false) {
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+/// 解包 tar，只保留普通文件和目录，拒绝逃出目标目录的路径
+fn unpack_tar(reader: impl Read, dir: &Path, max_bytes: u64) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    let mut total: u64 = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let kind = entry.header().entry_type();
+        if !kind.is_file() && !kind.is_dir() {
+            continue;
+        }
+        total = total.saturating_add(entry.size());
+        if total > max_bytes {
+            return Err(FluxError::ValidationError {
+                reason: format!("Artifact unpacks to more than {max_bytes} bytes"),
+            });
+        }
+        if !entry.unpack_in(dir)? {
+            return Err(FluxError::ValidationError {
+                reason: format!(
+                    "Artifact entry escapes the artifact directory: {}",
+                    entry.path()?.display()
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 目录中是否有可加载的函数：顶层源文件或带 `flux.toml` 的子目录
+fn has_functions(dir: &Path) -> Result<bool> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let found = if path.is_dir() {
+            path.join(FUNCTION_MANIFEST_FILE).is_file()
+        } else {
+            path.extension().is_some_and(|ext| ext == "rs")
+        };
+        if found {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// 一次拉取中使用的仓库客户端，令牌只保存在本次拉取的内存中
+struct RegistryClient<'a> {
+    http: reqwest::Client,
+    reference: &'a OciReference,
+    auth: Option<&'a OciAuth>,
+    base: String,
+    bearer: Option<String>,
+}
+
+impl<'a> RegistryClient<'a> {
+    fn new(
+        config: &OciSourceConfig,
+        reference: &'a OciReference,
+        auth: Option<&'a OciAuth>,
+    ) -> reqwest::Result<Self> {
+        let host = match reference.registry.as_str() {
+            DEFAULT_REGISTRY => "registry-1.docker.io",
+            host => host,
+        };
+        let hostname = host.rsplit_once(':').map_or(host, |(hostname, _)| hostname);
+        let insecure = matches!(hostname, "localhost" | "127.0.0.1" | "[::1]")
+            || config.insecure_registries.iter().any(|r| r == host);
+        let scheme = if insecure { "http" } else { "https" };
+        Ok(Self {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+                .build()?,
+            reference,
+            auth,
+            base: format!("{scheme}://{host}/v2/{}", reference.repository),
+            bearer: auth.and_then(|auth| auth.token.clone()),
+        })
+    }
+
+    fn pull_failed(&self, reason: impl Into<String>) -> FluxError {
+        FluxError::OciPullFailed {
+            reference: self.reference.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    async fn manifest(&mut self) -> Result<Vec<u8>> {
+        let path = format!("manifests/{}", self.reference.manifest_reference());
+        let response = self.get(&path, MANIFEST_MEDIA_TYPES).await?;
+        self.read_limited(response, u64::from(u32::MAX)).await
+    }
+
+    async fn blob(&mut self, layer: &Descriptor, max_bytes: u64) -> Result<Vec<u8>> {
+        let response = self
+            .get(&format!("blobs/{}", layer.digest), &layer.media_type)
+            .await?;
+        self.read_limited(response, max_bytes).await
+    }
+
+    /// 逐块读取响应体，超过上限时停止
+    async fn read_limited(
+        &self,
+        mut response: reqwest::Response,
+        max_bytes: u64,
+    ) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| self.pull_failed(e.to_string()))?
+        {
+            if body.len() as u64 + chunk.len() as u64 > max_bytes {
+                return Err(
+                    self.pull_failed(format!("Response exceeds the limit of {max_bytes} bytes"))
+                );
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    /// 发送请求；仓库要求认证时按质询换取令牌后重试一次
+    async fn get(&mut self, path: &str, accept: &str) -> Result<reqwest::Response> {
+        let url = format!("{}/{path}", self.base);
+        let mut response = self.send(&url, accept).await?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            let challenge = response
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            if self.authorize(&challenge).await? {
+                response = self.send(&url, accept).await?;
+            }
+        }
+        match response.status() {
+            status if status.is_success() => Ok(response),
+            StatusCode::NOT_FOUND => Err(FluxError::OciArtifactNotFound {
+                reference: self.reference.to_string(),
+            }),
+            status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                Err(self.pull_failed(format!("Registry denied access (HTTP {})", status.as_u16())))
+            }
+            status => Err(self.pull_failed(format!(
+                "Registry returned HTTP {} for {path}",
+                status.as_u16()
+            ))),
+        }
+    }
+
+    async fn send(&self, url: &str, accept: &str) -> Result<reqwest::Response> {
+        let mut request = self.http.get(url).header(ACCEPT, accept);
+        if let Some(token) = &self.bearer {
+            request = request.bearer_auth(token);
+        } else if let Some(OciAuth {
+            username: Some(username),
+            password,
+            ..
+        }) = self.auth
+        {
+            request = request.basic_auth(username, password.as_ref());
+        }
+        request
+            .send()
+            .await
+            .map_err(|e| self.pull_failed(e.without_url().to_string()))
+    }
+
+    /// 处理 Bearer 质询：向令牌服务换取拉取令牌；返回 false 表示无法认证
+    async fn authorize(&mut self, challenge: &str) -> Result<bool> {
+        let Some(params) = challenge.strip_prefix("Bearer ") else {
+            return Ok(false);
+        };
+        // 用户直接提供的令牌被拒绝时不再换取
+        if self.auth.is_some_and(|auth| auth.token.is_some()) {
+            return Ok(false);
+        }
+        let params = challenge_params(params);
+        let Some(realm) = params.iter().find(|(k, _)| k == "realm").map(|(_, v)| v) else {
+            return Ok(false);
+        };
+        let mut query: Vec<(&str, String)> = params
+            .iter()
+            .filter(|(key, _)| key == "service" || key == "scope")
+            .map(|(key, value)| (key.as_str(), value.clone()))
+            .collect();
+        if !query.iter().any(|(key, _)| *key == "scope") {
+            query.push((
+                "scope",
+                format!("repository:{}:pull", self.reference.repository),
+            ));
+        }
+        let mut request = self.http.get(realm).query(&query);
+        if let Some(OciAuth {
+            username: Some(username),
+            password,
+            ..
+        }) = self.auth
+        {
+            request = request.basic_auth(username, password.as_ref());
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| self.pull_failed(format!("Token request failed: {}", e.without_url())))?;
+        if !response.status().is_success() {
+            return Err(self.pull_failed(format!(
+                "Token service returned HTTP {}",
+                response.status().as_u16()
+            )));
+        }
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            token: Option<String>,
+            access_token: Option<String>,
+        }
+        let token: TokenResponse = response.json().await.map_err(|e| {
+            self.pull_failed(format!("Invalid token response: {}", e.without_url()))
+        })?;
+        self.bearer = token.token.or(token.access_token);
+        Ok(self.bearer.is_some())
+    }
+}
+
+/// 解析质询参数 `key="value",key="value"`
+fn challenge_params(params: &str) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    let mut rest = params.trim();
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_string();
+        let value = value.trim_start();
+        let (value, remaining) = match value.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, remaining)) => (value, remaining),
+                None => (quoted, ""),
+            },
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        result.push((key, value.to_string()));
+        rest = remaining.trim_start_matches(',').trim();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reference_and_challenge() {
+        let reference = OciReference::parse("ghcr.io/org/fns:v3").unwrap();
+        assert_eq!(reference.registry, "ghcr.io");
+        assert_eq!(reference.repository, "org/fns");
+        assert_eq!(reference.tag.as_deref(), Some("v3"));
+        assert_eq!(reference.to_string(), "ghcr.io/org/fns:v3");
+
+        let reference = OciReference::parse("localhost:5000/fns").unwrap();
+        assert_eq!(reference.registry, "localhost:5000");
+        assert_eq!(reference.tag.as_deref(), Some("latest"));
+
+        let digest = format!("sha256:{}", "a".repeat(64));
+        let reference = OciReference::parse(&format!("fns@{digest}")).unwrap();
+        assert_eq!(reference.registry, "docker.io");
+        assert_eq!(reference.repository, "library/fns");
+        assert_eq!(reference.tag, None);
+        assert_eq!(reference.manifest_reference(), digest);
+
+        assert!(OciReference::parse("ghcr.io/Org/fns").is_err());
+        assert!(OciReference::parse("ghcr.io/org/fns@md5:abc").is_err());
+
+        let params = challenge_params(
+            r#"realm="https://auth.example.com/token",service="registry.example.com",scope="repository:org/fns:pull""#,
+        );
+        assert_eq!(
+            params,
+            vec![
+                (
+                    "realm".to_string(),
+                    "https://auth.example.com/token".to_string()
+                ),
+                ("service".to_string(), "registry.example.com".to_string()),
+                ("scope".to_string(), "repository:org/fns:pull".to_string()),
+            ]
+        );
+
+        let auth = OciAuth {
+            username: Some("ci".to_string()),
+            password: Some("hunter2".to_string()),
+            token: Some("tok123".to_string()),
+        };
+        let debug = format!("{auth:?}");
+        assert!(
+            !debug.contains("hunter2") && !debug.contains("tok123"),
+            "{debug}"
+        );
+    }
+
+    fn tar_with(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unpack_validates_and_caches_atomically() {
+        let cache = tempfile::tempdir().unwrap();
+        let config = OciSourceConfig {
+            cache_dir: cache.path().to_path_buf(),
+            ..Default::default()
+        };
+        let layer = tar_with(&[
+            ("greet/flux.toml", "name = \"greet\"\n"),
+            (
+                "greet/main.rs",
+                "fn greet() -> String { \"hi\".to_string() }",
+            ),
+        ]);
+        let dir = cache.path().join("sha256-ok");
+        unpack(&config, LayerFormat::Tar, layer, dir.clone())
+            .await
+            .unwrap();
+        assert!(dir.join("greet/flux.toml").is_file());
+
+        // 没有函数的制品不进入缓存，也不留下临时目录
+        let layer = tar_with(&[("README.md", "docs only")]);
+        let dir = cache.path().join("sha256-empty");
+        let error = unpack(&config, LayerFormat::Tar, layer, dir.clone())
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains(FUNCTION_MANIFEST_FILE),
+            "{error}"
+        );
+        assert!(!dir.exists());
+        let leftovers: Vec<_> = std::fs::read_dir(cache.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers, vec![std::ffi::OsString::from("sha256-ok")]);
+
+        let error = unpack(
+            &config,
+            LayerFormat::Bundle,
+            b"{\"not\": \"a bundle\"}".to_vec(),
+            cache.path().join("sha256-bundle"),
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(error, FluxError::ValidationError { .. }),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,342 @@
+//! 沙箱使用的平台相关操作：目录权限、可执行文件、进程树终止和资源监控
+//!
+//! Unix 上用文件模式位和进程组；Windows 上用 `icacls` 收紧目录 ACL，
+//! 用 `taskkill /T` 终止整个进程树（包括子进程再启动的进程）。
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::{Child, Command as TokioCommand};
+
+/// 执行器可执行文件名（Windows 上为 `executor.exe`）
+pub fn executable_name(stem: &str) -> String {
+    format!("{stem}{}", std::env::consts::EXE_SUFFIX)
+}
+
+/// 把目录权限收紧到仅当前用户可访问
+///
+/// Unix 上设为 `0o700`；Windows 上移除继承的 ACL 并只授予当前用户完全控制。
+/// 无法收紧时（如找不到 `icacls` 或当前用户名）只记录警告，不阻止执行。
+pub fn restrict_to_owner(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+            .context("Failed to set directory permissions")?;
+    }
+
+    #[cfg(windows)]
+    {
+        let Ok(user) = std::env::var("USERNAME") else {
+            tracing::warn!("USERNAME is not set, skipping ACL tightening for {:?}", dir);
+            return Ok(());
+        };
+        let grant = format!("{user}:(OI)(CI)F");
+        match std::process::Command::new("icacls")
+            .arg(dir)
+            .args(["/inheritance:r", "/grant:r", &grant])
+            .output()
+        {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => tracing::warn!(
+                "icacls failed for {:?}, directory keeps inherited ACL: {}",
+                dir,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(e) => tracing::warn!(
+                "icacls unavailable, directory {:?} keeps inherited ACL: {}",
+                dir,
+                e
+            ),
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    tracing::warn!(
+        "Directory permissions are not supported on this platform: {:?}",
+        dir
+    );
+
+    Ok(())
+}
+
+/// 允许执行文件（Windows 按扩展名判断，无需额外操作）
+pub fn make_executable(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+            .context("Failed to set executor permissions")?;
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// 把文件设为只读（Unix 上为仅所有者可读的 `0o400`）
+pub fn make_read_only(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    let permissions = {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::Permissions::from_mode(0o400)
+    };
+
+    #[cfg(not(unix))]
+    let permissions = {
+        let mut permissions = std::fs::metadata(path)
+            .context("Failed to read file permissions")?
+            .permissions();
+        permissions.set_readonly(true);
+        permissions
+    };
+
+    std::fs::set_permissions(path, permissions).context("Failed to set read-only permissions")
+}
+
+/// 在隔离目录中创建指向宿主目录的链接
+///
+/// Windows 上创建目录符号链接需要开发者模式或相应权限，失败时由调用方报错。
+pub fn link_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(target, link);
+
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_dir(target, link);
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (target, link);
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "directory links are not supported on this platform",
+        ))
+    }
+}
+
+/// 让子进程成为独立进程树的根，以便超时或超限时连同其后代一起终止
+///
+/// Unix 上子进程成为新进程组的组长（进程组 ID 等于 PID）；
+/// Windows 上放入新的进程组，`taskkill /T` 按父子关系查找后代。
+pub fn isolate_process_tree(cmd: &mut TokioCommand) {
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    #[cfg(windows)]
+    {
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    let _ = cmd;
+}
+
+/// 终止由 [`isolate_process_tree`] 启动的进程及其所有后代
+///
+/// `force` 为 false 时请求进程退出（Unix 上为 SIGTERM），为 true 时立即强制终止。
+pub fn terminate_tree(pid: u32, force: bool) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{Signal, killpg};
+        use nix::unistd::Pid;
+
+        let signal = if force {
+            Signal::SIGKILL
+        } else {
+            Signal::SIGTERM
+        };
+        killpg(Pid::from_raw(pid as i32), signal)
+            .with_context(|| format!("Failed to send {signal} to process group {pid}"))
+    }
+
+    #[cfg(windows)]
+    {
+        let mut command = std::process::Command::new("taskkill");
+        command.args(["/PID", &pid.to_string(), "/T"]);
+        if force {
+            command.arg("/F");
+        }
+        let output = command.output().context("Failed to run taskkill")?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "taskkill failed for process {pid}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = force;
+        Err(anyhow::anyhow!(
+            "Process termination is not supported on this platform (pid {pid})"
+        ))
+    }
+}
+
+/// 终止由 [`isolate_process_tree`] 启动的子进程及其后代，并回收子进程
+///
+/// 先请求退出，`grace` 内没有退出时强制终止；返回是否需要强制终止。
+pub async fn terminate_child(child: &mut Child, grace: Duration) -> bool {
+    let Some(pid) = child.id() else {
+        // 已经回收
+        return false;
+    };
+    if let Err(e) = terminate_tree(pid, false) {
+        tracing::debug!("Failed to request termination of process {}: {}", pid, e);
+    }
+    let exited = matches!(tokio::time::timeout(grace, child.wait()).await, Ok(Ok(_)));
+    // 子进程已退出时后代进程可能仍在运行，同样强制终止整个进程组
+    if let Err(e) = terminate_tree(pid, true) {
+        tracing::debug!("Failed to force-terminate process {}: {}", pid, e);
+    }
+    if !exited && let Err(e) = child.wait().await {
+        tracing::warn!("Failed to reap process {}: {}", pid, e);
+    }
+    !exited
+}
+
+/// 资源监控（内存峰值、CPU 使用率和内存上限）在当前平台上是否可用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceMonitoring {
+    Supported,
+    /// 不可用：内存和 CPU 统计为 0，内存上限不生效，只有执行超时仍然有效
+    Unsupported,
+}
+
+impl ResourceMonitoring {
+    /// 当前平台的支持情况（由 sysinfo 判断）
+    pub fn current() -> Self {
+        if sysinfo::IS_SUPPORTED_SYSTEM {
+            Self::Supported
+        } else {
+            Self::Unsupported
+        }
+    }
+
+    pub fn is_supported(self) -> bool {
+        self == Self::Supported
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use std::time::Duration;
+
+    /// 一个持续约 30 秒的子进程
+    fn long_running() -> TokioCommand {
+        #[cfg(windows)]
+        let mut cmd = {
+            let mut cmd = TokioCommand::new("ping");
+            cmd.args(["-n", "30", "127.0.0.1"]);
+            cmd
+        };
+        #[cfg(not(windows))]
+        let mut cmd = {
+            let mut cmd = TokioCommand::new("sleep");
+            cmd.arg("30");
+            cmd
+        };
+        cmd.stdout(Stdio::null());
+        cmd
+    }
+
+    #[test]
+    fn test_executable_name_uses_platform_suffix() {
+        let name = executable_name("executor");
+        if cfg!(windows) {
+            assert_eq!(name, "executor.exe");
+        } else {
+            assert_eq!(name, "executor");
+        }
+    }
+
+    #[test]
+    fn test_permission_helpers() {
+        let dir = tempfile::tempdir().unwrap();
+        restrict_to_owner(dir.path()).unwrap();
+        let file = dir.path().join("script");
+        std::fs::write(&file, "print(1)").unwrap();
+        make_executable(&file).unwrap();
+        make_read_only(&file).unwrap();
+        assert!(std::fs::metadata(&file).unwrap().permissions().readonly());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.path()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+            let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o400);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_terminate_tree_stops_isolated_child() {
+        let mut cmd = long_running();
+        isolate_process_tree(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+        let pid = child.id().unwrap();
+
+        terminate_tree(pid, true).unwrap();
+        let status = tokio::time::timeout(Duration::from_secs(5), child.wait())
+            .await
+            .expect("process tree was not terminated")
+            .unwrap();
+        assert!(!status.success());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_terminate_tree_reaches_grandchildren() {
+        use tokio::io::AsyncBufReadExt;
+
+        // 子 shell 在后台启动孙进程并打印其 PID
+        let mut cmd = TokioCommand::new("sh");
+        cmd.args(["-c", "sleep 30 & echo $!; wait"])
+            .stdout(Stdio::piped());
+        isolate_process_tree(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+        let mut lines = tokio::io::BufReader::new(child.stdout.take().unwrap()).lines();
+        let grandchild: u32 = lines.next_line().await.unwrap().unwrap().parse().unwrap();
+
+        terminate_tree(child.id().unwrap(), true).unwrap();
+        child.wait().await.unwrap();
+
+        // 孙进程被收养后可能短暂处于僵尸状态，只要不再运行即可
+        let alive = || {
+            std::fs::read_to_string(format!("/proc/{grandchild}/stat"))
+                .map(|stat| {
+                    let state = stat.rsplit(')').next().unwrap_or("").trim_start();
+                    !state.starts_with('Z') && !state.starts_with('X')
+                })
+                .unwrap_or(false)
+        };
+        for _ in 0..50 {
+            if !alive() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("grandchild {grandchild} survived process tree termination");
+    }
+
+    #[test]
+    fn test_resource_monitoring_serializes_as_marker() {
+        assert_eq!(
+            serde_json::to_value(ResourceMonitoring::Unsupported).unwrap(),
+            "unsupported"
+        );
+        assert_eq!(
+            ResourceMonitoring::current().is_supported(),
+            sysinfo::IS_SUPPORTED_SYSTEM
+        );
+    }
+}
@@ -1,6 +1,8 @@
+use crate::functions::FluxError;
+use crate::runtime::disk;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, RwLock};
@@ -83,8 +85,9 @@ pub enum LimitType {
 /// 资源快照类型
 pub type ResourceSnapshot = (chrono::DateTime<chrono::Utc>, HashMap<ResourceType, u64>);
 
-/// 资源配额管理
+/// 资源配额管理（反序列化时缺省的字段取默认配额的值）
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ResourceQuota {
     /// 配额名称
     pub name: String,
@@ -148,6 +151,104 @@ impl Default for ResourceQuota {
     }
 }
 
+/// 配额名最大长度
+const MAX_QUOTA_NAME_LEN: usize = 64;
+/// 检查间隔的取值范围（毫秒）
+const CHECK_INTERVAL_RANGE_MS: std::ops::RangeInclusive<u64> = 10..=60_000;
+/// 时间窗口上限（秒）
+const MAX_TIME_WINDOW_SECS: u64 = 24 * 60 * 60;
+/// 每个配额保留的最近执行摘要数
+const USAGE_HISTORY_LIMIT: usize = 256;
+/// 资源事件历史保留的事件数
+const EVENT_HISTORY_LIMIT: usize = 1000;
+
+impl ResourceQuota {
+    /// 校验配额定义：名称只含字母、数字、`-` 和 `_`，软限制小于硬限制，
+    /// 检查间隔在 10ms 到 60s 之间，时间窗口在 1 秒到 1 天之间
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.name.is_empty()
+            || self.name.len() > MAX_QUOTA_NAME_LEN
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Invalid quota name '{}': use 1-{MAX_QUOTA_NAME_LEN} letters, digits, '-' or '_'",
+                self.name
+            ));
+        }
+        if self.time_window_secs == 0 || self.time_window_secs > MAX_TIME_WINDOW_SECS {
+            return Err(format!(
+                "time_window_secs must be between 1 and {MAX_TIME_WINDOW_SECS}, got {}",
+                self.time_window_secs
+            ));
+        }
+        for (resource_type, limit) in &self.limits {
+            if limit.resource_type != *resource_type {
+                return Err(format!(
+                    "Limit under {resource_type:?} declares resource_type {:?}",
+                    limit.resource_type
+                ));
+            }
+            if limit.soft_limit >= limit.hard_limit {
+                return Err(format!(
+                    "{resource_type:?} soft_limit ({}) must be lower than hard_limit ({})",
+                    limit.soft_limit, limit.hard_limit
+                ));
+            }
+            if !CHECK_INTERVAL_RANGE_MS.contains(&limit.check_interval_ms) {
+                return Err(format!(
+                    "{resource_type:?} check_interval_ms must be between {} and {}, got {}",
+                    CHECK_INTERVAL_RANGE_MS.start(),
+                    CHECK_INTERVAL_RANGE_MS.end(),
+                    limit.check_interval_ms
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 生效的资源限制（配额或该项限制被禁用时为 `None`）
+    pub fn active_limit(&self, resource_type: &ResourceType) -> Option<&ResourceLimit> {
+        self.limits
+            .get(resource_type)
+            .filter(|limit| self.enabled && limit.enabled)
+    }
+}
+
+/// 在某个配额下运行的最近执行的资源用量汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaUsage {
+    /// 配额名称
+    pub quota_name: String,
+    /// 汇总的执行数（最多保留最近 256 次）
+    pub executions: usize,
+    /// 超出硬限制的执行数
+    pub violations: usize,
+    /// 只超出软限制的执行数
+    pub warnings: usize,
+    /// 最早一次被汇总的执行结束时间
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    /// 各资源的用量与当前限制
+    pub resources: HashMap<ResourceType, ResourceUsageAggregate>,
+}
+
+/// 单项资源在最近执行中的用量
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceUsageAggregate {
+    /// 峰值
+    pub peak_usage: u64,
+    /// 各次执行峰值的平均值
+    pub average_usage: f64,
+    /// 当前定义的软限制
+    pub soft_limit: Option<u64>,
+    /// 当前定义的硬限制
+    pub hard_limit: Option<u64>,
+    /// 峰值占硬限制的比例，接近或超过 1 说明限制偏紧
+    pub peak_ratio: Option<f64>,
+}
+
 /// 资源监控事件
 #[derive(Debug, Clone, Serialize)]
 pub struct ResourceEvent {
@@ -393,10 +494,9 @@ impl ProcessResourceMonitor {
             let usage = match resource_type {
                 ResourceType::Memory => process.memory(),
                 ResourceType::Cpu => (process.cpu_usage() * 100.0) as u64,
-                ResourceType::DiskIo => {
-                    // sysinfo 在某些平台上可能不支持磁盘IO统计
-                    0 // 暂时返回0，后续可以通过其他方式获取
-                }
+                // 读写存储的字节数（只在 Linux 上可以读取 /proc/<pid>/io）
+                ResourceType::DiskIo => disk::proc_io(process_id)
+                    .map_or(0, |io| io.read_bytes.saturating_add(io.write_bytes)),
                 ResourceType::NetworkIo => {
                     // 网络IO统计需要额外的实现
                     0 // 暂时返回0
@@ -508,6 +608,8 @@ pub struct ResourceManager {
     active_monitors: Arc<RwLock<HashMap<u32, ProcessResourceMonitor>>>,
     /// 资源事件历史
     event_history: Arc<RwLock<Vec<ResourceEvent>>>,
+    /// 按配额名记录的最近执行摘要
+    usage_history: Arc<RwLock<HashMap<String, VecDeque<ResourceSummary>>>>,
 }
 
 impl ResourceManager {
@@ -520,6 +622,7 @@ impl ResourceManager {
             quotas: Arc::new(RwLock::new(quotas)),
             active_monitors: Arc::new(RwLock::new(HashMap::new())),
             event_history: Arc::new(RwLock::new(Vec::new())),
+            usage_history: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -535,6 +638,219 @@ impl ResourceManager {
         quotas.get(name).cloned()
     }
 
+    /// 所有资源配额（按名称排序）
+    pub async fn list_quotas(&self) -> Vec<ResourceQuota> {
+        let quotas = self.quotas.read().await;
+        let mut quotas: Vec<_> = quotas.values().cloned().collect();
+        quotas.sort_by(|a, b| a.name.cmp(&b.name));
+        quotas
+    }
+
+    /// 创建资源配额，同名配额已存在时返回错误
+    pub async fn create_quota(
+        &self,
+        mut quota: ResourceQuota,
+    ) -> crate::functions::Result<ResourceQuota> {
+        quota
+            .validate()
+            .map_err(|reason| FluxError::ValidationError { reason })?;
+        let mut quotas = self.quotas.write().await;
+        if quotas.contains_key(&quota.name) {
+            return Err(FluxError::QuotaAlreadyExists { name: quota.name });
+        }
+        quota.created_at = chrono::Utc::now();
+        quotas.insert(quota.name.clone(), quota.clone());
+        Ok(quota)
+    }
+
+    /// 替换已有配额的定义（保留创建时间），之后的执行立即按新定义检查
+    pub async fn update_quota(
+        &self,
+        mut quota: ResourceQuota,
+    ) -> crate::functions::Result<ResourceQuota> {
+        quota
+            .validate()
+            .map_err(|reason| FluxError::ValidationError { reason })?;
+        let mut quotas = self.quotas.write().await;
+        let Some(existing) = quotas.get_mut(&quota.name) else {
+            return Err(FluxError::QuotaNotFound { name: quota.name });
+        };
+        quota.created_at = existing.created_at;
+        *existing = quota.clone();
+        Ok(quota)
+    }
+
+    /// 删除资源配额及其用量记录，`default` 配额不能删除
+    pub async fn delete_quota(&self, name: &str) -> crate::functions::Result<ResourceQuota> {
+        if name == "default" {
+            return Err(FluxError::ValidationError {
+                reason: "The default quota cannot be deleted".to_string(),
+            });
+        }
+        let removed =
+            self.quotas
+                .write()
+                .await
+                .remove(name)
+                .ok_or_else(|| FluxError::QuotaNotFound {
+                    name: name.to_string(),
+                })?;
+        self.usage_history.write().await.remove(name);
+        Ok(removed)
+    }
+
+    /// 按配额检查一次执行的资源峰值：超出硬限制记为违规事件，超出软限制记为警告，
+    /// 摘要计入该配额的用量汇总
+    pub async fn record_execution(
+        &self,
+        process_id: u32,
+        function_name: &str,
+        quota: &ResourceQuota,
+        duration_ms: u64,
+        samples: &HashMap<ResourceType, u64>,
+    ) -> ResourceSummary {
+        let now = chrono::Utc::now();
+        let mut usage = HashMap::new();
+        let mut events = Vec::new();
+
+        for (resource_type, &value) in samples {
+            let limit = quota.active_limit(resource_type);
+            let exceeded = match limit {
+                Some(limit) if value > limit.hard_limit => {
+                    Some((LimitType::Hard, limit.hard_limit))
+                }
+                Some(limit) if value > limit.soft_limit => {
+                    Some((LimitType::Soft, limit.soft_limit))
+                }
+                _ => None,
+            };
+            if let Some((limit_type, limit_value)) = &exceeded {
+                let (event_type, kind) = match limit_type {
+                    LimitType::Hard => (ResourceEventType::Violation, "hard"),
+                    LimitType::Soft => (ResourceEventType::Warning, "soft"),
+                };
+                let description = format!(
+                    "Function {function_name} exceeded the {kind} {resource_type:?} limit of quota '{}': {value} > {limit_value}",
+                    quota.name
+                );
+                match limit_type {
+                    LimitType::Hard => tracing::warn!("{}", description),
+                    LimitType::Soft => tracing::info!("{}", description),
+                }
+                events.push(ResourceEvent {
+                    event_id: scru128::new_string(),
+                    process_id,
+                    function_name: function_name.to_string(),
+                    resource_type: resource_type.clone(),
+                    event_type,
+                    current_usage: value,
+                    limit_value: *limit_value,
+                    timestamp: now,
+                    description,
+                });
+            }
+            usage.insert(
+                resource_type.clone(),
+                ResourceUsage {
+                    resource_type: resource_type.clone(),
+                    current_usage: value,
+                    peak_usage: value,
+                    average_usage: value as f64,
+                    last_updated: now,
+                    is_exceeded: exceeded.is_some(),
+                    exceeded_type: exceeded.map(|(limit_type, _)| limit_type),
+                },
+            );
+        }
+
+        if !events.is_empty() {
+            let mut history = self.event_history.write().await;
+            history.extend(events);
+            let overflow = history.len().saturating_sub(EVENT_HISTORY_LIMIT);
+            history.drain(..overflow);
+        }
+
+        let summary = ResourceSummary {
+            process_id,
+            function_name: function_name.to_string(),
+            total_duration_ms: duration_ms,
+            resource_usage: usage,
+            quota_name: quota.name.clone(),
+            monitoring_ended_at: now,
+        };
+        let mut usage_history = self.usage_history.write().await;
+        let recent = usage_history.entry(quota.name.clone()).or_default();
+        if recent.len() == USAGE_HISTORY_LIMIT {
+            recent.pop_front();
+        }
+        recent.push_back(summary.clone());
+        summary
+    }
+
+    /// 汇总最近在该配额下运行的执行，限制取配额的当前定义
+    pub async fn quota_usage(&self, name: &str) -> crate::functions::Result<QuotaUsage> {
+        let quota = self
+            .get_quota(name)
+            .await
+            .ok_or_else(|| FluxError::QuotaNotFound {
+                name: name.to_string(),
+            })?;
+        let usage_history = self.usage_history.read().await;
+        let recent = usage_history.get(name);
+        let summaries = recent.into_iter().flatten();
+
+        let mut usage = QuotaUsage {
+            quota_name: quota.name.clone(),
+            executions: recent.map_or(0, VecDeque::len),
+            violations: 0,
+            warnings: 0,
+            since: recent
+                .and_then(|recent| recent.front())
+                .map(|s| s.monitoring_ended_at),
+            resources: HashMap::new(),
+        };
+        let mut totals: HashMap<ResourceType, (u64, u64, usize)> = HashMap::new();
+        for summary in summaries {
+            let exceeded = |limit_type: LimitType| {
+                summary
+                    .resource_usage
+                    .values()
+                    .any(|usage| usage.exceeded_type.as_ref() == Some(&limit_type))
+            };
+            if exceeded(LimitType::Hard) {
+                usage.violations += 1;
+            } else if exceeded(LimitType::Soft) {
+                usage.warnings += 1;
+            }
+            for (resource_type, resource) in &summary.resource_usage {
+                let (peak, sum, count) = totals.entry(resource_type.clone()).or_default();
+                *peak = (*peak).max(resource.peak_usage);
+                *sum += resource.peak_usage;
+                *count += 1;
+            }
+        }
+
+        for (resource_type, (peak, sum, count)) in totals {
+            let limit = quota.active_limit(&resource_type);
+            usage.resources.insert(
+                resource_type,
+                ResourceUsageAggregate {
+                    peak_usage: peak,
+                    average_usage: sum as f64 / count as f64,
+                    soft_limit: limit.map(|limit| limit.soft_limit),
+                    hard_limit: limit.map(|limit| limit.hard_limit),
+                    peak_ratio: limit.map(|limit| peak as f64 / limit.hard_limit as f64),
+                },
+            );
+        }
+        Ok(usage)
+    }
+
+    /// 最近的资源事件（按时间先后）
+    pub async fn recent_events(&self) -> Vec<ResourceEvent> {
+        self.event_history.read().await.clone()
+    }
+
     /// 开始监控进程
     pub async fn start_monitoring(
         &self,
@@ -654,4 +970,114 @@ mod tests {
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap().name, "high-memory");
     }
+
+    fn memory_quota(name: &str, soft_limit: u64, hard_limit: u64) -> ResourceQuota {
+        ResourceQuota {
+            name: name.to_string(),
+            limits: HashMap::from([(
+                ResourceType::Memory,
+                ResourceLimit {
+                    soft_limit,
+                    hard_limit,
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_quota_validation() {
+        assert!(ResourceQuota::default().validate().is_ok());
+        assert!(memory_quota("tight", 10, 20).validate().is_ok());
+
+        let invalid = [
+            memory_quota("", 10, 20),
+            memory_quota("no spaces", 10, 20),
+            memory_quota("tight", 20, 20),
+            memory_quota("tight", 30, 20),
+            ResourceQuota {
+                time_window_secs: 0,
+                ..memory_quota("tight", 10, 20)
+            },
+        ];
+        for quota in invalid {
+            assert!(quota.validate().is_err(), "{quota:?}");
+        }
+
+        let mut quota = memory_quota("tight", 10, 20);
+        quota
+            .limits
+            .get_mut(&ResourceType::Memory)
+            .unwrap()
+            .check_interval_ms = 0;
+        assert!(quota.validate().unwrap_err().contains("check_interval_ms"));
+        quota
+            .limits
+            .get_mut(&ResourceType::Memory)
+            .unwrap()
+            .resource_type = ResourceType::Cpu;
+        assert!(quota.validate().unwrap_err().contains("declares"));
+    }
+
+    #[tokio::test]
+    async fn test_quota_catalog_and_usage() {
+        let manager = ResourceManager::new();
+        manager
+            .create_quota(memory_quota("tight", 100, 200))
+            .await
+            .unwrap();
+        assert!(matches!(
+            manager.create_quota(memory_quota("tight", 100, 200)).await,
+            Err(FluxError::QuotaAlreadyExists { .. })
+        ));
+        assert!(matches!(
+            manager
+                .update_quota(memory_quota("missing", 100, 200))
+                .await,
+            Err(FluxError::QuotaNotFound { .. })
+        ));
+        assert!(manager.delete_quota("default").await.is_err());
+        let names: Vec<_> = manager
+            .list_quotas()
+            .await
+            .into_iter()
+            .map(|quota| quota.name)
+            .collect();
+        assert_eq!(names, ["default", "tight"]);
+
+        // 按执行时的定义检查：同样的峰值在放宽限制前后分别记为违规和正常
+        let samples = HashMap::from([(ResourceType::Memory, 150)]);
+        for (soft_limit, hard_limit) in [(50, 100), (100, 200), (200, 400)] {
+            manager
+                .update_quota(memory_quota("tight", soft_limit, hard_limit))
+                .await
+                .unwrap();
+            let quota = manager.get_quota("tight").await.unwrap();
+            manager
+                .record_execution(0, "hungry", &quota, 10, &samples)
+                .await;
+        }
+
+        let usage = manager.quota_usage("tight").await.unwrap();
+        assert_eq!(
+            (usage.executions, usage.violations, usage.warnings),
+            (3, 1, 1)
+        );
+        let memory = &usage.resources[&ResourceType::Memory];
+        assert_eq!(memory.peak_usage, 150);
+        assert_eq!(memory.hard_limit, Some(400));
+        assert_eq!(memory.peak_ratio, Some(0.375));
+
+        let events = manager.recent_events().await;
+        assert_eq!(events.len(), 2);
+        assert!(
+            events[0]
+                .description
+                .contains("hard Memory limit of quota 'tight'")
+        );
+
+        manager.delete_quota("tight").await.unwrap();
+        assert!(manager.quota_usage("tight").await.is_err());
+    }
 }
@@ -0,0 +1,197 @@
+//! 执行统计的累计量
+//!
+//! [`RollingStats`] 用 Welford 算法累计均值和方差，样本数再多也不会像
+//! `(avg * (n - 1) + x) / n` 那样随累计误差漂移；最小值和最大值在没有样本时为 `None`，
+//! 0ms 的执行也是合法的最小值；计数饱和而不溢出。实例统计、池统计、进程级执行器统计和
+//! 性能监控都用它计算平均、最小和最大耗时，API 中原有的字段由它导出。
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 饱和递增计数器（长期运行也不会溢出回绕）
+pub fn bump(counter: &mut u64) {
+    *counter = counter.saturating_add(1);
+}
+
+/// 样本的计数、均值、方差和最值
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RollingStats {
+    count: u64,
+    mean: f64,
+    /// 与均值之差的平方和（Welford 算法的 M2）
+    m2: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl RollingStats {
+    /// 记录一个样本（非有限值被忽略）
+    pub fn record(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        // 计数饱和后样本的权重不再变化，均值仍保持有限
+        self.count = self.count.saturating_add(1);
+        let n = self.count as f64;
+        let delta = value - self.mean;
+        self.mean += delta / n;
+        self.m2 += delta * (value - self.mean);
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    /// 以毫秒记录一个耗时样本
+    pub fn record_duration(&mut self, duration: Duration) {
+        self.record(duration.as_secs_f64() * 1000.0);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// 均值，没有样本时为 0
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// 样本方差，少于两个样本时为 0
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            (self.m2 / (self.count - 1) as f64).max(0.0)
+        }
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+
+    /// 均值对应的耗时（样本以毫秒记录时）
+    pub fn mean_duration(&self) -> Duration {
+        Duration::from_secs_f64(self.mean.max(0.0) / 1000.0)
+    }
+
+    /// 最小值取整为 `u64`，没有样本时为 0（兼容原有的整数毫秒字段）
+    pub fn min_u64(&self) -> u64 {
+        self.min.map_or(0, |min| min.max(0.0) as u64)
+    }
+
+    /// 最大值取整为 `u64`，没有样本时为 0
+    pub fn max_u64(&self) -> u64 {
+        self.max.map_or(0, |max| max.max(0.0) as u64)
+    }
+
+    /// 用于 API 输出的快照
+    pub fn snapshot(&self) -> RollingSnapshot {
+        RollingSnapshot {
+            count: self.count,
+            mean: self.mean,
+            std_dev: self.std_dev(),
+            min: self.min,
+            max: self.max,
+        }
+    }
+}
+
+/// [`RollingStats`] 的可序列化快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RollingSnapshot {
+    pub count: u64,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// 没有样本时为 `null`
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 确定性的伪随机数（xorshift64*），不依赖外部 crate
+    struct Samples(u64);
+
+    impl Samples {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+    }
+
+    #[test]
+    fn test_empty_and_zero_samples() {
+        let mut stats = RollingStats::default();
+        assert_eq!((stats.count(), stats.mean()), (0, 0.0));
+        assert_eq!((stats.min(), stats.max()), (None, None));
+        assert_eq!((stats.min_u64(), stats.max_u64()), (0, 0));
+
+        // 0ms 是合法的最小值，不是“尚无样本”的标记
+        stats.record(5.0);
+        stats.record(0.0);
+        stats.record(f64::NAN);
+        assert_eq!(stats.count(), 2);
+        assert_eq!(stats.min(), Some(0.0));
+        assert_eq!(stats.max_u64(), 5);
+        assert_eq!(stats.mean(), 2.5);
+        assert_eq!(stats.variance(), 12.5);
+        let snapshot = serde_json::to_value(stats.snapshot()).unwrap();
+        assert_eq!(snapshot["min"], 0.0);
+    }
+
+    #[test]
+    fn test_ten_million_samples_match_reference() {
+        let mut stats = RollingStats::default();
+        let mut samples = Samples(0x9e37_79b9_7f4a_7c15);
+        // 参考值用整数精确累计
+        let (mut sum, mut sum_sq, mut min, mut max) = (0u128, 0u128, u64::MAX, 0u64);
+        const N: u64 = 10_000_000;
+        for _ in 0..N {
+            // 0..=60000ms 的执行时间，偏向短耗时
+            let value = (samples.next() % 60_001) * (samples.next() % 4) / 3;
+            stats.record(value as f64);
+            sum += value as u128;
+            sum_sq += (value as u128) * (value as u128);
+            min = min.min(value);
+            max = max.max(value);
+        }
+
+        let mean = sum as f64 / N as f64;
+        let variance = (sum_sq as f64 - sum as f64 * mean) / (N - 1) as f64;
+        assert_eq!(stats.count(), N);
+        assert!(
+            (stats.mean() - mean).abs() <= mean * 1e-9,
+            "{}",
+            stats.mean()
+        );
+        assert!((stats.variance() - variance).abs() <= variance * 1e-6);
+        assert_eq!(stats.min(), Some(min as f64));
+        assert_eq!(stats.max(), Some(max as f64));
+        assert!(stats.snapshot().std_dev.is_finite());
+    }
+
+    #[test]
+    fn test_counts_saturate_instead_of_overflowing() {
+        let mut stats = RollingStats {
+            count: u64::MAX - 1,
+            mean: 10.0,
+            ..Default::default()
+        };
+        for _ in 0..3 {
+            stats.record(u64::MAX as f64);
+        }
+        assert_eq!(stats.count(), u64::MAX);
+        assert!(stats.mean().is_finite() && stats.variance().is_finite());
+        assert_eq!(stats.max_u64(), u64::MAX);
+    }
+}
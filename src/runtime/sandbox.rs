@@ -1,22 +1,45 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
-use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tempfile::TempDir;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio::process::{Child, Command as TokioCommand};
 use tokio::sync::{Mutex, RwLock};
 use tokio::time::timeout;
-
-use crate::functions::{ExecutionStatus, InvokeRequest};
+use tracing::Instrument;
+
+use crate::functions::context::InvocationContext;
+use crate::functions::package::FunctionPackage;
+use crate::functions::redaction::{Redactor, truncate_for_log};
+use crate::functions::{
+    ErrorKind, ErrorOrigin, ExecutionStatus, FluxError, InvocationTiming, InvokeRequest,
+    ProcessTermination, ResourceKind,
+};
 use crate::runtime::compiler::CompiledFunction;
-
-/// 沙箱配置
+use crate::runtime::cpu::{self, CpuSampler};
+use crate::runtime::debug_capture::DebugTrace;
+use crate::runtime::disk::{DiskSampler, DiskUsage};
+use crate::runtime::environment::{ResolvedRuntime, RuntimeEnvironment};
+use crate::runtime::events::{InstanceLifecycleEvent, LifecycleEventStream, LifecycleEventType};
+use crate::runtime::execution_gate::{ExecutionGate, ExecutionSlot, ExecutorStats};
+use crate::runtime::isolation::{Confinement, IsolationLevel, ROOT_MOUNT_DIR};
+use crate::runtime::platform::{self, ResourceMonitoring};
+use crate::runtime::resource::{ResourceQuota, ResourceType};
+use crate::runtime::scratch::{self, ARTIFACTS_DIR, SCRATCH_ENV, ScratchStore};
+use crate::runtime::script_cache::{ScriptCache, ScriptLanguage, WrappedScript, script_stdin};
+use crate::runtime::workers::{
+    ScriptWorker, ScriptWorkers, WorkerConfig, WorkerKey, WorkerOutcome,
+};
+use crate::tunables::Tunables;
+
+/// 沙箱配置（`[sandbox]`）
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SandboxConfig {
     /// 是否启用进程隔离
     pub enable_process_isolation: bool,
@@ -42,6 +65,21 @@ pub struct SandboxConfig {
     pub temp_root: PathBuf,
     /// 自定义Rust编译目标路径
     pub rust_target_dir: Option<PathBuf>,
+    /// 同时运行的沙箱执行数上限
+    pub max_concurrent_executions: usize,
+    /// 等待执行名额的队列长度上限，为 0 时超出并发上限直接拒绝
+    pub max_queued_executions: usize,
+    /// 排队等待的最长时间（毫秒），缺省只受函数执行超时限制
+    pub queue_timeout_ms: Option<u64>,
+    /// 函数代码的字节数上限，注册和包装脚本前检查
+    #[serde(default = "default_max_code_bytes")]
+    pub max_code_bytes: usize,
+    /// 每次调用的暂存目录大小上限（MB），函数的 `scratch_limit_mb` 可以覆盖
+    pub scratch_limit_mb: u64,
+    /// 设置了 `persist_scratch` 的函数保留暂存文件的时长（秒）
+    pub scratch_ttl_secs: u64,
+    /// 通过 `/executions/:id/artifacts` 下载的单个暂存文件的字节上限
+    pub max_artifact_download_bytes: u64,
 }
 
 impl Default for SandboxConfig {
@@ -56,9 +94,381 @@ impl Default for SandboxConfig {
             allow_filesystem: false,
             allowed_dirs: vec![],
             work_dir: None,
-            allowed_env_vars: vec!["PATH".to_string()],
-            temp_root: PathBuf::from("/tmp/flux_sandbox"),
+            allowed_env_vars: default_env_vars(),
+            temp_root: default_temp_root(),
             rust_target_dir: None,
+            max_concurrent_executions: 16,
+            max_queued_executions: 64,
+            queue_timeout_ms: None,
+            max_code_bytes: DEFAULT_MAX_CODE_BYTES,
+            scratch_limit_mb: 64,
+            scratch_ttl_secs: 3600,
+            max_artifact_download_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl SandboxConfig {
+    /// 执行超时、内存和 CPU 上限必须为正数
+    pub fn validate(&self) -> Result<()> {
+        if self.execution_timeout_secs == 0 || self.max_memory_mb == 0 {
+            anyhow::bail!("sandbox: execution_timeout_secs and max_memory_mb must be positive");
+        }
+        if !(self.max_cpu_percent.is_finite() && self.max_cpu_percent > 0.0) {
+            anyhow::bail!("sandbox: max_cpu_percent must be positive");
+        }
+        if self.scratch_limit_mb == 0 {
+            anyhow::bail!("sandbox: scratch_limit_mb must be positive");
+        }
+        Ok(())
+    }
+}
+
+/// 函数代码字节数的默认上限
+pub const DEFAULT_MAX_CODE_BYTES: usize = 8 * 1024 * 1024;
+
+fn default_max_code_bytes() -> usize {
+    DEFAULT_MAX_CODE_BYTES
+}
+
+/// 默认临时目录根路径：Unix 上为 `/tmp/flux_sandbox`，其他平台位于系统临时目录下
+fn default_temp_root() -> PathBuf {
+    if cfg!(unix) {
+        PathBuf::from("/tmp/flux_sandbox")
+    } else {
+        std::env::temp_dir().join("flux_sandbox")
+    }
+}
+
+/// 默认保留的环境变量（Windows 上的进程需要 SystemRoot 加载系统库）
+fn default_env_vars() -> Vec<String> {
+    let mut vars = vec!["PATH".to_string()];
+    if cfg!(windows) {
+        vars.push("SystemRoot".to_string());
+    }
+    vars
+}
+
+/// 包装脚本和编译函数的执行器在调用函数体前向标准错误写入的标记行：`<标记> <Unix 微秒>`
+pub const BODY_START_MARKER: &str = "__FLUX_BODY_START__";
+
+/// 取出标准错误中的函数体开始标记，返回去掉标记行后的标准错误和标记的时刻
+fn take_body_start(stderr: &str) -> (String, Option<SystemTime>) {
+    let mut started = None;
+    let mut rest = String::with_capacity(stderr.len());
+    for line in stderr.split_inclusive('\n') {
+        match line.trim_end().strip_prefix(BODY_START_MARKER) {
+            Some(micros) if started.is_none() => {
+                started = Some(
+                    micros
+                        .trim()
+                        .parse()
+                        .map(|micros| UNIX_EPOCH + Duration::from_micros(micros))
+                        .ok(),
+                );
+            }
+            _ => rest.push_str(line),
+        }
+    }
+    (rest, started.flatten())
+}
+
+/// 脚本进程的标准输入
+#[derive(Clone, Copy)]
+enum ScriptStdin<'a> {
+    /// 原样写入（`None` 时为空）
+    Raw(Option<&'a [u8]>),
+    /// 函数调用的输入和上下文，创建暂存目录后再渲染（见 [`script_stdin`]）
+    Invocation(&'a serde_json::Value, &'a InvocationContext),
+}
+
+/// 在隔离目录中创建本次调用的暂存目录，返回带暂存目录路径的上下文
+fn with_scratch(jail: &Path, context: &InvocationContext) -> Result<InvocationContext> {
+    let dir = scratch::create(jail).context("Failed to create scratch dir")?;
+    Ok(context.clone().with_scratch_dir(&dir))
+}
+
+/// 包装脚本成功时总是输出 JSON（返回 `undefined`/`None` 时为 `null`），
+/// 没有输出说明脚本在调用处理函数之前就退出了，按运行时错误处理
+fn require_wrapper_output(result: &mut SandboxResult) {
+    if result.status.is_success() && result.stdout.trim().is_empty() {
+        let message = "Handler produced no result: the script exited before writing its output";
+        result.status = ExecutionStatus::error(ErrorKind::Runtime, message);
+        result.output = serde_json::json!({"error": message});
+    }
+}
+
+/// 一次沙箱执行的各阶段起点
+#[derive(Debug, Clone, Copy)]
+struct PhaseClock {
+    /// 收到执行请求
+    started: Instant,
+    /// 等待执行名额的时间
+    queued: Duration,
+    /// 启动进程的时刻（单调时钟和系统时钟各一份，后者与进程写出的标记比较）
+    spawned: Instant,
+    spawned_wall: SystemTime,
+}
+
+impl PhaseClock {
+    /// 准备隔离目录（编译函数还包括构建执行器）到函数体开始都计为启动；
+    /// 进程没有写出开始标记时启动和执行无法区分，排队之外的时间都计为执行
+    fn timing(&self, body_started: Option<SystemTime>) -> InvocationTiming {
+        let total_ms = self.started.elapsed().as_millis() as u64;
+        let queue_ms = (self.queued.as_millis() as u64).min(total_ms);
+        let spawn_ms = body_started.map(|at| {
+            let prepare = self
+                .spawned
+                .duration_since(self.started)
+                .saturating_sub(self.queued);
+            let startup = at.duration_since(self.spawned_wall).unwrap_or_default();
+            ((prepare + startup).as_millis() as u64).min(total_ms - queue_ms)
+        });
+        InvocationTiming {
+            queue_ms: Some(queue_ms),
+            compile_ms: None,
+            spawn_ms,
+            execute_ms: Some(total_ms - queue_ms - spawn_ms.unwrap_or(0)),
+            network_ms: None,
+            total_ms,
+            cpu_ms: None,
+            instance: None,
+        }
+    }
+}
+
+/// 代理相关的环境变量，禁止网络时总是移除
+///
+/// Linux 上可用命名空间隔离时进程处于只有回环接口的网络命名空间中，直接连接同样失败；
+/// 仅进程隔离时这只能阻止遵循代理配置的客户端。
+pub const PROXY_ENV_VARS: &[&str] = &[
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "ALL_PROXY",
+    "NO_PROXY",
+    "http_proxy",
+    "https_proxy",
+    "all_proxy",
+    "no_proxy",
+];
+
+/// 传给函数进程的剩余执行预算（毫秒），函数可以据此在超时前自行结束
+pub const DEADLINE_ENV: &str = "FLUX_DEADLINE_MS";
+
+/// 严格模式（控制台执行的未审核代码）的内存上限（MB）
+pub const STRICT_MAX_MEMORY_MB: u64 = 128;
+
+/// 严格模式的 CPU 使用率上限（百分比）
+pub const STRICT_MAX_CPU_PERCENT: f64 = 50.0;
+
+/// 执行期间采样磁盘用量（暂存目录大小和写入字节数）的间隔
+const DISK_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 执行期间采样累计 CPU 时间的间隔
+const CPU_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 超时后请求进程退出到强制终止之间的宽限期
+const TERMINATION_GRACE: Duration = Duration::from_secs(1);
+
+/// 指向隔离目录的主目录和临时目录变量（Windows 上的运行时按 USERPROFILE/TEMP/TMP 查找）
+const JAIL_HOME_VARS: &[&str] = if cfg!(windows) {
+    &["HOME", "TMPDIR", "USERPROFILE", "TEMP", "TMP"]
+} else {
+    &["HOME", "TMPDIR"]
+};
+
+/// 单次沙箱执行的资源限制
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxLimits {
+    /// 执行超时时间（秒）
+    pub execution_timeout_secs: u64,
+    /// 最大内存使用（MB）
+    pub max_memory_mb: u64,
+    /// 最大CPU使用率（百分比，超出只告警）
+    pub max_cpu_percent: f64,
+    /// 限制来自的资源配额（超限终止时写入错误信息）
+    pub quota_name: Option<String>,
+    /// 只有命名空间隔离可用时才执行
+    pub requires_isolation: bool,
+    /// 调用的截止时间（函数自身的超时），早于执行超时时按它终止进程
+    pub deadline: Option<Instant>,
+    /// 暂存目录的大小上限（MB）
+    pub scratch_limit_mb: u64,
+    /// 调用结束后保留暂存目录中的文件
+    pub persist_scratch: bool,
+    /// 写入磁盘的字节上限（MB），缺省不限制
+    pub max_disk_write_mb: Option<u64>,
+    /// 累计 CPU 时间的上限（毫秒），与执行超时相互独立，缺省不限制
+    pub max_cpu_time_ms: Option<u64>,
+    /// 严格模式：不论沙箱配置如何都禁止网络和 `allowed_dirs`，工作进程不复用
+    pub strict: bool,
+}
+
+impl From<&SandboxConfig> for SandboxLimits {
+    fn from(config: &SandboxConfig) -> Self {
+        Self {
+            execution_timeout_secs: config.execution_timeout_secs,
+            max_memory_mb: config.max_memory_mb,
+            max_cpu_percent: config.max_cpu_percent,
+            quota_name: None,
+            requires_isolation: false,
+            deadline: None,
+            scratch_limit_mb: config.scratch_limit_mb,
+            persist_scratch: false,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            strict: false,
+        }
+    }
+}
+
+impl SandboxLimits {
+    /// 用配额的内存和 CPU 硬限制替换对应上限，配额未启用的限制保持不变
+    pub fn with_quota(mut self, quota: &ResourceQuota) -> Self {
+        if let Some(limit) = quota.active_limit(&ResourceType::Memory) {
+            self.max_memory_mb = limit.hard_limit.div_ceil(1024 * 1024).max(1);
+        }
+        if let Some(limit) = quota.active_limit(&ResourceType::Cpu) {
+            self.max_cpu_percent = limit.hard_limit as f64;
+        }
+        self.quota_name = Some(quota.name.clone());
+        self
+    }
+
+    /// 要求命名空间隔离（函数标记了 `requires_isolation` 时）
+    pub fn with_isolation_required(mut self, required: bool) -> Self {
+        self.requires_isolation |= required;
+        self
+    }
+
+    /// 使用函数的暂存目录设置（未设置上限时保持沙箱配置的上限）
+    pub fn with_scratch(mut self, limit_mb: Option<u64>, persist: bool) -> Self {
+        if let Some(limit_mb) = limit_mb {
+            self.scratch_limit_mb = limit_mb;
+        }
+        self.persist_scratch = persist;
+        self
+    }
+
+    /// 使用函数的磁盘写入上限（函数未设置上限时保持不变）
+    pub fn with_disk_write_limit(mut self, limit_mb: Option<u64>) -> Self {
+        if limit_mb.is_some() {
+            self.max_disk_write_mb = limit_mb;
+        }
+        self
+    }
+
+    /// 使用函数的 CPU 时间上限（函数未设置上限时保持不变）
+    pub fn with_cpu_time_limit(mut self, limit_ms: Option<u64>) -> Self {
+        if limit_ms.is_some() {
+            self.max_cpu_time_ms = limit_ms;
+        }
+        self
+    }
+
+    /// 使用最严格的沙箱配置（控制台执行的未审核代码）：内存和 CPU 上限不超过
+    /// [`STRICT_MAX_MEMORY_MB`] 和 [`STRICT_MAX_CPU_PERCENT`]，不保留暂存文件
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        if strict {
+            self.strict = true;
+            self.max_memory_mb = self.max_memory_mb.min(STRICT_MAX_MEMORY_MB);
+            self.max_cpu_percent = self.max_cpu_percent.min(STRICT_MAX_CPU_PERCENT);
+            self.persist_scratch = false;
+        }
+        self
+    }
+
+    /// 暂存目录的字节上限
+    fn scratch_limit_bytes(&self) -> u64 {
+        self.scratch_limit_mb.saturating_mul(1024 * 1024)
+    }
+
+    /// 按调用的截止时间收紧超时（已有更早的截止时间时保持不变）
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(
+            self.deadline
+                .map_or(deadline, |current| current.min(deadline)),
+        );
+        self
+    }
+
+    /// 扣除排队时间后进程可以运行的时间：执行超时和截止时间中较早的一个
+    fn budget(&self, queued: Duration) -> Duration {
+        let budget = Duration::from_secs(self.execution_timeout_secs).saturating_sub(queued);
+        match self.deadline {
+            Some(deadline) => budget.min(deadline.saturating_duration_since(Instant::now())),
+            None => budget,
+        }
+    }
+}
+
+/// 进程因超出资源限制被终止的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResourceLimit {
+    /// 执行超时
+    Timeout,
+    /// 内存超限
+    Memory,
+    /// 暂存目录超出大小上限
+    Disk,
+    /// 写入磁盘的字节数超出函数的 `max_disk_write_mb`
+    DiskWrite { written_bytes: u64, limit_mb: u64 },
+    /// 累计 CPU 时间超出函数的 `max_cpu_time_ms`
+    CpuTime { cpu_time_ms: u64, limit_ms: u64 },
+}
+
+impl ResourceLimit {
+    fn message(self) -> &'static str {
+        match self {
+            Self::Timeout => "Execution timeout",
+            Self::Memory => "Memory limit exceeded",
+            Self::Disk => "Scratch space limit exceeded",
+            Self::DiskWrite { .. } => "Disk write limit exceeded",
+            Self::CpuTime { .. } => "CPU time limit exceeded",
+        }
+    }
+
+    /// 终止原因，内存上限来自资源配额时附上配额名
+    fn describe(self, quota_name: Option<&str>) -> String {
+        match (self, quota_name) {
+            (Self::Memory, Some(quota)) => format!("{} (quota '{quota}')", self.message()),
+            (
+                Self::DiskWrite {
+                    written_bytes,
+                    limit_mb,
+                },
+                _,
+            ) => format!(
+                "{}: {written_bytes} bytes written, limit is {limit_mb}MB",
+                self.message()
+            ),
+            (
+                Self::CpuTime {
+                    cpu_time_ms,
+                    limit_ms,
+                },
+                _,
+            ) => format!(
+                "{}: {cpu_time_ms}ms of CPU time used, limit is {limit_ms}ms",
+                self.message()
+            ),
+            _ => self.message().to_string(),
+        }
+    }
+
+    /// 对应的执行状态
+    pub fn status(self) -> ExecutionStatus {
+        match self {
+            Self::Timeout => ExecutionStatus::Timeout,
+            Self::Memory => ExecutionStatus::ResourceLimitExceeded {
+                resource: ResourceKind::Memory,
+            },
+            Self::Disk | Self::DiskWrite { .. } => ExecutionStatus::ResourceLimitExceeded {
+                resource: ResourceKind::Disk,
+            },
+            Self::CpuTime { .. } => ExecutionStatus::ResourceLimitExceeded {
+                resource: ResourceKind::Cpu,
+            },
         }
     }
 }
@@ -82,6 +492,89 @@ pub struct SandboxResult {
     pub stdout: String,
     /// 标准错误
     pub stderr: String,
+    /// 因超出资源限制被终止时的原因
+    pub killed_by: Option<ResourceLimit>,
+    /// 本次执行所用限制来自的资源配额
+    pub quota_name: Option<String>,
+    /// 资源监控不可用时内存和CPU统计为 0，内存上限不生效
+    pub resource_monitoring: ResourceMonitoring,
+    /// 进程实际使用的隔离方式（未启动进程时为 `process-only`）
+    pub isolation_level: IsolationLevel,
+    /// 排队、启动和函数体执行的耗时
+    pub timing: InvocationTiming,
+    /// 进程被终止时是否强制终止以及已运行的时间
+    pub termination: Option<ProcessTermination>,
+    /// 磁盘用量的峰值（常驻工作进程执行的调用和未启动进程时为 `None`）
+    pub disk: Option<DiskUsage>,
+    /// 进程及其子进程累计使用的 CPU 时间（毫秒，见 [`crate::runtime::cpu`]），未启动进程时为 `None`
+    pub cpu_time_ms: Option<u64>,
+}
+
+impl SandboxResult {
+    /// 因资源限制被终止的结果（编译函数和脚本共用同一结构）
+    fn killed(
+        limit: ResourceLimit,
+        quota_name: Option<&str>,
+        execution_time_ms: u64,
+        peak_memory_bytes: u64,
+        stdout: String,
+    ) -> Self {
+        let message = limit.describe(quota_name);
+        Self {
+            status: limit.status(),
+            output: serde_json::json!({"error": message}),
+            execution_time_ms,
+            peak_memory_bytes,
+            cpu_usage_percent: 0.0,
+            exit_code: Some(-1),
+            stdout,
+            stderr: message,
+            killed_by: Some(limit),
+            quota_name: quota_name.map(str::to_string),
+            resource_monitoring: ResourceMonitoring::current(),
+            isolation_level: IsolationLevel::ProcessOnly,
+            timing: InvocationTiming {
+                total_ms: execution_time_ms,
+                ..Default::default()
+            },
+            termination: None,
+            disk: None,
+            cpu_time_ms: None,
+        }
+    }
+
+    /// 从标准输出/错误、失败信息和输出中的字符串抹去 `redactor` 记下的敏感值
+    pub fn scrubbed(mut self, redactor: &Redactor) -> Self {
+        self.stdout = redactor.scrub(&self.stdout);
+        self.stderr = redactor.scrub(&self.stderr);
+        if let ExecutionStatus::Error { message, .. } = &mut self.status {
+            *message = redactor.scrub(message);
+        }
+        if let serde_json::Value::Object(output) = &mut self.output
+            && let Some(serde_json::Value::String(error)) = output.get_mut("error")
+        {
+            *error = redactor.scrub(error);
+        }
+        self
+    }
+
+    /// 排队等待执行名额时耗尽了执行超时预算
+    fn expired_in_queue(start_time: Instant) -> Self {
+        let result = Self::killed(
+            ResourceLimit::Timeout,
+            None,
+            start_time.elapsed().as_millis() as u64,
+            0,
+            String::new(),
+        );
+        Self {
+            timing: InvocationTiming {
+                queue_ms: Some(result.execution_time_ms),
+                ..result.timing.clone()
+            },
+            ..result
+        }
+    }
 }
 
 /// 进程监控信息
@@ -97,6 +590,8 @@ pub struct ProcessMonitor {
     pub cpu_usage: f64,
     /// 是否仍在运行
     pub is_running: bool,
+    /// 进程的隔离工作目录
+    pub work_dir: PathBuf,
 }
 
 /// 沙箱隔离执行器
@@ -110,6 +605,20 @@ pub struct SandboxExecutor {
     system_monitor: Arc<Mutex<sysinfo::System>>,
     /// 临时目录管理
     temp_dirs: Arc<RwLock<Vec<TempDir>>>,
+    /// 按内容哈希缓存的脚本（`temp_root/scripts`）
+    scripts: ScriptCache,
+    /// 全局并发上限和等待队列
+    gate: ExecutionGate,
+    /// 缓存的解释器路径和版本
+    environment: Arc<RuntimeEnvironment>,
+    /// 启动时探测到的隔离方式
+    isolation: IsolationLevel,
+    /// 定义了 `init`/`teardown` 钩子的脚本函数的常驻工作进程
+    workers: Arc<ScriptWorkers>,
+    /// 可重新加载的资源上限，设置后替换配置中的执行超时、内存和 CPU 上限
+    tunables: Option<Arc<Tunables>>,
+    /// 保留的暂存目录（`temp_root/artifacts`）
+    scratch: Arc<ScratchStore>,
 }
 
 impl SandboxExecutor {
@@ -121,33 +630,164 @@ impl SandboxExecutor {
 
         let mut system = sysinfo::System::new_all();
         system.refresh_all();
+        if !ResourceMonitoring::current().is_supported() {
+            tracing::warn!(
+                "Resource monitoring is not supported on this platform, memory limits will not be enforced"
+            );
+        }
+        let isolation = IsolationLevel::detect();
+        if !isolation.is_confined() {
+            tracing::warn!(
+                "!!! Namespace isolation is unavailable, sandboxed functions run with process-only \
+                 isolation: allow_network=false and allowed_dirs are NOT enforced, and functions \
+                 with requires_isolation will be refused !!!"
+            );
+        }
 
         Ok(Self {
+            scripts: ScriptCache::new(config.temp_root.join("scripts")),
+            scratch: Arc::new(ScratchStore::new(
+                config.temp_root.join(ARTIFACTS_DIR),
+                Duration::from_secs(config.scratch_ttl_secs),
+                config.max_artifact_download_bytes,
+            )),
+            gate: ExecutionGate::new(
+                config.max_concurrent_executions,
+                config.max_queued_executions,
+                config.queue_timeout_ms.map(Duration::from_millis),
+            ),
             config,
             active_processes: Arc::new(RwLock::new(HashMap::new())),
             system_monitor: Arc::new(Mutex::new(system)),
             temp_dirs: Arc::new(RwLock::new(Vec::new())),
+            environment: Arc::new(RuntimeEnvironment::default()),
+            isolation,
+            workers: Arc::new(ScriptWorkers::new(WorkerConfig::default())),
+            tunables: None,
         })
     }
 
+    /// 使用共享的运行时环境（与 `/status` 和刷新接口共用同一份探测结果）
+    pub fn with_environment(mut self, environment: Arc<RuntimeEnvironment>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// 每次执行时从可重新加载的配置读取执行超时、内存和 CPU 上限
+    pub fn with_tunables(mut self, tunables: Arc<Tunables>) -> Self {
+        self.tunables = Some(tunables);
+        self
+    }
+
+    /// 工作进程的创建、就绪、初始化失败和停止发布到该事件流
+    pub fn with_lifecycle_events(self, events: Arc<LifecycleEventStream>) -> Self {
+        self.workers.attach_events(events);
+        self
+    }
+
+    /// 定义了钩子的脚本函数的工作进程池
+    pub fn workers(&self) -> &Arc<ScriptWorkers> {
+        &self.workers
+    }
+
+    /// 运行时探测结果
+    /// 函数代码的字节数上限
+    pub fn max_code_bytes(&self) -> usize {
+        self.config.max_code_bytes
+    }
+
+    /// 代码超过 `max_code_bytes` 时返回 `CodeTooLarge`，在包装脚本之前检查
+    fn check_code_size(&self, source: &str) -> Result<()> {
+        if source.len() > self.config.max_code_bytes {
+            return Err(FluxError::CodeTooLarge {
+                size: source.len(),
+                limit: self.config.max_code_bytes,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn environment(&self) -> &Arc<RuntimeEnvironment> {
+        &self.environment
+    }
+
+    /// 脚本缓存
+    pub fn script_cache(&self) -> &ScriptCache {
+        &self.scripts
+    }
+
+    /// 保留的暂存目录
+    pub fn scratch_store(&self) -> &Arc<ScratchStore> {
+        &self.scratch
+    }
+
+    /// 沙箱进程使用的隔离方式
+    pub fn isolation_level(&self) -> IsolationLevel {
+        self.isolation
+    }
+
+    /// 并发执行统计（运行中、排队中和被拒绝的执行数）
+    pub fn get_executor_stats(&self) -> ExecutorStats {
+        self.gate.stats()
+    }
+
+    /// 默认资源限制：沙箱配置，或可重新加载的配置的当前值
+    pub fn default_limits(&self) -> SandboxLimits {
+        let limits = SandboxLimits::from(&self.config);
+        match &self.tunables {
+            Some(tunables) => tunables.current().sandbox.apply(limits),
+            None => limits,
+        }
+    }
+
     /// 在沙箱中执行编译后的函数
     pub async fn execute_in_sandbox(
         &self,
         compiled: &CompiledFunction,
         request: &InvokeRequest,
+    ) -> Result<SandboxResult> {
+        self.execute_in_sandbox_with_limits(compiled, request, &self.default_limits())
+            .await
+    }
+
+    /// 按指定资源限制（例如函数选择的资源配额）在沙箱中执行编译后的函数
+    #[tracing::instrument(name = "sandbox", skip_all, fields(function = %compiled.metadata.name))]
+    pub async fn execute_in_sandbox_with_limits(
+        &self,
+        compiled: &CompiledFunction,
+        request: &InvokeRequest,
+        limits: &SandboxLimits,
     ) -> Result<SandboxResult> {
         let start_time = Instant::now();
+        let limits = &limits
+            .clone()
+            .with_isolation_required(compiled.metadata.requires_isolation)
+            // 这里的调用 ID 由沙箱生成，调用方无从查询，因此不保留暂存目录
+            .with_scratch(compiled.metadata.scratch_limit_mb, false)
+            .with_disk_write_limit(compiled.metadata.max_disk_write_mb)
+            .with_cpu_time_limit(compiled.metadata.max_cpu_time_ms)
+            .with_deadline(start_time + Duration::from_millis(compiled.metadata.timeout_ms));
+        let budget = limits.budget(Duration::ZERO);
+        let Some(slot) = self.gate.acquire(budget).await? else {
+            return Ok(SandboxResult::expired_in_queue(start_time));
+        };
 
-        if self.config.enable_container_isolation {
+        let result = if self.config.enable_container_isolation {
             // 容器化执行
-            self.execute_in_container(compiled, request, start_time)
+            self.execute_in_container(compiled, request, limits, start_time, slot.waited())
                 .await
         } else if self.config.enable_process_isolation {
             // 进程隔离执行
-            self.execute_in_process(compiled, request, start_time).await
+            self.execute_in_process(compiled, request, limits, start_time, slot.waited())
+                .await
         } else {
-            return Err(anyhow::anyhow!("No isolation method enabled"));
-        }
+            Err(anyhow::anyhow!("No isolation method enabled"))
+        };
+
+        // 捕获的标准输出/错误可能回显输入中的敏感值，返回前按函数的脱敏规则抹去
+        let redactor = Redactor::for_function(&compiled.metadata).with_secrets_from(&request.input);
+        result.map(|result| result.scrubbed(&redactor))
     }
 
     /// 在独立进程中执行函数
@@ -155,10 +795,12 @@ impl SandboxExecutor {
         &self,
         compiled: &CompiledFunction,
         request: &InvokeRequest,
+        limits: &SandboxLimits,
         start_time: Instant,
+        queued: Duration,
     ) -> Result<SandboxResult> {
         // 创建安全的临时工作目录
-        let temp_dir = self.create_secure_temp_dir().await?;
+        let temp_dir = self.prepare_jail(limits).await?;
         let work_dir = temp_dir.path();
 
         // 复制动态库到安全目录
@@ -177,78 +819,1004 @@ impl SandboxExecutor {
             .create_function_executor(&secure_lib_path, work_dir)
             .await?;
 
-        // 准备输入数据
+        // 准备输入数据和调用上下文（截止时间为执行超时和函数超时中较早的一个）
         let input_json =
             serde_json::to_string(&request.input).context("Failed to serialize input")?;
+        let context = InvocationContext::new(&compiled.metadata, scru128::new_string())
+            .with_deadline(start_time + limits.budget(Duration::ZERO));
+        let context = with_scratch(work_dir, &context)?;
+        let context_json =
+            serde_json::to_string(&context.snapshot()).context("Failed to serialize context")?;
+
+        tracing::info!(
+            "Starting sandboxed process for function: {}",
+            compiled.metadata.name
+        );
+
+        self.run_in_jail(
+            temp_dir,
+            executor_path.as_os_str(),
+            &[input_json, context_json],
+            &[],
+            None,
+            Some(&context),
+            limits,
+            start_time,
+            queued,
+        )
+        .await
+    }
+
+    /// 在沙箱中执行任意命令（如 node/python 解释器），与编译函数使用同样的
+    /// 临时目录隔离、环境变量过滤、资源监控和超时终止逻辑
+    pub async fn execute_command_in_sandbox(
+        &self,
+        program: impl AsRef<OsStr>,
+        args: &[String],
+        stdin: Option<&[u8]>,
+        limits: &SandboxLimits,
+    ) -> Result<SandboxResult> {
+        let start_time = Instant::now();
+        let Some(slot) = self.admit(limits).await? else {
+            return Ok(SandboxResult::expired_in_queue(start_time));
+        };
+        let jail = self.prepare_jail(limits).await?;
+        self.run_in_jail(
+            jail,
+            program.as_ref(),
+            args,
+            &[],
+            stdin,
+            None,
+            limits,
+            start_time,
+            slot.waited(),
+        )
+        .await
+    }
+
+    /// 用解释器执行脚本，例如 `("python3", "main.py", source)`
+    ///
+    /// 脚本按内容哈希缓存在 `temp_root/scripts` 中，相同代码只写入一次；
+    /// 进程仍在新的隔离目录中运行，通过绝对路径读取缓存的脚本。
+    pub async fn execute_script_in_sandbox(
+        &self,
+        interpreter: &str,
+        script_name: &str,
+        script_source: &str,
+        stdin: Option<&[u8]>,
+        limits: &SandboxLimits,
+    ) -> Result<SandboxResult> {
+        let script = WrappedScript::plain(script_source);
+        self.run_script(
+            interpreter,
+            &[],
+            script_name,
+            &script,
+            ScriptStdin::Raw(stdin),
+            limits,
+        )
+        .await
+    }
+
+    /// 执行 JavaScript/Python 函数：用户代码需定义 `handler(input, context)`，
+    /// 输入和调用上下文以 JSON 经标准输入传入，不会拼接进脚本
+    ///
+    /// `runtime` 为函数选择的执行环境名，缺省时使用该语言的默认解释器。
+    pub async fn execute_function_script(
+        &self,
+        language: ScriptLanguage,
+        runtime: Option<&str>,
+        source: &str,
+        input: &serde_json::Value,
+        context: &InvocationContext,
+        limits: &SandboxLimits,
+    ) -> Result<SandboxResult> {
+        self.check_code_size(source)?;
+        let resolved = self.environment.resolve_script(language.into(), runtime)?;
+        let hooks = language.defines_hooks(source);
+        let script_name = language.script_name_for(source);
+        let script = if hooks {
+            language.wrap_worker(source)
+        } else {
+            language.wrap(source)
+        };
+        if let Some(trace) = context.debug_trace() {
+            trace.set_sources(vec![(script_name.to_string(), script.to_string())]);
+            self.trace_environment(trace, language, runtime, &resolved);
+        }
+        let limits = &limits.clone().with_deadline(context.deadline());
+        let interpreter = resolved.binary.to_string_lossy();
+        let result = if hooks {
+            let (cached, _) = self
+                .scripts
+                .script_path(&interpreter, script_name, &script)
+                .await?;
+            self.run_in_worker(&resolved, cached, input, context, limits)
+                .await?
+        } else {
+            self.run_script(
+                &interpreter,
+                &resolved.env,
+                script_name,
+                &script,
+                ScriptStdin::Invocation(input, context),
+                limits,
+            )
+            .await?
+        };
+        if let Some(trace) = context.debug_trace() {
+            trace.set_output(&result.stdout, &result.stderr);
+        }
+        Ok(result)
+    }
+
+    /// 执行多文件 JavaScript/Python 函数包：整个文件树写入缓存目录并从入口文件运行，
+    /// 因此 `require('./lib/util')` 和相对导入按包内结构解析
+    pub async fn execute_package_script(
+        &self,
+        language: ScriptLanguage,
+        runtime: Option<&str>,
+        package: &FunctionPackage,
+        input: &serde_json::Value,
+        context: &InvocationContext,
+        limits: &SandboxLimits,
+    ) -> Result<SandboxResult> {
+        let start_time = Instant::now();
+        let resolved = self.environment.resolve_script(language.into(), runtime)?;
+        let interpreter = resolved.binary.to_string_lossy();
+        let entry_source = package.entry_source().ok_or_else(|| {
+            anyhow::anyhow!("Package entrypoint not found: {}", package.entrypoint)
+        })?;
+        self.check_code_size(entry_source)?;
+        let hooks = language.defines_hooks(entry_source);
+        let entry_script = if hooks {
+            language.wrap_worker(entry_source)
+        } else {
+            language.wrap(entry_source)
+        };
+        if let Some(trace) = context.debug_trace() {
+            let sources = package
+                .files
+                .iter()
+                .map(|file| {
+                    let content = if file.path == package.entrypoint {
+                        entry_script.to_string()
+                    } else {
+                        file.content.clone()
+                    };
+                    (file.path.clone(), content)
+                })
+                .collect();
+            trace.set_sources(sources);
+            self.trace_environment(trace, language, runtime, &resolved);
+        }
+        let (entry, _) = self
+            .scripts
+            .package_entry(&interpreter, package, &entry_script)
+            .await?;
+        let limits = &limits.clone().with_deadline(context.deadline());
+        if hooks {
+            let result = self
+                .run_in_worker(&resolved, entry, input, context, limits)
+                .await?;
+            if let Some(trace) = context.debug_trace() {
+                trace.set_output(&result.stdout, &result.stderr);
+            }
+            return Ok(result);
+        }
+        let Some(slot) = self.admit(limits).await? else {
+            return Ok(SandboxResult::expired_in_queue(start_time));
+        };
+        let jail = self.prepare_jail(limits).await?;
+        let context = with_scratch(jail.path(), context)?;
+        let input = script_stdin(input, &context)?;
+
+        let mut result = self
+            .run_in_jail(
+                jail,
+                resolved.binary.as_os_str(),
+                &[entry.to_string_lossy().to_string()],
+                &resolved.env,
+                Some(&input),
+                Some(&context),
+                limits,
+                start_time,
+                slot.waited(),
+            )
+            .await?;
+        require_wrapper_output(&mut result);
+        if let Some(trace) = context.debug_trace() {
+            trace.set_output(&result.stdout, &result.stderr);
+        }
+        Ok(result)
+    }
+
+    /// 记录调试包中的解释器版本、路径和脚本进程可见的环境变量名
+    fn trace_environment(
+        &self,
+        trace: &DebugTrace,
+        language: ScriptLanguage,
+        runtime: Option<&str>,
+        resolved: &ResolvedRuntime,
+    ) {
+        let version = self
+            .environment
+            .version(language.into(), runtime)
+            .unwrap_or_else(|| "unknown".to_string());
+        trace.set_toolchain(resolved.name.clone(), version);
+        trace.set_toolchain(
+            format!("{}.path", resolved.name),
+            resolved.binary.to_string_lossy(),
+        );
+        trace.set_env(self.script_env_names(&resolved.env));
+    }
+
+    /// 脚本进程可见的环境变量名（白名单中宿主已设置的变量、执行环境定义的变量、截止时间和暂存目录）
+    fn script_env_names(&self, runtime_env: &[(String, String)]) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .sandbox_env(Path::new(""), self.config.allow_network, |name| {
+                std::env::var(name).ok()
+            })
+            .into_iter()
+            .chain(runtime_env.iter().cloned())
+            .map(|(name, _)| name)
+            .chain([DEADLINE_ENV.to_string(), SCRATCH_ENV.to_string()])
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// 把脚本写入缓存后用解释器执行，`runtime_env` 为执行环境定义的额外变量
+    async fn run_script(
+        &self,
+        interpreter: &str,
+        runtime_env: &[(String, String)],
+        script_name: &str,
+        script: &WrappedScript<'_>,
+        stdin: ScriptStdin<'_>,
+        limits: &SandboxLimits,
+    ) -> Result<SandboxResult> {
+        let start_time = Instant::now();
+        let script_path = Path::new(script_name);
+        if script_path.is_absolute() || script_path.components().count() != 1 {
+            return Err(anyhow::anyhow!(
+                "Script name must be a plain file name: {script_name}"
+            ));
+        }
+
+        let (cached, _) = self
+            .scripts
+            .script_path(interpreter, script_name, script)
+            .await?;
+        let Some(slot) = self.admit(limits).await? else {
+            return Ok(SandboxResult::expired_in_queue(start_time));
+        };
+        let jail = self.prepare_jail(limits).await?;
+        let (stdin, invocation) = match stdin {
+            ScriptStdin::Raw(stdin) => (stdin.map(<[u8]>::to_vec), None),
+            ScriptStdin::Invocation(input, context) => {
+                let context = with_scratch(jail.path(), context)?;
+                (Some(script_stdin(input, &context)?), Some(context))
+            }
+        };
+
+        let mut result = self
+            .run_in_jail(
+                jail,
+                OsStr::new(interpreter),
+                &[cached.to_string_lossy().to_string()],
+                runtime_env,
+                stdin.as_deref(),
+                invocation.as_ref(),
+                limits,
+                start_time,
+                slot.waited(),
+            )
+            .await?;
+        if invocation.is_some() {
+            require_wrapper_output(&mut result);
+        }
+        Ok(result)
+    }
+
+    /// 在函数的常驻工作进程中执行一次调用；没有空闲进程时启动新进程并先调用 `init`
+    ///
+    /// 初始化计入本次调用的执行预算和启动耗时；初始化失败时进程被终止，本次调用失败，
+    /// 错误同时作为 `WarmingFailed` 事件发布。超时的工作进程被终止，不再复用。
+    async fn run_in_worker(
+        &self,
+        resolved: &ResolvedRuntime,
+        script: PathBuf,
+        input: &serde_json::Value,
+        context: &InvocationContext,
+        limits: &SandboxLimits,
+    ) -> Result<SandboxResult> {
+        let start_time = Instant::now();
+        let Some(slot) = self.admit(limits).await? else {
+            return Ok(SandboxResult::expired_in_queue(start_time));
+        };
+        let queued = slot.waited();
+        let key = (context.function_name.clone(), script);
+
+        let spawned = Instant::now();
+        // 带亲和键的调用优先由键映射到的工作进程执行；严格模式总是启动新的工作进程
+        let checked_out = match context.affinity() {
+            _ if limits.strict => None,
+            Some(affinity) => self.workers.checkout_affine(&key, affinity.key()),
+            None => self.workers.checkout(&key).map(|worker| (worker, false)),
+        };
+        let (mut worker, affinity_honored) = match checked_out {
+            Some(checked_out) => checked_out,
+            None => match self
+                .start_worker(resolved, &key, context, limits, queued)
+                .await?
+            {
+                Ok(worker) => (worker, false),
+                Err(message) => {
+                    let message = format!("Function init failed: {message}");
+                    return Ok(self.worker_result(
+                        ExecutionStatus::error(ErrorKind::Runtime, message.clone()),
+                        serde_json::json!({"error": message}),
+                        message,
+                        limits,
+                        start_time,
+                        queued,
+                        spawned.elapsed(),
+                    ));
+                }
+            },
+        };
+        let startup = spawned.elapsed();
+        let worker_id = worker.id().to_string();
+        if let Some(affinity) = context.affinity() {
+            affinity.record(&worker_id, affinity_honored);
+        }
+
+        // 工作进程一次只处理一个调用，每次调用在其隔离目录中重新创建暂存目录
+        let context = match with_scratch(worker.jail(), context) {
+            Ok(context) => context,
+            Err(e) => {
+                self.workers.discard(worker).await;
+                return Err(e);
+            }
+        };
+        let scratch = PathBuf::from(context.scratch_dir.clone().unwrap_or_default());
+        // 工作进程在多次调用间复用，按本次调用期间新增的 CPU 时间统计和限制
+        let pid = worker.pid().unwrap_or(0);
+        let cpu = Arc::new(StdMutex::new(CpuSampler::from_now(pid)));
+        let cpu_handle = watch_cpu_time(
+            pid,
+            cpu.clone(),
+            self.active_processes.clone(),
+            limits.max_cpu_time_ms,
+        );
+        let outcome = worker
+            .request(
+                &serde_json::json!({
+                    "op": "invoke",
+                    "input": input,
+                    "context": context.to_json(),
+                }),
+                limits.budget(start_time.elapsed()),
+            )
+            .await;
+        let cpu_time_ms = cpu.lock().unwrap_or_else(|e| e.into_inner()).sample();
+        let killed_by = if cpu_handle.is_finished() {
+            cpu_handle.await.ok()
+        } else {
+            cpu_handle.abort();
+            None
+        };
+        // 正常返回的工作进程在处理完暂存目录后放回池中，下一个调用会重新创建暂存目录；
+        // 超出 CPU 时间上限被终止的工作进程丢弃
+        let (result, reusable) = match (outcome, killed_by) {
+            (_, Some(limit)) => {
+                let ran_for = spawned.elapsed();
+                self.workers.discard(worker).await;
+                let result = SandboxResult {
+                    isolation_level: self.isolation,
+                    resource_monitoring: ResourceMonitoring::Unsupported,
+                    timing: InvocationTiming {
+                        queue_ms: Some(queued.as_millis() as u64),
+                        spawn_ms: Some(startup.as_millis() as u64),
+                        ..Default::default()
+                    },
+                    termination: Some(ProcessTermination {
+                        forced: true,
+                        ran_for_ms: ran_for.as_millis() as u64,
+                    }),
+                    ..SandboxResult::killed(
+                        limit,
+                        limits.quota_name.as_deref(),
+                        start_time.elapsed().as_millis() as u64,
+                        0,
+                        String::new(),
+                    )
+                };
+                (result, None)
+            }
+            (WorkerOutcome::Replied { reply, stderr }, None) => {
+                let (stderr, _) = take_body_start(&stderr);
+                let (status, output) = match reply.error {
+                    None => (ExecutionStatus::Success, reply.output),
+                    Some(error) => {
+                        let output = serde_json::json!({"error": error, "stack": reply.stack});
+                        let message = if stderr.trim().is_empty() {
+                            error
+                        } else {
+                            stderr.trim().to_string()
+                        };
+                        let origin = reply.origin.unwrap_or(ErrorOrigin::UserCode);
+                        (
+                            ExecutionStatus::error_with_origin(ErrorKind::Runtime, origin, message),
+                            output,
+                        )
+                    }
+                };
+                let result =
+                    self.worker_result(status, output, stderr, limits, start_time, queued, startup);
+                (result, Some(worker))
+            }
+            (WorkerOutcome::TimedOut { .. }, None) => {
+                let ran_for = spawned.elapsed();
+                self.workers.discard(worker).await;
+                let result = SandboxResult {
+                    isolation_level: self.isolation,
+                    resource_monitoring: ResourceMonitoring::Unsupported,
+                    timing: InvocationTiming {
+                        queue_ms: Some(queued.as_millis() as u64),
+                        spawn_ms: Some(startup.as_millis() as u64),
+                        ..Default::default()
+                    },
+                    termination: Some(ProcessTermination {
+                        forced: true,
+                        ran_for_ms: ran_for.as_millis() as u64,
+                    }),
+                    ..SandboxResult::killed(
+                        ResourceLimit::Timeout,
+                        limits.quota_name.as_deref(),
+                        start_time.elapsed().as_millis() as u64,
+                        0,
+                        String::new(),
+                    )
+                };
+                (result, None)
+            }
+            (WorkerOutcome::Exited { stderr }, None) => {
+                let message = format!("Script worker exited: {}", stderr.trim());
+                self.workers
+                    .publish(InstanceLifecycleEvent::new(
+                        worker.id(),
+                        &context.function_name,
+                        LifecycleEventType::Error,
+                        message.clone(),
+                    ))
+                    .await;
+                self.workers.discard(worker).await;
+                let result = self.worker_result(
+                    ExecutionStatus::error(ErrorKind::Runtime, message.clone()),
+                    serde_json::json!({"error": message}),
+                    stderr,
+                    limits,
+                    start_time,
+                    queued,
+                    startup,
+                );
+                (result, None)
+            }
+        };
+        if let Some(report) = context.cpu_report() {
+            report.record(cpu_time_ms);
+        }
+        let result = SandboxResult {
+            cpu_time_ms: Some(cpu_time_ms),
+            timing: InvocationTiming {
+                cpu_ms: Some(cpu_time_ms),
+                ..result.timing
+            },
+            ..result
+        };
+        self.workers
+            .record(&worker_id, result.status == ExecutionStatus::Success);
+        let result = self.finish_scratch(&scratch, &context.invocation_id, limits, Ok(result));
+        match reusable {
+            // 严格模式的工作进程只处理一次调用
+            Some(worker) if limits.strict => self.workers.discard(worker).await,
+            Some(worker) => self.workers.checkin(key, worker).await,
+            None => {}
+        }
+        result
+    }
+
+    /// 启动工作进程并调用 `init`；初始化失败时返回错误信息（进程已终止）
+    async fn start_worker(
+        &self,
+        resolved: &ResolvedRuntime,
+        key: &WorkerKey,
+        context: &InvocationContext,
+        limits: &SandboxLimits,
+        queued: Duration,
+    ) -> Result<std::result::Result<ScriptWorker, String>> {
+        let (function, script) = key;
+        let budget = limits.budget(queued);
+        let jail = self.prepare_jail(limits).await?;
+        let mut cmd = self.jail_command(
+            jail.path(),
+            resolved.binary.as_os_str(),
+            &[script.to_string_lossy().to_string()],
+            &resolved.env,
+            limits,
+            budget,
+        )?;
+        cmd.stdin(Stdio::piped());
+        let child = {
+            let _spawn_span = tracing::info_span!("spawn").entered();
+            cmd.spawn().context("Failed to spawn script worker")?
+        };
+        tracing::debug!(
+            phase = "spawn",
+            pid = child.id().unwrap_or(0),
+            "Spawned script worker for {}",
+            function
+        );
+        let mut worker = ScriptWorker::attach(function, child, jail)?;
+
+        let started = Instant::now();
+        self.workers
+            .publish(InstanceLifecycleEvent::new(
+                worker.id(),
+                function,
+                LifecycleEventType::WarmingStarted,
+                "Script worker started, running init".to_string(),
+            ))
+            .await;
+        let outcome = worker
+            .request(
+                &serde_json::json!({"op": "init", "context": context.to_json()}),
+                budget,
+            )
+            .await;
+        let failure = match outcome {
+            WorkerOutcome::Replied { reply, .. } if reply.error.is_none() => None,
+            WorkerOutcome::Replied { reply, stderr } => Some(
+                Some(stderr.trim())
+                    .filter(|stderr| !stderr.is_empty())
+                    .map(str::to_string)
+                    .or(reply.error)
+                    .unwrap_or_default(),
+            ),
+            WorkerOutcome::TimedOut { .. } => Some(format!(
+                "init did not finish within {}ms",
+                budget.as_millis()
+            )),
+            WorkerOutcome::Exited { stderr } => {
+                Some(format!("worker exited during init: {}", stderr.trim()))
+            }
+        };
+
+        let duration_ms = Some(started.elapsed().as_millis() as u64);
+        let event = match &failure {
+            None => InstanceLifecycleEvent {
+                duration_ms,
+                ..InstanceLifecycleEvent::new(
+                    worker.id(),
+                    function,
+                    LifecycleEventType::Ready,
+                    "Script worker initialized".to_string(),
+                )
+            },
+            Some(message) => {
+                let mut event = InstanceLifecycleEvent::new(
+                    worker.id(),
+                    function,
+                    LifecycleEventType::WarmingFailed,
+                    format!("Function init failed: {message}"),
+                );
+                event.metadata.insert("error".to_string(), message.clone());
+                InstanceLifecycleEvent {
+                    duration_ms,
+                    ..event
+                }
+            }
+        };
+        self.workers.publish(event).await;
+
+        Ok(match failure {
+            None => {
+                self.workers.track(&worker);
+                Ok(worker)
+            }
+            Some(message) => {
+                tracing::warn!("Init of function '{}' failed: {}", function, message);
+                worker.kill().await;
+                Err(message)
+            }
+        })
+    }
+
+    /// 工作进程执行的结果；启动耗时只在本次调用启动了新进程时非零
+    #[allow(clippy::too_many_arguments)]
+    fn worker_result(
+        &self,
+        status: ExecutionStatus,
+        output: serde_json::Value,
+        stderr: String,
+        limits: &SandboxLimits,
+        start_time: Instant,
+        queued: Duration,
+        startup: Duration,
+    ) -> SandboxResult {
+        let total_ms = start_time.elapsed().as_millis() as u64;
+        let queue_ms = (queued.as_millis() as u64).min(total_ms);
+        let spawn_ms = (startup.as_millis() as u64).min(total_ms - queue_ms);
+        SandboxResult {
+            status,
+            output,
+            execution_time_ms: total_ms,
+            peak_memory_bytes: 0,
+            cpu_usage_percent: 0.0,
+            exit_code: None,
+            stdout: String::new(),
+            stderr,
+            killed_by: None,
+            quota_name: limits.quota_name.clone(),
+            resource_monitoring: ResourceMonitoring::Unsupported,
+            isolation_level: self.isolation,
+            timing: InvocationTiming {
+                queue_ms: Some(queue_ms),
+                compile_ms: None,
+                spawn_ms: Some(spawn_ms),
+                execute_ms: Some(total_ms - queue_ms - spawn_ms),
+                network_ms: None,
+                total_ms,
+                cpu_ms: None,
+                instance: None,
+            },
+            termination: None,
+            disk: None,
+            cpu_time_ms: None,
+        }
+    }
+
+    /// 按单次执行的超时预算获取执行名额
+    async fn admit(&self, limits: &SandboxLimits) -> Result<Option<ExecutionSlot>> {
+        self.gate
+            .acquire(Duration::from_secs(limits.execution_timeout_secs))
+            .await
+    }
+
+    /// 创建隔离目录；允许文件系统访问时（严格模式除外）把 `allowed_dirs` 以目录名链接进来，
+    /// 进程通过相对路径访问，其余宿主路径不出现在工作目录中
+    async fn prepare_jail(&self, limits: &SandboxLimits) -> Result<TempDir> {
+        let jail = self.create_secure_temp_dir().await?;
+
+        if self.allows_filesystem(limits) {
+            for dir in &self.config.allowed_dirs {
+                let Some(name) = dir.file_name() else {
+                    tracing::warn!("Skipping allowed dir without a name: {:?}", dir);
+                    continue;
+                };
+                let link = jail.path().join(name);
+                if link.exists() {
+                    tracing::warn!("Skipping duplicate allowed dir name: {:?}", dir);
+                    continue;
+                }
+                platform::link_dir(dir, &link)
+                    .with_context(|| format!("Failed to expose allowed dir: {dir:?}"))?;
+            }
+        }
+
+        Ok(jail)
+    }
+
+    /// 本次执行是否允许网络访问（严格模式下总是禁止）
+    fn allows_network(&self, limits: &SandboxLimits) -> bool {
+        self.config.allow_network && !limits.strict
+    }
+
+    /// 本次执行是否开放 `allowed_dirs`（严格模式下总是禁止）
+    fn allows_filesystem(&self, limits: &SandboxLimits) -> bool {
+        self.config.allow_filesystem && !limits.strict
+    }
+
+    /// 子进程可见的环境变量：仅保留白名单，禁止网络时移除代理变量，HOME/TMPDIR 指向隔离目录
+    fn sandbox_env(
+        &self,
+        jail: &Path,
+        allow_network: bool,
+        host_env: impl Fn(&str) -> Option<String>,
+    ) -> Vec<(String, String)> {
+        let mut env: Vec<(String, String)> = self
+            .config
+            .allowed_env_vars
+            .iter()
+            .filter(|name| allow_network || !PROXY_ENV_VARS.contains(&name.as_str()))
+            .filter(|name| !JAIL_HOME_VARS.contains(&name.as_str()))
+            .filter_map(|name| host_env(name).map(|value| (name.clone(), value)))
+            .collect();
+
+        let jail = jail.to_string_lossy().to_string();
+        env.extend(
+            JAIL_HOME_VARS
+                .iter()
+                .map(|name| (name.to_string(), jail.clone())),
+        );
+        env
+    }
+
+    /// 在隔离目录中运行 `program` 的命令：过滤后的环境变量、截止时间、进程树隔离，
+    /// 可用时进入独立的命名空间；标准输出和错误为管道，标准输入由调用方设置
+    fn jail_command(
+        &self,
+        work_dir: &Path,
+        program: &OsStr,
+        args: &[String],
+        runtime_env: &[(String, String)],
+        limits: &SandboxLimits,
+        budget: Duration,
+    ) -> Result<TokioCommand> {
+        if limits.requires_isolation && !self.isolation.is_confined() {
+            return Err(anyhow::anyhow!(
+                "Function requires namespace isolation, but only process-only isolation is available"
+            ));
+        }
 
         // 构建安全的执行命令
-        let mut cmd = TokioCommand::new(&executor_path);
-        cmd.arg(&input_json)
+        let mut cmd = TokioCommand::new(program);
+        cmd.args(args)
             .current_dir(work_dir)
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .stdin(Stdio::null());
+            .stderr(Stdio::piped());
 
         // 设置安全的环境变量
         cmd.env_clear();
-        for env_var in &self.config.allowed_env_vars {
-            if let Ok(value) = std::env::var(env_var) {
-                cmd.env(env_var, value);
-            }
-        }
+        cmd.envs(
+            self.sandbox_env(work_dir, self.allows_network(limits), |name| {
+                std::env::var(name).ok()
+            }),
+        );
+        // 执行环境定义的变量由运维配置，覆盖白名单中的同名变量
+        cmd.envs(runtime_env.iter().map(|(name, value)| (name, value)));
+        // 超时预算（排队时间计入）同时告知函数进程
+        cmd.env(DEADLINE_ENV, budget.as_millis().to_string());
 
         // 设置工作目录权限限制
-        self.set_directory_permissions(work_dir).await?;
+        platform::restrict_to_owner(work_dir)?;
 
-        tracing::info!(
-            "Starting sandboxed process for function: {}",
-            compiled.metadata.name
-        );
+        // 子进程启动的后代进程随其一起被终止；调用被取消（例如函数被强制删除）时终止子进程
+        platform::isolate_process_tree(&mut cmd);
+        cmd.kill_on_drop(true);
+
+        // 可用时进入独立的命名空间，只能看到系统目录、运行时和隔离目录
+        if self.isolation.is_confined() {
+            self.confinement(work_dir, program, limits)?
+                .apply(&mut cmd)?;
+        }
+
+        Ok(cmd)
+    }
+
+    /// 在隔离目录中启动进程并监控到结束（超时、内存或暂存目录超限时终止）
+    ///
+    /// `invocation` 为函数调用带暂存目录的上下文，进程结束后检查并删除（或保留）暂存目录。
+    #[allow(clippy::too_many_arguments)]
+    async fn run_in_jail(
+        &self,
+        jail: TempDir,
+        program: &OsStr,
+        args: &[String],
+        runtime_env: &[(String, String)],
+        stdin: Option<&[u8]>,
+        invocation: Option<&InvocationContext>,
+        limits: &SandboxLimits,
+        start_time: Instant,
+        queued: Duration,
+    ) -> Result<SandboxResult> {
+        let work_dir = jail.path();
+        let scratch = invocation
+            .and_then(|context| context.scratch_dir.as_deref())
+            .map(PathBuf::from);
+        let timeout_duration = limits.budget(queued);
+        let mut cmd = self.jail_command(
+            work_dir,
+            program,
+            args,
+            runtime_env,
+            limits,
+            timeout_duration,
+        )?;
+        if let Some(scratch) = &scratch {
+            cmd.env(SCRATCH_ENV, scratch);
+        }
+        cmd.stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        });
+
+        // 启动前隔离目录已有的大小，执行期间只统计新增的部分
+        let baseline = {
+            let dir = work_dir.to_path_buf();
+            tokio::task::spawn_blocking(move || scratch::usage(&dir))
+                .await
+                .unwrap_or(0)
+        };
 
         // 启动进程
-        let child = cmd.spawn().context("Failed to spawn sandboxed process")?;
+        let spawned = Instant::now();
+        let clock = PhaseClock {
+            started: start_time,
+            queued,
+            spawned,
+            spawned_wall: SystemTime::now(),
+        };
+        let mut child = {
+            let _spawn_span = tracing::info_span!("spawn").entered();
+            cmd.spawn().context("Failed to spawn sandboxed process")?
+        };
 
         let pid = child.id().unwrap_or(0);
+        tracing::debug!(phase = "spawn", pid, "Spawned sandboxed process");
+        let disk = Arc::new(StdMutex::new(DiskSampler::new(
+            pid,
+            work_dir,
+            scratch.as_deref(),
+            baseline,
+        )));
+        let cpu = Arc::new(StdMutex::new(CpuSampler::new(pid)));
+
+        // 在后台写入标准输入，避免输出管道写满时互相等待
+        if let (Some(mut pipe), Some(input)) = (child.stdin.take(), stdin) {
+            let input = input.to_vec();
+            tokio::spawn(async move {
+                let _ = pipe.write_all(&input).await;
+            });
+        }
 
         // 注册进程监控
-        self.register_process_monitor(pid).await;
-
-        // 等待执行完成（带超时）
-        let timeout_duration = Duration::from_secs(self.config.execution_timeout_secs);
-        let execution_result =
-            timeout(timeout_duration, self.monitor_process_execution(child, pid)).await;
+        self.register_process_monitor(pid, work_dir).await;
+
+        // 等待执行完成（带超时）；超时后终止并回收进程，不留下继续运行或僵尸进程
+        let execution_result = timeout(
+            timeout_duration,
+            self.monitor_process_execution(&mut child, pid, limits, &disk, &cpu, &clock)
+                .instrument(tracing::info_span!("wait", pid)),
+        )
+        .await;
+        let termination = match execution_result {
+            Ok(_) => None,
+            Err(_) => Some(self.terminate_child(&mut child, pid, spawned).await),
+        };
 
         // 清理进程监控
         self.unregister_process_monitor(pid).await;
 
+        let execution_time_ms = start_time.elapsed().as_millis() as u64;
+
+        let result = match execution_result {
+            Ok(result) => result,
+            Err(_) => {
+                let timing = clock.timing(None);
+                Ok(SandboxResult {
+                    isolation_level: self.isolation,
+                    timing,
+                    termination,
+                    ..SandboxResult::killed(
+                        ResourceLimit::Timeout,
+                        limits.quota_name.as_deref(),
+                        execution_time_ms,
+                        0,
+                        String::new(),
+                    )
+                })
+            }
+        };
+        // 超时和被终止的调用同样删除暂存目录，不随隔离目录一起保留
+        let result = match (&scratch, invocation) {
+            (Some(scratch), Some(context)) => {
+                self.finish_scratch(scratch, &context.invocation_id, limits, result)
+            }
+            _ => result,
+        };
+        // 监控结束时已做最后一次采样，超时终止的调用取最近一次采样
+        let usage = disk
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .usage()
+            .clone();
+        if let Some(report) = invocation.and_then(InvocationContext::disk_report) {
+            report.record(usage.clone());
+        }
+        let cpu_time_ms = cpu.lock().unwrap_or_else(|e| e.into_inner()).cpu_time_ms();
+        if let Some(report) = invocation.and_then(InvocationContext::cpu_report) {
+            report.record(cpu_time_ms);
+        }
+        let result = result.map(|result| SandboxResult {
+            disk: Some(usage),
+            cpu_time_ms: Some(cpu_time_ms),
+            timing: InvocationTiming {
+                cpu_ms: Some(cpu_time_ms),
+                ..result.timing
+            },
+            ..result
+        });
+
         // 保持临时目录引用
         {
             let mut temp_dirs = self.temp_dirs.write().await;
-            temp_dirs.push(temp_dir);
+            temp_dirs.push(jail);
             if temp_dirs.len() > 20 {
                 temp_dirs.remove(0);
             }
         }
 
-        let execution_time_ms = start_time.elapsed().as_millis() as u64;
+        result
+    }
 
-        match execution_result {
-            Ok(result) => result,
-            Err(_) => {
-                // 超时，强制终止进程
-                self.kill_process(pid).await?;
-                Ok(SandboxResult {
-                    status: ExecutionStatus::Failed,
-                    output: serde_json::json!({"error": "Execution timeout"}),
-                    execution_time_ms,
-                    peak_memory_bytes: 0,
-                    cpu_usage_percent: 0.0,
-                    exit_code: Some(-1),
-                    stdout: String::new(),
-                    stderr: "Execution timeout".to_string(),
-                })
+    /// 调用结束后再检查一次暂存目录大小（超出上限时调用按 `Disk` 失败，文件不保留），
+    /// 然后删除暂存目录，函数设置了 `persist_scratch` 时移入暂存文件存储
+    fn finish_scratch(
+        &self,
+        scratch: &Path,
+        invocation_id: &str,
+        limits: &SandboxLimits,
+        result: Result<SandboxResult>,
+    ) -> Result<SandboxResult> {
+        let used = scratch::usage(scratch);
+        let exceeded = used > limits.scratch_limit_bytes();
+        let result = result.map(|result| {
+            if !exceeded || result.killed_by.is_some() {
+                return result;
+            }
+            tracing::warn!(
+                "Invocation {} left {} bytes in its scratch dir, exceeding the limit of {}MB",
+                invocation_id,
+                used,
+                limits.scratch_limit_mb
+            );
+            SandboxResult {
+                isolation_level: result.isolation_level,
+                resource_monitoring: result.resource_monitoring,
+                timing: result.timing,
+                ..SandboxResult::killed(
+                    ResourceLimit::Disk,
+                    None,
+                    result.execution_time_ms,
+                    result.peak_memory_bytes,
+                    result.stdout,
+                )
+            }
+        });
+        let persist = limits.persist_scratch && !exceeded;
+        self.scratch
+            .finish(scratch, persist.then_some(invocation_id));
+        result
+    }
+
+    /// 隔离目录的命名空间配置：脚本缓存和程序的安装目录只读可见，
+    /// 允许文件系统访问时 `allowed_dirs` 也以原路径只读挂载（隔离目录中的链接指向它们）
+    fn confinement(
+        &self,
+        work_dir: &Path,
+        program: &OsStr,
+        limits: &SandboxLimits,
+    ) -> Result<Confinement> {
+        let root = self.config.temp_root.join(ROOT_MOUNT_DIR);
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create sandbox root mount point: {root:?}"))?;
+        let program = which::which(program).unwrap_or_else(|_| PathBuf::from(program));
+
+        let mut confinement = Confinement::new(root, work_dir, self.allows_network(limits))
+            .with_read_only(self.scripts.dir())
+            .with_program(&program);
+        if self.allows_filesystem(limits) {
+            for dir in &self.config.allowed_dirs {
+                confinement = confinement.with_read_only(dir);
             }
         }
+        Ok(confinement)
     }
 
     /// 在容器中执行函数（Docker支持）
@@ -256,7 +1824,9 @@ impl SandboxExecutor {
         &self,
         compiled: &CompiledFunction,
         request: &InvokeRequest,
+        limits: &SandboxLimits,
         start_time: Instant,
+        queued: Duration,
     ) -> Result<SandboxResult> {
         // TODO: 实现Docker容器执行
         // 这需要集成bollard crate或直接调用docker命令
@@ -264,7 +1834,8 @@ impl SandboxExecutor {
         tracing::warn!(
             "Container execution not implemented yet, falling back to process isolation"
         );
-        self.execute_in_process(compiled, request, start_time).await
+        self.execute_in_process(compiled, request, limits, start_time, queued)
+            .await
     }
 
     /// 创建安全的临时目录
@@ -273,17 +1844,13 @@ impl SandboxExecutor {
             .context("Failed to create secure temporary directory")?;
 
         // 设置严格的权限（仅所有者可读写执行）
-        #[cfg(unix)]
-        {
-            let permissions = std::fs::Permissions::from_mode(0o700);
-            std::fs::set_permissions(temp_dir.path(), permissions)
-                .context("Failed to set directory permissions")?;
-        }
+        platform::restrict_to_owner(temp_dir.path())?;
 
         Ok(temp_dir)
     }
 
     /// 创建函数执行器可执行文件
+    #[tracing::instrument(name = "build_executor", skip_all)]
     async fn create_function_executor(
         &self,
         library_path: &Path,
@@ -343,8 +1910,14 @@ libloading = "0.8"
         let stderr = String::from_utf8_lossy(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
 
+        // 编译输出会引用函数源码，完整内容只在 TRACE 级别记录
         tracing::debug!(
             "Cargo build output:\nSTDOUT:\n{}\nSTDERR:\n{}",
+            truncate_for_log(&stdout),
+            truncate_for_log(&stderr)
+        );
+        tracing::trace!(
+            "Full cargo build output:\nSTDOUT:\n{}\nSTDERR:\n{}",
             stdout,
             stderr
         );
@@ -368,17 +1941,18 @@ libloading = "0.8"
         };
 
         // 首先尝试列出target目录的内容
+        let executor_name = platform::executable_name("executor");
         let mut executor_path = None;
-        if target_dir.exists() {
-            if let Ok(entries) = std::fs::read_dir(&target_dir) {
-                for entry in entries.flatten() {
-                    let entry_path = entry.path();
-                    if entry_path.is_dir() {
-                        let exe_path = entry_path.join("executor");
-                        if exe_path.exists() {
-                            executor_path = Some(exe_path);
-                            break;
-                        }
+        if target_dir.exists()
+            && let Ok(entries) = std::fs::read_dir(&target_dir)
+        {
+            for entry in entries.flatten() {
+                let entry_path = entry.path();
+                if entry_path.is_dir() {
+                    let exe_path = entry_path.join(&executor_name);
+                    if exe_path.exists() {
+                        executor_path = Some(exe_path);
+                        break;
                     }
                 }
             }
@@ -387,10 +1961,10 @@ libloading = "0.8"
         // 如果没找到，检查常见路径
         if executor_path.is_none() {
             let possible_paths = vec![
-                target_dir.join("debug/executor"),
-                target_dir.join("release/executor"),
-                work_dir.join("target/debug/executor"),
-                work_dir.join("target/release/executor"),
+                target_dir.join("debug").join(&executor_name),
+                target_dir.join("release").join(&executor_name),
+                work_dir.join("target/debug").join(&executor_name),
+                work_dir.join("target/release").join(&executor_name),
             ];
 
             for path in possible_paths {
@@ -408,11 +1982,11 @@ libloading = "0.8"
                 debug_info.push_str("Target directory contents:\n");
                 for entry in entries.flatten() {
                     debug_info.push_str(&format!("  {:?}\n", entry.path()));
-                    if entry.path().is_dir() {
-                        if let Ok(sub_entries) = std::fs::read_dir(entry.path()) {
-                            for sub_entry in sub_entries.flatten() {
-                                debug_info.push_str(&format!("    {:?}\n", sub_entry.path()));
-                            }
+                    if entry.path().is_dir()
+                        && let Ok(sub_entries) = std::fs::read_dir(entry.path())
+                    {
+                        for sub_entry in sub_entries.flatten() {
+                            debug_info.push_str(&format!("    {:?}\n", sub_entry.path()));
                         }
                     }
                 }
@@ -421,17 +1995,12 @@ libloading = "0.8"
         })?;
 
         // 设置执行权限
-        #[cfg(unix)]
-        {
-            let permissions = std::fs::Permissions::from_mode(0o755);
-            std::fs::set_permissions(&executor_path, permissions)
-                .context("Failed to set executor permissions")?;
-        }
+        platform::make_executable(&executor_path)?;
 
         Ok(executor_path)
     }
 
-    /// 生成执行器源代码（使用libloading）
+    /// 生成执行器源代码（使用libloading），调用函数前写出 [`BODY_START_MARKER`]
     fn generate_executor_source(&self, library_path: &Path) -> Result<String> {
         let lib_name = library_path
             .file_name()
@@ -445,12 +2014,13 @@ use std::os::raw::c_char;
 
 fn main() {{
     let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {{
-        eprintln!("Usage: executor <input_json>");
+    if args.len() != 2 && args.len() != 3 {{
+        eprintln!("Usage: executor <input_json> [context_json]");
         std::process::exit(1);
     }}
 
     let input = &args[1];
+    let context = args.get(2).cloned().unwrap_or_else(|| "null".to_string());
 
     // 动态加载库
     unsafe {{
@@ -462,15 +2032,6 @@ fn main() {{
             }}
         }};
 
-        // 获取函数符号
-        let flux_execute: libloading::Symbol<unsafe extern "C" fn(*const c_char) -> *mut c_char> =
-            match lib.get(b"flux_execute") {{
-                Ok(func) => func,
-                Err(e) => {{
-                    eprintln!("Failed to get flux_execute symbol: {{e}}");
-                    std::process::exit(1);
-                }}
-            }};
 
         let flux_free: libloading::Symbol<unsafe extern "C" fn(*mut c_char)> =
             match lib.get(b"flux_free_string") {{
@@ -485,13 +2046,44 @@ fn main() {{
         let input_cstring = match CString::new(input.clone()) {{
             Ok(s) => s,
             Err(e) => {{
-                eprintln!("Failed to create input CString: {{e}}");
+                eprintln!("Failed to create input CString: {{e}}");
+                std::process::exit(1);
+            }}
+        }};
+
+        let context_cstring = match CString::new(context) {{
+            Ok(s) => s,
+            Err(e) => {{
+                eprintln!("Failed to create context CString: {{e}}");
                 std::process::exit(1);
             }}
         }};
 
-        // 调用函数
-        let result_ptr = flux_execute(input_cstring.as_ptr());
+        eprintln!(
+            "{BODY_START_MARKER} {{}}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_micros())
+                .unwrap_or(0)
+        );
+
+        // 调用函数：优先使用带调用上下文的 flux_execute_v2，旧的动态库只导出 flux_execute
+        let result_ptr = match lib
+            .get::<unsafe extern "C" fn(*const c_char, *const c_char) -> *mut c_char>(b"flux_execute_v2")
+        {{
+            Ok(flux_execute_v2) => flux_execute_v2(input_cstring.as_ptr(), context_cstring.as_ptr()),
+            Err(_) => {{
+                let flux_execute: libloading::Symbol<unsafe extern "C" fn(*const c_char) -> *mut c_char> =
+                    match lib.get(b"flux_execute") {{
+                        Ok(func) => func,
+                        Err(e) => {{
+                            eprintln!("Failed to get flux_execute symbol: {{e}}");
+                            std::process::exit(1);
+                        }}
+                    }};
+                flux_execute(input_cstring.as_ptr())
+            }}
+        }};
 
         if result_ptr.is_null() {{
             eprintln!("Function returned null");
@@ -521,28 +2113,15 @@ fn main() {{
         Ok(source)
     }
 
-    /// 设置目录权限限制
-    async fn set_directory_permissions(&self, dir: &Path) -> Result<()> {
-        // 设置严格的目录权限
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let permissions = std::fs::Permissions::from_mode(0o700);
-            std::fs::set_permissions(dir, permissions)
-                .context("Failed to set directory permissions")?;
-        }
-
-        Ok(())
-    }
-
     /// 注册进程监控
-    async fn register_process_monitor(&self, pid: u32) {
+    async fn register_process_monitor(&self, pid: u32, work_dir: &Path) {
         let monitor = ProcessMonitor {
             pid,
             start_time: Instant::now(),
             memory_usage: 0,
             cpu_usage: 0.0,
             is_running: true,
+            work_dir: work_dir.to_path_buf(),
         };
 
         let mut processes = self.active_processes.write().await;
@@ -559,95 +2138,206 @@ fn main() {{
     }
 
     /// 监控进程执行
-    async fn monitor_process_execution(&self, child: Child, pid: u32) -> Result<SandboxResult> {
+    async fn monitor_process_execution(
+        &self,
+        child: &mut Child,
+        pid: u32,
+        limits: &SandboxLimits,
+        disk: &Arc<StdMutex<DiskSampler>>,
+        cpu: &Arc<StdMutex<CpuSampler>>,
+        clock: &PhaseClock,
+    ) -> Result<SandboxResult> {
         let start_time = Instant::now();
         let mut peak_memory = 0u64;
         let mut cpu_usage = 0.0f64;
 
-        // 启动资源监控任务
-        let monitor_handle = {
+        // 启动资源监控任务（平台不支持时只受执行超时限制）
+        let monitoring = ResourceMonitoring::current();
+        let monitor_handle = monitoring.is_supported().then(|| {
             let system_monitor = self.system_monitor.clone();
             let active_processes = self.active_processes.clone();
-            let max_memory = self.config.max_memory_mb * 1024 * 1024; // 转换为字节
-            let max_cpu = self.config.max_cpu_percent;
-
-            tokio::spawn(async move {
-                let mut interval = tokio::time::interval(Duration::from_millis(100));
-
-                loop {
-                    interval.tick().await;
-
-                    // 更新系统信息
-                    {
-                        let mut system = system_monitor.lock().await;
-                        system.refresh_processes();
-                    }
-
-                    // 检查进程状态
-                    let should_continue = {
-                        let mut processes = active_processes.write().await;
-                        if let Some(monitor) = processes.get_mut(&pid) {
-                            if !monitor.is_running {
-                                break;
-                            }
+            let max_memory = limits.max_memory_mb * 1024 * 1024; // 转换为字节
+            let max_cpu = limits.max_cpu_percent;
+            let quota = limits
+                .quota_name
+                .clone()
+                .unwrap_or_else(|| "sandbox".to_string());
+
+            tokio::spawn(
+                async move {
+                    let mut interval = tokio::time::interval(Duration::from_millis(100));
+
+                    loop {
+                        interval.tick().await;
+
+                        // 更新系统信息
+                        {
+                            let mut system = system_monitor.lock().await;
+                            system.refresh_processes();
+                        }
 
-                            // 获取进程资源使用情况
-                            let system = system_monitor.lock().await;
-                            if let Some(process) = system.process(sysinfo::Pid::from_u32(pid)) {
-                                monitor.memory_usage = process.memory();
-                                monitor.cpu_usage = process.cpu_usage() as f64;
-
-                                // 检查资源限制
-                                if process.memory() > max_memory {
-                                    tracing::warn!(
-                                        "Process {} exceeded memory limit: {} > {}",
-                                        pid,
-                                        process.memory(),
-                                        max_memory
-                                    );
-                                    monitor.is_running = false;
-                                    return Err(anyhow::anyhow!("Memory limit exceeded"));
+                        // 检查进程状态
+                        let should_continue = {
+                            let mut processes = active_processes.write().await;
+                            if let Some(monitor) = processes.get_mut(&pid) {
+                                if !monitor.is_running {
+                                    break;
                                 }
 
-                                if process.cpu_usage() as f64 > max_cpu {
-                                    tracing::warn!(
-                                        "Process {} exceeded CPU limit: {}% > {}%",
-                                        pid,
-                                        process.cpu_usage(),
-                                        max_cpu
-                                    );
-                                    // CPU超限警告但不立即终止
+                                // 获取进程资源使用情况
+                                let system = system_monitor.lock().await;
+                                if let Some(process) = system.process(sysinfo::Pid::from_u32(pid)) {
+                                    monitor.memory_usage = process.memory();
+                                    monitor.cpu_usage = process.cpu_usage() as f64;
+
+                                    // 检查资源限制
+                                    if process.memory() > max_memory {
+                                        tracing::warn!(
+                                            "Process {} exceeded memory limit of {}: {} > {}",
+                                            pid,
+                                            quota,
+                                            process.memory(),
+                                            max_memory
+                                        );
+                                        monitor.is_running = false;
+                                        kill_immediately(pid);
+                                        return Some(ResourceLimit::Memory);
+                                    }
+
+                                    // 使用率只告警，按累计 CPU 时间限制（见 max_cpu_time_ms）
+                                    if process.cpu_usage() as f64 > max_cpu {
+                                        tracing::warn!(
+                                            "Process {} exceeded CPU limit: {}% > {}%",
+                                            pid,
+                                            process.cpu_usage(),
+                                            max_cpu
+                                        );
+                                    }
+
+                                    true
+                                } else {
+                                    // 进程已不存在
+                                    monitor.is_running = false;
+                                    false
                                 }
-
-                                true
                             } else {
-                                // 进程已不存在
-                                monitor.is_running = false;
                                 false
                             }
-                        } else {
-                            false
+                        };
+
+                        if !should_continue {
+                            break;
                         }
-                    };
+                    }
 
-                    if !should_continue {
-                        break;
+                    None
+                }
+                .in_current_span(),
+            )
+        });
+
+        // 定期采样磁盘用量：暂存目录超出上限或写入字节数超出函数的上限时终止进程
+        let disk_handle = {
+            let sampler = disk.clone();
+            let max_scratch_bytes = limits.scratch_limit_bytes();
+            let max_write_mb = limits.max_disk_write_mb;
+            tokio::spawn(
+                async move {
+                    let mut interval = tokio::time::interval(DISK_CHECK_INTERVAL);
+                    loop {
+                        interval.tick().await;
+                        let sampler = sampler.clone();
+                        let Ok(usage) = tokio::task::spawn_blocking(move || {
+                            sampler
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner())
+                                .sample()
+                                .clone()
+                        })
+                        .await
+                        else {
+                            continue;
+                        };
+                        if usage.peak_scratch_bytes > max_scratch_bytes {
+                            tracing::warn!(
+                                "Process {} exceeded scratch limit: {} > {}",
+                                pid,
+                                usage.peak_scratch_bytes,
+                                max_scratch_bytes
+                            );
+                            kill_immediately(pid);
+                            return ResourceLimit::Disk;
+                        }
+                        if let Some(limit_mb) = max_write_mb
+                            && usage.write_bytes > limit_mb.saturating_mul(1024 * 1024)
+                        {
+                            tracing::warn!(
+                                "Process {} exceeded disk write limit: {} bytes > {}MB",
+                                pid,
+                                usage.write_bytes,
+                                limit_mb
+                            );
+                            kill_immediately(pid);
+                            return ResourceLimit::DiskWrite {
+                                written_bytes: usage.write_bytes,
+                                limit_mb,
+                            };
+                        }
                     }
                 }
-
-                Ok::<(), anyhow::Error>(())
-            })
+                .in_current_span(),
+            )
         };
 
-        // 等待进程完成
-        let child = child;
-        let output = child
-            .wait_with_output()
-            .await
-            .context("Failed to wait for process")?;
+        let cpu_handle = watch_cpu_time(
+            pid,
+            cpu.clone(),
+            self.active_processes.clone(),
+            limits.max_cpu_time_ms,
+        );
 
-        // 停止监控
-        monitor_handle.abort();
+        // 等待进程完成；子进程由调用方持有，超时时可以终止并回收。
+        // 进程退出后、回收前读取最终的 CPU 时间
+        let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+        let exited = async {
+            cpu::wait_exited(pid).await;
+            cpu.lock().unwrap_or_else(|e| e.into_inner()).sample();
+            child.wait().await
+        };
+        let (status, stdout, stderr) = tokio::join!(exited, read_pipe(stdout), read_pipe(stderr));
+        let output = std::process::Output {
+            status: status.context("Failed to wait for process")?,
+            stdout,
+            stderr,
+        };
+
+        // 停止监控（监控任务已因内存超限终止进程时取出原因）
+        let killed_by = match monitor_handle {
+            Some(handle) if handle.is_finished() => handle.await.ok().flatten(),
+            Some(handle) => {
+                handle.abort();
+                None
+            }
+            None => None,
+        };
+        let killed_by = if killed_by.is_none() && disk_handle.is_finished() {
+            disk_handle.await.ok()
+        } else {
+            disk_handle.abort();
+            killed_by
+        };
+        let killed_by = if killed_by.is_none() && cpu_handle.is_finished() {
+            cpu_handle.await.ok()
+        } else {
+            cpu_handle.abort();
+            killed_by
+        };
+        // 进程结束后再采样一次目录大小（暂存目录在调用结束后才删除）
+        let sampler = disk.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            sampler.lock().unwrap_or_else(|e| e.into_inner()).sample();
+        })
+        .await;
 
         // 更新最终统计
         {
@@ -661,19 +2351,53 @@ fn main() {{
         let execution_time_ms = start_time.elapsed().as_millis() as u64;
         let exit_code = output.status.code();
         let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let (stderr, body_started) = take_body_start(&String::from_utf8_lossy(&output.stderr));
+        let timing = clock.timing(body_started);
+
+        if let Some(limit) = killed_by {
+            // 资源超限时立即强制终止
+            return Ok(SandboxResult {
+                isolation_level: self.isolation,
+                timing,
+                termination: Some(ProcessTermination {
+                    forced: true,
+                    ran_for_ms: execution_time_ms,
+                }),
+                ..SandboxResult::killed(
+                    limit,
+                    limits.quota_name.as_deref(),
+                    execution_time_ms,
+                    peak_memory,
+                    stdout,
+                )
+            });
+        }
 
         let status = if output.status.success() {
-            ExecutionStatus::Completed
+            ExecutionStatus::Success
         } else {
-            ExecutionStatus::Failed
+            match exit_code {
+                Some(code) => ExecutionStatus::error(
+                    ErrorKind::Runtime,
+                    if stderr.trim().is_empty() {
+                        format!("Process exited with code {code}")
+                    } else {
+                        stderr.trim().to_string()
+                    },
+                ),
+                None => ExecutionStatus::error(ErrorKind::Sandbox, "Process terminated by signal"),
+            }
         };
 
         let output_json = if output.status.success() && !stdout.trim().is_empty() {
             serde_json::from_str(&stdout)
                 .unwrap_or_else(|_| serde_json::json!({"result": stdout.trim()}))
         } else {
-            serde_json::json!({"error": stderr.trim()})
+            // 包装脚本捕获的异常以 `{"error", "name", "stack"}` 写到标准输出
+            serde_json::from_str::<serde_json::Value>(&stdout)
+                .ok()
+                .filter(|value| value["error"].is_string())
+                .unwrap_or_else(|| serde_json::json!({"error": stderr.trim()}))
         };
 
         Ok(SandboxResult {
@@ -685,39 +2409,50 @@ fn main() {{
             exit_code,
             stdout,
             stderr,
+            killed_by: None,
+            quota_name: limits.quota_name.clone(),
+            resource_monitoring: monitoring,
+            isolation_level: self.isolation,
+            timing,
+            termination: None,
+            disk: None,
+            cpu_time_ms: None,
         })
     }
 
-    /// 强制终止进程
-    async fn kill_process(&self, pid: u32) -> Result<()> {
-        #[cfg(unix)]
-        {
-            use nix::sys::signal::{Signal, kill};
-            use nix::unistd::Pid;
-
-            let nix_pid = Pid::from_raw(pid as i32);
-
-            // 首先尝试优雅终止
-            if let Err(e) = kill(nix_pid, Signal::SIGTERM) {
-                tracing::warn!("Failed to send SIGTERM to process {}: {}", pid, e);
-            } else {
-                // 等待一段时间让进程优雅退出
-                tokio::time::sleep(Duration::from_millis(1000)).await;
-            }
+    /// 超时后终止并回收函数进程，返回是否强制终止以及进程已运行的时间
+    async fn terminate_child(
+        &self,
+        child: &mut Child,
+        pid: u32,
+        spawned: Instant,
+    ) -> ProcessTermination {
+        let ran_for_ms = spawned.elapsed().as_millis() as u64;
+        let forced = platform::terminate_child(child, TERMINATION_GRACE).await;
+        if let Some(monitor) = self.active_processes.write().await.get_mut(&pid) {
+            monitor.is_running = false;
+        }
+        tracing::info!(
+            "Terminated process {} after {}ms{}",
+            pid,
+            ran_for_ms,
+            if forced { " (forced)" } else { "" }
+        );
+        ProcessTermination { forced, ran_for_ms }
+    }
 
-            // 检查进程是否仍然存在，如果存在则强制终止
-            if let Err(e) = kill(nix_pid, Signal::SIGKILL) {
-                tracing::warn!("Failed to send SIGKILL to process {}: {}", pid, e);
-            }
+    /// 强制终止进程及其后代：先请求退出，等待片刻后强制终止
+    async fn kill_process(&self, pid: u32) -> Result<()> {
+        if let Err(e) = platform::terminate_tree(pid, false) {
+            tracing::warn!("Failed to request termination of process {}: {}", pid, e);
+        } else {
+            // 等待一段时间让进程优雅退出
+            tokio::time::sleep(TERMINATION_GRACE).await;
         }
 
-        #[cfg(windows)]
-        {
-            // Windows 版本的进程终止
-            use std::process::Command;
-            let _ = Command::new("taskkill")
-                .args(["/PID", &pid.to_string(), "/F"])
-                .output();
+        // 进程树可能已经退出，强制终止失败只记录日志
+        if let Err(e) = platform::terminate_tree(pid, true) {
+            tracing::debug!("Failed to force-terminate process {}: {}", pid, e);
         }
 
         // 更新监控状态
@@ -732,6 +2467,30 @@ fn main() {{
         Ok(())
     }
 
+    /// 临时目录根路径
+    pub fn temp_root(&self) -> &Path {
+        &self.config.temp_root
+    }
+
+    /// 仍在使用的隔离目录：运行中进程的工作目录和保留的最近执行目录
+    pub async fn active_work_dirs(&self) -> HashSet<PathBuf> {
+        let mut dirs: HashSet<PathBuf> = self
+            .active_processes
+            .read()
+            .await
+            .values()
+            .map(|monitor| monitor.work_dir.clone())
+            .collect();
+        dirs.extend(
+            self.temp_dirs
+                .read()
+                .await
+                .iter()
+                .map(|dir| dir.path().to_path_buf()),
+        );
+        dirs
+    }
+
     /// 获取当前活跃进程数量
     pub async fn get_active_process_count(&self) -> usize {
         let processes = self.active_processes.read().await;
@@ -776,6 +2535,70 @@ fn main() {{
     }
 }
 
+/// 读完子进程的输出管道（管道不存在或读取失败时返回已读到的内容）
+async fn read_pipe(pipe: Option<impl AsyncRead + Unpin>) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    if let Some(mut pipe) = pipe {
+        let _ = pipe.read_to_end(&mut buffer).await;
+    }
+    buffer
+}
+
+/// 立即强制终止进程及其后代（用于资源超限）
+/// 定期采样进程的累计 CPU 时间（无法读取时按资源监控采样的使用率估算），
+/// 超出 `limit_ms` 时终止进程并返回原因；没有上限时只采样，直到任务被取消
+fn watch_cpu_time(
+    pid: u32,
+    sampler: Arc<StdMutex<CpuSampler>>,
+    active_processes: Arc<RwLock<HashMap<u32, ProcessMonitor>>>,
+    limit_ms: Option<u64>,
+) -> tokio::task::JoinHandle<ResourceLimit> {
+    tokio::spawn(
+        async move {
+            let mut interval = tokio::time::interval(CPU_CHECK_INTERVAL);
+            let mut sampled_at = Instant::now();
+            loop {
+                interval.tick().await;
+                let percent = active_processes
+                    .read()
+                    .await
+                    .get(&pid)
+                    .map(|monitor| monitor.cpu_usage);
+                let used = {
+                    let mut sampler = sampler.lock().unwrap_or_else(|e| e.into_inner());
+                    if let Some(percent) = percent {
+                        sampler.add_sampled(percent, sampled_at.elapsed());
+                    }
+                    sampler.sample()
+                };
+                sampled_at = Instant::now();
+                if let Some(limit_ms) = limit_ms
+                    && used > limit_ms
+                {
+                    tracing::warn!(
+                        "Process {} exceeded CPU time limit: {}ms > {}ms",
+                        pid,
+                        used,
+                        limit_ms
+                    );
+                    kill_immediately(pid);
+                    return ResourceLimit::CpuTime {
+                        cpu_time_ms: used,
+                        limit_ms,
+                    };
+                }
+            }
+        }
+        .in_current_span(),
+    )
+}
+
+fn kill_immediately(pid: u32) {
+    if let Err(e) = platform::terminate_tree(pid, true) {
+        tracing::warn!("Failed to kill process {}: {}", pid, e);
+    }
+}
+
 /// 系统资源使用情况
 #[derive(Debug, Clone, Serialize)]
 pub struct SystemUsage {
@@ -803,6 +2626,20 @@ impl Drop for SandboxExecutor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::functions::FunctionMetadata;
+    use crate::functions::context::CallerInfo;
+    use crate::functions::package::PackageFile;
+    use crate::runtime::affinity::AffinityRoute;
+    use crate::runtime::environment::{RuntimeDefinition, RuntimeKind, RuntimeProbeConfig};
+    use crate::runtime::execution_gate::SandboxAdmissionError;
+    use crate::runtime::resource::{
+        ResourceEventType, ResourceLimit as QuotaLimit, ResourceManager,
+    };
+
+    fn context() -> InvocationContext {
+        let function = FunctionMetadata::new("script".to_string(), String::new());
+        InvocationContext::new(&function, "inv-1")
+    }
 
     #[test]
     fn test_sandbox_config_default() {
@@ -820,6 +2657,957 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_sandbox_env_filters_host_variables() {
+        let executor = SandboxExecutor::new(SandboxConfig {
+            allowed_env_vars: vec!["PATH".to_string(), "HTTPS_PROXY".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        let host_env = |name: &str| match name {
+            "PATH" | "HTTPS_PROXY" | "SECRET_TOKEN" => Some(format!("host-{name}")),
+            _ => None,
+        };
+
+        let env: HashMap<_, _> = executor
+            .sandbox_env(Path::new("/jail"), executor.config.allow_network, host_env)
+            .into_iter()
+            .collect();
+        assert_eq!(env["PATH"], "host-PATH");
+        assert_eq!(env["HOME"], "/jail");
+        assert!(!env.contains_key("SECRET_TOKEN"));
+        // 禁止网络时即使在白名单中也移除代理变量
+        assert!(!env.contains_key("HTTPS_PROXY"));
+    }
+
+    #[test]
+    fn test_strict_limits_override_permissive_config() {
+        let executor = SandboxExecutor::new(SandboxConfig {
+            allow_network: true,
+            allow_filesystem: true,
+            max_memory_mb: 1024,
+            ..Default::default()
+        })
+        .unwrap();
+        let limits = executor
+            .default_limits()
+            .with_scratch(None, true)
+            .with_strict(false);
+        assert!(executor.allows_network(&limits) && executor.allows_filesystem(&limits));
+        assert_eq!(limits.max_memory_mb, 1024);
+
+        let strict = limits.with_strict(true);
+        assert!(!executor.allows_network(&strict) && !executor.allows_filesystem(&strict));
+        assert_eq!(strict.max_memory_mb, STRICT_MAX_MEMORY_MB);
+        assert!(!strict.persist_scratch);
+    }
+
+    // 依赖 sh/sleep 等 Unix 命令
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_script_runs_confined_with_stdin() {
+        let executor = SandboxExecutor::new(SandboxConfig::default()).unwrap();
+        let limits = SandboxLimits::from(&executor.config);
+
+        let result = executor
+            .execute_script_in_sandbox(
+                "sh",
+                "main.sh",
+                r#"read input; printf '{"got": "%s", "cwd": "%s"}' "$input" "$(pwd)""#,
+                Some(b"hello\n"),
+                &limits,
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(result.status, ExecutionStatus::Success));
+        assert_eq!(result.output["got"], "hello");
+        let cwd = PathBuf::from(result.output["cwd"].as_str().unwrap());
+        assert!(cwd.starts_with(std::fs::canonicalize(&executor.config.temp_root).unwrap()));
+
+        assert!(
+            executor
+                .execute_script_in_sandbox("sh", "../escape.sh", "", None, &limits)
+                .await
+                .is_err()
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_namespaces_hide_host_paths_outside_allowed_dirs() {
+        if !IsolationLevel::detect().is_confined() {
+            return;
+        }
+        let host = tempfile::tempdir().unwrap();
+        std::fs::write(host.path().join("secret.txt"), "shared").unwrap();
+        let script = format!("cat {}/secret.txt", host.path().display());
+
+        for allow_filesystem in [false, true] {
+            let root = tempfile::tempdir().unwrap();
+            let executor = SandboxExecutor::new(SandboxConfig {
+                temp_root: root.path().to_path_buf(),
+                allow_filesystem,
+                allowed_dirs: vec![host.path().to_path_buf()],
+                ..Default::default()
+            })
+            .unwrap();
+            let limits = SandboxLimits::from(&executor.config);
+            let result = executor
+                .execute_script_in_sandbox("sh", "main.sh", &script, None, &limits)
+                .await
+                .unwrap();
+            assert_eq!(result.isolation_level, IsolationLevel::Namespaces);
+            assert_eq!(result.stdout == "shared", allow_filesystem, "{result:?}");
+
+            // 允许访问的目录只读
+            let write = format!("echo x > {}/new.txt", host.path().display());
+            executor
+                .execute_script_in_sandbox("sh", "write.sh", &write, None, &limits)
+                .await
+                .unwrap();
+            assert!(!host.path().join("new.txt").exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_function_scripts_receive_input_verbatim() {
+        let root = tempfile::tempdir().unwrap();
+        let executor = SandboxExecutor::new(SandboxConfig {
+            temp_root: root.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        let limits = SandboxLimits::from(&executor.config);
+        let input = serde_json::json!({"text": "''' \"\"\" \\ ${process.exit(1)} `whoami` \\n"});
+
+        for (language, source) in [
+            (
+                ScriptLanguage::Python,
+                "def handler(input):\n    return input",
+            ),
+            (
+                ScriptLanguage::JavaScript,
+                "function handler(input) { return input; }",
+            ),
+        ] {
+            if which::which(language.interpreter()).is_err() {
+                continue;
+            }
+            for _ in 0..2 {
+                let result = executor
+                    .execute_function_script(language, None, source, &input, &context(), &limits)
+                    .await
+                    .unwrap();
+                assert!(matches!(result.status, ExecutionStatus::Success));
+                assert_eq!(result.output, input);
+            }
+        }
+
+        // 第二次调用复用缓存的脚本
+        let stats = executor.script_cache().stats().await;
+        assert_eq!(stats.hits, stats.misses);
+        assert_eq!(stats.entries as u64, stats.misses);
+    }
+
+    /// 带钩子的函数在同一工作进程上执行的事件流和执行器
+    fn worker_executor(root: &Path) -> (SandboxExecutor, Arc<LifecycleEventStream>) {
+        let events = Arc::new(LifecycleEventStream::new(Default::default()));
+        let executor = SandboxExecutor::new(SandboxConfig {
+            temp_root: root.to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_lifecycle_events(events.clone());
+        (executor, events)
+    }
+
+    #[tokio::test]
+    async fn test_init_runs_once_per_worker_and_teardown_sees_state() {
+        for (language, source) in [
+            (
+                ScriptLanguage::Python,
+                "inits = 0\n\
+                 def init(context):\n    global inits\n    inits += 1\n    print('init for', context['function_name'])\n    return {'calls': 0}\n\
+                 def handler(input, context, state):\n    state['calls'] += 1\n    return {'inits': inits, 'calls': state['calls']}\n\
+                 def teardown(state):\n    if state['calls'] != 5:\n        raise RuntimeError('unexpected state')\n",
+            ),
+            (
+                ScriptLanguage::JavaScript,
+                "let inits = 0;\n\
+                 async function init(context) { inits += 1; console.log('init'); return { calls: 0 }; }\n\
+                 function handler(input, context, state) { state.calls += 1; return { inits, calls: state.calls }; }\n\
+                 function teardown(state) { if (state.calls !== 5) throw new Error('unexpected state'); }\n",
+            ),
+        ] {
+            if which::which(language.interpreter()).is_err() {
+                continue;
+            }
+            assert!(language.defines_hooks(source));
+            let root = tempfile::tempdir().unwrap();
+            let (executor, events) = worker_executor(root.path());
+            let limits = SandboxLimits::from(&executor.config);
+
+            for call in 1..=5 {
+                let result = executor
+                    .execute_function_script(
+                        language,
+                        None,
+                        source,
+                        &serde_json::json!({}),
+                        &context(),
+                        &limits,
+                    )
+                    .await
+                    .unwrap();
+                assert!(
+                    matches!(result.status, ExecutionStatus::Success),
+                    "{result:?}"
+                );
+                assert_eq!(
+                    result.output,
+                    serde_json::json!({"inits": 1, "calls": call})
+                );
+                // 初始化只计入启动工作进程的那次调用，其打印输出也只出现在该次调用中
+                if call > 1 {
+                    assert_eq!(result.timing.spawn_ms, Some(0));
+                    assert!(result.stderr.is_empty(), "{}", result.stderr);
+                }
+            }
+            assert_eq!(executor.workers().idle_count(), 1);
+
+            assert_eq!(executor.workers().retire("script").await, 1);
+            assert_eq!(executor.workers().idle_count(), 0);
+            let recent = events.recent(100).await;
+            let kinds: Vec<_> = recent.iter().map(|event| &event.event_type).collect();
+            assert_eq!(
+                kinds,
+                [
+                    &LifecycleEventType::WarmingStarted,
+                    &LifecycleEventType::Ready,
+                    &LifecycleEventType::Stopped
+                ]
+            );
+            assert_eq!(recent[2].metadata["teardown"], "ok");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_init_failure_fails_the_call_and_is_reported() {
+        if which::which("python3").is_err() {
+            return;
+        }
+        let root = tempfile::tempdir().unwrap();
+        let (executor, events) = worker_executor(root.path());
+        let limits = SandboxLimits::from(&executor.config);
+        let source = "def init():\n    raise ValueError('missing config')\n\
+                      def handler(input):\n    return input\n";
+
+        let result = executor
+            .execute_function_script(
+                ScriptLanguage::Python,
+                None,
+                source,
+                &serde_json::json!({}),
+                &context(),
+                &limits,
+            )
+            .await
+            .unwrap();
+        let ExecutionStatus::Error { message, .. } = &result.status else {
+            panic!("expected init failure: {result:?}");
+        };
+        assert!(message.starts_with("Function init failed"), "{message}");
+        assert!(message.contains("missing config"), "{message}");
+        // 初始化失败的工作进程不会被复用
+        assert_eq!(executor.workers().idle_count(), 0);
+
+        let failed = events.recent(100).await.pop().unwrap();
+        assert_eq!(failed.event_type, LifecycleEventType::WarmingFailed);
+        assert!(failed.metadata["error"].contains("missing config"));
+    }
+
+    #[tokio::test]
+    async fn test_affinity_key_reuses_the_same_worker() {
+        if which::which("python3").is_err() {
+            return;
+        }
+        let root = tempfile::tempdir().unwrap();
+        let (executor, _) = worker_executor(root.path());
+        let limits = SandboxLimits::from(&executor.config);
+        let source = "def init():\n    return {}\n\
+                      def handler(input, context, state):\n    return input\n";
+
+        let mut selections = Vec::new();
+        for _ in 0..3 {
+            let route = AffinityRoute::new("tenant-a");
+            let context = context().with_affinity(Some(route.clone()));
+            let result = executor
+                .execute_function_script(
+                    ScriptLanguage::Python,
+                    None,
+                    source,
+                    &serde_json::json!({}),
+                    &context,
+                    &limits,
+                )
+                .await
+                .unwrap();
+            assert!(
+                matches!(result.status, ExecutionStatus::Success),
+                "{result:?}"
+            );
+            selections.push(route.selection().unwrap());
+        }
+        // 首次调用启动工作进程，之后同一个键回到该工作进程
+        assert!(!selections[0].affinity_honored);
+        for selection in &selections[1..] {
+            assert!(selection.affinity_honored);
+            assert_eq!(selection.instance_id, selections[0].instance_id);
+        }
+    }
+
+    #[test]
+    fn test_hooks_are_detected_at_top_level_only() {
+        assert!(ScriptLanguage::Python.defines_hooks("def teardown(state):\n    pass"));
+        assert!(
+            !ScriptLanguage::Python
+                .defines_hooks("def handler(input):\n    def init():\n        pass")
+        );
+        assert!(ScriptLanguage::JavaScript.defines_hooks("const init = async () => ({});"));
+        assert!(ScriptLanguage::JavaScript.defines_hooks("async function init (context) {}"));
+        assert!(!ScriptLanguage::JavaScript.defines_hooks("function initialize() {}"));
+        assert!(
+            !ScriptLanguage::JavaScript.defines_hooks("function handler(input) { return input; }")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_javascript_handler_shapes_run_once_and_are_awaited() {
+        if which::which("node").is_err() {
+            return;
+        }
+        let root = tempfile::tempdir().unwrap();
+        let (executor, _) = worker_executor(root.path());
+        let limits = SandboxLimits::from(&executor.config);
+
+        for (source, expected) in [
+            // 不使用 fetch 的 async 代码
+            (
+                "const sleep = (ms) => new Promise((r) => setTimeout(r, ms));\nasync function handler(input) { await sleep(10); return input.a + 1; }",
+                serde_json::json!(3),
+            ),
+            // 带 `return` 的语句作为处理函数体，`input` 和 `context` 是其参数
+            ("const x = input.a * 2;\nreturn x;", serde_json::json!(4)),
+            (
+                "await new Promise((r) => setTimeout(r, 5));\nreturn 'done';",
+                serde_json::json!("done"),
+            ),
+            // module.exports 处理函数；顶层代码只执行一次
+            (
+                "let loads = 0;\nloads += 1;\nmodule.exports = async (input) => ({ doubled: input.a * 2, loads });",
+                serde_json::json!({"doubled": 4, "loads": 1}),
+            ),
+            (
+                "exports.handler = (input) => Promise.resolve(input.a);",
+                serde_json::json!(2),
+            ),
+            // ES 模块的默认导出
+            (
+                "import { setTimeout as wait } from 'node:timers/promises';\nlet loads = 0;\nloads += 1;\nexport default async function (input) { await wait(5); return [input.a, loads]; }",
+                serde_json::json!([2, 1]),
+            ),
+            // 定义了钩子时由工作进程执行，同样支持 module.exports
+            (
+                "function init() { return 40; }\nmodule.exports = (input, context, state) => state + input.a;",
+                serde_json::json!(42),
+            ),
+        ] {
+            let result = executor
+                .execute_function_script(
+                    ScriptLanguage::JavaScript,
+                    None,
+                    source,
+                    &serde_json::json!({"a": 2}),
+                    &context(),
+                    &limits,
+                )
+                .await
+                .unwrap();
+            assert_eq!(
+                result.status,
+                ExecutionStatus::Success,
+                "{source}: {result:?}"
+            );
+            assert_eq!(result.output, expected, "{source}");
+        }
+        executor.workers().retire("script").await;
+    }
+
+    #[tokio::test]
+    async fn test_javascript_rejections_report_the_stack() {
+        if which::which("node").is_err() {
+            return;
+        }
+        let root = tempfile::tempdir().unwrap();
+        let (executor, _) = worker_executor(root.path());
+        let limits = SandboxLimits::from(&executor.config);
+
+        for source in [
+            "async function handler(input) { await null; throw new TypeError('bad input'); }",
+            "export default async () => { await null; throw new TypeError('bad input'); };",
+            "function init() { return null; }\nasync function handler() { await null; throw new TypeError('bad input'); }",
+        ] {
+            let result = executor
+                .execute_function_script(
+                    ScriptLanguage::JavaScript,
+                    None,
+                    source,
+                    &serde_json::json!(null),
+                    &context(),
+                    &limits,
+                )
+                .await
+                .unwrap();
+            let ExecutionStatus::Error { message, .. } = &result.status else {
+                panic!("{source}: {result:?}");
+            };
+            assert!(message.contains("TypeError: bad input"), "{message}");
+            assert_eq!(result.output["error"], "bad input", "{source}");
+            let stack = result.output["stack"].as_str().unwrap();
+            assert!(stack.starts_with("TypeError: bad input\n"), "{stack}");
+            assert!(stack.contains("    at "), "{stack}");
+        }
+
+        // 找不到处理函数或处理函数没有输出结果时给出明确的错误，而不是空结果
+        for (source, error) in [
+            ("export const answer = 42;", "No handler found"),
+            ("process.exit(0);", "Handler produced no result"),
+        ] {
+            let result = executor
+                .execute_function_script(
+                    ScriptLanguage::JavaScript,
+                    None,
+                    source,
+                    &serde_json::json!(null),
+                    &context(),
+                    &limits,
+                )
+                .await
+                .unwrap();
+            let ExecutionStatus::Error { kind, .. } = &result.status else {
+                panic!("{source}: {result:?}");
+            };
+            assert_eq!(*kind, ErrorKind::Runtime, "{source}");
+            assert!(
+                result.output["error"].as_str().unwrap().starts_with(error),
+                "{source}: {result:?}"
+            );
+        }
+        executor.workers().retire("script").await;
+    }
+
+    #[tokio::test]
+    async fn test_function_scripts_split_spawn_from_execute() {
+        let root = tempfile::tempdir().unwrap();
+        let executor = SandboxExecutor::new(SandboxConfig {
+            temp_root: root.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        let limits = SandboxLimits::from(&executor.config);
+
+        for (language, source) in [
+            (
+                ScriptLanguage::Python,
+                "import sys, time\n\ndef handler(input):\n    sys.stderr.write('working\\n')\n    time.sleep(0.2)\n    raise ValueError('boom')",
+            ),
+            (
+                ScriptLanguage::JavaScript,
+                "async function handler(input) { console.error('working'); await new Promise((r) => setTimeout(r, 200)); throw new Error('boom'); }",
+            ),
+        ] {
+            if which::which(language.interpreter()).is_err() {
+                continue;
+            }
+            let result = executor
+                .execute_function_script(
+                    language,
+                    None,
+                    source,
+                    &serde_json::json!(null),
+                    &context(),
+                    &limits,
+                )
+                .await
+                .unwrap();
+            assert!(matches!(result.status, ExecutionStatus::Error { .. }));
+            // 开始标记不出现在标准错误和错误信息中，函数体自己的输出保留
+            assert!(
+                !result.stderr.contains(BODY_START_MARKER),
+                "{}",
+                result.stderr
+            );
+            assert!(result.stderr.starts_with("working"), "{}", result.stderr);
+
+            let timing = &result.timing;
+            assert!(timing.spawn_ms.is_some(), "{timing:?}");
+            assert!(timing.execute_ms.unwrap() >= 200, "{timing:?}");
+            assert_eq!(timing.compile_ms, None);
+            assert!(
+                timing.total_ms.abs_diff(timing.phases_ms()) <= 2,
+                "{timing:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_body_start_marker_is_stripped_once() {
+        let stderr = format!("{BODY_START_MARKER} 1700000000000000\nwarn\n{BODY_START_MARKER} x\n");
+        let (rest, started) = take_body_start(&stderr);
+        assert_eq!(
+            started,
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(rest, format!("warn\n{BODY_START_MARKER} x\n"));
+
+        let (rest, started) = take_body_start("Traceback\n");
+        assert_eq!((rest.as_str(), started), ("Traceback\n", None));
+    }
+
+    #[tokio::test]
+    async fn test_named_runtime_uses_definition_binary_and_env() {
+        let Ok(python) = which::which("python3") else {
+            return;
+        };
+        let root = tempfile::tempdir().unwrap();
+        let environment = RuntimeEnvironment::default();
+        environment
+            .configure(&RuntimeProbeConfig {
+                definitions: vec![RuntimeDefinition {
+                    name: "venv".to_string(),
+                    kind: RuntimeKind::Python,
+                    binary: python,
+                    env: [("FLUX_RUNTIME".to_string(), "venv".to_string())].into(),
+                }],
+                ..Default::default()
+            })
+            .unwrap();
+        let executor = SandboxExecutor::new(SandboxConfig {
+            temp_root: root.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_environment(Arc::new(environment));
+        let limits = SandboxLimits::from(&executor.config);
+        let source = "import os\n\ndef handler(input):\n    return os.environ.get('FLUX_RUNTIME')";
+
+        let result = executor
+            .execute_function_script(
+                ScriptLanguage::Python,
+                Some("venv"),
+                source,
+                &serde_json::json!(null),
+                &context(),
+                &limits,
+            )
+            .await
+            .unwrap();
+        assert!(
+            matches!(result.status, ExecutionStatus::Success),
+            "{result:?}"
+        );
+        assert_eq!(result.output, "venv");
+
+        // 种类不匹配的执行环境在启动进程前失败
+        assert!(
+            executor
+                .execute_function_script(
+                    ScriptLanguage::JavaScript,
+                    Some("venv"),
+                    "function handler() {}",
+                    &serde_json::json!(null),
+                    &context(),
+                    &limits,
+                )
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_function_scripts_receive_invocation_context() {
+        let root = tempfile::tempdir().unwrap();
+        let executor = SandboxExecutor::new(SandboxConfig {
+            temp_root: root.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        let limits = SandboxLimits::from(&executor.config);
+        let mut function = FunctionMetadata::new("ctx".to_string(), String::new());
+        function.timeout_ms = 5000;
+        let context = InvocationContext::new(&function, "inv-42")
+            .with_attempt(2)
+            .with_caller(CallerInfo {
+                api_key_id: Some("key-1".to_string()),
+                ip: Some("10.0.0.7".to_string()),
+            })
+            // 已消耗 1 秒（排队和编译）
+            .with_deadline(Instant::now() + Duration::from_millis(4000));
+
+        for (language, source) in [
+            (
+                ScriptLanguage::Python,
+                "def handler(input, ctx):\n    return {'arg': ctx, 'global': context, 'input': input}",
+            ),
+            (
+                ScriptLanguage::JavaScript,
+                "function handler(input, ctx) { return {arg: ctx, global: context, input}; }",
+            ),
+        ] {
+            if which::which(language.interpreter()).is_err() {
+                continue;
+            }
+            let result = executor
+                .execute_function_script(
+                    language,
+                    None,
+                    source,
+                    &serde_json::json!(7),
+                    &context,
+                    &limits,
+                )
+                .await
+                .unwrap();
+            assert!(
+                matches!(result.status, ExecutionStatus::Success),
+                "{result:?}"
+            );
+            let received = &result.output["arg"];
+            assert_eq!(received, &result.output["global"]);
+            assert_eq!(result.output["input"], 7);
+            assert_eq!(received["invocation_id"], "inv-42");
+            assert_eq!(received["function_name"], "ctx");
+            assert_eq!(received["version"], function.version);
+            assert_eq!(received["attempt"], 2);
+            assert_eq!(
+                received["caller"],
+                serde_json::json!({"api_key_id": "key-1", "ip": "10.0.0.7"})
+            );
+            let remaining = received["deadline_ms_remaining"].as_u64().unwrap();
+            assert!(remaining > 0 && remaining <= 4000, "{remaining}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_package_scripts_resolve_relative_modules() {
+        let root = tempfile::tempdir().unwrap();
+        let executor = SandboxExecutor::new(SandboxConfig {
+            temp_root: root.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        let limits = SandboxLimits::from(&executor.config);
+        let package = |entrypoint: &str, files: &[(&str, &str)]| FunctionPackage {
+            entrypoint: entrypoint.to_string(),
+            files: files
+                .iter()
+                .map(|(path, content)| PackageFile {
+                    path: path.to_string(),
+                    content: content.to_string(),
+                })
+                .collect(),
+        };
+
+        for (language, package) in [
+            (
+                ScriptLanguage::Python,
+                package(
+                    "main.py",
+                    &[
+                        (
+                            "main.py",
+                            "from lib.util import double\ndef handler(input):\n    return double(input)",
+                        ),
+                        ("lib/__init__.py", ""),
+                        ("lib/util.py", "def double(x):\n    return x * 2"),
+                    ],
+                ),
+            ),
+            (
+                ScriptLanguage::JavaScript,
+                package(
+                    "index.js",
+                    &[
+                        (
+                            "index.js",
+                            "const { double } = require('./lib/util');\nfunction handler(input) { return double(input); }",
+                        ),
+                        ("lib/util.js", "module.exports.double = (x) => x * 2;"),
+                    ],
+                ),
+            ),
+        ] {
+            if which::which(language.interpreter()).is_err() {
+                continue;
+            }
+            let result = executor
+                .execute_package_script(
+                    language,
+                    None,
+                    &package,
+                    &serde_json::json!(21),
+                    &context(),
+                    &limits,
+                )
+                .await
+                .unwrap();
+            assert!(
+                matches!(result.status, ExecutionStatus::Success),
+                "{result:?}"
+            );
+            assert_eq!(result.output, 42);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_timeout_is_killed_with_structured_status() {
+        let executor = SandboxExecutor::new(SandboxConfig::default()).unwrap();
+        let limits = SandboxLimits {
+            execution_timeout_secs: 1,
+            ..SandboxLimits::from(&executor.config)
+        };
+
+        let result = executor
+            .execute_command_in_sandbox("sleep", &["10".to_string()], None, &limits)
+            .await
+            .unwrap();
+
+        assert_eq!(result.killed_by, Some(ResourceLimit::Timeout));
+        assert_eq!(result.status, ExecutionStatus::Timeout);
+        assert_eq!(result.output["error"], "Execution timeout");
+        assert_eq!(result.resource_monitoring, ResourceMonitoring::current());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_script_timeout_kills_and_reaps_the_interpreter() {
+        if which::which("python3").is_err() {
+            return;
+        }
+        let executor = SandboxExecutor::new(SandboxConfig::default()).unwrap();
+        let limits = SandboxLimits::from(&executor.config);
+        let mut function = FunctionMetadata::new("sleepy".to_string(), String::new());
+        function.timeout_ms = 1000;
+        // 忽略 SIGTERM，只能在宽限期后被 SIGKILL 终止
+        let source = r#"
+import os, signal, time
+
+def handler(input, context):
+    signal.signal(signal.SIGTERM, signal.SIG_IGN)
+    with open("pid", "w") as f:
+        f.write(str(os.getpid()))
+    time.sleep(30)
+"#;
+
+        let started = Instant::now();
+        let result = executor
+            .execute_function_script(
+                ScriptLanguage::Python,
+                None,
+                source,
+                &serde_json::json!(null),
+                &InvocationContext::new(&function, "inv-1"),
+                &limits,
+            )
+            .await
+            .unwrap();
+        // 按函数自身的超时（1 秒）终止，而不是沙箱的执行超时（30 秒）。截止时间从调用开始计算，
+        // 进程启动的耗时不计入 ran_for_ms，因此只检查上限
+        assert_eq!(result.killed_by, Some(ResourceLimit::Timeout));
+        let termination = result
+            .termination
+            .expect("timed out process was not terminated");
+        assert!(termination.forced, "{termination:?}");
+        assert!(termination.ran_for_ms < 2000, "{termination:?}");
+        // SIGTERM 被忽略后等待宽限期再 SIGKILL
+        let elapsed = started.elapsed();
+        assert!(
+            elapsed >= Duration::from_secs(1) + TERMINATION_GRACE,
+            "{elapsed:?}"
+        );
+        assert!(elapsed < Duration::from_secs(5), "{elapsed:?}");
+
+        // 解释器进程不再存在（也没有留下僵尸进程）
+        let pid = executor
+            .active_work_dirs()
+            .await
+            .into_iter()
+            .find_map(|dir| std::fs::read_to_string(dir.join("pid")).ok())
+            .expect("script did not record its pid");
+        let proc = PathBuf::from(format!("/proc/{}", pid.trim()));
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while proc.exists() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(!proc.exists(), "process {} is still running", pid.trim());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_script_sees_remaining_deadline() {
+        if which::which("python3").is_err() {
+            return;
+        }
+        let executor = SandboxExecutor::new(SandboxConfig::default()).unwrap();
+        let limits = SandboxLimits::from(&executor.config);
+        let mut function = FunctionMetadata::new("deadline".to_string(), String::new());
+        function.timeout_ms = 5000;
+        let source = r#"
+import os
+
+def handler(input, context):
+    return int(os.environ["FLUX_DEADLINE_MS"])
+"#;
+        let result = executor
+            .execute_function_script(
+                ScriptLanguage::Python,
+                None,
+                source,
+                &serde_json::json!(null),
+                &InvocationContext::new(&function, "inv-1"),
+                &limits,
+            )
+            .await
+            .unwrap();
+        assert!(
+            matches!(result.status, ExecutionStatus::Success),
+            "{result:?}"
+        );
+        let remaining = result.output.as_u64().unwrap();
+        assert!((1..=5000).contains(&remaining), "{remaining}");
+        assert!(result.termination.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_concurrency_cap_queues_or_rejects_the_extra_call() {
+        let run = |executor: Arc<SandboxExecutor>| async move {
+            let limits = SandboxLimits::from(&executor.config);
+            executor
+                .execute_command_in_sandbox("sleep", &["1".to_string()], None, &limits)
+                .await
+        };
+
+        // 无等待队列：N+1 个并发调用中恰好一个被拒绝
+        let executor = Arc::new(
+            SandboxExecutor::new(SandboxConfig {
+                max_concurrent_executions: 2,
+                max_queued_executions: 0,
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let results = futures_util::future::join_all((0..3).map(|_| run(executor.clone()))).await;
+        let rejected: Vec<_> = results.iter().filter_map(|r| r.as_ref().err()).collect();
+        assert_eq!(rejected.len(), 1);
+        assert!(matches!(
+            rejected[0].downcast_ref(),
+            Some(SandboxAdmissionError::QueueFull { capacity: 0, .. })
+        ));
+        assert_eq!(executor.get_executor_stats().rejected, 1);
+
+        // 有等待队列：恰好一个排队，随后正常完成
+        let executor = Arc::new(
+            SandboxExecutor::new(SandboxConfig {
+                max_concurrent_executions: 2,
+                max_queued_executions: 4,
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let calls: Vec<_> = (0..3)
+            .map(|_| tokio::spawn(run(executor.clone())))
+            .collect();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let stats = executor.get_executor_stats();
+        assert_eq!((stats.running, stats.queued), (2, 1));
+        for call in calls {
+            let result = call.await.unwrap().unwrap();
+            assert_eq!(result.status, ExecutionStatus::Success);
+        }
+        assert_eq!(executor.get_executor_stats().rejected, 0);
+    }
+
+    #[tokio::test]
+    async fn test_quota_memory_limit_kills_and_names_the_quota() {
+        if !ResourceMonitoring::current().is_supported() {
+            return;
+        }
+        let Ok(python) = which::which("python3") else {
+            return;
+        };
+        let executor = SandboxExecutor::new(SandboxConfig::default()).unwrap();
+        let manager = ResourceManager::new();
+        let mut quota = ResourceQuota {
+            name: "tight".to_string(),
+            ..Default::default()
+        };
+        quota.limits.insert(
+            ResourceType::Memory,
+            QuotaLimit {
+                resource_type: ResourceType::Memory,
+                soft_limit: 16 * 1024 * 1024,
+                hard_limit: 32 * 1024 * 1024,
+                check_interval_ms: 100,
+                enabled: true,
+            },
+        );
+        manager.create_quota(quota).await.unwrap();
+
+        // 执行时从管理器读取配额，内存上限取其硬限制
+        let quota = manager.get_quota("tight").await.unwrap();
+        let limits = executor.default_limits().with_quota(&quota);
+        assert_eq!(limits.max_memory_mb, 32);
+
+        let result = executor
+            .execute_command_in_sandbox(
+                &python,
+                &[
+                    "-c".to_string(),
+                    "import time\nx = b'x' * (256 * 1024 * 1024)\ntime.sleep(5)".to_string(),
+                ],
+                None,
+                &limits,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.killed_by, Some(ResourceLimit::Memory));
+        assert_eq!(result.quota_name.as_deref(), Some("tight"));
+        assert_eq!(
+            result.output["error"],
+            "Memory limit exceeded (quota 'tight')"
+        );
+
+        // 执行峰值计入配额：记为违规事件并出现在用量汇总中
+        let summary = manager
+            .record_execution(
+                0,
+                "hungry",
+                &quota,
+                result.execution_time_ms,
+                &HashMap::from([(ResourceType::Memory, result.peak_memory_bytes)]),
+            )
+            .await;
+        assert_eq!(summary.quota_name, "tight");
+        let events = manager.recent_events().await;
+        assert!(matches!(events[0].event_type, ResourceEventType::Violation));
+        assert!(events[0].description.contains("quota 'tight'"));
+        let usage = manager.quota_usage("tight").await.unwrap();
+        assert_eq!((usage.executions, usage.violations), (1, 1));
+    }
+
     #[test]
     fn test_executor_source_generation() {
         let config = SandboxConfig::default();
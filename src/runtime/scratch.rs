@@ -0,0 +1,398 @@
+//! 函数调用的暂存目录
+//!
+//! 每次脚本和编译函数的调用都在隔离目录中得到一个新的可写目录 `scratch`，路径经上下文的
+//! `scratch_dir` 和环境变量 `FLUX_SCRATCH_DIR` 传给函数。沙箱在执行期间定期、在结束后再检查
+//! 一次目录大小，超出上限时按 `ResourceKind::Disk` 终止调用。调用结束（包括超时和被终止）后
+//! 目录被删除；函数设置了 `persist_scratch` 时目录移入 [`ScratchStore`]，可通过
+//! `GET /executions/:id/artifacts` 列出和下载，过了保留时长后由磁盘清理器删除。
+
+use crate::functions::{FluxError, Result};
+use crate::runtime::debug_capture::file_stem;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use utoipa::ToSchema;
+
+/// 隔离目录中暂存目录的名称
+pub const SCRATCH_DIR: &str = "scratch";
+
+/// 告知函数进程暂存目录路径的环境变量
+pub const SCRATCH_ENV: &str = "FLUX_SCRATCH_DIR";
+
+/// 保留的暂存目录所在的子目录（`temp_root/artifacts`）
+pub const ARTIFACTS_DIR: &str = "artifacts";
+
+/// 嗅探内容类型时读取的字节数
+const SNIFF_BYTES: usize = 512;
+
+/// 调用保留的一个暂存文件
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ScratchArtifact {
+    /// 相对暂存目录的路径（以 `/` 分隔）
+    pub path: String,
+    pub size_bytes: u64,
+    /// 按文件开头的字节嗅探的内容类型
+    pub content_type: String,
+    pub modified_at: DateTime<Utc>,
+}
+
+/// 调用保留的全部暂存文件
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ScratchArtifacts {
+    pub invocation_id: String,
+    pub total_bytes: u64,
+    /// 过了该时刻后文件被删除
+    pub expires_at: DateTime<Utc>,
+    /// 超出下载上限的文件仍会列出，但不能下载
+    pub max_download_bytes: u64,
+    pub files: Vec<ScratchArtifact>,
+}
+
+/// 在隔离目录中创建空的暂存目录（已存在时先删除，工作进程复用隔离目录）
+pub fn create(jail: &Path) -> std::io::Result<PathBuf> {
+    let scratch = jail.join(SCRATCH_DIR);
+    if fs::symlink_metadata(&scratch).is_ok() {
+        fs::remove_dir_all(&scratch)?;
+    }
+    fs::create_dir(&scratch)?;
+    Ok(scratch)
+}
+
+/// 目录树中普通文件的总字节数（不跟随符号链接）
+pub fn usage(path: &Path) -> u64 {
+    let mut bytes = 0;
+    let mut stack = vec![path.to_path_buf()];
+    while let Some(path) = stack.pop() {
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = fs::read_dir(&path) {
+                stack.extend(entries.flatten().map(|entry| entry.path()));
+            }
+        } else if metadata.is_file() {
+            bytes += metadata.len();
+        }
+    }
+    bytes
+}
+
+/// 保留的暂存目录：`<根目录>/<调用 ID>`，按保留时长过期
+#[derive(Debug)]
+pub struct ScratchStore {
+    root: PathBuf,
+    ttl: Duration,
+    max_download_bytes: u64,
+}
+
+impl ScratchStore {
+    pub fn new(root: impl Into<PathBuf>, ttl: Duration, max_download_bytes: u64) -> Self {
+        Self {
+            root: root.into(),
+            ttl,
+            max_download_bytes,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 调用结束后处理暂存目录：`persist_as` 为调用 ID 时移入存储，否则删除
+    pub fn finish(&self, scratch: &Path, persist_as: Option<&str>) {
+        if let Some(invocation_id) = persist_as {
+            match self.persist(invocation_id, scratch) {
+                Ok(()) => return,
+                Err(e) => {
+                    tracing::warn!("Failed to persist scratch dir of {}: {}", invocation_id, e)
+                }
+            }
+        }
+        if let Err(e) = fs::remove_dir_all(scratch)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::debug!("Failed to remove scratch dir {:?}: {}", scratch, e);
+        }
+    }
+
+    /// 把暂存目录移入存储（同一调用重试时替换上一次尝试留下的文件）
+    fn persist(&self, invocation_id: &str, scratch: &Path) -> Result<()> {
+        let target = self.root.join(file_stem(invocation_id)?);
+        fs::create_dir_all(&self.root)?;
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(scratch, &target)?;
+        // 保留时长从调用结束时算起
+        if let Ok(dir) = fs::File::open(&target) {
+            let _ = dir.set_modified(SystemTime::now());
+        }
+        Ok(())
+    }
+
+    /// 调用保留的文件（按路径排序）
+    pub fn list(&self, invocation_id: &str) -> Result<ScratchArtifacts> {
+        let (dir, persisted_at) = self.existing(invocation_id)?;
+        let mut files = Vec::new();
+        let mut stack = vec![dir.clone()];
+        while let Some(path) = stack.pop() {
+            let Ok(entries) = fs::read_dir(&path) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Ok(metadata) = fs::symlink_metadata(&path) else {
+                    continue;
+                };
+                if metadata.is_dir() {
+                    stack.push(path);
+                } else if metadata.is_file() {
+                    let relative = path.strip_prefix(&dir).unwrap_or(&path);
+                    files.push(ScratchArtifact {
+                        path: relative
+                            .components()
+                            .map(|part| part.as_os_str().to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join("/"),
+                        size_bytes: metadata.len(),
+                        content_type: sniff_file(&path),
+                        modified_at: metadata.modified().map(DateTime::from).unwrap_or_default(),
+                    });
+                }
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(ScratchArtifacts {
+            invocation_id: invocation_id.to_string(),
+            total_bytes: files.iter().map(|file| file.size_bytes).sum(),
+            expires_at: DateTime::<Utc>::from(persisted_at + self.ttl),
+            max_download_bytes: self.max_download_bytes,
+            files,
+        })
+    }
+
+    /// 读取调用保留的一个文件，返回内容和嗅探的内容类型
+    pub fn read(&self, invocation_id: &str, path: &str) -> Result<(Vec<u8>, String)> {
+        let not_found = || FluxError::ArtifactNotFound {
+            id: invocation_id.to_string(),
+            path: path.to_string(),
+        };
+        let relative = Path::new(path);
+        if path.is_empty()
+            || !relative
+                .components()
+                .all(|part| matches!(part, Component::Normal(_)))
+        {
+            return Err(not_found());
+        }
+        let (dir, _) = self.existing(invocation_id)?;
+        let file = dir.join(relative);
+        // 不跟随符号链接（包括路径中间的目录），函数不能借此读出暂存目录以外的文件
+        let metadata = fs::symlink_metadata(&file).map_err(|_| not_found())?;
+        let inside = match (file.canonicalize(), dir.canonicalize()) {
+            (Ok(file), Ok(dir)) => file.starts_with(dir),
+            _ => false,
+        };
+        if !metadata.is_file() || !inside {
+            return Err(not_found());
+        }
+        if metadata.len() > self.max_download_bytes {
+            return Err(FluxError::ArtifactTooLarge {
+                path: path.to_string(),
+                size: metadata.len(),
+                limit: self.max_download_bytes,
+            });
+        }
+        let content = fs::read(&file)?;
+        let content_type = sniff(&content[..content.len().min(SNIFF_BYTES)]).to_string();
+        Ok((content, content_type))
+    }
+
+    /// 删除过了保留时长的暂存目录，返回删除的目录数和释放的字节数
+    pub fn sweep(&self) -> (usize, u64) {
+        let Ok(entries) = fs::read_dir(&self.root) else {
+            return (0, 0);
+        };
+        let (mut removed, mut freed) = (0, 0);
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !entry.metadata().is_ok_and(|metadata| metadata.is_dir()) || !self.is_expired(&path)
+            {
+                continue;
+            }
+            let bytes = usage(&path);
+            match fs::remove_dir_all(&path) {
+                Ok(()) => {
+                    removed += 1;
+                    freed += bytes;
+                }
+                Err(e) => tracing::debug!("Failed to remove scratch artifacts {:?}: {}", path, e),
+            }
+        }
+        (removed, freed)
+    }
+
+    /// 未过期的保留目录和保留时刻，不存在或已过期时返回 `ArtifactsNotFound`
+    fn existing(&self, invocation_id: &str) -> Result<(PathBuf, SystemTime)> {
+        let not_found = || FluxError::ArtifactsNotFound {
+            id: invocation_id.to_string(),
+        };
+        let dir = self
+            .root
+            .join(file_stem(invocation_id).map_err(|_| not_found())?);
+        let persisted_at = fs::metadata(&dir)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|_| not_found())?;
+        if self.is_expired(&dir) {
+            return Err(not_found());
+        }
+        Ok((dir, persisted_at))
+    }
+
+    fn is_expired(&self, dir: &Path) -> bool {
+        fs::metadata(dir)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| {
+                SystemTime::now()
+                    .duration_since(modified)
+                    .is_ok_and(|age| age > self.ttl)
+            })
+    }
+}
+
+fn sniff_file(path: &Path) -> String {
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    if let Ok(file) = fs::File::open(path) {
+        let _ = file.take(SNIFF_BYTES as u64).read_to_end(&mut head);
+    }
+    sniff(&head).to_string()
+}
+
+/// 按文件开头的魔数判断内容类型，无法识别的 UTF-8 文本为 `text/plain`，其余为二进制
+fn sniff(head: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+    ];
+    if let Some((_, content_type)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| head.starts_with(signature))
+    {
+        return content_type;
+    }
+    if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    // 截断处可能落在多字节字符中间
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&head[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return "application/octet-stream",
+    };
+    if text.contains('\0') {
+        return "application/octet-stream";
+    }
+    match text.trim_start().chars().next() {
+        Some('{' | '[') => "application/json",
+        Some('<') if text.trim_start().to_ascii_lowercase().starts_with("<svg") => "image/svg+xml",
+        Some('<') => "text/html",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniffs_common_formats() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0"), "image/png");
+        assert_eq!(sniff(b"%PDF-1.7"), "application/pdf");
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(sniff(b"  {\"a\": 1}"), "application/json");
+        assert_eq!(sniff("列,值\n".as_bytes()), "text/plain; charset=utf-8");
+        // 截断在多字节字符中间仍按文本处理
+        assert_eq!(sniff(&"值".as_bytes()[..2]), "text/plain; charset=utf-8");
+        assert_eq!(sniff(b"a\0b"), "application/octet-stream");
+        assert_eq!(sniff(&[0xff, 0xfe, 0x00]), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_persisted_scratch_is_listed_read_and_swept() {
+        let root = tempfile::tempdir().unwrap();
+        let jail = root.path().join("jail");
+        fs::create_dir(&jail).unwrap();
+        let scratch = create(&jail).unwrap();
+        fs::create_dir(scratch.join("out")).unwrap();
+        fs::write(scratch.join("out/report.json"), b"{\"ok\": true}").unwrap();
+        fs::write(scratch.join("big.bin"), vec![0u8; 2048]).unwrap();
+        #[cfg(unix)]
+        {
+            fs::write(root.path().join("secret.txt"), b"host").unwrap();
+            std::os::unix::fs::symlink(root.path(), scratch.join("escape")).unwrap();
+        }
+        assert_eq!(usage(&scratch), 2048 + 12);
+
+        let store = ScratchStore::new(
+            root.path().join(ARTIFACTS_DIR),
+            Duration::from_secs(60),
+            1024,
+        );
+        store.finish(&scratch, Some("inv:1"));
+        assert!(!scratch.exists());
+
+        let artifacts = store.list("inv:1").unwrap();
+        let paths: Vec<_> = artifacts.files.iter().map(|file| &file.path).collect();
+        assert_eq!(paths, ["big.bin", "out/report.json"]);
+        assert_eq!(artifacts.total_bytes, 2060);
+        assert_eq!(artifacts.files[1].content_type, "application/json");
+
+        let (content, content_type) = store.read("inv:1", "out/report.json").unwrap();
+        assert_eq!(content, b"{\"ok\": true}");
+        assert_eq!(content_type, "application/json");
+        assert!(matches!(
+            store.read("inv:1", "big.bin"),
+            Err(FluxError::ArtifactTooLarge { size: 2048, .. })
+        ));
+        for path in [
+            "../jail",
+            "/etc/passwd",
+            "out/../big.bin",
+            "missing",
+            "escape/secret.txt",
+        ] {
+            assert!(
+                matches!(
+                    store.read("inv:1", path),
+                    Err(FluxError::ArtifactNotFound { .. })
+                ),
+                "{path}"
+            );
+        }
+        assert!(matches!(
+            store.list("inv-2"),
+            Err(FluxError::ArtifactsNotFound { .. })
+        ));
+
+        // 未保留的暂存目录直接删除
+        let scratch = create(&jail).unwrap();
+        store.finish(&scratch, None);
+        assert!(!scratch.exists());
+
+        assert_eq!(store.sweep(), (0, 0));
+        let expired = ScratchStore::new(store.root(), Duration::ZERO, 1024);
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(expired.list("inv:1").is_err());
+        assert_eq!(expired.sweep(), (1, 2060));
+    }
+}
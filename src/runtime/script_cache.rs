@@ -0,0 +1,871 @@
+use crate::functions::context::InvocationContext;
+use crate::functions::package::FunctionPackage;
+use crate::runtime::platform;
+use crate::runtime::sandbox::BODY_START_MARKER;
+use crate::runtime::scratch::SCRATCH_ENV;
+use crate::runtime::workers::CALL_END_MARKER;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use tokio::io::AsyncWriteExt;
+
+/// 包装脚本格式版本，修改包装模板时递增以作废旧的缓存脚本
+pub const WRAPPER_VERSION: u32 = 7;
+
+/// 缓存脚本的默认闲置时长，超过后由淘汰过程删除
+pub const DEFAULT_MAX_IDLE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 两次自动淘汰之间的最小间隔
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// JavaScript 包装脚本提供的状态访问函数，经上下文中的 `state.url` 和 `state.token` 访问回环接口
+const JS_STATE_HELPERS: &str = r#"globalThis.__fluxState = async (op, key, fields) => {
+  const state = globalThis.context && globalThis.context.state;
+  if (!state || !state.url) throw new Error('State is not available to this invocation');
+  const res = await fetch(state.url, {
+    method: 'POST',
+    headers: { 'content-type': 'application/json', 'x-flux-state-token': state.token },
+    body: JSON.stringify({ ...fields, op, key }),
+  });
+  const reply = await res.json().catch(() => ({}));
+  if (!res.ok) throw new Error(reply.error || ('State ' + op + ' failed with status ' + res.status));
+  return reply.data === undefined ? null : reply.data;
+};
+globalThis.state_entry = (key) => globalThis.__fluxState('get', key, {});
+globalThis.state_get = async (key) => {
+  const entry = await globalThis.state_entry(key);
+  return entry ? entry.value : null;
+};
+globalThis.state_set = (key, value, ttl, expectedVersion) =>
+  globalThis.__fluxState('set', key, { value: value === undefined ? null : value, ttl_secs: ttl, expected_version: expectedVersion });
+globalThis.state_delete = (key) => globalThis.__fluxState('delete', key, {});
+"#;
+
+/// Python 包装脚本提供的状态访问函数（同 [`JS_STATE_HELPERS`]）
+const PY_STATE_HELPERS: &str = r#"def __flux_state(op, key, **fields):
+    import json, urllib.error, urllib.request
+    state = (globals().get('context') or {}).get('state') or {}
+    if not state.get('url'):
+        raise RuntimeError('State is not available to this invocation')
+    request = urllib.request.Request(
+        state['url'],
+        data=json.dumps(dict(fields, op=op, key=key)).encode(),
+        method='POST',
+        headers={'content-type': 'application/json', 'x-flux-state-token': state['token']},
+    )
+    opener = urllib.request.build_opener(urllib.request.ProxyHandler({}))
+    try:
+        with opener.open(request) as response:
+            reply = json.loads(response.read() or b'{}')
+    except urllib.error.HTTPError as e:
+        reply = json.loads(e.read() or b'{}')
+        raise RuntimeError(reply.get('error') or 'State %s failed with status %s' % (op, e.code))
+    return reply.get('data')
+
+def state_entry(key):
+    return __flux_state('get', key)
+
+def state_get(key):
+    entry = state_entry(key)
+    return None if entry is None else entry['value']
+
+def state_set(key, value, ttl=None, expected_version=None):
+    return __flux_state('set', key, value=value, ttl_secs=ttl, expected_version=expected_version)
+
+def state_delete(key):
+    return __flux_state('delete', key)
+"#;
+
+/// JavaScript 包装脚本提供的出站 HTTP 函数，经上下文中的 `egress.url` 和 `egress.token` 交给宿主发送；
+/// 请求可以是 URL 字符串，非字符串的 `body` 按 JSON 发送
+const JS_EGRESS_HELPERS: &str = r#"globalThis.http_fetch = async (request) => {
+  const egress = globalThis.context && globalThis.context.egress;
+  if (!egress || !egress.url) throw new Error('HTTP fetch is not available to this invocation');
+  const req = typeof request === 'string' ? { url: request } : { ...request };
+  if (req.body !== undefined && req.body !== null && typeof req.body !== 'string') {
+    req.headers = { 'content-type': 'application/json', ...(req.headers || {}) };
+    req.body = JSON.stringify(req.body);
+  }
+  const res = await fetch(egress.url, {
+    method: 'POST',
+    headers: { 'content-type': 'application/json', 'x-flux-egress-token': egress.token },
+    body: JSON.stringify(req),
+  });
+  const reply = await res.json().catch(() => ({}));
+  if (!res.ok) throw new Error(reply.error || ('HTTP fetch failed with status ' + res.status));
+  return reply.data;
+};
+"#;
+
+/// JavaScript 包装脚本查找处理函数：顶层 `handler`、`module.exports = fn`（或其 `handler`/`default` 属性），
+/// ES 模块还查找 `export default fn`（见 [`js_handler_lookup`]）；找不到时报告明确的错误
+const JS_HANDLER_LOOKUP: &str = r#"const __fluxHandler = async () => {
+  if (typeof handler === 'function') return handler;
+  const exported = typeof module === 'undefined' ? undefined : module.exports;
+  if (typeof exported === 'function') return exported;
+  if (exported && typeof exported.handler === 'function') return exported.handler;
+  if (exported && typeof exported.default === 'function') return exported.default;
+  const namespace = await __fluxModule();
+  if (namespace && typeof namespace.default === 'function') return namespace.default;
+  throw new Error('No handler found: define handler(input, context), assign module.exports = handler or export default handler');
+};
+const __fluxError = (error) => ({
+  error: String((error && error.message) || error),
+  name: (error && error.name) || 'Error',
+  stack: (error && error.stack) || null,
+});
+"#;
+
+/// 没有以可查找的形式提供处理函数的 JavaScript 代码（带 `return` 的语句）作为处理函数体包装
+const JS_STATEMENTS_PREFIX: &str = "async function handler(input, context) {\n";
+
+/// [`JS_HANDLER_LOOKUP`] 读取模块命名空间的方式：ES 模块动态导入自身，CommonJS 脚本没有命名空间
+fn js_handler_lookup(module: bool) -> String {
+    let namespace = if module {
+        "const __fluxModule = () => import(import.meta.url);"
+    } else {
+        "const __fluxModule = async () => null;"
+    };
+    format!("{namespace}\n{JS_HANDLER_LOOKUP}")
+}
+
+/// Python 包装脚本提供的出站 HTTP 函数（同 [`JS_EGRESS_HELPERS`]）
+const PY_EGRESS_HELPERS: &str = r#"def http_fetch(request):
+    import json, urllib.error, urllib.request
+    egress = (globals().get('context') or {}).get('egress') or {}
+    if not egress.get('url'):
+        raise RuntimeError('HTTP fetch is not available to this invocation')
+    request = {'url': request} if isinstance(request, str) else dict(request)
+    if request.get('body') is not None and not isinstance(request['body'], str):
+        request['headers'] = dict({'content-type': 'application/json'}, **(request.get('headers') or {}))
+        request['body'] = json.dumps(request['body'])
+    call = urllib.request.Request(
+        egress['url'],
+        data=json.dumps(request).encode(),
+        method='POST',
+        headers={'content-type': 'application/json', 'x-flux-egress-token': egress['token']},
+    )
+    opener = urllib.request.build_opener(urllib.request.ProxyHandler({}))
+    try:
+        with opener.open(call) as response:
+            reply = json.loads(response.read() or b'{}')
+    except urllib.error.HTTPError as e:
+        reply = json.loads(e.read() or b'{}')
+        raise RuntimeError(reply.get('error') or 'HTTP fetch failed with status %s' % e.code)
+    return reply.get('data')
+
+class __FluxContext(dict):
+    http_fetch = staticmethod(http_fetch)
+"#;
+
+/// 脚本函数的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptLanguage {
+    JavaScript,
+    Python,
+}
+
+impl ScriptLanguage {
+    /// 解释器命令
+    pub fn interpreter(self) -> &'static str {
+        match self {
+            Self::JavaScript => "node",
+            Self::Python => "python3",
+        }
+    }
+
+    /// 脚本文件名
+    pub fn script_name(self) -> &'static str {
+        match self {
+            Self::JavaScript => "main.js",
+            Self::Python => "main.py",
+        }
+    }
+
+    /// 用户代码写入的脚本文件名：ES 模块写入 `main.mjs`
+    pub fn script_name_for(self, source: &str) -> &'static str {
+        if self.is_es_module(source) {
+            "main.mjs"
+        } else {
+            self.script_name()
+        }
+    }
+
+    /// JavaScript 代码是否为 ES 模块：顶层有 `import`/`export` 语句（动态 `import()` 不算）
+    pub fn is_es_module(self, source: &str) -> bool {
+        self == Self::JavaScript
+            && source.lines().any(|line| {
+                ["import", "export"].iter().any(|keyword| {
+                    line.strip_prefix(keyword)
+                        .is_some_and(|rest| rest.starts_with([' ', '{', '*', '"', '\'']))
+                })
+            })
+    }
+
+    /// JavaScript 代码是否在顶层提供了 [`JS_HANDLER_LOOKUP`] 能找到的处理函数：
+    /// `handler` 函数声明或绑定、`module.exports`/`exports.` 赋值，或者是 ES 模块
+    fn provides_js_handler(self, source: &str) -> bool {
+        let named = |rest: &str, next: char| {
+            rest.trim_start()
+                .strip_prefix("handler")
+                .is_some_and(|rest| rest.trim_start().starts_with(next))
+        };
+        self.is_es_module(source)
+            || source.lines().any(|line| {
+                let line = line.strip_prefix("async ").unwrap_or(line);
+                line.strip_prefix("function")
+                    .is_some_and(|rest| named(rest, '('))
+                    || ["const ", "let ", "var "].iter().any(|binding| {
+                        line.strip_prefix(binding)
+                            .is_some_and(|rest| named(rest, '='))
+                    })
+                    || line.starts_with("exports.")
+                    || line.contains("module.exports")
+            })
+    }
+
+    /// 包装用户代码：从标准输入读取 `{"input", "context"}`（见 [`script_stdin`]），
+    /// 调用 `handler(input, context)`，将返回值以 JSON 写到标准输出
+    ///
+    /// JavaScript 的上下文同时是全局 `context`；Python 的上下文是模块级 `context`，
+    /// 只接受一个参数的 `handler(input)` 仍按原方式调用。输入从不出现在脚本中，
+    /// 因此同一份代码的包装脚本对所有调用都相同，可以缓存复用。
+    ///
+    /// 调用 `handler` 前向标准错误写入 [`BODY_START_MARKER`] 行，沙箱据此区分解释器启动和函数体执行。
+    ///
+    /// 两种语言都提供 `state_get(key)`、`state_set(key, value, ttl?, expected_version?)`、
+    /// `state_entry(key)`（含版本号）和 `state_delete(key)`，读写本函数的键值状态
+    /// （JavaScript 中为 async 函数）。`http_fetch(request)` 经宿主发送出站 HTTP 请求，
+    /// 返回 `{status, headers, body, base64}`，也可以作为上下文的方法 `context.http_fetch` 调用。
+    ///
+    /// JavaScript 的处理函数可以是顶层 `handler`、`module.exports = fn` 或 ES 模块的 `export default fn`
+    /// （ES 模块写入 `.mjs` 文件，见 [`Self::script_name_for`]）；都没有时代码作为带 `return` 的语句
+    /// 包装成 `async function handler(input, context)`。用户代码只执行一次，返回值总是被 `await`。
+    /// 抛出的异常和被拒绝的 Promise 以 `{"error", "name", "stack"}` 写到标准输出，调用栈同时写到标准错误。
+    ///
+    /// 定义了 `init`/`teardown` 钩子的代码改用 [`Self::wrap_worker`] 包装。
+    pub fn wrap(self, source: &str) -> WrappedScript<'_> {
+        if self == Self::JavaScript && !self.provides_js_handler(source) {
+            return WrappedScript {
+                prefix: JS_STATEMENTS_PREFIX,
+                source,
+                wrapper: format!("\n}}{}", self.wrapper(false)),
+            };
+        }
+        WrappedScript {
+            prefix: "",
+            source,
+            wrapper: self.wrapper(self.is_es_module(source)),
+        }
+    }
+
+    /// [`Self::wrap`] 追加在用户代码之后的包装代码
+    fn wrapper(self, module: bool) -> String {
+        match self {
+            Self::JavaScript => format!(
+                "\n\n\
+                 {JS_STATE_HELPERS}\n\
+                 {JS_EGRESS_HELPERS}\n\
+                 {lookup}\n\
+                 const __fluxChunks = [];\n\
+                 process.stdin.on('data', (chunk) => __fluxChunks.push(chunk));\n\
+                 process.stdin.on('end', async () => {{\n\
+                 \x20 const __fluxPayload = JSON.parse(Buffer.concat(__fluxChunks).toString() || '{{}}');\n\
+                 \x20 globalThis.context = __fluxPayload.context === undefined ? null : __fluxPayload.context;\n\
+                 \x20 if (globalThis.context) Object.defineProperty(globalThis.context, 'http_fetch', {{ value: globalThis.http_fetch }});\n\
+                 \x20 const __fluxInput = __fluxPayload.input === undefined ? null : __fluxPayload.input;\n\
+                 \x20 try {{\n\
+                 \x20   const handle = await __fluxHandler();\n\
+                 \x20   process.stderr.write('{BODY_START_MARKER} ' + Date.now() * 1000 + '\\n');\n\
+                 \x20   const output = await handle(__fluxInput, globalThis.context);\n\
+                 \x20   process.stdout.write(JSON.stringify(output === undefined ? null : output));\n\
+                 \x20 }} catch (error) {{\n\
+                 \x20   const failure = __fluxError(error);\n\
+                 \x20   process.stderr.write((failure.stack || failure.name + ': ' + failure.error) + '\\n');\n\
+                 \x20   process.stdout.write(JSON.stringify(failure));\n\
+                 \x20   process.exitCode = 1;\n\
+                 \x20 }}\n\
+                 }});\n",
+                lookup = js_handler_lookup(module)
+            ),
+            Self::Python => format!(
+                "\n\n\
+                 {PY_STATE_HELPERS}\n\
+                 {PY_EGRESS_HELPERS}\n\
+                 if __name__ == '__main__':\n\
+                 \x20   import inspect as __flux_inspect, json as __flux_json, sys as __flux_sys, time as __flux_time\n\
+                 \x20   __flux_raw = __flux_sys.stdin.read()\n\
+                 \x20   __flux_payload = __flux_json.loads(__flux_raw) if __flux_raw.strip() else {{}}\n\
+                 \x20   context = __flux_payload.get('context')\n\
+                 \x20   if isinstance(context, dict):\n\
+                 \x20       context = __FluxContext(context)\n\
+                 \x20   __flux_args = [__flux_payload.get('input')]\n\
+                 \x20   try:\n\
+                 \x20       __flux_params = list(__flux_inspect.signature(handler).parameters.values())\n\
+                 \x20   except (TypeError, ValueError):\n\
+                 \x20       __flux_params = []\n\
+                 \x20   if len(__flux_params) >= 2 or any(p.kind == p.VAR_POSITIONAL for p in __flux_params):\n\
+                 \x20       __flux_args.append(context)\n\
+                 \x20   __flux_sys.stderr.write('{BODY_START_MARKER} %d\\n' % (__flux_time.time_ns() // 1000))\n\
+                 \x20   __flux_sys.stderr.flush()\n\
+                 \x20   __flux_sys.stdout.write(__flux_json.dumps(handler(*__flux_args)))\n"
+            ),
+        }
+    }
+
+    /// 用户代码是否在顶层定义了 `init` 或 `teardown` 钩子
+    ///
+    /// Python 为 `def init(`/`def teardown(`；JavaScript 为同名的 `function` 声明
+    /// （可为 `async`）或 `const`/`let`/`var` 绑定。定义了钩子的函数由常驻工作进程执行。
+    pub fn defines_hooks(self, source: &str) -> bool {
+        let hook = |rest: &str, next: char| {
+            ["init", "teardown"].iter().any(|hook| {
+                rest.strip_prefix(hook)
+                    .is_some_and(|rest| rest.trim_start().starts_with(next))
+            })
+        };
+        source.lines().any(|line| match self {
+            Self::Python => line
+                .strip_prefix("def ")
+                .is_some_and(|rest| ["init(", "teardown("].iter().any(|h| rest.starts_with(h))),
+            Self::JavaScript => {
+                let line = line.strip_prefix("async ").unwrap_or(line);
+                line.strip_prefix("function ")
+                    .is_some_and(|rest| hook(rest, '('))
+                    || ["const ", "let ", "var "].iter().any(|binding| {
+                        line.strip_prefix(binding)
+                            .is_some_and(|rest| hook(rest, '='))
+                    })
+            }
+        })
+    }
+
+    /// 包装定义了钩子的用户代码，作为常驻工作进程运行（协议见 [`crate::runtime::workers`]）
+    ///
+    /// 收到 `init` 时调用 `init(context)`（可省略参数），返回值作为 `state`；
+    /// 每次 `invoke` 调用 `handler(input, context, state)`，Python 只传入 `handler` 声明的参数个数；
+    /// 收到 `teardown` 时调用 `teardown(state)` 后退出。用户代码的打印输出写到标准错误。
+    /// 工作进程跨调用复用，`FLUX_SCRATCH_DIR` 在每次调用时按上下文中的 `scratch_dir` 更新。
+    pub fn wrap_worker(self, source: &str) -> WrappedScript<'_> {
+        WrappedScript {
+            prefix: "",
+            source,
+            wrapper: self.worker_wrapper(self.is_es_module(source)),
+        }
+    }
+
+    /// [`Self::wrap_worker`] 追加在用户代码之后的包装代码
+    fn worker_wrapper(self, module: bool) -> String {
+        match self {
+            Self::JavaScript => format!(
+                "\n\n\
+                 {JS_STATE_HELPERS}\n\
+                 {JS_EGRESS_HELPERS}\n\
+                 {lookup}\n\
+                 const __fluxOut = process.stdout.write.bind(process.stdout);\n\
+                 process.stdout.write = (...args) => process.stderr.write(...args);\n\
+                 console.log = console.info = console.debug = (...args) => console.error(...args);\n\
+                 const __fluxReply = (message) => {{\n\
+                 \x20 const line = JSON.stringify(message) + '\\n';\n\
+                 \x20 process.stderr.write('{CALL_END_MARKER}\\n');\n\
+                 \x20 __fluxOut(line);\n\
+                 }};\n\
+                 const __fluxContext = (message) => {{\n\
+                 \x20 globalThis.context = message.context === undefined ? null : message.context;\n\
+                 \x20 if (globalThis.context) Object.defineProperty(globalThis.context, 'http_fetch', {{ value: globalThis.http_fetch }});\n\
+                 \x20 if (globalThis.context && globalThis.context.scratch_dir) process.env.{SCRATCH_ENV} = globalThis.context.scratch_dir;\n\
+                 \x20 return globalThis.context;\n\
+                 }};\n\
+                 let __fluxState = null;\n\
+                 const __fluxHandle = async (message) => {{\n\
+                 \x20 try {{\n\
+                 \x20   if (message.op === 'init') {{\n\
+                 \x20     const state = typeof init === 'function' ? await init(__fluxContext(message)) : null;\n\
+                 \x20     __fluxState = state === undefined ? null : state;\n\
+                 \x20     __fluxReply({{ ok: true }});\n\
+                 \x20   }} else if (message.op === 'invoke') {{\n\
+                 \x20     const context = __fluxContext(message);\n\
+                 \x20     const input = message.input === undefined ? null : message.input;\n\
+                 \x20     const handle = await __fluxHandler();\n\
+                 \x20     process.stderr.write('{BODY_START_MARKER} ' + Date.now() * 1000 + '\\n');\n\
+                 \x20     const output = await handle(input, context, __fluxState);\n\
+                 \x20     __fluxReply({{ ok: true, output: output === undefined ? null : output }});\n\
+                 \x20   }} else if (message.op === 'teardown') {{\n\
+                 \x20     if (typeof teardown === 'function') await teardown(__fluxState);\n\
+                 \x20     __fluxReply({{ ok: true }});\n\
+                 \x20     process.exit(0);\n\
+                 \x20   }}\n\
+                 \x20 }} catch (error) {{\n\
+                 \x20   console.error((error && error.stack) || String(error));\n\
+                 \x20   __fluxReply({{ ...__fluxError(error), origin: 'user_code' }});\n\
+                 \x20   if (message.op !== 'invoke') process.exit(1);\n\
+                 \x20 }}\n\
+                 }};\n\
+                 let __fluxQueue = Promise.resolve();\n\
+                 let __fluxPending = '';\n\
+                 process.stdin.setEncoding('utf8');\n\
+                 process.stdin.on('data', (chunk) => {{\n\
+                 \x20 const lines = (__fluxPending + chunk).split('\\n');\n\
+                 \x20 __fluxPending = lines.pop();\n\
+                 \x20 for (const line of lines) {{\n\
+                 \x20   if (line.trim()) __fluxQueue = __fluxQueue.then(() => __fluxHandle(JSON.parse(line)));\n\
+                 \x20 }}\n\
+                 }});\n\
+                 process.stdin.on('end', () => {{\n\
+                 \x20 if (__fluxPending.trim()) __fluxQueue = __fluxQueue.then(() => __fluxHandle(JSON.parse(__fluxPending)));\n\
+                 \x20 __fluxQueue.then(() => process.exit(0));\n\
+                 }});\n",
+                lookup = js_handler_lookup(module)
+            ),
+            Self::Python => format!(
+                "\n\n\
+                 {PY_STATE_HELPERS}\n\
+                 {PY_EGRESS_HELPERS}\n\
+                 if __name__ == '__main__':\n\
+                 \x20   import inspect as __flux_inspect, json as __flux_json, os as __flux_os, sys as __flux_sys, time as __flux_time, traceback as __flux_traceback\n\
+                 \x20   __flux_out = __flux_sys.stdout\n\
+                 \x20   __flux_sys.stdout = __flux_sys.stderr\n\
+                 \x20   def __flux_reply(message):\n\
+                 \x20       line = __flux_json.dumps(message)\n\
+                 \x20       __flux_sys.stderr.write('{CALL_END_MARKER}\\n')\n\
+                 \x20       __flux_sys.stderr.flush()\n\
+                 \x20       __flux_out.write(line + '\\n')\n\
+                 \x20       __flux_out.flush()\n\
+                 \x20   def __flux_context(message):\n\
+                 \x20       global context\n\
+                 \x20       context = message.get('context')\n\
+                 \x20       if isinstance(context, dict):\n\
+                 \x20           if context.get('scratch_dir'):\n\
+                 \x20               __flux_os.environ['{SCRATCH_ENV}'] = context['scratch_dir']\n\
+                 \x20           context = __FluxContext(context)\n\
+                 \x20       return context\n\
+                 \x20   def __flux_call(fn, args, least=0):\n\
+                 \x20       try:\n\
+                 \x20           params = list(__flux_inspect.signature(fn).parameters.values())\n\
+                 \x20       except (TypeError, ValueError):\n\
+                 \x20           params = []\n\
+                 \x20       if not any(p.kind == p.VAR_POSITIONAL for p in params):\n\
+                 \x20           args = args[:max(len(params), least)]\n\
+                 \x20       return fn(*args)\n\
+                 \x20   __flux_state = None\n\
+                 \x20   while True:\n\
+                 \x20       __flux_line = __flux_sys.stdin.readline()\n\
+                 \x20       if not __flux_line:\n\
+                 \x20           break\n\
+                 \x20       if not __flux_line.strip():\n\
+                 \x20           continue\n\
+                 \x20       __flux_message = __flux_json.loads(__flux_line)\n\
+                 \x20       __flux_op = __flux_message.get('op')\n\
+                 \x20       try:\n\
+                 \x20           if __flux_op == 'init':\n\
+                 \x20               __flux_init = globals().get('init')\n\
+                 \x20               __flux_state = __flux_call(__flux_init, [__flux_context(__flux_message)]) if callable(__flux_init) else None\n\
+                 \x20               __flux_reply({{'ok': True}})\n\
+                 \x20           elif __flux_op == 'invoke':\n\
+                 \x20               __flux_args = [__flux_message.get('input'), __flux_context(__flux_message), __flux_state]\n\
+                 \x20               __flux_sys.stderr.write('{BODY_START_MARKER} %d\\n' % (__flux_time.time_ns() // 1000))\n\
+                 \x20               __flux_reply({{'ok': True, 'output': __flux_call(handler, __flux_args, 1)}})\n\
+                 \x20           elif __flux_op == 'teardown':\n\
+                 \x20               __flux_teardown = globals().get('teardown')\n\
+                 \x20               if callable(__flux_teardown):\n\
+                 \x20                   __flux_call(__flux_teardown, [__flux_state])\n\
+                 \x20               __flux_reply({{'ok': True}})\n\
+                 \x20               break\n\
+                 \x20       except Exception as __flux_error:\n\
+                 \x20           __flux_traceback.print_exc()\n\
+                 \x20           __flux_reply({{'error': '%s: %s' % (type(__flux_error).__name__, __flux_error), 'name': type(__flux_error).__name__, 'stack': __flux_traceback.format_exc(), 'origin': 'user_code'}})\n\
+                 \x20           if __flux_op != 'invoke':\n\
+                 \x20               break\n"
+            ),
+        }
+    }
+}
+
+/// 包装后的脚本：用户代码原样在前，随后是包装代码
+///
+/// 用户代码只借用不复制，计算缓存键和写入缓存文件时依次处理两部分，
+/// 因此包装的额外内存开销是与代码大小无关的常量。
+#[derive(Debug, Clone)]
+pub struct WrappedScript<'a> {
+    /// 用户代码之前的包装代码
+    prefix: &'static str,
+    source: &'a str,
+    wrapper: String,
+}
+
+impl<'a> WrappedScript<'a> {
+    /// 不加包装的脚本
+    pub fn plain(source: &'a str) -> Self {
+        Self {
+            prefix: "",
+            source,
+            wrapper: String::new(),
+        }
+    }
+
+    /// 按顺序组成脚本的各部分
+    pub fn parts(&self) -> [&str; 3] {
+        [self.prefix, self.source, &self.wrapper]
+    }
+
+    /// 脚本的总字节数
+    pub fn len(&self) -> usize {
+        self.prefix.len() + self.source.len() + self.wrapper.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 包装代码（用户代码前后）的字节数
+    pub fn wrapper_len(&self) -> usize {
+        self.prefix.len() + self.wrapper.len()
+    }
+}
+
+impl fmt::Display for WrappedScript<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.parts().iter().try_for_each(|part| f.write_str(part))
+    }
+}
+
+/// 按顺序哈希各字段（以 `\0` 分隔）和脚本内容，不拼接成完整字符串
+fn cache_key(fields: &[&str], script: &WrappedScript<'_>) -> String {
+    let mut context = md5::Context::new();
+    for field in fields {
+        context.consume(field.as_bytes());
+        context.consume(b"\0");
+    }
+    for part in script.parts() {
+        context.consume(part.as_bytes());
+    }
+    format!("{:x}", context.compute())
+}
+
+/// 依次把脚本的各部分写入文件
+async fn write_script(path: &Path, script: &WrappedScript<'_>) -> std::io::Result<()> {
+    let mut file = tokio::fs::File::create(path).await?;
+    for part in script.parts() {
+        file.write_all(part.as_bytes()).await?;
+    }
+    file.flush().await
+}
+
+/// 脚本函数的标准输入：`{"input": ..., "context": ...}`，上下文的剩余时间按当前时刻计算
+pub fn script_stdin(input: &serde_json::Value, context: &InvocationContext) -> Result<Vec<u8>> {
+    serde_json::to_vec(&serde_json::json!({
+        "input": input,
+        "context": context.to_json(),
+    }))
+    .context("Failed to serialize script input")
+}
+
+/// 脚本缓存统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScriptCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub evictions: u64,
+}
+
+/// 磁盘上的脚本缓存，按（解释器、源代码哈希、包装版本）只写一次
+///
+/// 脚本以只读文件保存在缓存目录中，调用时由解释器通过绝对路径读取，
+/// 相同代码的调用不再为每次执行写入新脚本。
+#[derive(Debug)]
+pub struct ScriptCache {
+    dir: PathBuf,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    /// 本进程内每个脚本最近一次使用的时间
+    last_used: StdMutex<HashMap<PathBuf, SystemTime>>,
+    last_eviction: StdMutex<SystemTime>,
+    max_idle: Duration,
+}
+
+impl ScriptCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            last_used: StdMutex::new(HashMap::new()),
+            last_eviction: StdMutex::new(SystemTime::now()),
+            max_idle: DEFAULT_MAX_IDLE,
+        }
+    }
+
+    /// 设置缓存脚本的闲置时长上限
+    pub fn with_max_idle(mut self, max_idle: Duration) -> Self {
+        self.max_idle = max_idle;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 缓存中脚本的路径，首次使用时写入；返回路径以及是否命中缓存
+    pub async fn script_path(
+        &self,
+        interpreter: &str,
+        script_name: &str,
+        script: &WrappedScript<'_>,
+    ) -> Result<(PathBuf, bool)> {
+        let key = cache_key(&[interpreter, &WRAPPER_VERSION.to_string()], script);
+        let path = self.dir.join(format!("{key}-{script_name}"));
+
+        let hit = tokio::fs::try_exists(&path).await.unwrap_or(false);
+        if hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            self.write(&path, script).await?;
+        }
+        tracing::debug!(
+            phase = "compile",
+            cache_hit = hit,
+            "Prepared {} for {}",
+            script_name,
+            interpreter
+        );
+        self.last_used
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.clone(), SystemTime::now());
+
+        self.maybe_evict().await;
+        Ok((path, hit))
+    }
+
+    /// 缓存中函数包目录的入口文件路径，首次使用时写入整个文件树（入口替换为 `entry`）；
+    /// 返回入口路径以及是否命中缓存
+    pub async fn package_entry(
+        &self,
+        interpreter: &str,
+        package: &FunctionPackage,
+        entry: &WrappedScript<'_>,
+    ) -> Result<(PathBuf, bool)> {
+        let key = cache_key(
+            &[
+                interpreter,
+                &WRAPPER_VERSION.to_string(),
+                &package.content_hash(),
+            ],
+            entry,
+        );
+        let dir = self.dir.join(format!("{key}-package"));
+
+        let hit = tokio::fs::try_exists(&dir).await.unwrap_or(false);
+        if hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            self.write_package(&dir, package, entry).await?;
+        }
+        self.last_used
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(dir.clone(), SystemTime::now());
+
+        self.maybe_evict().await;
+        Ok((dir.join(&package.entrypoint), hit))
+    }
+
+    /// 在临时目录中写好整个文件树后重命名到位
+    async fn write_package(
+        &self,
+        dir: &Path,
+        package: &FunctionPackage,
+        entry: &WrappedScript<'_>,
+    ) -> Result<()> {
+        package.validate()?;
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("Failed to create script cache dir: {:?}", self.dir))?;
+        let temp = dir.with_extension(format!("tmp-{}", scru128::new_string()));
+        let (tree, target) = (package.clone(), temp.clone());
+        tokio::task::spawn_blocking(move || tree.write_to(&target))
+            .await
+            .context("Package writer task failed")?
+            .context("Failed to write cached package")?;
+        write_script(&temp.join(&package.entrypoint), entry)
+            .await
+            .context("Failed to write cached package entrypoint")?;
+
+        if let Err(e) = tokio::fs::rename(&temp, dir).await {
+            let _ = tokio::fs::remove_dir_all(&temp).await;
+            // 并发写入同一个包时由先完成的一方放置
+            if !tokio::fs::try_exists(dir).await.unwrap_or(false) {
+                return Err(e).context("Failed to move cached package into place");
+            }
+        }
+        Ok(())
+    }
+
+    /// 先写入临时文件再重命名，并发写入同一脚本时不会读到不完整的内容
+    async fn write(&self, path: &Path, script: &WrappedScript<'_>) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("Failed to create script cache dir: {:?}", self.dir))?;
+        let temp = path.with_extension(format!("tmp-{}", scru128::new_string()));
+        write_script(&temp, script)
+            .await
+            .context("Failed to write cached script")?;
+        platform::make_read_only(&temp).context("Failed to set cached script permissions")?;
+        tokio::fs::rename(&temp, path)
+            .await
+            .context("Failed to move cached script into place")
+    }
+
+    /// 距上次淘汰超过一小时时执行一次淘汰
+    async fn maybe_evict(&self) {
+        let due = {
+            let mut last = self.last_eviction.lock().unwrap_or_else(|e| e.into_inner());
+            let due = last
+                .elapsed()
+                .map(|e| e >= EVICTION_INTERVAL)
+                .unwrap_or(true);
+            if due {
+                *last = SystemTime::now();
+            }
+            due
+        };
+        if due {
+            let removed = self.evict_unused(self.max_idle).await;
+            if removed > 0 {
+                tracing::info!("Evicted {} unused cached scripts", removed);
+            }
+        }
+    }
+
+    /// 删除闲置超过 `max_idle` 的脚本，返回删除数量
+    ///
+    /// 本进程使用过的脚本按最近使用时间判断，其余（例如上次运行留下的）按文件修改时间判断。
+    pub async fn evict_unused(&self, max_idle: Duration) -> usize {
+        let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await else {
+            return 0;
+        };
+        let now = SystemTime::now();
+        let mut removed = 0;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let used = self
+                .last_used
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(&path)
+                .copied();
+            let last_used = match used {
+                Some(used) => used,
+                None => match entry.metadata().await.and_then(|m| m.modified()) {
+                    Ok(modified) => modified,
+                    Err(_) => continue,
+                },
+            };
+            if now.duration_since(last_used).unwrap_or_default() < max_idle {
+                continue;
+            }
+            let removed_entry = if entry.file_type().await.is_ok_and(|t| t.is_dir()) {
+                tokio::fs::remove_dir_all(&path).await
+            } else {
+                tokio::fs::remove_file(&path).await
+            };
+            if removed_entry.is_ok() {
+                self.last_used
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .remove(&path);
+                removed += 1;
+            }
+        }
+        self.evictions.fetch_add(removed as u64, Ordering::Relaxed);
+        removed
+    }
+
+    /// 缓存统计
+    pub async fn stats(&self) -> ScriptCacheStats {
+        let mut entries = 0;
+        if let Ok(mut dir) = tokio::fs::read_dir(&self.dir).await {
+            while let Ok(Some(_)) = dir.next_entry().await {
+                entries += 1;
+            }
+        }
+        ScriptCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries,
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scripts_written_once_and_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ScriptCache::new(dir.path().join("scripts"));
+
+        let script = WrappedScript::plain("echo 1");
+        let (path, hit) = cache.script_path("sh", "main.sh", &script).await.unwrap();
+        assert!(!hit);
+        let (again, hit) = cache.script_path("sh", "main.sh", &script).await.unwrap();
+        assert!(hit);
+        assert_eq!(path, again);
+        // 不同解释器或代码对应不同脚本
+        let (other, _) = cache.script_path("bash", "main.sh", &script).await.unwrap();
+        assert_ne!(path, other);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "echo 1");
+
+        assert_eq!(cache.evict_unused(Duration::from_secs(3600)).await, 0);
+        assert_eq!(cache.evict_unused(Duration::ZERO).await, 2);
+        let stats = cache.stats().await;
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 0));
+        assert_eq!(stats.evictions, 2);
+    }
+
+    #[tokio::test]
+    async fn test_wrapped_script_embeds_code_once_and_streams_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ScriptCache::new(dir.path().join("scripts"));
+        // 原始字符串分隔符、花括号和大量行都原样保留
+        let source = format!(
+            "const marker = \"flux-unique-marker\"#{{}};\n{}function handler(input) {{ return input; }}\n",
+            "// r#\"line\"# {{}}\n".repeat(100_000)
+        );
+
+        let small = "function handler(input) { return input; }";
+        for language in [ScriptLanguage::JavaScript, ScriptLanguage::Python] {
+            for (script, empty) in [
+                (language.wrap(&source), language.wrap(small)),
+                (language.wrap_worker(&source), language.wrap_worker(small)),
+            ] {
+                // 包装只增加与代码大小无关的常量
+                assert_eq!(script.wrapper_len(), empty.wrapper_len());
+                assert_eq!(script.len(), source.len() + script.wrapper_len());
+
+                let (path, _) = cache
+                    .script_path(language.interpreter(), language.script_name(), &script)
+                    .await
+                    .unwrap();
+                let written = std::fs::read_to_string(&path).unwrap();
+                assert_eq!(written.len(), script.len());
+                assert!(written.starts_with(&source));
+                assert_eq!(written.matches("flux-unique-marker").count(), 1);
+                assert_eq!(written, script.to_string());
+            }
+        }
+    }
+}
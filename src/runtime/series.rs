@@ -0,0 +1,753 @@
+//! 按时间分桶的函数执行统计
+//!
+//! 每个函数按固定时长（默认 1 分钟）分桶，每桶记录调用数、错误数、内存峰值和对数分桶的
+//! 延迟直方图，在保留窗口（默认 24 小时）外的桶被丢弃。直方图可以跨桶合并，
+//! 因此任意窗口和分辨率的分位数都由桶内直方图直接计算。
+//!
+//! 跟踪分桶数据的函数数量有上限，超出时最久未调用的函数降级为只保留汇总（调用数、
+//! 错误数、内存峰值和合并后的直方图），汇总条目数同样有上限。
+//!
+//! 调度器收尾的调用另外按阶段（排队、编译、启动、执行、总耗时）和沙箱进程的 CPU 时间
+//! 记录直方图，报告中给出各阶段的 p95；CPU 时间另外累计总量，可以按它排行。
+
+use crate::functions::InvocationTiming;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+/// 直方图相邻分桶的比例，分位数估计的相对误差约为其一半（约 2.5%）
+const HISTOGRAM_GROWTH: f64 = 1.05;
+/// 直方图记录的延迟范围（毫秒），超出范围的值计入两端的分桶
+const HISTOGRAM_MIN_MS: f64 = 0.01;
+const HISTOGRAM_MAX_MS: f64 = 3_600_000.0;
+
+/// 时间序列统计配置（`[metrics]`）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SeriesConfig {
+    /// 分桶时长（秒）
+    pub bucket_secs: u64,
+    /// 分桶数据保留时长（秒）
+    pub retention_secs: u64,
+    /// 保留分桶数据的函数数量上限，超出时最久未调用的函数降级为只保留汇总
+    pub max_tracked_functions: usize,
+    /// 只保留汇总的函数数量上限，超出时丢弃最久未调用的汇总
+    pub max_summary_functions: usize,
+}
+
+impl Default for SeriesConfig {
+    fn default() -> Self {
+        Self {
+            bucket_secs: 60,
+            retention_secs: 24 * 3600,
+            max_tracked_functions: 256,
+            max_summary_functions: 4096,
+        }
+    }
+}
+
+/// 对数分桶的延迟直方图（稀疏存储，按分桶序号升序）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyHistogram {
+    bins: Vec<(u16, u32)>,
+    count: u64,
+}
+
+impl LatencyHistogram {
+    fn bin_of(ms: f64) -> u16 {
+        let clamped = ms.clamp(HISTOGRAM_MIN_MS, HISTOGRAM_MAX_MS);
+        ((clamped / HISTOGRAM_MIN_MS).ln() / HISTOGRAM_GROWTH.ln()).floor() as u16
+    }
+
+    /// 分桶的代表值（分桶上下界的几何中点）
+    fn value_of(bin: u16) -> f64 {
+        HISTOGRAM_MIN_MS * HISTOGRAM_GROWTH.powf(bin as f64 + 0.5)
+    }
+
+    fn add(&mut self, bin: u16, count: u32) {
+        match self.bins.binary_search_by_key(&bin, |(b, _)| *b) {
+            Ok(i) => self.bins[i].1 = self.bins[i].1.saturating_add(count),
+            Err(i) => self.bins.insert(i, (bin, count)),
+        }
+        self.count += count as u64;
+    }
+
+    /// 记录一个延迟样本
+    pub fn record(&mut self, latency: Duration) {
+        self.add(Self::bin_of(latency.as_secs_f64() * 1000.0), 1);
+    }
+
+    /// 合并另一个直方图
+    pub fn merge(&mut self, other: &Self) {
+        for (bin, count) in &other.bins {
+            self.add(*bin, *count);
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// 估计分位数（毫秒），没有样本时为 `None`
+    pub fn quantile(&self, quantile: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((quantile * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        self.bins.iter().find_map(|(bin, count)| {
+            seen += *count as u64;
+            (seen >= rank).then(|| Self::value_of(*bin))
+        })
+    }
+}
+
+/// 单个时间桶
+#[derive(Debug, Clone, Default)]
+struct Bucket {
+    /// 桶起始时间（Unix 秒，按分桶时长对齐）
+    start: u64,
+    stats: Aggregate,
+}
+
+/// 各阶段耗时的直方图
+#[derive(Debug, Clone, Default)]
+struct PhaseHistograms {
+    queue: LatencyHistogram,
+    compile: LatencyHistogram,
+    spawn: LatencyHistogram,
+    execute: LatencyHistogram,
+    total: LatencyHistogram,
+    cpu: LatencyHistogram,
+}
+
+impl PhaseHistograms {
+    /// 没有发生的阶段不记录样本
+    fn record(&mut self, timing: &InvocationTiming) {
+        for (histogram, ms) in [
+            (&mut self.queue, timing.queue_ms),
+            (&mut self.compile, timing.compile_ms),
+            (&mut self.spawn, timing.spawn_ms),
+            (&mut self.execute, timing.execute_ms),
+            (&mut self.total, Some(timing.total_ms)),
+            (&mut self.cpu, timing.cpu_ms),
+        ] {
+            if let Some(ms) = ms {
+                histogram.record(Duration::from_millis(ms));
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.queue.merge(&other.queue);
+        self.compile.merge(&other.compile);
+        self.spawn.merge(&other.spawn);
+        self.execute.merge(&other.execute);
+        self.total.merge(&other.total);
+        self.cpu.merge(&other.cpu);
+    }
+
+    fn summary(&self) -> PhaseSummary {
+        PhaseSummary {
+            queue_p95_ms: self.queue.quantile(0.95),
+            compile_p95_ms: self.compile.quantile(0.95),
+            spawn_p95_ms: self.spawn.quantile(0.95),
+            execute_p95_ms: self.execute.quantile(0.95),
+            total_p95_ms: self.total.quantile(0.95),
+            cpu_p95_ms: self.cpu.quantile(0.95),
+        }
+    }
+}
+
+/// 可合并的聚合统计
+#[derive(Debug, Clone, Default)]
+struct Aggregate {
+    invocations: u64,
+    errors: u64,
+    max_memory: u64,
+    latencies: LatencyHistogram,
+    phases: PhaseHistograms,
+    /// 沙箱进程累计使用的 CPU 时间（毫秒）
+    cpu_ms: u64,
+}
+
+impl Aggregate {
+    fn record(&mut self, latency: Duration, success: bool, memory: u64) {
+        self.invocations += 1;
+        if !success {
+            self.errors += 1;
+        }
+        self.max_memory = self.max_memory.max(memory);
+        self.latencies.record(latency);
+    }
+
+    fn record_timing(&mut self, timing: &InvocationTiming) {
+        self.phases.record(timing);
+        self.cpu_ms = self.cpu_ms.saturating_add(timing.cpu_ms.unwrap_or(0));
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.invocations += other.invocations;
+        self.errors += other.errors;
+        self.max_memory = self.max_memory.max(other.max_memory);
+        self.latencies.merge(&other.latencies);
+        self.phases.merge(&other.phases);
+        self.cpu_ms = self.cpu_ms.saturating_add(other.cpu_ms);
+    }
+
+    fn summary(&self) -> SeriesSummary {
+        SeriesSummary {
+            invocations: self.invocations,
+            errors: self.errors,
+            error_rate: if self.invocations > 0 {
+                self.errors as f64 / self.invocations as f64
+            } else {
+                0.0
+            },
+            p50_ms: self.latencies.quantile(0.50),
+            p90_ms: self.latencies.quantile(0.90),
+            p95_ms: self.latencies.quantile(0.95),
+            p99_ms: self.latencies.quantile(0.99),
+            max_memory_bytes: self.max_memory,
+            cpu_ms: self.cpu_ms,
+        }
+    }
+}
+
+/// 一段时间内的汇总
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SeriesSummary {
+    pub invocations: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub p50_ms: Option<f64>,
+    pub p90_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub max_memory_bytes: u64,
+    /// 沙箱进程累计使用的 CPU 时间（毫秒）
+    #[serde(default)]
+    pub cpu_ms: u64,
+}
+
+/// 各阶段耗时的 p95（毫秒），窗口内没有发生过该阶段时为 `None`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PhaseSummary {
+    pub queue_p95_ms: Option<f64>,
+    pub compile_p95_ms: Option<f64>,
+    pub spawn_p95_ms: Option<f64>,
+    pub execute_p95_ms: Option<f64>,
+    pub total_p95_ms: Option<f64>,
+    /// 沙箱进程 CPU 时间的 p95
+    #[serde(default)]
+    pub cpu_p95_ms: Option<f64>,
+}
+
+/// 序列中的一个点（按请求的分辨率合并的若干时间桶）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SeriesPoint {
+    pub start: DateTime<Utc>,
+    #[serde(flatten)]
+    pub summary: SeriesSummary,
+}
+
+/// 函数执行报告
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FunctionReport {
+    pub function: String,
+    pub window_secs: u64,
+    pub resolution_secs: u64,
+    /// 整个窗口的汇总；降级函数为降级前保留的全部数据的汇总
+    pub summary: SeriesSummary,
+    /// 整个窗口内各阶段耗时的 p95
+    #[serde(default)]
+    pub phases: PhaseSummary,
+    /// 按时间升序的序列（包含没有调用的空点）
+    pub series: Vec<SeriesPoint>,
+    /// 函数已降级为只保留汇总，没有时间序列
+    pub summary_only: bool,
+}
+
+/// 函数最近一段时间的汇总及实际覆盖的窗口
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionWindow {
+    pub summary: SeriesSummary,
+    /// 实际统计的窗口（秒），请求的窗口超出保留时长时为保留时长
+    pub window_secs: u64,
+    /// 函数已降级为只保留汇总，汇总不限于窗口
+    pub summary_only: bool,
+}
+
+/// 排行指标
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RankMetric {
+    P50,
+    P90,
+    #[default]
+    P99,
+    ErrorRate,
+    Invocations,
+    MaxMemory,
+    /// 窗口内累计的 CPU 时间
+    CpuTime,
+}
+
+impl RankMetric {
+    fn value(self, summary: &SeriesSummary) -> Option<f64> {
+        match self {
+            Self::P50 => summary.p50_ms,
+            Self::P90 => summary.p90_ms,
+            Self::P99 => summary.p99_ms,
+            Self::ErrorRate => (summary.invocations > 0).then_some(summary.error_rate),
+            Self::Invocations => Some(summary.invocations as f64),
+            Self::MaxMemory => Some(summary.max_memory_bytes as f64),
+            Self::CpuTime => Some(summary.cpu_ms as f64),
+        }
+    }
+}
+
+/// 排行中的一项
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RankedFunction {
+    pub function: String,
+    pub value: f64,
+    pub summary: SeriesSummary,
+}
+
+#[derive(Debug, Default)]
+struct TrackedFunction {
+    buckets: VecDeque<Bucket>,
+    last_recorded: u64,
+}
+
+#[derive(Debug, Default)]
+struct SummaryOnly {
+    stats: Aggregate,
+    last_recorded: u64,
+}
+
+/// 所有函数的时间序列
+#[derive(Debug, Default)]
+pub struct SeriesStore {
+    config: SeriesConfig,
+    tracked: HashMap<String, TrackedFunction>,
+    summaries: HashMap<String, SummaryOnly>,
+}
+
+/// 当前 Unix 时间（秒）
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl SeriesStore {
+    pub fn new(config: SeriesConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn config(&self) -> &SeriesConfig {
+        &self.config
+    }
+
+    fn bucket_secs(&self) -> u64 {
+        self.config.bucket_secs.max(1)
+    }
+
+    /// 在 `now`（Unix 秒）记录一次执行，返回是否开启了新的时间桶（之前的桶不再变化）
+    pub fn record(
+        &mut self,
+        function: &str,
+        now: u64,
+        latency: Duration,
+        success: bool,
+        memory: u64,
+    ) -> bool {
+        self.update(function, now, |stats| {
+            stats.record(latency, success, memory)
+        })
+    }
+
+    /// 在 `now` 记录一次调用的各阶段耗时，不计入调用数；返回是否开启了新的时间桶
+    pub fn record_timing(&mut self, function: &str, now: u64, timing: &InvocationTiming) -> bool {
+        self.update(function, now, |stats| stats.record_timing(timing))
+    }
+
+    /// 更新函数在 `now` 所在的时间桶，返回是否开启了新的时间桶
+    fn update(&mut self, function: &str, now: u64, apply: impl FnOnce(&mut Aggregate)) -> bool {
+        if !self.tracked.contains_key(function) {
+            // 重新活跃的降级函数恢复分桶统计，旧汇总丢弃
+            self.summaries.remove(function);
+            self.make_room(now);
+        }
+        let bucket_secs = self.bucket_secs();
+        let start = now - now % bucket_secs;
+        let retention = self.config.retention_secs;
+        let tracked = self.tracked.entry(function.to_string()).or_default();
+        tracked.last_recorded = now;
+        let rolled_over = match tracked.buckets.back_mut() {
+            // 时钟回拨时计入最新的桶
+            Some(bucket) if bucket.start >= start => {
+                apply(&mut bucket.stats);
+                false
+            }
+            _ => {
+                let mut bucket = Bucket {
+                    start,
+                    ..Default::default()
+                };
+                apply(&mut bucket.stats);
+                tracked.buckets.push_back(bucket);
+                true
+            }
+        };
+        Self::discard_expired(&mut tracked.buckets, now, retention);
+        rolled_over
+    }
+
+    /// 丢弃保留窗口之外的桶
+    fn discard_expired(buckets: &mut VecDeque<Bucket>, now: u64, retention: u64) {
+        let cutoff = now.saturating_sub(retention);
+        while buckets.front().is_some_and(|bucket| bucket.start < cutoff) {
+            buckets.pop_front();
+        }
+    }
+
+    /// 跟踪数量达到上限时，将最久未调用的函数降级为汇总
+    fn make_room(&mut self, now: u64) {
+        let limit = self.config.max_tracked_functions;
+        while self.tracked.len() >= limit.max(1) {
+            let Some(oldest) = self
+                .tracked
+                .iter()
+                .min_by_key(|(_, tracked)| tracked.last_recorded)
+                .map(|(name, _)| name.clone())
+            else {
+                break;
+            };
+            let Some(mut tracked) = self.tracked.remove(&oldest) else {
+                break;
+            };
+            Self::discard_expired(&mut tracked.buckets, now, self.config.retention_secs);
+            let mut stats = Aggregate::default();
+            for bucket in &tracked.buckets {
+                stats.merge(&bucket.stats);
+            }
+            tracing::debug!("Demoting function {} to summary-only statistics", oldest);
+            self.summaries.insert(
+                oldest,
+                SummaryOnly {
+                    stats,
+                    last_recorded: tracked.last_recorded,
+                },
+            );
+        }
+        while self.summaries.len() > self.config.max_summary_functions {
+            let Some(oldest) = self
+                .summaries
+                .iter()
+                .min_by_key(|(_, summary)| summary.last_recorded)
+                .map(|(name, _)| name.clone())
+            else {
+                break;
+            };
+            self.summaries.remove(&oldest);
+        }
+    }
+
+    /// 生成截至 `now` 的函数报告，`resolution` 向上取整为分桶时长的整数倍；
+    /// 没有记录的函数返回全为空点的序列
+    pub fn report(
+        &self,
+        function: &str,
+        now: u64,
+        window: Duration,
+        resolution: Duration,
+    ) -> FunctionReport {
+        let bucket_secs = self.bucket_secs();
+        let window_secs = window
+            .as_secs()
+            .clamp(bucket_secs, self.config.retention_secs.max(bucket_secs));
+        let resolution_secs = resolution
+            .as_secs()
+            .div_ceil(bucket_secs)
+            .max(1)
+            .saturating_mul(bucket_secs)
+            .min(window_secs);
+
+        if let Some(summary) = self.summaries.get(function) {
+            return FunctionReport {
+                function: function.to_string(),
+                window_secs,
+                resolution_secs,
+                summary: summary.stats.summary(),
+                phases: summary.stats.phases.summary(),
+                series: Vec::new(),
+                summary_only: true,
+            };
+        }
+
+        let tracked = self.tracked.get(function);
+        let current = now - now % bucket_secs;
+        // 点的起始时间按分辨率对齐，共 ⌈窗口/分辨率⌉ 个点，最后一个点包含当前桶
+        let last = current - current % resolution_secs;
+        let first =
+            last.saturating_sub((window_secs.div_ceil(resolution_secs) - 1) * resolution_secs);
+        let mut points = Vec::new();
+        let mut total = Aggregate::default();
+        let mut start = first;
+        while start <= current {
+            let end = start + resolution_secs;
+            let mut point = Aggregate::default();
+            for bucket in tracked
+                .iter()
+                .flat_map(|tracked| tracked.buckets.iter())
+                .filter(|bucket| bucket.start >= start && bucket.start < end)
+            {
+                point.merge(&bucket.stats);
+            }
+            total.merge(&point);
+            points.push(SeriesPoint {
+                start: DateTime::from_timestamp(start as i64, 0).unwrap_or_default(),
+                summary: point.summary(),
+            });
+            start = end;
+        }
+
+        FunctionReport {
+            function: function.to_string(),
+            window_secs,
+            resolution_secs,
+            summary: total.summary(),
+            phases: total.phases.summary(),
+            series: points,
+            summary_only: false,
+        }
+    }
+
+    /// 函数在截至 `now` 的最近 `window` 内的汇总，窗口限制在一个分桶和保留时长之间
+    pub fn function_window(&self, function: &str, now: u64, window: Duration) -> FunctionWindow {
+        let bucket_secs = self.bucket_secs();
+        let window_secs = window
+            .as_secs()
+            .clamp(bucket_secs, self.config.retention_secs.max(bucket_secs));
+        if let Some(summary) = self.summaries.get(function) {
+            return FunctionWindow {
+                summary: summary.stats.summary(),
+                window_secs,
+                summary_only: true,
+            };
+        }
+        let cutoff = now.saturating_sub(window_secs);
+        let mut total = Aggregate::default();
+        for bucket in self
+            .tracked
+            .get(function)
+            .iter()
+            .flat_map(|tracked| tracked.buckets.iter())
+            .filter(|bucket| bucket.start >= cutoff)
+        {
+            total.merge(&bucket.stats);
+        }
+        FunctionWindow {
+            summary: total.summary(),
+            window_secs,
+            summary_only: false,
+        }
+    }
+
+    /// 各跟踪函数在截至 `now` 的最近 `window` 内的汇总（窗口至少为一个分桶）
+    pub fn window_summaries(&self, now: u64, window: Duration) -> HashMap<String, SeriesSummary> {
+        let cutoff = now.saturating_sub(window.as_secs().max(self.bucket_secs()));
+        self.tracked
+            .iter()
+            .map(|(name, tracked)| {
+                let mut total = Aggregate::default();
+                for bucket in tracked
+                    .buckets
+                    .iter()
+                    .filter(|bucket| bucket.start >= cutoff)
+                {
+                    total.merge(&bucket.stats);
+                }
+                (name.clone(), total.summary())
+            })
+            .collect()
+    }
+
+    /// 按指标对窗口内有调用的函数排行（降序）
+    pub fn top(
+        &self,
+        now: u64,
+        window: Duration,
+        metric: RankMetric,
+        limit: usize,
+    ) -> Vec<RankedFunction> {
+        let mut ranked: Vec<_> = self
+            .window_summaries(now, window)
+            .into_iter()
+            .filter_map(|(function, summary)| {
+                let value = metric.value(&summary).filter(|_| summary.invocations > 0)?;
+                Some(RankedFunction {
+                    function,
+                    value,
+                    summary,
+                })
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            b.value
+                .total_cmp(&a.value)
+                .then_with(|| a.function.cmp(&b.function))
+        });
+        ranked.truncate(limit);
+        ranked
+    }
+
+    /// 当前跟踪分桶数据和只保留汇总的函数数
+    pub fn tracked_counts(&self) -> (usize, usize) {
+        (self.tracked.len(), self.summaries.len())
+    }
+
+    pub fn clear(&mut self) {
+        self.tracked.clear();
+        self.summaries.clear();
+    }
+}
+
+/// 解析 `30s`、`5m`、`1h`、`7d` 形式的时长（纯数字按秒）
+pub fn parse_span(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (value, unit) = text.split_at(split);
+    let value: u64 = value.parse().ok()?;
+    let unit_secs = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return None,
+    };
+    value.checked_mul(unit_secs).map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_are_within_tolerance_of_known_distribution() {
+        // 1..=10000 毫秒的均匀分布：p50 = 5000，p90 = 9000，p99 = 9900
+        let mut store = SeriesStore::new(SeriesConfig::default());
+        let now = 1_700_000_100;
+        for ms in 1..=10_000u64 {
+            // 分散到最近 10 分钟的桶里，同时验证跨桶合并
+            let at = now - (ms % 10) * 60;
+            store.record("f", at, Duration::from_millis(ms), ms % 50 != 0, ms);
+        }
+
+        let report = store.report(
+            "f",
+            now,
+            Duration::from_secs(3600),
+            Duration::from_secs(300),
+        );
+        let summary = &report.summary;
+        assert_eq!(summary.invocations, 10_000);
+        assert_eq!(summary.errors, 200);
+        assert!((summary.error_rate - 0.02).abs() < 1e-9);
+        assert_eq!(summary.max_memory_bytes, 10_000);
+        for (estimate, expected) in [
+            (summary.p50_ms, 5000.0),
+            (summary.p90_ms, 9000.0),
+            (summary.p99_ms, 9900.0),
+        ] {
+            let estimate = estimate.unwrap();
+            assert!(
+                (estimate - expected).abs() / expected < 0.03,
+                "{estimate} vs {expected}"
+            );
+        }
+
+        // 1 小时窗口、5 分钟分辨率共 12 个点，调用都在最后 10 分钟内
+        assert_eq!(report.resolution_secs, 300);
+        assert_eq!(report.series.len(), 12);
+        let counts: u64 = report.series.iter().map(|p| p.summary.invocations).sum();
+        assert_eq!(counts, 10_000);
+        assert!(
+            report.series[..9]
+                .iter()
+                .all(|p| p.summary.invocations == 0)
+        );
+    }
+
+    #[test]
+    fn test_rotation_discards_old_buckets_and_demotes_idle_functions() {
+        let mut store = SeriesStore::new(SeriesConfig {
+            bucket_secs: 60,
+            retention_secs: 600,
+            max_tracked_functions: 2,
+            max_summary_functions: 1,
+        });
+        let start = 1_700_000_000 - 1_700_000_000 % 60;
+        // 每个新桶的第一次记录报告桶已滚动
+        assert!(store.record("a", start, Duration::from_millis(500), true, 0));
+        assert!(store.record("a", start + 700, Duration::from_millis(5), true, 0));
+        let report = store.report(
+            "a",
+            start + 700,
+            Duration::from_secs(3600),
+            Duration::from_secs(60),
+        );
+        // 超过保留时长的桶已丢弃，窗口被限制在保留时长内
+        assert_eq!(report.window_secs, 600);
+        assert_eq!(report.summary.invocations, 1);
+        assert!(report.summary.p99_ms.unwrap() < 10.0);
+
+        store.record("b", start + 710, Duration::from_millis(5), false, 0);
+        store.record("c", start + 720, Duration::from_millis(5), true, 0);
+        assert_eq!(store.tracked_counts(), (2, 1));
+        let demoted = store.report(
+            "a",
+            start + 720,
+            Duration::from_secs(600),
+            Duration::from_secs(60),
+        );
+        assert!(demoted.summary_only);
+        assert!(demoted.series.is_empty());
+        assert_eq!(demoted.summary.invocations, 1);
+
+        store.record("d", start + 730, Duration::from_millis(5), true, 0);
+        // 汇总数量同样有上限，最早的汇总被丢弃
+        let dropped = store.report(
+            "a",
+            start + 730,
+            Duration::from_secs(600),
+            Duration::from_secs(60),
+        );
+        assert!(!dropped.summary_only);
+        assert_eq!(dropped.summary.invocations, 0);
+        let top = store.top(
+            start + 730,
+            Duration::from_secs(600),
+            RankMetric::ErrorRate,
+            10,
+        );
+        assert_eq!(top.len(), 2);
+        assert!(top.iter().all(|entry| entry.function != "b"));
+        assert!(!store.record("d", start + 731, Duration::from_millis(5), true, 0));
+
+        assert_eq!(parse_span("5m"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_span("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_span("1w"), None);
+    }
+}
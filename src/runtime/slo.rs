@@ -0,0 +1,431 @@
+//! 函数 SLO 的合规统计和燃烧率告警
+//!
+//! 合规数字由按时间分桶的执行统计（见 [`crate::runtime::series`]）计算：窗口内的成功率、
+//! p95 延迟、剩余错误预算和燃烧率（失败率与允许失败率之比）。SLO 窗口超出分桶数据的
+//! 保留时长时退化为保留时长并标记 `window_degraded`，不把较短窗口的结果当作完整窗口报告。
+//!
+//! 告警分别检查两个窗口的燃烧率：快速窗口（默认 1 小时、14.4 倍）发现突发故障，慢速窗口
+//! （默认 6 小时、6 倍）发现持续的预算消耗。燃烧率达到阈值时开始告警，降到阈值的
+//! `recovery_ratio` 以下才解除，阈值附近的波动不会反复告警。
+
+use crate::functions::slo::SloConfig;
+use crate::functions::{FluxError, Result};
+use crate::runtime::series::{FunctionWindow, SeriesSummary};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::Mutex as StdMutex;
+use utoipa::ToSchema;
+
+/// SLO 告警配置（`[slo]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SloAlertConfig {
+    /// 是否检查燃烧率告警（默认 true），关闭后 `GET /functions/:name/slo` 仍报告合规数字
+    pub enabled: bool,
+    /// 检查间隔（毫秒）
+    pub tick_ms: u64,
+    /// 快速窗口（秒）及其告警阈值
+    pub fast_window_secs: u64,
+    pub fast_burn_rate: f64,
+    /// 慢速窗口（秒）及其告警阈值
+    pub slow_window_secs: u64,
+    pub slow_burn_rate: f64,
+    /// 燃烧率降到阈值的该比例以下时解除告警（0 到 1）
+    pub recovery_ratio: f64,
+    /// 窗口内的最少调用数，不足时不开始告警
+    pub min_invocations: u64,
+}
+
+impl Default for SloAlertConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tick_ms: 10_000,
+            fast_window_secs: 3600,
+            fast_burn_rate: 14.4,
+            slow_window_secs: 6 * 3600,
+            slow_burn_rate: 6.0,
+            recovery_ratio: 0.5,
+            min_invocations: 10,
+        }
+    }
+}
+
+impl SloAlertConfig {
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| {
+            Err(FluxError::ValidationError {
+                reason: format!("slo: {reason}"),
+            })
+        };
+        if self.fast_window_secs == 0 || self.slow_window_secs == 0 {
+            return invalid("fast_window_secs and slow_window_secs must be positive");
+        }
+        if !(self.fast_burn_rate > 0.0 && self.slow_burn_rate > 0.0) {
+            return invalid("fast_burn_rate and slow_burn_rate must be positive");
+        }
+        if !(self.recovery_ratio > 0.0 && self.recovery_ratio <= 1.0) {
+            return invalid("recovery_ratio must be in (0, 1]");
+        }
+        Ok(())
+    }
+
+    /// 窗口时长（秒）和告警阈值
+    pub fn window(&self, window: BurnWindow) -> (u64, f64) {
+        match window {
+            BurnWindow::Fast => (self.fast_window_secs, self.fast_burn_rate),
+            BurnWindow::Slow => (self.slow_window_secs, self.slow_burn_rate),
+        }
+    }
+}
+
+/// 燃烧率告警窗口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BurnWindow {
+    Fast,
+    Slow,
+}
+
+impl BurnWindow {
+    pub const ALL: [Self; 2] = [Self::Fast, Self::Slow];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fast => "fast",
+            Self::Slow => "slow",
+        }
+    }
+}
+
+/// 失败率与允许失败率之比，没有调用时为 0
+pub fn burn_rate(slo: &SloConfig, summary: &SeriesSummary) -> f64 {
+    if summary.invocations == 0 {
+        return 0.0;
+    }
+    summary.error_rate / slo.error_budget_ratio()
+}
+
+/// SLO 窗口内的合规数字
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SloCompliance {
+    /// 配置的窗口（秒）
+    pub window_secs: u64,
+    /// 实际统计的窗口（秒）
+    pub effective_window_secs: u64,
+    /// 配置的窗口超出分桶数据的保留时长（或函数已降级为只保留汇总），
+    /// 数字只代表实际统计的数据
+    pub window_degraded: bool,
+    pub invocations: u64,
+    pub errors: u64,
+    /// 成功调用比例，窗口内没有调用时为 `None`
+    pub success_ratio: Option<f64>,
+    pub latency_p95_ms: Option<f64>,
+    /// 成功率达到目标（没有调用时视为达到）
+    pub availability_met: bool,
+    /// p95 延迟达到目标，未配置延迟目标或没有调用时为 `None`
+    pub latency_met: Option<bool>,
+    /// 窗口内允许失败的调用数
+    pub error_budget: f64,
+    /// 剩余错误预算的比例：1 为尚未消耗，小于 0 为已超支
+    pub error_budget_remaining: f64,
+    /// 整个窗口的燃烧率
+    pub burn_rate: f64,
+}
+
+impl SloCompliance {
+    /// 由函数在 SLO 窗口内的汇总计算合规数字
+    pub fn compute(slo: &SloConfig, window: &FunctionWindow) -> Self {
+        let summary = &window.summary;
+        let window_secs = slo.window().as_secs();
+        let success_ratio = (summary.invocations > 0).then_some(1.0 - summary.error_rate);
+        let error_budget = summary.invocations as f64 * slo.error_budget_ratio();
+        let error_budget_remaining = if summary.invocations == 0 {
+            1.0
+        } else {
+            1.0 - summary.errors as f64 / error_budget
+        };
+        Self {
+            window_secs,
+            effective_window_secs: window.window_secs,
+            window_degraded: window.window_secs < window_secs || window.summary_only,
+            invocations: summary.invocations,
+            errors: summary.errors,
+            success_ratio,
+            latency_p95_ms: summary.p95_ms,
+            availability_met: success_ratio.is_none_or(|ratio| ratio >= slo.availability),
+            latency_met: slo
+                .latency_p95_ms
+                .zip(summary.p95_ms)
+                .map(|(target, p95)| p95 <= target as f64),
+            error_budget,
+            error_budget_remaining,
+            burn_rate: burn_rate(slo, summary),
+        }
+    }
+}
+
+/// 一个告警窗口的燃烧率和告警状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BurnRateStatus {
+    pub window: BurnWindow,
+    pub window_secs: u64,
+    pub threshold: f64,
+    pub burn_rate: f64,
+    pub invocations: u64,
+    /// 最近一次检查时正在告警
+    pub alerting: bool,
+    /// 告警开始的时间
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// 告警的开始或解除
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SloAlertTransition {
+    pub function: String,
+    pub window: BurnWindow,
+    /// true 为开始告警，false 为解除
+    pub firing: bool,
+    pub burn_rate: f64,
+    pub threshold: f64,
+    pub window_secs: u64,
+    /// SLO 窗口内剩余错误预算的比例
+    pub error_budget_remaining: f64,
+    pub at: DateTime<Utc>,
+}
+
+impl SloAlertTransition {
+    pub fn description(&self) -> String {
+        if self.firing {
+            format!(
+                "SLO burn rate {:.2} over {}s reached the {} threshold {}",
+                self.burn_rate,
+                self.window_secs,
+                self.window.as_str(),
+                self.threshold
+            )
+        } else {
+            format!(
+                "SLO burn rate {:.2} over {}s recovered below the {} threshold {}",
+                self.burn_rate,
+                self.window_secs,
+                self.window.as_str(),
+                self.threshold
+            )
+        }
+    }
+}
+
+/// 按函数和告警窗口记录的告警状态
+#[derive(Debug, Default)]
+pub struct SloAlerts {
+    config: StdMutex<SloAlertConfig>,
+    /// 正在告警的 (函数, 窗口) 及开始时间
+    firing: StdMutex<HashMap<(String, BurnWindow), DateTime<Utc>>>,
+}
+
+impl SloAlerts {
+    pub fn configure(&self, config: &SloAlertConfig) -> Result<()> {
+        config.validate()?;
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        Ok(())
+    }
+
+    pub fn config(&self) -> SloAlertConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 告警开始的时间，没有告警时为 `None`
+    pub fn since(&self, function: &str, window: BurnWindow) -> Option<DateTime<Utc>> {
+        self.firing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(function.to_string(), window))
+            .copied()
+    }
+
+    /// 按一个窗口在 `now` 的燃烧率更新告警状态，返回状态是否变化：`Some(true)` 为开始告警，
+    /// `Some(false)` 为解除
+    pub fn observe(
+        &self,
+        function: &str,
+        window: BurnWindow,
+        burn_rate: f64,
+        invocations: u64,
+        now: DateTime<Utc>,
+    ) -> Option<bool> {
+        let config = self.config();
+        let (_, threshold) = config.window(window);
+        let mut firing = self.firing.lock().unwrap_or_else(|e| e.into_inner());
+        match firing.entry((function.to_string(), window)) {
+            Entry::Occupied(entry) if burn_rate < threshold * config.recovery_ratio => {
+                entry.remove();
+                Some(false)
+            }
+            Entry::Vacant(entry)
+                if invocations >= config.min_invocations && burn_rate >= threshold =>
+            {
+                entry.insert(now);
+                Some(true)
+            }
+            _ => None,
+        }
+    }
+
+    /// 只保留 `keep` 返回 true 的函数的告警状态（SLO 被移除或函数被删除时不发出解除事件）
+    pub fn retain(&self, mut keep: impl FnMut(&str) -> bool) {
+        self.firing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|(function, _), _| keep(function));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::series::{SeriesConfig, SeriesStore};
+    use std::time::Duration;
+
+    fn slo(window: &str) -> SloConfig {
+        SloConfig {
+            availability: 0.99,
+            latency_p95_ms: Some(100),
+            window: window.to_string(),
+        }
+    }
+
+    /// 在 `[from, to)` 内每 `every` 秒记录一次调用，每 `fail_every` 次失败一次
+    fn record(
+        store: &mut SeriesStore,
+        from: u64,
+        to: u64,
+        every: u64,
+        fail_every: u64,
+        latency_ms: u64,
+    ) {
+        for (i, at) in (from..to).step_by(every as usize).enumerate() {
+            let success = fail_every == 0 || !(i as u64 + 1).is_multiple_of(fail_every);
+            store.record("f", at, Duration::from_millis(latency_ms), success, 0);
+        }
+    }
+
+    #[test]
+    fn test_budget_math_on_synthetic_timeline() {
+        let mut store = SeriesStore::new(SeriesConfig::default());
+        let start = 1_700_000_000 - 1_700_000_000 % 60;
+        // 前 10 小时每 10 秒一次调用，0.5% 失败；之后 1 小时全部失败
+        record(&mut store, start, start + 36_000, 10, 200, 50);
+        let now = start + 36_000;
+        let window = store.function_window("f", now, Duration::from_secs(12 * 3600));
+        let compliance = SloCompliance::compute(&slo("12h"), &window);
+        assert_eq!(compliance.invocations, 3600);
+        assert_eq!(compliance.errors, 18);
+        assert!(!compliance.window_degraded);
+        assert!(compliance.availability_met);
+        assert_eq!(compliance.latency_met, Some(true));
+        // 允许 36 次失败，已消耗一半
+        assert!((compliance.error_budget - 36.0).abs() < 1e-9);
+        assert!((compliance.error_budget_remaining - 0.5).abs() < 1e-9);
+        assert!((compliance.burn_rate - 0.5).abs() < 1e-9);
+
+        record(&mut store, now, now + 3600, 10, 1, 500);
+        let now = now + 3600;
+        let window = store.function_window("f", now, Duration::from_secs(12 * 3600));
+        let compliance = SloCompliance::compute(&slo("12h"), &window);
+        assert_eq!((compliance.invocations, compliance.errors), (3960, 378));
+        assert!(!compliance.availability_met);
+        assert!(compliance.error_budget_remaining < 0.0);
+        let fast = store.function_window("f", now, Duration::from_secs(3600));
+        // 快速窗口内全部失败：燃烧率为允许失败率的 100 倍
+        assert!((burn_rate(&slo("12h"), &fast.summary) - 100.0).abs() < 1e-9);
+
+        // 窗口超出 24 小时的保留时长：退化为保留时长并标记
+        let compliance = SloCompliance::compute(
+            &slo("7d"),
+            &store.function_window("f", now, Duration::from_secs(7 * 86400)),
+        );
+        assert!(compliance.window_degraded);
+        assert_eq!(compliance.window_secs, 7 * 86400);
+        assert_eq!(compliance.effective_window_secs, 86400);
+
+        // 没有调用时预算完整，视为达到目标
+        let idle = SloCompliance::compute(
+            &slo("1h"),
+            &store.function_window("g", now, Duration::from_secs(3600)),
+        );
+        assert_eq!(idle.success_ratio, None);
+        assert!(idle.availability_met);
+        assert_eq!(idle.latency_met, None);
+        assert_eq!((idle.error_budget_remaining, idle.burn_rate), (1.0, 0.0));
+    }
+
+    #[test]
+    fn test_alerts_fire_and_recover_with_hysteresis() {
+        let alerts = SloAlerts::default();
+        let slo = slo("30d");
+        let mut store = SeriesStore::new(SeriesConfig::default());
+        let start = 1_700_000_000 - 1_700_000_000 % 60;
+        let check = |store: &SeriesStore, now: u64| {
+            let fast = store.function_window("f", now, Duration::from_secs(3600));
+            let rate = burn_rate(&slo, &fast.summary);
+            let at = DateTime::from_timestamp(now as i64, 0).unwrap();
+            (
+                rate,
+                alerts.observe("f", BurnWindow::Fast, rate, fast.summary.invocations, at),
+            )
+        };
+
+        // 调用太少时不告警
+        store.record("f", start, Duration::from_millis(5), false, 0);
+        assert_eq!(check(&store, start).1, None);
+
+        // 1 小时内 20% 失败：燃烧率 20，超过 14.4
+        record(&mut store, start + 1, start + 3600, 60, 5, 5);
+        let (rate, transition) = check(&store, start + 3600);
+        assert!(rate > 14.4, "{rate}");
+        assert_eq!(transition, Some(true));
+        assert!(alerts.since("f", BurnWindow::Fast).is_some());
+
+        // 燃烧率在阈值附近波动但没有低于阈值的一半：保持告警，不重复发出
+        let mut now = start + 3600;
+        for _ in 0..6 {
+            record(&mut store, now, now + 600, 60, 10, 5);
+            now += 600;
+            let (rate, transition) = check(&store, now);
+            assert!(rate >= 7.2, "{rate}");
+            assert_eq!(transition, None);
+        }
+
+        // 恢复后燃烧率低于 7.2 才解除
+        record(&mut store, now, now + 3600, 60, 0, 5);
+        now += 3600;
+        let (rate, transition) = check(&store, now);
+        assert!(rate < 7.2, "{rate}");
+        assert_eq!(transition, Some(false));
+        assert_eq!(alerts.since("f", BurnWindow::Fast), None);
+
+        // 移除 SLO 后状态直接丢弃
+        let at = DateTime::from_timestamp(now as i64, 0).unwrap();
+        assert_eq!(
+            alerts.observe("f", BurnWindow::Slow, 50.0, 100, at),
+            Some(true)
+        );
+        alerts.retain(|function| function != "f");
+        assert_eq!(alerts.since("f", BurnWindow::Slow), None);
+        assert!(
+            SloAlertConfig {
+                recovery_ratio: 0.0,
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+    }
+}
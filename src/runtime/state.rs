@@ -0,0 +1,663 @@
+//! 函数状态存储：按函数名隔离的键值对，供函数在多次调用之间读写少量状态
+//!
+//! 执行中的函数通过调用上下文中的授权访问自己的状态：脚本函数经回环 HTTP 接口
+//! （`POST /state`，请求头携带本次调用的令牌），编译的 Rust 函数经 `flux_execute_v3`
+//! 传入的宿主回调。写入为后写者胜，携带 `expected_version` 时为比较并交换。
+
+use crate::functions::{FluxError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+/// 访问状态的令牌所在的请求头
+pub const STATE_TOKEN_HEADER: &str = "x-flux-state-token";
+
+/// 状态存储配置（`[state]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateConfig {
+    /// 单个值序列化后的字节上限
+    pub max_value_bytes: usize,
+    /// 每个函数的键数上限
+    pub max_keys_per_function: usize,
+    /// 每个函数所有键和值的字节上限
+    pub max_bytes_per_function: usize,
+    /// 持久化文件，设置后每次写入都保存快照，启动时从中恢复
+    pub persist_path: Option<PathBuf>,
+    /// 清理过期键的间隔（秒）
+    pub sweep_interval_secs: u64,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            max_value_bytes: 64 * 1024,
+            max_keys_per_function: 1000,
+            max_bytes_per_function: 1024 * 1024,
+            persist_path: None,
+            sweep_interval_secs: 30,
+        }
+    }
+}
+
+/// 一个键的当前值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateEntry {
+    pub value: serde_json::Value,
+    /// 每次写入递增（从 1 开始），用于比较并交换
+    pub version: u64,
+    pub updated_at: DateTime<Utc>,
+    /// 过期时间，未设置存活时间时为 `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl StateEntry {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// 写入请求：`expected_version` 为 0 表示键必须不存在，其他值表示当前版本必须相等
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StateWrite {
+    pub value: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_version: Option<u64>,
+}
+
+/// 回环接口的请求体
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum StateOperation {
+    Get {
+        key: String,
+    },
+    Set {
+        key: String,
+        #[serde(flatten)]
+        write: StateWrite,
+    },
+    Delete {
+        key: String,
+    },
+}
+
+/// 一个函数的全部状态及用量
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FunctionState {
+    pub function: String,
+    pub keys: usize,
+    pub bytes: usize,
+    pub entries: BTreeMap<String, StateEntry>,
+}
+
+/// 传给执行中函数的状态访问方式（序列化为上下文中的 `state` 字段）
+#[derive(Debug, Clone)]
+pub struct StateHandle {
+    store: Arc<StateStore>,
+    function: String,
+    token: String,
+    read_only: bool,
+}
+
+impl StateHandle {
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    pub fn get(&self, key: &str) -> Option<StateEntry> {
+        self.store.get(&self.function, key)
+    }
+
+    pub fn set(&self, key: &str, write: StateWrite) -> Result<StateEntry> {
+        self.check_writable()?;
+        self.store.set(&self.function, key, write)
+    }
+
+    pub fn delete(&self, key: &str) -> Result<bool> {
+        self.check_writable()?;
+        Ok(self.store.delete(&self.function, key))
+    }
+
+    /// 执行一次回环接口的操作，返回键的当前值（不存在或已删除时为 `None`）
+    pub fn apply(&self, operation: StateOperation) -> Result<Option<StateEntry>> {
+        match operation {
+            StateOperation::Get { key } => Ok(self.get(&key)),
+            StateOperation::Set { key, write } => self.set(&key, write).map(Some),
+            StateOperation::Delete { key } => self.delete(&key).map(|_| None),
+        }
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(FluxError::StateReadOnly {
+                function: self.function.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for StateHandle {
+    fn eq(&self, other: &Self) -> bool {
+        self.token == other.token
+    }
+}
+
+impl Serialize for StateHandle {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Access<'a> {
+            url: Option<String>,
+            token: &'a str,
+        }
+        Access {
+            url: self.store.endpoint(),
+            token: &self.token,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// 一次调用的状态访问授权，释放时令牌失效
+#[derive(Debug)]
+pub struct StateGrant {
+    handle: StateHandle,
+}
+
+impl StateGrant {
+    pub fn handle(&self) -> StateHandle {
+        self.handle.clone()
+    }
+}
+
+impl Drop for StateGrant {
+    fn drop(&mut self) {
+        self.handle
+            .store
+            .tokens
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.handle.token);
+    }
+}
+
+/// 一个函数的键值及其字节数
+#[derive(Debug, Default)]
+struct FunctionEntries {
+    entries: HashMap<String, StateEntry>,
+    bytes: usize,
+}
+
+/// 键和值占用的字节数
+fn entry_size(key: &str, value: &serde_json::Value) -> usize {
+    key.len() + serde_json::to_vec(value).map_or(0, |bytes| bytes.len())
+}
+
+/// 按函数名隔离的键值存储
+#[derive(Debug, Default)]
+pub struct StateStore {
+    config: StdMutex<StateConfig>,
+    functions: StdMutex<HashMap<String, FunctionEntries>>,
+    /// 有效的调用令牌 → 授权
+    tokens: StdMutex<HashMap<String, (String, bool)>>,
+    /// 回环接口地址（服务绑定端口后设置）
+    endpoint: StdMutex<Option<String>>,
+}
+
+impl StateStore {
+    pub fn new(config: StateConfig) -> Self {
+        Self {
+            config: StdMutex::new(config),
+            ..Default::default()
+        }
+    }
+
+    /// 应用配置；配置了持久化文件且文件存在时从中恢复状态（未过期的键）
+    pub fn configure(&self, config: &StateConfig) -> anyhow::Result<()> {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        let Some(path) = &config.persist_path else {
+            return Ok(());
+        };
+        if !path.exists() {
+            return Ok(());
+        }
+        let data = std::fs::read(path)?;
+        let saved: HashMap<String, HashMap<String, StateEntry>> = serde_json::from_slice(&data)?;
+        let now = Utc::now();
+        let mut functions = self.functions.lock().unwrap_or_else(|e| e.into_inner());
+        functions.clear();
+        for (function, entries) in saved {
+            let entries: HashMap<_, _> = entries
+                .into_iter()
+                .filter(|(_, entry)| !entry.is_expired(now))
+                .collect();
+            let bytes = entries
+                .iter()
+                .map(|(key, entry)| entry_size(key, &entry.value))
+                .sum();
+            functions.insert(function, FunctionEntries { entries, bytes });
+        }
+        tracing::info!(
+            "Restored state of {} functions from {}",
+            functions.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn config(&self) -> StateConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 设置回环接口地址，例如 `http://127.0.0.1:3000/state`
+    pub fn set_endpoint(&self, url: impl Into<String>) {
+        *self.endpoint.lock().unwrap_or_else(|e| e.into_inner()) = Some(url.into());
+    }
+
+    pub fn endpoint(&self) -> Option<String> {
+        self.endpoint
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 为一次调用签发访问函数状态的授权，`read_only` 时拒绝写入（用于影子重放）
+    pub fn grant(self: &Arc<Self>, function: &str, read_only: bool) -> StateGrant {
+        let token = scru128::new_string();
+        self.tokens
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(token.clone(), (function.to_string(), read_only));
+        StateGrant {
+            handle: StateHandle {
+                store: self.clone(),
+                function: function.to_string(),
+                token,
+                read_only,
+            },
+        }
+    }
+
+    /// 令牌对应的访问方式，令牌未签发或调用已结束时为 `None`
+    pub fn resolve(self: &Arc<Self>, token: &str) -> Option<StateHandle> {
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        let (function, read_only) = tokens.get(token)?;
+        Some(StateHandle {
+            store: self.clone(),
+            function: function.clone(),
+            token: token.to_string(),
+            read_only: *read_only,
+        })
+    }
+
+    /// 读取键的当前值，已过期的键视为不存在
+    pub fn get(&self, function: &str, key: &str) -> Option<StateEntry> {
+        let functions = self.functions.lock().unwrap_or_else(|e| e.into_inner());
+        functions
+            .get(function)?
+            .entries
+            .get(key)
+            .filter(|entry| !entry.is_expired(Utc::now()))
+            .cloned()
+    }
+
+    /// 写入键，超出值大小、键数或总字节上限，或比较并交换的版本不符时返回错误
+    pub fn set(&self, function: &str, key: &str, write: StateWrite) -> Result<StateEntry> {
+        let config = self.config();
+        let limit_error = |reason: String| FluxError::StateLimitExceeded {
+            function: function.to_string(),
+            reason,
+        };
+        if key.is_empty() {
+            return Err(FluxError::ValidationError {
+                reason: "State key must not be empty".to_string(),
+            });
+        }
+        let size = entry_size(key, &write.value);
+        if size > config.max_value_bytes {
+            return Err(limit_error(format!(
+                "value of key '{key}' is {size} bytes (limit {})",
+                config.max_value_bytes
+            )));
+        }
+
+        let now = Utc::now();
+        let entry = {
+            let mut functions = self.functions.lock().unwrap_or_else(|e| e.into_inner());
+            let state = functions.entry(function.to_string()).or_default();
+            let current = state
+                .entries
+                .get(key)
+                .filter(|entry| !entry.is_expired(now));
+            if let Some(expected) = write.expected_version {
+                let version = current.map_or(0, |entry| entry.version);
+                if version != expected {
+                    return Err(FluxError::StateVersionConflict {
+                        function: function.to_string(),
+                        key: key.to_string(),
+                        expected,
+                        current: version,
+                    });
+                }
+            }
+            let previous = state.entries.get(key);
+            let freed = previous.map_or(0, |entry| entry_size(key, &entry.value));
+            if previous.is_none() && state.entries.len() >= config.max_keys_per_function {
+                return Err(limit_error(format!(
+                    "function already has {} keys (limit {})",
+                    state.entries.len(),
+                    config.max_keys_per_function
+                )));
+            }
+            let bytes = state.bytes - freed + size;
+            if bytes > config.max_bytes_per_function {
+                return Err(limit_error(format!(
+                    "state would grow to {bytes} bytes (limit {})",
+                    config.max_bytes_per_function
+                )));
+            }
+
+            let entry = StateEntry {
+                value: write.value,
+                version: current.map_or(0, |entry| entry.version) + 1,
+                updated_at: now,
+                expires_at: write
+                    .ttl_secs
+                    .map(|ttl| now + chrono::Duration::seconds(ttl.min(i64::MAX as u64) as i64)),
+            };
+            state.entries.insert(key.to_string(), entry.clone());
+            state.bytes = bytes;
+            entry
+        };
+        self.persist();
+        Ok(entry)
+    }
+
+    /// 删除键，返回键是否存在
+    pub fn delete(&self, function: &str, key: &str) -> bool {
+        let removed = {
+            let mut functions = self.functions.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(state) = functions.get_mut(function) else {
+                return false;
+            };
+            let removed = state.entries.remove(key);
+            if let Some(entry) = &removed {
+                state.bytes -= entry_size(key, &entry.value);
+            }
+            if state.entries.is_empty() {
+                functions.remove(function);
+            }
+            removed.is_some()
+        };
+        if removed {
+            self.persist();
+        }
+        removed
+    }
+
+    /// 函数的全部未过期状态
+    pub fn snapshot(&self, function: &str) -> FunctionState {
+        let now = Utc::now();
+        let functions = self.functions.lock().unwrap_or_else(|e| e.into_inner());
+        let entries: BTreeMap<_, _> = functions
+            .get(function)
+            .into_iter()
+            .flat_map(|state| state.entries.iter())
+            .filter(|(_, entry)| !entry.is_expired(now))
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect();
+        FunctionState {
+            function: function.to_string(),
+            keys: entries.len(),
+            bytes: entries
+                .iter()
+                .map(|(key, entry)| entry_size(key, &entry.value))
+                .sum(),
+            entries,
+        }
+    }
+
+    /// 清空函数的状态，返回删除的键数
+    pub fn clear(&self, function: &str) -> usize {
+        let removed = self
+            .functions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(function)
+            .map_or(0, |state| state.entries.len());
+        if removed > 0 {
+            self.persist();
+        }
+        removed
+    }
+
+    /// 删除所有过期的键，返回删除的键数
+    pub fn purge_expired(&self) -> usize {
+        let now = Utc::now();
+        let mut purged = 0;
+        {
+            let mut functions = self.functions.lock().unwrap_or_else(|e| e.into_inner());
+            for state in functions.values_mut() {
+                let before = state.entries.len();
+                state.entries.retain(|_, entry| !entry.is_expired(now));
+                purged += before - state.entries.len();
+                state.bytes = state
+                    .entries
+                    .iter()
+                    .map(|(key, entry)| entry_size(key, &entry.value))
+                    .sum();
+            }
+            functions.retain(|_, state| !state.entries.is_empty());
+        }
+        if purged > 0 {
+            tracing::debug!("Purged {} expired state keys", purged);
+            self.persist();
+        }
+        purged
+    }
+
+    /// 定期清理过期的键（存储被释放后任务自行结束）
+    pub fn spawn_sweeper(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let store = Arc::downgrade(self);
+        let interval = Duration::from_secs(self.config().sweep_interval_secs.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(store) = store.upgrade() else {
+                    break;
+                };
+                store.purge_expired();
+            }
+        })
+    }
+
+    /// 配置了持久化文件时保存全部状态（先写临时文件再重命名）
+    fn persist(&self) {
+        let Some(path) = self.config().persist_path else {
+            return;
+        };
+        let data = {
+            let functions = self.functions.lock().unwrap_or_else(|e| e.into_inner());
+            let saved: HashMap<&String, &HashMap<String, StateEntry>> = functions
+                .iter()
+                .map(|(function, state)| (function, &state.entries))
+                .collect();
+            serde_json::to_vec(&saved)
+        };
+        let result = data.map_err(anyhow::Error::from).and_then(|data| {
+            let partial = path.with_extension("partial");
+            std::fs::write(&partial, data)?;
+            std::fs::rename(&partial, &path)?;
+            Ok(())
+        });
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to persist function state to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(value: serde_json::Value) -> StateWrite {
+        StateWrite {
+            value,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_last_write_wins_and_compare_and_swap() {
+        let store = StateStore::default();
+        assert_eq!(store.get("counter", "hits"), None);
+
+        let first = store.set("counter", "hits", write(1.into())).unwrap();
+        assert_eq!(first.version, 1);
+        let second = store.set("counter", "hits", write(2.into())).unwrap();
+        assert_eq!(second.version, 2);
+        assert_eq!(store.get("counter", "hits").unwrap().value, 2);
+        // 键按函数名隔离
+        assert_eq!(store.get("other", "hits"), None);
+
+        let stale = StateWrite {
+            value: 3.into(),
+            expected_version: Some(1),
+            ..Default::default()
+        };
+        assert!(matches!(
+            store.set("counter", "hits", stale),
+            Err(FluxError::StateVersionConflict {
+                expected: 1,
+                current: 2,
+                ..
+            })
+        ));
+        let fresh = StateWrite {
+            value: 3.into(),
+            expected_version: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(store.set("counter", "hits", fresh).unwrap().version, 3);
+
+        // 版本 0 表示键必须不存在
+        let create = StateWrite {
+            value: "first".into(),
+            expected_version: Some(0),
+            ..Default::default()
+        };
+        assert!(store.set("counter", "owner", create.clone()).is_ok());
+        assert!(store.set("counter", "owner", create).is_err());
+
+        assert!(store.delete("counter", "owner"));
+        assert_eq!(store.clear("counter"), 1);
+        assert_eq!(store.snapshot("counter").keys, 0);
+    }
+
+    #[test]
+    fn test_limits_are_enforced_per_function() {
+        let store = StateStore::new(StateConfig {
+            max_value_bytes: 16,
+            max_keys_per_function: 2,
+            max_bytes_per_function: 12,
+            ..Default::default()
+        });
+        let err = store
+            .set("f", "big", write("x".repeat(32).into()))
+            .unwrap_err();
+        assert!(err.to_string().contains("limit 16"), "{err}");
+
+        store.set("f", "a", write(1.into())).unwrap();
+        store.set("f", "b", write(2.into())).unwrap();
+        assert!(store.set("f", "c", write(3.into())).is_err());
+        // 覆盖已有键不增加键数
+        store.set("f", "a", write(10.into())).unwrap();
+        assert!(
+            store
+                .set("f", "b", write("0123456789".into()))
+                .unwrap_err()
+                .to_string()
+                .contains("limit 12")
+        );
+        // 其他函数有自己的额度
+        store.set("g", "c", write(3.into())).unwrap();
+        assert_eq!(store.snapshot("f").bytes, 5);
+    }
+
+    #[test]
+    fn test_ttl_expiry_and_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = StateConfig {
+            persist_path: Some(dir.path().join("state.json")),
+            ..Default::default()
+        };
+        let store = StateStore::new(config.clone());
+        store.set("f", "kept", write("v".into())).unwrap();
+        store
+            .set(
+                "f",
+                "expired",
+                StateWrite {
+                    value: 1.into(),
+                    ttl_secs: Some(0),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(store.get("f", "expired"), None);
+        assert_eq!(store.snapshot("f").keys, 1);
+        assert_eq!(store.purge_expired(), 1);
+
+        let restored = StateStore::default();
+        restored.configure(&config).unwrap();
+        assert_eq!(restored.get("f", "kept").unwrap().value, "v");
+        assert_eq!(restored.snapshot("f").keys, 1);
+    }
+
+    #[test]
+    fn test_grants_expire_with_the_invocation() {
+        let store = Arc::new(StateStore::default());
+        store.set_endpoint("http://127.0.0.1:1/state");
+        let grant = store.grant("f", false);
+        let handle = grant.handle();
+        let json = serde_json::to_value(&handle).unwrap();
+        assert_eq!(json["url"], "http://127.0.0.1:1/state");
+        let token = json["token"].as_str().unwrap().to_string();
+
+        let resolved = store.resolve(&token).unwrap();
+        assert_eq!(resolved.function(), "f");
+        resolved
+            .apply(StateOperation::Set {
+                key: "k".to_string(),
+                write: write(1.into()),
+            })
+            .unwrap();
+        assert_eq!(store.get("f", "k").unwrap().value, 1);
+
+        let shadow = store.grant("f", true).handle();
+        assert_eq!(shadow.get("k").unwrap().value, 1);
+        assert!(shadow.set("k", write(2.into())).is_err());
+
+        drop(grant);
+        assert!(store.resolve(&token).is_none());
+    }
+}
@@ -0,0 +1,372 @@
+//! 函数执行的滑动窗口和衰减汇总
+//!
+//! 每个函数维护 1 分钟、5 分钟和 1 小时三个滑动窗口，窗口由固定数量的小桶组成环，
+//! 过期的桶在被复用时清空；另有按半衰期指数衰减的全生命周期汇总，早期的数据随时间
+//! 逐渐失去权重。记录一次执行只更新每个窗口的当前桶和衰减汇总，开销与历史长度无关。
+
+use crate::runtime::series::LatencyHistogram;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 全生命周期汇总的默认半衰期（秒）
+pub const LIFETIME_HALF_LIFE_SECS: u64 = 3600;
+
+/// 可查询的滑动窗口
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatsWindow {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[default]
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+
+impl StatsWindow {
+    pub const ALL: [StatsWindow; 3] = [Self::OneMinute, Self::FiveMinutes, Self::OneHour];
+
+    /// 解析 `1m`、`5m`、`1h`
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|window| window.as_str() == text.trim())
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::OneMinute => "1m",
+            Self::FiveMinutes => "5m",
+            Self::OneHour => "1h",
+        }
+    }
+
+    pub fn span(self) -> Duration {
+        Duration::from_secs(self.bucket_secs() * self.bucket_count() as u64)
+    }
+
+    /// 每个小桶的时长（秒）
+    fn bucket_secs(self) -> u64 {
+        match self {
+            Self::OneMinute => 5,
+            Self::FiveMinutes => 30,
+            Self::OneHour => 300,
+        }
+    }
+
+    fn bucket_count(self) -> usize {
+        match self {
+            Self::OneMinute => 12,
+            Self::FiveMinutes => 10,
+            Self::OneHour => 12,
+        }
+    }
+}
+
+/// 窗口中的一个小桶
+#[derive(Debug, Clone, Default)]
+struct WindowBucket {
+    /// 桶起始时间（Unix 秒，按桶时长对齐）
+    start: u64,
+    invocations: u64,
+    failures: u64,
+    total_latency_ms: f64,
+    latencies: LatencyHistogram,
+    total_memory: u64,
+    peak_memory: u64,
+}
+
+/// 由小桶组成环的滑动窗口
+#[derive(Debug, Clone)]
+struct SlidingWindow {
+    window: StatsWindow,
+    buckets: Vec<WindowBucket>,
+}
+
+impl SlidingWindow {
+    fn new(window: StatsWindow) -> Self {
+        Self {
+            window,
+            buckets: vec![WindowBucket::default(); window.bucket_count()],
+        }
+    }
+
+    fn record(&mut self, now: u64, latency: Duration, success: bool, memory: u64) {
+        let bucket_secs = self.window.bucket_secs();
+        let start = now - now % bucket_secs;
+        let index = (now / bucket_secs) as usize % self.buckets.len();
+        let bucket = &mut self.buckets[index];
+        if bucket.start != start {
+            // 复用已过期的桶
+            *bucket = WindowBucket {
+                start,
+                ..Default::default()
+            };
+        }
+        bucket.invocations += 1;
+        if !success {
+            bucket.failures += 1;
+        }
+        bucket.total_latency_ms += latency.as_secs_f64() * 1000.0;
+        bucket.latencies.record(latency);
+        bucket.total_memory += memory;
+        bucket.peak_memory = bucket.peak_memory.max(memory);
+    }
+
+    /// 截至 `now` 的窗口汇总（包含当前未满的桶）
+    fn summary(&self, now: u64) -> WindowSummary {
+        let bucket_secs = self.window.bucket_secs();
+        let current = now - now % bucket_secs;
+        let first = current.saturating_sub(bucket_secs * (self.buckets.len() as u64 - 1));
+        let mut merged = WindowBucket::default();
+        for bucket in self
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.invocations > 0 && (first..=current).contains(&bucket.start))
+        {
+            merged.invocations += bucket.invocations;
+            merged.failures += bucket.failures;
+            merged.total_latency_ms += bucket.total_latency_ms;
+            merged.latencies.merge(&bucket.latencies);
+            merged.total_memory += bucket.total_memory;
+            merged.peak_memory = merged.peak_memory.max(bucket.peak_memory);
+        }
+        let invocations = merged.invocations;
+        WindowSummary {
+            window: self.window,
+            window_secs: self.window.span().as_secs(),
+            invocations,
+            failures: merged.failures,
+            error_rate: ratio(merged.failures as f64, invocations as f64),
+            avg_latency_ms: (invocations > 0).then(|| merged.total_latency_ms / invocations as f64),
+            p95_latency_ms: merged.latencies.quantile(0.95),
+            avg_memory_bytes: (invocations > 0).then(|| merged.total_memory / invocations),
+            peak_memory_bytes: merged.peak_memory,
+        }
+    }
+}
+
+fn ratio(part: f64, total: f64) -> f64 {
+    if total > 0.0 { part / total } else { 0.0 }
+}
+
+/// 一个滑动窗口内的执行汇总
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowSummary {
+    pub window: StatsWindow,
+    pub window_secs: u64,
+    pub invocations: u64,
+    pub failures: u64,
+    pub error_rate: f64,
+    pub avg_latency_ms: Option<f64>,
+    pub p95_latency_ms: Option<f64>,
+    pub avg_memory_bytes: Option<u64>,
+    pub peak_memory_bytes: u64,
+}
+
+/// 按半衰期指数衰减的全生命周期汇总
+#[derive(Debug, Clone)]
+struct DecayedSummary {
+    half_life_secs: u64,
+    /// 各衰减量最后一次衰减到的时间（Unix 秒）
+    updated_at: u64,
+    weight: f64,
+    failures: f64,
+    latency_ms: f64,
+    memory: f64,
+    /// 未衰减的总调用数和内存峰值
+    total_invocations: u64,
+    peak_memory: u64,
+}
+
+impl DecayedSummary {
+    fn new(half_life_secs: u64) -> Self {
+        Self {
+            half_life_secs: half_life_secs.max(1),
+            updated_at: 0,
+            weight: 0.0,
+            failures: 0.0,
+            latency_ms: 0.0,
+            memory: 0.0,
+            total_invocations: 0,
+            peak_memory: 0,
+        }
+    }
+
+    /// 从 `updated_at` 到 `now` 的衰减系数
+    fn factor(&self, now: u64) -> f64 {
+        let elapsed = now.saturating_sub(self.updated_at) as f64;
+        0.5f64.powf(elapsed / self.half_life_secs as f64)
+    }
+
+    fn record(&mut self, now: u64, latency: Duration, success: bool, memory: u64) {
+        let factor = self.factor(now);
+        self.weight = self.weight * factor + 1.0;
+        self.failures = self.failures * factor + if success { 0.0 } else { 1.0 };
+        self.latency_ms = self.latency_ms * factor + latency.as_secs_f64() * 1000.0;
+        self.memory = self.memory * factor + memory as f64;
+        self.updated_at = self.updated_at.max(now);
+        self.total_invocations += 1;
+        self.peak_memory = self.peak_memory.max(memory);
+    }
+
+    fn summary(&self, now: u64) -> LifetimeSummary {
+        let has_data = self.weight > 0.0;
+        LifetimeSummary {
+            decayed: true,
+            half_life_secs: self.half_life_secs,
+            total_invocations: self.total_invocations,
+            weighted_invocations: self.weight * self.factor(now),
+            error_rate: ratio(self.failures, self.weight),
+            avg_latency_ms: has_data.then(|| self.latency_ms / self.weight),
+            avg_memory_bytes: has_data.then(|| (self.memory / self.weight) as u64),
+            peak_memory_bytes: self.peak_memory,
+        }
+    }
+}
+
+/// 全生命周期的衰减汇总：错误率、平均延迟和平均内存按调用的衰减权重计算
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LifetimeSummary {
+    /// 始终为 true，表示各比率和平均值是衰减后的数字
+    pub decayed: bool,
+    pub half_life_secs: u64,
+    /// 未衰减的总调用数
+    pub total_invocations: u64,
+    /// 衰减后的调用权重
+    pub weighted_invocations: f64,
+    pub error_rate: f64,
+    pub avg_latency_ms: Option<f64>,
+    pub avg_memory_bytes: Option<u64>,
+    /// 未衰减的内存峰值
+    pub peak_memory_bytes: u64,
+}
+
+/// 一个函数（或全局）的滑动窗口和衰减汇总
+#[derive(Debug, Clone)]
+pub struct WindowedStats {
+    windows: [SlidingWindow; 3],
+    lifetime: DecayedSummary,
+}
+
+impl Default for WindowedStats {
+    fn default() -> Self {
+        Self::new(LIFETIME_HALF_LIFE_SECS)
+    }
+}
+
+impl WindowedStats {
+    pub fn new(half_life_secs: u64) -> Self {
+        Self {
+            windows: StatsWindow::ALL.map(SlidingWindow::new),
+            lifetime: DecayedSummary::new(half_life_secs),
+        }
+    }
+
+    /// 记录一次在 `now`（Unix 秒）完成的执行
+    pub fn record(&mut self, now: u64, latency: Duration, success: bool, memory: u64) {
+        for window in &mut self.windows {
+            window.record(now, latency, success, memory);
+        }
+        self.lifetime.record(now, latency, success, memory);
+    }
+
+    /// 截至 `now` 的窗口汇总
+    pub fn window(&self, window: StatsWindow, now: u64) -> WindowSummary {
+        self.windows
+            .iter()
+            .find(|sliding| sliding.window == window)
+            .expect("every window is tracked")
+            .summary(now)
+    }
+
+    /// 截至 `now` 的衰减汇总
+    pub fn lifetime(&self, now: u64) -> LifetimeSummary {
+        self.lifetime.summary(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_window_recovers_while_lifetime_decays_gradually() {
+        let mut stats = WindowedStats::default();
+        let start = 1_700_000_000 - 1_700_000_000 % 300;
+        // 前 10 分钟每秒一次调用且全部失败，之后 50 分钟全部成功
+        for t in 0..600 {
+            stats.record(start + t, Duration::from_millis(800), false, 4096);
+        }
+        let failing = start + 599;
+        assert_eq!(
+            stats.window(StatsWindow::FiveMinutes, failing).error_rate,
+            1.0
+        );
+        assert_eq!(stats.lifetime(failing).error_rate, 1.0);
+
+        let mut lifetime_rates = Vec::new();
+        for t in 600..3600 {
+            stats.record(start + t, Duration::from_millis(20), true, 1024);
+            if (t + 1) % 600 == 0 {
+                lifetime_rates.push(stats.lifetime(start + t).error_rate);
+            }
+        }
+        let now = start + 3599;
+
+        // 5 分钟窗口里只剩成功的调用
+        let recent = stats.window(StatsWindow::FiveMinutes, now);
+        assert_eq!(recent.failures, 0);
+        assert_eq!(recent.error_rate, 0.0);
+        assert!(
+            recent.invocations >= 270 && recent.invocations <= 300,
+            "{recent:?}"
+        );
+        let p95 = recent.p95_latency_ms.unwrap();
+        assert!((p95 - 20.0).abs() < 1.0, "{p95}");
+        assert_eq!(recent.peak_memory_bytes, 1024);
+        let hour = stats.window(StatsWindow::OneHour, now);
+        assert_eq!(hour.invocations, 3600);
+        assert_eq!(hour.failures, 600);
+
+        // 生命周期错误率逐步下降，但早期的失败仍有权重
+        assert!(
+            lifetime_rates.windows(2).all(|pair| pair[1] < pair[0]),
+            "{lifetime_rates:?}"
+        );
+        let lifetime = stats.lifetime(now);
+        assert!(lifetime.decayed);
+        assert_eq!(lifetime.total_invocations, 3600);
+        assert!(
+            lifetime.error_rate > 0.05 && lifetime.error_rate < 600.0 / 3600.0,
+            "{lifetime:?}"
+        );
+        assert_eq!(lifetime.peak_memory_bytes, 4096);
+
+        // 闲置后窗口清空，衰减汇总的比率不变而权重继续衰减
+        let idle = now + 2 * 3600;
+        assert_eq!(stats.window(StatsWindow::OneHour, idle).invocations, 0);
+        let later = stats.lifetime(idle);
+        assert_eq!(later.error_rate, lifetime.error_rate);
+        assert!(later.weighted_invocations < lifetime.weighted_invocations / 3.0);
+    }
+
+    #[test]
+    fn test_buckets_are_reused_after_the_window_passes() {
+        let mut stats = WindowedStats::default();
+        let start = 1_700_000_000 - 1_700_000_000 % 60;
+        for t in 0..60 {
+            stats.record(start + t, Duration::from_millis(5), t % 2 == 0, 0);
+        }
+        let minute = stats.window(StatsWindow::OneMinute, start + 59);
+        assert_eq!((minute.invocations, minute.failures), (60, 30));
+        assert_eq!(minute.error_rate, 0.5);
+
+        // 一分钟后同一批桶被复用，旧数据不再计入
+        stats.record(start + 125, Duration::from_millis(5), true, 0);
+        let minute = stats.window(StatsWindow::OneMinute, start + 125);
+        assert_eq!((minute.invocations, minute.failures), (1, 0));
+        assert_eq!(StatsWindow::parse("5m"), Some(StatsWindow::FiveMinutes));
+        assert_eq!(StatsWindow::parse("10m"), None);
+    }
+}
@@ -0,0 +1,557 @@
+//! 定义了 `init`/`teardown` 钩子的脚本函数的常驻工作进程
+//!
+//! 没有钩子的函数每次调用启动新的解释器；定义了钩子的函数由工作进程执行：
+//! 进程启动后调用一次 `init(context)`，返回值作为 `state` 传给之后每次调用的 `handler`，
+//! 进程被回收（空闲超时、超过空闲上限、函数更新或删除、服务关闭）前调用 `teardown(state)`。
+//!
+//! 宿主与工作进程之间每行一个 JSON 消息（见 [`ScriptLanguage::wrap_worker`]）：
+//! 请求为 `{"op": "init" | "invoke" | "teardown", ...}`，回复为 `{"ok": true, "output"?}`
+//! 或 `{"error": "...", "stack"?, "origin": "user_code"}`（包装代码捕获的异常都来自用户代码）。工作进程在每次回复前向标准错误写入 [`CALL_END_MARKER`] 行，
+//! 宿主据此把标准错误划分到各次调用；用户代码的打印输出重定向到标准错误。
+//!
+//! 工作进程不受内存监控，只有执行超时有效；超时的工作进程被终止而不调用 `teardown`。
+//!
+//! [`ScriptLanguage::wrap_worker`]: crate::runtime::script_cache::ScriptLanguage::wrap_worker
+
+use crate::functions::ErrorOrigin;
+use crate::runtime::events::{InstanceLifecycleEvent, LifecycleEventStream, LifecycleEventType};
+use crate::runtime::platform;
+use crate::scheduler::balancer::HashRing;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// 工作进程在每次回复前写入标准错误的标记行
+pub const CALL_END_MARKER: &str = "__FLUX_CALL_END__";
+
+/// 收到回复后等待对应标准错误标记的上限
+const STDERR_SETTLE: Duration = Duration::from_secs(1);
+
+/// 终止工作进程时请求退出到强制终止之间的宽限期
+const TERMINATION_GRACE: Duration = Duration::from_millis(500);
+
+/// 工作进程池配置
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// 每个函数（每份代码）保留的空闲工作进程数上限
+    pub max_idle_per_function: usize,
+    /// 空闲超过该时长的工作进程被回收
+    pub idle_timeout: Duration,
+    /// 等待 `teardown` 完成的上限，超时后强制终止
+    pub teardown_timeout: Duration,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_per_function: 4,
+            idle_timeout: Duration::from_secs(300),
+            teardown_timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+/// 工作进程的键：函数名和缓存的脚本路径（按内容寻址，代码变更后旧进程不再被选中）
+pub type WorkerKey = (String, PathBuf);
+
+/// 工作进程的回复
+#[derive(Debug, Deserialize)]
+pub struct WorkerReply {
+    #[serde(default)]
+    pub ok: bool,
+    #[serde(default)]
+    pub output: serde_json::Value,
+    pub error: Option<String>,
+    /// 用户代码异常的调用栈
+    #[serde(default)]
+    pub stack: Option<String>,
+    /// 失败的归属（旧的包装代码不输出，按用户代码处理）
+    #[serde(default)]
+    pub origin: Option<ErrorOrigin>,
+}
+
+/// 一次请求的结果
+#[derive(Debug)]
+pub enum WorkerOutcome {
+    /// 收到回复，附带本次请求期间的标准错误
+    Replied { reply: WorkerReply, stderr: String },
+    /// 超时未回复
+    TimedOut { stderr: String },
+    /// 进程在回复前退出或回复无法解析
+    Exited { stderr: String },
+}
+
+/// 存活工作进程的概况，实例管理器据此列出脚本函数的实例
+#[derive(Debug, Clone)]
+pub struct WorkerSummary {
+    pub id: String,
+    pub function: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub last_used: chrono::DateTime<chrono::Utc>,
+    /// 正在执行调用
+    pub busy: bool,
+    pub executions: u64,
+    pub failures: u64,
+}
+
+/// 一个常驻的解释器进程
+#[derive(Debug)]
+pub struct ScriptWorker {
+    id: String,
+    function: String,
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    stderr: mpsc::UnboundedReceiver<String>,
+    /// 进程的隔离目录，随工作进程一起删除
+    jail: TempDir,
+    last_used: Instant,
+}
+
+impl ScriptWorker {
+    /// 接管已启动的进程（标准输入、输出和错误须为管道）
+    pub fn attach(function: &str, mut child: Child, jail: TempDir) -> Result<Self> {
+        let stdin = child.stdin.take().context("Worker stdin is not piped")?;
+        let stdout = child.stdout.take().context("Worker stdout is not piped")?;
+        let stderr = child.stderr.take().context("Worker stderr is not piped")?;
+
+        // 在后台逐行读取标准错误，避免管道写满时阻塞工作进程
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            id: scru128::new_string(),
+            function: function.to_string(),
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+            stderr: receiver,
+            jail,
+            last_used: Instant::now(),
+        })
+    }
+
+    /// 工作进程ID（生命周期事件中的实例ID）
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 进程的隔离目录
+    pub fn jail(&self) -> &Path {
+        self.jail.path()
+    }
+
+    /// 进程ID
+    pub fn pid(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// 发送一个请求并在 `budget` 内等待回复
+    pub async fn request(
+        &mut self,
+        message: &serde_json::Value,
+        budget: Duration,
+    ) -> WorkerOutcome {
+        let mut line = message.to_string();
+        line.push('\n');
+        if self.stdin.write_all(line.as_bytes()).await.is_err() || self.stdin.flush().await.is_err()
+        {
+            return WorkerOutcome::Exited {
+                stderr: self.drain_stderr().await,
+            };
+        }
+        self.last_used = Instant::now();
+
+        match tokio::time::timeout(budget, self.stdout.next_line()).await {
+            Ok(Ok(Some(line))) => match serde_json::from_str(&line) {
+                Ok(reply) => WorkerOutcome::Replied {
+                    reply,
+                    stderr: self.take_call_stderr().await,
+                },
+                Err(_) => WorkerOutcome::Exited {
+                    stderr: self.drain_stderr().await,
+                },
+            },
+            Ok(_) => WorkerOutcome::Exited {
+                stderr: self.drain_stderr().await,
+            },
+            Err(_) => WorkerOutcome::TimedOut {
+                stderr: self.pending_stderr(),
+            },
+        }
+    }
+
+    /// 本次调用的标准错误：读取到调用结束标记为止
+    async fn take_call_stderr(&mut self) -> String {
+        let mut stderr = String::new();
+        let _ = tokio::time::timeout(STDERR_SETTLE, async {
+            while let Some(line) = self.stderr.recv().await {
+                if line == CALL_END_MARKER {
+                    break;
+                }
+                stderr.push_str(&line);
+                stderr.push('\n');
+            }
+        })
+        .await;
+        stderr
+    }
+
+    /// 已经到达的标准错误
+    fn pending_stderr(&mut self) -> String {
+        let mut stderr = String::new();
+        while let Ok(line) = self.stderr.try_recv() {
+            if line != CALL_END_MARKER {
+                stderr.push_str(&line);
+                stderr.push('\n');
+            }
+        }
+        stderr
+    }
+
+    /// 进程退出后剩余的标准错误
+    async fn drain_stderr(&mut self) -> String {
+        let _ = tokio::time::timeout(STDERR_SETTLE, self.child.wait()).await;
+        let mut stderr = String::new();
+        let _ = tokio::time::timeout(STDERR_SETTLE, async {
+            while let Some(line) = self.stderr.recv().await {
+                if line != CALL_END_MARKER {
+                    stderr.push_str(&line);
+                    stderr.push('\n');
+                }
+            }
+        })
+        .await;
+        stderr
+    }
+
+    /// 终止进程及其后代，不调用 `teardown`
+    pub async fn kill(mut self) {
+        platform::terminate_child(&mut self.child, TERMINATION_GRACE).await;
+    }
+
+    /// 调用 `teardown` 后终止进程，返回 `teardown` 的结果
+    async fn stop(mut self, teardown_timeout: Duration) -> std::result::Result<(), String> {
+        let outcome = self
+            .request(&serde_json::json!({"op": "teardown"}), teardown_timeout)
+            .await;
+        let result = match outcome {
+            WorkerOutcome::Replied { reply, .. } => match reply.error {
+                None => Ok(()),
+                Some(error) => Err(error),
+            },
+            WorkerOutcome::TimedOut { .. } => Err(format!(
+                "teardown did not finish within {}ms",
+                teardown_timeout.as_millis()
+            )),
+            WorkerOutcome::Exited { stderr } => {
+                Err(format!("worker exited before teardown: {}", stderr.trim()))
+            }
+        };
+        self.kill().await;
+        result
+    }
+}
+
+/// 按函数和代码保留的空闲工作进程
+#[derive(Debug, Default)]
+pub struct ScriptWorkers {
+    config: WorkerConfig,
+    idle: StdMutex<HashMap<WorkerKey, Vec<ScriptWorker>>>,
+    /// 存活的（空闲和执行中的）工作进程ID，亲和键按它们构建的哈希环映射到工作进程
+    members: StdMutex<HashMap<WorkerKey, BTreeSet<String>>>,
+    /// 初始化完成、尚未终止的工作进程概况
+    live: StdMutex<HashMap<String, WorkerSummary>>,
+    events: OnceLock<Arc<LifecycleEventStream>>,
+}
+
+impl ScriptWorkers {
+    /// 创建工作进程池
+    pub fn new(config: WorkerConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// 设置发布工作进程生命周期事件的事件流（只能设置一次）
+    pub fn attach_events(&self, events: Arc<LifecycleEventStream>) {
+        let _ = self.events.set(events);
+    }
+
+    /// 获取配置
+    pub fn config(&self) -> &WorkerConfig {
+        &self.config
+    }
+
+    /// 记录初始化完成的工作进程（此时正在执行启动它的调用）
+    pub fn track(&self, worker: &ScriptWorker) {
+        let now = chrono::Utc::now();
+        self.live.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            worker.id.clone(),
+            WorkerSummary {
+                id: worker.id.clone(),
+                function: worker.function.clone(),
+                started_at: now,
+                last_used: now,
+                busy: true,
+                executions: 0,
+                failures: 0,
+            },
+        );
+    }
+
+    /// 记录工作进程完成一次调用
+    pub fn record(&self, id: &str, success: bool) {
+        let mut live = self.live.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(summary) = live.get_mut(id) {
+            summary.busy = false;
+            summary.last_used = chrono::Utc::now();
+            summary.executions += 1;
+            summary.failures += u64::from(!success);
+        }
+    }
+
+    /// 存活工作进程的概况（按启动时间排序）
+    pub fn summaries(&self) -> Vec<WorkerSummary> {
+        let live = self.live.lock().unwrap_or_else(|e| e.into_inner());
+        let mut summaries: Vec<_> = live.values().cloned().collect();
+        summaries.sort_by_key(|summary| summary.started_at);
+        summaries
+    }
+
+    fn mark_busy(&self, worker: &ScriptWorker) {
+        let mut live = self.live.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(summary) = live.get_mut(&worker.id) {
+            summary.busy = true;
+        }
+    }
+
+    /// 取出一个空闲的工作进程（最近使用的优先）
+    pub fn checkout(&self, key: &WorkerKey) -> Option<ScriptWorker> {
+        let worker = {
+            let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+            let workers = idle.get_mut(key)?;
+            let worker = workers.pop();
+            if workers.is_empty() {
+                idle.remove(key);
+            }
+            worker
+        };
+        if let Some(worker) = &worker {
+            self.mark_busy(worker);
+        }
+        worker
+    }
+
+    /// 按亲和键取出空闲的工作进程：键映射到的工作进程空闲时取出它，否则取最近使用的；
+    /// 同时返回是否为键映射到的工作进程
+    pub fn checkout_affine(&self, key: &WorkerKey, affinity: &str) -> Option<(ScriptWorker, bool)> {
+        let target = {
+            let members = self.members.lock().unwrap_or_else(|e| e.into_inner());
+            members.get(key).and_then(|ids| {
+                HashRing::new(ids.iter().map(|id| (id.as_str(), 100)))
+                    .get(affinity)
+                    .map(str::to_string)
+            })
+        };
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let workers = idle.get_mut(key)?;
+        let mapped =
+            target.and_then(|target| workers.iter().position(|worker| worker.id == target));
+        let checked_out = match mapped {
+            Some(index) => Some((workers.remove(index), true)),
+            None => workers.pop().map(|worker| (worker, false)),
+        };
+        if workers.is_empty() {
+            idle.remove(key);
+        }
+        drop(idle);
+        if let Some((worker, _)) = &checked_out {
+            self.mark_busy(worker);
+        }
+        checked_out
+    }
+
+    /// 归还工作进程；空闲数已达上限时回收
+    pub async fn checkin(&self, key: WorkerKey, worker: ScriptWorker) {
+        self.members
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.clone())
+            .or_default()
+            .insert(worker.id.clone());
+        let surplus = {
+            let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+            let workers = idle.entry(key).or_default();
+            if workers.len() < self.config.max_idle_per_function {
+                workers.push(worker);
+                None
+            } else {
+                Some(worker)
+            }
+        };
+        if let Some(worker) = surplus {
+            self.recycle(worker, "idle worker limit reached").await;
+        }
+    }
+
+    /// 空闲的工作进程数
+    pub fn idle_count(&self) -> usize {
+        let idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        idle.values().map(Vec::len).sum()
+    }
+
+    /// 回收函数的所有空闲工作进程（函数更新或删除时），返回回收的数量
+    pub async fn retire(&self, function: &str) -> usize {
+        let retired: Vec<ScriptWorker> = {
+            let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+            let keys: Vec<WorkerKey> = idle
+                .keys()
+                .filter(|(name, _)| name == function)
+                .cloned()
+                .collect();
+            keys.iter()
+                .filter_map(|key| idle.remove(key))
+                .flatten()
+                .collect()
+        };
+        self.recycle_all(retired, "function updated or deleted")
+            .await
+    }
+
+    /// 回收空闲超时的工作进程，返回回收的数量
+    pub async fn sweep(&self) -> usize {
+        let expired: Vec<ScriptWorker> = {
+            let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+            let mut expired = Vec::new();
+            idle.retain(|_, workers| {
+                let (stale, fresh) = std::mem::take(workers)
+                    .into_iter()
+                    .partition(|worker| worker.last_used.elapsed() >= self.config.idle_timeout);
+                *workers = fresh;
+                expired.extend::<Vec<_>>(stale);
+                !workers.is_empty()
+            });
+            expired
+        };
+        self.recycle_all(expired, "idle timeout").await
+    }
+
+    /// 服务关闭时回收所有空闲工作进程
+    pub async fn shutdown(&self) -> usize {
+        self.drain_idle("shutdown").await
+    }
+
+    /// 回收所有函数的空闲工作进程（无法确定哪些函数被更新时），返回回收的数量
+    pub async fn retire_all(&self) -> usize {
+        self.drain_idle("functions updated or deleted").await
+    }
+
+    async fn drain_idle(&self, reason: &str) -> usize {
+        let all: Vec<ScriptWorker> = {
+            let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+            idle.drain().flat_map(|(_, workers)| workers).collect()
+        };
+        self.recycle_all(all, reason).await
+    }
+
+    /// 启动定期回收空闲工作进程的后台任务
+    pub fn spawn_sweeper(self: &Arc<Self>) -> JoinHandle<()> {
+        let workers = Arc::downgrade(self);
+        let period = (self.config.idle_timeout / 4).max(Duration::from_secs(1));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(workers) = workers.upgrade() else {
+                    break;
+                };
+                let swept = workers.sweep().await;
+                if swept > 0 {
+                    tracing::debug!("Recycled {} idle script workers", swept);
+                }
+            }
+        })
+    }
+
+    async fn recycle_all(&self, workers: Vec<ScriptWorker>, reason: &str) -> usize {
+        let count = workers.len();
+        futures_util::future::join_all(
+            workers
+                .into_iter()
+                .map(|worker| self.recycle(worker, reason)),
+        )
+        .await;
+        count
+    }
+
+    /// 终止不再复用的工作进程（超时或已退出），不调用 `teardown`
+    pub async fn discard(&self, worker: ScriptWorker) {
+        self.forget(&worker.id);
+        worker.kill().await;
+    }
+
+    /// 从存活的工作进程中移除，映射到它的亲和键改由其他工作进程执行
+    fn forget(&self, id: &str) {
+        let mut members = self.members.lock().unwrap_or_else(|e| e.into_inner());
+        members.retain(|_, ids| {
+            ids.remove(id);
+            !ids.is_empty()
+        });
+        drop(members);
+        self.live
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id);
+    }
+
+    /// 调用 `teardown` 后终止工作进程并发布停止事件
+    async fn recycle(&self, worker: ScriptWorker, reason: &str) {
+        self.forget(&worker.id);
+        let (id, function) = (worker.id.clone(), worker.function.clone());
+        let started = Instant::now();
+        let teardown = worker.stop(self.config.teardown_timeout).await;
+        if let Err(error) = &teardown {
+            tracing::warn!("Teardown of function '{}' failed: {}", function, error);
+        }
+        let mut event = InstanceLifecycleEvent::new(
+            &id,
+            &function,
+            LifecycleEventType::Stopped,
+            format!("Script worker stopped: {reason}"),
+        );
+        event
+            .metadata
+            .insert("reason".to_string(), reason.to_string());
+        event.metadata.insert(
+            "teardown".to_string(),
+            teardown.err().unwrap_or_else(|| "ok".to_string()),
+        );
+        event.duration_ms = Some(started.elapsed().as_millis() as u64);
+        self.publish(event).await;
+    }
+
+    /// 发布工作进程的生命周期事件（未设置事件流时忽略）
+    pub async fn publish(&self, event: InstanceLifecycleEvent) {
+        if let Some(events) = self.events.get() {
+            events.publish(event).await;
+        }
+    }
+}
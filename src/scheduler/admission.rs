@@ -0,0 +1,346 @@
+use crate::functions::priority::Priority;
+use crate::functions::{FluxError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// 调用准入配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdmissionConfig {
+    /// 同时执行的调用数（工作许可数）
+    pub max_concurrent: usize,
+    /// 每个优先级的等待队列上限，超出时拒绝
+    pub queue_capacity: usize,
+    /// 有更高优先级排队时保证给低优先级的出队比例（0 表示只在空闲时执行）
+    pub low_share: f64,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 64,
+            queue_capacity: 1000,
+            low_share: 0.1,
+        }
+    }
+}
+
+impl AdmissionConfig {
+    /// 低优先级最多连续让出的出队次数
+    fn low_every(&self) -> u64 {
+        if self.low_share <= 0.0 {
+            u64::MAX
+        } else {
+            ((1.0 / self.low_share.min(1.0)).ceil() as u64).saturating_sub(1)
+        }
+    }
+}
+
+/// 单个优先级队列的统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PriorityQueueStats {
+    /// 当前排队数
+    pub depth: usize,
+    /// 已准入的调用数（包括无需排队的）
+    pub admitted: u64,
+    /// 队列已满被拒绝的调用数
+    pub rejected: u64,
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: u64,
+    #[serde(skip)]
+    total_wait: Duration,
+}
+
+/// 准入控制统计
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AdmissionStats {
+    pub max_concurrent: usize,
+    pub running: usize,
+    pub queues: BTreeMap<Priority, PriorityQueueStats>,
+}
+
+struct Waiter {
+    sender: oneshot::Sender<AdmissionPermit>,
+    enqueued_at: Instant,
+}
+
+#[derive(Default)]
+struct AdmissionState {
+    running: usize,
+    queues: [VecDeque<Waiter>; 3],
+    stats: [PriorityQueueStats; 3],
+    /// 低优先级排队期间连续出队的高/普通优先级调用数
+    since_low: u64,
+}
+
+impl AdmissionState {
+    fn record_admitted(&mut self, priority: Priority, waited: Duration) {
+        let stats = &mut self.stats[priority.index()];
+        stats.admitted += 1;
+        stats.total_wait += waited;
+        stats.max_wait_ms = stats.max_wait_ms.max(waited.as_millis() as u64);
+    }
+
+    /// 选出下一个出队的调用：高优先级先于普通，低优先级在更高队列为空
+    /// 或已连续让出 `low_every` 次时出队
+    fn next(&mut self, low_every: u64) -> Option<(Priority, Waiter)> {
+        let low_waiting = !self.queues[Priority::Low.index()].is_empty();
+        let higher_waiting = Priority::ALL[..2]
+            .iter()
+            .any(|p| !self.queues[p.index()].is_empty());
+
+        let priority = if low_waiting && (!higher_waiting || self.since_low >= low_every) {
+            self.since_low = 0;
+            Priority::Low
+        } else {
+            let priority = Priority::ALL
+                .into_iter()
+                .find(|p| !self.queues[p.index()].is_empty())?;
+            if low_waiting {
+                self.since_low += 1;
+            }
+            priority
+        };
+        let waiter = self.queues[priority.index()].pop_front()?;
+        self.record_admitted(priority, waiter.enqueued_at.elapsed());
+        Some((priority, waiter))
+    }
+}
+
+/// 按优先级排队的调用准入控制：最多 `max_concurrent` 个调用同时执行，
+/// 其余按优先级排队等待许可（只影响排队顺序，不会中断执行中的调用）
+pub struct AdmissionController {
+    config: AdmissionConfig,
+    state: StdMutex<AdmissionState>,
+}
+
+impl std::fmt::Debug for AdmissionController {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdmissionController")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for AdmissionController {
+    fn default() -> Self {
+        Self::new(AdmissionConfig::default())
+    }
+}
+
+/// 执行许可，释放时把许可交给下一个排队的调用
+#[derive(Debug)]
+pub struct AdmissionPermit {
+    controller: Arc<AdmissionController>,
+    priority: Priority,
+    waited: Duration,
+    armed: bool,
+}
+
+impl AdmissionPermit {
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// 在队列中等待的时间
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        if self.armed {
+            self.controller.release();
+        }
+    }
+}
+
+impl AdmissionController {
+    pub fn new(config: AdmissionConfig) -> Self {
+        Self {
+            config,
+            state: StdMutex::default(),
+        }
+    }
+
+    pub fn config(&self) -> &AdmissionConfig {
+        &self.config
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, AdmissionState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 获取执行许可；没有空闲许可时按优先级排队，队列已满时返回 `QueueFull`
+    pub async fn acquire(self: &Arc<Self>, priority: Priority) -> Result<AdmissionPermit> {
+        let receiver = {
+            let mut state = self.lock();
+            if state.running < self.config.max_concurrent.max(1) {
+                state.running += 1;
+                state.record_admitted(priority, Duration::ZERO);
+                return Ok(self.permit(priority, Duration::ZERO));
+            }
+            let depth = state.queues[priority.index()].len();
+            if depth >= self.config.queue_capacity {
+                state.stats[priority.index()].rejected += 1;
+                return Err(FluxError::QueueFull { priority, depth });
+            }
+            let (sender, receiver) = oneshot::channel();
+            state.queues[priority.index()].push_back(Waiter {
+                sender,
+                enqueued_at: Instant::now(),
+            });
+            receiver
+        };
+        receiver
+            .await
+            .map_err(|_| FluxError::Runtime("Admission queue was dropped".to_string()))
+    }
+
+    fn permit(self: &Arc<Self>, priority: Priority, waited: Duration) -> AdmissionPermit {
+        AdmissionPermit {
+            controller: self.clone(),
+            priority,
+            waited,
+            armed: true,
+        }
+    }
+
+    /// 把释放的许可交给下一个仍在等待的调用，没有等待者时归还
+    fn release(self: &Arc<Self>) {
+        loop {
+            let (priority, waiter) = {
+                let mut state = self.lock();
+                match state.next(self.config.low_every()) {
+                    Some(next) => next,
+                    None => {
+                        state.running = state.running.saturating_sub(1);
+                        return;
+                    }
+                }
+            };
+            let permit = self.permit(priority, waiter.enqueued_at.elapsed());
+            match waiter.sender.send(permit) {
+                Ok(()) => return,
+                // 等待者已取消：撤销这次出队，继续交给下一个
+                Err(mut permit) => {
+                    permit.armed = false;
+                    self.lock().stats[priority.index()].admitted -= 1;
+                }
+            }
+        }
+    }
+
+    /// 所有执行许可都被占用，新的调用需要排队
+    pub fn is_saturated(&self) -> bool {
+        self.lock().running >= self.config.max_concurrent.max(1)
+    }
+
+    /// 各优先级的队列深度和等待时间
+    pub fn stats(&self) -> AdmissionStats {
+        let state = self.lock();
+        let queues = Priority::ALL
+            .into_iter()
+            .map(|priority| {
+                let mut stats = state.stats[priority.index()].clone();
+                stats.depth = state.queues[priority.index()].len();
+                if stats.admitted > 0 {
+                    stats.avg_wait_ms =
+                        stats.total_wait.as_secs_f64() * 1000.0 / stats.admitted as f64;
+                }
+                (priority, stats)
+            })
+            .collect();
+        AdmissionStats {
+            max_concurrent: self.config.max_concurrent,
+            running: state.running,
+            queues,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_high_priority_skips_low_backlog() {
+        let controller = Arc::new(AdmissionController::new(AdmissionConfig {
+            max_concurrent: 2,
+            queue_capacity: 100,
+            low_share: 0.1,
+        }));
+
+        // 用低优先级任务占满许可并积压队列
+        let mut tasks = Vec::new();
+        for _ in 0..40 {
+            let controller = controller.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = controller.acquire(Priority::Low).await.unwrap();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }));
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(controller.stats().queues[&Priority::Low].depth > 30);
+
+        // 高优先级最多等待一个低优先级任务结束，而不是整个积压
+        let permit = controller.acquire(Priority::High).await.unwrap();
+        assert!(permit.waited() < Duration::from_millis(100));
+        drop(permit);
+
+        let stats = controller.stats();
+        assert_eq!(stats.queues[&Priority::High].admitted, 1);
+        assert!(stats.queues[&Priority::Low].depth > 20);
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(controller.stats().running, 0);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_rejects_and_low_is_not_starved() {
+        let controller = Arc::new(AdmissionController::new(AdmissionConfig {
+            max_concurrent: 1,
+            queue_capacity: 2,
+            low_share: 0.5,
+        }));
+        let running = controller.acquire(Priority::Normal).await.unwrap();
+
+        let order = Arc::new(StdMutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for priority in [Priority::Low, Priority::High, Priority::High, Priority::Low] {
+            let controller = controller.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = controller.acquire(priority).await.unwrap();
+                order.lock().unwrap().push(priority);
+            }));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let err = controller.acquire(Priority::Low).await.unwrap_err();
+        assert!(matches!(
+            err,
+            FluxError::QueueFull {
+                priority: Priority::Low,
+                depth: 2
+            }
+        ));
+
+        // low_share = 0.5：每次高优先级出队后轮到一次低优先级
+        drop(running);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(
+            *order.lock().unwrap(),
+            [Priority::High, Priority::Low, Priority::High, Priority::Low]
+        );
+        assert_eq!(controller.stats().queues[&Priority::Low].rejected, 1);
+    }
+}
@@ -250,7 +250,7 @@ pub struct LoadBalancerState {
     /// 随机种子
     pub random_seed: u64,
     /// 一致性哈希环
-    pub consistent_hash_ring: Vec<(u64, String)>,
+    pub consistent_hash_ring: HashRing,
     /// 自适应权重
     pub adaptive_weights: HashMap<String, f64>,
 }
@@ -573,22 +573,10 @@ impl LoadBalancer {
         request_key: Option<&str>,
     ) -> String {
         let key = request_key.unwrap_or("default");
-        let hash = self.hash_key(key);
-
         let state = self.state.read().await;
-
-        // 在哈希环中查找
-        for (ring_hash, target_id) in &state.consistent_hash_ring {
-            if hash <= *ring_hash {
-                return target_id.clone();
-            }
-        }
-
-        // 如果没找到，返回第一个
-        if let Some((_, target_id)) = state.consistent_hash_ring.first() {
-            target_id.clone()
-        } else {
-            targets[0].0.clone()
+        match state.consistent_hash_ring.get(key) {
+            Some(target_id) => target_id.to_string(),
+            None => targets[0].0.clone(),
         }
     }
 
@@ -621,33 +609,12 @@ impl LoadBalancer {
     /// 更新一致性哈希环
     async fn update_consistent_hash_ring(&self) {
         let targets = self.targets.read().await;
-        let mut state = self.state.write().await;
-
-        state.consistent_hash_ring.clear();
-
-        for (id, target) in targets.iter() {
-            // 每个目标根据权重创建适量的虚拟节点（限制最大数量）
-            let virtual_nodes = ((target.weight as usize).max(1) * 3).min(300); // 最多300个虚拟节点
-
-            for i in 0..virtual_nodes {
-                let virtual_key = format!("{id}:{i}");
-                let hash = self.hash_key(&virtual_key);
-                state.consistent_hash_ring.push((hash, id.clone()));
-            }
-        }
-
-        // 排序哈希环
-        state.consistent_hash_ring.sort_by_key(|(hash, _)| *hash);
-    }
-
-    /// 哈希函数
-    fn hash_key(&self, key: &str) -> u64 {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        hasher.finish()
+        let ring = HashRing::new(
+            targets
+                .iter()
+                .map(|(id, target)| (id.as_str(), target.weight)),
+        );
+        self.state.write().await.consistent_hash_ring = ring;
     }
 
     /// 更新断路器状态
@@ -798,6 +765,55 @@ impl PerformanceMonitor {
     }
 }
 
+/// 一致性哈希环
+///
+/// 每个成员按权重放置虚拟节点（每单位权重 3 个，最多 300 个），键映射到环上顺时针方向的
+/// 第一个虚拟节点。成员增减时只有原来映射到该成员（或现在映射到新成员）的键改变归属。
+#[derive(Debug, Clone, Default)]
+pub struct HashRing {
+    nodes: Vec<(u64, String)>,
+}
+
+impl HashRing {
+    /// 按成员 ID 和权重构建哈希环
+    pub fn new<'a>(members: impl IntoIterator<Item = (&'a str, u32)>) -> Self {
+        let mut nodes = Vec::new();
+        for (id, weight) in members {
+            let virtual_nodes = ((weight as usize).max(1) * 3).min(300);
+            for i in 0..virtual_nodes {
+                nodes.push((hash_key(&format!("{id}:{i}")), id.to_string()));
+            }
+        }
+        nodes.sort();
+        Self { nodes }
+    }
+
+    /// 环上没有成员
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 键映射到的成员
+    pub fn get(&self, key: &str) -> Option<&str> {
+        let hash = hash_key(key);
+        let index = self.nodes.partition_point(|(node, _)| *node < hash);
+        self.nodes
+            .get(index)
+            .or_else(|| self.nodes.first())
+            .map(|(_, id)| id.as_str())
+    }
+}
+
+/// 哈希函数（固定种子，同一个键在各个进程中的哈希值相同）
+fn hash_key(key: &str) -> u64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
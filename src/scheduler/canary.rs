@@ -0,0 +1,533 @@
+//! 金丝雀探测
+//!
+//! 配置了 `canary` 的函数由后台任务按间隔以固定输入调用（见 [`SimpleScheduler::run_canaries`]），
+//! 每个函数的健康状态记录在 [`CanaryProbes`] 中，`/health/ready` 汇总失败的探测。
+//! 探测调用与影子重放一样没有副作用，以最低优先级经过命名空间限制和准入队列。
+
+use super::{ScheduleOptions, SimpleScheduler};
+use crate::functions::canary::{CRITICAL_LABEL, CanaryConfig};
+use crate::functions::priority::Priority;
+use crate::functions::{FunctionMetadata, InvokeRequest, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+/// 金丝雀探测的全局配置（`[canaries]`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CanaryProbeConfig {
+    /// 是否运行金丝雀探测（默认 true），关闭后不启动后台任务，就绪检查也忽略探测结果
+    pub enabled: bool,
+    /// 检查到期探测的间隔（毫秒）
+    pub tick_ms: u64,
+}
+
+impl Default for CanaryProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tick_ms: 1000,
+        }
+    }
+}
+
+/// 金丝雀探测的健康状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CanaryStatus {
+    /// 尚未完成探测
+    Pending,
+    Passing,
+    Failing,
+}
+
+impl CanaryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Passing => "passing",
+            Self::Failing => "failing",
+        }
+    }
+}
+
+/// 函数的金丝雀健康状态
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct CanaryHealth {
+    pub status: CanaryStatus,
+    /// 连续失败次数，探测成功后清零
+    pub consecutive_failures: u32,
+    /// 最近一次失败的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// 最近一次探测的耗时
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_latency_ms: Option<u64>,
+    pub runs: u64,
+    pub failures: u64,
+}
+
+impl Default for CanaryHealth {
+    fn default() -> Self {
+        Self {
+            status: CanaryStatus::Pending,
+            consecutive_failures: 0,
+            last_error: None,
+            last_run_at: None,
+            last_success_at: None,
+            last_latency_ms: None,
+            runs: 0,
+            failures: 0,
+        }
+    }
+}
+
+/// 函数的金丝雀配置和健康状态
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CanaryReport {
+    pub function: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<CanaryConfig>,
+    pub health: CanaryHealth,
+    /// 函数带有 `critical=true` 标签，探测失败时就绪检查失败
+    pub critical: bool,
+}
+
+/// 就绪检查中失败的金丝雀
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FailingCanary {
+    pub profile: String,
+    pub function: String,
+    pub critical: bool,
+    pub consecutive_failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// `/health/ready` 的结果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReadinessReport {
+    /// 没有关键函数的金丝雀失败
+    pub ready: bool,
+    /// 是否运行金丝雀探测（`[canaries] enabled`）
+    pub canaries_enabled: bool,
+    pub passing: usize,
+    pub pending: usize,
+    /// 失败的金丝雀，非关键函数只作为警告
+    pub failing: Vec<FailingCanary>,
+}
+
+impl ReadinessReport {
+    /// 汇总各调度器配置的金丝雀报告
+    pub fn from_reports(
+        canaries_enabled: bool,
+        reports: impl IntoIterator<Item = (String, CanaryReport)>,
+    ) -> Self {
+        let mut report = Self {
+            ready: true,
+            canaries_enabled,
+            passing: 0,
+            pending: 0,
+            failing: Vec::new(),
+        };
+        if !canaries_enabled {
+            return report;
+        }
+        for (profile, canary) in reports {
+            match canary.health.status {
+                CanaryStatus::Pending => report.pending += 1,
+                CanaryStatus::Passing => report.passing += 1,
+                CanaryStatus::Failing => {
+                    report.ready &= !canary.critical;
+                    report.failing.push(FailingCanary {
+                        profile,
+                        function: canary.function,
+                        critical: canary.critical,
+                        consecutive_failures: canary.health.consecutive_failures,
+                        last_error: canary.health.last_error,
+                    });
+                }
+            }
+        }
+        report
+    }
+}
+
+/// 一个函数的探测配置、状态和下一次探测时间
+#[derive(Debug)]
+struct ProbeEntry {
+    config: CanaryConfig,
+    critical: bool,
+    /// 函数超时，探测未配置超时时使用
+    timeout_ms: u64,
+    health: CanaryHealth,
+    next_due: Instant,
+    running: bool,
+}
+
+#[derive(Debug, Default)]
+struct ProbeTable {
+    /// 同步时注册表的修订号，变化后重新读取函数的探测配置
+    revision: Option<u64>,
+    entries: HashMap<String, ProbeEntry>,
+}
+
+/// 按函数记录的金丝雀探测状态
+#[derive(Debug)]
+pub struct CanaryProbes {
+    enabled: AtomicBool,
+    table: StdMutex<ProbeTable>,
+}
+
+impl Default for CanaryProbes {
+    fn default() -> Self {
+        Self {
+            enabled: AtomicBool::new(true),
+            table: StdMutex::default(),
+        }
+    }
+}
+
+impl CanaryProbes {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    fn needs_sync(&self, revision: u64) -> bool {
+        self.table
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .revision
+            != Some(revision)
+    }
+
+    /// 按函数的当前配置更新探测表：配置变化的探测重置状态并立即到期，
+    /// 删除或停用探测的函数移出探测表
+    fn sync(&self, revision: u64, functions: &[FunctionMetadata], now: Instant) {
+        let mut table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = std::mem::take(&mut table.entries);
+        for function in functions {
+            let Some(config) = function.canary.as_ref().filter(|config| config.enabled) else {
+                continue;
+            };
+            let critical = function
+                .labels
+                .get(CRITICAL_LABEL)
+                .is_some_and(|value| value == "true");
+            let entry = match entries.remove(&function.name) {
+                Some(entry) if entry.config == *config => ProbeEntry {
+                    critical,
+                    timeout_ms: function.timeout_ms,
+                    ..entry
+                },
+                _ => ProbeEntry {
+                    config: config.clone(),
+                    critical,
+                    timeout_ms: function.timeout_ms,
+                    health: CanaryHealth::default(),
+                    next_due: now,
+                    running: false,
+                },
+            };
+            table.entries.insert(function.name.clone(), entry);
+        }
+        table.revision = Some(revision);
+    }
+
+    /// 取出到期且未在运行的探测，标记为运行中并安排下一次
+    fn take_due(&self, now: Instant) -> Vec<(String, CanaryConfig, Duration)> {
+        let mut table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        table
+            .entries
+            .iter_mut()
+            .filter(|(_, entry)| !entry.running && entry.next_due <= now)
+            .map(|(name, entry)| {
+                entry.running = true;
+                entry.next_due = now + Duration::from_secs(entry.config.interval_secs);
+                let timeout =
+                    Duration::from_millis(entry.config.timeout_ms.unwrap_or(entry.timeout_ms));
+                (name.clone(), entry.config.clone(), timeout)
+            })
+            .collect()
+    }
+
+    /// 记录一次探测的结果；探测期间配置已变化时丢弃
+    fn record(
+        &self,
+        name: &str,
+        config: &CanaryConfig,
+        latency: Duration,
+        outcome: std::result::Result<(), String>,
+    ) {
+        let mut table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(entry) = table.entries.get_mut(name) else {
+            return;
+        };
+        if entry.config != *config {
+            return;
+        }
+        entry.running = false;
+        let health = &mut entry.health;
+        let now = Utc::now();
+        health.runs += 1;
+        health.last_run_at = Some(now);
+        health.last_latency_ms = Some(latency.as_millis() as u64);
+        match outcome {
+            Ok(()) => {
+                health.status = CanaryStatus::Passing;
+                health.consecutive_failures = 0;
+                health.last_success_at = Some(now);
+            }
+            Err(error) => {
+                if health.consecutive_failures == 0 {
+                    tracing::warn!("Canary of function {} is failing: {}", name, error);
+                }
+                health.status = CanaryStatus::Failing;
+                health.consecutive_failures += 1;
+                health.failures += 1;
+                health.last_error = Some(error);
+            }
+        }
+    }
+
+    /// 函数的健康状态，没有启用探测时为 `None`
+    fn health(&self, name: &str) -> Option<(CanaryHealth, bool)> {
+        let table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        table
+            .entries
+            .get(name)
+            .map(|entry| (entry.health.clone(), entry.critical))
+    }
+
+    fn reports(&self) -> Vec<CanaryReport> {
+        let table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        let mut reports: Vec<CanaryReport> = table
+            .entries
+            .iter()
+            .map(|(name, entry)| CanaryReport {
+                function: name.clone(),
+                config: Some(entry.config.clone()),
+                health: entry.health.clone(),
+                critical: entry.critical,
+            })
+            .collect();
+        reports.sort_by(|a, b| a.function.cmp(&b.function));
+        reports
+    }
+}
+
+impl SimpleScheduler {
+    /// 注册表变化后按函数的当前配置同步探测表
+    async fn sync_canaries(&self) {
+        let revision = self.registry.last_revision();
+        if self.canaries.needs_sync(revision) {
+            let functions = self.registry.list().await;
+            self.canaries.sync(revision, &functions, Instant::now());
+        }
+    }
+
+    /// 执行一轮到期的金丝雀探测，返回探测的函数数
+    pub async fn run_canaries(&self) -> usize {
+        if !self.canaries.is_enabled() {
+            return 0;
+        }
+        self.sync_canaries().await;
+        let due = self.canaries.take_due(Instant::now());
+        let count = due.len();
+        futures_util::future::join_all(due.into_iter().map(|(name, config, timeout)| async move {
+            let started = Instant::now();
+            let outcome = self.probe(&name, &config, timeout).await;
+            self.canaries
+                .record(&name, &config, started.elapsed(), outcome);
+        }))
+        .await;
+        count
+    }
+
+    /// 以探测输入调用函数，检查调用成功且输出符合期望
+    async fn probe(
+        &self,
+        name: &str,
+        config: &CanaryConfig,
+        timeout: Duration,
+    ) -> std::result::Result<(), String> {
+        let request = InvokeRequest {
+            input: config.input.clone(),
+            retry_policy: None,
+            priority: Some(Priority::Low),
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+        let options = ScheduleOptions {
+            invocation_id: Some(format!("canary-{}", scru128::new_string())),
+            canary: true,
+            ..Default::default()
+        };
+        match tokio::time::timeout(timeout, self.schedule_with(name, request, options)).await {
+            Err(_) => Err(format!(
+                "Canary invocation timed out after {} ms",
+                timeout.as_millis()
+            )),
+            Ok(Err(e)) => Err(e.to_string()),
+            Ok(Ok(response)) => match response.status.failure_message() {
+                Some(error) => Err(error),
+                None => config.check_output(&response.output),
+            },
+        }
+    }
+
+    /// 在后台按 `tick` 检查并执行到期的探测，慢的探测不阻塞其他函数的探测
+    pub fn spawn_canaries(self: &Arc<Self>, tick: Duration) -> JoinHandle<()> {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(tick);
+            loop {
+                ticker.tick().await;
+                let scheduler = scheduler.clone();
+                tokio::spawn(async move { scheduler.run_canaries().await });
+            }
+        })
+    }
+
+    /// 函数的金丝雀配置和健康状态
+    pub async fn canary_report(&self, name: &str) -> Result<CanaryReport> {
+        let function = self.registry.get(name).await?;
+        self.sync_canaries().await;
+        let (health, critical) = self.canaries.health(name).unwrap_or_else(|| {
+            let critical = function
+                .labels
+                .get(CRITICAL_LABEL)
+                .is_some_and(|value| value == "true");
+            (CanaryHealth::default(), critical)
+        });
+        Ok(CanaryReport {
+            function: function.name,
+            config: function.canary,
+            health,
+            critical,
+        })
+    }
+
+    /// 所有启用探测的函数的金丝雀报告（按函数名排序）
+    pub async fn canary_reports(&self) -> Vec<CanaryReport> {
+        self.sync_canaries().await;
+        self.canaries.reports()
+    }
+
+    /// 设置（`None` 时移除）函数的金丝雀探测，配置变化后探测状态重置
+    pub async fn set_canary(
+        &self,
+        name: &str,
+        canary: Option<CanaryConfig>,
+    ) -> Result<FunctionMetadata> {
+        if let Some(canary) = &canary {
+            canary.validate()?;
+        }
+        let _guard = self.registry.lock_name(name).await;
+        let mut function = self.registry.get(name).await?;
+        function.canary = canary;
+        function.updated_at = Utc::now();
+        self.registry
+            .upsert_if(function, None)
+            .await
+            .map(|(function, _)| function)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn canary(input: serde_json::Value, expected: serde_json::Value) -> CanaryConfig {
+        CanaryConfig {
+            enabled: true,
+            interval_secs: 60,
+            input,
+            expected_output: Some(expected),
+            timeout_ms: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_canaries_track_health_without_touching_stats() {
+        let scheduler = SimpleScheduler::new();
+        let mut add = FunctionMetadata::new("add".to_string(), "return a + b".to_string());
+        add.canary = Some(canary(json!({"a": 1, "b": 2}), json!({"result": 3})));
+        scheduler.register_function(add).await.unwrap();
+        let mut critical = FunctionMetadata::new("mul".to_string(), "return a * b".to_string());
+        critical
+            .labels
+            .insert(CRITICAL_LABEL.to_string(), "true".to_string());
+        critical.canary = Some(canary(json!({"a": 2, "b": 2}), json!({"result": 5})));
+        scheduler.register_function(critical).await.unwrap();
+
+        assert_eq!(scheduler.run_canaries().await, 2);
+        // 未到间隔时不再探测
+        assert_eq!(scheduler.run_canaries().await, 0);
+
+        let report = scheduler.canary_report("add").await.unwrap();
+        assert_eq!(report.health.status, CanaryStatus::Passing);
+        assert_eq!(report.health.runs, 1);
+        let report = scheduler.canary_report("mul").await.unwrap();
+        assert_eq!(report.health.status, CanaryStatus::Failing);
+        assert_eq!(report.health.consecutive_failures, 1);
+        assert!(report.critical);
+        let error = report.health.last_error.unwrap();
+        assert!(error.contains("expected output"), "{error}");
+
+        let readiness = ReadinessReport::from_reports(
+            true,
+            scheduler
+                .canary_reports()
+                .await
+                .into_iter()
+                .map(|report| ("default".to_string(), report)),
+        );
+        assert!(!readiness.ready);
+        assert_eq!((readiness.passing, readiness.failing.len()), (1, 1));
+
+        // 探测调用不进入调用统计和执行历史
+        let monitor = scheduler.runtime().monitor();
+        let stats = monitor.get_global_stats().await;
+        assert_eq!(stats.total_requests, 0);
+        assert_eq!(stats.canary_executions, 2);
+
+        // 修正配置后状态重置并立即重新探测
+        scheduler
+            .set_canary(
+                "mul",
+                Some(canary(json!({"a": 2, "b": 2}), json!({"result": 4}))),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            scheduler.canary_report("mul").await.unwrap().health.status,
+            CanaryStatus::Pending
+        );
+        assert_eq!(scheduler.run_canaries().await, 1);
+        let report = scheduler.canary_report("mul").await.unwrap();
+        assert_eq!(report.health.status, CanaryStatus::Passing);
+        assert_eq!(report.health.runs, 1);
+
+        // 全局关闭后不再探测
+        scheduler.set_canary("add", None).await.unwrap();
+        assert_eq!(scheduler.canary_reports().await.len(), 1);
+        scheduler.canaries().set_enabled(false);
+        assert_eq!(scheduler.run_canaries().await, 0);
+    }
+}
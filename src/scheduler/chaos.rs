@@ -0,0 +1,398 @@
+use crate::functions::status::ErrorKind;
+use crate::functions::{ExecutionStatus, FluxError, InvocationTiming, InvokeResponse, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex as StdMutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 故障注入配置（`[chaos]`）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChaosConfig {
+    /// 是否允许通过 `/admin/chaos/rules` 注入故障，默认关闭
+    pub enabled: bool,
+}
+
+/// 注入的故障
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChaosAction {
+    /// 延迟 `delay_ms` 后正常执行
+    Delay,
+    /// 不执行函数，直接返回错误
+    Fail,
+    /// 不执行函数，等到截止时间过后返回超时
+    Timeout,
+    /// 正常执行，但把输出替换为截断的 JSON 文本
+    CorruptOutput,
+}
+
+/// 规则匹配条件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChaosMatch {
+    /// 函数名通配符，`*` 匹配任意字符串，`?` 匹配单个字符
+    pub function: String,
+}
+
+/// 创建规则的请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChaosRuleRequest {
+    #[serde(rename = "match")]
+    pub matcher: ChaosMatch,
+    pub action: ChaosAction,
+    /// `delay` 的延迟时间（毫秒）
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+    /// 每次调用触发的概率（0 到 1，默认 1）
+    #[serde(default = "default_probability")]
+    pub probability: f64,
+    /// 规则的存活时间（秒），缺省时一直生效直到删除
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+}
+
+fn default_probability() -> f64 {
+    1.0
+}
+
+/// 生效中的故障注入规则
+#[derive(Debug, Clone, Serialize)]
+pub struct ChaosRule {
+    pub id: String,
+    #[serde(rename = "match")]
+    pub matcher: ChaosMatch,
+    pub action: ChaosAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+    pub probability: f64,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 已注入的次数
+    pub injected: u64,
+}
+
+impl ChaosRule {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// 一次调用要注入的故障
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChaosInjection {
+    pub action: ChaosAction,
+    pub delay: Duration,
+}
+
+/// 故障注入规则（进程内的调度器共享）
+///
+/// 未启用时调度器只做一次原子读取，不查看规则。
+#[derive(Debug)]
+pub struct ChaosEngine {
+    enabled: AtomicBool,
+    rules: StdMutex<Vec<ChaosRule>>,
+    /// 按概率触发用的伪随机数状态（xorshift64*）
+    rng: StdMutex<u64>,
+}
+
+impl Default for ChaosEngine {
+    fn default() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::with_seed(seed)
+    }
+}
+
+impl ChaosEngine {
+    /// 使用固定种子（测试中得到可复现的触发序列）
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            rules: StdMutex::default(),
+            rng: StdMutex::new(seed | 1),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if !self.enabled.swap(enabled, Ordering::Relaxed) && enabled {
+            tracing::warn!("Chaos fault injection is enabled");
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// 添加规则
+    pub fn add_rule(&self, request: ChaosRuleRequest) -> Result<ChaosRule> {
+        if !self.is_enabled() {
+            return Err(FluxError::ChaosDisabled);
+        }
+        if request.matcher.function.is_empty() {
+            return Err(FluxError::ValidationError {
+                reason: "match.function must not be empty".to_string(),
+            });
+        }
+        if !(0.0..=1.0).contains(&request.probability) {
+            return Err(FluxError::ValidationError {
+                reason: "probability must be between 0 and 1".to_string(),
+            });
+        }
+        if request.action == ChaosAction::Delay && request.delay_ms.is_none() {
+            return Err(FluxError::ValidationError {
+                reason: "delay_ms is required for the delay action".to_string(),
+            });
+        }
+
+        let now = Utc::now();
+        let rule = ChaosRule {
+            id: scru128::new_string(),
+            matcher: request.matcher,
+            action: request.action,
+            delay_ms: request
+                .delay_ms
+                .filter(|_| request.action == ChaosAction::Delay),
+            probability: request.probability,
+            created_at: now,
+            expires_at: request
+                .ttl_secs
+                .map(|ttl| now + chrono::Duration::seconds(ttl.min(i64::MAX as u64) as i64)),
+            injected: 0,
+        };
+        tracing::warn!(
+            "Added chaos rule {}: {:?} for functions matching '{}' (probability {})",
+            rule.id,
+            rule.action,
+            rule.matcher.function,
+            rule.probability
+        );
+        self.lock_rules().push(rule.clone());
+        Ok(rule)
+    }
+
+    /// 生效中的规则（已过期的规则同时被清除）
+    pub fn rules(&self) -> Vec<ChaosRule> {
+        let mut rules = self.lock_rules();
+        Self::prune(&mut rules);
+        rules.clone()
+    }
+
+    /// 删除规则，返回是否存在
+    pub fn remove_rule(&self, id: &str) -> bool {
+        let mut rules = self.lock_rules();
+        let before = rules.len();
+        rules.retain(|rule| rule.id != id);
+        rules.len() != before
+    }
+
+    /// 本次调用要注入的故障：按创建顺序取第一个匹配函数名的规则，按其概率决定是否触发
+    pub fn inject(&self, function_name: &str) -> Option<ChaosInjection> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let mut rules = self.lock_rules();
+        Self::prune(&mut rules);
+        let rule = rules
+            .iter_mut()
+            .find(|rule| glob_match(&rule.matcher.function, function_name))?;
+        if self.roll() >= rule.probability {
+            return None;
+        }
+        rule.injected += 1;
+        tracing::info!(
+            "Injecting chaos {:?} into {} (rule {})",
+            rule.action,
+            function_name,
+            rule.id
+        );
+        Some(ChaosInjection {
+            action: rule.action,
+            delay: Duration::from_millis(rule.delay_ms.unwrap_or_default()),
+        })
+    }
+
+    fn lock_rules(&self) -> std::sync::MutexGuard<'_, Vec<ChaosRule>> {
+        self.rules.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn prune(rules: &mut Vec<ChaosRule>) {
+        let now = Utc::now();
+        rules.retain(|rule| !rule.is_expired(now));
+    }
+
+    /// `[0, 1)` 中的伪随机数
+    fn roll(&self) -> f64 {
+        let mut state = self.rng.lock().unwrap_or_else(|e| e.into_inner());
+        *state ^= *state >> 12;
+        *state ^= *state << 25;
+        *state ^= *state >> 27;
+        let value = state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// 不执行函数时的故障响应（`fail`、`timeout`）
+pub fn injected_response(action: ChaosAction, execution_time: Duration) -> InvokeResponse {
+    let (output, status) = match action {
+        ChaosAction::Timeout => (
+            serde_json::json!({"error": "Execution timeout"}),
+            ExecutionStatus::Timeout,
+        ),
+        _ => {
+            let message = "Injected failure (chaos)";
+            (
+                serde_json::json!({"error": message}),
+                ExecutionStatus::error(ErrorKind::Internal, message),
+            )
+        }
+    };
+    InvokeResponse {
+        output,
+        execution_time_ms: execution_time.as_millis() as u64,
+        status,
+        request_id: None,
+        attempts_made: 1,
+        succeeded_on_retry: false,
+        cold_start: false,
+        output_schema_violations: None,
+        backend: None,
+        replayed: false,
+        chaos_injected: true,
+        return_type_mismatch: false,
+        timing: InvocationTiming {
+            total_ms: execution_time.as_millis() as u64,
+            ..Default::default()
+        },
+        termination: None,
+        execution_path: None,
+        peer: None,
+        redirected_from: None,
+    }
+}
+
+/// `corrupt_output`：把输出替换为截断一半的 JSON 文本
+pub fn corrupt_output(output: &serde_json::Value) -> serde_json::Value {
+    let text = output.to_string();
+    let mut end = text.len() / 2;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    serde_json::Value::String(text[..end].to_string())
+}
+
+/// 通配符匹配：`*` 匹配任意（包括空）字符串，`?` 匹配单个字符
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 `*` 的位置及其匹配到的文本位置，失配时回溯
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(function: &str, action: ChaosAction, probability: f64) -> ChaosRuleRequest {
+        ChaosRuleRequest {
+            matcher: ChaosMatch {
+                function: function.to_string(),
+            },
+            action,
+            delay_ms: None,
+            probability,
+            ttl_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("pay*", "payment"));
+        assert!(glob_match("pay*", "pay"));
+        assert!(glob_match("*ment", "payment"));
+        assert!(glob_match("p?y*t", "payment"));
+        assert!(glob_match("billing/*", "billing/charge"));
+        assert!(!glob_match("pay*", "repay"));
+        assert!(!glob_match("pay", "payment"));
+    }
+
+    #[test]
+    fn test_probability_is_respected() {
+        let engine = ChaosEngine::with_seed(42);
+        engine.set_enabled(true);
+        let rule = engine
+            .add_rule(rule("pay*", ChaosAction::Fail, 0.2))
+            .unwrap();
+
+        let invocations = 10_000;
+        let injected = (0..invocations)
+            .filter(|_| engine.inject("payment").is_some())
+            .count();
+        let rate = injected as f64 / invocations as f64;
+        assert!((rate - 0.2).abs() < 0.02, "injection rate {rate}");
+        assert_eq!(engine.rules()[0].injected, injected as u64);
+
+        // 不匹配的函数不受影响
+        assert!((0..1000).all(|_| engine.inject("orders").is_none()));
+
+        assert!(engine.remove_rule(&rule.id));
+        assert!(engine.inject("payment").is_none());
+    }
+
+    #[test]
+    fn test_rules_expire_and_require_enabled() {
+        let engine = ChaosEngine::default();
+        assert!(matches!(
+            engine.add_rule(rule("*", ChaosAction::Fail, 1.0)),
+            Err(FluxError::ChaosDisabled)
+        ));
+
+        engine.set_enabled(true);
+        assert!(engine.add_rule(rule("*", ChaosAction::Delay, 1.0)).is_err());
+        assert!(engine.add_rule(rule("*", ChaosAction::Fail, 1.5)).is_err());
+        let mut expiring = rule("*", ChaosAction::Timeout, 1.0);
+        expiring.ttl_secs = Some(0);
+        engine.add_rule(expiring).unwrap();
+        assert!(engine.rules().is_empty());
+        assert!(engine.inject("anything").is_none());
+
+        // 关闭后规则保留但不再触发
+        engine.add_rule(rule("*", ChaosAction::Fail, 1.0)).unwrap();
+        engine.set_enabled(false);
+        assert!(engine.inject("anything").is_none());
+        assert_eq!(engine.rules().len(), 1);
+    }
+
+    #[test]
+    fn test_corrupt_output_truncates_json() {
+        let corrupted = corrupt_output(&serde_json::json!({"message": "héllo wörld"}));
+        let text = corrupted.as_str().unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(text).is_err());
+        assert!(text.starts_with("{\"message\""));
+    }
+}
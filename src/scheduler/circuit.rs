@@ -0,0 +1,533 @@
+//! 调用路径上的按函数熔断
+//!
+//! 每个函数在滑动窗口内统计调用的失败率，达到阈值（且调用数不少于 `min_requests`）时打开熔断：
+//! 冷却期内的调用直接以 `CircuitOpen` 拒绝，不再编译或执行；冷却结束后进入半开状态，
+//! 放行有限次数的探测调用，全部成功后关闭，任一失败则重新打开。
+
+use crate::functions::{FluxError, FunctionMetadata, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// 每个函数保留的最近状态转换数
+pub const TRANSITIONS_PER_FUNCTION: usize = 50;
+
+/// 熔断配置（`[circuit_breaker]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    /// 是否启用熔断（函数可以通过 `circuit_breaker = false` 单独关闭）
+    pub enabled: bool,
+    /// 统计失败率的滑动窗口（秒）
+    pub window_secs: u64,
+    /// 窗口内的最少调用数，不足时不打开熔断
+    pub min_requests: usize,
+    /// 打开熔断的失败率阈值（0 到 1）
+    pub failure_threshold: f64,
+    /// 打开后的冷却时间（毫秒），之后进入半开状态
+    pub open_ms: u64,
+    /// 半开状态放行的探测调用数，全部成功后关闭熔断
+    pub half_open_probes: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_secs: 60,
+            min_requests: 20,
+            failure_threshold: 0.5,
+            open_ms: 30_000,
+            half_open_probes: 3,
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| {
+            Err(FluxError::ValidationError {
+                reason: format!("circuit_breaker: {reason}"),
+            })
+        };
+        if !(self.failure_threshold > 0.0 && self.failure_threshold <= 1.0) {
+            return invalid("failure_threshold must be in (0, 1]");
+        }
+        if self.window_secs == 0 || self.min_requests == 0 || self.half_open_probes == 0 {
+            return invalid("window_secs, min_requests and half_open_probes must be positive");
+        }
+        Ok(())
+    }
+}
+
+/// 熔断状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// 正常放行并统计失败率
+    Closed,
+    /// 冷却中，调用直接失败
+    Open,
+    /// 放行有限次数的探测调用
+    HalfOpen,
+}
+
+/// 一次状态转换
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CircuitTransition {
+    pub from: CircuitState,
+    pub to: CircuitState,
+    /// 转换原因（失败率、冷却结束、探测结果或手动重置）
+    pub reason: String,
+    pub at: DateTime<Utc>,
+}
+
+/// 函数的熔断状态（`GET /functions/:name/circuit`）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CircuitStatus {
+    pub function: String,
+    /// 全局配置和函数配置是否都启用了熔断
+    pub enabled: bool,
+    pub state: CircuitState,
+    /// 滑动窗口内的调用数和失败数
+    pub requests: usize,
+    pub failures: usize,
+    pub failure_rate: f64,
+    /// 打开状态下距离进入半开的剩余时间（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+    /// 半开状态下进行中的探测调用数和已成功的探测数
+    pub probes_in_flight: u32,
+    pub probe_successes: u32,
+    /// 熔断打开以来被直接拒绝的调用数
+    pub rejected: u64,
+    /// 最近的状态转换（最近的在前）
+    pub transitions: Vec<CircuitTransition>,
+    pub config: CircuitBreakerConfig,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: CircuitState,
+    /// 窗口内每次调用的完成时间和是否成功
+    outcomes: VecDeque<(Instant, bool)>,
+    opened_at: Option<Instant>,
+    probes_in_flight: u32,
+    probe_successes: u32,
+    rejected: u64,
+    transitions: VecDeque<CircuitTransition>,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            outcomes: VecDeque::new(),
+            opened_at: None,
+            probes_in_flight: 0,
+            probe_successes: 0,
+            rejected: 0,
+            transitions: VecDeque::new(),
+        }
+    }
+}
+
+impl Breaker {
+    fn transition(&mut self, function: &str, to: CircuitState, reason: String) {
+        if self.transitions.len() >= TRANSITIONS_PER_FUNCTION {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(CircuitTransition {
+            from: self.state,
+            to,
+            reason: reason.clone(),
+            at: Utc::now(),
+        });
+        tracing::info!(
+            "Circuit breaker of {} {:?} -> {:?}: {}",
+            function,
+            self.state,
+            to,
+            reason
+        );
+        self.state = to;
+        self.probes_in_flight = 0;
+        self.probe_successes = 0;
+        match to {
+            CircuitState::Open => self.opened_at = Some(Instant::now()),
+            CircuitState::Closed => {
+                self.opened_at = None;
+                self.outcomes.clear();
+                self.rejected = 0;
+            }
+            CircuitState::HalfOpen => {}
+        }
+    }
+
+    fn trim(&mut self, window: Duration) {
+        let now = Instant::now();
+        while self
+            .outcomes
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > window)
+        {
+            self.outcomes.pop_front();
+        }
+    }
+
+    fn failures(&self) -> usize {
+        self.outcomes.iter().filter(|(_, success)| !success).count()
+    }
+
+    /// 打开状态下的剩余冷却时间
+    fn remaining(&self, config: &CircuitBreakerConfig) -> Duration {
+        self.opened_at.map_or(Duration::ZERO, |opened_at| {
+            Duration::from_millis(config.open_ms).saturating_sub(opened_at.elapsed())
+        })
+    }
+}
+
+/// 调度器的全部熔断器
+#[derive(Debug, Default)]
+pub struct CircuitBreakers {
+    config: StdMutex<CircuitBreakerConfig>,
+    breakers: StdMutex<HashMap<String, Breaker>>,
+}
+
+/// 通过熔断检查的一次调用，执行结束后用 [`CircuitPermit::record`] 记录结果
+///
+/// 未记录结果就被丢弃（调用在执行前失败或被取消）时释放占用的探测名额，不计入失败率。
+#[derive(Debug)]
+pub struct CircuitPermit {
+    breakers: Option<Arc<CircuitBreakers>>,
+    function: String,
+    probe: bool,
+}
+
+impl CircuitPermit {
+    /// 不参与熔断的调用（熔断被关闭、影子重放或镜像调用）
+    pub fn untracked() -> Self {
+        Self {
+            breakers: None,
+            function: String::new(),
+            probe: false,
+        }
+    }
+
+    pub fn record(mut self, success: bool) {
+        if let Some(breakers) = self.breakers.take() {
+            breakers.record(&self.function, self.probe, success);
+        }
+    }
+}
+
+impl Drop for CircuitPermit {
+    fn drop(&mut self) {
+        if let Some(breakers) = self.breakers.take()
+            && self.probe
+        {
+            let mut all = breakers.breakers.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(breaker) = all.get_mut(&self.function)
+                && breaker.state == CircuitState::HalfOpen
+            {
+                breaker.probes_in_flight = breaker.probes_in_flight.saturating_sub(1);
+            }
+        }
+    }
+}
+
+impl CircuitBreakers {
+    pub fn configure(&self, config: &CircuitBreakerConfig) -> Result<()> {
+        config.validate()?;
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        Ok(())
+    }
+
+    pub fn config(&self) -> CircuitBreakerConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 调用前检查熔断状态：打开时返回 `CircuitOpen`，半开时占用一个探测名额
+    pub fn admit(self: &Arc<Self>, function: &FunctionMetadata) -> Result<CircuitPermit> {
+        let config = self.config();
+        if !config.enabled || !function.circuit_breaker {
+            return Ok(CircuitPermit::untracked());
+        }
+        let mut breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        let breaker = breakers.entry(function.name.clone()).or_default();
+        if breaker.state == CircuitState::Open {
+            let remaining = breaker.remaining(&config);
+            if !remaining.is_zero() {
+                breaker.rejected += 1;
+                return Err(FluxError::CircuitOpen {
+                    name: function.name.clone(),
+                    retry_after: remaining,
+                });
+            }
+            breaker.transition(
+                &function.name,
+                CircuitState::HalfOpen,
+                "cool-down elapsed".to_string(),
+            );
+        }
+        let probe = breaker.state == CircuitState::HalfOpen;
+        if probe {
+            if breaker.probes_in_flight + breaker.probe_successes >= config.half_open_probes {
+                breaker.rejected += 1;
+                return Err(FluxError::CircuitOpen {
+                    name: function.name.clone(),
+                    retry_after: Duration::from_secs(1),
+                });
+            }
+            breaker.probes_in_flight += 1;
+        }
+        Ok(CircuitPermit {
+            breakers: Some(self.clone()),
+            function: function.name.clone(),
+            probe,
+        })
+    }
+
+    fn record(&self, function: &str, probe: bool, success: bool) {
+        let config = self.config();
+        let mut breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        let breaker = breakers.entry(function.to_string()).or_default();
+        match breaker.state {
+            CircuitState::HalfOpen if probe => {
+                breaker.probes_in_flight = breaker.probes_in_flight.saturating_sub(1);
+                if !success {
+                    breaker.transition(
+                        function,
+                        CircuitState::Open,
+                        "half-open probe failed".to_string(),
+                    );
+                } else {
+                    breaker.probe_successes += 1;
+                    if breaker.probe_successes >= config.half_open_probes {
+                        breaker.transition(
+                            function,
+                            CircuitState::Closed,
+                            format!("{} half-open probes succeeded", config.half_open_probes),
+                        );
+                    }
+                }
+            }
+            CircuitState::Closed if !probe => {
+                breaker.outcomes.push_back((Instant::now(), success));
+                breaker.trim(Duration::from_secs(config.window_secs));
+                let requests = breaker.outcomes.len();
+                let failures = breaker.failures();
+                let failure_rate = failures as f64 / requests as f64;
+                if requests >= config.min_requests && failure_rate >= config.failure_threshold {
+                    breaker.transition(
+                        function,
+                        CircuitState::Open,
+                        format!(
+                            "failure rate {:.0}% ({failures}/{requests}) in the last {}s",
+                            failure_rate * 100.0,
+                            config.window_secs
+                        ),
+                    );
+                }
+            }
+            // 状态在调用期间已被其他调用或手动重置改变，结果不再计入
+            _ => {}
+        }
+    }
+
+    /// 函数的熔断状态
+    pub fn status(&self, function: &FunctionMetadata) -> CircuitStatus {
+        let config = self.config();
+        let mut breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        let default = Breaker::default();
+        let breaker = match breakers.get_mut(&function.name) {
+            Some(breaker) => {
+                breaker.trim(Duration::from_secs(config.window_secs));
+                &*breaker
+            }
+            None => &default,
+        };
+        let requests = breaker.outcomes.len();
+        let failures = breaker.failures();
+        CircuitStatus {
+            function: function.name.clone(),
+            enabled: config.enabled && function.circuit_breaker,
+            state: breaker.state,
+            requests,
+            failures,
+            failure_rate: if requests == 0 {
+                0.0
+            } else {
+                failures as f64 / requests as f64
+            },
+            retry_after_ms: (breaker.state == CircuitState::Open)
+                .then(|| breaker.remaining(&config).as_millis() as u64),
+            probes_in_flight: breaker.probes_in_flight,
+            probe_successes: breaker.probe_successes,
+            rejected: breaker.rejected,
+            transitions: breaker.transitions.iter().rev().cloned().collect(),
+            config,
+        }
+    }
+
+    /// 手动关闭熔断并清空统计窗口（状态转换历史保留）
+    pub fn reset(&self, function: &str) {
+        let mut breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        let breaker = breakers.entry(function.to_string()).or_default();
+        breaker.transition(function, CircuitState::Closed, "manual reset".to_string());
+    }
+
+    /// 函数被删除时丢弃其熔断器，同名函数重新注册后从关闭状态开始
+    pub fn remove(&self, function: &str) {
+        self.breakers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(function);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::InvokeRequest;
+    use crate::scheduler::{Scheduler, SimpleScheduler};
+
+    fn invoke(input: serde_json::Value) -> InvokeRequest {
+        InvokeRequest {
+            input,
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        }
+    }
+
+    fn config() -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            min_requests: 4,
+            failure_threshold: 0.5,
+            open_ms: 100,
+            half_open_probes: 2,
+            ..Default::default()
+        }
+    }
+
+    async fn scheduler_with_function(name: &str) -> SimpleScheduler {
+        let scheduler = SimpleScheduler::new();
+        scheduler.circuits().configure(&config()).unwrap();
+        scheduler
+            .register_function(FunctionMetadata::new(
+                name.to_string(),
+                "return a + b".to_string(),
+            ))
+            .await
+            .unwrap();
+        scheduler
+    }
+
+    /// 缺少参数时表达式求值失败
+    async fn fail(scheduler: &SimpleScheduler, name: &str) {
+        let response = scheduler
+            .schedule(name, invoke(serde_json::json!({})))
+            .await;
+        assert!(response.is_ok_and(|response| !response.status.is_success()));
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_fails_fast_and_recovers() {
+        let scheduler = scheduler_with_function("checkout").await;
+        let function = scheduler.registry().get("checkout").await.unwrap();
+        for _ in 0..4 {
+            fail(&scheduler, "checkout").await;
+        }
+        let status = scheduler.circuits().status(&function);
+        assert_eq!(status.state, CircuitState::Open);
+        assert_eq!(status.transitions[0].to, CircuitState::Open);
+
+        // 打开后直接拒绝，不再执行函数
+        let mut fastest = Duration::MAX;
+        for _ in 0..10 {
+            let started = Instant::now();
+            let result = scheduler
+                .schedule("checkout", invoke(serde_json::json!({"a": 1, "b": 2})))
+                .await;
+            fastest = fastest.min(started.elapsed());
+            assert!(matches!(result, Err(FluxError::CircuitOpen { .. })));
+        }
+        assert!(
+            fastest < Duration::from_millis(1),
+            "fast-fail took {fastest:?}"
+        );
+        let calls = scheduler
+            .runtime()
+            .monitor()
+            .get_function_stats("checkout")
+            .await
+            .unwrap()
+            .total_calls;
+        assert_eq!(calls, 4);
+
+        // 冷却后进入半开状态，探测调用全部成功后关闭
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        for _ in 0..2 {
+            let response = scheduler
+                .schedule("checkout", invoke(serde_json::json!({"a": 1, "b": 2})))
+                .await
+                .unwrap();
+            assert!(response.status.is_success());
+        }
+        let status = scheduler.circuits().status(&function);
+        assert_eq!(status.state, CircuitState::Closed);
+        let states: Vec<_> = status.transitions.iter().map(|t| t.to).collect();
+        assert_eq!(
+            states,
+            [
+                CircuitState::Closed,
+                CircuitState::HalfOpen,
+                CircuitState::Open
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_probe_reopens_and_reset_closes() {
+        let scheduler = scheduler_with_function("flaky").await;
+        let function = scheduler.registry().get("flaky").await.unwrap();
+        for _ in 0..4 {
+            fail(&scheduler, "flaky").await;
+        }
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        fail(&scheduler, "flaky").await;
+        assert_eq!(
+            scheduler.circuits().status(&function).state,
+            CircuitState::Open
+        );
+
+        scheduler.circuits().reset("flaky");
+        let status = scheduler.circuits().status(&function);
+        assert_eq!(status.state, CircuitState::Closed);
+        assert_eq!(status.requests, 0);
+        assert_eq!(status.transitions[0].reason, "manual reset");
+    }
+
+    #[tokio::test]
+    async fn test_breaker_can_be_disabled_per_function() {
+        let scheduler = scheduler_with_function("noisy").await;
+        let mut function = scheduler.registry().get("noisy").await.unwrap();
+        function.circuit_breaker = false;
+        scheduler.registry().upsert(function.clone()).await.unwrap();
+        for _ in 0..8 {
+            fail(&scheduler, "noisy").await;
+        }
+        let status = scheduler.circuits().status(&function);
+        assert!(!status.enabled);
+        assert_eq!(status.state, CircuitState::Closed);
+    }
+}
@@ -0,0 +1,672 @@
+//! 交互式控制台：不注册函数直接执行代码
+//!
+//! `POST /console/execute` 把一段代码当作临时函数执行：与注册时一样识别脚本类型并检查运行环境，
+//! 经过全局调用名额和准入队列（最低优先级）后由默认调度器的运行时执行（校验、包装、外部运行时或沙箱），
+//! 不经过注册表、运行时路由和转发节点。控制台代码未经审核，脚本进程总是使用最严格的沙箱配置
+//! （见 [`SandboxLimits::with_strict`](crate::runtime::sandbox::SandboxLimits::with_strict)），
+//! 调用没有函数状态和出站 HTTP，不写入函数缓存、执行历史和任何函数的统计，只计入控制台的用量计数。
+//!
+//! 执行按调用方（API 密钥 ID，其次为 IP）限速，代码和输入有大小上限。执行过的代码保留一段时间，
+//! `POST /console/promote` 按返回的控制台执行 ID 把它注册为正式函数。
+
+use super::SimpleScheduler;
+use super::namespaces::script_type;
+use super::profiles::{SchedulerProfile, SchedulerRegistry};
+use crate::functions::context::{CallerInfo, InvocationContext};
+use crate::functions::priority::Priority;
+use crate::functions::{
+    FluxError, FunctionMetadata, InvokeRequest, InvokeResponse, RegisterFunctionRequest, Result,
+};
+use crate::runtime::debug_capture::DebugTrace;
+use crate::runtime::invocation_log::{CaptureLevel, LogCaptureGuard, LogEntry};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex as StdMutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+use utoipa::ToSchema;
+
+/// 控制台可以执行的脚本类型（Rust 函数需要注册后编译）
+pub const CONSOLE_SCRIPT_TYPES: &[&str] = &["expression", "javascript", "python"];
+
+/// 请求未给出 `timeout_ms` 时的执行超时（毫秒，不超过配置的上限）
+pub const DEFAULT_CONSOLE_TIMEOUT_MS: u64 = 5000;
+
+/// 跟踪限速状态的调用方数上限，超出时丢弃令牌已补满的调用方
+const MAX_TRACKED_CALLERS: usize = 10_000;
+
+/// 控制台配置（`[console]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsoleConfig {
+    /// 为 false 时控制台接口返回 403
+    pub enabled: bool,
+    /// 代码的字节数上限
+    pub max_code_bytes: usize,
+    /// 输入（序列化为 JSON 后）的字节数上限
+    pub max_input_bytes: usize,
+    /// 执行超时的上限（毫秒）
+    pub max_timeout_ms: u64,
+    /// 每个调用方每分钟允许的执行数
+    pub executions_per_minute: u32,
+    /// 每个调用方允许的突发执行数（令牌桶容量）
+    pub burst: u32,
+    /// 每次执行的暂存目录大小上限（MB）
+    pub scratch_limit_mb: u64,
+    /// 每次执行写入磁盘的字节上限（MB）
+    pub max_disk_write_mb: u64,
+    /// 执行过的代码保留的时长（秒），过期后不能再提升为函数
+    pub retention_secs: u64,
+    /// 保留的代码数上限，超出时丢弃最早的
+    pub max_retained: usize,
+    /// 随结果返回的日志的捕获级别
+    pub log_level: CaptureLevel,
+}
+
+impl Default for ConsoleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_code_bytes: 64 * 1024,
+            max_input_bytes: 64 * 1024,
+            max_timeout_ms: 10_000,
+            executions_per_minute: 30,
+            burst: 10,
+            scratch_limit_mb: 16,
+            max_disk_write_mb: 32,
+            retention_secs: 900,
+            max_retained: 1000,
+            log_level: CaptureLevel::Debug,
+        }
+    }
+}
+
+/// 控制台执行请求
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ConsoleExecuteRequest {
+    pub code: String,
+    /// 期望的脚本类型（`javascript`、`python` 或 `expression`），与按代码识别的类型不一致时拒绝
+    #[serde(default)]
+    pub script_type: Option<String>,
+    /// 调用输入（默认 null）
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub input: Value,
+    /// 执行超时（毫秒），缺省为 5000 且不超过配置的上限
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// 一次控制台执行的结果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ConsoleExecution {
+    /// 控制台执行 ID（也是调用 ID，`GET /executions/:id/log` 可以再次读取日志）
+    pub console_id: String,
+    /// 识别的脚本类型
+    pub script_type: String,
+    /// 与正常调用相同的结果，包括各阶段耗时
+    pub response: InvokeResponse,
+    /// 脚本进程的标准输出（进程内执行时为空）
+    pub stdout: String,
+    /// 脚本进程的标准错误（进程内执行时为空）
+    pub stderr: String,
+    /// 执行期间捕获的结构化日志
+    pub logs: Vec<LogEntry>,
+    /// 日志是否因上限被截断
+    pub logs_truncated: bool,
+    /// 代码保留到该时间，之前可以通过 `POST /console/promote` 注册为函数
+    pub retained_until: DateTime<Utc>,
+}
+
+/// 把控制台执行过的代码注册为函数
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ConsolePromoteRequest {
+    /// `POST /console/execute` 返回的控制台执行 ID
+    pub console_id: String,
+    /// 注册请求（`name` 必填）；代码取自控制台执行，不能另外给出 `code` 或 `files`，
+    /// `timeout_ms` 缺省沿用控制台执行的超时
+    #[serde(flatten)]
+    pub function: RegisterFunctionRequest,
+}
+
+/// 提升的结果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ConsolePromotion {
+    pub console_id: String,
+    pub name: String,
+    /// 函数注册到的调度器配置名
+    pub profile: String,
+}
+
+/// 控制台的用量计数，出现在 `GET /console/stats` 中
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ConsoleStats {
+    /// 执行的次数（不包括被拒绝的请求）
+    pub executions: u64,
+    pub succeeded: u64,
+    /// 执行失败的次数（用户代码失败、超时、超出资源限制或执行出错）
+    pub failed: u64,
+    /// 因调用方超出速率被拒绝的请求数
+    pub rate_limited: u64,
+    /// 因代码或输入过大、超时超出上限或脚本类型不支持被拒绝的请求数
+    pub rejected: u64,
+    /// 提升为函数的次数
+    pub promoted: u64,
+    /// 当前保留的代码数
+    pub retained: usize,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// 保留的控制台代码
+#[derive(Debug)]
+struct RetainedPayload {
+    caller: String,
+    code: String,
+    timeout_ms: u64,
+    expires_at: Instant,
+}
+
+/// 按执行 ID 保留的代码，以及按执行顺序排列的 ID（用于丢弃最早的代码）
+#[derive(Debug, Default)]
+struct Retained {
+    payloads: HashMap<String, RetainedPayload>,
+    order: VecDeque<String>,
+}
+
+impl Retained {
+    /// 丢弃过期的代码和超出数量上限的最早的代码
+    fn prune(&mut self, max_retained: usize) {
+        let now = Instant::now();
+        self.payloads.retain(|_, payload| payload.expires_at > now);
+        while self.payloads.len() > max_retained {
+            let Some(id) = self.order.pop_front() else {
+                break;
+            };
+            self.payloads.remove(&id);
+        }
+        let payloads = &self.payloads;
+        self.order.retain(|id| payloads.contains_key(id));
+    }
+}
+
+/// 控制台的限速状态、保留的代码和用量计数（所有调度器共享）
+#[derive(Debug)]
+pub struct ConsoleStore {
+    config: StdMutex<ConsoleConfig>,
+    buckets: StdMutex<HashMap<String, TokenBucket>>,
+    retained: StdMutex<Retained>,
+    executions: AtomicU64,
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+    rate_limited: AtomicU64,
+    rejected: AtomicU64,
+    promoted: AtomicU64,
+}
+
+impl Default for ConsoleStore {
+    fn default() -> Self {
+        Self::new(ConsoleConfig::default())
+    }
+}
+
+/// 限速和弃用调用计数使用的调用方标识：API 密钥 ID，其次为 IP
+pub fn caller_key(caller: &CallerInfo) -> String {
+    match (&caller.api_key_id, &caller.ip) {
+        (Some(key), _) => format!("key:{key}"),
+        (None, Some(ip)) => format!("ip:{ip}"),
+        (None, None) => "anonymous".to_string(),
+    }
+}
+
+impl ConsoleStore {
+    pub fn new(config: ConsoleConfig) -> Self {
+        Self {
+            config: StdMutex::new(config),
+            buckets: StdMutex::default(),
+            retained: StdMutex::default(),
+            executions: AtomicU64::new(0),
+            succeeded: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            rate_limited: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            promoted: AtomicU64::new(0),
+        }
+    }
+
+    pub fn configure(&self, config: &ConsoleConfig) {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+    }
+
+    pub fn config(&self) -> ConsoleConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 检查控制台已启用，代码、输入和超时不超出上限，返回本次执行的超时（毫秒）
+    pub fn check(&self, request: &ConsoleExecuteRequest) -> Result<u64> {
+        let config = self.config();
+        if !config.enabled {
+            return Err(FluxError::ConsoleDisabled);
+        }
+        let result = Self::check_limits(&config, request);
+        if result.is_err() {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn check_limits(config: &ConsoleConfig, request: &ConsoleExecuteRequest) -> Result<u64> {
+        if request.code.trim().is_empty() {
+            return Err(FluxError::ValidationError {
+                reason: "Console code must not be empty".to_string(),
+            });
+        }
+        if request.code.len() > config.max_code_bytes {
+            return Err(FluxError::CodeTooLarge {
+                size: request.code.len(),
+                limit: config.max_code_bytes,
+            });
+        }
+        let input_size = serde_json::to_vec(&request.input)?.len();
+        if input_size > config.max_input_bytes {
+            return Err(FluxError::InputTooLarge {
+                size: input_size,
+                limit: config.max_input_bytes,
+            });
+        }
+        match request.timeout_ms {
+            Some(timeout_ms) if timeout_ms == 0 || timeout_ms > config.max_timeout_ms => {
+                Err(FluxError::ValidationError {
+                    reason: format!(
+                        "Console timeout_ms must be between 1 and {}",
+                        config.max_timeout_ms
+                    ),
+                })
+            }
+            Some(timeout_ms) => Ok(timeout_ms),
+            None => Ok(DEFAULT_CONSOLE_TIMEOUT_MS.min(config.max_timeout_ms)),
+        }
+    }
+
+    /// 为调用方的一次执行消耗一个令牌，令牌不足时返回 `ConsoleRateLimited`
+    pub fn acquire(&self, caller: &str) -> Result<()> {
+        let config = self.config();
+        let rate = f64::from(config.executions_per_minute.max(1));
+        let capacity = f64::from(config.burst.max(1));
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_TRACKED_CALLERS && !buckets.contains_key(caller) {
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.refilled_at).as_secs_f64() * rate / 60.0
+                    < capacity
+            });
+        }
+        let bucket = buckets
+            .entry(caller.to_string())
+            .or_insert_with(|| TokenBucket {
+                tokens: capacity,
+                refilled_at: now,
+            });
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate / 60.0).min(capacity);
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+        Err(FluxError::ConsoleRateLimited {
+            per_minute: config.executions_per_minute,
+            retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) * 60.0 / rate),
+        })
+    }
+
+    /// 记录一次执行的结果
+    pub fn record(&self, success: bool) {
+        self.executions.fetch_add(1, Ordering::Relaxed);
+        if success {
+            self.succeeded.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 保留执行过的代码，返回保留到的时间
+    pub fn retain(&self, id: &str, caller: &str, code: &str, timeout_ms: u64) -> DateTime<Utc> {
+        let config = self.config();
+        let retention = Duration::from_secs(config.retention_secs);
+        let mut retained = self.retained.lock().unwrap_or_else(|e| e.into_inner());
+        retained.payloads.insert(
+            id.to_string(),
+            RetainedPayload {
+                caller: caller.to_string(),
+                code: code.to_string(),
+                timeout_ms,
+                expires_at: Instant::now() + retention,
+            },
+        );
+        retained.order.push_back(id.to_string());
+        retained.prune(config.max_retained);
+        Utc::now() + retention
+    }
+
+    /// 调用方保留的代码及其超时；不存在、已过期或属于其他调用方时返回 `ConsoleExecutionNotFound`
+    pub fn payload(&self, id: &str, caller: &str) -> Result<(String, u64)> {
+        let mut retained = self.retained.lock().unwrap_or_else(|e| e.into_inner());
+        retained.prune(self.config().max_retained);
+        retained
+            .payloads
+            .get(id)
+            .filter(|payload| payload.caller == caller)
+            .map(|payload| (payload.code.clone(), payload.timeout_ms))
+            .ok_or_else(|| FluxError::ConsoleExecutionNotFound { id: id.to_string() })
+    }
+
+    pub fn record_promoted(&self) {
+        self.promoted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> ConsoleStats {
+        let retained = {
+            let mut retained = self.retained.lock().unwrap_or_else(|e| e.into_inner());
+            retained.prune(self.config().max_retained);
+            retained.payloads.len()
+        };
+        ConsoleStats {
+            executions: self.executions.load(Ordering::Relaxed),
+            succeeded: self.succeeded.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            promoted: self.promoted.load(Ordering::Relaxed),
+            retained,
+        }
+    }
+}
+
+impl SchedulerRegistry {
+    /// 在控制台中执行一段代码：检查上限和调用方的速率，识别脚本类型后由默认调度器执行，
+    /// 返回结果、进程输出和捕获的日志，并保留代码以便提升为函数
+    pub async fn execute_console(
+        &self,
+        request: ConsoleExecuteRequest,
+        caller: CallerInfo,
+    ) -> Result<ConsoleExecution> {
+        let console = self.console();
+        let timeout_ms = console.check(&request)?;
+        let key = caller_key(&caller);
+        console.acquire(&key)?;
+
+        let config = console.config();
+        let console_id = scru128::new_string();
+        let mut function = FunctionMetadata::new(format!("console-{console_id}"), request.code);
+        function.timeout_ms = timeout_ms;
+        function.idempotent = false;
+        function.scratch_limit_mb = Some(config.scratch_limit_mb);
+        function.max_disk_write_mb = Some(config.max_disk_write_mb).filter(|mb| *mb > 0);
+        let detected = script_type(&function);
+        let rejected = |reason: String| {
+            console.rejected.fetch_add(1, Ordering::Relaxed);
+            Err(FluxError::ValidationError { reason })
+        };
+        if !CONSOLE_SCRIPT_TYPES.contains(&detected) {
+            return rejected(format!(
+                "Console code must be a JavaScript or Python handler or an expression, but it was detected as {detected}"
+            ));
+        }
+        if let Some(requested) = request.script_type.as_deref()
+            && !requested.eq_ignore_ascii_case(detected)
+        {
+            return rejected(format!(
+                "Console code was detected as {detected}, not {requested}"
+            ));
+        }
+
+        // 捕获在 span 创建前开始，执行结束后读取
+        let logs = self.invocation_logs();
+        let log_capture = logs.begin(&console_id, &function.name, config.log_level)?;
+        let span = tracing::info_span!(
+            "invoke",
+            request_id = %console_id,
+            function = %function.name,
+            log_capture = log_capture.as_ref().map(LogCaptureGuard::id)
+        );
+        let trace = DebugTrace::default();
+        let result = self
+            .default_profile()
+            .scheduler
+            .execute_console(&function, request.input, &console_id, caller, &trace)
+            .instrument(span)
+            .await;
+        drop(log_capture);
+        console.record(result.as_ref().is_ok_and(|r| r.status.is_success()));
+        let mut response = result?;
+        response.request_id = Some(console_id.clone());
+
+        let (logs, logs_truncated) = match logs.get(&console_id) {
+            Ok(log) => (log.entries, log.truncated),
+            Err(_) => (Vec::new(), false),
+        };
+        let (stdout, stderr) = trace.output();
+        let retained_until = console.retain(&console_id, &key, &function.code, timeout_ms);
+        Ok(ConsoleExecution {
+            console_id,
+            script_type: detected.to_string(),
+            response,
+            stdout,
+            stderr,
+            logs,
+            logs_truncated,
+            retained_until,
+        })
+    }
+
+    /// 把调用方在控制台执行过的代码注册为函数
+    pub async fn promote_console(
+        &self,
+        request: ConsolePromoteRequest,
+        caller: &CallerInfo,
+    ) -> Result<&SchedulerProfile> {
+        let ConsolePromoteRequest {
+            console_id,
+            mut function,
+        } = request;
+        if !function.code.is_empty() || function.files.is_some() {
+            return Err(FluxError::ValidationError {
+                reason: "Promoted functions take their code from the console execution; omit code and files".to_string(),
+            });
+        }
+        let (code, timeout_ms) = self.console().payload(&console_id, &caller_key(caller))?;
+        function.code = code;
+        function.timeout_ms = function.timeout_ms.or(Some(timeout_ms));
+        let profile = self.register(function).await?;
+        self.console().record_promoted();
+        Ok(profile)
+    }
+}
+
+impl SimpleScheduler {
+    /// 以控制台模式执行临时函数：经过全局调用名额和准入队列（最低优先级），只执行一次，
+    /// 在本调度器的运行时上执行（不经过运行时路由），进程输出记录到 `trace`
+    async fn execute_console(
+        &self,
+        function: &FunctionMetadata,
+        input: Value,
+        console_id: &str,
+        caller: CallerInfo,
+        trace: &DebugTrace,
+    ) -> Result<InvokeResponse> {
+        self.check_capability(function)?;
+        let deadline = Instant::now() + Duration::from_millis(function.timeout_ms);
+        let _global_permit = self.invocations.acquire(deadline).await?;
+        let _permit = self.admission.acquire(Priority::Low).await?;
+        let context = InvocationContext::new(function, console_id)
+            .with_caller(caller)
+            .with_test(true)
+            .with_console(true)
+            .with_debug_trace(Some(trace.clone()))
+            .with_deadline(deadline);
+        let request = InvokeRequest {
+            input,
+            retry_policy: None,
+            priority: Some(Priority::Low),
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+        self.runtime
+            .execute_attempt(function, &request, &context)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(code: &str) -> ConsoleExecuteRequest {
+        ConsoleExecuteRequest {
+            code: code.to_string(),
+            script_type: None,
+            input: json!({"a": 1}),
+            timeout_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_limits_and_rate_are_enforced_per_caller() {
+        let console = ConsoleStore::new(ConsoleConfig {
+            max_code_bytes: 16,
+            max_input_bytes: 8,
+            executions_per_minute: 1,
+            burst: 2,
+            ..Default::default()
+        });
+        assert_eq!(
+            console.check(&request("return a")).unwrap(),
+            DEFAULT_CONSOLE_TIMEOUT_MS
+        );
+        assert!(matches!(
+            console.check(&request("return a + a + a + a")),
+            Err(FluxError::CodeTooLarge { limit: 16, .. })
+        ));
+        let large_input = ConsoleExecuteRequest {
+            input: json!({"a": "0123456789"}),
+            ..request("return a")
+        };
+        assert!(matches!(
+            console.check(&large_input),
+            Err(FluxError::InputTooLarge { limit: 8, .. })
+        ));
+        let long = ConsoleExecuteRequest {
+            timeout_ms: Some(60_000),
+            ..request("return a")
+        };
+        assert!(matches!(
+            console.check(&long),
+            Err(FluxError::ValidationError { .. })
+        ));
+
+        // 突发两次后被限速，其他调用方不受影响
+        console.acquire("ip:10.0.0.1").unwrap();
+        console.acquire("ip:10.0.0.1").unwrap();
+        assert!(matches!(
+            console.acquire("ip:10.0.0.1"),
+            Err(FluxError::ConsoleRateLimited { per_minute: 1, .. })
+        ));
+        console.acquire("ip:10.0.0.2").unwrap();
+
+        let stats = console.stats();
+        assert_eq!((stats.rejected, stats.rate_limited), (3, 1));
+
+        console.configure(&ConsoleConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        assert!(matches!(
+            console.check(&request("return a")),
+            Err(FluxError::ConsoleDisabled)
+        ));
+    }
+
+    #[test]
+    fn test_retained_payloads_belong_to_the_caller_and_expire() {
+        let console = ConsoleStore::new(ConsoleConfig {
+            max_retained: 2,
+            ..Default::default()
+        });
+        for id in ["one", "two", "three"] {
+            console.retain(id, "ip:10.0.0.1", id, 1000);
+        }
+        // 超出数量上限时丢弃最早的代码
+        assert!(matches!(
+            console.payload("one", "ip:10.0.0.1"),
+            Err(FluxError::ConsoleExecutionNotFound { .. })
+        ));
+        assert_eq!(
+            console.payload("three", "ip:10.0.0.1").unwrap(),
+            ("three".to_string(), 1000)
+        );
+        assert!(console.payload("three", "ip:10.0.0.2").is_err());
+        assert_eq!(console.stats().retained, 2);
+
+        console.configure(&ConsoleConfig {
+            retention_secs: 0,
+            ..Default::default()
+        });
+        console.retain("four", "ip:10.0.0.1", "four", 1000);
+        assert!(console.payload("four", "ip:10.0.0.1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_console_runs_expressions_without_touching_the_registry() {
+        let scheduler = std::sync::Arc::new(SimpleScheduler::new());
+        let registry = SchedulerRegistry::with_default(scheduler.clone());
+        let caller = CallerInfo {
+            api_key_id: None,
+            ip: Some("10.0.0.1".to_string()),
+        };
+
+        let execution = registry
+            .execute_console(request("return a + 1"), caller.clone())
+            .await
+            .unwrap();
+        assert_eq!(execution.script_type, "expression");
+        assert!(execution.response.status.is_success());
+        assert_eq!(execution.response.output["result"], 2);
+        assert!(scheduler.registry().list().await.is_empty());
+        assert!(
+            scheduler
+                .runtime()
+                .monitor()
+                .get_function_stats(&format!("console-{}", execution.console_id))
+                .await
+                .is_none()
+        );
+
+        let mismatched = ConsoleExecuteRequest {
+            script_type: Some("python".to_string()),
+            ..request("return a + 1")
+        };
+        assert!(matches!(
+            registry.execute_console(mismatched, caller.clone()).await,
+            Err(FluxError::ValidationError { .. })
+        ));
+        let stats = registry.console().stats();
+        assert_eq!(
+            (stats.executions, stats.succeeded, stats.rejected),
+            (1, 1, 1)
+        );
+    }
+}
@@ -0,0 +1,765 @@
+use super::SimpleScheduler;
+use super::namespaces::script_type;
+use super::warmup::DEFAULT_WARMUP_CONCURRENCY;
+use crate::functions::bundle::FunctionBundle;
+use crate::functions::registry::FunctionRegistry;
+use crate::functions::testing::FunctionTestReport;
+use crate::functions::{FluxError, FunctionMetadata, Result};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Mutex as StdMutex;
+use utoipa::ToSchema;
+
+/// 保留的已结束（失败、已激活、已回滚）部署数，超出时丢弃最早创建的
+pub const MAX_FINISHED_DEPLOYMENTS: usize = 100;
+
+/// 部署状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentState {
+    /// 正在校验和预编译，线上函数不受影响
+    Preparing,
+    /// 全部条目准备就绪，可以激活
+    Ready,
+    /// 准备或激活失败，线上函数未被修改
+    Failed,
+    /// 已激活
+    Active,
+    /// 已恢复为激活前的版本
+    RolledBack,
+}
+
+impl DeploymentState {
+    /// 尚未结束的部署独占其函数名，与之重叠的新部署被拒绝
+    fn claims_names(self) -> bool {
+        matches!(self, Self::Preparing | Self::Ready)
+    }
+}
+
+impl fmt::Display for DeploymentState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Preparing => "preparing",
+            Self::Ready => "ready",
+            Self::Failed => "failed",
+            Self::Active => "active",
+            Self::RolledBack => "rolled_back",
+        })
+    }
+}
+
+/// 部署中单个函数的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentItemStatus {
+    Pending,
+    Ready,
+    Failed,
+}
+
+/// 部署中的单个函数
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeploymentItem {
+    pub name: String,
+    /// 部署的版本（与线上版本相同但定义不同时，激活时递增补丁号）
+    pub version: String,
+    pub status: DeploymentItemStatus,
+    /// 准备阶段是否预编译了该 Rust 函数
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub precompiled: bool,
+    /// 准备或激活失败的原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 激活前线上的版本（新增函数或尚未激活时缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_version: Option<String>,
+    /// 准备阶段执行的测试用例报告（部署要求测试通过时）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tests: Option<FunctionTestReport>,
+}
+
+/// 一次多函数部署
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Deployment {
+    pub id: String,
+    pub state: DeploymentState,
+    pub items: Vec<DeploymentItem>,
+    /// 准备时执行各函数的测试用例，全部通过后自动激活
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub activate_if_tests_pass: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activated_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rolled_back_at: Option<DateTime<Utc>>,
+}
+
+/// 单个条目的准备结果：函数定义以及是否预编译，和执行过的测试用例报告
+type PreparedItem = (Result<(FunctionMetadata, bool)>, Option<FunctionTestReport>);
+
+#[derive(Debug)]
+struct DeploymentRecord {
+    deployment: Deployment,
+    /// 提交的函数包（开始准备时取出）
+    bundles: Vec<FunctionBundle>,
+    /// 准备就绪的函数定义，与条目一一对应
+    functions: Vec<FunctionMetadata>,
+    /// 激活时写入的函数及其激活前的版本（新增函数为 `None`），用于回滚
+    activated: Vec<(FunctionMetadata, Option<FunctionMetadata>)>,
+}
+
+/// 调度器的部署记录
+#[derive(Debug, Default)]
+pub struct DeploymentStore {
+    records: StdMutex<HashMap<String, DeploymentRecord>>,
+}
+
+impl DeploymentStore {
+    /// 创建处于 `Preparing` 状态的部署
+    ///
+    /// 同一部署中的函数名（忽略大小写）不能重复；与尚未结束的其他部署有重叠函数名时返回
+    /// `DeploymentConflict`。
+    pub fn create(
+        &self,
+        bundles: Vec<FunctionBundle>,
+        activate_if_tests_pass: bool,
+    ) -> Result<Deployment> {
+        if bundles.is_empty() {
+            return Err(FluxError::ValidationError {
+                reason: "A deployment must contain at least one function".to_string(),
+            });
+        }
+        let mut names = HashSet::new();
+        for bundle in &bundles {
+            if !names.insert(bundle.name.to_ascii_lowercase()) {
+                return Err(FluxError::ValidationError {
+                    reason: format!("Function '{}' appears more than once", bundle.name),
+                });
+            }
+        }
+
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        for record in records.values() {
+            let deployment = &record.deployment;
+            if !deployment.state.claims_names() {
+                continue;
+            }
+            let mut overlapping: Vec<&str> = deployment
+                .items
+                .iter()
+                .filter(|item| names.contains(&item.name.to_ascii_lowercase()))
+                .map(|item| item.name.as_str())
+                .collect();
+            if !overlapping.is_empty() {
+                overlapping.sort_unstable();
+                return Err(FluxError::DeploymentConflict {
+                    reason: format!(
+                        "Functions {} are part of deployment {} which is still {}",
+                        overlapping.join(", "),
+                        deployment.id,
+                        deployment.state
+                    ),
+                });
+            }
+        }
+
+        let now = Utc::now();
+        let deployment = Deployment {
+            id: scru128::new_string(),
+            state: DeploymentState::Preparing,
+            items: bundles
+                .iter()
+                .map(|bundle| DeploymentItem {
+                    name: bundle.name.clone(),
+                    version: bundle.version.clone(),
+                    status: DeploymentItemStatus::Pending,
+                    precompiled: false,
+                    error: None,
+                    previous_version: None,
+                    tests: None,
+                })
+                .collect(),
+            activate_if_tests_pass,
+            created_at: now,
+            updated_at: now,
+            activated_at: None,
+            rolled_back_at: None,
+        };
+        Self::prune(&mut records);
+        records.insert(
+            deployment.id.clone(),
+            DeploymentRecord {
+                deployment: deployment.clone(),
+                bundles,
+                functions: Vec::new(),
+                activated: Vec::new(),
+            },
+        );
+        Ok(deployment)
+    }
+
+    /// 已结束的部署超出保留数时丢弃最早创建的
+    fn prune(records: &mut HashMap<String, DeploymentRecord>) {
+        let mut finished: Vec<(DateTime<Utc>, String)> = records
+            .values()
+            .filter(|record| !record.deployment.state.claims_names())
+            .map(|record| (record.deployment.created_at, record.deployment.id.clone()))
+            .collect();
+        let excess = finished.len().saturating_sub(MAX_FINISHED_DEPLOYMENTS);
+        finished.sort();
+        for (_, id) in finished.into_iter().take(excess) {
+            records.remove(&id);
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Result<Deployment> {
+        self.with_record(id, |record| Ok(record.deployment.clone()))
+    }
+
+    /// 部署涉及的函数名
+    fn names(&self, id: &str) -> Result<Vec<String>> {
+        self.with_record(id, |record| {
+            Ok(record
+                .deployment
+                .items
+                .iter()
+                .map(|item| item.name.clone())
+                .collect())
+        })
+    }
+
+    fn with_record<T>(
+        &self,
+        id: &str,
+        f: impl FnOnce(&mut DeploymentRecord) -> Result<T>,
+    ) -> Result<T> {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let record = records
+            .get_mut(id)
+            .ok_or_else(|| FluxError::DeploymentNotFound { id: id.to_string() })?;
+        f(record)
+    }
+
+    /// 要求部署处于 `expected` 状态
+    fn expect_state(record: &DeploymentRecord, expected: DeploymentState) -> Result<()> {
+        let deployment = &record.deployment;
+        if deployment.state == expected {
+            return Ok(());
+        }
+        Err(FluxError::DeploymentConflict {
+            reason: format!(
+                "Deployment {} is {}, expected {expected}",
+                deployment.id, deployment.state
+            ),
+        })
+    }
+
+    /// 取出待准备的函数包以及是否要求测试通过，每个部署只准备一次
+    fn take_bundles(&self, id: &str) -> Result<(Vec<FunctionBundle>, bool)> {
+        self.with_record(id, |record| {
+            Self::expect_state(record, DeploymentState::Preparing)?;
+            if record.bundles.is_empty() {
+                return Err(FluxError::DeploymentConflict {
+                    reason: format!("Deployment {id} is already being prepared"),
+                });
+            }
+            Ok((
+                std::mem::take(&mut record.bundles),
+                record.deployment.activate_if_tests_pass,
+            ))
+        })
+    }
+
+    /// 记录准备结果：全部成功时进入 `Ready`，否则进入 `Failed`
+    fn finish_preparation(&self, id: &str, results: Vec<PreparedItem>) -> Result<Deployment> {
+        self.with_record(id, |record| {
+            let mut functions = Vec::with_capacity(results.len());
+            for (item, (result, tests)) in record.deployment.items.iter_mut().zip(results) {
+                item.tests = tests;
+                match result {
+                    Ok((function, precompiled)) => {
+                        item.status = DeploymentItemStatus::Ready;
+                        item.precompiled = precompiled;
+                        functions.push(function);
+                    }
+                    Err(e) => {
+                        item.status = DeploymentItemStatus::Failed;
+                        item.error = Some(e.to_string());
+                    }
+                }
+            }
+            let deployment = &mut record.deployment;
+            deployment.state = if functions.len() == deployment.items.len() {
+                record.functions = functions;
+                DeploymentState::Ready
+            } else {
+                DeploymentState::Failed
+            };
+            deployment.updated_at = Utc::now();
+            Ok(deployment.clone())
+        })
+    }
+
+    /// 待激活的函数定义（部署必须处于 `Ready`）
+    fn ready_functions(&self, id: &str) -> Result<Vec<FunctionMetadata>> {
+        self.with_record(id, |record| {
+            Self::expect_state(record, DeploymentState::Ready)?;
+            Ok(record.functions.clone())
+        })
+    }
+
+    /// 激活失败：按条目记录原因（没有原因的条目保持就绪），部署进入 `Failed`
+    fn fail_activation(&self, id: &str, errors: Vec<Option<String>>) -> Result<Deployment> {
+        self.with_record(id, |record| {
+            for (item, error) in record.deployment.items.iter_mut().zip(errors) {
+                if let Some(error) = error {
+                    item.status = DeploymentItemStatus::Failed;
+                    item.error = Some(error);
+                }
+            }
+            let deployment = &mut record.deployment;
+            deployment.state = DeploymentState::Failed;
+            deployment.updated_at = Utc::now();
+            Ok(deployment.clone())
+        })
+    }
+
+    /// 记录激活写入的函数及其激活前的版本，部署进入 `Active`
+    fn activate(
+        &self,
+        id: &str,
+        activated: Vec<(FunctionMetadata, Option<FunctionMetadata>)>,
+    ) -> Result<Deployment> {
+        self.with_record(id, |record| {
+            let now = Utc::now();
+            let deployment = &mut record.deployment;
+            for (item, (function, previous)) in deployment.items.iter_mut().zip(&activated) {
+                item.version = function.version.clone();
+                item.previous_version = previous.as_ref().map(|p| p.version.clone());
+            }
+            deployment.state = DeploymentState::Active;
+            deployment.activated_at = Some(now);
+            deployment.updated_at = now;
+            record.activated = activated;
+            Ok(deployment.clone())
+        })
+    }
+
+    /// 激活时写入的函数及其激活前的版本（部署必须处于 `Active`）
+    fn activated(&self, id: &str) -> Result<Vec<(FunctionMetadata, Option<FunctionMetadata>)>> {
+        self.with_record(id, |record| {
+            Self::expect_state(record, DeploymentState::Active)?;
+            Ok(record.activated.clone())
+        })
+    }
+
+    fn rolled_back(&self, id: &str) -> Result<Deployment> {
+        self.with_record(id, |record| {
+            let now = Utc::now();
+            let deployment = &mut record.deployment;
+            deployment.state = DeploymentState::RolledBack;
+            deployment.rolled_back_at = Some(now);
+            deployment.updated_at = now;
+            Ok(deployment.clone())
+        })
+    }
+}
+
+/// 递增最后一段数字版本号（`1.2.3` -> `1.2.4`），最后一段不是数字时返回 `None`
+fn bump_version(version: &str) -> Option<String> {
+    let (prefix, last) = match version.rsplit_once('.') {
+        Some((prefix, last)) => (Some(prefix), last),
+        None => (None, version),
+    };
+    let next = last.parse::<u64>().ok()?.checked_add(1)?;
+    Some(match prefix {
+        Some(prefix) => format!("{prefix}.{next}"),
+        None => next.to_string(),
+    })
+}
+
+impl SimpleScheduler {
+    /// 创建部署，之后由 [`prepare_deployment`](Self::prepare_deployment) 校验和预编译
+    ///
+    /// `activate_if_tests_pass` 为真时，准备阶段还执行各函数的测试用例，全部就绪后自动激活。
+    pub fn create_deployment(
+        &self,
+        bundles: Vec<FunctionBundle>,
+        activate_if_tests_pass: bool,
+    ) -> Result<Deployment> {
+        let deployment = self.deployments.create(bundles, activate_if_tests_pass)?;
+        tracing::info!(
+            "Created deployment {} with {} functions",
+            deployment.id,
+            deployment.items.len()
+        );
+        Ok(deployment)
+    }
+
+    /// 获取部署
+    pub fn deployment(&self, id: &str) -> Result<Deployment> {
+        self.deployments.get(id)
+    }
+
+    /// 准备部署：逐个校验函数定义，启用编译时预编译 Rust 函数；不修改注册表
+    ///
+    /// 全部条目成功时部署进入 `Ready`，否则进入 `Failed` 并在条目中记录原因。要求测试通过的
+    /// 部署还执行各函数的测试用例，任一用例失败的条目失败；全部就绪后立即激活。
+    pub async fn prepare_deployment(&self, id: &str) -> Result<Deployment> {
+        let (bundles, run_tests) = self.deployments.take_bundles(id)?;
+        let results = futures_util::stream::iter(bundles)
+            .map(|bundle| self.prepare_deployment_item(bundle, run_tests))
+            .buffered(DEFAULT_WARMUP_CONCURRENCY)
+            .collect()
+            .await;
+        let deployment = self.deployments.finish_preparation(id, results)?;
+        tracing::info!("Prepared deployment {}: {}", id, deployment.state);
+        if run_tests && deployment.state == DeploymentState::Ready {
+            return self.activate_deployment(id).await;
+        }
+        Ok(deployment)
+    }
+
+    /// 校验单个函数（`run_tests` 时在最后执行其测试用例）
+    async fn prepare_deployment_item(
+        &self,
+        bundle: FunctionBundle,
+        run_tests: bool,
+    ) -> PreparedItem {
+        let prepared = match self.prepare_definition(bundle).await {
+            Ok(prepared) => prepared,
+            Err(e) => return (Err(e), None),
+        };
+        if !run_tests {
+            return (Ok(prepared), None);
+        }
+        let function = &prepared.0;
+        let report = self.run_tests(function, function.tests.clone()).await;
+        if report.all_passed() {
+            (Ok(prepared), Some(report))
+        } else {
+            let error = FluxError::TestsFailed {
+                name: function.name.clone(),
+                reason: report.summary(),
+            };
+            (Err(error), Some(report))
+        }
+    }
+
+    /// 校验单个函数定义，返回函数定义以及是否预编译
+    async fn prepare_definition(&self, bundle: FunctionBundle) -> Result<(FunctionMetadata, bool)> {
+        bundle.verify()?;
+        let name = bundle.name.clone();
+        let function = bundle.into_metadata(name);
+        FunctionRegistry::validate(&function)?;
+        self.check_namespace(&function).await?;
+        self.check_runtime(&function)?;
+        self.check_capability(&function)?;
+        match self.runtime.compiler() {
+            // 编译产物按源代码哈希保存，激活后的后台编译直接命中
+            Some(compiler) if script_type(&function) == "rust" => {
+                compiler
+                    .compile_function(&function)
+                    .await
+                    .map_err(|e| FluxError::CompilationError(format!("{e:#}")))?;
+                Ok((function, true))
+            }
+            _ => Ok((function, false)),
+        }
+    }
+
+    /// 激活部署：在一个注册表事务中写入全部函数
+    ///
+    /// 任一函数写入失败时不写入任何函数，部署进入 `Failed`（返回的部署中带有各条目的原因）。
+    /// 成功时使旧版本的缓存失效并预热新版本，激活前的版本保留用于回滚。
+    pub async fn activate_deployment(&self, id: &str) -> Result<Deployment> {
+        let names = self.deployments.names(id)?;
+        let _guards = self
+            .registry
+            .lock_names(names.iter().map(String::as_str))
+            .await;
+        // 持有函数名锁后再检查状态，同一部署的并发激活只有一个生效
+        let functions = self.deployments.ready_functions(id)?;
+
+        let mut tx = self.registry.transaction();
+        let mut previous = Vec::with_capacity(functions.len());
+        let mut errors = Vec::with_capacity(functions.len());
+        for mut function in functions {
+            // 命名空间的函数数可能在准备之后发生变化
+            errors.push(
+                self.check_namespace(&function)
+                    .await
+                    .err()
+                    .map(|e| e.to_string()),
+            );
+            match self.registry.get(&function.name).await {
+                Ok(existing) => {
+                    if existing.version == function.version
+                        && !super::same_definition(&existing, &function)
+                        && let Some(version) = bump_version(&existing.version)
+                    {
+                        function.version = version;
+                    }
+                    function.id = existing.id;
+                    function.created_at = existing.created_at;
+                    tx.upsert(function);
+                    previous.push(Some(existing));
+                }
+                Err(_) => {
+                    tx.register(function);
+                    previous.push(None);
+                }
+            }
+        }
+        if errors.iter().any(Option::is_some) {
+            tracing::warn!("Deployment {} failed namespace checks on activation", id);
+            return self.deployments.fail_activation(id, errors);
+        }
+
+        let results = tx.all_or_nothing().commit().await;
+        if results.iter().any(Result::is_err) {
+            tracing::warn!("Deployment {} failed to activate", id);
+            let errors = results
+                .iter()
+                .map(|result| result.as_ref().err().map(ToString::to_string))
+                .collect();
+            return self.deployments.fail_activation(id, errors);
+        }
+
+        let written: Vec<FunctionMetadata> = results.into_iter().flatten().collect();
+        for function in &written {
+            self.apply_registry_changes().await;
+            self.compile_in_background(function).await;
+            if let Err(e) = self.warm_function(function).await {
+                tracing::warn!("Failed to warm deployed function {}: {}", function.name, e);
+            }
+        }
+        tracing::info!("Activated deployment {} ({} functions)", id, written.len());
+        self.deployments
+            .activate(id, written.into_iter().zip(previous).collect())
+    }
+
+    /// 回滚已激活的部署：恢复激活前的版本，删除部署新增的函数
+    ///
+    /// 激活之后又被修改或删除的函数不会被覆盖，这时返回 `DeploymentConflict`，不做任何修改。
+    pub async fn rollback_deployment(&self, id: &str) -> Result<Deployment> {
+        let names = self.deployments.names(id)?;
+        let _guards = self
+            .registry
+            .lock_names(names.iter().map(String::as_str))
+            .await;
+        let activated = self.deployments.activated(id)?;
+        for (written, _) in &activated {
+            match self.registry.get(&written.name).await {
+                Ok(current) if current.revision == written.revision => {}
+                _ => {
+                    return Err(FluxError::DeploymentConflict {
+                        reason: format!(
+                            "Function {} was changed after deployment {id} was activated",
+                            written.name
+                        ),
+                    });
+                }
+            }
+        }
+
+        let mut tx = self.registry.transaction();
+        for previous in activated
+            .iter()
+            .filter_map(|(_, previous)| previous.clone())
+        {
+            tx.upsert(previous);
+        }
+        for result in tx.all_or_nothing().commit().await {
+            let function = result?;
+            self.apply_registry_changes().await;
+            self.compile_in_background(&function).await;
+        }
+        for (written, _) in activated.iter().filter(|(_, previous)| previous.is_none()) {
+            self.remove_locked(&written.name, Some(written.revision))
+                .await?;
+        }
+        tracing::info!("Rolled back deployment {}", id);
+        self.deployments.rolled_back(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(name: &str, code: &str, version: &str) -> FunctionBundle {
+        let mut function = FunctionMetadata::new(name.to_string(), code.to_string());
+        function.version = version.to_string();
+        FunctionBundle::from_metadata(&function)
+    }
+
+    #[test]
+    fn test_bump_version() {
+        assert_eq!(bump_version("1.2.3").as_deref(), Some("1.2.4"));
+        assert_eq!(bump_version("7").as_deref(), Some("8"));
+        assert_eq!(bump_version("1.0.0-beta"), None);
+    }
+
+    #[tokio::test]
+    async fn test_prepare_activate_and_rollback() {
+        let scheduler = SimpleScheduler::new();
+        let live = FunctionMetadata::new("calc".to_string(), "return 1".to_string());
+        scheduler.register_function(live).await.unwrap();
+
+        let deployment = scheduler
+            .create_deployment(
+                vec![
+                    bundle("calc", "return 2", "1.0.0"),
+                    bundle("fresh", "return 3", "1.0.0"),
+                ],
+                false,
+            )
+            .unwrap();
+        assert_eq!(deployment.state, DeploymentState::Preparing);
+        assert!(matches!(
+            scheduler.activate_deployment(&deployment.id).await,
+            Err(FluxError::DeploymentConflict { .. })
+        ));
+
+        // 准备不修改注册表
+        let prepared = scheduler.prepare_deployment(&deployment.id).await.unwrap();
+        assert_eq!(prepared.state, DeploymentState::Ready);
+        assert_eq!(
+            scheduler.registry().get("calc").await.unwrap().code,
+            "return 1"
+        );
+        assert!(!scheduler.registry().exists("fresh").await);
+
+        let active = scheduler.activate_deployment(&deployment.id).await.unwrap();
+        assert_eq!(active.state, DeploymentState::Active);
+        let calc = scheduler.registry().get("calc").await.unwrap();
+        assert_eq!(
+            (calc.code.as_str(), calc.version.as_str()),
+            ("return 2", "1.0.1")
+        );
+        assert_eq!(active.items[0].previous_version.as_deref(), Some("1.0.0"));
+        assert_eq!(active.items[1].previous_version, None);
+        assert!(scheduler.registry().exists("fresh").await);
+        assert!(matches!(
+            scheduler.activate_deployment(&deployment.id).await,
+            Err(FluxError::DeploymentConflict { .. })
+        ));
+
+        let rolled_back = scheduler.rollback_deployment(&deployment.id).await.unwrap();
+        assert_eq!(rolled_back.state, DeploymentState::RolledBack);
+        let calc = scheduler.registry().get("calc").await.unwrap();
+        assert_eq!(
+            (calc.code.as_str(), calc.version.as_str()),
+            ("return 1", "1.0.0")
+        );
+        assert!(!scheduler.registry().exists("fresh").await);
+    }
+
+    #[tokio::test]
+    async fn test_failed_items_and_overlapping_deployments() {
+        let scheduler = SimpleScheduler::new();
+        let mut invalid = FunctionMetadata::new("broken".to_string(), "return 1".to_string());
+        invalid.input_schema = Some(serde_json::json!({"type": 5}));
+
+        let first = scheduler
+            .create_deployment(
+                vec![
+                    bundle("ok", "return 1", "1.0.0"),
+                    FunctionBundle::from_metadata(&invalid),
+                ],
+                false,
+            )
+            .unwrap();
+        // 未结束的部署独占函数名（忽略大小写）
+        assert!(matches!(
+            scheduler.create_deployment(vec![bundle("OK", "return 2", "1.0.0")], false),
+            Err(FluxError::DeploymentConflict { reason }) if reason.contains(&first.id)
+        ));
+        assert!(matches!(
+            scheduler.create_deployment(
+                vec![
+                    bundle("dup", "return 1", "1.0.0"),
+                    bundle("dup", "return 2", "1.0.0"),
+                ],
+                false
+            ),
+            Err(FluxError::ValidationError { .. })
+        ));
+
+        let failed = scheduler.prepare_deployment(&first.id).await.unwrap();
+        assert_eq!(failed.state, DeploymentState::Failed);
+        assert_eq!(failed.items[0].status, DeploymentItemStatus::Ready);
+        assert_eq!(failed.items[1].status, DeploymentItemStatus::Failed);
+        assert!(failed.items[1].error.is_some());
+        assert!(scheduler.activate_deployment(&first.id).await.is_err());
+        assert_eq!(scheduler.registry().count().await, 0);
+
+        // 失败的部署不再独占函数名；激活后被修改的函数不能回滚
+        let second = scheduler
+            .create_deployment(vec![bundle("ok", "return 2", "1.0.0")], false)
+            .unwrap();
+        scheduler.prepare_deployment(&second.id).await.unwrap();
+        scheduler.activate_deployment(&second.id).await.unwrap();
+        let mut changed = scheduler.registry().get("ok").await.unwrap();
+        changed.code = "return 3".to_string();
+        scheduler.upsert_function(changed).await.unwrap();
+        assert!(matches!(
+            scheduler.rollback_deployment(&second.id).await,
+            Err(FluxError::DeploymentConflict { .. })
+        ));
+        assert!(matches!(
+            scheduler.deployment("missing"),
+            Err(FluxError::DeploymentNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_activation_gated_on_tests() {
+        use crate::functions::testing::{FunctionTestCase, OutputMatch, TestStatus};
+
+        let scheduler = SimpleScheduler::new();
+        let tested = |name: &str, expected: i64| {
+            let mut function = FunctionMetadata::new(name.to_string(), "return a + 1".to_string());
+            function.tests = vec![FunctionTestCase {
+                name: "increments".to_string(),
+                input: serde_json::json!({"a": 1}),
+                expected_output: Some(serde_json::json!({"result": expected})),
+                expected_status: TestStatus::Success,
+                output_match: OutputMatch::Subset,
+                timeout_ms: None,
+            }];
+            FunctionBundle::from_metadata(&function)
+        };
+
+        let failing = scheduler
+            .create_deployment(vec![tested("good", 2), tested("bad", 3)], true)
+            .unwrap();
+        assert!(failing.activate_if_tests_pass);
+        let failed = scheduler.prepare_deployment(&failing.id).await.unwrap();
+        assert_eq!(failed.state, DeploymentState::Failed);
+        assert!(failed.items[0].tests.as_ref().unwrap().all_passed());
+        assert_eq!(failed.items[1].status, DeploymentItemStatus::Failed);
+        assert_eq!(failed.items[1].tests.as_ref().unwrap().failed, 1);
+        assert!(
+            failed.items[1]
+                .error
+                .as_deref()
+                .unwrap()
+                .contains("increments")
+        );
+        assert_eq!(scheduler.registry().count().await, 0);
+
+        // 测试全部通过时准备完成后自动激活
+        let passing = scheduler
+            .create_deployment(vec![tested("good", 2)], true)
+            .unwrap();
+        let active = scheduler.prepare_deployment(&passing.id).await.unwrap();
+        assert_eq!(active.state, DeploymentState::Active);
+        assert!(scheduler.registry().exists("good").await);
+    }
+}
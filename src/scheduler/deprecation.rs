@@ -0,0 +1,263 @@
+//! 函数弃用
+//!
+//! 弃用的函数照常执行，每次调用按调用方（[`console::caller_key`]）计入监控，网关在响应中
+//! 附加 `Deprecation`、`Sunset` 头和 `meta.deprecated`。过了停用时间后按 `on_sunset` 处理：
+//! `reject` 拒绝调用，`redirect` 改为调用替代函数（只改调一次，替代函数同样停用时照常执行）。
+
+use super::{ScheduleOptions, SimpleScheduler, console};
+use crate::functions::deprecation::{DeprecateRequest, Deprecation, SunsetBehavior};
+use crate::functions::{FluxError, FunctionMetadata, Result};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// 函数的弃用状态和各调用方在弃用后的调用次数
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeprecationReport {
+    pub function: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<Deprecation>,
+    /// 是否已过停用时间
+    pub sunset: bool,
+    /// 各调用方的调用次数（`key:<id>`、`ip:<addr>` 或 `anonymous`）
+    pub callers: BTreeMap<String, u64>,
+}
+
+impl SimpleScheduler {
+    /// 弃用函数；`on_sunset` 为 `redirect` 时替代函数必须在同一调度器中注册
+    pub async fn deprecate(
+        &self,
+        name: &str,
+        request: DeprecateRequest,
+    ) -> Result<FunctionMetadata> {
+        let deprecation = request.into_deprecation(name, Utc::now())?;
+        if deprecation.on_sunset == SunsetBehavior::Redirect
+            && let Some(replacement) = &deprecation.replacement
+            && self.registry.get(replacement).await.is_err()
+        {
+            return Err(FluxError::ValidationError {
+                reason: format!("deprecation: replacement function '{replacement}' not found"),
+            });
+        }
+        let _guard = self.registry.lock_name(name).await;
+        self.registry.set_deprecation(name, Some(deprecation)).await
+    }
+
+    /// 取消弃用
+    pub async fn undeprecate(&self, name: &str) -> Result<FunctionMetadata> {
+        let _guard = self.registry.lock_name(name).await;
+        self.registry.set_deprecation(name, None).await
+    }
+
+    pub async fn deprecation_report(&self, name: &str) -> Result<DeprecationReport> {
+        let function = self.registry.get(name).await?;
+        let callers = self.runtime.monitor().deprecated_calls(name).await;
+        Ok(DeprecationReport {
+            function: function.name,
+            sunset: function
+                .deprecation
+                .as_ref()
+                .is_some_and(|deprecation| deprecation.is_sunset(Utc::now())),
+            deprecation: function.deprecation,
+            callers,
+        })
+    }
+
+    /// 调度前检查弃用状态：记录调用方，停用后按 `on_sunset` 拒绝调用或返回需要改调的替代函数
+    ///
+    /// 影子重放、镜像和金丝雀探测不计数也不受停用影响。
+    pub(crate) async fn check_deprecation(
+        &self,
+        function: &FunctionMetadata,
+        options: &ScheduleOptions,
+    ) -> Result<Option<String>> {
+        let Some(deprecation) = function
+            .deprecation
+            .as_ref()
+            .filter(|_| !options.is_shadow())
+        else {
+            return Ok(None);
+        };
+        self.runtime
+            .monitor()
+            .record_deprecated_call(&function.name, &console::caller_key(&options.caller))
+            .await;
+        let now = Utc::now();
+        if deprecation.rejects(now) {
+            return Err(FluxError::FunctionSunset {
+                name: function.name.clone(),
+                sunset_at: deprecation.sunset_at,
+                replacement: deprecation.replacement.clone(),
+            });
+        }
+        Ok(deprecation
+            .redirect_target(now)
+            .filter(|_| !options.redirected)
+            .map(str::to_string))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::InvokeRequest;
+    use crate::functions::context::CallerInfo;
+    use crate::scheduler::Scheduler;
+    use chrono::Duration;
+    use serde_json::json;
+
+    fn request() -> InvokeRequest {
+        InvokeRequest {
+            input: json!({"a": 2}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        }
+    }
+
+    fn deprecate_request(on_sunset: SunsetBehavior, days: i64) -> DeprecateRequest {
+        DeprecateRequest {
+            reason: "moved to new".to_string(),
+            sunset_at: Utc::now() + Duration::days(days),
+            replacement: Some("new".to_string()),
+            on_sunset,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sunset_behaviors_and_caller_counts() {
+        let scheduler = SimpleScheduler::new();
+        for (name, code) in [
+            ("old", "return input.a + 1"),
+            ("new", "return input.a * 10"),
+        ] {
+            let function = FunctionMetadata::new(name.to_string(), code.to_string());
+            scheduler.register_function(function).await.unwrap();
+        }
+        let as_caller = |key: &str| ScheduleOptions {
+            caller: CallerInfo {
+                api_key_id: Some(key.to_string()),
+                ip: None,
+            },
+            ..Default::default()
+        };
+
+        // 停用前照常执行并按调用方计数
+        scheduler
+            .deprecate("old", deprecate_request(SunsetBehavior::Reject, 1))
+            .await
+            .unwrap();
+        for key in ["alice", "alice", "bob"] {
+            let response = scheduler
+                .schedule_with("old", request(), as_caller(key))
+                .await
+                .unwrap();
+            assert_eq!(response.output["result"], json!(3));
+            assert_eq!(response.redirected_from, None);
+        }
+
+        // 停用后拒绝，被拒绝的调用同样计数
+        scheduler
+            .deprecate("old", deprecate_request(SunsetBehavior::Reject, -1))
+            .await
+            .unwrap();
+        let err = scheduler
+            .schedule_with("old", request(), as_caller("bob"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FluxError::FunctionSunset { .. }));
+        assert!(err.to_string().contains("use new instead"), "{err}");
+
+        // 停用后改调替代函数
+        scheduler
+            .deprecate("old", deprecate_request(SunsetBehavior::Redirect, -1))
+            .await
+            .unwrap();
+        let response = scheduler
+            .schedule_with("old", request(), as_caller("alice"))
+            .await
+            .unwrap();
+        assert_eq!(response.output["result"], json!(20));
+        assert_eq!(response.redirected_from.as_deref(), Some("old"));
+
+        // 停用后只提示
+        scheduler
+            .deprecate("old", deprecate_request(SunsetBehavior::Warn, -1))
+            .await
+            .unwrap();
+        let response = scheduler
+            .schedule_with("old", request(), ScheduleOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.output["result"], json!(3));
+
+        let report = scheduler.deprecation_report("old").await.unwrap();
+        assert!(report.sunset);
+        assert_eq!(
+            report.callers,
+            BTreeMap::from([
+                ("anonymous".to_string(), 1),
+                ("key:alice".to_string(), 3),
+                ("key:bob".to_string(), 2),
+            ])
+        );
+        // 替代函数没有弃用，改调的调用不计入替代函数
+        assert!(
+            scheduler
+                .deprecation_report("new")
+                .await
+                .unwrap()
+                .callers
+                .is_empty()
+        );
+
+        // 重新注册保留弃用状态，取消弃用后不再计数
+        let function = FunctionMetadata::new("old".to_string(), "return input.a + 2".to_string());
+        scheduler.upsert_function(function).await.unwrap();
+        assert!(
+            scheduler
+                .registry()
+                .get("old")
+                .await
+                .unwrap()
+                .deprecation
+                .is_some()
+        );
+        scheduler.undeprecate("old").await.unwrap();
+        let response = scheduler.schedule("old", request()).await.unwrap();
+        assert_eq!(response.output["result"], json!(4));
+        assert_eq!(
+            scheduler.deprecation_report("old").await.unwrap().callers["anonymous"],
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirect_requires_a_registered_replacement() {
+        let scheduler = SimpleScheduler::new();
+        let function = FunctionMetadata::new("old".to_string(), "return 1".to_string());
+        scheduler.register_function(function).await.unwrap();
+        assert!(matches!(
+            scheduler
+                .deprecate("old", deprecate_request(SunsetBehavior::Redirect, 1))
+                .await,
+            Err(FluxError::ValidationError { .. })
+        ));
+        // 只提示时替代函数可以在其他调度器中
+        let deprecated = scheduler
+            .deprecate("old", deprecate_request(SunsetBehavior::Warn, 1))
+            .await
+            .unwrap();
+        assert_eq!(
+            deprecated.deprecation.unwrap().replacement.as_deref(),
+            Some("new")
+        );
+        assert!(matches!(
+            scheduler.undeprecate("missing").await,
+            Err(FluxError::FunctionNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,295 @@
+//! 删除和替换函数时的排空
+//!
+//! 调度器为每个函数按修订号统计在途调用。删除函数时先把函数标记为排空：新调用以
+//! `FunctionDraining` 拒绝，等待在途调用结束（最长为排空超时）后再清理元数据、缓存和编译产物；
+//! 强制删除不等待，直接取消在途调用。替换函数的代码使正在使用的编译产物失效时，
+//! 同样等待旧修订号的调用结束后再清理。
+
+use crate::functions::{FluxError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::sync::{Notify, watch};
+use utoipa::ToSchema;
+
+/// 默认排空超时
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 函数的生命周期状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FunctionState {
+    /// 正常接受调用
+    #[default]
+    Active,
+    /// 正在删除：拒绝新调用，等待在途调用结束
+    Draining,
+}
+
+/// 函数的生命周期状态和在途调用数
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DrainStatus {
+    pub state: FunctionState,
+    pub in_flight: usize,
+}
+
+/// 一个函数的在途调用
+#[derive(Debug)]
+struct Flights {
+    /// 区分同名函数删除前后的记录，旧调用结束时不影响新记录
+    generation: u64,
+    /// 修订号 -> 在途调用数
+    by_revision: HashMap<u64, usize>,
+    draining: bool,
+    /// 强制删除时置为 true，取消全部在途调用
+    cancel: watch::Sender<bool>,
+}
+
+impl Flights {
+    fn new(generation: u64) -> Self {
+        Self {
+            generation,
+            by_revision: HashMap::new(),
+            draining: false,
+            cancel: watch::channel(false).0,
+        }
+    }
+
+    fn count(&self, filter: impl Fn(u64) -> bool) -> usize {
+        self.by_revision
+            .iter()
+            .filter(|(revision, _)| filter(**revision))
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// 按函数统计在途调用，支持排空和取消
+#[derive(Debug, Default)]
+pub struct InFlightTracker {
+    functions: StdMutex<HashMap<String, Flights>>,
+    next_generation: AtomicU64,
+    /// 每次有调用结束时通知等待排空的一方
+    released: Notify,
+}
+
+impl InFlightTracker {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Flights>> {
+        self.functions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn flights<'a>(
+        &self,
+        functions: &'a mut HashMap<String, Flights>,
+        name: &str,
+    ) -> &'a mut Flights {
+        functions
+            .entry(name.to_string())
+            .or_insert_with(|| Flights::new(self.next_generation.fetch_add(1, Ordering::Relaxed)))
+    }
+
+    /// 登记一次调用，函数正在排空时返回 `FunctionDraining`；调用结束时丢弃返回的守卫
+    pub fn begin(self: &Arc<Self>, name: &str, revision: u64) -> Result<InFlightGuard> {
+        let mut functions = self.lock();
+        let flights = self.flights(&mut functions, name);
+        if flights.draining {
+            return Err(FluxError::FunctionDraining {
+                name: name.to_string(),
+            });
+        }
+        *flights.by_revision.entry(revision).or_default() += 1;
+        Ok(InFlightGuard {
+            tracker: self.clone(),
+            name: name.to_string(),
+            revision,
+            generation: flights.generation,
+            cancel: flights.cancel.subscribe(),
+        })
+    }
+
+    /// 函数的生命周期状态和在途调用数
+    pub fn status(&self, name: &str) -> DrainStatus {
+        match self.lock().get(name) {
+            Some(flights) => DrainStatus {
+                state: if flights.draining {
+                    FunctionState::Draining
+                } else {
+                    FunctionState::Active
+                },
+                in_flight: flights.count(|_| true),
+            },
+            None => DrainStatus::default(),
+        }
+    }
+
+    /// 开始排空：之后的新调用被拒绝
+    pub fn start_drain(&self, name: &str) {
+        let mut functions = self.lock();
+        self.flights(&mut functions, name).draining = true;
+    }
+
+    /// 取消函数的全部在途调用，返回被取消的调用数
+    pub fn cancel(&self, name: &str) -> usize {
+        match self.lock().get(name) {
+            Some(flights) => {
+                flights.cancel.send_replace(true);
+                flights.count(|_| true)
+            }
+            None => 0,
+        }
+    }
+
+    /// 结束排空并丢弃函数的记录（仍未结束的调用不再统计）
+    pub fn finish_drain(&self, name: &str) {
+        self.lock().remove(name);
+    }
+
+    /// 等待修订号满足 `filter` 的在途调用全部结束，超时返回 false
+    pub async fn wait_idle(
+        &self,
+        name: &str,
+        timeout: Duration,
+        filter: impl Fn(u64) -> bool,
+    ) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                // 先注册通知再检查，避免错过检查之后、等待之前结束的调用
+                let released = self.released.notified();
+                tokio::pin!(released);
+                released.as_mut().enable();
+                let busy = self
+                    .lock()
+                    .get(name)
+                    .map_or(0, |flights| flights.count(&filter));
+                if busy == 0 {
+                    return;
+                }
+                released.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+}
+
+/// 一次在途调用，丢弃时结束登记
+#[derive(Debug)]
+pub struct InFlightGuard {
+    tracker: Arc<InFlightTracker>,
+    name: String,
+    revision: u64,
+    generation: u64,
+    cancel: watch::Receiver<bool>,
+}
+
+impl InFlightGuard {
+    /// 调用被强制删除取消时完成，否则一直等待
+    pub async fn cancelled(&mut self) {
+        if self.cancel.wait_for(|cancelled| *cancelled).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut functions = self.tracker.lock();
+        if let Some(flights) = functions.get_mut(&self.name)
+            && flights.generation == self.generation
+        {
+            if let Some(count) = flights.by_revision.get_mut(&self.revision) {
+                *count -= 1;
+                if *count == 0 {
+                    flights.by_revision.remove(&self.revision);
+                }
+            }
+            if flights.by_revision.is_empty() && !flights.draining {
+                functions.remove(&self.name);
+            }
+        }
+        drop(functions);
+        self.tracker.released.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_draining_rejects_new_invocations_and_waits_for_running_ones() {
+        let tracker = Arc::new(InFlightTracker::default());
+        let first = tracker.begin("slow", 1).unwrap();
+        let second = tracker.begin("slow", 2).unwrap();
+        assert_eq!(tracker.status("slow").in_flight, 2);
+
+        tracker.start_drain("slow");
+        assert!(matches!(
+            tracker.begin("slow", 2),
+            Err(FluxError::FunctionDraining { .. })
+        ));
+        assert_eq!(
+            tracker.status("slow"),
+            DrainStatus {
+                state: FunctionState::Draining,
+                in_flight: 2
+            }
+        );
+
+        // 只等待旧修订号时，新修订号的调用不影响结果
+        drop(first);
+        assert!(
+            tracker
+                .wait_idle("slow", Duration::from_millis(10), |revision| revision == 1)
+                .await
+        );
+        assert!(
+            !tracker
+                .wait_idle("slow", Duration::from_millis(10), |_| true)
+                .await
+        );
+
+        let waiter = {
+            let tracker = tracker.clone();
+            tokio::spawn(async move {
+                tracker
+                    .wait_idle("slow", Duration::from_secs(5), |_| true)
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(second);
+        assert!(waiter.await.unwrap());
+
+        tracker.finish_drain("slow");
+        assert_eq!(tracker.status("slow"), DrainStatus::default());
+        assert!(tracker.begin("slow", 1).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_wakes_running_invocations() {
+        let tracker = Arc::new(InFlightTracker::default());
+        let mut guard = tracker.begin("slow", 1).unwrap();
+        let cancelled = tokio::spawn(async move {
+            guard.cancelled().await;
+        });
+        tracker.start_drain("slow");
+        assert_eq!(tracker.cancel("slow"), 1);
+        tokio::time::timeout(Duration::from_secs(5), cancelled)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // 排空结束后记录被丢弃，旧调用结束不影响同名的新函数
+        let old = tracker.begin("other", 1).unwrap();
+        tracker.start_drain("other");
+        tracker.finish_drain("other");
+        let new = tracker.begin("other", 1).unwrap();
+        drop(old);
+        assert_eq!(tracker.status("other").in_flight, 1);
+        drop(new);
+        assert_eq!(tracker.status("other").in_flight, 0);
+    }
+}
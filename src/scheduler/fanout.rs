@@ -0,0 +1,392 @@
+use super::ScheduleOptions;
+use super::profiles::SchedulerRegistry;
+use crate::functions::{FluxError, InvokeRequest, InvokeResponse, Result};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Instant;
+use utoipa::ToSchema;
+
+/// 默认同时调用的下游函数数
+pub const DEFAULT_FANOUT_CONCURRENCY: usize = 4;
+/// 同时调用的下游函数数上限
+pub const MAX_FANOUT_CONCURRENCY: usize = 16;
+/// 单次扇出的下游函数数上限
+pub const MAX_FANOUT_TARGETS: usize = 32;
+
+/// 扇出调用请求：先执行主函数，再把其输出并发传给各下游函数
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct FanoutRequest {
+    /// 主函数的输入
+    #[serde(default)]
+    pub input: serde_json::Value,
+    pub targets: Vec<FanoutTarget>,
+    /// 同时调用的下游函数数（默认 4，最多 16）
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+}
+
+/// 下游函数
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct FanoutTarget {
+    pub function: String,
+    /// 从主函数输出中选取下游输入，缺省时传入完整输出
+    #[serde(default)]
+    pub input_map: Option<InputMap>,
+    /// 为 true 时该函数失败会使整个调用失败
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// 下游输入映射（JSON Pointer，如 `/document/id`）
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum InputMap {
+    /// 输出中的一个值作为完整输入
+    Pointer(String),
+    /// 按字段分别选取：`{"<输入字段>": "<指针>"}`
+    Fields(BTreeMap<String, String>),
+}
+
+impl InputMap {
+    fn pointers(&self) -> Vec<&str> {
+        match self {
+            Self::Pointer(pointer) => vec![pointer],
+            Self::Fields(fields) => fields.values().map(String::as_str).collect(),
+        }
+    }
+
+    /// 从主函数输出构造下游输入，指针不存在时报错
+    pub fn apply(&self, output: &serde_json::Value) -> Result<serde_json::Value> {
+        let select = |pointer: &str| {
+            output
+                .pointer(pointer)
+                .cloned()
+                .ok_or_else(|| FluxError::ValidationError {
+                    reason: format!("input_map pointer '{pointer}' not found in primary output"),
+                })
+        };
+        match self {
+            Self::Pointer(pointer) => select(pointer),
+            Self::Fields(fields) => fields
+                .iter()
+                .map(|(field, pointer)| Ok((field.clone(), select(pointer)?)))
+                .collect::<Result<serde_json::Map<_, _>>>()
+                .map(serde_json::Value::Object),
+        }
+    }
+}
+
+impl FanoutRequest {
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+            .unwrap_or(DEFAULT_FANOUT_CONCURRENCY)
+            .clamp(1, MAX_FANOUT_CONCURRENCY)
+    }
+
+    /// 校验下游列表：非空、不超过上限、不直接调用主函数自身、指针格式正确
+    pub fn validate(&self, primary: &str) -> Result<()> {
+        let invalid = |reason: String| Err(FluxError::ValidationError { reason });
+        if self.targets.is_empty() {
+            return invalid("targets must not be empty".to_string());
+        }
+        if self.targets.len() > MAX_FANOUT_TARGETS {
+            return invalid(format!(
+                "at most {MAX_FANOUT_TARGETS} targets are allowed, got {}",
+                self.targets.len()
+            ));
+        }
+        for target in &self.targets {
+            if target.function == primary {
+                return invalid(format!(
+                    "target '{primary}' refers back to the primary function"
+                ));
+            }
+            for pointer in target.input_map.iter().flat_map(InputMap::pointers) {
+                if !pointer.is_empty() && !pointer.starts_with('/') {
+                    return invalid(format!(
+                        "input_map pointer '{pointer}' of target '{}' must be empty or start with '/'",
+                        target.function
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 单个下游函数的调用结果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FanoutTargetResult {
+    pub function: String,
+    pub required: bool,
+    /// 调用成功且执行状态为成功
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<InvokeResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// 扇出调用结果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FanoutResponse {
+    /// 主函数和所有 `required` 下游函数都成功
+    pub success: bool,
+    pub primary: InvokeResponse,
+    /// 各下游函数的结果（与请求中的顺序一致）
+    pub targets: Vec<FanoutTargetResult>,
+    /// 从开始执行主函数到所有下游函数完成的总耗时
+    pub total_duration_ms: u64,
+}
+
+impl SchedulerRegistry {
+    /// 执行主函数，成功后按并发上限调用各下游函数（各自在所属调度器上执行）
+    ///
+    /// 主函数调度失败时直接返回错误；主函数执行失败时不调用下游函数，下游结果标记为跳过。
+    pub async fn fanout(
+        &self,
+        function_name: &str,
+        request: FanoutRequest,
+        options: ScheduleOptions,
+    ) -> Result<FanoutResponse> {
+        request.validate(function_name)?;
+        let started = Instant::now();
+        let invocation_id = options
+            .invocation_id
+            .clone()
+            .unwrap_or_else(scru128::new_string);
+
+        let primary = self
+            .resolve(function_name)
+            .await
+            .scheduler
+            .schedule_with(
+                function_name,
+                InvokeRequest {
+                    input: request.input.clone(),
+                    retry_policy: None,
+                    priority: None,
+                    idempotency_key: None,
+                    session_id: None,
+                    affinity_key: None,
+                },
+                ScheduleOptions {
+                    invocation_id: Some(invocation_id.clone()),
+                    ..options.clone()
+                },
+            )
+            .await?;
+
+        let concurrency = request.concurrency();
+        let targets: Vec<FanoutTargetResult> = if primary.status.is_success() {
+            let mut results: Vec<(usize, FanoutTargetResult)> =
+                futures_util::stream::iter(request.targets.into_iter().enumerate())
+                    .map(|(index, target)| {
+                        let output = primary.output.clone();
+                        let options = ScheduleOptions {
+                            invocation_id: Some(format!("{invocation_id}.{index}")),
+                            on_compiling: options.on_compiling,
+                            version: None,
+                            caller: options.caller.clone(),
+                            ..Default::default()
+                        };
+                        async move { (index, self.invoke_target(&target, &output, options).await) }
+                    })
+                    .buffer_unordered(concurrency)
+                    .collect()
+                    .await;
+            results.sort_by_key(|(index, _)| *index);
+            results.into_iter().map(|(_, result)| result).collect()
+        } else {
+            request
+                .targets
+                .iter()
+                .map(|target| FanoutTargetResult {
+                    function: target.function.clone(),
+                    required: target.required,
+                    success: false,
+                    response: None,
+                    error: Some("Skipped: primary function failed".to_string()),
+                    duration_ms: 0,
+                })
+                .collect()
+        };
+
+        let success = primary.status.is_success()
+            && targets
+                .iter()
+                .all(|target| target.success || !target.required);
+        let total_duration_ms = started.elapsed().as_millis() as u64;
+        tracing::info!(
+            "Fan-out of {} to {} targets finished in {}ms (success: {})",
+            function_name,
+            targets.len(),
+            total_duration_ms,
+            success
+        );
+        Ok(FanoutResponse {
+            success,
+            primary,
+            targets,
+            total_duration_ms,
+        })
+    }
+
+    async fn invoke_target(
+        &self,
+        target: &FanoutTarget,
+        output: &serde_json::Value,
+        options: ScheduleOptions,
+    ) -> FanoutTargetResult {
+        let started = Instant::now();
+        let result = match &target.input_map {
+            Some(input_map) => input_map.apply(output),
+            None => Ok(output.clone()),
+        };
+        let result = match result {
+            Ok(input) => {
+                self.resolve(&target.function)
+                    .await
+                    .scheduler
+                    .schedule_with(
+                        &target.function,
+                        InvokeRequest {
+                            input,
+                            retry_policy: None,
+                            priority: None,
+                            idempotency_key: None,
+                            session_id: None,
+                            affinity_key: None,
+                        },
+                        options,
+                    )
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        let (success, response, error) = match result {
+            Ok(response) => (response.status.is_success(), Some(response), None),
+            Err(e) => (false, None, Some(e.to_string())),
+        };
+        FanoutTargetResult {
+            function: target.function.clone(),
+            required: target.required,
+            success,
+            response,
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::FunctionMetadata;
+    use crate::scheduler::SimpleScheduler;
+    use std::sync::Arc;
+
+    fn target(function: &str, input_map: Option<InputMap>, required: bool) -> FanoutTarget {
+        FanoutTarget {
+            function: function.to_string(),
+            input_map,
+            required,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fanout_maps_outputs_and_reports_failures() {
+        let scheduler = SimpleScheduler::new();
+        for (name, code) in [
+            ("ingest", "return a + b"),
+            ("index", "return input"),
+            ("alert", "return input"),
+        ] {
+            let function = FunctionMetadata::new(name.to_string(), code.to_string());
+            scheduler.registry().register(function).await.unwrap();
+        }
+        let schedulers = SchedulerRegistry::with_default(Arc::new(scheduler));
+
+        let request = FanoutRequest {
+            input: serde_json::json!({"a": 1, "b": 2}),
+            targets: vec![
+                target("index", None, true),
+                target(
+                    "alert",
+                    Some(InputMap::Fields(BTreeMap::from([(
+                        "value".to_string(),
+                        "/result".to_string(),
+                    )]))),
+                    true,
+                ),
+                target("archive", None, false),
+            ],
+            concurrency: Some(2),
+        };
+        let response = schedulers
+            .fanout("ingest", request.clone(), ScheduleOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.primary.output["result"], 3);
+        assert_eq!(response.targets.len(), 3);
+        assert_eq!(response.targets[0].function, "index");
+        assert!(response.targets[0].success);
+        assert!(response.targets[1].success);
+        // 不存在的非必需函数只报告错误，不影响整体结果
+        assert!(!response.targets[2].success);
+        assert!(response.targets[2].error.is_some());
+        assert!(response.success);
+
+        // 必需函数失败（指针不存在）时整体失败，其他结果照常返回
+        let mut request = request;
+        request.targets[1].input_map = Some(InputMap::Pointer("/missing".to_string()));
+        let response = schedulers
+            .fanout("ingest", request, ScheduleOptions::default())
+            .await
+            .unwrap();
+        assert!(!response.success);
+        assert!(response.targets[0].success);
+        assert!(
+            response.targets[1]
+                .error
+                .as_deref()
+                .unwrap()
+                .contains("/missing")
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_self_reference_and_bad_pointers() {
+        let request = |targets| FanoutRequest {
+            input: serde_json::Value::Null,
+            targets,
+            concurrency: None,
+        };
+        assert!(request(vec![]).validate("ingest").is_err());
+        assert!(
+            request(vec![target("ingest", None, false)])
+                .validate("ingest")
+                .is_err()
+        );
+        assert!(
+            request(vec![target(
+                "index",
+                Some(InputMap::Pointer("result".to_string())),
+                false
+            )])
+            .validate("ingest")
+            .is_err()
+        );
+        assert!(
+            request(vec![target(
+                "index",
+                Some(InputMap::Pointer(String::new())),
+                false
+            )])
+            .validate("ingest")
+            .is_ok()
+        );
+    }
+}
@@ -0,0 +1,567 @@
+use super::ScheduleOptions;
+use super::profiles::SchedulerRegistry;
+use crate::functions::redaction::Redactor;
+use crate::functions::{
+    ErrorOrigin, ExecutionStatus, FluxError, FunctionMetadata, InvokeRequest, InvokeResponse,
+    Result,
+};
+use crate::runtime::disk::DiskUsage;
+use crate::runtime::egress::EgressCall;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// 执行历史配置（`[history]`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// 保留的最近执行数，为 0 时不记录执行历史（也就无法重放）
+    pub capacity: usize,
+    /// 单次执行保留的输入和输出的字节上限，超出时不保留
+    pub max_payload_bytes: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1000,
+            max_payload_bytes: 64 * 1024,
+        }
+    }
+}
+
+/// 执行历史中输入的保留情况
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InputCapture {
+    /// 完整保留，可以重放
+    Complete,
+    /// 超出 `max_payload_bytes`，未保留
+    Truncated,
+    /// 包含函数 `log_redaction` 规则匹配的字段，未保留
+    Redacted,
+}
+
+/// 一次已完成的执行
+///
+/// 输出按函数的脱敏规则处理后保存；输入保存的是应用输入模板前的原始输入，
+/// 只有完整保留时才能重放。
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExecutionRecord {
+    pub invocation_id: String,
+    pub function: String,
+    /// 执行的函数版本
+    pub version: String,
+    /// 执行环境名（使用脚本类型的默认解释器时缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<String>,
+    pub input_capture: InputCapture,
+    /// 输入的 JSON 字节数
+    pub input_bytes: usize,
+    #[serde(skip)]
+    #[schema(ignore)]
+    input: Option<Value>,
+    /// 调用成功且执行状态为成功
+    pub success: bool,
+    /// 执行状态（调度错误时缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ExecutionStatus>,
+    /// 脱敏后的输出（调度错误或超出字节上限时缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    /// 输出是否因超出字节上限未保留
+    pub output_truncated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 失败的归属（成功时缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_origin: Option<ErrorOrigin>,
+    pub duration_ms: u64,
+    pub recorded_at: DateTime<Utc>,
+    /// 本次执行重放的原调用 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<String>,
+    /// 是否为影子重放
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shadow: bool,
+    /// 经宿主代理的出站 HTTP 请求（包括被拒绝的请求）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub egress: Vec<EgressCall>,
+    /// 沙箱进程的磁盘用量（没有启动沙箱进程的调用缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk: Option<DiskUsage>,
+}
+
+impl ExecutionRecord {
+    /// 重放使用的输入；输入未完整保留时返回 `ReplayUnavailable`
+    pub fn replay_input(&self) -> Result<Value> {
+        let reason = match (self.input_capture, &self.input) {
+            (InputCapture::Complete, Some(input)) => return Ok(input.clone()),
+            (InputCapture::Complete, None) => "input was not retained".to_string(),
+            (InputCapture::Truncated, _) => format!(
+                "input of {} bytes exceeded history.max_payload_bytes and was not retained",
+                self.input_bytes
+            ),
+            (InputCapture::Redacted, _) => {
+                "input contains fields matched by the function's log_redaction rules and was not retained"
+                    .to_string()
+            }
+        };
+        Err(FluxError::ReplayUnavailable {
+            id: self.invocation_id.clone(),
+            reason,
+        })
+    }
+}
+
+/// 最近执行的有界记录（同一进程的调度器配置共享）
+#[derive(Debug, Default)]
+pub struct ExecutionHistory {
+    config: StdMutex<HistoryConfig>,
+    records: StdMutex<VecDeque<ExecutionRecord>>,
+}
+
+impl ExecutionHistory {
+    pub fn new(config: HistoryConfig) -> Self {
+        Self {
+            config: StdMutex::new(config),
+            records: StdMutex::default(),
+        }
+    }
+
+    /// 更新配置，容量缩小时丢弃最早的记录
+    pub fn configure(&self, config: &HistoryConfig) {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        while records.len() > config.capacity {
+            records.pop_front();
+        }
+    }
+
+    pub fn config(&self) -> HistoryConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config().capacity > 0
+    }
+
+    /// 记录一次执行结果，`input` 为应用输入模板前的原始输入，`egress` 为本次调用的代理请求，
+    /// `disk` 为沙箱进程的磁盘用量
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &self,
+        function: &FunctionMetadata,
+        invocation_id: &str,
+        input: &Value,
+        result: std::result::Result<&InvokeResponse, &FluxError>,
+        duration: Duration,
+        options: &ScheduleOptions,
+        egress: Vec<EgressCall>,
+        disk: Option<DiskUsage>,
+    ) {
+        let config = self.config();
+        if config.capacity == 0 {
+            return;
+        }
+        let redactor = Redactor::for_function(function).with_secrets_from(input);
+        let input_bytes = serde_json::to_vec(input).map_or(0, |bytes| bytes.len());
+        let input_capture = if redactor.redact(input) != *input {
+            InputCapture::Redacted
+        } else if input_bytes > config.max_payload_bytes {
+            InputCapture::Truncated
+        } else {
+            InputCapture::Complete
+        };
+
+        let (status, output, error, error_origin) = match result {
+            Ok(response) => (
+                Some(response.status.clone()),
+                Some(redactor.redact(&response.output)),
+                response.status.failure_message(),
+                response.status.origin(),
+            ),
+            Err(e) => (None, None, Some(e.to_string()), Some(e.origin())),
+        };
+        let output_truncated = output.as_ref().is_some_and(|output| {
+            serde_json::to_vec(output).map_or(0, |bytes| bytes.len()) > config.max_payload_bytes
+        });
+
+        let record = ExecutionRecord {
+            invocation_id: invocation_id.to_string(),
+            function: function.name.clone(),
+            version: function.version.clone(),
+            runtime: function.runtime.clone(),
+            input_capture,
+            input_bytes,
+            input: (input_capture == InputCapture::Complete).then(|| input.clone()),
+            success: status.as_ref().is_some_and(ExecutionStatus::is_success),
+            status,
+            output: output.filter(|_| !output_truncated),
+            output_truncated,
+            error: error.map(|error| redactor.scrub(&error)),
+            error_origin,
+            duration_ms: duration.as_millis() as u64,
+            recorded_at: Utc::now(),
+            replay_of: options.replay_of.clone(),
+            shadow: options.is_shadow(),
+            egress,
+            disk,
+        };
+
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        while records.len() >= config.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// 按调用 ID 查找执行记录
+    pub fn get(&self, invocation_id: &str) -> Option<ExecutionRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records
+            .iter()
+            .rev()
+            .find(|record| record.invocation_id == invocation_id)
+            .cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 输出中一处变化的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// 输出中的一处变化，`path` 为 JSON Pointer（整个输出为空字符串）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct JsonChange {
+    pub path: String,
+    pub change: ChangeKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<Value>,
+}
+
+/// 比较两个 JSON 值：对象按字段、数组按下标逐层比较，其余值整体比较
+pub fn diff_json(before: &Value, after: &Value) -> Vec<JsonChange> {
+    let mut changes = Vec::new();
+    diff_at(String::new(), before, after, &mut changes);
+    changes
+}
+
+fn diff_at(path: String, before: &Value, after: &Value, changes: &mut Vec<JsonChange>) {
+    let child = |key: &str| format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, value) in before {
+                match after.get(key) {
+                    Some(other) => diff_at(child(key), value, other, changes),
+                    None => changes.push(JsonChange {
+                        path: child(key),
+                        change: ChangeKind::Removed,
+                        before: Some(value.clone()),
+                        after: None,
+                    }),
+                }
+            }
+            for (key, value) in after {
+                if !before.contains_key(key) {
+                    changes.push(JsonChange {
+                        path: child(key),
+                        change: ChangeKind::Added,
+                        before: None,
+                        after: Some(value.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            for index in 0..before.len().max(after.len()) {
+                let path = child(&index.to_string());
+                match (before.get(index), after.get(index)) {
+                    (Some(value), Some(other)) => diff_at(path, value, other, changes),
+                    (value, other) => changes.push(JsonChange {
+                        path,
+                        change: if value.is_some() {
+                            ChangeKind::Removed
+                        } else {
+                            ChangeKind::Added
+                        },
+                        before: value.cloned(),
+                        after: other.cloned(),
+                    }),
+                }
+            }
+        }
+        (before, after) if before != after => changes.push(JsonChange {
+            path,
+            change: ChangeKind::Changed,
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }),
+        _ => {}
+    }
+}
+
+/// 重放结果：原执行和重放执行并列，附带输出的结构化差异
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReplayResponse {
+    pub original: ExecutionRecord,
+    pub replay: ExecutionRecord,
+    /// 输出的差异（为空表示一致）；任一输出未保留时缺省
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<JsonChange>>,
+}
+
+impl SchedulerRegistry {
+    /// 在各调度器的执行历史中查找调用
+    pub fn find_execution(&self, invocation_id: &str) -> Option<ExecutionRecord> {
+        self.profiles()
+            .find_map(|profile| profile.scheduler.history().get(invocation_id))
+    }
+
+    /// 用记录的输入重新调用函数，`original_version` 为 true 时固定到原执行的版本
+    ///
+    /// 重放和普通调用一样经过命名空间限制和准入队列；`options.shadow` 为 true 时
+    /// 只在监控中计入重放次数。
+    pub async fn replay(
+        &self,
+        invocation_id: &str,
+        original_version: bool,
+        options: ScheduleOptions,
+    ) -> Result<ReplayResponse> {
+        let original =
+            self.find_execution(invocation_id)
+                .ok_or_else(|| FluxError::ExecutionNotFound {
+                    id: invocation_id.to_string(),
+                })?;
+        let input = original.replay_input()?;
+        let replay_id = options
+            .invocation_id
+            .clone()
+            .unwrap_or_else(scru128::new_string);
+
+        let scheduler = &self.resolve(&original.function).await.scheduler;
+        scheduler
+            .schedule_with(
+                &original.function,
+                InvokeRequest {
+                    input,
+                    retry_policy: None,
+                    priority: None,
+                    idempotency_key: None,
+                    session_id: None,
+                    affinity_key: None,
+                },
+                ScheduleOptions {
+                    invocation_id: Some(replay_id.clone()),
+                    version: original_version.then(|| original.version.clone()),
+                    replay_of: Some(original.invocation_id.clone()),
+                    ..options
+                },
+            )
+            .await?;
+
+        let replay =
+            scheduler
+                .history()
+                .get(&replay_id)
+                .ok_or_else(|| FluxError::ReplayUnavailable {
+                    id: invocation_id.to_string(),
+                    reason: "the replay was evicted from history before it could be compared"
+                        .to_string(),
+                })?;
+        let changes = match (&original.output, &replay.output) {
+            (Some(before), Some(after)) => Some(diff_json(before, after)),
+            _ => None,
+        };
+        tracing::info!(
+            "Replayed invocation {} of {} as {} ({} output changes)",
+            invocation_id,
+            original.function,
+            replay_id,
+            changes.as_ref().map_or(0, Vec::len)
+        );
+        Ok(ReplayResponse {
+            original,
+            replay,
+            changes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::SimpleScheduler;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_diff_json_reports_paths() {
+        let before = json!({"a": 1, "b": {"c": [1, 2]}, "gone": true, "x/y": 0});
+        let after = json!({"a": 2, "b": {"c": [1]}, "new": null, "x/y": 0});
+        let changes = diff_json(&before, &after);
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.change))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("/a", ChangeKind::Changed),
+                ("/b/c/1", ChangeKind::Removed),
+                ("/gone", ChangeKind::Removed),
+                ("/new", ChangeKind::Added),
+            ]
+        );
+        assert_eq!(changes[0].before, Some(json!(1)));
+        assert_eq!(changes[0].after, Some(json!(2)));
+        assert!(diff_json(&json!("same"), &json!("same")).is_empty());
+        assert_eq!(diff_json(&json!(1), &json!("1"))[0].path, "");
+    }
+
+    #[tokio::test]
+    async fn test_replay_against_original_and_latest_versions() {
+        let scheduler = SimpleScheduler::new();
+        let function = FunctionMetadata::new("sum".to_string(), "return a + b".to_string());
+        scheduler.register_function(function).await.unwrap();
+        let schedulers = SchedulerRegistry::with_default(Arc::new(scheduler));
+        let scheduler = &schedulers.default_profile().scheduler;
+
+        let request = InvokeRequest {
+            input: json!({"a": 1, "b": 2}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+        let options = ScheduleOptions {
+            invocation_id: Some("inv-1".to_string()),
+            ..Default::default()
+        };
+        scheduler
+            .schedule_with("sum", request, options)
+            .await
+            .unwrap();
+
+        let updated = FunctionMetadata::new_with_version(
+            "sum".to_string(),
+            "return a * b".to_string(),
+            "1.0.1".to_string(),
+        );
+        scheduler.upsert_function(updated).await.unwrap();
+
+        // 最新版本：输出发生变化
+        let replay = schedulers
+            .replay("inv-1", false, ScheduleOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(replay.original.version, "1.0.0");
+        assert_eq!(replay.replay.version, "1.0.1");
+        assert_eq!(replay.replay.replay_of.as_deref(), Some("inv-1"));
+        let changes = replay.changes.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "/result");
+
+        // 原版本的影子重放：输出一致，只计入重放次数
+        let before = scheduler.runtime().monitor().get_global_stats().await;
+        let replay = schedulers
+            .replay(
+                "inv-1",
+                true,
+                ScheduleOptions {
+                    shadow: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(replay.replay.version, "1.0.0");
+        assert!(replay.replay.shadow);
+        assert_eq!(replay.changes, Some(vec![]));
+        let after = scheduler.runtime().monitor().get_global_stats().await;
+        assert_eq!(after.total_requests, before.total_requests);
+        assert_eq!(after.shadow_replays, before.shadow_replays + 1);
+
+        assert!(matches!(
+            schedulers
+                .replay("missing", false, ScheduleOptions::default())
+                .await,
+            Err(FluxError::ExecutionNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_redacted_or_oversized_input_is_not_replayable() {
+        let history = ExecutionHistory::new(HistoryConfig {
+            capacity: 2,
+            max_payload_bytes: 32,
+        });
+        let mut function = FunctionMetadata::new("login".to_string(), String::new());
+        function.log_redaction = vec!["password".to_string()];
+        let response = Err(&FluxError::Timeout);
+        let options = ScheduleOptions::default();
+        let record = |id: &str, input: Value| {
+            history.record(
+                &function,
+                id,
+                &input,
+                response,
+                Duration::ZERO,
+                &options,
+                Vec::new(),
+                None,
+            )
+        };
+
+        record("redacted", json!({"user": "a", "password": "hunter22"}));
+        record("large", json!({"user": "a".repeat(64)}));
+        record("ok", json!({"user": "a"}));
+
+        // 容量为 2：最早的记录被丢弃
+        assert_eq!(history.len(), 2);
+        assert!(history.get("redacted").is_none());
+        let large = history.get("large").unwrap();
+        assert_eq!(large.input_capture, InputCapture::Truncated);
+        assert!(matches!(
+            large.replay_input(),
+            Err(FluxError::ReplayUnavailable { .. })
+        ));
+        assert_eq!(
+            history.get("ok").unwrap().replay_input().unwrap()["user"],
+            "a"
+        );
+
+        history.configure(&HistoryConfig {
+            capacity: 4,
+            ..Default::default()
+        });
+        record("redacted", json!({"user": "a", "password": "hunter22"}));
+        let redacted = history.get("redacted").unwrap();
+        assert_eq!(redacted.input_capture, InputCapture::Redacted);
+        let reason = redacted.replay_input().unwrap_err().to_string();
+        assert!(reason.contains("log_redaction"));
+    }
+}
@@ -0,0 +1,92 @@
+//! 自定义 HTTP 路由表
+//!
+//! 绑定保存在目标函数的元数据中（见 [`HttpRoute`]），路由表是各调度器注册表中绑定的快照，
+//! 任一注册表的修订号变化后在下一次查找时重建，因此增删绑定和删除函数无需重启即可生效。
+
+use crate::functions::http_route::{self, HttpRoute, RouteLookup};
+use crate::functions::registry::FunctionRegistry;
+use crate::functions::{FluxError, Result};
+use std::sync::{Arc, RwLock as StdRwLock};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// 路由快照及其对应的各注册表修订号
+#[derive(Debug, Default)]
+struct RouteSnapshot {
+    revisions: Vec<u64>,
+    routes: Arc<Vec<HttpRoute>>,
+}
+
+/// 所有调度器共享的路由表
+#[derive(Debug, Default)]
+pub struct HttpRouteTable {
+    snapshot: StdRwLock<RouteSnapshot>,
+    /// 串行化绑定的增删，冲突检测和写入之间不会插入其他绑定
+    changes: Mutex<()>,
+}
+
+impl HttpRouteTable {
+    /// 当前的全部绑定（按 ID 即创建顺序）
+    pub async fn routes(&self, registries: &[&FunctionRegistry]) -> Arc<Vec<HttpRoute>> {
+        let revisions: Vec<u64> = registries
+            .iter()
+            .map(|registry| registry.last_revision())
+            .collect();
+        {
+            let snapshot = self.snapshot.read().unwrap_or_else(|e| e.into_inner());
+            if snapshot.revisions == revisions {
+                return snapshot.routes.clone();
+            }
+        }
+
+        let mut routes = Vec::new();
+        for registry in registries {
+            for function in registry.list().await {
+                routes.extend(function.http_routes);
+            }
+        }
+        routes.sort_by(|a, b| a.id.cmp(&b.id));
+        let routes = Arc::new(routes);
+        // 重建期间注册表再次变化时记录的是旧修订号，下一次查找会再次重建
+        *self.snapshot.write().unwrap_or_else(|e| e.into_inner()) = RouteSnapshot {
+            revisions,
+            routes: routes.clone(),
+        };
+        routes
+    }
+
+    /// 查找请求对应的绑定
+    pub async fn lookup(
+        &self,
+        registries: &[&FunctionRegistry],
+        method: &str,
+        path: &str,
+    ) -> RouteLookup {
+        http_route::lookup(&self.routes(registries).await, method, path)
+    }
+
+    /// 获取增删绑定的锁
+    pub async fn lock_changes(&self) -> MutexGuard<'_, ()> {
+        self.changes.lock().await
+    }
+
+    /// 与已有绑定冲突时返回 `RouteConflict`
+    pub fn check_conflicts(routes: &[HttpRoute], route: &HttpRoute) -> Result<()> {
+        match routes
+            .iter()
+            .find(|existing| existing.conflicts_with(route))
+        {
+            Some(existing) => Err(FluxError::RouteConflict {
+                reason: format!(
+                    "{} {} overlaps route {} ({} {} -> {})",
+                    route.method,
+                    route.path_pattern,
+                    existing.id,
+                    existing.method,
+                    existing.path_pattern,
+                    existing.function
+                ),
+            }),
+            None => Ok(()),
+        }
+    }
+}
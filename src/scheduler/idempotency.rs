@@ -0,0 +1,249 @@
+use crate::functions::{FluxError, InvokeResponse, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// 幂等键的最大长度
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// 调用幂等键配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdempotencyConfig {
+    /// 已完成调用的结果保留时间（秒）
+    pub ttl_secs: u64,
+    /// 保留的键数上限，超出时淘汰最早的已完成调用
+    pub max_entries: usize,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 24 * 60 * 60,
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// 校验幂等键：非空、不超过 255 个字符且只含可见 ASCII 字符
+pub fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty()
+        || key.len() > MAX_IDEMPOTENCY_KEY_LEN
+        || !key.bytes().all(|b| b.is_ascii_graphic())
+    {
+        return Err(FluxError::ValidationError {
+            reason: format!(
+                "Idempotency key must be 1-{MAX_IDEMPOTENCY_KEY_LEN} visible ASCII characters"
+            ),
+        });
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// 调用结果，首个请求执行期间为空，重复请求等待它完成
+    result: Arc<OnceCell<InvokeResponse>>,
+    created_at: Instant,
+}
+
+/// 最近见过的幂等键（按函数隔离，只保存在内存中）
+///
+/// 同一个键的重复请求不会再次执行函数：首个请求仍在执行时等待并返回同一结果，
+/// 执行完成后返回保存的响应并标记 `replayed`。调度失败（未执行）的结果不保存，
+/// 可以用同一个键重试。
+#[derive(Debug, Default)]
+pub struct IdempotencyStore {
+    config: IdempotencyConfig,
+    entries: StdMutex<HashMap<(String, String), Entry>>,
+}
+
+impl IdempotencyStore {
+    pub fn new(config: IdempotencyConfig) -> Self {
+        Self {
+            config,
+            entries: StdMutex::default(),
+        }
+    }
+
+    pub fn config(&self) -> &IdempotencyConfig {
+        &self.config
+    }
+
+    /// 当前保留的键数（包括执行中的）
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 以 `function` 下的 `key` 执行 `execute`，同一个键最多成功执行一次
+    pub async fn run<F, Fut>(&self, function: &str, key: &str, execute: F) -> Result<InvokeResponse>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<InvokeResponse>>,
+    {
+        let id = (function.to_string(), key.to_string());
+        let cell = self.entry(&id);
+
+        let mut executed = false;
+        let result = cell
+            .get_or_try_init(|| {
+                executed = true;
+                execute()
+            })
+            .await;
+        match result {
+            Ok(response) if executed => Ok(response.clone()),
+            Ok(response) => {
+                tracing::info!(
+                    "Replaying result of idempotency key '{}' for function {}",
+                    key,
+                    function
+                );
+                let mut response = response.clone();
+                response.replayed = true;
+                Ok(response)
+            }
+            Err(e) => {
+                // 没有结果时移除键，允许用同一个键重试（等待中的重复请求会自行执行）
+                let mut entries = self.entries.lock().unwrap();
+                if entries
+                    .get(&id)
+                    .is_some_and(|entry| Arc::ptr_eq(&entry.result, &cell) && !cell.initialized())
+                {
+                    entries.remove(&id);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// 取出或创建键对应的结果槽，创建时淘汰过期和超出上限的已完成调用
+    fn entry(&self, id: &(String, String)) -> Arc<OnceCell<InvokeResponse>> {
+        let ttl = Duration::from_secs(self.config.ttl_secs);
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.get(id) {
+            if !(entry.result.initialized() && entry.created_at.elapsed() >= ttl) {
+                return entry.result.clone();
+            }
+            entries.remove(id);
+        }
+
+        entries.retain(|_, entry| !entry.result.initialized() || entry.created_at.elapsed() < ttl);
+        while entries.len() >= self.config.max_entries.max(1) {
+            let oldest = entries
+                .iter()
+                .filter(|(_, entry)| entry.result.initialized())
+                .min_by_key(|(_, entry)| entry.created_at)
+                .map(|(id, _)| id.clone());
+            match oldest {
+                Some(oldest) => {
+                    entries.remove(&oldest);
+                }
+                // 全部在执行中时暂时超出上限
+                None => break,
+            }
+        }
+
+        let result = Arc::new(OnceCell::new());
+        entries.insert(
+            id.clone(),
+            Entry {
+                result: result.clone(),
+                created_at: Instant::now(),
+            },
+        );
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::ExecutionStatus;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn response(output: u32) -> InvokeResponse {
+        InvokeResponse {
+            output: serde_json::json!(output),
+            execution_time_ms: 0,
+            status: ExecutionStatus::Success,
+            request_id: None,
+            attempts_made: 1,
+            succeeded_on_retry: false,
+            cold_start: false,
+            output_schema_violations: None,
+            backend: None,
+            replayed: false,
+            chaos_injected: false,
+            return_type_mismatch: false,
+            timing: Default::default(),
+            termination: None,
+            execution_path: None,
+            peer: None,
+            redirected_from: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_scoped_per_function_and_evicted() {
+        let store = IdempotencyStore::new(IdempotencyConfig {
+            ttl_secs: 3600,
+            max_entries: 2,
+        });
+        let calls = AtomicU32::new(0);
+        let run = |function: &'static str, key: &'static str| {
+            let store = &store;
+            let calls = &calls;
+            async move {
+                store
+                    .run(function, key, || async {
+                        Ok(response(calls.fetch_add(1, Ordering::SeqCst)))
+                    })
+                    .await
+                    .unwrap()
+            }
+        };
+
+        assert!(!run("a", "k1").await.replayed);
+        let replay = run("a", "k1").await;
+        assert!(replay.replayed);
+        assert_eq!(replay.output, serde_json::json!(0));
+        // 相同的键在其他函数下独立
+        assert!(!run("b", "k1").await.replayed);
+        // 超出上限时淘汰最早的键
+        assert!(!run("a", "k2").await.replayed);
+        assert_eq!(store.len(), 2);
+        assert!(!run("a", "k1").await.replayed);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_failed_schedule_is_not_stored() {
+        let store = IdempotencyStore::default();
+        let failed = store
+            .run("a", "k", || async {
+                Err(FluxError::Runtime("queue full".to_string()))
+            })
+            .await;
+        assert!(failed.is_err());
+        assert!(store.is_empty());
+
+        let retried = store.run("a", "k", || async { Ok(response(1)) }).await;
+        assert!(!retried.unwrap().replayed);
+    }
+
+    #[test]
+    fn test_validate_key() {
+        assert!(validate_key("order-42").is_ok());
+        assert!(validate_key("").is_err());
+        assert!(validate_key("has space").is_err());
+        assert!(validate_key(&"k".repeat(256)).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! 注册表变更驱动的缓存失效
+//!
+//! 调度器订阅注册表的变更事件（[`RegistryChange`]），按函数名和版本、代码哈希只使受影响的
+//! 函数缓存条目、JSON Schema、编译产物（包括路由后端的）和脚本工作进程失效。
+//! 每次调度前先处理积压的事件，因此绕过调度器直接修改注册表后，下一次调用同样使用新代码；
+//! 订阅落后而丢失事件时退回到全部失效。脚本缓存按内容寻址，不需要失效。
+
+use super::SimpleScheduler;
+use crate::functions::registry::{FunctionRegistry, RegistryChange};
+use crate::runtime::compiler::{CompileKey, RustCompiler};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::TryRecvError;
+use tokio::task::JoinHandle;
+
+/// 调度器对注册表变更事件的订阅，处理时持有锁，事件按发布顺序处理
+#[derive(Debug)]
+pub(crate) struct RegistrySubscription {
+    receiver: Mutex<Receiver<RegistryChange>>,
+}
+
+impl RegistrySubscription {
+    pub(crate) fn new(registry: &FunctionRegistry) -> Arc<Self> {
+        Arc::new(Self {
+            receiver: Mutex::new(registry.subscribe_changes()),
+        })
+    }
+}
+
+impl SimpleScheduler {
+    /// 处理积压的注册表变更事件，返回处理的事件数
+    ///
+    /// 调度和调度器自身的变更操作都会先调用；丢失事件时使所有缓存失效。
+    pub async fn apply_registry_changes(&self) -> usize {
+        let mut receiver = self.registry_changes.receiver.lock().await;
+        let mut applied = 0;
+        loop {
+            match receiver.try_recv() {
+                Ok(change) => {
+                    self.apply_change(&change).await;
+                    applied += 1;
+                }
+                Err(TryRecvError::Lagged(skipped)) => {
+                    tracing::warn!(
+                        "Missed {} registry changes, invalidating all cached functions",
+                        skipped
+                    );
+                    self.invalidate_all().await;
+                    applied += 1;
+                }
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return applied,
+            }
+        }
+    }
+
+    /// 在后台及时处理注册表变更事件（不依赖下一次调度）
+    pub fn spawn_invalidation(self: &Arc<Self>) -> JoinHandle<()> {
+        let scheduler = self.clone();
+        // 这个订阅只用来唤醒，事件本身由共享的订阅按顺序处理
+        let mut wakeups = self.registry.subscribe_changes();
+        tokio::spawn(async move {
+            while !matches!(
+                wakeups.recv().await,
+                Err(tokio::sync::broadcast::error::RecvError::Closed)
+            ) {
+                scheduler.apply_registry_changes().await;
+            }
+        })
+    }
+
+    async fn apply_change(&self, change: &RegistryChange) {
+        match change {
+            RegistryChange::Registered(_) => {}
+            RegistryChange::Updated {
+                name,
+                version,
+                old_hash,
+                new_hash,
+            } => {
+                self.evict_cached(name, version).await;
+                if old_hash != new_hash {
+                    self.evict_artifact(name, old_hash).await;
+                    self.retire_workers(name).await;
+                }
+            }
+            RegistryChange::Deleted(name) => {
+                self.evict_unretained(name).await;
+                self.retire_workers(name).await;
+            }
+            RegistryChange::VersionPruned {
+                name,
+                version,
+                hash,
+            } => {
+                let retained = self.registry.versions(name).await.unwrap_or_default();
+                if !retained.iter().any(|function| &function.version == version) {
+                    self.evict_cached(name, version).await;
+                }
+                self.evict_artifact(name, hash).await;
+            }
+        }
+    }
+
+    /// 移除函数一个版本的缓存条目和已编译的 JSON Schema
+    async fn evict_cached(&self, name: &str, version: &str) {
+        let key = format!("{name}@{version}");
+        self.runtime.cache().remove(&key).await;
+        self.schemas
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&key);
+    }
+
+    /// 代码哈希不再被函数的任何保留版本使用时，移除其编译产物
+    async fn evict_artifact(&self, name: &str, hash: &str) {
+        let retained = self.registry.versions(name).await.unwrap_or_default();
+        if retained
+            .iter()
+            .any(|function| RustCompiler::compile_key(function).1 == hash)
+        {
+            return;
+        }
+        let key: CompileKey = (name.to_string(), hash.to_string());
+        for compiler in self.compilers() {
+            if compiler.evict(&key).await {
+                tracing::info!("Invalidated stale artifact of function {}", name);
+            }
+        }
+    }
+
+    /// 丢失事件时：清空函数缓存和 JSON Schema，移除不属于任何保留版本的编译产物，回收所有工作进程
+    async fn invalidate_all(&self) {
+        self.runtime.cache().clear().await;
+        self.schemas
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        let mut keep = HashSet::new();
+        for function in self.registry.list().await {
+            for version in self
+                .registry
+                .versions(&function.name)
+                .await
+                .unwrap_or_default()
+            {
+                keep.insert(RustCompiler::compile_key(&version));
+            }
+        }
+        for compiler in self.compilers() {
+            compiler.retain_keys(&keep).await;
+        }
+        if let Some(sandbox) = self.runtime.sandbox() {
+            sandbox.workers().retire_all().await;
+        }
+    }
+
+    /// 本调度器及其路由后端的编译器
+    pub(super) fn compilers(&self) -> Vec<Arc<RustCompiler>> {
+        let mut compilers: Vec<_> = self.runtime.compiler().cloned().into_iter().collect();
+        if let Some(router) = &self.router {
+            compilers.extend(router.compilers());
+        }
+        compilers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::{FunctionMetadata, InvokeRequest};
+    use crate::scheduler::{ScheduleOptions, Scheduler};
+    use serde_json::json;
+
+    fn request() -> InvokeRequest {
+        InvokeRequest {
+            input: json!({"a": 2, "b": 3}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        }
+    }
+
+    fn function(version: &str, code: &str) -> FunctionMetadata {
+        FunctionMetadata::new_with_version(
+            "calc".to_string(),
+            code.to_string(),
+            version.to_string(),
+        )
+    }
+
+    async fn result(scheduler: &SimpleScheduler, version: Option<&str>) -> Option<f64> {
+        let options = ScheduleOptions {
+            version: version.map(str::to_string),
+            ..Default::default()
+        };
+        let response = scheduler
+            .schedule_with("calc", request(), options)
+            .await
+            .unwrap();
+        response.output["result"].as_f64()
+    }
+
+    #[tokio::test]
+    async fn test_direct_registry_update_reaches_next_invocation() {
+        let scheduler = SimpleScheduler::new();
+        let registry = scheduler.registry().clone();
+        registry
+            .register(function("1.0.0", "return a + b"))
+            .await
+            .unwrap();
+        assert_eq!(result(&scheduler, None).await, Some(5.0));
+        assert_eq!(scheduler.runtime.cache().stats().await.size, 1);
+
+        // 绕过调度器直接替换同一版本：下一次调用前事件已处理，执行新代码
+        registry
+            .upsert(function("1.0.0", "return a * b"))
+            .await
+            .unwrap();
+        assert_eq!(result(&scheduler, None).await, Some(6.0));
+
+        registry.remove("calc").await.unwrap();
+        assert_eq!(scheduler.apply_registry_changes().await, 1);
+        assert_eq!(scheduler.runtime.cache().stats().await.size, 0);
+        assert!(scheduler.schedule("calc", request()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pruned_versions_are_evicted_and_lag_invalidates_everything() {
+        let scheduler = SimpleScheduler::new().with_max_versions(2);
+        let registry = scheduler.registry().clone();
+        for (version, code) in [
+            ("1.0.0", "return a + b"),
+            ("2.0.0", "return a * b"),
+            ("3.0.0", "return a - b"),
+        ] {
+            registry.upsert(function(version, code)).await.unwrap();
+            result(&scheduler, Some(version)).await;
+        }
+        // 写入 3.0.0 时 1.0.0 被裁剪，调用前已移除其缓存条目
+        let cache = scheduler.runtime.cache();
+        assert!(cache.get("calc@1.0.0").await.is_none());
+        assert!(cache.get("calc@2.0.0").await.is_some());
+        assert!(cache.get("calc@3.0.0").await.is_some());
+
+        // 积压超过通知缓冲区时退回到全部失效
+        for _ in 0..300 {
+            registry
+                .upsert(FunctionMetadata::new(
+                    "other".to_string(),
+                    "return a".to_string(),
+                ))
+                .await
+                .unwrap();
+        }
+        scheduler.apply_registry_changes().await;
+        assert_eq!(cache.stats().await.size, 0);
+        assert_eq!(result(&scheduler, None).await, Some(-1.0));
+    }
+}
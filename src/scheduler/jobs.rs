@@ -0,0 +1,544 @@
+//! 异步调用任务
+//!
+//! `POST /invoke/:name/async` 立即返回任务 ID，调用在后台执行。调用方可以长轮询
+//! `GET /jobs/:id?wait_ms=` 等待任务完成：每个任务一个 `watch` 通道，等待者订阅时先检查当前状态，
+//! 任务在订阅之前完成也不会丢失通知。也可以在请求中给出 `callback_url`，任务完成后把结果 POST
+//! 到该地址（函数配置了 webhook 密钥时与 webhook 一样签名），失败时有限次重试。
+
+use super::webhooks::{DELIVERY_ID_HEADER, DeliveryStatus, SIGNATURE_HEADER, sign};
+use super::{ScheduleOptions, SimpleScheduler};
+use crate::functions::{FluxError, InvokeRequest, InvokeResponse, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use utoipa::ToSchema;
+
+/// 异步调用任务的配置（`[jobs]`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobsConfig {
+    /// 保留的任务数上限，超出时丢弃最早完成的任务
+    pub max_jobs: usize,
+    /// 长轮询的最长等待时间（毫秒），更长的 `wait_ms` 按此截断
+    pub max_wait_ms: u64,
+    /// 同时进行的长轮询数上限，超出时立即返回任务的当前状态
+    pub max_long_polls: usize,
+    /// 回调的最大尝试次数
+    pub callback_max_attempts: u32,
+    /// 首次重试回调前的等待时间（毫秒），之后每次翻倍
+    pub callback_initial_backoff_ms: u64,
+    /// 单次回调请求超时（毫秒）
+    pub callback_timeout_ms: u64,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            max_jobs: 10_000,
+            max_wait_ms: 30_000,
+            max_long_polls: 1000,
+            callback_max_attempts: 3,
+            callback_initial_backoff_ms: 500,
+            callback_timeout_ms: 5000,
+        }
+    }
+}
+
+/// 异步调用请求：调用请求加上可选的完成回调地址
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct AsyncInvokeRequest {
+    #[serde(flatten)]
+    pub request: InvokeRequest,
+    /// 任务完成后 POST 任务结果的地址（http 或 https）
+    #[serde(default)]
+    pub callback_url: Option<String>,
+}
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// 正在排队或执行
+    Running,
+    /// 调用已完成，结果见 `response`（函数本身可能执行失败，见其中的 `status`）
+    Completed,
+    /// 调度失败（例如函数已删除、队列已满），原因见 `error`
+    Failed,
+}
+
+/// 完成回调的投递状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct JobCallback {
+    pub url: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// 最后一次请求的 HTTP 状态码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// 一次异步调用
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Job {
+    pub id: String,
+    pub function: String,
+    pub status: JobStatus,
+    pub submitted_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<InvokeResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback: Option<JobCallback>,
+}
+
+impl Job {
+    pub fn is_finished(&self) -> bool {
+        self.status != JobStatus::Running
+    }
+}
+
+/// 任务表：任务 ID -> 状态通道，以及按提交顺序排列的任务 ID（用于丢弃最早完成的任务）
+#[derive(Debug, Default)]
+struct JobTable {
+    jobs: HashMap<String, watch::Sender<Job>>,
+    order: VecDeque<String>,
+}
+
+/// 异步调用任务（所有调度器共享）
+#[derive(Debug, Default)]
+pub struct JobStore {
+    config: StdMutex<JobsConfig>,
+    table: StdMutex<JobTable>,
+    long_polls: Arc<AtomicUsize>,
+    client: reqwest::Client,
+}
+
+/// 占用一个长轮询名额，释放时归还
+#[derive(Debug)]
+pub struct LongPollPermit(Arc<AtomicUsize>);
+
+impl Drop for LongPollPermit {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// 校验回调地址：必须是 http 或 https 的绝对地址
+pub fn validate_callback_url(url: &str) -> Result<()> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+        _ => Err(FluxError::ValidationError {
+            reason: format!("callback_url must be an absolute http or https URL: {url}"),
+        }),
+    }
+}
+
+impl JobStore {
+    pub fn configure(&self, config: &JobsConfig) {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+    }
+
+    pub fn config(&self) -> JobsConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 提交异步调用并立即返回任务，调用和回调在后台执行（调用 ID 即任务 ID）
+    pub fn submit(
+        self: &Arc<Self>,
+        scheduler: Arc<SimpleScheduler>,
+        function: &str,
+        request: AsyncInvokeRequest,
+        options: ScheduleOptions,
+    ) -> Result<Job> {
+        if let Some(url) = &request.callback_url {
+            validate_callback_url(url)?;
+        }
+        let job = self.insert(function, request.callback_url);
+        let store = self.clone();
+        let (id, function) = (job.id.clone(), function.to_string());
+        tokio::spawn(async move {
+            let options = ScheduleOptions {
+                invocation_id: Some(id.clone()),
+                ..options
+            };
+            let result = scheduler
+                .schedule_with(&function, request.request, options)
+                .await;
+            let finished = store.finish(&id, result);
+            if let Some(job) = finished.filter(|job| job.callback.is_some()) {
+                let secret = scheduler
+                    .registry()
+                    .get(&function)
+                    .await
+                    .ok()
+                    .and_then(|function| function.webhooks)
+                    .and_then(|webhooks| webhooks.secret);
+                store.deliver_callback(job, secret).await;
+            }
+        });
+        Ok(job)
+    }
+
+    /// 任务的当前状态
+    pub fn get(&self, id: &str) -> Option<Job> {
+        let table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        table.jobs.get(id).map(|sender| sender.borrow().clone())
+    }
+
+    /// 等待任务完成，最长 `timeout`；返回等待结束时的状态（任务不存在时为 `None`）
+    pub async fn wait(&self, id: &str, timeout: Duration) -> Option<Job> {
+        let mut receiver = {
+            let table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+            table.jobs.get(id)?.subscribe()
+        };
+        // `wait_for` 先检查当前值，订阅之前已完成的任务立即返回
+        let _ = tokio::time::timeout(timeout, receiver.wait_for(Job::is_finished)).await;
+        let job = receiver.borrow().clone();
+        Some(job)
+    }
+
+    /// 占用一个长轮询名额，已达上限时返回 `None`
+    pub fn try_long_poll(&self) -> Option<LongPollPermit> {
+        let max = self.config().max_long_polls;
+        let previous = self.long_polls.fetch_add(1, Ordering::AcqRel);
+        let permit = LongPollPermit(self.long_polls.clone());
+        (previous < max).then_some(permit)
+    }
+
+    /// 登记一个执行中的任务，超出保留数时丢弃最早完成的任务
+    fn insert(&self, function: &str, callback_url: Option<String>) -> Job {
+        let job = Job {
+            id: scru128::new_string(),
+            function: function.to_string(),
+            status: JobStatus::Running,
+            submitted_at: Utc::now(),
+            completed_at: None,
+            response: None,
+            error: None,
+            callback: callback_url.map(|url| JobCallback {
+                url,
+                status: DeliveryStatus::Pending,
+                attempts: 0,
+                response_status: None,
+                last_error: None,
+            }),
+        };
+        let max_jobs = self.config().max_jobs.max(1);
+        let mut table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        table
+            .jobs
+            .insert(job.id.clone(), watch::channel(job.clone()).0);
+        table.order.push_back(job.id.clone());
+        let JobTable { jobs, order } = &mut *table;
+        let mut excess = jobs.len().saturating_sub(max_jobs);
+        order.retain(|id| {
+            let finished = jobs
+                .get(id)
+                .is_some_and(|sender| sender.borrow().is_finished());
+            if excess > 0 && finished {
+                jobs.remove(id);
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+        job
+    }
+
+    /// 记录任务结果并唤醒等待者，返回完成后的任务（任务已被丢弃时为 `None`）
+    fn finish(&self, id: &str, result: Result<InvokeResponse>) -> Option<Job> {
+        let table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        let sender = table.jobs.get(id)?;
+        sender.send_modify(|job| {
+            job.completed_at = Some(Utc::now());
+            match result {
+                Ok(mut response) => {
+                    response.request_id = Some(job.id.clone());
+                    job.status = JobStatus::Completed;
+                    job.response = Some(response);
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e.to_string());
+                }
+            }
+        });
+        let job = sender.borrow().clone();
+        Some(job)
+    }
+
+    fn update_callback(&self, id: &str, apply: impl FnOnce(&mut JobCallback)) {
+        let table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = table.jobs.get(id) {
+            sender.send_modify(|job| {
+                if let Some(callback) = &mut job.callback {
+                    apply(callback);
+                }
+            });
+        }
+    }
+
+    /// POST 完成的任务（不含回调状态）到回调地址，失败时按指数退避重试，直到成功或尝试次数用尽
+    async fn deliver_callback(&self, mut job: Job, secret: Option<String>) {
+        let Some(callback) = job.callback.take() else {
+            return;
+        };
+        let body = match serde_json::to_vec(&job) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!("Failed to serialize job {}: {}", job.id, e);
+                return;
+            }
+        };
+        let config = self.config();
+        let max_attempts = config.callback_max_attempts.max(1);
+        for attempt in 1..=max_attempts {
+            let mut request = self
+                .client
+                .post(&callback.url)
+                .timeout(Duration::from_millis(config.callback_timeout_ms))
+                .header("content-type", "application/json")
+                .header(DELIVERY_ID_HEADER, &job.id)
+                .body(body.clone());
+            if let Some(secret) = &secret {
+                request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+            }
+            let (response_status, error) = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status().as_u16()), None)
+                }
+                Ok(response) => (
+                    Some(response.status().as_u16()),
+                    Some(format!("Receiver returned {}", response.status())),
+                ),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            let delivered = error.is_none();
+            let exhausted = attempt == max_attempts;
+            self.update_callback(&job.id, |callback| {
+                callback.attempts = attempt;
+                callback.response_status = response_status;
+                callback.last_error = error.clone();
+                callback.status = match (delivered, exhausted) {
+                    (true, _) => DeliveryStatus::Delivered,
+                    (false, true) => DeliveryStatus::Failed,
+                    (false, false) => DeliveryStatus::Pending,
+                };
+            });
+            if delivered {
+                return;
+            }
+            tracing::warn!(
+                "Callback of job {} failed (attempt {}/{}): {}",
+                job.id,
+                attempt,
+                max_attempts,
+                error.unwrap_or_default()
+            );
+            if !exhausted {
+                let backoff = config
+                    .callback_initial_backoff_ms
+                    .saturating_mul(1 << (attempt - 1).min(16));
+                tokio::time::sleep(Duration::from_millis(backoff)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::webhook::WebhookConfig;
+    use crate::functions::{FunctionMetadata, InvokeRequest};
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    fn response() -> InvokeResponse {
+        serde_json::from_value(json!({
+            "output": {"ok": true},
+            "execution_time_ms": 1,
+            "status": "Success"
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wait_then_complete_and_complete_then_wait() {
+        let store = Arc::new(JobStore::default());
+        let job = store.insert("f", None);
+        assert!(!job.is_finished());
+
+        // 先等待后完成：完成时立即唤醒
+        let waiter = {
+            let store = store.clone();
+            let id = job.id.clone();
+            tokio::spawn(async move { store.wait(&id, Duration::from_secs(10)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        store.finish(&job.id, Ok(response()));
+        let waited = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(waited.status, JobStatus::Completed);
+        assert_eq!(waited.response.unwrap().output, json!({"ok": true}));
+
+        // 先完成后等待：不会错过通知
+        let job = store.insert("f", None);
+        store.finish(&job.id, Err(FluxError::Timeout));
+        let waited = tokio::time::timeout(
+            Duration::from_secs(1),
+            store.wait(&job.id, Duration::from_secs(10)),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(waited.status, JobStatus::Failed);
+        assert!(waited.error.is_some());
+
+        // 未完成的任务等到超时后返回当前状态
+        let job = store.insert("f", None);
+        let waited = store
+            .wait(&job.id, Duration::from_millis(20))
+            .await
+            .unwrap();
+        assert_eq!(waited.status, JobStatus::Running);
+        assert!(store.wait("missing", Duration::ZERO).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_long_poll_cap_and_retention() {
+        let store = JobStore::default();
+        store.configure(&JobsConfig {
+            max_jobs: 2,
+            max_long_polls: 1,
+            ..Default::default()
+        });
+        let permit = store.try_long_poll().unwrap();
+        assert!(store.try_long_poll().is_none());
+        drop(permit);
+        assert!(store.try_long_poll().is_some());
+
+        // 超出保留数时只丢弃已完成的任务
+        let running = store.insert("f", None);
+        let done = store.insert("f", None);
+        store.finish(&done.id, Ok(response()));
+        let latest = store.insert("f", None);
+        assert!(store.get(&running.id).is_some());
+        assert!(store.get(&done.id).is_none());
+        assert!(store.get(&latest.id).is_some());
+        assert!(validate_callback_url("ftp://example.com").is_err());
+    }
+
+    /// 依次以给定状态码应答的 HTTP 接收方，转发收到的请求头和请求体
+    async fn spawn_receiver(
+        statuses: Vec<u16>,
+    ) -> (String, mpsc::UnboundedReceiver<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/done", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let (head, body_start) = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break (
+                            String::from_utf8_lossy(&buffer[..pos]).to_lowercase(),
+                            pos + 4,
+                        );
+                    }
+                };
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map(|value| value.trim().parse().unwrap())
+                    .unwrap_or(0);
+                while buffer.len() < body_start + length {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..n]);
+                }
+                let response = format!(
+                    "HTTP/1.1 {status} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                let _ = tx.send((head, buffer[body_start..body_start + length].to_vec()));
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn test_callback_is_signed_and_retried_after_server_error() {
+        let (url, mut requests) = spawn_receiver(vec![500, 200]).await;
+        let store = Arc::new(JobStore::default());
+        store.configure(&JobsConfig {
+            callback_initial_backoff_ms: 10,
+            ..Default::default()
+        });
+        let scheduler = Arc::new(SimpleScheduler::new());
+        let mut function = FunctionMetadata::new("sum".to_string(), "return a + b".to_string());
+        function.webhooks = Some(WebhookConfig {
+            secret: Some("s3cret".to_string()),
+            ..Default::default()
+        });
+        scheduler.registry().register(function).await.unwrap();
+
+        let request = AsyncInvokeRequest {
+            request: InvokeRequest {
+                input: json!({"a": 1, "b": 2}),
+                retry_policy: None,
+                priority: None,
+                idempotency_key: None,
+                session_id: None,
+                affinity_key: None,
+            },
+            callback_url: Some(url),
+        };
+        let job = store
+            .submit(scheduler, "sum", request, ScheduleOptions::default())
+            .unwrap();
+
+        // 第一次回调收到 500，重试后成功；两次请求的内容和签名相同
+        let (first_head, first_body) = requests.recv().await.unwrap();
+        let (head, body) = requests.recv().await.unwrap();
+        assert_eq!((first_head, first_body), (head.clone(), body.clone()));
+        assert!(head.contains(&format!("{SIGNATURE_HEADER}: {}", sign("s3cret", &body))));
+        assert!(head.contains(&format!("{DELIVERY_ID_HEADER}: {}", job.id.to_lowercase())));
+        let delivered: Job = serde_json::from_slice(&body).unwrap();
+        assert_eq!(delivered.id, job.id);
+        assert_eq!(delivered.status, JobStatus::Completed);
+        assert_eq!(delivered.response.unwrap().output["result"], 3.0);
+        assert!(delivered.callback.is_none());
+
+        let mut callback = store.get(&job.id).unwrap().callback.unwrap();
+        for _ in 0..50 {
+            if callback.status == DeliveryStatus::Delivered {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            callback = store.get(&job.id).unwrap().callback.unwrap();
+        }
+        assert_eq!(callback.status, DeliveryStatus::Delivered);
+        assert_eq!(callback.attempts, 2);
+        assert_eq!(callback.response_status, Some(200));
+    }
+}
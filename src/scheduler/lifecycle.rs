@@ -1,4 +1,5 @@
 use anyhow::Result;
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -6,8 +7,17 @@ use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, RwLock};
 use tokio::time::{interval, sleep};
 
+use crate::functions::registry::FunctionRegistry;
 use crate::functions::{FunctionMetadata, InvokeRequest, InvokeResponse};
+pub use crate::runtime::cache::CacheStrategy;
+use crate::runtime::events::InstanceLifecycleEvent;
+pub use crate::runtime::events::{InstanceLifecycleEvent as LifecycleEvent, LifecycleEventType};
 use crate::runtime::instance::{InstanceManager, InstanceState};
+use crate::runtime::lock_order;
+use crate::runtime::monitor::PerformanceMonitor;
+
+/// 生命周期元数据中记录预热触发条件的键
+pub const WARMUP_TRIGGER_KEY: &str = "warmup_trigger";
 
 /// 生命周期管理器配置
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -39,6 +49,20 @@ pub struct WarmupConfig {
     pub warmup_strategy: WarmupStrategy,
     /// 预热触发条件
     pub warmup_trigger: WarmupTrigger,
+    /// 负载预热：调用速率阈值（次/秒），达到时为该函数预热 `warmup_count` 个实例
+    #[serde(default = "default_load_threshold_per_sec")]
+    pub load_threshold_per_sec: f64,
+    /// 负载预热：统计调用速率的时间窗口（秒）
+    #[serde(default = "default_load_window_secs")]
+    pub load_window_secs: u64,
+}
+
+fn default_load_threshold_per_sec() -> f64 {
+    5.0
+}
+
+fn default_load_window_secs() -> u64 {
+    60
 }
 
 impl Default for WarmupConfig {
@@ -50,6 +74,8 @@ impl Default for WarmupConfig {
             warmup_interval_ms: 100,
             warmup_strategy: WarmupStrategy::Eager,
             warmup_trigger: WarmupTrigger::OnDemand,
+            load_threshold_per_sec: default_load_threshold_per_sec(),
+            load_window_secs: default_load_window_secs(),
         }
     }
 }
@@ -80,6 +106,18 @@ pub enum WarmupTrigger {
     Predictive,
 }
 
+impl WarmupTrigger {
+    /// 记录到事件元数据中的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OnDemand => "on_demand",
+            Self::Scheduled => "scheduled",
+            Self::LoadBased => "load_based",
+            Self::Predictive => "predictive",
+        }
+    }
+}
+
 /// 闲置管理配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IdleConfig {
@@ -141,19 +179,6 @@ impl Default for CacheConfig {
     }
 }
 
-/// 缓存策略
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum CacheStrategy {
-    /// 最近最少使用
-    Lru,
-    /// 先进先出
-    Fifo,
-    /// 最不常用
-    Lfu,
-    /// 时间过期
-    Ttl,
-}
-
 /// 清理配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CleanupConfig {
@@ -233,62 +258,6 @@ pub enum LifecyclePhase {
     Error(String),
 }
 
-/// 生命周期事件
-#[derive(Debug, Clone, Serialize)]
-pub struct LifecycleEvent {
-    /// 事件ID
-    pub event_id: String,
-    /// 实例ID
-    pub instance_id: String,
-    /// 函数名
-    pub function_name: String,
-    /// 事件类型
-    pub event_type: LifecycleEventType,
-    /// 事件时间
-    pub timestamp: chrono::DateTime<chrono::Utc>,
-    /// 阶段转换
-    pub phase_transition: Option<(LifecyclePhase, LifecyclePhase)>,
-    /// 事件数据
-    pub data: HashMap<String, serde_json::Value>,
-    /// 耗时（毫秒）
-    pub duration_ms: Option<u64>,
-}
-
-/// 生命周期事件类型
-#[derive(Debug, Clone, Serialize)]
-pub enum LifecycleEventType {
-    /// 实例创建
-    InstanceCreated,
-    /// 实例预热开始
-    WarmupStarted,
-    /// 实例预热完成
-    WarmupCompleted,
-    /// 实例预热失败
-    WarmupFailed,
-    /// 实例就绪
-    InstanceReady,
-    /// 执行开始
-    ExecutionStarted,
-    /// 执行完成
-    ExecutionCompleted,
-    /// 执行失败
-    ExecutionFailed,
-    /// 实例闲置
-    InstanceIdle,
-    /// 实例暂停
-    InstanceSuspended,
-    /// 实例恢复
-    InstanceResumed,
-    /// 清理开始
-    CleanupStarted,
-    /// 清理完成
-    CleanupCompleted,
-    /// 实例终止
-    InstanceTerminated,
-    /// 生命周期错误
-    LifecycleError,
-}
-
 /// 生命周期统计
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct LifecycleStatistics {
@@ -300,6 +269,12 @@ pub struct LifecycleStatistics {
     pub idle_instances: u64,
     /// 预热实例数
     pub warming_instances: u64,
+    /// 暂停实例数
+    pub suspended_instances: u64,
+    /// 累计恢复次数
+    pub total_resumes: u64,
+    /// 平均恢复时间（毫秒）
+    pub avg_resume_time_ms: f64,
     /// 平均创建时间（毫秒）
     pub avg_creation_time_ms: f64,
     /// 平均预热时间（毫秒）
@@ -317,7 +292,7 @@ pub struct LifecycleStatistics {
 }
 
 /// 实例生命周期信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct InstanceLifecycle {
     /// 实例ID
     pub instance_id: String,
@@ -326,36 +301,35 @@ pub struct InstanceLifecycle {
     /// 当前阶段
     pub current_phase: LifecyclePhase,
     /// 创建时间
-    pub created_at: Instant,
+    pub created_at: chrono::DateTime<chrono::Utc>,
     /// 最后活动时间
-    pub last_activity: Instant,
+    pub last_activity: chrono::DateTime<chrono::Utc>,
     /// 预热开始时间
-    pub warmup_started_at: Option<Instant>,
+    pub warmup_started_at: Option<chrono::DateTime<chrono::Utc>>,
     /// 预热完成时间
-    pub warmup_completed_at: Option<Instant>,
+    pub warmup_completed_at: Option<chrono::DateTime<chrono::Utc>>,
     /// 执行次数
     pub execution_count: u64,
-    /// 总执行时间
-    pub total_execution_time: Duration,
-    /// 生命周期事件历史
-    pub event_history: Vec<LifecycleEvent>,
+    /// 总执行时间（毫秒）
+    pub total_execution_time_ms: u64,
     /// 实例元数据
     pub metadata: HashMap<String, String>,
 }
 
 /// 生命周期管理器
-#[derive(Debug)]
+///
+/// 生命周期表为 `DashMap`，分片锁不跨越 `.await`；统计信息锁只在生命周期表的
+/// 引用释放后获取。锁顺序见 [`lock_order`]。
+#[derive(Debug, Clone)]
 pub struct LifecycleManager {
     /// 配置
     config: LifecycleConfig,
     /// 实例管理器
     instance_manager: Arc<InstanceManager>,
     /// 生命周期信息
-    lifecycles: Arc<RwLock<HashMap<String, InstanceLifecycle>>>,
+    lifecycles: Arc<DashMap<String, InstanceLifecycle>>,
     /// 统计信息
     statistics: Arc<RwLock<LifecycleStatistics>>,
-    /// 事件历史
-    event_history: Arc<RwLock<Vec<LifecycleEvent>>>,
     /// 预热队列
     warmup_queue: Arc<Mutex<Vec<String>>>,
     /// 清理队列
@@ -364,6 +338,14 @@ pub struct LifecycleManager {
     monitoring_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
     /// 清理任务句柄
     cleanup_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    /// 调用速率来源（负载预热）
+    monitor: Option<Arc<PerformanceMonitor>>,
+    /// 函数元数据来源（负载预热）
+    registry: Option<Arc<FunctionRegistry>>,
+    /// 负载预热任务句柄
+    load_warmup_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    /// 负载预热创建实例失败的函数及其定义的更新时间（定义变更前不再重试）
+    prewarm_failures: Arc<Mutex<HashMap<String, chrono::DateTime<chrono::Utc>>>>,
 }
 
 impl LifecycleManager {
@@ -372,13 +354,30 @@ impl LifecycleManager {
         Self {
             config,
             instance_manager,
-            lifecycles: Arc::new(RwLock::new(HashMap::new())),
+            lifecycles: Arc::new(DashMap::new()),
             statistics: Arc::new(RwLock::new(LifecycleStatistics::default())),
-            event_history: Arc::new(RwLock::new(Vec::new())),
             warmup_queue: Arc::new(Mutex::new(Vec::new())),
             cleanup_queue: Arc::new(Mutex::new(Vec::new())),
             monitoring_handle: Arc::new(Mutex::new(None)),
             cleanup_handle: Arc::new(Mutex::new(None)),
+            monitor: None,
+            registry: None,
+            load_warmup_handle: Arc::new(Mutex::new(None)),
+            prewarm_failures: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 创建支持负载预热的生命周期管理器，调用速率取自 `monitor`，函数定义取自 `registry`
+    pub fn with_load_source(
+        config: LifecycleConfig,
+        instance_manager: Arc<InstanceManager>,
+        monitor: Arc<PerformanceMonitor>,
+        registry: Arc<FunctionRegistry>,
+    ) -> Self {
+        Self {
+            monitor: Some(monitor),
+            registry: Some(registry),
+            ..Self::new(config, instance_manager)
         }
     }
 
@@ -392,6 +391,18 @@ impl LifecycleManager {
         // 启动清理任务
         self.start_cleanup().await;
 
+        // 启动负载预热任务
+        let warmup = &self.config.warmup_config;
+        if warmup.enabled && matches!(warmup.warmup_trigger, WarmupTrigger::LoadBased) {
+            if self.monitor.is_some() && self.registry.is_some() {
+                self.start_load_warmup().await;
+            } else {
+                tracing::warn!(
+                    "Load-based warmup requires a performance monitor and function registry; trigger disabled"
+                );
+            }
+        }
+
         tracing::info!("Lifecycle manager started");
         Ok(())
     }
@@ -408,6 +419,11 @@ impl LifecycleManager {
             handle.abort();
         }
 
+        // 停止负载预热任务
+        if let Some(handle) = self.load_warmup_handle.lock().await.take() {
+            handle.abort();
+        }
+
         // 清理所有实例
         self.cleanup_all_instances().await?;
 
@@ -417,7 +433,7 @@ impl LifecycleManager {
 
     /// 创建实例
     pub async fn create_instance(&self, function_metadata: FunctionMetadata) -> Result<String> {
-        let start_time = Instant::now();
+        let now = chrono::Utc::now();
 
         // 创建实例
         let instance_id = self
@@ -430,32 +446,17 @@ impl LifecycleManager {
             instance_id: instance_id.clone(),
             function_name: function_metadata.name.clone(),
             current_phase: LifecyclePhase::Creating,
-            created_at: start_time,
-            last_activity: start_time,
+            created_at: now,
+            last_activity: now,
             warmup_started_at: None,
             warmup_completed_at: None,
             execution_count: 0,
-            total_execution_time: Duration::ZERO,
-            event_history: Vec::new(),
+            total_execution_time_ms: 0,
             metadata: HashMap::new(),
         };
 
         // 保存生命周期信息
-        {
-            let mut lifecycles = self.lifecycles.write().await;
-            lifecycles.insert(instance_id.clone(), lifecycle);
-        }
-
-        // 记录创建事件
-        self.emit_lifecycle_event(
-            &instance_id,
-            &function_metadata.name,
-            LifecycleEventType::InstanceCreated,
-            None,
-            HashMap::new(),
-            Some(start_time.elapsed().as_millis() as u64),
-        )
-        .await;
+        self.lifecycles.insert(instance_id.clone(), lifecycle);
 
         // 更新阶段为就绪
         self.update_phase(&instance_id, LifecyclePhase::Ready)
@@ -473,6 +474,20 @@ impl LifecycleManager {
 
     /// 预热实例
     pub async fn warmup_instance(&self, instance_id: &str) -> Result<()> {
+        let trigger = HashMap::from([(
+            "trigger".to_string(),
+            WarmupTrigger::OnDemand.as_str().to_string(),
+        )]);
+        self.warmup_instance_with_trigger(instance_id, trigger)
+            .await
+    }
+
+    /// 预热实例，`trigger` 记录触发条件和原因，附加到预热事件的元数据中
+    pub async fn warmup_instance_with_trigger(
+        &self,
+        instance_id: &str,
+        trigger: HashMap<String, String>,
+    ) -> Result<()> {
         let start_time = Instant::now();
 
         // 更新阶段为预热中
@@ -483,20 +498,16 @@ impl LifecycleManager {
         self.emit_lifecycle_event(
             instance_id,
             &self.get_function_name(instance_id).await?,
-            LifecycleEventType::WarmupStarted,
-            None,
-            HashMap::new(),
+            LifecycleEventType::WarmingStarted,
+            trigger.clone(),
             None,
         )
         .await;
 
         // 更新预热开始时间
-        {
-            let mut lifecycles = self.lifecycles.write().await;
-            if let Some(lifecycle) = lifecycles.get_mut(instance_id) {
-                lifecycle.warmup_started_at = Some(start_time);
-            }
-        }
+        self.modify_lifecycle(instance_id, |lifecycle| {
+            lifecycle.warmup_started_at = Some(chrono::Utc::now());
+        });
 
         // 执行预热逻辑
         match self.perform_warmup(instance_id).await {
@@ -505,20 +516,16 @@ impl LifecycleManager {
                 let warmup_time = start_time.elapsed();
 
                 // 更新预热完成时间
-                {
-                    let mut lifecycles = self.lifecycles.write().await;
-                    if let Some(lifecycle) = lifecycles.get_mut(instance_id) {
-                        lifecycle.warmup_completed_at = Some(Instant::now());
-                    }
-                }
+                self.modify_lifecycle(instance_id, |lifecycle| {
+                    lifecycle.warmup_completed_at = Some(chrono::Utc::now());
+                });
 
                 // 记录预热完成事件
                 self.emit_lifecycle_event(
                     instance_id,
                     &self.get_function_name(instance_id).await?,
-                    LifecycleEventType::WarmupCompleted,
-                    None,
-                    HashMap::new(),
+                    LifecycleEventType::WarmingCompleted,
+                    trigger,
                     Some(warmup_time.as_millis() as u64),
                 )
                 .await;
@@ -542,15 +549,11 @@ impl LifecycleManager {
                 self.emit_lifecycle_event(
                     instance_id,
                     &self.get_function_name(instance_id).await?,
-                    LifecycleEventType::WarmupFailed,
-                    None,
-                    [(
-                        "error".to_string(),
-                        serde_json::Value::String(e.to_string()),
-                    )]
-                    .iter()
-                    .cloned()
-                    .collect(),
+                    LifecycleEventType::WarmingFailed,
+                    trigger
+                        .into_iter()
+                        .chain([("error".to_string(), e.to_string())])
+                        .collect(),
                     Some(warmup_time.as_millis() as u64),
                 )
                 .await;
@@ -565,7 +568,76 @@ impl LifecycleManager {
         }
     }
 
-    /// 执行函数
+    /// 为函数选择实例：优先使用就绪或闲置的实例，其次恢复已暂停的实例，都没有时创建新实例
+    pub async fn acquire_instance(&self, function_metadata: FunctionMetadata) -> Result<String> {
+        let (available, suspended) = {
+            let candidates = || {
+                self.lifecycles
+                    .iter()
+                    .filter(|lifecycle| lifecycle.function_name == function_metadata.name)
+            };
+            let available = candidates()
+                .find(|lifecycle| {
+                    matches!(
+                        lifecycle.current_phase,
+                        LifecyclePhase::Ready | LifecyclePhase::Idle
+                    )
+                })
+                .map(|lifecycle| lifecycle.instance_id.clone());
+            let suspended = candidates()
+                .find(|lifecycle| lifecycle.current_phase == LifecyclePhase::Suspended)
+                .map(|lifecycle| lifecycle.instance_id.clone());
+            (available, suspended)
+        };
+
+        if let Some(instance_id) = available {
+            return Ok(instance_id);
+        }
+        if let Some(instance_id) = suspended {
+            self.resume_instance(&instance_id).await?;
+            return Ok(instance_id);
+        }
+        self.create_instance(function_metadata).await
+    }
+
+    /// 暂停实例（闲置策略为 `Suspend` 时由清理任务调用）
+    pub async fn suspend_instance(&self, instance_id: &str) -> Result<()> {
+        self.instance_manager.suspend_instance(instance_id).await?;
+        self.update_phase(instance_id, LifecyclePhase::Suspended)
+            .await?;
+        Ok(())
+    }
+
+    /// 恢复已暂停的实例，返回恢复耗时（并发恢复时只有一个请求实际加载）
+    pub async fn resume_instance(&self, instance_id: &str) -> Result<Duration> {
+        let resume_time = match self.instance_manager.resume_instance(instance_id).await {
+            Ok(resume_time) => resume_time,
+            Err(e) => {
+                self.update_phase(instance_id, LifecyclePhase::Error(e.to_string()))
+                    .await?;
+                return Err(e);
+            }
+        };
+
+        // 只有实际执行恢复的请求计入统计
+        if !resume_time.is_zero() {
+            let mut stats =
+                lock_order::acquire("lifecycle.statistics", self.statistics.write()).await;
+            stats.avg_resume_time_ms = (stats.avg_resume_time_ms * stats.total_resumes as f64
+                + resume_time.as_secs_f64() * 1000.0)
+                / (stats.total_resumes + 1) as f64;
+            stats.total_resumes += 1;
+        }
+        self.modify_lifecycle(instance_id, |lifecycle| {
+            if lifecycle.current_phase == LifecyclePhase::Suspended {
+                lifecycle.current_phase = LifecyclePhase::Ready;
+                lifecycle.last_activity = chrono::Utc::now();
+            }
+        });
+        Ok(resume_time)
+    }
+
+    /// 执行函数（已暂停的实例先恢复）
     pub async fn execute_instance(
         &self,
         instance_id: &str,
@@ -573,21 +645,18 @@ impl LifecycleManager {
     ) -> Result<InvokeResponse> {
         let start_time = Instant::now();
 
+        let suspended = self
+            .get_instance_lifecycle(instance_id)
+            .await
+            .is_some_and(|lifecycle| lifecycle.current_phase == LifecyclePhase::Suspended);
+        if suspended {
+            self.resume_instance(instance_id).await?;
+        }
+
         // 更新阶段为执行中
         self.update_phase(instance_id, LifecyclePhase::Executing)
             .await?;
 
-        // 记录执行开始事件
-        self.emit_lifecycle_event(
-            instance_id,
-            &self.get_function_name(instance_id).await?,
-            LifecycleEventType::ExecutionStarted,
-            None,
-            HashMap::new(),
-            None,
-        )
-        .await;
-
         // 执行函数
         let result = self
             .instance_manager
@@ -596,28 +665,14 @@ impl LifecycleManager {
         let execution_time = start_time.elapsed();
 
         // 更新执行统计
-        {
-            let mut lifecycles = self.lifecycles.write().await;
-            if let Some(lifecycle) = lifecycles.get_mut(instance_id) {
-                lifecycle.execution_count += 1;
-                lifecycle.total_execution_time += execution_time;
-                lifecycle.last_activity = Instant::now();
-            }
-        }
+        self.modify_lifecycle(instance_id, |lifecycle| {
+            lifecycle.execution_count += 1;
+            lifecycle.total_execution_time_ms += execution_time.as_millis() as u64;
+            lifecycle.last_activity = chrono::Utc::now();
+        });
 
         match result {
             Ok(response) => {
-                // 执行成功
-                self.emit_lifecycle_event(
-                    instance_id,
-                    &self.get_function_name(instance_id).await?,
-                    LifecycleEventType::ExecutionCompleted,
-                    None,
-                    HashMap::new(),
-                    Some(execution_time.as_millis() as u64),
-                )
-                .await;
-
                 // 更新阶段为就绪
                 self.update_phase(instance_id, LifecyclePhase::Ready)
                     .await?;
@@ -625,23 +680,6 @@ impl LifecycleManager {
                 Ok(response)
             }
             Err(e) => {
-                // 执行失败
-                self.emit_lifecycle_event(
-                    instance_id,
-                    &self.get_function_name(instance_id).await?,
-                    LifecycleEventType::ExecutionFailed,
-                    None,
-                    [(
-                        "error".to_string(),
-                        serde_json::Value::String(e.to_string()),
-                    )]
-                    .iter()
-                    .cloned()
-                    .collect(),
-                    Some(execution_time.as_millis() as u64),
-                )
-                .await;
-
                 // 更新阶段为错误
                 self.update_phase(instance_id, LifecyclePhase::Error(e.to_string()))
                     .await?;
@@ -658,8 +696,7 @@ impl LifecycleManager {
         self.emit_lifecycle_event(
             instance_id,
             &self.get_function_name(instance_id).await?,
-            LifecycleEventType::InstanceIdle,
-            None,
+            LifecycleEventType::Idle,
             HashMap::new(),
             None,
         )
@@ -682,7 +719,6 @@ impl LifecycleManager {
             instance_id,
             &self.get_function_name(instance_id).await?,
             LifecycleEventType::CleanupStarted,
-            None,
             HashMap::new(),
             None,
         )
@@ -698,7 +734,6 @@ impl LifecycleManager {
                     instance_id,
                     &self.get_function_name(instance_id).await?,
                     LifecycleEventType::CleanupCompleted,
-                    None,
                     HashMap::new(),
                     Some(cleanup_time.as_millis() as u64),
                 )
@@ -708,22 +743,8 @@ impl LifecycleManager {
                 self.update_phase(instance_id, LifecyclePhase::Terminated)
                     .await?;
 
-                // 记录终止事件
-                self.emit_lifecycle_event(
-                    instance_id,
-                    &self.get_function_name(instance_id).await?,
-                    LifecycleEventType::InstanceTerminated,
-                    None,
-                    HashMap::new(),
-                    None,
-                )
-                .await;
-
                 // 移除生命周期信息
-                {
-                    let mut lifecycles = self.lifecycles.write().await;
-                    lifecycles.remove(instance_id);
-                }
+                self.lifecycles.remove(instance_id);
 
                 tracing::info!(
                     "Instance terminated: {} ({}ms)",
@@ -737,15 +758,8 @@ impl LifecycleManager {
                 self.emit_lifecycle_event(
                     instance_id,
                     &self.get_function_name(instance_id).await?,
-                    LifecycleEventType::LifecycleError,
-                    None,
-                    [(
-                        "error".to_string(),
-                        serde_json::Value::String(e.to_string()),
-                    )]
-                    .iter()
-                    .cloned()
-                    .collect(),
+                    LifecycleEventType::Error,
+                    HashMap::from([("error".to_string(), e.to_string())]),
                     None,
                 )
                 .await;
@@ -758,15 +772,17 @@ impl LifecycleManager {
 
     /// 获取生命周期统计信息
     pub async fn get_statistics(&self) -> LifecycleStatistics {
-        let mut stats = self.statistics.read().await.clone();
+        let mut stats = lock_order::acquire("lifecycle.statistics", self.statistics.read())
+            .await
+            .clone();
 
         // 实时计算统计信息
-        let lifecycles = self.lifecycles.read().await;
-        stats.total_instances = lifecycles.len() as u64;
+        stats.total_instances = 0;
 
         // 统计各阶段实例数
         stats.phase_counts.clear();
-        for lifecycle in lifecycles.values() {
+        for lifecycle in self.lifecycles.iter() {
+            stats.total_instances += 1;
             let phase_name = match &lifecycle.current_phase {
                 LifecyclePhase::Creating => "creating",
                 LifecyclePhase::Warming => "warming",
@@ -788,39 +804,52 @@ impl LifecycleManager {
             + stats.phase_counts.get("ready").unwrap_or(&0);
         stats.idle_instances = *stats.phase_counts.get("idle").unwrap_or(&0);
         stats.warming_instances = *stats.phase_counts.get("warming").unwrap_or(&0);
+        stats.suspended_instances = *stats.phase_counts.get("suspended").unwrap_or(&0);
 
         stats
     }
 
     /// 获取实例生命周期信息
     pub async fn get_instance_lifecycle(&self, instance_id: &str) -> Option<InstanceLifecycle> {
-        let lifecycles = self.lifecycles.read().await;
-        lifecycles.get(instance_id).cloned()
+        self.lifecycles
+            .get(instance_id)
+            .map(|lifecycle| lifecycle.clone())
     }
 
     /// 获取所有实例生命周期信息
     pub async fn get_all_lifecycles(&self) -> Vec<InstanceLifecycle> {
-        let lifecycles = self.lifecycles.read().await;
-        lifecycles.values().cloned().collect()
+        self.lifecycles
+            .iter()
+            .map(|lifecycle| lifecycle.clone())
+            .collect()
     }
 
-    /// 获取生命周期事件历史
+    /// 获取生命周期事件历史（与实例管理器共用同一事件流）
     pub async fn get_event_history(&self, limit: Option<usize>) -> Vec<LifecycleEvent> {
-        let event_history = self.event_history.read().await;
-        if let Some(limit) = limit {
-            event_history.iter().rev().take(limit).cloned().collect()
-        } else {
-            event_history.clone()
-        }
+        let events = self.instance_manager.events();
+        let limit = match limit {
+            Some(limit) => limit,
+            None => events.len().await,
+        };
+        events.recent(limit).await
+    }
+
+    /// 原地修改生命周期信息，分片锁只在闭包执行期间持有；实例不存在时返回 `None`
+    fn modify_lifecycle<R>(
+        &self,
+        instance_id: &str,
+        f: impl FnOnce(&mut InstanceLifecycle) -> R,
+    ) -> Option<R> {
+        lock_order::get_mut("lifecycle.lifecycles", &self.lifecycles, instance_id)
+            .map(|mut lifecycle| f(&mut lifecycle))
     }
 
     /// 更新阶段
     async fn update_phase(&self, instance_id: &str, new_phase: LifecyclePhase) -> Result<()> {
-        let mut lifecycles = self.lifecycles.write().await;
-        if let Some(lifecycle) = lifecycles.get_mut(instance_id) {
+        self.modify_lifecycle(instance_id, |lifecycle| {
             let old_phase = lifecycle.current_phase.clone();
             lifecycle.current_phase = new_phase.clone();
-            lifecycle.last_activity = Instant::now();
+            lifecycle.last_activity = chrono::Utc::now();
 
             tracing::debug!(
                 "Instance {} phase changed: {:?} -> {:?}",
@@ -828,18 +857,16 @@ impl LifecycleManager {
                 old_phase,
                 new_phase
             );
-        }
+        });
         Ok(())
     }
 
     /// 获取函数名
     async fn get_function_name(&self, instance_id: &str) -> Result<String> {
-        let lifecycles = self.lifecycles.read().await;
-        if let Some(lifecycle) = lifecycles.get(instance_id) {
-            Ok(lifecycle.function_name.clone())
-        } else {
-            Err(anyhow::anyhow!("Instance not found: {}", instance_id))
-        }
+        self.lifecycles
+            .get(instance_id)
+            .map(|lifecycle| lifecycle.function_name.clone())
+            .ok_or_else(|| anyhow::anyhow!("Instance not found: {}", instance_id))
     }
 
     /// 发出生命周期事件
@@ -848,39 +875,16 @@ impl LifecycleManager {
         instance_id: &str,
         function_name: &str,
         event_type: LifecycleEventType,
-        phase_transition: Option<(LifecyclePhase, LifecyclePhase)>,
-        data: HashMap<String, serde_json::Value>,
+        metadata: HashMap<String, String>,
         duration_ms: Option<u64>,
     ) {
-        let event = LifecycleEvent {
-            event_id: scru128::new_string(),
-            instance_id: instance_id.to_string(),
-            function_name: function_name.to_string(),
-            event_type,
-            timestamp: chrono::Utc::now(),
-            phase_transition,
-            data,
-            duration_ms,
-        };
+        let description = format!("Lifecycle event: {event_type:?}");
+        let mut event =
+            InstanceLifecycleEvent::new(instance_id, function_name, event_type, description);
+        event.metadata = metadata;
+        event.duration_ms = duration_ms;
 
-        // 添加到实例事件历史
-        {
-            let mut lifecycles = self.lifecycles.write().await;
-            if let Some(lifecycle) = lifecycles.get_mut(instance_id) {
-                lifecycle.event_history.push(event.clone());
-            }
-        }
-
-        // 添加到全局事件历史
-        {
-            let mut event_history = self.event_history.write().await;
-            event_history.push(event);
-
-            // 限制历史记录数量
-            if event_history.len() > 10000 {
-                event_history.drain(0..1000);
-            }
-        }
+        self.instance_manager.events().publish(event).await;
     }
 
     /// 执行预热
@@ -920,35 +924,38 @@ impl LifecycleManager {
             loop {
                 interval.tick().await;
 
-                // 更新统计信息
-                let lifecycles_guard = lifecycles.read().await;
-                let mut stats = statistics.write().await;
-
-                // 计算各种统计指标
-                let total_instances = lifecycles_guard.len() as u64;
+                let now = chrono::Utc::now();
+                // 计算各种统计指标（先遍历生命周期表，再获取统计信息锁）
+                let mut total_instances = 0u64;
                 let mut total_execution_time = 0u64;
                 let mut total_warmup_time = 0u64;
                 let mut total_lifecycle_time = 0u64;
                 let mut successful_executions = 0u64;
                 let mut successful_warmups = 0u64;
 
-                for lifecycle in lifecycles_guard.values() {
+                for lifecycle in lifecycles.iter() {
+                    total_instances += 1;
                     if lifecycle.execution_count > 0 {
-                        total_execution_time += lifecycle.total_execution_time.as_millis() as u64;
+                        total_execution_time += lifecycle.total_execution_time_ms;
                         successful_executions += lifecycle.execution_count;
                     }
 
-                    if let Some(warmup_completed) = lifecycle.warmup_completed_at {
-                        if let Some(warmup_started) = lifecycle.warmup_started_at {
-                            total_warmup_time +=
-                                warmup_completed.duration_since(warmup_started).as_millis() as u64;
-                            successful_warmups += 1;
-                        }
+                    if let Some(warmup_completed) = lifecycle.warmup_completed_at
+                        && let Some(warmup_started) = lifecycle.warmup_started_at
+                    {
+                        total_warmup_time += (warmup_completed - warmup_started)
+                            .num_milliseconds()
+                            .max(0) as u64;
+                        successful_warmups += 1;
                     }
 
-                    total_lifecycle_time += lifecycle.created_at.elapsed().as_millis() as u64;
+                    total_lifecycle_time +=
+                        (now - lifecycle.created_at).num_milliseconds().max(0) as u64;
                 }
 
+                // 更新统计信息
+                let mut stats =
+                    lock_order::acquire("lifecycle.statistics", statistics.write()).await;
                 if total_instances > 0 {
                     stats.avg_lifecycle_time_ms =
                         total_lifecycle_time as f64 / total_instances as f64;
@@ -982,71 +989,233 @@ impl LifecycleManager {
         *handle = Some(monitoring_task);
     }
 
-    /// 启动清理任务
-    async fn start_cleanup(&self) {
-        let lifecycles = self.lifecycles.clone();
-        let cleanup_queue = self.cleanup_queue.clone();
-        let config = self.config.clone();
-        let instance_manager = self.instance_manager.clone();
-
-        let cleanup_task = tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(
-                config.cleanup_config.cleanup_interval_secs,
-            ));
-
+    /// 启动负载预热任务：每个监控间隔检查一次各函数的调用速率
+    async fn start_load_warmup(&self) {
+        let manager = self.clone();
+        let period = Duration::from_secs(
+            self.config
+                .monitoring_config
+                .monitoring_interval_secs
+                .max(1),
+        );
+
+        let load_warmup_task = tokio::spawn(async move {
+            let mut interval = interval(period);
             loop {
                 interval.tick().await;
+                manager.evaluate_load_warmup().await;
+            }
+        });
 
-                // 检查闲置实例
-                let idle_instances = {
-                    let lifecycles_guard = lifecycles.read().await;
-                    let now = Instant::now();
-                    let idle_timeout = Duration::from_secs(config.idle_config.idle_timeout_secs);
-
-                    lifecycles_guard
-                        .iter()
-                        .filter(|(_, lifecycle)| {
-                            matches!(
-                                lifecycle.current_phase,
-                                LifecyclePhase::Idle | LifecyclePhase::Ready
-                            ) && now.duration_since(lifecycle.last_activity) > idle_timeout
-                        })
-                        .map(|(id, _)| id.clone())
-                        .collect::<Vec<_>>()
-                };
+        let mut handle = self.load_warmup_handle.lock().await;
+        *handle = Some(load_warmup_task);
+    }
+
+    /// 按调用速率调整负载预热的实例
+    ///
+    /// 速率达到阈值的函数补足 `warmup_count` 个预热实例；速率回落后，预热实例标记为闲置，
+    /// 由清理任务在闲置超时后回收。
+    pub async fn evaluate_load_warmup(&self) {
+        let (Some(monitor), Some(registry)) = (&self.monitor, &self.registry) else {
+            return;
+        };
+        let warmup = &self.config.warmup_config;
+        let window = Duration::from_secs(warmup.load_window_secs.max(1));
+        let rates = monitor.recent_invocation_rates(window).await;
+
+        // 当前由负载预热创建的实例（按函数分组）
+        let mut prewarmed: HashMap<String, Vec<(String, LifecyclePhase)>> = HashMap::new();
+        {
+            for lifecycle in self.lifecycles.iter().filter(|lifecycle| {
+                lifecycle
+                    .metadata
+                    .get(WARMUP_TRIGGER_KEY)
+                    .map(String::as_str)
+                    == Some(WarmupTrigger::LoadBased.as_str())
+            }) {
+                prewarmed
+                    .entry(lifecycle.function_name.clone())
+                    .or_default()
+                    .push((
+                        lifecycle.instance_id.clone(),
+                        lifecycle.current_phase.clone(),
+                    ));
+            }
+        }
+
+        for (function_name, rate) in &rates {
+            if *rate < warmup.load_threshold_per_sec {
+                continue;
+            }
+            let instances = prewarmed.remove(function_name).unwrap_or_default();
 
-                // 添加到清理队列
-                {
-                    let mut cleanup_queue_guard = cleanup_queue.lock().await;
-                    cleanup_queue_guard.extend(idle_instances);
+            // 流量回升时，重新启用尚未回收的闲置实例
+            for (instance_id, phase) in &instances {
+                if *phase == LifecyclePhase::Idle {
+                    let _ = self.update_phase(instance_id, LifecyclePhase::Ready).await;
                 }
+            }
 
-                // 处理清理队列
-                let instances_to_cleanup = {
-                    let mut cleanup_queue_guard = cleanup_queue.lock().await;
-                    cleanup_queue_guard.drain(..).collect::<Vec<_>>()
-                };
+            let missing = (warmup.warmup_count as usize).saturating_sub(instances.len());
+            if missing == 0 {
+                continue;
+            }
+            let function = match registry.get(function_name).await {
+                Ok(function) => function,
+                Err(e) => {
+                    tracing::debug!("Skipping load-based warmup of {}: {}", function_name, e);
+                    continue;
+                }
+            };
+            if self.prewarm_failures.lock().await.get(function_name) == Some(&function.updated_at) {
+                continue;
+            }
 
-                for instance_id in instances_to_cleanup {
-                    if let Err(e) = instance_manager.stop_instance(&instance_id).await {
-                        tracing::warn!("Failed to cleanup instance {}: {}", instance_id, e);
-                    } else {
-                        tracing::info!("Cleaned up idle instance: {}", instance_id);
-                    }
+            let reason = format!(
+                "invocation rate {:.2}/s over {}s reached threshold {:.2}/s",
+                rate,
+                window.as_secs(),
+                warmup.load_threshold_per_sec
+            );
+            tracing::info!(
+                "Pre-warming {} instances of {}: {}",
+                missing,
+                function_name,
+                reason
+            );
+            for _ in 0..missing {
+                if let Err(e) = self.prewarm_instance(function.clone(), &reason).await {
+                    tracing::warn!("Load-based warmup of {} failed: {}", function_name, e);
+                    self.prewarm_failures
+                        .lock()
+                        .await
+                        .insert(function_name.clone(), function.updated_at);
+                    break;
+                }
+            }
+        }
+
+        // 速率回落的函数：预热实例转为闲置，等待清理任务回收
+        for instances in prewarmed.into_values() {
+            for (instance_id, phase) in instances {
+                let result = match phase {
+                    LifecyclePhase::Ready => self.mark_instance_idle(&instance_id).await,
+                    // 预热失败的实例不再保留
+                    LifecyclePhase::Error(_) => self.terminate_instance(&instance_id).await,
+                    _ => Ok(()),
+                };
+                if let Err(e) = result {
+                    tracing::warn!(
+                        "Failed to retire pre-warmed instance {}: {}",
+                        instance_id,
+                        e
+                    );
                 }
             }
+        }
+    }
+
+    /// 由负载触发创建并预热一个实例
+    async fn prewarm_instance(&self, function: FunctionMetadata, reason: &str) -> Result<()> {
+        let instance_id = self.create_instance(function).await?;
+        self.modify_lifecycle(&instance_id, |lifecycle| {
+            lifecycle.metadata.insert(
+                WARMUP_TRIGGER_KEY.to_string(),
+                WarmupTrigger::LoadBased.as_str().to_string(),
+            );
+        });
+
+        let trigger = HashMap::from([
+            (
+                "trigger".to_string(),
+                WarmupTrigger::LoadBased.as_str().to_string(),
+            ),
+            ("reason".to_string(), reason.to_string()),
+        ]);
+        self.warmup_instance_with_trigger(&instance_id, trigger)
+            .await
+    }
+
+    /// 启动清理任务
+    async fn start_cleanup(&self) {
+        let manager = self.clone();
+        let period = Duration::from_secs(self.config.cleanup_config.cleanup_interval_secs);
+
+        let cleanup_task = tokio::spawn(async move {
+            let mut interval = interval(period);
+            loop {
+                interval.tick().await;
+                manager.process_idle_instances().await;
+            }
         });
 
         let mut handle = self.cleanup_handle.lock().await;
         *handle = Some(cleanup_task);
     }
 
+    /// 按闲置策略处理闲置超时的实例：`Suspend` 暂停实例，其他策略终止实例
+    pub async fn process_idle_instances(&self) {
+        // 检查闲置实例
+        let idle_instances = {
+            let now = chrono::Utc::now();
+            let idle_timeout = chrono::Duration::seconds(
+                self.config
+                    .idle_config
+                    .idle_timeout_secs
+                    .min(i64::MAX as u64) as i64,
+            );
+
+            self.lifecycles
+                .iter()
+                .filter(|lifecycle| {
+                    matches!(
+                        lifecycle.current_phase,
+                        LifecyclePhase::Idle | LifecyclePhase::Ready
+                    ) && now - lifecycle.last_activity > idle_timeout
+                })
+                .map(|lifecycle| lifecycle.key().clone())
+                .collect::<Vec<_>>()
+        };
+
+        if matches!(self.config.idle_config.idle_strategy, IdleStrategy::Suspend) {
+            for instance_id in idle_instances {
+                match self.suspend_instance(&instance_id).await {
+                    Ok(()) => tracing::info!("Suspended idle instance: {}", instance_id),
+                    Err(e) => tracing::warn!("Failed to suspend instance {}: {}", instance_id, e),
+                }
+            }
+            return;
+        }
+
+        // 添加到清理队列
+        {
+            let mut cleanup_queue = self.cleanup_queue.lock().await;
+            cleanup_queue.extend(idle_instances);
+        }
+
+        // 处理清理队列
+        let instances_to_cleanup = {
+            let mut cleanup_queue = self.cleanup_queue.lock().await;
+            cleanup_queue.drain(..).collect::<Vec<_>>()
+        };
+
+        for instance_id in instances_to_cleanup {
+            if let Err(e) = self.instance_manager.stop_instance(&instance_id).await {
+                tracing::warn!("Failed to cleanup instance {}: {}", instance_id, e);
+            } else {
+                self.lifecycles.remove(&instance_id);
+                tracing::info!("Cleaned up idle instance: {}", instance_id);
+            }
+        }
+    }
+
     /// 清理所有实例
     async fn cleanup_all_instances(&self) -> Result<()> {
-        let instance_ids = {
-            let lifecycles = self.lifecycles.read().await;
-            lifecycles.keys().cloned().collect::<Vec<_>>()
-        };
+        let instance_ids = self
+            .lifecycles
+            .iter()
+            .map(|lifecycle| lifecycle.key().clone())
+            .collect::<Vec<_>>();
 
         for instance_id in instance_ids {
             if let Err(e) = self.terminate_instance(&instance_id).await {
@@ -1065,6 +1234,8 @@ impl LifecycleManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::functions::compilation::HandlerMode;
+    use crate::functions::priority::Priority;
     use crate::runtime::compiler::RustCompiler;
     use crate::runtime::resource::ResourceManager;
     use crate::runtime::sandbox::SandboxExecutor;
@@ -1129,6 +1300,42 @@ mod tests {
             dependencies: vec![],
             parameters: vec![],
             return_type: "()".to_string(),
+            retry_policy: None,
+            idempotent: true,
+            labels: std::collections::HashMap::new(),
+            webhooks: None,
+            priority: Priority::Normal,
+            package: None,
+            http_response: false,
+            input_template: None,
+            revision: 0,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: false,
+            coerce_output: false,
+            strict_return_type: false,
+            log_redaction: Vec::new(),
+            circuit_breaker: true,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: false,
+            scratch_limit_mb: None,
+            persist_scratch: false,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: Vec::new(),
+            execution_hint: Default::default(),
+            mirror: None,
+            canary: None,
+            slo: None,
+            documentation: None,
+            header_fields: Default::default(),
+            http_routes: Vec::new(),
+            deprecation: None,
+            parameters_inferred: false,
+            return_type_inferred: false,
+            egress: None,
+            debug_capture: Default::default(),
         };
 
         // 创建实例
@@ -1151,4 +1358,234 @@ mod tests {
         assert_eq!(stats.total_instances, 1);
         assert_eq!(stats.active_instances, 1);
     }
+
+    #[tokio::test]
+    async fn test_idle_suspend_then_resume_without_recompiling() {
+        use crate::runtime::compiler::{CompiledFunction, CompilerConfig};
+        use crate::runtime::sandbox::SandboxConfig;
+
+        let temp_dir = TempDir::new().unwrap();
+        let compiler = Arc::new(
+            RustCompiler::new(CompilerConfig {
+                cache_dir: temp_dir.path().to_path_buf(),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        // 预置编译产物，实例创建和恢复都不触发 cargo
+        let function = FunctionMetadata::new("sleepy".to_string(), "fn f() {}".to_string());
+        let key = RustCompiler::compile_key(&function);
+        let library_path = temp_dir.path().join("libsleepy.so");
+        std::fs::write(&library_path, b"lib").unwrap();
+        compiler
+            .cache_compiled_function(
+                &key,
+                CompiledFunction {
+                    metadata: function.clone(),
+                    library_path: library_path.clone(),
+                    compiled_at: chrono::Utc::now(),
+                    source_hash: key.1.clone(),
+                    compile_time_ms: 0,
+                    handler_mode: HandlerMode::RawJson,
+                },
+            )
+            .await;
+        // 不启用任何隔离方式：执行在沙箱入口立即返回，用于确认实例带着编译产物到达沙箱
+        let sandbox = Arc::new(
+            SandboxExecutor::new(SandboxConfig {
+                enable_process_isolation: false,
+                temp_root: temp_dir.path().join("sandbox"),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let instance_manager = Arc::new(InstanceManager::new(
+            compiler.clone(),
+            sandbox,
+            Arc::new(ResourceManager::new()),
+            Some(crate::runtime::instance::InstanceConfig {
+                enable_auto_warm: false,
+                ..Default::default()
+            }),
+        ));
+
+        let mut config = LifecycleConfig::default();
+        config.idle_config.idle_strategy = IdleStrategy::Suspend;
+        config.idle_config.idle_timeout_secs = 0;
+        let manager = LifecycleManager::new(config, instance_manager.clone());
+
+        let instance_id = manager.create_instance(function.clone()).await.unwrap();
+        let compiled_at = instance_manager
+            .get_instance(&instance_id)
+            .await
+            .unwrap()
+            .compiled_function
+            .unwrap()
+            .compiled_at;
+        sleep(Duration::from_millis(10)).await;
+
+        // 闲置 -> 暂停：释放编译产物句柄，保留实例
+        manager.process_idle_instances().await;
+        let instance = instance_manager.get_instance(&instance_id).await.unwrap();
+        assert_eq!(instance.state, InstanceState::Paused);
+        assert!(instance.compiled_function.is_none());
+        assert_eq!(manager.get_statistics().await.suspended_instances, 1);
+        assert_eq!(
+            instance_manager
+                .get_instance_stats()
+                .await
+                .suspended_instances,
+            1
+        );
+
+        // 两个请求同时到达：恢复同一个实例，且只加载一次
+        let (a, b) = tokio::join!(
+            manager.acquire_instance(function.clone()),
+            manager.acquire_instance(function.clone())
+        );
+        assert_eq!(a.unwrap(), instance_id);
+        assert_eq!(b.unwrap(), instance_id);
+        assert_eq!(
+            instance_manager.get_function_instances("sleepy").await,
+            vec![instance_id.clone()]
+        );
+        let stats = instance_manager.get_instance_stats().await;
+        assert_eq!(
+            (
+                stats.suspended_instances,
+                stats.total_suspensions,
+                stats.total_resumes
+            ),
+            (0, 1, 1)
+        );
+        assert_eq!(manager.get_statistics().await.total_resumes, 1);
+
+        // 恢复后可以执行，编译产物未重新编译
+        let response = manager
+            .execute_instance(
+                &instance_id,
+                &InvokeRequest {
+                    input: serde_json::json!({}),
+                    retry_policy: None,
+                    priority: None,
+                    idempotency_key: None,
+                    session_id: None,
+                    affinity_key: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(
+            response.output["error"]
+                .as_str()
+                .unwrap()
+                .contains("No isolation method enabled")
+        );
+        let instance = instance_manager.get_instance(&instance_id).await.unwrap();
+        let compiled = instance.compiled_function.unwrap();
+        assert_eq!(compiled.compiled_at, compiled_at);
+        assert_eq!(compiled.library_path, library_path);
+        assert_eq!(compiler.compiled_count().await, 1);
+        assert_eq!(stats.recompiled_resumes, 0);
+        let resumed = manager
+            .get_event_history(None)
+            .await
+            .into_iter()
+            .find(|event| event.event_type == LifecycleEventType::Resumed)
+            .unwrap();
+        assert_eq!(resumed.metadata["recompiled"], "false");
+        assert!(resumed.duration_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_load_based_warmup_on_traffic_spike() {
+        use crate::functions::registry::FunctionRegistry;
+        use crate::runtime::monitor::{ExecutionResult, PerformanceMonitor};
+
+        let temp_dir = TempDir::new().unwrap();
+        // 编译会立即失败，预热事件仍然带有触发原因
+        let compiler = Arc::new(
+            RustCompiler::new(crate::runtime::compiler::CompilerConfig {
+                cache_dir: temp_dir.path().to_path_buf(),
+                rustc_path: Some("/bin/false".into()),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let instance_manager = Arc::new(InstanceManager::new(
+            compiler,
+            Arc::new(SandboxExecutor::new(Default::default()).unwrap()),
+            Arc::new(ResourceManager::new()),
+            Some(crate::runtime::instance::InstanceConfig {
+                enable_auto_warm: false,
+                ..Default::default()
+            }),
+        ));
+        let monitor = Arc::new(PerformanceMonitor::new());
+        let registry = Arc::new(FunctionRegistry::new());
+        registry
+            .register(FunctionMetadata::new("hot".to_string(), String::new()))
+            .await
+            .unwrap();
+
+        let mut config = LifecycleConfig::default();
+        config.warmup_config.warmup_trigger = WarmupTrigger::LoadBased;
+        config.warmup_config.warmup_count = 1;
+        config.warmup_config.warmup_interval_ms = 10;
+        config.warmup_config.load_threshold_per_sec = 5.0;
+        config.warmup_config.load_window_secs = 2;
+        config.monitoring_config.monitoring_interval_secs = 1;
+        let manager =
+            LifecycleManager::with_load_source(config, instance_manager, monitor.clone(), registry);
+        manager.start().await.unwrap();
+
+        // 模拟流量突增：2 秒窗口内 20 次调用（10 次/秒）
+        for i in 0..20 {
+            monitor
+                .record_execution(ExecutionResult {
+                    function_name: "hot".to_string(),
+                    duration: Duration::from_millis(if i == 0 { 50 } else { 5 }),
+                    success: true,
+                    memory_usage: 0,
+                    error_message: None,
+                    error_origin: None,
+                    attempt: 1,
+                    cold_start: i == 0,
+                    chaos_injected: false,
+                    shadow: false,
+                    mirror: false,
+                    canary: false,
+                    test: false,
+                    console: false,
+                    execution_path: None,
+                })
+                .await
+                .unwrap();
+        }
+        let cold = monitor.cold_start_stats().await["hot"].clone();
+        assert_eq!((cold.cold_starts, cold.warm_calls), (1, 19));
+        assert_eq!(cold.cold_p50_ms, Some(50.0));
+        assert_eq!(cold.warm_p95_ms, Some(5.0));
+
+        // 一个监控间隔内（留出调度余量）应出现带触发原因的预热事件
+        let spike = Instant::now();
+        let event = loop {
+            let events = manager.get_event_history(None).await;
+            if let Some(event) = events.into_iter().find(|event| {
+                event.event_type == LifecycleEventType::WarmingStarted
+                    && event.function_name == "hot"
+            }) {
+                break event;
+            }
+            assert!(
+                spike.elapsed() < Duration::from_secs(3),
+                "no warmup event within one monitoring interval"
+            );
+            sleep(Duration::from_millis(20)).await;
+        };
+        assert_eq!(event.metadata["trigger"], "load_based");
+        assert!(event.metadata["reason"].contains("threshold"));
+
+        manager.stop().await.unwrap();
+    }
 }
@@ -0,0 +1,411 @@
+//! 全进程的调用并发上限
+//!
+//! 所有调度器共享同一个 [`InvocationLimiter`]：调用在查到函数后、编译和启动进程之前获取名额，
+//! 超出上限的调用进入有界队列等待。队列已满，或排队超过 `queue_timeout_ms`（不超过函数自身的截止时间）时
+//! 以 `Overloaded` 拒绝，网关返回 503 和 `Retry-After`。
+//!
+//! 重新加载配置时在原有的信号量上调整名额：提高上限时补发名额，降低上限时先收回空闲名额，
+//! 不足的部分在正在执行的调用归还时收回，因此调整期间同时执行的调用数不会超过新旧上限中较大者，
+//! 新调用只在执行数降到新上限以下后才被放行。
+
+use crate::functions::{FluxError, Result};
+use crate::runtime::series::LatencyHistogram;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 全局调用并发配置（`[limits]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InvocationLimitConfig {
+    /// 全进程同时执行的调用数上限（所有调度器合计，与函数和命名空间的限制独立）
+    pub max_concurrent_invocations: usize,
+    /// 等待名额的调用数上限，超出时直接拒绝
+    pub queue_capacity: usize,
+    /// 单个调用排队等待的最长时间（毫秒），实际不超过函数的截止时间
+    pub queue_timeout_ms: u64,
+    /// 拒绝时建议客户端的重试间隔（秒）
+    pub retry_after_secs: u64,
+}
+
+impl Default for InvocationLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_invocations: 256,
+            queue_capacity: 1024,
+            queue_timeout_ms: 30_000,
+            retry_after_secs: 1,
+        }
+    }
+}
+
+/// 全局调用并发统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InvocationLimitStats {
+    pub max_concurrent: usize,
+    pub running: usize,
+    /// 启动以来同时执行的调用数峰值
+    pub peak_running: usize,
+    /// 当前排队数
+    pub queued: usize,
+    pub queue_capacity: usize,
+    /// 已获得名额的调用数（包括无需排队的）
+    pub admitted: u64,
+    /// 队列已满被拒绝的调用数
+    pub shed_queue_full: u64,
+    /// 排队超时被拒绝的调用数
+    pub shed_timeout: u64,
+    /// 已获得名额的调用的排队时间分位数（毫秒），没有调用时为 `None`
+    pub wait_p50_ms: Option<f64>,
+    pub wait_p95_ms: Option<f64>,
+    pub wait_p99_ms: Option<f64>,
+}
+
+/// 已获得的调用名额，释放时归还（降低上限后尚未收回的名额在释放时收回）
+#[derive(Debug)]
+pub struct InvocationPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    slots: Arc<Slots>,
+    waited: Duration,
+}
+
+impl InvocationPermit {
+    /// 在队列中等待的时间
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+}
+
+impl Drop for InvocationPermit {
+    fn drop(&mut self) {
+        self.slots.running.fetch_sub(1, Ordering::SeqCst);
+        let reclaimed = self
+            .slots
+            .debt
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |debt| {
+                debt.checked_sub(1)
+            })
+            .is_ok();
+        if let Some(permit) = self.permit.take()
+            && reclaimed
+        {
+            permit.forget();
+        }
+    }
+}
+
+/// 名额的信号量和计数，与已发出的名额共享
+#[derive(Debug)]
+struct Slots {
+    semaphore: Arc<Semaphore>,
+    /// 当前上限（信号量中的名额总数减去待收回的名额）
+    capacity: StdMutex<usize>,
+    /// 降低上限时仍被调用占用、需要在归还时收回的名额数
+    debt: AtomicUsize,
+    running: AtomicUsize,
+}
+
+/// 全进程的调用并发上限和等待队列
+#[derive(Debug)]
+pub struct InvocationLimiter {
+    config: StdMutex<InvocationLimitConfig>,
+    slots: Arc<Slots>,
+    queued: AtomicUsize,
+    peak: AtomicUsize,
+    admitted: AtomicU64,
+    shed_full: AtomicU64,
+    shed_timeout: AtomicU64,
+    waits: StdMutex<LatencyHistogram>,
+}
+
+impl Default for InvocationLimiter {
+    fn default() -> Self {
+        Self::new(InvocationLimitConfig::default())
+    }
+}
+
+impl InvocationLimiter {
+    pub fn new(config: InvocationLimitConfig) -> Self {
+        let permits = config.max_concurrent_invocations.max(1);
+        Self {
+            config: StdMutex::new(config),
+            slots: Arc::new(Slots {
+                semaphore: Arc::new(Semaphore::new(permits)),
+                capacity: StdMutex::new(permits),
+                debt: AtomicUsize::new(0),
+                running: AtomicUsize::new(0),
+            }),
+            queued: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            admitted: AtomicU64::new(0),
+            shed_full: AtomicU64::new(0),
+            shed_timeout: AtomicU64::new(0),
+            waits: StdMutex::default(),
+        }
+    }
+
+    /// 应用配置（启动和重新加载时调用）；并发上限变化时在原信号量上增减名额，
+    /// 正在执行和排队的调用不受影响
+    pub fn configure(&self, config: &InvocationLimitConfig) {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        let target = config.max_concurrent_invocations.max(1);
+        let slots = &self.slots;
+        let mut capacity = slots.capacity.lock().unwrap_or_else(|e| e.into_inner());
+        if target > *capacity {
+            // 先抵消尚未收回的名额，其余补发
+            let grow = target - *capacity;
+            let mut cancelled = 0;
+            let _ = slots
+                .debt
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |debt| {
+                    cancelled = debt.min(grow);
+                    Some(debt - cancelled)
+                });
+            slots.semaphore.add_permits(grow - cancelled);
+        } else if target < *capacity {
+            let shrink = *capacity - target;
+            let forgotten = slots.semaphore.forget_permits(shrink);
+            slots.debt.fetch_add(shrink - forgotten, Ordering::SeqCst);
+        }
+        *capacity = target;
+    }
+
+    pub fn config(&self) -> InvocationLimitConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 获取调用名额；没有空闲名额时排队，最多等到 `deadline`（函数的截止时间）
+    /// 或配置的排队超时，队列已满或等待超时时返回 `Overloaded`
+    pub async fn acquire(&self, deadline: Instant) -> Result<InvocationPermit> {
+        let config = self.config();
+        let semaphore = self.slots.semaphore.clone();
+        if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+            return Ok(self.admit(permit, Duration::ZERO));
+        }
+
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst);
+        if queued >= config.queue_capacity {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            self.shed_full.fetch_add(1, Ordering::Relaxed);
+            return Err(Self::overloaded(
+                &config,
+                format!(
+                    "{queued} invocations are already waiting (capacity {})",
+                    config.queue_capacity
+                ),
+            ));
+        }
+
+        let start = Instant::now();
+        let wait = Duration::from_millis(config.queue_timeout_ms)
+            .min(deadline.saturating_duration_since(start));
+        let result = tokio::time::timeout(wait, semaphore.clone().acquire_owned()).await;
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+
+        match result {
+            Ok(Ok(permit)) => Ok(self.admit(permit, start.elapsed())),
+            Ok(Err(_)) => Err(FluxError::Runtime(
+                "Invocation limiter is closed".to_string(),
+            )),
+            Err(_) => {
+                self.shed_timeout.fetch_add(1, Ordering::Relaxed);
+                Err(Self::overloaded(
+                    &config,
+                    format!(
+                        "no invocation slot became free within {}ms",
+                        start.elapsed().as_millis()
+                    ),
+                ))
+            }
+        }
+    }
+
+    fn admit(&self, permit: OwnedSemaphorePermit, waited: Duration) -> InvocationPermit {
+        let running = self.slots.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(running, Ordering::Relaxed);
+        self.admitted.fetch_add(1, Ordering::Relaxed);
+        self.waits
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(waited);
+        InvocationPermit {
+            permit: Some(permit),
+            slots: self.slots.clone(),
+            waited,
+        }
+    }
+
+    fn overloaded(config: &InvocationLimitConfig, reason: String) -> FluxError {
+        FluxError::Overloaded {
+            reason,
+            retry_after: Duration::from_secs(config.retry_after_secs.max(1)),
+        }
+    }
+
+    pub fn stats(&self) -> InvocationLimitStats {
+        let config = self.config();
+        let max_concurrent = config.max_concurrent_invocations.max(1);
+        let waits = self.waits.lock().unwrap_or_else(|e| e.into_inner());
+        InvocationLimitStats {
+            max_concurrent,
+            running: self.slots.running.load(Ordering::SeqCst),
+            peak_running: self.peak.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::SeqCst),
+            queue_capacity: config.queue_capacity,
+            admitted: self.admitted.load(Ordering::Relaxed),
+            shed_queue_full: self.shed_full.load(Ordering::Relaxed),
+            shed_timeout: self.shed_timeout.load(Ordering::Relaxed),
+            wait_p50_ms: waits.quantile(0.50),
+            wait_p95_ms: waits.quantile(0.95),
+            wait_p99_ms: waits.quantile(0.99),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(max: usize, queue: usize, timeout_ms: u64) -> Arc<InvocationLimiter> {
+        Arc::new(InvocationLimiter::new(InvocationLimitConfig {
+            max_concurrent_invocations: max,
+            queue_capacity: queue,
+            queue_timeout_ms: timeout_ms,
+            retry_after_secs: 2,
+        }))
+    }
+
+    fn far() -> Instant {
+        Instant::now() + Duration::from_secs(60)
+    }
+
+    #[tokio::test]
+    async fn test_queue_full_and_timeout_are_shed() {
+        let limiter = limiter(1, 1, 50);
+        let running = limiter.acquire(far()).await.unwrap();
+
+        // 排队的调用在超时后被拒绝，队列已满时直接拒绝
+        let waiting = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move { limiter.acquire(far()).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(limiter.stats().queued, 1);
+        let err = limiter.acquire(far()).await.unwrap_err();
+        assert!(
+            matches!(&err, FluxError::Overloaded { retry_after, .. } if *retry_after == Duration::from_secs(2)),
+            "{err}"
+        );
+        let err = waiting.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("no invocation slot"), "{err}");
+
+        // 函数的截止时间早于排队超时时按截止时间放弃
+        let started = Instant::now();
+        let limiter = self::limiter(1, 1, 60_000);
+        let _running = limiter.acquire(far()).await.unwrap();
+        let err = limiter
+            .acquire(Instant::now() + Duration::from_millis(30))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FluxError::Overloaded { .. }));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        drop(running);
+    }
+
+    #[tokio::test]
+    async fn test_waiters_are_admitted_and_counted() {
+        let limiter = limiter(2, 100, 10_000);
+        let mut tasks = Vec::new();
+        for _ in 0..10 {
+            let limiter = limiter.clone();
+            tasks.push(tokio::spawn(async move {
+                let permit = limiter.acquire(far()).await.unwrap();
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                permit.waited()
+            }));
+        }
+        let mut waited = Vec::new();
+        for task in tasks {
+            waited.push(task.await.unwrap());
+        }
+        assert!(waited.iter().any(|wait| *wait >= Duration::from_millis(5)));
+
+        let stats = limiter.stats();
+        assert_eq!(stats.admitted, 10);
+        assert_eq!(stats.running, 0);
+        assert_eq!(stats.queued, 0);
+        assert_eq!(stats.peak_running, 2);
+        assert_eq!(stats.shed_queue_full + stats.shed_timeout, 0);
+        assert!(stats.wait_p99_ms.unwrap() >= 5.0, "{stats:?}");
+    }
+
+    #[tokio::test]
+    async fn test_reload_resizes_without_exceeding_the_new_cap() {
+        let limiter = limiter(4, 100, 10_000);
+        let mut held = Vec::new();
+        for _ in 0..4 {
+            held.push(limiter.acquire(far()).await.unwrap());
+        }
+
+        // 只改其他字段时不影响名额
+        let mut config = limiter.config();
+        config.retry_after_secs = 5;
+        limiter.configure(&config);
+        assert_eq!(limiter.stats().running, 4);
+        assert_eq!(limiter.slots.semaphore.available_permits(), 0);
+
+        // 降到 2：正在执行的调用继续，归还的名额先被收回
+        config.max_concurrent_invocations = 2;
+        limiter.configure(&config);
+        let peak = Arc::new(AtomicUsize::new(0));
+        let mut tasks = Vec::new();
+        for _ in 0..8 {
+            let limiter = limiter.clone();
+            let peak = peak.clone();
+            tasks.push(tokio::spawn(async move {
+                let permit = limiter.acquire(far()).await.unwrap();
+                peak.fetch_max(limiter.stats().running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                drop(permit);
+            }));
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        held.pop();
+        held.pop();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // 两个名额已收回，执行数仍为 2，没有新调用被放行
+        assert_eq!(limiter.stats().running, 2);
+        assert_eq!(limiter.stats().queued, 8);
+        held.clear();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 2, "{peak:?}");
+        assert_eq!(limiter.stats().running, 0);
+        assert_eq!(limiter.slots.semaphore.available_permits(), 2);
+
+        // 收回未完成时提高上限，先抵消待收回的名额
+        let mut held = Vec::new();
+        for _ in 0..2 {
+            held.push(limiter.acquire(far()).await.unwrap());
+        }
+        config.max_concurrent_invocations = 1;
+        limiter.configure(&config);
+        config.max_concurrent_invocations = 3;
+        limiter.configure(&config);
+        held.push(limiter.acquire(far()).await.unwrap());
+        assert!(
+            limiter
+                .acquire(Instant::now() + Duration::from_millis(20))
+                .await
+                .is_err()
+        );
+        held.clear();
+        assert_eq!(limiter.slots.semaphore.available_permits(), 3);
+    }
+}
@@ -0,0 +1,367 @@
+use super::history::{JsonChange, diff_json};
+use super::{ScheduleOptions, SimpleScheduler};
+use crate::functions::mirror::MirrorConfig;
+use crate::functions::priority::Priority;
+use crate::functions::redaction::{self, Redactor};
+use crate::functions::{FunctionMetadata, InvokeRequest, InvokeResponse};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex as StdMutex;
+use utoipa::ToSchema;
+
+/// 每个函数保留的最近不一致记录数
+pub const MISMATCHES_PER_FUNCTION: usize = 100;
+
+/// 一次主调用与镜像调用的不一致
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MirrorMismatch {
+    /// 主调用 ID
+    pub invocation_id: String,
+    pub mirror_invocation_id: String,
+    pub target: String,
+    /// 原始输入 JSON 的 MD5，用于关联同一输入的多次不一致
+    pub input_hash: String,
+    /// 按主函数的脱敏规则处理后的输入，超出日志载荷上限时缺省
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub input: Option<Value>,
+    pub input_truncated: bool,
+    /// 主调用输出到镜像输出的差异（镜像调用失败时为空）
+    pub changes: Vec<JsonChange>,
+    /// 镜像调用的调度错误（目标不存在、队列已满等）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirror_error: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// 函数的镜像计数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct MirrorStats {
+    /// 发出的镜像调用数
+    pub mirrored: u64,
+    /// 输出一致的次数（未开启比较时不计）
+    pub matched: u64,
+    /// 输出不一致的次数
+    pub mismatched: u64,
+    /// 镜像调用未能执行的次数
+    pub failed: u64,
+}
+
+/// 函数的镜像配置、计数和最近的不一致记录（最近的在前）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MirrorReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<MirrorConfig>,
+    pub stats: MirrorStats,
+    pub mismatches: Vec<MirrorMismatch>,
+}
+
+/// 一次镜像调用的结果
+#[derive(Debug)]
+pub enum MirrorOutcome {
+    /// 未开启比较，镜像调用已执行
+    Completed,
+    Matched,
+    Mismatched(MirrorMismatch),
+    /// 镜像调用未能执行；开启比较时附带不一致记录
+    Failed(Option<MirrorMismatch>),
+}
+
+#[derive(Debug, Default)]
+struct FunctionMirrorLog {
+    stats: MirrorStats,
+    mismatches: VecDeque<MirrorMismatch>,
+}
+
+/// 按主函数记录镜像结果
+#[derive(Debug, Default)]
+pub struct MirrorRecorder {
+    functions: StdMutex<HashMap<String, FunctionMirrorLog>>,
+}
+
+impl MirrorRecorder {
+    pub fn record(&self, function: &str, outcome: MirrorOutcome) {
+        let mut functions = self.functions.lock().unwrap_or_else(|e| e.into_inner());
+        let log = functions.entry(function.to_string()).or_default();
+        log.stats.mirrored += 1;
+        let mismatch = match outcome {
+            MirrorOutcome::Completed => None,
+            MirrorOutcome::Matched => {
+                log.stats.matched += 1;
+                None
+            }
+            MirrorOutcome::Mismatched(mismatch) => {
+                log.stats.mismatched += 1;
+                Some(mismatch)
+            }
+            MirrorOutcome::Failed(mismatch) => {
+                log.stats.failed += 1;
+                mismatch
+            }
+        };
+        if let Some(mismatch) = mismatch {
+            if log.mismatches.len() >= MISMATCHES_PER_FUNCTION {
+                log.mismatches.pop_front();
+            }
+            log.mismatches.push_back(mismatch);
+        }
+    }
+
+    /// 函数的计数和不一致记录（最近的在前）
+    pub fn report(&self, function: &str, config: Option<MirrorConfig>) -> MirrorReport {
+        let functions = self.functions.lock().unwrap_or_else(|e| e.into_inner());
+        let log = functions.get(function);
+        MirrorReport {
+            config,
+            stats: log.map(|log| log.stats.clone()).unwrap_or_default(),
+            mismatches: log
+                .map(|log| log.mismatches.iter().rev().cloned().collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl SimpleScheduler {
+    /// 在后台以主调用的原始输入调用镜像目标；镜像的失败和超时只记录，不影响主调用
+    pub(super) fn spawn_mirror(
+        &self,
+        function: &FunctionMetadata,
+        mirror: MirrorConfig,
+        invocation_id: &str,
+        input: Value,
+        primary: &InvokeResponse,
+    ) {
+        tokio::spawn(self.clone().mirror_invocation(
+            function.clone(),
+            mirror,
+            invocation_id.to_string(),
+            input,
+            primary.output.clone(),
+        ));
+    }
+
+    /// 镜像调用再次经过调度器，返回装箱的 future 以打断 `schedule_once` 的递归类型
+    fn mirror_invocation(
+        self,
+        function: FunctionMetadata,
+        mirror: MirrorConfig,
+        invocation_id: String,
+        input: Value,
+        primary_output: Value,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(async move {
+            let mirror_invocation_id = format!("{invocation_id}-mirror");
+            let request = InvokeRequest {
+                input: input.clone(),
+                retry_policy: None,
+                priority: Some(Priority::Low),
+                idempotency_key: None,
+                session_id: None,
+                affinity_key: None,
+            };
+            let options = ScheduleOptions {
+                invocation_id: Some(mirror_invocation_id.clone()),
+                mirror_of: Some(invocation_id.clone()),
+                ..Default::default()
+            };
+            let result = self.schedule_with(&mirror.target, request, options).await;
+
+            let redactor = Redactor::for_function(&function).with_secrets_from(&input);
+            let mismatch = |changes: Vec<JsonChange>, mirror_error: Option<String>| {
+                let raw = serde_json::to_vec(&input).unwrap_or_default();
+                let input_truncated = raw.len() > redaction::max_payload_bytes();
+                MirrorMismatch {
+                    invocation_id: invocation_id.clone(),
+                    mirror_invocation_id: mirror_invocation_id.clone(),
+                    target: mirror.target.clone(),
+                    input_hash: format!("{:x}", md5::compute(&raw)),
+                    input: (!input_truncated).then(|| redactor.redact(&input)),
+                    input_truncated,
+                    changes,
+                    mirror_error,
+                    recorded_at: Utc::now(),
+                }
+            };
+            let outcome = match result {
+                Err(e) => {
+                    tracing::warn!(
+                        "Mirror of {} to {} failed: {}",
+                        function.name,
+                        mirror.target,
+                        e
+                    );
+                    let error = redactor.scrub(&e.to_string());
+                    MirrorOutcome::Failed(mirror.compare.then(|| mismatch(Vec::new(), Some(error))))
+                }
+                Ok(_) if !mirror.compare => MirrorOutcome::Completed,
+                Ok(response) => {
+                    let changes = diff_json(
+                        &redactor.redact(&primary_output),
+                        &redactor.redact(&response.output),
+                    );
+                    if changes.is_empty() {
+                        MirrorOutcome::Matched
+                    } else {
+                        MirrorOutcome::Mismatched(mismatch(changes, None))
+                    }
+                }
+            };
+            self.mirrors().record(&function.name, outcome);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::Scheduler;
+    use serde_json::json;
+    use std::time::Duration;
+
+    async fn wait_for_mirrors(scheduler: &SimpleScheduler, function: &str, mirrored: u64) {
+        for _ in 0..100 {
+            if scheduler.mirrors().report(function, None).stats.mirrored >= mirrored {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("mirror invocations of {function} did not complete");
+    }
+
+    fn invoke(input: Value) -> InvokeRequest {
+        InvokeRequest {
+            input,
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mirror_records_output_mismatches() {
+        let scheduler = SimpleScheduler::new();
+        let mut primary =
+            FunctionMetadata::new("parse-invoice".to_string(), "return a + b".to_string());
+        primary.log_redaction = vec!["token".to_string()];
+        scheduler.register_function(primary).await.unwrap();
+        let shadow =
+            FunctionMetadata::new("parse-invoice-v2".to_string(), "return a * b".to_string());
+        scheduler.register_function(shadow).await.unwrap();
+        let mirror = MirrorConfig {
+            target: "parse-invoice-v2".to_string(),
+            sample_rate: 1.0,
+            compare: true,
+        };
+        scheduler
+            .set_mirror("parse-invoice", Some(mirror))
+            .await
+            .unwrap();
+
+        // 调用方得到的始终是主函数的结果
+        let response = scheduler
+            .schedule(
+                "parse-invoice",
+                invoke(json!({"a": 2, "b": 2, "token": "s3cret"})),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.output["result"], 4);
+        let response = scheduler
+            .schedule("parse-invoice", invoke(json!({"a": 1, "b": 2})))
+            .await
+            .unwrap();
+        assert_eq!(response.output["result"], 3);
+        wait_for_mirrors(&scheduler, "parse-invoice", 2).await;
+
+        let report = scheduler.mirrors().report("parse-invoice", None);
+        assert_eq!(
+            report.stats,
+            MirrorStats {
+                mirrored: 2,
+                matched: 1,
+                mismatched: 1,
+                failed: 0
+            }
+        );
+        let mismatch = &report.mismatches[0];
+        assert_eq!(mismatch.target, "parse-invoice-v2");
+        assert_eq!(mismatch.changes.len(), 1);
+        assert_eq!(mismatch.changes[0].path, "/result");
+        assert_eq!(mismatch.input.as_ref().unwrap(), &json!({"a": 1, "b": 2}));
+
+        // 镜像执行不进入 SLO 统计，只计入镜像次数
+        let monitor = scheduler.runtime().monitor();
+        let stats = monitor.get_global_stats().await;
+        assert_eq!(stats.total_requests, 2);
+        assert_eq!(stats.mirror_executions, 2);
+        assert!(
+            monitor
+                .get_function_stats("parse-invoice-v2")
+                .await
+                .is_none_or(|stats| stats.total_calls == 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mirror_failures_do_not_reach_caller() {
+        let scheduler = SimpleScheduler::new();
+        scheduler
+            .register_function(FunctionMetadata::new(
+                "primary".to_string(),
+                "return a + b".to_string(),
+            ))
+            .await
+            .unwrap();
+        scheduler
+            .register_function(FunctionMetadata::new(
+                "doomed".to_string(),
+                "return a".to_string(),
+            ))
+            .await
+            .unwrap();
+        scheduler
+            .set_mirror(
+                "primary",
+                Some(MirrorConfig {
+                    target: "doomed".to_string(),
+                    sample_rate: 1.0,
+                    compare: true,
+                }),
+            )
+            .await
+            .unwrap();
+        // 目标在配置后被删除，镜像调用失败
+        scheduler.delete_function("doomed", None).await.unwrap();
+
+        let response = scheduler
+            .schedule("primary", invoke(json!({"a": 1, "b": 2})))
+            .await
+            .unwrap();
+        assert!(response.status.is_success());
+        wait_for_mirrors(&scheduler, "primary", 1).await;
+        let report = scheduler.mirrors().report("primary", None);
+        assert_eq!(report.stats.failed, 1);
+        assert!(report.mismatches[0].mirror_error.is_some());
+
+        // 目标必须存在
+        assert!(
+            scheduler
+                .set_mirror(
+                    "primary",
+                    Some(MirrorConfig {
+                        target: "missing".to_string(),
+                        sample_rate: 1.0,
+                        compare: false,
+                    }),
+                )
+                .await
+                .is_err()
+        );
+    }
+}
@@ -1,14 +1,97 @@
 #![allow(dead_code)]
+use crate::functions::bundle::{ConflictStrategy, FunctionArchive, FunctionBundle, ImportResult};
+use crate::functions::compilation::{
+    CompilationRecord, CompilationStatus, HandlerMode, OnCompiling,
+};
+use crate::functions::context::{CallerInfo, InvocationContext};
+use crate::functions::guardrails::RegistryGuardrailsConfig;
+use crate::functions::http_route::HttpRoute;
+use crate::functions::inference;
+use crate::functions::labels::LabelSelector;
+use crate::functions::mirror::MirrorConfig;
+use crate::functions::name::FunctionName;
+use crate::functions::redaction::{Redactor, truncate_for_log};
 use crate::functions::registry::FunctionRegistry;
-use crate::functions::{InvokeRequest, InvokeResponse, Result};
+use crate::functions::schema::FunctionSchemas;
+use crate::functions::storage::RegistryStorage;
+use crate::functions::template::InputTemplate;
+use crate::functions::webhook::WebhookConfig;
+use crate::functions::{
+    ExecutionStatus, FluxError, FunctionMetadata, InvocationTiming, InvokeRequest, InvokeResponse,
+    Result, UpdateFunctionRequest,
+};
 use crate::runtime::SimpleRuntime;
-use crate::runtime::loader::FunctionLoader;
-use std::sync::Arc;
+use crate::runtime::affinity::{self, AffinityRoute};
+use crate::runtime::cache::FunctionCache;
+use crate::runtime::capabilities::{CapabilityMatrix, ExecutionCapability};
+use crate::runtime::compiler::RustCompiler;
+use crate::runtime::cpu::CpuReport;
+use crate::runtime::debug_capture::{Capture, DebugArtifactStore};
+use crate::runtime::disk::DiskReport;
+use crate::runtime::egress::EgressBroker;
+use crate::runtime::environment::RuntimeEnvironment;
+use crate::runtime::git::{GitFunctionSource, GitLoadRequest, GitSyncReport, sanitize_url};
+use crate::runtime::invocation_log::InvocationLogStore;
+use crate::runtime::loader::{
+    DirectoryLoadResult, DirectoryLoadSummary, FileLoadResult, FunctionLoader, LoadAction,
+    ScannedEntry,
+};
+use crate::runtime::monitor::ExecutionResult;
+use crate::runtime::oci::{OciArtifact, OciFunctionSource, OciLoadRequest, OciLoadResult};
+use crate::runtime::resource::ResourceManager;
+use crate::runtime::slo::SloAlerts;
+use crate::runtime::state::StateStore;
+use admission::{AdmissionConfig, AdmissionController};
+use canary::CanaryProbes;
+use chaos::{ChaosAction, ChaosEngine};
+use circuit::{CircuitBreakers, CircuitPermit};
+use deployments::DeploymentStore;
+use drain::{DEFAULT_DRAIN_TIMEOUT, DrainStatus, InFlightTracker};
+use history::ExecutionHistory;
+use idempotency::{IdempotencyConfig, IdempotencyStore};
+use invalidation::RegistrySubscription;
+use limiter::InvocationLimiter;
+use mirror::MirrorRecorder;
+use namespaces::{NamespaceRegistry, script_type};
+use peers::PeerSet;
+use routing::{MultiRuntimeScheduler, RoutingConfig};
+use sessions::SessionStore;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use webhooks::{WebhookDispatcher, WebhookDispatcherConfig, WebhookPayload};
 
+pub mod admission;
 pub mod balancer;
+pub mod canary;
+pub mod chaos;
+pub mod circuit;
+pub mod console;
+pub mod deployments;
+pub mod deprecation;
+pub mod drain;
+pub mod fanout;
+pub mod history;
+pub mod http_routes;
+pub mod idempotency;
+pub mod invalidation;
+pub mod jobs;
 pub mod lifecycle;
+pub mod limiter;
+pub mod mirror;
+pub mod namespaces;
+pub mod peers;
 pub mod pool;
+pub mod profiles;
+pub mod routing;
+pub mod sessions;
 pub mod simple;
+pub mod slo;
+pub mod testing;
+pub mod warmup;
+pub mod webhooks;
 
 /// 调度器特征
 #[async_trait::async_trait]
@@ -24,49 +107,511 @@ pub struct SimpleScheduler {
     registry: FunctionRegistry,
     runtime: Arc<SimpleRuntime>,
     loader: Arc<FunctionLoader>,
+    git_source: Arc<GitFunctionSource>,
+    /// 每个函数正在进行的后台编译任务，代码更新时中止旧任务
+    compile_tasks: Arc<StdMutex<HashMap<String, JoinHandle<()>>>>,
+    /// 调用完成后的 webhook 投递
+    webhooks: Arc<WebhookDispatcher>,
+    /// 按优先级排队的执行许可
+    admission: Arc<AdmissionController>,
+    /// 编译后的 JSON Schema：`<函数名>@<版本>` -> (修订号, schema)，修订号变化时重新编译
+    schemas: Arc<StdMutex<HashMap<String, CompiledSchemas>>>,
+    /// 命名空间及其默认限制（同一进程的调度器配置共享）
+    namespaces: Arc<NamespaceRegistry>,
+    /// 多运行时路由，缺省时所有调用都在 `runtime` 上执行
+    router: Option<Arc<MultiRuntimeScheduler>>,
+    /// 最近见过的调用幂等键及其结果
+    idempotency: Arc<IdempotencyStore>,
+    /// 故障注入规则（默认关闭，同一进程的调度器配置共享）
+    chaos: Arc<ChaosEngine>,
+    /// 最近执行的输入和结果，用于重放（同一进程的调度器配置共享）
+    history: Arc<ExecutionHistory>,
+    /// 按客户端会话分组的调用记录（同一进程的调度器配置共享）
+    sessions: Arc<SessionStore>,
+    /// 解释器探测结果和命名执行环境（同一进程的调度器配置共享）
+    environment: Arc<RuntimeEnvironment>,
+    /// 命名资源配额（同一进程的调度器配置共享）
+    resources: Arc<ResourceManager>,
+    /// 函数的键值状态（同一进程的调度器配置共享）
+    state: Arc<StateStore>,
+    /// 代执行中的函数发送出站 HTTP 请求（同一进程的调度器配置共享）
+    egress: Arc<EgressBroker>,
+    /// 全进程的调用并发上限和等待队列（同一进程的调度器配置共享）
+    invocations: Arc<InvocationLimiter>,
+    /// 失败调用的调试包（同一进程的调度器配置共享）
+    debug: Arc<DebugArtifactStore>,
+    /// 按请求捕获的调用日志（同一进程的调度器配置共享）
+    logs: Arc<InvocationLogStore>,
+    /// 转发调用的 FluxFaaS 节点（同一进程的调度器配置共享）
+    peers: Arc<PeerSet>,
+    /// 请求镜像的计数和不一致记录
+    mirrors: Arc<MirrorRecorder>,
+    /// 按函数的金丝雀探测状态
+    canaries: Arc<CanaryProbes>,
+    /// 按函数的调用熔断器
+    circuits: Arc<CircuitBreakers>,
+    /// 按函数的 SLO 燃烧率告警状态
+    slo_alerts: Arc<SloAlerts>,
+    /// 多函数部署（准备中、待激活以及可回滚的部署）
+    deployments: Arc<DeploymentStore>,
+    /// 按函数和修订号统计的在途调用，删除和替换时据此排空
+    in_flight: Arc<InFlightTracker>,
+    /// 删除或替换函数时等待在途调用结束的最长时间
+    drain_timeout: Duration,
+    /// 注册表变更事件的订阅，据此使缓存失效
+    registry_changes: Arc<RegistrySubscription>,
+}
+
+/// 已编译的 JSON Schema 及其对应的函数修订号
+type CompiledSchemas = (u64, Arc<FunctionSchemas>);
+
+/// 单次调度的选项
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleOptions {
+    /// 函数仍在后台编译时的处理方式
+    pub on_compiling: OnCompiling,
+    /// 调用 ID（通常为请求 ID），用于 webhook 通知；缺省时自动生成
+    pub invocation_id: Option<String>,
+    /// 调用指定的保留版本，缺省时调用最新版本
+    pub version: Option<String>,
+    /// 调用方信息，随调用上下文传给函数
+    pub caller: CallerInfo,
+    /// 重放历史调用时为原调用 ID，记录在执行历史中
+    pub replay_of: Option<String>,
+    /// 影子重放：照常执行，但监控只计入重放次数且不发送 webhook
+    pub shadow: bool,
+    /// 请求镜像时为主调用 ID：与影子重放一样不发送 webhook，监控只计入镜像次数
+    pub mirror_of: Option<String>,
+    /// 金丝雀探测：与影子重放一样没有副作用，监控只计入探测次数
+    pub canary: bool,
+    /// 其他节点转发来的调用：在本地执行，不再转发
+    pub forwarded: bool,
+    /// 弃用函数停用后改为调用替代函数：替代函数同样停用时不再继续改调
+    pub redirected: bool,
+}
+
+impl ScheduleOptions {
+    /// 影子重放、请求镜像或金丝雀探测：不影响调用统计、不发送 webhook，也不再触发镜像
+    pub fn is_shadow(&self) -> bool {
+        self.shadow || self.mirror_of.is_some() || self.canary
+    }
 }
 
 impl SimpleScheduler {
     pub fn new() -> Self {
+        let registry = FunctionRegistry::new();
         Self {
-            registry: FunctionRegistry::new(),
+            registry_changes: RegistrySubscription::new(&registry),
+            registry,
             runtime: Arc::new(SimpleRuntime::new()),
             loader: Arc::new(FunctionLoader::new()),
+            git_source: Arc::new(GitFunctionSource::default()),
+            compile_tasks: Arc::default(),
+            webhooks: Arc::default(),
+            admission: Arc::default(),
+            schemas: Arc::default(),
+            namespaces: Arc::default(),
+            router: None,
+            idempotency: Arc::default(),
+            chaos: Arc::default(),
+            history: Arc::default(),
+            sessions: Arc::default(),
+            environment: Arc::default(),
+            resources: Arc::default(),
+            state: Arc::default(),
+            egress: Arc::default(),
+            invocations: Arc::default(),
+            debug: Arc::default(),
+            logs: Arc::default(),
+            peers: Arc::default(),
+            mirrors: Arc::default(),
+            canaries: Arc::default(),
+            circuits: Arc::default(),
+            slo_alerts: Arc::default(),
+            deployments: Arc::default(),
+            in_flight: Arc::default(),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
         }
     }
 
     pub fn new_with_compilation() -> anyhow::Result<Self> {
+        let registry = FunctionRegistry::new();
         Ok(Self {
-            registry: FunctionRegistry::new(),
+            registry_changes: RegistrySubscription::new(&registry),
+            registry,
             runtime: Arc::new(SimpleRuntime::new_with_compilation()?),
             loader: Arc::new(FunctionLoader::new()),
+            git_source: Arc::new(GitFunctionSource::default()),
+            compile_tasks: Arc::default(),
+            webhooks: Arc::default(),
+            admission: Arc::default(),
+            schemas: Arc::default(),
+            namespaces: Arc::default(),
+            router: None,
+            idempotency: Arc::default(),
+            chaos: Arc::default(),
+            history: Arc::default(),
+            sessions: Arc::default(),
+            environment: Arc::default(),
+            resources: Arc::default(),
+            state: Arc::default(),
+            egress: Arc::default(),
+            invocations: Arc::default(),
+            debug: Arc::default(),
+            logs: Arc::default(),
+            peers: Arc::default(),
+            mirrors: Arc::default(),
+            canaries: Arc::default(),
+            circuits: Arc::default(),
+            slo_alerts: Arc::default(),
+            deployments: Arc::default(),
+            in_flight: Arc::default(),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
         })
     }
 
     pub fn with_registry(registry: FunctionRegistry) -> Self {
         Self {
+            registry_changes: RegistrySubscription::new(&registry),
             registry,
             runtime: Arc::new(SimpleRuntime::new()),
             loader: Arc::new(FunctionLoader::new()),
+            git_source: Arc::new(GitFunctionSource::default()),
+            compile_tasks: Arc::default(),
+            webhooks: Arc::default(),
+            admission: Arc::default(),
+            schemas: Arc::default(),
+            namespaces: Arc::default(),
+            router: None,
+            idempotency: Arc::default(),
+            chaos: Arc::default(),
+            history: Arc::default(),
+            sessions: Arc::default(),
+            environment: Arc::default(),
+            resources: Arc::default(),
+            state: Arc::default(),
+            egress: Arc::default(),
+            invocations: Arc::default(),
+            debug: Arc::default(),
+            logs: Arc::default(),
+            peers: Arc::default(),
+            mirrors: Arc::default(),
+            canaries: Arc::default(),
+            circuits: Arc::default(),
+            slo_alerts: Arc::default(),
+            deployments: Arc::default(),
+            in_flight: Arc::default(),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
         }
     }
 
     pub fn with_loader(loader: Arc<FunctionLoader>) -> Self {
+        let registry = FunctionRegistry::new();
         Self {
-            registry: FunctionRegistry::new(),
+            registry_changes: RegistrySubscription::new(&registry),
+            registry,
             runtime: Arc::new(SimpleRuntime::new()),
             loader,
+            git_source: Arc::new(GitFunctionSource::default()),
+            compile_tasks: Arc::default(),
+            webhooks: Arc::default(),
+            admission: Arc::default(),
+            schemas: Arc::default(),
+            namespaces: Arc::default(),
+            router: None,
+            idempotency: Arc::default(),
+            chaos: Arc::default(),
+            history: Arc::default(),
+            sessions: Arc::default(),
+            environment: Arc::default(),
+            resources: Arc::default(),
+            state: Arc::default(),
+            egress: Arc::default(),
+            invocations: Arc::default(),
+            debug: Arc::default(),
+            logs: Arc::default(),
+            peers: Arc::default(),
+            mirrors: Arc::default(),
+            canaries: Arc::default(),
+            circuits: Arc::default(),
+            slo_alerts: Arc::default(),
+            deployments: Arc::default(),
+            in_flight: Arc::default(),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+        }
+    }
+
+    pub fn with_git_source(git_source: Arc<GitFunctionSource>) -> Self {
+        let registry = FunctionRegistry::new();
+        Self {
+            registry_changes: RegistrySubscription::new(&registry),
+            registry,
+            runtime: Arc::new(SimpleRuntime::new()),
+            loader: Arc::new(FunctionLoader::new()),
+            git_source,
+            compile_tasks: Arc::default(),
+            webhooks: Arc::default(),
+            admission: Arc::default(),
+            schemas: Arc::default(),
+            namespaces: Arc::default(),
+            router: None,
+            idempotency: Arc::default(),
+            chaos: Arc::default(),
+            history: Arc::default(),
+            sessions: Arc::default(),
+            environment: Arc::default(),
+            resources: Arc::default(),
+            state: Arc::default(),
+            egress: Arc::default(),
+            invocations: Arc::default(),
+            debug: Arc::default(),
+            logs: Arc::default(),
+            peers: Arc::default(),
+            mirrors: Arc::default(),
+            canaries: Arc::default(),
+            circuits: Arc::default(),
+            slo_alerts: Arc::default(),
+            deployments: Arc::default(),
+            in_flight: Arc::default(),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
         }
     }
 
     pub fn with_runtime(runtime: Arc<SimpleRuntime>) -> Self {
+        let registry = FunctionRegistry::new();
         Self {
-            registry: FunctionRegistry::new(),
+            registry_changes: RegistrySubscription::new(&registry),
+            registry,
             runtime,
             loader: Arc::new(FunctionLoader::new()),
+            git_source: Arc::new(GitFunctionSource::default()),
+            compile_tasks: Arc::default(),
+            webhooks: Arc::default(),
+            admission: Arc::default(),
+            schemas: Arc::default(),
+            namespaces: Arc::default(),
+            router: None,
+            idempotency: Arc::default(),
+            chaos: Arc::default(),
+            history: Arc::default(),
+            sessions: Arc::default(),
+            environment: Arc::default(),
+            resources: Arc::default(),
+            state: Arc::default(),
+            egress: Arc::default(),
+            invocations: Arc::default(),
+            debug: Arc::default(),
+            logs: Arc::default(),
+            peers: Arc::default(),
+            mirrors: Arc::default(),
+            canaries: Arc::default(),
+            circuits: Arc::default(),
+            slo_alerts: Arc::default(),
+            deployments: Arc::default(),
+            in_flight: Arc::default(),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
         }
     }
 
+    /// 使用指定的 webhook 投递配置
+    pub fn with_webhook_config(mut self, config: WebhookDispatcherConfig) -> Self {
+        self.webhooks = Arc::new(WebhookDispatcher::new(config));
+        self
+    }
+
+    /// 设置每个函数保留的可调用版本数（包含最新版本）
+    pub fn with_max_versions(mut self, max_versions: usize) -> Self {
+        self.registry = self.registry.with_max_versions(max_versions);
+        self
+    }
+
+    /// 设置注册表的函数总数上限和变更速率限制
+    pub fn with_registry_guardrails(mut self, config: RegistryGuardrailsConfig) -> Self {
+        self.registry = self.registry.with_guardrails(config);
+        self
+    }
+
+    /// 使用指定的准入配置（并发许可数、队列上限）
+    pub fn with_admission_config(mut self, config: AdmissionConfig) -> Self {
+        self.admission = Arc::new(AdmissionController::new(config));
+        self
+    }
+
+    /// 使用指定的幂等键配置（结果保留时间、键数上限）
+    pub fn with_idempotency_config(mut self, config: IdempotencyConfig) -> Self {
+        self.idempotency = Arc::new(IdempotencyStore::new(config));
+        self
+    }
+
+    /// 删除或替换函数时等待在途调用结束的最长时间（默认 30 秒）
+    pub fn with_drain_timeout(mut self, drain_timeout: Duration) -> Self {
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
+    /// 使用共享的命名空间注册表
+    pub fn with_namespaces(mut self, namespaces: Arc<NamespaceRegistry>) -> Self {
+        self.namespaces = namespaces;
+        self
+    }
+
+    /// 使用共享的故障注入规则
+    pub fn with_chaos(mut self, chaos: Arc<ChaosEngine>) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
+    /// 使用共享的执行历史
+    pub fn with_history(mut self, history: Arc<ExecutionHistory>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// 使用共享的会话调用记录
+    pub fn with_sessions(mut self, sessions: Arc<SessionStore>) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    /// 使用共享的运行时环境（执行环境定义）
+    pub fn with_environment(mut self, environment: Arc<RuntimeEnvironment>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// 使用共享的资源配额
+    pub fn with_resources(mut self, resources: Arc<ResourceManager>) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// 使用共享的函数状态存储
+    pub fn with_state(mut self, state: Arc<StateStore>) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// 使用共享的出站 HTTP 代理
+    pub fn with_egress(mut self, egress: Arc<EgressBroker>) -> Self {
+        self.egress = egress;
+        self
+    }
+
+    /// 使用共享的全局调用并发上限
+    pub fn with_invocation_limiter(mut self, invocations: Arc<InvocationLimiter>) -> Self {
+        self.invocations = invocations;
+        self
+    }
+
+    /// 使用共享的调试包目录
+    pub fn with_debug_artifacts(mut self, debug: Arc<DebugArtifactStore>) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// 使用共享的调用日志存储
+    pub fn with_invocation_logs(mut self, logs: Arc<InvocationLogStore>) -> Self {
+        self.logs = logs;
+        self
+    }
+
+    /// 使用共享的转发节点
+    pub fn with_peers(mut self, peers: Arc<PeerSet>) -> Self {
+        self.peers = peers;
+        self
+    }
+
+    /// 按路由配置把调用分发到多个运行时后端（后端与本调度器的运行时共享缓存和性能监控）
+    pub fn with_routing(mut self, config: &RoutingConfig) -> anyhow::Result<Self> {
+        let router =
+            MultiRuntimeScheduler::from_config(self.registry.clone(), &self.runtime, config)?;
+        self.router = Some(Arc::new(router));
+        Ok(self)
+    }
+
+    /// 多运行时路由（未配置时为 `None`）
+    pub fn router(&self) -> Option<&Arc<MultiRuntimeScheduler>> {
+        self.router.as_ref()
+    }
+
+    /// 获取命名空间注册表
+    pub fn namespaces(&self) -> &Arc<NamespaceRegistry> {
+        &self.namespaces
+    }
+
+    /// 执行历史
+    pub fn history(&self) -> &Arc<ExecutionHistory> {
+        &self.history
+    }
+
+    /// 会话调用记录
+    pub fn sessions(&self) -> &Arc<SessionStore> {
+        &self.sessions
+    }
+
+    /// 运行时环境
+    pub fn environment(&self) -> &Arc<RuntimeEnvironment> {
+        &self.environment
+    }
+
+    /// 资源配额
+    pub fn resources(&self) -> &Arc<ResourceManager> {
+        &self.resources
+    }
+
+    /// 函数状态存储
+    pub fn state(&self) -> &Arc<StateStore> {
+        &self.state
+    }
+
+    /// 出站 HTTP 代理
+    pub fn egress(&self) -> &Arc<EgressBroker> {
+        &self.egress
+    }
+
+    /// 全局调用并发上限
+    pub fn invocation_limiter(&self) -> &Arc<InvocationLimiter> {
+        &self.invocations
+    }
+
+    /// 调试包目录
+    pub fn debug_artifacts(&self) -> &Arc<DebugArtifactStore> {
+        &self.debug
+    }
+
+    /// 按请求捕获的调用日志
+    pub fn invocation_logs(&self) -> &Arc<InvocationLogStore> {
+        &self.logs
+    }
+
+    /// 请求镜像的计数和不一致记录
+    pub fn mirrors(&self) -> &Arc<MirrorRecorder> {
+        &self.mirrors
+    }
+
+    pub fn canaries(&self) -> &Arc<CanaryProbes> {
+        &self.canaries
+    }
+
+    /// 按函数的调用熔断器
+    pub fn circuits(&self) -> &Arc<CircuitBreakers> {
+        &self.circuits
+    }
+
+    /// 按函数的 SLO 燃烧率告警状态
+    pub fn slo_alerts(&self) -> &Arc<SloAlerts> {
+        &self.slo_alerts
+    }
+
+    pub fn peers(&self) -> &Arc<PeerSet> {
+        &self.peers
+    }
+
+    /// 故障注入规则
+    pub fn chaos(&self) -> &Arc<ChaosEngine> {
+        &self.chaos
+    }
+
     /// 获取函数注册表的引用
     pub fn registry(&self) -> &FunctionRegistry {
         &self.registry
@@ -81,6 +626,1125 @@ impl SimpleScheduler {
     pub fn loader(&self) -> &Arc<FunctionLoader> {
         &self.loader
     }
+
+    /// 获取 Git 函数源的引用
+    pub fn git_source(&self) -> &Arc<GitFunctionSource> {
+        &self.git_source
+    }
+
+    /// 获取 webhook 投递器的引用
+    pub fn webhooks(&self) -> &Arc<WebhookDispatcher> {
+        &self.webhooks
+    }
+
+    /// 获取准入控制器
+    pub fn admission(&self) -> &Arc<AdmissionController> {
+        &self.admission
+    }
+
+    /// 从 Git 仓库同步函数：新增、更新变更的函数，并按需删除已消失的函数
+    pub async fn load_from_git(&self, req: &GitLoadRequest) -> Result<GitSyncReport> {
+        let repo_lock = self.git_source.lock_repo(&req.url).await;
+        let _guard = repo_lock.lock().await;
+
+        let checkout = self.git_source.checkout(req).await?;
+        if !checkout.functions_dir.is_dir() {
+            return Err(FluxError::ValidationError {
+                reason: format!(
+                    "Subdir not found in repository: {}",
+                    req.subdir.as_deref().unwrap_or(".")
+                ),
+            });
+        }
+
+        let scanned = self.loader.scan_directory(&checkout.functions_dir).await?;
+
+        let previously_tracked = self.git_source.tracked_functions(&req.url).await;
+        let mut tracked = HashSet::new();
+        let mut report = GitSyncReport {
+            url: sanitize_url(&req.url),
+            git_ref: req.git_ref.clone().unwrap_or_else(|| "HEAD".to_string()),
+            commit: checkout.commit,
+            ..Default::default()
+        };
+        report.failed.extend(
+            checkout
+                .rejected_links
+                .into_iter()
+                .map(|path| format!("{path}: symlink points outside the repository")),
+        );
+
+        let mut functions = Vec::new();
+        for entry in scanned {
+            match entry {
+                ScannedEntry::Function { function, .. } => functions.push(*function),
+                ScannedEntry::Failed {
+                    path, name, error, ..
+                } => {
+                    // 文件仍在但加载失败的函数继续归属该仓库，保留已注册的版本，不会被 prune 删除
+                    if let Some(name) = name.filter(|name| previously_tracked.contains(name)) {
+                        tracked.insert(name);
+                    }
+                    report.failed.push(format!("{}: {error}", path.display()));
+                }
+                ScannedEntry::Skipped { .. } => {}
+            }
+        }
+
+        for function in functions {
+            let name = function.name.clone();
+            match self.registry.get(&name).await {
+                Ok(existing) if previously_tracked.contains(&name) => {
+                    tracked.insert(name.clone());
+                    if same_definition(&existing, &function) {
+                        report.unchanged.push(name);
+                        continue;
+                    }
+
+                    // 保留原有 ID 和创建时间，只更新定义
+                    let mut function = function;
+                    function.id = existing.id;
+                    function.created_at = existing.created_at;
+                    match self.upsert_function(function).await {
+                        Ok(_) => report.updated.push(name),
+                        Err(e) if e.is_registry_limit() => {
+                            report.rejected.push(format!("{name}: {e}"))
+                        }
+                        Err(e) => report.failed.push(format!("{name}: {e}")),
+                    }
+                }
+                Ok(_) => {
+                    report.failed.push(format!(
+                        "{name}: {}",
+                        FluxError::FunctionAlreadyExists { name: name.clone() }
+                    ));
+                }
+                Err(_) => match self.register_function(function).await {
+                    Ok(_) => {
+                        tracked.insert(name.clone());
+                        report.added.push(name);
+                    }
+                    Err(e) if e.is_registry_limit() => report.rejected.push(format!("{name}: {e}")),
+                    Err(e) => report.failed.push(format!("{name}: {e}")),
+                },
+            }
+        }
+
+        let stale: Vec<String> = previously_tracked.difference(&tracked).cloned().collect();
+        for name in &stale {
+            if req.prune {
+                if self.delete_function(name, None).await.is_ok() {
+                    report.removed.push(name.clone());
+                }
+            } else if self.registry.exists(name).await {
+                // 未删除的函数继续归属该仓库，便于后续 prune
+                tracked.insert(name.clone());
+            }
+        }
+
+        self.git_source
+            .set_tracked_functions(&req.url, tracked)
+            .await;
+
+        tracing::info!(
+            "Git sync of {} at {}: {} added, {} updated, {} removed, {} failed, {} rejected",
+            report.url,
+            report.commit,
+            report.added.len(),
+            report.updated.len(),
+            report.removed.len(),
+            report.failed.len(),
+            report.rejected.len()
+        );
+        Ok(report)
+    }
+}
+
+impl SimpleScheduler {
+    /// 注册函数，并在启用编译时启动后台编译
+    ///
+    /// 同名函数的注册、更新和删除持有注册表的函数名锁，注册表写入、缓存失效和启动编译不会交错。
+    pub async fn register_function(&self, function: FunctionMetadata) -> Result<()> {
+        let _guard = self.registry.lock_name(&function.name).await;
+        self.check_namespace(&function).await?;
+        self.check_runtime(&function)?;
+        if let Some(canary) = &function.canary {
+            canary.validate()?;
+        }
+        if let Some(slo) = &function.slo {
+            slo.validate()?;
+        }
+        self.check_capability(&function)?;
+        self.check_quota(&function).await?;
+        self.registry.register(function.clone()).await?;
+        self.compile_in_background(&function).await;
+        Ok(())
+    }
+
+    /// 注册或替换函数；代码变化时重新启动后台编译
+    pub async fn upsert_function(
+        &self,
+        function: FunctionMetadata,
+    ) -> Result<Option<FunctionMetadata>> {
+        let _guard = self.registry.lock_name(&function.name).await;
+        self.replace_locked(function, None)
+            .await
+            .map(|(_, previous)| previous)
+    }
+
+    /// 写入函数、使缓存失效并按需启动编译（调用方持有函数名锁）
+    async fn replace_locked(
+        &self,
+        function: FunctionMetadata,
+        expected_revision: Option<u64>,
+    ) -> Result<(FunctionMetadata, Option<FunctionMetadata>)> {
+        self.check_namespace(&function).await?;
+        self.check_runtime(&function)?;
+        if let Some(canary) = &function.canary {
+            canary.validate()?;
+        }
+        if let Some(slo) = &function.slo {
+            slo.validate()?;
+        }
+        self.check_capability(&function)?;
+        self.check_quota(&function).await?;
+        let (function, previous) = self.registry.upsert_if(function, expected_revision).await?;
+        if let Some(previous) = &previous {
+            if self.evicts_artifact(previous).await
+                && !self
+                    .in_flight
+                    .wait_idle(&function.name, self.drain_timeout, |revision| {
+                        revision != function.revision
+                    })
+                    .await
+            {
+                tracing::warn!(
+                    "Replacing artifacts of {} with invocations of the previous code still running after {:?}",
+                    function.name,
+                    self.drain_timeout
+                );
+            }
+            self.apply_registry_changes().await;
+        }
+        self.compile_in_background(&function).await;
+        Ok((function, previous))
+    }
+
+    /// 校验函数所属命名空间存在、允许其脚本类型且未超出函数数上限
+    async fn check_namespace(&self, function: &FunctionMetadata) -> Result<()> {
+        let namespace = function.namespace();
+        let existing = self
+            .registry
+            .list()
+            .await
+            .iter()
+            .filter(|other| other.namespace() == namespace && other.name != function.name)
+            .count();
+        self.namespaces.check_function(function, existing)
+    }
+
+    /// 校验函数选择的执行环境已定义且与其脚本类型一致
+    fn check_runtime(&self, function: &FunctionMetadata) -> Result<()> {
+        self.environment
+            .check_selection(function.runtime.as_deref(), script_type(function))
+            .map_err(|reason| FluxError::ValidationError { reason })
+    }
+
+    /// 校验当前构建和运行环境可以执行函数的脚本类型；配置了路由时，有编译后端即可执行 Rust 函数
+    fn check_capability(&self, function: &FunctionMetadata) -> Result<()> {
+        match self.runtime.check_capability(function) {
+            Err(FluxError::UnsupportedScriptType { .. })
+                if script_type(function) == "rust" && self.routes_to_compiler() =>
+            {
+                Ok(())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    fn routes_to_compiler(&self) -> bool {
+        self.router
+            .as_ref()
+            .is_some_and(|router| router.supports_compilation())
+    }
+
+    /// 各脚本类型在本调度器上的执行方式
+    pub fn capabilities(&self) -> CapabilityMatrix {
+        let mut capabilities = self.runtime.capabilities();
+        if self.routes_to_compiler() {
+            capabilities.insert("rust".to_string(), ExecutionCapability::Native);
+        }
+        capabilities
+    }
+
+    /// 校验函数选择的资源配额已定义
+    async fn check_quota(&self, function: &FunctionMetadata) -> Result<()> {
+        match function.resource_quota.as_deref() {
+            Some(name) if self.resources.get_quota(name).await.is_none() => {
+                Err(FluxError::ValidationError {
+                    reason: format!("Unknown resource quota '{name}'"),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 命名空间中的函数（按名称排序）
+    pub async fn list_namespace(&self, namespace: &str) -> Vec<FunctionMetadata> {
+        let mut functions: Vec<_> = self
+            .registry
+            .list()
+            .await
+            .into_iter()
+            .filter(|function| function.namespace() == namespace)
+            .collect();
+        functions.sort_by(|a, b| a.name.cmp(&b.name));
+        functions
+    }
+
+    /// 替换后旧代码的编译产物不再被任何保留版本使用，清理时会使正在运行的调用失去产物
+    async fn evicts_artifact(&self, previous: &FunctionMetadata) -> bool {
+        if self.runtime.compiler().is_none() {
+            return false;
+        }
+        let (_, hash) = RustCompiler::compile_key(previous);
+        !self
+            .registry
+            .versions(&previous.name)
+            .await
+            .unwrap_or_default()
+            .iter()
+            .any(|function| RustCompiler::compile_key(function).1 == hash)
+    }
+
+    /// 回收函数的脚本工作进程（回收前调用 `teardown`），之后的调用以新代码重新初始化
+    async fn retire_workers(&self, name: &str) {
+        if let Some(sandbox) = self.runtime.sandbox() {
+            sandbox.workers().retire(name).await;
+        }
+    }
+
+    /// 函数当前定义的已编译 JSON Schema（没有声明 schema 时为 `None`），按版本和修订号缓存
+    fn schemas_for(&self, function: &FunctionMetadata) -> Result<Option<Arc<FunctionSchemas>>> {
+        if function.input_schema.is_none() && function.output_schema.is_none() {
+            return Ok(None);
+        }
+        let key = FunctionCache::key(function);
+        let mut schemas = self.schemas.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((revision, compiled)) = schemas.get(&key)
+            && *revision == function.revision
+        {
+            return Ok(Some(compiled.clone()));
+        }
+        let compiled = Arc::new(FunctionSchemas::compile(function)?);
+        schemas.insert(key, (function.revision, compiled.clone()));
+        Ok(Some(compiled))
+    }
+
+    /// 移除函数已不再保留（被裁剪、删除）的版本的缓存条目和编译产物
+    async fn evict_unretained(&self, name: &str) {
+        let retained = self.registry.versions(name).await.unwrap_or_default();
+        let versions: Vec<&str> = retained
+            .iter()
+            .map(|function| function.version.as_str())
+            .collect();
+        self.runtime.cache().retain_versions(name, &versions).await;
+        let prefix = format!("{name}@");
+        self.schemas
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|key, _| {
+                key.strip_prefix(&prefix)
+                    .is_none_or(|version| versions.contains(&version))
+            });
+        if let Some(compiler) = self.runtime.compiler() {
+            let hashes = retained
+                .iter()
+                .map(|function| RustCompiler::compile_key(function).1)
+                .collect();
+            let evicted = compiler.evict_stale(name, &hashes).await;
+            if evicted > 0 {
+                tracing::info!(
+                    "Invalidated {} stale artifacts of function {}",
+                    evicted,
+                    name
+                );
+            }
+        }
+    }
+
+    /// 删除函数的一个历史版本及其缓存条目和编译产物；最新版本不能单独删除
+    pub async fn delete_version(&self, name: &str, version: &str) -> Result<FunctionMetadata> {
+        let _guard = self.registry.lock_name(name).await;
+        let removed = self.registry.remove_version(name, version).await?;
+        self.apply_registry_changes().await;
+        Ok(removed)
+    }
+
+    /// 删除函数，`expected_revision` 不为空时要求修订号一致；返回被删除的函数
+    ///
+    /// 删除前先排空：新调用以 `FunctionDraining` 拒绝，在途调用结束（最长为排空超时）后再清理。
+    pub async fn delete_function(
+        &self,
+        name: &str,
+        expected_revision: Option<u64>,
+    ) -> Result<FunctionMetadata> {
+        self.drain_and_delete(name, expected_revision, false).await
+    }
+
+    /// 与 [`Self::delete_function`] 相同，`force` 时不等待，直接取消在途调用
+    pub async fn drain_and_delete(
+        &self,
+        name: &str,
+        expected_revision: Option<u64>,
+        force: bool,
+    ) -> Result<FunctionMetadata> {
+        let _guard = self.registry.lock_name(name).await;
+        let current = self.registry.get(name).await?;
+        FunctionRegistry::check_revision(name, current.revision, expected_revision)?;
+
+        self.in_flight.start_drain(name);
+        if force {
+            let cancelled = self.in_flight.cancel(name);
+            if cancelled > 0 {
+                tracing::info!("Cancelled {} in-flight invocations of {}", cancelled, name);
+            }
+        } else if !self
+            .in_flight
+            .wait_idle(name, self.drain_timeout, |_| true)
+            .await
+        {
+            tracing::warn!(
+                "Deleting {} with {} invocations still running after {:?}",
+                name,
+                self.in_flight.status(name).in_flight,
+                self.drain_timeout
+            );
+        }
+        let result = self.remove_locked(name, expected_revision).await;
+        self.in_flight.finish_drain(name);
+        result
+    }
+
+    /// 函数的生命周期状态（是否正在排空）和在途调用数
+    pub fn drain_status(&self, name: &str) -> DrainStatus {
+        self.in_flight.status(name)
+    }
+
+    /// 删除函数并清理其缓存条目、编译产物、熔断状态、键值状态和编译任务（调用方持有函数名锁）
+    async fn remove_locked(
+        &self,
+        name: &str,
+        expected_revision: Option<u64>,
+    ) -> Result<FunctionMetadata> {
+        let removed = self.registry.remove_if(name, expected_revision).await?;
+        if !removed.http_routes.is_empty() {
+            tracing::warn!(
+                "Removed {} HTTP routes bound to deleted function {}",
+                removed.http_routes.len(),
+                name
+            );
+        }
+        self.apply_registry_changes().await;
+        self.circuits.remove(name);
+        self.state.clear(name);
+        let task = self
+            .compile_tasks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(name);
+        if let Some(task) = task {
+            task.abort();
+        }
+        Ok(removed)
+    }
+
+    /// 设置函数目录的持久化后端并加载其中的函数（加载后在后台编译），返回加载的函数数
+    ///
+    /// 后端不可达时返回 `StorageUnavailable`，注册表保持只读，可用 `spawn_storage_reload` 稍后重试。
+    pub async fn load_from_storage(&self, storage: Arc<dyn RegistryStorage>) -> Result<usize> {
+        self.registry.set_storage(storage);
+        self.reload_storage().await
+    }
+
+    async fn reload_storage(&self) -> Result<usize> {
+        let loaded = self.registry.load_from_storage().await?;
+        for function in &loaded {
+            self.compile_in_background(function).await;
+        }
+        Ok(loaded.len())
+    }
+
+    /// 在后台按间隔重新加载函数目录，直到成功一次
+    pub fn spawn_storage_reload(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                match scheduler.reload_storage().await {
+                    Ok(count) => {
+                        tracing::info!("Function storage recovered, loaded {} functions", count);
+                        return;
+                    }
+                    Err(e) => tracing::warn!("Function storage is still unavailable: {}", e),
+                }
+            }
+        })
+    }
+
+    /// 启用编译时在后台编译函数的当前代码，编译状态记录在注册表中
+    ///
+    /// 代码变化时中止旧的编译任务并启动新的编译（仍保留的历史版本的编译产物不受影响）；
+    /// 代码未变且已在编译或已就绪时不做任何事。
+    pub async fn compile_in_background(&self, function: &FunctionMetadata) {
+        let Some(compiler) = self.runtime.compiler().cloned() else {
+            return;
+        };
+        // JavaScript/Python 函数由解释器或内嵌引擎执行，不编译
+        if !matches!(script_type(function), "rust" | "expression") {
+            return;
+        }
+        let (name, source_hash) = RustCompiler::compile_key(function);
+        let handler_mode = HandlerMode::for_function(function);
+        if !self
+            .registry
+            .start_compilation(&name, &source_hash, handler_mode)
+            .await
+        {
+            return;
+        }
+
+        let registry = self.registry.clone();
+        let function = function.clone();
+        let task_name = name.clone();
+        let task = tokio::spawn(async move {
+            registry
+                .update_compilation(&name, &source_hash, CompilationStatus::Compiling, None)
+                .await;
+            let (status, stderr) = match compiler.compile_function(&function).await {
+                Ok(_) => (CompilationStatus::Ready, None),
+                Err(e) => {
+                    // 编译错误会引用函数源码，日志中按字节上限截断（编译记录保留完整信息）
+                    tracing::warn!(
+                        "Background compilation of {} failed: {}",
+                        name,
+                        truncate_for_log(&format!("{e:#}"))
+                    );
+                    CompilationRecord::failure(&format!("{e:#}"))
+                }
+            };
+            registry
+                .update_compilation(&name, &source_hash, status, stderr)
+                .await;
+        });
+
+        let previous = self
+            .compile_tasks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(task_name, task);
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+    }
+
+    /// 调用前检查函数的编译状态：编译中时按 `on_compiling` 等待或拒绝，编译失败时直接报错
+    async fn ensure_compiled(
+        &self,
+        function: &FunctionMetadata,
+        on_compiling: OnCompiling,
+    ) -> Result<()> {
+        let record = match on_compiling {
+            OnCompiling::Wait => {
+                self.registry
+                    .wait_for_compilation(
+                        &function.name,
+                        Duration::from_millis(function.timeout_ms),
+                    )
+                    .await
+            }
+            OnCompiling::Reject => self.registry.compilation(&function.name).await,
+        };
+        let Some(record) = record else {
+            return Ok(());
+        };
+        match &record.status {
+            status if status.is_in_progress() => Err(FluxError::StillCompiling {
+                name: function.name.clone(),
+            }),
+            CompilationStatus::Failed(message) => {
+                Err(FluxError::CompilationError(match record.rustc_errors() {
+                    Some(errors) => format!("{message}:\n{errors}"),
+                    None => message.clone(),
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 更新函数元数据（不修改代码），`expected_revision` 不为空时要求修订号一致
+    pub async fn update_function(
+        &self,
+        name: &str,
+        req: UpdateFunctionRequest,
+        expected_revision: Option<u64>,
+    ) -> Result<FunctionMetadata> {
+        let _guard = self.registry.lock_name(name).await;
+        let mut function = self.registry.get(name).await?;
+        function.apply_update(req);
+        self.replace_locked(function, expected_revision)
+            .await
+            .map(|(function, _)| function)
+    }
+
+    /// 按当前代码重新推断函数签名，只替换此前推断的参数和返回类型
+    pub async fn infer_signature(
+        &self,
+        name: &str,
+        expected_revision: Option<u64>,
+    ) -> Result<FunctionMetadata> {
+        let _guard = self.registry.lock_name(name).await;
+        let mut function = self.registry.get(name).await?;
+        if let Some(expected) = expected_revision
+            && function.revision != expected
+        {
+            return Err(FluxError::RevisionMismatch {
+                name: name.to_string(),
+                expected,
+                current: function.revision,
+            });
+        }
+        let previous = serde_json::to_value(&function).ok();
+        inference::refresh(&mut function);
+        if serde_json::to_value(&function).ok() == previous {
+            return Ok(function);
+        }
+        function.updated_at = chrono::Utc::now();
+        self.replace_locked(function, expected_revision)
+            .await
+            .map(|(function, _)| function)
+    }
+
+    /// 设置（`None` 时清除）函数的 webhook 配置
+    pub async fn set_webhooks(
+        &self,
+        name: &str,
+        webhooks: Option<WebhookConfig>,
+    ) -> Result<FunctionMetadata> {
+        if let Some(webhooks) = &webhooks {
+            webhooks.validate()?;
+        }
+        let _guard = self.registry.lock_name(name).await;
+        let mut function = self.registry.get(name).await?;
+        function.webhooks = webhooks;
+        function.updated_at = chrono::Utc::now();
+        self.registry
+            .upsert_if(function, None)
+            .await
+            .map(|(function, _)| function)
+    }
+
+    /// 设置（`None` 时停止）函数的请求镜像，镜像目标必须已注册
+    pub async fn set_mirror(
+        &self,
+        name: &str,
+        mirror: Option<MirrorConfig>,
+    ) -> Result<FunctionMetadata> {
+        if let Some(mirror) = &mirror {
+            mirror.validate(name)?;
+            if self.registry.get(&mirror.target).await.is_err() {
+                return Err(FluxError::ValidationError {
+                    reason: format!("Mirror target function '{}' not found", mirror.target),
+                });
+            }
+        }
+        let _guard = self.registry.lock_name(name).await;
+        let mut function = self.registry.get(name).await?;
+        function.mirror = mirror;
+        function.updated_at = chrono::Utc::now();
+        self.registry
+            .upsert_if(function, None)
+            .await
+            .map(|(function, _)| function)
+    }
+
+    /// 替换函数的自定义 HTTP 路由；只更新元数据，不改变函数版本
+    pub async fn set_http_routes(
+        &self,
+        name: &str,
+        routes: Vec<HttpRoute>,
+    ) -> Result<FunctionMetadata> {
+        let _guard = self.registry.lock_name(name).await;
+        self.registry.set_http_routes(name, routes).await
+    }
+
+    /// 替换函数文档（`None` 表示移除）；只更新元数据，不改变函数版本也不触发重新编译
+    pub async fn set_documentation(
+        &self,
+        name: &str,
+        documentation: Option<String>,
+    ) -> Result<FunctionMetadata> {
+        let _guard = self.registry.lock_name(name).await;
+        let mut function = self.registry.get(name).await?;
+        function.documentation = documentation;
+        function.updated_at = chrono::Utc::now();
+        self.registry
+            .upsert_if(function, None)
+            .await
+            .map(|(function, _)| function)
+    }
+
+    /// 删除所有匹配选择器的函数，返回被删除的函数名
+    pub async fn delete_by_selector(&self, selector: &LabelSelector) -> Vec<String> {
+        let mut deleted = Vec::new();
+        for function in self.registry.list_by_selector(selector).await {
+            if self.delete_function(&function.name, None).await.is_ok() {
+                deleted.push(function.name);
+            }
+        }
+        deleted.sort();
+        deleted
+    }
+
+    /// 并发调用所有匹配选择器的函数，按函数名排序返回结果
+    pub async fn invoke_by_selector(
+        &self,
+        selector: &LabelSelector,
+        request: InvokeRequest,
+    ) -> Vec<(String, Result<InvokeResponse>)> {
+        let functions = self.registry.list_by_selector(selector).await;
+        let mut results = futures_util::future::join_all(functions.into_iter().map(|function| {
+            let request = request.clone();
+            async move {
+                let result = self.schedule(&function.name, request).await;
+                (function.name, result)
+            }
+        }))
+        .await;
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        results
+    }
+
+    /// 导出单个函数
+    pub async fn export_function(&self, name: &str) -> Result<FunctionBundle> {
+        let function = self.registry.get(name).await?;
+        Ok(FunctionBundle::from_metadata(&function))
+    }
+
+    /// 导出所有函数（按名称排序）
+    pub async fn export_all(&self) -> FunctionArchive {
+        let mut functions = self.registry.list().await;
+        functions.sort_by(|a, b| a.name.cmp(&b.name));
+        FunctionArchive::from_functions(&functions)
+    }
+
+    /// 导入函数包，返回每个函数的导入结果
+    ///
+    /// 策略为 [`ConflictStrategy::Fail`] 时，只要有一个函数与已有函数冲突就整体拒绝，不导入任何函数。
+    /// `activate_if_tests_pass` 为真时先执行每个函数的测试用例，有用例失败的函数不导入。
+    pub async fn import_bundles(
+        &self,
+        bundles: Vec<FunctionBundle>,
+        strategy: ConflictStrategy,
+        activate_if_tests_pass: bool,
+    ) -> Result<Vec<ImportResult>> {
+        if strategy == ConflictStrategy::Fail {
+            let mut conflicts = Vec::new();
+            for bundle in &bundles {
+                if self.registry.exists(&bundle.name).await {
+                    conflicts.push(bundle.name.clone());
+                }
+            }
+            if !conflicts.is_empty() {
+                return Err(FluxError::FunctionAlreadyExists {
+                    name: conflicts.join(", "),
+                });
+            }
+        }
+
+        // 先按当前注册表规划每个函数的写入，再在一个注册表事务中统一提交
+        let mut results: Vec<Option<ImportResult>> = Vec::with_capacity(bundles.len());
+        let mut planned = Vec::new();
+        let mut renamed = HashSet::new();
+        let mut tx = self.registry.transaction();
+        for bundle in bundles {
+            let name = bundle.name.clone();
+            if let Err(e) = bundle.verify().and(FunctionName::parse(&name)) {
+                results.push(Some(ImportResult::failed(&name, e.to_string())));
+                continue;
+            }
+            let candidate = bundle.clone().into_metadata(name.clone());
+            let checked = match self.check_namespace(&candidate).await {
+                Ok(()) => self.check_quota(&candidate).await,
+                Err(e) => Err(e),
+            }
+            .and_then(|()| self.check_capability(&candidate));
+            if let Err(e) = checked {
+                results.push(Some(ImportResult::failed(&name, e.to_string())));
+                continue;
+            }
+            if activate_if_tests_pass {
+                let report = self.run_tests(&candidate, candidate.tests.clone()).await;
+                if !report.all_passed() {
+                    let e = FluxError::TestsFailed {
+                        name: name.clone(),
+                        reason: report.summary(),
+                    };
+                    results.push(Some(ImportResult::failed(&name, e.to_string())));
+                    continue;
+                }
+            }
+
+            let target = match self.registry.get(&name).await {
+                Err(_) => {
+                    tx.register(bundle.into_metadata(name.clone()));
+                    name.clone()
+                }
+                Ok(_) if strategy == ConflictStrategy::Skip => {
+                    results.push(Some(ImportResult::skipped(
+                        &name,
+                        "Function already exists",
+                    )));
+                    continue;
+                }
+                Ok(existing) if strategy == ConflictStrategy::Overwrite => {
+                    // 覆盖时保留原有 ID 和创建时间
+                    let mut function = bundle.into_metadata(name.clone());
+                    function.id = existing.id;
+                    function.created_at = existing.created_at;
+                    tx.upsert(function);
+                    name.clone()
+                }
+                Ok(_) => match self.rename_target(&name, &renamed).await {
+                    Ok(candidate) => {
+                        renamed.insert(candidate.clone());
+                        tx.register(bundle.into_metadata(candidate.clone()));
+                        candidate
+                    }
+                    Err(reason) => {
+                        results.push(Some(ImportResult::failed(&name, reason)));
+                        continue;
+                    }
+                },
+            };
+            planned.push((results.len(), name, target));
+            results.push(None);
+        }
+
+        if strategy == ConflictStrategy::Fail {
+            tx = tx.all_or_nothing();
+        }
+        let _guards = self.registry.lock_names(tx.names()).await;
+        for ((index, name, target), result) in planned.into_iter().zip(tx.commit().await) {
+            results[index] = Some(match result {
+                Ok(function) => {
+                    self.apply_registry_changes().await;
+                    self.compile_in_background(&function).await;
+                    ImportResult::imported(&name, &target)
+                }
+                Err(e) => ImportResult::failed(&name, e.to_string()),
+            });
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// 从目录加载函数，返回每个文件的处理结果
+    ///
+    /// `dry_run` 为 true 时完成全部解析、验证和冲突检测，但不注册任何函数。
+    /// 策略为 [`ConflictStrategy::Fail`] 时，只要有一个函数与已有函数冲突就不注册任何函数，
+    /// 冲突文件标记为失败，其余文件标记为跳过。目录加载不支持 [`ConflictStrategy::Rename`]。
+    pub async fn load_directory(
+        &self,
+        dir_path: impl AsRef<Path>,
+        strategy: ConflictStrategy,
+        dry_run: bool,
+    ) -> Result<DirectoryLoadResult> {
+        let dir_path = dir_path.as_ref();
+        if strategy == ConflictStrategy::Rename {
+            return Err(FluxError::ValidationError {
+                reason: "on_conflict=rename is not supported for directory loading".to_string(),
+            });
+        }
+
+        let scanned = self.loader.scan_directory(dir_path).await?;
+        self.load_scanned(&dir_path.display().to_string(), scanned, strategy, dry_run)
+            .await
+    }
+
+    /// 从 OCI 仓库拉取函数制品并加载，逐函数结果与目录加载相同
+    pub async fn load_from_oci(
+        &self,
+        source: &OciFunctionSource,
+        req: &OciLoadRequest,
+        strategy: ConflictStrategy,
+        dry_run: bool,
+    ) -> Result<OciLoadResult> {
+        let pull = source.pull(req).await?;
+        let load = match pull.artifact {
+            OciArtifact::Directory(dir) => self.load_directory(dir, strategy, dry_run).await?,
+            OciArtifact::Bundles(bundles) => {
+                self.load_bundles(&pull.reference, bundles, strategy, dry_run)
+                    .await?
+            }
+        };
+        Ok(OciLoadResult {
+            reference: pull.reference,
+            digest: pull.digest,
+            cached: pull.cached,
+            load,
+        })
+    }
+
+    /// 加载导出格式的函数包（例如 OCI 制品中的归档），结果与目录加载相同，每个函数包对应一个条目
+    pub async fn load_bundles(
+        &self,
+        source: &str,
+        bundles: Vec<FunctionBundle>,
+        strategy: ConflictStrategy,
+        dry_run: bool,
+    ) -> Result<DirectoryLoadResult> {
+        if strategy == ConflictStrategy::Rename {
+            return Err(FluxError::ValidationError {
+                reason: "on_conflict=rename is not supported for bundle loading".to_string(),
+            });
+        }
+        let scanned = bundles
+            .into_iter()
+            .map(|bundle| {
+                let path = PathBuf::from(&bundle.name);
+                match bundle.verify().and(FunctionName::parse(&bundle.name)) {
+                    Ok(_) => ScannedEntry::Function {
+                        path,
+                        script_type: bundle.script_type.clone(),
+                        function: Box::new(bundle.clone().into_metadata(bundle.name)),
+                    },
+                    Err(e) => ScannedEntry::Failed {
+                        path,
+                        name: Some(bundle.name),
+                        script_type: Some(bundle.script_type),
+                        error: e.to_string(),
+                    },
+                }
+            })
+            .collect();
+        self.load_scanned(source, scanned, strategy, dry_run).await
+    }
+
+    /// 按冲突策略注册扫描出的函数（见 [`Self::load_directory`]）
+    async fn load_scanned(
+        &self,
+        source: &str,
+        scanned: Vec<ScannedEntry>,
+        strategy: ConflictStrategy,
+        dry_run: bool,
+    ) -> Result<DirectoryLoadResult> {
+        let mut files = Vec::with_capacity(scanned.len());
+        let mut pending = Vec::new();
+        for entry in scanned {
+            match entry {
+                ScannedEntry::Skipped { path, reason } => files.push(FileLoadResult {
+                    path: path.display().to_string(),
+                    name: None,
+                    script_type: None,
+                    action: LoadAction::Skipped,
+                    reason: Some(reason),
+                }),
+                ScannedEntry::Failed {
+                    path,
+                    name,
+                    script_type,
+                    error,
+                } => files.push(FileLoadResult {
+                    path: path.display().to_string(),
+                    name,
+                    script_type,
+                    action: LoadAction::Failed,
+                    reason: Some(error),
+                }),
+                ScannedEntry::Function {
+                    path,
+                    script_type,
+                    function,
+                } => {
+                    let checked = match self.check_namespace(&function).await {
+                        Ok(()) => self.check_capability(&function),
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = checked {
+                        files.push(FileLoadResult {
+                            path: path.display().to_string(),
+                            name: Some(function.name.clone()),
+                            script_type: Some(script_type),
+                            action: LoadAction::Failed,
+                            reason: Some(e.to_string()),
+                        });
+                        continue;
+                    }
+                    let existing = self.registry.get(&function.name).await.ok();
+                    let (action, reason) = match (&existing, strategy) {
+                        (None, _) => (LoadAction::Registered, None),
+                        // 只有文件头变化时代码哈希不变，按元数据更新处理而不重新编译
+                        (Some(existing), ConflictStrategy::Overwrite)
+                            if existing.code == function.code
+                                && existing.package == function.package =>
+                        {
+                            (
+                                LoadAction::Updated,
+                                Some("Metadata updated; code unchanged".to_string()),
+                            )
+                        }
+                        (Some(_), ConflictStrategy::Overwrite) => (LoadAction::Updated, None),
+                        (Some(_), ConflictStrategy::Skip) => (
+                            LoadAction::SkippedDuplicate,
+                            Some("Function already exists".to_string()),
+                        ),
+                        (Some(_), _) => (
+                            LoadAction::Failed,
+                            Some(
+                                FluxError::FunctionAlreadyExists {
+                                    name: function.name.clone(),
+                                }
+                                .to_string(),
+                            ),
+                        ),
+                    };
+                    files.push(FileLoadResult {
+                        path: path.display().to_string(),
+                        name: Some(function.name.clone()),
+                        script_type: Some(script_type),
+                        action,
+                        reason,
+                    });
+                    pending.push((files.len() - 1, *function, existing));
+                }
+            }
+        }
+
+        let conflicts = pending
+            .iter()
+            .filter(|(index, ..)| files[*index].action == LoadAction::Failed)
+            .count();
+        let mut aborted = conflicts > 0;
+        if aborted {
+            for (index, ..) in &pending {
+                if files[*index].action == LoadAction::Registered {
+                    files[*index].action = LoadAction::Skipped;
+                    files[*index].reason = Some(format!(
+                        "Not loaded: {conflicts} name conflict(s) with on_conflict=fail"
+                    ));
+                }
+            }
+        } else if !dry_run {
+            // 全部文件在一个注册表事务中提交；on_conflict=fail 时任一失败则都不注册
+            let mut tx = self.registry.transaction();
+            let mut written = Vec::new();
+            for (index, function, existing) in pending {
+                match (files[index].action, existing) {
+                    (LoadAction::Registered, _) => {
+                        tx.register(function);
+                    }
+                    (LoadAction::Updated, Some(existing)) => {
+                        // 覆盖时保留原有 ID 和创建时间
+                        let mut function = function;
+                        function.id = existing.id;
+                        function.created_at = existing.created_at;
+                        tx.upsert(function);
+                    }
+                    _ => continue,
+                }
+                written.push(index);
+            }
+            if strategy == ConflictStrategy::Fail {
+                tx = tx.all_or_nothing();
+            }
+
+            let _guards = self.registry.lock_names(tx.names()).await;
+            for (index, result) in written.into_iter().zip(tx.commit().await) {
+                let file = &mut files[index];
+                match result {
+                    Ok(function) => {
+                        self.apply_registry_changes().await;
+                        self.compile_in_background(&function).await;
+                    }
+                    Err(e @ FluxError::TransactionAborted { .. }) => {
+                        aborted = true;
+                        file.action = LoadAction::Skipped;
+                        file.reason = Some(e.to_string());
+                    }
+                    Err(e) if e.is_registry_limit() => {
+                        file.action = LoadAction::Rejected;
+                        file.reason = Some(e.to_string());
+                    }
+                    Err(e) => {
+                        file.action = LoadAction::Failed;
+                        file.reason = Some(e.to_string());
+                    }
+                }
+            }
+        }
+
+        let summary = DirectoryLoadSummary::from_files(&files);
+        tracing::info!(
+            "{} directory {}: {} registered, {} updated, {} duplicates skipped, {} skipped, {} failed, {} rejected",
+            if dry_run { "Previewed" } else { "Loaded" },
+            source,
+            summary.registered,
+            summary.updated,
+            summary.skipped_duplicate,
+            summary.skipped,
+            summary.failed,
+            summary.rejected
+        );
+        Ok(DirectoryLoadResult {
+            directory: source.to_string(),
+            dry_run,
+            aborted,
+            files,
+            summary,
+        })
+    }
+
+    /// `name-2`、`name-3`... 中第一个未被占用（也未被本次导入使用）的名称
+    async fn rename_target(
+        &self,
+        name: &str,
+        taken: &HashSet<String>,
+    ) -> std::result::Result<String, String> {
+        for suffix in 2..=100 {
+            let candidate = format!("{name}-{suffix}");
+            if let Err(e) = FunctionName::parse(&candidate) {
+                return Err(e.to_string());
+            }
+            if !taken.contains(&candidate) && !self.registry.exists(&candidate).await {
+                return Ok(candidate);
+            }
+        }
+        Err("No available name to rename to".to_string())
+    }
+}
+
+/// 比较两个函数定义是否一致（忽略 ID 和时间戳）
+fn same_definition(a: &FunctionMetadata, b: &FunctionMetadata) -> bool {
+    a.code == b.code
+        && a.description == b.description
+        && a.timeout_ms == b.timeout_ms
+        && a.version == b.version
+        && a.dependencies == b.dependencies
+        && a.return_type == b.return_type
+        && a.coerce_output == b.coerce_output
+        && a.strict_return_type == b.strict_return_type
+        && a.retry_policy == b.retry_policy
+        && a.idempotent == b.idempotent
+        && a.labels == b.labels
+        && a.priority == b.priority
+        && a.package == b.package
+        && a.http_response == b.http_response
+        && a.input_template == b.input_template
+        && a.circuit_breaker == b.circuit_breaker
+        && a.tests == b.tests
+        && a.execution_hint == b.execution_hint
+        && serde_json::to_value(&a.parameters).ok() == serde_json::to_value(&b.parameters).ok()
 }
 
 #[async_trait::async_trait]
@@ -89,14 +1753,498 @@ impl Scheduler for SimpleScheduler {
         &self,
         function_name: &str,
         request: InvokeRequest,
+    ) -> Result<InvokeResponse> {
+        self.schedule_with(function_name, request, ScheduleOptions::default())
+            .await
+    }
+}
+
+impl SimpleScheduler {
+    /// 调度函数执行；函数仍在后台编译时按 `on_compiling` 等待或拒绝
+    ///
+    /// 执行前先获取全局调用名额（排队已满或超时时返回 `Overloaded`），再按优先级（请求覆盖函数默认值）
+    /// 排队获取执行许可，队列已满时返回 `QueueFull`。
+    /// 执行结束后（包括执行失败）按函数的 webhook 配置入队通知，投递不影响调用结果。
+    #[tracing::instrument(name = "schedule", skip(self, request, options))]
+    ///
+    /// 请求带有幂等键时，同一函数下重复的键等待或重放首次执行的结果，不会再次执行。
+    /// 请求带有会话 ID 时，调用结束后（包括调度错误）记入该会话的调用列表。
+    pub async fn schedule_with(
+        &self,
+        function_name: &str,
+        request: InvokeRequest,
+        options: ScheduleOptions,
+    ) -> Result<InvokeResponse> {
+        let Some(session_id) = request.session_id.clone() else {
+            return self
+                .schedule_deduplicated(function_name, request, options)
+                .await;
+        };
+        sessions::validate_session_id(&session_id)?;
+        let started = Instant::now();
+        let input = request.input.clone();
+        let invocation_id = options
+            .invocation_id
+            .clone()
+            .unwrap_or_else(scru128::new_string);
+        let version = options.version.clone();
+        let options = ScheduleOptions {
+            invocation_id: Some(invocation_id.clone()),
+            ..options
+        };
+        let result = self
+            .schedule_deduplicated(function_name, request, options)
+            .await;
+        // 按执行的版本取脱敏规则
+        let function = match &version {
+            Some(version) => self.registry.get_version(function_name, version).await,
+            None => self.registry.get(function_name).await,
+        };
+        self.sessions.record(
+            &session_id,
+            function_name,
+            function.as_ref().ok(),
+            &invocation_id,
+            &input,
+            result.as_ref(),
+            started.elapsed(),
+        );
+        result
+    }
+
+    /// 按幂等键去重后调度
+    async fn schedule_deduplicated(
+        &self,
+        function_name: &str,
+        request: InvokeRequest,
+        options: ScheduleOptions,
+    ) -> Result<InvokeResponse> {
+        match request.idempotency_key.clone() {
+            Some(key) => {
+                idempotency::validate_key(&key)?;
+                self.idempotency
+                    .run(function_name, &key, || {
+                        self.schedule_once(function_name, request, options)
+                    })
+                    .await
+            }
+            None => self.schedule_once(function_name, request, options).await,
+        }
+    }
+
+    /// 执行一次调度（不检查幂等键）
+    async fn schedule_once(
+        &self,
+        function_name: &str,
+        mut request: InvokeRequest,
+        options: ScheduleOptions,
     ) -> Result<InvokeResponse> {
         tracing::info!("Scheduling function: {}", function_name);
+        let arrived = Instant::now();
+        if let Some(key) = &request.affinity_key {
+            affinity::validate_key(key)?;
+        }
+        let invocation_id = options
+            .invocation_id
+            .clone()
+            .unwrap_or_else(scru128::new_string);
 
-        // 从注册表获取函数
-        let function = self.registry.get(function_name).await?;
+        // 先处理积压的注册表变更，刚更新的函数不会命中旧的缓存
+        self.apply_registry_changes().await;
+        // 从注册表获取函数（指定版本时获取该版本）
+        let latest = self.registry.get(function_name).await?;
+        // 弃用的函数按调用方计数；停用后按 `on_sunset` 拒绝，或改为调用最新版本的替代函数
+        if let Some(replacement) = self.check_deprecation(&latest, &options).await? {
+            let options = ScheduleOptions {
+                version: None,
+                redirected: true,
+                ..options
+            };
+            let mut response = Box::pin(self.schedule_once(&replacement, request, options)).await?;
+            response.redirected_from = Some(function_name.to_string());
+            return Ok(response);
+        }
+        let (mut function, pinned) = match options.version.as_deref() {
+            Some(version) if version != latest.version => (
+                self.registry.get_version(function_name, version).await?,
+                true,
+            ),
+            _ => (latest, false),
+        };
+        // 按请求捕获调用日志时，读取日志按该函数的脱敏规则和本次输入中的敏感值处理
+        self.logs
+            .attach_redactor(&invocation_id, &function, &request.input);
+        // 本地执行许可已占满或函数优先在节点执行时转发给节点；节点没有执行时继续在本地执行
+        if !options.forwarded
+            && !options.is_shadow()
+            && options.version.is_none()
+            && (peers::prefers_remote(&function) || self.admission.is_saturated())
+            && let Some(lease) = self.peers.select()
+            && let Some(mut response) = self.forward_to_peer(lease, &function, &request).await?
+        {
+            response.timing = std::mem::take(&mut response.timing).finish(arrived.elapsed());
+            return Ok(response);
+        }
+        // 函数正在排空时拒绝新调用；强制删除时通过守卫取消本次调用
+        let mut flight = self.in_flight.begin(function_name, function.revision)?;
+        // 全局调用名额在编译、写临时文件和启动进程之前获取，排队不超过函数的截止时间
+        let global_permit = self
+            .invocations
+            .acquire(arrived + Duration::from_millis(function.timeout_ms))
+            .await?;
+        // 影子调用不再触发镜像，避免镜像目标之间互相复制
+        let mirror = function
+            .mirror
+            .clone()
+            .filter(|mirror| !options.is_shadow() && mirror.samples(&invocation_id));
+        // 执行历史和镜像调用使用应用输入模板前的原始输入，重放和镜像时重新应用模板；
+        // 金丝雀探测不进入执行历史
+        let original_input = ((self.history.is_enabled() && !options.canary) || mirror.is_some())
+            .then(|| request.input.clone());
+        if let Some(template) = &function.input_template {
+            request.input = InputTemplate::parse(template)?.apply(&request.input)?;
+        }
+        let schemas = self.schemas_for(&function)?;
+        if let Some(schemas) = &schemas {
+            let violations = schemas.check_input(&request.input);
+            if !violations.is_empty() {
+                return Err(FluxError::InputSchemaViolation {
+                    name: function.name.clone(),
+                    violations,
+                });
+            }
+        }
+        // 熔断打开时直接拒绝，不再等待编译或执行；影子重放和镜像调用不参与熔断
+        let circuit = if options.is_shadow() {
+            CircuitPermit::untracked()
+        } else {
+            self.circuits.admit(&function)?
+        };
+        // 启用调试包的函数由运行时记录源代码树、环境变量名、工具链和进程输出
+        let trace = self.debug.trace_for(&function, options.is_shadow());
+        // 后台编译只针对最新版本，历史版本在执行时按需编译
+        if !pinned && let Err(e) = self.ensure_compiled(&function, options.on_compiling).await {
+            // 编译失败的调用同样保存调试包，编译器输出记为标准错误
+            if let Some(trace) = trace
+                && matches!(e, FluxError::CompilationError(_))
+            {
+                self.runtime.trace_compilation(&function, &trace);
+                trace.set_output("", &e.to_string());
+                let capture = Capture::new(
+                    &function,
+                    &invocation_id,
+                    &request.input,
+                    Err(&e),
+                    arrived.elapsed(),
+                    trace,
+                );
+                self.debug.capture(capture).await;
+            }
+            return Err(e);
+        }
+
+        // 命名空间的超时上限和并发调用数上限
+        let namespace_permit = self.namespaces.admit(&mut function)?;
+        let priority = request.priority.unwrap_or(function.priority);
+        let permit = self.admission.acquire(priority).await?;
+        if !options.is_shadow() {
+            self.runtime
+                .monitor()
+                .record_queue_wait(function_name, priority, permit.waited())
+                .await;
+        }
+
+        // 状态按函数名隔离，授权在调用结束时失效；影子重放和镜像调用只能读
+        let state = self.state.grant(&function.name, options.is_shadow());
+        // 截止时间从收到调用时算起，等待编译和排队的时间都计入
+        let deadline = arrived + Duration::from_millis(function.timeout_ms);
+        // 出站 HTTP 按函数的允许列表代理，影子重放和镜像调用不发起请求
+        let egress = self.egress.grant(&function, deadline, options.is_shadow());
+        let context = InvocationContext::new(&function, invocation_id.clone())
+            .with_caller(options.caller.clone())
+            .with_state(state.handle())
+            .with_egress(egress.handle())
+            .with_shadow(options.shadow)
+            .with_mirror(options.mirror_of.is_some())
+            .with_canary(options.canary)
+            .with_debug_trace(trace.clone())
+            .with_affinity(request.affinity_key.as_deref().map(AffinityRoute::new))
+            .with_disk_report(Some(DiskReport::default()))
+            .with_cpu_report(Some(CpuReport::default()))
+            .with_deadline(deadline);
 
-        // 执行函数
-        let response = self.runtime.execute(&function, &request).await?;
+        let started = Instant::now();
+        let mut result = tokio::select! {
+            result = self.execute_with_retries(&function, &request, &context) => result,
+            _ = flight.cancelled() => Ok(Self::cancelled_response(&function, started.elapsed())),
+        };
+        drop(flight);
+        drop(state);
+        let egress_calls = egress.calls();
+        drop(egress);
+        drop(permit);
+        drop(namespace_permit);
+        drop(global_permit);
+        if let Ok(response) = &mut result {
+            if let Some(affinity) = context.affinity() {
+                response.timing.instance = affinity.selection();
+            }
+            // 运行时在进程内组装的响应不带沙箱进程的 CPU 时间，取各次尝试记录的累计值
+            if let Some(cpu_ms) = context.cpu_report().and_then(CpuReport::cpu_time_ms) {
+                response.timing.cpu_ms = Some(cpu_ms);
+            }
+            response.timing = std::mem::take(&mut response.timing).finish(arrived.elapsed());
+            if !options.is_shadow() {
+                self.runtime
+                    .monitor()
+                    .record_timing(function_name, &response.timing)
+                    .await;
+            }
+        }
+        if let (Some(schemas), Ok(response)) = (&schemas, &mut result)
+            && response.status.is_success()
+        {
+            let violations = schemas.check_output(&response.output);
+            if !violations.is_empty() {
+                if !options.is_shadow() {
+                    self.runtime
+                        .monitor()
+                        .record_output_schema_violation(function_name)
+                        .await;
+                }
+                if function.strict_output_schema {
+                    result = Err(FluxError::OutputSchemaViolation {
+                        name: function.name.clone(),
+                        violations,
+                    });
+                } else {
+                    response.output_schema_violations = Some(violations);
+                }
+            }
+        }
+        circuit.record(matches!(&result, Ok(response) if response.status.is_success()));
+        if let Some(input) = &original_input {
+            self.history.record(
+                &function,
+                &invocation_id,
+                input,
+                result.as_ref(),
+                started.elapsed(),
+                &options,
+                egress_calls,
+                context.disk_report().and_then(DiskReport::usage),
+            );
+        }
+        if let Some(trace) = trace {
+            let capture = Capture::new(
+                &function,
+                &invocation_id,
+                &request.input,
+                result.as_ref(),
+                started.elapsed(),
+                trace,
+            );
+            self.debug.capture(capture).await;
+        }
+        // 影子重放和镜像调用不通知 webhook
+        if let Some(webhooks) = &function.webhooks
+            && !options.is_shadow()
+        {
+            let redactor = Redactor::for_function(&function).with_secrets_from(&request.input);
+            let payload = WebhookPayload::from_result(
+                function_name,
+                &invocation_id,
+                result.as_ref(),
+                &redactor,
+                started.elapsed(),
+                self.webhooks.config().max_payload_chars,
+            );
+            self.webhooks.enqueue(webhooks, payload);
+        }
+        if let (Some(mirror), Some(input), Ok(response)) = (mirror, original_input, &result) {
+            self.spawn_mirror(&function, mirror, &invocation_id, input, response);
+        }
+        result
+    }
+
+    /// 函数被强制删除时在途调用的结果
+    fn cancelled_response(function: &FunctionMetadata, elapsed: Duration) -> InvokeResponse {
+        InvokeResponse {
+            output: serde_json::json!({
+                "error": format!("Function {} was deleted during the invocation", function.name)
+            }),
+            execution_time_ms: elapsed.as_millis() as u64,
+            status: ExecutionStatus::Cancelled,
+            request_id: None,
+            attempts_made: 1,
+            succeeded_on_retry: false,
+            cold_start: false,
+            output_schema_violations: None,
+            backend: None,
+            replayed: false,
+            chaos_injected: false,
+            return_type_mismatch: false,
+            timing: InvocationTiming::in_process(elapsed, None),
+            termination: None,
+            execution_path: None,
+            peer: None,
+            redirected_from: None,
+        }
+    }
+
+    /// 执行一次尝试，启用故障注入且有规则命中时按规则延迟、失败、超时或破坏输出
+    async fn run_attempt(
+        &self,
+        function: &FunctionMetadata,
+        request: &InvokeRequest,
+        context: &InvocationContext,
+    ) -> Result<InvokeResponse> {
+        let Some(injection) = self.chaos.inject(&function.name) else {
+            return self.dispatch(function, request, context).await;
+        };
+
+        let started = Instant::now();
+        let context = context.clone().with_chaos_injected(true);
+        let mut response = match injection.action {
+            ChaosAction::Delay => {
+                tokio::time::sleep(injection.delay).await;
+                self.dispatch(function, request, &context).await?
+            }
+            ChaosAction::CorruptOutput => {
+                let mut response = self.dispatch(function, request, &context).await?;
+                response.output = chaos::corrupt_output(&response.output);
+                response
+            }
+            ChaosAction::Fail | ChaosAction::Timeout => {
+                if injection.action == ChaosAction::Timeout {
+                    // 睡过截止时间，调用方看到的耗时与真实超时一致
+                    tokio::time::sleep_until(
+                        (context.deadline() + Duration::from_millis(1)).into(),
+                    )
+                    .await;
+                }
+                let response = chaos::injected_response(injection.action, started.elapsed());
+                let execution_result = ExecutionResult {
+                    function_name: function.name.clone(),
+                    duration: started.elapsed(),
+                    success: false,
+                    memory_usage: 0,
+                    error_message: response.output["error"].as_str().map(str::to_string),
+                    error_origin: response.status.origin(),
+                    attempt: context.attempt,
+                    cold_start: false,
+                    chaos_injected: true,
+                    shadow: context.shadow(),
+                    mirror: context.mirror(),
+                    canary: context.canary(),
+                    test: context.test(),
+                    console: context.console(),
+                    execution_path: None,
+                };
+                if let Err(e) = self
+                    .runtime
+                    .monitor()
+                    .record_execution(execution_result)
+                    .await
+                {
+                    tracing::warn!("Failed to record performance data: {}", e);
+                }
+                response
+            }
+        };
+        response.chaos_injected = true;
+        Ok(response)
+    }
+
+    /// 配置了路由时由路由选择后端，否则在本调度器的运行时上执行
+    async fn dispatch(
+        &self,
+        function: &FunctionMetadata,
+        request: &InvokeRequest,
+        context: &InvocationContext,
+    ) -> Result<InvokeResponse> {
+        match &self.router {
+            Some(router) => router.dispatch(function, request, context).await,
+            None => {
+                self.runtime
+                    .execute_attempt(function, request, context)
+                    .await
+            }
+        }
+    }
+
+    /// 按重试策略执行函数，`context` 为首次尝试的上下文
+    async fn execute_with_retries(
+        &self,
+        function: &FunctionMetadata,
+        request: &InvokeRequest,
+        context: &InvocationContext,
+    ) -> Result<InvokeResponse> {
+        let function_name = function.name.as_str();
+
+        // 请求中的策略优先于函数默认策略；非幂等函数从不重试
+        let policy = request
+            .retry_policy
+            .clone()
+            .or_else(|| function.retry_policy.clone())
+            .filter(|_| function.idempotent)
+            .unwrap_or_default();
+        let max_attempts = policy.max_attempts.max(1);
+        let deadline = Duration::from_millis(
+            policy
+                .deadline_ms
+                .unwrap_or_else(|| function.timeout_ms.saturating_mul(max_attempts as u64)),
+        );
+        let started = Instant::now();
+
+        let mut attempt_function = function.clone();
+        let mut attempt = 1;
+        // 执行函数；调度错误（如校验失败）直接返回，不会重试
+        let mut response = self
+            .run_attempt(&attempt_function, request, context)
+            .await?;
+        let mut timing = response.timing.clone();
+
+        while attempt < max_attempts && policy.should_retry(&response.status) {
+            let backoff = policy.backoff_for(attempt);
+            let remaining = deadline.saturating_sub(started.elapsed());
+            if backoff >= remaining {
+                tracing::warn!(
+                    "Retry budget of {}ms exhausted for function {} after {} attempts",
+                    deadline.as_millis(),
+                    function_name,
+                    attempt
+                );
+                break;
+            }
+
+            tracing::info!(
+                "Retrying function {} (attempt {}/{}) after {}ms: {:?}",
+                function_name,
+                attempt + 1,
+                max_attempts,
+                backoff.as_millis(),
+                response.status
+            );
+            tokio::time::sleep(backoff).await;
+
+            // 单次执行超时不能超过剩余的总预算
+            let remaining_ms = (remaining - backoff).as_millis().min(u64::MAX as u128) as u64;
+            attempt_function.timeout_ms = function.timeout_ms.min(remaining_ms.max(1));
+            attempt += 1;
+            let attempt_context = context
+                .clone()
+                .with_attempt(attempt)
+                .with_deadline(Instant::now() + Duration::from_millis(attempt_function.timeout_ms));
+            response = self
+                .run_attempt(&attempt_function, request, &attempt_context)
+                .await?;
+            timing.absorb(&response.timing);
+        }
+
+        response.attempts_made = attempt;
+        response.timing = timing;
+        response.succeeded_on_retry = attempt > 1 && !policy.should_retry(&response.status);
 
         tracing::info!("Function {} scheduled and executed", function_name);
         Ok(response)
@@ -108,3 +2256,1409 @@ impl Default for SimpleScheduler {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::RetryPolicy;
+    use crate::runtime::sandbox::DEFAULT_MAX_CODE_BYTES;
+
+    fn failing_request() -> InvokeRequest {
+        // 示例 add 函数缺少参数时返回执行错误
+        InvokeRequest {
+            input: serde_json::json!({}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        let scheduler = SimpleScheduler::new();
+        let mut function = FunctionMetadata::new("add".to_string(), String::new());
+        function.retry_policy = Some(RetryPolicy {
+            max_attempts: 3,
+            backoff_ms: 50,
+            backoff_multiplier: 2.0,
+            ..Default::default()
+        });
+        scheduler.registry().register(function).await.unwrap();
+
+        let started = Instant::now();
+        let response = scheduler.schedule("add", failing_request()).await.unwrap();
+        let elapsed = started.elapsed();
+
+        assert!(matches!(response.status, ExecutionStatus::Error { .. }));
+        assert_eq!(response.attempts_made, 3);
+        assert!(!response.succeeded_on_retry);
+        // 两次等待：50ms + 100ms
+        assert!(elapsed >= Duration::from_millis(150), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1000), "{elapsed:?}");
+
+        let stats = scheduler
+            .runtime()
+            .monitor()
+            .get_function_stats("add")
+            .await
+            .unwrap();
+        assert_eq!(stats.total_calls, 3);
+        assert_eq!(stats.retry_attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_timing_phases_sum_to_total_across_retries() {
+        let scheduler = SimpleScheduler::new();
+        let mut function = FunctionMetadata::new("add".to_string(), String::new());
+        function.retry_policy = Some(RetryPolicy {
+            max_attempts: 2,
+            backoff_ms: 50,
+            ..Default::default()
+        });
+        scheduler.registry().register(function).await.unwrap();
+
+        let response = scheduler.schedule("add", failing_request()).await.unwrap();
+        let timing = &response.timing;
+        assert_eq!(response.attempts_made, 2);
+        // 内置函数在进程内执行，不编译也不启动进程
+        assert_eq!(timing.compile_ms, None);
+        assert_eq!(timing.spawn_ms, None);
+        assert!(timing.execute_ms.is_some());
+        // 重试退避计入排队
+        assert!(timing.queue_ms.unwrap() >= 50, "{timing:?}");
+        assert!(
+            timing.total_ms.abs_diff(timing.phases_ms()) <= 2,
+            "{timing:?}"
+        );
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json["timing"]["compile_ms"].is_null());
+        assert!(json["timing"]["total_ms"].is_u64());
+
+        let report = scheduler
+            .runtime()
+            .monitor()
+            .function_report("add", Duration::from_secs(3600), Duration::from_secs(60))
+            .await;
+        // 阶段耗时按调用记录，不影响调用数
+        assert_eq!(report.summary.invocations, 2);
+        assert!(report.phases.queue_p95_ms.unwrap() >= 50.0 * 0.95);
+        assert!(report.phases.execute_p95_ms.is_some());
+        assert!(report.phases.total_p95_ms.is_some());
+        assert_eq!(report.phases.compile_p95_ms, None);
+        assert_eq!(report.phases.spawn_p95_ms, None);
+    }
+
+    #[tokio::test]
+    async fn test_chaos_rules_tag_responses_and_stats() {
+        use chaos::{ChaosMatch, ChaosRuleRequest};
+
+        let scheduler = SimpleScheduler::new();
+        let function = FunctionMetadata::new("payment".to_string(), "return input".to_string());
+        scheduler.registry().register(function).await.unwrap();
+        let request = InvokeRequest {
+            input: serde_json::json!({"a": 1, "b": 2}),
+            ..failing_request()
+        };
+        let rule = |action| ChaosRuleRequest {
+            matcher: ChaosMatch {
+                function: "pay*".to_string(),
+            },
+            action,
+            delay_ms: None,
+            probability: 1.0,
+            ttl_secs: None,
+        };
+        let chaos = scheduler.chaos().clone();
+        chaos.set_enabled(true);
+
+        let fail = chaos.add_rule(rule(ChaosAction::Fail)).unwrap();
+        let response = scheduler
+            .schedule("payment", request.clone())
+            .await
+            .unwrap();
+        assert!(matches!(response.status, ExecutionStatus::Error { .. }));
+        assert!(response.chaos_injected);
+        chaos.remove_rule(&fail.id);
+
+        let corrupt = chaos.add_rule(rule(ChaosAction::CorruptOutput)).unwrap();
+        let response = scheduler
+            .schedule("payment", request.clone())
+            .await
+            .unwrap();
+        assert!(response.status.is_success());
+        assert!(response.output.is_string(), "{}", response.output);
+        assert!(response.chaos_injected);
+        chaos.remove_rule(&corrupt.id);
+
+        let response = scheduler.schedule("payment", request).await.unwrap();
+        assert!(response.status.is_success());
+        assert!(!response.chaos_injected);
+
+        let monitor = scheduler.runtime().monitor();
+        let stats = monitor.get_function_stats("payment").await.unwrap();
+        assert_eq!(stats.total_calls, 3);
+        assert_eq!(stats.chaos_injected, 2);
+        assert_eq!(monitor.chaos_injections().await["payment"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_for_non_idempotent_or_exhausted_budget() {
+        let scheduler = SimpleScheduler::new();
+        let mut function = FunctionMetadata::new("add".to_string(), String::new());
+        function.idempotent = false;
+        scheduler.registry().register(function).await.unwrap();
+
+        let mut request = failing_request();
+        request.retry_policy = Some(RetryPolicy {
+            max_attempts: 5,
+            backoff_ms: 10,
+            ..Default::default()
+        });
+        let response = scheduler.schedule("add", request.clone()).await.unwrap();
+        assert_eq!(response.attempts_made, 1);
+
+        // 等待时间超过总预算时停止重试
+        let mut function = scheduler.registry().get("add").await.unwrap();
+        function.idempotent = true;
+        scheduler.registry().upsert(function).await.unwrap();
+        request.retry_policy = Some(RetryPolicy {
+            max_attempts: 5,
+            backoff_ms: 200,
+            deadline_ms: Some(100),
+            ..Default::default()
+        });
+        let response = scheduler.schedule("add", request).await.unwrap();
+        assert_eq!(response.attempts_made, 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_duplicate_idempotency_keys_execute_once() {
+        let scheduler = SimpleScheduler::new();
+        // 非幂等函数（如发送邮件），监控中的调用次数即函数体的执行次数
+        let mut function = FunctionMetadata::new("add".to_string(), String::new());
+        function.idempotent = false;
+        scheduler.registry().register(function).await.unwrap();
+
+        let request = InvokeRequest {
+            input: serde_json::json!({"a": 1, "b": 2}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: Some("order-42".to_string()),
+            session_id: None,
+            affinity_key: None,
+        };
+        let invocations = (0..20).map(|_| {
+            let scheduler = scheduler.clone();
+            let request = request.clone();
+            tokio::spawn(async move { scheduler.schedule("add", request).await })
+        });
+        let mut replayed = 0;
+        for invocation in invocations.collect::<Vec<_>>() {
+            let response = invocation.await.unwrap().unwrap();
+            assert_eq!(response.output, serde_json::json!({"result": 3.0}));
+            replayed += response.replayed as usize;
+        }
+        assert_eq!(replayed, 19);
+
+        let calls = |scheduler: SimpleScheduler| async move {
+            scheduler
+                .runtime()
+                .monitor()
+                .get_function_stats("add")
+                .await
+                .unwrap()
+                .total_calls
+        };
+        assert_eq!(calls(scheduler.clone()).await, 1);
+
+        // 完成后的重复请求返回保存的结果，其他键正常执行
+        let response = scheduler.schedule("add", request.clone()).await.unwrap();
+        assert!(response.replayed);
+        let mut other = request;
+        other.idempotency_key = Some("order-43".to_string());
+        assert!(!scheduler.schedule("add", other).await.unwrap().replayed);
+        assert_eq!(calls(scheduler).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_input_template_reshapes_invocation_input() {
+        let scheduler = SimpleScheduler::new();
+        let mut function = FunctionMetadata::new("add".to_string(), "return input".to_string());
+        function.input_template =
+            Some(r#"{"a": "{{body.x | number}}", "b": "{{body.y | default(2)}}"}"#.to_string());
+        scheduler.registry().register(function).await.unwrap();
+
+        let request = InvokeRequest {
+            input: serde_json::json!({"body": {"x": "40"}}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+        let response = scheduler.schedule("add", request.clone()).await.unwrap();
+        assert_eq!(response.output, serde_json::json!({"result": 42.0}));
+
+        // 修改模板不需要修改代码或版本
+        let updated = scheduler
+            .update_function(
+                "add",
+                UpdateFunctionRequest {
+                    input_template: Some(
+                        r#"{"a": "{{body.x | number}}", "b": "{{body.z}}"}"#.into(),
+                    ),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.version, "1.0.0");
+        let err = scheduler.schedule("add", request).await.unwrap_err();
+        assert!(
+            matches!(&err, FluxError::ValidationError { reason } if reason.contains("'body.z'")),
+            "{err}"
+        );
+
+        let invalid = UpdateFunctionRequest {
+            input_template: Some("{{body}}".into()),
+            ..Default::default()
+        };
+        assert!(
+            scheduler
+                .update_function("add", invalid, None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failure_origins_by_scenario() {
+        use crate::functions::priority::Priority;
+        use crate::functions::{ErrorKind, ErrorOrigin, ResourceKind};
+        use crate::runtime::sandbox::{SandboxConfig, SandboxExecutor};
+        use chaos::{ChaosMatch, ChaosRuleRequest};
+
+        let python = which::which("python3").is_ok();
+        let scheduler = SimpleScheduler::new();
+        if python {
+            scheduler.runtime().attach_sandbox(Arc::new(
+                SandboxExecutor::new(SandboxConfig::default()).unwrap(),
+            ));
+        }
+        let mut functions = vec![
+            ("add", String::new()),
+            ("expr", "return a +".to_string()),
+            ("checked", "return input".to_string()),
+            ("templated", "return input".to_string()),
+            ("chaos-fail", "return input".to_string()),
+            ("chaos-timeout", "return input".to_string()),
+        ];
+        if python {
+            functions.extend([
+                ("py-raise", "def handler(input):\n    raise ValueError('bad')\n".to_string()),
+                ("py-exit", "import sys\ndef handler(input):\n    sys.exit(3)\n".to_string()),
+                (
+                    "py-init",
+                    "def init(context):\n    raise RuntimeError('no config')\ndef handler(input, context, state):\n    return state\n".to_string(),
+                ),
+                ("py-slow", "import time\ndef handler(input):\n    time.sleep(5)\n".to_string()),
+            ]);
+        }
+        for (name, code) in &functions {
+            let mut function = FunctionMetadata::new(name.to_string(), code.clone());
+            match *name {
+                "checked" => {
+                    function.input_schema = Some(serde_json::json!({"required": ["id"]}));
+                }
+                "templated" => function.input_template = Some(r#"{"a": "{{body.x}}"}"#.into()),
+                "chaos-timeout" | "py-slow" => function.timeout_ms = 300,
+                _ => {}
+            }
+            scheduler.registry().register(function).await.unwrap();
+        }
+        let chaos = scheduler.chaos().clone();
+        chaos.set_enabled(true);
+        for (function, action) in [
+            ("chaos-fail", ChaosAction::Fail),
+            ("chaos-timeout", ChaosAction::Timeout),
+        ] {
+            chaos
+                .add_rule(ChaosRuleRequest {
+                    matcher: ChaosMatch {
+                        function: function.to_string(),
+                    },
+                    action,
+                    delay_ms: None,
+                    probability: 1.0,
+                    ttl_secs: None,
+                })
+                .unwrap();
+        }
+
+        // 实际调用的失败：执行失败看响应状态的归属，调度失败看错误的归属
+        let mut scenarios = vec![
+            (
+                "add",
+                "built-in function rejects its input",
+                ErrorOrigin::UserCode,
+            ),
+            ("expr", "expression syntax error", ErrorOrigin::UserCode),
+            ("checked", "input violates input_schema", ErrorOrigin::Input),
+            (
+                "templated",
+                "input template misses a field",
+                ErrorOrigin::Input,
+            ),
+            ("missing", "function does not exist", ErrorOrigin::Input),
+            (
+                "chaos-fail",
+                "injected platform failure",
+                ErrorOrigin::Platform,
+            ),
+            ("chaos-timeout", "injected timeout", ErrorOrigin::Limit),
+        ];
+        if python {
+            scenarios.extend([
+                ("py-raise", "Python handler raises", ErrorOrigin::UserCode),
+                ("py-exit", "Python exits with code 3", ErrorOrigin::UserCode),
+                ("py-init", "Python init hook raises", ErrorOrigin::UserCode),
+                ("py-slow", "Python handler times out", ErrorOrigin::Limit),
+            ]);
+        }
+        for (function, scenario, expected) in scenarios {
+            let origin = match scheduler.schedule(function, failing_request()).await {
+                Ok(response) => response.status.origin(),
+                Err(e) => Some(e.origin()),
+            };
+            assert_eq!(origin, Some(expected), "{scenario}");
+        }
+
+        // 不易在测试中触发的失败：错误本身和由它生成的执行状态都带有归属
+        let errors = [
+            (
+                FluxError::CompilationError("expected `;`".into()),
+                ErrorOrigin::Platform,
+            ),
+            (
+                FluxError::Platform("Script execution failed: python9: not found".into()),
+                ErrorOrigin::Platform,
+            ),
+            (
+                FluxError::Io(std::io::Error::other("temp dir is read-only")),
+                ErrorOrigin::Platform,
+            ),
+            (
+                FluxError::Platform("Execution failed: Failed to load library".into()),
+                ErrorOrigin::Platform,
+            ),
+            (
+                FluxError::ResourceLimitExceeded {
+                    resource: ResourceKind::Memory,
+                },
+                ErrorOrigin::Limit,
+            ),
+            (
+                FluxError::QueueFull {
+                    priority: Priority::Normal,
+                    depth: 8,
+                },
+                ErrorOrigin::Limit,
+            ),
+            (
+                FluxError::NamespaceQuotaExceeded {
+                    namespace: "team".into(),
+                    reason: "concurrency".into(),
+                },
+                ErrorOrigin::Limit,
+            ),
+        ];
+        for (error, expected) in errors {
+            assert_eq!(error.origin(), expected, "{error}");
+            let status = ExecutionStatus::from_error(&error, error.to_string());
+            assert_eq!(status.origin(), Some(expected), "{error}");
+        }
+        assert!(matches!(
+            ExecutionStatus::from_error(&FluxError::CompilationError("x".into()), "x"),
+            ExecutionStatus::Error {
+                kind: ErrorKind::Compilation,
+                ..
+            }
+        ));
+
+        // 监控按归属统计失败次数
+        let failures = scheduler.runtime().monitor().failures_by_origin().await;
+        assert_eq!(failures["add"][&ErrorOrigin::UserCode], 1);
+        assert_eq!(failures["chaos-fail"][&ErrorOrigin::Platform], 1);
+    }
+
+    #[tokio::test]
+    async fn test_json_schemas_reject_input_and_report_output_violations() {
+        let scheduler = SimpleScheduler::new();
+        let mut function =
+            FunctionMetadata::new("checked".to_string(), "return \"ok\"".to_string());
+        function.input_schema = Some(serde_json::json!({
+            "type": "object",
+            "required": ["id"],
+            "properties": {"id": {"type": "integer"}}
+        }));
+        function.output_schema = Some(serde_json::json!({"type": "object", "required": ["total"]}));
+        scheduler.register_function(function).await.unwrap();
+        let invoke = |input| {
+            scheduler.schedule(
+                "checked",
+                InvokeRequest {
+                    input,
+                    retry_policy: None,
+                    priority: None,
+                    idempotency_key: None,
+                    session_id: None,
+                    affinity_key: None,
+                },
+            )
+        };
+
+        let err = invoke(serde_json::json!({"id": "x"})).await.unwrap_err();
+        assert!(
+            matches!(&err, FluxError::InputSchemaViolation { violations, .. }
+                if violations[0].path == "/id" && violations[0].keyword == "type"),
+            "{err}"
+        );
+
+        // 默认只报告输出违规，调用本身成功
+        let response = invoke(serde_json::json!({"id": 1})).await.unwrap();
+        let violations = response.output_schema_violations.unwrap();
+        assert_eq!(violations[0].keyword, "required");
+        let counts = scheduler
+            .runtime()
+            .monitor()
+            .output_schema_violations()
+            .await;
+        assert_eq!(counts.get("checked"), Some(&1));
+
+        // 严格模式下输出违规使调用失败；更新后按新修订号重新编译 schema
+        let update = UpdateFunctionRequest {
+            strict_output_schema: Some(true),
+            log_redaction: None,
+            circuit_breaker: None,
+            output_schema: Some(serde_json::json!({"type": "object", "required": ["result"]})),
+            ..Default::default()
+        };
+        scheduler
+            .update_function("checked", update, None)
+            .await
+            .unwrap();
+        assert!(invoke(serde_json::json!({"id": 1})).await.is_ok());
+        let update = UpdateFunctionRequest {
+            output_schema: Some(serde_json::json!({"type": "string"})),
+            ..Default::default()
+        };
+        scheduler
+            .update_function("checked", update, None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            invoke(serde_json::json!({"id": 1})).await,
+            Err(FluxError::OutputSchemaViolation { .. })
+        ));
+
+        let invalid = UpdateFunctionRequest {
+            input_schema: Some(serde_json::json!({"type": 5})),
+            ..Default::default()
+        };
+        assert!(matches!(
+            scheduler.update_function("checked", invalid, None).await,
+            Err(FluxError::ValidationError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_return_type_matrix_in_lenient_and_strict_modes() {
+        use serde_json::json;
+        // (声明的类型, 实际输出, coerce_output, 符合时的输出；None 表示不符合)
+        let cases = [
+            (
+                "serde_json::Value",
+                json!({"a": 1}),
+                false,
+                Some(json!({"a": 1})),
+            ),
+            ("MyStruct", json!("x"), false, Some(json!("x"))),
+            ("()", json!(null), false, Some(json!(null))),
+            ("()", json!(0), false, None),
+            ("()", json!({}), false, None),
+            ("i64", json!(3), false, Some(json!(3))),
+            ("i64", json!(3.0), false, Some(json!(3.0))),
+            ("i64", json!(3.5), false, None),
+            ("i64", json!("3"), false, None),
+            ("i64", json!("3"), true, Some(json!(3))),
+            ("i64", json!("3.5"), true, None),
+            ("i64", json!({"value": 3}), true, None),
+            ("f64", json!(2.5), false, Some(json!(2.5))),
+            ("f64", json!(2), false, Some(json!(2))),
+            ("f64", json!("2.5"), false, None),
+            ("f64", json!("2.5"), true, Some(json!(2.5))),
+            ("f64", json!("abc"), true, None),
+            ("bool", json!(true), false, Some(json!(true))),
+            ("bool", json!("true"), true, None),
+            ("String", json!("s"), false, Some(json!("s"))),
+            ("String", json!(1), true, None),
+            (
+                "HashMap<String, i64>",
+                json!({"a": 1}),
+                false,
+                Some(json!({"a": 1})),
+            ),
+            ("HashMap<String, i64>", json!([1]), false, None),
+            ("Vec<i64>", json!([1, 2]), false, Some(json!([1, 2]))),
+            ("Vec<i64>", json!([1, "2"]), false, None),
+            ("Vec<i64>", json!([1, "2"]), true, Some(json!([1, 2]))),
+            ("Vec<i64>", json!({"a": 1}), false, None),
+            ("Option<String>", json!(null), false, Some(json!(null))),
+            ("Option<String>", json!("s"), false, Some(json!("s"))),
+            ("Option<String>", json!(1), false, None),
+        ];
+
+        for strict in [false, true] {
+            for (declared, output, coerce, expected) in &cases {
+                let case = format!("{declared} / {output} / coerce={coerce} / strict={strict}");
+                let scheduler = SimpleScheduler::new();
+                let mut function =
+                    FunctionMetadata::new("echo".to_string(), "return input".to_string());
+                function.return_type = declared.to_string();
+                function.coerce_output = *coerce;
+                function.strict_return_type = strict;
+                scheduler.register_function(function).await.unwrap();
+
+                let response = scheduler
+                    .schedule(
+                        "echo",
+                        InvokeRequest {
+                            input: output.clone(),
+                            retry_policy: None,
+                            priority: None,
+                            idempotency_key: None,
+                            session_id: None,
+                            affinity_key: None,
+                        },
+                    )
+                    .await
+                    .unwrap();
+                let mismatches = scheduler.runtime().monitor().return_type_mismatches().await;
+
+                match expected {
+                    Some(expected) => {
+                        assert!(response.status.is_success(), "{case}");
+                        assert!(!response.return_type_mismatch, "{case}");
+                        assert_eq!(&response.output, expected, "{case}");
+                        assert!(mismatches.is_empty(), "{case}");
+                    }
+                    None if strict => {
+                        assert!(
+                            matches!(&response.status, ExecutionStatus::Error { .. }),
+                            "{case}"
+                        );
+                        assert!(
+                            response.output["error"]
+                                .as_str()
+                                .unwrap()
+                                .contains("does not match its return type"),
+                            "{case}"
+                        );
+                        assert_eq!(mismatches.get("echo"), Some(&1), "{case}");
+                    }
+                    None => {
+                        assert!(response.status.is_success(), "{case}");
+                        assert!(response.return_type_mismatch, "{case}");
+                        assert_eq!(&response.output, output, "{case}");
+                        assert_eq!(mismatches.get("echo"), Some(&1), "{case}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pinned_versions_execute_retained_code_until_pruned() {
+        let scheduler = SimpleScheduler::new().with_max_versions(2);
+        for (version, word) in [("1.0.0", "one"), ("2.0.0", "two"), ("3.0.0", "three")] {
+            let function = FunctionMetadata::new_with_version(
+                "versioned".to_string(),
+                format!("return \"{word}\""),
+                version.to_string(),
+            );
+            scheduler.upsert_function(function).await.unwrap();
+        }
+        let invoke = |version: Option<&str>| {
+            scheduler.schedule_with(
+                "versioned",
+                InvokeRequest {
+                    input: serde_json::json!({}),
+                    retry_policy: None,
+                    priority: None,
+                    idempotency_key: None,
+                    session_id: None,
+                    affinity_key: None,
+                },
+                ScheduleOptions {
+                    version: version.map(str::to_string),
+                    ..Default::default()
+                },
+            )
+        };
+
+        for (version, word) in [
+            (None, "three"),
+            (Some("2.0.0"), "two"),
+            (Some("3.0.0"), "three"),
+        ] {
+            let response = invoke(version).await.unwrap();
+            assert_eq!(response.output["result"], word, "{version:?}");
+        }
+        // 两个版本的缓存条目互不覆盖
+        assert_eq!(scheduler.runtime().cache().stats().await.size, 2);
+
+        let err = invoke(Some("1.0.0")).await.unwrap_err();
+        assert!(
+            matches!(&err, FluxError::VersionNotFound { available, .. } if available == &["2.0.0", "3.0.0"]),
+            "{err}"
+        );
+        assert!(matches!(
+            scheduler.delete_version("versioned", "3.0.0").await,
+            Err(FluxError::LatestVersion { .. })
+        ));
+
+        scheduler
+            .delete_version("versioned", "2.0.0")
+            .await
+            .unwrap();
+        assert!(matches!(
+            invoke(Some("2.0.0")).await,
+            Err(FluxError::VersionNotFound { .. })
+        ));
+        assert_eq!(scheduler.runtime().cache().stats().await.size, 1);
+        let versions = scheduler.registry().versions("versioned").await.unwrap();
+        assert_eq!(versions.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_interleaved_mutations_on_one_name_stay_consistent() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let scheduler = SimpleScheduler::new();
+        let writes = Arc::new(AtomicU64::new(0));
+        let tasks: Vec<_> = (0..8u64)
+            .map(|task| {
+                let scheduler = scheduler.clone();
+                let writes = writes.clone();
+                tokio::spawn(async move {
+                    let labels = HashMap::from([("writer".to_string(), task.to_string())]);
+                    for i in 0..125u64 {
+                        let mut function =
+                            FunctionMetadata::new("hot".to_string(), "return input".to_string());
+                        function.labels = labels.clone();
+                        let result = match (task + i) % 4 {
+                            0 => scheduler.register_function(function).await,
+                            1 => scheduler.upsert_function(function).await.map(drop),
+                            2 => scheduler.delete_function("hot", None).await.map(drop),
+                            _ => match scheduler.registry().get("hot").await {
+                                // 以刚读到的修订号更新，期间有其他写入时被拒绝
+                                Ok(current) => scheduler
+                                    .update_function(
+                                        "hot",
+                                        UpdateFunctionRequest {
+                                            labels: Some(labels.clone()),
+                                            ..Default::default()
+                                        },
+                                        Some(current.revision),
+                                    )
+                                    .await
+                                    .map(|updated| assert!(updated.revision > current.revision)),
+                                Err(e) => Err(e),
+                            },
+                        };
+                        if result.is_ok() {
+                            writes.fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        // 每次成功的变更恰好分配一个修订号，标签索引与注册表一致
+        let registry = scheduler.registry();
+        assert_eq!(registry.last_revision(), writes.load(Ordering::SeqCst));
+        let indexed = registry
+            .list_by_selector(&LabelSelector::parse("writer").unwrap())
+            .await;
+        match registry.get("hot").await {
+            Ok(function) => {
+                assert_eq!(indexed.len(), 1);
+                assert_eq!(indexed[0].revision, function.revision);
+            }
+            Err(_) => assert!(indexed.is_empty()),
+        }
+
+        scheduler
+            .upsert_function(FunctionMetadata::new(
+                "hot".to_string(),
+                "return input".to_string(),
+            ))
+            .await
+            .unwrap();
+        let stale = registry.get("hot").await.unwrap().revision;
+        scheduler
+            .update_function("hot", UpdateFunctionRequest::default(), Some(stale))
+            .await
+            .unwrap();
+        let err = scheduler
+            .delete_function("hot", Some(stale))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FluxError::RevisionMismatch { .. }), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_functions_must_select_a_defined_quota() {
+        use crate::runtime::resource::ResourceQuota;
+
+        let scheduler = SimpleScheduler::new();
+        let mut function = FunctionMetadata::new("hungry".to_string(), "return input".to_string());
+        function.resource_quota = Some("tight".to_string());
+        let err = scheduler
+            .register_function(function.clone())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown resource quota 'tight'"));
+
+        scheduler
+            .resources()
+            .create_quota(ResourceQuota {
+                name: "tight".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        scheduler.register_function(function).await.unwrap();
+
+        // 函数更新同样校验配额
+        let mut other = FunctionMetadata::new("hungry".to_string(), "return input".to_string());
+        other.resource_quota = Some("missing".to_string());
+        assert!(scheduler.upsert_function(other).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registration_rejects_oversized_code() {
+        let scheduler = SimpleScheduler::new();
+        let code = format!("return input;{}", " ".repeat(DEFAULT_MAX_CODE_BYTES));
+        let err = scheduler
+            .register_function(FunctionMetadata::new("huge".to_string(), code))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, FluxError::CodeTooLarge { limit, .. } if limit == DEFAULT_MAX_CODE_BYTES),
+            "{err}"
+        );
+        assert!(scheduler.registry().get("huge").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registration_requires_an_executable_script_type() {
+        let scheduler = SimpleScheduler::new();
+        let request = |input| InvokeRequest {
+            input,
+            ..failing_request()
+        };
+
+        // 不编译的调度器不能执行 Rust 函数；绕过注册校验的函数执行失败，不返回模拟的结果
+        let rust = FunctionMetadata::new(
+            "rusty".to_string(),
+            "fn handler(input: Value) -> Value { input }".to_string(),
+        );
+        let err = scheduler.register_function(rust.clone()).await.unwrap_err();
+        assert!(
+            matches!(&err, FluxError::UnsupportedScriptType { script_type, .. } if script_type == "rust"),
+            "{err}"
+        );
+        assert!(err.to_string().contains("compilation"), "{err}");
+        scheduler.registry().register(rust).await.unwrap();
+        let response = scheduler
+            .schedule("rusty", request(serde_json::json!(1)))
+            .await
+            .unwrap();
+        assert!(matches!(response.status, ExecutionStatus::Error { .. }));
+        assert!(
+            response.output["error"]
+                .as_str()
+                .unwrap()
+                .contains("compilation"),
+            "{}",
+            response.output
+        );
+
+        // 没有外部解释器时 Python 函数无法执行（内嵌引擎只执行 JavaScript）
+        let python = FunctionMetadata::new(
+            "evens-py".to_string(),
+            "def handler(input):\n    return [x for x in input['items'] if x % 2 == 0]\n"
+                .to_string(),
+        );
+        let err = scheduler.register_function(python).await.unwrap_err();
+        assert!(
+            matches!(&err, FluxError::UnsupportedScriptType { script_type, .. } if script_type == "python"),
+            "{err}"
+        );
+        assert!(scheduler.registry().get("evens-py").await.is_err());
+
+        let javascript = FunctionMetadata::new(
+            "evens".to_string(),
+            "function handler(input) {\n  return input.items.filter((x) => x % 2 === 0).map((x) => x * 10);\n}\n"
+                .to_string(),
+        );
+        #[cfg(not(feature = "pure"))]
+        {
+            let err = scheduler.register_function(javascript).await.unwrap_err();
+            assert!(err.to_string().contains("`pure`"), "{err}");
+            assert_eq!(
+                scheduler.capabilities()["javascript"],
+                ExecutionCapability::Unsupported
+            );
+        }
+        #[cfg(feature = "pure")]
+        {
+            scheduler.register_function(javascript).await.unwrap();
+            let response = scheduler
+                .schedule("evens", request(serde_json::json!({"items": [1, 2, 3, 4]})))
+                .await
+                .unwrap();
+            assert!(response.status.is_success(), "{:?}", response.status);
+            assert_eq!(response.output, serde_json::json!([20, 40]));
+            assert_eq!(
+                scheduler.capabilities()["javascript"],
+                ExecutionCapability::Embedded
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        use crate::functions::bundle::{ConflictStrategy, ImportPayload, ImportStatus};
+
+        let source = SimpleScheduler::new();
+        let mut function = FunctionMetadata::new(
+            "greet".to_string(),
+            "fn greet(input: Value) -> Value {\n    json!({\"hi\": input})\n}\n".to_string(),
+        );
+        function.description = "问候函数".to_string();
+        function.dependencies = vec!["serde_json".to_string()];
+        function.retry_policy = Some(RetryPolicy {
+            max_attempts: 2,
+            ..Default::default()
+        });
+        source.registry().register(function.clone()).await.unwrap();
+
+        // 通过 JSON 传输，模拟跨实例导出导入
+        let json = serde_json::to_string(&source.export_all().await).unwrap();
+        let payload: ImportPayload = serde_json::from_str(&json).unwrap();
+
+        // Rust 函数只能导入能编译执行的调度器
+        let target = SimpleScheduler::new()
+            .with_routing(&RoutingConfig::default())
+            .unwrap();
+        let results = target
+            .import_bundles(
+                payload.into_bundles().unwrap(),
+                ConflictStrategy::Fail,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].status, ImportStatus::Imported);
+
+        let imported = target.registry().get("greet").await.unwrap();
+        assert_eq!(imported.code.as_bytes(), function.code.as_bytes());
+        assert_ne!(imported.id, function.id);
+        assert!(same_definition(&imported, &function));
+
+        // 冲突策略
+        let bundle = target.export_function("greet").await.unwrap();
+        assert!(
+            target
+                .import_bundles(vec![bundle.clone()], ConflictStrategy::Fail, false)
+                .await
+                .is_err()
+        );
+        let results = target
+            .import_bundles(vec![bundle.clone()], ConflictStrategy::Skip, false)
+            .await
+            .unwrap();
+        assert_eq!(results[0].status, ImportStatus::Skipped);
+        let results = target
+            .import_bundles(vec![bundle.clone()], ConflictStrategy::Rename, false)
+            .await
+            .unwrap();
+        assert_eq!(results[0].imported_as.as_deref(), Some("greet-2"));
+
+        let mut tampered = bundle;
+        tampered.code.push_str("// tampered");
+        let results = target
+            .import_bundles(vec![tampered], ConflictStrategy::Overwrite, false)
+            .await
+            .unwrap();
+        assert_eq!(results[0].status, ImportStatus::Failed);
+        assert_eq!(
+            target.registry().get("greet").await.unwrap().code,
+            function.code
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_bundles_reports_like_directory_loading() {
+        let source = SimpleScheduler::new();
+        for name in ["alpha", "beta"] {
+            let code = format!("fn {name}() -> String {{ return \"{name}\".to_string(); }}");
+            source
+                .registry()
+                .register(FunctionMetadata::new(name.to_string(), code))
+                .await
+                .unwrap();
+        }
+        let mut bundles = source.export_all().await.functions;
+        bundles[1].code.push_str("// tampered");
+
+        let target = SimpleScheduler::new()
+            .with_routing(&RoutingConfig::default())
+            .unwrap();
+        let result = target
+            .load_bundles("oci", bundles.clone(), ConflictStrategy::Skip, false)
+            .await
+            .unwrap();
+        assert_eq!(result.directory, "oci");
+        assert_eq!((result.summary.registered, result.summary.failed), (1, 1));
+        let failed = result
+            .files
+            .iter()
+            .find(|file| file.action == LoadAction::Failed)
+            .unwrap();
+        assert!(failed.reason.as_ref().unwrap().contains("Content hash"));
+
+        let result = target
+            .load_bundles("oci", bundles, ConflictStrategy::Skip, false)
+            .await
+            .unwrap();
+        assert_eq!(result.summary.skipped_duplicate, 1);
+        assert!(
+            target
+                .load_bundles("oci", Vec::new(), ConflictStrategy::Rename, true)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_directory_dry_run_and_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["alpha", "beta"] {
+            std::fs::write(
+                dir.path().join(format!("{name}.rs")),
+                format!("fn {name}() -> String {{ return \"{name}\".to_string(); }}"),
+            )
+            .unwrap();
+        }
+        std::fs::write(dir.path().join("broken.rs"), "fn broken( {").unwrap();
+
+        let scheduler = SimpleScheduler::new()
+            .with_routing(&RoutingConfig::default())
+            .unwrap();
+        let mut existing = FunctionMetadata::new("alpha".to_string(), "fn old() {}".to_string());
+        existing.description = "kept".to_string();
+        scheduler
+            .registry()
+            .register(existing.clone())
+            .await
+            .unwrap();
+
+        // 预览：报告将执行的操作，但不注册任何函数
+        let preview = scheduler
+            .load_directory(dir.path(), ConflictStrategy::Overwrite, true)
+            .await
+            .unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(preview.summary.discovered, 3);
+        assert_eq!(preview.summary.updated, 1);
+        assert_eq!(preview.summary.registered, 1);
+        assert_eq!(preview.summary.failed, 1);
+        assert!(!scheduler.registry().exists("beta").await);
+
+        // fail：存在冲突时不注册任何函数
+        let aborted = scheduler
+            .load_directory(dir.path(), ConflictStrategy::Fail, false)
+            .await
+            .unwrap();
+        assert!(aborted.aborted);
+        assert_eq!(aborted.summary.registered, 0);
+        assert!(!scheduler.registry().exists("beta").await);
+
+        // skip：保留已有函数，只注册新函数
+        let skipped = scheduler
+            .load_directory(dir.path(), ConflictStrategy::Skip, false)
+            .await
+            .unwrap();
+        assert_eq!(skipped.summary.skipped_duplicate, 1);
+        assert_eq!(skipped.summary.registered, 1);
+        assert_eq!(skipped.files[0].action, LoadAction::SkippedDuplicate);
+        assert_eq!(
+            scheduler.registry().get("alpha").await.unwrap().code,
+            existing.code
+        );
+
+        // overwrite：更新定义，保留原有 ID
+        let overwritten = scheduler
+            .load_directory(dir.path(), ConflictStrategy::Overwrite, false)
+            .await
+            .unwrap();
+        assert_eq!(overwritten.summary.updated, 2);
+        let alpha = scheduler.registry().get("alpha").await.unwrap();
+        assert_eq!(alpha.id, existing.id);
+        assert!(alpha.code.contains("fn alpha()"));
+
+        assert!(
+            scheduler
+                .load_directory(dir.path(), ConflictStrategy::Rename, true)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reloading_header_only_changes_updates_metadata() {
+        use crate::runtime::compiler::RustCompiler;
+
+        let dir = tempfile::tempdir().unwrap();
+        let code = "fn hello() -> String { return \"hi\".to_string(); }\n";
+        let write = |header: &str| {
+            std::fs::write(dir.path().join("hello.rs"), format!("{header}\n{code}")).unwrap()
+        };
+        write(r#"// flux: {"timeout_ms": 1000}"#);
+        let scheduler = SimpleScheduler::new()
+            .with_routing(&RoutingConfig::default())
+            .unwrap();
+        let load = || scheduler.load_directory(dir.path(), ConflictStrategy::Overwrite, false);
+        assert_eq!(load().await.unwrap().summary.registered, 1);
+        let before = scheduler.registry().get("hello").await.unwrap();
+        assert_eq!(before.timeout_ms, 1000);
+
+        // 只修改文件头：作为元数据更新，编译键不变
+        write(r#"// flux: {"timeout_ms": 2000, "description": "greets"}"#);
+        let result = load().await.unwrap();
+        assert_eq!(result.files[0].action, LoadAction::Updated);
+        assert_eq!(
+            result.files[0].reason.as_deref(),
+            Some("Metadata updated; code unchanged")
+        );
+        let after = scheduler.registry().get("hello").await.unwrap();
+        assert_eq!(
+            (after.timeout_ms, after.description.as_str()),
+            (2000, "greets")
+        );
+        assert_eq!(after.id, before.id);
+        assert_eq!(
+            RustCompiler::compile_key(&after),
+            RustCompiler::compile_key(&before)
+        );
+        assert_eq!(after.header_fields.len(), 2);
+
+        // 代码变化时按普通更新处理
+        std::fs::write(dir.path().join("hello.rs"), code.replace("hi", "hello")).unwrap();
+        let result = load().await.unwrap();
+        assert_eq!(result.files[0].reason, None);
+        assert!(
+            scheduler
+                .registry()
+                .get("hello")
+                .await
+                .unwrap()
+                .header_fields
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_background_compilation_status() {
+        use crate::runtime::compiler::CompilerConfig;
+
+        // 使用必然失败的编译器，编译任务很快结束
+        let cache_dir = tempfile::tempdir().unwrap();
+        let runtime = SimpleRuntime::new_with_compiler_config(CompilerConfig {
+            rustc_path: Some("/bin/false".into()),
+            cache_dir: cache_dir.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        let scheduler = SimpleScheduler::with_runtime(Arc::new(runtime));
+        let request = || InvokeRequest {
+            input: serde_json::json!({}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+
+        // 编译进行中：reject 立即拒绝，wait 在函数超时后放弃
+        let mut function = FunctionMetadata::new("slow".to_string(), "fn a() {}".to_string());
+        function.timeout_ms = 50;
+        scheduler
+            .registry()
+            .register(function.clone())
+            .await
+            .unwrap();
+        let (_, hash) = RustCompiler::compile_key(&function);
+        assert!(
+            scheduler
+                .registry()
+                .start_compilation("slow", &hash, HandlerMode::RawJson)
+                .await
+        );
+        assert!(
+            !scheduler
+                .registry()
+                .start_compilation("slow", &hash, HandlerMode::RawJson)
+                .await
+        );
+        for on_compiling in [OnCompiling::Reject, OnCompiling::Wait] {
+            assert!(matches!(
+                scheduler
+                    .schedule_with(
+                        "slow",
+                        request(),
+                        ScheduleOptions {
+                            on_compiling,
+                            ..Default::default()
+                        }
+                    )
+                    .await,
+                Err(FluxError::StillCompiling { .. })
+            ));
+        }
+
+        // 注册时启动后台编译，失败后调用直接返回编译错误
+        let function = FunctionMetadata::new("broken".to_string(), "fn a() {}".to_string());
+        scheduler.register_function(function.clone()).await.unwrap();
+        assert!(matches!(
+            scheduler.schedule("broken", request()).await,
+            Err(FluxError::CompilationError(_))
+        ));
+        let record = scheduler.registry().compilation("broken").await.unwrap();
+        assert!(matches!(record.status, CompilationStatus::Failed(_)));
+
+        // 代码变化后重新编译，记录对应新代码
+        let mut changed = function.clone();
+        changed.code = "fn b() {}".to_string();
+        scheduler.upsert_function(changed.clone()).await.unwrap();
+        let record = scheduler.registry().compilation("broken").await.unwrap();
+        assert_eq!(record.source_hash, RustCompiler::compile_key(&changed).1);
+        assert_ne!(record.source_hash, RustCompiler::compile_key(&function).1);
+    }
+
+    /// 执行时先延迟 `delay_ms` 的函数（通过故障注入），用于模拟长时间运行的调用
+    async fn slow_scheduler(delay_ms: u64) -> Arc<SimpleScheduler> {
+        use chaos::{ChaosMatch, ChaosRuleRequest};
+
+        let scheduler =
+            Arc::new(SimpleScheduler::new().with_drain_timeout(Duration::from_secs(10)));
+        let mut function = FunctionMetadata::new("slow".to_string(), "return input".to_string());
+        function.timeout_ms = 30_000;
+        scheduler.register_function(function).await.unwrap();
+        scheduler.chaos().set_enabled(true);
+        scheduler
+            .chaos()
+            .add_rule(ChaosRuleRequest {
+                matcher: ChaosMatch {
+                    function: "slow".to_string(),
+                },
+                action: ChaosAction::Delay,
+                delay_ms: Some(delay_ms),
+                probability: 1.0,
+                ttl_secs: None,
+            })
+            .unwrap();
+        scheduler
+    }
+
+    fn slow_request() -> InvokeRequest {
+        InvokeRequest {
+            input: serde_json::json!({"n": 1}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        }
+    }
+
+    /// 在后台调用 `slow`，等到调用开始执行后返回
+    async fn invoke_in_background(
+        scheduler: &Arc<SimpleScheduler>,
+    ) -> JoinHandle<Result<InvokeResponse>> {
+        let invocation = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.schedule("slow", slow_request()).await })
+        };
+        while scheduler.drain_status("slow").in_flight == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        invocation
+    }
+
+    #[tokio::test]
+    async fn test_delete_waits_for_running_invocation() {
+        let scheduler = slow_scheduler(300).await;
+        let invocation = invoke_in_background(&scheduler).await;
+
+        let started = Instant::now();
+        let deletion = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.delete_function("slow", None).await })
+        };
+        while scheduler.drain_status("slow").state != drain::FunctionState::Draining {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        // 排空期间拒绝新调用，函数仍可查询
+        assert!(matches!(
+            scheduler.schedule("slow", slow_request()).await,
+            Err(FluxError::FunctionDraining { .. })
+        ));
+        assert_eq!(scheduler.drain_status("slow").in_flight, 1);
+        assert!(scheduler.registry().exists("slow").await);
+
+        // 在途调用正常完成后才删除
+        let response = invocation.await.unwrap().unwrap();
+        assert!(response.status.is_success(), "{:?}", response.status);
+        deletion.await.unwrap().unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(!scheduler.registry().exists("slow").await);
+        assert_eq!(scheduler.drain_status("slow"), DrainStatus::default());
+    }
+
+    #[tokio::test]
+    async fn test_forced_delete_cancels_running_invocation() {
+        let scheduler = slow_scheduler(20_000).await;
+        let invocation = invoke_in_background(&scheduler).await;
+
+        let started = Instant::now();
+        scheduler
+            .drain_and_delete("slow", None, true)
+            .await
+            .unwrap();
+        let response = invocation.await.unwrap().unwrap();
+        assert!(matches!(response.status, ExecutionStatus::Cancelled));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!scheduler.registry().exists("slow").await);
+
+        // 同名函数可以重新注册并正常调用
+        scheduler
+            .register_function(FunctionMetadata::new(
+                "slow".to_string(),
+                "return input".to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(scheduler.drain_status("slow"), DrainStatus::default());
+    }
+
+    #[tokio::test]
+    async fn test_infer_signature_after_code_update() {
+        use crate::runtime::sandbox::{SandboxConfig, SandboxExecutor};
+
+        // 更新时校验 Python 函数可以执行
+        if which::which("python3").is_err() {
+            return;
+        }
+        let scheduler = SimpleScheduler::new();
+        scheduler.runtime().attach_sandbox(Arc::new(
+            SandboxExecutor::new(SandboxConfig::default()).unwrap(),
+        ));
+        let mut function = FunctionMetadata::new(
+            "greet".to_string(),
+            "def handler(name, greeting='hi'):\n    return greeting + name\n".to_string(),
+        );
+        inference::fill_missing(&mut function);
+        function.return_type = "string".to_string();
+        scheduler.registry().register(function).await.unwrap();
+
+        // 代码更新后推断的参数过期，重新推断；声明的返回类型保持不变
+        let mut updated = scheduler.registry().get("greet").await.unwrap();
+        updated.code =
+            "def handler(name: str, times: int = 1) -> str:\n    return name * times\n".to_string();
+        let (updated, _) = scheduler.registry().upsert_if(updated, None).await.unwrap();
+
+        let stale = updated.revision + 1;
+        assert!(matches!(
+            scheduler.infer_signature("greet", Some(stale)).await,
+            Err(FluxError::RevisionMismatch { .. })
+        ));
+        let inferred = scheduler
+            .infer_signature("greet", Some(updated.revision))
+            .await
+            .unwrap();
+        let params: Vec<(&str, &str)> = inferred
+            .parameters
+            .iter()
+            .map(|param| (param.name.as_str(), param.param_type.as_str()))
+            .collect();
+        assert_eq!(params, vec![("name", "string"), ("times", "integer")]);
+        assert!(inferred.parameters_inferred && !inferred.return_type_inferred);
+        assert_eq!(inferred.return_type, "string");
+        assert!(inferred.revision > updated.revision);
+
+        // 再次推断没有变化时不写入新修订
+        let again = scheduler.infer_signature("greet", None).await.unwrap();
+        assert_eq!(again.revision, inferred.revision);
+    }
+
+    #[tokio::test]
+    async fn test_load_directory_reports_entries_rejected_by_registry_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["alpha", "beta", "gamma"] {
+            std::fs::write(
+                dir.path().join(format!("{name}.rs")),
+                format!("fn {name}() -> String {{ return \"{name}\".to_string(); }}"),
+            )
+            .unwrap();
+        }
+        let scheduler = SimpleScheduler::new()
+            .with_routing(&RoutingConfig::default())
+            .unwrap()
+            .with_registry_guardrails(RegistryGuardrailsConfig {
+                max_functions: Some(2),
+                ..Default::default()
+            });
+
+        let result = scheduler
+            .load_directory(dir.path(), ConflictStrategy::Skip, false)
+            .await
+            .unwrap();
+        assert_eq!(result.summary.registered, 2);
+        assert_eq!(result.summary.rejected, 1);
+        let rejected = result
+            .files
+            .iter()
+            .find(|file| file.action == LoadAction::Rejected)
+            .unwrap();
+        assert!(
+            rejected
+                .reason
+                .as_deref()
+                .unwrap()
+                .contains("at most 2 functions"),
+            "{rejected:?}"
+        );
+        assert_eq!(scheduler.registry().count().await, 2);
+    }
+}
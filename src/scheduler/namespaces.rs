@@ -0,0 +1,346 @@
+use crate::functions::inference;
+use crate::functions::name::{DEFAULT_NAMESPACE, parse_namespace};
+use crate::functions::{FluxError, FunctionMetadata, Result};
+use crate::runtime::expression::CodeType;
+use crate::runtime::script_cache::ScriptLanguage;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use utoipa::ToSchema;
+
+/// 命名空间的默认限制（`[namespaces.<name>]` 或创建命名空间时的请求体）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct NamespaceConfig {
+    /// 执行超时上限（毫秒），函数的 `timeout_ms` 更大时按该值执行
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_timeout_ms: Option<u64>,
+    /// 命名空间内的函数数上限（每个调度器配置分别计数）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_functions: Option<usize>,
+    /// 命名空间内同时执行的调用数上限，超出时拒绝（429）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_invocations: Option<usize>,
+    /// 允许的脚本类型（`rust`、`expression`、`javascript`、`python`），缺省不限制
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_script_types: Option<Vec<String>>,
+}
+
+/// 命名空间
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Namespace {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub config: NamespaceConfig,
+    /// 当前正在执行的调用数
+    pub running: usize,
+    /// 当前正在执行的测试用例调用数（不占用 `max_concurrent_invocations`）
+    pub running_tests: usize,
+}
+
+/// 创建命名空间的请求
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateNamespaceRequest {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: NamespaceConfig,
+}
+
+/// 函数的脚本类型：函数包按入口文件扩展名判断；单文件函数为 `expression`，
+/// 定义了 Python/JavaScript `handler` 的代码为 `python`/`javascript`，其他代码为 `rust`
+pub fn script_type(function: &FunctionMetadata) -> &'static str {
+    let extension = function
+        .package
+        .as_ref()
+        .and_then(|package| package.entrypoint.rsplit_once('.'))
+        .map(|(_, extension)| extension);
+    match extension {
+        Some("js" | "mjs" | "cjs") => "javascript",
+        Some("ts") => "typescript",
+        Some("py") => "python",
+        _ => match CodeType::detect(&function.code) {
+            CodeType::SimpleExpression => "expression",
+            CodeType::Program => match inference::script_language(&function.code) {
+                Some(ScriptLanguage::JavaScript) => "javascript",
+                Some(ScriptLanguage::Python) => "python",
+                None => "rust",
+            },
+        },
+    }
+}
+
+struct NamespaceEntry {
+    created_at: DateTime<Utc>,
+    config: NamespaceConfig,
+    running: Arc<AtomicUsize>,
+    running_tests: Arc<AtomicUsize>,
+}
+
+impl NamespaceEntry {
+    fn new(config: NamespaceConfig) -> Self {
+        Self {
+            created_at: Utc::now(),
+            config,
+            running: Arc::default(),
+            running_tests: Arc::default(),
+        }
+    }
+
+    fn snapshot(&self, name: &str) -> Namespace {
+        Namespace {
+            name: name.to_string(),
+            created_at: self.created_at,
+            config: self.config.clone(),
+            running: self.running.load(Ordering::Relaxed),
+            running_tests: self.running_tests.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 命名空间执行名额，释放时归还
+#[derive(Debug)]
+pub struct NamespacePermit {
+    running: Arc<AtomicUsize>,
+}
+
+impl Drop for NamespacePermit {
+    fn drop(&mut self) {
+        self.running.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// 所有命名空间及其默认限制，始终包含 `default`
+///
+/// 同一进程的所有调度器配置共享一个注册表：函数注册时校验命名空间存在、脚本类型和函数数上限，
+/// 执行时按命名空间限制超时和并发调用数。
+pub struct NamespaceRegistry {
+    namespaces: StdMutex<BTreeMap<String, NamespaceEntry>>,
+}
+
+impl std::fmt::Debug for NamespaceRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.list()).finish()
+    }
+}
+
+impl Default for NamespaceRegistry {
+    fn default() -> Self {
+        Self {
+            namespaces: StdMutex::new(BTreeMap::from([(
+                DEFAULT_NAMESPACE.to_string(),
+                NamespaceEntry::new(NamespaceConfig::default()),
+            )])),
+        }
+    }
+}
+
+impl NamespaceRegistry {
+    /// 应用配置文件中的命名空间（已存在的命名空间只更新限制）
+    pub fn configure(&self, configs: &BTreeMap<String, NamespaceConfig>) -> Result<()> {
+        let mut namespaces = self.namespaces.lock().unwrap_or_else(|e| e.into_inner());
+        for (name, config) in configs {
+            parse_namespace(name)?;
+            namespaces
+                .entry(name.clone())
+                .or_insert_with(|| NamespaceEntry::new(NamespaceConfig::default()))
+                .config = config.clone();
+        }
+        Ok(())
+    }
+
+    /// 创建命名空间
+    pub fn create(&self, name: &str, config: NamespaceConfig) -> Result<Namespace> {
+        parse_namespace(name)?;
+        let mut namespaces = self.namespaces.lock().unwrap_or_else(|e| e.into_inner());
+        if namespaces.contains_key(name) {
+            return Err(FluxError::NamespaceAlreadyExists {
+                name: name.to_string(),
+            });
+        }
+        let entry = NamespaceEntry::new(config);
+        let namespace = entry.snapshot(name);
+        namespaces.insert(name.to_string(), entry);
+        Ok(namespace)
+    }
+
+    pub fn get(&self, name: &str) -> Result<Namespace> {
+        self.namespaces
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .map(|entry| entry.snapshot(name))
+            .ok_or_else(|| FluxError::NamespaceNotFound {
+                name: name.to_string(),
+            })
+    }
+
+    /// 所有命名空间（按名称排序）
+    pub fn list(&self) -> Vec<Namespace> {
+        self.namespaces
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(name, entry)| entry.snapshot(name))
+            .collect()
+    }
+
+    /// 移除命名空间（不检查其中的函数，见 `SchedulerRegistry::delete_namespace`）；默认命名空间不能删除
+    pub fn remove(&self, name: &str) -> Result<Namespace> {
+        if name == DEFAULT_NAMESPACE {
+            return Err(FluxError::ValidationError {
+                reason: format!("The '{DEFAULT_NAMESPACE}' namespace cannot be deleted"),
+            });
+        }
+        self.namespaces
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(name)
+            .map(|entry| entry.snapshot(name))
+            .ok_or_else(|| FluxError::NamespaceNotFound {
+                name: name.to_string(),
+            })
+    }
+
+    /// 注册或更新函数前的校验；`existing` 为该命名空间中已有的其他函数数
+    pub fn check_function(&self, function: &FunctionMetadata, existing: usize) -> Result<()> {
+        let namespace = self.get(function.namespace())?;
+        check_script_type(&namespace, function)?;
+        if let Some(max) = namespace.config.max_functions
+            && existing >= max
+        {
+            return Err(FluxError::NamespaceQuotaExceeded {
+                namespace: namespace.name,
+                reason: format!("at most {max} functions are allowed"),
+            });
+        }
+        Ok(())
+    }
+
+    /// 执行前应用命名空间限制：校验脚本类型、按超时上限收紧函数超时并占用一个执行名额
+    pub fn admit(&self, function: &mut FunctionMetadata) -> Result<NamespacePermit> {
+        let namespaces = self.namespaces.lock().unwrap_or_else(|e| e.into_inner());
+        let (name, entry) = Self::apply_limits(&namespaces, function)?;
+        let limit = entry
+            .config
+            .max_concurrent_invocations
+            .unwrap_or(usize::MAX);
+        entry
+            .running
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |running| {
+                (running < limit).then_some(running + 1)
+            })
+            .map_err(|_| FluxError::NamespaceQuotaExceeded {
+                namespace: name,
+                reason: format!("at most {limit} concurrent invocations are allowed"),
+            })?;
+        Ok(NamespacePermit {
+            running: entry.running.clone(),
+        })
+    }
+
+    /// 测试用例的调用：与 [`admit`](Self::admit) 一样校验脚本类型和收紧超时，
+    /// 但单独计数，不占用命名空间的并发调用名额（测试运行本身限制了并发）
+    pub fn admit_test(&self, function: &mut FunctionMetadata) -> Result<NamespacePermit> {
+        let namespaces = self.namespaces.lock().unwrap_or_else(|e| e.into_inner());
+        let (_, entry) = Self::apply_limits(&namespaces, function)?;
+        entry.running_tests.fetch_add(1, Ordering::AcqRel);
+        Ok(NamespacePermit {
+            running: entry.running_tests.clone(),
+        })
+    }
+
+    fn apply_limits<'a>(
+        namespaces: &'a BTreeMap<String, NamespaceEntry>,
+        function: &mut FunctionMetadata,
+    ) -> Result<(String, &'a NamespaceEntry)> {
+        let name = function.namespace().to_string();
+        let entry = namespaces
+            .get(&name)
+            .ok_or_else(|| FluxError::NamespaceNotFound { name: name.clone() })?;
+        check_script_type(&entry.snapshot(&name), function)?;
+        if let Some(max) = entry.config.max_timeout_ms {
+            function.timeout_ms = function.timeout_ms.min(max);
+        }
+        Ok((name, entry))
+    }
+}
+
+fn check_script_type(namespace: &Namespace, function: &FunctionMetadata) -> Result<()> {
+    let script_type = script_type(function);
+    match &namespace.config.allowed_script_types {
+        Some(allowed) if !allowed.iter().any(|t| t.eq_ignore_ascii_case(script_type)) => {
+            Err(FluxError::ValidationError {
+                reason: format!(
+                    "Script type '{script_type}' is not allowed in namespace '{}' (allowed: {})",
+                    namespace.name,
+                    allowed.join(", ")
+                ),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespace_limits_apply_to_functions() {
+        let namespaces = NamespaceRegistry::default();
+        namespaces
+            .create(
+                "team-a",
+                NamespaceConfig {
+                    max_timeout_ms: Some(1000),
+                    max_functions: Some(1),
+                    max_concurrent_invocations: Some(1),
+                    allowed_script_types: Some(vec!["expression".to_string()]),
+                },
+            )
+            .unwrap();
+        assert!(matches!(
+            namespaces.create("team-a", NamespaceConfig::default()),
+            Err(FluxError::NamespaceAlreadyExists { .. })
+        ));
+
+        let mut function = FunctionMetadata::new("team-a/sum".to_string(), "return a + b".into());
+        function.timeout_ms = 30_000;
+        namespaces.check_function(&function, 0).unwrap();
+        assert!(matches!(
+            namespaces.check_function(&function, 1),
+            Err(FluxError::NamespaceQuotaExceeded { .. })
+        ));
+        let program = FunctionMetadata::new("team-a/prog".to_string(), "fn main() {}".into());
+        assert!(namespaces.check_function(&program, 0).is_err());
+        let missing = FunctionMetadata::new("team-b/sum".to_string(), "return 1".into());
+        assert!(matches!(
+            namespaces.check_function(&missing, 0),
+            Err(FluxError::NamespaceNotFound { .. })
+        ));
+
+        // 超时被收紧，并发名额在释放后归还
+        let permit = namespaces.admit(&mut function).unwrap();
+        assert_eq!(function.timeout_ms, 1000);
+        assert_eq!(namespaces.get("team-a").unwrap().running, 1);
+        assert!(matches!(
+            namespaces.admit(&mut function.clone()),
+            Err(FluxError::NamespaceQuotaExceeded { .. })
+        ));
+        // 测试用例的调用单独计数，不受并发调用上限限制
+        let test_permit = namespaces.admit_test(&mut function.clone()).unwrap();
+        let namespace = namespaces.get("team-a").unwrap();
+        assert_eq!((namespace.running, namespace.running_tests), (1, 1));
+        drop(test_permit);
+        drop(permit);
+        assert!(namespaces.admit(&mut function).is_ok());
+        assert_eq!(namespaces.get("team-a").unwrap().running_tests, 0);
+
+        assert!(namespaces.remove(DEFAULT_NAMESPACE).is_err());
+        namespaces.remove("team-a").unwrap();
+        assert_eq!(namespaces.list().len(), 1);
+    }
+}
@@ -0,0 +1,592 @@
+//! 简单联邦：把调用转发给其他 FluxFaaS 节点
+//!
+//! 配置了 `[peers] urls` 时，本地准入队列已满（所有执行许可都被占用），或函数带有
+//! `placement=remote-preferred` 标签时，调度器把调用转发给健康的节点（见
+//! [`SimpleScheduler::forward_to_peer`]），响应的 `peer` 为实际执行的节点，
+//! `timing.network_ms` 为往返耗时中不属于节点执行的部分；监控、执行历史和 webhook 由执行的节点记录。
+//! 节点没有执行调用时（连接失败或拒绝）在本地执行。
+//!
+//! 转发的请求带有 [`FORWARDED_BY_HEADER`]，收到转发请求的节点不再转发。连续失败达到
+//! `failure_threshold` 的节点熔断 `open_ms`，之后放行一次试探调用。`sync = true` 时函数注册
+//! 和更新后以导出包推送给所有节点（同名函数覆盖，删除不同步），同步状态见 `GET /peers`。
+
+use super::SimpleScheduler;
+use crate::client::{ClientError, FluxClient};
+use crate::functions::bundle::{ConflictStrategy, FunctionBundle, ImportPayload, ImportStatus};
+use crate::functions::registry::RegistryChange;
+use crate::functions::{FluxError, FunctionMetadata, InvokeRequest, InvokeResponse, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+/// 转发请求的来源节点，收到带有该请求头的调用时不再转发
+pub const FORWARDED_BY_HEADER: &str = "x-flux-forwarded-by";
+
+/// 函数的放置标签：值为 [`REMOTE_PREFERRED`] 时优先转发给节点执行
+pub const PLACEMENT_LABEL: &str = "placement";
+pub const REMOTE_PREFERRED: &str = "remote-preferred";
+
+/// 节点转发配置（`[peers]`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeersConfig {
+    /// 节点的基础地址，例如 `http://10.0.0.2:3000`
+    pub urls: Vec<String>,
+    /// 访问节点时使用的 API 密钥
+    pub api_key: Option<String>,
+    /// 健康检查间隔（毫秒）
+    pub health_interval_ms: u64,
+    /// 健康检查超时（毫秒）
+    pub health_timeout_ms: u64,
+    /// 转发调用的超时（毫秒）
+    pub forward_timeout_ms: u64,
+    /// 连续失败多少次后熔断该节点
+    pub failure_threshold: u32,
+    /// 熔断持续时间（毫秒），之后放行一次试探调用
+    pub open_ms: u64,
+    /// 函数注册和更新后推送给所有节点
+    pub sync: bool,
+}
+
+impl Default for PeersConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            api_key: None,
+            health_interval_ms: 5000,
+            health_timeout_ms: 2000,
+            forward_timeout_ms: 60_000,
+            failure_threshold: 3,
+            open_ms: 30_000,
+            sync: false,
+        }
+    }
+}
+
+impl PeersConfig {
+    /// 校验节点地址和阈值
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| {
+            Err(FluxError::ValidationError {
+                reason: format!("peers: {reason}"),
+            })
+        };
+        if let Some(url) = self
+            .urls
+            .iter()
+            .find(|url| !(url.starts_with("http://") || url.starts_with("https://")))
+        {
+            return invalid(format!(
+                "peer url '{url}' must start with http:// or https://"
+            ));
+        }
+        if self.failure_threshold == 0 {
+            return invalid("failure_threshold must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 函数是否标记为优先在节点执行
+pub fn prefers_remote(function: &FunctionMetadata) -> bool {
+    function.labels.get(PLACEMENT_LABEL).map(String::as_str) == Some(REMOTE_PREFERRED)
+}
+
+/// 函数推送到节点的结果
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct FunctionSyncStatus {
+    /// 推送的版本
+    pub version: String,
+    /// 导出包的内容哈希
+    pub content_hash: String,
+    pub synced: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// 节点的健康、熔断、转发统计和函数同步状态
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct PeerStatus {
+    pub url: String,
+    /// 最近一次健康检查是否通过（尚未检查时视为健康）
+    pub healthy: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_checked_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// 是否处于熔断
+    pub circuit_open: bool,
+    pub consecutive_failures: u32,
+    /// 正在转发的调用数
+    pub in_flight: usize,
+    /// 转发成功的调用数
+    pub forwarded: u64,
+    /// 转发失败（在本地执行）的调用数
+    pub failures: u64,
+    /// 转发往返中网络和节点排队之外开销的平均值（毫秒）
+    pub avg_network_ms: f64,
+    /// 节点上执行的平均耗时（毫秒）
+    pub avg_execution_ms: f64,
+    /// 按函数名的同步状态
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub sync: BTreeMap<String, FunctionSyncStatus>,
+}
+
+#[derive(Debug)]
+struct Peer {
+    client: FluxClient,
+    health: FluxClient,
+    status: PeerStatus,
+    open_until: Option<Instant>,
+    /// 熔断到期后已放行试探调用
+    probing: bool,
+    network_ms_total: u64,
+    execution_ms_total: u64,
+}
+
+impl Peer {
+    fn new(url: &str, config: &PeersConfig, node_id: &str) -> Self {
+        let mut client = FluxClient::new(url)
+            .with_header(FORWARDED_BY_HEADER, node_id)
+            // 未送达的转发直接在本地执行，不在这里重试
+            .with_retries(0, Duration::ZERO);
+        if let Some(api_key) = &config.api_key {
+            client = client.with_api_key(api_key);
+        }
+        let health = client
+            .clone()
+            .with_timeout(Duration::from_millis(config.health_timeout_ms));
+        Self {
+            client: client.with_timeout(Duration::from_millis(config.forward_timeout_ms)),
+            health,
+            status: PeerStatus {
+                url: url.trim_end_matches('/').to_string(),
+                healthy: true,
+                last_checked_at: None,
+                last_error: None,
+                circuit_open: false,
+                consecutive_failures: 0,
+                in_flight: 0,
+                forwarded: 0,
+                failures: 0,
+                avg_network_ms: 0.0,
+                avg_execution_ms: 0.0,
+                sync: BTreeMap::new(),
+            },
+            open_until: None,
+            probing: false,
+            network_ms_total: 0,
+            execution_ms_total: 0,
+        }
+    }
+
+    /// 是否可以接收转发：健康且未熔断（熔断到期后只放行一次试探）
+    fn available(&self, now: Instant) -> bool {
+        self.status.healthy
+            && self
+                .open_until
+                .is_none_or(|until| now >= until && !self.probing)
+    }
+}
+
+/// 选中的节点，drop 时减少在途转发数
+#[derive(Debug)]
+pub struct PeerLease {
+    peers: Arc<PeerSet>,
+    index: usize,
+    url: String,
+    client: FluxClient,
+}
+
+impl PeerLease {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for PeerLease {
+    fn drop(&mut self) {
+        if let Some(peer) = self.peers.lock().get_mut(self.index) {
+            peer.status.in_flight = peer.status.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+/// 已配置的节点（同一进程的调度器配置共享）
+#[derive(Debug)]
+pub struct PeerSet {
+    config: StdMutex<PeersConfig>,
+    peers: StdMutex<Vec<Peer>>,
+    /// 本节点的标识，随转发请求发送
+    node_id: String,
+}
+
+impl Default for PeerSet {
+    fn default() -> Self {
+        Self {
+            config: StdMutex::default(),
+            peers: StdMutex::default(),
+            node_id: scru128::new_string(),
+        }
+    }
+}
+
+impl PeerSet {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Peer>> {
+        self.peers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 替换节点列表和配置，统计和同步状态重新开始
+    pub fn configure(&self, config: &PeersConfig) -> Result<()> {
+        config.validate()?;
+        *self.lock() = config
+            .urls
+            .iter()
+            .map(|url| Peer::new(url, config, &self.node_id))
+            .collect();
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        Ok(())
+    }
+
+    pub fn config(&self) -> PeersConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// 所有节点的状态
+    pub fn status(&self) -> Vec<PeerStatus> {
+        let now = Instant::now();
+        self.lock()
+            .iter()
+            .map(|peer| {
+                let mut status = peer.status.clone();
+                status.circuit_open = peer.open_until.is_some_and(|until| now < until);
+                status
+            })
+            .collect()
+    }
+
+    /// 选出可用节点中在途转发最少的一个
+    pub fn select(self: &Arc<Self>) -> Option<PeerLease> {
+        let now = Instant::now();
+        let mut peers = self.lock();
+        let index = (0..peers.len())
+            .filter(|&index| peers[index].available(now))
+            .min_by_key(|&index| (peers[index].status.in_flight, peers[index].status.forwarded))?;
+        let peer = &mut peers[index];
+        peer.status.in_flight += 1;
+        peer.probing = peer.open_until.is_some();
+        Some(PeerLease {
+            peers: self.clone(),
+            index,
+            url: peer.status.url.clone(),
+            client: peer.client.clone(),
+        })
+    }
+
+    /// 记录转发成功，关闭熔断
+    fn record_success(&self, lease: &PeerLease, network_ms: u64, execution_ms: u64) {
+        if let Some(peer) = self.lock().get_mut(lease.index) {
+            peer.status.consecutive_failures = 0;
+            peer.open_until = None;
+            peer.probing = false;
+            peer.status.forwarded += 1;
+            peer.network_ms_total += network_ms;
+            peer.execution_ms_total += execution_ms;
+            let forwarded = peer.status.forwarded as f64;
+            peer.status.avg_network_ms = peer.network_ms_total as f64 / forwarded;
+            peer.status.avg_execution_ms = peer.execution_ms_total as f64 / forwarded;
+        }
+    }
+
+    /// 记录转发失败；`unhealthy` 时计入连续失败，达到阈值或试探失败时熔断
+    fn record_failure(&self, lease: &PeerLease, error: &str, unhealthy: bool) {
+        let config = self.config();
+        if let Some(peer) = self.lock().get_mut(lease.index) {
+            peer.status.failures += 1;
+            peer.status.last_error = Some(error.to_string());
+            peer.probing = false;
+            if !unhealthy {
+                return;
+            }
+            peer.status.consecutive_failures += 1;
+            if peer.status.consecutive_failures >= config.failure_threshold
+                || peer.open_until.is_some()
+            {
+                peer.open_until = Some(Instant::now() + Duration::from_millis(config.open_ms));
+            }
+        }
+    }
+
+    /// 检查所有节点的健康状态
+    pub async fn check_health(&self) {
+        let clients: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|peer| (peer.status.url.clone(), peer.health.clone()))
+            .collect();
+        for (url, client) in clients {
+            let result = client.health().await;
+            let mut peers = self.lock();
+            let Some(peer) = peers.iter_mut().find(|peer| peer.status.url == url) else {
+                continue;
+            };
+            peer.status.last_checked_at = Some(Utc::now());
+            match result {
+                Ok(_) => peer.status.healthy = true,
+                Err(e) => {
+                    if peer.status.healthy {
+                        tracing::warn!("Peer {} failed its health check: {}", url, e);
+                    }
+                    peer.status.healthy = false;
+                    peer.status.last_error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// 在后台按配置的间隔检查节点健康状态
+    pub fn spawn_health_checks(self: &Arc<Self>) -> JoinHandle<()> {
+        let peers = self.clone();
+        let interval = Duration::from_millis(self.config().health_interval_ms.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                peers.check_health().await;
+            }
+        })
+    }
+
+    /// 以导出包把函数推送给所有节点；内容与上次成功推送相同的节点跳过
+    pub async fn sync_function(&self, function: &FunctionMetadata) {
+        let bundle = FunctionBundle::from_metadata(function);
+        let targets: Vec<_> = self
+            .lock()
+            .iter()
+            .filter(|peer| {
+                // 节点之间互相同步时，收到推送的节点不会再推回相同的内容
+                !peer.status.sync.get(&function.name).is_some_and(|status| {
+                    status.synced && status.content_hash == bundle.content_hash
+                })
+            })
+            .map(|peer| (peer.status.url.clone(), peer.client.clone()))
+            .collect();
+        for (url, client) in targets {
+            let payload = ImportPayload::Bundle(Box::new(bundle.clone()));
+            let error = match client.import(&payload, ConflictStrategy::Overwrite).await {
+                Ok(results) => results
+                    .into_iter()
+                    .find(|result| result.status != ImportStatus::Imported)
+                    .map(|result| result.reason.unwrap_or_else(|| "not imported".to_string())),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(error) = &error {
+                tracing::warn!(
+                    "Failed to sync function {} to peer {}: {}",
+                    function.name,
+                    url,
+                    error
+                );
+            }
+            let mut peers = self.lock();
+            if let Some(peer) = peers.iter_mut().find(|peer| peer.status.url == url) {
+                peer.status.sync.insert(
+                    function.name.clone(),
+                    FunctionSyncStatus {
+                        version: function.version.clone(),
+                        content_hash: bundle.content_hash.clone(),
+                        synced: error.is_none(),
+                        error,
+                        at: Utc::now(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+impl SimpleScheduler {
+    /// 把调用转发给节点执行，返回节点的响应（`peer` 为该节点，`timing.network_ms` 为网络开销）
+    ///
+    /// 节点确定没有执行调用时（连接失败或节点拒绝）返回 `None`，由调用方在本地执行；
+    /// 请求已送达但没有拿到可用的响应时（例如超时）返回错误，避免同一调用执行两次。
+    pub async fn forward_to_peer(
+        &self,
+        lease: PeerLease,
+        function: &FunctionMetadata,
+        request: &InvokeRequest,
+    ) -> Result<Option<InvokeResponse>> {
+        // 幂等键和会话只在收到调用的节点处理
+        let mut request = request.clone();
+        request.idempotency_key = None;
+        request.session_id = None;
+        tracing::debug!("Forwarding {} to peer {}", function.name, lease.url());
+        let started = Instant::now();
+        let result = lease.client.invoke(&function.name, &request).await;
+        let round_trip = started.elapsed().as_millis() as u64;
+        let e = match result {
+            Ok(mut response) => {
+                let execution_ms = response.timing.total_ms.min(round_trip);
+                let network_ms = round_trip - execution_ms;
+                self.peers.record_success(&lease, network_ms, execution_ms);
+                response.peer = Some(lease.url().to_string());
+                response.timing.network_ms = Some(network_ms);
+                return Ok(Some(response));
+            }
+            Err(e) => e,
+        };
+        // 连接失败、超时和 5xx 计入节点熔断；4xx（函数不存在、节点排队已满等）不计入
+        let unhealthy = e.status().is_none_or(|status| status >= 500);
+        let error = e.to_string();
+        self.peers.record_failure(&lease, &error, unhealthy);
+        match e {
+            // 500 可能发生在函数执行之后（例如输出不符合 schema），其余服务端错误都在执行前
+            ClientError::Server { status, .. } if status != 500 => {
+                tracing::warn!(
+                    "Peer {} rejected {}, running locally: {}",
+                    lease.url(),
+                    function.name,
+                    error
+                );
+                Ok(None)
+            }
+            ClientError::Transport(e) if e.is_connect() => {
+                tracing::warn!(
+                    "Peer {} is unreachable, running locally: {}",
+                    lease.url(),
+                    e
+                );
+                Ok(None)
+            }
+            _ => Err(FluxError::Platform(format!(
+                "Forwarding to peer {} failed: {error}",
+                lease.url()
+            ))),
+        }
+    }
+
+    /// 在后台把注册和更新的函数推送给节点（`[peers] sync = true` 时启动）
+    pub fn spawn_peer_sync(self: &Arc<Self>) -> JoinHandle<()> {
+        let scheduler = self.clone();
+        let mut changes = self.registry.subscribe_changes();
+        tokio::spawn(async move {
+            loop {
+                let name = match changes.recv().await {
+                    Ok(RegistryChange::Registered(name) | RegistryChange::Updated { name, .. }) => {
+                        name
+                    }
+                    Ok(_) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(
+                            "Missed {} registry changes, re-syncing all functions to peers",
+                            skipped
+                        );
+                        for function in scheduler.registry.list().await {
+                            scheduler.peers.sync_function(&function).await;
+                        }
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+                };
+                if let Ok(function) = scheduler.registry.get(&name).await {
+                    scheduler.peers.sync_function(&function).await;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_skips_unhealthy_and_open_peers() {
+        let peers = Arc::new(PeerSet::default());
+        assert!(
+            peers
+                .configure(&PeersConfig {
+                    urls: vec!["ftp://peer".to_string()],
+                    ..Default::default()
+                })
+                .is_err()
+        );
+        peers
+            .configure(&PeersConfig {
+                urls: vec![
+                    "http://127.0.0.1:1".to_string(),
+                    "http://127.0.0.1:2/".to_string(),
+                ],
+                failure_threshold: 2,
+                open_ms: 60_000,
+                ..Default::default()
+            })
+            .unwrap();
+
+        // 在途转发少的节点优先
+        let first = peers.select().unwrap();
+        let second = peers.select().unwrap();
+        assert_ne!(first.url(), second.url());
+        assert_eq!(second.url(), "http://127.0.0.1:2");
+        drop(second);
+
+        // 第一个节点连续失败达到阈值后熔断，只选第二个；4xx 不计入
+        peers.record_failure(&first, "not found", false);
+        peers.record_failure(&first, "connection refused", true);
+        peers.record_failure(&first, "connection refused", true);
+        drop(first);
+        let status = peers.status();
+        assert!(status[0].circuit_open);
+        assert_eq!((status[0].failures, status[0].consecutive_failures), (3, 2));
+        for _ in 0..3 {
+            assert_eq!(peers.select().unwrap().url(), "http://127.0.0.1:2");
+        }
+
+        // 健康检查失败的节点不再被选中
+        peers.lock()[1].status.healthy = false;
+        assert!(peers.select().is_none());
+        assert!(status.iter().all(|peer| peer.in_flight == 0));
+    }
+
+    #[test]
+    fn test_open_circuit_allows_a_single_probe() {
+        let peers = Arc::new(PeerSet::default());
+        peers
+            .configure(&PeersConfig {
+                urls: vec!["http://127.0.0.1:1".to_string()],
+                failure_threshold: 1,
+                open_ms: 0,
+                ..Default::default()
+            })
+            .unwrap();
+        let lease = peers.select().unwrap();
+        peers.record_failure(&lease, "refused", true);
+        drop(lease);
+        // 熔断到期：放行一次试探，试探结束前不再放行
+        let probe = peers.select().unwrap();
+        assert!(peers.select().is_none());
+        peers.record_success(&probe, 3, 7);
+        drop(probe);
+        let status = &peers.status()[0];
+        assert!(!status.circuit_open);
+        assert_eq!(status.consecutive_failures, 0);
+        assert_eq!((status.avg_network_ms, status.avg_execution_ms), (3.0, 7.0));
+        assert!(peers.select().is_some());
+    }
+}
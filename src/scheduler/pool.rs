@@ -1,13 +1,26 @@
+//! 函数实例池
+//!
+//! 池内实例表为 `DashMap`，执行热路径只在同步代码中短暂持有分片锁；调用实例管理器前
+//! 所有守卫均已释放。扩缩容由 `scaling` 互斥锁串行化，这是唯一允许跨越 `.await`
+//! 持有的锁。锁顺序见 [`lock_order`]。
+
 use anyhow::Result;
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, RwLock};
 use tokio::time::interval;
 
-use crate::functions::{FunctionMetadata, InvokeRequest, InvokeResponse};
+use crate::functions::{
+    ExecutionStatus, FunctionMetadata, InstanceSelection, InvokeRequest, InvokeResponse,
+};
+use crate::runtime::affinity::{InstanceKeyStats, KeyCounts};
 use crate::runtime::instance::{InstanceConfig, InstanceManager, InstanceState};
+use crate::runtime::lock_order;
+use crate::runtime::rolling::{RollingSnapshot, RollingStats, bump};
+use crate::scheduler::balancer::HashRing;
 
 /// 实例池配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +47,27 @@ pub struct PoolConfig {
     pub load_balance_strategy: LoadBalanceStrategy,
     /// 实例配置
     pub instance_config: InstanceConfig,
+    /// 响应时间 EWMA 平滑系数（0-1，越大越偏向最近的请求）
+    #[serde(default = "default_response_time_alpha")]
+    pub response_time_alpha: f64,
+    /// 单个实例的并发能力提示，用于计算负载
+    #[serde(default = "default_instance_concurrency")]
+    pub instance_concurrency: u32,
+    /// 带亲和键的请求只在对应实例的负载低于该值时由它执行，否则按负载均衡策略选择
+    #[serde(default = "default_affinity_max_load")]
+    pub affinity_max_load: f64,
+}
+
+fn default_response_time_alpha() -> f64 {
+    0.3
+}
+
+fn default_affinity_max_load() -> f64 {
+    1.0
+}
+
+fn default_instance_concurrency() -> u32 {
+    10
 }
 
 impl Default for PoolConfig {
@@ -50,6 +84,9 @@ impl Default for PoolConfig {
             health_check_interval_secs: 30,
             load_balance_strategy: LoadBalanceStrategy::RoundRobin,
             instance_config: InstanceConfig::default(),
+            response_time_alpha: default_response_time_alpha(),
+            instance_concurrency: default_instance_concurrency(),
+            affinity_max_load: default_affinity_max_load(),
         }
     }
 }
@@ -101,8 +138,14 @@ pub struct PoolInstance {
     pub active_connections: u32,
     /// 最后活动时间
     pub last_activity: chrono::DateTime<chrono::Utc>,
-    /// 平均响应时间（毫秒）
+    /// 平均响应时间（毫秒，EWMA）
     pub avg_response_time_ms: f64,
+    /// 总请求数
+    pub total_requests: u64,
+    /// 成功请求数
+    pub successful_requests: u64,
+    /// 失败请求数
+    pub failed_requests: u64,
     /// 健康状态
     pub is_healthy: bool,
     /// 实例创建时间
@@ -119,11 +162,13 @@ pub struct FunctionPool {
     /// 池状态
     state: Arc<RwLock<PoolState>>,
     /// 池中的实例
-    instances: Arc<RwLock<HashMap<String, PoolInstance>>>,
+    instances: Arc<DashMap<String, PoolInstance>>,
     /// 实例管理器
     instance_manager: Arc<InstanceManager>,
-    /// 负载均衡器状态
-    load_balancer_state: Arc<RwLock<LoadBalancerState>>,
+    /// 负载均衡器状态（只在同步代码中持有）
+    load_balancer_state: StdMutex<LoadBalancerState>,
+    /// 扩缩容互斥锁，保证实例数检查和增删实例不会交错
+    scaling: Mutex<()>,
     /// 扩缩容历史
     scaling_history: Arc<RwLock<VecDeque<ScalingEvent>>>,
     /// 最后扩容时间
@@ -134,6 +179,17 @@ pub struct FunctionPool {
     health_check_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
     /// 自动扩缩容任务句柄
     auto_scaling_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    /// 池级执行统计（不随实例缩容丢失）
+    execution_totals: Arc<RwLock<ExecutionTotals>>,
+}
+
+/// 池级累计执行统计
+#[derive(Debug, Clone, Default)]
+struct ExecutionTotals {
+    total_requests: u64,
+    successful_requests: u64,
+    failed_requests: u64,
+    response_times: RollingStats,
 }
 
 /// 负载均衡器状态
@@ -143,6 +199,12 @@ pub struct LoadBalancerState {
     pub round_robin_counter: usize,
     /// 随机种子
     pub random_seed: u64,
+    /// 亲和键的一致性哈希环（包含不健康的实例，健康状态变化不改变键的归属）
+    pub affinity_ring: HashRing,
+    /// 构建哈希环时的实例ID（已排序），实例增减后重建
+    pub affinity_members: Vec<String>,
+    /// 各实例上亲和键的请求数
+    pub affinity_keys: HashMap<String, KeyCounts>,
 }
 
 /// 扩缩容事件
@@ -186,12 +248,14 @@ pub struct PoolExecutionStats {
     pub successful_requests: u64,
     /// 失败请求数
     pub failed_requests: u64,
-    /// 平均响应时间（毫秒）
+    /// 平均响应时间（毫秒，池内全部请求的累计均值）
     pub avg_response_time_ms: f64,
     /// 最大响应时间（毫秒）
     pub max_response_time_ms: u64,
-    /// 最小响应时间（毫秒）
+    /// 最小响应时间（毫秒，没有请求时为 0）
     pub min_response_time_ms: u64,
+    /// 响应时间的计数、均值、标准差和最值（毫秒）
+    pub response_time: RollingSnapshot,
     /// 当前负载
     pub current_load: f64,
     /// 活跃连接数
@@ -200,6 +264,9 @@ pub struct PoolExecutionStats {
     pub healthy_instances: u32,
     /// 总实例数
     pub total_instances: u32,
+    /// 各实例的亲和键数和请求最多的键（按实例ID排序，没有带亲和键的请求时为空）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub affinity: Vec<InstanceKeyStats>,
 }
 
 impl FunctionPool {
@@ -213,14 +280,16 @@ impl FunctionPool {
             function_metadata,
             config,
             state: Arc::new(RwLock::new(PoolState::Initializing)),
-            instances: Arc::new(RwLock::new(HashMap::new())),
+            instances: Arc::new(DashMap::new()),
             instance_manager,
-            load_balancer_state: Arc::new(RwLock::new(LoadBalancerState::default())),
+            load_balancer_state: StdMutex::new(LoadBalancerState::default()),
+            scaling: Mutex::new(()),
             scaling_history: Arc::new(RwLock::new(VecDeque::new())),
             last_scale_up: Arc::new(RwLock::new(None)),
             last_scale_down: Arc::new(RwLock::new(None)),
             health_check_handle: Arc::new(Mutex::new(None)),
             auto_scaling_handle: Arc::new(Mutex::new(None)),
+            execution_totals: Arc::new(RwLock::new(ExecutionTotals::default())),
         };
 
         // 初始化池
@@ -253,12 +322,14 @@ impl FunctionPool {
                 active_connections: 0,
                 last_activity: chrono::Utc::now(),
                 avg_response_time_ms: 0.0,
+                total_requests: 0,
+                successful_requests: 0,
+                failed_requests: 0,
                 is_healthy: true,
                 created_at: chrono::Utc::now(),
             };
 
-            let mut instances = self.instances.write().await;
-            instances.insert(instance_id, pool_instance);
+            self.instances.insert(instance_id, pool_instance);
 
             tracing::info!(
                 "Created initial instance {}/{} for function: {}",
@@ -269,10 +340,7 @@ impl FunctionPool {
         }
 
         // 更新状态为运行中
-        {
-            let mut state = self.state.write().await;
-            *state = PoolState::Running;
-        }
+        self.set_state(PoolState::Running).await;
 
         // 启动健康检查
         self.start_health_check().await;
@@ -293,70 +361,143 @@ impl FunctionPool {
         let start_time = Instant::now();
 
         // 选择实例
-        let instance_id = self.select_instance().await?;
+        let (instance_id, affinity_honored) = self
+            .select_instance(request.affinity_key.as_deref())
+            .await?;
 
         // 更新实例负载
-        self.update_instance_load(&instance_id, true).await;
+        self.update_instance_load(&instance_id, true);
 
         // 执行请求
-        let result = self
+        let mut result = self
             .instance_manager
             .execute_instance(&instance_id, request)
             .await;
+        if let (Ok(response), Some(affinity_honored)) = (&mut result, affinity_honored) {
+            response.timing.instance = Some(InstanceSelection {
+                instance_id: instance_id.clone(),
+                affinity_honored,
+            });
+        }
 
         // 更新实例负载
-        self.update_instance_load(&instance_id, false).await;
+        self.update_instance_load(&instance_id, false);
 
         // 更新统计信息
         let execution_time = start_time.elapsed();
-        self.update_execution_stats(&result, execution_time).await;
+        self.update_execution_stats(&instance_id, &result, execution_time)
+            .await;
 
         result
     }
 
-    /// 选择实例进行负载均衡
-    async fn select_instance(&self) -> Result<String> {
-        let instances = self.instances.read().await;
+    /// 设置池状态
+    async fn set_state(&self, state: PoolState) {
+        *lock_order::acquire("pool.state", self.state.write()).await = state;
+    }
 
-        if instances.is_empty() {
+    /// 选择实例进行负载均衡（基于实例表快照，不持有分片锁）
+    ///
+    /// 给出亲和键时优先选择键映射到的实例，该实例不健康或负载达到 `affinity_max_load` 时按
+    /// 负载均衡策略选择；同时返回是否由键映射到的实例执行（没有亲和键时为 `None`）。
+    async fn select_instance(&self, affinity_key: Option<&str>) -> Result<(String, Option<bool>)> {
+        if self.instances.is_empty() {
             return Err(anyhow::anyhow!("No instances available in pool"));
         }
 
-        let healthy_instances: Vec<_> = instances
+        let snapshot: Vec<PoolInstance> = self
+            .instances
+            .iter()
+            .map(|instance| instance.clone())
+            .collect();
+        let mut healthy_instances: Vec<PoolInstance> = snapshot
             .iter()
-            .filter(|(_, instance)| instance.is_healthy)
+            .filter(|instance| instance.is_healthy)
+            .cloned()
             .collect();
 
         if healthy_instances.is_empty() {
             return Err(anyhow::anyhow!("No healthy instances available in pool"));
         }
 
+        if let Some(key) = affinity_key {
+            let mapped = self.affinity_target(&snapshot, key);
+            let available = healthy_instances.iter().any(|instance| {
+                Some(&instance.instance_id) == mapped.as_ref()
+                    && instance.current_load < self.config.affinity_max_load
+            });
+            if let Some(instance_id) = mapped.filter(|_| available) {
+                self.record_affinity(&instance_id, key);
+                return Ok((instance_id, Some(true)));
+            }
+        }
+
+        // 分片迭代顺序不固定，按实例ID排序使轮询稳定
+        healthy_instances.sort_by(|a, b| a.instance_id.cmp(&b.instance_id));
+
         let selected_id = match self.config.load_balance_strategy {
-            LoadBalanceStrategy::RoundRobin => self.select_round_robin(&healthy_instances).await,
-            LoadBalanceStrategy::Random => self.select_random(&healthy_instances).await,
+            LoadBalanceStrategy::RoundRobin => self.select_round_robin(&healthy_instances),
+            LoadBalanceStrategy::Random => self.select_random(&healthy_instances),
             LoadBalanceStrategy::LeastConnections => {
-                self.select_least_connections(&healthy_instances).await
+                self.select_least_connections(&healthy_instances)
             }
-            LoadBalanceStrategy::LeastLoad => self.select_least_load(&healthy_instances).await,
+            LoadBalanceStrategy::LeastLoad => self.select_least_load(&healthy_instances),
             LoadBalanceStrategy::FastestResponse => {
-                self.select_fastest_response(&healthy_instances).await
+                self.select_fastest_response(&healthy_instances)
             }
         };
 
-        Ok(selected_id)
+        if let Some(key) = affinity_key {
+            self.record_affinity(&selected_id, key);
+        }
+        Ok((selected_id, affinity_key.map(|_| false)))
+    }
+
+    /// 亲和键映射到的实例；实例增减后重建哈希环，并丢弃已移除实例的键统计
+    fn affinity_target(&self, instances: &[PoolInstance], key: &str) -> Option<String> {
+        let mut members: Vec<String> = instances
+            .iter()
+            .map(|instance| instance.instance_id.clone())
+            .collect();
+        members.sort();
+        let mut state = self.balancer();
+        if state.affinity_members != members {
+            state.affinity_ring = HashRing::new(members.iter().map(|id| (id.as_str(), 100)));
+            state
+                .affinity_keys
+                .retain(|instance_id, _| members.binary_search(instance_id).is_ok());
+            state.affinity_members = members;
+        }
+        state.affinity_ring.get(key).map(str::to_string)
+    }
+
+    /// 记录实例执行了一次带亲和键的请求
+    fn record_affinity(&self, instance_id: &str, key: &str) {
+        self.balancer()
+            .affinity_keys
+            .entry(instance_id.to_string())
+            .or_default()
+            .record(key);
+    }
+
+    /// 负载均衡器状态（只在同步代码中持有）
+    fn balancer(&self) -> std::sync::MutexGuard<'_, LoadBalancerState> {
+        self.load_balancer_state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
     }
 
     /// 轮询选择实例
-    async fn select_round_robin(&self, instances: &[(&String, &PoolInstance)]) -> String {
-        let mut state = self.load_balancer_state.write().await;
+    fn select_round_robin(&self, instances: &[PoolInstance]) -> String {
+        let mut state = self.balancer();
         let index = state.round_robin_counter % instances.len();
         state.round_robin_counter = (state.round_robin_counter + 1) % instances.len();
-        instances[index].0.clone()
+        instances[index].instance_id.clone()
     }
 
     /// 随机选择实例
-    async fn select_random(&self, instances: &[(&String, &PoolInstance)]) -> String {
-        let mut state = self.load_balancer_state.write().await;
+    fn select_random(&self, instances: &[PoolInstance]) -> String {
+        let mut state = self.balancer();
         // 简单的线性同余生成器
         state.random_seed = (state
             .random_seed
@@ -364,44 +505,45 @@ impl FunctionPool {
             .wrapping_add(12345))
             & 0x7fffffff;
         let index = (state.random_seed as usize) % instances.len();
-        instances[index].0.clone()
+        instances[index].instance_id.clone()
     }
 
     /// 选择连接数最少的实例
-    async fn select_least_connections(&self, instances: &[(&String, &PoolInstance)]) -> String {
+    fn select_least_connections(&self, instances: &[PoolInstance]) -> String {
         let min_instance = instances
             .iter()
-            .min_by_key(|(_, instance)| instance.active_connections)
+            .min_by_key(|instance| instance.active_connections)
             .unwrap();
-        min_instance.0.clone()
+        min_instance.instance_id.clone()
     }
 
     /// 选择负载最小的实例
-    async fn select_least_load(&self, instances: &[(&String, &PoolInstance)]) -> String {
+    fn select_least_load(&self, instances: &[PoolInstance]) -> String {
         let min_instance = instances
             .iter()
-            .min_by(|(_, a), (_, b)| a.current_load.partial_cmp(&b.current_load).unwrap())
+            .min_by(|a, b| a.current_load.partial_cmp(&b.current_load).unwrap())
             .unwrap();
-        min_instance.0.clone()
+        min_instance.instance_id.clone()
     }
 
     /// 选择响应时间最短的实例
-    async fn select_fastest_response(&self, instances: &[(&String, &PoolInstance)]) -> String {
+    fn select_fastest_response(&self, instances: &[PoolInstance]) -> String {
         let fastest_instance = instances
             .iter()
-            .min_by(|(_, a), (_, b)| {
+            .min_by(|a, b| {
                 a.avg_response_time_ms
                     .partial_cmp(&b.avg_response_time_ms)
                     .unwrap()
             })
             .unwrap();
-        fastest_instance.0.clone()
+        fastest_instance.instance_id.clone()
     }
 
     /// 更新实例负载
-    async fn update_instance_load(&self, instance_id: &str, increment: bool) {
-        let mut instances = self.instances.write().await;
-        if let Some(instance) = instances.get_mut(instance_id) {
+    fn update_instance_load(&self, instance_id: &str, increment: bool) {
+        if let Some(mut instance) =
+            lock_order::get_mut("pool.instances", &self.instances, instance_id)
+        {
             if increment {
                 instance.active_connections += 1;
             } else {
@@ -409,36 +551,74 @@ impl FunctionPool {
             }
             instance.last_activity = chrono::Utc::now();
 
-            // 简单的负载计算（基于活跃连接数）
-            instance.current_load = instance.active_connections as f64 / 10.0; // 假设最大10个并发
+            // 负载 = 在途请求数 / 单实例并发能力
+            instance.current_load =
+                instance.active_connections as f64 / self.config.instance_concurrency.max(1) as f64;
         }
     }
 
-    /// 更新执行统计信息
+    /// 更新执行统计信息：实例的 EWMA 响应时间、成功/失败计数以及池级累计统计
     async fn update_execution_stats(
         &self,
+        instance_id: &str,
         result: &Result<InvokeResponse>,
         execution_time: Duration,
     ) {
-        // 这里可以实现详细的统计信息更新
-        // 目前简化处理
+        let success = matches!(
+            result,
+            Ok(InvokeResponse {
+                status: ExecutionStatus::Success,
+                ..
+            })
+        );
+        let elapsed_ms = execution_time.as_secs_f64() * 1000.0;
+
+        if let Some(mut instance) =
+            lock_order::get_mut("pool.instances", &self.instances, instance_id)
+        {
+            instance.avg_response_time_ms = if instance.total_requests == 0 {
+                elapsed_ms
+            } else {
+                let alpha = self.config.response_time_alpha.clamp(0.0, 1.0);
+                alpha * elapsed_ms + (1.0 - alpha) * instance.avg_response_time_ms
+            };
+            bump(&mut instance.total_requests);
+            if success {
+                bump(&mut instance.successful_requests);
+            } else {
+                bump(&mut instance.failed_requests);
+            }
+        }
+
+        let mut totals =
+            lock_order::acquire("pool.execution_totals", self.execution_totals.write()).await;
+        bump(&mut totals.total_requests);
+        if success {
+            bump(&mut totals.successful_requests);
+        } else {
+            bump(&mut totals.failed_requests);
+        }
+        totals.response_times.record_duration(execution_time);
+
         tracing::debug!(
-            "Execution completed in {}ms, success: {}",
+            "Execution on instance {} completed in {}ms, success: {}",
+            instance_id,
             execution_time.as_millis(),
-            result.is_ok()
+            success
         );
     }
 
     /// 扩容实例池
     pub async fn scale_up(&self, target_count: u32) -> Result<u32> {
-        let current_count = self.instances.read().await.len() as u32;
+        let _scaling = lock_order::acquire("pool.scaling", self.scaling.lock()).await;
+        let current_count = self.instances.len() as u32;
 
         if target_count <= current_count {
             return Ok(0);
         }
 
         let add_count = target_count - current_count;
-        let max_add = self.config.max_instances - current_count;
+        let max_add = self.config.max_instances.saturating_sub(current_count);
         let actual_add = add_count.min(max_add);
 
         if actual_add == 0 {
@@ -453,10 +633,7 @@ impl FunctionPool {
         );
 
         // 更新状态
-        {
-            let mut state = self.state.write().await;
-            *state = PoolState::ScalingUp;
-        }
+        self.set_state(PoolState::ScalingUp).await;
 
         let mut created_count = 0;
         for _ in 0..actual_add {
@@ -475,12 +652,14 @@ impl FunctionPool {
                         active_connections: 0,
                         last_activity: chrono::Utc::now(),
                         avg_response_time_ms: 0.0,
+                        total_requests: 0,
+                        successful_requests: 0,
+                        failed_requests: 0,
                         is_healthy: true,
                         created_at: chrono::Utc::now(),
                     };
 
-                    let mut instances = self.instances.write().await;
-                    instances.insert(instance_id, pool_instance);
+                    self.instances.insert(instance_id, pool_instance);
                     created_count += 1;
                 }
                 Err(e) => {
@@ -506,10 +685,7 @@ impl FunctionPool {
         }
 
         // 恢复运行状态
-        {
-            let mut state = self.state.write().await;
-            *state = PoolState::Running;
-        }
+        self.set_state(PoolState::Running).await;
 
         tracing::info!(
             "Scale up completed: created {} instances for function: {}",
@@ -522,14 +698,15 @@ impl FunctionPool {
 
     /// 缩容实例池
     pub async fn scale_down(&self, target_count: u32) -> Result<u32> {
-        let current_count = self.instances.read().await.len() as u32;
+        let _scaling = lock_order::acquire("pool.scaling", self.scaling.lock()).await;
+        let current_count = self.instances.len() as u32;
 
         if target_count >= current_count {
             return Ok(0);
         }
 
         let remove_count = current_count - target_count;
-        let min_remove = current_count - self.config.min_instances;
+        let min_remove = current_count.saturating_sub(self.config.min_instances);
         let actual_remove = remove_count.min(min_remove);
 
         if actual_remove == 0 {
@@ -544,35 +721,37 @@ impl FunctionPool {
         );
 
         // 更新状态
-        {
-            let mut state = self.state.write().await;
-            *state = PoolState::ScalingDown;
-        }
+        self.set_state(PoolState::ScalingDown).await;
 
         // 选择要移除的实例（优先移除负载最低的）
         let instances_to_remove: Vec<String> = {
-            let instances = self.instances.read().await;
-            let mut sorted_instances: Vec<_> = instances.iter().collect();
-            sorted_instances
-                .sort_by(|a, b| a.1.current_load.partial_cmp(&b.1.current_load).unwrap());
+            let mut sorted_instances: Vec<(String, f64)> = self
+                .instances
+                .iter()
+                .map(|instance| (instance.instance_id.clone(), instance.current_load))
+                .collect();
+            sorted_instances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
 
             sorted_instances
-                .iter()
+                .into_iter()
                 .take(actual_remove as usize)
-                .map(|(id, _)| (*id).clone())
+                .map(|(id, _)| id)
                 .collect()
         };
 
         let mut removed_count = 0;
         for instance_id in instances_to_remove {
+            // 先从池中摘除，新的请求不再选中该实例；停止失败时放回
+            let Some((_, pool_instance)) = self.instances.remove(&instance_id) else {
+                continue;
+            };
             match self.instance_manager.stop_instance(&instance_id).await {
                 Ok(_) => {
-                    let mut instances = self.instances.write().await;
-                    instances.remove(&instance_id);
                     removed_count += 1;
                 }
                 Err(e) => {
                     tracing::error!("Failed to stop instance during scale down: {}", e);
+                    self.instances.insert(instance_id, pool_instance);
                 }
             }
         }
@@ -593,10 +772,7 @@ impl FunctionPool {
         }
 
         // 恢复运行状态
-        {
-            let mut state = self.state.write().await;
-            *state = PoolState::Running;
-        }
+        self.set_state(PoolState::Running).await;
 
         tracing::info!(
             "Scale down completed: removed {} instances for function: {}",
@@ -625,7 +801,8 @@ impl FunctionPool {
             metrics: HashMap::new(), // 可以添加更多指标
         };
 
-        let mut history = self.scaling_history.write().await;
+        let mut history =
+            lock_order::acquire("pool.scaling_history", self.scaling_history.write()).await;
         history.push_back(event);
 
         // 限制历史记录大小
@@ -647,10 +824,10 @@ impl FunctionPool {
             loop {
                 interval.tick().await;
 
-                let instance_ids: Vec<String> = {
-                    let instances_guard = instances.read().await;
-                    instances_guard.keys().cloned().collect()
-                };
+                let instance_ids: Vec<String> = instances
+                    .iter()
+                    .map(|instance| instance.key().clone())
+                    .collect();
 
                 for instance_id in instance_ids {
                     // 检查实例健康状态
@@ -662,11 +839,10 @@ impl FunctionPool {
                         };
 
                         // 更新健康状态
+                        if let Some(mut pool_instance) =
+                            lock_order::get_mut("pool.instances", &instances, &instance_id)
                         {
-                            let mut instances_guard = instances.write().await;
-                            if let Some(pool_instance) = instances_guard.get_mut(&instance_id) {
-                                pool_instance.is_healthy = is_healthy;
-                            }
+                            pool_instance.is_healthy = is_healthy;
                         }
 
                         if !is_healthy {
@@ -702,24 +878,19 @@ impl FunctionPool {
 
                 // 计算当前负载
                 let (current_load, healthy_count) = {
-                    let instances_guard = instances.read().await;
-                    let healthy_instances: Vec<_> = instances_guard
-                        .values()
-                        .filter(|instance| instance.is_healthy)
-                        .collect();
-
-                    let total_load: f64 = healthy_instances
+                    let healthy_loads: Vec<f64> = instances
                         .iter()
+                        .filter(|instance| instance.is_healthy)
                         .map(|instance| instance.current_load)
-                        .sum();
+                        .collect();
 
-                    let avg_load = if healthy_instances.is_empty() {
+                    let avg_load = if healthy_loads.is_empty() {
                         0.0
                     } else {
-                        total_load / healthy_instances.len() as f64
+                        healthy_loads.iter().sum::<f64>() / healthy_loads.len() as f64
                     };
 
-                    (avg_load, healthy_instances.len() as u32)
+                    (avg_load, healthy_loads.len() as u32)
                 };
 
                 // 检查是否需要扩容
@@ -778,10 +949,15 @@ impl FunctionPool {
 
     /// 获取池统计信息
     pub async fn get_stats(&self) -> PoolExecutionStats {
-        let instances = self.instances.read().await;
+        // 先取实例表快照，再读取池级统计，两把锁不同时持有
+        let snapshot: Vec<PoolInstance> = self
+            .instances
+            .iter()
+            .map(|instance| instance.clone())
+            .collect();
 
-        let healthy_instances: Vec<_> = instances
-            .values()
+        let healthy_instances: Vec<_> = snapshot
+            .iter()
             .filter(|instance| instance.is_healthy)
             .collect();
 
@@ -800,27 +976,35 @@ impl FunctionPool {
                 / healthy_instances.len() as f64
         };
 
-        let avg_response_time = if healthy_instances.is_empty() {
-            0.0
-        } else {
-            healthy_instances
+        let mut affinity: Vec<InstanceKeyStats> = {
+            let state = self.balancer();
+            snapshot
                 .iter()
-                .map(|instance| instance.avg_response_time_ms)
-                .sum::<f64>()
-                / healthy_instances.len() as f64
+                .filter_map(|instance| {
+                    let counts = state.affinity_keys.get(&instance.instance_id)?;
+                    Some(counts.stats(&instance.instance_id))
+                })
+                .collect()
         };
+        affinity.sort_by(|a, b| a.instance_id.cmp(&b.instance_id));
+
+        let totals = lock_order::acquire("pool.execution_totals", self.execution_totals.read())
+            .await
+            .clone();
 
         PoolExecutionStats {
-            total_requests: 0, // 需要实现请求计数
-            successful_requests: 0,
-            failed_requests: 0,
-            avg_response_time_ms: avg_response_time,
-            max_response_time_ms: 0,
-            min_response_time_ms: 0,
+            total_requests: totals.total_requests,
+            successful_requests: totals.successful_requests,
+            failed_requests: totals.failed_requests,
+            avg_response_time_ms: totals.response_times.mean(),
+            max_response_time_ms: totals.response_times.max_u64(),
+            min_response_time_ms: totals.response_times.min_u64(),
+            response_time: totals.response_times.snapshot(),
             current_load: avg_load,
             active_connections: total_connections,
             healthy_instances: healthy_instances.len() as u32,
-            total_instances: instances.len() as u32,
+            total_instances: snapshot.len() as u32,
+            affinity,
         }
     }
 
@@ -844,16 +1028,14 @@ impl FunctionPool {
 
     /// 暂停池
     pub async fn pause(&self) -> Result<()> {
-        let mut state = self.state.write().await;
-        *state = PoolState::Paused;
+        self.set_state(PoolState::Paused).await;
         tracing::info!("Function pool paused: {}", self.function_metadata.name);
         Ok(())
     }
 
     /// 恢复池
     pub async fn resume(&self) -> Result<()> {
-        let mut state = self.state.write().await;
-        *state = PoolState::Running;
+        self.set_state(PoolState::Running).await;
         tracing::info!("Function pool resumed: {}", self.function_metadata.name);
         Ok(())
     }
@@ -863,10 +1045,7 @@ impl FunctionPool {
         tracing::info!("Stopping function pool: {}", self.function_metadata.name);
 
         // 更新状态
-        {
-            let mut state = self.state.write().await;
-            *state = PoolState::Stopping;
-        }
+        self.set_state(PoolState::Stopping).await;
 
         // 停止健康检查任务
         {
@@ -885,10 +1064,13 @@ impl FunctionPool {
         }
 
         // 停止所有实例
-        let instance_ids: Vec<String> = {
-            let instances = self.instances.read().await;
-            instances.keys().cloned().collect()
-        };
+        let _scaling = lock_order::acquire("pool.scaling", self.scaling.lock()).await;
+        let instance_ids: Vec<String> = self
+            .instances
+            .iter()
+            .map(|instance| instance.key().clone())
+            .collect();
+        self.instances.clear();
 
         for instance_id in instance_ids {
             if let Err(e) = self.instance_manager.stop_instance(&instance_id).await {
@@ -896,17 +1078,8 @@ impl FunctionPool {
             }
         }
 
-        // 清空实例列表
-        {
-            let mut instances = self.instances.write().await;
-            instances.clear();
-        }
-
         // 更新状态
-        {
-            let mut state = self.state.write().await;
-            *state = PoolState::Stopped;
-        }
+        self.set_state(PoolState::Stopped).await;
 
         tracing::info!("Function pool stopped: {}", self.function_metadata.name);
         Ok(())
@@ -917,7 +1090,7 @@ impl FunctionPool {
 #[derive(Debug)]
 pub struct PoolManager {
     /// 函数池映射
-    pools: Arc<RwLock<HashMap<String, Arc<FunctionPool>>>>,
+    pools: Arc<DashMap<String, Arc<FunctionPool>>>,
     /// 实例管理器
     instance_manager: Arc<InstanceManager>,
     /// 默认池配置
@@ -928,7 +1101,7 @@ impl PoolManager {
     /// 创建新的池管理器
     pub fn new(instance_manager: Arc<InstanceManager>, default_config: Option<PoolConfig>) -> Self {
         Self {
-            pools: Arc::new(RwLock::new(HashMap::new())),
+            pools: Arc::new(DashMap::new()),
             instance_manager,
             default_config: default_config.unwrap_or_default(),
         }
@@ -949,8 +1122,7 @@ impl PoolManager {
             FunctionPool::new(function_metadata, config, self.instance_manager.clone()).await?,
         );
 
-        let mut pools = self.pools.write().await;
-        pools.insert(function_name.clone(), pool.clone());
+        self.pools.insert(function_name.clone(), pool.clone());
 
         tracing::info!("Function pool created successfully: {}", function_name);
         Ok(pool)
@@ -958,18 +1130,12 @@ impl PoolManager {
 
     /// 获取函数池
     pub async fn get_pool(&self, function_name: &str) -> Option<Arc<FunctionPool>> {
-        let pools = self.pools.read().await;
-        pools.get(function_name).cloned()
+        self.pools.get(function_name).map(|pool| pool.clone())
     }
 
     /// 移除函数池
     pub async fn remove_pool(&self, function_name: &str) -> Result<()> {
-        let pool = {
-            let mut pools = self.pools.write().await;
-            pools.remove(function_name)
-        };
-
-        if let Some(pool) = pool {
+        if let Some((_, pool)) = self.pools.remove(function_name) {
             pool.stop().await?;
             tracing::info!("Function pool removed: {}", function_name);
         }
@@ -979,11 +1145,16 @@ impl PoolManager {
 
     /// 获取所有池的统计信息
     pub async fn get_all_stats(&self) -> HashMap<String, PoolExecutionStats> {
-        let pools = self.pools.read().await;
+        // 先复制池列表，读取各池统计时不持有池表的分片锁
+        let pools: Vec<(String, Arc<FunctionPool>)> = self
+            .pools
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
         let mut stats = HashMap::new();
 
-        for (name, pool) in pools.iter() {
-            stats.insert(name.clone(), pool.get_stats().await);
+        for (name, pool) in pools {
+            stats.insert(name, pool.get_stats().await);
         }
 
         stats
@@ -991,10 +1162,11 @@ impl PoolManager {
 
     /// 清理所有池
     pub async fn cleanup(&self) -> Result<()> {
-        let pools: Vec<_> = {
-            let mut pools_guard = self.pools.write().await;
-            pools_guard.drain().collect::<Vec<_>>()
-        };
+        let names: Vec<String> = self.pools.iter().map(|entry| entry.key().clone()).collect();
+        let pools: Vec<_> = names
+            .into_iter()
+            .filter_map(|name| self.pools.remove(&name))
+            .collect();
 
         for (name, pool) in pools {
             if let Err(e) = pool.stop().await {
@@ -1010,14 +1182,13 @@ impl PoolManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::functions::priority::Priority;
     use crate::runtime::compiler::{CompilerConfig, RustCompiler};
     use crate::runtime::resource::ResourceManager;
     use crate::runtime::sandbox::{SandboxConfig, SandboxExecutor};
     use tempfile::TempDir;
 
-    #[tokio::test]
-    async fn test_pool_creation() {
-        let temp_dir = TempDir::new().unwrap();
+    fn test_instance_manager(temp_dir: &TempDir) -> Arc<InstanceManager> {
         let compiler_config = CompilerConfig {
             cache_dir: temp_dir.path().to_path_buf(),
             ..Default::default()
@@ -1027,16 +1198,16 @@ mod tests {
         let sandbox = Arc::new(SandboxExecutor::new(SandboxConfig::default()).unwrap());
         let resource_manager = Arc::new(ResourceManager::new());
 
-        let instance_manager = Arc::new(InstanceManager::new(
+        Arc::new(InstanceManager::new(
             compiler,
             sandbox,
             resource_manager,
             None,
-        ));
-
-        let pool_manager = PoolManager::new(instance_manager, None);
+        ))
+    }
 
-        let function_metadata = FunctionMetadata {
+    fn test_function() -> FunctionMetadata {
+        FunctionMetadata {
             id: scru128::new(),
             name: "test_pool_function".to_string(),
             description: "Test pool function".to_string(),
@@ -1048,7 +1219,50 @@ mod tests {
             dependencies: vec![],
             parameters: vec![],
             return_type: "i32".to_string(),
-        };
+            retry_policy: None,
+            idempotent: true,
+            labels: std::collections::HashMap::new(),
+            webhooks: None,
+            priority: Priority::Normal,
+            package: None,
+            http_response: false,
+            input_template: None,
+            revision: 0,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: false,
+            coerce_output: false,
+            strict_return_type: false,
+            log_redaction: Vec::new(),
+            circuit_breaker: true,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: false,
+            scratch_limit_mb: None,
+            persist_scratch: false,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: Vec::new(),
+            execution_hint: Default::default(),
+            mirror: None,
+            canary: None,
+            slo: None,
+            documentation: None,
+            header_fields: Default::default(),
+            http_routes: Vec::new(),
+            deprecation: None,
+            parameters_inferred: false,
+            return_type_inferred: false,
+            egress: None,
+            debug_capture: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pool_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let pool_manager = PoolManager::new(test_instance_manager(&temp_dir), None);
+        let function_metadata = test_function();
 
         let pool = pool_manager
             .create_pool(function_metadata, None)
@@ -1059,4 +1273,239 @@ mod tests {
         let stats = pool.get_stats().await;
         assert!(stats.total_instances > 0);
     }
+
+    #[tokio::test]
+    async fn test_fastest_response_prefers_fast_instance() {
+        let temp_dir = TempDir::new().unwrap();
+        let instance_manager = test_instance_manager(&temp_dir);
+        let config = PoolConfig {
+            min_instances: 2,
+            max_instances: 2,
+            target_instances: 2,
+            load_balance_strategy: LoadBalanceStrategy::FastestResponse,
+            ..Default::default()
+        };
+        let pool = FunctionPool::new(test_function(), config, instance_manager.clone())
+            .await
+            .unwrap();
+
+        let mut ids: Vec<String> = pool
+            .instances
+            .iter()
+            .map(|instance| instance.key().clone())
+            .collect();
+        ids.sort();
+        let (slow, fast) = (&ids[0], &ids[1]);
+        instance_manager
+            .inject_latency(slow, Some(Duration::from_millis(300)))
+            .await;
+
+        let request = InvokeRequest {
+            input: serde_json::json!({}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+        for _ in 0..10 {
+            let _ = pool.execute(&request).await;
+        }
+
+        let (slow_requests, fast_requests) = (
+            pool.instances.get(slow).unwrap().total_requests,
+            pool.instances.get(fast).unwrap().total_requests,
+        );
+        assert!(
+            fast_requests > slow_requests,
+            "fast: {fast_requests}, slow: {slow_requests}"
+        );
+        assert!(pool.instances.get(slow).unwrap().avg_response_time_ms >= 300.0);
+
+        let stats = pool.get_stats().await;
+        assert_eq!(stats.total_requests, 10);
+        assert_eq!(
+            stats.successful_requests + stats.failed_requests,
+            stats.total_requests
+        );
+        assert!(stats.max_response_time_ms >= 300);
+        assert!(stats.min_response_time_ms <= stats.max_response_time_ms);
+
+        pool.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_concurrent_execute_scale_and_stats_complete() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PoolConfig {
+            min_instances: 1,
+            max_instances: 4,
+            target_instances: 2,
+            ..Default::default()
+        };
+        // 只编译一次（其余实例命中编译缓存），放宽超时避免机器繁忙时编译失败；
+        // 不启用任何隔离方式，执行在沙箱准入后立即失败，压力集中在锁上而不是进程创建
+        let compiler = RustCompiler::new(CompilerConfig {
+            cache_dir: temp_dir.path().to_path_buf(),
+            compile_timeout_secs: 300,
+            ..Default::default()
+        })
+        .unwrap();
+        let instance_manager = Arc::new(InstanceManager::new(
+            Arc::new(compiler),
+            Arc::new(
+                SandboxExecutor::new(SandboxConfig {
+                    enable_process_isolation: false,
+                    enable_container_isolation: false,
+                    ..Default::default()
+                })
+                .unwrap(),
+            ),
+            Arc::new(ResourceManager::new()),
+            None,
+        ));
+        let pool = Arc::new(
+            FunctionPool::new(test_function(), config, instance_manager)
+                .await
+                .unwrap(),
+        );
+        let request = InvokeRequest {
+            input: serde_json::json!({}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+
+        // 看门狗：任何锁等待环都会让这里超时，而不是让测试永久挂起
+        let workload = async {
+            let mut tasks = tokio::task::JoinSet::new();
+            for i in 0..1000u32 {
+                let pool_ref = pool.clone();
+                let request = request.clone();
+                tasks.spawn(async move {
+                    let _ = pool_ref.execute(&request).await;
+                });
+
+                let pool_ref = pool.clone();
+                if i % 100 == 0 {
+                    tasks.spawn(async move {
+                        let _ = if (i / 100) % 2 == 0 {
+                            pool_ref.scale_up(4).await
+                        } else {
+                            pool_ref.scale_down(1).await
+                        };
+                    });
+                } else if i % 10 == 0 {
+                    tasks.spawn(async move {
+                        let stats = pool_ref.get_stats().await;
+                        assert!(stats.healthy_instances <= stats.total_instances);
+                        pool_ref.get_scaling_history(None).await;
+                    });
+                }
+            }
+            while let Some(result) = tasks.join_next().await {
+                result.unwrap();
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(120), workload)
+            .await
+            .expect("pool operations did not complete: possible deadlock");
+
+        let stats = pool.get_stats().await;
+        assert_eq!(stats.total_requests, 1000);
+        assert_eq!(
+            stats.successful_requests + stats.failed_requests,
+            stats.total_requests
+        );
+        assert_eq!(stats.active_connections, 0);
+        let instances = pool.instances.len() as u32;
+        assert!((1..=4).contains(&instances), "instances: {instances}");
+
+        pool.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_affinity_keys_stick_to_one_instance() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PoolConfig {
+            min_instances: 2,
+            max_instances: 3,
+            target_instances: 3,
+            ..Default::default()
+        };
+        let pool = FunctionPool::new(test_function(), config, test_instance_manager(&temp_dir))
+            .await
+            .unwrap();
+        let keys: Vec<String> = (0..100).map(|i| format!("customer-{i}")).collect();
+
+        // 每个键在重复调用中都落在同一个实例上，三个实例都分到键
+        let mut placement = HashMap::new();
+        for key in &keys {
+            let (instance_id, honored) = pool.select_instance(Some(key)).await.unwrap();
+            assert_eq!(honored, Some(true));
+            placement.insert(key.clone(), instance_id);
+        }
+        for _ in 0..3 {
+            for key in &keys {
+                assert_eq!(
+                    pool.select_instance(Some(key)).await.unwrap().0,
+                    placement[key]
+                );
+            }
+        }
+        let mut owners: Vec<&String> = placement.values().collect();
+        owners.sort();
+        owners.dedup();
+        assert_eq!(owners.len(), 3);
+
+        // 对应实例负载过高时按负载均衡策略选择，键的归属不变
+        let busy = placement[&keys[0]].clone();
+        pool.instances.get_mut(&busy).unwrap().current_load = 1.0;
+        assert_eq!(
+            pool.select_instance(Some(&keys[0])).await.unwrap().1,
+            Some(false)
+        );
+        pool.instances.get_mut(&busy).unwrap().current_load = 0.0;
+        assert_eq!(
+            pool.select_instance(Some(&keys[0])).await.unwrap(),
+            (busy, Some(true))
+        );
+
+        let stats = pool.get_stats().await;
+        assert_eq!(stats.affinity.len(), 3);
+        assert!(stats.affinity.iter().map(|s| s.keys).sum::<usize>() >= 100);
+        assert!(stats.affinity.iter().all(|s| s.hot_keys.len() == 5));
+
+        // 移除一个实例：只有映射到它的键改变归属
+        let removed = pool.scale_down(2).await.unwrap();
+        assert_eq!(removed, 1);
+        for key in &keys {
+            let (instance_id, _) = pool.select_instance(Some(key)).await.unwrap();
+            if pool.instances.contains_key(&placement[key]) {
+                assert_eq!(instance_id, placement[key], "{key} moved");
+            } else {
+                assert_ne!(instance_id, placement[key]);
+            }
+        }
+        assert_eq!(pool.get_stats().await.affinity.len(), 2);
+
+        // 执行结果报告执行实例和是否遵循了亲和键
+        let request = InvokeRequest {
+            input: serde_json::json!({}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: Some(keys[1].clone()),
+        };
+        if let Ok(response) = pool.execute(&request).await {
+            let selection = response.timing.instance.unwrap();
+            assert!(pool.instances.contains_key(&selection.instance_id));
+            assert!(selection.affinity_honored);
+        }
+
+        pool.stop().await.unwrap();
+    }
 }
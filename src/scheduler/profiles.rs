@@ -0,0 +1,788 @@
+use super::SimpleScheduler;
+use super::admission::AdmissionConfig;
+use super::chaos::ChaosEngine;
+use super::console::ConsoleStore;
+use super::history::ExecutionHistory;
+use super::http_routes::HttpRouteTable;
+use super::idempotency::IdempotencyConfig;
+use super::jobs::JobStore;
+use super::limiter::InvocationLimiter;
+use super::namespaces::NamespaceRegistry;
+use super::peers::PeerSet;
+use super::routing::RoutingConfig;
+use super::sessions::SessionStore;
+use crate::functions::guardrails::RegistryGuardrailsConfig;
+use crate::functions::http_route::{HttpRoute, RouteLookup};
+use crate::functions::labels::LabelSelector;
+use crate::functions::registry::FunctionRegistry;
+use crate::functions::{FluxError, FunctionMetadata, RegisterFunctionRequest, Result};
+use crate::runtime::SimpleRuntime;
+use crate::runtime::cache::{FunctionCache, FunctionCacheConfig};
+use crate::runtime::debug_capture::DebugArtifactStore;
+use crate::runtime::egress::EgressBroker;
+use crate::runtime::environment::RuntimeEnvironment;
+use crate::runtime::invocation_log::InvocationLogStore;
+use crate::runtime::oci::OciFunctionSource;
+use crate::runtime::resource::{ResourceManager, ResourceQuota};
+use crate::runtime::state::StateStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 默认调度器配置名
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// 调度器配置（`[profiles.<name>]`）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerProfileConfig {
+    /// 是否启用真实编译
+    pub compilation: bool,
+    /// 函数超时上限（毫秒），注册或更新时超出则拒绝
+    pub max_timeout_ms: Option<u64>,
+    /// 执行许可数和各优先级队列上限（缺省使用默认值）
+    pub admission: Option<AdmissionConfig>,
+    /// 每个函数保留的可调用版本数（包含最新版本，缺省为 5）
+    pub max_versions: Option<usize>,
+    /// 多运行时路由（策略和后端列表），缺省时只使用调度器自身的运行时
+    pub routing: Option<RoutingConfig>,
+    /// 调用幂等键的结果保留时间和键数上限（缺省保留 24 小时、10000 个键）
+    pub idempotency: Option<IdempotencyConfig>,
+    /// 函数缓存的容量、字节上限和磁盘溢出（缺省为 100 个条目、50MB，不溢出）
+    pub cache: Option<FunctionCacheConfig>,
+    /// 删除或替换函数时等待在途调用结束的最长时间（毫秒，缺省为 30 秒）
+    pub drain_timeout_ms: Option<u64>,
+    /// 函数总数上限和注册表变更速率限制（缺省不限制）
+    pub guardrails: Option<RegistryGuardrailsConfig>,
+}
+
+/// 命名的调度器及其配置
+#[derive(Debug, Clone)]
+pub struct SchedulerProfile {
+    pub name: String,
+    pub config: SchedulerProfileConfig,
+    pub scheduler: Arc<SimpleScheduler>,
+}
+
+impl SchedulerProfile {
+    /// 校验函数超时是否在本配置的上限内
+    pub fn check_timeout(&self, timeout_ms: u64) -> Result<()> {
+        match self.config.max_timeout_ms {
+            Some(max) if timeout_ms > max => Err(FluxError::ValidationError {
+                reason: format!(
+                    "timeout_ms {timeout_ms} exceeds the limit of {max} ms for profile '{}'",
+                    self.name
+                ),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 进程内多个相互隔离的调度器，按配置名索引，始终包含 `default`
+///
+/// 注册、导入和从文件加载时校验函数名在所有调度器间唯一，请求按目标函数所在的调度器分发。
+#[derive(Debug, Clone)]
+pub struct SchedulerRegistry {
+    profiles: BTreeMap<String, SchedulerProfile>,
+    /// 所有调度器中函数的自定义 HTTP 路由
+    http_routes: Arc<HttpRouteTable>,
+    /// OCI 制品的拉取配置和缓存（所有调度器共享）
+    oci: Arc<OciFunctionSource>,
+    /// 异步调用任务（所有调度器共享）
+    jobs: Arc<JobStore>,
+    /// 控制台的限速、保留的代码和用量计数（所有调度器共享）
+    console: Arc<ConsoleStore>,
+}
+
+impl SchedulerRegistry {
+    /// 只包含默认调度器的注册表
+    pub fn with_default(scheduler: Arc<SimpleScheduler>) -> Self {
+        Self {
+            profiles: BTreeMap::new(),
+            http_routes: Arc::default(),
+            oci: Arc::default(),
+            jobs: Arc::default(),
+            console: Arc::default(),
+        }
+        .with_profile(
+            DEFAULT_PROFILE,
+            SchedulerProfileConfig::default(),
+            scheduler,
+        )
+    }
+
+    /// 根据配置构建所有调度器，未配置 `default` 时按默认配置补齐
+    pub fn from_config(configs: &BTreeMap<String, SchedulerProfileConfig>) -> anyhow::Result<Self> {
+        let mut registry = Self {
+            profiles: BTreeMap::new(),
+            http_routes: Arc::default(),
+            oci: Arc::default(),
+            jobs: Arc::default(),
+            console: Arc::default(),
+        };
+        // 所有调度器共享同一组命名空间、故障注入规则、执行历史、会话调用记录、运行时环境、资源配额、函数状态、出站 HTTP 代理、
+        // 全局调用并发上限、调试包目录、调用日志和转发节点
+        let namespaces = Arc::new(NamespaceRegistry::default());
+        let chaos = Arc::new(ChaosEngine::default());
+        let history = Arc::new(ExecutionHistory::default());
+        let sessions = Arc::new(SessionStore::default());
+        let environment = Arc::new(RuntimeEnvironment::default());
+        let resources = Arc::new(ResourceManager::new());
+        let state = Arc::new(StateStore::default());
+        let egress = Arc::new(EgressBroker::default());
+        let invocations = Arc::new(InvocationLimiter::default());
+        let debug = Arc::new(DebugArtifactStore::default());
+        let logs = Arc::new(InvocationLogStore::default());
+        let peers = Arc::new(PeerSet::default());
+        if !configs.contains_key(DEFAULT_PROFILE) {
+            registry = registry.with_profile(
+                DEFAULT_PROFILE,
+                SchedulerProfileConfig::default(),
+                Arc::new(
+                    SimpleScheduler::new()
+                        .with_namespaces(namespaces.clone())
+                        .with_chaos(chaos.clone())
+                        .with_history(history.clone())
+                        .with_sessions(sessions.clone())
+                        .with_environment(environment.clone())
+                        .with_resources(resources.clone())
+                        .with_state(state.clone())
+                        .with_egress(egress.clone())
+                        .with_invocation_limiter(invocations.clone())
+                        .with_debug_artifacts(debug.clone())
+                        .with_invocation_logs(logs.clone())
+                        .with_peers(peers.clone()),
+                ),
+            );
+        }
+        for (name, config) in configs {
+            let mut scheduler = match &config.cache {
+                Some(cache) => {
+                    let runtime = if config.compilation {
+                        SimpleRuntime::new_with_compilation()?
+                    } else {
+                        SimpleRuntime::new()
+                    }
+                    .with_function_cache(Arc::new(FunctionCache::from_config(cache)?));
+                    SimpleScheduler::with_runtime(Arc::new(runtime))
+                }
+                None if config.compilation => SimpleScheduler::new_with_compilation()?,
+                None => SimpleScheduler::new(),
+            }
+            .with_namespaces(namespaces.clone())
+            .with_chaos(chaos.clone())
+            .with_history(history.clone())
+            .with_sessions(sessions.clone())
+            .with_environment(environment.clone())
+            .with_resources(resources.clone())
+            .with_state(state.clone())
+            .with_egress(egress.clone())
+            .with_invocation_limiter(invocations.clone())
+            .with_debug_artifacts(debug.clone())
+            .with_invocation_logs(logs.clone())
+            .with_peers(peers.clone());
+            if let Some(admission) = &config.admission {
+                scheduler = scheduler.with_admission_config(admission.clone());
+            }
+            if let Some(max_versions) = config.max_versions {
+                scheduler = scheduler.with_max_versions(max_versions);
+            }
+            if let Some(guardrails) = &config.guardrails {
+                scheduler = scheduler.with_registry_guardrails(guardrails.clone());
+            }
+            if let Some(drain_timeout_ms) = config.drain_timeout_ms {
+                scheduler = scheduler.with_drain_timeout(Duration::from_millis(drain_timeout_ms));
+            }
+            if let Some(idempotency) = &config.idempotency {
+                scheduler = scheduler.with_idempotency_config(idempotency.clone());
+            }
+            if let Some(routing) = &config.routing {
+                scheduler = scheduler.with_routing(routing)?;
+            }
+            registry = registry.with_profile(name, config.clone(), Arc::new(scheduler));
+        }
+        Ok(registry)
+    }
+
+    /// 添加（或替换）一个命名调度器
+    pub fn with_profile(
+        mut self,
+        name: &str,
+        config: SchedulerProfileConfig,
+        scheduler: Arc<SimpleScheduler>,
+    ) -> Self {
+        self.profiles.insert(
+            name.to_string(),
+            SchedulerProfile {
+                name: name.to_string(),
+                config,
+                scheduler,
+            },
+        );
+        self
+    }
+
+    /// 默认调度器
+    pub fn default_profile(&self) -> &SchedulerProfile {
+        &self.profiles[DEFAULT_PROFILE]
+    }
+
+    /// 命名空间注册表（取自默认调度器，`from_config` 构建的调度器共享同一个）
+    pub fn namespaces(&self) -> &Arc<NamespaceRegistry> {
+        self.default_profile().scheduler.namespaces()
+    }
+
+    /// 运行时环境（取自默认调度器，`from_config` 构建的调度器共享同一个）
+    pub fn environment(&self) -> &Arc<RuntimeEnvironment> {
+        self.default_profile().scheduler.environment()
+    }
+
+    /// 资源配额（取自默认调度器，`from_config` 构建的调度器共享同一个）
+    pub fn resources(&self) -> &Arc<ResourceManager> {
+        self.default_profile().scheduler.resources()
+    }
+
+    /// 函数状态存储（取自默认调度器，`from_config` 构建的调度器共享同一个）
+    pub fn state(&self) -> &Arc<StateStore> {
+        self.default_profile().scheduler.state()
+    }
+
+    /// 出站 HTTP 代理（取自默认调度器，`from_config` 构建的调度器共享同一个）
+    pub fn egress(&self) -> &Arc<EgressBroker> {
+        self.default_profile().scheduler.egress()
+    }
+
+    /// 全局调用并发上限（取自默认调度器，`from_config` 构建的调度器共享同一个）
+    pub fn invocation_limiter(&self) -> &Arc<InvocationLimiter> {
+        self.default_profile().scheduler.invocation_limiter()
+    }
+
+    /// 会话调用记录（所有调度器共享）
+    pub fn sessions(&self) -> &Arc<SessionStore> {
+        self.default_profile().scheduler.sessions()
+    }
+
+    /// 调试包目录（所有调度器共享）
+    pub fn debug_artifacts(&self) -> &Arc<DebugArtifactStore> {
+        self.default_profile().scheduler.debug_artifacts()
+    }
+
+    /// 按请求捕获的调用日志（所有调度器共享）
+    pub fn invocation_logs(&self) -> &Arc<InvocationLogStore> {
+        self.default_profile().scheduler.invocation_logs()
+    }
+
+    /// 转发调用的节点（取自默认调度器，`from_config` 构建的调度器共享同一个）
+    pub fn peers(&self) -> &Arc<PeerSet> {
+        self.default_profile().scheduler.peers()
+    }
+
+    /// OCI 函数源（所有调度器共享）
+    pub fn oci_source(&self) -> &Arc<OciFunctionSource> {
+        &self.oci
+    }
+
+    /// 异步调用任务（所有调度器共享）
+    pub fn jobs(&self) -> &Arc<JobStore> {
+        &self.jobs
+    }
+
+    /// 控制台（所有调度器共享）
+    pub fn console(&self) -> &Arc<ConsoleStore> {
+        &self.console
+    }
+
+    /// 所有调度器中选择该资源配额的函数名
+    pub async fn quota_functions(&self, quota: &str) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .list()
+            .await
+            .into_iter()
+            .filter(|(_, function)| function.resource_quota.as_deref() == Some(quota))
+            .map(|(_, function)| function.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// 删除资源配额，仍有函数选择它时拒绝删除
+    pub async fn delete_quota(&self, name: &str) -> Result<ResourceQuota> {
+        let functions = self.quota_functions(name).await;
+        if !functions.is_empty() {
+            return Err(FluxError::QuotaInUse {
+                name: name.to_string(),
+                functions: functions.len(),
+            });
+        }
+        self.resources().delete_quota(name).await
+    }
+
+    /// 故障注入规则（取自默认调度器，`from_config` 构建的调度器共享同一个）
+    pub fn chaos(&self) -> &Arc<ChaosEngine> {
+        self.default_profile().scheduler.chaos()
+    }
+
+    /// 所有调度器中属于该命名空间的函数名
+    pub async fn namespace_functions(&self, namespace: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        for profile in self.profiles.values() {
+            for function in profile.scheduler.list_namespace(namespace).await {
+                names.push(function.name);
+            }
+        }
+        names.sort();
+        names
+    }
+
+    /// 删除命名空间：命名空间非空时需要 `force`，此时先移除命名空间（阻止新的注册和调用）
+    /// 再删除其中的所有函数，返回被删除的函数名
+    pub async fn delete_namespace(&self, namespace: &str, force: bool) -> Result<Vec<String>> {
+        self.namespaces().get(namespace)?;
+        let functions = self.namespace_functions(namespace).await;
+        if !functions.is_empty() && !force {
+            return Err(FluxError::NamespaceNotEmpty {
+                name: namespace.to_string(),
+                functions: functions.len(),
+            });
+        }
+        self.namespaces().remove(namespace)?;
+
+        let mut deleted = Vec::new();
+        for profile in self.profiles.values() {
+            for function in profile.scheduler.list_namespace(namespace).await {
+                if profile
+                    .scheduler
+                    .delete_function(&function.name, None)
+                    .await
+                    .is_ok()
+                {
+                    deleted.push(function.name);
+                }
+            }
+        }
+        deleted.sort();
+        Ok(deleted)
+    }
+
+    /// 按配置名获取调度器，未知配置名返回验证错误
+    pub fn get(&self, name: &str) -> Result<&SchedulerProfile> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| FluxError::ValidationError {
+                reason: format!(
+                    "Unknown scheduler profile '{name}' (available: {})",
+                    self.names().join(", ")
+                ),
+            })
+    }
+
+    /// 所有配置名（有序）
+    pub fn names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// 所有调度器（按配置名排序）
+    pub fn profiles(&self) -> impl Iterator<Item = &SchedulerProfile> {
+        self.profiles.values()
+    }
+
+    /// 查找持有该函数的调度器
+    pub async fn find(&self, function_name: &str) -> Option<&SchedulerProfile> {
+        for profile in self.profiles.values() {
+            if profile.scheduler.registry().exists(function_name).await {
+                return Some(profile);
+            }
+        }
+        None
+    }
+
+    /// 持有该函数的调度器；函数不存在时返回默认调度器，由其报告函数不存在
+    pub async fn resolve(&self, function_name: &str) -> &SchedulerProfile {
+        match self.find(function_name).await {
+            Some(profile) => profile,
+            None => self.default_profile(),
+        }
+    }
+
+    /// 校验目标配置名且函数名未被其他调度器占用，返回目标调度器
+    pub async fn target_for(
+        &self,
+        function_name: &str,
+        profile: Option<&str>,
+    ) -> Result<&SchedulerProfile> {
+        let target = self.get(profile.unwrap_or(DEFAULT_PROFILE))?;
+        if let Some(owner) = self.find(function_name).await
+            && owner.name != target.name
+        {
+            return Err(FluxError::FunctionAlreadyExists {
+                name: format!("{function_name} (in profile '{}')", owner.name),
+            });
+        }
+        Ok(target)
+    }
+
+    /// 按请求中的 `profile` 注册函数，返回目标调度器
+    pub async fn register(&self, request: RegisterFunctionRequest) -> Result<&SchedulerProfile> {
+        let target = self
+            .target_for(&request.qualified_name(), request.profile.as_deref())
+            .await?;
+        let function = FunctionMetadata::from_request(request);
+        target.check_timeout(function.timeout_ms)?;
+        if let Some(webhooks) = &function.webhooks {
+            webhooks.validate()?;
+        }
+        target.scheduler.register_function(function).await?;
+        Ok(target)
+    }
+
+    /// 所有调度器中的函数及其所属配置名
+    pub async fn list(&self) -> Vec<(String, FunctionMetadata)> {
+        let mut functions = Vec::new();
+        for profile in self.profiles.values() {
+            for function in profile.scheduler.registry().list().await {
+                functions.push((profile.name.clone(), function));
+            }
+        }
+        functions
+    }
+
+    /// 所有调度器中匹配标签选择器的函数及其所属配置名
+    pub async fn list_by_selector(
+        &self,
+        selector: &LabelSelector,
+    ) -> Vec<(String, FunctionMetadata)> {
+        let mut functions = Vec::new();
+        for profile in self.profiles.values() {
+            for function in profile
+                .scheduler
+                .registry()
+                .list_by_selector(selector)
+                .await
+            {
+                functions.push((profile.name.clone(), function));
+            }
+        }
+        functions
+    }
+
+    fn function_registries(&self) -> Vec<&FunctionRegistry> {
+        self.profiles
+            .values()
+            .map(|profile| profile.scheduler.registry())
+            .collect()
+    }
+
+    /// 所有自定义 HTTP 路由（按创建顺序）
+    pub async fn http_routes(&self) -> Arc<Vec<HttpRoute>> {
+        self.http_routes.routes(&self.function_registries()).await
+    }
+
+    /// 按方法和路径查找自定义 HTTP 路由
+    pub async fn lookup_http_route(&self, method: &str, path: &str) -> RouteLookup {
+        self.http_routes
+            .lookup(&self.function_registries(), method, path)
+            .await
+    }
+
+    /// 校验并添加一条自定义 HTTP 路由，返回分配了 ID 的绑定
+    ///
+    /// `reserved` 为内置路由的第一段路径；与已有绑定重叠时返回 `RouteConflict`。
+    pub async fn add_http_route(
+        &self,
+        mut route: HttpRoute,
+        reserved: &[String],
+    ) -> Result<HttpRoute> {
+        route.normalize(reserved)?;
+        let _changes = self.http_routes.lock_changes().await;
+        HttpRouteTable::check_conflicts(&self.http_routes().await, &route)?;
+        let profile =
+            self.find(&route.function)
+                .await
+                .ok_or_else(|| FluxError::FunctionNotFound {
+                    name: route.function.clone(),
+                })?;
+        route.id = scru128::new_string();
+        let mut routes = profile
+            .scheduler
+            .registry()
+            .get(&route.function)
+            .await?
+            .http_routes;
+        routes.push(route.clone());
+        profile
+            .scheduler
+            .set_http_routes(&route.function, routes)
+            .await?;
+        Ok(route)
+    }
+
+    /// 删除一条自定义 HTTP 路由，返回被删除的绑定
+    pub async fn remove_http_route(&self, id: &str) -> Result<HttpRoute> {
+        let _changes = self.http_routes.lock_changes().await;
+        let route = self
+            .http_routes()
+            .await
+            .iter()
+            .find(|route| route.id == id)
+            .cloned()
+            .ok_or_else(|| FluxError::RouteNotFound { id: id.to_string() })?;
+        let profile = self
+            .find(&route.function)
+            .await
+            .ok_or_else(|| FluxError::RouteNotFound { id: id.to_string() })?;
+        let routes = profile
+            .scheduler
+            .registry()
+            .get(&route.function)
+            .await?
+            .http_routes
+            .into_iter()
+            .filter(|existing| existing.id != id)
+            .collect();
+        profile
+            .scheduler
+            .set_http_routes(&route.function, routes)
+            .await?;
+        Ok(route)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(
+        name: &str,
+        profile: Option<&str>,
+        timeout_ms: Option<u64>,
+    ) -> RegisterFunctionRequest {
+        RegisterFunctionRequest {
+            name: name.to_string(),
+            description: None,
+            code: "return input".to_string(),
+            timeout_ms,
+            version: None,
+            dependencies: None,
+            parameters: None,
+            return_type: None,
+            retry_policy: None,
+            idempotent: None,
+            labels: None,
+            profile: profile.map(str::to_string),
+            webhooks: None,
+            priority: None,
+            files: None,
+            entrypoint: None,
+            http_response: None,
+            input_template: None,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: None,
+            coerce_output: None,
+            strict_return_type: None,
+            log_redaction: None,
+            circuit_breaker: None,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: None,
+            scratch_limit_mb: None,
+            persist_scratch: None,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: None,
+            execution_hint: None,
+            namespace: None,
+            documentation: None,
+            egress: None,
+            debug_capture: None,
+            canary: None,
+            slo: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_profiles_isolate_functions() {
+        let config: BTreeMap<String, SchedulerProfileConfig> =
+            toml::from_str("[untrusted]\nmax_timeout_ms = 1000\n").unwrap();
+        let registry = SchedulerRegistry::from_config(&config).unwrap();
+        assert_eq!(registry.names(), vec!["default", "untrusted"]);
+
+        let target = registry
+            .register(request("echo", None, None))
+            .await
+            .unwrap();
+        assert_eq!(target.name, DEFAULT_PROFILE);
+        registry
+            .register(request("sandboxed", Some("untrusted"), Some(500)))
+            .await
+            .unwrap();
+
+        // 未知配置名、超出上限的超时、跨调度器同名均被拒绝
+        let err = registry
+            .register(request("other", Some("missing"), None))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Unknown scheduler profile 'missing'")
+        );
+        assert!(
+            registry
+                .register(request("slow", Some("untrusted"), Some(5000)))
+                .await
+                .is_err()
+        );
+        assert!(matches!(
+            registry
+                .register(request("echo", Some("untrusted"), None))
+                .await,
+            Err(FluxError::FunctionAlreadyExists { .. })
+        ));
+
+        // 调度器之间互不可见，按函数解析到所属调度器
+        let untrusted = registry.get("untrusted").unwrap();
+        assert!(!untrusted.scheduler.registry().exists("echo").await);
+        assert_eq!(registry.resolve("sandboxed").await.name, "untrusted");
+        assert_eq!(registry.resolve("unknown").await.name, DEFAULT_PROFILE);
+
+        let listed: Vec<_> = registry
+            .list()
+            .await
+            .into_iter()
+            .map(|(profile, f)| (profile, f.name))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("default".to_string(), "echo".to_string()),
+                ("untrusted".to_string(), "sandboxed".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_profile_guardrails_limit_registry_changes() {
+        let config: BTreeMap<String, SchedulerProfileConfig> = toml::from_str(
+            "[capped.guardrails]\nmax_functions = 2\nmutations_per_minute = 1\nmutation_burst = 4\n",
+        )
+        .unwrap();
+        let registry = SchedulerRegistry::from_config(&config).unwrap();
+        for name in ["one", "two"] {
+            registry
+                .register(request(name, Some("capped"), None))
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            registry
+                .register(request("three", Some("capped"), None))
+                .await,
+            Err(FluxError::RegistryFull { limit: 2 })
+        ));
+
+        // 已有函数的更新不受函数数上限限制，但仍消耗变更配额
+        let capped = registry.get("capped").unwrap();
+        let mut updated = capped.scheduler.registry().get("one").await.unwrap();
+        updated.description = "updated".to_string();
+        capped.scheduler.upsert_function(updated).await.unwrap();
+        capped.scheduler.delete_function("two", None).await.unwrap();
+        assert!(matches!(
+            registry
+                .register(request("three", Some("capped"), None))
+                .await,
+            Err(FluxError::MutationRateLimited { per_minute: 1, .. })
+        ));
+
+        let status = capped.scheduler.registry().guardrails_status().await;
+        assert_eq!(status.functions, 1);
+        assert_eq!(status.max_functions, Some(2));
+        assert_eq!(status.mutation_tokens, Some(0));
+        assert_eq!((status.rejected_full, status.rejected_rate_limited), (1, 1));
+
+        // 其他调度器不受影响
+        registry
+            .register(request("free", None, None))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_isolate_names_and_cascade_on_forced_delete() {
+        use crate::scheduler::Scheduler;
+        use crate::scheduler::namespaces::NamespaceConfig;
+
+        let config: BTreeMap<String, SchedulerProfileConfig> =
+            toml::from_str("[untrusted]\n").unwrap();
+        let registry = SchedulerRegistry::from_config(&config).unwrap();
+        let in_namespace = |name: &str, namespace: &str, profile: Option<&str>| {
+            let mut request = request(name, profile, None);
+            request.code = format!("return '{namespace}'");
+            request.namespace = Some(namespace.to_string());
+            request
+        };
+
+        // 命名空间必须先创建；不同命名空间中可以使用相同的函数名
+        assert!(matches!(
+            registry
+                .register(in_namespace("greet", "team-a", None))
+                .await,
+            Err(FluxError::NamespaceNotFound { .. })
+        ));
+        registry
+            .namespaces()
+            .create("team-a", NamespaceConfig::default())
+            .unwrap();
+        registry
+            .register(in_namespace("greet", "default", None))
+            .await
+            .unwrap();
+        registry
+            .register(in_namespace("greet", "team-a", None))
+            .await
+            .unwrap();
+        registry
+            .register(in_namespace("other", "team-a", Some("untrusted")))
+            .await
+            .unwrap();
+
+        // 不带前缀的名称只在默认命名空间中查找，跨命名空间调用必须显式使用 `ns/name`
+        let registry = &registry;
+        let invoke = |name: &'static str| async move {
+            let profile = registry.resolve(name).await;
+            let request = crate::functions::InvokeRequest {
+                input: serde_json::json!({}),
+                retry_policy: None,
+                priority: None,
+                idempotency_key: None,
+                session_id: None,
+                affinity_key: None,
+            };
+            profile.scheduler.schedule(name, request).await
+        };
+        assert_eq!(invoke("greet").await.unwrap().output["result"], "default");
+        assert_eq!(
+            invoke("team-a/greet").await.unwrap().output["result"],
+            "team-a"
+        );
+        assert!(invoke("other").await.is_err());
+        assert_eq!(
+            registry.namespace_functions("team-a").await,
+            vec!["team-a/greet", "team-a/other"]
+        );
+
+        assert!(matches!(
+            registry.delete_namespace("team-a", false).await,
+            Err(FluxError::NamespaceNotEmpty { functions: 2, .. })
+        ));
+        let deleted = registry.delete_namespace("team-a", true).await.unwrap();
+        assert_eq!(deleted, vec!["team-a/greet", "team-a/other"]);
+        assert!(registry.find("team-a/other").await.is_none());
+        assert!(registry.find("greet").await.is_some());
+        assert!(matches!(
+            registry.namespaces().get("team-a"),
+            Err(FluxError::NamespaceNotFound { .. })
+        ));
+    }
+}
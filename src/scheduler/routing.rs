@@ -0,0 +1,393 @@
+use super::Scheduler;
+use super::namespaces::script_type;
+use crate::functions::context::InvocationContext;
+use crate::functions::registry::FunctionRegistry;
+use crate::functions::{FluxError, FunctionMetadata, InvokeRequest, InvokeResponse, Result};
+use crate::runtime::compiler::RustCompiler;
+use crate::runtime::monitor::PerformanceMonitor;
+use crate::runtime::{RuntimeBackend, SimpleRuntime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// 路由策略决策时看到的后端负载（取自性能监控）
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendLoad {
+    pub name: String,
+    /// 是否编译执行 Rust 函数
+    pub supports_compilation: bool,
+    /// 正在执行的调用数
+    pub in_flight: usize,
+    /// 已完成的调用数
+    pub invocations: u64,
+}
+
+/// 路由策略：按函数和各后端的当前负载选择执行的后端
+pub trait RoutingPolicy: Send + Sync + std::fmt::Debug {
+    /// 返回 `backends` 中被选中的下标，`backends` 至少有一个元素
+    fn choose(&self, function: &FunctionMetadata, backends: &[BackendLoad]) -> usize;
+}
+
+/// 按脚本类型路由：Rust 函数交给支持编译的后端，其他函数交给不编译的解释后端；
+/// 没有对应类型的后端时使用第一个后端
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByScriptType;
+
+impl RoutingPolicy for ByScriptType {
+    fn choose(&self, function: &FunctionMetadata, backends: &[BackendLoad]) -> usize {
+        let compiled = script_type(function) == "rust";
+        backends
+            .iter()
+            .position(|backend| backend.supports_compilation == compiled)
+            .unwrap_or(0)
+    }
+}
+
+/// 选择正在执行的调用数最少的后端，相同时选择已完成调用数较少的后端
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LeastInFlight;
+
+impl RoutingPolicy for LeastInFlight {
+    fn choose(&self, _function: &FunctionMetadata, backends: &[BackendLoad]) -> usize {
+        backends
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, backend)| (backend.in_flight, backend.invocations))
+            .map(|(index, _)| index)
+            .unwrap_or(0)
+    }
+}
+
+/// 可在配置中选择的路由策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingPolicyKind {
+    #[default]
+    ByScriptType,
+    LeastInFlight,
+}
+
+impl RoutingPolicyKind {
+    pub fn build(self) -> Arc<dyn RoutingPolicy> {
+        match self {
+            Self::ByScriptType => Arc::new(ByScriptType),
+            Self::LeastInFlight => Arc::new(LeastInFlight),
+        }
+    }
+}
+
+/// 运行时后端配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackendConfig {
+    pub name: String,
+    /// 是否启用真实编译
+    #[serde(default)]
+    pub compilation: bool,
+}
+
+/// 多运行时路由配置（`[profiles.<name>.routing]`），缺省时调度器只使用自己的运行时
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoutingConfig {
+    pub policy: RoutingPolicyKind,
+    /// 后端列表（默认为编译后端 `compiled` 和解释后端 `interpreter`）
+    pub backends: Vec<BackendConfig>,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            policy: RoutingPolicyKind::default(),
+            backends: vec![
+                BackendConfig {
+                    name: "compiled".to_string(),
+                    compilation: true,
+                },
+                BackendConfig {
+                    name: "interpreter".to_string(),
+                    compilation: false,
+                },
+            ],
+        }
+    }
+}
+
+/// 持有多个命名运行时后端的调度器，每次调用按路由策略选择后端
+///
+/// 后端负载和选择结果记录在共享的性能监控中，响应的 `backend` 字段为实际执行的后端。
+#[derive(Debug, Clone)]
+pub struct MultiRuntimeScheduler {
+    registry: FunctionRegistry,
+    monitor: Arc<PerformanceMonitor>,
+    policy: Arc<dyn RoutingPolicy>,
+    backends: Vec<(String, Arc<dyn RuntimeBackend>)>,
+}
+
+impl MultiRuntimeScheduler {
+    /// 没有后端的调度器，需要通过 `with_backend` 添加至少一个后端
+    pub fn new(
+        registry: FunctionRegistry,
+        monitor: Arc<PerformanceMonitor>,
+        policy: Arc<dyn RoutingPolicy>,
+    ) -> Self {
+        Self {
+            registry,
+            monitor,
+            policy,
+            backends: Vec::new(),
+        }
+    }
+
+    /// 按配置创建后端，后端与 `runtime` 共享缓存和性能监控
+    pub fn from_config(
+        registry: FunctionRegistry,
+        runtime: &SimpleRuntime,
+        config: &RoutingConfig,
+    ) -> anyhow::Result<Self> {
+        let mut names = HashSet::new();
+        if let Some(duplicate) = config.backends.iter().find(|b| !names.insert(&b.name)) {
+            anyhow::bail!("Duplicate runtime backend name: {}", duplicate.name);
+        }
+        if config.backends.is_empty() {
+            anyhow::bail!("Routing requires at least one runtime backend");
+        }
+
+        let mut scheduler = Self::new(registry, runtime.monitor().clone(), config.policy.build());
+        for backend in &config.backends {
+            scheduler = scheduler.with_backend(
+                &backend.name,
+                Arc::new(runtime.sharing(backend.compilation)?),
+            );
+        }
+        Ok(scheduler)
+    }
+
+    /// 添加一个命名后端
+    pub fn with_backend(mut self, name: &str, backend: Arc<dyn RuntimeBackend>) -> Self {
+        self.backends.push((name.to_string(), backend));
+        self
+    }
+
+    /// 后端名（按添加顺序）
+    pub fn backend_names(&self) -> Vec<&str> {
+        self.backends
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// 是否有编译执行 Rust 函数的后端
+    pub fn supports_compilation(&self) -> bool {
+        self.backends
+            .iter()
+            .any(|(_, backend)| backend.supports_compilation())
+    }
+
+    /// 各后端的编译器
+    pub fn compilers(&self) -> Vec<Arc<RustCompiler>> {
+        self.backends
+            .iter()
+            .filter_map(|(_, backend)| backend.compiler().cloned())
+            .collect()
+    }
+
+    /// 各后端当前的负载
+    pub async fn loads(&self) -> Vec<BackendLoad> {
+        let stats = self.monitor.backend_stats().await;
+        self.backends
+            .iter()
+            .map(|(name, backend)| {
+                let stats = stats.get(name).cloned().unwrap_or_default();
+                BackendLoad {
+                    name: name.clone(),
+                    supports_compilation: backend.supports_compilation(),
+                    in_flight: stats.in_flight,
+                    invocations: stats.invocations,
+                }
+            })
+            .collect()
+    }
+
+    /// 按路由策略选择后端并执行一次尝试
+    pub async fn dispatch(
+        &self,
+        function: &FunctionMetadata,
+        request: &InvokeRequest,
+        context: &InvocationContext,
+    ) -> Result<InvokeResponse> {
+        if self.backends.is_empty() {
+            return Err(FluxError::Runtime(
+                "No runtime backend configured".to_string(),
+            ));
+        }
+        let loads = self.loads().await;
+        let index = self
+            .policy
+            .choose(function, &loads)
+            .min(self.backends.len() - 1);
+        let (name, backend) = &self.backends[index];
+        tracing::debug!("Routing function {} to backend {}", function.name, name);
+
+        self.monitor.backend_started(name).await;
+        let started = Instant::now();
+        let result = backend.execute_attempt(function, request, context).await;
+        let success = matches!(&result, Ok(response) if response.status.is_success());
+        self.monitor
+            .backend_finished(name, success, started.elapsed())
+            .await;
+
+        result.map(|mut response| {
+            response.backend = Some(name.clone());
+            response
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Scheduler for MultiRuntimeScheduler {
+    async fn schedule(
+        &self,
+        function_name: &str,
+        request: InvokeRequest,
+    ) -> Result<InvokeResponse> {
+        let function = self.registry.get(function_name).await?;
+        let context = InvocationContext::new(&function, scru128::new_string());
+        self.dispatch(&function, &request, &context).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::ExecutionStatus;
+    use std::time::Duration;
+
+    /// 返回后端名的假后端，执行期间阻塞一段时间以便观察在途调用数
+    #[derive(Debug)]
+    struct NamedBackend {
+        compilation: bool,
+        delay: Duration,
+    }
+
+    #[async_trait::async_trait]
+    impl RuntimeBackend for NamedBackend {
+        async fn execute_attempt(
+            &self,
+            _function: &FunctionMetadata,
+            _request: &InvokeRequest,
+            context: &InvocationContext,
+        ) -> Result<InvokeResponse> {
+            tokio::time::sleep(self.delay).await;
+            Ok(InvokeResponse {
+                output: serde_json::json!({"compiled": self.compilation}),
+                execution_time_ms: 0,
+                status: ExecutionStatus::Success,
+                request_id: None,
+                attempts_made: context.attempt,
+                succeeded_on_retry: false,
+                cold_start: false,
+                output_schema_violations: None,
+                backend: None,
+                replayed: false,
+                chaos_injected: false,
+                return_type_mismatch: false,
+                timing: Default::default(),
+                termination: None,
+                execution_path: None,
+                peer: None,
+                redirected_from: None,
+            })
+        }
+
+        fn supports_compilation(&self) -> bool {
+            self.compilation
+        }
+    }
+
+    fn request() -> InvokeRequest {
+        InvokeRequest {
+            input: serde_json::json!({}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_by_script_type_routes_rust_to_compiled_backend() {
+        let registry = FunctionRegistry::new();
+        let program = FunctionMetadata::new("prog".to_string(), "fn main() {}".to_string());
+        let expression = FunctionMetadata::new("sum".to_string(), "return a + b".to_string());
+        registry.register(program).await.unwrap();
+        registry.register(expression).await.unwrap();
+
+        let monitor = Arc::new(PerformanceMonitor::new());
+        let backend = |compilation| {
+            Arc::new(NamedBackend {
+                compilation,
+                delay: Duration::ZERO,
+            })
+        };
+        let scheduler = MultiRuntimeScheduler::new(
+            registry,
+            monitor.clone(),
+            RoutingPolicyKind::ByScriptType.build(),
+        )
+        .with_backend("interpreter", backend(false))
+        .with_backend("compiled", backend(true));
+
+        let response = scheduler.schedule("prog", request()).await.unwrap();
+        assert_eq!(response.backend.as_deref(), Some("compiled"));
+        let response = scheduler.schedule("sum", request()).await.unwrap();
+        assert_eq!(response.backend.as_deref(), Some("interpreter"));
+
+        let stats = monitor.backend_stats().await;
+        assert_eq!(stats["compiled"].invocations, 1);
+        assert_eq!(stats["interpreter"].invocations, 1);
+        assert_eq!(stats["interpreter"].in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_least_in_flight_spreads_concurrent_invocations() {
+        let registry = FunctionRegistry::new();
+        let function = FunctionMetadata::new("sum".to_string(), "return a + b".to_string());
+        registry.register(function).await.unwrap();
+
+        let backend = || {
+            Arc::new(NamedBackend {
+                compilation: false,
+                delay: Duration::from_millis(200),
+            })
+        };
+        let scheduler = MultiRuntimeScheduler::new(
+            registry,
+            Arc::new(PerformanceMonitor::new()),
+            RoutingPolicyKind::LeastInFlight.build(),
+        )
+        .with_backend("a", backend())
+        .with_backend("b", backend());
+
+        let first = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.schedule("sum", request()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let loads = scheduler.loads().await;
+        assert_eq!(loads[0].in_flight, 1);
+
+        let second = scheduler.schedule("sum", request()).await.unwrap();
+        assert_eq!(second.backend.as_deref(), Some("b"));
+        assert_eq!(first.await.unwrap().unwrap().backend.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn test_routing_config_defaults() {
+        let config: RoutingConfig = toml::from_str("policy = \"least_in_flight\"").unwrap();
+        assert_eq!(config.policy, RoutingPolicyKind::LeastInFlight);
+        assert_eq!(config.backends.len(), 2);
+        assert!(config.backends[0].compilation);
+    }
+}
@@ -0,0 +1,443 @@
+//! 按客户端会话分组的调用记录
+//!
+//! 调用请求带有 `session_id`（请求体字段或 `X-Flux-Session-Id` 请求头）时，调度器在调用结束后
+//! 把函数名、按函数脱敏规则处理的输入、状态、耗时和较小的输出记入该会话，并累计调用数、
+//! 错误数和总执行时间。没有会话 ID 的调用不经过这里。
+//!
+//! 存储只在内存中且有界：每个会话只保留最近的若干条调用，会话数超出上限时淘汰最久未活动的会话。
+
+use crate::functions::redaction::Redactor;
+use crate::functions::{
+    ErrorOrigin, ExecutionStatus, FluxError, FunctionMetadata, InvokeResponse, Result,
+};
+use crate::runtime::rolling::bump;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// 会话 ID 的最大长度
+pub const MAX_SESSION_ID_LEN: usize = 128;
+
+/// 会话调用记录配置（`[sessions]`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionsConfig {
+    /// 同时保留的会话数上限，超出时淘汰最久未活动的会话
+    pub max_sessions: usize,
+    /// 每个会话保留的最近调用数
+    pub max_executions_per_session: usize,
+    /// 输入和输出的 JSON 字节数不超过该值时才随调用记录保留
+    pub max_payload_bytes: usize,
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self {
+            max_sessions: 1000,
+            max_executions_per_session: 100,
+            max_payload_bytes: 4 * 1024,
+        }
+    }
+}
+
+/// 校验会话 ID：非空、不超过 128 个字符，只含字母、数字和 `-`、`_`、`.`、`:`
+pub fn validate_session_id(id: &str) -> Result<()> {
+    if id.is_empty()
+        || id.len() > MAX_SESSION_ID_LEN
+        || !id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+    {
+        return Err(FluxError::ValidationError {
+            reason: format!(
+                "Session id must be 1-{MAX_SESSION_ID_LEN} characters of letters, digits, '-', '_', '.' or ':'"
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// 会话中的一次调用
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SessionExecution {
+    pub invocation_id: String,
+    pub function: String,
+    /// 输入的 JSON 字节数
+    pub input_bytes: usize,
+    /// 脱敏后的输入（超出字节上限或函数不存在时缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<Value>,
+    /// 调用成功且执行状态为成功
+    pub success: bool,
+    /// 执行状态（调度错误时缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ExecutionStatus>,
+    /// 脱敏后的输出（调度错误或超出字节上限时缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    /// 输出是否因超出字节上限未保留
+    pub output_truncated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_origin: Option<ErrorOrigin>,
+    pub duration_ms: u64,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// 会话的累计统计
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SessionSummary {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_active_at: DateTime<Utc>,
+    /// 累计调用数（包括已不再保留的调用）
+    pub invocations: u64,
+    /// 累计失败的调用数
+    pub errors: u64,
+    /// 累计执行时间（毫秒）
+    pub total_execution_ms: u64,
+    /// 当前保留的调用数
+    pub retained_executions: usize,
+}
+
+/// 会话调用列表的一页（最新的调用在前）
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SessionExecutionsPage {
+    pub session: SessionSummary,
+    pub offset: usize,
+    pub limit: usize,
+    pub executions: Vec<SessionExecution>,
+}
+
+#[derive(Debug)]
+struct Session {
+    summary: SessionSummary,
+    executions: VecDeque<SessionExecution>,
+    /// 最近活动的序号，用于按 LRU 淘汰
+    touched: u64,
+}
+
+#[derive(Debug, Default)]
+struct Table {
+    sessions: HashMap<String, Session>,
+    clock: u64,
+}
+
+/// 会话调用记录（同一进程的调度器配置共享）
+#[derive(Debug, Default)]
+pub struct SessionStore {
+    config: StdMutex<SessionsConfig>,
+    table: StdMutex<Table>,
+}
+
+impl SessionStore {
+    /// 更新配置，上限缩小时淘汰多余的会话和调用
+    pub fn configure(&self, config: &SessionsConfig) {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        let mut table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        for session in table.sessions.values_mut() {
+            while session.executions.len() > config.max_executions_per_session {
+                session.executions.pop_front();
+            }
+            session.summary.retained_executions = session.executions.len();
+        }
+        while table.sessions.len() > config.max_sessions {
+            evict_oldest(&mut table);
+        }
+    }
+
+    pub fn config(&self) -> SessionsConfig {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 记录一次调用；`function` 为 `None`（函数不存在）时不保留输入和输出
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &self,
+        session_id: &str,
+        function_name: &str,
+        function: Option<&FunctionMetadata>,
+        invocation_id: &str,
+        input: &Value,
+        result: std::result::Result<&InvokeResponse, &FluxError>,
+        duration: Duration,
+    ) {
+        let config = self.config();
+        if config.max_sessions == 0 {
+            return;
+        }
+        let redactor =
+            function.map(|function| Redactor::for_function(function).with_secrets_from(input));
+        let retain = |value: &Value| {
+            let redactor = redactor.as_ref()?;
+            let redacted = redactor.redact(value);
+            let bytes = serde_json::to_vec(&redacted).map_or(usize::MAX, |bytes| bytes.len());
+            (bytes <= config.max_payload_bytes).then_some(redacted)
+        };
+
+        let (status, output, error, error_origin) = match result {
+            Ok(response) => (
+                Some(response.status.clone()),
+                Some(retain(&response.output)),
+                response.status.failure_message(),
+                response.status.origin(),
+            ),
+            Err(e) => (None, None, Some(e.to_string()), Some(e.origin())),
+        };
+        let success = status.as_ref().is_some_and(ExecutionStatus::is_success);
+        let duration_ms = duration.as_millis() as u64;
+        let now = Utc::now();
+        let execution = SessionExecution {
+            invocation_id: invocation_id.to_string(),
+            function: function_name.to_string(),
+            input_bytes: serde_json::to_vec(input).map_or(0, |bytes| bytes.len()),
+            input: retain(input),
+            success,
+            status,
+            output_truncated: redactor.is_some() && output.as_ref().is_some_and(Option::is_none),
+            output: output.flatten(),
+            error: error.map(|error| match &redactor {
+                Some(redactor) => redactor.scrub(&error),
+                None => error,
+            }),
+            error_origin,
+            duration_ms,
+            recorded_at: now,
+        };
+
+        let mut table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        table.clock += 1;
+        let touched = table.clock;
+        if !table.sessions.contains_key(session_id) {
+            while table.sessions.len() >= config.max_sessions {
+                evict_oldest(&mut table);
+            }
+        }
+        let session = table
+            .sessions
+            .entry(session_id.to_string())
+            .or_insert_with(|| Session {
+                summary: SessionSummary {
+                    id: session_id.to_string(),
+                    created_at: now,
+                    last_active_at: now,
+                    invocations: 0,
+                    errors: 0,
+                    total_execution_ms: 0,
+                    retained_executions: 0,
+                },
+                executions: VecDeque::new(),
+                touched,
+            });
+        session.touched = touched;
+        let summary = &mut session.summary;
+        summary.last_active_at = now;
+        bump(&mut summary.invocations);
+        if !success {
+            bump(&mut summary.errors);
+        }
+        summary.total_execution_ms = summary.total_execution_ms.saturating_add(duration_ms);
+        while session.executions.len() >= config.max_executions_per_session.max(1) {
+            session.executions.pop_front();
+        }
+        session.executions.push_back(execution);
+        session.summary.retained_executions = session.executions.len();
+    }
+
+    /// 会话的累计统计
+    pub fn get(&self, session_id: &str) -> Option<SessionSummary> {
+        let table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        table
+            .sessions
+            .get(session_id)
+            .map(|session| session.summary.clone())
+    }
+
+    /// 会话的调用列表（最新的在前），跳过 `offset` 条后最多返回 `limit` 条
+    pub fn executions(
+        &self,
+        session_id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Option<SessionExecutionsPage> {
+        let table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        let session = table.sessions.get(session_id)?;
+        Some(SessionExecutionsPage {
+            session: session.summary.clone(),
+            offset,
+            limit,
+            executions: session
+                .executions
+                .iter()
+                .rev()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// 清除一个会话，返回是否存在
+    pub fn remove(&self, session_id: &str) -> bool {
+        self.table
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .sessions
+            .remove(session_id)
+            .is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.table
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .sessions
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 淘汰最久未活动的会话
+fn evict_oldest(table: &mut Table) {
+    if let Some(oldest) = table
+        .sessions
+        .iter()
+        .min_by_key(|(_, session)| session.touched)
+        .map(|(id, _)| id.clone())
+    {
+        table.sessions.remove(&oldest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(output: Value, status: ExecutionStatus) -> InvokeResponse {
+        let mut response: InvokeResponse = serde_json::from_value(json!({
+            "output": null,
+            "execution_time_ms": 1,
+            "status": "Success",
+        }))
+        .unwrap();
+        response.output = output;
+        response.status = status;
+        response
+    }
+
+    fn store(max_sessions: usize, max_executions_per_session: usize) -> SessionStore {
+        let store = SessionStore::default();
+        store.configure(&SessionsConfig {
+            max_sessions,
+            max_executions_per_session,
+            max_payload_bytes: 64,
+        });
+        store
+    }
+
+    #[test]
+    fn test_records_are_bounded_per_session_and_redacted() {
+        let store = store(10, 3);
+        let mut function = FunctionMetadata::new("calc".to_string(), "return a".to_string());
+        function.log_redaction = vec!["password".to_string()];
+        let ok = response(json!({"result": 1}), ExecutionStatus::Success);
+        let failed = response(
+            json!({"error": "boom"}),
+            ExecutionStatus::error(crate::functions::ErrorKind::Runtime, "boom"),
+        );
+        for i in 0..5 {
+            let result = if i == 4 { &failed } else { &ok };
+            store.record(
+                "nb-1",
+                "calc",
+                Some(&function),
+                &format!("call-{i}"),
+                &json!({"a": i, "password": "hunter2"}),
+                Ok(result),
+                Duration::from_millis(10),
+            );
+        }
+        let large = response(json!({"data": "x".repeat(100)}), ExecutionStatus::Success);
+        store.record(
+            "nb-1",
+            "calc",
+            Some(&function),
+            "call-5",
+            &json!({"a": 5}),
+            Ok(&large),
+            Duration::from_millis(10),
+        );
+
+        let summary = store.get("nb-1").unwrap();
+        assert_eq!(summary.invocations, 6);
+        assert_eq!(summary.errors, 1);
+        assert_eq!(summary.total_execution_ms, 60);
+        assert_eq!(summary.retained_executions, 3);
+
+        let page = store.executions("nb-1", 0, 2).unwrap();
+        let ids: Vec<_> = page
+            .executions
+            .iter()
+            .map(|execution| execution.invocation_id.as_str())
+            .collect();
+        assert_eq!(ids, ["call-5", "call-4"]);
+        assert!(page.executions[0].output_truncated && page.executions[0].output.is_none());
+        assert!(!page.executions[1].success);
+        assert_ne!(
+            page.executions[1].input.as_ref().unwrap()["password"],
+            "hunter2"
+        );
+        assert_eq!(store.executions("nb-1", 2, 10).unwrap().executions.len(), 1);
+
+        assert!(store.remove("nb-1"));
+        assert!(store.get("nb-1").is_none());
+        assert!(!store.remove("nb-1"));
+    }
+
+    #[test]
+    fn test_least_recently_active_session_is_evicted() {
+        let store = store(2, 10);
+        let ok = response(json!(1), ExecutionStatus::Success);
+        let record = |session: &str| {
+            store.record(
+                session,
+                "missing",
+                None,
+                "call",
+                &json!({}),
+                Ok(&ok),
+                Duration::ZERO,
+            )
+        };
+        record("a");
+        record("b");
+        record("a");
+        record("c");
+        assert_eq!(store.len(), 2);
+        assert!(store.get("a").is_some() && store.get("c").is_some());
+        assert!(store.get("b").is_none());
+        // 函数不存在时无法脱敏，不保留输入和输出
+        let execution = &store.executions("a", 0, 1).unwrap().executions[0];
+        assert!(execution.input.is_none() && execution.output.is_none());
+    }
+
+    #[test]
+    fn test_validate_session_id() {
+        assert!(validate_session_id("notebook-42:cell.3").is_ok());
+        assert!(validate_session_id("").is_err());
+        assert!(validate_session_id("has space").is_err());
+        assert!(validate_session_id(&"s".repeat(129)).is_err());
+    }
+}
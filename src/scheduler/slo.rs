@@ -0,0 +1,310 @@
+//! 函数 SLO 的报告和燃烧率告警
+//!
+//! 配置了 `slo` 的函数由后台任务按间隔检查快速和慢速窗口的燃烧率（见
+//! [`SimpleScheduler::run_slo_alerts`]），告警的开始和解除发布到生命周期事件流，
+//! 并按函数的 webhook 配置通知 `on_failure_url`。
+
+use super::SimpleScheduler;
+use super::webhooks::WebhookPayload;
+use crate::functions::slo::SloConfig;
+use crate::functions::{FunctionMetadata, Result};
+use crate::runtime::events::{InstanceLifecycleEvent, LifecycleEventStream, LifecycleEventType};
+use crate::runtime::slo::{
+    BurnRateStatus, BurnWindow, SloAlertTransition, SloCompliance, burn_rate,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+/// SLO 告警事件不属于某个实例，实例 ID 固定为该值
+pub const SLO_EVENT_INSTANCE: &str = "slo";
+
+/// 函数的 SLO 配置、合规数字和各告警窗口的燃烧率
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SloReport {
+    pub function: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<SloConfig>,
+    /// SLO 窗口内的合规数字
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compliance: Option<SloCompliance>,
+    /// 快速和慢速告警窗口的燃烧率及告警状态
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub burn_rates: Vec<BurnRateStatus>,
+}
+
+impl SimpleScheduler {
+    /// 函数的 SLO 报告，没有配置 SLO 时只有函数名
+    pub async fn slo_report(&self, name: &str) -> Result<SloReport> {
+        let function = self.registry.get(name).await?;
+        Ok(self.evaluate_slo(&function).await)
+    }
+
+    async fn evaluate_slo(&self, function: &FunctionMetadata) -> SloReport {
+        let mut report = SloReport {
+            function: function.name.clone(),
+            config: function.slo.clone(),
+            compliance: None,
+            burn_rates: Vec::new(),
+        };
+        let Some(slo) = &function.slo else {
+            return report;
+        };
+        let monitor = self.runtime.monitor();
+        let window = monitor.function_window(&function.name, slo.window()).await;
+        report.compliance = Some(SloCompliance::compute(slo, &window));
+        let config = self.slo_alerts.config();
+        for burn_window in BurnWindow::ALL {
+            let (window_secs, threshold) = config.window(burn_window);
+            let summary = monitor
+                .function_window(&function.name, Duration::from_secs(window_secs))
+                .await
+                .summary;
+            let since = self.slo_alerts.since(&function.name, burn_window);
+            report.burn_rates.push(BurnRateStatus {
+                window: burn_window,
+                window_secs,
+                threshold,
+                burn_rate: burn_rate(slo, &summary),
+                invocations: summary.invocations,
+                alerting: since.is_some(),
+                since,
+            });
+        }
+        report
+    }
+
+    /// 检查所有配置了 SLO 的函数的燃烧率，发出告警的开始和解除，返回本轮的状态变化
+    ///
+    /// 状态变化发布到 `events`（缺省时不发布），并按函数的 webhook 配置投递。
+    pub async fn run_slo_alerts(
+        &self,
+        events: Option<&LifecycleEventStream>,
+    ) -> Vec<SloAlertTransition> {
+        let functions: Vec<_> = self
+            .registry
+            .list()
+            .await
+            .into_iter()
+            .filter(|function| function.slo.is_some())
+            .collect();
+        // SLO 被移除或函数被删除时丢弃告警状态
+        self.slo_alerts
+            .retain(|name| functions.iter().any(|function| function.name == name));
+
+        let mut transitions = Vec::new();
+        for function in &functions {
+            let report = self.evaluate_slo(function).await;
+            let error_budget_remaining = report
+                .compliance
+                .as_ref()
+                .map_or(1.0, |compliance| compliance.error_budget_remaining);
+            for status in report.burn_rates {
+                let now = Utc::now();
+                let Some(firing) = self.slo_alerts.observe(
+                    &function.name,
+                    status.window,
+                    status.burn_rate,
+                    status.invocations,
+                    now,
+                ) else {
+                    continue;
+                };
+                let transition = SloAlertTransition {
+                    function: function.name.clone(),
+                    window: status.window,
+                    firing,
+                    burn_rate: status.burn_rate,
+                    threshold: status.threshold,
+                    window_secs: status.window_secs,
+                    error_budget_remaining,
+                    at: now,
+                };
+                self.notify_slo_alert(function, &transition, events).await;
+                transitions.push(transition);
+            }
+        }
+        transitions
+    }
+
+    async fn notify_slo_alert(
+        &self,
+        function: &FunctionMetadata,
+        transition: &SloAlertTransition,
+        events: Option<&LifecycleEventStream>,
+    ) {
+        let description = transition.description();
+        let event_type = if transition.firing {
+            tracing::warn!("Function {}: {}", function.name, description);
+            LifecycleEventType::SloBurnRateAlert
+        } else {
+            tracing::info!("Function {}: {}", function.name, description);
+            LifecycleEventType::SloBurnRateRecovered
+        };
+        let mut event = InstanceLifecycleEvent::new(
+            SLO_EVENT_INSTANCE,
+            &function.name,
+            event_type,
+            description,
+        );
+        event.timestamp = transition.at;
+        event.metadata.extend([
+            ("window".to_string(), transition.window.as_str().to_string()),
+            (
+                "window_secs".to_string(),
+                transition.window_secs.to_string(),
+            ),
+            (
+                "burn_rate".to_string(),
+                format!("{:.4}", transition.burn_rate),
+            ),
+            ("threshold".to_string(), transition.threshold.to_string()),
+            (
+                "error_budget_remaining".to_string(),
+                format!("{:.4}", transition.error_budget_remaining),
+            ),
+        ]);
+        if let Some(webhooks) = &function.webhooks {
+            self.webhooks.enqueue(
+                webhooks,
+                WebhookPayload::slo_alert(&event.event_id, transition),
+            );
+        }
+        if let Some(events) = events {
+            events.publish(event).await;
+        }
+    }
+
+    /// 在后台按 `tick` 检查燃烧率告警，告警事件发布到 `events`
+    pub fn spawn_slo_alerts(
+        self: &Arc<Self>,
+        tick: Duration,
+        events: Arc<LifecycleEventStream>,
+    ) -> JoinHandle<()> {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(tick);
+            loop {
+                ticker.tick().await;
+                scheduler.run_slo_alerts(Some(&events)).await;
+            }
+        })
+    }
+
+    /// 设置（`None` 时移除）函数的 SLO
+    pub async fn set_slo(&self, name: &str, slo: Option<SloConfig>) -> Result<FunctionMetadata> {
+        if let Some(slo) = &slo {
+            slo.validate()?;
+        }
+        let _guard = self.registry.lock_name(name).await;
+        let mut function = self.registry.get(name).await?;
+        function.slo = slo;
+        function.updated_at = Utc::now();
+        self.registry
+            .upsert_if(function, None)
+            .await
+            .map(|(function, _)| function)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::webhook::WebhookConfig;
+    use crate::functions::{FluxError, InvokeRequest};
+    use crate::scheduler::Scheduler;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_failing_function_raises_and_reports_burn_rate_alerts() {
+        let scheduler = SimpleScheduler::new();
+        let mut function =
+            FunctionMetadata::new("flaky".to_string(), "return input.a.b".to_string());
+        function.slo = Some(SloConfig {
+            availability: 0.99,
+            latency_p95_ms: None,
+            window: "7d".to_string(),
+        });
+        // 接收方不存在，只检查入队的投递
+        function.webhooks = Some(WebhookConfig {
+            on_failure_url: Some("http://127.0.0.1:9/alerts".to_string()),
+            statuses: vec![crate::functions::webhook::WebhookEvent::SloAlert],
+            ..Default::default()
+        });
+        scheduler.register_function(function).await.unwrap();
+        assert!(matches!(
+            scheduler
+                .set_slo(
+                    "flaky",
+                    Some(SloConfig {
+                        availability: 1.5,
+                        latency_p95_ms: None,
+                        window: "1h".to_string(),
+                    })
+                )
+                .await,
+            Err(FluxError::ValidationError { .. })
+        ));
+
+        for i in 0..20 {
+            let input = if i % 2 == 0 {
+                json!({"a": {"b": 1}})
+            } else {
+                json!(1)
+            };
+            let request = InvokeRequest {
+                input,
+                retry_policy: None,
+                priority: None,
+                idempotency_key: None,
+                session_id: None,
+                affinity_key: None,
+            };
+            let _ = scheduler.schedule("flaky", request).await;
+        }
+
+        let report = scheduler.slo_report("flaky").await.unwrap();
+        let compliance = report.compliance.unwrap();
+        assert_eq!((compliance.invocations, compliance.errors), (20, 10));
+        // 7 天窗口超出 24 小时的保留时长
+        assert!(compliance.window_degraded);
+        assert!(!compliance.availability_met);
+        assert!((compliance.burn_rate - 50.0).abs() < 1e-6);
+        assert!(report.burn_rates.iter().all(|status| !status.alerting));
+
+        let events = LifecycleEventStream::default();
+        let transitions = scheduler.run_slo_alerts(Some(&events)).await;
+        assert_eq!(transitions.len(), 2);
+        assert!(transitions.iter().all(|transition| transition.firing));
+        let published = events.recent(10).await;
+        assert_eq!(published.len(), 2);
+        assert!(published.iter().all(|event| {
+            event.event_type == LifecycleEventType::SloBurnRateAlert
+                && event.instance_id == SLO_EVENT_INSTANCE
+                && event.function_name == "flaky"
+        }));
+        let deliveries = scheduler.webhooks().deliveries("flaky").deliveries;
+        assert_eq!(deliveries.len(), 2);
+        assert!(deliveries.iter().all(|delivery| {
+            delivery.payload.status == crate::functions::webhook::WebhookEvent::SloAlert
+        }));
+
+        // 告警持续期间不重复发出
+        assert!(scheduler.run_slo_alerts(Some(&events)).await.is_empty());
+        let report = scheduler.slo_report("flaky").await.unwrap();
+        assert!(report.burn_rates.iter().all(|status| status.alerting));
+
+        // 移除 SLO 后告警状态被丢弃，报告只有函数名
+        scheduler.set_slo("flaky", None).await.unwrap();
+        assert!(scheduler.run_slo_alerts(Some(&events)).await.is_empty());
+        let report = scheduler.slo_report("flaky").await.unwrap();
+        assert!(report.config.is_none() && report.burn_rates.is_empty());
+        assert_eq!(
+            scheduler.slo_alerts().since("flaky", BurnWindow::Fast),
+            None
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! 执行函数自带的测试用例
+//!
+//! 用例以测试模式执行：与金丝雀探测一样没有副作用（状态只读、不发起出站请求、不发送 webhook、
+//! 不进入执行历史），监控只计入测试次数，命名空间中单独计数。执行的函数定义可以尚未注册，
+//! 部署和导入据此在激活前测试候选定义。
+
+use super::SimpleScheduler;
+use crate::functions::context::InvocationContext;
+use crate::functions::priority::Priority;
+use crate::functions::schema::FunctionSchemas;
+use crate::functions::template::InputTemplate;
+use crate::functions::testing::{
+    self, FunctionTestCase, FunctionTestReport, MAX_TEST_RUN_MS, TEST_CONCURRENCY, TestCaseResult,
+    TestStatus,
+};
+use crate::functions::{FluxError, FunctionMetadata, InvokeRequest, InvokeResponse, Result};
+use futures_util::StreamExt;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+impl SimpleScheduler {
+    /// 执行已注册函数的全部用例，`names` 不为空时只执行其中的用例
+    pub async fn run_function_tests(
+        &self,
+        name: &str,
+        names: Option<&[String]>,
+    ) -> Result<FunctionTestReport> {
+        let function = self.registry.get(name).await?;
+        let cases = testing::select(&function, names)?;
+        Ok(self.run_tests(&function, cases).await)
+    }
+
+    /// 执行函数定义的用例：最多同时执行 [`TEST_CONCURRENCY`] 个，总时长超过
+    /// [`MAX_TEST_RUN_MS`] 后剩余的用例记为失败
+    pub async fn run_tests(
+        &self,
+        function: &FunctionMetadata,
+        cases: Vec<FunctionTestCase>,
+    ) -> FunctionTestReport {
+        let started = Instant::now();
+        let budget_end = started + Duration::from_millis(MAX_TEST_RUN_MS);
+        let results = futures_util::stream::iter(cases)
+            .map(|case| self.run_test_case(function, case, budget_end))
+            .buffered(TEST_CONCURRENCY)
+            .collect()
+            .await;
+        let report = FunctionTestReport::new(function, results, started.elapsed());
+        tracing::info!(
+            "Tested function {} {}: {}",
+            function.name,
+            function.version,
+            report.summary()
+        );
+        report
+    }
+
+    async fn run_test_case(
+        &self,
+        function: &FunctionMetadata,
+        case: FunctionTestCase,
+        budget_end: Instant,
+    ) -> TestCaseResult {
+        let started = Instant::now();
+        let remaining = budget_end.saturating_duration_since(started);
+        if remaining.is_zero() {
+            let error = format!("Test run exceeded {MAX_TEST_RUN_MS} ms before this case started");
+            return case.evaluate(
+                TestStatus::Timeout,
+                json!(null),
+                Some(error),
+                Duration::ZERO,
+            );
+        }
+        let timeout =
+            Duration::from_millis(case.timeout_ms.unwrap_or(function.timeout_ms)).min(remaining);
+        let (status, output, error) = match tokio::time::timeout(
+            timeout,
+            self.execute_test(function, &case, timeout),
+        )
+        .await
+        {
+            Err(_) => (
+                TestStatus::Timeout,
+                json!(null),
+                Some(format!(
+                    "Test case timed out after {} ms",
+                    timeout.as_millis()
+                )),
+            ),
+            Ok(Err(e)) => (
+                TestStatus::Error,
+                json!({"error": e.to_string()}),
+                Some(e.to_string()),
+            ),
+            Ok(Ok(response)) => (
+                TestStatus::of(&response.status),
+                response.output,
+                response.status.failure_message(),
+            ),
+        };
+        case.evaluate(status, output, error, started.elapsed())
+    }
+
+    /// 以测试模式执行一个用例：应用输入模板和 JSON Schema，经过全局调用名额、命名空间限制和
+    /// 准入队列（最低优先级），按函数的重试策略执行
+    async fn execute_test(
+        &self,
+        function: &FunctionMetadata,
+        case: &FunctionTestCase,
+        timeout: Duration,
+    ) -> Result<InvokeResponse> {
+        let mut function = function.clone();
+        function.timeout_ms = (timeout.as_millis() as u64).max(1);
+        let deadline = Instant::now() + timeout;
+        let _global_permit = self.invocations.acquire(deadline).await?;
+
+        let mut input = case.input.clone();
+        if let Some(template) = &function.input_template {
+            input = InputTemplate::parse(template)?.apply(&input)?;
+        }
+        // 候选定义尚未注册，schema 不经过按函数缓存的编译结果
+        let schemas = FunctionSchemas::compile(&function)?;
+        let violations = schemas.check_input(&input);
+        if !violations.is_empty() {
+            return Err(FluxError::InputSchemaViolation {
+                name: function.name.clone(),
+                violations,
+            });
+        }
+
+        let _namespace_permit = self.namespaces.admit_test(&mut function)?;
+        let _permit = self.admission.acquire(Priority::Low).await?;
+        let state = self.state.grant(&function.name, true);
+        let egress = self.egress.grant(&function, deadline, true);
+        let context = InvocationContext::new(&function, format!("test-{}", scru128::new_string()))
+            .with_state(state.handle())
+            .with_egress(egress.handle())
+            .with_test(true)
+            .with_deadline(deadline);
+        let request = InvokeRequest {
+            input,
+            retry_policy: None,
+            priority: Some(Priority::Low),
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+        let response = self
+            .execute_with_retries(&function, &request, &context)
+            .await?;
+        if response.status.is_success() && function.strict_output_schema {
+            let violations = schemas.check_output(&response.output);
+            if !violations.is_empty() {
+                return Err(FluxError::OutputSchemaViolation {
+                    name: function.name.clone(),
+                    violations,
+                });
+            }
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::testing::{FunctionTestCase, OutputMatch, TestStatus};
+    use crate::functions::{FluxError, FunctionMetadata};
+    use crate::scheduler::SimpleScheduler;
+    use serde_json::json;
+
+    fn case(name: &str, input: serde_json::Value, expected: serde_json::Value) -> FunctionTestCase {
+        FunctionTestCase {
+            name: name.to_string(),
+            input,
+            expected_output: Some(expected),
+            expected_status: TestStatus::Success,
+            output_match: OutputMatch::Exact,
+            timeout_ms: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cases_run_without_touching_invocation_stats() {
+        let scheduler = SimpleScheduler::new();
+        let mut function = FunctionMetadata::new("sum".to_string(), "return a + b".to_string());
+        function.input_schema = Some(json!({"type": "object", "required": ["a"]}));
+        function.tests = vec![
+            // 表达式函数的输出带有输入，只比较 `result`
+            FunctionTestCase {
+                output_match: OutputMatch::Subset,
+                ..case("adds", json!({"a": 1, "b": 2}), json!({"result": 3}))
+            },
+            case("wrong", json!({"a": 1, "b": 2}), json!({"result": 4})),
+            FunctionTestCase {
+                expected_status: TestStatus::Error,
+                expected_output: None,
+                ..case("rejects", json!({}), json!(null))
+            },
+        ];
+        scheduler.registry().register(function).await.unwrap();
+
+        let report = scheduler.run_function_tests("sum", None).await.unwrap();
+        assert_eq!((report.total, report.passed, report.failed), (3, 2, 1));
+        let wrong = &report.results[1];
+        assert!(!wrong.passed);
+        assert_eq!(wrong.actual_output["result"], 3);
+        let diff = wrong.diffs.iter().find(|d| d.path == "/result").unwrap();
+        assert_eq!(
+            (&diff.expected, &diff.actual),
+            (&Some(json!(4)), &Some(json!(3)))
+        );
+        // 完全匹配时输出中多出的字段也是差异
+        assert!(wrong.diffs.iter().any(|d| d.path == "/input"));
+        assert!(
+            report.results[2]
+                .error
+                .as_deref()
+                .unwrap()
+                .contains("input_schema")
+        );
+
+        let names = ["adds".to_string()];
+        let report = scheduler
+            .run_function_tests("sum", Some(&names))
+            .await
+            .unwrap();
+        assert_eq!((report.total, report.passed), (1, 1));
+        assert!(matches!(
+            scheduler
+                .run_function_tests("sum", Some(&["missing".to_string()]))
+                .await,
+            Err(FluxError::TestCaseNotFound { .. })
+        ));
+
+        // 测试调用只计入测试次数（两次运行共执行 3 次，被 schema 拒绝的用例没有执行），
+        // 也不写入函数缓存
+        let monitor = scheduler.runtime().monitor();
+        assert_eq!(monitor.test_executions().await["sum"], 3);
+        let stats = monitor.get_function_stats("sum").await.unwrap();
+        assert_eq!(stats.total_calls, 0);
+        assert_eq!(scheduler.runtime().cache().stats().await.size, 0);
+    }
+}
@@ -0,0 +1,222 @@
+use super::SimpleScheduler;
+use super::namespaces::script_type;
+use crate::functions::{FunctionMetadata, Result};
+use crate::runtime::cache::FunctionCache;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Instant;
+use utoipa::ToSchema;
+
+/// 默认的预热并发数
+pub const DEFAULT_WARMUP_CONCURRENCY: usize = 4;
+/// 预热并发数上限，避免大批量预热挤占编译器
+pub const MAX_WARMUP_CONCURRENCY: usize = 16;
+
+/// 缓存预热请求
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct WarmupRequest {
+    /// 要预热的函数名
+    pub functions: Vec<String>,
+    /// 预热所有函数（忽略 `functions`）
+    pub all: bool,
+    /// 同时预热的函数数（默认 4，最多 16）
+    pub concurrency: Option<usize>,
+}
+
+impl WarmupRequest {
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+            .unwrap_or(DEFAULT_WARMUP_CONCURRENCY)
+            .clamp(1, MAX_WARMUP_CONCURRENCY)
+    }
+}
+
+/// 缓存预热结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WarmupReport {
+    /// 已放入缓存的函数
+    pub warmed: Vec<String>,
+    /// 其中预编译的 Rust 函数
+    pub compiled: Vec<String>,
+    /// 预热失败的函数及原因
+    pub failed: BTreeMap<String, String>,
+    pub duration_ms: u64,
+}
+
+impl WarmupReport {
+    /// 合并另一个调度器的预热结果（耗时取较大值）
+    pub fn merge(&mut self, other: WarmupReport) {
+        self.warmed.extend(other.warmed);
+        self.compiled.extend(other.compiled);
+        self.failed.extend(other.failed);
+        self.warmed.sort();
+        self.compiled.sort();
+        self.duration_ms = self.duration_ms.max(other.duration_ms);
+    }
+}
+
+/// 单个函数的预热结果
+pub(super) enum Warmed {
+    Cached,
+    Compiled,
+}
+
+impl SimpleScheduler {
+    /// 预热缓存：把函数放入缓存，启用编译时预编译 Rust 函数
+    ///
+    /// `names` 为 `None` 时预热所有函数；最多同时预热 `concurrency` 个函数。
+    pub async fn warm_cache(&self, names: Option<&[String]>, concurrency: usize) -> WarmupReport {
+        let started = Instant::now();
+        let mut report = WarmupReport::default();
+
+        let mut functions = Vec::new();
+        match names {
+            Some(names) => {
+                for name in names {
+                    match self.registry.get(name).await {
+                        Ok(function) => functions.push(function),
+                        Err(e) => {
+                            report.failed.insert(name.clone(), e.to_string());
+                        }
+                    }
+                }
+            }
+            None => functions = self.registry.list().await,
+        }
+
+        let mut results = futures_util::stream::iter(functions)
+            .map(|function| async move {
+                let result = self.warm_function(&function).await;
+                (function.name, result)
+            })
+            .buffer_unordered(concurrency.max(1));
+        while let Some((name, result)) = results.next().await {
+            match result {
+                Ok(warmed) => {
+                    if matches!(warmed, Warmed::Compiled) {
+                        report.compiled.push(name.clone());
+                    }
+                    report.warmed.push(name);
+                }
+                Err(e) => {
+                    report.failed.insert(name, e.to_string());
+                }
+            }
+        }
+        report.warmed.sort();
+        report.compiled.sort();
+
+        let duration = started.elapsed();
+        report.duration_ms = duration.as_millis() as u64;
+        self.runtime.cache().record_warmup(duration).await;
+        tracing::info!(
+            "Warmed {} functions ({} compiled, {} failed) in {}ms",
+            report.warmed.len(),
+            report.compiled.len(),
+            report.failed.len(),
+            report.duration_ms
+        );
+        report
+    }
+
+    pub(super) async fn warm_function(&self, function: &FunctionMetadata) -> Result<Warmed> {
+        let cache = self.runtime.cache();
+        let key = FunctionCache::key(function);
+        // 已缓存同一修订的条目时不替换，保留访问统计
+        let cached = cache
+            .peek(&key)
+            .await
+            .is_some_and(|cached| cached.metadata.revision == function.revision);
+        if !cached {
+            cache.put(key, function.clone()).await?;
+        }
+
+        match self.runtime.compiler() {
+            Some(compiler) if script_type(function) == "rust" => {
+                compiler.compile_function(function).await?;
+                Ok(Warmed::Compiled)
+            }
+            _ => Ok(Warmed::Cached),
+        }
+    }
+
+    /// 固定函数的缓存条目（未缓存时先预热），固定的条目不会被驱逐
+    pub async fn pin_cached(&self, name: &str) -> Result<bool> {
+        let function = self.registry.get(name).await?;
+        let newly_pinned = self.runtime.cache().pin(&function.name)?;
+        if let Err(e) = self.warm_function(&function).await {
+            tracing::warn!("Failed to warm pinned function {}: {}", function.name, e);
+        }
+        Ok(newly_pinned)
+    }
+
+    /// 取消固定函数的缓存条目
+    pub async fn unpin_cached(&self, name: &str) -> Result<bool> {
+        let function = self.registry.get(name).await?;
+        Ok(self.runtime.cache().unpin(&function.name))
+    }
+
+    /// 移除函数所有版本的缓存条目（固定状态保留），返回移除的条目数
+    pub async fn invalidate_cached(&self, name: &str) -> Result<usize> {
+        let function = self.registry.get(name).await?;
+        Ok(self.runtime.cache().remove_function(&function.name).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::SimpleRuntime;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_warm_pin_and_invalidate() {
+        // 容量为 4 的缓存，最多固定 2 个函数
+        let runtime =
+            SimpleRuntime::with_cache(Arc::new(FunctionCache::new(4, 50, 3600).with_max_pinned(2)));
+        let scheduler = SimpleScheduler::with_runtime(Arc::new(runtime));
+        for i in 0..6 {
+            let function = FunctionMetadata::new(format!("f{i}"), "return a + b".to_string());
+            scheduler.registry().register(function).await.unwrap();
+        }
+
+        let names = vec!["f0".to_string(), "f1".to_string(), "missing".to_string()];
+        let report = scheduler.warm_cache(Some(&names), 2).await;
+        assert_eq!(report.warmed, vec!["f0", "f1"]);
+        assert!(report.compiled.is_empty());
+        assert!(report.failed.contains_key("missing"));
+
+        assert!(scheduler.pin_cached("f0").await.unwrap());
+        assert!(!scheduler.pin_cached("f0").await.unwrap());
+        assert!(scheduler.pin_cached("f1").await.unwrap());
+        assert!(scheduler.pin_cached("f2").await.is_err());
+
+        // 大量一次性调用不会驱逐固定的条目
+        let report = scheduler.warm_cache(None, 3).await;
+        assert_eq!(report.warmed.len(), 6);
+        let cache = scheduler.runtime().cache();
+        let stats = cache.stats().await;
+        assert_eq!(stats.size, 4);
+        assert_eq!(stats.pinned_entries, 2);
+        assert_eq!(stats.evictable_entries, 2);
+        assert!(stats.last_warmup_ms.is_some());
+        let entries = cache.entries().await;
+        assert!(
+            entries
+                .iter()
+                .any(|entry| entry.function == "f0" && entry.pinned)
+        );
+        assert!(
+            entries
+                .iter()
+                .any(|entry| entry.function == "f1" && entry.pinned)
+        );
+
+        assert_eq!(scheduler.invalidate_cached("f0").await.unwrap(), 1);
+        assert!(cache.is_pinned("f0"));
+        assert!(scheduler.unpin_cached("f0").await.unwrap());
+        assert_eq!(cache.stats().await.pinned_entries, 1);
+    }
+}
@@ -0,0 +1,624 @@
+use crate::functions::redaction::Redactor;
+use crate::functions::webhook::{WebhookConfig, WebhookEvent};
+use crate::functions::{ErrorOrigin, FluxError, InvokeResponse};
+use crate::runtime::slo::SloAlertTransition;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::sync::{Notify, Semaphore};
+use utoipa::ToSchema;
+
+/// 签名头，值为 `sha256=<十六进制 HMAC>`
+pub const SIGNATURE_HEADER: &str = "x-flux-signature";
+/// 投递 ID 头，重试时保持不变，接收方可据此去重
+pub const DELIVERY_ID_HEADER: &str = "x-flux-delivery";
+
+/// webhook 投递配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookDispatcherConfig {
+    /// 等待投递的队列上限，超出时丢弃最早的投递
+    pub max_queue_depth: usize,
+    /// 每次投递的最大尝试次数
+    pub max_attempts: u32,
+    /// 首次重试前的等待时间（毫秒），之后每次翻倍
+    pub initial_backoff_ms: u64,
+    /// 单次请求超时（毫秒）
+    pub request_timeout_ms: u64,
+    /// 请求体中输出或错误信息的最大字符数
+    pub max_payload_chars: usize,
+    /// 每个函数保留的投递记录数
+    pub history_per_function: usize,
+    /// 同时进行的投递数
+    pub concurrency: usize,
+}
+
+impl Default for WebhookDispatcherConfig {
+    fn default() -> Self {
+        Self {
+            max_queue_depth: 1000,
+            max_attempts: 5,
+            initial_backoff_ms: 500,
+            request_timeout_ms: 5000,
+            max_payload_chars: 4096,
+            history_per_function: 100,
+            concurrency: 4,
+        }
+    }
+}
+
+/// webhook 请求体
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WebhookPayload {
+    pub function: String,
+    pub invocation_id: String,
+    pub status: WebhookEvent,
+    pub duration_ms: u64,
+    /// 函数输出的 JSON 文本（可能被截断）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// 错误信息（可能被截断）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 失败的归属（成功时缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_origin: Option<ErrorOrigin>,
+    /// 输出或错误信息是否被截断
+    pub truncated: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl WebhookPayload {
+    /// 由调度结果生成请求体；调度错误（如编译失败）按失败通知
+    ///
+    /// 输出和错误信息先按函数的日志脱敏规则处理，再截断。
+    pub fn from_result(
+        function: &str,
+        invocation_id: &str,
+        result: std::result::Result<&InvokeResponse, &FluxError>,
+        redactor: &Redactor,
+        duration: Duration,
+        max_chars: usize,
+    ) -> Self {
+        let (status, output, error) = match result {
+            Ok(response) => {
+                let status = WebhookEvent::from_status(&response.status);
+                let error = response.status.failure_message();
+                let output = redactor.scrub(&redactor.redact(&response.output).to_string());
+                (status, Some(output), error)
+            }
+            Err(FluxError::Timeout) => (
+                WebhookEvent::Timeout,
+                None,
+                Some("Execution timeout".into()),
+            ),
+            Err(e) => (WebhookEvent::Error, None, Some(e.to_string())),
+        };
+        let error_origin = match result {
+            Ok(response) => response.status.origin(),
+            Err(e) => Some(e.origin()),
+        };
+        let error = error.map(|error| redactor.scrub(&error));
+        let (output, output_truncated) = truncate(output, max_chars);
+        let (error, error_truncated) = truncate(error, max_chars);
+        Self {
+            function: function.to_string(),
+            invocation_id: invocation_id.to_string(),
+            status,
+            duration_ms: duration.as_millis() as u64,
+            output,
+            error,
+            error_origin,
+            truncated: output_truncated || error_truncated,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// SLO 告警的请求体：`invocation_id` 为告警事件 ID，`output` 为告警内容的 JSON 文本，
+    /// 开始告警时 `error` 为告警说明
+    pub fn slo_alert(event_id: &str, alert: &SloAlertTransition) -> Self {
+        Self {
+            function: alert.function.clone(),
+            invocation_id: event_id.to_string(),
+            status: WebhookEvent::SloAlert,
+            duration_ms: 0,
+            output: serde_json::to_string(alert).ok(),
+            error: alert.firing.then(|| alert.description()),
+            error_origin: None,
+            truncated: false,
+            timestamp: alert.at,
+        }
+    }
+}
+
+fn truncate(text: Option<String>, max_chars: usize) -> (Option<String>, bool) {
+    match text {
+        Some(text) if text.chars().count() > max_chars => {
+            (Some(text.chars().take(max_chars).collect()), true)
+        }
+        text => (text, false),
+    }
+}
+
+/// 投递状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    /// 等待投递或正在重试
+    Pending,
+    /// 接收方返回 2xx
+    Delivered,
+    /// 重试次数用尽
+    Failed,
+    /// 队列已满时被丢弃
+    Dropped,
+}
+
+/// 一次 webhook 投递及其结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub url: String,
+    pub payload: WebhookPayload,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// 最后一次请求的 HTTP 状态码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 函数的投递记录（最近的在前）以及全局丢弃计数
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDeliveryLog {
+    /// 因队列已满被丢弃的投递总数
+    pub dropped: u64,
+    pub deliveries: Vec<WebhookDelivery>,
+}
+
+/// 等待投递的任务
+#[derive(Debug)]
+struct DeliveryJob {
+    id: String,
+    function: String,
+    url: String,
+    secret: Option<String>,
+    body: Vec<u8>,
+}
+
+/// webhook 投递器
+///
+/// 调用路径只负责入队；后台任务按至少一次语义投递，失败时指数退避重试。
+#[derive(Debug)]
+pub struct WebhookDispatcher {
+    config: WebhookDispatcherConfig,
+    queue: StdMutex<VecDeque<DeliveryJob>>,
+    queued: Notify,
+    dropped: AtomicU64,
+    history: StdMutex<HashMap<String, VecDeque<WebhookDelivery>>>,
+    worker_started: AtomicBool,
+    client: reqwest::Client,
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::new(WebhookDispatcherConfig::default())
+    }
+}
+
+impl WebhookDispatcher {
+    pub fn new(config: WebhookDispatcherConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.request_timeout_ms))
+            .build()
+            .unwrap_or_default();
+        Self {
+            config,
+            queue: StdMutex::new(VecDeque::new()),
+            queued: Notify::new(),
+            dropped: AtomicU64::new(0),
+            history: StdMutex::new(HashMap::new()),
+            worker_started: AtomicBool::new(false),
+            client,
+        }
+    }
+
+    pub fn config(&self) -> &WebhookDispatcherConfig {
+        &self.config
+    }
+
+    /// 按函数的 webhook 配置为一次调用结果入队投递，不需要通知时不做任何事
+    ///
+    /// 只做内存操作，不会阻塞调用路径；队列已满时丢弃最早的投递。
+    pub fn enqueue(
+        self: &Arc<Self>,
+        webhooks: &WebhookConfig,
+        payload: WebhookPayload,
+    ) -> Option<String> {
+        let url = webhooks.target(payload.status)?.to_string();
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!("Failed to serialize webhook payload: {}", e);
+                return None;
+            }
+        };
+        let id = scru128::new_string();
+        let now = Utc::now();
+        let function = payload.function.clone();
+        self.record(WebhookDelivery {
+            id: id.clone(),
+            url: url.clone(),
+            payload,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            response_status: None,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        });
+
+        let dropped = {
+            let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+            let dropped = if queue.len() >= self.config.max_queue_depth.max(1) {
+                queue.pop_front()
+            } else {
+                None
+            };
+            queue.push_back(DeliveryJob {
+                id: id.clone(),
+                function,
+                url,
+                secret: webhooks.secret.clone(),
+                body,
+            });
+            dropped
+        };
+        if let Some(job) = dropped {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                "Webhook queue full, dropped delivery {} of function {}",
+                job.id,
+                job.function
+            );
+            self.update(&job.function, &job.id, |delivery| {
+                delivery.status = DeliveryStatus::Dropped;
+                delivery.last_error = Some("Dropped: webhook queue full".to_string());
+            });
+        }
+
+        self.ensure_worker();
+        self.queued.notify_one();
+        Some(id)
+    }
+
+    /// 因队列已满被丢弃的投递总数
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// 当前排队的投递数
+    pub fn queue_depth(&self) -> usize {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// 函数的投递记录（最近的在前）
+    pub fn deliveries(&self, function: &str) -> WebhookDeliveryLog {
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        WebhookDeliveryLog {
+            dropped: self.dropped_count(),
+            deliveries: history
+                .get(function)
+                .map(|deliveries| deliveries.iter().rev().cloned().collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn record(&self, delivery: WebhookDelivery) {
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let deliveries = history
+            .entry(delivery.payload.function.clone())
+            .or_default();
+        if deliveries.len() >= self.config.history_per_function.max(1) {
+            deliveries.pop_front();
+        }
+        deliveries.push_back(delivery);
+    }
+
+    fn update(&self, function: &str, id: &str, apply: impl FnOnce(&mut WebhookDelivery)) {
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(delivery) = history
+            .get_mut(function)
+            .and_then(|deliveries| deliveries.iter_mut().find(|delivery| delivery.id == id))
+        {
+            apply(delivery);
+            delivery.updated_at = Utc::now();
+        }
+    }
+
+    /// 首次入队时启动后台投递任务
+    fn ensure_worker(self: &Arc<Self>) {
+        if self.worker_started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            let permits = Arc::new(Semaphore::new(dispatcher.config.concurrency.max(1)));
+            loop {
+                let job = dispatcher
+                    .queue
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .pop_front();
+                let Some(job) = job else {
+                    dispatcher.queued.notified().await;
+                    continue;
+                };
+                let Ok(permit) = permits.clone().acquire_owned().await else {
+                    break;
+                };
+                let dispatcher = dispatcher.clone();
+                tokio::spawn(async move {
+                    dispatcher.deliver(job).await;
+                    drop(permit);
+                });
+            }
+        });
+    }
+
+    /// 投递一次，失败时按指数退避重试，直到成功或尝试次数用尽
+    async fn deliver(&self, job: DeliveryJob) {
+        let max_attempts = self.config.max_attempts.max(1);
+        for attempt in 1..=max_attempts {
+            let mut request = self
+                .client
+                .post(&job.url)
+                .header("content-type", "application/json")
+                .header(DELIVERY_ID_HEADER, &job.id)
+                .body(job.body.clone());
+            if let Some(secret) = &job.secret {
+                request = request.header(SIGNATURE_HEADER, sign(secret, &job.body));
+            }
+
+            let (response_status, error) = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status().as_u16()), None)
+                }
+                Ok(response) => (
+                    Some(response.status().as_u16()),
+                    Some(format!("Receiver returned {}", response.status())),
+                ),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            let delivered = error.is_none();
+            let exhausted = attempt == max_attempts;
+            self.update(&job.function, &job.id, |delivery| {
+                delivery.attempts = attempt;
+                delivery.response_status = response_status;
+                delivery.last_error = error.clone();
+                delivery.status = match (delivered, exhausted) {
+                    (true, _) => DeliveryStatus::Delivered,
+                    (false, true) => DeliveryStatus::Failed,
+                    (false, false) => DeliveryStatus::Pending,
+                };
+            });
+            if delivered {
+                return;
+            }
+
+            tracing::warn!(
+                "Webhook delivery {} of function {} failed (attempt {}/{}): {}",
+                job.id,
+                job.function,
+                attempt,
+                max_attempts,
+                error.unwrap_or_default()
+            );
+            if !exhausted {
+                let backoff = self
+                    .config
+                    .initial_backoff_ms
+                    .saturating_mul(1 << (attempt - 1).min(16));
+                tokio::time::sleep(Duration::from_millis(backoff)).await;
+            }
+        }
+    }
+}
+
+/// 请求体的 HMAC-SHA256 签名，格式为 `sha256=<hex>`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("sha256={hex}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::{ExecutionStatus, FunctionMetadata, InvokeRequest};
+    use crate::scheduler::{Scheduler, SimpleScheduler};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// 依次以给定状态码应答的 HTTP 接收方，转发收到的请求头和请求体
+    async fn spawn_receiver(
+        statuses: Vec<u16>,
+    ) -> (String, mpsc::UnboundedReceiver<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let (head, body_start) = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break (
+                            String::from_utf8_lossy(&buffer[..pos]).to_lowercase(),
+                            pos + 4,
+                        );
+                    }
+                };
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map(|value| value.trim().parse().unwrap())
+                    .unwrap_or(0);
+                while buffer.len() < body_start + length {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..n]);
+                }
+                let response = format!(
+                    "HTTP/1.1 {status} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                let _ = tx.send((head, buffer[body_start..body_start + length].to_vec()));
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn test_failed_invocation_delivers_signed_webhook_with_retry() {
+        let (url, mut requests) = spawn_receiver(vec![500, 200]).await;
+        let scheduler = SimpleScheduler::new().with_webhook_config(WebhookDispatcherConfig {
+            initial_backoff_ms: 10,
+            ..Default::default()
+        });
+        // 示例 add 函数缺少参数时返回执行错误
+        let mut function = FunctionMetadata::new("add".to_string(), String::new());
+        function.webhooks = Some(WebhookConfig {
+            on_failure_url: Some(url),
+            secret: Some("s3cret".to_string()),
+            ..Default::default()
+        });
+        scheduler.registry().register(function).await.unwrap();
+
+        let request = InvokeRequest {
+            input: serde_json::json!({}),
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            session_id: None,
+            affinity_key: None,
+        };
+        scheduler.schedule("add", request).await.unwrap();
+
+        // 第一次投递失败，重试后成功；两次请求携带相同的投递 ID 和签名
+        let (first_head, first_body) = requests.recv().await.unwrap();
+        let (head, body) = requests.recv().await.unwrap();
+        assert_eq!(first_body, body);
+        assert_eq!(first_head, head);
+        assert!(head.contains(&format!("{SIGNATURE_HEADER}: {}", sign("s3cret", &body))));
+
+        let payload: WebhookPayload = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload.function, "add");
+        assert_eq!(payload.status, WebhookEvent::Error);
+        assert!(payload.error.is_some());
+        assert!(head.contains(&format!("{DELIVERY_ID_HEADER}: ")));
+
+        // 投递结果可查询
+        let mut log = scheduler.webhooks().deliveries("add");
+        for _ in 0..50 {
+            if log.deliveries[0].status == DeliveryStatus::Delivered {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            log = scheduler.webhooks().deliveries("add");
+        }
+        assert_eq!(log.deliveries[0].status, DeliveryStatus::Delivered);
+        assert_eq!(log.deliveries[0].attempts, 2);
+        assert_eq!(log.deliveries[0].response_status, Some(200));
+        assert_eq!(
+            log.deliveries[0].payload.invocation_id,
+            payload.invocation_id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_drops_oldest_delivery() {
+        let dispatcher = Arc::new(WebhookDispatcher::new(WebhookDispatcherConfig {
+            max_queue_depth: 1,
+            ..Default::default()
+        }));
+        let webhooks = WebhookConfig {
+            on_success_url: Some("http://127.0.0.1:9/hook".to_string()),
+            statuses: vec![WebhookEvent::Success],
+            ..Default::default()
+        };
+        let response = InvokeResponse {
+            output: serde_json::json!("x".repeat(10)),
+            execution_time_ms: 1,
+            status: ExecutionStatus::Success,
+            request_id: None,
+            attempts_made: 1,
+            succeeded_on_retry: false,
+            cold_start: false,
+            output_schema_violations: None,
+            backend: None,
+            replayed: false,
+            chaos_injected: false,
+            return_type_mismatch: false,
+            timing: Default::default(),
+            termination: None,
+            execution_path: None,
+            peer: None,
+            redirected_from: None,
+        };
+
+        // 单线程运行时中入队期间后台任务不会运行，队列只保留最新的投递
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let payload = WebhookPayload::from_result(
+                "f",
+                &i.to_string(),
+                Ok(&response),
+                &Redactor::default(),
+                Duration::ZERO,
+                4,
+            );
+            assert!(payload.truncated);
+            ids.push(dispatcher.enqueue(&webhooks, payload).unwrap());
+        }
+        assert_eq!(dispatcher.dropped_count(), 2);
+        assert_eq!(dispatcher.queue_depth(), 1);
+
+        let log = dispatcher.deliveries("f");
+        let statuses: Vec<_> = log.deliveries.iter().map(|d| d.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                DeliveryStatus::Pending,
+                DeliveryStatus::Dropped,
+                DeliveryStatus::Dropped
+            ]
+        );
+        assert_eq!(log.deliveries[0].id, ids[2]);
+
+        // 未在通知列表中的结果不入队
+        let failed = WebhookPayload::from_result(
+            "f",
+            "e",
+            Err(&FluxError::Timeout),
+            &Redactor::default(),
+            Duration::ZERO,
+            64,
+        );
+        assert!(dispatcher.enqueue(&webhooks, failed).is_none());
+    }
+}
@@ -0,0 +1,719 @@
+use crate::config::FluxConfig;
+use crate::functions::{self, FluxError, FunctionMetadata, RegisterFunctionRequest};
+use crate::gateway::{self, routes::build_routes};
+use crate::runtime::compiler::{CompilerConfig, RustCompiler};
+use crate::runtime::events::LifecycleEventStream;
+use crate::runtime::instance::InstanceManager;
+use crate::runtime::janitor::DiskJanitor;
+use crate::runtime::sandbox::SandboxExecutor;
+use crate::scheduler::SimpleScheduler;
+use crate::scheduler::profiles::{DEFAULT_PROFILE, SchedulerRegistry};
+use crate::telemetry;
+use crate::tunables::{ConfigReloader, RuntimeTunables, Tunables};
+use silent::prelude::*;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// 默认监听地址
+pub const DEFAULT_ADDR: &str = "127.0.0.1:3000";
+
+/// 网关服务构建器：组装调度器、运行时环境和路由并启动 HTTP 服务
+///
+/// `main` 用默认地址运行；测试可以 `bind_ephemeral()` 在随机端口上启动真实服务，
+/// 通过 [`RunningServer::shutdown`] 确定地关闭。
+pub struct FluxServer {
+    config: FluxConfig,
+    config_path: Option<PathBuf>,
+    scheduler: Option<Arc<SimpleScheduler>>,
+    sample_functions: bool,
+    addr: SocketAddr,
+}
+
+impl Default for FluxServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FluxServer {
+    pub fn new() -> Self {
+        Self {
+            config: FluxConfig::default(),
+            config_path: None,
+            scheduler: None,
+            sample_functions: true,
+            addr: DEFAULT_ADDR.parse().expect("valid default address"),
+        }
+    }
+
+    /// 使用指定配置（调度器、命名空间、请求体上限等）
+    pub fn with_config(mut self, config: FluxConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 重新加载配置（`POST /admin/config/reload`、SIGHUP）时读取的文件，通常即加载 `config` 的文件
+    pub fn with_config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// 使用指定的调度器作为默认调度器（替换按配置创建的 `default`）
+    pub fn with_scheduler(mut self, scheduler: Arc<SimpleScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 不预注册 `hello`、`echo`、`add` 示例函数
+    pub fn disable_sample_functions(mut self) -> Self {
+        self.sample_functions = false;
+        self
+    }
+
+    /// 监听地址（默认 `127.0.0.1:3000`）
+    pub fn bind(mut self, addr: SocketAddr) -> Self {
+        self.addr = addr;
+        self
+    }
+
+    /// 在 `127.0.0.1` 的随机端口上启动服务，实际地址见 [`RunningServer::addr`]
+    pub async fn bind_ephemeral(self) -> anyhow::Result<RunningServer> {
+        self.bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .start()
+            .await
+    }
+
+    /// 启动服务并一直运行到收到 Ctrl-C 或 SIGTERM
+    pub async fn run(self) -> anyhow::Result<()> {
+        let server = self.start().await?;
+        log_endpoints();
+        server.wait().await;
+        Ok(())
+    }
+
+    /// 绑定地址并在后台任务中启动服务
+    pub async fn start(self) -> anyhow::Result<RunningServer> {
+        let config = self.config;
+        config.validate()?;
+        functions::status::set_wire_format(config.status_format);
+        functions::redaction::set_logging_config(&config.logging);
+        telemetry::ensure_log_capture();
+        gateway::status::mark_started();
+
+        // 按配置初始化各调度器
+        let mut schedulers = SchedulerRegistry::from_config(&config.profiles)?;
+        if let Some(scheduler) = self.scheduler {
+            let profile_config = config
+                .profiles
+                .get(DEFAULT_PROFILE)
+                .cloned()
+                .unwrap_or_default();
+            schedulers = schedulers.with_profile(DEFAULT_PROFILE, profile_config, scheduler);
+        }
+        let schedulers = Arc::new(schedulers);
+        info!("🧭 Scheduler profiles: {}", schedulers.names().join(", "));
+        schedulers.namespaces().configure(&config.namespaces)?;
+        schedulers.environment().configure(&config.runtimes)?;
+        schedulers.state().configure(&config.state)?;
+        schedulers.invocation_limiter().configure(&config.limits);
+        schedulers
+            .debug_artifacts()
+            .configure(&config.debug_capture);
+        schedulers.invocation_logs().configure(&config.log_capture);
+        schedulers.oci_source().configure(&config.oci);
+        schedulers.jobs().configure(&config.jobs);
+        schedulers.console().configure(&config.console);
+        schedulers.sessions().configure(&config.sessions);
+        schedulers.peers().configure(&config.peers)?;
+        for profile in schedulers.profiles() {
+            profile
+                .scheduler
+                .runtime()
+                .monitor()
+                .set_series_config(config.metrics.clone())
+                .await;
+            profile.scheduler.chaos().set_enabled(config.chaos.enabled);
+            profile
+                .scheduler
+                .canaries()
+                .set_enabled(config.canaries.enabled);
+            profile.scheduler.history().configure(&config.history);
+            profile
+                .scheduler
+                .circuits()
+                .configure(&config.circuit_breaker)?;
+            profile.scheduler.slo_alerts().configure(&config.slo)?;
+        }
+
+        // 从持久化后端加载各调度器的函数目录；后端暂时不可达时只读启动并在后台重试
+        let mut background = Vec::new();
+        for profile in schedulers.profiles() {
+            let Some(storage) = config.storage.open(&profile.name)? else {
+                continue;
+            };
+            match profile.scheduler.load_from_storage(storage).await {
+                Ok(count) => info!(
+                    "🗄️ Loaded {} functions for profile {} from storage",
+                    count, profile.name
+                ),
+                Err(FluxError::StorageUnavailable { reason }) => {
+                    warn!(
+                        "Function storage for profile {} is unavailable, serving a read-only catalog: {}",
+                        profile.name, reason
+                    );
+                    background.push(profile.scheduler.spawn_storage_reload(Duration::from_secs(
+                        config.storage.reload_interval_secs.max(1),
+                    )));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        // 预注册示例函数（默认调度器）
+        if self.sample_functions {
+            register_sample_functions(&schedulers.default_profile().scheduler).await?;
+        }
+
+        // 初始化实例管理器（生命周期事件按配置保留）
+        let compiler = Arc::new(RustCompiler::new(CompilerConfig::default())?);
+        // 探测可用的解释器和编译器以及配置的执行环境，并按配置间隔定期重新探测
+        let environment = schedulers.environment().clone();
+        let snapshot = environment.refresh();
+        for detected in snapshot.runtimes {
+            match (&detected.path, &detected.version) {
+                (Some(path), Some(version)) => {
+                    info!(
+                        "🔧 Runtime {}: {} ({})",
+                        detected.runtime.name(),
+                        version,
+                        path.display()
+                    )
+                }
+                _ => info!("🔧 Runtime {}: not available", detected.runtime.name()),
+            }
+        }
+        for definition in snapshot.definitions {
+            match (&definition.version, &definition.error) {
+                (Some(version), _) => info!(
+                    "🔧 Runtime definition {} ({}): {} ({})",
+                    definition.name,
+                    definition.kind.name(),
+                    version,
+                    definition.binary.display()
+                ),
+                (None, error) => warn!(
+                    "Runtime definition {} is not available ({}): {}",
+                    definition.name,
+                    definition.binary.display(),
+                    error.as_deref().unwrap_or("not detected")
+                ),
+            }
+        }
+        background.extend(environment.spawn());
+        let events = Arc::new(LifecycleEventStream::new(config.events.clone()));
+        // 沙箱和网关每次操作时读取可重新加载的配置
+        let tunables = Arc::new(Tunables::new(RuntimeTunables::from(&config)));
+        let sandbox = Arc::new(
+            SandboxExecutor::new(config.sandbox.clone())?
+                .with_environment(environment.clone())
+                .with_lifecycle_events(events.clone())
+                .with_tunables(tunables.clone()),
+        );
+        // 回收空闲超时的脚本函数工作进程（回收前调用 teardown）
+        background.push(sandbox.workers().spawn_sweeper());
+        // 各调度器（及其路由后端）在该沙箱中执行 JavaScript/Python 函数
+        for profile in schedulers.profiles() {
+            profile.scheduler.runtime().attach_sandbox(sandbox.clone());
+        }
+        let instance_manager = Arc::new(InstanceManager::with_event_stream(
+            compiler.clone(),
+            sandbox.clone(),
+            schedulers.resources().clone(),
+            None,
+            events.clone(),
+        ));
+        // 按间隔检查配置了 SLO 的函数的燃烧率，告警发布到生命周期事件流
+        if config.slo.enabled {
+            for profile in schedulers.profiles() {
+                background.push(profile.scheduler.spawn_slo_alerts(
+                    Duration::from_millis(config.slo.tick_ms.max(1)),
+                    events.clone(),
+                ));
+            }
+        }
+
+        // 启动磁盘清理任务
+        let janitor = Arc::new(DiskJanitor::new(
+            config.janitor.clone(),
+            compiler,
+            sandbox.clone(),
+        ));
+        background.extend(janitor.spawn());
+        // 定期清理各调度器函数缓存中过期的条目，并按注册表变更及时使缓存失效
+        for profile in schedulers.profiles() {
+            background.push(profile.scheduler.runtime().cache().spawn_sweeper());
+            background.push(profile.scheduler.spawn_invalidation());
+        }
+        // 定期清理过期的函数状态
+        background.push(schedulers.state().spawn_sweeper());
+        // 检查转发节点的健康状态；启用同步时把注册和更新的函数推送给节点
+        if !schedulers.peers().is_empty() {
+            info!(
+                "🌐 Forwarding to peers: {} (node {})",
+                config.peers.urls.join(", "),
+                schedulers.peers().node_id()
+            );
+            background.push(schedulers.peers().spawn_health_checks());
+            if config.peers.sync {
+                for profile in schedulers.profiles() {
+                    background.push(profile.scheduler.spawn_peer_sync());
+                }
+            }
+        }
+        // 按间隔执行各函数的金丝雀探测
+        if config.canaries.enabled {
+            for profile in schedulers.profiles() {
+                background.push(
+                    profile
+                        .scheduler
+                        .spawn_canaries(Duration::from_millis(config.canaries.tick_ms.max(1))),
+                );
+            }
+        }
+
+        // 创建配置并注入调度器注册表
+        let mut configs = Configs::default();
+        configs.insert(schedulers.clone());
+        configs.insert(instance_manager);
+        configs.insert(janitor);
+        configs.insert(sandbox.scratch_store().clone());
+        configs.insert(environment);
+        configs.insert(Arc::new(config.websocket.clone()));
+        configs.insert(Arc::new(config.dashboard.clone()));
+
+        // 配置文件的重新加载（启动时的文件内容用于比较需要重启的改动）
+        let file = match &self.config_path {
+            Some(path) => std::fs::read_to_string(path)
+                .ok()
+                .and_then(|content| content.parse().ok())
+                .unwrap_or_default(),
+            None => toml::Table::new(),
+        };
+        let reloader = Arc::new(ConfigReloader::new(
+            self.config_path,
+            tunables.clone(),
+            schedulers.clone(),
+            file,
+        ));
+        background.extend(reloader.spawn_signal_handler());
+        configs.insert(tunables);
+        configs.insert(reloader);
+
+        // 先绑定端口，端口为 0 时由系统分配
+        let listener = tokio::net::TcpListener::bind(self.addr).await?;
+        let addr = listener.local_addr()?;
+        info!("🌐 FluxFaaS HTTP Server listening on http://{}", addr);
+        // 脚本函数通过回环地址读写状态和发送出站 HTTP 请求
+        schedulers
+            .state()
+            .set_endpoint(format!("http://127.0.0.1:{}/state", addr.port()));
+        schedulers
+            .egress()
+            .set_endpoint(format!("http://127.0.0.1:{}/egress", addr.port()));
+
+        let shutdown = ShutdownHandle::new();
+        let mut stopped = shutdown.subscribe();
+        let task = tokio::spawn(async move {
+            let server = Server::new()
+                .with_configs(configs)
+                .listen(Listener::from(listener))
+                .serve(build_routes());
+            // 停止时丢弃服务 future，监听端口和进行中的连接随之关闭
+            tokio::select! {
+                _ = server => {}
+                _ = stopped.wait_for(|stopped| *stopped) => {}
+            }
+        });
+
+        Ok(RunningServer {
+            addr,
+            schedulers,
+            shutdown,
+            task: Some(task),
+            background,
+            sandbox,
+        })
+    }
+}
+
+/// 触发服务关闭的句柄，可克隆后交给其他任务
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownHandle {
+    fn new() -> Self {
+        Self {
+            sender: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    fn subscribe(&self) -> watch::Receiver<bool> {
+        self.sender.subscribe()
+    }
+
+    /// 通知服务停止（重复调用无副作用）
+    pub fn shutdown(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        *self.sender.borrow()
+    }
+}
+
+/// 运行中的服务，丢弃时同样会停止服务
+pub struct RunningServer {
+    addr: SocketAddr,
+    schedulers: Arc<SchedulerRegistry>,
+    shutdown: ShutdownHandle,
+    task: Option<JoinHandle<()>>,
+    background: Vec<JoinHandle<()>>,
+    sandbox: Arc<SandboxExecutor>,
+}
+
+impl RunningServer {
+    /// 实际绑定的地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 服务上某个路径的完整 URL，例如 `server.url("/v1/functions")`
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// 服务使用的调度器注册表
+    pub fn schedulers(&self) -> &Arc<SchedulerRegistry> {
+        &self.schedulers
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// 等待服务停止（收到信号或通过 [`ShutdownHandle`] 关闭），随后回收脚本函数的工作进程
+    pub async fn wait(mut self) {
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+        self.sandbox.workers().shutdown().await;
+    }
+
+    /// 停止服务并等待监听端口关闭
+    pub async fn shutdown(self) {
+        self.shutdown.shutdown();
+        self.wait().await;
+    }
+}
+
+impl Drop for RunningServer {
+    fn drop(&mut self) {
+        self.shutdown.shutdown();
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        for task in &self.background {
+            task.abort();
+        }
+    }
+}
+
+/// 输出可用的接口列表
+fn log_endpoints() {
+    info!(
+        "📋 Available endpoints (all also under /v1 with the versioned envelope; unprefixed paths are deprecated):"
+    );
+    info!("  GET  /health                    - Health check");
+    info!("  GET  /openapi.json              - OpenAPI 3.0 specification");
+    info!("  GET  /functions?label=k=v       - List functions (optional label selector)");
+    info!("  POST /functions                 - Register new function");
+    info!("  GET  /functions/:name           - Get function details");
+    info!("  GET  /namespaces                - List namespaces");
+    info!("  POST /namespaces                - Create a namespace with default limits");
+    info!("  GET  /namespaces/:ns            - Namespace limits and usage");
+    info!("  DELETE /namespaces/:ns          - Delete an empty namespace (?force=true cascades)");
+    info!("  GET  /quotas                    - List resource quotas");
+    info!("  POST /quotas                    - Define a resource quota");
+    info!("  GET  /quotas/:name              - Quota definition and functions using it");
+    info!(
+        "  PUT  /quotas/:name              - Replace a quota definition (applies to the next executions)"
+    );
+    info!("  DELETE /quotas/:name            - Delete an unused quota");
+    info!("  GET  /quotas/:name/usage        - Resource usage of recent executions under a quota");
+    info!(
+        "  *    /namespaces/:ns/functions/..., /namespaces/:ns/invoke/:name - Namespaced function routes"
+    );
+    info!("  GET  /functions/:name/compilation - Get background compilation status");
+    info!(
+        "  GET  /functions/:name/report    - Latency percentiles and time series (?window=&resolution=)"
+    );
+    info!("  PATCH /functions/:name          - Update function metadata and labels");
+    info!("  PUT  /functions/:name/webhooks  - Configure completion webhooks");
+    info!("  GET  /functions/:name/webhooks/deliveries - Recent webhook deliveries");
+    info!("  PUT  /functions/:name/mirror    - Mirror invocations to a shadow function");
+    info!("  GET  /functions/:name/state     - Inspect a function's key-value state");
+    info!("  DELETE /functions/:name/state   - Clear a function's key-value state");
+    info!(
+        "  POST /state                     - State access for executing functions (x-flux-state-token)"
+    );
+    info!("  GET  /functions/:name/mirror/mismatches - Mirror output mismatches");
+    info!("  GET  /functions/:name/slo       - SLO compliance, error budget and burn rates");
+    info!("  PUT  /functions/:name/slo       - Set a function's availability/latency SLO");
+    info!("  GET  /functions/:name/circuit   - Circuit breaker state and transitions");
+    info!("  POST /functions/:name/circuit/reset - Close a function's circuit breaker");
+    info!("  GET  /functions/:name/docs      - Function documentation (markdown or ?format=html)");
+    info!("  PUT  /functions/:name/docs      - Replace function documentation");
+    info!("  POST /functions/:name/test      - Run the function's own test cases");
+    info!("  DELETE /functions/:name         - Delete function");
+    info!("  POST /functions/bulk/delete     - Delete functions matching a label selector");
+    info!("  POST /functions/bulk/invoke     - Invoke functions matching a label selector");
+    info!("  GET  /functions/:name/export    - Export function bundle");
+    info!("  GET  /functions/export          - Export all functions");
+    info!("  POST /functions/import          - Import function bundle or archive");
+    info!("  POST /deployments               - Prepare a set of functions for atomic activation");
+    info!("  GET  /deployments/:id           - Deployment state and per-function diagnostics");
+    info!(
+        "  POST /deployments/:id/activate  - Atomically swap a ready deployment into the registry"
+    );
+    info!("  POST /deployments/:id/rollback  - Restore the versions replaced by a deployment");
+    info!(
+        "  POST /invoke/:name              - Invoke function (JSON, text or binary body, ?version= to pin)"
+    );
+    info!(
+        "  POST /invoke/:name/fanout       - Invoke a function and fan its output out to other functions"
+    );
+    info!(
+        "  POST /invoke/:name/async        - Invoke a function asynchronously (optional callback_url)"
+    );
+    info!("  GET  /jobs/:id                  - Async job status (?wait_ms= to long-poll)");
+    info!("  POST /console/execute           - Run unregistered code in the strictest sandbox");
+    info!(
+        "  POST /console/promote           - Register code from a console execution as a function"
+    );
+    info!("  GET  /console/stats             - Console usage counters");
+    info!("  GET  /sessions/:id              - Session invocation totals");
+    info!("  GET  /sessions/:id/executions   - Session invocation list (paginated)");
+    info!("  DELETE /sessions/:id            - Clear a session");
+    info!("  GET  /ws/invoke                 - Invoke functions over a WebSocket");
+    info!("  GET  /status                    - System status across all subsystems");
+    info!("  GET  /peers                     - Peer health, circuits, forwarding and sync status");
+    info!("  GET  /dashboard/summary         - Per-function dashboard snapshot");
+    info!("  GET  /dashboard/stream          - Dashboard row updates (SSE)");
+    info!("  POST /load/file                 - Load function from file");
+    info!("  POST /load/directory            - Load functions from directory");
+    info!("  POST /load/git                  - Load functions from git repository");
+    info!("  POST /load/oci                  - Load functions from an OCI artifact");
+    info!("  GET  /cache/stats               - Cache statistics");
+    info!("  POST /cache/warm                - Warm the function cache");
+    info!("  GET  /cache/config              - Cache eviction strategy and TTL");
+    info!("  PUT  /cache/config              - Change cache eviction strategy and TTL");
+    info!("  POST /cache/pin/:name           - Pin a function in the cache");
+    info!("  DELETE /cache/pin/:name         - Unpin a function");
+    info!("  DELETE /cache/:name             - Invalidate a function's cache entries");
+    info!("  POST /admin/gc                  - Run a disk cleanup pass");
+    info!(
+        "  GET  /runtimes                  - List runtimes and configured execution environments"
+    );
+    info!("  POST /admin/runtimes/refresh    - Re-detect installed interpreters and compilers");
+    info!(
+        "  POST /admin/config/reload       - Re-read the config file and apply reloadable limits (also SIGHUP)"
+    );
+    info!("  POST /admin/chaos/rules         - Create a fault injection rule (chaos.enabled)");
+    info!("  GET  /admin/chaos/rules         - List fault injection rules");
+    info!("  DELETE /admin/chaos/rules/:id   - Delete a fault injection rule");
+    info!(
+        "  POST /executions/:id/replay     - Replay a past invocation (?original_version&shadow)"
+    );
+    info!("  GET  /executions/:id/bundle     - Download the debug bundle of an invocation");
+    info!("  GET  /executions/:id/bundle/manifest - Inspect a debug bundle without downloading");
+    info!(
+        "  GET  /executions/:id/artifacts  - List or download the persisted scratch files of an invocation"
+    );
+    info!(
+        "  GET  /executions/:id/log        - Structured log lines captured with ?capture_log=<level>"
+    );
+    info!("  GET  /performance/stats         - Performance statistics");
+    info!(
+        "  GET  /performance/top           - Rank functions (?metric=p99|cpu_time&limit=10&window=1h)"
+    );
+    info!("  GET  /instances                 - List function instances");
+    info!("  GET  /instances/stats           - Instance statistics");
+    info!("  GET  /instances/:id/events      - Instance lifecycle events");
+    info!("  GET  /instances/events/stream   - Live lifecycle events (SSE)");
+    info!("  POST /reset                     - Reset scheduler");
+    info!("");
+    info!(
+        "💡 Use 'flux-cli fn list' or 'flux-cli invoke <name> -d <json>' to interact with the server (FLUX_SERVER sets its address)"
+    );
+    info!("🚀 Server is ready to accept requests!");
+}
+
+/// 预注册示例函数
+async fn register_sample_functions(scheduler: &SimpleScheduler) -> anyhow::Result<()> {
+    let registry = scheduler.registry();
+
+    let sample_functions = vec![
+        RegisterFunctionRequest {
+            name: "hello".to_string(),
+            description: Some("Hello World 函数".to_string()),
+            code: "return \"Hello, World!\"".to_string(),
+            timeout_ms: Some(5000),
+            version: None,
+            dependencies: None,
+            parameters: None,
+            return_type: None,
+            retry_policy: None,
+            idempotent: None,
+            labels: None,
+            profile: None,
+            webhooks: None,
+            priority: None,
+            files: None,
+            entrypoint: None,
+            http_response: None,
+            input_template: None,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: None,
+            coerce_output: None,
+            strict_return_type: None,
+            log_redaction: None,
+            circuit_breaker: None,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: None,
+            scratch_limit_mb: None,
+            persist_scratch: None,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: None,
+            execution_hint: None,
+            namespace: None,
+            documentation: None,
+            egress: None,
+            debug_capture: None,
+            canary: None,
+            slo: None,
+        },
+        RegisterFunctionRequest {
+            name: "echo".to_string(),
+            description: Some("回声函数".to_string()),
+            code: "return input".to_string(),
+            timeout_ms: Some(3000),
+            version: None,
+            dependencies: None,
+            parameters: None,
+            return_type: None,
+            retry_policy: None,
+            idempotent: None,
+            labels: None,
+            profile: None,
+            webhooks: None,
+            priority: None,
+            files: None,
+            entrypoint: None,
+            http_response: None,
+            input_template: None,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: None,
+            coerce_output: None,
+            strict_return_type: None,
+            log_redaction: None,
+            circuit_breaker: None,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: None,
+            scratch_limit_mb: None,
+            persist_scratch: None,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: None,
+            execution_hint: None,
+            namespace: None,
+            documentation: None,
+            egress: None,
+            debug_capture: None,
+            canary: None,
+            slo: None,
+        },
+        RegisterFunctionRequest {
+            name: "add".to_string(),
+            description: Some("加法函数".to_string()),
+            code: "const {a, b} = JSON.parse(input); return (a + b).toString();".to_string(),
+            timeout_ms: Some(2000),
+            version: None,
+            dependencies: None,
+            parameters: None,
+            return_type: None,
+            retry_policy: None,
+            idempotent: None,
+            labels: None,
+            profile: None,
+            webhooks: None,
+            priority: None,
+            files: None,
+            entrypoint: None,
+            http_response: None,
+            input_template: None,
+            input_schema: None,
+            output_schema: None,
+            strict_output_schema: None,
+            coerce_output: None,
+            strict_return_type: None,
+            log_redaction: None,
+            circuit_breaker: None,
+            runtime: None,
+            resource_quota: None,
+            requires_isolation: None,
+            scratch_limit_mb: None,
+            persist_scratch: None,
+            max_disk_write_mb: None,
+            max_cpu_time_ms: None,
+            tests: None,
+            execution_hint: None,
+            namespace: None,
+            documentation: None,
+            egress: None,
+            debug_capture: None,
+            canary: None,
+            slo: None,
+        },
+    ];
+
+    for func_req in sample_functions {
+        let metadata = FunctionMetadata::from_request(func_req);
+        // 持久化的函数目录中已有的同名函数保持不变
+        match registry.register(metadata).await {
+            Ok(_) | Err(FluxError::FunctionAlreadyExists { .. }) => {}
+            Err(FluxError::StorageUnavailable { reason }) => {
+                warn!(
+                    "Skipping sample functions, storage is unavailable: {}",
+                    reason
+                );
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    info!("📚 Sample functions registered successfully");
+    Ok(())
+}
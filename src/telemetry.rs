@@ -0,0 +1,86 @@
+use crate::config::TracingConfig;
+use crate::runtime::invocation_log;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+/// 链路追踪守卫，drop 时刷新并关闭导出器
+#[derive(Debug, Default)]
+pub struct TelemetryGuard {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to shut down OTLP exporter: {e}");
+        }
+    }
+}
+
+/// 初始化 tracing：默认使用 fmt 输出，配置了 OTLP 地址且启用 `otlp` 特性时额外导出 span
+///
+/// fmt 输出和 OTLP 导出只记录 INFO 及以上；按调用捕获日志（`?capture_log=debug`）的层
+/// 单独过滤，捕获期间也能记录该调用的 DEBUG/TRACE 事件。
+pub fn init(config: &TracingConfig) -> anyhow::Result<TelemetryGuard> {
+    let fmt_layer = tracing_subscriber::fmt::layer().with_filter(LevelFilter::INFO);
+
+    #[cfg(feature = "otlp")]
+    if let Some(endpoint) = &config.otlp_endpoint {
+        use opentelemetry::trace::TracerProvider as _;
+        use opentelemetry_otlp::WithExportConfig;
+
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()?;
+        let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                opentelemetry_sdk::Resource::builder()
+                    .with_service_name(config.service_name.clone())
+                    .build(),
+            )
+            .build();
+        let tracer = provider.tracer(config.service_name.clone());
+
+        tracing_subscriber::registry()
+            .with(fmt_layer)
+            .with(
+                tracing_opentelemetry::layer()
+                    .with_tracer(tracer)
+                    .with_filter(LevelFilter::INFO),
+            )
+            .with(invocation_log::layer())
+            .try_init()?;
+        tracing::info!("Exporting traces via OTLP to {}", endpoint);
+
+        return Ok(TelemetryGuard {
+            provider: Some(provider),
+        });
+    }
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(invocation_log::layer())
+        .try_init()?;
+
+    #[cfg(not(feature = "otlp"))]
+    if config.otlp_endpoint.is_some() {
+        tracing::warn!("OTLP endpoint configured but flux was built without the `otlp` feature");
+    }
+
+    Ok(TelemetryGuard::default())
+}
+
+/// 还没有安装全局 subscriber 时（例如嵌入服务的测试）只安装调用日志捕获层
+pub fn ensure_log_capture() {
+    if !tracing::dispatcher::has_been_set() {
+        let subscriber = tracing_subscriber::registry().with(invocation_log::layer());
+        // 并发启动时可能已被其他调用安装
+        let _ = tracing::subscriber::set_global_default(subscriber);
+    }
+}
@@ -0,0 +1,395 @@
+//! 可热加载的运行参数
+//!
+//! 配置中的一部分（沙箱的超时、内存和 CPU 上限，全局调用并发，调用请求体和解压上限，任务、会话和执行历史的
+//! 保留数，熔断参数和日志载荷上限）无需重启即可生效：`POST /admin/config/reload` 或 Unix 上的 SIGHUP
+//! 重新读取配置文件，完整校验后一次性替换 [`RuntimeTunables`] 快照。沙箱和网关在每次操作时读取当前快照，
+//! 同一次操作只会看到整组旧值或整组新值。文件中其余配置的改动不会被应用，在重新加载的结果中列为需要重启。
+
+use crate::config::FluxConfig;
+use crate::functions::redaction::{self, LoggingConfig};
+use crate::gateway::payload::{CompressionConfig, InvokeBodyConfig};
+use crate::runtime::sandbox::{SandboxConfig, SandboxLimits};
+use crate::scheduler::circuit::CircuitBreakerConfig;
+use crate::scheduler::history::HistoryConfig;
+use crate::scheduler::jobs::JobsConfig;
+use crate::scheduler::limiter::InvocationLimitConfig;
+use crate::scheduler::profiles::SchedulerRegistry;
+use crate::scheduler::sessions::SessionsConfig;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock as StdRwLock};
+use tokio::sync::Mutex;
+
+/// 可重新加载的配置项（整个表，或 `表.字段`）
+pub const RELOADABLE_FIELDS: &[&str] = &[
+    "sandbox.execution_timeout_secs",
+    "sandbox.max_memory_mb",
+    "sandbox.max_cpu_percent",
+    "limits",
+    "invoke",
+    "compression",
+    "jobs",
+    "sessions",
+    "history",
+    "circuit_breaker",
+    "logging",
+];
+
+/// 配置项是否可以重新加载
+pub fn is_reloadable(field: &str) -> bool {
+    RELOADABLE_FIELDS.iter().any(|reloadable| {
+        field == *reloadable
+            || field
+                .strip_prefix(reloadable)
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// 沙箱中可重新加载的资源上限
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SandboxTunables {
+    pub execution_timeout_secs: u64,
+    pub max_memory_mb: u64,
+    pub max_cpu_percent: f64,
+}
+
+impl Default for SandboxTunables {
+    fn default() -> Self {
+        Self::from(&SandboxConfig::default())
+    }
+}
+
+impl From<&SandboxConfig> for SandboxTunables {
+    fn from(config: &SandboxConfig) -> Self {
+        Self {
+            execution_timeout_secs: config.execution_timeout_secs,
+            max_memory_mb: config.max_memory_mb,
+            max_cpu_percent: config.max_cpu_percent,
+        }
+    }
+}
+
+impl SandboxTunables {
+    /// 用当前上限替换沙箱配置给出的默认限制
+    pub fn apply(&self, limits: SandboxLimits) -> SandboxLimits {
+        SandboxLimits {
+            execution_timeout_secs: self.execution_timeout_secs,
+            max_memory_mb: self.max_memory_mb,
+            max_cpu_percent: self.max_cpu_percent,
+            ..limits
+        }
+    }
+}
+
+/// 可重新加载的配置快照
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RuntimeTunables {
+    pub sandbox: SandboxTunables,
+    pub limits: InvocationLimitConfig,
+    pub invoke: InvokeBodyConfig,
+    pub compression: CompressionConfig,
+    pub jobs: JobsConfig,
+    pub sessions: SessionsConfig,
+    pub history: HistoryConfig,
+    pub circuit_breaker: CircuitBreakerConfig,
+    pub logging: LoggingConfig,
+}
+
+impl From<&FluxConfig> for RuntimeTunables {
+    fn from(config: &FluxConfig) -> Self {
+        Self {
+            sandbox: SandboxTunables::from(&config.sandbox),
+            limits: config.limits.clone(),
+            invoke: config.invoke.clone(),
+            compression: config.compression.clone(),
+            jobs: config.jobs.clone(),
+            sessions: config.sessions.clone(),
+            history: config.history.clone(),
+            circuit_breaker: config.circuit_breaker.clone(),
+            logging: config.logging.clone(),
+        }
+    }
+}
+
+/// 当前的 [`RuntimeTunables`]，重新加载时整体替换
+#[derive(Debug, Default)]
+pub struct Tunables {
+    current: StdRwLock<Arc<RuntimeTunables>>,
+}
+
+impl Tunables {
+    pub fn new(tunables: RuntimeTunables) -> Self {
+        Self {
+            current: StdRwLock::new(Arc::new(tunables)),
+        }
+    }
+
+    /// 当前快照；一次操作应只读取一次，之后的重新加载不会改变已取得的快照
+    pub fn current(&self) -> Arc<RuntimeTunables> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 替换快照，返回之前的快照
+    pub fn replace(&self, tunables: RuntimeTunables) -> Arc<RuntimeTunables> {
+        std::mem::replace(
+            &mut *self.current.write().unwrap_or_else(|e| e.into_inner()),
+            Arc::new(tunables),
+        )
+    }
+}
+
+/// 一个配置项的改动（`old`/`new` 为 `null` 表示该项被删除或新增）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    /// 以点分隔的配置项路径，例如 `sandbox.max_memory_mb`
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// 一次重新加载的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadReport {
+    /// 读取的配置文件
+    pub path: PathBuf,
+    /// 已生效的改动
+    pub applied: Vec<ConfigChange>,
+    /// 文件中改动了但需要重启才能生效的配置项
+    pub requires_restart: Vec<String>,
+    pub reloaded_at: DateTime<Utc>,
+}
+
+/// 重新加载失败的原因（失败时不应用任何改动）
+#[derive(Debug, thiserror::Error)]
+pub enum ReloadError {
+    #[error("Server was started without a configuration file")]
+    NoConfigFile,
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+/// 重新读取配置文件并应用可热加载的部分
+#[derive(Debug)]
+pub struct ConfigReloader {
+    path: Option<PathBuf>,
+    tunables: Arc<Tunables>,
+    schedulers: Arc<SchedulerRegistry>,
+    /// 上次加载的配置文件内容，同时串行化并发的重新加载
+    file: Mutex<Value>,
+}
+
+impl ConfigReloader {
+    /// `file` 为启动时配置文件的内容，与之后读取的文件比较需要重启的改动
+    pub fn new(
+        path: Option<PathBuf>,
+        tunables: Arc<Tunables>,
+        schedulers: Arc<SchedulerRegistry>,
+        file: toml::Table,
+    ) -> Self {
+        Self {
+            path,
+            tunables,
+            schedulers,
+            file: Mutex::new(serde_json::to_value(file).unwrap_or_default()),
+        }
+    }
+
+    pub fn tunables(&self) -> &Arc<Tunables> {
+        &self.tunables
+    }
+
+    /// 读取并完整校验配置文件，通过后替换快照并更新各组件的配置
+    pub async fn reload(&self) -> Result<ReloadReport, ReloadError> {
+        let path = self.path.clone().ok_or(ReloadError::NoConfigFile)?;
+        let mut previous_file = self.file.lock().await;
+
+        let invalid = |e: &dyn std::fmt::Display| ReloadError::Invalid(e.to_string());
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| ReloadError::Invalid(format!("{}: {e}", path.display())))?;
+        let file: toml::Table = content.parse().map_err(|e| invalid(&e))?;
+        let config: FluxConfig = toml::from_str(&content).map_err(|e| invalid(&e))?;
+        config.validate().map_err(|e| invalid(&e))?;
+
+        let file = serde_json::to_value(file).unwrap_or_default();
+        let requires_restart = diff(&previous_file, &file)
+            .into_iter()
+            .map(|change| change.field)
+            .filter(|field| !is_reloadable(field))
+            .collect();
+        let tunables = RuntimeTunables::from(&config);
+        let previous = self.tunables.replace(tunables.clone());
+        let applied = diff(
+            &serde_json::to_value(previous.as_ref()).unwrap_or_default(),
+            &serde_json::to_value(&tunables).unwrap_or_default(),
+        );
+        self.apply(&tunables);
+        *previous_file = file;
+
+        let report = ReloadReport {
+            path,
+            applied,
+            requires_restart,
+            reloaded_at: Utc::now(),
+        };
+        tracing::info!(
+            target: "flux::audit",
+            path = %report.path.display(),
+            changes = %serde_json::to_string(&report.applied).unwrap_or_default(),
+            requires_restart = ?report.requires_restart,
+            "Configuration reloaded"
+        );
+        Ok(report)
+    }
+
+    /// 把新配置交给自行保存配置的组件（沙箱和网关每次操作时读取快照）
+    fn apply(&self, tunables: &RuntimeTunables) {
+        // 并发上限变化时在原有名额上增减，正在执行的调用仍计入上限
+        self.schedulers
+            .invocation_limiter()
+            .configure(&tunables.limits);
+        self.schedulers.jobs().configure(&tunables.jobs);
+        self.schedulers.sessions().configure(&tunables.sessions);
+        for profile in self.schedulers.profiles() {
+            profile.scheduler.history().configure(&tunables.history);
+            // 已在校验时检查过
+            if let Err(e) = profile
+                .scheduler
+                .circuits()
+                .configure(&tunables.circuit_breaker)
+            {
+                tracing::warn!("Failed to apply circuit breaker config: {}", e);
+            }
+        }
+        redaction::set_logging_config(&tunables.logging);
+    }
+
+    /// 收到 SIGHUP 时重新加载（仅 Unix，且服务由配置文件启动）
+    pub fn spawn_signal_handler(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        self.path.as_ref()?;
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    tracing::warn!("Failed to install SIGHUP handler: {}", e);
+                    return None;
+                }
+            };
+            let reloader = Arc::downgrade(self);
+            Some(tokio::spawn(async move {
+                while hangup.recv().await.is_some() {
+                    let Some(reloader) = reloader.upgrade() else {
+                        break;
+                    };
+                    if let Err(e) = reloader.reload().await {
+                        tracing::warn!("Configuration reload on SIGHUP failed: {}", e);
+                    }
+                }
+            }))
+        }
+        #[cfg(not(unix))]
+        None
+    }
+}
+
+/// 两个 JSON 值按叶子（对象之外的值）比较的改动，路径按字典序
+fn diff(old: &Value, new: &Value) -> Vec<ConfigChange> {
+    let (mut old_leaves, mut new_leaves) = (BTreeMap::new(), BTreeMap::new());
+    flatten("", old, &mut old_leaves);
+    flatten("", new, &mut new_leaves);
+    let mut fields: Vec<_> = old_leaves.keys().chain(new_leaves.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let old = old_leaves.get(field).cloned().unwrap_or(Value::Null);
+            let new = new_leaves.get(field).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| ConfigChange {
+                field: field.clone(),
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+fn flatten(prefix: &str, value: &Value, leaves: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&path, value, leaves);
+            }
+        }
+        leaf => {
+            leaves.insert(prefix.to_string(), leaf.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reloadable_fields_and_diff() {
+        assert!(is_reloadable("sandbox.max_memory_mb"));
+        assert!(is_reloadable("limits.queue_capacity"));
+        assert!(!is_reloadable("sandbox.temp_root"));
+        assert!(!is_reloadable("limitsx"));
+        assert!(!is_reloadable("storage.url"));
+
+        let changes = diff(
+            &json!({"sandbox": {"max_memory_mb": 128, "temp_root": "/tmp"}, "status_format": "legacy"}),
+            &json!({"sandbox": {"max_memory_mb": 32, "temp_root": "/tmp"}, "storage": {"url": "memory:///"}}),
+        );
+        let fields: Vec<_> = changes.iter().map(|change| change.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["sandbox.max_memory_mb", "status_format", "storage.url"]
+        );
+        assert_eq!(changes[0].old, 128);
+        assert_eq!(changes[1].new, Value::Null);
+    }
+
+    #[test]
+    fn test_readers_never_see_a_torn_snapshot() {
+        let tunables = Arc::new(Tunables::default());
+        let writer = {
+            let tunables = tunables.clone();
+            std::thread::spawn(move || {
+                for n in 1..=2000u64 {
+                    let mut next = RuntimeTunables::default();
+                    next.sandbox.max_memory_mb = n;
+                    next.sandbox.execution_timeout_secs = n;
+                    next.limits.queue_capacity = n as usize;
+                    tunables.replace(next);
+                }
+            })
+        };
+        for _ in 0..2000 {
+            let snapshot = tunables.current();
+            let n = snapshot.sandbox.max_memory_mb;
+            if n != SandboxTunables::default().max_memory_mb {
+                assert_eq!(snapshot.sandbox.execution_timeout_secs, n);
+                assert_eq!(snapshot.limits.queue_capacity, n as usize);
+            }
+        }
+        writer.join().unwrap();
+        assert_eq!(tunables.current().sandbox.max_memory_mb, 2000);
+    }
+}
@@ -0,0 +1,261 @@
+//! `flux-cli` 端到端测试：在随机端口上启动服务，以子进程运行命令行客户端
+
+use flux::scheduler::SimpleScheduler;
+use flux::scheduler::routing::RoutingConfig;
+use flux::server::{FluxServer, RunningServer};
+use serde_json::{Value, json};
+use std::path::Path;
+use std::process::Output;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+async fn start() -> RunningServer {
+    FluxServer::new()
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .expect("server starts")
+}
+
+fn cli(server: &str) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_flux-cli"));
+    command
+        .env("FLUX_SERVER", server)
+        .env_remove("FLUX_API_KEY")
+        .kill_on_drop(true);
+    command
+}
+
+async fn run(server: &str, args: &[&str]) -> (i32, String, String) {
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = cli(server).args(args).output().await.unwrap();
+    (
+        status.code().unwrap_or(-1),
+        String::from_utf8_lossy(&stdout).to_string(),
+        String::from_utf8_lossy(&stderr).to_string(),
+    )
+}
+
+async fn run_json(server: &str, args: &[&str]) -> (i32, Value) {
+    let mut args = args.to_vec();
+    args.push("--json");
+    let (code, stdout, stderr) = run(server, &args).await;
+    let value = serde_json::from_str(&stdout).unwrap_or_else(|e| panic!("{e}: {stdout}{stderr}"));
+    (code, value)
+}
+
+fn write(dir: &Path, name: &str, contents: &str) -> String {
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+#[tokio::test]
+async fn test_function_commands_against_running_server() {
+    let running = start().await;
+    let server = &running.url("");
+    let dir = tempfile::tempdir().unwrap();
+    let file = write(dir.path(), "greet.js", "return input");
+
+    let (code, stdout, stderr) = run(
+        server,
+        &["fn", "register", "-f", &file, "--timeout", "3000"],
+    )
+    .await;
+    assert_eq!(code, 0, "{stderr}");
+    assert!(stdout.contains("Registered function 'greet'"), "{stdout}");
+    // 同名函数已存在：服务端拒绝
+    let (code, _, stderr) = run(server, &["fn", "register", "-f", &file]).await;
+    assert_eq!(code, 6, "{stderr}");
+    assert!(stderr.contains("conflict"), "{stderr}");
+
+    let (code, stdout, _) = run(server, &["fn", "list"]).await;
+    assert_eq!(code, 0);
+    assert!(
+        stdout.lines().next().unwrap().starts_with("NAME"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("greet") && stdout.contains("3000ms"),
+        "{stdout}"
+    );
+    let (code, functions) = run_json(server, &["fn", "list"]).await;
+    assert_eq!(code, 0);
+    assert_eq!(functions[0]["name"], "greet");
+
+    let (code, details) = run_json(server, &["fn", "get", "greet"]).await;
+    assert_eq!(code, 0);
+    assert_eq!(details["code"], "return input");
+    assert_eq!(details["timeout_ms"], 3000);
+
+    let (code, stdout, _) = run(server, &["invoke", "greet", "-d", r#"{"a": 1}"#]).await;
+    assert_eq!(code, 0, "{stdout}");
+    assert!(stdout.starts_with("success ("), "{stdout}");
+    assert!(stdout.contains("\"a\": 1"), "{stdout}");
+    let (code, response) = run_json(server, &["invoke", "greet", "-d", "[1, 2]"]).await;
+    assert_eq!(code, 0);
+    assert_eq!(response["output"]["input"], json!([1, 2]));
+    let (code, _, stderr) = run(server, &["invoke", "greet", "-d", "{oops"]).await;
+    assert_eq!(code, 2, "{stderr}");
+
+    let (code, stdout, _) = run(server, &["stats", "greet"]).await;
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("INVOCATIONS"), "{stdout}");
+    let (_, report) = run_json(server, &["stats", "greet"]).await;
+    assert_eq!(report["summary"]["invocations"], 2, "{report}");
+
+    let (code, stdout, _) = run(server, &["fn", "delete", "greet"]).await;
+    assert_eq!(code, 0);
+    assert!(stdout.contains("Deleted function 'greet'"));
+    let (code, _, stderr) = run(server, &["fn", "get", "greet"]).await;
+    assert_eq!(code, 6);
+    assert!(stderr.contains("not_found"), "{stderr}");
+
+    running.shutdown().await;
+
+    // 服务已停止：无法连接
+    let (code, _, stderr) = run(server, &["fn", "list"]).await;
+    assert_eq!(code, 7, "{stderr}");
+}
+
+#[tokio::test]
+async fn test_invoke_exit_code_follows_execution_status() {
+    let running = start().await;
+    let server = &running.url("");
+    let dir = tempfile::tempdir().unwrap();
+    // 内置的 add 示例函数缺少参数时执行失败
+    let file = write(dir.path(), "add.js", "return a + b");
+    let (code, _, stderr) = run(server, &["fn", "register", "-f", &file]).await;
+    assert_eq!(code, 0, "{stderr}");
+
+    let (code, response) = run_json(server, &["invoke", "add", "-d", r#"{"a": 1, "b": 2}"#]).await;
+    assert_eq!(code, 0);
+    assert_eq!(response["output"]["result"], 3.0);
+    let (code, stdout, _) = run(server, &["invoke", "add", "-d", "{}"]).await;
+    assert_eq!(code, 1, "{stdout}");
+    assert!(stdout.starts_with("failed: "), "{stdout}");
+    running.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_load_dir_and_watch_mode() {
+    // Rust 函数需要编译后端，路由到编译后端的调度器才接受
+    let scheduler = SimpleScheduler::new()
+        .with_routing(&RoutingConfig::default())
+        .unwrap();
+    let running = FluxServer::new()
+        .with_scheduler(Arc::new(scheduler))
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .expect("server starts");
+    let server = &running.url("");
+    let dir = tempfile::tempdir().unwrap();
+    let functions = dir.path().join("functions");
+    std::fs::create_dir(&functions).unwrap();
+    write(
+        &functions,
+        "echo.rs",
+        "pub fn handler(input: serde_json::Value) -> serde_json::Value {\n    input\n}\n",
+    );
+
+    let (code, stdout, stderr) = run(server, &["load", "dir", &functions.to_string_lossy()]).await;
+    assert_eq!(code, 0, "{stderr}");
+    assert!(
+        stdout.contains("echo.rs") && stdout.contains("1 registered"),
+        "{stdout}"
+    );
+
+    // 输入文件每次变化都重新调用（表达式函数，不需要编译）
+    let file = write(dir.path(), "mirror.js", "return input");
+    let (code, _, stderr) = run(server, &["fn", "register", "-f", &file]).await;
+    assert_eq!(code, 0, "{stderr}");
+    let input = write(dir.path(), "input.json", r#"{"n": 1}"#);
+    let mut child = cli(server)
+        .args(["invoke", "mirror", "--watch", &input])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut wait_for = async |needle: &str| {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if line.contains(needle) {
+                    return;
+                }
+            }
+            panic!("watch mode exited before printing {needle}");
+        })
+        .await
+        .unwrap_or_else(|_| panic!("timed out waiting for {needle}"));
+    };
+    wait_for("\"n\": 1").await;
+    // 等监控就绪后再修改文件
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    write(dir.path(), "input.json", r#"{"n": 2}"#);
+    wait_for("\"n\": 2").await;
+    child.kill().await.unwrap();
+    running.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_debug_fetch() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = flux::config::FluxConfig::default();
+    config.debug_capture.dir = dir.path().join("bundles");
+    let running = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .expect("server starts");
+    let server = &running.url("");
+    let client = reqwest::Client::new();
+    let registration =
+        json!({"name": "flaky", "code": "return a + b", "debug_capture": "on_failure"});
+    let response = client
+        .post(running.url("/v1/functions"))
+        .json(&registration)
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    client
+        .post(running.url("/v1/invoke/flaky"))
+        .header("x-request-id", "flaky:1")
+        .json(&json!({"input": {"a": 1}}))
+        .send()
+        .await
+        .unwrap();
+
+    let (code, manifest) = run_json(server, &["debug", "fetch", "flaky:1", "--manifest"]).await;
+    assert_eq!(code, 0, "{manifest}");
+    assert_eq!(manifest["function"], "flaky");
+    assert_eq!(manifest["success"], false);
+    let (code, stdout, stderr) = run(server, &["debug", "fetch", "flaky:1", "--manifest"]).await;
+    assert_eq!(code, 0, "{stderr}");
+    assert!(stdout.contains("source/expression.txt"), "{stdout}");
+
+    let output = dir.path().join("flaky.tar.gz");
+    let (code, saved) = run_json(
+        server,
+        &["debug", "fetch", "flaky:1", "-o", &output.to_string_lossy()],
+    )
+    .await;
+    assert_eq!(code, 0, "{saved}");
+    let bundle = std::fs::read(&output).unwrap();
+    assert_eq!(saved["bytes"], bundle.len());
+    // gzip 魔数
+    assert_eq!(bundle[..2], [0x1f, 0x8b]);
+
+    let (code, _, stderr) = run(server, &["debug", "fetch", "missing"]).await;
+    assert_eq!(code, 6, "{stderr}");
+    assert!(stderr.contains("not_found"), "{stderr}");
+    running.shutdown().await;
+}
@@ -0,0 +1,3371 @@
+//! 端到端测试：在随机端口上启动完整的网关服务，通过 HTTP 调用各接口
+
+use flux::config::FluxConfig;
+use flux::functions::context::InvocationContext;
+use flux::functions::{FunctionMetadata, FunctionParameter, InvokeRequest};
+use flux::runtime::SimpleRuntime;
+use flux::runtime::compiler::{CompilerConfig, RustCompiler};
+use flux::runtime::disk::DiskAccounting;
+use flux::runtime::egress::{EgressPolicy, EgressRule};
+use flux::runtime::environment::{RuntimeDefinition, RuntimeKind};
+use flux::runtime::isolation::IsolationLevel;
+use flux::runtime::sandbox::{ResourceLimit, SandboxConfig, SandboxExecutor};
+use flux::runtime::script_cache::ScriptLanguage;
+use flux::scheduler::SimpleScheduler;
+use flux::scheduler::routing::RoutingConfig;
+use flux::server::{FluxServer, RunningServer};
+use reqwest::{Client, StatusCode};
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+async fn start() -> RunningServer {
+    FluxServer::new()
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .expect("server starts")
+}
+
+/// 运行时未安装时跳过测试（返回 false）
+fn has_runtime(command: &str) -> bool {
+    let installed = which::which(command).is_ok();
+    if !installed {
+        eprintln!("skipping: `{command}` is not installed");
+    }
+    installed
+}
+
+async fn send(request: reqwest::RequestBuilder) -> (StatusCode, Value) {
+    let response = request.send().await.expect("request succeeds");
+    let status = response.status();
+    let body = response.json().await.unwrap_or(Value::Null);
+    (status, body)
+}
+
+/// 注册→调用→统计→删除，返回调用结果
+async fn function_lifecycle(server: &RunningServer, registration: Value) -> Value {
+    let client = Client::new();
+    let name = registration["name"].as_str().unwrap().to_string();
+
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // 函数详情说明调用上下文的传递方式
+    let (status, body) = send(client.get(server.url(&format!("/v1/functions/{name}")))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let fields = body["data"]["context"]["fields"].as_array().unwrap();
+    assert!(fields.contains(&json!("deadline_ms_remaining")), "{body}");
+
+    let (status, body) = send(
+        client
+            .post(server.url(&format!("/v1/invoke/{name}")))
+            .json(&json!({"input": {"a": 1, "b": 2}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let invocation = body["data"].clone();
+
+    let (status, body) = send(client.get(server.url("/v1/performance/stats"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["global_stats"]["total_requests"], 1, "{body}");
+    // 默认返回最近 5 分钟的窗口，生命周期汇总标明为衰减后的数字
+    let window = &body["data"]["window"];
+    assert_eq!(window["global"]["window"], "5m", "{body}");
+    assert_eq!(window["functions"][&name]["invocations"], 1, "{body}");
+    assert_eq!(
+        body["data"]["lifetime"]["global"]["decayed"], true,
+        "{body}"
+    );
+    let (status, body) = send(client.get(server.url("/v1/performance/stats?window=1h"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body["data"]["window"]["global"]["window_secs"], 3600,
+        "{body}"
+    );
+    let (status, _) = send(client.get(server.url("/v1/performance/stats?window=2d"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = send(client.delete(server.url(&format!("/v1/functions/{name}")))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, body) = send(client.get(server.url(&format!("/v1/functions/{name}")))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+
+    invocation
+}
+
+#[tokio::test]
+async fn test_javascript_function_lifecycle() {
+    if !has_runtime("node") {
+        return;
+    }
+    let server = start().await;
+    let invocation = function_lifecycle(
+        &server,
+        json!({
+            "name": "js-add",
+            "files": [{
+                "path": "index.js",
+                "content": "function handler(input) { return {sum: input.a + input.b}; }",
+            }],
+            "entrypoint": "index.js",
+        }),
+    )
+    .await;
+    assert_eq!(invocation["status"], "Success", "{invocation}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_python_function_lifecycle() {
+    if !has_runtime("python3") {
+        return;
+    }
+    let server = start().await;
+    let invocation = function_lifecycle(
+        &server,
+        json!({
+            "name": "py-add",
+            "files": [{
+                "path": "main.py",
+                "content": "def handler(input):\n    return {'sum': input['a'] + input['b']}\n",
+            }],
+            "entrypoint": "main.py",
+        }),
+    )
+    .await;
+    assert_eq!(invocation["status"], "Success", "{invocation}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_expression_function_result() {
+    let server = start().await;
+    let invocation =
+        function_lifecycle(&server, json!({"name": "sum", "code": "return a + b"})).await;
+    assert_eq!(invocation["output"]["result"], 3, "{invocation}");
+    // 表达式在进程内求值：没有编译和启动阶段
+    let timing = &invocation["timing"];
+    assert!(timing["compile_ms"].is_null(), "{invocation}");
+    assert!(timing["spawn_ms"].is_null(), "{invocation}");
+    assert_phases_sum_to_total(timing);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_timeout_is_reported() {
+    if !has_runtime("cargo") {
+        return;
+    }
+    // 启用编译的运行时：首次调用需要 cargo 编译，远超函数的 100ms 超时
+    let cache_dir = tempfile::tempdir().unwrap();
+    let runtime = SimpleRuntime::new_with_compiler_config(CompilerConfig {
+        cache_dir: cache_dir.path().to_path_buf(),
+        ..Default::default()
+    })
+    .unwrap();
+    let scheduler = Arc::new(SimpleScheduler::with_runtime(Arc::new(runtime)));
+    // 直接写入注册表，跳过注册时的后台编译
+    let mut function = FunctionMetadata::new("slow".to_string(), "fn slow() {}".to_string());
+    function.timeout_ms = 100;
+    scheduler.registry().register(function).await.unwrap();
+
+    let server = FluxServer::new()
+        .with_scheduler(scheduler)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let (status, body) = send(
+        Client::new()
+            .post(server.url("/v1/invoke/slow"))
+            .json(&json!({"input": {}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["status"], "Timeout", "{body}");
+    assert_eq!(
+        body["data"]["output"]["error"], "Execution timeout",
+        "{body}"
+    );
+    server.shutdown().await;
+}
+
+/// 各阶段（为 null 的除外）之和与总耗时只差取整误差
+fn assert_phases_sum_to_total(timing: &Value) {
+    let phases: u64 = ["queue_ms", "compile_ms", "spawn_ms", "execute_ms"]
+        .iter()
+        .filter_map(|phase| timing[phase].as_u64())
+        .sum();
+    let total = timing["total_ms"].as_u64().unwrap();
+    assert!(phases.abs_diff(total) <= 2, "{timing}");
+}
+
+#[tokio::test]
+async fn test_timing_breakdown_reports_compilation_once() {
+    if !has_runtime("cargo") {
+        return;
+    }
+    let cache_dir = tempfile::tempdir().unwrap();
+    let runtime = SimpleRuntime::new_with_compiler_config(CompilerConfig {
+        cache_dir: cache_dir.path().to_path_buf(),
+        ..Default::default()
+    })
+    .unwrap();
+    let scheduler = Arc::new(SimpleScheduler::with_runtime(Arc::new(runtime)));
+    // 直接写入注册表，首次调用时才编译
+    let mut function = FunctionMetadata::new("noop".to_string(), "fn noop() {}".to_string());
+    function.timeout_ms = 300_000;
+    scheduler.registry().register(function).await.unwrap();
+
+    let server = FluxServer::new()
+        .with_scheduler(scheduler)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let client = Client::new();
+    for compiled in [true, false] {
+        let (status, body) = send(
+            client
+                .post(server.url("/v1/invoke/noop"))
+                .json(&json!({"input": {}})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["data"]["status"], "Success", "{body}");
+        let timing = &body["data"]["timing"];
+        assert_eq!(timing["compile_ms"].is_u64(), compiled, "{timing}");
+        // 编译函数在服务进程内执行，没有启动阶段
+        assert!(timing["spawn_ms"].is_null(), "{timing}");
+        assert!(timing["execute_ms"].is_u64(), "{timing}");
+        assert_phases_sum_to_total(timing);
+    }
+
+    let (status, body) = send(client.get(server.url("/v1/functions/noop/report"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let phases = &body["data"]["phases"];
+    assert!(phases["compile_p95_ms"].as_f64().unwrap() > 0.0, "{body}");
+    assert!(phases["execute_p95_ms"].is_number(), "{body}");
+    assert!(phases["spawn_p95_ms"].is_null(), "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_error_mapping() {
+    let server = start().await;
+    let client = Client::new();
+
+    // 未知函数
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/missing"))
+            .json(&json!({"input": {}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+    assert_eq!(body["error"]["code"], "not_found", "{body}");
+
+    // 非法请求体
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/functions"))
+            .header("content-type", "application/json")
+            .body("{not json"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert_eq!(body["error"]["code"], "bad_request", "{body}");
+
+    // 重复注册
+    let registration = json!({"name": "dup", "code": "return input"});
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+    // 输入不符合 input_schema
+    let registration = json!({
+        "name": "typed",
+        "code": "return input",
+        "input_schema": {"type": "object", "required": ["id"]},
+    });
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/typed"))
+            .json(&json!({"input": {}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+
+    // 执行失败默认返回 200，严格模式按失败归属返回状态码；两种情况都带归属响应头
+    let registration = json!({"name": "sum", "code": "return a + b"});
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK);
+    for (query, expected) in [
+        ("", StatusCode::OK),
+        ("?strict=true", StatusCode::UNPROCESSABLE_ENTITY),
+    ] {
+        let response = client
+            .post(server.url(&format!("/v1/invoke/sum{query}")))
+            .json(&json!({"input": {"a": 1}}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), expected);
+        assert_eq!(response.headers()["x-flux-error-origin"], "user_code");
+    }
+    let response = client
+        .post(server.url("/v1/invoke/typed"))
+        .json(&json!({"input": {}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-flux-error-origin"], "input");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_fanout_invoke() {
+    let server = start().await;
+    let client = Client::new();
+    for registration in [
+        json!({"name": "ingest", "code": "return a + b"}),
+        json!({"name": "index", "code": "return input"}),
+    ] {
+        let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let url = server.url("/v1/invoke/ingest/fanout");
+    let (status, body) = send(client.post(&url).json(&json!({
+        "input": {"a": 1, "b": 2},
+        "targets": [
+            {"function": "index", "input_map": {"value": "/result"}, "required": true},
+            {"function": "missing"},
+        ],
+    })))
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let data = &body["data"];
+    assert_eq!(data["success"], true, "{body}");
+    assert_eq!(data["primary"]["output"]["result"], 3, "{body}");
+    assert_eq!(data["targets"][0]["success"], true, "{body}");
+    assert_eq!(data["targets"][1]["success"], false, "{body}");
+    assert!(data["total_duration_ms"].is_u64(), "{body}");
+
+    // 直接引用主函数的请求被拒绝
+    let (status, body) = send(
+        client
+            .post(&url)
+            .json(&json!({"targets": [{"function": "ingest"}]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_replay_execution() {
+    let server = start().await;
+    let client = Client::new();
+    let registration =
+        json!({"name": "replay-sum", "code": "return a + b", "log_redaction": ["token"]});
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let invoke = |input: serde_json::Value, id: &str| {
+        client
+            .post(server.url("/v1/invoke/replay-sum"))
+            .header("x-request-id", id)
+            .json(&json!({ "input": input }))
+    };
+    let (status, _) = send(invoke(json!({"a": 1, "b": 2}), "orig-1")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send(
+        client.post(server.url("/v1/executions/orig-1/replay?original_version=true&shadow=true")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let data = &body["data"];
+    assert_eq!(data["original"]["invocation_id"], "orig-1", "{body}");
+    assert_eq!(data["replay"]["replay_of"], "orig-1", "{body}");
+    assert_eq!(data["replay"]["shadow"], true, "{body}");
+    assert_eq!(data["changes"], json!([]), "{body}");
+    let (_, body) = send(client.get(server.url("/v1/performance/stats"))).await;
+    assert_eq!(body["data"]["global_stats"]["shadow_replays"], 1, "{body}");
+
+    // 包含脱敏字段的输入无法重放
+    let (status, _) = send(invoke(json!({"a": 1, "b": 2, "token": "secret"}), "orig-2")).await;
+    assert_eq!(status, StatusCode::OK);
+    let url = server.url("/v1/executions/orig-2/replay");
+    let (status, body) = send(client.post(&url)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+
+    let (status, _) = send(client.post(server.url("/v1/executions/unknown/replay"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+/// 解出调试包中的文件（路径去掉以调用 ID 命名的根目录）
+fn unpack_bundle(bundle: &[u8]) -> std::collections::BTreeMap<String, String> {
+    use std::io::Read;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(bundle));
+    archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().to_string();
+            let (_, path) = path.split_once('/').unwrap();
+            let path = path.to_string();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            (path, content)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_debug_bundle_for_failed_invocation() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = FluxConfig::default();
+    config.debug_capture.dir = dir.path().to_path_buf();
+    let server = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let client = Client::new();
+    let invoke = |name: &str, id: &str, input: Value| {
+        client
+            .post(server.url(&format!("/v1/invoke/{name}")))
+            .header("x-request-id", id)
+            .json(&json!({ "input": input }))
+    };
+    let manifest =
+        |id: &str| client.get(server.url(&format!("/v1/executions/{id}/bundle/manifest")));
+
+    let registration = json!({
+        "name": "debug-sum",
+        "code": "return a + b",
+        "debug_capture": "on_failure",
+        "log_redaction": ["password"],
+    });
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // 成功的调用不保存调试包
+    let (status, _) = send(invoke("debug-sum", "debug-ok", json!({"a": 1, "b": 2}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(manifest("debug-ok")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+
+    send(invoke(
+        "debug-sum",
+        "debug-fail",
+        json!({"a": 1, "password": "hunter2-secret"}),
+    ))
+    .await;
+    let (status, body) = send(manifest("debug-fail")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let data = &body["data"];
+    assert_eq!(data["success"], false, "{body}");
+    assert_eq!(data["script_type"], "expression", "{body}");
+    assert_eq!(data["input_redacted"], true, "{body}");
+
+    let response = client
+        .get(server.url("/v1/executions/debug-fail/bundle"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/gzip");
+    let files = unpack_bundle(&response.bytes().await.unwrap());
+    assert_eq!(files["source/expression.txt"], "return a + b");
+    assert!(files["input.json"].contains("[REDACTED]"), "{files:?}");
+    assert!(
+        files.values().all(|content| !content.contains("hunter2")),
+        "{files:?}"
+    );
+
+    // 脚本函数的调试包包含包装后的脚本、环境变量名、解释器版本和标准错误
+    if has_runtime("python3") {
+        let registration = json!({
+            "name": "debug-py",
+            "code": "import sys\n\ndef handler(input):\n    print('login as', input['password'], file=sys.stderr)\n    raise ValueError('denied')\n",
+            "debug_capture": "always",
+            "log_redaction": ["password"],
+        });
+        let (status, body) =
+            send(client.post(server.url("/v1/functions")).json(&registration)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        send(invoke(
+            "debug-py",
+            "debug-py-1",
+            json!({"password": "hunter2-secret"}),
+        ))
+        .await;
+
+        let (status, body) = send(manifest("debug-py-1")).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let data = &body["data"];
+        assert!(data["toolchain"]["python"].is_string(), "{body}");
+        assert_eq!(data["env"]["FLUX_DEADLINE_MS"], "[REDACTED]", "{body}");
+        let response = client
+            .get(server.url("/v1/executions/debug-py-1/bundle"))
+            .send()
+            .await
+            .unwrap();
+        let files = unpack_bundle(&response.bytes().await.unwrap());
+        assert!(files["source/main.py"].contains("def handler"), "{files:?}");
+        assert!(
+            files["stderr.txt"].contains("login as [REDACTED]"),
+            "{files:?}"
+        );
+        assert!(
+            files.values().all(|content| !content.contains("hunter2")),
+            "{files:?}"
+        );
+    }
+
+    let (status, _) = send(client.get(server.url("/v1/executions/unknown/bundle"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_function_mirror() {
+    let server = start().await;
+    let client = Client::new();
+    for (name, code) in [
+        ("mirror-sum", "return a + b"),
+        ("mirror-sum-v2", "return a * b"),
+    ] {
+        let registration = json!({"name": name, "code": code});
+        let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let url = server.url("/v1/functions/mirror-sum/mirror");
+    let (status, body) = send(client.put(&url).json(&json!({"target": "mirror-sum-v2"}))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["sample_rate"], 1.0, "{body}");
+
+    // 调用方得到主函数的结果，不一致在后台记录
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/mirror-sum"))
+            .json(&json!({"input": {"a": 1, "b": 2}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["output"]["result"], 3, "{body}");
+
+    let mismatches_url = server.url("/v1/functions/mirror-sum/mirror/mismatches");
+    let mut report = Value::Null;
+    for _ in 0..100 {
+        let (_, body) = send(client.get(&mismatches_url)).await;
+        report = body["data"].clone();
+        if report["stats"]["mirrored"] == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(report["stats"]["mismatched"], 1, "{report}");
+    assert_eq!(
+        report["mismatches"][0]["changes"][0]["path"], "/result",
+        "{report}"
+    );
+
+    let (status, body) = send(client.put(&url).json(&json!({"target": "missing"}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    let (status, _) = send(client.delete(&url)).await;
+    assert_eq!(status, StatusCode::OK);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_chaos_rules() {
+    let client = Client::new();
+    let rule = json!({"match": {"function": "flaky*"}, "action": "fail", "probability": 1.0});
+
+    // 未启用时拒绝创建规则
+    let server = start().await;
+    let (status, body) = send(client.post(server.url("/v1/admin/chaos/rules")).json(&rule)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+    server.shutdown().await;
+
+    let mut config = FluxConfig::default();
+    config.chaos.enabled = true;
+    let server = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let registration = json!({"name": "flaky-sum", "code": "return a + b"});
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send(client.post(server.url("/v1/admin/chaos/rules")).json(&rule)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/flaky-sum"))
+            .json(&json!({"input": {"a": 1, "b": 2}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["chaos_injected"], true, "{body}");
+    assert_ne!(body["data"]["status"], "Success", "{body}");
+
+    let (_, body) = send(client.get(server.url("/v1/performance/stats"))).await;
+    assert_eq!(body["data"]["global_stats"]["chaos_injected"], 1, "{body}");
+    let (_, body) = send(client.get(server.url("/v1/admin/chaos/rules"))).await;
+    assert_eq!(body["data"]["rules"][0]["injected"], 1, "{body}");
+
+    let url = server.url(&format!("/v1/admin/chaos/rules/{id}"));
+    let (status, _) = send(client.delete(&url)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(client.delete(&url)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, body) = send(
+        client
+            .post(server.url("/v1/invoke/flaky-sum"))
+            .json(&json!({"input": {"a": 1, "b": 2}})),
+    )
+    .await;
+    assert!(body["data"].get("chaos_injected").is_none(), "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_circuit_breaker() {
+    let mut config = FluxConfig::default();
+    config.circuit_breaker.min_requests = 2;
+    config.circuit_breaker.open_ms = 60_000;
+    let server = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let client = Client::new();
+    let registration = json!({"name": "brittle", "code": "return a + b"});
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 缺少参数时执行失败，两次失败后打开熔断
+    let invoke = || {
+        client
+            .post(server.url("/v1/invoke/brittle"))
+            .json(&json!({"input": {}}))
+    };
+    for _ in 0..2 {
+        let (status, _) = send(invoke()).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let response = invoke().send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let retry_after: u64 = response.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after), "{retry_after}");
+
+    let url = server.url("/v1/functions/brittle/circuit");
+    let (status, body) = send(client.get(&url)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["state"], "open", "{body}");
+    assert_eq!(body["data"]["rejected"], 1, "{body}");
+
+    let (status, body) = send(client.post(server.url("/v1/functions/brittle/circuit/reset"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["state"], "closed", "{body}");
+    let (status, _) = send(invoke()).await;
+    assert_eq!(status, StatusCode::OK);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_function_docs() {
+    let server = start().await;
+    let client = Client::new();
+    let registration = json!({
+        "name": "documented",
+        "code": "return a + b",
+        "documentation": "# documented\n\nAdds `a` and `b`.\n\n<script>alert(1)</script>",
+    });
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // 列表只包含第一段的摘要
+    let (_, body) = send(client.get(server.url("/v1/functions"))).await;
+    let listed = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["name"] == "documented")
+        .unwrap();
+    assert_eq!(listed["excerpt"], "Adds a and b.", "{body}");
+
+    let url = server.url("/v1/functions/documented/docs");
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/markdown")
+    );
+    assert!(response.text().await.unwrap().contains("<script>"));
+
+    let response = client
+        .get(format!("{url}?format=html"))
+        .send()
+        .await
+        .unwrap();
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html")
+    );
+    let html = response.text().await.unwrap();
+    assert!(html.contains("<h1>documented</h1>"), "{html}");
+    assert!(!html.contains("<script"), "{html}");
+
+    // 单独更新文档不改变函数版本
+    let (_, before) = send(client.get(server.url("/v1/functions/documented"))).await;
+    let (status, body) = send(client.put(&url).body("Sums two numbers.")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["excerpt"], "Sums two numbers.", "{body}");
+    let (_, after) = send(client.get(server.url("/v1/functions/documented"))).await;
+    assert_eq!(
+        after["data"]["version"], before["data"]["version"],
+        "{after}"
+    );
+    assert_eq!(
+        after["data"]["documentation"], "Sums two numbers.",
+        "{after}"
+    );
+
+    // 导出包携带文档
+    let (_, body) = send(client.get(server.url("/v1/functions/documented/export"))).await;
+    assert_eq!(body["data"]["documentation"], "Sums two numbers.", "{body}");
+
+    let (status, body) = send(
+        client
+            .put(&url)
+            .body("x".repeat(flux::functions::docs::MAX_DOCUMENTATION_BYTES + 1)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE, "{body}");
+
+    // 空请求体移除文档
+    let (status, _) = send(client.put(&url).body("")).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(client.get(&url)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_signature_inference_on_registration() {
+    let server = start().await;
+    let client = Client::new();
+    let registration = json!({
+        "name": "scale",
+        "code": "def handler(values: list[float], factor: float = 2.0, *rest, context=None) -> list[float]:\n    return [v * factor for v in values]\n",
+    });
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (_, body) = send(client.get(server.url("/v1/functions/scale"))).await;
+    let function = &body["data"];
+    assert_eq!(function["parameters_inferred"], true, "{body}");
+    assert_eq!(function["return_type_inferred"], true, "{body}");
+    assert_eq!(function["return_type"], "Vec<number>", "{body}");
+    let parameters = function["parameters"].as_array().unwrap();
+    assert_eq!(parameters.len(), 2, "{body}");
+    assert_eq!(parameters[1]["name"], "factor");
+    assert_eq!(parameters[1]["required"], false);
+    assert_eq!(parameters[1]["default_value"], "2.0");
+
+    // 推断的参数进入 OpenAPI 文档
+    let (_, spec) = send(client.get(server.url("/v1/openapi.json"))).await;
+    assert!(spec.to_string().contains("factor"), "{spec}");
+
+    // 声明了参数的函数不推断
+    let declared = json!({
+        "name": "declared",
+        "code": "def handler(a, b):\n    return a\n",
+        "parameters": [{"name": "x", "param_type": "integer", "required": true}],
+    });
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&declared)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send(client.get(server.url("/v1/functions/declared"))).await;
+    assert!(body["data"].get("parameters_inferred").is_none(), "{body}");
+    assert_eq!(body["data"]["parameters"][0]["name"], "x");
+
+    let url = server.url("/v1/functions/scale/infer");
+    let (status, body) = send(client.post(&url).header("If-Match", "\"999\"")).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED, "{body}");
+    let (status, body) = send(client.post(&url)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["parameters"].as_array().unwrap().len(), 2);
+    let (status, _) = send(client.post(server.url("/v1/functions/missing/infer"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_function_diff() {
+    let server = start().await;
+    let client = Client::new();
+    for registration in [
+        json!({"name": "report", "version": "1.0.0", "code": "return input.a + input.b;"}),
+        json!({"name": "report_py", "code": "def handler(input, context):\n    return input['a']\n"}),
+    ] {
+        let (status, body) =
+            send(client.post(server.url("/v1/functions")).json(&registration)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+    let update = json!({"version": "1.1.0", "timeout_ms": 9000});
+    let (status, body) = send(
+        client
+            .patch(server.url("/v1/functions/report"))
+            .json(&update),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let url = server.url("/v1/functions/report/diff");
+    let (status, body) = send(client.get(&url).query(&[("version", "1.0.0")])).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let diff = &body["data"];
+    assert_eq!(diff["from"]["version"], "1.0.0", "{body}");
+    assert_eq!(diff["to"]["version"], "1.1.0", "{body}");
+    assert_eq!(diff["fields"][0]["field"], "timeout_ms", "{body}");
+    assert_eq!(diff["summary"]["identical"], false);
+    assert!(diff["code"].as_array().unwrap().is_empty(), "{body}");
+
+    // 任一侧不存在时返回 404
+    let (status, _) = send(client.get(&url).query(&[("version", "0.1.0")])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(client.get(&url).query(&[("against", "missing")])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 脚本类型不同需要 force=true
+    let (status, body) = send(client.get(&url).query(&[("against", "report_py")])).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    let (status, body) = send(
+        client
+            .get(&url)
+            .query(&[("against", "report_py"), ("force", "true")]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let code = &body["data"]["code"][0];
+    assert_eq!(code["lines_added"], 2, "{body}");
+    assert!(
+        code["unified"]
+            .as_str()
+            .unwrap()
+            .starts_with("--- report:1.1.0\n")
+    );
+
+    // 版本列表附带代码行数统计
+    let (_, body) = send(client.get(server.url("/v1/functions/report/versions"))).await;
+    assert!(body.to_string().contains("lines_added"), "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_deployment_lifecycle() {
+    use flux::functions::bundle::FunctionArchive;
+
+    let server = start().await;
+    let client = Client::new();
+    let registration = json!({"name": "calc", "code": "return 1"});
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let archive = FunctionArchive::from_functions(&[
+        FunctionMetadata::new("calc".to_string(), "return 2".to_string()),
+        FunctionMetadata::new("fresh".to_string(), "return 3".to_string()),
+    ]);
+    let (status, body) = send(client.post(server.url("/v1/deployments")).json(&archive)).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+    let url = server.url(&format!("/v1/deployments/{id}"));
+
+    // 准备在后台完成，期间线上函数不变
+    let mut deployment = body["data"].clone();
+    for _ in 0..50 {
+        if deployment["state"] != "preparing" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        deployment = send(client.get(&url)).await.1["data"].clone();
+    }
+    assert_eq!(deployment["state"], "ready", "{deployment}");
+    let (_, body) = send(client.get(server.url("/v1/functions/calc"))).await;
+    assert_eq!(body["data"]["code"], "return 1", "{body}");
+
+    // 未结束的部署独占其函数名
+    let (status, body) = send(client.post(server.url("/v1/deployments")).json(&archive)).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+    let (status, body) = send(client.post(format!("{url}/activate"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["state"], "active", "{body}");
+    let (_, body) = send(client.get(server.url("/v1/functions/calc"))).await;
+    assert_eq!(body["data"]["code"], "return 2", "{body}");
+    let (status, _) = send(client.post(format!("{url}/activate"))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send(client.post(format!("{url}/rollback"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["state"], "rolled_back", "{body}");
+    let (_, body) = send(client.get(server.url("/v1/functions/calc"))).await;
+    assert_eq!(body["data"]["code"], "return 1", "{body}");
+    let (status, _) = send(client.get(server.url("/v1/functions/fresh"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(client.get(server.url("/v1/deployments/missing"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_dashboard() {
+    let mut config = FluxConfig::default();
+    config.dashboard.heartbeat_secs = 1;
+    let server = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let client = Client::new();
+    let registration = json!({"name": "sum", "code": "return a + b"});
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK);
+    for input in [json!({"a": 1, "b": 2}), json!({})] {
+        let (status, _) = send(
+            client
+                .post(server.url("/v1/invoke/sum"))
+                .json(&json!({"input": input})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (status, body) = send(client.get(server.url("/v1/dashboard/summary"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["data"]["generated_at"].is_string(), "{body}");
+    let row = &body["data"]["functions"][0];
+    assert_eq!(row["function"], "sum", "{body}");
+    assert_eq!(row["invocations_5m"], 2, "{body}");
+    assert_eq!(row["error_rate"], 0.5, "{body}");
+    assert!(row["rate_1m"].as_f64().unwrap() > 0.0, "{body}");
+
+    // 流以快照开始，空闲时发送心跳注释
+    let mut response = client
+        .get(server.url("/v1/dashboard/stream"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let mut received = String::new();
+    while !received.contains(":heartbeat") {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), response.chunk())
+            .await
+            .expect("stream keeps sending")
+            .unwrap()
+            .expect("stream is open");
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+    assert!(received.starts_with("event:snapshot\n"), "{received}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_runtime_definitions() {
+    let mut config = FluxConfig::default();
+    config.runtimes.definitions = vec![RuntimeDefinition {
+        name: "py39".to_string(),
+        kind: RuntimeKind::Python,
+        binary: PathBuf::from("/opt/py39/bin/python"),
+        env: Default::default(),
+    }];
+    let server = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let client = Client::new();
+
+    let (status, body) = send(client.get(server.url("/v1/runtimes"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let definition = &body["data"]["definitions"][0];
+    assert_eq!(definition["name"], "py39", "{body}");
+    assert_eq!(definition["available"], false, "{body}");
+
+    let registration = |name: &str, entrypoint: &str, runtime: &str| {
+        json!({
+            "name": name,
+            "files": [{"path": entrypoint, "content": "def handler(input):\n    return input"}],
+            "entrypoint": entrypoint,
+            "runtime": runtime,
+        })
+    };
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/functions"))
+            .json(&registration("pinned", "main.py", "py39")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (_, body) = send(client.get(server.url("/v1/functions/pinned"))).await;
+    assert_eq!(body["data"]["runtime"], "py39", "{body}");
+
+    // 未定义或与脚本类型不匹配的执行环境在注册时被拒绝
+    for (name, entrypoint, runtime) in [("js", "index.js", "py39"), ("missing", "main.py", "py27")]
+    {
+        let (status, body) = send(
+            client
+                .post(server.url("/v1/functions"))
+                .json(&registration(name, entrypoint, runtime)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    }
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_resource_quotas() {
+    let server = start().await;
+    let client = Client::new();
+    let quota = |soft_limit: u64, hard_limit: u64| {
+        json!({
+            "name": "tight",
+            "limits": {"Memory": {
+                "resource_type": "Memory",
+                "soft_limit": soft_limit,
+                "hard_limit": hard_limit,
+                "check_interval_ms": 100,
+                "enabled": true
+            }}
+        })
+    };
+
+    // 软限制不小于硬限制的定义被拒绝
+    let (status, body) = send(client.post(server.url("/v1/quotas")).json(&quota(64, 32))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    // 选择未定义配额的函数在注册时被拒绝
+    let registration = json!({"name": "hungry", "code": "return input", "resource_quota": "tight"});
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    let (status, body) = send(client.post(server.url("/v1/quotas")).json(&quota(16, 32))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, body) = send(client.post(server.url("/v1/quotas")).json(&quota(16, 32))).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, body) = send(
+        client
+            .put(server.url("/v1/quotas/tight"))
+            .json(&quota(32, 64)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (_, body) = send(client.get(server.url("/v1/quotas/tight"))).await;
+    assert_eq!(body["data"]["limits"]["Memory"]["hard_limit"], 64, "{body}");
+    assert_eq!(body["data"]["functions"], json!(["hungry"]), "{body}");
+    let (status, body) = send(client.get(server.url("/v1/quotas/tight/usage"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["executions"], 0, "{body}");
+
+    // 仍被函数选择的配额不能删除
+    let (status, body) = send(client.delete(server.url("/v1/quotas/tight"))).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    send(client.delete(server.url("/v1/functions/hungry"))).await;
+    let (status, body) = send(client.delete(server.url("/v1/quotas/tight"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, body) = send(client.get(server.url("/v1/quotas/tight"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_function_state() {
+    let server = start().await;
+    let client = Client::new();
+    let registration = json!({"name": "counter", "code": "return input"});
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // 脚本通过包装提供的 state_get/state_set 经回环接口读写状态，状态在多次执行之间保留
+    // （禁止网络时脚本处于独立的网络命名空间中，访问不到宿主的回环接口）
+    let sandbox = SandboxExecutor::new(SandboxConfig {
+        allow_network: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let function = FunctionMetadata::new("counter".to_string(), "input".to_string());
+    let scripts = [
+        (
+            ScriptLanguage::Python,
+            "def handler(input):\n    count = (state_get('count') or 0) + input['by']\n    state_set('count', count)\n    return {'count': count}\n",
+        ),
+        (
+            ScriptLanguage::JavaScript,
+            "async function handler(input) { const count = ((await state_get('count')) || 0) + input.by; await state_set('count', count, 3600); return {count}; }",
+        ),
+    ];
+    let mut expected = 0;
+    for (language, source) in scripts {
+        if !has_runtime(language.interpreter()) {
+            continue;
+        }
+        for _ in 0..2 {
+            let grant = server.schedulers().state().grant("counter", false);
+            let context = InvocationContext::new(&function, "inv").with_state(grant.handle());
+            let result = sandbox
+                .execute_function_script(
+                    language,
+                    None,
+                    source,
+                    &json!({"by": 2}),
+                    &context,
+                    &sandbox.default_limits(),
+                )
+                .await
+                .unwrap();
+            expected += 2;
+            assert_eq!(result.output, json!({"count": expected}), "{result:?}");
+        }
+    }
+
+    let state_url = server.url("/v1/functions/counter/state");
+    let (status, body) = send(client.get(&state_url)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    if expected > 0 {
+        assert_eq!(
+            body["data"]["entries"]["count"]["value"], expected,
+            "{body}"
+        );
+        assert_eq!(
+            body["data"]["entries"]["count"]["version"],
+            expected / 2,
+            "{body}"
+        );
+    }
+
+    // 调用结束后令牌失效
+    let token = server.schedulers().state().grant("counter", false).handle();
+    let token = serde_json::to_value(&token).unwrap()["token"].clone();
+    let (status, body) = send(
+        client
+            .post(server.url("/state"))
+            .header("x-flux-state-token", token.as_str().unwrap())
+            .json(&json!({"op": "get", "key": "count"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{body}");
+
+    let (status, body) = send(client.delete(&state_url)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (_, body) = send(client.get(&state_url)).await;
+    assert_eq!(body["data"]["keys"], 0, "{body}");
+    let (status, body) = send(client.get(server.url("/v1/functions/missing/state"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_brokered_http_fetch() {
+    let server = start().await;
+    let client = Client::new();
+    let health = server.url("/v1/health");
+    let mut function = FunctionMetadata::new("fetcher".to_string(), "input".to_string());
+    function.egress = Some(EgressPolicy {
+        allow: vec![EgressRule {
+            url: health.clone(),
+            methods: vec!["GET".to_string()],
+        }],
+        max_calls: 2,
+        ..Default::default()
+    });
+
+    // 脚本通过包装提供的 http_fetch 经代理发起请求，只允许策略中列出的 URL
+    let sandbox = SandboxExecutor::new(SandboxConfig {
+        allow_network: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let scripts = [
+        (
+            ScriptLanguage::Python,
+            "def handler(input):\n    ok = http_fetch(input['url'])\n    try:\n        http_fetch(input['url'].replace('/v1/health', '/v1/functions'))\n        denied = None\n    except RuntimeError as e:\n        denied = str(e)\n    return {'status': ok['status'], 'denied': denied}\n",
+        ),
+        (
+            ScriptLanguage::JavaScript,
+            "async function handler(input) { const ok = await http_fetch(input.url); let denied = null; try { await http_fetch(input.url.replace('/v1/health', '/v1/functions')); } catch (e) { denied = e.message; } return {status: ok.status, denied}; }",
+        ),
+    ];
+    for (language, source) in scripts {
+        if !has_runtime(language.interpreter()) {
+            continue;
+        }
+        let deadline = std::time::Instant::now() + Duration::from_secs(30);
+        let grant = server
+            .schedulers()
+            .egress()
+            .grant(&function, deadline, false);
+        let context = InvocationContext::new(&function, "inv").with_egress(grant.handle());
+        let result = sandbox
+            .execute_function_script(
+                language,
+                None,
+                source,
+                &json!({"url": health}),
+                &context,
+                &sandbox.default_limits(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.output["status"], 200, "{result:?}");
+        let denied = result.output["denied"].as_str().unwrap_or_default();
+        assert!(denied.contains("not allowed"), "{result:?}");
+        let calls = grant.calls();
+        assert_eq!(calls.len(), 2, "{calls:?}");
+        assert_eq!(calls[0].status, Some(200), "{calls:?}");
+        assert!(calls[1].error.is_some(), "{calls:?}");
+    }
+
+    // 调用结束后令牌失效
+    let grant = server.schedulers().egress().grant(
+        &function,
+        std::time::Instant::now() + Duration::from_secs(30),
+        false,
+    );
+    let token = serde_json::to_value(grant.handle()).unwrap()["token"].clone();
+    drop(grant);
+    let (status, body) = send(
+        client
+            .post(server.url("/egress"))
+            .header("x-flux-egress-token", token.as_str().unwrap())
+            .json(&json!({"url": health})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{body}");
+
+    // 未配置策略的函数不能发起请求
+    let plain = FunctionMetadata::new("plain".to_string(), "input".to_string());
+    let grant = server.schedulers().egress().grant(
+        &plain,
+        std::time::Instant::now() + Duration::from_secs(30),
+        false,
+    );
+    let token = serde_json::to_value(grant.handle()).unwrap()["token"].clone();
+    let (status, body) = send(
+        client
+            .post(server.url("/egress"))
+            .header("x-flux-egress-token", token.as_str().unwrap())
+            .json(&json!({"url": health})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_shutdown_releases_port() {
+    let server = start().await;
+    let addr = server.addr();
+    let handle = server.shutdown_handle();
+    let (status, _) = send(Client::new().get(server.url("/v1/health"))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    handle.shutdown();
+    assert!(handle.is_shutdown());
+    server.shutdown().await;
+    assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sandbox_network_follows_allow_network() {
+    if IsolationLevel::detect() != IsolationLevel::Namespaces || !has_runtime("cargo") {
+        return;
+    }
+    let cache_dir = tempfile::tempdir().unwrap();
+    let compiler = RustCompiler::new(CompilerConfig {
+        cache_dir: cache_dir.path().to_path_buf(),
+        compile_timeout_secs: 300,
+        ..Default::default()
+    })
+    .unwrap();
+    let mut function = FunctionMetadata::new(
+        "connect".to_string(),
+        "fn handler(addr: String) -> anyhow::Result<bool> {\n    Ok(std::net::TcpStream::connect(addr).is_ok())\n}".to_string(),
+    );
+    function.parameters = vec![FunctionParameter {
+        name: "addr".to_string(),
+        param_type: "string".to_string(),
+        description: None,
+        required: true,
+        default_value: None,
+    }];
+    function.return_type = "boolean".to_string();
+    let compiled = compiler.compile_function(&function).await.unwrap();
+
+    // 宿主上监听的端口：禁止网络时函数处于只有回环接口的网络命名空间中，连接失败
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let request: InvokeRequest = serde_json::from_value(json!({"input": {"addr": addr}})).unwrap();
+    for allow_network in [false, true] {
+        let temp_root = tempfile::tempdir().unwrap();
+        let sandbox = SandboxExecutor::new(SandboxConfig {
+            allow_network,
+            temp_root: temp_root.path().to_path_buf(),
+            execution_timeout_secs: 300,
+            ..Default::default()
+        })
+        .unwrap();
+        let result = sandbox
+            .execute_in_sandbox(&compiled, &request)
+            .await
+            .unwrap();
+        assert_eq!(result.isolation_level, IsolationLevel::Namespaces);
+        assert_eq!(result.output, json!(allow_network), "{result:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_sandbox_disk_write_limit_and_usage() {
+    if !has_runtime("cargo") {
+        return;
+    }
+    let cache_dir = tempfile::tempdir().unwrap();
+    let compiler = RustCompiler::new(CompilerConfig {
+        cache_dir: cache_dir.path().to_path_buf(),
+        compile_timeout_secs: 300,
+        ..Default::default()
+    })
+    .unwrap();
+    let mut function = FunctionMetadata::new(
+        "writer".to_string(),
+        "fn handler(size_mb: i64, hold_ms: i64) -> anyhow::Result<i64> {\n    use std::io::Write;\n    let mut file = std::fs::File::create(\"out.bin\")?;\n    for _ in 0..size_mb {\n        file.write_all(&vec![7u8; 1024 * 1024])?;\n    }\n    file.sync_all()?;\n    std::thread::sleep(std::time::Duration::from_millis(hold_ms as u64));\n    Ok(size_mb)\n}".to_string(),
+    );
+    function.parameters = ["size_mb", "hold_ms"]
+        .iter()
+        .map(|name| FunctionParameter {
+            name: name.to_string(),
+            param_type: "integer".to_string(),
+            description: None,
+            required: true,
+            default_value: None,
+        })
+        .collect();
+    function.return_type = "integer".to_string();
+    let compiled = compiler.compile_function(&function).await.unwrap();
+
+    let temp_root = tempfile::tempdir().unwrap();
+    let sandbox = SandboxExecutor::new(SandboxConfig {
+        temp_root: temp_root.path().to_path_buf(),
+        execution_timeout_secs: 300,
+        ..Default::default()
+    })
+    .unwrap();
+    let limits = sandbox.default_limits().with_disk_write_limit(Some(10));
+    let request = |size_mb: u64, hold_ms: u64| -> InvokeRequest {
+        serde_json::from_value(json!({"input": {"size_mb": size_mb, "hold_ms": hold_ms}})).unwrap()
+    };
+
+    // 写入 50MB 后停留：下一次采样时被终止，而不是等到停留结束
+    let result = sandbox
+        .execute_in_sandbox_with_limits(&compiled, &request(50, 60_000), &limits)
+        .await
+        .unwrap();
+    let Some(ResourceLimit::DiskWrite {
+        written_bytes,
+        limit_mb: 10,
+    }) = result.killed_by
+    else {
+        panic!("expected disk write kill: {result:?}");
+    };
+    assert!(written_bytes > 10 * 1024 * 1024, "{written_bytes}");
+    assert!(
+        result
+            .stderr
+            .contains(&format!("{written_bytes} bytes written")),
+        "{}",
+        result.stderr
+    );
+    assert!(
+        result.termination.unwrap().ran_for_ms < 30_000,
+        "{result:?}"
+    );
+    assert!(result.disk.unwrap().write_bytes >= written_bytes);
+
+    // 上限内的函数正常完成并报告磁盘用量
+    let result = sandbox
+        .execute_in_sandbox_with_limits(&compiled, &request(2, 500), &limits)
+        .await
+        .unwrap();
+    assert_eq!(result.output, json!(2), "{result:?}");
+    let disk = result.disk.unwrap();
+    assert!(disk.peak_dir_bytes >= 2 * 1024 * 1024, "{disk:?}");
+    assert!(disk.write_bytes >= disk.peak_dir_bytes, "{disk:?}");
+    assert!(disk.write_bytes < 10 * 1024 * 1024, "{disk:?}");
+    if cfg!(target_os = "linux") {
+        assert_eq!(disk.method, DiskAccounting::ProcIo, "{disk:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_sandbox_cpu_time_limit_is_independent_of_timeout() {
+    if !has_runtime("cargo") {
+        return;
+    }
+    let cache_dir = tempfile::tempdir().unwrap();
+    let compiler = RustCompiler::new(CompilerConfig {
+        cache_dir: cache_dir.path().to_path_buf(),
+        compile_timeout_secs: 300,
+        ..Default::default()
+    })
+    .unwrap();
+    let mut function = FunctionMetadata::new(
+        "spinner".to_string(),
+        "fn handler(spins: i64) -> anyhow::Result<i64> {\n    let mut count: i64 = 0;\n    while spins < 0 || count < spins {\n        count = std::hint::black_box(count + 1);\n    }\n    Ok(count)\n}".to_string(),
+    );
+    function.parameters = vec![FunctionParameter {
+        name: "spins".to_string(),
+        param_type: "integer".to_string(),
+        description: None,
+        required: true,
+        default_value: None,
+    }];
+    function.return_type = "integer".to_string();
+    function.timeout_ms = 10_000;
+    function.max_cpu_time_ms = Some(500);
+    let compiled = compiler.compile_function(&function).await.unwrap();
+
+    let temp_root = tempfile::tempdir().unwrap();
+    let sandbox = SandboxExecutor::new(SandboxConfig {
+        temp_root: temp_root.path().to_path_buf(),
+        execution_timeout_secs: 300,
+        ..Default::default()
+    })
+    .unwrap();
+    let limits = sandbox.default_limits();
+    let request = |spins: i64| -> InvokeRequest {
+        serde_json::from_value(json!({"input": {"spins": spins}})).unwrap()
+    };
+
+    // 无限空转：累计 CPU 时间超出 500ms 后被终止，远早于 10 秒的超时
+    let result = sandbox
+        .execute_in_sandbox_with_limits(&compiled, &request(-1), &limits)
+        .await
+        .unwrap();
+    let Some(ResourceLimit::CpuTime {
+        cpu_time_ms,
+        limit_ms: 500,
+    }) = result.killed_by
+    else {
+        panic!("expected CPU time kill: {result:?}");
+    };
+    assert!(cpu_time_ms > 500, "{cpu_time_ms}");
+    assert!(
+        result
+            .stderr
+            .contains("ms of CPU time used, limit is 500ms"),
+        "{}",
+        result.stderr
+    );
+    assert!(result.termination.unwrap().ran_for_ms < 5_000, "{result:?}");
+    assert!(result.cpu_time_ms.unwrap() >= cpu_time_ms, "{result:?}");
+
+    // 上限内的调用正常完成并报告 CPU 时间
+    let result = sandbox
+        .execute_in_sandbox_with_limits(&compiled, &request(1000), &limits)
+        .await
+        .unwrap();
+    assert_eq!(result.output, json!(1000), "{result:?}");
+    assert!(result.killed_by.is_none(), "{result:?}");
+    assert!(result.cpu_time_ms.unwrap() < 500, "{result:?}");
+    assert_eq!(result.timing.cpu_ms, result.cpu_time_ms);
+}
+
+#[tokio::test]
+async fn test_instances_list_script_workers() {
+    if !has_runtime("python3") {
+        return;
+    }
+    let server = start().await;
+    let client = Client::new();
+    // 定义了 init 钩子的函数由常驻工作进程执行，工作进程作为实例列出
+    let code = "def init(context):\n    return {'calls': 0}\n\ndef handler(input, context, state):\n    state['calls'] += 1\n    return state['calls']\n";
+    let registration = json!({"name": "warm", "code": code});
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    for calls in 1..=2 {
+        let (status, body) = send(
+            client
+                .post(server.url("/v1/invoke/warm"))
+                .json(&json!({"input": {}})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["data"]["output"], calls, "{body}");
+    }
+
+    let (status, body) = send(client.get(server.url("/v1/instances"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let instances = body["data"].as_array().unwrap();
+    assert_eq!(instances.len(), 1, "{body}");
+    let instance = &instances[0];
+    assert_eq!(instance["function_name"], "warm", "{body}");
+    assert_eq!(instance["state"], "Idle", "{body}");
+    assert_eq!(instance["execution_stats"]["total_executions"], 2, "{body}");
+    assert_eq!(
+        instance["execution_stats"]["failed_executions"], 0,
+        "{body}"
+    );
+
+    let (status, body) = send(client.get(server.url("/v1/instances/stats"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let stats = &body["data"];
+    assert_eq!(stats["total_instances"], 1, "{body}");
+    assert_eq!(stats["idle_instances"], 1, "{body}");
+    assert_eq!(stats["active_functions"], 1, "{body}");
+    assert_eq!(stats["total_executions"], 2, "{body}");
+
+    let id = instance["instance_id"].as_str().unwrap();
+    let (status, body) = send(client.get(server.url(&format!("/v1/instances/{id}/events")))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let kinds: Vec<_> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| event["event_type"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, ["WarmingStarted", "Ready"], "{body}");
+
+    // 删除函数后工作进程被回收，不再列出
+    let (status, body) = send(client.delete(server.url("/v1/functions/warm"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    loop {
+        let (_, body) = send(client.get(server.url("/v1/instances"))).await;
+        if body["data"].as_array().unwrap().is_empty() {
+            break;
+        }
+        assert!(std::time::Instant::now() < deadline, "{body}");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_cpu_time_is_reported_and_ranked() {
+    if !has_runtime("python3") {
+        return;
+    }
+    let server = start().await;
+    let client = Client::new();
+    let code = "import time\n\ndef handler(input):\n    end = time.process_time() + input['cpu_ms'] / 1000\n    while time.process_time() < end:\n        pass\n    time.sleep(input.get('idle_ms', 0) / 1000)\n    return 'done'\n";
+    // 墙钟超时足够长，只有 CPU 时间上限会终止调用
+    let registration = json!({
+        "name": "cpu-burner",
+        "code": code,
+        "max_cpu_time_ms": 2000,
+        "timeout_ms": 30_000,
+    });
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // CPU 时间按进程实际占用计算，空闲等待不计入
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/cpu-burner"))
+            .json(&json!({"input": {"cpu_ms": 300, "idle_ms": 500}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["output"], "done", "{body}");
+    let timing = &body["data"]["timing"];
+    let cpu_ms = timing["cpu_ms"].as_u64().unwrap();
+    assert!(cpu_ms >= 300, "{timing}");
+    assert!(cpu_ms < timing["total_ms"].as_u64().unwrap(), "{timing}");
+
+    // 超出上限的调用按资源限制失败
+    let response = client
+        .post(server.url("/v1/invoke/cpu-burner"))
+        .json(&json!({"input": {"cpu_ms": 10_000}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-flux-error-origin"], "limit");
+    let body: Value = response.json().await.unwrap();
+    assert!(
+        body.to_string().contains("CPU time limit exceeded"),
+        "{body}"
+    );
+
+    let (status, body) = send(client.get(server.url("/v1/functions/cpu-burner/report"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(
+        body["data"]["summary"]["cpu_ms"].as_u64().unwrap() >= 2300,
+        "{body}"
+    );
+    assert!(body["data"]["phases"]["cpu_p95_ms"].is_number(), "{body}");
+    let (status, body) = send(client.get(server.url("/v1/performance/top?metric=cpu_time"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"][0]["function"], "cpu-burner", "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_disk_write_limit_fails_invocation_and_is_recorded() {
+    if !has_runtime("python3") {
+        return;
+    }
+    let server = start().await;
+    let client = Client::new();
+    let code = "import time\n\ndef handler(input):\n    with open('out.bin', 'wb') as f:\n        f.write(b'\\0' * input['size'])\n        f.flush()\n    time.sleep(input.get('hold', 0))\n    return input['size']\n";
+    let registration = json!({"name": "disk-writer", "code": code, "max_disk_write_mb": 1});
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let response = client
+        .post(server.url("/v1/invoke/disk-writer"))
+        .json(&json!({"input": {"size": 4 * 1048576, "hold": 30}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-flux-error-origin"], "limit");
+    let body: Value = response.json().await.unwrap();
+    assert!(
+        body.to_string().contains("bytes written, limit is 1MB"),
+        "{body}"
+    );
+
+    // 上限内的调用成功，执行记录带磁盘用量
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/disk-writer"))
+            .header("x-request-id", "disk-1")
+            .json(&json!({"input": {"size": 102400}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["output"], 102400, "{body}");
+    let (status, body) = send(client.post(server.url("/v1/executions/disk-1/replay"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let disk = &body["data"]["original"]["disk"];
+    assert!(disk["write_bytes"].as_u64().unwrap() >= 102400, "{body}");
+    assert!(disk["peak_dir_bytes"].as_u64().unwrap() >= 102400, "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_console_round_trips_without_registering() {
+    let server = start().await;
+    let client = Client::new();
+    let mut cases = vec![];
+    if has_runtime("node") {
+        cases.push((
+            "javascript",
+            "function handler(input) { console.error('adding', input.a); return {sum: input.a + input.b}; }",
+            json!({"sum": 5}),
+            "adding 2",
+        ));
+    }
+    if has_runtime("python3") {
+        cases.push((
+            "python",
+            "import sys\n\ndef handler(input):\n    sys.stderr.write('adding %d\\n' % input['a'])\n    return {'sum': input['a'] + input['b']}\n",
+            json!({"sum": 5}),
+            "adding 2",
+        ));
+    }
+    let (_, before) = send(client.get(server.url("/v1/functions"))).await;
+    for (script_type, code, expected, logged) in &cases {
+        let request = json!({"code": code, "script_type": script_type, "input": {"a": 2, "b": 3}});
+        let (status, body) = send(
+            client
+                .post(server.url("/v1/console/execute"))
+                .json(&request),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let execution = &body["data"];
+        assert_eq!(execution["script_type"], *script_type, "{body}");
+        assert_eq!(execution["response"]["status"], "Success", "{body}");
+        assert_eq!(execution["response"]["output"], *expected, "{body}");
+        assert!(execution["response"]["timing"].is_object(), "{body}");
+        assert!(
+            execution["stderr"].as_str().unwrap().contains(logged),
+            "{body}"
+        );
+        assert!(!execution["logs"].as_array().unwrap().is_empty(), "{body}");
+    }
+
+    // 声明的脚本类型与代码不符、代码过大时拒绝
+    let request = json!({"code": "return a + b", "script_type": "python"});
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/console/execute"))
+            .json(&request),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    let request = json!({"code": format!("return a + {}", "1".repeat(70_000))});
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/console/execute"))
+            .json(&request),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE, "{body}");
+
+    // 控制台执行不注册函数，只计入控制台的用量
+    let (_, after) = send(client.get(server.url("/v1/functions"))).await;
+    assert_eq!(before["data"], after["data"]);
+    let (status, body) = send(client.get(server.url("/v1/console/stats"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["executions"], cases.len(), "{body}");
+    assert_eq!(body["data"]["succeeded"], cases.len(), "{body}");
+    assert_eq!(body["data"]["rejected"], 2, "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_console_promote_registers_an_invocable_function() {
+    let server = start().await;
+    let client = Client::new();
+    let code = if has_runtime("python3") {
+        "def handler(input):\n    return {'greeting': 'hi ' + input['name']}\n"
+    } else {
+        "return \"hi \" + name"
+    };
+    let request = json!({"code": code, "input": {"name": "flux"}});
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/console/execute"))
+            .json(&request),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let console_id = body["data"]["console_id"].as_str().unwrap().to_string();
+    let output = body["data"]["response"]["output"].clone();
+
+    // 其他调用方不能提升这次执行
+    let promote = json!({"console_id": console_id, "name": "console-greeter", "description": "from the console"});
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/console/promote"))
+            .header("x-real-ip", "10.1.2.3")
+            .json(&promote),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/console/promote"))
+            .json(&promote),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["name"], "console-greeter", "{body}");
+    let (status, body) = send(client.get(server.url("/v1/functions/console-greeter"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["code"], code, "{body}");
+    assert_eq!(body["data"]["description"], "from the console", "{body}");
+
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/console-greeter"))
+            .json(&json!({"input": {"name": "flux"}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["output"], output, "{body}");
+
+    // 同名函数已存在；未知的执行 ID
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/console/promote"))
+            .json(&promote),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    let unknown = json!({"console_id": "missing", "name": "console-other"});
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/console/promote"))
+            .json(&unknown),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+    let (_, body) = send(client.get(server.url("/v1/console/stats"))).await;
+    assert_eq!(body["data"]["promoted"], 1, "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_delete_drains_running_invocations() {
+    let client = Client::new();
+    let mut config = FluxConfig::default();
+    config.chaos.enabled = true;
+    let server = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    // 故障注入的延迟让调用长时间处于执行中
+    for (name, delay_ms) in [("slow-echo", 800), ("stuck-echo", 60_000)] {
+        let registration = json!({"name": name, "code": "return input", "timeout_ms": 120_000});
+        let (status, body) =
+            send(client.post(server.url("/v1/functions")).json(&registration)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let rule = json!({"match": {"function": name}, "action": "delay", "delay_ms": delay_ms});
+        let (status, body) =
+            send(client.post(server.url("/v1/admin/chaos/rules")).json(&rule)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+
+    let invoke = |name: &str| {
+        let request = client
+            .post(server.url(&format!("/v1/invoke/{name}")))
+            .json(&json!({"input": {"a": 1}}));
+        tokio::spawn(send(request))
+    };
+    let lifecycle = |name: &str| {
+        let request = client.get(server.url(&format!("/v1/functions/{name}")));
+        async move { send(request).await.1["data"]["lifecycle"].clone() }
+    };
+    let wait_for = async |name: &str, field: &str, expected: Value| {
+        for _ in 0..500 {
+            if lifecycle(name).await[field] == expected {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("{name} never reported {field} = {expected}");
+    };
+
+    // 不强制删除：等待在途调用完成，期间拒绝新调用
+    let invocation = invoke("slow-echo");
+    wait_for("slow-echo", "in_flight", json!(1)).await;
+    let deletion = tokio::spawn(send(client.delete(server.url("/v1/functions/slow-echo"))));
+    wait_for("slow-echo", "state", json!("draining")).await;
+    assert_eq!(lifecycle("slow-echo").await["in_flight"], 1);
+    let (status, body) = invoke("slow-echo").await.unwrap();
+    assert_eq!(status, StatusCode::GONE, "{body}");
+    assert_eq!(body["error"]["code"], "gone", "{body}");
+
+    let (status, body) = invocation.await.unwrap();
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["status"], "Success", "{body}");
+    let (status, body) = deletion.await.unwrap();
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, _) = send(client.get(server.url("/v1/functions/slow-echo"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 强制删除：立即取消在途调用
+    let invocation = invoke("stuck-echo");
+    wait_for("stuck-echo", "in_flight", json!(1)).await;
+    let (status, body) =
+        send(client.delete(server.url("/v1/functions/stuck-echo?force=true"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, body) = tokio::time::timeout(Duration::from_secs(10), invocation)
+        .await
+        .expect("cancelled invocation returns promptly")
+        .unwrap();
+    assert_eq!(status, StatusCode::OK, "{body}");
+    // 兼容格式下取消表示为 Error 状态
+    assert_eq!(
+        body["data"]["status"],
+        json!({"Error": "Execution cancelled"}),
+        "{body}"
+    );
+    let (status, _) = send(client.get(server.url("/v1/functions/stuck-echo"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_global_invocation_limit_sheds_load() {
+    if !has_runtime("python3") {
+        return;
+    }
+    let mut config = FluxConfig::default();
+    config.limits.max_concurrent_invocations = 10;
+    config.limits.queue_capacity = 50;
+    let server = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let client = Client::new();
+    // 每个调用在沙箱中启动一个解释器进程并执行 200ms
+    let code = "import time\n\ndef handler(input):\n    time.sleep(0.2)\n    return input\n";
+    let registration = json!({"name": "nap", "code": code, "timeout_ms": 10_000});
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let sandbox = server
+        .schedulers()
+        .resolve("nap")
+        .await
+        .scheduler
+        .runtime()
+        .sandbox()
+        .unwrap()
+        .clone();
+    assert!(sandbox.get_executor_stats().max_concurrent > 10);
+
+    // 压测期间持续采样沙箱中的子进程数和执行中的沙箱执行数
+    let sampling = Arc::new(std::sync::atomic::AtomicBool::new(true));
+    let sampler = {
+        let sampling = sampling.clone();
+        tokio::spawn(async move {
+            let (mut peak_processes, mut peak_running) = (0, 0);
+            while sampling.load(std::sync::atomic::Ordering::Relaxed) {
+                peak_processes = peak_processes.max(sandbox.get_active_process_count().await);
+                peak_running = peak_running.max(sandbox.get_executor_stats().running);
+                tokio::time::sleep(Duration::from_millis(2)).await;
+            }
+            (peak_processes, peak_running)
+        })
+    };
+
+    let requests: Vec<_> = (0..200)
+        .map(|i| {
+            let request = client
+                .post(server.url("/v1/invoke/nap"))
+                .json(&json!({"input": {"i": i}}))
+                .send();
+            tokio::spawn(async move {
+                let response = request.await.unwrap();
+                let retry_after = response.headers().get("retry-after").cloned();
+                (response.status(), retry_after)
+            })
+        })
+        .collect();
+    let (mut succeeded, mut shed) = (0, 0);
+    for request in requests {
+        match request.await.unwrap() {
+            (StatusCode::OK, _) => succeeded += 1,
+            (StatusCode::SERVICE_UNAVAILABLE, Some(retry_after)) => {
+                assert_eq!(retry_after, "1");
+                shed += 1;
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+    sampling.store(false, std::sync::atomic::Ordering::Relaxed);
+    let (peak_processes, peak_running) = sampler.await.unwrap();
+    assert!(peak_processes <= 10, "{peak_processes} sandbox processes");
+    assert!(peak_running <= 10, "{peak_running} sandbox executions");
+    assert!(peak_processes > 1, "invocations did not run concurrently");
+    assert_eq!(succeeded + shed, 200);
+    assert!(succeeded >= 10, "{succeeded} succeeded");
+    assert!(shed > 0, "no invocation was shed");
+
+    let (_, body) = send(client.get(server.url("/v1/status"))).await;
+    let invocations = &body["data"]["invocations"];
+    assert_eq!(invocations["max_concurrent"], 10, "{body}");
+    assert_eq!(invocations["peak_running"], 10, "{body}");
+    assert_eq!(invocations["queued"], 0, "{body}");
+    let shed_total = invocations["shed_queue_full"].as_u64().unwrap()
+        + invocations["shed_timeout"].as_u64().unwrap();
+    assert_eq!(shed_total, shed, "{body}");
+    assert!(invocations["wait_p95_ms"].as_f64().unwrap() > 0.0, "{body}");
+    let (_, body) = send(client.get(server.url("/v1/performance/stats"))).await;
+    assert_eq!(
+        body["data"]["invocation_limit"]["admitted"], succeeded,
+        "{body}"
+    );
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_custom_http_routes() {
+    let server = start().await;
+    let client = Client::new();
+    let registration = json!({"name": "get-user", "code": "return id"});
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let binding =
+        json!({"method": "get", "path_pattern": "/api/users/:id", "function": "get-user"});
+    let (status, body) = send(client.post(server.url("/v1/routes")).json(&binding)).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(body["data"]["method"], "GET", "{body}");
+
+    // 路径参数合并到输入顶层，函数输出直接作为响应
+    let (status, body) = send(client.get(server.url("/api/users/42?verbose=1"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["result"], "42", "{body}");
+
+    // 路径匹配但方法不匹配
+    let response = client
+        .post(server.url("/api/users/42"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()["allow"], "GET");
+    let (status, _) = send(client.get(server.url("/api/groups/42"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 重叠的模式、遮蔽内置路由和不存在的函数在注册时被拒绝
+    let overlapping =
+        json!({"method": "GET", "path_pattern": "/api/users/me", "function": "get-user"});
+    let (status, body) = send(client.post(server.url("/v1/routes")).json(&overlapping)).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    let shadowing =
+        json!({"method": "GET", "path_pattern": "/functions/:name", "function": "get-user"});
+    let (status, body) = send(client.post(server.url("/v1/routes")).json(&shadowing)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    let missing = json!({"method": "GET", "path_pattern": "/api/items/:id", "function": "missing"});
+    let (status, body) = send(client.post(server.url("/v1/routes")).json(&missing)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+
+    // 绑定可以单独删除，立即生效
+    let posts = json!({"method": "GET", "path_pattern": "/api/posts/:id", "function": "get-user"});
+    let (status, body) = send(client.post(server.url("/v1/routes")).json(&posts)).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+    let (_, body) = send(client.get(server.url("/v1/routes"))).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 2, "{body}");
+    let url = server.url(&format!("/v1/routes/{id}"));
+    let (status, _) = send(client.delete(&url)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(client.delete(&url)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(client.get(server.url("/api/posts/1"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 删除函数时移除其绑定
+    let (status, _) = send(client.delete(server.url("/v1/functions/get-user"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send(client.get(server.url("/v1/routes"))).await;
+    assert_eq!(body["data"], json!([]), "{body}");
+    let (status, _) = send(client.get(server.url("/api/users/42"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_function_canaries() {
+    let mut config = FluxConfig::default();
+    config.canaries.tick_ms = 20;
+    let server = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let client = Client::new();
+    let registration = json!({
+        "name": "canary-sum",
+        "code": "return a + b",
+        "labels": {"critical": "true"},
+        "canary": {"input": {"a": 1, "b": 2}, "expected_output": {"result": 3}},
+    });
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let url = server.url("/v1/functions/canary-sum/canary");
+    let wait_for = |expected: &'static str| {
+        let client = client.clone();
+        let url = url.clone();
+        async move {
+            let mut report = Value::Null;
+            for _ in 0..200 {
+                let (_, body) = send(client.get(&url)).await;
+                report = body["data"].clone();
+                if report["health"]["status"] == expected {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            report
+        }
+    };
+    let report = wait_for("passing").await;
+    assert_eq!(report["health"]["status"], "passing", "{report}");
+    assert_eq!(report["critical"], true, "{report}");
+    let (status, body) = send(client.get(server.url("/v1/health/ready"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["passing"], 1, "{body}");
+
+    // 探测调用不计入调用统计
+    let (_, body) = send(client.get(server.url("/v1/performance/stats"))).await;
+    assert_eq!(body["data"]["global_stats"]["total_requests"], 0, "{body}");
+    assert!(
+        body["data"]["canary_executions"]["canary-sum"].as_u64() >= Some(1),
+        "{body}"
+    );
+
+    // 期望输出不符时关键函数的探测失败，就绪检查返回 503
+    let canary =
+        json!({"interval_secs": 1, "input": {"a": 1, "b": 2}, "expected_output": {"result": 4}});
+    let (status, body) = send(client.put(&url).json(&canary)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let report = wait_for("failing").await;
+    assert_eq!(report["health"]["status"], "failing", "{report}");
+    let (status, body) = send(client.get(server.url("/v1/health/ready"))).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+    assert_eq!(
+        body["error"]["details"]["failing"][0]["function"], "canary-sum",
+        "{body}"
+    );
+
+    let (status, body) = send(client.put(&url).json(&json!({"interval_secs": 0}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    let (status, body) = send(client.delete(&url)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["data"].get("config").is_none(), "{body}");
+    let (status, _) = send(client.get(server.url("/v1/health/ready"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(client.get(server.url("/v1/functions/missing/canary"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+/// 内存中的 OCI 仓库：`/token` 按 Basic 凭据签发令牌，其他路径要求 Bearer 令牌
+struct OciRegistryFixture {
+    addr: std::net::SocketAddr,
+    blob_requests: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl OciRegistryFixture {
+    const TOKEN: &'static str = "fixture-token";
+
+    async fn start(routes: std::collections::HashMap<String, Vec<u8>>) -> Self {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let blob_requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let routes = Arc::new(routes);
+        let counter = blob_requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let routes = routes.clone();
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let mut head = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                        let n = stream.read(&mut buf).await.unwrap();
+                        if n == 0 {
+                            return;
+                        }
+                        head.extend_from_slice(&buf[..n]);
+                    }
+                    let head = String::from_utf8_lossy(&head).to_lowercase();
+                    let path = head.split_whitespace().nth(1).unwrap().to_string();
+                    let (status, body) = if path.starts_with("/token") {
+                        // ci:secret 的 Basic 凭据
+                        if head.contains("authorization: basic y2k6c2vjcmv0") {
+                            (
+                                "200 OK",
+                                format!(r#"{{"token":"{}"}}"#, Self::TOKEN).into_bytes(),
+                            )
+                        } else {
+                            ("401 Unauthorized", Vec::new())
+                        }
+                    } else if !head.contains(&format!("authorization: bearer {}", Self::TOKEN)) {
+                        ("401 Unauthorized", Vec::new())
+                    } else {
+                        if path.contains("/blobs/") {
+                            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        }
+                        match routes.get(&path) {
+                            Some(body) => ("200 OK", body.clone()),
+                            None => ("404 Not Found", Vec::new()),
+                        }
+                    };
+                    let challenge = format!(
+                        "www-authenticate: Bearer realm=\"http://{addr}/token\",service=\"fixture\"\r\n"
+                    );
+                    let header = format!(
+                        "HTTP/1.1 {status}\r\ncontent-length: {}\r\n{}connection: close\r\n\r\n",
+                        body.len(),
+                        if status.starts_with("401") {
+                            challenge.as_str()
+                        } else {
+                            ""
+                        }
+                    );
+                    let _ = stream.write_all(header.as_bytes()).await;
+                    let _ = stream.write_all(&body).await;
+                });
+            }
+        });
+        Self {
+            addr,
+            blob_requests,
+        }
+    }
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    use sha2::Digest;
+    format!("sha256:{:x}", sha2::Sha256::digest(bytes))
+}
+
+fn tar_gz(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        Vec::new(),
+        flate2::Compression::default(),
+    ));
+    for (path, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, content.as_bytes())
+            .unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// 以 `tag` 发布一个单层制品；`served` 不为空时仓库返回与清单摘要不符的层内容
+fn publish(
+    routes: &mut std::collections::HashMap<String, Vec<u8>>,
+    tag: &str,
+    layer: Vec<u8>,
+    served: Option<Vec<u8>>,
+) {
+    let digest = sha256(&layer);
+    let manifest = json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.manifest.v1+json",
+        "config": {"mediaType": "application/vnd.oci.empty.v1+json", "digest": sha256(b"{}"), "size": 2},
+        "layers": [{
+            "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+            "digest": digest,
+            "size": layer.len(),
+        }],
+    });
+    routes.insert(
+        format!("/v2/org/fns/manifests/{tag}"),
+        serde_json::to_vec(&manifest).unwrap(),
+    );
+    routes.insert(
+        format!("/v2/org/fns/blobs/{digest}"),
+        served.unwrap_or(layer),
+    );
+}
+
+#[tokio::test]
+async fn test_load_functions_from_oci_artifact() {
+    let mut routes = std::collections::HashMap::new();
+    let functions = tar_gz(&[
+        (
+            "hello.rs",
+            "fn hello() -> String { return \"hello\".to_string(); }",
+        ),
+        (
+            "greet/flux.toml",
+            "name = \"greet\"\ndescription = \"Greets\"\n",
+        ),
+        (
+            "greet/main.rs",
+            "fn greet() -> String { return \"hi\".to_string(); }",
+        ),
+    ]);
+    publish(&mut routes, "v1", functions, None);
+    publish(
+        &mut routes,
+        "tampered",
+        tar_gz(&[("tampered.rs", "fn tampered() {}")]),
+        Some(tar_gz(&[("evil.rs", "fn evil() {}")])),
+    );
+    publish(
+        &mut routes,
+        "docs",
+        tar_gz(&[("README.md", "no functions")]),
+        None,
+    );
+    let registry = OciRegistryFixture::start(routes).await;
+
+    let cache = tempfile::tempdir().unwrap();
+    let mut config = FluxConfig::default();
+    config.oci.cache_dir = cache.path().to_path_buf();
+    // 目录中的 Rust 函数需要能执行 Rust 的调度器
+    let scheduler = SimpleScheduler::new()
+        .with_routing(&RoutingConfig::default())
+        .unwrap();
+    let server = FluxServer::new()
+        .with_config(config)
+        .with_scheduler(Arc::new(scheduler))
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let client = Client::new();
+    let load = |tag: &str| {
+        client.post(server.url("/v1/load/oci")).json(&json!({
+            "reference": format!("{}/org/fns:{tag}", registry.addr),
+            "auth": {"username": "ci", "password": "secret"},
+        }))
+    };
+
+    let (status, body) = send(load("v1")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["cached"], false, "{body}");
+    assert_eq!(body["data"]["summary"]["registered"], 2, "{body}");
+    assert!(!body.to_string().contains("secret"), "{body}");
+    assert!(
+        !body.to_string().contains(OciRegistryFixture::TOKEN),
+        "{body}"
+    );
+    let (status, body) = send(client.get(server.url("/v1/functions/greet"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["description"], "Greets", "{body}");
+
+    // 同一制品再次加载时使用缓存，不再下载函数层
+    let (status, body) = send(load("v1")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["cached"], true, "{body}");
+    assert_eq!(body["data"]["summary"]["skipped_duplicate"], 2, "{body}");
+    assert_eq!(
+        registry
+            .blob_requests
+            .load(std::sync::atomic::Ordering::SeqCst),
+        1
+    );
+
+    let (status, body) = send(load("tampered")).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY, "{body}");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Digest mismatch"),
+        "{body}"
+    );
+    let (status, _) = send(client.get(server.url("/v1/functions/evil"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send(load("docs")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(body.to_string().contains("flux.toml"), "{body}");
+
+    let (status, body) = send(load("missing")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+
+    // 凭据错误时令牌服务拒绝，错误信息中不包含凭据
+    let (status, body) = send(client.post(server.url("/v1/load/oci")).json(&json!({
+        "reference": format!("{}/org/fns:v1", registry.addr),
+        "auth": {"username": "ci", "password": "wrong-password"},
+    })))
+    .await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY, "{body}");
+    assert!(!body.to_string().contains("wrong-password"), "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_updated_code_reaches_next_invocation() {
+    // 表达式在进程内求值；有 python3 时 Python 函数由外部解释器执行，有 cargo 时 Rust 函数由路由后端编译执行
+    let compiled = has_runtime("cargo");
+    let scheduler = Arc::new(
+        SimpleScheduler::new()
+            .with_routing(&RoutingConfig::default())
+            .unwrap(),
+    );
+    let mut functions = vec![("expr-marker", "return \"{marker}\"")];
+    if has_runtime("python3") {
+        functions.push((
+            "py-marker",
+            "def handler(input):\n    return {'marker': '{marker}'}\n",
+        ));
+    }
+    if compiled {
+        functions.push((
+            "rs-marker",
+            "fn handler(tag: String) -> anyhow::Result<String> {\n    Ok(format!(\"{marker} {}\", tag))\n}\n",
+        ));
+    }
+    let code = |template: &str, marker: &str| template.replace("{marker}", marker);
+    for (name, template) in &functions {
+        let mut function = FunctionMetadata::new(name.to_string(), code(template, "v1"));
+        function.timeout_ms = 300_000;
+        if *name == "rs-marker" {
+            function.parameters = vec![FunctionParameter {
+                name: "tag".to_string(),
+                param_type: "string".to_string(),
+                description: None,
+                required: true,
+                default_value: None,
+            }];
+            function.return_type = "string".to_string();
+        }
+        scheduler.registry().register(function).await.unwrap();
+    }
+
+    let server = FluxServer::new()
+        .with_scheduler(scheduler.clone())
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let client = Client::new();
+    for (name, template) in &functions {
+        for marker in ["v1", "v2", "v3"] {
+            if marker != "v1" {
+                // 直接替换注册表中的同一版本，不经过调度器也不重启服务
+                let mut function = scheduler.registry().get(name).await.unwrap();
+                function.code = code(template, marker);
+                scheduler.registry().upsert(function).await.unwrap();
+            }
+            let (status, body) = send(
+                client
+                    .post(server.url(&format!("/v1/invoke/{name}")))
+                    .json(&json!({"input": {"tag": "test"}})),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK, "{body}");
+            assert_eq!(body["data"]["status"], "Success", "{body}");
+            let output = body["data"]["output"].to_string();
+            assert!(output.contains(marker), "{name} {marker}: {body}");
+        }
+    }
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_async_invoke_with_long_poll() {
+    let server = start().await;
+    let client = Client::new();
+    let registration = json!({"name": "sum", "code": "return a + b"});
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/sum/async"))
+            .json(&json!({"input": {"a": 1, "b": 2}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+
+    // 长轮询直到任务完成
+    let (status, body) =
+        send(client.get(server.url(&format!("/v1/jobs/{id}?wait_ms=10000")))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["status"], "completed", "{body}");
+    assert_eq!(body["data"]["response"]["output"]["result"], 3, "{body}");
+    assert_eq!(
+        body["data"]["response"]["request_id"],
+        id.as_str(),
+        "{body}"
+    );
+
+    let (status, _) = send(client.get(server.url("/v1/jobs/missing"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(
+        client
+            .post(server.url("/v1/invoke/missing/async"))
+            .json(&json!({"input": {}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/sum/async"))
+            .json(&json!({"input": {"a": 1, "b": 2}, "callback_url": "not a url"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 24)]
+async fn test_rolling_upgrade_keeps_in_flight_invocations() {
+    if !has_runtime("cargo") {
+        return;
+    }
+    // 宽限期为 0：旧库文件只靠执行中调用持有的句柄保留
+    let cache_dir = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(
+        SimpleRuntime::new_with_compiler_config(CompilerConfig {
+            cache_dir: cache_dir.path().to_path_buf(),
+            compile_timeout_secs: 300,
+            artifact_grace_secs: 0,
+            ..Default::default()
+        })
+        .unwrap(),
+    );
+    let compiler = runtime.compiler().unwrap().clone();
+    let scheduler = Arc::new(SimpleScheduler::with_runtime(runtime));
+    // 处理函数阻塞到闸门文件出现为止，由测试决定调用何时结束
+    let gate = cache_dir.path().join("gate");
+    let code = |marker: &str| {
+        format!(
+            "fn handler(tag: String) -> anyhow::Result<String> {{\n    while !std::path::Path::new({gate:?}).exists() {{\n        std::thread::sleep(std::time::Duration::from_millis(10));\n    }}\n    Ok(format!(\"{marker} {{}}\", tag))\n}}\n"
+        )
+    };
+    let mut function = FunctionMetadata::new("rolling".to_string(), code("v1"));
+    function.timeout_ms = 300_000;
+    function.parameters = vec![FunctionParameter {
+        name: "tag".to_string(),
+        param_type: "string".to_string(),
+        description: None,
+        required: true,
+        default_value: None,
+    }];
+    function.return_type = "string".to_string();
+    scheduler.registry().register(function).await.unwrap();
+
+    let server = FluxServer::new()
+        .with_scheduler(scheduler.clone())
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let url = server.url("/v1/invoke/rolling");
+    let invoke = |client: Client, url: String| async move {
+        send(client.post(url).json(&json!({"input": {"tag": "test"}}))).await
+    };
+    let client = Client::new();
+
+    // 预热：编译 v1（闸门打开）
+    std::fs::write(&gate, b"").unwrap();
+    let (status, body) = invoke(client.clone(), url.clone()).await;
+    assert_eq!(body["data"]["status"], "Success", "{status} {body}");
+    std::fs::remove_file(&gate).unwrap();
+    let v1 = compiler.current_artifact("rolling").unwrap();
+    let v1_library = std::fs::read_dir(cache_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_string_lossy().contains(&v1.1))
+        .unwrap();
+
+    let in_flight: Vec<_> = (0..20)
+        .map(|_| tokio::spawn(invoke(client.clone(), url.clone())))
+        .collect();
+    // 闸门关闭时调用不会结束，等到所有调用都持有 v1 的库句柄
+    let deadline = std::time::Instant::now() + Duration::from_secs(120);
+    while compiler.artifact_holders(&v1) < in_flight.len() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "only {} of {} invocations hold v1",
+            compiler.artifact_holders(&v1),
+            in_flight.len()
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    // 调用执行中更新代码：旧产物退役，但执行中的调用仍持有其库句柄
+    let mut function = scheduler.registry().get("rolling").await.unwrap();
+    function.code = code("v2");
+    scheduler.registry().upsert(function).await.unwrap();
+    scheduler.apply_registry_changes().await;
+    assert!(v1_library.exists());
+    assert_eq!(compiler.artifact_holders(&v1), in_flight.len());
+
+    // 打开闸门，执行中的调用完成
+    std::fs::write(&gate, b"").unwrap();
+    for task in in_flight {
+        let (status, body) = task.await.unwrap();
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["data"]["status"], "Success", "{body}");
+        assert_eq!(body["data"]["output"], "v1 test", "{body}");
+    }
+
+    // 更新后的调用执行新代码，当前产物切换到新的编译键
+    let (status, body) = invoke(client.clone(), url.clone()).await;
+    assert_eq!(body["data"]["status"], "Success", "{status} {body}");
+    assert_eq!(body["data"]["output"], "v2 test", "{body}");
+    let v2 = compiler.current_artifact("rolling").unwrap();
+    assert_ne!(v1, v2);
+
+    // 句柄全部释放后旧库文件才被删除
+    compiler.sweep_retired();
+    assert!(!v1_library.exists());
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_invocation_sessions() {
+    let server = start().await;
+    let client = Client::new();
+    let registration = json!({"name": "sum", "code": "return a + b"});
+    let (status, _) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 请求体和请求头都可以给出会话 ID
+    for b in 1..=3 {
+        let (status, body) = send(
+            client
+                .post(server.url("/v1/invoke/sum"))
+                .json(&json!({"input": {"a": 1, "b": b}, "session_id": "notebook-1"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/sum"))
+            .header("X-Flux-Session-Id", "notebook-1")
+            .json(&json!({"input": {"a": 1}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, _) = send(
+        client
+            .post(server.url("/v1/invoke/sum"))
+            .header("X-Flux-Session-Id", "notebook-2")
+            .json(&json!({"input": {"a": 5, "b": 5}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send(client.get(server.url("/v1/sessions/notebook-1"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["invocations"], 4, "{body}");
+    assert_eq!(body["data"]["errors"], 1, "{body}");
+    assert_eq!(body["data"]["retained_executions"], 4, "{body}");
+
+    // 最新的调用在前，会话之间互不可见
+    let (status, body) =
+        send(client.get(server.url("/v1/sessions/notebook-1/executions?offset=1&limit=2"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let executions = body["data"]["executions"].as_array().unwrap();
+    assert_eq!(executions.len(), 2, "{body}");
+    assert_eq!(executions[0]["function"], "sum");
+    assert_eq!(executions[0]["input"], json!({"a": 1, "b": 3}), "{body}");
+    assert_eq!(executions[0]["output"]["result"], 4, "{body}");
+    assert_eq!(executions[1]["input"], json!({"a": 1, "b": 2}), "{body}");
+    let (_, body) = send(client.get(server.url("/v1/sessions/notebook-2/executions"))).await;
+    assert_eq!(body["data"]["executions"].as_array().unwrap().len(), 1);
+
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/sum"))
+            .header("X-Flux-Session-Id", "bad id!")
+            .json(&json!({"input": {"a": 1, "b": 1}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    let (status, _) = send(client.delete(server.url("/v1/sessions/notebook-1"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(client.get(server.url("/v1/sessions/notebook-1"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(client.delete(server.url("/v1/sessions/notebook-1"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_config_reload_applies_sandbox_memory_limit() {
+    let client = Client::new();
+    // 没有配置文件时无法重新加载
+    let server = start().await;
+    let (status, body) = send(client.post(server.url("/v1/admin/config/reload"))).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    server.shutdown().await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("flux-server.toml");
+    std::fs::write(&path, "[sandbox]\nmax_memory_mb = 512\n").unwrap();
+    let server = FluxServer::new()
+        .with_config(FluxConfig::from_file(&path).unwrap())
+        .with_config_path(&path)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let enforced = has_runtime("python3")
+        && flux::runtime::platform::ResourceMonitoring::current().is_supported();
+    let registration = json!({
+        "name": "hungry",
+        "files": [{
+            "path": "main.py",
+            "content": "import time\n\ndef handler(input):\n    data = b'x' * (128 * 1024 * 1024)\n    time.sleep(1)\n    return {'size': len(data)}\n",
+        }],
+        "entrypoint": "main.py",
+    });
+    if enforced {
+        let (status, body) =
+            send(client.post(server.url("/v1/functions")).json(&registration)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let (_, body) = send(
+            client
+                .post(server.url("/v1/invoke/hungry"))
+                .json(&json!({"input": {}})),
+        )
+        .await;
+        assert_eq!(body["data"]["status"], "Success", "{body}");
+    }
+
+    // 校验失败时不应用任何改动
+    std::fs::write(&path, "[sandbox]\nmax_memory_mb = 0\n").unwrap();
+    let (status, body) = send(client.post(server.url("/v1/admin/config/reload"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    std::fs::write(
+        &path,
+        "status_format = \"typed\"\n[sandbox]\nmax_memory_mb = 32\n[limits]\nqueue_capacity = 10\n",
+    )
+    .unwrap();
+    let (status, body) = send(client.post(server.url("/v1/admin/config/reload"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let applied = body["data"]["applied"].as_array().unwrap();
+    assert!(
+        applied.contains(&json!({"field": "sandbox.max_memory_mb", "old": 512, "new": 32})),
+        "{body}"
+    );
+    assert!(
+        applied.contains(&json!({"field": "limits.queue_capacity", "old": 1024, "new": 10})),
+        "{body}"
+    );
+    assert_eq!(body["data"]["requires_restart"], json!(["status_format"]));
+    let limits = server.schedulers().invocation_limiter().config();
+    assert_eq!(limits.queue_capacity, 10);
+
+    // 下一次执行按新的内存上限终止
+    if enforced {
+        let (_, body) = send(
+            client
+                .post(server.url("/v1/invoke/hungry"))
+                .json(&json!({"input": {}})),
+        )
+        .await;
+        assert_ne!(body["data"]["status"], "Success", "{body}");
+        assert!(body.to_string().contains("Memory"), "{body}");
+    }
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_scratch_artifacts() {
+    if !has_runtime("python3") {
+        return;
+    }
+    let server = start().await;
+    let client = Client::new();
+    let code = "import os\n\ndef handler(input, context):\n    path = os.path.join(os.environ['FLUX_SCRATCH_DIR'], 'out.bin')\n    with open(path, 'wb') as f:\n        f.write(b'\\x89PNG\\r\\n\\x1a\\n' + b'\\0' * (input['size'] - 8))\n    return context['scratch_dir'] == os.environ['FLUX_SCRATCH_DIR']\n";
+    for registration in [
+        json!({"name": "keep-scratch", "code": code, "persist_scratch": true}),
+        json!({"name": "small-scratch", "code": code, "scratch_limit_mb": 1}),
+    ] {
+        let (status, body) =
+            send(client.post(server.url("/v1/functions")).json(&registration)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/keep-scratch"))
+            .header("x-request-id", "scratch-1")
+            .json(&json!({"input": {"size": 1048576}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["output"], true, "{body}");
+
+    let (status, body) = send(client.get(server.url("/v1/executions/scratch-1/artifacts"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let files = body["data"]["files"].as_array().unwrap();
+    assert_eq!(files.len(), 1, "{body}");
+    assert_eq!(files[0]["path"], "out.bin");
+    assert_eq!(files[0]["size_bytes"], 1048576);
+    assert_eq!(files[0]["content_type"], "image/png");
+
+    let response = client
+        .get(server.url("/v1/executions/scratch-1/artifacts?path=out.bin"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(response.bytes().await.unwrap().len(), 1048576);
+
+    let (status, _) = send(
+        client.get(server.url("/v1/executions/scratch-1/artifacts?path=../scratch-1/out.bin")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(client.get(server.url("/v1/executions/unknown/artifacts"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 超出暂存上限的调用按资源限制失败，未保留的目录不能列出
+    let response = client
+        .post(server.url("/v1/invoke/small-scratch"))
+        .header("x-request-id", "scratch-2")
+        .json(&json!({"input": {"size": 4 * 1048576}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-flux-error-origin"], "limit");
+    let body: Value = response.json().await.unwrap();
+    assert_ne!(body["data"]["status"], "Success", "{body}");
+    assert!(body.to_string().contains("Scratch"), "{body}");
+    let (status, _) = send(client.get(server.url("/v1/executions/scratch-2/artifacts"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_function_test_cases() {
+    if !has_runtime("node") {
+        return;
+    }
+    let server = start().await;
+    let client = Client::new();
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&json!({
+        "name": "js-tested",
+        "files": [{
+            "path": "index.js",
+            "content": "function handler(input) { return {sum: input.a + input.b, tag: 'v1'}; }",
+        }],
+        "entrypoint": "index.js",
+        "tests": [
+            {
+                "name": "adds",
+                "input": {"a": 1, "b": 2},
+                "expected_output": {"sum": 3},
+                "output_match": "subset",
+            },
+            {
+                "name": "wrong",
+                "input": {"a": 2, "b": 2},
+                "expected_output": {"sum": 5, "tag": "v1"},
+            },
+        ],
+    })))
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, body) = send(client.post(server.url("/v1/functions/js-tested/test"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let report = &body["data"];
+    assert_eq!(report["total"], 2, "{body}");
+    assert_eq!(report["passed"], 1, "{body}");
+    assert_eq!(report["failed"], 1, "{body}");
+    let results = report["results"].as_array().unwrap();
+    assert_eq!(results[0]["name"], "adds");
+    assert_eq!(results[0]["passed"], true, "{body}");
+    let wrong = &results[1];
+    assert_eq!(wrong["passed"], false);
+    assert_eq!(wrong["actual_status"], "success");
+    assert_eq!(wrong["actual_output"]["sum"], 4);
+    assert_eq!(
+        wrong["diffs"],
+        json!([{"path": "/sum", "expected": 5, "actual": 4}]),
+        "{body}"
+    );
+    assert!(wrong["duration_ms"].is_u64());
+
+    let (status, body) =
+        send(client.post(server.url("/v1/functions/js-tested/test?cases=adds"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["total"], 1, "{body}");
+    let (status, _) =
+        send(client.post(server.url("/v1/functions/js-tested/test?cases=nope"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 测试调用单独计数，不计入调用统计
+    let (status, body) = send(client.get(server.url("/v1/performance/stats"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["test_executions"]["js-tested"], 3, "{body}");
+    assert_eq!(body["data"]["global_stats"]["total_requests"], 0, "{body}");
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_function_slo_burn_rate_alerts() {
+    let mut config = FluxConfig::default();
+    config.slo.tick_ms = 20;
+    let server = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind_ephemeral()
+        .await
+        .unwrap();
+    let client = Client::new();
+    let registration = json!({
+        "name": "slo-flaky",
+        "code": "return input.a.b",
+        "slo": {"availability": 0.995, "latency_p95_ms": 300, "window": "7d"},
+    });
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&registration)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // `input.a` 不存在时读取 `b` 失败
+    for input in
+        std::iter::repeat_n(json!({"a": {"b": 1}}), 10).chain(std::iter::repeat_n(json!({}), 10))
+    {
+        send(
+            client
+                .post(server.url("/v1/invoke/slo-flaky"))
+                .json(&json!({"input": input})),
+        )
+        .await;
+    }
+
+    let url = server.url("/v1/functions/slo-flaky/slo");
+    let mut report = Value::Null;
+    for _ in 0..200 {
+        let (_, body) = send(client.get(&url)).await;
+        report = body["data"].clone();
+        if report["burn_rates"][0]["alerting"] == true {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let compliance = &report["compliance"];
+    assert_eq!(compliance["invocations"], 20, "{report}");
+    assert_eq!(compliance["errors"], 10, "{report}");
+    assert_eq!(compliance["availability_met"], false, "{report}");
+    assert_eq!(compliance["latency_met"], true, "{report}");
+    // 7 天窗口超出 24 小时的保留时长
+    assert_eq!(compliance["window_degraded"], true, "{report}");
+    assert_eq!(compliance["effective_window_secs"], 86400, "{report}");
+    assert_eq!(report["burn_rates"][0]["window"], "fast", "{report}");
+    assert_eq!(report["burn_rates"][0]["alerting"], true, "{report}");
+
+    // 告警发布在生命周期事件流上
+    let (status, body) = send(client.get(server.url("/v1/instances/slo/events"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let events = body["data"].as_array().unwrap();
+    assert!(
+        events
+            .iter()
+            .any(|event| event["event_type"] == "SloBurnRateAlert"
+                && event["function_name"] == "slo-flaky"
+                && event["metadata"]["window"] == "fast"),
+        "{body}"
+    );
+
+    let (status, body) = send(client.put(&url).json(&json!({"availability": 1.0}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    let (status, body) = send(client.delete(&url)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["data"].get("compliance").is_none(), "{body}");
+    let (status, _) = send(client.get(server.url("/v1/functions/missing/slo"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_function_deprecation_and_sunset() {
+    let server = start().await;
+    let client = Client::new();
+    for (name, code) in [
+        ("dep-old", "return input.a + 1"),
+        ("dep-new", "return input.a * 10"),
+    ] {
+        let registration = json!({"name": name, "code": code});
+        let (status, body) =
+            send(client.post(server.url("/v1/functions")).json(&registration)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+    let deprecate_url = server.url("/v1/functions/dep-old/deprecate");
+    let deprecate = |on_sunset: &str, sunset_in: chrono::Duration| {
+        let request = json!({
+            "reason": "use dep-new",
+            "sunset_at": (chrono::Utc::now() + sunset_in).to_rfc3339(),
+            "replacement": "dep-new",
+            "on_sunset": on_sunset,
+        });
+        client.post(&deprecate_url).json(&request)
+    };
+    let invoke = |ip: &str| {
+        client
+            .post(server.url("/v1/invoke/dep-old"))
+            .header("x-real-ip", ip)
+            .json(&json!({"input": {"a": 2}}))
+    };
+
+    // 停用前照常执行，响应带弃用头和 meta.deprecated
+    let (status, body) = send(deprecate("reject", chrono::Duration::days(1))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["deprecation"]["on_sunset"], "reject", "{body}");
+    assert_eq!(body["data"]["sunset"], false, "{body}");
+    let response = invoke("10.0.0.1").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers().clone();
+    assert!(headers["deprecation"].to_str().unwrap().starts_with('@'));
+    assert!(headers["sunset"].to_str().unwrap().ends_with(" GMT"));
+    assert_eq!(
+        headers["link"],
+        "</v1/invoke/dep-new>; rel=\"successor-version\""
+    );
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["output"]["result"], 3, "{body}");
+    assert_eq!(
+        body["meta"]["deprecated"]["reason"], "use dep-new",
+        "{body}"
+    );
+    assert_eq!(
+        body["meta"]["deprecated"]["replacement"], "dep-new",
+        "{body}"
+    );
+    assert!(
+        body["meta"]["deprecated"]["sunset_at"].is_string(),
+        "{body}"
+    );
+    send(invoke("10.0.0.1")).await;
+    send(invoke("10.0.0.2")).await;
+
+    // 按弃用状态筛选函数列表
+    let (_, body) = send(client.get(server.url("/v1/functions?deprecated=true"))).await;
+    let names: Vec<_> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["name"].clone())
+        .collect();
+    assert_eq!(names, vec![json!("dep-old")], "{body}");
+    assert_eq!(
+        body["data"][0]["deprecation"]["replacement"], "dep-new",
+        "{body}"
+    );
+    let (_, body) = send(client.get(server.url("/v1/functions?deprecated=false"))).await;
+    let names: Vec<_> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["name"].clone())
+        .collect();
+    assert_eq!(names, vec![json!("dep-new")], "{body}");
+
+    // 停用后拒绝：410 并提示替代函数
+    let (status, body) = send(deprecate("reject", -chrono::Duration::days(1))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["sunset"], true, "{body}");
+    let (status, body) = send(invoke("10.0.0.2")).await;
+    assert_eq!(status, StatusCode::GONE, "{body}");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("use dep-new instead"),
+        "{body}"
+    );
+    assert_eq!(
+        body["meta"]["deprecated"]["replacement"], "dep-new",
+        "{body}"
+    );
+
+    // 停用后改调替代函数，响应标记改调
+    let (status, body) = send(deprecate("redirect", -chrono::Duration::days(1))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, body) = send(invoke("10.0.0.2")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["output"]["result"], 20, "{body}");
+    assert_eq!(body["data"]["redirected_from"], "dep-old", "{body}");
+
+    // 停用后只提示
+    let (status, body) = send(deprecate("warn", -chrono::Duration::days(1))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, body) = send(invoke("10.0.0.3")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["output"]["result"], 3, "{body}");
+    assert!(body["data"].get("redirected_from").is_none(), "{body}");
+    assert!(body["meta"]["deprecated"].is_object(), "{body}");
+
+    // 各调用方的调用次数（被拒绝和改调的调用同样计入原函数）
+    let (status, body) = send(client.get(&deprecate_url)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body["data"]["callers"],
+        json!({"ip:10.0.0.1": 2, "ip:10.0.0.2": 3, "ip:10.0.0.3": 1}),
+        "{body}"
+    );
+    let (_, body) = send(client.get(server.url("/v1/performance/stats"))).await;
+    assert_eq!(
+        body["data"]["deprecated_calls"]["dep-old"]["ip:10.0.0.2"], 3,
+        "{body}"
+    );
+    assert!(
+        body["data"]["deprecated_calls"].get("dep-new").is_none(),
+        "{body}"
+    );
+
+    // redirect 需要已注册的替代函数
+    let request = json!({
+        "reason": "gone",
+        "sunset_at": chrono::Utc::now().to_rfc3339(),
+        "replacement": "missing",
+        "on_sunset": "redirect",
+    });
+    let (status, body) = send(client.post(&deprecate_url).json(&request)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    // 取消弃用后不再带弃用头
+    let (status, body) = send(client.delete(&deprecate_url)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["data"].get("deprecation").is_none(), "{body}");
+    let response = invoke("10.0.0.1").send().await.unwrap();
+    assert!(!response.headers().contains_key("deprecation"));
+    assert!(!response.headers().contains_key("sunset"));
+    let body: Value = response.json().await.unwrap();
+    assert!(body["meta"].get("deprecated").is_none(), "{body}");
+    let (_, body) = send(client.get(server.url("/v1/functions?deprecated=true"))).await;
+    assert_eq!(body["data"], json!([]), "{body}");
+    let (status, _) = send(client.get(server.url("/v1/functions/missing/deprecate"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_captured_invocation_log() {
+    if !has_runtime("node") {
+        return;
+    }
+    let server = start().await;
+    let client = Client::new();
+    let (status, body) = send(client.post(server.url("/v1/functions")).json(&json!({
+        "name": "js-logged",
+        "code": "function handler(input) { throw new Error('boom ' + input.token); }",
+        "log_redaction": ["token"],
+    })))
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/js-logged?capture_log=debug"))
+            .header("x-request-id", "log-1")
+            .json(&json!({"input": {"token": "s3cret-value"}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_ne!(body["data"]["status"], "Success", "{body}");
+
+    let response = client
+        .get(server.url("/v1/executions/log-1/log"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    let text = response.text().await.unwrap();
+    assert!(!text.contains("s3cret-value"), "{text}");
+    let entries: Vec<Value> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let position = |predicate: &dyn Fn(&Value) -> bool| {
+        entries
+            .iter()
+            .position(predicate)
+            .unwrap_or_else(|| panic!("missing entry in {text}"))
+    };
+    let compile = position(&|entry| entry["fields"]["phase"] == "compile");
+    let spawn = position(&|entry| entry["fields"]["phase"] == "spawn");
+    let error = position(&|entry| {
+        entry["level"] == "error" && entry["message"].as_str().unwrap().contains("failed")
+    });
+    assert!(compile < spawn && spawn < error, "{text}");
+    let offsets: Vec<u64> = entries
+        .iter()
+        .map(|entry| entry["elapsed_us"].as_u64().unwrap())
+        .collect();
+    assert!(offsets.windows(2).all(|pair| pair[0] <= pair[1]), "{text}");
+    assert!(offsets[compile] < offsets[error], "{text}");
+
+    // 重用请求 ID 的捕获被拒绝，不替换已捕获的日志
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/js-logged?capture_log=info"))
+            .header("x-request-id", "log-1")
+            .json(&json!({"input": {"token": "other"}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    let response = client
+        .get(server.url("/v1/executions/log-1/log"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.text().await.unwrap(), text);
+
+    // 没有请求捕获的调用没有日志
+    let (status, body) = send(
+        client
+            .post(server.url("/v1/invoke/js-logged"))
+            .header("x-request-id", "log-2")
+            .json(&json!({"input": {"token": "x"}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, _) = send(client.get(server.url("/v1/executions/log-2/log"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_peer_forwarding() {
+    use flux::scheduler::admission::AdmissionConfig;
+    use flux::scheduler::profiles::SchedulerProfileConfig;
+
+    // 先占用两个端口再释放，两个节点的配置互相指向对方
+    let (addr_a, addr_b) = {
+        let a = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let b = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        (a.local_addr().unwrap(), b.local_addr().unwrap())
+    };
+    let peer_config = |peer: std::net::SocketAddr| {
+        let mut config = FluxConfig::default();
+        config.peers.urls = vec![format!("http://{peer}")];
+        config.peers.sync = true;
+        config.peers.health_interval_ms = 100;
+        config
+    };
+    // A 只有一个执行许可，许可被占用时的调用转发给 B
+    let mut config = peer_config(addr_b);
+    config.chaos.enabled = true;
+    config.profiles.insert(
+        "default".to_string(),
+        SchedulerProfileConfig {
+            admission: Some(AdmissionConfig {
+                max_concurrent: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    );
+    let a = FluxServer::new()
+        .with_config(config)
+        .disable_sample_functions()
+        .bind(addr_a)
+        .start()
+        .await
+        .unwrap();
+    let b = FluxServer::new()
+        .with_config(peer_config(addr_a))
+        .disable_sample_functions()
+        .bind(addr_b)
+        .start()
+        .await
+        .unwrap();
+    let b_url = format!("http://{addr_b}");
+    let client = Client::new();
+
+    for registration in [
+        json!({"name": "remote", "code": "return input", "labels": {"placement": "remote-preferred"}}),
+        json!({"name": "overflow", "code": "return input"}),
+    ] {
+        let (status, body) = send(client.post(a.url("/v1/functions")).json(&registration)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+    // 注册后推送给 B
+    let mut synced = false;
+    for _ in 0..100 {
+        let (status_remote, _) = send(client.get(b.url("/v1/functions/remote"))).await;
+        let (status_overflow, _) = send(client.get(b.url("/v1/functions/overflow"))).await;
+        if status_remote == StatusCode::OK && status_overflow == StatusCode::OK {
+            synced = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(synced, "functions were not synced to the peer");
+    // 等 A 的健康检查看到 B
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // 优先在节点执行的函数转发给 B；B 收到的是转发请求，不会再转发回 A
+    let (status, body) = send(
+        client
+            .post(a.url("/v1/invoke/remote"))
+            .json(&json!({"input": {"n": 1}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["peer"], b_url, "{body}");
+    assert_eq!(body["data"]["output"]["input"], json!({"n": 1}), "{body}");
+    let timing = &body["data"]["timing"];
+    assert!(timing["network_ms"].is_u64(), "{body}");
+    assert_eq!(
+        timing["queue_ms"].as_u64().unwrap()
+            + timing["execute_ms"].as_u64().unwrap_or(0)
+            + timing["network_ms"].as_u64().unwrap(),
+        timing["total_ms"].as_u64().unwrap(),
+        "{body}"
+    );
+
+    // A 的执行许可被延迟的调用占用时，新的调用转发给 B
+    let rule = json!({"match": {"function": "overflow"}, "action": "delay", "delay_ms": 1500});
+    let (status, body) = send(client.post(a.url("/v1/admin/chaos/rules")).json(&rule)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let first = tokio::spawn(send(
+        client
+            .post(a.url("/v1/invoke/overflow"))
+            .json(&json!({"input": 1})),
+    ));
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let (status, body) = send(
+        client
+            .post(a.url("/v1/invoke/overflow"))
+            .json(&json!({"input": 2})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["peer"], b_url, "{body}");
+    assert!(body["data"].get("chaos_injected").is_none(), "{body}");
+    let (status, body) = first.await.unwrap();
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["data"].get("peer").is_none(), "{body}");
+    assert_eq!(body["data"]["chaos_injected"], true, "{body}");
+
+    // 节点状态：转发统计和同步状态；B 没有转发任何调用
+    let (status, body) = send(client.get(a.url("/v1/peers"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let peer = &body["data"][0];
+    assert_eq!(peer["url"], b_url, "{body}");
+    assert_eq!(peer["healthy"], true, "{body}");
+    assert_eq!(peer["forwarded"], 2, "{body}");
+    assert_eq!(peer["sync"]["remote"]["synced"], true, "{body}");
+    assert_eq!(peer["sync"]["overflow"]["synced"], true, "{body}");
+    let (_, body) = send(client.get(b.url("/v1/peers"))).await;
+    assert_eq!(body["data"][0]["forwarded"], 0, "{body}");
+
+    // B 停止后在本地执行
+    b.shutdown().await;
+    let (status, body) = send(
+        client
+            .post(a.url("/v1/invoke/remote"))
+            .json(&json!({"input": {"n": 2}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["data"].get("peer").is_none(), "{body}");
+    assert_eq!(body["data"]["output"]["input"], json!({"n": 2}), "{body}");
+    a.shutdown().await;
+}